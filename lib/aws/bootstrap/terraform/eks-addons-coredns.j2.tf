@@ -1,5 +1,3 @@
-{% if enable_karpenter %} # TODO PG remove once we are confident that CoreDns addon is ok
-
 resource "aws_eks_addon" "aws_coredns" {
   cluster_name = aws_eks_cluster.eks_cluster.name
   addon_name   = "coredns"
@@ -15,6 +13,12 @@ resource "aws_eks_addon" "aws_coredns" {
   depends_on = [
     aws_eks_fargate_profile.core-dns
   ]
+  {% elif not enable_karpenter %}
+  # CoreDNS pods have to be scheduled for the addon to become active
+  depends_on = [
+    {%- for eks_worker_node in eks_worker_nodes %}
+    aws_eks_node_group.eks_cluster_workers_{{ loop.index }},
+    {%- endfor %}
+  ]
   {% endif %}
 }
-{% endif %}
\ No newline at end of file
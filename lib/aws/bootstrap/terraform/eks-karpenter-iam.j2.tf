@@ -184,7 +184,7 @@ resource "aws_iam_role_policy" "karpenter_controller" {
           "Resource" : "*",
           "Action" : "iam:GetInstanceProfile"
         }
-        {% if enable_karpenter %}
+        {% if enable_karpenter_interruption_queue %}
         ,{
           "Action": [
             "sqs:DeleteMessage",
@@ -1,4 +1,4 @@
-{% if enable_karpenter %}
+{% if enable_karpenter_interruption_queue %}
 locals {
   events = {
     health_event = {
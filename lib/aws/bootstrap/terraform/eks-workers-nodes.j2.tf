@@ -37,11 +37,36 @@ resource "aws_eks_node_group" "eks_cluster_workers_{{ loop.index }}" {
   subnet_ids       = flatten([aws_subnet.eks_zone_a[*].id, aws_subnet.eks_zone_b[*].id, aws_subnet.eks_zone_c[*].id])
   {%- endif %}
   instance_types   = ["{{ eks_worker_node.instance_type }}"]
+  capacity_type    = "{% if eks_worker_node.capacity_type == "Spot" %}SPOT{% else %}ON_DEMAND{% endif %}"
   {% if eks_worker_node.instance_architecture == "ARM64" -%}
   ami_type         = "AL2_ARM_64"
   {%- else -%}
   ami_type         = "AL2_x86_64"
   {%- endif %}
+  {%- if eks_worker_node.labels %}
+
+  labels = {
+    {%- for key, value in eks_worker_node.labels %}
+    "{{ key }}" = "{{ value }}"
+    {%- endfor %}
+  }
+  {%- endif %}
+  {%- for taint in eks_worker_node.taints %}
+
+  taint {
+    key    = "{{ taint.key }}"
+    {%- if taint.value %}
+    value  = "{{ taint.value }}"
+    {%- endif %}
+    {%- if taint.effect == "NoExecute" %}
+    effect = "NO_EXECUTE"
+    {%- elif taint.effect == "PreferNoSchedule" %}
+    effect = "PREFER_NO_SCHEDULE"
+    {%- else %}
+    effect = "NO_SCHEDULE"
+    {%- endif %}
+  }
+  {%- endfor %}
 
   tags = merge(
   local.tags_eks,
@@ -27,6 +27,9 @@ function check_args() {
 function get_connection_details() { ## print environment variables to connect to cluster
   echo 'export AWS_ACCESS_KEY_ID="{{ aws_access_key }}"'
   echo 'export AWS_SECRET_ACCESS_KEY="{{ aws_secret_key }}"'
+{%- if aws_session_token is defined %}
+  echo 'export AWS_SESSION_TOKEN="{{ aws_session_token }}"'
+{%- endif %}
   echo 'export AWS_DEFAULT_REGION="{{ aws_region }}"'
   echo 'export KUBECONFIG={{ s3_kubeconfig_bucket }}/{{ kubernetes_cluster_id }}.yaml'
 }
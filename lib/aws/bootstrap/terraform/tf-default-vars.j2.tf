@@ -24,6 +24,12 @@ variable "organization_long_id" {
   type        = string
 }
 
+variable "execution_id" {
+  description = "Qovery execution ID, to find the execution which rendered these files"
+  default     = "{{ execution_id }}"
+  type        = string
+}
+
 # AWS specific
 variable "aws_availability_zones" {
   description = "AWS availability zones"
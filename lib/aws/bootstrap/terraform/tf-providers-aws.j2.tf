@@ -31,6 +31,9 @@ terraform {
 provider "aws" {
   access_key = "{{ aws_access_key }}"
   secret_key = "{{ aws_secret_key }}"
+{%- if aws_session_token is defined %}
+  token      = "{{ aws_session_token }}"
+{%- endif %}
   region     = "{{ aws_region }}"
 }
 
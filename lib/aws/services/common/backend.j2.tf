@@ -15,6 +15,9 @@ terraform {
       env = {
         AWS_ACCESS_KEY_ID     = "{{ aws_access_key }}"
         AWS_SECRET_ACCESS_KEY = "{{ aws_secret_key }}"
+{%- if aws_session_token is defined %}
+        AWS_SESSION_TOKEN     = "{{ aws_session_token }}"
+{%- endif %}
         AWS_DEFAULT_REGION    = "{{ region }}"
       }
     }
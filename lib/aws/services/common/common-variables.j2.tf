@@ -118,7 +118,7 @@ variable "apply_changes_now" {
 
 variable "preferred_maintenance_window" {
   description = "Maintenance window"
-  default = "Tue:02:00-Tue:04:00"
+  default = "{{ preferred_maintenance_window | default(value="Tue:02:00-Tue:04:00") }}"
   type = string
 }
 
@@ -140,7 +140,7 @@ variable "performance_insights_enabled_retention" {
 
 variable "backup_retention_period" {
   description = "Backup retention period"
-  default = 14
+  default = {{ backup_retention_period | default(value=14) }}
   type = number
 }
 
@@ -40,6 +40,14 @@ resource "aws_db_parameter_group" "mysql_parameter_group" {
     name  = "log_bin_trust_function_creators"
     value = "1"
   }
+{%- for parameter in database_parameters %}
+
+  parameter {
+    name         = "{{ parameter.name }}"
+    value        = "{{ parameter.value }}"
+    apply_method = "{{ parameter.rds_apply_method }}"
+  }
+{%- endfor %}
 }
 
 # Non snapshoted version
@@ -29,6 +29,23 @@ locals {
   final_snapshot_name = "${var.final_snapshot_name}-${local.final_snap_timestamp}"
 }
 
+{%- if database_parameters %}
+resource "aws_db_parameter_group" "postgresql_parameter_group" {
+  name   = "qovery-${var.postgresql_identifier}"
+  family = "{{ parameter_group_family }}"
+
+  tags = local.postgres_database_tags
+{%- for parameter in database_parameters %}
+
+  parameter {
+    name         = "{{ parameter.name }}"
+    value        = "{{ parameter.value }}"
+    apply_method = "{{ parameter.rds_apply_method }}"
+  }
+{%- endfor %}
+}
+{%- endif %}
+
 # Non snapshoted version
 resource "aws_db_instance" "postgresql_instance" {
   identifier = var.postgresql_identifier
@@ -44,6 +61,9 @@ resource "aws_db_instance" "postgresql_instance" {
     delete = "60m"
   }
   password = var.password
+{%- if database_parameters %}
+  parameter_group_name = aws_db_parameter_group.postgresql_parameter_group.name
+{%- endif %}
   storage_encrypted = var.encrypt_disk
   {%- if snapshot and snapshot["snapshot_id"] %}
   # Snapshot
@@ -87,7 +107,9 @@ resource "aws_db_instance" "postgresql_instance" {
 {%- if not skip_final_snapshot %}
       final_snapshot_identifier,
 {%- endif %}
+{%- if not database_parameters %}
       parameter_group_name,
+{%- endif %}
     ]
   }
   copy_tags_to_snapshot = true
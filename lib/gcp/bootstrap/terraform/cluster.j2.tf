@@ -102,11 +102,16 @@ resource "google_container_cluster" "primary" {
   # This is the default and it is also the least restrictive option.
   # Since authorized networks are not enabled, you can administer your cluster from any source IP address
   # as long as you authenticate.
+  # Private clusters can disable the public endpoint, the control plane is then only reachable from the VPC
+  # (and authorized networks, if any).
   # https://cloud.google.com/kubernetes-engine/docs/concepts/private-cluster-concept
   private_cluster_config {
     {% if cluster_is_private == true %}
-    enable_private_endpoint = false
+    enable_private_endpoint = {{ enable_private_endpoint }}
     enable_private_nodes    = true
+    {%- if master_ipv4_cidr_block %}
+    master_ipv4_cidr_block  = "{{ master_ipv4_cidr_block }}"
+    {%- endif %}
     {% else %}
     enable_private_endpoint = false
     enable_private_nodes    = false
@@ -17,6 +17,12 @@ variable "organization_long_id" {
   type        = string
 }
 
+variable "execution_id" {
+  description = "Qovery execution ID, to find the execution which rendered these files"
+  default     = "{{ execution_id }}"
+  type        = string
+}
+
 variable "object_storage_kubeconfig_bucket" {
   description = "Object storage bucket name containing cluster's kubeconfig"
   default     = "{{ object_storage_kubeconfig_bucket }}"
@@ -141,7 +147,11 @@ variable "cluster_is_private" {
 variable "master_authorized_networks" {
   type        = list(object({ cidr_block = string, display_name = string }))
   description = "List of master authorized networks. If none are provided, disallow external access (except the cluster node IPs, which GKE automatically whitelists)."
-  default     = []
+  default = [
+    {%- for network in master_authorized_networks %}
+    { cidr_block = "{{ network.cidr_block }}", display_name = "{{ network.display_name }}" },
+    {%- endfor %}
+  ]
 }
 
 variable "auto_create_subnetworks" {
@@ -9,12 +9,19 @@ resource "scaleway_k8s_cluster" "kubernetes_cluster"  {
   type    = var.scaleway_ks_type
   version = var.scaleway_ks_version
   cni     = "cilium"
+  {%- if existing_private_network_id %}
+  # would delete the customer owned private network if empty
+  delete_additional_resources = false
+  {%- else %}
   delete_additional_resources = true
+  {%- endif %}
 
   region  = var.region
 
   tags    = local.tags_ks_list
-  {% if create_private_network %}
+  {% if existing_private_network_id %}
+  private_network_id = "{{ existing_private_network_id }}"
+  {% elif create_private_network %}
   private_network_id = scaleway_vpc_private_network.private_network.id
   {% endif %}
 
@@ -23,8 +30,9 @@ resource "scaleway_k8s_cluster" "kubernetes_cluster"  {
     max_graceful_termination_sec    = 3600
     disable_scale_down              = false
     estimator                       = "binpacking"
-    scale_down_delay_after_add      = "10m"
-    balance_similar_node_groups     = true
+{%- for key, value in scw_ks_autoscaler_config %}
+    {{ key }} = {{ value }}
+{%- endfor %}
   }
 
   auto_upgrade {
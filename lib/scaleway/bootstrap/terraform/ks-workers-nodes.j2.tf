@@ -12,7 +12,12 @@ resource "scaleway_k8s_pool" "kubernetes_cluster_workers_{{ loop.index }}" {
   # use Scaleway built-in cluster autoscaler
   autoscaling         = {{ scw_ks_pool_autoscale }}
   autohealing         = true
+  # a resumed cluster gets back the nodes it had when paused
+{%- if scw_ks_worker_node.name in scw_ks_worker_nodes_desired_size %}
+  size                = "{{ scw_ks_worker_nodes_desired_size[scw_ks_worker_node.name] }}"
+{%- else %}
   size                = "{{ scw_ks_worker_node.min_nodes }}"
+{%- endif %}
   min_size            = "{{ scw_ks_worker_node.min_nodes }}"
   max_size            = "{{ scw_ks_worker_node.max_nodes }}"
   wait_for_pool_ready = false
@@ -27,6 +32,7 @@ resource "scaleway_k8s_pool" "kubernetes_cluster_workers_{{ loop.index }}" {
   lifecycle {
     create_before_destroy = true
   }
-  tags          =  concat(local.tags_ks_list, ["QoveryNodeGroupName:{{ scw_ks_worker_node.name }}", "QoveryNodeGroupId:${var.kubernetes_cluster_id}_{{ scw_ks_worker_node.instance_type }}_{{ loop.index }}"])
+  # `noprefix=` tags become node labels and `taint=noprefix=` ones node taints, keys are kept as is
+  tags          =  concat(local.tags_ks_list, ["QoveryNodeGroupName:{{ scw_ks_worker_node.name }}", "QoveryNodeGroupId:${var.kubernetes_cluster_id}_{{ scw_ks_worker_node.instance_type }}_{{ loop.index }}"{% for key, value in scw_ks_worker_node.labels %}, "noprefix={{ key }}={{ value }}"{% endfor %}{% for taint in scw_ks_worker_node.taints %}, "taint=noprefix={{ taint.key }}={{ taint.value }}:{{ taint.effect }}"{% endfor %}])
 }
 {% endfor %}
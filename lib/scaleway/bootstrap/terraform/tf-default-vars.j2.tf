@@ -37,6 +37,12 @@ variable "organization_long_id" {
   type        = string
 }
 
+variable "execution_id" {
+  description = "Qovery execution ID, to find the execution which rendered these files"
+  default     = "{{ execution_id }}"
+  type        = string
+}
+
 variable "scaleway_project_id" {
   description = "Scaleway project ID (namespace)"
   default     = "{{ scaleway_project_id }}"
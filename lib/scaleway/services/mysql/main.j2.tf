@@ -47,6 +47,9 @@ resource "scaleway_rdb_instance" "mysql_instance" {
 
   is_ha_cluster     = var.activate_high_availability
   disable_backup    = !var.activate_backups
+{%- if backup_retention_period is defined %}
+  backup_schedule_retention = var.backup_retention_period
+{%- endif %}
 
   user_name         = var.username
   password          = var.password
@@ -78,6 +81,9 @@ resource "scaleway_rdb_instance" "mysql_instance" {
   # https://api.scaleway.com/rdb/v1/regions/fr-par/database-engines
   settings          = {
     slow_query_log = true
+{%- for parameter in database_parameters %}
+    {{ parameter.name }} = "{{ parameter.value }}"
+{%- endfor %}
   }
 }
 
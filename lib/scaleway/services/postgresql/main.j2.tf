@@ -47,6 +47,9 @@ resource "scaleway_rdb_instance" "postgresql_instance" {
 
   is_ha_cluster     = var.activate_high_availability
   disable_backup    = !var.activate_backups
+{%- if backup_retention_period is defined %}
+  backup_schedule_retention = var.backup_retention_period
+{%- endif %}
 
   user_name         = var.username
   password          = var.password
@@ -77,6 +80,13 @@ resource "scaleway_rdb_instance" "postgresql_instance" {
   # https://developers.scaleway.com/en/products/rdb/api/#get-1eafb7
   # https://api.scaleway.com/rdb/v1/regions/fr-par/database-engines
   # settings          = {} TODO(benjaminch): to activate slow queries logs, but not possible for now via `log_min_duration_statement`
+{%- if database_parameters %}
+  settings          = {
+{%- for parameter in database_parameters %}
+    {{ parameter.name }} = "{{ parameter.value }}"
+{%- endfor %}
+  }
+{%- endif %}
 }
 
 resource "scaleway_rdb_database" "postgresql_main" {
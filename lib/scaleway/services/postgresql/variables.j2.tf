@@ -154,7 +154,7 @@ variable "activate_backups" {
 
 variable "backup_retention_period" {
   description = "Backup rentention period"
-  default = 7
+  default = {{ backup_retention_period | default(value=7) }}
   type = number
 }
 
@@ -1,34 +1,52 @@
+use std::collections::VecDeque;
 use std::ffi::OsStr;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::io::{Error, ErrorKind};
 use std::path::Path;
 use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender};
+use std::thread;
 
-use crate::cmd::command::CommandError::{ExecutionError, ExitStatusError, Killed, TimeoutError};
+use crate::cmd::command::CommandError::{ExecutionError, ExitStatusError, IdleTimeoutError, Killed, TimeoutError};
 
+use crate::constants::{QOVERY_CLUSTER_ID, QOVERY_EXECUTION_ID, QOVERY_STAGE};
 use crate::environment::models::abort::Abort;
+use crate::events::{EventDetails, Stage};
+use crate::proxy;
 use itertools::Itertools;
+use std::cell::RefCell;
 use std::time::{Duration, Instant};
-use timeout_readwrite::TimeoutReader;
 
 #[derive(thiserror::Error, Debug)]
 pub enum CommandError {
     #[error("Error while executing command")]
     ExecutionError(#[from] Error),
 
-    #[error("Command terminated with a non success exit status code: {0}")]
-    ExitStatusError(ExitStatus),
+    /// Exit status, and what has been kept of the output of the command
+    #[error("Command terminated with a non success exit status code: {0}{}", dropped_output_message(.1.dropped_bytes))]
+    ExitStatusError(ExitStatus, CommandOutputDetails),
 
     #[error("Command killed due to timeout: {0}")]
     TimeoutError(String),
 
+    #[error("Command killed because it did not produce any output for too long: {0}")]
+    IdleTimeoutError(String),
+
     #[error("Command killed by user request: {0}")]
     Killed(String),
 }
 
+fn dropped_output_message(dropped_bytes: u64) -> String {
+    match dropped_bytes {
+        0 => String::new(),
+        dropped_bytes => format!(" ({dropped_bytes} bytes of output dropped)"),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum AbortReason {
     Timeout(Duration),
+    IdleTimeout(Duration),
     Canceled(String),
 }
 
@@ -44,6 +62,10 @@ pub enum CommandKillerTrigger<'a> {
 }
 
 const LOGGING_INTERVAL: Duration = Duration::from_secs(120);
+// how long the output of a killed command is still read, its children can keep the streams open forever
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+// lines waiting to be forwarded, the reading threads block once it is full
+const OUTPUT_CHANNEL_CAPACITY: usize = 1024;
 
 impl<'a> CommandKillerTrigger<'a> {
     pub fn should_abort(&self) -> Option<AbortReason> {
@@ -101,6 +123,332 @@ impl<'a> CommandKiller<'a> {
     }
 }
 
+/// How much of the output of a command is kept in memory to explain its failure, per stream (stdout and stderr).
+/// Every line is forwarded to the callbacks as it arrives, only the first `head_bytes` and the last `tail_bytes`
+/// of the output are kept. Lines longer than `max_line_bytes` are truncated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommandOutputLimits {
+    pub head_bytes: usize,
+    pub tail_bytes: usize,
+    pub max_line_bytes: usize,
+}
+
+impl Default for CommandOutputLimits {
+    fn default() -> Self {
+        CommandOutputLimits {
+            head_bytes: 1024 * 1024,
+            tail_bytes: 1024 * 1024,
+            max_line_bytes: 1024 * 1024,
+        }
+    }
+}
+
+/// What has been kept of the output of a command, bounded by `CommandOutputLimits`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommandOutputDetails {
+    pub stdout: Vec<String>,
+    pub stderr: Vec<String>,
+    /// Bytes of output not kept, because they were out of the head and the tail, or in a too long line
+    pub dropped_bytes: u64,
+}
+
+/// Output of one stream of a command, bounded by `CommandOutputLimits`.
+/// What does not fit between the head and the tail is dropped, and replaced by an elision marker.
+#[derive(Debug)]
+pub struct BoundedOutput {
+    limits: CommandOutputLimits,
+    head: Vec<String>,
+    head_bytes: usize,
+    is_head_full: bool,
+    tail: VecDeque<String>,
+    tail_bytes: usize,
+    dropped_lines: u64,
+    dropped_bytes: u64,
+}
+
+impl BoundedOutput {
+    pub fn new(limits: CommandOutputLimits) -> BoundedOutput {
+        BoundedOutput {
+            limits,
+            head: Vec::new(),
+            head_bytes: 0,
+            is_head_full: false,
+            tail: VecDeque::new(),
+            tail_bytes: 0,
+            dropped_lines: 0,
+            dropped_bytes: 0,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        // the newline counts, so a flood of empty lines is bounded as well
+        let line_bytes = line.len() + 1;
+        if !self.is_head_full && self.head_bytes + line_bytes <= self.limits.head_bytes {
+            self.head_bytes += line_bytes;
+            self.head.push(line);
+            return;
+        }
+
+        self.is_head_full = true;
+        self.tail_bytes += line_bytes;
+        self.tail.push_back(line);
+        while self.tail_bytes > self.limits.tail_bytes {
+            let Some(dropped) = self.tail.pop_front() else {
+                break;
+            };
+            self.tail_bytes -= dropped.len() + 1;
+            self.dropped_lines += 1;
+            self.dropped_bytes += dropped.len() as u64 + 1;
+        }
+    }
+
+    /// Lines kept: the head, the elision marker if anything has been dropped, then the tail
+    pub fn finish(&mut self) -> Vec<String> {
+        let mut lines = Vec::with_capacity(self.head.len() + self.tail.len() + 1);
+        lines.append(&mut self.head);
+        if self.dropped_lines > 0 {
+            lines.push(format!(
+                "[... {} lines ({} bytes) of output dropped ...]",
+                self.dropped_lines, self.dropped_bytes
+            ));
+        }
+        lines.extend(self.tail.drain(..));
+        self.head_bytes = 0;
+        self.tail_bytes = 0;
+
+        lines
+    }
+
+    pub fn dropped_bytes(&self) -> u64 {
+        self.dropped_bytes
+    }
+
+    /// Bytes held in memory, waiting for the end of the command
+    pub fn buffered_bytes(&self) -> usize {
+        self.head_bytes + self.tail_bytes
+    }
+}
+
+/// Lines of a reader, truncated to `max_line_bytes` so a single endless line cannot exhaust the memory.
+/// Unlike `BufRead::lines`, a line interrupted by a read timeout is kept and completed by the next call,
+/// and invalid UTF-8 is replaced instead of ending the stream.
+struct BoundedLines<R> {
+    reader: R,
+    max_line_bytes: usize,
+    line: Vec<u8>,
+    line_truncated_bytes: usize,
+    truncated_bytes: u64,
+}
+
+impl<R: BufRead> BoundedLines<R> {
+    fn new(reader: R, max_line_bytes: usize) -> BoundedLines<R> {
+        BoundedLines {
+            reader,
+            max_line_bytes,
+            line: Vec::new(),
+            line_truncated_bytes: 0,
+            truncated_bytes: 0,
+        }
+    }
+
+    fn take_line(&mut self) -> String {
+        if self.line.last() == Some(&b'\r') {
+            self.line.pop();
+        }
+        let mut line = String::from_utf8_lossy(&self.line).into_owned();
+        if self.line_truncated_bytes > 0 {
+            line.push_str(&format!(" [... {} bytes truncated]", self.line_truncated_bytes));
+            self.truncated_bytes += self.line_truncated_bytes as u64;
+        }
+        self.line.clear();
+        self.line_truncated_bytes = 0;
+
+        line
+    }
+}
+
+impl<R: BufRead> Iterator for BoundedLines<R> {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Some(Err(err)),
+            };
+            if available.is_empty() {
+                // end of the stream, the last line may have no newline
+                if self.line.is_empty() && self.line_truncated_bytes == 0 {
+                    return None;
+                }
+                return Some(Ok(self.take_line()));
+            }
+
+            let newline = available.iter().position(|byte| *byte == b'\n');
+            let content = &available[..newline.unwrap_or(available.len())];
+            let kept = content.len().min(self.max_line_bytes.saturating_sub(self.line.len()));
+            self.line.extend_from_slice(&content[..kept]);
+            self.line_truncated_bytes += content.len() - kept;
+
+            let consumed = content.len() + usize::from(newline.is_some());
+            self.reader.consume(consumed);
+            if newline.is_some() {
+                return Some(Ok(self.take_line()));
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+enum OutputEvent {
+    Line(OutputStream, String),
+    /// The stream is closed, with the number of bytes truncated from its lines
+    Closed(u64),
+}
+
+/// Output of a running command. Each stream is read line by line on its own thread,
+/// so a command filling one stream never blocks the reading of the other one.
+struct CommandOutput {
+    receiver: Receiver<OutputEvent>,
+    stdout: BoundedOutput,
+    stderr: BoundedOutput,
+    open_streams: usize,
+    truncated_bytes: u64,
+    last_line_at: Instant,
+}
+
+impl CommandOutput {
+    fn read(cmd_handle: &mut Child, limits: CommandOutputLimits) -> Result<CommandOutput, Error> {
+        let stdout = cmd_handle
+            .stdout
+            .take()
+            .ok_or_else(|| Error::new(ErrorKind::BrokenPipe, "Cannot get stdout for command"))?;
+        let stderr = cmd_handle
+            .stderr
+            .take()
+            .ok_or_else(|| Error::new(ErrorKind::BrokenPipe, "Cannot get stderr for command"))?;
+
+        let (sender, receiver) = sync_channel(OUTPUT_CHANNEL_CAPACITY);
+        spawn_output_reader(OutputStream::Stdout, stdout, limits.max_line_bytes, sender.clone())?;
+        spawn_output_reader(OutputStream::Stderr, stderr, limits.max_line_bytes, sender)?;
+
+        Ok(CommandOutput {
+            receiver,
+            stdout: BoundedOutput::new(limits),
+            stderr: BoundedOutput::new(limits),
+            open_streams: 2,
+            truncated_bytes: 0,
+            last_line_at: Instant::now(),
+        })
+    }
+
+    fn is_closed(&self) -> bool {
+        self.open_streams == 0
+    }
+
+    fn idle_since(&self) -> Duration {
+        self.last_line_at.elapsed()
+    }
+
+    /// Forwards the next line of the command, returns false if none arrived before the timeout
+    fn forward_next(
+        &mut self,
+        timeout: Duration,
+        stdout_output: &mut impl FnMut(String),
+        stderr_output: &mut impl FnMut(String),
+    ) -> bool {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(OutputEvent::Line(stream, line)) => {
+                self.last_line_at = Instant::now();
+                // every line is forwarded right away, only the copy kept for the error details is bounded
+                match stream {
+                    OutputStream::Stdout => {
+                        self.stdout.push(line.clone());
+                        stdout_output(line);
+                    }
+                    OutputStream::Stderr => {
+                        self.stderr.push(line.clone());
+                        stderr_output(line);
+                    }
+                }
+                true
+            }
+            Ok(OutputEvent::Closed(truncated_bytes)) => {
+                self.open_streams -= 1;
+                self.truncated_bytes += truncated_bytes;
+                true
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => {
+                // a reading thread died without closing its stream
+                self.open_streams = 0;
+                true
+            }
+        }
+    }
+
+    /// Forwards what is left of the output of a killed command, for at most `timeout`
+    fn drain(
+        &mut self,
+        timeout: Duration,
+        stdout_output: &mut impl FnMut(String),
+        stderr_output: &mut impl FnMut(String),
+    ) {
+        let deadline = Instant::now() + timeout;
+        while !self.is_closed() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || !self.forward_next(remaining, stdout_output, stderr_output) {
+                break;
+            }
+        }
+    }
+
+    /// What has been kept of the output, once the command ends
+    fn details(&mut self) -> CommandOutputDetails {
+        CommandOutputDetails {
+            stdout: self.stdout.finish(),
+            stderr: self.stderr.finish(),
+            dropped_bytes: self.stdout.dropped_bytes() + self.stderr.dropped_bytes() + self.truncated_bytes,
+        }
+    }
+}
+
+fn spawn_output_reader<R: Read + Send + 'static>(
+    stream: OutputStream,
+    reader: R,
+    max_line_bytes: usize,
+    sender: SyncSender<OutputEvent>,
+) -> Result<(), Error> {
+    thread::Builder::new()
+        .name(format!("command-{stream:?}").to_lowercase())
+        .spawn(move || {
+            let mut lines = BoundedLines::new(BufReader::new(reader), max_line_bytes);
+            for line in lines.by_ref() {
+                match line {
+                    Ok(line) => {
+                        // nobody is listening anymore, the stream is closed by dropping the reader
+                        if sender.send(OutputEvent::Line(stream, line)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(err) => {
+                        error!("Error on {:?} of command: {:?}", stream, err);
+                        break;
+                    }
+                }
+            }
+            let _ = sender.send(OutputEvent::Closed(lines.truncated_bytes));
+        })?;
+
+    Ok(())
+}
+
 pub trait ExecutableCommand {
     fn get_args(&self) -> Vec<String>;
 
@@ -128,9 +476,98 @@ pub trait ExecutableCommand {
         STDERR: FnMut(String);
 }
 
+/// Identifies the execution the engine runs commands for, so the output of terraform, helm, docker, ... can be
+/// correlated with the engine logs.
+/// The current execution context belongs to the thread running the task, so it has to be propagated to the threads
+/// the task spawns, the same way as the tracing span.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionContext {
+    execution_id: String,
+    cluster_id: String,
+    stage: String,
+}
+
+thread_local! {
+    static CURRENT_EXECUTION_CONTEXT: RefCell<Option<ExecutionContext>> = const { RefCell::new(None) };
+}
+
+impl ExecutionContext {
+    pub fn new(execution_id: String, cluster_id: String, stage: String) -> Self {
+        ExecutionContext {
+            execution_id,
+            cluster_id,
+            stage,
+        }
+    }
+
+    /// Returns the context of the execution the current thread runs, if any
+    pub fn current() -> Option<ExecutionContext> {
+        CURRENT_EXECUTION_CONTEXT.with_borrow(|current| current.clone())
+    }
+
+    /// Makes it the context of every command launched by the current thread until the returned guard is dropped
+    pub fn enter(self) -> ExecutionContextGuard {
+        Self::replace_current(Some(self))
+    }
+
+    /// Moves the current execution, if any, to the given stage until the returned guard is dropped
+    pub fn enter_stage(stage: &Stage) -> ExecutionContextGuard {
+        Self::replace_current(Self::current().map(|current| ExecutionContext {
+            stage: stage_name(stage),
+            ..current
+        }))
+    }
+
+    fn replace_current(execution_context: Option<ExecutionContext>) -> ExecutionContextGuard {
+        ExecutionContextGuard {
+            previous: CURRENT_EXECUTION_CONTEXT.replace(execution_context),
+        }
+    }
+
+    pub fn execution_id(&self) -> &str {
+        &self.execution_id
+    }
+
+    fn envs(&self) -> [(&'static str, &str); 3] {
+        [
+            (QOVERY_EXECUTION_ID, self.execution_id.as_str()),
+            (QOVERY_CLUSTER_ID, self.cluster_id.as_str()),
+            (QOVERY_STAGE, self.stage.as_str()),
+        ]
+    }
+}
+
+fn stage_name(stage: &Stage) -> String {
+    format!("{}:{}", stage, stage.sub_step_name())
+}
+
+impl From<&EventDetails> for ExecutionContext {
+    fn from(event_details: &EventDetails) -> Self {
+        ExecutionContext::new(
+            event_details.execution_id().to_string(),
+            event_details.cluster_id().to_string(),
+            stage_name(event_details.stage()),
+        )
+    }
+}
+
+/// Restores the previous execution context of the thread when dropped
+pub struct ExecutionContextGuard {
+    previous: Option<ExecutionContext>,
+}
+
+impl Drop for ExecutionContextGuard {
+    fn drop(&mut self) {
+        CURRENT_EXECUTION_CONTEXT.set(self.previous.take());
+    }
+}
+
 pub struct QoveryCommand {
     command: Command,
     kill_grace_period: Duration,
+    idle_timeout: Option<Duration>,
+    output_limits: CommandOutputLimits,
+    stdin: Option<String>,
 }
 
 impl QoveryCommand {
@@ -138,13 +575,24 @@ impl QoveryCommand {
         let mut command = Command::new(binary.as_ref().as_os_str());
         command.args(args);
 
+        // proxy first, so a command can still override it with its own envs
+        proxy::command_environment_variables().iter().for_each(|(k, v)| {
+            command.env(k, v);
+        });
         envs.iter().for_each(|(k, v)| {
             command.env(k, v);
         });
+        // set last, so the execution context cannot be overridden by a caller
+        if let Some(execution_context) = ExecutionContext::current() {
+            command.envs(execution_context.envs());
+        }
 
         QoveryCommand {
             command,
             kill_grace_period: Duration::from_secs(60 * 5),
+            idle_timeout: None,
+            output_limits: CommandOutputLimits::default(),
+            stdin: None,
         }
     }
 
@@ -152,9 +600,43 @@ impl QoveryCommand {
         self.kill_grace_period = grace_period;
     }
 
+    /// Kills the command when it does not output any line for this long, whatever its total timeout.
+    /// Meant for commands printing their progress, a silent one is considered stuck.
+    pub fn set_idle_timeout(&mut self, idle_timeout: Duration) {
+        self.idle_timeout = Some(idle_timeout);
+    }
+
+    /// How much of the output is kept for the error details, every line is forwarded to the callbacks anyway.
+    /// Meant for the commands whose output is huge, or made of very long lines.
+    pub fn set_output_limits(&mut self, output_limits: CommandOutputLimits) {
+        self.output_limits = output_limits;
+    }
+
+    /// Input written to the stdin of the command, which is closed right after.
+    /// Use it to hand secrets to a command instead of putting them in its arguments.
+    pub fn set_stdin(&mut self, input: String) {
+        self.stdin = Some(input);
+    }
+
     pub fn set_current_dir<P: AsRef<Path>>(&mut self, root_dir: P) {
         self.command.current_dir(root_dir);
     }
+
+    #[cfg(test)]
+    fn get_env(&self, key: &str) -> Option<String> {
+        self.command
+            .get_envs()
+            .find(|(k, _)| *k == key)
+            .and_then(|(_, v)| v)
+            .map(|v| v.to_string_lossy().to_string())
+    }
+
+    fn should_abort(&self, abort_notifier: &CommandKiller, output: &CommandOutput) -> Option<AbortReason> {
+        abort_notifier.should_abort().or_else(|| match self.idle_timeout {
+            Some(idle_timeout) if output.idle_since() >= idle_timeout => Some(AbortReason::IdleTimeout(idle_timeout)),
+            _ => None,
+        })
+    }
 }
 
 impl ExecutableCommand for QoveryCommand {
@@ -220,158 +702,92 @@ impl ExecutableCommand for QoveryCommand {
         STDERR: FnMut(String),
     {
         info!("command: {:?}", self.command);
+        #[cfg(feature = "failure-injection")]
+        if let Some(scripted_command) = crate::cmd::failure_injection::intercept(&self.command) {
+            info!("command replaced by an injected failure");
+            return scripted_command.replay(stdout_output, stderr_output);
+        }
+
         let mut cmd_handle = self
             .command
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            .stdin(if self.stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::inherit()
+            })
             .spawn()
             .map_err(ExecutionError)?;
 
-        // Read stdout/stderr until timeout is reached
-        let reader_timeout = Duration::from_secs(1);
-        let stdout = cmd_handle
-            .stdout
-            .take()
-            .ok_or_else(|| ExecutionError(Error::new(ErrorKind::BrokenPipe, "Cannot get stdout for command")))?;
-        let mut stdout_reader = BufReader::new(TimeoutReader::new(stdout, reader_timeout)).lines();
-
-        let stderr = cmd_handle
-            .stderr
-            .take()
-            .ok_or_else(|| ExecutionError(Error::new(ErrorKind::BrokenPipe, "Cannot get stderr for command")))?;
-        let mut stderr_reader = BufReader::new(TimeoutReader::new(
-            stderr,
-            Duration::from_secs(0), // don't block on stderr
-        ))
-        .lines();
-
-        let mut stdout_closed = false;
-        let mut stderr_closed = false;
-        let mut last_log_check = Instant::now();
-        let mut log_counter: u64 = 0;
-
-        while !stdout_closed || !stderr_closed {
-            // We should abort and kill the process
-            if abort_notifier.should_abort().is_some() {
-                break;
+        if let Some(input) = &self.stdin {
+            let written = match cmd_handle.stdin.take() {
+                Some(mut stdin) => stdin.write_all(input.as_bytes()),
+                None => Err(Error::new(ErrorKind::BrokenPipe, "Cannot get stdin for command")),
+            };
+            // stdin is dropped here, so the command does not wait for more input
+            if let Err(err) = written {
+                let _ = cmd_handle.kill();
+                let _ = cmd_handle.wait();
+                return Err(ExecutionError(err));
             }
+        }
 
-            // Read on stdout first
-            while !stdout_closed {
-                let line = match stdout_reader.next() {
-                    Some(line) => line,
-                    None => {
-                        // Stdout has been closed
-                        stdout_closed = true;
-                        break;
-                    }
-                };
-
-                match line {
-                    Err(ref err) if err.kind() == ErrorKind::TimedOut => {
-                        if last_log_check.elapsed() > LOGGING_INTERVAL {
-                            if log_counter == 0 {
-                                stderr_output(
-                                    "Command still running. No output available. Waiting for next line...".to_string(),
-                                );
-                            }
-
-                            last_log_check = Instant::now();
-                            log_counter = 0;
-                        }
-                        break;
-                    }
-                    Ok(line) => {
-                        log_counter = log_counter.wrapping_add(1);
-                        stdout_output(line)
-                    }
-                    Err(err) => {
-                        error!("Error on stdout of cmd {:?}: {:?}", self.command, err);
-                        stdout_closed = true;
-                        break;
-                    }
-                }
-
-                // Should we abort and kill the process
-                if abort_notifier.should_abort().is_some() {
-                    stdout_closed = true;
-                    stderr_closed = true;
-                    break;
-                }
+        // the output kept for the error details is bounded, whatever the command prints
+        let mut output = match CommandOutput::read(&mut cmd_handle, self.output_limits) {
+            Ok(output) => output,
+            Err(err) => {
+                let _ = cmd_handle.kill();
+                let _ = cmd_handle.wait();
+                return Err(ExecutionError(err));
+            }
+        };
+
+        let mut last_heartbeat = Instant::now();
+        let exit_status = loop {
+            if let Some(reason) = self.should_abort(abort_notifier, &output) {
+                let msg = format!("Killing process {:?} due to {:?}", self.command, reason);
+                warn!("{}", msg);
+                self.kill(&mut cmd_handle);
+
+                output.drain(OUTPUT_DRAIN_TIMEOUT, stdout_output, stderr_output);
+                let msg = format!("{msg}{}", dropped_output_message(output.details().dropped_bytes));
+
+                return Err(match reason {
+                    AbortReason::Canceled(_) => Killed(msg),
+                    AbortReason::Timeout(_) => TimeoutError(msg),
+                    AbortReason::IdleTimeout(_) => IdleTimeoutError(msg),
+                });
             }
 
-            // Read stderr now
-            while !stderr_closed {
-                let line = match stderr_reader.next() {
-                    Some(line) => line,
-                    None => {
-                        // Stdout has been closed
-                        stderr_closed = true;
-                        break;
-                    }
-                };
-
-                match line {
-                    Err(ref err) if err.kind() == ErrorKind::TimedOut => break,
-                    Ok(line) => {
-                        log_counter = log_counter.wrapping_add(1);
-                        stderr_output(line)
-                    }
-                    Err(err) => {
-                        error!("Error on stderr of cmd {:?}: {:?}", self.command, err);
-                        stderr_closed = true;
-                        break;
-                    }
-                }
-
-                // should we abort and kill the process
-                if abort_notifier.should_abort().is_some() {
-                    stdout_closed = true;
-                    stderr_closed = true;
-                    break;
+            // Once its output is closed, wait for the process to exit
+            if output.is_closed() {
+                match cmd_handle.try_wait() {
+                    Ok(Some(status)) => break status,
+                    Ok(None) => thread::sleep(Duration::from_millis(100)),
+                    Err(err) => return Err(ExecutionError(err)),
                 }
+                continue;
             }
-        }
 
-        // Wait for the process to exit before reaching the timeout
-        // If not, we just kill it
-        let exit_status;
-        loop {
-            match cmd_handle.try_wait() {
-                Ok(Some(status)) => {
-                    exit_status = status;
-                    break;
-                }
-                Ok(None) => {
-                    // Does the process should be killed ?
-                    match abort_notifier.should_abort() {
-                        None => {}
-                        Some(reason @ AbortReason::Timeout(_)) | Some(reason @ AbortReason::Canceled(_)) => {
-                            let msg = format!("Killing process {:?} due to {:?}", self.command, reason);
-                            warn!("{}", msg);
-                            self.kill(&mut cmd_handle);
-
-                            // Drain output
-                            while let Some(Ok(line)) = stdout_reader.next() {
-                                stdout_output(line);
-                            }
-                            while let Some(Ok(line)) = stderr_reader.next() {
-                                stderr_output(line);
-                            }
-
-                            return if reason.is_cancel() {
-                                Err(Killed(msg))
-                            } else {
-                                Err(TimeoutError(msg))
-                            };
-                        }
-                    }
-                }
-                Err(err) => return Err(ExecutionError(err)),
-            };
+            if !output.forward_next(Duration::from_secs(1), stdout_output, stderr_output)
+                && output.idle_since() > LOGGING_INTERVAL
+                && last_heartbeat.elapsed() > LOGGING_INTERVAL
+            {
+                stderr_output(format!(
+                    "Command still running. No output available for {}s. Waiting for next line...",
+                    output.idle_since().as_secs()
+                ));
+                last_heartbeat = Instant::now();
+            }
+        };
 
-            // Sleep a bit and retry to check
-            std::thread::sleep(Duration::from_secs(1));
+        let output_details = output.details();
+        if output_details.dropped_bytes > 0 {
+            warn!(
+                "{} bytes of output of command {:?} have not been kept",
+                output_details.dropped_bytes, self.command
+            );
         }
 
         if !exit_status.success() {
@@ -379,7 +795,7 @@ impl ExecutableCommand for QoveryCommand {
                 "command: {:?} terminated with error exist status {:?}",
                 self.command, exit_status
             );
-            return Err(ExitStatusError(exit_status));
+            return Err(ExitStatusError(exit_status, output_details));
         }
 
         Ok(())
@@ -421,13 +837,17 @@ where
 #[cfg(test)]
 mod tests {
     use crate::cmd::command::{
-        does_binary_exist, run_version_command_for, CommandError, CommandKiller, ExecutableCommand, QoveryCommand,
+        does_binary_exist, run_version_command_for, BoundedLines, BoundedOutput, CommandError, CommandKiller,
+        CommandOutputLimits, ExecutableCommand, ExecutionContext, QoveryCommand,
     };
+    use crate::constants::{KUBECONFIG, QOVERY_CLUSTER_ID, QOVERY_EXECUTION_ID, QOVERY_STAGE, TF_PLUGIN_CACHE_DIR};
     use crate::environment::models::abort::{AbortStatus, AtomicAbortStatus};
+    use crate::events::{InfrastructureStep, Stage};
+    use std::io::{BufReader, Error, ErrorKind, Read};
     use std::sync::atomic::Ordering;
     use std::sync::{Arc, Barrier};
     use std::thread;
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_binary_exist() {
@@ -447,7 +867,7 @@ mod tests {
     fn test_error() {
         let mut cmd = QoveryCommand::new("false", &[], &[]);
         assert!(cmd.exec().is_err());
-        assert!(matches!(cmd.exec(), Err(CommandError::ExitStatusError(_))));
+        assert!(matches!(cmd.exec(), Err(CommandError::ExitStatusError(_, details)) if details.dropped_bytes == 0));
     }
 
     #[test]
@@ -468,6 +888,67 @@ mod tests {
         assert!(ret.is_ok());
     }
 
+    #[test]
+    fn test_command_with_idle_timeout() {
+        // silent after its first line, killed long before its total timeout
+        let mut cmd = QoveryCommand::new("sh", &["-c", "echo started; sleep 120"], &[]);
+        cmd.set_kill_grace_period(Duration::from_secs(2));
+        cmd.set_idle_timeout(Duration::from_secs(2));
+        let mut stdout = Vec::new();
+        let started_at = Instant::now();
+        let ret = cmd.exec_with_abort(
+            &mut |line| stdout.push(line),
+            &mut |_| {},
+            &CommandKiller::from_timeout(Duration::from_secs(60)),
+        );
+
+        assert!(matches!(ret, Err(CommandError::IdleTimeoutError(_))));
+        assert!(started_at.elapsed() < Duration::from_secs(30));
+        assert_eq!(stdout, vec!["started"]);
+
+        // each line resets the idle timeout
+        let mut cmd = QoveryCommand::new("sh", &["-c", "for i in 1 2 3 4 5; do echo $i; sleep 1; done"], &[]);
+        cmd.set_idle_timeout(Duration::from_secs(3));
+        let mut stdout = Vec::new();
+        let ret = cmd.exec_with_abort(
+            &mut |line| stdout.push(line),
+            &mut |_| {},
+            &CommandKiller::from_timeout(Duration::from_secs(60)),
+        );
+
+        assert!(ret.is_ok());
+        assert_eq!(stdout, vec!["1", "2", "3", "4", "5"]);
+    }
+
+    #[test]
+    fn test_command_with_total_timeout_while_producing_output() {
+        let mut cmd = QoveryCommand::new("sh", &["-c", "while true; do echo still working; sleep 1; done"], &[]);
+        cmd.set_kill_grace_period(Duration::from_secs(2));
+        cmd.set_idle_timeout(Duration::from_secs(3));
+        let ret = cmd.exec_with_abort(&mut |_| {}, &mut |_| {}, &CommandKiller::from_timeout(Duration::from_secs(5)));
+
+        assert!(matches!(ret, Err(CommandError::TimeoutError(_))));
+    }
+
+    #[test]
+    fn test_command_reads_stdout_and_stderr_concurrently() {
+        // stderr is filled way above the pipe capacity before anything is written on stdout
+        let mut cmd =
+            QoveryCommand::new("sh", &["-c", "seq 1 100000 >&2; echo done; sleep 2; echo stderr end >&2"], &[]);
+        cmd.set_idle_timeout(Duration::from_secs(10));
+        let mut stdout = Vec::new();
+        let mut stderr_lines = 0;
+        let ret = cmd.exec_with_abort(
+            &mut |line| stdout.push(line),
+            &mut |_| stderr_lines += 1,
+            &CommandKiller::from_timeout(Duration::from_secs(30)),
+        );
+
+        assert!(ret.is_ok());
+        assert_eq!(stdout, vec!["done"]);
+        assert_eq!(stderr_lines, 100_001);
+    }
+
     #[test]
     fn test_command_with_abort() {
         let mut cmd = QoveryCommand::new("sleep", &["120"], &[]);
@@ -491,4 +972,253 @@ mod tests {
 
         assert!(matches!(ret, Err(CommandError::Killed(_))));
     }
+
+    #[test]
+    fn test_execution_context_is_set_on_every_command() {
+        // setup:
+        let execution_context = ExecutionContext::new(
+            "execution-id".to_string(),
+            "cluster-id".to_string(),
+            "infrastructure:create".to_string(),
+        );
+        let guard = execution_context.clone().enter();
+
+        // execute:
+        // commands built the way the terraform, helm, docker, kubectl, skopeo and git wrappers build them
+        let commands = [
+            QoveryCommand::new("terraform", &["apply"], &[(TF_PLUGIN_CACHE_DIR, "/tmp/plugins")]),
+            QoveryCommand::new("helm", &["upgrade"], &[(KUBECONFIG, "/tmp/kubeconfig")]),
+            QoveryCommand::new("docker", &["buildx", "build"], &[]),
+            QoveryCommand::new("kubectl", &["get", "pods"], &[(KUBECONFIG, "/tmp/kubeconfig")]),
+            QoveryCommand::new("skopeo", &["copy"], &[]),
+            // a caller cannot override the execution context
+            QoveryCommand::new("git", &["lfs", "pull"], &[(QOVERY_EXECUTION_ID, "another-execution-id")]),
+        ];
+
+        // verify:
+        for cmd in &commands {
+            assert_eq!(cmd.get_env(QOVERY_EXECUTION_ID).as_deref(), Some("execution-id"));
+            assert_eq!(cmd.get_env(QOVERY_CLUSTER_ID).as_deref(), Some("cluster-id"));
+            assert_eq!(cmd.get_env(QOVERY_STAGE).as_deref(), Some("infrastructure:create"));
+        }
+        assert_eq!(commands[0].get_env(TF_PLUGIN_CACHE_DIR).as_deref(), Some("/tmp/plugins"));
+
+        let mut child_envs = vec![];
+        let ret = QoveryCommand::new("env", &[], &[]).exec_with_output(&mut |line| child_envs.push(line), &mut |_| {});
+        assert!(ret.is_ok());
+        assert!(child_envs.contains(&"QOVERY_EXECUTION_ID=execution-id".to_string()));
+        assert!(child_envs.contains(&"QOVERY_STAGE=infrastructure:create".to_string()));
+        assert_eq!(ExecutionContext::current(), Some(execution_context));
+
+        drop(guard);
+        assert_eq!(ExecutionContext::current(), None);
+    }
+
+    #[test]
+    fn test_execution_context_is_scoped_to_its_thread_and_follows_the_stage() {
+        // setup:
+        let execution_context = ExecutionContext::new(
+            "execution-id".to_string(),
+            "cluster-id".to_string(),
+            "infrastructure:start".to_string(),
+        );
+        let _guard = execution_context.clone().enter();
+
+        // execute:
+        let stage_guard = ExecutionContext::enter_stage(&Stage::Infrastructure(InfrastructureStep::Create));
+        let cmd = QoveryCommand::new("terraform", &["apply"], &[]);
+        let other_thread_context = thread::spawn(ExecutionContext::current).join().unwrap();
+        let propagated_context = thread::spawn({
+            let execution_context = ExecutionContext::current();
+            move || {
+                let _execution_context = execution_context.map(ExecutionContext::enter);
+                QoveryCommand::new("helm", &["upgrade"], &[]).get_env(QOVERY_STAGE)
+            }
+        })
+        .join()
+        .unwrap();
+        drop(stage_guard);
+
+        // verify:
+        assert_eq!(cmd.get_env(QOVERY_STAGE).as_deref(), Some("infrastructure:create"));
+        assert_eq!(cmd.get_env(QOVERY_EXECUTION_ID).as_deref(), Some("execution-id"));
+        assert_eq!(other_thread_context, None);
+        assert_eq!(propagated_context.as_deref(), Some("infrastructure:create"));
+        assert_eq!(ExecutionContext::current(), Some(execution_context));
+    }
+
+    #[test]
+    fn test_bounded_output_keeps_head_and_tail_of_huge_output() {
+        // setup:
+        let limits = CommandOutputLimits {
+            head_bytes: 1024 * 1024,
+            tail_bytes: 1024 * 1024,
+            max_line_bytes: 1024,
+        };
+        let mut output = BoundedOutput::new(limits);
+        let line_count: u64 = 3_000_000; // 100 bytes per line, ~300MB
+
+        // execute:
+        for index in 0..line_count {
+            output.push(format!("{index:099}"));
+            assert!(output.buffered_bytes() <= limits.head_bytes + limits.tail_bytes);
+        }
+        let kept = output.finish();
+
+        // verify:
+        let lines_per_buffer = (limits.head_bytes / 100) as u64;
+        let dropped_lines = line_count - 2 * lines_per_buffer;
+        assert_eq!(kept.len() as u64, 2 * lines_per_buffer + 1);
+        assert_eq!(kept[0], format!("{:099}", 0));
+        assert_eq!(kept[lines_per_buffer as usize - 1], format!("{:099}", lines_per_buffer - 1));
+        assert_eq!(
+            kept[lines_per_buffer as usize],
+            format!(
+                "[... {} lines ({} bytes) of output dropped ...]",
+                dropped_lines,
+                dropped_lines * 100
+            )
+        );
+        assert_eq!(kept.last(), Some(&format!("{:099}", line_count - 1)));
+        assert_eq!(output.dropped_bytes(), dropped_lines * 100);
+        assert_eq!(output.buffered_bytes(), 0);
+    }
+
+    #[test]
+    fn test_bounded_output_without_overflow_has_no_marker() {
+        let mut output = BoundedOutput::new(CommandOutputLimits {
+            head_bytes: 10,
+            tail_bytes: 10,
+            max_line_bytes: 10,
+        });
+
+        output.push("head".to_string());
+        output.push("tail1".to_string());
+        output.push("end".to_string());
+        assert_eq!(output.finish(), vec!["head", "tail1", "end"]);
+        assert_eq!(output.dropped_bytes(), 0);
+    }
+
+    #[test]
+    fn test_bounded_lines_truncates_endless_line() {
+        // setup:
+        let endless_line = std::io::repeat(b'a').take(200 * 1024 * 1024);
+        let mut lines = BoundedLines::new(BufReader::new(endless_line), 1024);
+
+        // execute:
+        let line = lines.next().expect("a line").expect("a valid line");
+
+        // verify:
+        let truncated_bytes = 200 * 1024 * 1024 - 1024;
+        assert_eq!(line, format!("{} [... {} bytes truncated]", "a".repeat(1024), truncated_bytes));
+        assert_eq!(lines.truncated_bytes, truncated_bytes as u64);
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn test_bounded_lines_keeps_partial_line_across_timeouts() {
+        // reader returning its chunks one by one, with a timeout between each of them
+        struct SlowReader {
+            chunks: Vec<&'static [u8]>,
+            is_timeout: bool,
+        }
+        impl Read for SlowReader {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.is_timeout = !self.is_timeout;
+                if self.is_timeout {
+                    return Err(Error::new(ErrorKind::TimedOut, "timeout"));
+                }
+                if self.chunks.is_empty() {
+                    return Ok(0);
+                }
+                let chunk = self.chunks.remove(0);
+                buf[..chunk.len()].copy_from_slice(chunk);
+                Ok(chunk.len())
+            }
+        }
+
+        let reader = SlowReader {
+            chunks: vec![b"first li", b"ne\r\nsecond", b" line\nlast"],
+            is_timeout: false,
+        };
+        let mut lines = BoundedLines::new(BufReader::new(reader), 1024);
+        let mut received = Vec::new();
+        for line in lines.by_ref() {
+            match line {
+                Ok(line) => received.push(line),
+                Err(err) => assert_eq!(err.kind(), ErrorKind::TimedOut),
+            }
+        }
+
+        assert_eq!(received, vec!["first line", "second line", "last"]);
+        assert_eq!(lines.truncated_bytes, 0);
+    }
+
+    #[test]
+    fn test_command_with_stdin() {
+        let mut cmd = QoveryCommand::new("cat", &[], &[]);
+        cmd.set_stdin("first line\nsecond line".to_string());
+        let mut stdout = Vec::new();
+
+        let ret = cmd.exec_with_output(&mut |line| stdout.push(line), &mut |_| {});
+
+        assert!(ret.is_ok());
+        assert_eq!(stdout, vec!["first line", "second line"]);
+    }
+
+    #[test]
+    fn test_command_output_is_forwarded_entirely_and_kept_bounded() {
+        // setup:
+        let limits = CommandOutputLimits {
+            head_bytes: 64 * 1024,
+            tail_bytes: 64 * 1024,
+            max_line_bytes: 1024,
+        };
+        let mut cmd = QoveryCommand::new(
+            "sh",
+            &[
+                "-c",
+                "echo FIRST; yes $(printf '%0100d' 0) | head -n 3000000; echo LAST; exit 3",
+            ],
+            &[],
+        );
+        cmd.set_output_limits(limits);
+        let mut forwarded_lines: u64 = 0;
+        let mut first_line = None;
+        let mut last_line = None;
+
+        // execute:
+        let ret = cmd.exec_with_output(
+            &mut |line| {
+                forwarded_lines += 1;
+                first_line.get_or_insert_with(|| line.clone());
+                last_line = Some(line);
+            },
+            &mut |_| {},
+        );
+
+        // verify:
+        assert_eq!(forwarded_lines, 3_000_002);
+        assert_eq!(first_line.as_deref(), Some("FIRST"));
+        assert_eq!(last_line.as_deref(), Some("LAST"));
+
+        let Err(err @ CommandError::ExitStatusError(exit_status, details)) = &ret else {
+            panic!("command must fail with its exit status: {ret:?}");
+        };
+        assert_eq!(exit_status.code(), Some(3));
+        let marker_bytes = 128;
+        let kept_bytes: usize = details.stdout.iter().map(|line| line.len() + 1).sum();
+        assert!(kept_bytes <= limits.head_bytes + limits.tail_bytes + marker_bytes);
+        assert_eq!(details.stdout.first().map(String::as_str), Some("FIRST"));
+        assert_eq!(details.stdout.last().map(String::as_str), Some("LAST"));
+        assert!(details
+            .stdout
+            .iter()
+            .any(|line| line.starts_with("[... ") && line.ends_with(" of output dropped ...]")));
+        assert!(details.stderr.is_empty());
+        assert!(details.dropped_bytes > 0);
+        assert!(err
+            .to_string()
+            .ends_with(&format!(" ({} bytes of output dropped)", details.dropped_bytes)));
+    }
 }
@@ -1,7 +1,11 @@
-use crate::cmd::command::{CommandError, CommandKiller, ExecutableCommand, QoveryCommand};
+use crate::cmd::command::{CommandError, CommandKiller, ExecutableCommand, ExecutionContext, QoveryCommand};
+use crate::cmd::docker_push_errors::{
+    classify_push_output, classify_push_output_line, pushed_manifest_digest, PushFailureKind,
+};
 use crate::io_models::models::CpuArchitecture;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
+use serde_derive::Deserialize;
 use std::cmp::max;
 use std::fmt::{Display, Formatter};
 use std::io::Write;
@@ -12,8 +16,8 @@ use std::str::FromStr;
 use std::sync::Mutex;
 use std::time::Duration;
 use std::{fs, thread};
-use tempfile::TempDir;
-use url::Url;
+use tempfile::{NamedTempFile, TempDir};
+use url::{Position, Url};
 use uuid::Uuid;
 
 #[derive(thiserror::Error, Debug)]
@@ -32,6 +36,9 @@ pub enum DockerError {
 
     #[error("Docker command terminated due to timeout: {raw_error_message:?}")]
     Timeout { raw_error_message: String },
+
+    #[error("Docker pushed image cannot be found in the registry: {raw_error_message:?}")]
+    PushNotVerified { raw_error_message: String },
 }
 
 impl DockerError {
@@ -45,6 +52,10 @@ impl DockerError {
 // Mostly use for CI/Test when all test start in parallel and it the login phase at the same time
 static LOGIN_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 static DEFAULT_BUILDER_NAME: &str = "qovery-engine";
+const PUSH_MAX_RETRY: u32 = 3;
+// a build step compiling without printing anything can be long, but not that long
+const DOCKER_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+static BINFMT_IMAGE: &str = "tonistiigi/binfmt:qemu-v8.1.5";
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Architecture {
@@ -59,6 +70,15 @@ impl Architecture {
             Architecture::ARM64 => "linux/arm64",
         }
     }
+
+    /// Architecture of the host the engine is running on
+    pub fn native() -> Option<Architecture> {
+        match std::env::consts::ARCH {
+            "x86_64" => Some(Architecture::AMD64),
+            "aarch64" => Some(Architecture::ARM64),
+            _ => None,
+        }
+    }
 }
 
 impl FromStr for Architecture {
@@ -93,7 +113,6 @@ impl Display for Architecture {
 
 #[derive(Debug, Clone)]
 enum ImageId {
-    #[allow(dead_code)]
     Digest(String),
     Tags(Vec<String>),
 }
@@ -116,7 +135,7 @@ impl ContainerImage {
         }
     }
 
-    fn _new_for_digest(registry: Url, name: String, digest: String) -> Self {
+    pub fn new_for_digest(registry: Url, name: String, digest: String) -> Self {
         ContainerImage {
             registry,
             name,
@@ -189,6 +208,8 @@ pub struct BuilderHandle {
     config_path: PathBuf,
     pub nb_builder: NonZeroUsize,
     pub builder_name: Option<String>,
+    // architectures without a native builder, built through QEMU emulation, which is far slower
+    pub emulated_architectures: Vec<Architecture>,
 }
 
 impl Drop for BuilderHandle {
@@ -326,30 +347,49 @@ impl Docker {
         should_abort: &CommandKiller,
         http_registries: &[&str],
         tls_invalid_registries: &[&str],
+        registry_mirrors: &[Url],
         bootstrap_builder: bool,
     ) -> Result<BuilderHandle, DockerError> {
         match &self.builder_location {
             // For local builder, we have at max 1 builder available
-            BuilderLocation::Local => Ok(BuilderHandle {
-                config_path: self.config_path.path().to_path_buf(),
-                nb_builder: NonZeroUsize::new(1).unwrap(),
-                builder_name: None,
-            }),
+            // Its registry mirrors are the ones of the local docker daemon, configured in its daemon.json
+            BuilderLocation::Local => {
+                let emulated_architectures = requested_architectures
+                    .iter()
+                    .filter(|arch| Some(**arch) != Architecture::native())
+                    .sorted()
+                    .dedup()
+                    .cloned()
+                    .collect_vec();
+                if !emulated_architectures.is_empty() {
+                    self.install_emulators(&emulated_architectures, should_abort)?;
+                }
+
+                Ok(BuilderHandle {
+                    config_path: self.config_path.path().to_path_buf(),
+                    nb_builder: NonZeroUsize::new(1).unwrap(),
+                    builder_name: None,
+                    emulated_architectures,
+                })
+            }
             BuilderLocation::Kubernetes {
                 namespace,
                 builder_prefix,
                 supported_architectures,
                 enable_rootless,
             } => {
-                let available_architectures = requested_architectures
-                    .iter()
-                    .filter(|arch| supported_architectures.contains(arch))
-                    .count();
-
-                if available_architectures != requested_architectures.len() {
+                // Architectures without builder nodes are emulated by the first node, buildkit ships QEMU for that
+                let (native_architectures, emulated_architectures): (Vec<&Architecture>, Vec<&Architecture>) =
+                    requested_architectures
+                        .iter()
+                        .sorted()
+                        .dedup()
+                        .partition(|arch| supported_architectures.contains(arch));
+
+                if native_architectures.is_empty() {
                     return Err(DockerError::InvalidConfig {
                         raw_error_message:
-                        format!("Some requested architectures are not supported by current docker builder. Available architectures are {supported_architectures:?} while requested are {requested_architectures:?}.")
+                        format!("None of the requested architectures are supported by current docker builder. Available architectures are {supported_architectures:?} while requested are {requested_architectures:?}.")
                     });
                 }
 
@@ -359,18 +399,13 @@ impl Docker {
                     config_path: self.config_path.path().to_path_buf(),
                     nb_builder,
                     builder_name: Some(builder_name.clone()),
+                    emulated_architectures: emulated_architectures.iter().map(|arch| **arch).collect(),
                 };
 
                 info!("docker spawn builder {:?} {:?}", builder_name, requested_architectures);
                 let buildkitd_cfg_arg = {
                     let cfg_path = self.config_path.path().join("buildkitd.toml");
-                    let mut cfg_file = String::new();
-                    for http in http_registries {
-                        cfg_file.push_str(&format!("[registry.\"{}\"]\n  http = true\n", http));
-                    }
-                    for tls in tls_invalid_registries {
-                        cfg_file.push_str(&format!("[registry.\"{}\"]\n  insecure = true\n", tls));
-                    }
+                    let cfg_file = buildkitd_config(http_registries, tls_invalid_registries, registry_mirrors);
                     info!("Docker buildkitd config {}", &cfg_file);
                     let _ = fs::write(&cfg_path, cfg_file);
 
@@ -378,11 +413,17 @@ impl Docker {
                 };
 
                 // Reference doc https://docs.docker.com/engine/reference/commandline/buildx_create
-                for (ix, arch) in requested_architectures.iter().enumerate() {
+                for (ix, arch) in native_architectures.iter().enumerate() {
                     let mut node_name = format!("{builder_prefix}{exec_id}-{arch}");
                     node_name.truncate(60);
                     let node_name = node_name.trim_matches(|c: char| !c.is_alphanumeric());
-                    let platform = format!("linux/{arch}");
+                    let platform = match ix {
+                        0 => std::iter::once(arch)
+                            .chain(emulated_architectures.iter())
+                            .map(|arch| arch.to_platform())
+                            .join(","),
+                        _ => arch.to_platform().to_string(),
+                    };
                     let mut driver_opt = format!(concat!(
                     "--driver-opt=",
                     "\"namespace={}\",",
@@ -436,10 +477,41 @@ impl Docker {
         }
     }
 
+    // Registers the QEMU emulators of the architectures in the kernel of the docker host, for RUN steps of foreign images
+    fn install_emulators(
+        &self,
+        architectures: &[Architecture],
+        should_abort: &CommandKiller,
+    ) -> Result<(), DockerError> {
+        info!("Docker install emulators for {:?}", architectures);
+        let architectures = architectures.iter().join(",");
+        docker_exec(
+            &[
+                "--config",
+                self.config_path.path().to_str().unwrap_or(""),
+                "run",
+                "--privileged",
+                "--rm",
+                BINFMT_IMAGE,
+                "--install",
+                &architectures,
+            ],
+            &self.get_all_envs(&[]),
+            &mut |line| info!("{}", line),
+            &mut |line| info!("{}", line),
+            should_abort,
+        )
+    }
+
     pub fn socket_url(&self) -> &Option<Url> {
         &self.socket_location
     }
 
+    // Directory of the docker config, holding the credentials of the registries we are logged into
+    pub fn config_path(&self) -> &Path {
+        self.config_path.path()
+    }
+
     fn get_all_envs<'a>(&'a self, envs: &'a [(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
         let mut all_envs: Vec<(&str, &str)> = self.common_envs.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
         all_envs.append(&mut envs.to_vec());
@@ -587,6 +659,110 @@ impl Docker {
         }
     }
 
+    /// Digest of the manifest the image points to, the index one for a multi-platform image.
+    /// Returns `None` when the image does not exist in the registry.
+    pub fn image_digest(&self, image: &ContainerImage) -> Result<Option<String>, DockerError> {
+        info!("Docker get remote digest of image {:?}", image);
+
+        let builder = self.configure_builder_for_http_registries(image);
+        let image_name = image.image_name();
+        let mut args = vec![
+            "--config",
+            self.config_path.path().to_str().unwrap_or(""),
+            "buildx",
+            "imagetools",
+            "inspect",
+            &image_name,
+            "--format",
+            "{{ .Manifest.Digest }}",
+        ];
+        if let Some(builder_name) = &builder.as_ref().and_then(|b| b.builder_name.as_deref()) {
+            args.push("--builder");
+            args.push(builder_name)
+        }
+
+        let mut digest = None;
+        let ret = docker_exec(
+            &args,
+            &self.get_all_envs(&[]),
+            &mut |line| {
+                if line.trim().starts_with("sha256:") {
+                    digest = Some(line.trim().to_string());
+                }
+            },
+            &mut |line| warn!("{}", line),
+            &CommandKiller::never(),
+        );
+
+        match ret {
+            Ok(_) => digest.map(Some).ok_or_else(|| DockerError::ExecutionError {
+                raw_error: std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("No digest returned for image {image_name}"),
+                ),
+            }),
+            Err(DockerError::ExitStatusError { .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Architectures of the images listed by the manifest list of the image, None if the image does not exist
+    pub fn image_architectures(&self, image: &ContainerImage) -> Result<Option<Vec<Architecture>>, DockerError> {
+        info!("Docker inspect manifest of image {:?}", image);
+
+        let builder = self.configure_builder_for_http_registries(image);
+        let image_name = image.image_name();
+        let mut args = vec![
+            "--config",
+            self.config_path.path().to_str().unwrap_or(""),
+            "buildx",
+            "imagetools",
+            "inspect",
+            "--raw",
+            &image_name,
+        ];
+        if let Some(builder_name) = &builder.as_ref().and_then(|b| b.builder_name.as_deref()) {
+            args.push("--builder");
+            args.push(builder_name)
+        }
+
+        let mut output: Vec<String> = vec![];
+        let ret = docker_exec(
+            &args,
+            &self.get_all_envs(&[]),
+            &mut |line| output.push(line),
+            &mut |line| warn!("{}", line),
+            &CommandKiller::never(),
+        );
+
+        match ret {
+            Ok(_) => manifest_list_architectures(&output.join("\n"))
+                .map(Some)
+                .map_err(|err| DockerError::InvalidConfig {
+                    raw_error_message: format!("Invalid manifest of image {image_name}: {err}"),
+                }),
+            Err(DockerError::ExitStatusError { .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Same as `does_image_exist_remotely`, but an image built for several architectures must have a manifest list
+    /// with all of them. Otherwise, pods scheduled on the nodes of a missing architecture could not pull it.
+    pub fn does_image_exist_remotely_for_architectures(
+        &self,
+        image: &ContainerImage,
+        architectures: &[Architecture],
+    ) -> Result<bool, DockerError> {
+        if architectures.iter().all_equal() {
+            return self.does_image_exist_remotely(image);
+        }
+
+        Ok(match self.image_architectures(image)? {
+            Some(image_architectures) => architectures.iter().all(|arch| image_architectures.contains(arch)),
+            None => false,
+        })
+    }
+
     pub fn pull<Stdout, Stderr>(
         &self,
         image: &ContainerImage,
@@ -621,7 +797,8 @@ impl Docker {
         context: &Path,
         image_to_build: &ContainerImage,
         build_args: &[(&str, &str)],
-        cache: &ContainerImage,
+        secrets: &[(&str, &str)],
+        cache: Option<&ContainerImage>,
         push_after_build: bool,
         architectures: &[Architecture],
         stdout_output: &mut Stdout,
@@ -645,17 +822,31 @@ impl Docker {
             });
         }
 
+        // A manifest list cannot be loaded into the local docker images, it only exists in a registry
+        if !push_after_build && !architectures.iter().all_equal() {
+            return Err(DockerError::InvalidConfig {
+                raw_error_message: format!(
+                    "building for several architectures {architectures:?} requires to push the image"
+                ),
+            });
+        }
+
+        // Files are removed when dropped, so whatever the outcome of the build (even an abort)
+        let secret_files = BuildSecretFiles::new(secrets)?;
+        let secret_values = secrets.iter().map(|(_, value)| *value).collect::<Vec<_>>();
+
         self.build_with_buildkit(
             builder_name,
             dockerfile,
             context,
             image_to_build,
             build_args,
+            &secret_files.args(),
             cache,
             push_after_build,
             architectures,
-            stdout_output,
-            stderr_output,
+            &mut |line| stdout_output(mask_secrets(line, &secret_values)),
+            &mut |line| stderr_output(mask_secrets(line, &secret_values)),
             should_abort,
         )
     }
@@ -667,7 +858,8 @@ impl Docker {
         context: &Path,
         image_to_build: &ContainerImage,
         build_args: &[(&str, &str)],
-        cache: &ContainerImage,
+        secrets: &[(&str, &Path)],
+        cache: Option<&ContainerImage>,
         push_after_build: bool,
         architectures: &[Architecture],
         stdout_output: &mut Stdout,
@@ -680,74 +872,62 @@ impl Docker {
     {
         info!("Docker buildkit build {:?}", image_to_build.image_name());
 
-        let mut args_string: Vec<String> = vec![
-            "--config".to_string(),
-            self.config_path.path().to_str().unwrap_or("").to_string(),
-            "buildx".to_string(),
-            "build".to_string(),
-            if let Some(builder_name) = builder_name {
-                format!("--builder={}", builder_name)
-            } else {
-                format!("--builder={}", DEFAULT_BUILDER_NAME)
-            },
-            "--progress=plain".to_string(),
-            if push_after_build {
-                "--output=type=registry".to_string() // tell buildkit to push image to registry
-            } else {
-                "--output=type=docker".to_string() // tell buildkit to load the image into docker after build
-            },
-            "--cache-from".to_string(),
-            format!("type=registry,ref={}", cache.image_name()),
-            "-f".to_string(),
-            dockerfile.to_str().unwrap_or_default().to_string(),
-        ];
-
-        if push_after_build {
-            args_string.push("--cache-to".to_string());
-            args_string.push(format!(
-                "type=registry,mode=max,image-manifest=true,oci-mediatypes=true,ref={}",
-                cache.image_name()
-            ));
-        }
-
-        // Build for all requested architectures, if empty build for the current architecture the engine is running on
-        if !architectures.is_empty() {
-            args_string.push(format!(
-                "--platform={}",
-                architectures.iter().map(|arch| arch.to_platform()).join(",")
-            ));
-        };
-
-        for image_name in image_to_build.image_names() {
-            args_string.push("--tag".to_string());
-            args_string.push(image_name.to_string())
-        }
-
-        for (k, v) in build_args {
-            args_string.push("--build-arg".to_string());
-            args_string.push(format!("{k}={v}"));
-        }
-        args_string.push(context.to_str().unwrap_or_default().to_string());
+        let export_cache = push_after_build && cache.is_some();
+        with_cache_export_fallback(export_cache, should_abort, |export_cache| {
+            let args = buildkit_build_args(
+                self.config_path.path(),
+                builder_name,
+                dockerfile,
+                context,
+                image_to_build,
+                build_args,
+                secrets,
+                cache,
+                export_cache,
+                push_after_build,
+                architectures,
+            );
+            self.exec_buildkit_build(&args, push_after_build, stdout_output, stderr_output, should_abort)
+        })
+    }
 
+    // Returns the result of the build, and whether it failed to export its cache
+    fn exec_buildkit_build<Stdout, Stderr>(
+        &self,
+        args: &[String],
+        push_after_build: bool,
+        stdout_output: &mut Stdout,
+        stderr_output: &mut Stderr,
+        should_abort: &CommandKiller,
+    ) -> (Result<(), DockerError>, bool)
+    where
+        Stdout: FnMut(String),
+        Stderr: FnMut(String),
+    {
         // Hack
         // Sometimes, the build can fail with a transient error, we need to retry, for stability ...
         let mut nb_retry = 3;
         let started_at = std::time::Instant::now();
-        let ret = loop {
+        loop {
             let mut transient_error = false;
+            let mut cache_export_error = false;
             let ret = {
                 let mut stderr_output = |line: String| {
                     if line.contains("ERROR: listing workers for Build")
                         || line.contains("use of closed network connection")
                         || line.contains("i/o timeout")
+                        || (push_after_build && classify_push_output_line(&line) == Some(PushFailureKind::Retriable))
                     {
                         transient_error = true;
                     }
+                    if is_cache_export_error(&line) {
+                        cache_export_error = true;
+                    }
 
                     stderr_output(line);
                 };
                 docker_exec(
-                    &args_string.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
+                    &args.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
                     &self.get_all_envs(&[]),
                     stdout_output,
                     &mut stderr_output,
@@ -758,7 +938,7 @@ impl Docker {
             if ret.is_err() && transient_error && should_abort.should_abort().is_none() {
                 if nb_retry == 0 && started_at.elapsed() > Duration::from_secs(60 * 3) {
                     info!("Docker buildkit build failed with a transient error, but we already retried for too long, aborting ...");
-                    break ret;
+                    break (ret, cache_export_error);
                 }
 
                 nb_retry = max(nb_retry - 1, 0);
@@ -767,10 +947,8 @@ impl Docker {
                 continue;
             }
 
-            break ret;
-        };
-
-        ret
+            break (ret, cache_export_error);
+        }
     }
 
     pub fn push<Stdout, Stderr>(
@@ -792,7 +970,59 @@ impl Docker {
                 "push",
                 image_name.as_str(),
             ];
-            docker_exec(&args, &self.get_all_envs(&[]), stdout_output, stderr_output, should_abort)?
+
+            // Registries sometimes answer 5xx in the middle of a layer upload. On retry, docker skips the layers
+            // already present in the registry, so only the missing ones are uploaded again.
+            let mut nb_retry = PUSH_MAX_RETRY;
+            let pushed_digest = loop {
+                let mut output: Vec<String> = vec![];
+                let mut errors: Vec<String> = vec![];
+                let ret = docker_exec(
+                    &args,
+                    &self.get_all_envs(&[]),
+                    &mut |line| {
+                        output.push(line.clone());
+                        stdout_output(line)
+                    },
+                    &mut |line| {
+                        errors.push(line.clone());
+                        stderr_output(line)
+                    },
+                    should_abort,
+                );
+
+                let err = match ret {
+                    Ok(_) => break pushed_manifest_digest(output.iter().map(|l| l.as_str())),
+                    Err(err) => err,
+                };
+
+                let failure_kind = classify_push_output(output.iter().chain(errors.iter()).map(|l| l.as_str()));
+                if failure_kind == PushFailureKind::Permanent || nb_retry == 0 || should_abort.should_abort().is_some()
+                {
+                    return Err(err);
+                }
+
+                let backoff = Duration::from_secs(2_u64.pow(PUSH_MAX_RETRY - nb_retry) * 5);
+                nb_retry -= 1;
+                info!(
+                    "Docker push of {} failed with a transient registry error, retrying in {:?} ...",
+                    image_name, backoff
+                );
+                thread::sleep(backoff);
+            };
+
+            // Make sure the registry really has the manifest we pushed, and not a partial upload
+            if let Some(digest) = pushed_digest {
+                let pushed_image = ContainerImage::new_for_digest(image.registry.clone(), image.name.clone(), digest);
+                if !self.does_image_exist_remotely(&pushed_image)? {
+                    return Err(DockerError::PushNotVerified {
+                        raw_error_message: format!(
+                            "Manifest {} is not present in the registry after push",
+                            pushed_image.image_name()
+                        ),
+                    });
+                }
+            }
         }
 
         Ok(())
@@ -903,6 +1133,7 @@ impl Docker {
                     &CommandKiller::never(),
                     &[http_registries.as_str()],
                     &[],
+                    &[],
                     false,
                 )
                 .ok();
@@ -912,6 +1143,237 @@ impl Docker {
     }
 }
 
+// Content of the buildkitd.toml of the kubernetes builders.
+// Docker Hub pulls go through the mirrors in order, buildkit falls back on docker.io when none of them answers.
+fn buildkitd_config(http_registries: &[&str], tls_invalid_registries: &[&str], registry_mirrors: &[Url]) -> String {
+    let mut cfg_file = String::new();
+    if !registry_mirrors.is_empty() {
+        let mirrors = registry_mirrors
+            .iter()
+            .map(|mirror| {
+                format!(
+                    "\"{}\"",
+                    mirror[Position::BeforeHost..Position::AfterPath].trim_end_matches('/')
+                )
+            })
+            .join(", ");
+        cfg_file.push_str(&format!("[registry.\"docker.io\"]\n  mirrors = [{}]\n", mirrors));
+    }
+
+    let http_mirrors = registry_mirrors
+        .iter()
+        .filter(|mirror| mirror.scheme() == "http")
+        .map(|mirror| &mirror[Position::BeforeHost..Position::AfterPort]);
+    for http in http_registries.iter().copied().chain(http_mirrors).unique() {
+        cfg_file.push_str(&format!("[registry.\"{}\"]\n  http = true\n", http));
+    }
+    for tls in tls_invalid_registries {
+        cfg_file.push_str(&format!("[registry.\"{}\"]\n  insecure = true\n", tls));
+    }
+
+    cfg_file
+}
+
+// Lines of buildx output telling the registry refused the cache we tried to export
+const CACHE_EXPORT_ERRORS: &[&str] = &["failed to export cache", "error writing cache", "cache manifest"];
+
+fn is_cache_export_error(line: &str) -> bool {
+    let line = line.to_lowercase();
+    line.contains("error") && CACHE_EXPORT_ERRORS.iter().any(|error| line.contains(error))
+}
+
+// Some registries reject cache manifests, it must not fail the build: it is done again without exporting the cache
+fn with_cache_export_fallback<F>(
+    export_cache: bool,
+    should_abort: &CommandKiller,
+    mut build: F,
+) -> Result<(), DockerError>
+where
+    F: FnMut(bool) -> (Result<(), DockerError>, bool),
+{
+    let (ret, cache_export_error) = build(export_cache);
+    if ret.is_err() && export_cache && cache_export_error && should_abort.should_abort().is_none() {
+        info!("Docker buildkit build failed to export its cache to the registry, building again without exporting it");
+        return build(false).0;
+    }
+
+    ret
+}
+
+/// `--platform` flag of a build targeting the architectures, several architectures produce a manifest list
+pub fn platforms_flag(architectures: &[Architecture]) -> Option<String> {
+    if architectures.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "--platform={}",
+        architectures
+            .iter()
+            .sorted()
+            .dedup()
+            .map(|arch| arch.to_platform())
+            .join(",")
+    ))
+}
+
+// Architectures of the images listed by a manifest list (or OCI index), attestations are not images and are ignored.
+// A single manifest has no platform, it is returned as an empty list.
+fn manifest_list_architectures(raw_manifest: &str) -> Result<Vec<Architecture>, serde_json::Error> {
+    #[derive(Deserialize)]
+    struct Manifest {
+        #[serde(default)]
+        manifests: Vec<ManifestDescriptor>,
+    }
+    #[derive(Deserialize)]
+    struct ManifestDescriptor {
+        platform: Option<Platform>,
+    }
+    #[derive(Deserialize)]
+    struct Platform {
+        os: String,
+        architecture: String,
+    }
+
+    let manifest: Manifest = serde_json::from_str(raw_manifest)?;
+    Ok(manifest
+        .manifests
+        .into_iter()
+        .filter_map(|descriptor| descriptor.platform)
+        .filter(|platform| platform.os == "linux")
+        .filter_map(|platform| Architecture::from_str(&platform.architecture).ok())
+        .sorted()
+        .dedup()
+        .collect())
+}
+
+fn buildkit_build_args(
+    config_path: &Path,
+    builder_name: &Option<&str>,
+    dockerfile: &Path,
+    context: &Path,
+    image_to_build: &ContainerImage,
+    build_args: &[(&str, &str)],
+    secrets: &[(&str, &Path)],
+    cache: Option<&ContainerImage>,
+    export_cache: bool,
+    push_after_build: bool,
+    architectures: &[Architecture],
+) -> Vec<String> {
+    let mut args_string: Vec<String> = vec![
+        "--config".to_string(),
+        config_path.to_str().unwrap_or("").to_string(),
+        "buildx".to_string(),
+        "build".to_string(),
+        if let Some(builder_name) = builder_name {
+            format!("--builder={}", builder_name)
+        } else {
+            format!("--builder={}", DEFAULT_BUILDER_NAME)
+        },
+        "--progress=plain".to_string(),
+        if push_after_build {
+            "--output=type=registry".to_string() // tell buildkit to push image to registry
+        } else {
+            "--output=type=docker".to_string() // tell buildkit to load the image into docker after build
+        },
+    ];
+
+    if let Some(cache) = cache {
+        args_string.push("--cache-from".to_string());
+        args_string.push(format!("type=registry,ref={}", cache.image_name()));
+
+        // image manifest media type, as some registries (ECR) reject the default cache manifest list
+        if export_cache {
+            args_string.push("--cache-to".to_string());
+            args_string.push(format!(
+                "type=registry,mode=max,image-manifest=true,oci-mediatypes=true,ref={}",
+                cache.image_name()
+            ));
+        }
+    }
+
+    args_string.push("-f".to_string());
+    args_string.push(dockerfile.to_str().unwrap_or_default().to_string());
+
+    // Build for all requested architectures, if empty build for the current architecture the engine is running on
+    if let Some(platforms) = platforms_flag(architectures) {
+        args_string.push(platforms);
+    };
+
+    for image_name in image_to_build.image_names() {
+        args_string.push("--tag".to_string());
+        args_string.push(image_name.to_string())
+    }
+
+    for (k, v) in build_args {
+        args_string.push("--build-arg".to_string());
+        args_string.push(format!("{k}={v}"));
+    }
+
+    // only the path of the secret is given, the value never appears in the command line
+    for (id, path) in secrets {
+        args_string.push("--secret".to_string());
+        args_string.push(format!("id={id},src={}", path.to_str().unwrap_or_default()));
+    }
+    // the execution which built an image can be found from its labels
+    if let Some(execution_context) = ExecutionContext::current() {
+        args_string.push("--label".to_string());
+        args_string.push(format!("qovery.com/execution-id={}", execution_context.execution_id()));
+    }
+    args_string.push(context.to_str().unwrap_or_default().to_string());
+
+    args_string
+}
+
+// Build secrets written into files for buildkit, prefer tmpfs so the values never touch the disk.
+// Files are only readable by the engine and are removed on drop.
+struct BuildSecretFiles {
+    files: Vec<(String, NamedTempFile)>,
+}
+
+impl BuildSecretFiles {
+    fn new(secrets: &[(&str, &str)]) -> Result<Self, DockerError> {
+        Self::new_in(secrets, &Self::secrets_dir())
+    }
+
+    fn new_in(secrets: &[(&str, &str)], dir: &Path) -> Result<Self, DockerError> {
+        let mut files = Vec::with_capacity(secrets.len());
+        for (id, value) in secrets {
+            let file = tempfile::Builder::new()
+                .prefix("build-secret-")
+                .tempfile_in(dir)
+                .and_then(|mut file| file.write_all(value.as_bytes()).map(|_| file))
+                .map_err(|err| DockerError::InvalidConfig {
+                    raw_error_message: format!("cannot write build secret `{id}`: {err}"),
+                })?;
+            files.push((id.to_string(), file));
+        }
+
+        Ok(BuildSecretFiles { files })
+    }
+
+    fn secrets_dir() -> PathBuf {
+        let shm = Path::new("/dev/shm");
+        if shm.is_dir() {
+            shm.to_path_buf()
+        } else {
+            std::env::temp_dir()
+        }
+    }
+
+    fn args(&self) -> Vec<(&str, &Path)> {
+        self.files.iter().map(|(id, file)| (id.as_str(), file.path())).collect()
+    }
+}
+
+// Build output can echo a secret (i.e: a RUN printing it), it must never reach the logs
+fn mask_secrets(line: String, secrets: &[&str]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(line, |line, secret| line.replace(secret, "xxx"))
+}
+
 fn docker_exec<F, X>(
     args: &[&str],
     envs: &[(&str, &str)],
@@ -925,13 +1387,16 @@ where
 {
     let mut cmd = QoveryCommand::new("docker", args, envs);
     cmd.set_kill_grace_period(Duration::from_secs(0));
+    cmd.set_idle_timeout(DOCKER_IDLE_TIMEOUT);
     let ret = cmd.exec_with_abort(stdout_output, stderr_output, cmd_killer);
 
     match ret {
         Ok(_) => Ok(()),
-        Err(CommandError::TimeoutError(msg)) => Err(DockerError::Timeout { raw_error_message: msg }),
+        Err(CommandError::TimeoutError(msg) | CommandError::IdleTimeoutError(msg)) => {
+            Err(DockerError::Timeout { raw_error_message: msg })
+        }
         Err(CommandError::Killed(msg)) => Err(DockerError::Aborted { raw_error_message: msg }),
-        Err(CommandError::ExitStatusError(err)) => Err(DockerError::ExitStatusError { exit_status: err }),
+        Err(CommandError::ExitStatusError(err, _)) => Err(DockerError::ExitStatusError { exit_status: err }),
         Err(CommandError::ExecutionError(err)) => Err(DockerError::ExecutionError { raw_error: err }),
     }
 }
@@ -1024,7 +1489,8 @@ mod tests {
             Path::new("tests/docker/multi_stage_simple/"),
             &image_to_build,
             &[],
-            &image_cache,
+            &[],
+            Some(&image_cache),
             false,
             CPU_ARCHITECTURE,
             &mut |msg| println!("{msg}"),
@@ -1040,7 +1506,8 @@ mod tests {
             Path::new("tests/docker/multi_stage_simple/"),
             &image_to_build,
             &[],
-            &image_cache,
+            &[],
+            Some(&image_cache),
             false,
             CPU_ARCHITECTURE,
             &mut |msg| println!("{msg}"),
@@ -1074,7 +1541,8 @@ mod tests {
             Path::new("tests/docker/multi_stage_simple/"),
             &image_to_build,
             &[],
-            &image_cache,
+            &[],
+            Some(&image_cache),
             false,
             CPU_ARCHITECTURE,
             &mut |msg| println!("{msg}"),
@@ -1182,6 +1650,7 @@ mod tests {
                 &CommandKiller::never(),
                 &[],
                 &[],
+                &[],
                 true,
             )
             .unwrap();
@@ -1204,7 +1673,8 @@ mod tests {
             Path::new("tests/docker/multi_stage_simple/"),
             &image_to_build,
             &[],
-            &image_cache,
+            &[],
+            Some(&image_cache),
             false,
             &[Architecture::AMD64],
             &mut |msg| println!("{msg}"),
@@ -1215,3 +1685,250 @@ mod tests {
         assert!(ret.is_ok());
     }
 }
+
+#[cfg(test)]
+mod build_cache_tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    fn image(tag: &str) -> ContainerImage {
+        ContainerImage::new(
+            Url::parse("https://123.dkr.ecr.eu-west-3.amazonaws.com").unwrap(),
+            "my-app".to_string(),
+            vec![tag.to_string()],
+        )
+    }
+
+    fn build_args(cache: Option<&ContainerImage>, export_cache: bool, push_after_build: bool) -> Vec<String> {
+        buildkit_build_args(
+            Path::new("/tmp/docker-config"),
+            &Some("builder-1"),
+            Path::new("app/Dockerfile"),
+            Path::new("app/"),
+            &image("v1"),
+            &[("KEY", "value")],
+            &[],
+            cache,
+            export_cache,
+            push_after_build,
+            &[Architecture::AMD64],
+        )
+    }
+
+    #[test]
+    fn test_build_args_import_and_export_registry_cache() {
+        let cache = image("cache");
+
+        assert_eq!(
+            build_args(Some(&cache), true, true),
+            vec![
+                "--config",
+                "/tmp/docker-config",
+                "buildx",
+                "build",
+                "--builder=builder-1",
+                "--progress=plain",
+                "--output=type=registry",
+                "--cache-from",
+                "type=registry,ref=123.dkr.ecr.eu-west-3.amazonaws.com/my-app:cache",
+                "--cache-to",
+                "type=registry,mode=max,image-manifest=true,oci-mediatypes=true,ref=123.dkr.ecr.eu-west-3.amazonaws.com/my-app:cache",
+                "-f",
+                "app/Dockerfile",
+                "--platform=linux/amd64",
+                "--tag",
+                "123.dkr.ecr.eu-west-3.amazonaws.com/my-app:v1",
+                "--build-arg",
+                "KEY=value",
+                "app/",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_args_without_cache_export() {
+        let cache = image("cache");
+
+        let args = build_args(Some(&cache), false, true);
+        assert!(args.contains(&"--cache-from".to_string()));
+        assert!(!args.contains(&"--cache-to".to_string()));
+
+        let args = build_args(None, false, true);
+        assert!(!args.iter().any(|arg| arg.starts_with("--cache")));
+    }
+
+    #[test]
+    fn test_cache_export_error_lines() {
+        assert!(is_cache_export_error(
+            "ERROR: failed to solve: failed to export cache: error writing manifest blob: unexpected status: 400 Bad Request"
+        ));
+        assert!(is_cache_export_error(
+            "error: failed to solve: error writing cache manifest: unexpected status 415"
+        ));
+        assert!(!is_cache_export_error("#12 exporting cache to registry"));
+        assert!(!is_cache_export_error(
+            "ERROR: failed to solve: process \"/bin/sh -c make\" did not complete successfully: exit code: 2"
+        ));
+    }
+
+    fn failed_build() -> Result<(), DockerError> {
+        Err(DockerError::ExitStatusError {
+            exit_status: ExitStatus::from_raw(256),
+        })
+    }
+
+    #[test]
+    fn test_build_falls_back_without_cache_export_when_cache_push_fails() {
+        let mut exports = vec![];
+        let ret = with_cache_export_fallback(true, &CommandKiller::never(), |export_cache| {
+            exports.push(export_cache);
+            match export_cache {
+                true => (failed_build(), true),
+                false => (Ok(()), false),
+            }
+        });
+
+        assert!(ret.is_ok());
+        assert_eq!(exports, vec![true, false]);
+    }
+
+    #[test]
+    fn test_build_failure_unrelated_to_cache_is_not_retried() {
+        let mut exports = vec![];
+        let ret = with_cache_export_fallback(true, &CommandKiller::never(), |export_cache| {
+            exports.push(export_cache);
+            (failed_build(), false)
+        });
+        assert!(matches!(ret, Err(DockerError::ExitStatusError { .. })));
+        assert_eq!(exports, vec![true]);
+
+        // an aborted build is not done again
+        let mut exports = vec![];
+        let ret = with_cache_export_fallback(true, &CommandKiller::from_timeout(Duration::ZERO), |export_cache| {
+            exports.push(export_cache);
+            (failed_build(), true)
+        });
+        assert!(ret.is_err());
+        assert_eq!(exports, vec![true]);
+    }
+
+    #[test]
+    fn test_build_args_with_secrets() {
+        let args = buildkit_build_args(
+            Path::new("/tmp/docker-config"),
+            &None,
+            Path::new("app/Dockerfile"),
+            Path::new("app/"),
+            &image("v1"),
+            &[],
+            &[
+                ("npm", Path::new("/dev/shm/build-secret-1")),
+                ("pip", Path::new("/dev/shm/build-secret-2")),
+            ],
+            None,
+            false,
+            true,
+            &[],
+        );
+
+        assert_eq!(
+            args[args.len() - 5..],
+            [
+                "--secret",
+                "id=npm,src=/dev/shm/build-secret-1",
+                "--secret",
+                "id=pip,src=/dev/shm/build-secret-2",
+                "app/",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_secret_files_are_removed_on_drop() {
+        let dir = TempDir::new().unwrap();
+        let files = BuildSecretFiles::new_in(&[("npm", "my-npm-token"), ("empty", "")], dir.path()).unwrap();
+
+        let args = files.args();
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[0].0, "npm");
+        assert_eq!(fs::read_to_string(args[0].1).unwrap(), "my-npm-token");
+        assert_eq!(fs::read_to_string(args[1].1).unwrap(), "");
+        let paths = args.iter().map(|(_, path)| path.to_path_buf()).collect::<Vec<_>>();
+
+        drop(files);
+        assert!(paths.iter().all(|path| !path.exists()));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_build_output_masks_secrets() {
+        assert_eq!(
+            mask_secrets("#7 0.31 token=my-npm-token".to_string(), &["my-npm-token", ""]),
+            "#7 0.31 token=xxx"
+        );
+        assert_eq!(mask_secrets("#8 DONE 0.2s".to_string(), &["my-npm-token"]), "#8 DONE 0.2s");
+    }
+
+    #[test]
+    fn test_platforms_flag_deduplicates_architectures() {
+        assert_eq!(platforms_flag(&[]), None);
+        assert_eq!(
+            platforms_flag(&[Architecture::ARM64, Architecture::AMD64, Architecture::ARM64]),
+            Some("--platform=linux/amd64,linux/arm64".to_string())
+        );
+    }
+
+    #[test]
+    fn test_manifest_list_architectures() {
+        let manifest_list = r#"{
+          "schemaVersion": 2,
+          "mediaType": "application/vnd.oci.image.index.v1+json",
+          "manifests": [
+            {"mediaType": "application/vnd.oci.image.manifest.v1+json", "digest": "sha256:a1", "platform": {"architecture": "arm64", "os": "linux"}},
+            {"mediaType": "application/vnd.oci.image.manifest.v1+json", "digest": "sha256:a2", "platform": {"architecture": "amd64", "os": "linux"}},
+            {"mediaType": "application/vnd.oci.image.manifest.v1+json", "digest": "sha256:a3", "platform": {"architecture": "unknown", "os": "unknown"}}
+          ]
+        }"#;
+        assert_eq!(
+            manifest_list_architectures(manifest_list).unwrap(),
+            vec![Architecture::AMD64, Architecture::ARM64]
+        );
+
+        // a single arch image has no manifest list
+        let manifest = r#"{
+          "schemaVersion": 2,
+          "mediaType": "application/vnd.docker.distribution.manifest.v2+json",
+          "config": {"mediaType": "application/vnd.docker.container.image.v1+json", "digest": "sha256:c1"},
+          "layers": []
+        }"#;
+        assert_eq!(manifest_list_architectures(manifest).unwrap(), vec![]);
+
+        assert!(manifest_list_architectures("not a manifest").is_err());
+    }
+
+    #[test]
+    fn test_buildkitd_config() {
+        assert_eq!(buildkitd_config(&[], &[], &[]), "");
+        assert_eq!(
+            buildkitd_config(&["localhost:5000"], &["registry.lan:443"], &[]),
+            "[registry.\"localhost:5000\"]\n  http = true\n[registry.\"registry.lan:443\"]\n  insecure = true\n"
+        );
+    }
+
+    #[test]
+    fn test_buildkitd_config_with_registry_mirrors() {
+        let mirrors = vec![
+            Url::parse("https://mirror.gcr.io").unwrap(),
+            Url::parse("http://registry.lan:5000/dockerhub/").unwrap(),
+        ];
+        assert_eq!(
+            buildkitd_config(&["registry.lan:5000"], &[], &mirrors),
+            concat!(
+                "[registry.\"docker.io\"]\n",
+                "  mirrors = [\"mirror.gcr.io\", \"registry.lan:5000/dockerhub\"]\n",
+                "[registry.\"registry.lan:5000\"]\n",
+                "  http = true\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,280 @@
+// Classification of the docker push output, to know if a failed push is worth retrying.
+// Registries (ECR, GAR, Scaleway, ...) regularly answer 5xx during layer upload, while authorization
+// or quota errors will fail the same way on every attempt.
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PushFailureKind {
+    Retriable,
+    Permanent,
+}
+
+struct PushErrorPattern {
+    // lowercase pattern, matched against the lowercased output line
+    pattern: &'static str,
+    kind: PushFailureKind,
+}
+
+const PUSH_ERROR_PATTERNS: &[PushErrorPattern] = &[
+    // Permanent failures first, they win over retriable ones when both appear in the output
+    PushErrorPattern {
+        pattern: "unauthorized",
+        kind: PushFailureKind::Permanent,
+    },
+    PushErrorPattern {
+        pattern: "denied",
+        kind: PushFailureKind::Permanent,
+    },
+    PushErrorPattern {
+        pattern: "no basic auth credentials",
+        kind: PushFailureKind::Permanent,
+    },
+    PushErrorPattern {
+        pattern: "authentication required",
+        kind: PushFailureKind::Permanent,
+    },
+    PushErrorPattern {
+        pattern: "403 forbidden",
+        kind: PushFailureKind::Permanent,
+    },
+    PushErrorPattern {
+        pattern: "quota",
+        kind: PushFailureKind::Permanent,
+    },
+    PushErrorPattern {
+        pattern: "toomanyrequests",
+        kind: PushFailureKind::Permanent,
+    },
+    PushErrorPattern {
+        pattern: "name unknown",
+        kind: PushFailureKind::Permanent,
+    },
+    PushErrorPattern {
+        pattern: "repository does not exist",
+        kind: PushFailureKind::Permanent,
+    },
+    PushErrorPattern {
+        pattern: "manifest invalid",
+        kind: PushFailureKind::Permanent,
+    },
+    // Transient failures
+    PushErrorPattern {
+        pattern: "500 internal server error",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "502 bad gateway",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "503 service unavailable",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "504 gateway timeout",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "received unexpected http status: 5",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "unexpected status: 5",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "blob upload unknown",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "connection reset by peer",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "use of closed network connection",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "i/o timeout",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "tls handshake timeout",
+        kind: PushFailureKind::Retriable,
+    },
+    PushErrorPattern {
+        pattern: "unexpected eof",
+        kind: PushFailureKind::Retriable,
+    },
+];
+
+pub fn classify_push_output_line(line: &str) -> Option<PushFailureKind> {
+    let line = line.to_lowercase();
+    PUSH_ERROR_PATTERNS
+        .iter()
+        .find(|p| line.contains(p.pattern))
+        .map(|p| p.kind)
+}
+
+/// Classifies the whole output of a failed push. A single permanent error makes the push not retriable,
+/// and an output without any known error is considered permanent as we don't know if retrying would help.
+pub fn classify_push_output<'a>(lines: impl IntoIterator<Item = &'a str>) -> PushFailureKind {
+    let mut kind = None;
+    for line in lines {
+        match classify_push_output_line(line) {
+            Some(PushFailureKind::Permanent) => return PushFailureKind::Permanent,
+            Some(PushFailureKind::Retriable) => kind = Some(PushFailureKind::Retriable),
+            None => {}
+        }
+    }
+
+    kind.unwrap_or(PushFailureKind::Permanent)
+}
+
+/// Returns the digest of the pushed manifest, from the `<tag>: digest: sha256:<hash> size: <size>` line
+pub fn pushed_manifest_digest<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    lines.into_iter().find_map(|line| {
+        let (_, digest) = line.split_once("digest: ")?;
+        let digest = digest.split_whitespace().next()?;
+        digest.starts_with("sha256:").then(|| digest.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct PushOutputTestCase<'a> {
+        registry: &'a str,
+        output: &'a [&'a str],
+        expected: PushFailureKind,
+    }
+
+    #[test]
+    fn test_classify_push_output() {
+        let test_cases = vec![
+            PushOutputTestCase {
+                registry: "ECR 502 during layer upload",
+                output: &[
+                    "The push refers to repository [123456789012.dkr.ecr.eu-west-3.amazonaws.com/z3bc1a2f9]",
+                    "5f70bf18a086: Layer already exists",
+                    "a1b2c3d4e5f6: Pushing [==================>          ]  1.2GB/1.9GB",
+                    "received unexpected HTTP status: 502 Bad Gateway",
+                ],
+                expected: PushFailureKind::Retriable,
+            },
+            PushOutputTestCase {
+                registry: "ECR 503",
+                output: &["error parsing HTTP 503 response body: unexpected end of JSON input: \"\"", "503 Service Unavailable"],
+                expected: PushFailureKind::Retriable,
+            },
+            PushOutputTestCase {
+                registry: "ECR expired token",
+                output: &[
+                    "The push refers to repository [123456789012.dkr.ecr.eu-west-3.amazonaws.com/z3bc1a2f9]",
+                    "denied: Your authorization token has expired. Reauthenticate and try again.",
+                ],
+                expected: PushFailureKind::Permanent,
+            },
+            PushOutputTestCase {
+                registry: "ECR missing repository",
+                output: &["name unknown: The repository with name 'z3bc1a2f9' does not exist in the registry with id '123456789012'"],
+                expected: PushFailureKind::Permanent,
+            },
+            PushOutputTestCase {
+                registry: "ECR no login",
+                output: &["no basic auth credentials"],
+                expected: PushFailureKind::Permanent,
+            },
+            PushOutputTestCase {
+                registry: "GAR 503 with closed connection",
+                output: &[
+                    "The push refers to repository [europe-west9-docker.pkg.dev/my-project/qovery/app]",
+                    "Put \"https://europe-west9-docker.pkg.dev/v2/my-project/qovery/app/blobs/uploads/abc\": write tcp 10.0.0.4:51234->142.250.75.234:443: use of closed network connection",
+                    "unexpected status: 503 Service Unavailable",
+                ],
+                expected: PushFailureKind::Retriable,
+            },
+            PushOutputTestCase {
+                registry: "GAR permission",
+                output: &["denied: Permission \"artifactregistry.repositories.uploadArtifacts\" denied on resource \"projects/my-project/locations/europe-west9/repositories/qovery\" (or it may not exist)"],
+                expected: PushFailureKind::Permanent,
+            },
+            PushOutputTestCase {
+                registry: "GAR quota",
+                output: &["failed commit on ref \"layer-sha256:abc\": unexpected status: 429 Too Many Requests: Quota exceeded for quota metric 'Requests per project per region'"],
+                expected: PushFailureKind::Permanent,
+            },
+            PushOutputTestCase {
+                registry: "Scaleway 500",
+                output: &[
+                    "The push refers to repository [rg.fr-par.scw.cloud/qovery-registry/app]",
+                    "blob upload unknown: blob upload unknown to registry",
+                    "500 Internal Server Error",
+                ],
+                expected: PushFailureKind::Retriable,
+            },
+            PushOutputTestCase {
+                registry: "Scaleway 504",
+                output: &["received unexpected HTTP status: 504 Gateway Timeout"],
+                expected: PushFailureKind::Retriable,
+            },
+            PushOutputTestCase {
+                registry: "Scaleway namespace quota",
+                output: &["denied: quota exceeded for namespace qovery-registry"],
+                expected: PushFailureKind::Permanent,
+            },
+            PushOutputTestCase {
+                registry: "Scaleway unauthorized",
+                output: &["unauthorized: authentication required"],
+                expected: PushFailureKind::Permanent,
+            },
+            PushOutputTestCase {
+                registry: "Permanent wins over retriable",
+                output: &["received unexpected HTTP status: 502 Bad Gateway", "unauthorized: authentication required"],
+                expected: PushFailureKind::Permanent,
+            },
+            PushOutputTestCase {
+                registry: "Unknown error",
+                output: &["An image does not exist locally with the tag: rg.fr-par.scw.cloud/qovery-registry/app"],
+                expected: PushFailureKind::Permanent,
+            },
+            PushOutputTestCase {
+                registry: "Empty output",
+                output: &[],
+                expected: PushFailureKind::Permanent,
+            },
+        ];
+
+        for tc in test_cases {
+            assert_eq!(classify_push_output(tc.output.iter().copied()), tc.expected, "{}", tc.registry);
+        }
+    }
+
+    #[test]
+    fn test_classify_push_output_line() {
+        assert_eq!(classify_push_output_line("5f70bf18a086: Layer already exists"), None);
+        assert_eq!(
+            classify_push_output_line("RECEIVED UNEXPECTED HTTP STATUS: 503 SERVICE UNAVAILABLE"),
+            Some(PushFailureKind::Retriable)
+        );
+        assert_eq!(
+            classify_push_output_line("toomanyrequests: Rate exceeded"),
+            Some(PushFailureKind::Permanent)
+        );
+    }
+
+    #[test]
+    fn test_pushed_manifest_digest() {
+        let output = [
+            "The push refers to repository [rg.fr-par.scw.cloud/qovery-registry/app]",
+            "5f70bf18a086: Pushed",
+            "v42.42: digest: sha256:8c2d1e4b0a7f6e5d4c3b2a1908f7e6d5c4b3a291807f6e5d4c3b2a1908f7e6d5 size: 1573",
+        ];
+        assert_eq!(
+            pushed_manifest_digest(output.iter().copied()),
+            Some("sha256:8c2d1e4b0a7f6e5d4c3b2a1908f7e6d5c4b3a291807f6e5d4c3b2a1908f7e6d5".to_string())
+        );
+        assert_eq!(pushed_manifest_digest(output[..2].iter().copied()), None);
+        assert_eq!(pushed_manifest_digest(["digest: md5:abc size: 1"].iter().copied()), None);
+    }
+}
@@ -0,0 +1,399 @@
+// Test only failure injection: commands run by the engine (terraform, helm, docker, kubectl, ...) can be replaced by a
+// scripted output, so tests can provoke real error paths and check the resulting errors, without a cloud account.
+//
+// Scripts are registered against an operation name, made of the binary name followed by some of its arguments,
+// e.g. `terraform apply` or `docker buildx imagetools create`. An operation matches a command when the binary is the
+// same and the other words appear in the command arguments, in the same order.
+//
+// Calls to the object storage and to the container registry are scripted the same way, with an operation made of
+// `object_storage` or `container_registry` followed by the name of the method, e.g. `object_storage put_object`.
+// They return the error registered in place of calling the provider.
+
+use crate::cmd::command::{CommandError, CommandOutputDetails};
+use std::any::Any;
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum ScriptedOutcome {
+    Success,
+    ExitCode(i32),
+    Timeout,
+    Killed,
+    CannotExecute,
+}
+
+/// Output and result of a command, replayed in place of the real one
+#[derive(Clone, Debug)]
+pub struct ScriptedCommand {
+    stdout: Vec<String>,
+    stderr: Vec<String>,
+    outcome: ScriptedOutcome,
+    remaining_calls: Option<usize>,
+}
+
+impl ScriptedCommand {
+    fn new(outcome: ScriptedOutcome) -> Self {
+        ScriptedCommand {
+            stdout: vec![],
+            stderr: vec![],
+            outcome,
+            remaining_calls: None,
+        }
+    }
+
+    /// The command succeeds, useful to get past the commands run before the one that fails
+    pub fn success() -> Self {
+        Self::new(ScriptedOutcome::Success)
+    }
+
+    pub fn exit_code(exit_code: i32) -> Self {
+        Self::new(ScriptedOutcome::ExitCode(exit_code))
+    }
+
+    pub fn timeout() -> Self {
+        Self::new(ScriptedOutcome::Timeout)
+    }
+
+    pub fn killed() -> Self {
+        Self::new(ScriptedOutcome::Killed)
+    }
+
+    /// The binary cannot be started at all
+    pub fn cannot_execute() -> Self {
+        Self::new(ScriptedOutcome::CannotExecute)
+    }
+
+    pub fn with_stdout(mut self, line: &str) -> Self {
+        self.stdout.extend(line.lines().map(str::to_string));
+        self
+    }
+
+    pub fn with_stderr(mut self, line: &str) -> Self {
+        self.stderr.extend(line.lines().map(str::to_string));
+        self
+    }
+
+    /// Only replaces the next `calls` commands, the following ones go to the next script of the operation, or run
+    /// for real. Allows to script transient errors.
+    pub fn times(mut self, calls: usize) -> Self {
+        self.remaining_calls = Some(calls.max(1));
+        self
+    }
+
+    pub(crate) fn replay<STDOUT, STDERR>(
+        &self,
+        stdout_output: &mut STDOUT,
+        stderr_output: &mut STDERR,
+    ) -> Result<(), CommandError>
+    where
+        STDOUT: FnMut(String),
+        STDERR: FnMut(String),
+    {
+        self.stdout.iter().for_each(|line| stdout_output(line.to_string()));
+        self.stderr.iter().for_each(|line| stderr_output(line.to_string()));
+
+        match self.outcome {
+            ScriptedOutcome::Success => Ok(()),
+            // raw wait status, the exit code lives in the second byte
+            ScriptedOutcome::ExitCode(exit_code) => Err(CommandError::ExitStatusError(
+                ExitStatus::from_raw((exit_code & 0xff) << 8),
+                CommandOutputDetails {
+                    stdout: self.stdout.clone(),
+                    stderr: self.stderr.clone(),
+                    dropped_bytes: 0,
+                },
+            )),
+            ScriptedOutcome::Timeout => Err(CommandError::TimeoutError("injected command timeout".to_string())),
+            ScriptedOutcome::Killed => Err(CommandError::Killed("injected command abort".to_string())),
+            ScriptedOutcome::CannotExecute => Err(CommandError::ExecutionError(io::Error::new(
+                io::ErrorKind::NotFound,
+                "injected command execution error",
+            ))),
+        }
+    }
+}
+
+/// Error returned in place of a call to the object storage or to the container registry
+#[derive(Clone)]
+pub struct ScriptedError {
+    error: Arc<dyn Any + Send + Sync>,
+    remaining_calls: Option<usize>,
+}
+
+impl ScriptedError {
+    /// The error must be of the type returned by the trait, i.e: `ObjectStorageError` or `ContainerRegistryError`
+    pub fn new<E: Any + Send + Sync>(error: E) -> Self {
+        ScriptedError {
+            error: Arc::new(error),
+            remaining_calls: None,
+        }
+    }
+
+    /// Only fails the next `calls` calls, the following ones go to the next script of the operation, or to the
+    /// provider. Allows to script transient errors.
+    pub fn times(mut self, calls: usize) -> Self {
+        self.remaining_calls = Some(calls.max(1));
+        self
+    }
+}
+
+#[derive(Clone)]
+enum Script {
+    Command(ScriptedCommand),
+    Error(ScriptedError),
+}
+
+impl Script {
+    fn remaining_calls(&mut self) -> &mut Option<usize> {
+        match self {
+            Script::Command(command) => &mut command.remaining_calls,
+            Script::Error(error) => &mut error.remaining_calls,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    scripts: Vec<(Vec<String>, VecDeque<Script>)>,
+    intercepted: Vec<String>,
+}
+
+static REGISTRY: Mutex<Option<Registry>> = Mutex::new(None);
+// Scenarios touch the whole process, so only one can be active at a time
+static SCENARIO_LOCK: Mutex<()> = Mutex::new(());
+
+fn registry() -> MutexGuard<'static, Option<Registry>> {
+    // a failing test must not prevent the next ones to run
+    REGISTRY.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn operation_matches(operation: &[String], command: &[&str]) -> bool {
+    let Some((binary, words)) = operation.split_first() else {
+        return false;
+    };
+    let Some((command_binary, command_args)) = command.split_first() else {
+        return false;
+    };
+    if binary != command_binary {
+        return false;
+    }
+
+    let mut args = command_args.iter();
+    words.iter().all(|word| args.any(|arg| arg == word))
+}
+
+// next script of the first operation matching the command line, the last one of an operation stays
+fn next_script(registry: &mut Registry, command_line: &[&str]) -> Option<Script> {
+    let scripts = registry
+        .scripts
+        .iter_mut()
+        .find(|(operation, scripts)| !scripts.is_empty() && operation_matches(operation, command_line))
+        .map(|(_, scripts)| scripts)?;
+
+    let script = match scripts.front_mut()?.remaining_calls().as_mut() {
+        Some(remaining_calls) => {
+            *remaining_calls -= 1;
+            if *remaining_calls == 0 {
+                scripts.pop_front()?
+            } else {
+                scripts.front()?.clone()
+            }
+        }
+        None => scripts.front()?.clone(),
+    };
+
+    registry.intercepted.push(command_line.join(" "));
+    Some(script)
+}
+
+/// Returns the script to replay instead of running the command, if any
+pub(crate) fn intercept(command: &Command) -> Option<ScriptedCommand> {
+    let mut guard = registry();
+    let registry = guard.as_mut()?;
+
+    let binary = Path::new(command.get_program())
+        .file_name()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default()
+        .to_string();
+    let args: Vec<String> = command
+        .get_args()
+        .map(|arg| arg.to_str().unwrap_or_default().to_string())
+        .collect();
+    let command_line: Vec<&str> = std::iter::once(binary.as_str())
+        .chain(args.iter().map(String::as_str))
+        .collect();
+
+    match next_script(registry, &command_line)? {
+        Script::Command(script) => Some(script),
+        Script::Error(_) => None,
+    }
+}
+
+/// Returns the error to return instead of calling the provider, if any.
+/// `call` is the component, the method and its main arguments, e.g. `["container_registry", "create_repository", name]`
+pub(crate) fn intercept_call<E: Any + Clone>(call: &[&str]) -> Option<E> {
+    let mut guard = registry();
+    match next_script(guard.as_mut()?, call)? {
+        Script::Error(script) => script.error.downcast_ref::<E>().cloned(),
+        Script::Command(_) => None,
+    }
+}
+
+/// An active failure injection scenario. Scripts are removed when it is dropped.
+pub struct FailureInjection {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl FailureInjection {
+    pub fn start() -> Self {
+        let lock = SCENARIO_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        *registry() = Some(Registry::default());
+
+        FailureInjection { _lock: lock }
+    }
+
+    /// Registers a script for an operation. Operations are looked up in registration order, and scripts of a same
+    /// operation are replayed one after the other.
+    pub fn inject(&self, operation: &str, script: ScriptedCommand) -> &Self {
+        self.register(operation, Script::Command(script))
+    }
+
+    /// Registers an error returned by a call to the object storage or to the container registry
+    pub fn inject_error(&self, operation: &str, script: ScriptedError) -> &Self {
+        self.register(operation, Script::Error(script))
+    }
+
+    fn register(&self, operation: &str, script: Script) -> &Self {
+        let operation: Vec<String> = operation.split_whitespace().map(str::to_string).collect();
+        if let Some(registry) = registry().as_mut() {
+            match registry.scripts.iter_mut().find(|(op, _)| op == &operation) {
+                Some((_, scripts)) => scripts.push_back(script),
+                None => registry.scripts.push((operation, VecDeque::from([script]))),
+            }
+        }
+
+        self
+    }
+
+    /// Command lines and calls that have been replaced by a script, in execution order
+    pub fn intercepted_commands(&self) -> Vec<String> {
+        registry()
+            .as_ref()
+            .map(|registry| registry.intercepted.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for FailureInjection {
+    fn drop(&mut self) {
+        *registry() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::command::{ExecutableCommand, QoveryCommand};
+
+    fn run(binary: &str, args: &[&str]) -> (Vec<String>, Result<(), CommandError>) {
+        let mut output = vec![];
+        let ret =
+            QoveryCommand::new(binary, args, &[]).exec_with_output(&mut |l| output.push(l), &mut |l| output.push(l));
+        (output, ret)
+    }
+
+    #[test]
+    fn test_operation_matches() {
+        let operation = |op: &str| op.split_whitespace().map(str::to_string).collect::<Vec<_>>();
+
+        assert!(operation_matches(&operation("helm"), &["helm", "upgrade", "--install"]));
+        assert!(operation_matches(
+            &operation("helm upgrade"),
+            &["helm", "--kubeconfig", "/tmp/kubeconfig", "upgrade", "--install"]
+        ));
+        assert!(!operation_matches(&operation("helm upgrade"), &["helm", "uninstall"]));
+        assert!(!operation_matches(
+            &operation("helm upgrade --install"),
+            &["helm", "--install", "upgrade"]
+        ));
+        assert!(!operation_matches(&operation("kubectl"), &["helm", "kubectl"]));
+    }
+
+    #[test]
+    fn test_scripted_command_replay() {
+        let scenario = FailureInjection::start();
+        scenario
+            .inject(
+                "binary-that-does-not-exist apply",
+                ScriptedCommand::exit_code(1)
+                    .with_stdout("planning")
+                    .with_stderr("Error: boom"),
+            )
+            .inject("binary-that-does-not-exist plan", ScriptedCommand::success().times(2))
+            .inject("binary-that-does-not-exist plan", ScriptedCommand::timeout());
+
+        let (output, ret) = run("binary-that-does-not-exist", &["apply", "-auto-approve"]);
+        assert_eq!(output, vec!["planning", "Error: boom"]);
+        assert!(matches!(ret, Err(CommandError::ExitStatusError(status, _)) if status.code() == Some(1)));
+
+        assert!(run("binary-that-does-not-exist", &["plan"]).1.is_ok());
+        assert!(run("binary-that-does-not-exist", &["plan"]).1.is_ok());
+        assert!(matches!(
+            run("binary-that-does-not-exist", &["plan"]).1,
+            Err(CommandError::TimeoutError(_))
+        ));
+        // last script of an operation stays
+        assert!(matches!(
+            run("binary-that-does-not-exist", &["plan"]).1,
+            Err(CommandError::TimeoutError(_))
+        ));
+
+        // not scripted, really executed
+        assert!(matches!(
+            run("binary-that-does-not-exist", &["init"]).1,
+            Err(CommandError::ExecutionError(_))
+        ));
+        assert_eq!(scenario.intercepted_commands().len(), 5);
+
+        drop(scenario);
+        assert!(intercept(&Command::new("binary-that-does-not-exist")).is_none());
+    }
+
+    #[test]
+    fn test_scripted_error_replay() {
+        let scenario = FailureInjection::start();
+        scenario
+            .inject_error("object_storage put_object", ScriptedError::new("503".to_string()).times(2))
+            .inject_error("object_storage put_object", ScriptedError::new("403".to_string()));
+
+        assert_eq!(
+            intercept_call::<String>(&["object_storage", "put_object"]).as_deref(),
+            Some("503")
+        );
+        assert_eq!(
+            intercept_call::<String>(&["object_storage", "put_object"]).as_deref(),
+            Some("503")
+        );
+        assert_eq!(
+            intercept_call::<String>(&["object_storage", "put_object"]).as_deref(),
+            Some("403")
+        );
+        // not scripted, the provider is called
+        assert_eq!(intercept_call::<String>(&["object_storage", "get_object"]), None);
+        // an error of another type is never returned
+        assert_eq!(intercept_call::<u32>(&["object_storage", "put_object"]), None);
+        // commands are never replaced by an error
+        assert!(intercept(Command::new("object_storage").arg("put_object")).is_none());
+        assert_eq!(scenario.intercepted_commands().len(), 5);
+
+        drop(scenario);
+        assert_eq!(intercept_call::<String>(&["object_storage", "put_object"]), None);
+    }
+}
@@ -158,9 +158,11 @@ where
 
     match ret {
         Ok(_) => Ok(()),
-        Err(CommandError::TimeoutError(msg)) => Err(GitLfsError::Timeout { raw_error_message: msg }),
+        Err(CommandError::TimeoutError(msg) | CommandError::IdleTimeoutError(msg)) => {
+            Err(GitLfsError::Timeout { raw_error_message: msg })
+        }
         Err(CommandError::Killed(msg)) => Err(GitLfsError::Aborted { raw_error_message: msg }),
-        Err(CommandError::ExitStatusError(err)) => Err(GitLfsError::ExitStatusError { exit_status: err }),
+        Err(CommandError::ExitStatusError(err, _)) => Err(GitLfsError::ExitStatusError { exit_status: err }),
         Err(CommandError::ExecutionError(err)) => Err(GitLfsError::ExecutionError { raw_error: err }),
     }
 }
@@ -169,6 +171,7 @@ where
 mod tests {
     use super::*;
     use crate::cmd::git;
+    use crate::cmd::git::GitCloneOptions;
     use uuid::Uuid;
 
     struct DirectoryForTests {
@@ -206,6 +209,7 @@ mod tests {
             VALID_COMMIT,
             &repo_path,
             &|_| Vec::new(),
+            &GitCloneOptions::default(),
         )
         .unwrap();
         let cmd = GitLfs::default();
@@ -4,28 +4,33 @@ use std::path::{Path, PathBuf};
 
 use tracing::{error, info};
 
-use crate::cmd::command::{CommandError, CommandKiller, ExecutableCommand, QoveryCommand};
+use crate::cmd::command::{CommandError, CommandKiller, ExecutableCommand, ExecutionContext, QoveryCommand};
 use crate::cmd::helm::HelmCommand::{DEPENDENCY, FETCH, LIST, LOGIN, PULL, REPO, ROLLBACK, STATUS, UNINSTALL, UPGRADE};
 use crate::cmd::helm::HelmError::{
     CannotRollback, CmdError, InvalidKubeConfig, InvalidRepositoryConfig, ReleaseDoesNotExist, ReleaseNameInvalid,
 };
+use crate::cmd::helm_manifest;
+use crate::cmd::helm_post_renderer;
 use crate::cmd::helm_utils::{ChartDependencyYAML, ChartYAML};
 use crate::cmd::structs::{HelmChart, HelmChartVersions, HelmListItem};
 use crate::errors;
 use crate::errors::EngineError;
 use crate::events::EventDetails;
-use crate::helm::ChartInfo;
+use crate::helm::{ChartInfo, ChartSource};
 use crate::io_models::container::Registry;
 use semver::Version;
 use serde_derive::Deserialize;
 use std::fs::File;
 use std::str::FromStr;
+use std::time::Duration;
 use tempfile::TempDir;
 use url::Url;
 use uuid::Uuid;
 
 const HELM_DEFAULT_TIMEOUT_IN_SECONDS: u32 = 600;
 const HELM_MAX_HISTORY: &str = "50";
+// helm prints nothing while waiting for the resources of a release, this is on top of its --timeout
+const HELM_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
 pub enum Timeout<T> {
     Default,
@@ -75,6 +80,9 @@ pub enum HelmError {
 
     #[error("Cannot get credentials error.")]
     CannotGetCredentials(String),
+
+    #[error("Cannot authenticate to the Helm chart registry `{0}`: {1:?}")]
+    RegistryAuthenticationFailed(String, errors::CommandError),
 }
 
 #[derive(Debug, Clone)]
@@ -91,6 +99,7 @@ pub enum HelmCommand {
     LIST,
     DIFF,
     TEMPLATE,
+    GET,
     FETCH,
     PULL,
     LOGIN,
@@ -149,6 +158,26 @@ impl Helm {
         Ok(Helm { common_envs })
     }
 
+    /// Cluster wide mutations are applied to the rendered manifests by the engine itself, see [`helm_post_renderer`]
+    fn post_renderer_args(
+        &self,
+        chart: &ChartInfo,
+        envs: &[(&str, &str)],
+        command: HelmCommand,
+    ) -> Result<Vec<String>, HelmError> {
+        helm_post_renderer::helm_args(&self.get_all_envs(envs)).map_err(|e| {
+            CmdError(
+                chart.name.clone(),
+                command,
+                errors::CommandError::new(
+                    "Cannot find the engine binary to use it as helm post-renderer".to_string(),
+                    Some(e.to_string()),
+                    None,
+                ),
+            )
+        })
+    }
+
     pub fn check_release_exist(&self, chart: &ChartInfo, envs: &[(&str, &str)]) -> Result<ReleaseStatus, HelmError> {
         let namespace = chart.get_namespace_string();
         let args = vec!["status", &chart.name, "--namespace", &namespace, "-o", "json"];
@@ -254,6 +283,14 @@ impl Helm {
             stderr_output(line)
         };
         match helm_exec_with_output(&args, &self.get_all_envs(envs), stdout_output, &mut stderr_output, cmd_killer) {
+            Err(CommandError::Killed(_)) => Err(HelmError::Killed(chart.name.clone(), UNINSTALL)),
+            Err(CommandError::TimeoutError(msg) | CommandError::IdleTimeoutError(msg)) => {
+                Err(HelmError::Timeout(chart.name.clone(), UNINSTALL, msg))
+            }
+            // helm --wait gave up before the resources of the release were gone
+            Err(err) if stderr.contains("timed out waiting") || stderr.contains("deadline exceeded") => {
+                Err(HelmError::Timeout(chart.name.clone(), UNINSTALL, format!("{stderr}: {err}")))
+            }
             Err(err) => {
                 stderr.push_str(&err.to_string());
                 Err(CmdError(chart.name.clone(), UNINSTALL, err.into()))
@@ -549,7 +586,9 @@ impl Helm {
 
             // If the helm command has been canceled by the user, propagate correctly the killed error
             return match err {
-                CommandError::TimeoutError(_) => Err(HelmError::Timeout(chart_name.to_string(), PULL, stderr_msg)),
+                CommandError::TimeoutError(_) | CommandError::IdleTimeoutError(_) => {
+                    Err(HelmError::Timeout(chart_name.to_string(), PULL, stderr_msg))
+                }
                 CommandError::Killed(_) => Err(HelmError::Killed(chart_name.to_string(), PULL)),
                 _ => Err(CmdError(
                     chart_name.to_string(),
@@ -666,7 +705,9 @@ impl Helm {
 
             // If the helm command has been canceled by the user, propagate correctly the killed error
             return match err {
-                CommandError::TimeoutError(_) => Err(HelmError::Timeout(chart_name.to_string(), FETCH, stderr_msg)),
+                CommandError::TimeoutError(_) | CommandError::IdleTimeoutError(_) => {
+                    Err(HelmError::Timeout(chart_name.to_string(), FETCH, stderr_msg))
+                }
                 CommandError::Killed(_) => Err(HelmError::Killed(chart_name.to_string(), FETCH)),
                 _ => Err(CmdError(
                     chart_name.to_string(),
@@ -822,20 +863,24 @@ impl Helm {
             args_string.push(file_path);
         }
 
+        args_string.extend(self.post_renderer_args(chart, envs, HelmCommand::DIFF)?);
+
         // add last elements
-        args_string.push(chart.name.clone());
-        args_string.push(chart.path.clone());
+        let all_envs = self.get_all_envs(envs);
+        let cmd_killer = CommandKiller::never();
+        let oci_registry = OciChartRegistry::new(chart, &all_envs, &cmd_killer, HelmCommand::DIFF)?;
+        args_string.extend(chart_reference_args(chart, oci_registry.as_ref()));
 
         let mut stderr_msg = String::new();
         let helm_ret = helm_exec_with_output(
             &args_string.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
-            &self.get_all_envs(envs),
+            &all_envs,
             stdout_output,
             &mut |line| {
                 stderr_msg.push_str(&line);
                 warn!("chart {}: {}", chart.name, line);
             },
-            &CommandKiller::never(),
+            &cmd_killer,
         );
 
         match helm_ret {
@@ -848,6 +893,133 @@ impl Helm {
         }
     }
 
+    /// Diff, object by object, between the deployed release and the chart about to be upgraded, see
+    /// [`helm_manifest::manifests_diff`]. Every object is added when the release is not installed yet.
+    pub fn manifest_diff(
+        &self,
+        chart: &ChartInfo,
+        envs: &[(&str, &str)],
+        max_lines: usize,
+    ) -> Result<Vec<String>, HelmError> {
+        let deployed_manifest = match self.check_release_exist(chart, envs) {
+            Ok(_) => self.get_manifest(chart, envs)?,
+            Err(ReleaseDoesNotExist(_)) => String::new(),
+            Err(err) => return Err(err),
+        };
+        let target_manifest = self.template_manifest(chart, envs)?;
+
+        Ok(helm_manifest::manifests_diff(&deployed_manifest, &target_manifest, max_lines))
+    }
+
+    /// Manifest of the deployed release, without its hooks
+    pub fn get_manifest(&self, chart: &ChartInfo, envs: &[(&str, &str)]) -> Result<String, HelmError> {
+        let namespace = chart.get_namespace_string();
+        let args = vec!["get", "manifest", &chart.name, "--namespace", &namespace];
+
+        let mut manifest = String::new();
+        let helm_ret = helm_exec_with_output(
+            &args,
+            &self.get_all_envs(envs),
+            &mut |line| {
+                manifest.push_str(&line);
+                manifest.push('\n');
+            },
+            &mut |line| warn!("chart {}: {}", chart.name, line),
+            &CommandKiller::never(),
+        );
+
+        match helm_ret {
+            Ok(_) => Ok(manifest),
+            Err(err) => Err(CmdError(chart.name.clone(), HelmCommand::GET, err.into())),
+        }
+    }
+
+    /// Manifest the upgrade would apply, rendered like [`Helm::upgrade`] does but without its hooks, as
+    /// `helm get manifest` does not show them
+    fn template_manifest(&self, chart: &ChartInfo, envs: &[(&str, &str)]) -> Result<String, HelmError> {
+        let mut args_string: Vec<String> = vec![
+            "template".to_string(),
+            "--is-upgrade".to_string(),
+            "--no-hooks".to_string(),
+            "--namespace".to_string(),
+            chart.get_namespace_string(),
+        ];
+        args_string.extend(self.values_args(chart, envs, HelmCommand::TEMPLATE)?);
+        let all_envs = self.get_all_envs(envs);
+        let cmd_killer = CommandKiller::never();
+        let oci_registry = OciChartRegistry::new(chart, &all_envs, &cmd_killer, HelmCommand::TEMPLATE)?;
+        args_string.extend(chart_reference_args(chart, oci_registry.as_ref()));
+
+        let mut manifest = String::new();
+        let helm_ret = helm_exec_with_output(
+            &args_string.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
+            &all_envs,
+            &mut |line| {
+                manifest.push_str(&line);
+                manifest.push('\n');
+            },
+            &mut |line| warn!("chart {}: {}", chart.name, line),
+            &cmd_killer,
+        );
+
+        match helm_ret {
+            Ok(_) => Ok(manifest),
+            Err(err) => Err(CmdError(chart.name.clone(), HelmCommand::TEMPLATE, err.into())),
+        }
+    }
+
+    /// Overrides, values files and post-renderer arguments shared by the commands rendering the chart
+    fn values_args(
+        &self,
+        chart: &ChartInfo,
+        envs: &[(&str, &str)],
+        command: HelmCommand,
+    ) -> Result<Vec<String>, HelmError> {
+        let mut args: Vec<String> = vec![];
+        for value in &chart.values {
+            args.push("--set".to_string());
+            args.push(format!("{}={}", value.key, value.value));
+        }
+        for value in &chart.values_string {
+            args.push("--set-string".to_string());
+            args.push(format!("{}={}", value.key, value.value));
+        }
+
+        for value_file in &chart.values_files {
+            args.push("-f".to_string());
+            args.push(value_file.clone());
+        }
+        for value_file in &chart.yaml_files_content {
+            let file_path = format!("{}/{}", chart.path, &value_file.filename);
+            let file_create = || -> Result<(), Error> {
+                let mut file = File::create(&file_path)?;
+                file.write_all(value_file.yaml_content.as_bytes())?;
+                Ok(())
+            };
+
+            // no need to validate yaml as it will be done by helm
+            if let Err(e) = file_create() {
+                let cmd_err = errors::CommandError::new(
+                    format!("Error while writing yaml content to file `{}`", &file_path),
+                    Some(format!("Content\n{}\nError: {}", value_file.yaml_content, e)),
+                    Some(
+                        envs.iter()
+                            .map(|(k, v)| (k.to_string(), v.to_string()))
+                            .collect::<Vec<(String, String)>>(),
+                    ),
+                );
+                return Err(CmdError(chart.name.clone(), command, cmd_err));
+            };
+
+            args.push("-f".to_string());
+            args.push(file_path);
+        }
+
+        args.extend(self.post_renderer_args(chart, envs, command)?);
+
+        Ok(args)
+    }
+
     pub fn upgrade(
         &self,
         chart: &ChartInfo,
@@ -893,56 +1065,24 @@ impl Helm {
         if chart.wait {
             args_string.push("--wait".to_string())
         }
-
-        // overrides and files overrides
-        for value in &chart.values {
-            args_string.push("--set".to_string());
-            args_string.push(format!("{}={}", value.key, value.value));
-        }
-        for value in &chart.values_string {
-            args_string.push("--set-string".to_string());
-            args_string.push(format!("{}={}", value.key, value.value));
-        }
-
-        for value_file in &chart.values_files {
-            args_string.push("-f".to_string());
-            args_string.push(value_file.clone());
+        // the execution which deployed a revision can be found with `helm history`
+        if let Some(execution_context) = ExecutionContext::current() {
+            args_string.push("--description".to_string());
+            args_string.push(format!("qovery.com/execution-id={}", execution_context.execution_id()));
         }
-        for value_file in &chart.yaml_files_content {
-            let file_path = format!("{}/{}", chart.path, &value_file.filename);
-            let file_create = || -> Result<(), Error> {
-                let mut file = File::create(&file_path)?;
-                file.write_all(value_file.yaml_content.as_bytes())?;
-                Ok(())
-            };
 
-            // no need to validate yaml as it will be done by helm
-            if let Err(e) = file_create() {
-                let cmd_err = errors::CommandError::new(
-                    format!("Error while writing yaml content to file `{}`", &file_path),
-                    Some(format!("Content\n{}\nError: {}", value_file.yaml_content, e)),
-                    Some(
-                        envs.iter()
-                            .map(|(k, v)| (k.to_string(), v.to_string()))
-                            .collect::<Vec<(String, String)>>(),
-                    ),
-                );
-                return Err(CmdError(chart.name.clone(), UPGRADE, cmd_err));
-            };
-
-            args_string.push("-f".to_string());
-            args_string.push(file_path);
-        }
+        args_string.extend(self.values_args(chart, envs, UPGRADE)?);
 
         // add last elements
-        args_string.push(chart.name.clone());
-        args_string.push(chart.path.clone());
+        let all_envs = self.get_all_envs(envs);
+        let oci_registry = OciChartRegistry::new(chart, &all_envs, cmd_killer, UPGRADE)?;
+        args_string.extend(chart_reference_args(chart, oci_registry.as_ref()));
 
         let mut error_message: Vec<String> = vec![];
 
         let helm_ret = helm_exec_with_output(
             &args_string.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
-            &self.get_all_envs(envs),
+            &all_envs,
             &mut |_line| {
                 // Helm with --debug dump all the content of the charts which flood the logs
                 // We are only interested in stderr where there is the debug messages
@@ -968,7 +1108,7 @@ impl Helm {
 
             // If the helm command has been canceled by the user, propagate correctly the killed error
             match err {
-                CommandError::TimeoutError(_) => {
+                CommandError::TimeoutError(_) | CommandError::IdleTimeoutError(_) => {
                     return Err(HelmError::Timeout(chart.name.clone(), UPGRADE, stderr_msg));
                 }
                 CommandError::Killed(_) => {
@@ -977,7 +1117,18 @@ impl Helm {
                 _ => {}
             }
 
-            let error = if stderr_msg.contains("another operation (install/upgrade/rollback) is in progress") {
+            let error = if let (ChartSource::OciRepository { url, .. }, true) =
+                (&chart.source, is_registry_authentication_error(&stderr_msg))
+            {
+                HelmError::RegistryAuthenticationFailed(
+                    url.to_string(),
+                    errors::CommandError::new(
+                        format!("Helm cannot pull chart {} from its registry", chart.name),
+                        Some(stderr_msg),
+                        Some(envs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()),
+                    ),
+                )
+            } else if stderr_msg.contains("another operation (install/upgrade/rollback) is in progress") {
                 HelmError::ReleaseLocked(chart.name.clone())
             } else if stderr_msg.contains("has been rolled back") {
                 HelmError::Rollbacked(chart.name.clone(), UPGRADE)
@@ -1097,7 +1248,7 @@ impl Helm {
             // Ok is ok
             Ok(_) => Ok(()),
             Err(err) => match err {
-                CommandError::TimeoutError(_) => {
+                CommandError::TimeoutError(_) | CommandError::IdleTimeoutError(_) => {
                     Err(HelmError::Timeout(release_name.to_string(), DEPENDENCY, stderr_msg))
                 }
                 CommandError::Killed(_) => Err(HelmError::Killed(release_name.to_string(), DEPENDENCY)),
@@ -1168,7 +1319,9 @@ impl Helm {
                 }
             }
             Err(err) => match err {
-                CommandError::TimeoutError(_) => Err(HelmError::Timeout("".to_string(), HelmCommand::SHOW, stderr_msg)),
+                CommandError::TimeoutError(_) | CommandError::IdleTimeoutError(_) => {
+                    Err(HelmError::Timeout("".to_string(), HelmCommand::SHOW, stderr_msg))
+                }
                 CommandError::Killed(_) => Err(HelmError::Killed("".to_string(), DEPENDENCY)),
                 _ => {
                     error!("Helm error: {:?}", err);
@@ -1234,7 +1387,9 @@ impl Helm {
             // Ok is ok
             Ok(_) => Ok(()),
             Err(err) => match err {
-                CommandError::TimeoutError(_) => Err(HelmError::Timeout(chart_url.to_string(), REPO, stderr_msg)),
+                CommandError::TimeoutError(_) | CommandError::IdleTimeoutError(_) => {
+                    Err(HelmError::Timeout(chart_url.to_string(), REPO, stderr_msg))
+                }
                 CommandError::Killed(_) => Err(HelmError::Killed(chart_url.to_string(), REPO)),
                 _ => {
                     error!("Helm error: {:?}", err);
@@ -1285,7 +1440,9 @@ impl Helm {
             // Ok is ok
             Ok(_) => Ok(stdout),
             Err(err) => match err {
-                CommandError::TimeoutError(_) => Err(HelmError::Timeout(release_name.to_string(), UPGRADE, stderr_msg)),
+                CommandError::TimeoutError(_) | CommandError::IdleTimeoutError(_) => {
+                    Err(HelmError::Timeout(release_name.to_string(), UPGRADE, stderr_msg))
+                }
                 CommandError::Killed(_) => Err(HelmError::Killed(release_name.to_string(), UPGRADE)),
                 _ => {
                     error!("Helm error: {:?}", err);
@@ -1353,7 +1510,7 @@ impl Helm {
 
             // If the helm command has been canceled by the user, propagate correctly the killed error
             match err {
-                CommandError::TimeoutError(_) => {
+                CommandError::TimeoutError(_) | CommandError::IdleTimeoutError(_) => {
                     return Err(HelmError::Timeout(chart.name.clone(), UPGRADE, err.to_string()));
                 }
                 CommandError::Killed(_) => {
@@ -1487,6 +1644,11 @@ impl Helm {
             args_string.push(format!("{}={}", value.key, value.value));
         }
 
+        for value_file in &chart.values_files {
+            args_string.push("-f".to_string());
+            args_string.push(value_file.clone());
+        }
+
         let mut stderr_msg = String::new();
         let helm_ret = helm_exec_with_output(
             &args_string.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
@@ -1530,21 +1692,155 @@ where
     // Helm returns an error each time a command does not succeed as they want. Which leads to handling error with status code 1
     // It means that the command successfully ran, but it didn't terminate as expected
     let mut cmd = QoveryCommand::new("helm", args, envs);
+    if let Some(idle_timeout) = helm_idle_timeout(args) {
+        cmd.set_idle_timeout(idle_timeout);
+    }
     match cmd.exec_with_abort(stdout_output, stderr_output, cmd_killer) {
         Err(err) => Err(err),
         _ => Ok(()),
     }
 }
 
+fn helm_exec_with_input<STDOUT, STDERR>(
+    args: &[&str],
+    envs: &[(&str, &str)],
+    stdin: String,
+    stdout_output: &mut STDOUT,
+    stderr_output: &mut STDERR,
+    cmd_killer: &CommandKiller,
+) -> Result<(), CommandError>
+where
+    STDOUT: FnMut(String),
+    STDERR: FnMut(String),
+{
+    let mut cmd = QoveryCommand::new("helm", args, envs);
+    if let Some(idle_timeout) = helm_idle_timeout(args) {
+        cmd.set_idle_timeout(idle_timeout);
+    }
+    cmd.set_stdin(stdin);
+    cmd.exec_with_abort(stdout_output, stderr_output, cmd_killer)
+}
+
+// helm can stay silent for the whole --timeout of the command, unknown timeout formats disable the idle timeout
+fn helm_idle_timeout(args: &[&str]) -> Option<Duration> {
+    let Some(index) = args.iter().position(|arg| *arg == "--timeout") else {
+        return Some(HELM_IDLE_TIMEOUT);
+    };
+
+    args.get(index + 1)
+        .and_then(|timeout| timeout.strip_suffix('s'))
+        .and_then(|seconds| seconds.parse::<u64>().ok())
+        .map(|seconds| HELM_IDLE_TIMEOUT + Duration::from_secs(seconds))
+}
+
+// registries answer with those when the credentials are missing or wrong
+const REGISTRY_AUTHENTICATION_ERRORS: [&str; 4] = ["401 Unauthorized", "unauthorized:", "403 Forbidden", "denied:"];
+
+fn is_registry_authentication_error(stderr_msg: &str) -> bool {
+    REGISTRY_AUTHENTICATION_ERRORS
+        .iter()
+        .any(|error| stderr_msg.contains(error))
+}
+
+/// Arguments designating the chart to install, at the end of the helm command line
+fn chart_reference_args(chart: &ChartInfo, oci_registry: Option<&OciChartRegistry>) -> Vec<String> {
+    let (ChartSource::OciRepository { url, version, .. }, Some(oci_registry)) = (&chart.source, oci_registry) else {
+        return vec![chart.name.clone(), chart.path.clone()];
+    };
+
+    let (registry_config_path, repository_config_path, repository_cache_path) =
+        Helm::get_helm_cmd_paths(oci_registry.registry_dir.path());
+    let mut args = vec![
+        "--registry-config".to_string(),
+        registry_config_path,
+        "--repository-config".to_string(),
+        repository_config_path,
+        "--repository-cache".to_string(),
+        repository_cache_path,
+    ];
+    if let Some(version) = version {
+        args.push("--version".to_string());
+        args.push(version.clone());
+    }
+    args.push(chart.name.clone());
+    args.push(url.to_string());
+
+    args
+}
+
+/// Helm config of a chart hosted on an OCI registry, logged out and removed once dropped
+struct OciChartRegistry<'a> {
+    // declared first, so the logout happens before the removal of the config
+    _registry: Option<HelmRegistry<'a>>,
+    registry_dir: TempDir,
+}
+
+impl<'a> OciChartRegistry<'a> {
+    fn new(
+        chart: &ChartInfo,
+        envs: &'a [(&'a str, &'a str)],
+        cmd_killer: &'a CommandKiller,
+        helm_command: HelmCommand,
+    ) -> Result<Option<OciChartRegistry<'a>>, HelmError> {
+        let ChartSource::OciRepository { url, credentials, .. } = &chart.source else {
+            return Ok(None);
+        };
+
+        let registry_dir = tempfile::tempdir().map_err(|err| {
+            CmdError(
+                chart.name.clone(),
+                helm_command,
+                errors::CommandError::new(
+                    "Cannot create a temporary directory for the chart registry config".to_string(),
+                    Some(err.to_string()),
+                    Some(vec![]),
+                ),
+            )
+        })?;
+
+        let Some(credentials) = credentials else {
+            return Ok(Some(OciChartRegistry {
+                _registry: None,
+                registry_dir,
+            }));
+        };
+        let registry_url = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => {
+                return Err(InvalidRepositoryConfig(format!(
+                    "Cannot get the registry host of chart {} from {}",
+                    chart.name, url
+                )))
+            }
+        };
+
+        let mut registry = HelmRegistry::new(
+            &registry_url,
+            &credentials.username,
+            &credentials.password,
+            registry_dir.path(),
+            envs,
+            cmd_killer,
+        );
+        registry.login(false)?;
+
+        Ok(Some(OciChartRegistry {
+            _registry: Some(registry),
+            registry_dir,
+        }))
+    }
+}
+
 pub fn to_engine_error(event_details: &EventDetails, error: HelmError) -> EngineError {
     EngineError::new_helm_error(event_details.clone(), error)
 }
 
 struct HelmRegistry<'a> {
-    registry_url: &'a str,
-    username: &'a str,
-    password: &'a str,
-    repository_cache_path: &'a Path,
+    registry_url: String,
+    username: String,
+    password: String,
+    repository_cache_path: PathBuf,
     envs: &'a [(&'a str, &'a str)],
     cmd_killer: &'a CommandKiller<'a>,
     login: bool,
@@ -1552,52 +1848,59 @@ struct HelmRegistry<'a> {
 
 impl<'a> HelmRegistry<'a> {
     fn new(
-        registry_url: &'a str,
-        username: &'a str,
-        password: &'a str,
-        repository_cache_path: &'a Path,
+        registry_url: &str,
+        username: &str,
+        password: &str,
+        repository_cache_path: &Path,
         envs: &'a [(&'a str, &'a str)],
         cmd_killer: &'a CommandKiller,
     ) -> Self {
         HelmRegistry {
-            registry_url,
-            username,
-            password,
-            repository_cache_path,
+            registry_url: registry_url.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            repository_cache_path: repository_cache_path.to_path_buf(),
             envs,
             cmd_killer,
             login: false,
         }
     }
 
-    fn login(&mut self, skip_tls_verification: bool) -> Result<(), HelmError> {
+    // the password is given through stdin, so it never shows up in the command line
+    fn login_args(&self, skip_tls_verification: bool) -> Vec<String> {
         let (registry_config_path, repository_config_path, repository_cache_path) =
-            Helm::get_helm_cmd_paths(self.repository_cache_path);
+            Helm::get_helm_cmd_paths(&self.repository_cache_path);
         let mut helm_login_args = vec![
-            "registry",
-            "--debug", // there is no debug log but if someday they appear
-            "login",
-            self.registry_url,
-            "--username",
-            self.username,
-            "--password",
-            self.password,
-            "--registry-config",
-            &registry_config_path,
-            "--repository-config",
-            &repository_config_path,
-            "--repository-cache",
-            &repository_cache_path,
+            "registry".to_string(),
+            "--debug".to_string(), // there is no debug log but if someday they appear
+            "login".to_string(),
+            self.registry_url.clone(),
+            "--username".to_string(),
+            self.username.clone(),
+            "--password-stdin".to_string(),
+            "--registry-config".to_string(),
+            registry_config_path,
+            "--repository-config".to_string(),
+            repository_config_path,
+            "--repository-cache".to_string(),
+            repository_cache_path,
         ];
 
         if skip_tls_verification {
-            helm_login_args.push("--insecure");
+            helm_login_args.push("--insecure".to_string());
         }
 
+        helm_login_args
+    }
+
+    fn login(&mut self, skip_tls_verification: bool) -> Result<(), HelmError> {
+        let helm_login_args = self.login_args(skip_tls_verification);
+
         let mut error_message: Vec<String> = Vec::new();
-        let helm_ret = helm_exec_with_output(
-            helm_login_args.as_slice(),
+        let helm_ret = helm_exec_with_input(
+            &helm_login_args.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
             self.envs,
+            self.password.clone(),
             &mut |line| {
                 info!("{}", line);
             },
@@ -1621,13 +1924,12 @@ impl<'a> HelmRegistry<'a> {
 
             // If the helm command has been canceled by the user, propagate correctly the killed error
             return match err {
-                CommandError::TimeoutError(_) => {
+                CommandError::TimeoutError(_) | CommandError::IdleTimeoutError(_) => {
                     Err(HelmError::Timeout(self.registry_url.to_string(), LOGIN, stderr_msg))
                 }
                 CommandError::Killed(_) => Err(HelmError::Killed(self.registry_url.to_string(), LOGIN)),
-                _ => Err(CmdError(
+                _ => Err(HelmError::RegistryAuthenticationFailed(
                     self.registry_url.to_string(),
-                    LOGIN,
                     errors::CommandError::new(
                         format!(
                             "Helm failed to login repository {} for username {}",
@@ -1652,12 +1954,12 @@ impl Drop for HelmRegistry<'_> {
         }
 
         let (registry_config_path, repository_config_path, repository_cache_path) =
-            Helm::get_helm_cmd_paths(self.repository_cache_path);
+            Helm::get_helm_cmd_paths(&self.repository_cache_path);
         let helm_logout_args = vec![
             "registry",
             "--debug", // there is no debug log but if someday they appear
             "logout",
-            self.registry_url,
+            &self.registry_url,
             "--registry-config",
             &registry_config_path,
             "--repository-config",
@@ -1693,7 +1995,7 @@ impl Drop for HelmRegistry<'_> {
 #[cfg(test)]
 mod tests {
     use crate::cmd::command::{CommandKiller, ExecutableCommand, QoveryCommand};
-    use crate::cmd::helm::{helm_exec_with_output, Helm, HelmError};
+    use crate::cmd::helm::{helm_exec_with_output, helm_idle_timeout, Helm, HelmError, HELM_IDLE_TIMEOUT};
     use crate::environment::action::deploy_helm::default_helm_timeout;
     use crate::helm::{ChartInfo, ChartSetValue};
     use crate::io_models::container::Registry::GenericCr;
@@ -1845,6 +2147,25 @@ mod tests {
         assert!(matches!(ret, Ok(())));
     }
 
+    #[test]
+    fn test_manifest_diff() {
+        let HelmTestCtx { ref helm, ref charts } = HelmTestCtx::new("test-manifest-diff");
+
+        // not installed yet, every object is added
+        let diff = helm.manifest_diff(&charts[0], &[], 0).unwrap();
+        assert!(diff.iter().any(|line| line == "--- /dev/null"));
+        assert!(diff
+            .iter()
+            .all(|line| !line.starts_with('-') || line == "--- /dev/null"));
+
+        let ret = helm.upgrade(&charts[0], &[], &CommandKiller::never());
+        assert!(matches!(ret, Ok(())));
+
+        // nothing changed since the upgrade
+        let diff = helm.manifest_diff(&charts[0], &[], 0).unwrap();
+        assert!(diff.is_empty());
+    }
+
     #[test]
     fn test_rollback() {
         let HelmTestCtx { ref helm, ref charts } = HelmTestCtx::new("test-rollback");
@@ -2174,4 +2495,112 @@ mod tests {
         // Check that the files are there
         assert!(target_dir.path().join("values.yaml").exists());
     }
+
+    #[test]
+    fn test_helm_idle_timeout() {
+        assert_eq!(helm_idle_timeout(&["list", "-A"]), Some(HELM_IDLE_TIMEOUT));
+        assert_eq!(
+            helm_idle_timeout(&["upgrade", "--install", "--timeout", "1200s", "--wait"]),
+            Some(HELM_IDLE_TIMEOUT + Duration::from_secs(1200))
+        );
+        assert_eq!(helm_idle_timeout(&["upgrade", "--timeout", "20m"]), None);
+    }
+}
+
+#[cfg(test)]
+mod oci_tests {
+    use crate::cmd::command::CommandKiller;
+    use crate::cmd::helm::{chart_reference_args, HelmRegistry, OciChartRegistry};
+    use crate::helm::{ChartInfo, ChartRepositoryCredentials, ChartSource};
+    use url::Url;
+
+    #[test]
+    fn test_oci_chart_args() {
+        // setup:
+        let chart = ChartInfo {
+            name: "my-chart".to_string(),
+            path: "/tmp/values".to_string(),
+            source: ChartSource::OciRepository {
+                url: Url::parse("oci://ghcr.io/qovery/charts/my-chart").unwrap(),
+                version: Some("1.2.3".to_string()),
+                credentials: Some(ChartRepositoryCredentials {
+                    username: "qovery".to_string(),
+                    password: "my-secret-token".to_string(),
+                }),
+            },
+            ..Default::default()
+        };
+        let oci_registry = OciChartRegistry {
+            _registry: None,
+            registry_dir: tempfile::tempdir().unwrap(),
+        };
+        let registry_dir = oci_registry.registry_dir.path().display().to_string();
+        let cmd_killer = CommandKiller::never();
+        let registry = HelmRegistry::new(
+            "ghcr.io",
+            "qovery",
+            "my-secret-token",
+            oci_registry.registry_dir.path(),
+            &[],
+            &cmd_killer,
+        );
+
+        // execute:
+        let chart_args = chart_reference_args(&chart, Some(&oci_registry));
+        let login_args = registry.login_args(false);
+
+        // verify:
+        assert_eq!(
+            chart_args,
+            vec![
+                "--registry-config".to_string(),
+                format!("{registry_dir}/config.json"),
+                "--repository-config".to_string(),
+                format!("{registry_dir}/repositories.yaml"),
+                "--repository-cache".to_string(),
+                registry_dir.clone(),
+                "--version".to_string(),
+                "1.2.3".to_string(),
+                "my-chart".to_string(),
+                "oci://ghcr.io/qovery/charts/my-chart".to_string(),
+            ]
+        );
+        assert_eq!(
+            login_args,
+            vec![
+                "registry".to_string(),
+                "--debug".to_string(),
+                "login".to_string(),
+                "ghcr.io".to_string(),
+                "--username".to_string(),
+                "qovery".to_string(),
+                "--password-stdin".to_string(),
+                "--registry-config".to_string(),
+                format!("{registry_dir}/config.json"),
+                "--repository-config".to_string(),
+                format!("{registry_dir}/repositories.yaml"),
+                "--repository-cache".to_string(),
+                registry_dir,
+            ]
+        );
+        assert!(!chart_args
+            .iter()
+            .chain(login_args.iter())
+            .any(|arg| arg.contains("my-secret-token")));
+        assert!(!format!("{chart:?}").contains("my-secret-token"));
+    }
+
+    #[test]
+    fn test_local_chart_args() {
+        let chart = ChartInfo {
+            name: "my-chart".to_string(),
+            path: "/charts/my-chart".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            chart_reference_args(&chart, None),
+            vec!["my-chart".to_string(), "/charts/my-chart".to_string()]
+        );
+    }
 }
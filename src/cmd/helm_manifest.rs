@@ -0,0 +1,305 @@
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+const CONTEXT_LINES: usize = 3;
+const SECRET_FIELDS: [&str; 2] = ["data", "stringData"];
+
+/// Unified diff, object by object, between the manifest of the deployed release and the one about to be applied.
+/// Values of the secrets are masked, the diff only tells which ones changed.
+/// The diff is truncated after `max_lines` lines, 0 means no limit.
+pub fn manifests_diff(deployed_manifest: &str, target_manifest: &str, max_lines: usize) -> Vec<String> {
+    let deployed_objects = manifest_objects(deployed_manifest);
+    let target_objects = manifest_objects(target_manifest);
+    let object_keys: BTreeSet<&String> = deployed_objects.keys().chain(target_objects.keys()).collect();
+
+    let mut lines = vec![];
+    for object_key in object_keys {
+        let mut deployed = deployed_objects.get(object_key).cloned();
+        let mut target = target_objects.get(object_key).cloned();
+        if let Some(deployed) = &mut deployed {
+            mask_secret_values(deployed, target_objects.get(object_key), "*** (previous value)");
+        }
+        if let Some(target) = &mut target {
+            mask_secret_values(target, deployed_objects.get(object_key), "*** (new value)");
+        }
+
+        let deployed = deployed.map(|object| to_yaml(&object)).unwrap_or_default();
+        let target = target.map(|object| to_yaml(&object)).unwrap_or_default();
+        lines.extend(object_diff(object_key, &deployed, &target));
+    }
+
+    if max_lines > 0 && lines.len() > max_lines {
+        let truncated_lines = lines.len() - max_lines;
+        lines.truncate(max_lines);
+        lines.push(format!("... {truncated_lines} more lines of diff not shown"));
+    }
+
+    lines
+}
+
+/// Objects of a multi-documents manifest, by `<kind> <namespace>/<name>`.
+/// A manifest which is not valid YAML is kept as a single object, to still show its changes.
+fn manifest_objects(manifest: &str) -> BTreeMap<String, Value> {
+    let mut objects = BTreeMap::new();
+    for document in serde_yaml::Deserializer::from_str(manifest) {
+        let object = match Value::deserialize(document) {
+            Ok(Value::Null) => continue,
+            Ok(object) => object,
+            Err(_) => {
+                return BTreeMap::from([("manifest".to_string(), Value::String(manifest.to_string()))]);
+            }
+        };
+
+        let field = |object: &Value, path: &[&str]| {
+            path.iter()
+                .try_fold(object, |value, key| value.get(key))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let object_key = format!(
+            "{} {}/{}",
+            field(&object, &["kind"]),
+            field(&object, &["metadata", "namespace"]),
+            field(&object, &["metadata", "name"])
+        );
+        objects.insert(object_key, object);
+    }
+
+    objects
+}
+
+/// Replaces the values of a secret by `changed_mask` when they differ from the ones of the other side of the diff,
+/// by a mask common to both sides otherwise
+fn mask_secret_values(object: &mut Value, other_side: Option<&Value>, changed_mask: &str) {
+    if object.get("kind").and_then(Value::as_str) != Some("Secret") {
+        return;
+    }
+
+    for field in SECRET_FIELDS {
+        let other_side_values = other_side.and_then(|other_side| other_side.get(field));
+        let Some(values) = object.get_mut(field).and_then(Value::as_mapping_mut) else {
+            continue;
+        };
+
+        for (key, value) in values.iter_mut() {
+            let is_unchanged = other_side_values.and_then(|values| values.get(key)) == Some(&*value);
+            *value = Value::String(if is_unchanged { "***" } else { changed_mask }.to_string());
+        }
+    }
+}
+
+fn to_yaml(object: &Value) -> String {
+    match object {
+        Value::String(raw_manifest) => raw_manifest.clone(),
+        _ => serde_yaml::to_string(object).unwrap_or_default(),
+    }
+}
+
+enum Edit<'a> {
+    Unchanged(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+fn object_diff(object_key: &str, deployed: &str, target: &str) -> Vec<String> {
+    let deployed_lines: Vec<&str> = deployed.lines().collect();
+    let target_lines: Vec<&str> = target.lines().collect();
+    let edits = line_edits(&deployed_lines, &target_lines);
+    if edits.iter().all(|edit| matches!(edit, Edit::Unchanged(_))) {
+        return vec![];
+    }
+
+    let mut lines = vec![
+        match deployed_lines.is_empty() {
+            true => "--- /dev/null".to_string(),
+            false => format!("--- {object_key}"),
+        },
+        match target_lines.is_empty() {
+            true => "+++ /dev/null".to_string(),
+            false => format!("+++ {object_key}"),
+        },
+    ];
+
+    // hunks are the changes with the unchanged lines around them, merged when their context overlaps
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Unchanged(_)))
+        .map(|(ix, _)| ix)
+        .collect();
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for ix in changes {
+        let start = ix.saturating_sub(CONTEXT_LINES);
+        let end = (ix + CONTEXT_LINES + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        let deployed_start = edits[..start]
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Added(_)))
+            .count();
+        let target_start = edits[..start]
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Removed(_)))
+            .count();
+        let hunk = &edits[start..end];
+        let deployed_count = hunk.iter().filter(|edit| !matches!(edit, Edit::Added(_))).count();
+        let target_count = hunk.iter().filter(|edit| !matches!(edit, Edit::Removed(_))).count();
+
+        // like diff, an empty side starts at the line before the hunk
+        lines.push(format!(
+            "@@ -{},{} +{},{} @@",
+            deployed_start + usize::from(deployed_count > 0),
+            deployed_count,
+            target_start + usize::from(target_count > 0),
+            target_count
+        ));
+        lines.extend(hunk.iter().map(|edit| match edit {
+            Edit::Unchanged(line) => format!(" {line}"),
+            Edit::Removed(line) => format!("-{line}"),
+            Edit::Added(line) => format!("+{line}"),
+        }));
+    }
+
+    lines
+}
+
+/// Edits from the longest common subsequence of lines, removals before additions
+fn line_edits<'a>(deployed: &[&'a str], target: &[&'a str]) -> Vec<Edit<'a>> {
+    // common[i][j]: length of the longest common subsequence of deployed[i..] and target[j..]
+    let mut common = vec![vec![0u32; target.len() + 1]; deployed.len() + 1];
+    for i in (0..deployed.len()).rev() {
+        for j in (0..target.len()).rev() {
+            common[i][j] = match deployed[i] == target[j] {
+                true => common[i + 1][j + 1] + 1,
+                false => common[i + 1][j].max(common[i][j + 1]),
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut edits = Vec::with_capacity(deployed.len().max(target.len()));
+    while i < deployed.len() || j < target.len() {
+        if i < deployed.len() && j < target.len() && deployed[i] == target[j] {
+            edits.push(Edit::Unchanged(deployed[i]));
+            i += 1;
+            j += 1;
+        } else if i < deployed.len() && (j == target.len() || common[i + 1][j] >= common[i][j + 1]) {
+            edits.push(Edit::Removed(deployed[i]));
+            i += 1;
+        } else {
+            edits.push(Edit::Added(target[j]));
+            j += 1;
+        }
+    }
+
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEPLOYED_MANIFEST: &str = include_str!("test_data/helm_manifest_deployed.yaml");
+    const TARGET_MANIFEST: &str = include_str!("test_data/helm_manifest_target.yaml");
+
+    #[test]
+    fn test_manifests_diff() {
+        let diff = manifests_diff(DEPLOYED_MANIFEST, TARGET_MANIFEST, 0);
+
+        assert_eq!(
+            diff,
+            vec![
+                "--- ConfigMap qovery/metrics-server-config",
+                "+++ /dev/null",
+                "@@ -1,7 +0,0 @@",
+                "-apiVersion: v1",
+                "-kind: ConfigMap",
+                "-metadata:",
+                "-  name: metrics-server-config",
+                "-  namespace: qovery",
+                "-data:",
+                "-  log_level: info",
+                "--- Deployment qovery/metrics-server",
+                "+++ Deployment qovery/metrics-server",
+                "@@ -6,7 +6,7 @@",
+                "   labels:",
+                "     app.kubernetes.io/name: metrics-server",
+                " spec:",
+                "-  replicas: 1",
+                "+  replicas: 2",
+                "   selector:",
+                "     matchLabels:",
+                "       app.kubernetes.io/name: metrics-server",
+                "@@ -17,6 +17,6 @@",
+                "     spec:",
+                "       containers:",
+                "       - name: metrics-server",
+                "-        image: registry.k8s.io/metrics-server/metrics-server:v0.7.1",
+                "+        image: registry.k8s.io/metrics-server/metrics-server:v0.7.2",
+                "         args:",
+                "         - --kubelet-preferred-address-types=InternalIP",
+                "--- Secret qovery/metrics-server-credentials",
+                "+++ Secret qovery/metrics-server-credentials",
+                "@@ -5,5 +5,6 @@",
+                "   namespace: qovery",
+                " type: Opaque",
+                " data:",
+                "-  password: '*** (previous value)'",
+                "+  password: '*** (new value)'",
+                "   username: '***'",
+                "+  token: '*** (new value)'",
+                "--- /dev/null",
+                "+++ Service qovery/metrics-server",
+                "@@ -0,0 +1,11 @@",
+                "+apiVersion: v1",
+                "+kind: Service",
+                "+metadata:",
+                "+  name: metrics-server",
+                "+  namespace: qovery",
+                "+spec:",
+                "+  ports:",
+                "+  - port: 443",
+                "+    targetPort: https",
+                "+  selector:",
+                "+    app.kubernetes.io/name: metrics-server",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_manifests_diff_masks_secrets() {
+        let diff = manifests_diff(DEPLOYED_MANIFEST, TARGET_MANIFEST, 0).join("\n");
+
+        for secret_value in ["cWRydDEyMzQ=", "bmV3LXBhc3N3b3Jk", "YWRtaW4=", "c2VjcmV0LXRva2Vu"] {
+            assert!(!diff.contains(secret_value), "{secret_value} should be masked");
+        }
+    }
+
+    #[test]
+    fn test_manifests_diff_without_changes() {
+        assert!(manifests_diff(DEPLOYED_MANIFEST, DEPLOYED_MANIFEST, 0).is_empty());
+        // only the formatting and the comments differ
+        assert!(manifests_diff(
+            "# Source: chart/templates/cm.yaml\nkind: ConfigMap\nmetadata: {name: cm}\ndata: {a: b}\n",
+            "---\nkind: ConfigMap\nmetadata:\n  name: cm\ndata:\n  a: \"b\"\n",
+            0
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_manifests_diff_is_truncated() {
+        let diff = manifests_diff("", TARGET_MANIFEST, 5);
+
+        assert_eq!(diff.len(), 6);
+        assert_eq!(diff[0], "--- /dev/null");
+        assert!(diff[5].starts_with("... ") && diff[5].ends_with(" more lines of diff not shown"));
+    }
+}
@@ -0,0 +1,543 @@
+//! Helm post-renderer applying cluster wide mutations to the manifests of every chart the engine deploys.
+//!
+//! Helm pipes the rendered manifests through `--post-renderer`, which is the engine binary itself started with
+//! [`HELM_POST_RENDERER_ARG`] as first argument: the binary has to call [`run_if_requested`] before anything else.
+//! The mutators to apply are serialized in the [`HELM_POST_RENDERER_ENV`] environment variable of the helm command,
+//! that helm passes down to the post-renderer.
+
+use crate::infrastructure::models::cloud_provider::io::ClusterAdvancedSettings;
+use k8s_openapi::api::core::v1::Toleration;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+pub const HELM_POST_RENDERER_ARG: &str = "helm-post-renderer";
+pub const HELM_POST_RENDERER_ENV: &str = "QOVERY_HELM_POST_RENDERER";
+
+// images without registry are pulled from docker hub
+const DOCKER_HUB_REGISTRY: &str = "docker.io";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ManifestMutator {
+    /// Labels of objects and pod templates, the ones already set by the chart are kept
+    AddLabels { labels: BTreeMap<String, String> },
+    /// Tolerations appended to every pod spec, unless already present
+    SetTolerations { tolerations: Vec<Toleration> },
+    /// Node selector entries of every pod spec, the ones already set by the chart are kept
+    SetNodeSelector { node_selector: BTreeMap<String, String> },
+    /// Images of the `from` registry are pulled from the `to` registry instead
+    RewriteImageRegistry { from: String, to: String },
+    /// Priority class of the pod specs not setting one
+    SetPriorityClassName { priority_class_name: String },
+}
+
+impl ManifestMutator {
+    fn mutate(&self, manifest: &mut Value) {
+        match self {
+            ManifestMutator::AddLabels { labels } => {
+                let pod_template_path = pod_spec_path(manifest).map(|mut path| {
+                    path.pop();
+                    path
+                });
+                // the object itself and its pod template
+                for path in [Some(vec![]), pod_template_path].into_iter().flatten() {
+                    if let Some(object) = mapping_at(manifest, &path) {
+                        let existing = child_mapping(child_mapping(object, "metadata"), "labels");
+                        for (key, value) in labels {
+                            insert_if_absent(existing, key, Value::String(value.clone()));
+                        }
+                    }
+                }
+            }
+            ManifestMutator::SetTolerations { tolerations } => {
+                if let Some(pod_spec) = pod_spec(manifest) {
+                    let existing = pod_spec
+                        .entry(Value::String("tolerations".to_string()))
+                        .or_insert_with(|| Value::Sequence(vec![]));
+                    if let Value::Sequence(existing) = existing {
+                        for toleration in tolerations.iter().filter_map(|t| serde_yaml::to_value(t).ok()) {
+                            if !existing.contains(&toleration) {
+                                existing.push(toleration);
+                            }
+                        }
+                    }
+                }
+            }
+            ManifestMutator::SetNodeSelector { node_selector } => {
+                if let Some(pod_spec) = pod_spec(manifest) {
+                    let existing = child_mapping(pod_spec, "nodeSelector");
+                    for (key, value) in node_selector {
+                        insert_if_absent(existing, key, Value::String(value.clone()));
+                    }
+                }
+            }
+            ManifestMutator::RewriteImageRegistry { from, to } => {
+                if let Some(pod_spec) = pod_spec(manifest) {
+                    for containers in ["initContainers", "containers"] {
+                        let Some(Value::Sequence(containers)) = pod_spec.get_mut(containers) else {
+                            continue;
+                        };
+                        for image in containers.iter_mut().filter_map(|container| container.get_mut("image")) {
+                            if let Some(rewritten) = image.as_str().and_then(|image| rewrite_image(image, from, to)) {
+                                *image = Value::String(rewritten);
+                            }
+                        }
+                    }
+                }
+            }
+            ManifestMutator::SetPriorityClassName { priority_class_name } => {
+                if let Some(pod_spec) = pod_spec(manifest) {
+                    insert_if_absent(pod_spec, "priorityClassName", Value::String(priority_class_name.clone()));
+                }
+            }
+        }
+    }
+}
+
+/// Ordered chain of mutators applied to each manifest rendered by helm
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PostRenderer {
+    mutators: Vec<ManifestMutator>,
+}
+
+impl PostRenderer {
+    pub fn new(mutators: Vec<ManifestMutator>) -> Self {
+        PostRenderer { mutators }
+    }
+
+    pub fn from_cluster_settings(advanced_settings: &ClusterAdvancedSettings) -> Self {
+        let mut mutators = vec![];
+        if !advanced_settings.k8s_post_renderer_labels.is_empty() {
+            mutators.push(ManifestMutator::AddLabels {
+                labels: advanced_settings.k8s_post_renderer_labels.clone(),
+            });
+        }
+        if !advanced_settings.k8s_post_renderer_tolerations.is_empty() {
+            mutators.push(ManifestMutator::SetTolerations {
+                tolerations: advanced_settings.k8s_post_renderer_tolerations.clone(),
+            });
+        }
+        if !advanced_settings.k8s_post_renderer_node_selector.is_empty() {
+            mutators.push(ManifestMutator::SetNodeSelector {
+                node_selector: advanced_settings.k8s_post_renderer_node_selector.clone(),
+            });
+        }
+        for (from, to) in &advanced_settings.k8s_post_renderer_image_registry_rewrites {
+            mutators.push(ManifestMutator::RewriteImageRegistry {
+                from: from.clone(),
+                to: to.clone(),
+            });
+        }
+        if let Some(priority_class_name) = &advanced_settings.k8s_post_renderer_priority_class_name {
+            mutators.push(ManifestMutator::SetPriorityClassName {
+                priority_class_name: priority_class_name.clone(),
+            });
+        }
+
+        PostRenderer { mutators }
+    }
+
+    /// Environment variable to add to the helm commands, none when there is nothing to mutate
+    pub fn to_env(&self) -> Option<(String, String)> {
+        if self.mutators.is_empty() {
+            return None;
+        }
+
+        serde_json::to_string(self)
+            .ok()
+            .map(|post_renderer| (HELM_POST_RENDERER_ENV.to_string(), post_renderer))
+    }
+
+    pub fn render(&self, manifests: &str) -> Result<String, serde_yaml::Error> {
+        let mut rendered = vec![];
+        for document in serde_yaml::Deserializer::from_str(manifests) {
+            let mut manifest = Value::deserialize(document)?;
+            if manifest.is_null() {
+                continue;
+            }
+            for mutator in &self.mutators {
+                mutator.mutate(&mut manifest);
+            }
+            rendered.push(serde_yaml::to_string(&manifest)?);
+        }
+
+        Ok(rendered.join("---\n"))
+    }
+}
+
+/// Helm arguments plugging the post-renderer, when the helm command environment requests one
+pub fn helm_args(envs: &[(&str, &str)]) -> Result<Vec<String>, std::io::Error> {
+    if !envs.iter().any(|(key, _)| *key == HELM_POST_RENDERER_ENV) {
+        return Ok(vec![]);
+    }
+
+    let engine_binary: PathBuf = std::env::current_exe()?;
+    Ok(vec![
+        "--post-renderer".to_string(),
+        engine_binary.to_string_lossy().to_string(),
+        "--post-renderer-args".to_string(),
+        HELM_POST_RENDERER_ARG.to_string(),
+    ])
+}
+
+/// Runs the post-renderer and exits when the binary has been started by helm as a post-renderer, does nothing otherwise
+pub fn run_if_requested() {
+    if std::env::args().nth(1).as_deref() != Some(HELM_POST_RENDERER_ARG) {
+        return;
+    }
+
+    let exit_code = match run() {
+        Ok(_) => 0,
+        Err(err) => {
+            eprintln!("helm post-renderer error: {err}");
+            1
+        }
+    };
+    std::process::exit(exit_code);
+}
+
+fn run() -> Result<(), String> {
+    let post_renderer: PostRenderer = match std::env::var(HELM_POST_RENDERER_ENV) {
+        Ok(post_renderer) => serde_json::from_str(&post_renderer)
+            .map_err(|e| format!("invalid {HELM_POST_RENDERER_ENV} environment variable: {e}"))?,
+        Err(_) => PostRenderer::default(),
+    };
+
+    let mut manifests = String::new();
+    std::io::stdin()
+        .read_to_string(&mut manifests)
+        .map_err(|e| format!("cannot read manifests from stdin: {e}"))?;
+    let rendered = post_renderer
+        .render(&manifests)
+        .map_err(|e| format!("cannot parse manifests: {e}"))?;
+    std::io::stdout()
+        .write_all(rendered.as_bytes())
+        .map_err(|e| format!("cannot write manifests to stdout: {e}"))
+}
+
+fn pod_spec_path(manifest: &Value) -> Option<Vec<&'static str>> {
+    match manifest.get("kind")?.as_str()? {
+        "Pod" => Some(vec!["spec"]),
+        "Deployment" | "StatefulSet" | "DaemonSet" | "ReplicaSet" | "ReplicationController" | "Job" => {
+            Some(vec!["spec", "template", "spec"])
+        }
+        "CronJob" => Some(vec!["spec", "jobTemplate", "spec", "template", "spec"]),
+        _ => None,
+    }
+}
+
+fn pod_spec(manifest: &mut Value) -> Option<&mut Mapping> {
+    let path = pod_spec_path(manifest)?;
+    mapping_at(manifest, &path)
+}
+
+fn mapping_at<'a>(manifest: &'a mut Value, path: &[&str]) -> Option<&'a mut Mapping> {
+    path.iter()
+        .try_fold(manifest, |value, key| value.get_mut(*key))?
+        .as_mapping_mut()
+}
+
+fn child_mapping<'a>(parent: &'a mut Mapping, key: &str) -> &'a mut Mapping {
+    let child = parent
+        .entry(Value::String(key.to_string()))
+        .or_insert_with(|| Value::Mapping(Mapping::new()));
+    // charts may render an explicit null, e.g. `labels: {{ .Values.labels }}`
+    if !child.is_mapping() {
+        *child = Value::Mapping(Mapping::new());
+    }
+
+    child.as_mapping_mut().expect("value has just been set to a mapping")
+}
+
+fn insert_if_absent(mapping: &mut Mapping, key: &str, value: Value) {
+    mapping.entry(Value::String(key.to_string())).or_insert(value);
+}
+
+fn rewrite_image(image: &str, from: &str, to: &str) -> Option<String> {
+    let from = from.trim_end_matches('/');
+    let to = to.trim_end_matches('/');
+    if let Some(repository) = image.strip_prefix(from).and_then(|rest| rest.strip_prefix('/')) {
+        return Some(format!("{to}/{repository}"));
+    }
+
+    // `nginx` and `bitnami/redis` are implicitly pulled from docker hub
+    let first_component = image.split('/').next().unwrap_or_default();
+    let has_registry = image.contains('/') && (first_component.contains(['.', ':']) || first_component == "localhost");
+    match (from == DOCKER_HUB_REGISTRY, has_registry, image.contains('/')) {
+        (true, false, true) => Some(format!("{to}/{image}")),
+        (true, false, false) => Some(format!("{to}/library/{image}")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEPLOYMENT: &str = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app
+  labels:
+    app: app
+spec:
+  selector:
+    matchLabels:
+      app: app
+  template:
+    metadata:
+      labels:
+        app: app
+    spec:
+      nodeSelector:
+        kubernetes.io/os: linux
+      initContainers:
+        - name: init
+          image: busybox:1.36
+      containers:
+        - name: app
+          image: public.ecr.aws/qovery/app:1.0.0
+"#;
+
+    const CRON_JOB_AND_SERVICE: &str = r#"
+---
+apiVersion: batch/v1
+kind: CronJob
+metadata:
+  name: job
+spec:
+  schedule: "0 2 * * *"
+  jobTemplate:
+    spec:
+      template:
+        spec:
+          priorityClassName: high
+          tolerations:
+            - key: nodepool
+              operator: Equal
+              value: stable
+              effect: NoSchedule
+          containers:
+            - name: job
+              image: bitnami/kubectl:1.30
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: app
+spec:
+  ports:
+    - port: 80
+"#;
+
+    fn render(mutator: ManifestMutator, manifests: &str) -> Vec<Value> {
+        let rendered = PostRenderer::new(vec![mutator]).render(manifests).unwrap();
+        serde_yaml::Deserializer::from_str(&rendered)
+            .map(|document| Value::deserialize(document).unwrap())
+            .collect()
+    }
+
+    fn at<'a>(manifest: &'a Value, path: &[&str]) -> &'a Value {
+        path.iter().fold(manifest, |value, key| &value[*key])
+    }
+
+    fn toleration(key: &str, value: &str) -> Toleration {
+        Toleration {
+            key: Some(key.to_string()),
+            operator: Some("Equal".to_string()),
+            value: Some(value.to_string()),
+            effect: Some("NoSchedule".to_string()),
+            toleration_seconds: None,
+        }
+    }
+
+    #[test]
+    fn test_add_labels() {
+        let mutator = ManifestMutator::AddLabels {
+            labels: BTreeMap::from([
+                ("app".to_string(), "overridden".to_string()),
+                ("qovery.com/cluster-id".to_string(), "42".to_string()),
+            ]),
+        };
+
+        let manifests = render(mutator.clone(), DEPLOYMENT);
+        for labels in [
+            at(&manifests[0], &["metadata", "labels"]),
+            at(&manifests[0], &["spec", "template", "metadata", "labels"]),
+        ] {
+            assert_eq!(labels["app"], "app");
+            assert_eq!(labels["qovery.com/cluster-id"], "42");
+        }
+        // selectors are immutable and must never change
+        assert_eq!(
+            at(&manifests[0], &["spec", "selector", "matchLabels"])
+                .as_mapping()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let manifests = render(mutator, CRON_JOB_AND_SERVICE);
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(at(&manifests[0], &["metadata", "labels", "qovery.com/cluster-id"]), "42");
+        assert_eq!(
+            at(
+                &manifests[0],
+                &[
+                    "spec",
+                    "jobTemplate",
+                    "spec",
+                    "template",
+                    "metadata",
+                    "labels",
+                    "qovery.com/cluster-id"
+                ]
+            ),
+            "42"
+        );
+        assert_eq!(at(&manifests[1], &["metadata", "labels", "qovery.com/cluster-id"]), "42");
+    }
+
+    #[test]
+    fn test_set_tolerations() {
+        let mutator = ManifestMutator::SetTolerations {
+            tolerations: vec![toleration("nodepool", "stable"), toleration("dedicated", "qovery")],
+        };
+
+        let manifests = render(mutator.clone(), DEPLOYMENT);
+        let tolerations = at(&manifests[0], &["spec", "template", "spec", "tolerations"]);
+        assert_eq!(tolerations.as_sequence().unwrap().len(), 2);
+        assert_eq!(tolerations[1]["key"], "dedicated");
+        assert_eq!(tolerations[1]["effect"], "NoSchedule");
+
+        // the toleration already set by the chart is not duplicated
+        let manifests = render(mutator, CRON_JOB_AND_SERVICE);
+        let tolerations = at(
+            &manifests[0],
+            &["spec", "jobTemplate", "spec", "template", "spec", "tolerations"],
+        );
+        assert_eq!(tolerations.as_sequence().unwrap().len(), 2);
+        assert_eq!(tolerations[0]["key"], "nodepool");
+        assert_eq!(tolerations[1]["key"], "dedicated");
+        assert!(manifests[1]["spec"].get("tolerations").is_none());
+    }
+
+    #[test]
+    fn test_set_node_selector() {
+        let mutator = ManifestMutator::SetNodeSelector {
+            node_selector: BTreeMap::from([
+                ("kubernetes.io/os".to_string(), "windows".to_string()),
+                ("nodepool".to_string(), "stable".to_string()),
+            ]),
+        };
+
+        let manifests = render(mutator, DEPLOYMENT);
+        let node_selector = at(&manifests[0], &["spec", "template", "spec", "nodeSelector"]);
+        assert_eq!(node_selector["kubernetes.io/os"], "linux");
+        assert_eq!(node_selector["nodepool"], "stable");
+    }
+
+    #[test]
+    fn test_rewrite_image_registry() {
+        let manifests = render(
+            ManifestMutator::RewriteImageRegistry {
+                from: "public.ecr.aws".to_string(),
+                to: "mirror.qovery.com/ecr/".to_string(),
+            },
+            DEPLOYMENT,
+        );
+        let pod_spec = at(&manifests[0], &["spec", "template", "spec"]);
+        assert_eq!(pod_spec["containers"][0]["image"], "mirror.qovery.com/ecr/qovery/app:1.0.0");
+        assert_eq!(pod_spec["initContainers"][0]["image"], "busybox:1.36");
+
+        let docker_hub = ManifestMutator::RewriteImageRegistry {
+            from: "docker.io".to_string(),
+            to: "mirror.qovery.com/hub".to_string(),
+        };
+        let manifests = render(docker_hub.clone(), DEPLOYMENT);
+        let pod_spec = at(&manifests[0], &["spec", "template", "spec"]);
+        assert_eq!(pod_spec["containers"][0]["image"], "public.ecr.aws/qovery/app:1.0.0");
+        assert_eq!(
+            pod_spec["initContainers"][0]["image"],
+            "mirror.qovery.com/hub/library/busybox:1.36"
+        );
+
+        let manifests = render(docker_hub, CRON_JOB_AND_SERVICE);
+        assert_eq!(
+            at(
+                &manifests[0],
+                &["spec", "jobTemplate", "spec", "template", "spec", "containers"]
+            )[0]["image"],
+            "mirror.qovery.com/hub/bitnami/kubectl:1.30"
+        );
+    }
+
+    #[test]
+    fn test_set_priority_class_name() {
+        let mutator = ManifestMutator::SetPriorityClassName {
+            priority_class_name: "qovery-standard".to_string(),
+        };
+
+        let manifests = render(mutator.clone(), DEPLOYMENT);
+        assert_eq!(
+            at(&manifests[0], &["spec", "template", "spec", "priorityClassName"]),
+            "qovery-standard"
+        );
+
+        let manifests = render(mutator, CRON_JOB_AND_SERVICE);
+        assert_eq!(
+            at(
+                &manifests[0],
+                &["spec", "jobTemplate", "spec", "template", "spec", "priorityClassName"]
+            ),
+            "high"
+        );
+        assert!(manifests[1]["spec"].get("priorityClassName").is_none());
+    }
+
+    #[test]
+    fn test_post_renderer_from_cluster_settings() {
+        let advanced_settings = ClusterAdvancedSettings::default();
+        let post_renderer = PostRenderer::from_cluster_settings(&advanced_settings);
+        assert_eq!(post_renderer, PostRenderer::default());
+        assert_eq!(post_renderer.to_env(), None);
+        assert!(helm_args(&[("KUBECONFIG", "/tmp/kubeconfig")]).unwrap().is_empty());
+
+        let advanced_settings = ClusterAdvancedSettings {
+            k8s_post_renderer_labels: BTreeMap::from([("team".to_string(), "platform".to_string())]),
+            k8s_post_renderer_image_registry_rewrites: BTreeMap::from([(
+                "docker.io".to_string(),
+                "mirror.qovery.com".to_string(),
+            )]),
+            k8s_post_renderer_priority_class_name: Some("qovery-standard".to_string()),
+            ..Default::default()
+        };
+        let post_renderer = PostRenderer::from_cluster_settings(&advanced_settings);
+        assert_eq!(
+            post_renderer,
+            PostRenderer::new(vec![
+                ManifestMutator::AddLabels {
+                    labels: BTreeMap::from([("team".to_string(), "platform".to_string())]),
+                },
+                ManifestMutator::RewriteImageRegistry {
+                    from: "docker.io".to_string(),
+                    to: "mirror.qovery.com".to_string(),
+                },
+                ManifestMutator::SetPriorityClassName {
+                    priority_class_name: "qovery-standard".to_string(),
+                },
+            ])
+        );
+
+        // the chain reaches the post-renderer process through the helm environment
+        let (key, value) = post_renderer.to_env().unwrap();
+        assert_eq!(serde_json::from_str::<PostRenderer>(&value).unwrap(), post_renderer);
+        let args = helm_args(&[(key.as_str(), value.as_str())]).unwrap();
+        assert_eq!(args[0], "--post-renderer");
+        assert_eq!(args[2..], ["--post-renderer-args", HELM_POST_RENDERER_ARG]);
+    }
+}
@@ -0,0 +1,415 @@
+use crate::cmd::command::CommandKiller;
+use crate::cmd::helm::HelmCommand::UNINSTALL;
+use crate::cmd::helm::{Helm, HelmError};
+use crate::errors;
+use crate::helm::ChartInfo;
+use crate::runtime::block_on;
+use k8s_openapi::api::core::v1::Namespace;
+use kube::api::{DeleteParams, DynamicObject, ListParams, Patch, PatchParams};
+use kube::discovery::{verbs, ApiResource, Discovery, Scope};
+use kube::Api;
+use serde_json::json;
+use std::fmt::{Display, Formatter};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const HELM_RELEASE_NAME_ANNOTATION: &str = "meta.helm.sh/release-name";
+const HELM_RELEASE_NAMESPACE_ANNOTATION: &str = "meta.helm.sh/release-namespace";
+const HELM_INSTANCE_LABEL: &str = "app.kubernetes.io/instance";
+const NAMESPACE_DELETION_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Kubernetes calls required to force clean a release stuck in deletion.
+/// Implemented by `kube::Client`, and by an in memory cluster in tests.
+pub trait ReleaseCleanupApi {
+    /// List every object of every namespaced resource kind of the namespace
+    fn list_namespaced_objects(&self, namespace: &str) -> Result<Vec<(ApiResource, DynamicObject)>, kube::Error>;
+    fn remove_finalizers(&self, resource: &ApiResource, namespace: &str, name: &str) -> Result<(), kube::Error>;
+    fn get_namespace(&self, namespace: &str) -> Result<Option<Namespace>, kube::Error>;
+    fn delete_namespace(&self, namespace: &str) -> Result<(), kube::Error>;
+}
+
+fn ignore_not_found<T>(result: Result<T, kube::Error>) -> Result<(), kube::Error> {
+    match result {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+impl ReleaseCleanupApi for kube::Client {
+    fn list_namespaced_objects(&self, namespace: &str) -> Result<Vec<(ApiResource, DynamicObject)>, kube::Error> {
+        let discovery = block_on(Discovery::new(self.clone()).run())?;
+        let mut objects = vec![];
+        for group in discovery.groups() {
+            for (resource, capabilities) in group.recommended_resources() {
+                if capabilities.scope != Scope::Namespaced || !capabilities.supports_operation(verbs::LIST) {
+                    continue;
+                }
+
+                let api: Api<DynamicObject> = Api::namespaced_with(self.clone(), namespace, &resource);
+                match block_on(api.list(&ListParams::default())) {
+                    Ok(list) => objects.extend(list.items.into_iter().map(|object| (resource.clone(), object))),
+                    // Some aggregated APIs may be unavailable, it should not prevent cleaning the others
+                    Err(err) => warn!("Cannot list {} in namespace {}: {}", resource.plural, namespace, err),
+                }
+            }
+        }
+
+        Ok(objects)
+    }
+
+    fn remove_finalizers(&self, resource: &ApiResource, namespace: &str, name: &str) -> Result<(), kube::Error> {
+        let api: Api<DynamicObject> = Api::namespaced_with(self.clone(), namespace, resource);
+        let patch = json!({ "metadata": { "finalizers": null } });
+        ignore_not_found(block_on(api.patch(name, &PatchParams::default(), &Patch::Merge(&patch))))
+    }
+
+    fn get_namespace(&self, namespace: &str) -> Result<Option<Namespace>, kube::Error> {
+        let api: Api<Namespace> = Api::all(self.clone());
+        block_on(api.get_opt(namespace))
+    }
+
+    fn delete_namespace(&self, namespace: &str) -> Result<(), kube::Error> {
+        let api: Api<Namespace> = Api::all(self.clone());
+        ignore_not_found(block_on(api.delete(namespace, &DeleteParams::default())))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForceCleanedObject {
+    pub kind: String,
+    pub name: String,
+    pub finalizers: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NamespaceCleanup {
+    /// The namespace is not being deleted, it has been left untouched
+    NotTerminating,
+    Deleted,
+    StillTerminating,
+}
+
+/// What had to be force cleaned after a helm uninstall timed out
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForceCleanReport {
+    pub release: String,
+    pub namespace: String,
+    pub objects: Vec<ForceCleanedObject>,
+    pub namespace_cleanup: NamespaceCleanup,
+}
+
+impl Display for ForceCleanReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Helm uninstall of release `{}` timed out, finalizers have been removed from {} object(s) of namespace `{}`",
+            self.release,
+            self.objects.len(),
+            self.namespace
+        )?;
+        for (ix, object) in self.objects.iter().enumerate() {
+            let separator = if ix == 0 { ": " } else { ", " };
+            write!(
+                f,
+                "{separator}{}/{} [{}]",
+                object.kind,
+                object.name,
+                object.finalizers.join(", ")
+            )?;
+        }
+
+        match self.namespace_cleanup {
+            NamespaceCleanup::NotTerminating => Ok(()),
+            NamespaceCleanup::Deleted => write!(f, ". Namespace `{}` has been deleted", self.namespace),
+            NamespaceCleanup::StillTerminating => write!(f, ". Namespace `{}` is still terminating", self.namespace),
+        }
+    }
+}
+
+fn is_owned_by_release(object: &DynamicObject, release: &str, namespace: &str) -> bool {
+    let annotations = &object.metadata.annotations;
+    let annotated = annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(HELM_RELEASE_NAME_ANNOTATION))
+        .map(|name| name == release)
+        .unwrap_or(false)
+        && annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(HELM_RELEASE_NAMESPACE_ANNOTATION))
+            .map(|ns| ns == namespace)
+            .unwrap_or(true);
+    let labelled = object
+        .metadata
+        .labels
+        .as_ref()
+        .and_then(|labels| labels.get(HELM_INSTANCE_LABEL))
+        .map(|name| name == release)
+        .unwrap_or(false);
+
+    annotated || labelled
+}
+
+// Objects being deleted but still held by a finalizer that nobody is going to remove
+fn is_stuck_in_deletion(object: &DynamicObject) -> bool {
+    object.metadata.deletion_timestamp.is_some()
+        && object
+            .metadata
+            .finalizers
+            .as_ref()
+            .map(|finalizers| !finalizers.is_empty())
+            .unwrap_or(false)
+}
+
+/// Strip the finalizers of the objects of the release stuck in deletion, then retry the deletion of
+/// the namespace if it is terminating, waiting at most `namespace_deletion_timeout` for it to be gone.
+pub fn force_clean_release(
+    api: &impl ReleaseCleanupApi,
+    release: &str,
+    namespace: &str,
+    namespace_deletion_timeout: Duration,
+) -> Result<ForceCleanReport, kube::Error> {
+    let mut objects = vec![];
+    for (resource, object) in api.list_namespaced_objects(namespace)? {
+        if !is_owned_by_release(&object, release, namespace) || !is_stuck_in_deletion(&object) {
+            continue;
+        }
+
+        let name = object.metadata.name.unwrap_or_default();
+        warn!(
+            "Removing finalizers of {} {}/{} stuck in deletion",
+            resource.kind, namespace, name
+        );
+        api.remove_finalizers(&resource, namespace, &name)?;
+        objects.push(ForceCleanedObject {
+            kind: resource.kind,
+            name,
+            finalizers: object.metadata.finalizers.unwrap_or_default(),
+        });
+    }
+
+    let namespace_cleanup = match api.get_namespace(namespace)? {
+        None => NamespaceCleanup::Deleted,
+        Some(ns) if ns.metadata.deletion_timestamp.is_none() => NamespaceCleanup::NotTerminating,
+        Some(_) => {
+            api.delete_namespace(namespace)?;
+            let since = Instant::now();
+            loop {
+                if api.get_namespace(namespace)?.is_none() {
+                    break NamespaceCleanup::Deleted;
+                }
+                if since.elapsed() >= namespace_deletion_timeout {
+                    break NamespaceCleanup::StillTerminating;
+                }
+                thread::sleep(NAMESPACE_DELETION_POLL_INTERVAL);
+            }
+        }
+    };
+
+    Ok(ForceCleanReport {
+        release: release.to_string(),
+        namespace: namespace.to_string(),
+        objects,
+        namespace_cleanup,
+    })
+}
+
+/// Uninstall the release, and if helm times out waiting for its resources to be deleted, force clean
+/// the objects stuck on a finalizer before uninstalling it again.
+/// Returns the report of what has been force cleaned, if anything had to be.
+pub fn uninstall_with_finalizers_cleanup(
+    helm: &Helm,
+    chart: &ChartInfo,
+    envs: &[(&str, &str)],
+    api: &impl ReleaseCleanupApi,
+    cmd_killer: &CommandKiller,
+) -> Result<Option<ForceCleanReport>, HelmError> {
+    match helm.uninstall(chart, envs, cmd_killer, &mut |_| {}, &mut |_| {}) {
+        Ok(()) => return Ok(None),
+        // Only helm --wait timed out, not our own deadline
+        Err(HelmError::Timeout(..)) if cmd_killer.should_abort().is_none() => {}
+        Err(err) => return Err(err),
+    }
+
+    let namespace = chart.get_namespace_string();
+    let namespace_deletion_timeout = Duration::from_secs(chart.timeout_in_seconds.max(0) as u64);
+    let report = force_clean_release(api, &chart.name, &namespace, namespace_deletion_timeout).map_err(|err| {
+        HelmError::CmdError(
+            chart.name.clone(),
+            UNINSTALL,
+            errors::CommandError::new(
+                format!("Cannot force clean the objects of release `{}` stuck in deletion", chart.name),
+                Some(err.to_string()),
+                None,
+            ),
+        )
+    })?;
+
+    // Objects are gone now, the release itself can be removed
+    helm.uninstall(chart, envs, cmd_killer, &mut |_| {}, &mut |_| {})?;
+    Ok(Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    fn certificate_resource() -> ApiResource {
+        ApiResource {
+            group: "cert-manager.io".to_string(),
+            version: "v1".to_string(),
+            api_version: "cert-manager.io/v1".to_string(),
+            kind: "Certificate".to_string(),
+            plural: "certificates".to_string(),
+        }
+    }
+
+    fn object(name: &str, release: Option<&str>, finalizers: &[&str], deleting: bool) -> DynamicObject {
+        let mut object = DynamicObject::new(name, &certificate_resource()).within("my-ns");
+        object.metadata.annotations = release.map(|release| {
+            BTreeMap::from([
+                (HELM_RELEASE_NAME_ANNOTATION.to_string(), release.to_string()),
+                (HELM_RELEASE_NAMESPACE_ANNOTATION.to_string(), "my-ns".to_string()),
+            ])
+        });
+        object.metadata.finalizers = Some(finalizers.iter().map(|f| f.to_string()).collect());
+        object.metadata.deletion_timestamp = deleting.then(|| Time(chrono::Utc::now()));
+        object
+    }
+
+    // Namespace is removed by the mock once no object holds a finalizer anymore
+    struct MockCluster {
+        objects: RefCell<Vec<DynamicObject>>,
+        namespace: RefCell<Option<Namespace>>,
+        patched: RefCell<Vec<String>>,
+    }
+
+    impl MockCluster {
+        fn new(objects: Vec<DynamicObject>, terminating: bool) -> Self {
+            let mut namespace = Namespace::default();
+            namespace.metadata.name = Some("my-ns".to_string());
+            namespace.metadata.deletion_timestamp = terminating.then(|| Time(chrono::Utc::now()));
+            MockCluster {
+                objects: RefCell::new(objects),
+                namespace: RefCell::new(Some(namespace)),
+                patched: RefCell::new(vec![]),
+            }
+        }
+    }
+
+    impl ReleaseCleanupApi for MockCluster {
+        fn list_namespaced_objects(&self, _namespace: &str) -> Result<Vec<(ApiResource, DynamicObject)>, kube::Error> {
+            Ok(self
+                .objects
+                .borrow()
+                .iter()
+                .map(|object| (certificate_resource(), object.clone()))
+                .collect())
+        }
+
+        fn remove_finalizers(&self, _resource: &ApiResource, _namespace: &str, name: &str) -> Result<(), kube::Error> {
+            self.patched.borrow_mut().push(name.to_string());
+            // deleted objects without finalizers are gone
+            self.objects
+                .borrow_mut()
+                .retain(|object| object.metadata.name.as_deref() != Some(name) || !is_stuck_in_deletion(object));
+            Ok(())
+        }
+
+        fn get_namespace(&self, _namespace: &str) -> Result<Option<Namespace>, kube::Error> {
+            Ok(self.namespace.borrow().clone())
+        }
+
+        fn delete_namespace(&self, _namespace: &str) -> Result<(), kube::Error> {
+            if !self.objects.borrow().iter().any(is_stuck_in_deletion) {
+                *self.namespace.borrow_mut() = None;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_force_clean_release_strips_only_stuck_objects_of_the_release() {
+        let cluster = MockCluster::new(
+            vec![
+                object("stuck", Some("my-release"), &["cert-manager.io/finalizer"], true),
+                object("running", Some("my-release"), &["cert-manager.io/finalizer"], false),
+                object("deleting-without-finalizer", Some("my-release"), &[], true),
+                object("other-release", Some("other"), &["cert-manager.io/finalizer"], true),
+                object("not-helm", None, &["cert-manager.io/finalizer"], true),
+            ],
+            false,
+        );
+
+        let report = force_clean_release(&cluster, "my-release", "my-ns", Duration::ZERO).unwrap();
+
+        assert_eq!(*cluster.patched.borrow(), vec!["stuck".to_string()]);
+        assert_eq!(
+            report,
+            ForceCleanReport {
+                release: "my-release".to_string(),
+                namespace: "my-ns".to_string(),
+                objects: vec![ForceCleanedObject {
+                    kind: "Certificate".to_string(),
+                    name: "stuck".to_string(),
+                    finalizers: vec!["cert-manager.io/finalizer".to_string()],
+                }],
+                namespace_cleanup: NamespaceCleanup::NotTerminating,
+            }
+        );
+        assert!(cluster.namespace.borrow().is_some());
+    }
+
+    #[test]
+    fn test_force_clean_release_matches_instance_label() {
+        let mut labelled = object("labelled", None, &["kubernetes.io/pvc-protection"], true);
+        labelled.metadata.labels = Some(BTreeMap::from([(HELM_INSTANCE_LABEL.to_string(), "my-release".to_string())]));
+        let cluster = MockCluster::new(vec![labelled], false);
+
+        let report = force_clean_release(&cluster, "my-release", "my-ns", Duration::ZERO).unwrap();
+
+        assert_eq!(*cluster.patched.borrow(), vec!["labelled".to_string()]);
+        assert_eq!(report.objects.len(), 1);
+    }
+
+    #[test]
+    fn test_force_clean_release_retries_terminating_namespace_deletion() {
+        let cluster = MockCluster::new(
+            vec![
+                object("stuck-1", Some("my-release"), &["a"], true),
+                object("stuck-2", Some("my-release"), &["b", "c"], true),
+            ],
+            true,
+        );
+
+        let report = force_clean_release(&cluster, "my-release", "my-ns", Duration::ZERO).unwrap();
+
+        assert_eq!(report.namespace_cleanup, NamespaceCleanup::Deleted);
+        assert!(cluster.namespace.borrow().is_none());
+        assert_eq!(
+            report.to_string(),
+            "Helm uninstall of release `my-release` timed out, finalizers have been removed from 2 object(s) of namespace `my-ns`: Certificate/stuck-1 [a], Certificate/stuck-2 [b, c]. Namespace `my-ns` has been deleted"
+        );
+    }
+
+    #[test]
+    fn test_force_clean_release_reports_namespace_still_terminating() {
+        // an object of another release keeps the namespace terminating
+        let cluster = MockCluster::new(
+            vec![
+                object("stuck", Some("my-release"), &["a"], true),
+                object("other-release", Some("other"), &["b"], true),
+            ],
+            true,
+        );
+
+        let report = force_clean_release(&cluster, "my-release", "my-ns", Duration::ZERO).unwrap();
+
+        assert_eq!(report.objects.len(), 1);
+        assert_eq!(report.namespace_cleanup, NamespaceCleanup::StillTerminating);
+        assert!(cluster.namespace.borrow().is_some());
+    }
+}
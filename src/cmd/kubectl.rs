@@ -13,9 +13,9 @@ use uuid::Uuid;
 
 use crate::cmd::command::{ExecutableCommand, QoveryCommand};
 use crate::cmd::structs::{
-    Configmap, Item, KubernetesIngress, KubernetesIngressStatusLoadBalancerIngress, KubernetesJob, KubernetesKind,
-    KubernetesList, KubernetesNode, KubernetesPod, KubernetesPodStatusReason, KubernetesVersion, MetricsServer,
-    Secrets, PDB, PVC, SVC,
+    Configmap, Item, KubernetesApiCustomMetric, KubernetesIngress, KubernetesIngressStatusLoadBalancerIngress,
+    KubernetesJob, KubernetesKind, KubernetesList, KubernetesNode, KubernetesPod, KubernetesPodStatusReason,
+    KubernetesVersion, MetricsServer, Secrets, PDB, PVC, SVC,
 };
 use crate::constants::KUBECONFIG;
 use crate::errors::{CommandError, ErrorMessageVerbosity};
@@ -197,6 +197,35 @@ where
     Ok(())
 }
 
+/// Builds a kustomization directory and returns the rendered manifests.
+/// It does not need access to the cluster, so no kubeconfig is required.
+pub fn kubectl_exec_kustomize<P>(kustomization_dir: P, envs: Vec<(&str, &str)>) -> Result<String, CommandError>
+where
+    P: AsRef<Path>,
+{
+    let kustomization_dir = kustomization_dir.as_ref().to_string_lossy().to_string();
+    let mut output = String::new();
+    let mut errors = vec![];
+    let result = kubectl_exec_with_output(
+        vec!["kustomize", kustomization_dir.as_str()],
+        envs,
+        &mut |line| {
+            output.push_str(&line);
+            output.push('\n');
+        },
+        &mut |line| errors.push(line),
+    );
+    result.map_err(|err| {
+        CommandError::new(
+            format!("Cannot build kustomization in {kustomization_dir}"),
+            Some(format!("{}\n{}", err.message_raw().unwrap_or_default(), errors.join("\n"))),
+            None,
+        )
+    })?;
+
+    Ok(output)
+}
+
 pub fn kubectl_exec_version<P>(kubernetes_config: P, envs: Vec<(&str, &str)>) -> Result<KubernetesVersion, CommandError>
 where
     P: AsRef<Path>,
@@ -740,6 +769,22 @@ where
     kubectl_exec::<P, MetricsServer>(cmd_args, kubernetes_config, envs)
 }
 
+/// Values of a metric exposed through the custom metrics api, for every pod of the namespace
+pub fn kubectl_exec_api_custom_metrics<P>(
+    kubernetes_config: P,
+    envs: Vec<(&str, &str)>,
+    namespace: &str,
+    metric_name: &str,
+) -> Result<KubernetesApiCustomMetric, CommandError>
+where
+    P: AsRef<Path>,
+{
+    let path = format!("/apis/custom.metrics.k8s.io/v1beta1/namespaces/{namespace}/pods/*/{metric_name}");
+    let cmd_args = vec!["get", "--raw", path.as_str()];
+
+    kubectl_exec::<P, KubernetesApiCustomMetric>(cmd_args, kubernetes_config, envs)
+}
+
 pub fn kubectl_get_resource_yaml<P>(
     kubernetes_config: P,
     envs: Vec<(&str, &str)>,
@@ -898,27 +943,75 @@ where
     kubectl_exec_raw_output(cmd_args, kubernetes_config, envs, false)
 }
 
+const JOB_OUTPUT_CONTAINER_NAME: &str = "qovery-wait-container-output";
+const JOB_OUTPUT_FILE_PATH: &str = "/qovery-output/qovery-output.json";
+
+/// Output a job wrote in the shared volume of its pod
+pub struct JobPodOutput {
+    /// Content of the output, cut after the max size requested
+    pub content: String,
+    pub size_in_bytes: u64,
+}
+
+/// Reads at most `max_size_in_bytes` of the job output, the rest of the file is never sent by the pod,
+/// so an oversized output can't exhaust the memory of the engine.
 pub fn kubectl_get_job_pod_output<P>(
     kubernetes_config: P,
     envs: Vec<(&str, &str)>,
     namespace: &str,
     pod_name: &str,
-) -> Result<String, CommandError>
+    max_size_in_bytes: u64,
+) -> Result<JobPodOutput, CommandError>
 where
     P: AsRef<Path>,
 {
-    let cmd_args = vec![
-        "exec",
-        "-n",
-        namespace,
-        pod_name,
-        "-c",
-        "qovery-wait-container-output",
-        "--",
-        "cat",
-        "/qovery-output/qovery-output.json",
-    ];
-    kubectl_exec_raw_output(cmd_args, kubernetes_config, envs, false)
+    let size = kubectl_exec_raw_output(
+        vec![
+            "exec",
+            "-n",
+            namespace,
+            pod_name,
+            "-c",
+            JOB_OUTPUT_CONTAINER_NAME,
+            "--",
+            "stat",
+            "-c",
+            "%s",
+            JOB_OUTPUT_FILE_PATH,
+        ],
+        kubernetes_config.as_ref(),
+        envs.clone(),
+        false,
+    )?;
+    let size_in_bytes = size.trim().parse::<u64>().map_err(|err| {
+        CommandError::new(
+            format!("Cannot get the size of the job output `{JOB_OUTPUT_FILE_PATH}`"),
+            Some(format!("{err}: {size}")),
+            None,
+        )
+    })?;
+
+    let max_size_in_bytes = max_size_in_bytes.to_string();
+    let content = kubectl_exec_raw_output(
+        vec![
+            "exec",
+            "-n",
+            namespace,
+            pod_name,
+            "-c",
+            JOB_OUTPUT_CONTAINER_NAME,
+            "--",
+            "head",
+            "-c",
+            max_size_in_bytes.as_str(),
+            JOB_OUTPUT_FILE_PATH,
+        ],
+        kubernetes_config,
+        envs,
+        true,
+    )?;
+
+    Ok(JobPodOutput { content, size_in_bytes })
 }
 
 pub fn kubectl_get_secret(kube_client: Client, fields_selector: &str) -> Result<Vec<Secret>, CommandError> {
@@ -1,11 +1,18 @@
 pub mod command;
 pub mod docker;
+pub mod docker_push_errors;
+#[cfg(feature = "failure-injection")]
+pub mod failure_injection;
 pub mod git;
 pub mod git_lfs;
 pub mod helm;
+pub mod helm_manifest;
+pub mod helm_post_renderer;
+pub mod helm_uninstall;
 pub mod helm_utils;
 pub mod kubectl;
 pub mod skopeo;
 pub mod structs;
+pub mod syft;
 pub mod terraform;
 pub mod terraform_validators;
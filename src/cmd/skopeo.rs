@@ -204,9 +204,11 @@ where
 
     match ret {
         Ok(_) => Ok(()),
-        Err(CommandError::TimeoutError(msg)) => Err(SkopeoError::Timeout { raw_error_message: msg }),
+        Err(CommandError::TimeoutError(msg) | CommandError::IdleTimeoutError(msg)) => {
+            Err(SkopeoError::Timeout { raw_error_message: msg })
+        }
         Err(CommandError::Killed(msg)) => Err(SkopeoError::Aborted { raw_error_message: msg }),
-        Err(CommandError::ExitStatusError(err)) => Err(SkopeoError::ExitStatusError { exit_status: err }),
+        Err(CommandError::ExitStatusError(err, _)) => Err(SkopeoError::ExitStatusError { exit_status: err }),
         Err(CommandError::ExecutionError(err)) => Err(SkopeoError::ExecutionError { raw_error: err }),
     }
 }
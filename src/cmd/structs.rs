@@ -601,6 +601,21 @@ pub struct MetricsServer {
     pub name: String,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KubernetesApiCustomMetric {
+    pub kind: String,
+    pub items: Vec<KubernetesApiCustomMetricItem>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KubernetesApiCustomMetricItem {
+    pub metric_name: String,
+    /// Kubernetes quantity, i.e: `2` or `1500m`
+    pub value: String,
+}
+
 #[derive(Deserialize, Clone, Eq, PartialEq, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct KubernetesDeployment {
@@ -0,0 +1,100 @@
+use crate::cmd::command::{does_binary_exist, CommandError, CommandKiller, ExecutableCommand, QoveryCommand};
+use crate::cmd::docker::ContainerImage;
+use std::path::Path;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+// syft is optional, engines without it don't generate SBOMs
+const SYFT_BINARY: &str = "syft";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SyftError {
+    #[error("Syft binary is not available")]
+    NotInstalled,
+
+    #[error("Syft terminated with a non success exit status code: {exit_status:?}")]
+    ExitStatusError { exit_status: ExitStatus },
+
+    #[error("Syft terminated with an unknown error: {raw_error:?}")]
+    ExecutionError { raw_error: std::io::Error },
+
+    #[error("Syft aborted due to user cancel request: {raw_error_message:?}")]
+    Aborted { raw_error_message: String },
+
+    #[error("Syft command terminated due to timeout: {raw_error_message:?}")]
+    Timeout { raw_error_message: String },
+}
+
+pub fn is_syft_available() -> bool {
+    does_binary_exist(SYFT_BINARY)
+}
+
+/// Scans the image directly from its registry and writes its SPDX JSON SBOM into `output`.
+/// `docker_config` is the directory of the docker config holding the registry credentials.
+pub fn generate_spdx_sbom(
+    image: &ContainerImage,
+    docker_config: &Path,
+    output: &Path,
+    cmd_killer: &CommandKiller,
+) -> Result<(), SyftError> {
+    if !is_syft_available() {
+        return Err(SyftError::NotInstalled);
+    }
+
+    info!("Generating SBOM of image {}", image.image_name());
+    let args = sbom_args(image, output);
+    let mut cmd = QoveryCommand::new(
+        SYFT_BINARY,
+        &args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>(),
+        &[("DOCKER_CONFIG", docker_config.to_str().unwrap_or_default())],
+    );
+    cmd.set_kill_grace_period(Duration::from_secs(0));
+    let ret = cmd.exec_with_abort(&mut |line| info!("{}", line), &mut |line| info!("{}", line), cmd_killer);
+
+    match ret {
+        Ok(_) => Ok(()),
+        Err(CommandError::TimeoutError(msg) | CommandError::IdleTimeoutError(msg)) => {
+            Err(SyftError::Timeout { raw_error_message: msg })
+        }
+        Err(CommandError::Killed(msg)) => Err(SyftError::Aborted { raw_error_message: msg }),
+        Err(CommandError::ExitStatusError(err, _)) => Err(SyftError::ExitStatusError { exit_status: err }),
+        Err(CommandError::ExecutionError(err)) => Err(SyftError::ExecutionError { raw_error: err }),
+    }
+}
+
+fn sbom_args(image: &ContainerImage, output: &Path) -> Vec<String> {
+    vec![
+        "scan".to_string(),
+        // pull the image from the registry, it is not loaded in any local docker daemon
+        format!("registry:{}", image.image_name()),
+        "--output".to_string(),
+        format!("spdx-json={}", output.to_str().unwrap_or_default()),
+        "--quiet".to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    #[test]
+    fn test_sbom_args() {
+        let image = ContainerImage::new(
+            Url::parse("https://123.dkr.ecr.eu-west-3.amazonaws.com").unwrap(),
+            "my-app".to_string(),
+            vec!["v1".to_string()],
+        );
+
+        assert_eq!(
+            sbom_args(&image, Path::new("/tmp/sbom/my-app.spdx.json")),
+            vec![
+                "scan",
+                "registry:123.dkr.ecr.eu-west-3.amazonaws.com/my-app:v1",
+                "--output",
+                "spdx-json=/tmp/sbom/my-app.spdx.json",
+                "--quiet",
+            ]
+        );
+    }
+}
@@ -3,17 +3,28 @@ use dirs::home_dir;
 use retry::delay::Fixed;
 use retry::OperationResult;
 
-use crate::cmd::command::{ExecutableCommand, QoveryCommand};
+use crate::cmd::command::{CommandError, CommandKiller, CommandOutputLimits, ExecutableCommand, QoveryCommand};
 use crate::cmd::terraform_validators::{TerraformValidationError, TerraformValidators};
 use crate::constants::TF_PLUGIN_CACHE_DIR;
 use crate::events::{EngineEvent, EventDetails, EventMessage};
 use crate::logger::Logger;
+use once_cell::sync::Lazy;
 use rand::Rng;
 use regex::Regex;
 use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use std::fmt::{Display, Formatter};
 use std::{env, fs, thread, time};
 
+// terraform reports the resources it is still waiting for every 10 seconds, a silent one is stuck
+const TERRAFORM_IDLE_TIMEOUT: time::Duration = time::Duration::from_secs(20 * 60);
+// the json of a plan is printed on a single line, it must not be truncated
+const TERRAFORM_PLAN_JSON_OUTPUT_LIMITS: CommandOutputLimits = CommandOutputLimits {
+    head_bytes: 1024 * 1024,
+    tail_bytes: 1024 * 1024,
+    max_line_bytes: 256 * 1024 * 1024,
+};
+
 #[derive(Debug, Default, PartialEq, Eq)]
 pub struct TerraformOutput {
     pub raw_error_output: Vec<String>,
@@ -34,6 +45,114 @@ impl TerraformOutput {
     }
 }
 
+/// Resource types whose replacement re-creates the Kubernetes cluster or loses the data of a managed database
+const REPLACEMENT_PROTECTED_RESOURCE_TYPES: [&str; 7] = [
+    "aws_eks_cluster",
+    "aws_db_instance",
+    "aws_rds_cluster",
+    "aws_docdb_cluster",
+    "aws_docdb_cluster_instance",
+    "aws_elasticache_cluster",
+    "aws_elasticache_replication_group",
+];
+
+/// Addresses of the resources changed by a plan, grouped by action.
+/// A replaced resource (destroyed then created, or the opposite) is only listed in `replaced`.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct PlanSummary {
+    pub to_create: Vec<String>,
+    pub to_update: Vec<String>,
+    pub to_destroy: Vec<String>,
+    pub replaced: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PlanJson {
+    #[serde(default)]
+    resource_changes: Vec<PlanResourceChangeJson>,
+}
+
+#[derive(Deserialize)]
+struct PlanResourceChangeJson {
+    address: String,
+    change: PlanChangeJson,
+}
+
+#[derive(Deserialize)]
+struct PlanChangeJson {
+    actions: Vec<String>,
+}
+
+impl PlanSummary {
+    /// Parses the output of `terraform show -json <plan file>`
+    pub fn from_plan_json(plan_json: &str) -> Result<PlanSummary, TerraformError> {
+        let plan: PlanJson =
+            serde_json::from_str(plan_json).map_err(|e| TerraformError::OutputCannotBeDeserialized {
+                raw_message: e.to_string(),
+            })?;
+
+        let mut summary = PlanSummary::default();
+        for resource_change in plan.resource_changes {
+            let actions: Vec<&str> = resource_change.change.actions.iter().map(String::as_str).collect();
+            match actions.as_slice() {
+                ["create"] => summary.to_create.push(resource_change.address),
+                ["update"] => summary.to_update.push(resource_change.address),
+                ["delete"] => summary.to_destroy.push(resource_change.address),
+                ["delete", "create"] | ["create", "delete"] => summary.replaced.push(resource_change.address),
+                // no-op and data sources reads
+                _ => {}
+            }
+        }
+
+        Ok(summary)
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !(self.to_create.is_empty()
+            && self.to_update.is_empty()
+            && self.to_destroy.is_empty()
+            && self.replaced.is_empty())
+    }
+
+    /// Replaced resources re-creating the Kubernetes cluster or a managed database
+    pub fn protected_replacements(&self) -> Vec<&str> {
+        self.replaced
+            .iter()
+            .filter(|address| {
+                address
+                    .split('.')
+                    .any(|segment| REPLACEMENT_PROTECTED_RESOURCE_TYPES.contains(&segment))
+            })
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+impl Display for PlanSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Plan: {} to create, {} to update, {} to destroy, {} to replace",
+            self.to_create.len(),
+            self.to_update.len(),
+            self.to_destroy.len(),
+            self.replaced.len()
+        )?;
+        for (symbol, addresses) in [
+            ("+", &self.to_create),
+            ("~", &self.to_update),
+            ("-", &self.to_destroy),
+            ("-/+", &self.replaced),
+        ] {
+            for address in addresses {
+                write!(f, "\n  {symbol} {address}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 bitflags! {
     /// Using a bitwise operator here allows to combine actions
     struct TerraformAction: u32 {
@@ -88,6 +207,12 @@ pub enum TerraformError {
         /// raw_message: raw Terraform error message with all details.
         raw_message: String,
     },
+    ApiThrottling {
+        /// service: cloud provider API rate limiting the requests, e.g. `EKS`.
+        service: String,
+        /// raw_message: raw Terraform error message with all details.
+        raw_message: String,
+    },
     NotEnoughPermissions {
         resource_type_and_name: String,
         action: Option<String>,
@@ -178,6 +303,11 @@ pub enum TerraformError {
         /// raw_message: raw Terraform error message with all details.
         raw_message: String,
     },
+    /// Terraform has been interrupted because the task has been canceled
+    Aborted {
+        /// raw_message: raw Terraform error message with all details.
+        raw_message: String,
+    },
     StateLocked {
         lock_id: String,
         /// raw_message: raw Terraform error message with all details.
@@ -212,6 +342,11 @@ pub enum TerraformError {
         /// raw_message: raw serde error.
         raw_message: String,
     },
+    InvalidTargetAddress {
+        target: String,
+        /// raw_message: why the address is rejected.
+        raw_message: String,
+    },
 }
 
 impl TerraformError {
@@ -734,6 +869,33 @@ impl TerraformError {
             }
         }
 
+        // Cloud provider API throttling, checked last as any other error would still be there once retried
+        // AWS
+        if let Ok(aws_throttling_re) = Regex::new(
+            r"(Throttling|ThrottlingException|RequestLimitExceeded|TooManyRequestsException): (Rate exceeded|Request limit exceeded)",
+        ) {
+            if aws_throttling_re.is_match(raw_terraform_error_output.as_str()) {
+                // AWS SDK v2 errors name the service (`operation error EKS: DescribeCluster`), v1 ones only the resource
+                let service = Regex::new(r"operation error (?P<service>[\w\s]+?):")
+                    .ok()
+                    .and_then(|re| re.captures(raw_terraform_error_output.as_str()))
+                    .or_else(|| {
+                        Regex::new(
+                            r"(creating|reading|updating|deleting|describing|listing) (?P<service>[A-Z][A-Za-z0-9]+)",
+                        )
+                        .ok()
+                        .and_then(|re| re.captures(raw_terraform_error_output.as_str()))
+                    })
+                    .and_then(|cap| cap.name("service").map(|e| e.as_str().to_string()))
+                    .unwrap_or_else(|| "AWS".to_string());
+
+                return TerraformError::ApiThrottling {
+                    service,
+                    raw_message: raw_terraform_error_output,
+                };
+            }
+        }
+
         // This kind of error should be triggered as little as possible, ideally, there is no unknown errors
         // (un-caught) so we can act / report properly to the user.
         TerraformError::Unknown {
@@ -742,6 +904,10 @@ impl TerraformError {
         }
     }
 
+    pub fn is_aborted(&self) -> bool {
+        matches!(self, TerraformError::Aborted { .. })
+    }
+
     /// Returns safe Terraform error message part (not full error message).
     pub fn to_safe_message(&self) -> String {
         match self {
@@ -750,6 +916,7 @@ impl TerraformError {
                 terraform_args.join(" "),
             ),
             TerraformError::MultipleInterruptsReceived { .. } => "Multiple interrupts received, stopping immediately.".to_string(),
+            TerraformError::Aborted { .. } => "Terraform has been interrupted because the task has been canceled.".to_string(),
             TerraformError::AccountBlockedByProvider { .. } => "Your account has been blocked by cloud provider.".to_string(),
             TerraformError::InvalidCredentials { .. } => "Invalid credentials.".to_string(),
             TerraformError::NotEnoughPermissions {
@@ -818,6 +985,9 @@ impl TerraformError {
             TerraformError::ServiceNotActivatedOptInRequired { service_type, .. } => {
                 format!("Error, service `{service_type}` requiring an opt-in is not activated.",)
             }
+            TerraformError::ApiThrottling { service, .. } => {
+                format!("Error, cloud provider `{service}` API rate limit exceeded.")
+            }
             TerraformError::AlreadyExistingResource { resource_type, resource_name, .. } => {
                 match resource_name {
                     Some(name) => format!("Error, resource type `{resource_type}` with name `{name}` already exists."),
@@ -876,6 +1046,7 @@ impl TerraformError {
                 format!("Error, validator `{validator_name}` ({validation_description}) has raised an error: {raw_message}")
             }
             TerraformError::OutputCannotBeDeserialized { .. } => "Error, cannot deserialize Terraform output. Check the logs for more details.".to_string(),
+            TerraformError::InvalidTargetAddress { target, .. } => format!("Error, `{target}` is not a valid Terraform resource address to target."),
         }
     }
 }
@@ -889,6 +1060,9 @@ impl Display for TerraformError {
             TerraformError::MultipleInterruptsReceived { raw_message, .. } => {
                 format!("{}, here is the error:\n{}", self.to_safe_message(), raw_message)
             }
+            TerraformError::Aborted { raw_message } => {
+                format!("{}, here is the error:\n{}", self.to_safe_message(), raw_message)
+            }
             TerraformError::AccountBlockedByProvider { raw_message, .. } => {
                 format!("{}, here is the error:\n{}", self.to_safe_message(), raw_message)
             }
@@ -919,6 +1093,9 @@ impl Display for TerraformError {
             TerraformError::ServiceNotActivatedOptInRequired { raw_message, .. } => {
                 format!("{}\n{}", self.to_safe_message(), raw_message)
             }
+            TerraformError::ApiThrottling { raw_message, .. } => {
+                format!("{}\n{}", self.to_safe_message(), raw_message)
+            }
             TerraformError::AlreadyExistingResource { raw_message, .. } => {
                 format!("{}\n{}", self.to_safe_message(), raw_message)
             }
@@ -961,6 +1138,9 @@ impl Display for TerraformError {
             TerraformError::OutputCannotBeDeserialized { raw_message, .. } => {
                 format!("{}\n{}", self.to_safe_message(), raw_message)
             }
+            TerraformError::InvalidTargetAddress { raw_message, .. } => {
+                format!("{}\n{}", self.to_safe_message(), raw_message)
+            }
         };
 
         f.write_str(&message)
@@ -1021,11 +1201,11 @@ fn terraform_plugins_failed_load(
             });
         };
         thread::sleep(sleep_time);
-        return terraform_init(root_dir, &[], validators);
+        return terraform_init(root_dir, &[], validators, &CommandKiller::never());
     }
 
     if error_string.contains("Plugin reinitialization required") {
-        return terraform_init(root_dir, &[], validators);
+        return terraform_init(root_dir, &[], validators, &CommandKiller::never());
     }
 
     Ok(output)
@@ -1067,6 +1247,7 @@ fn terraform_init(
     root_dir: &str,
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     // issue with provider lock since 0.14 and CI, need to manage terraform lock
     let terraform_provider_lock = format!("{}/.terraform.lock.hcl", &root_dir);
@@ -1082,8 +1263,9 @@ fn terraform_init(
 
     let result = retry::retry(Fixed::from_millis(3000).take(5), || {
         // terraform init
-        match terraform_exec(root_dir, terraform_providers_lock_args.clone(), envs, validators) {
+        match terraform_exec(root_dir, terraform_providers_lock_args.clone(), envs, validators, abort) {
             Ok(output) => OperationResult::Ok(output),
+            Err(err) if err.is_aborted() => OperationResult::Err(err),
             Err(err) => OperationResult::Retry(err),
         }
     });
@@ -1096,8 +1278,9 @@ fn terraform_init(
     let terraform_args = vec!["init", "-no-color"];
     let result = retry::retry(Fixed::from_millis(3000).take(5), || {
         // terraform init
-        match terraform_exec(root_dir, terraform_args.clone(), envs, validators) {
+        match terraform_exec(root_dir, terraform_args.clone(), envs, validators, abort) {
             Ok(output) => OperationResult::Ok(output),
+            Err(err) if err.is_aborted() => OperationResult::Err(err),
             Err(err) => {
                 let _ = manage_common_issues(root_dir, &terraform_provider_lock, &err, validators);
                 // Error while trying to run terraform init, retrying...
@@ -1116,6 +1299,7 @@ fn terraform_validate(
     root_dir: &str,
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     let terraform_args = vec!["validate", "-no-color"];
     let terraform_provider_lock = format!("{}/.terraform.lock.hcl", &root_dir);
@@ -1123,8 +1307,9 @@ fn terraform_validate(
     // Retry is not needed, fixing it to 1 only for the time being
     let result = retry::retry(Fixed::from_millis(3000).take(1), || {
         // validate config
-        match terraform_exec(root_dir, terraform_args.clone(), envs, validators) {
+        match terraform_exec(root_dir, terraform_args.clone(), envs, validators, abort) {
             Ok(output) => OperationResult::Ok(output),
+            Err(err) if err.is_aborted() => OperationResult::Err(err),
             Err(err) => {
                 let _ = manage_common_issues(root_dir, &terraform_provider_lock, &err, validators);
                 // error while trying to Terraform validate on the rendered templates
@@ -1139,13 +1324,18 @@ fn terraform_validate(
     }
 }
 
-fn terraform_output_internal(root_dir: &str, envs: &[(&str, &str)]) -> Result<TerraformOutput, TerraformError> {
+fn terraform_output_internal(
+    root_dir: &str,
+    envs: &[(&str, &str)],
+    abort: &CommandKiller,
+) -> Result<TerraformOutput, TerraformError> {
     let terraform_args = vec!["output", "-json"];
 
     // Retry is not needed, fixing it to 1 only for the time being
     let result = retry::retry(Fixed::from_millis(3000).take(1), || {
-        match terraform_exec(root_dir, terraform_args.clone(), envs, &TerraformValidators::None) {
+        match terraform_exec(root_dir, terraform_args.clone(), envs, &TerraformValidators::None, abort) {
             Ok(output) => OperationResult::Ok(output),
+            Err(err) if err.is_aborted() => OperationResult::Err(err),
             Err(err) => OperationResult::Retry(err),
         }
     });
@@ -1160,12 +1350,14 @@ pub fn terraform_state_list(
     root_dir: &str,
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     // get terraform state list output
     let terraform_args = vec!["state", "list"];
     let result = retry::retry(Fixed::from_millis(3000).take(5), || {
-        match terraform_exec(root_dir, terraform_args.clone(), envs, validators) {
+        match terraform_exec(root_dir, terraform_args.clone(), envs, validators, abort) {
             Ok(out) => OperationResult::Ok(out),
+            Err(err) if err.is_aborted() => OperationResult::Err(err),
             Err(err) => {
                 // Error while trying to run terraform state list, retrying...
                 OperationResult::Retry(err)
@@ -1184,6 +1376,7 @@ pub fn terraform_plan_internal(
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
     is_destroy: bool,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     // plan
     let terraform_args = if is_destroy {
@@ -1191,24 +1384,26 @@ pub fn terraform_plan_internal(
     } else {
         vec!["plan", "-out", "-no-color", "tf_plan"]
     };
-    terraform_exec(root_dir, terraform_args, envs, validators)
+    terraform_exec(root_dir, terraform_args, envs, validators, abort)
 }
 
 fn terraform_apply_internal(
     root_dir: &str,
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     let terraform_args = vec!["apply", "-lock=false", "-no-color", "-auto-approve", "tf_plan"];
     let result = retry::retry(Fixed::from_millis(3000).take(1), || {
         // terraform apply
-        match terraform_exec(root_dir, terraform_args.clone(), envs, validators) {
+        match terraform_exec(root_dir, terraform_args.clone(), envs, validators, abort) {
             Ok(out) => OperationResult::Ok(out),
+            Err(err) if err.is_aborted() => OperationResult::Err(err),
             Err(err) => {
                 let _ = manage_common_issues(root_dir, "", &err, validators);
 
                 // We have to re-do a plan to update the tf_plan file state
-                let _ = match terraform_plan_internal(root_dir, envs, validators, false) {
+                let _ = match terraform_plan_internal(root_dir, envs, validators, false, abort) {
                     Ok(plan) => plan,
                     Err(err) => return OperationResult::Retry(err),
                 };
@@ -1230,6 +1425,7 @@ pub fn terraform_apply_with_tf_workers_resources(
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
     is_dry_run: bool,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     let mut terraform_args_string = vec![
         "apply".to_string(),
@@ -1242,8 +1438,9 @@ pub fn terraform_apply_with_tf_workers_resources(
 
     let result = retry::retry(Fixed::from_millis(3000).take(1), || {
         // terraform plan first
-        let plan = match terraform_plan_internal(root_dir, envs, validators, false) {
+        let plan = match terraform_plan_internal(root_dir, envs, validators, false, abort) {
             Ok(plan) => plan,
+            Err(err) if err.is_aborted() => return OperationResult::Err(err),
             Err(err) => return OperationResult::Retry(err),
         };
 
@@ -1257,8 +1454,10 @@ pub fn terraform_apply_with_tf_workers_resources(
             terraform_args_string.iter().map(|e| e.as_str()).collect(),
             envs,
             validators,
+            abort,
         ) {
             Ok(out) => OperationResult::Ok(out),
+            Err(err) if err.is_aborted() => OperationResult::Err(err),
             Err(err) => {
                 // Error while trying to run terraform apply on rendered templates, retrying...
                 OperationResult::Retry(err)
@@ -1272,12 +1471,167 @@ pub fn terraform_apply_with_tf_workers_resources(
     }
 }
 
+/// Summary of the plan saved by the last `terraform plan`.
+/// Its json is not logged, it holds the sensitive values of the resources.
+pub fn terraform_plan_summary(
+    root_dir: &str,
+    envs: &[(&str, &str)],
+    abort: &CommandKiller,
+) -> Result<PlanSummary, TerraformError> {
+    let mut cmd = QoveryCommand::new("terraform", &["show", "-json", "-no-color", "tf_plan"], envs);
+    cmd.set_current_dir(root_dir);
+    cmd.set_output_limits(TERRAFORM_PLAN_JSON_OUTPUT_LIMITS);
+
+    let mut plan_json = String::new();
+    let mut raw_error_output = vec![];
+    let result = cmd.exec_with_abort(
+        &mut |line| plan_json.push_str(&line),
+        &mut |line| raw_error_output.push(line),
+        abort,
+    );
+    check_silent_command_result(&cmd, result, raw_error_output)?;
+
+    PlanSummary::from_plan_json(&plan_json)
+}
+
+/// Current state of the root module, empty when nothing has been applied yet.
+/// It holds sensitive values, so it is not logged.
+pub fn terraform_state_pull(
+    root_dir: &str,
+    envs: &[(&str, &str)],
+    abort: &CommandKiller,
+) -> Result<String, TerraformError> {
+    let mut cmd = QoveryCommand::new("terraform", &["state", "pull"], envs);
+    cmd.set_current_dir(root_dir);
+
+    let mut state = vec![];
+    let mut raw_error_output = vec![];
+    let result = cmd.exec_with_abort(&mut |line| state.push(line), &mut |line| raw_error_output.push(line), abort);
+    check_silent_command_result(&cmd, result, raw_error_output)?;
+
+    Ok(state.join("\n"))
+}
+
+// for the commands whose output is not logged, only their errors are kept
+fn check_silent_command_result(
+    cmd: &QoveryCommand,
+    result: Result<(), CommandError>,
+    raw_error_output: Vec<String>,
+) -> Result<(), TerraformError> {
+    match result {
+        Ok(_) => Ok(()),
+        Err(CommandError::Killed(msg)) => Err(TerraformError::Aborted { raw_message: msg }),
+        Err(_) => Err(TerraformError::new(cmd.get_args(), "".to_string(), raw_error_output.join("\n"))),
+    }
+}
+
+// a resource address, optionally in (nested) modules, i.e: `module.eks["a"].aws_eks_node_group.workers[0]`,
+// or a whole module. Anything else, like a flag, is rejected before reaching terraform.
+static TERRAFORM_TARGET_ADDRESS: Lazy<Regex> = Lazy::new(|| {
+    let name = r"[A-Za-z_][A-Za-z0-9_-]*";
+    let index = r#"(\[(\d+|"[^"\s]+")\])?"#;
+    Regex::new(&format!(
+        r"^(module\.{name}{index}\.)*(module\.{name}{index}|(data\.)?{name}\.{name}{index})$"
+    ))
+    .expect("invalid terraform target address regex")
+});
+
+fn validate_target_address(target: &str) -> Result<(), TerraformError> {
+    if TERRAFORM_TARGET_ADDRESS.is_match(target) {
+        return Ok(());
+    }
+
+    Err(TerraformError::InvalidTargetAddress {
+        target: target.to_string(),
+        raw_message: "a target must be a resource address like `aws_eks_node_group.workers` or `module.name`"
+            .to_string(),
+    })
+}
+
+fn terraform_targeted_plan_args(targets: &[String]) -> Result<Vec<String>, TerraformError> {
+    if targets.is_empty() {
+        return Err(TerraformError::InvalidTargetAddress {
+            target: "".to_string(),
+            raw_message: "at least one target is required, terraform would apply every resource otherwise".to_string(),
+        });
+    }
+
+    let mut terraform_args = vec![
+        "plan".to_string(),
+        "-no-color".to_string(),
+        "-out".to_string(),
+        "tf_plan".to_string(),
+    ];
+    for target in targets {
+        validate_target_address(target)?;
+        terraform_args.push(format!("-target={target}"));
+    }
+
+    Ok(terraform_args)
+}
+
+/// Plans and applies only the targeted resources, and the ones they depend on.
+/// It is meant to repair a few resources, i.e: a node group, without waiting for a full apply.
+/// Errors are the same as a full apply's, targets are validated before running terraform.
+pub fn terraform_apply_with_targets(
+    root_dir: &str,
+    targets: &[String],
+    envs: &[(&str, &str)],
+    validators: &TerraformValidators,
+    is_dry_run: bool,
+    abort: &CommandKiller,
+) -> Result<TerraformOutput, TerraformError> {
+    let plan_args = terraform_targeted_plan_args(targets)?;
+
+    let result = retry::retry(Fixed::from_millis(3000).take(1), || {
+        // the plan is restricted to the targets, applying it applies only them
+        let mut output = match terraform_exec(
+            root_dir,
+            plan_args.iter().map(|e| e.as_str()).collect(),
+            envs,
+            validators,
+            abort,
+        ) {
+            Ok(plan) => plan,
+            Err(err) if err.is_aborted() => return OperationResult::Err(err),
+            Err(err) => return OperationResult::Retry(err),
+        };
+
+        if is_dry_run {
+            return OperationResult::Ok(output);
+        }
+
+        match terraform_exec(
+            root_dir,
+            vec!["apply", "-lock=false", "-no-color", "-auto-approve", "tf_plan"],
+            envs,
+            validators,
+            abort,
+        ) {
+            Ok(apply) => {
+                output.extend(apply);
+                OperationResult::Ok(output)
+            }
+            Err(err) if err.is_aborted() => OperationResult::Err(err),
+            Err(err) => {
+                let _ = manage_common_issues(root_dir, "", &err, validators);
+                OperationResult::Retry(err)
+            }
+        }
+    });
+
+    match result {
+        Ok(output) => Ok(output),
+        Err(retry::Error { error, .. }) => Err(error),
+    }
+}
+
 pub fn terraform_state_rm_entry(
     root_dir: &str,
     entry: &str,
     validators: &TerraformValidators,
 ) -> Result<TerraformOutput, TerraformError> {
-    match terraform_exec(root_dir, vec!["state", "rm", entry], &[], validators) {
+    match terraform_exec(root_dir, vec!["state", "rm", entry], &[], validators, &CommandKiller::never()) {
         Ok(out) => Ok(out),
         Err(err) => {
             // Error while trying to run terraform state rm entry, retrying...
@@ -1293,13 +1647,15 @@ pub fn terraform_destroy(
     root_dir: &str,
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     // terraform destroy
     let terraform_args = vec!["destroy", "-lock=false", "-no-color", "-auto-approve"];
     let result = retry::retry(Fixed::from_millis(3000).take(1), || {
         // terraform destroy
-        match terraform_exec(root_dir, terraform_args.clone(), envs, validators) {
+        match terraform_exec(root_dir, terraform_args.clone(), envs, validators, abort) {
             Ok(out) => OperationResult::Ok(out),
+            Err(err) if err.is_aborted() => OperationResult::Err(err),
             Err(err) => {
                 // Error while trying to run terraform destroy on rendered templates, retrying...
                 OperationResult::Retry(err)
@@ -1323,7 +1679,7 @@ pub fn terraform_import(
     let terraform_args = vec!["import", resource, resource_identifier];
 
     // terraform import
-    match terraform_exec(root_dir, terraform_args.clone(), envs, validators) {
+    match terraform_exec(root_dir, terraform_args.clone(), envs, validators, &CommandKiller::never()) {
         Ok(output) => Ok(output),
         Err(err) => Err(TerraformError::CannotImportResource {
             resource_type: resource.to_string(),
@@ -1367,7 +1723,7 @@ pub fn terraform_remove_resource_from_tf_state(
 
     let result = retry::retry(Fixed::from_millis(3000).take(1), || {
         // terraform destroy a specific resource
-        match terraform_exec(root_dir, terraform_args.clone(), &[], validators) {
+        match terraform_exec(root_dir, terraform_args.clone(), &[], validators, &CommandKiller::never()) {
             Ok(output) => OperationResult::Ok(output),
             Err(err) => {
                 // Error while trying to run terraform init, retrying...
@@ -1388,19 +1744,20 @@ fn terraform_run(
     dry_run: bool,
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     let mut output = TerraformOutput::default();
 
     if actions.contains(TerraformAction::INIT) {
-        output.extend(terraform_init(root_dir, envs, validators)?);
+        output.extend(terraform_init(root_dir, envs, validators, abort)?);
     }
 
     if actions.contains(TerraformAction::VALIDATE) {
-        output.extend(terraform_validate(root_dir, envs, validators)?);
+        output.extend(terraform_validate(root_dir, envs, validators, abort)?);
     }
 
     if actions.contains(TerraformAction::STATE_LIST) {
-        output.extend(terraform_state_list(root_dir, envs, validators)?);
+        output.extend(terraform_state_list(root_dir, envs, validators, abort)?);
     }
 
     if actions.contains(TerraformAction::PLAN) {
@@ -1409,54 +1766,41 @@ fn terraform_run(
             envs,
             validators,
             actions.contains(TerraformAction::DESTROY),
+            abort,
         )?);
     }
 
     if actions.contains(TerraformAction::APPLY) && !dry_run {
-        output.extend(terraform_apply_internal(root_dir, envs, validators)?);
+        output.extend(terraform_apply_internal(root_dir, envs, validators, abort)?);
     }
 
     if actions.contains(TerraformAction::OUTPUT) {
-        output.extend(terraform_output_internal(root_dir, envs)?);
+        output.extend(terraform_output_internal(root_dir, envs, abort)?);
     }
 
     if actions.contains(TerraformAction::DESTROY) && !dry_run {
-        output.extend(terraform_destroy(root_dir, envs, validators)?);
+        output.extend(terraform_destroy(root_dir, envs, validators, abort)?);
     }
 
     Ok(output)
 }
 
-pub fn terraform_init_validate_plan_apply(
-    root_dir: &str,
-    dry_run: bool,
-    envs: &[(&str, &str)],
-    validators: &TerraformValidators,
-) -> Result<TerraformOutput, TerraformError> {
-    // Terraform init, validate, plan and apply
-    terraform_run(
-        TerraformAction::INIT | TerraformAction::VALIDATE | TerraformAction::PLAN | TerraformAction::APPLY,
-        root_dir,
-        dry_run,
-        envs,
-        validators,
-    )
-}
-
 pub fn terraform_apply(
     root_dir: &str,
     dry_run: bool,
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     // Terraform init, validate, plan and apply
-    terraform_run(TerraformAction::APPLY, root_dir, dry_run, envs, validators)
+    terraform_run(TerraformAction::APPLY, root_dir, dry_run, envs, validators, abort)
 }
 
 pub fn terraform_plan(
     root_dir: &str,
     envs: &[(&str, &str)],
     is_destroy: bool,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     // Terraform init, validate, plan and appl
     let validators = if is_destroy {
@@ -1465,12 +1809,23 @@ pub fn terraform_plan(
         TerraformValidators::Default
     };
 
-    terraform_plan_internal(root_dir, envs, &validators, is_destroy)
+    terraform_plan_internal(root_dir, envs, &validators, is_destroy, abort)
 }
 
-pub fn terraform_output<T: DeserializeOwned>(root_dir: &str, envs: &[(&str, &str)]) -> Result<T, TerraformError> {
+pub fn terraform_output<T: DeserializeOwned>(
+    root_dir: &str,
+    envs: &[(&str, &str)],
+    abort: &CommandKiller,
+) -> Result<T, TerraformError> {
     // Terraform output must call alone and after init, because we need to retrieve the json output from stdout
-    let output = terraform_run(TerraformAction::OUTPUT, root_dir, false, envs, &TerraformValidators::None)?;
+    let output = terraform_run(
+        TerraformAction::OUTPUT,
+        root_dir,
+        false,
+        envs,
+        &TerraformValidators::None,
+        abort,
+    )?;
     serde_json::from_str(&output.raw_std_output.join("\n")).map_err(|e| TerraformError::OutputCannotBeDeserialized {
         raw_message: e.to_string(),
     })
@@ -1480,6 +1835,7 @@ pub fn terraform_init_validate(
     root_dir: &str,
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     // Terraform init & validate
     terraform_run(
@@ -1488,6 +1844,7 @@ pub fn terraform_init_validate(
         false,
         envs,
         validators,
+        abort,
     )
 }
 
@@ -1495,6 +1852,7 @@ pub fn terraform_init_validate_destroy(
     root_dir: &str,
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     let terraform_actions_to_be_performed = TerraformAction::INIT | TerraformAction::VALIDATE | TerraformAction::PLAN;
 
@@ -1504,6 +1862,7 @@ pub fn terraform_init_validate_destroy(
         false,
         envs,
         validators,
+        abort,
     )
 }
 
@@ -1511,6 +1870,7 @@ pub fn terraform_init_validate_state_list(
     root_dir: &str,
     envs: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     // Terraform init, validate and statelist
     terraform_run(
@@ -1519,6 +1879,7 @@ pub fn terraform_init_validate_state_list(
         false,
         envs,
         validators,
+        abort,
     )
 }
 
@@ -1528,10 +1889,11 @@ pub fn terraform_init_validate_state_list(
 fn terraform_exec_from_command(
     cmd: &mut impl ExecutableCommand,
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     let mut terraform_output = TerraformOutput::default();
 
-    let result = cmd.exec_with_output(
+    let result = cmd.exec_with_abort(
         &mut |line| {
             info!("{}", line);
             terraform_output.raw_std_output.push(line);
@@ -1540,10 +1902,23 @@ fn terraform_exec_from_command(
             error!("{}", line);
             terraform_output.raw_error_output.push(line);
         },
+        abort,
     );
 
+    // terraform has been interrupted on our side, its output is not relevant to qualify the error
+    if let Err(CommandError::Killed(msg)) = &result {
+        terraform_output.raw_error_output.push(msg.to_string());
+        return Err(TerraformError::Aborted {
+            raw_message: terraform_output.raw_error_output.join("\n"),
+        });
+    }
+
     validators.validate(&terraform_output).map_err(TerraformError::from)?;
 
+    if let Err(err @ CommandError::IdleTimeoutError(_)) = &result {
+        terraform_output.raw_error_output.push(err.to_string());
+    }
+
     match result {
         Ok(_) => Ok(terraform_output),
         Err(_) => Err(TerraformError::new(
@@ -1560,6 +1935,7 @@ fn terraform_exec(
     args: Vec<&str>,
     env: &[(&str, &str)],
     validators: &TerraformValidators,
+    abort: &CommandKiller,
 ) -> Result<TerraformOutput, TerraformError> {
     // override if environment variable is set
     let tf_plugin_cache_dir_value = match env::var_os(TF_PLUGIN_CACHE_DIR) {
@@ -1577,19 +1953,26 @@ fn terraform_exec(
     envs.extend(env);
     let mut cmd = QoveryCommand::new("terraform", &args, &envs);
     cmd.set_current_dir(root_dir);
+    cmd.set_idle_timeout(TERRAFORM_IDLE_TIMEOUT);
 
-    terraform_exec_from_command(&mut cmd, validators)
+    terraform_exec_from_command(&mut cmd, validators, abort)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::cmd::command::{CommandError, CommandKiller, ExecutableCommand};
+    use crate::cmd::command::{CommandError, CommandKiller, ExecutableCommand, QoveryCommand};
     use crate::cmd::terraform::{
-        manage_common_issues, terraform_exec_from_command, terraform_init, terraform_init_validate, DatabaseError,
-        QuotaExceededError, TerraformError, TerraformOutput,
+        manage_common_issues, terraform_apply_with_targets, terraform_exec_from_command, terraform_init,
+        terraform_init_validate, terraform_targeted_plan_args, DatabaseError, PlanSummary, QuotaExceededError,
+        TerraformError, TerraformOutput,
     };
+    use crate::environment::models::abort::{AbortStatus, AtomicAbortStatus};
     use std::fs;
     use std::process::Child;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
 
     use crate::cmd::terraform_validators::{TerraformValidationError, TerraformValidator, TerraformValidators};
     use tracing::{span, Level};
@@ -1636,15 +2019,15 @@ mod tests {
 
         fn exec_with_abort<STDOUT, STDERR>(
             &mut self,
-            _stdout_output: &mut STDOUT,
-            _stderr_output: &mut STDERR,
+            stdout_output: &mut STDOUT,
+            stderr_output: &mut STDERR,
             _abort_notifier: &CommandKiller,
         ) -> Result<(), CommandError>
         where
             STDOUT: FnMut(String),
             STDERR: FnMut(String),
         {
-            todo!()
+            self.exec_with_output(stdout_output, stderr_output)
         }
     }
 
@@ -1682,7 +2065,10 @@ in the dependency lock file
             &could_not_load_plugin_error,
             &TerraformValidators::None,
         );
-        assert_eq!(result, terraform_init("", &[], &TerraformValidators::Default));
+        assert_eq!(
+            result,
+            terraform_init("", &[], &TerraformValidators::Default, &CommandKiller::never())
+        );
     }
 
     #[test]
@@ -1705,7 +2091,8 @@ in the dependency lock file
         };
 
         // execute:
-        let result = terraform_exec_from_command(qovery_cmd_mock, &TerraformValidators::Default);
+        let result =
+            terraform_exec_from_command(qovery_cmd_mock, &TerraformValidators::Default, &CommandKiller::never());
 
         // verify:
         assert_eq!(
@@ -1757,7 +2144,7 @@ terraform {
         let _ = fs::write(format!("{}/.terraform.lock.hcl", &dest_dir), terraform_lock_file);
         let _ = fs::write(format!("{}/providers.tf", &dest_dir), provider_file);
 
-        let res = terraform_init_validate(dest_dir, &[], &TerraformValidators::Default);
+        let res = terraform_init_validate(dest_dir, &[], &TerraformValidators::Default, &CommandKiller::never());
 
         assert!(res.is_ok());
     }
@@ -2317,6 +2704,115 @@ Error: creating Amazon S3 (Simple Storage) Bucket (qovery-logs-z0bb3e862): Bucke
         assert_eq!(TerraformError::AccountBlockedByProvider { raw_message }, result);
     }
 
+    #[test]
+    fn test_terraform_error_aws_api_throttling() {
+        // setup:
+        struct TestCase<'a> {
+            input_raw_error: &'a str,
+            expected_service: &'a str,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                input_raw_error: r#"Error: creating EC2 Subnet: operation error EC2: CreateSubnet, https response error StatusCode: 503, RequestID: 2b4f0c1e-5d7a-4b9e-9c61-3f1d2a8e7b40, api error RequestLimitExceeded: Request limit exceeded."#,
+                expected_service: "EC2",
+            },
+            TestCase {
+                input_raw_error: r#"Error: error reading EKS Cluster (qovery-z8e9a7b5c): ThrottlingException: Rate exceeded
+{
+  RespMetadata: {
+    StatusCode: 429,
+    RequestID: "6c0a4d5e-91f2-4c37-b8a1-0e5d7f3b2c19"
+  },
+  Message_: "Rate exceeded"
+}"#,
+                expected_service: "EKS",
+            },
+            TestCase {
+                input_raw_error: r#"Error: reading ELBv2 Load Balancer (arn:aws:elasticloadbalancing:eu-west-3:843237546537:loadbalancer/net/a8f3c2b1d0e94f7e/9b1d2c3e4f5a6b7c): operation error Elastic Load Balancing v2: DescribeLoadBalancers, https response error StatusCode: 400, RequestID: 0f4e2a6b-3c5d-4e7f-8a9b-1c2d3e4f5a6b, api error Throttling: Rate exceeded"#,
+                expected_service: "Elastic Load Balancing v2",
+            },
+            TestCase {
+                input_raw_error: r#"Error: error describing ELB (a8f3c2b1d0e94f7e9b1d2c3e4f5a6b7c): Throttling: Rate exceeded
+	status code: 400, request id: 7d8e9f0a-1b2c-4d3e-a5f6-0718293a4b5c"#,
+                expected_service: "ELB",
+            },
+        ];
+
+        for tc in test_cases {
+            // execute:
+            let result = TerraformError::new(vec!["apply".to_string()], "".to_string(), tc.input_raw_error.to_string());
+
+            // validate:
+            assert_eq!(
+                TerraformError::ApiThrottling {
+                    service: tc.expected_service.to_string(),
+                    raw_message: tc.input_raw_error.to_string(),
+                },
+                result
+            );
+            assert_eq!(
+                result.to_safe_message(),
+                format!("Error, cloud provider `{}` API rate limit exceeded.", tc.expected_service)
+            );
+        }
+    }
+
+    #[test]
+    fn test_plan_summary_from_plan_json() {
+        // execute:
+        let summary = PlanSummary::from_plan_json(include_str!("test_data/terraform_plan_eks_replacement.json"))
+            .expect("cannot parse plan");
+
+        // validate:
+        assert_eq!(
+            summary,
+            PlanSummary {
+                to_create: vec!["aws_subnet.eks_zone_a[0]".to_string()],
+                to_update: vec!["aws_eks_node_group.eks_cluster_workers_1".to_string()],
+                to_destroy: vec!["aws_security_group_rule.cluster_ingress_workstation_https".to_string()],
+                replaced: vec!["aws_eks_cluster.eks_cluster".to_string()],
+            }
+        );
+        assert!(summary.has_changes());
+        assert_eq!(summary.protected_replacements(), vec!["aws_eks_cluster.eks_cluster"]);
+        assert_eq!(
+            summary.to_string(),
+            "Plan: 1 to create, 1 to update, 1 to destroy, 1 to replace
+  + aws_subnet.eks_zone_a[0]
+  ~ aws_eks_node_group.eks_cluster_workers_1
+  - aws_security_group_rule.cluster_ingress_workstation_https
+  -/+ aws_eks_cluster.eks_cluster"
+        );
+    }
+
+    #[test]
+    fn test_plan_summary_protected_replacements() {
+        // managed database replaced, create before destroy replacements are replacements too
+        let summary =
+            PlanSummary::from_plan_json(include_str!("test_data/terraform_plan_database_replacement.json")).unwrap();
+        assert_eq!(
+            summary.replaced,
+            vec![
+                "aws_db_instance.postgresql_instance".to_string(),
+                "aws_db_parameter_group.postgresql_parameter_group".to_string()
+            ]
+        );
+        assert_eq!(summary.protected_replacements(), vec!["aws_db_instance.postgresql_instance"]);
+
+        // no changes
+        let summary = PlanSummary::from_plan_json(include_str!("test_data/terraform_plan_no_changes.json")).unwrap();
+        assert_eq!(summary, PlanSummary::default());
+        assert!(!summary.has_changes());
+        assert!(summary.protected_replacements().is_empty());
+
+        // not a plan
+        assert!(matches!(
+            PlanSummary::from_plan_json("Error: Failed to read the given file as a state or plan file"),
+            Err(TerraformError::OutputCannotBeDeserialized { .. })
+        ));
+    }
+
     #[test]
     fn test_terraform_error_state_lock() {
         // setup:
@@ -2463,11 +2959,171 @@ Error: updating EKS Cluster (qovery-z09a5408e) version: InvalidParameterExceptio
             });
 
             // execute:
-            let result =
-                terraform_exec_from_command(&mut DumbCommand {}, &TerraformValidators::Custom(vec![&validator_mock]));
+            let result = terraform_exec_from_command(
+                &mut DumbCommand {},
+                &TerraformValidators::Custom(vec![&validator_mock]),
+                &CommandKiller::never(),
+            );
 
             // validate:
             assert_eq!(validator_is_valid, result.is_ok());
         }
     }
+
+    /// Runs a fake terraform `script` and requests its cancellation once it has been running for a second
+    fn exec_and_cancel_fake_terraform(
+        script: &str,
+        kill_grace_period: Duration,
+    ) -> (Result<TerraformOutput, TerraformError>, Duration) {
+        let mut cmd = QoveryCommand::new("bash", &["-c", script], &[]);
+        cmd.set_kill_grace_period(kill_grace_period);
+        let abort_status = Arc::new(AtomicAbortStatus::new(AbortStatus::None));
+
+        let _ = thread::spawn({
+            let abort_status = abort_status.clone();
+            move || {
+                thread::sleep(Duration::from_secs(1));
+                abort_status.store(AbortStatus::Requested, Ordering::Release);
+            }
+        });
+
+        let should_abort = move || abort_status.load(Ordering::Acquire);
+        let started_at = Instant::now();
+        let result = terraform_exec_from_command(
+            &mut cmd,
+            &TerraformValidators::None,
+            &CommandKiller::from_cancelable(&should_abort),
+        );
+
+        (result, started_at.elapsed())
+    }
+
+    #[test]
+    fn test_terraform_exec_sends_a_single_interrupt_before_killing() {
+        // setup:
+        // ignores interrupts as a stuck terraform would, so only a SIGKILL can stop it
+        let script =
+            r#"n=0; trap 'n=$((n+1)); echo "interrupt $n" >&2' INT; echo started; while true; do sleep 0.1; done"#;
+        let kill_grace_period = Duration::from_secs(2);
+
+        // execute:
+        let (result, elapsed) = exec_and_cancel_fake_terraform(script, kill_grace_period);
+
+        // verify:
+        // one interrupt to let terraform stop gracefully, then a SIGKILL once the grace period is over
+        match result {
+            Err(TerraformError::Aborted { raw_message }) => {
+                assert!(raw_message.contains("interrupt 1"), "{raw_message}");
+                assert!(!raw_message.contains("interrupt 2"), "{raw_message}");
+            }
+            other => panic!("terraform should have been aborted: {other:?}"),
+        }
+        assert!(elapsed >= kill_grace_period, "killed after {elapsed:?}");
+        assert!(elapsed < Duration::from_secs(30), "killed after {elapsed:?}");
+    }
+
+    #[test]
+    fn test_terraform_exec_interrupted_gracefully_is_aborted() {
+        // setup:
+        // exits on the first interrupt, echoing what terraform prints when it receives several of them
+        let script = r#"trap 'echo "Interrupt received." >&2; echo "Two interrupts received. Exiting immediately." >&2; exit 1' INT; echo started; while true; do sleep 0.1; done"#;
+
+        // execute:
+        let (result, elapsed) = exec_and_cancel_fake_terraform(script, Duration::from_secs(60));
+
+        // verify:
+        // our own interrupt must never be reported as terraform having received several of them
+        match result {
+            Err(TerraformError::Aborted { raw_message }) => {
+                assert!(raw_message.contains("Two interrupts received"), "{raw_message}")
+            }
+            other => panic!("terraform should have been aborted: {other:?}"),
+        }
+        assert!(elapsed < Duration::from_secs(30), "killed after {elapsed:?}");
+    }
+
+    #[test]
+    fn test_terraform_targeted_plan_args() {
+        // execute:
+        let result = terraform_targeted_plan_args(&[
+            "aws_eks_node_group.eks_cluster_workers_1".to_string(),
+            "aws_subnet.eks_zone_a[0]".to_string(),
+            r#"module.vpc["eu-west-3a"].aws_route_table.private"#.to_string(),
+            "module.karpenter".to_string(),
+            "data.aws_subnet.eks_zone_b[2]".to_string(),
+        ]);
+
+        // validate:
+        assert_eq!(
+            result,
+            Ok(vec![
+                "plan".to_string(),
+                "-no-color".to_string(),
+                "-out".to_string(),
+                "tf_plan".to_string(),
+                "-target=aws_eks_node_group.eks_cluster_workers_1".to_string(),
+                "-target=aws_subnet.eks_zone_a[0]".to_string(),
+                r#"-target=module.vpc["eu-west-3a"].aws_route_table.private"#.to_string(),
+                "-target=module.karpenter".to_string(),
+                "-target=data.aws_subnet.eks_zone_b[2]".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_terraform_targeted_apply_rejects_invalid_targets() {
+        // setup:
+        let invalid_targets = vec![
+            "",
+            "aws_eks_node_group",
+            "aws_eks_node_group.",
+            ".eks_cluster_workers_1",
+            "aws_eks_node_group.eks_cluster_workers_1.",
+            "aws_eks_node_group eks_cluster_workers_1",
+            "aws_eks_node_group.eks_cluster_workers_1 -destroy",
+            "-destroy",
+            "aws_eks_node_group.1workers",
+            "aws_subnet.eks_zone_a[a]",
+            "aws_subnet.eks_zone_a[0",
+            "aws_subnet.eks_zone_a[\"\"]",
+            "module..aws_subnet.eks_zone_a",
+            "module.vpc.aws_subnet",
+            "aws_subnet.eks_zone_a;rm -rf /",
+        ];
+
+        for invalid_target in invalid_targets {
+            // execute:
+            let targets = vec![
+                "aws_eks_node_group.eks_cluster_workers_1".to_string(),
+                invalid_target.to_string(),
+            ];
+            let result = terraform_apply_with_targets(
+                "/nonexistent",
+                &targets,
+                &[],
+                &TerraformValidators::None,
+                true,
+                &CommandKiller::never(),
+            );
+
+            // validate:
+            match result {
+                Err(TerraformError::InvalidTargetAddress { target, .. }) => assert_eq!(target, invalid_target),
+                other => panic!("`{invalid_target}` should be rejected, got {other:?}"),
+            }
+        }
+
+        // without any target, everything would be applied
+        assert!(matches!(
+            terraform_apply_with_targets(
+                "/nonexistent",
+                &[],
+                &[],
+                &TerraformValidators::None,
+                true,
+                &CommandKiller::never()
+            ),
+            Err(TerraformError::InvalidTargetAddress { .. })
+        ));
+    }
 }
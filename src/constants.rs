@@ -1,6 +1,7 @@
 pub const TF_PLUGIN_CACHE_DIR: &str = "TF_PLUGIN_CACHE_DIR";
 pub const AWS_ACCESS_KEY_ID: &str = "AWS_ACCESS_KEY_ID";
 pub const AWS_SECRET_ACCESS_KEY: &str = "AWS_SECRET_ACCESS_KEY";
+pub const AWS_SESSION_TOKEN: &str = "AWS_SESSION_TOKEN";
 pub const AWS_DEFAULT_REGION: &str = "AWS_DEFAULT_REGION";
 pub const KUBECONFIG: &str = "KUBECONFIG";
 pub const SCW_ACCESS_KEY: &str = "SCW_ACCESS_KEY";
@@ -9,3 +10,6 @@ pub const SCW_DEFAULT_PROJECT_ID: &str = "SCW_DEFAULT_PROJECT_ID";
 pub const GCP_PROJECT: &str = "GOOGLE_PROJECT";
 pub const GCP_REGION: &str = "GOOGLE_REGION";
 pub const GCP_CREDENTIALS: &str = "GOOGLE_CREDENTIALS";
+pub const QOVERY_EXECUTION_ID: &str = "QOVERY_EXECUTION_ID";
+pub const QOVERY_CLUSTER_ID: &str = "QOVERY_CLUSTER_ID";
+pub const QOVERY_STAGE: &str = "QOVERY_STAGE";
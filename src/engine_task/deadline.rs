@@ -0,0 +1,299 @@
+// Overall deadline of an engine task. Its stages share what is left of the budget, a stage doesn't start when less
+// than its minimum duration remains, and some time is kept aside to cancel what is running and clean up.
+
+use crate::environment::models::abort::AbortStatus;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Used when the request doesn't set the maximum duration of the task
+pub const DEFAULT_TASK_MAX_DURATION: Duration = Duration::from_secs(4 * 60 * 60);
+/// Kept aside for the cancellation and the cleanup, at most a quarter of the budget
+const CLEANUP_GRACE_DURATION: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskStage {
+    pub name: &'static str,
+    /// The stage is not started if less than this remains of the budget
+    pub min_duration: Duration,
+}
+
+impl TaskStage {
+    pub const fn new(name: &'static str, min_duration: Duration) -> Self {
+        TaskStage { name, min_duration }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StageTiming {
+    pub stage: &'static str,
+    pub duration: Duration,
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error(
+    "Task exceeded its maximum duration of {} during stage `{stage}`{}",
+    format_duration(*.max_duration),
+    .longest_stage.as_ref().map(|timing| format!(", stage `{}` took the most time ({})", timing.stage, format_duration(timing.duration))).unwrap_or_default()
+)]
+pub struct DeadlineExceeded {
+    /// Stage running, or refused, when the budget got exhausted
+    pub stage: &'static str,
+    pub longest_stage: Option<StageTiming>,
+    pub max_duration: Duration,
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, (secs % 3600) / 60, secs % 60) {
+        (0, 0, secs) => format!("{secs}s"),
+        (0, mins, secs) => format!("{mins}m{secs:02}s"),
+        (hours, mins, _) => format!("{hours}h{mins:02}m"),
+    }
+}
+
+/// Accounting of the time spent by the stages of a task, the elapsed time is given by the caller
+#[derive(Clone, Debug)]
+pub struct TaskBudget {
+    max_duration: Duration,
+    grace: Duration,
+    timings: Vec<StageTiming>,
+    /// Running stage, and the elapsed time when it started
+    current: Option<(&'static str, Duration)>,
+}
+
+impl TaskBudget {
+    pub fn new(max_duration: Duration) -> Self {
+        TaskBudget {
+            max_duration,
+            grace: CLEANUP_GRACE_DURATION.min(max_duration / 4),
+            timings: vec![],
+            current: None,
+        }
+    }
+
+    /// Time the stages can still use, the grace duration excluded
+    pub fn remaining(&self, elapsed: Duration) -> Duration {
+        self.max_duration.saturating_sub(self.grace).saturating_sub(elapsed)
+    }
+
+    /// Once exhausted, what is running must be canceled, the grace duration is left to clean up
+    pub fn is_exhausted(&self, elapsed: Duration) -> bool {
+        self.remaining(elapsed).is_zero()
+    }
+
+    /// Ends the running stage, if any, and starts the next one when enough of the budget remains.
+    /// Returns the time allocated to the stage, i.e. what remains of the budget.
+    pub fn start_stage(&mut self, stage: TaskStage, elapsed: Duration) -> Result<Duration, DeadlineExceeded> {
+        self.end_stage(elapsed);
+
+        let remaining = self.remaining(elapsed);
+        if remaining.is_zero() || remaining < stage.min_duration {
+            return Err(self.exceeded(stage.name));
+        }
+
+        self.current = Some((stage.name, elapsed));
+        Ok(remaining)
+    }
+
+    pub fn end_stage(&mut self, elapsed: Duration) {
+        let Some((stage, started_at)) = self.current.take() else {
+            return;
+        };
+
+        let duration = elapsed.saturating_sub(started_at);
+        match self.timings.iter_mut().find(|timing| timing.stage == stage) {
+            Some(timing) => timing.duration += duration,
+            None => self.timings.push(StageTiming { stage, duration }),
+        }
+    }
+
+    pub fn current_stage(&self) -> Option<&'static str> {
+        self.current.map(|(stage, _)| stage)
+    }
+
+    /// Time spent by each stage, in the order they first ran
+    pub fn timings(&self) -> &[StageTiming] {
+        &self.timings
+    }
+
+    /// The first of the stages which took the most time
+    pub fn longest_stage(&self) -> Option<&StageTiming> {
+        self.timings.iter().rev().max_by_key(|timing| timing.duration)
+    }
+
+    pub fn exceeded(&self, stage: &'static str) -> DeadlineExceeded {
+        DeadlineExceeded {
+            stage,
+            longest_stage: self.longest_stage().cloned(),
+            max_duration: self.max_duration,
+        }
+    }
+}
+
+/// Deadline of a running task, measured from the moment it is started
+pub struct TaskDeadline {
+    started_at: OnceLock<Instant>,
+    budget: Mutex<TaskBudget>,
+}
+
+impl TaskDeadline {
+    pub fn new(max_duration: Duration) -> Self {
+        TaskDeadline {
+            started_at: OnceLock::new(),
+            budget: Mutex::new(TaskBudget::new(max_duration)),
+        }
+    }
+
+    pub fn start(&self) {
+        let _ = self.started_at.set(Instant::now());
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started_at
+            .get()
+            .map(|started_at| started_at.elapsed())
+            .unwrap_or_default()
+    }
+
+    pub fn start_stage(&self, stage: TaskStage) -> Result<Duration, DeadlineExceeded> {
+        let elapsed = self.elapsed();
+        self.budget.lock().unwrap().start_stage(stage, elapsed)
+    }
+
+    pub fn end_stage(&self) {
+        let elapsed = self.elapsed();
+        self.budget.lock().unwrap().end_stage(elapsed)
+    }
+
+    /// Requests the cancellation of the task once the budget of its stages is exhausted
+    pub fn abort_status(&self) -> AbortStatus {
+        match self.budget.lock().unwrap().is_exhausted(self.elapsed()) {
+            true => AbortStatus::Requested,
+            false => AbortStatus::None,
+        }
+    }
+
+    /// Set when the task has been canceled because of its deadline
+    pub fn exceeded(&self) -> Option<DeadlineExceeded> {
+        let elapsed = self.elapsed();
+        let mut budget = self.budget.lock().unwrap();
+        if !budget.is_exhausted(elapsed) {
+            return None;
+        }
+
+        let stage = budget.current_stage();
+        budget.end_stage(elapsed);
+        let last_stage = budget.timings().last().map(|timing| timing.stage);
+        Some(budget.exceeded(stage.or(last_stage).unwrap_or("start")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILD: TaskStage = TaskStage::new("build", Duration::from_secs(5 * 60));
+    const DEPLOY: TaskStage = TaskStage::new("deploy", Duration::from_secs(2 * 60));
+    const CLEANUP: TaskStage = TaskStage::new("cleanup", Duration::ZERO);
+
+    fn mins(mins: u64) -> Duration {
+        Duration::from_secs(mins * 60)
+    }
+
+    #[test]
+    fn test_stages_share_the_remaining_budget() {
+        let mut budget = TaskBudget::new(mins(60));
+
+        // 10 minutes are kept aside for the cleanup
+        assert_eq!(budget.start_stage(BUILD, Duration::ZERO), Ok(mins(50)));
+        assert_eq!(budget.start_stage(DEPLOY, mins(30)), Ok(mins(20)));
+        assert_eq!(budget.start_stage(BUILD, mins(35)), Ok(mins(15)));
+        budget.end_stage(mins(40));
+
+        assert_eq!(
+            budget.timings(),
+            &[
+                StageTiming {
+                    stage: "build",
+                    duration: mins(35)
+                },
+                StageTiming {
+                    stage: "deploy",
+                    duration: mins(5)
+                },
+            ]
+        );
+        assert_eq!(budget.current_stage(), None);
+        assert!(!budget.is_exhausted(mins(49)));
+        assert!(budget.is_exhausted(mins(50)));
+    }
+
+    #[test]
+    fn test_stage_is_not_started_below_its_minimum() {
+        let mut budget = TaskBudget::new(mins(60));
+        assert!(budget.start_stage(BUILD, Duration::ZERO).is_ok());
+
+        // 3 minutes remain, enough to deploy but not to build
+        assert_eq!(
+            budget.start_stage(BUILD, mins(47)),
+            Err(DeadlineExceeded {
+                stage: "build",
+                longest_stage: Some(StageTiming {
+                    stage: "build",
+                    duration: mins(47)
+                }),
+                max_duration: mins(60),
+            })
+        );
+        assert_eq!(budget.start_stage(DEPLOY, mins(47)), Ok(mins(3)));
+
+        // a stage without minimum needs some budget anyway
+        assert!(budget.start_stage(CLEANUP, mins(50)).is_err());
+    }
+
+    #[test]
+    fn test_grace_duration_of_short_budgets() {
+        // a quarter of the budget at most
+        let budget = TaskBudget::new(mins(20));
+        assert_eq!(budget.remaining(Duration::ZERO), mins(15));
+        assert_eq!(budget.remaining(mins(16)), Duration::ZERO);
+
+        let budget = TaskBudget::new(Duration::ZERO);
+        assert!(budget.is_exhausted(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_deadline_exceeded_names_the_longest_stage() {
+        let mut budget = TaskBudget::new(mins(4 * 60));
+        assert!(budget.start_stage(BUILD, Duration::ZERO).is_ok());
+        assert!(budget.start_stage(DEPLOY, mins(25)).is_ok());
+        assert!(budget.start_stage(CLEANUP, mins(26)).is_ok());
+        assert!(budget.start_stage(DEPLOY, mins(27)).is_ok());
+        budget.end_stage(mins(230));
+
+        let err = budget.exceeded("deploy");
+        assert_eq!(
+            err.longest_stage,
+            Some(StageTiming {
+                stage: "deploy",
+                duration: mins(204)
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "Task exceeded its maximum duration of 4h00m during stage `deploy`, stage `deploy` took the most time (3h24m)"
+        );
+
+        // equal durations, the first stage wins
+        let mut budget = TaskBudget::new(mins(60));
+        assert!(budget.start_stage(BUILD, Duration::ZERO).is_ok());
+        assert!(budget.start_stage(DEPLOY, mins(5)).is_ok());
+        budget.end_stage(mins(10));
+        assert_eq!(budget.longest_stage().map(|timing| timing.stage), Some("build"));
+
+        assert_eq!(
+            TaskBudget::new(Duration::from_secs(90)).exceeded("build").to_string(),
+            "Task exceeded its maximum duration of 1m30s during stage `build`"
+        );
+    }
+}
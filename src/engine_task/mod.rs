@@ -1,13 +1,22 @@
 use crate::environment::models::abort::Abort;
-use crate::fs::workspace_directory;
+use crate::events::{EngineEvent, EventDetails, EventMessage};
 use crate::io_models::context::Context;
+use crate::io_models::deprecations::DeprecatedFieldUsage;
 use crate::io_models::engine_request::Archive;
+use crate::io_models::feature_flags::FeatureFlags;
 use crate::log_file_writer::LogFileWriter;
+use crate::logger::Logger;
+use crate::proxy;
+use crate::proxy::ProxyConfig;
 use reqwest::header::CONTENT_TYPE;
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::time::Duration;
 use tokio::sync::broadcast;
+use url::Url;
 
+pub mod deadline;
+pub mod progress;
 pub mod qovery_api;
 
 pub trait Task: Send + Sync {
@@ -50,9 +59,82 @@ pub fn upload_s3_file(archive: Option<&Archive>, file_path: &Path) -> Result<(),
     Ok(())
 }
 
+// Reports sent to the core are small, so they are not streamed from the workspace like the archive
+pub fn upload_json_document(upload_url: &Url, payload: String) -> Result<(), reqwest::Error> {
+    reqwest::blocking::Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .build()?
+        .put(upload_url.clone())
+        .header(CONTENT_TYPE, "application/json")
+        .body(payload)
+        .timeout(Duration::from_secs(60))
+        .send()?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// Sends the flags the task runs with to the core, so a behavior change can be traced back to a flag
+pub fn log_feature_flags(requested: &BTreeMap<String, bool>, logger: &dyn Logger, event_details: EventDetails) {
+    let (feature_flags, warnings) = FeatureFlags::resolve(requested);
+    for warning in warnings {
+        logger.log(EngineEvent::Warning(
+            event_details.clone(),
+            EventMessage::new(warning.to_string(), None),
+        ));
+    }
+
+    let effective = feature_flags.effective();
+    let json = serde_json::to_string(&effective).unwrap_or_default();
+    logger.log(EngineEvent::Info(
+        event_details,
+        EventMessage::new_for_sending_core_data(format!("🚩 Feature flags: {effective}"), json),
+    ));
+}
+
+/// Warns about the deprecated fields of the payload, before they get rejected by a later schema version
+pub fn log_deprecated_fields(usages: &[DeprecatedFieldUsage], logger: &dyn Logger, event_details: EventDetails) {
+    for usage in usages {
+        logger.log(EngineEvent::Warning(
+            event_details.clone(),
+            EventMessage::new(format!("⚠️ {usage}"), None),
+        ));
+    }
+}
+
+/// Routes the outbound traffic of the task through the proxy, and reports which endpoints it can reach
+pub fn install_proxy(proxy: Option<&ProxyConfig>, logger: &dyn Logger, event_details: EventDetails) {
+    proxy::install(proxy);
+    let Some(proxy) = proxy else {
+        return;
+    };
+
+    let connectivity = proxy::check_connectivity(proxy);
+    let report = connectivity
+        .iter()
+        .map(|endpoint| endpoint.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let event = if connectivity.iter().all(|endpoint| endpoint.result.is_ok()) {
+        EngineEvent::Info(
+            event_details,
+            EventMessage::new(format!("🌐 Outbound traffic goes through HTTP proxy {proxy:?}"), Some(report)),
+        )
+    } else {
+        EngineEvent::Warning(
+            event_details,
+            EventMessage::new(
+                format!("🌐 Some endpoints cannot be reached with HTTP proxy {proxy:?}"),
+                Some(report),
+            ),
+        )
+    };
+    logger.log(event);
+}
+
 pub fn enable_log_file_writer(context: &Context, log_file_writer: &Option<LogFileWriter>) {
     if let Some(log_file_writer) = &log_file_writer {
-        let temp_dir = workspace_directory(context.workspace_root_dir(), context.execution_id(), "logs");
+        let temp_dir = context.workspace().and_then(|workspace| workspace.directory("logs"));
         if let Ok(temp_dir) = temp_dir {
             log_file_writer.enable(&temp_dir);
         }
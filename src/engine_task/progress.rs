@@ -0,0 +1,161 @@
+use crate::events::{EngineEvent, EventDetails, EventProgress};
+use crate::logger::Logger;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Reports the progress of a task as `EngineEvent::Progress` events.
+/// The remaining time is estimated from the average duration of the steps already done in the current sequence, a
+/// sequence starting over whenever the total of steps changes or a step doesn't come after the previous one.
+pub struct ProgressReporter {
+    event_details: EventDetails,
+    logger: Box<dyn Logger>,
+    sequence: Mutex<Option<ProgressSequence>>,
+}
+
+struct ProgressSequence {
+    first_step: u32,
+    last_step: u32,
+    total: u32,
+    started_at: Instant,
+}
+
+impl ProgressReporter {
+    pub fn new(event_details: EventDetails, logger: Box<dyn Logger>) -> Self {
+        ProgressReporter {
+            event_details,
+            logger,
+            sequence: Mutex::new(None),
+        }
+    }
+
+    /// Reports that `step`, the `current` one out of `total` (starting at 1), is being run
+    pub fn report_progress(&self, step: &str, current: u32, total: u32) {
+        let now = Instant::now();
+        let mut sequence = self.sequence.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (first_step, started_at) = match sequence.take() {
+            Some(seq) if seq.total == total && current > seq.last_step => (seq.first_step, seq.started_at),
+            _ => (current, now),
+        };
+        *sequence = Some(ProgressSequence {
+            first_step,
+            last_step: current,
+            total,
+            started_at,
+        });
+
+        let eta = estimate_remaining_time(
+            now.duration_since(started_at),
+            current - first_step,
+            total.saturating_sub(current) + 1,
+        );
+        self.logger.log(EngineEvent::Progress(
+            self.event_details.clone(),
+            EventProgress::new(step.to_string(), current, total, eta),
+        ));
+    }
+}
+
+/// Remaining time of the `remaining_steps`, assuming they last as long as the `done_steps` did on average
+fn estimate_remaining_time(elapsed: Duration, done_steps: u32, remaining_steps: u32) -> Option<Duration> {
+    if done_steps == 0 {
+        return None;
+    }
+
+    Some(elapsed / done_steps * remaining_steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{InfrastructureStep, Stage, Transmitter};
+    use crate::io_models::QoveryIdentifier;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    #[derive(Clone, Default)]
+    struct RecordingLogger {
+        events: Arc<Mutex<Vec<EngineEvent>>>,
+    }
+
+    impl Logger for RecordingLogger {
+        fn log(&self, event: EngineEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn clone_dyn(&self) -> Box<dyn Logger> {
+            Box::new(self.clone())
+        }
+
+        fn with_secrets(&self, _secrets: Vec<String>) -> Box<dyn Logger> {
+            Box::new(self.clone())
+        }
+    }
+
+    fn event_details() -> EventDetails {
+        EventDetails::new(
+            None,
+            QoveryIdentifier::new_random(),
+            QoveryIdentifier::new_random(),
+            Uuid::new_v4().to_string(),
+            Stage::Infrastructure(InfrastructureStep::Create),
+            Transmitter::Kubernetes(Uuid::new_v4(), "cluster".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_estimate_remaining_time() {
+        assert_eq!(estimate_remaining_time(Duration::from_secs(10), 0, 3), None);
+        assert_eq!(
+            estimate_remaining_time(Duration::from_secs(60), 2, 3),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            estimate_remaining_time(Duration::from_secs(60), 3, 1),
+            Some(Duration::from_secs(20))
+        );
+    }
+
+    #[test]
+    fn test_report_progress_of_a_deployment() {
+        let logger = RecordingLogger::default();
+        let reporter = ProgressReporter::new(event_details(), logger.clone_dyn());
+
+        // terraform phases, then the helm charts, then the nodes readiness joining once 1 node is already ready
+        reporter.report_progress("Terraform init", 1, 3);
+        reporter.report_progress("Terraform plan", 2, 3);
+        reporter.report_progress("Terraform apply", 3, 3);
+        reporter.report_progress("Helm chart cert-manager", 1, 2);
+        reporter.report_progress("Helm chart ingress-nginx", 2, 2);
+        reporter.report_progress("Worker nodes readiness", 2, 3);
+        reporter.report_progress("Worker nodes readiness", 3, 3);
+
+        let progress = logger
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| match event {
+                EngineEvent::Progress(_, progress) => (
+                    progress.step().to_string(),
+                    progress.current(),
+                    progress.total(),
+                    progress.eta().is_some(),
+                ),
+                _ => panic!("only progress events are expected"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            progress,
+            vec![
+                ("Terraform init".to_string(), 1, 3, false),
+                ("Terraform plan".to_string(), 2, 3, true),
+                ("Terraform apply".to_string(), 3, 3, true),
+                ("Helm chart cert-manager".to_string(), 1, 2, false),
+                ("Helm chart ingress-nginx".to_string(), 2, 2, true),
+                ("Worker nodes readiness".to_string(), 2, 3, false),
+                ("Worker nodes readiness".to_string(), 3, 3, true),
+            ]
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::infrastructure::models::cloud_provider::service::ServiceType;
-use crate::io_models::application::GitCredentials;
+use crate::io_models::application::GitAccessToken;
 use anyhow::anyhow;
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -13,7 +13,7 @@ pub enum EngineServiceType {
 
 pub trait QoveryApi: Send + Sync {
     fn service_version(&self, service_type: EngineServiceType) -> anyhow::Result<String>;
-    fn git_token(&self, service_type: ServiceType, service_id: &Uuid) -> anyhow::Result<GitCredentials>;
+    fn git_token(&self, service_type: ServiceType, service_id: &Uuid) -> anyhow::Result<GitAccessToken>;
 
     fn update_cluster_credentials(&self, kubeconfig: String) -> anyhow::Result<()>;
 }
@@ -25,7 +25,7 @@ impl QoveryApi for FakeQoveryApi {
         Err(anyhow!("not implemented"))
     }
 
-    fn git_token(&self, _service_type: ServiceType, _service_id: &Uuid) -> anyhow::Result<GitCredentials> {
+    fn git_token(&self, _service_type: ServiceType, _service_id: &Uuid) -> anyhow::Result<GitAccessToken> {
         Err(anyhow!("not implemented"))
     }
 
@@ -47,7 +47,7 @@ impl QoveryApi for StaticQoveryApi {
             .clone())
     }
 
-    fn git_token(&self, _service_type: ServiceType, _service_id: &Uuid) -> anyhow::Result<GitCredentials> {
+    fn git_token(&self, _service_type: ServiceType, _service_id: &Uuid) -> anyhow::Result<GitAccessToken> {
         Err(anyhow!("not implemented"))
     }
 
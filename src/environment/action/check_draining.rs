@@ -0,0 +1,192 @@
+use crate::environment::report::logger::EnvProgressLogger;
+use crate::runtime::block_on;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::api::discovery::v1::EndpointSlice;
+use kube::api::ListParams;
+use kube::{Api, ResourceExt};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Pods receiving traffic according to EndpointSlices.
+/// Kubernetes flags terminating pods as not ready right away, it is then up to the ingress to stop routing to them.
+pub fn serving_pods(slices: &[EndpointSlice]) -> BTreeSet<String> {
+    slices
+        .iter()
+        .flat_map(|slice| slice.endpoints.iter())
+        .filter(|endpoint| {
+            let conditions = endpoint.conditions.as_ref();
+            // a nil ready condition must be interpreted as ready
+            conditions.and_then(|c| c.ready).unwrap_or(true) && !conditions.and_then(|c| c.terminating).unwrap_or(false)
+        })
+        .filter_map(|endpoint| endpoint.target_ref.as_ref())
+        .filter(|target| target.kind.as_deref().unwrap_or("Pod") == "Pod")
+        .filter_map(|target| target.name.clone())
+        .collect()
+}
+
+/// Follows pods of a service during a rollout, and records the ones that are gone while still receiving traffic.
+#[derive(Default)]
+pub struct DrainingTracker {
+    pods: BTreeSet<String>,
+    serving_pods: BTreeSet<String>,
+    violations: BTreeSet<String>,
+}
+
+impl DrainingTracker {
+    pub fn observe(&mut self, pods: BTreeSet<String>, serving_pods: BTreeSet<String>) {
+        // A pod is drained when it has left the endpoints before its termination.
+        // Being still listed as serving in the previous poll means its endpoint was removed less than a poll ago.
+        let violations: Vec<String> = self
+            .pods
+            .difference(&pods)
+            .filter(|pod| self.serving_pods.contains(*pod) || serving_pods.contains(*pod))
+            .cloned()
+            .collect();
+
+        self.violations.extend(violations);
+        self.pods = pods;
+        self.serving_pods = serving_pods;
+    }
+
+    pub fn violations(&self) -> &BTreeSet<String> {
+        &self.violations
+    }
+}
+
+/// Checks in background, while a service is rolled out, that old pods stop receiving traffic before being terminated
+pub(super) struct DrainingCheck {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<DrainingTracker>>,
+}
+
+impl DrainingCheck {
+    pub(super) fn start(kube: kube::Client, namespace: &str, selector: &str) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let pods_api: Api<Pod> = Api::namespaced(kube.clone(), namespace);
+        // EndpointSlices inherit the labels of their service
+        let slices_api: Api<EndpointSlice> = Api::namespaced(kube, namespace);
+        let list_params = ListParams::default().labels(selector);
+
+        let handle = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut tracker = DrainingTracker::default();
+                while !stop.load(Ordering::Relaxed) {
+                    let ret = block_on(async {
+                        let pods = pods_api.list(&list_params).await?;
+                        let slices = slices_api.list(&list_params).await?;
+                        Ok::<_, kube::Error>((pods.items, slices.items))
+                    });
+                    match ret {
+                        Ok((pods, slices)) => {
+                            tracker.observe(pods.iter().map(|pod| pod.name_any()).collect(), serving_pods(&slices))
+                        }
+                        Err(err) => warn!("cannot poll endpoints to check pods draining: {}", err),
+                    }
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                tracker
+            })
+        };
+
+        DrainingCheck {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stops the check and warns the user about each pod terminated while still receiving traffic
+    pub(super) fn stop(mut self, logger: &EnvProgressLogger) {
+        self.stop.store(true, Ordering::Relaxed);
+        let Some(Ok(tracker)) = self.handle.take().map(|handle| handle.join()) else {
+            return;
+        };
+
+        for pod in tracker.violations() {
+            logger.warning(format!(
+                "⚠️ Pod {pod} has been terminated while still receiving traffic, requests may have failed during the rollout. Consider increasing the advanced setting graceful_shutdown.drain_seconds"
+            ));
+        }
+    }
+}
+
+impl Drop for DrainingCheck {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::ObjectReference;
+    use k8s_openapi::api::discovery::v1::{Endpoint, EndpointConditions};
+
+    fn pods(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn endpoint(pod: &str, ready: Option<bool>, terminating: Option<bool>) -> Endpoint {
+        Endpoint {
+            addresses: vec!["10.0.0.1".to_string()],
+            conditions: Some(EndpointConditions {
+                ready,
+                terminating,
+                ..Default::default()
+            }),
+            target_ref: Some(ObjectReference {
+                kind: Some("Pod".to_string()),
+                name: Some(pod.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_serving_pods() {
+        let slice = EndpointSlice {
+            address_type: "IPv4".to_string(),
+            endpoints: vec![
+                endpoint("pod-a", Some(true), None),
+                endpoint("pod-b", None, None),
+                endpoint("pod-c", Some(false), Some(true)),
+                endpoint("pod-d", Some(false), None),
+                endpoint("pod-e", Some(true), Some(true)),
+            ],
+            metadata: Default::default(),
+            ports: None,
+        };
+
+        assert_eq!(serving_pods(&[slice]), pods(&["pod-a", "pod-b"]));
+    }
+
+    #[test]
+    fn test_drained_pods_are_not_reported() {
+        let mut tracker = DrainingTracker::default();
+        tracker.observe(pods(&["pod-a", "pod-b"]), pods(&["pod-a", "pod-b"]));
+        // new pod is ready, pod-a is terminating and sleeping in its preStop hook
+        tracker.observe(pods(&["pod-a", "pod-b", "pod-c"]), pods(&["pod-b", "pod-c"]));
+        tracker.observe(pods(&["pod-a", "pod-b", "pod-c"]), pods(&["pod-b", "pod-c"]));
+        tracker.observe(pods(&["pod-b", "pod-c"]), pods(&["pod-b", "pod-c"]));
+
+        assert!(tracker.violations().is_empty());
+    }
+
+    #[test]
+    fn test_pods_terminated_while_serving_are_reported() {
+        let mut tracker = DrainingTracker::default();
+        tracker.observe(pods(&["pod-a", "pod-b"]), pods(&["pod-a", "pod-b"]));
+        // pod-a exits right away, before its endpoint is removed
+        tracker.observe(pods(&["pod-b", "pod-c"]), pods(&["pod-a", "pod-b"]));
+        // pod-b endpoint was still there at the previous poll
+        tracker.observe(pods(&["pod-c"]), pods(&["pod-c"]));
+
+        assert_eq!(tracker.violations(), &pods(&["pod-a", "pod-b"]));
+    }
+}
@@ -0,0 +1,444 @@
+use crate::environment::report::logger::EnvProgressLogger;
+use crate::errors::EngineError;
+use crate::events::EventDetails;
+use crate::infrastructure::models::cloud_provider::DeploymentTarget;
+use crate::runtime::block_on;
+use futures::{StreamExt, TryStreamExt};
+use k8s_openapi::api::core::v1::{Pod, Service};
+use k8s_openapi::api::discovery::v1::EndpointSlice;
+use kube::api::{EvictParams, ListParams};
+use kube::runtime::watcher;
+use kube::{Api, ResourceExt};
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+// Must stay in sync with maxUnavailable of the PodDisruptionBudget of our charts (q-container/templates/pdb.j2.yaml)
+const PDB_MAX_UNAVAILABLE_PERCENT: usize = 10;
+
+/// Minimum number of endpoints which must stay available while one pod is replaced.
+/// Kubernetes rounds up a percentage of max unavailable pods, so at least one pod can always be disrupted.
+pub fn min_available_endpoints(replicas: usize) -> usize {
+    let max_unavailable = (replicas * PDB_MAX_UNAVAILABLE_PERCENT).div_ceil(100);
+    replicas.saturating_sub(max_unavailable.max(1))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResilienceCheckOutcome {
+    Passed {
+        recovery_time: Duration,
+        lowest_available: usize,
+    },
+    BelowMinAvailable {
+        lowest_available: usize,
+        min_available: usize,
+    },
+    NotRecovered {
+        available: usize,
+        expected: usize,
+    },
+}
+
+/// Follows ready endpoints of a kubernetes service, across all of its EndpointSlices,
+/// while one of its pods is evicted and replaced.
+pub struct EndpointsWatcher {
+    expected: usize,
+    min_available: usize,
+    evicted_pod: String,
+    // EndpointSlice name -> ready endpoints (pod name, or address when not backed by a pod)
+    ready_endpoints: HashMap<String, BTreeSet<String>>,
+    lowest_available: Option<usize>,
+    is_listing: bool,
+}
+
+impl EndpointsWatcher {
+    pub fn new(expected: usize, evicted_pod: String) -> Self {
+        EndpointsWatcher {
+            expected,
+            min_available: min_available_endpoints(expected),
+            evicted_pod,
+            ready_endpoints: HashMap::new(),
+            lowest_available: None,
+            is_listing: true,
+        }
+    }
+
+    pub fn min_available(&self) -> usize {
+        self.min_available
+    }
+
+    /// A (re)list of EndpointSlices is starting, transitions are not tracked until it is done
+    /// to avoid reporting a partial view of the slices as a drop.
+    pub fn list_started(&mut self) {
+        self.is_listing = true;
+        self.ready_endpoints.clear();
+    }
+
+    pub fn list_done(&mut self) {
+        self.is_listing = false;
+        self.record_transition();
+    }
+
+    pub fn apply(&mut self, slice: &EndpointSlice) {
+        let ready_endpoints = slice
+            .endpoints
+            .iter()
+            // a nil ready condition must be interpreted as ready
+            .filter(|endpoint| endpoint.conditions.as_ref().and_then(|c| c.ready).unwrap_or(true))
+            .filter_map(|endpoint| {
+                endpoint
+                    .target_ref
+                    .as_ref()
+                    .and_then(|target| target.name.clone())
+                    .or_else(|| endpoint.addresses.first().cloned())
+            })
+            .collect();
+
+        self.ready_endpoints.insert(slice.name_any(), ready_endpoints);
+        self.record_transition();
+    }
+
+    pub fn delete(&mut self, slice: &EndpointSlice) {
+        self.ready_endpoints.remove(&slice.name_any());
+        self.record_transition();
+    }
+
+    pub fn available(&self) -> usize {
+        self.ready_endpoints.values().flatten().collect::<BTreeSet<_>>().len()
+    }
+
+    /// The evicted pod is gone and a replacement one serves traffic
+    pub fn is_recovered(&self) -> bool {
+        !self.is_listing
+            && self.available() >= self.expected
+            && !self
+                .ready_endpoints
+                .values()
+                .any(|endpoints| endpoints.contains(&self.evicted_pod))
+    }
+
+    pub fn outcome(&self) -> Option<ResilienceCheckOutcome> {
+        let lowest_available = self.lowest_available?;
+        if lowest_available < self.min_available {
+            return Some(ResilienceCheckOutcome::BelowMinAvailable {
+                lowest_available,
+                min_available: self.min_available,
+            });
+        }
+
+        None
+    }
+
+    pub fn final_outcome(&self, elapsed: Duration) -> ResilienceCheckOutcome {
+        if let Some(outcome) = self.outcome() {
+            return outcome;
+        }
+
+        if !self.is_recovered() {
+            return ResilienceCheckOutcome::NotRecovered {
+                available: self.available(),
+                expected: self.expected,
+            };
+        }
+
+        ResilienceCheckOutcome::Passed {
+            recovery_time: elapsed,
+            lowest_available: self.lowest_available.unwrap_or(self.expected),
+        }
+    }
+
+    fn record_transition(&mut self) {
+        if self.is_listing {
+            return;
+        }
+
+        let available = self.available();
+        self.lowest_available = Some(self.lowest_available.map_or(available, |lowest| lowest.min(available)));
+    }
+}
+
+fn is_pod_ready(pod: &Pod) -> bool {
+    pod.metadata.deletion_timestamp.is_none()
+        && pod
+            .status
+            .as_ref()
+            .and_then(|status| status.conditions.as_ref())
+            .map(|conditions| conditions.iter().any(|c| c.type_ == "Ready" && c.status == "True"))
+            .unwrap_or(false)
+}
+
+/// Evicts one pod of the service right after its deployment and checks that its endpoints
+/// never drop below the minimum available threshold while the pod is replaced.
+pub(super) fn check_resilience(
+    service_name: &str,
+    selector: &str,
+    timeout: Duration,
+    target: &DeploymentTarget,
+    logger: &EnvProgressLogger,
+    event_details: &EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let to_error = |details: String| {
+        Box::new(EngineError::new_k8s_resilience_check_failed(
+            event_details.clone(),
+            service_name.to_string(),
+            details,
+        ))
+    };
+    let namespace = target.environment.namespace();
+    let pods_api: Api<Pod> = Api::namespaced(target.kube.clone(), namespace);
+    let services_api: Api<Service> = Api::namespaced(target.kube.clone(), namespace);
+    let list_params = ListParams::default().labels(selector);
+
+    let ready_pods: Vec<Pod> = block_on(pods_api.list(&list_params))
+        .map_err(|e| to_error(format!("cannot list pods: {e}")))?
+        .items
+        .into_iter()
+        .filter(is_pod_ready)
+        .collect();
+    if ready_pods.len() <= 1 {
+        logger.info(
+            "🩺 Resilience check skipped: it requires more than one ready pod, killing a single one would only measure a downtime"
+                .to_string(),
+        );
+        return Ok(());
+    }
+
+    let k8s_service_name = match block_on(services_api.list(&list_params))
+        .map_err(|e| to_error(format!("cannot list kubernetes services: {e}")))?
+        .items
+        .first()
+    {
+        Some(service) => service.name_any(),
+        None => {
+            logger.info(
+                "🩺 Resilience check skipped: service does not expose any port, there is no endpoint to watch"
+                    .to_string(),
+            );
+            return Ok(());
+        }
+    };
+
+    let evicted_pod = ready_pods[0].name_any();
+    let mut endpoints = EndpointsWatcher::new(ready_pods.len(), evicted_pod.clone());
+    logger.info(format!(
+        "🩺 Resilience check: evicting pod {evicted_pod}, at least {}/{} endpoints must stay available",
+        endpoints.min_available(),
+        ready_pods.len()
+    ));
+
+    let slices_api: Api<EndpointSlice> = Api::namespaced(target.kube.clone(), namespace);
+    let watcher_config = watcher::Config::default().labels(&format!("kubernetes.io/service-name={k8s_service_name}"));
+
+    let elapsed = block_on(async {
+        let mut stream = watcher(slices_api, watcher_config).boxed();
+        let mut eviction_started_at: Option<Instant> = None;
+
+        let watch = async {
+            while let Some(event) = stream
+                .try_next()
+                .await
+                .map_err(|e| to_error(format!("cannot watch endpoint slices: {e}")))?
+            {
+                match event {
+                    watcher::Event::Init => endpoints.list_started(),
+                    watcher::Event::InitApply(slice) | watcher::Event::Apply(slice) => endpoints.apply(&slice),
+                    watcher::Event::Delete(slice) => endpoints.delete(&slice),
+                    watcher::Event::InitDone => {
+                        endpoints.list_done();
+                        // Evict only once the initial state of endpoints is known
+                        if eviction_started_at.is_none() {
+                            pods_api
+                                .evict(&evicted_pod, &EvictParams::default())
+                                .await
+                                .map_err(|e| to_error(format!("cannot evict pod {evicted_pod}: {e}")))?;
+                            eviction_started_at = Some(Instant::now());
+                        }
+                    }
+                }
+
+                if endpoints.outcome().is_some() || (eviction_started_at.is_some() && endpoints.is_recovered()) {
+                    break;
+                }
+            }
+
+            Ok::<(), Box<EngineError>>(())
+        };
+
+        match tokio::time::timeout(timeout, watch).await {
+            Ok(ret) => ret,
+            // Timeout is reported as a not recovered outcome
+            Err(_) => Ok(()),
+        }?;
+
+        Ok::<Duration, Box<EngineError>>(eviction_started_at.map(|t| t.elapsed()).unwrap_or_default())
+    })?;
+
+    match endpoints.final_outcome(elapsed) {
+        ResilienceCheckOutcome::Passed {
+            recovery_time,
+            lowest_available,
+        } => {
+            logger.info(format!(
+                "🩺 Resilience check passed: service recovered in {}s, with at least {lowest_available} endpoints available",
+                recovery_time.as_secs()
+            ));
+            Ok(())
+        }
+        ResilienceCheckOutcome::BelowMinAvailable {
+            lowest_available,
+            min_available,
+        } => Err(to_error(format!(
+            "only {lowest_available} endpoints were available while pod {evicted_pod} was replaced, at least {min_available} are required"
+        ))),
+        ResilienceCheckOutcome::NotRecovered { available, expected } => Err(to_error(format!(
+            "service did not recover after {} seconds, {available}/{expected} endpoints are available",
+            timeout.as_secs()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::ObjectReference;
+    use k8s_openapi::api::discovery::v1::{Endpoint, EndpointConditions};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+    fn slice(name: &str, endpoints: &[(&str, Option<bool>)]) -> EndpointSlice {
+        EndpointSlice {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                ..Default::default()
+            },
+            address_type: "IPv4".to_string(),
+            endpoints: endpoints
+                .iter()
+                .map(|(pod, ready)| Endpoint {
+                    addresses: vec!["10.0.0.1".to_string()],
+                    conditions: Some(EndpointConditions {
+                        ready: *ready,
+                        ..Default::default()
+                    }),
+                    target_ref: Some(ObjectReference {
+                        name: Some(pod.to_string()),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .collect(),
+            ports: None,
+        }
+    }
+
+    #[test]
+    fn test_min_available_endpoints() {
+        assert_eq!(min_available_endpoints(2), 1);
+        assert_eq!(min_available_endpoints(3), 2);
+        assert_eq!(min_available_endpoints(10), 9);
+        assert_eq!(min_available_endpoints(11), 9);
+        assert_eq!(min_available_endpoints(25), 22);
+    }
+
+    #[test]
+    fn test_resilience_check_passes_when_pod_is_replaced() {
+        let mut watcher = EndpointsWatcher::new(3, "pod-a".to_string());
+        watcher.list_started();
+        watcher.apply(&slice("svc-1", &[("pod-a", Some(true)), ("pod-b", Some(true))]));
+        watcher.apply(&slice("svc-2", &[("pod-c", None)]));
+        watcher.list_done();
+        assert_eq!(watcher.available(), 3);
+        assert!(!watcher.is_recovered());
+
+        // pod-a is terminating, then removed, then pod-d starts and becomes ready
+        watcher.apply(&slice("svc-1", &[("pod-a", Some(false)), ("pod-b", Some(true))]));
+        watcher.apply(&slice("svc-1", &[("pod-b", Some(true))]));
+        watcher.apply(&slice("svc-1", &[("pod-b", Some(true)), ("pod-d", Some(false))]));
+        assert!(!watcher.is_recovered());
+        assert_eq!(watcher.outcome(), None);
+        watcher.apply(&slice("svc-1", &[("pod-b", Some(true)), ("pod-d", Some(true))]));
+        assert!(watcher.is_recovered());
+
+        assert_eq!(
+            watcher.final_outcome(Duration::from_secs(12)),
+            ResilienceCheckOutcome::Passed {
+                recovery_time: Duration::from_secs(12),
+                lowest_available: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_resilience_check_fails_when_endpoints_drop() {
+        let mut watcher = EndpointsWatcher::new(3, "pod-a".to_string());
+        watcher.list_started();
+        watcher.apply(&slice(
+            "svc-1",
+            &[("pod-a", Some(true)), ("pod-b", Some(true)), ("pod-c", Some(true))],
+        ));
+        watcher.list_done();
+
+        // other pods fail their readiness probe when losing pod-a (i.e: shared local state)
+        watcher.apply(&slice("svc-1", &[("pod-b", Some(false)), ("pod-c", Some(true))]));
+        let failure = ResilienceCheckOutcome::BelowMinAvailable {
+            lowest_available: 1,
+            min_available: 2,
+        };
+        assert_eq!(watcher.outcome(), Some(failure.clone()));
+
+        // recovering later does not change the verdict
+        watcher.apply(&slice(
+            "svc-1",
+            &[("pod-b", Some(true)), ("pod-c", Some(true)), ("pod-d", Some(true))],
+        ));
+        assert_eq!(watcher.final_outcome(Duration::from_secs(30)), failure);
+    }
+
+    #[test]
+    fn test_resilience_check_ignores_partial_lists() {
+        let mut watcher = EndpointsWatcher::new(2, "pod-a".to_string());
+        watcher.list_started();
+        watcher.apply(&slice("svc-1", &[("pod-a", Some(true))]));
+        watcher.list_done();
+        watcher.apply(&slice("svc-2", &[("pod-b", Some(true))]));
+
+        // a relist in progress only sees some of the slices, it must not count as a drop
+        watcher.list_started();
+        watcher.apply(&slice("svc-1", &[("pod-a", Some(true))]));
+        assert!(!watcher.is_recovered());
+        watcher.apply(&slice("svc-2", &[("pod-b", Some(true))]));
+        watcher.list_done();
+
+        assert_eq!(watcher.outcome(), None);
+    }
+
+    #[test]
+    fn test_resilience_check_not_recovered() {
+        let mut watcher = EndpointsWatcher::new(2, "pod-a".to_string());
+        watcher.list_started();
+        watcher.apply(&slice("svc-1", &[("pod-a", Some(true)), ("pod-b", Some(true))]));
+        watcher.list_done();
+        watcher.delete(&slice("svc-1", &[]));
+        watcher.apply(&slice("svc-2", &[("pod-b", Some(true)), ("pod-c", Some(false))]));
+
+        // endpoints dropped to 0 when the slice was deleted
+        assert_eq!(
+            watcher.final_outcome(Duration::from_secs(300)),
+            ResilienceCheckOutcome::BelowMinAvailable {
+                lowest_available: 0,
+                min_available: 1,
+            }
+        );
+
+        let mut watcher = EndpointsWatcher::new(2, "pod-a".to_string());
+        watcher.list_started();
+        watcher.apply(&slice("svc-1", &[("pod-a", Some(true)), ("pod-b", Some(true))]));
+        watcher.list_done();
+        watcher.apply(&slice("svc-1", &[("pod-b", Some(true)), ("pod-c", Some(false))]));
+        assert_eq!(
+            watcher.final_outcome(Duration::from_secs(300)),
+            ResilienceCheckOutcome::NotRecovered {
+                available: 1,
+                expected: 2,
+            }
+        );
+    }
+}
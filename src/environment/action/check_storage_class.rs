@@ -0,0 +1,114 @@
+use crate::errors::EngineError;
+use crate::events::{EngineEvent, EventDetails, EventMessage};
+use crate::infrastructure::models::cloud_provider::DeploymentTarget;
+use crate::runtime::block_on;
+use k8s_openapi::api::storage::v1::StorageClass;
+use kube::api::ListParams;
+use kube::{Api, ResourceExt};
+
+const DEFAULT_STORAGE_CLASS_ANNOTATION: &str = "storageclass.kubernetes.io/is-default-class";
+
+pub fn is_default_storage_class(storage_class: &StorageClass) -> bool {
+    storage_class
+        .annotations()
+        .get(DEFAULT_STORAGE_CLASS_ANNOTATION)
+        .map(|value| value == "true")
+        .unwrap_or(false)
+}
+
+/// Makes sure the storage class backing the persistent volumes exists among the ones of the cluster.
+/// Returns whether it is the default storage class of the cluster.
+pub fn check_storage_class(
+    event_details: &EventDetails,
+    storage_classes: &[StorageClass],
+    storage_class_name: &str,
+) -> Result<bool, Box<EngineError>> {
+    match storage_classes.iter().find(|sc| sc.name_any() == storage_class_name) {
+        Some(storage_class) => Ok(is_default_storage_class(storage_class)),
+        None => Err(Box::new(EngineError::new_k8s_storage_class_not_found(
+            event_details.clone(),
+            storage_class_name,
+            &storage_classes.iter().map(|sc| sc.name_any()).collect::<Vec<_>>(),
+        ))),
+    }
+}
+
+/// Checks the storage class set on the cluster exists before deploying services requesting persistent volumes.
+/// Otherwise, their PVCs would stay pending until the deployment times out.
+pub fn check_cluster_storage_class(
+    target: &DeploymentTarget,
+    event_details: &EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let Some(storage_class_name) = target.kubernetes.default_storage_class() else {
+        return Ok(());
+    };
+
+    let storage_classes_api: Api<StorageClass> = Api::all(target.kube.clone());
+    let storage_classes = block_on(storage_classes_api.list(&ListParams::default()))
+        .map_err(|e| Box::new(EngineError::new_cannot_connect_to_k8s_cluster(event_details.clone(), e)))?
+        .items;
+
+    // the storage class is explicitly set in the rendered values of the services, it doesn't need to be the default one
+    if !check_storage_class(event_details, &storage_classes, storage_class_name)? {
+        target.kubernetes.logger().log(EngineEvent::Info(
+            event_details.clone(),
+            EventMessage::new_from_safe(format!(
+                "Storage class `{storage_class_name}` is not the default one of the cluster, it is set explicitly on the persistent volumes"
+            )),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Tag;
+    use crate::events::{InfrastructureStep, Stage, Transmitter};
+    use crate::io_models::QoveryIdentifier;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    use std::collections::BTreeMap;
+    use uuid::Uuid;
+
+    fn storage_class(name: &str, is_default: bool) -> StorageClass {
+        StorageClass {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                annotations: Some(BTreeMap::from([(
+                    DEFAULT_STORAGE_CLASS_ANNOTATION.to_string(),
+                    is_default.to_string(),
+                )])),
+                ..Default::default()
+            },
+            provisioner: "rancher.io/local-path".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn event_details() -> EventDetails {
+        EventDetails::new(
+            None,
+            QoveryIdentifier::new_random(),
+            QoveryIdentifier::new_random(),
+            Uuid::new_v4().to_string(),
+            Stage::Infrastructure(InfrastructureStep::LoadConfiguration),
+            Transmitter::Kubernetes(Uuid::new_v4(), "cluster".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_check_storage_class() {
+        let storage_classes = vec![storage_class("local-path", true), storage_class("nfs", false)];
+
+        assert!(check_storage_class(&event_details(), &storage_classes, "local-path").unwrap());
+        assert!(!check_storage_class(&event_details(), &storage_classes, "nfs").unwrap());
+
+        let err = check_storage_class(&event_details(), &storage_classes, "gp2").unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sStorageClassNotFound);
+        assert!(err.user_log_message().contains("local-path, nfs"));
+
+        let err = check_storage_class(&event_details(), &[], "local-path").unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sStorageClassNotFound);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::environment::report::logger::EnvProgressLogger;
+use crate::errors::EngineError;
+use crate::events::EventDetails;
+use crate::helm::HelmChartNamespaces;
+use crate::infrastructure::models::cloud_provider::DeploymentTarget;
+use crate::io_models::context::Features;
+use crate::runtime::block_on;
+use kube::api::GetParams;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+const PROMETHEUS_SERVICE: &str = "prometheus-operated:9090";
+
+/// Prometheus query of the requests per second received by each of the hosts, averaged over the window.
+/// Hosts are matched exactly, a wildcard domain matches any of its direct subdomains.
+pub fn requests_rate_query(hosts: &[String], window_in_min: u32) -> String {
+    let hosts_regex = hosts
+        .iter()
+        .map(|host| match host.strip_prefix("*.") {
+            Some(domain) => format!(r"[^.]+\.{}", regex::escape(domain)),
+            None => regex::escape(host),
+        })
+        .collect::<Vec<_>>()
+        .join("|");
+
+    format!(
+        r#"sum by (host) (rate(nginx_ingress_controller_requests{{host=~"{}"}}[{}m]))"#,
+        hosts_regex.replace('\\', r"\\").replace('"', r#"\""#),
+        window_in_min
+    )
+}
+
+#[derive(Deserialize)]
+struct PrometheusResponse {
+    status: String,
+    data: Option<PrometheusData>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PrometheusData {
+    result: Vec<PrometheusSample>,
+}
+
+#[derive(Deserialize)]
+struct PrometheusSample {
+    metric: BTreeMap<String, String>,
+    /// timestamp, then the value as a string
+    value: (f64, String),
+}
+
+/// Requests per second of each host from the response of `requests_rate_query`.
+/// Hosts without any request since the controller started are not listed, their rate is 0.
+pub fn parse_requests_rates(response: &str) -> Result<BTreeMap<String, f64>, String> {
+    let response: PrometheusResponse =
+        serde_json::from_str(response).map_err(|e| format!("invalid prometheus response: {e}"))?;
+    if response.status != "success" {
+        return Err(format!(
+            "prometheus query failed: {}",
+            response.error.unwrap_or_else(|| response.status.clone())
+        ));
+    }
+
+    let mut rates = BTreeMap::new();
+    for sample in response.data.map(|data| data.result).unwrap_or_default() {
+        let Some(host) = sample.metric.get("host") else {
+            continue;
+        };
+        let rate: f64 = sample
+            .value
+            .1
+            .parse()
+            .map_err(|e| format!("invalid rate `{}` for host {host}: {e}", sample.value.1))?;
+        // no request at all over the window gives NaN
+        if rate.is_finite() && rate > 0.0 {
+            rates.insert(host.clone(), rate);
+        }
+    }
+
+    Ok(rates)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TrafficVerdict {
+    BelowThreshold,
+    /// Above the threshold, but the deletion has been forced
+    Forced {
+        requests_per_second: f64,
+        hosts: Vec<String>,
+    },
+    Refused {
+        requests_per_second: f64,
+        hosts: Vec<String>,
+    },
+}
+
+pub fn traffic_verdict(rates: &BTreeMap<String, f64>, max_requests_per_second: f64, force: bool) -> TrafficVerdict {
+    let requests_per_second: f64 = rates.values().sum();
+    if requests_per_second <= max_requests_per_second {
+        return TrafficVerdict::BelowThreshold;
+    }
+
+    let hosts = rates.keys().cloned().collect();
+    match force {
+        true => TrafficVerdict::Forced {
+            requests_per_second,
+            hosts,
+        },
+        false => TrafficVerdict::Refused {
+            requests_per_second,
+            hosts,
+        },
+    }
+}
+
+/// Refuses to delete a service still receiving requests on the domains of its routers, unless the deletion is forced.
+/// The requests are counted by the ingress controller metrics, the check is skipped when they are not collected
+/// or cannot be queried.
+pub(super) fn check_no_traffic_before_deletion(
+    service_id: &Uuid,
+    target: &DeploymentTarget,
+    event_details: &EventDetails,
+    logger: &EnvProgressLogger,
+) -> Result<(), Box<EngineError>> {
+    let window_in_min = target
+        .kubernetes
+        .advanced_settings()
+        .deletion_traffic_check_window_in_min;
+    if window_in_min == 0
+        || !target
+            .kubernetes
+            .context()
+            .is_feature_enabled(&Features::MetricsHistory)
+    {
+        return Ok(());
+    }
+
+    let hosts: Vec<String> = target
+        .environment
+        .routers
+        .iter()
+        .filter(|router| router.associated_service_id().as_ref() == Some(service_id))
+        .flat_map(|router| router.domains())
+        .collect();
+    if hosts.is_empty() {
+        return Ok(());
+    }
+
+    let response = match block_on(query_prometheus(&target.kube, &requests_rate_query(&hosts, window_in_min))) {
+        Ok(response) => response,
+        Err(err) => {
+            logger.warning(format!("Cannot check the traffic of the service before deleting it: {err}"));
+            return Ok(());
+        }
+    };
+    let rates = match parse_requests_rates(&response) {
+        Ok(rates) => rates,
+        Err(err) => {
+            logger.warning(format!("Cannot check the traffic of the service before deleting it: {err}"));
+            return Ok(());
+        }
+    };
+
+    match traffic_verdict(
+        &rates,
+        target
+            .kubernetes
+            .advanced_settings()
+            .deletion_traffic_check_max_requests_per_second,
+        target.kubernetes.context().is_forced(),
+    ) {
+        TrafficVerdict::BelowThreshold => Ok(()),
+        TrafficVerdict::Forced {
+            requests_per_second,
+            hosts,
+        } => {
+            logger.warning(format!(
+                "⚠️ Service still receives {:.2} requests per second on {}, deleting it anyway as the deletion is forced",
+                requests_per_second,
+                hosts.join(", ")
+            ));
+            Ok(())
+        }
+        TrafficVerdict::Refused {
+            requests_per_second,
+            hosts,
+        } => Err(Box::new(EngineError::new_service_still_receiving_traffic(
+            event_details.clone(),
+            requests_per_second,
+            hosts,
+            window_in_min,
+        ))),
+    }
+}
+
+/// Runs an instant query through the API server proxy, the engine may not reach the cluster network
+async fn query_prometheus(kube: &kube::Client, query: &str) -> Result<String, String> {
+    let request = kube::core::Request::new(format!("/api/v1/namespaces/{}/services", HelmChartNamespaces::Prometheus))
+        .get(
+            &format!("{PROMETHEUS_SERVICE}/proxy/api/v1/query?query={}", urlencoding::encode(query)),
+            &GetParams::default(),
+        )
+        .map_err(|e| e.to_string())?;
+
+    kube.request_text(request).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rates(rates: &[(&str, f64)]) -> BTreeMap<String, f64> {
+        rates.iter().map(|(host, rate)| (host.to_string(), *rate)).collect()
+    }
+
+    #[test]
+    fn test_requests_rate_query() {
+        let hosts = vec![
+            "p8080.z12345678-zabcdef01.example.qovery.io".to_string(),
+            "api.my-app.com".to_string(),
+            "*.my-app.com".to_string(),
+        ];
+
+        assert_eq!(
+            requests_rate_query(&hosts, 60),
+            r#"sum by (host) (rate(nginx_ingress_controller_requests{host=~"p8080\\.z12345678\\-zabcdef01\\.example\\.qovery\\.io|api\\.my\\-app\\.com|[^.]+\\.my\\-app\\.com"}[60m]))"#
+        );
+    }
+
+    #[test]
+    fn test_parse_requests_rates() {
+        let response = r#"{
+          "status": "success",
+          "data": {
+            "resultType": "vector",
+            "result": [
+              { "metric": { "host": "api.my-app.com" }, "value": [1729065600.123, "2.5"] },
+              { "metric": { "host": "www.my-app.com" }, "value": [1729065600.123, "0.016666666666666666"] },
+              { "metric": { "host": "old.my-app.com" }, "value": [1729065600.123, "0"] },
+              { "metric": { "host": "idle.my-app.com" }, "value": [1729065600.123, "NaN"] },
+              { "metric": {}, "value": [1729065600.123, "12"] }
+            ]
+          }
+        }"#;
+
+        assert_eq!(
+            parse_requests_rates(response),
+            Ok(rates(&[("api.my-app.com", 2.5), ("www.my-app.com", 0.016666666666666666)]))
+        );
+        assert_eq!(
+            parse_requests_rates(r#"{"status": "success", "data": {"resultType": "vector", "result": []}}"#),
+            Ok(BTreeMap::new())
+        );
+    }
+
+    #[test]
+    fn test_parse_requests_rates_errors() {
+        assert_eq!(
+            parse_requests_rates(r#"{"status": "error", "errorType": "bad_data", "error": "parse error at char 12"}"#),
+            Err("prometheus query failed: parse error at char 12".to_string())
+        );
+        assert!(parse_requests_rates("404 page not found").is_err());
+        assert!(parse_requests_rates(
+            r#"{"status": "success", "data": {"result": [{"metric": {"host": "a.com"}, "value": [1, "many"]}]}}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_traffic_verdict() {
+        let traffic = rates(&[("api.my-app.com", 0.3), ("www.my-app.com", 0.05)]);
+
+        assert_eq!(traffic_verdict(&BTreeMap::new(), 0.1, false), TrafficVerdict::BelowThreshold);
+        assert_eq!(traffic_verdict(&traffic, 0.5, false), TrafficVerdict::BelowThreshold);
+        // the threshold applies to the requests of every host
+        assert_eq!(
+            traffic_verdict(&traffic, 0.3, false),
+            TrafficVerdict::Refused {
+                requests_per_second: 0.35,
+                hosts: vec!["api.my-app.com".to_string(), "www.my-app.com".to_string()],
+            }
+        );
+        assert_eq!(
+            traffic_verdict(&traffic, 0.3, true),
+            TrafficVerdict::Forced {
+                requests_per_second: 0.35,
+                hosts: vec!["api.my-app.com".to_string(), "www.my-app.com".to_string()],
+            }
+        );
+        assert_eq!(traffic_verdict(&traffic, 0.5, true), TrafficVerdict::BelowThreshold);
+    }
+}
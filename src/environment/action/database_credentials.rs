@@ -0,0 +1,642 @@
+use crate::environment::action::secret_client::SecretClient;
+use crate::environment::models::secret_replication::secret_data_hash;
+use crate::errors::{CommandError, EngineError};
+use crate::events::EventDetails;
+use crate::infrastructure::models::cloud_provider::service::DatabaseType;
+use crate::infrastructure::models::cloud_provider::DeploymentTarget;
+use crate::io_models::models::EnvironmentVariable;
+use k8s_openapi::api::core::v1::{Pod, Secret};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::ByteString;
+use kube::api::{AttachParams, ListParams};
+use kube::Api;
+use rand::distributions::{Alphanumeric, DistString};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use tokio::io::AsyncWriteExt;
+
+const USERNAME_SECRET_KEY: &str = "username";
+const GENERATED_PASSWORD_LENGTH: usize = 32;
+
+/// Keys of the password in the credentials secret, the ones expected by the `auth.existingSecret` value of the
+/// database charts. The admin and the application users share the same password.
+fn password_secret_keys(db_type: DatabaseType) -> &'static [&'static str] {
+    match db_type {
+        DatabaseType::PostgreSQL => &["postgres-password", "password"],
+        DatabaseType::MySQL => &["mysql-root-password", "mysql-password", "mysql-replication-password"],
+        DatabaseType::MongoDB => &["mongodb-root-password", "mongodb-passwords"],
+        DatabaseType::Redis => &["redis-password"],
+    }
+}
+
+/// Key of the admin password in the credentials secret
+pub fn admin_password_secret_key(db_type: DatabaseType) -> &'static str {
+    password_secret_keys(db_type)[0]
+}
+
+/// Secret holding the credentials of a database, consumed by the database chart and the services connecting to it,
+/// so the password never appears in the pod specs.
+pub fn database_credentials_secret(
+    name: &str,
+    namespace: &str,
+    labels: BTreeMap<String, String>,
+    db_type: DatabaseType,
+    login: &str,
+    password: &str,
+) -> Secret {
+    let mut data = BTreeMap::from([(USERNAME_SECRET_KEY.to_string(), ByteString(login.as_bytes().to_vec()))]);
+    for key in password_secret_keys(db_type) {
+        data.insert(key.to_string(), ByteString(password.as_bytes().to_vec()));
+    }
+
+    Secret {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: Some(namespace.to_string()),
+            labels: Some(labels),
+            ..Default::default()
+        },
+        type_: Some("Opaque".to_string()),
+        data: Some(data),
+        ..Default::default()
+    }
+}
+
+/// Alphanumeric only, so the password never needs to be escaped in connection strings and database statements
+pub fn generate_database_password() -> String {
+    Alphanumeric.sample_string(&mut rand::thread_rng(), GENERATED_PASSWORD_LENGTH)
+}
+
+/// Makes the credentials secret match the `desired` one, it is only patched when the credentials changed.
+/// The password of an existing secret is never replaced by the desired one: the secret holds the password the
+/// database uses, which only changes through a rotation. Returns the secret as it is in the cluster.
+pub fn sync_database_credentials_secret(
+    client: &SecretClient,
+    event_details: &EventDetails,
+    desired: &Secret,
+) -> Result<Secret, Box<EngineError>> {
+    let name = desired.metadata.name.as_deref().unwrap_or_default();
+    let existing = client
+        .get(name)
+        .map_err(|err| Box::new(EngineError::new_k8s_get_secret_error(event_details.clone(), err)))?;
+
+    let mut desired = desired.clone();
+    if let Some(existing) = existing {
+        let existing_data = existing.data.unwrap_or_default();
+        let existing_password = existing_data
+            .iter()
+            .find(|(key, _)| key.as_str() != USERNAME_SECRET_KEY)
+            .map(|(_, password)| password.clone());
+        if let Some(existing_password) = existing_password {
+            for (key, value) in desired.data.iter_mut().flatten() {
+                if key.as_str() != USERNAME_SECRET_KEY {
+                    *value = existing_password.clone();
+                }
+            }
+        }
+        if desired.data.as_ref() == Some(&existing_data) {
+            return Ok(desired);
+        }
+    }
+
+    client
+        .apply(&desired)
+        .map_err(|err| Box::new(EngineError::new_k8s_patch_secret_error(event_details.clone(), err)))?;
+    Ok(desired)
+}
+
+/// Password stored in the credentials secret of a database
+pub fn database_password(secret: &Secret, db_type: DatabaseType) -> Option<String> {
+    secret
+        .data
+        .as_ref()
+        .and_then(|data| data.get(admin_password_secret_key(db_type)))
+        .and_then(|password| String::from_utf8(password.0.clone()).ok())
+}
+
+/// Variable of a service holding the password of a database of its environment. It is read from the credentials
+/// secret of the database, instead of being copied in the secret of the service.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseCredentialsRef {
+    pub variable: String,
+    pub secret_name: String,
+    pub secret_key: String,
+}
+
+/// Name the core gives to the variable holding the password of a database, eq. `QOVERY_POSTGRESQL_Z909E13C8_PASSWORD`
+fn password_variable_name(db_type: DatabaseType, db_short_id: &str) -> String {
+    format!(
+        "QOVERY_{}_{}_PASSWORD",
+        db_type.to_string().to_uppercase(),
+        db_short_id.to_uppercase()
+    )
+}
+
+/// Splits the variables of a service between the ones kept in its own secret, and the passwords of the `databases`
+/// (type, short id, credentials secret name) which are read from their credentials secret.
+pub fn split_database_credentials(
+    environment_variables: &[EnvironmentVariable],
+    databases: &[(DatabaseType, &str, String)],
+) -> (Vec<EnvironmentVariable>, Vec<DatabaseCredentialsRef>) {
+    let mut variables = Vec::with_capacity(environment_variables.len());
+    let mut credentials = vec![];
+    for variable in environment_variables {
+        match databases
+            .iter()
+            .find(|(db_type, db_short_id, _)| password_variable_name(*db_type, db_short_id) == variable.key)
+        {
+            Some((db_type, _, secret_name)) => credentials.push(DatabaseCredentialsRef {
+                variable: variable.key.clone(),
+                secret_name: secret_name.clone(),
+                secret_key: admin_password_secret_key(*db_type).to_string(),
+            }),
+            None => variables.push(variable.clone()),
+        }
+    }
+
+    (variables, credentials)
+}
+
+/// Variables of a service read from the credentials secrets of databases, and the checksum of these secrets
+#[derive(Serialize, Debug, Clone)]
+pub struct DatabaseCredentialsTeraContext {
+    pub variables: Vec<DatabaseCredentialsRef>,
+    pub checksum: Option<String>,
+}
+
+/// Variables the service keeps in its own secret, and the ones it reads from the credentials secrets of the databases
+/// of the environment
+pub fn service_database_credentials(
+    target: &DeploymentTarget,
+    environment_variables: &[EnvironmentVariable],
+    event_details: &EventDetails,
+) -> Result<(Vec<EnvironmentVariable>, DatabaseCredentialsTeraContext), Box<EngineError>> {
+    let databases = target
+        .environment
+        .databases
+        .iter()
+        .map(|db| (db.db_type(), db.id(), db.credentials_secret_name()))
+        .collect::<Vec<_>>();
+    let (variables, credentials) = split_database_credentials(environment_variables, &databases);
+    let checksum = database_credentials_checksum(
+        &SecretClient::new(target.kube.clone(), target.environment.namespace()),
+        event_details,
+        &credentials,
+    )?;
+
+    Ok((
+        variables,
+        DatabaseCredentialsTeraContext {
+            variables: credentials,
+            checksum,
+        },
+    ))
+}
+
+/// Checksum of the credentials secrets a service reads its variables from, set on its pod template so it rolls when a
+/// password is rotated. Secrets not created yet are skipped, None when the service doesn't read any.
+pub fn database_credentials_checksum(
+    client: &SecretClient,
+    event_details: &EventDetails,
+    credentials: &[DatabaseCredentialsRef],
+) -> Result<Option<String>, Box<EngineError>> {
+    let secret_names: BTreeSet<&str> = credentials.iter().map(|c| c.secret_name.as_str()).collect();
+    if secret_names.is_empty() {
+        return Ok(None);
+    }
+
+    let mut hasher = Sha256::new();
+    for name in secret_names {
+        let secret = client
+            .get(name)
+            .map_err(|err| Box::new(EngineError::new_k8s_get_secret_error(event_details.clone(), err)))?;
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        if let Some(secret) = secret {
+            hasher.update(secret_data_hash(&secret).as_bytes());
+        }
+        hasher.update([0]);
+    }
+
+    Ok(Some(format!("{:x}", hasher.finalize())))
+}
+
+/// Rotates the password of a database: the credentials secret is read first, then the database is updated with
+/// `new_password`, and the secret is only patched once the database accepted it. If the update fails, the secret
+/// still holds the password the database uses.
+pub fn rotate_database_credentials(
+    client: &SecretClient,
+    event_details: &EventDetails,
+    name: &str,
+    db_type: DatabaseType,
+    new_password: &str,
+    update_database: impl FnOnce(&str) -> Result<(), Box<EngineError>>,
+) -> Result<Secret, Box<EngineError>> {
+    let mut secret = client
+        .get(name)
+        .map_err(|err| Box::new(EngineError::new_k8s_get_secret_error(event_details.clone(), err)))?
+        .ok_or_else(|| {
+            Box::new(EngineError::new_k8s_get_secret_error(
+                event_details.clone(),
+                CommandError::new_from_safe_message(format!(
+                    "Secret `{name}` doesn't exist, credentials can only be rotated once the database is deployed"
+                )),
+            ))
+        })?;
+
+    update_database(new_password)?;
+
+    let data = secret.data.get_or_insert_with(BTreeMap::new);
+    for key in password_secret_keys(db_type) {
+        data.insert(key.to_string(), ByteString(new_password.as_bytes().to_vec()));
+    }
+    secret.metadata = ObjectMeta {
+        name: secret.metadata.name,
+        namespace: secret.metadata.namespace,
+        labels: secret.metadata.labels,
+        ..Default::default()
+    };
+    client
+        .apply(&secret)
+        .map_err(|err| Box::new(EngineError::new_k8s_patch_secret_error(event_details.clone(), err)))?;
+
+    Ok(secret)
+}
+
+fn database_container_name(db_type: DatabaseType) -> &'static str {
+    match db_type {
+        DatabaseType::PostgreSQL => "postgresql",
+        DatabaseType::MySQL => "mysql",
+        DatabaseType::MongoDB => "mongodb",
+        DatabaseType::Redis => "redis",
+    }
+}
+
+/// Script changing the passwords of the admin and application users from inside the database container.
+/// The new password is read from stdin so it is neither part of the exec request nor of the engine logs, the current
+/// one and the user names come from the container environment.
+fn change_password_script(db_type: DatabaseType) -> &'static str {
+    match db_type {
+        DatabaseType::PostgreSQL => {
+            r#"set -eu
+read -r NEW_PASSWORD
+export PGPASSWORD="${POSTGRES_POSTGRES_PASSWORD}"
+for user in "${POSTGRES_USER}" postgres; do
+  psql -v ON_ERROR_STOP=1 -U postgres -d postgres -c "ALTER USER \"${user}\" WITH PASSWORD '${NEW_PASSWORD}'"
+done"#
+        }
+        DatabaseType::MySQL => {
+            r#"set -eu
+read -r NEW_PASSWORD
+mysql -u root -p"${MYSQL_ROOT_PASSWORD}" -e "ALTER USER IF EXISTS '${MYSQL_USER}'@'%' IDENTIFIED BY '${NEW_PASSWORD}'; ALTER USER IF EXISTS 'root'@'%' IDENTIFIED BY '${NEW_PASSWORD}'; ALTER USER IF EXISTS 'root'@'localhost' IDENTIFIED BY '${NEW_PASSWORD}'""#
+        }
+        DatabaseType::MongoDB => {
+            r#"set -eu
+read -r NEW_PASSWORD
+mongosh admin --quiet -u "${MONGODB_ROOT_USER}" -p "${MONGODB_ROOT_PASSWORD}" --eval "db.getSiblingDB('${MONGODB_EXTRA_DATABASES}').changeUserPassword('${MONGODB_EXTRA_USERNAMES}', '${NEW_PASSWORD}'); db.changeUserPassword('${MONGODB_ROOT_USER}', '${NEW_PASSWORD}')""#
+        }
+        DatabaseType::Redis => {
+            r#"set -eu
+read -r NEW_PASSWORD
+[ "$(redis-cli -a "${REDIS_PASSWORD}" --no-auth-warning CONFIG SET requirepass "${NEW_PASSWORD}")" = "OK" ]"#
+        }
+    }
+}
+
+/// Changes the password of a container database by running the database client in its running pod
+pub async fn change_container_database_password(
+    kube: &kube::Client,
+    namespace: &str,
+    selector: &str,
+    db_type: DatabaseType,
+    new_password: &str,
+) -> Result<(), CommandError> {
+    let container_name = database_container_name(db_type);
+    let pods: Api<Pod> = Api::namespaced(kube.clone(), namespace);
+    // the selector also matches the health snapshot jobs, only the database pod runs the database container
+    let pod_name = pods
+        .list(&ListParams::default().labels(selector))
+        .await
+        .map_err(|err| {
+            CommandError::new(
+                format!("Cannot list pods with selector `{selector}`"),
+                Some(err.to_string()),
+                None,
+            )
+        })?
+        .items
+        .into_iter()
+        .find(|pod| {
+            pod.status.as_ref().and_then(|status| status.phase.as_deref()) == Some("Running")
+                && pod
+                    .spec
+                    .as_ref()
+                    .is_some_and(|spec| spec.containers.iter().any(|c| c.name == container_name))
+        })
+        .and_then(|pod| pod.metadata.name)
+        .ok_or_else(|| {
+            CommandError::new_from_safe_message(format!(
+                "No running database pod found with selector `{selector}`, the database must be running to rotate its credentials"
+            ))
+        })?;
+
+    let exec_error = |err: String| {
+        CommandError::new(
+            format!("Cannot change the database password from pod `{pod_name}`"),
+            Some(err),
+            None,
+        )
+    };
+    let mut process = pods
+        .exec(
+            &pod_name,
+            vec!["/bin/bash", "-c", change_password_script(db_type)],
+            &AttachParams::default()
+                .container(container_name)
+                .stdin(true)
+                .stdout(false)
+                .stderr(false),
+        )
+        .await
+        .map_err(|err| exec_error(err.to_string()))?;
+
+    let mut stdin = process
+        .stdin()
+        .ok_or_else(|| exec_error("exec stdin is not available".to_string()))?;
+    stdin
+        .write_all(format!("{new_password}\n").as_bytes())
+        .await
+        .map_err(|err| exec_error(err.to_string()))?;
+    drop(stdin);
+
+    let status = match process.take_status() {
+        Some(status) => status.await,
+        None => None,
+    };
+    process.join().await.map_err(|err| exec_error(err.to_string()))?;
+
+    match status.and_then(|status| status.status) {
+        Some(status) if status == "Success" => Ok(()),
+        status => Err(exec_error(format!(
+            "password change exited with status {}",
+            status.unwrap_or_else(|| "unknown".to_string())
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::action::test_utils::{command_error, event_details};
+    use crate::errors::Tag;
+    use std::cell::RefCell;
+
+    const SECRET_NAME: &str = "postgresql-abcdef-credentials";
+
+    fn secret(db_type: DatabaseType, password: &str) -> Secret {
+        database_credentials_secret(SECRET_NAME, "my-namespace", BTreeMap::new(), db_type, "superuser", password)
+    }
+
+    fn value(secret: &Secret, key: &str) -> Option<String> {
+        secret
+            .data
+            .as_ref()
+            .and_then(|data| data.get(key))
+            .map(|value| String::from_utf8(value.0.clone()).unwrap())
+    }
+
+    #[test]
+    fn test_database_credentials_secret_keys() {
+        let expected_keys = [
+            (DatabaseType::PostgreSQL, vec!["password", "postgres-password", "username"]),
+            (
+                DatabaseType::MySQL,
+                vec![
+                    "mysql-password",
+                    "mysql-replication-password",
+                    "mysql-root-password",
+                    "username",
+                ],
+            ),
+            (
+                DatabaseType::MongoDB,
+                vec!["mongodb-passwords", "mongodb-root-password", "username"],
+            ),
+            (DatabaseType::Redis, vec!["redis-password", "username"]),
+        ];
+
+        for (db_type, keys) in expected_keys {
+            let secret = secret(db_type, "p4ssw0rd");
+            assert_eq!(secret.data.as_ref().unwrap().keys().collect::<Vec<_>>(), keys);
+            assert_eq!(value(&secret, "username").as_deref(), Some("superuser"));
+            assert_eq!(value(&secret, admin_password_secret_key(db_type)).as_deref(), Some("p4ssw0rd"));
+        }
+    }
+
+    #[test]
+    fn test_generate_database_password() {
+        let password = generate_database_password();
+        assert_eq!(password.len(), GENERATED_PASSWORD_LENGTH);
+        assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(password, generate_database_password());
+    }
+
+    #[test]
+    fn test_sync_database_credentials_secret() {
+        // same credentials, nothing to patch: apply is not mocked and would panic if called
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret(DatabaseType::PostgreSQL, "p4ssw0rd"))));
+        let desired = secret(DatabaseType::PostgreSQL, "p4ssw0rd");
+        let synced = sync_database_credentials_secret(&client, &event_details(), &desired).unwrap();
+        assert_eq!(synced.data, desired.data);
+
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(None));
+        faux::when!(client.apply(_)).then_return(Ok(()));
+        let synced = sync_database_credentials_secret(&client, &event_details(), &desired).unwrap();
+        assert_eq!(synced.data, desired.data);
+
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Err(command_error()));
+        let err = sync_database_credentials_secret(&client, &event_details(), &desired).unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sGetSecretError);
+    }
+
+    #[test]
+    fn test_sync_database_credentials_secret_keeps_the_existing_password() {
+        // the password differs from the existing one only: nothing to patch
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret(DatabaseType::MySQL, "rotated"))));
+        let desired = secret(DatabaseType::MySQL, "from-the-payload");
+        let synced = sync_database_credentials_secret(&client, &event_details(), &desired).unwrap();
+        for key in password_secret_keys(DatabaseType::MySQL) {
+            assert_eq!(value(&synced, key).as_deref(), Some("rotated"));
+        }
+
+        // the login changed: the secret is patched, with the existing password
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret(DatabaseType::MySQL, "rotated"))));
+        faux::when!(client.apply(_)).then_return(Ok(()));
+        let desired = database_credentials_secret(
+            SECRET_NAME,
+            "my-namespace",
+            BTreeMap::new(),
+            DatabaseType::MySQL,
+            "another-user",
+            "from-the-payload",
+        );
+        let synced = sync_database_credentials_secret(&client, &event_details(), &desired).unwrap();
+        assert_eq!(value(&synced, "username").as_deref(), Some("another-user"));
+        assert_eq!(database_password(&synced, DatabaseType::MySQL).as_deref(), Some("rotated"));
+    }
+
+    #[test]
+    fn test_split_database_credentials() {
+        let variable = |key: &str| EnvironmentVariable {
+            key: key.to_string(),
+            value: "dmFsdWU=".to_string(),
+            is_secret: true,
+        };
+        let environment_variables = vec![
+            variable("QOVERY_POSTGRESQL_Z909E13C8_PASSWORD"),
+            variable("QOVERY_POSTGRESQL_Z909E13C8_LOGIN"),
+            variable("QOVERY_REDIS_ZABCDEF12_PASSWORD"),
+            variable("MY_VARIABLE"),
+        ];
+        let databases = [(
+            DatabaseType::PostgreSQL,
+            "z909e13c8",
+            "postgresql-z909e13c8-credentials".to_string(),
+        )];
+
+        let (variables, credentials) = split_database_credentials(&environment_variables, &databases);
+
+        // the redis database isn't part of the environment, its password stays in the secret of the service
+        assert_eq!(
+            variables.iter().map(|v| v.key.as_str()).collect::<Vec<_>>(),
+            vec![
+                "QOVERY_POSTGRESQL_Z909E13C8_LOGIN",
+                "QOVERY_REDIS_ZABCDEF12_PASSWORD",
+                "MY_VARIABLE"
+            ]
+        );
+        assert_eq!(
+            credentials,
+            vec![DatabaseCredentialsRef {
+                variable: "QOVERY_POSTGRESQL_Z909E13C8_PASSWORD".to_string(),
+                secret_name: "postgresql-z909e13c8-credentials".to_string(),
+                secret_key: "postgres-password".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_database_credentials_checksum_changes_with_the_password() {
+        let credentials = vec![DatabaseCredentialsRef {
+            variable: "QOVERY_POSTGRESQL_Z909E13C8_PASSWORD".to_string(),
+            secret_name: SECRET_NAME.to_string(),
+            secret_key: "postgres-password".to_string(),
+        }];
+        let checksum = |password: &str| {
+            let mut client = SecretClient::faux();
+            faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret(DatabaseType::PostgreSQL, password))));
+            database_credentials_checksum(&client, &event_details(), &credentials).unwrap()
+        };
+
+        assert!(checksum("p4ssw0rd").is_some());
+        assert_eq!(checksum("p4ssw0rd"), checksum("p4ssw0rd"));
+        assert_ne!(checksum("p4ssw0rd"), checksum("n3w"));
+
+        // no secret is read when the service doesn't read any credentials
+        let client = SecretClient::faux();
+        assert_eq!(database_credentials_checksum(&client, &event_details(), &[]).unwrap(), None);
+
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Err(command_error()));
+        let err = database_credentials_checksum(&client, &event_details(), &credentials).unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sGetSecretError);
+    }
+
+    #[test]
+    fn test_rotate_database_credentials() {
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret(DatabaseType::MySQL, "old"))));
+        faux::when!(client.apply(_)).then_return(Ok(()));
+        let updated_with = RefCell::new(None);
+        let rotated = rotate_database_credentials(
+            &client,
+            &event_details(),
+            SECRET_NAME,
+            DatabaseType::MySQL,
+            "n3w",
+            |password| {
+                *updated_with.borrow_mut() = Some(password.to_string());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(updated_with.into_inner().as_deref(), Some("n3w"));
+        for key in password_secret_keys(DatabaseType::MySQL) {
+            assert_eq!(value(&rotated, key).as_deref(), Some("n3w"));
+        }
+        assert_eq!(value(&rotated, "username").as_deref(), Some("superuser"));
+    }
+
+    #[test]
+    fn test_rotate_database_credentials_ordering() {
+        // the secret can't be read, the database is never updated
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Err(command_error()));
+        let err =
+            rotate_database_credentials(&client, &event_details(), SECRET_NAME, DatabaseType::Redis, "n3w", |_| {
+                panic!("the database must not be updated")
+            })
+            .unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sGetSecretError);
+
+        // the database has never been deployed, there is nothing to rotate
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(None));
+        let err =
+            rotate_database_credentials(&client, &event_details(), SECRET_NAME, DatabaseType::Redis, "n3w", |_| {
+                panic!("the database must not be updated")
+            })
+            .unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sGetSecretError);
+
+        // the database refused the new password: apply is not mocked and would panic if the secret was patched
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret(DatabaseType::Redis, "old"))));
+        let err =
+            rotate_database_credentials(&client, &event_details(), SECRET_NAME, DatabaseType::Redis, "n3w", |_| {
+                Err(Box::new(EngineError::new_task_cancellation_requested(event_details())))
+            })
+            .unwrap_err();
+        assert_eq!(err.tag(), &Tag::TaskCancellationRequested);
+
+        // the database has been updated but the secret can't be patched
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret(DatabaseType::Redis, "old"))));
+        faux::when!(client.apply(_)).then_return(Err(command_error()));
+        let err =
+            rotate_database_credentials(&client, &event_details(), SECRET_NAME, DatabaseType::Redis, "n3w", |_| Ok(()))
+                .unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sPatchSecretError);
+    }
+
+    #[test]
+    fn test_change_password_script_reads_password_from_stdin() {
+        for db_type in [
+            DatabaseType::PostgreSQL,
+            DatabaseType::MySQL,
+            DatabaseType::MongoDB,
+            DatabaseType::Redis,
+        ] {
+            let script = change_password_script(db_type);
+            assert!(script.starts_with("set -eu\nread -r NEW_PASSWORD\n"));
+            assert!(script.contains("${NEW_PASSWORD}"));
+        }
+    }
+}
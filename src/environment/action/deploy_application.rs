@@ -1,3 +1,6 @@
+use crate::environment::action::check_draining::DrainingCheck;
+use crate::environment::action::check_resilience::check_resilience;
+use crate::environment::action::check_traffic::check_no_traffic_before_deletion;
 use crate::environment::action::deploy_helm::HelmDeployment;
 use crate::environment::action::pause_service::PauseServiceAction;
 use crate::environment::action::DeploymentAction;
@@ -7,11 +10,12 @@ use crate::environment::models::application::{
 use crate::environment::models::types::{CloudProvider, ToTeraContext};
 use crate::environment::report::application::reporter::ApplicationDeploymentReporter;
 use crate::environment::report::execute_long_deployment;
-use crate::errors::{CommandError, EngineError};
+use crate::errors::{CommandError, EngineError, Tag};
 use crate::events::{EnvironmentStep, Stage};
 use crate::helm::{ChartInfo, HelmAction, HelmChartNamespaces};
 use crate::infrastructure::models::cloud_provider::service::{Action, Service};
 use crate::infrastructure::models::cloud_provider::{DeploymentTarget, Kind};
+use crate::io_models::feature_flags::FeatureFlag;
 use crate::kubers_utils::{kube_delete_all_from_selector, KubeDeleteMode};
 use crate::runtime::block_on;
 use k8s_openapi::api::core::v1::PersistentVolumeClaim;
@@ -22,7 +26,10 @@ use std::path::PathBuf;
 use std::time::Duration;
 use tera::Context;
 
-use super::utils::{delete_nlb_or_alb_service, update_pvcs};
+use super::utils::{
+    delete_nlb_or_alb_service, restart_statefulset_after_pvcs_update, update_pvcs, warn_if_promotion_pin_is_replaced,
+    KubeObjectKind,
+};
 
 impl<T: CloudProvider> DeploymentAction for Application<T>
 where
@@ -31,6 +38,17 @@ where
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         let long_task = |logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
             let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
+            // the image has just been built, it replaces any image promoted from another environment
+            warn_if_promotion_pin_is_replaced(
+                target,
+                &self.kube_label_selector(),
+                if self.is_stateful() {
+                    KubeObjectKind::Statefulset
+                } else {
+                    KubeObjectKind::Deployment
+                },
+                logger,
+            );
             // If the service have been paused, we must ensure we un-pause it first as hpa will not kick in
             let _ = PauseServiceAction::new(
                 self.kube_label_selector(),
@@ -41,24 +59,29 @@ where
             )
             .unpause_if_needed(target);
 
-            match get_application_with_invalid_storage_size(
+            let resized_statefulset_storage = match get_application_with_invalid_storage_size(
                 self,
                 &target.kube,
                 target.environment.namespace(),
                 &event_details,
             ) {
-                Ok(invalid_statefulset_storage) => {
-                    if let Some(invalid_statefulset_storage) = invalid_statefulset_storage {
-                        update_pvcs(
-                            self.as_service(),
-                            &invalid_statefulset_storage,
-                            target.environment.namespace(),
-                            &event_details,
-                            &target.kube,
-                        )?;
-                    }
+                Ok(Some(invalid_statefulset_storage)) => {
+                    update_pvcs(
+                        self.as_service(),
+                        &invalid_statefulset_storage,
+                        target.environment.namespace(),
+                        &event_details,
+                        &target.kube,
+                    )?;
+                    Some(invalid_statefulset_storage)
+                }
+                Ok(None) => None,
+                // volume claim templates can't be shrunk, no need to go further
+                Err(e) if e.tag() == &Tag::K8sStorageCannotBeReduced => return Err(e),
+                Err(e) => {
+                    logger.warning(e.to_string());
+                    None
                 }
-                Err(e) => logger.warning(e.to_string()),
             };
 
             let chart = ChartInfo {
@@ -77,7 +100,8 @@ where
                 PathBuf::from(self.helm_chart_dir()),
                 None,
                 chart,
-            );
+            )
+            .with_release_owner(*self.long_id(), Some(self.legacy_helm_release_name()));
 
             if target.cloud_provider.kind() == Kind::Aws {
                 delete_nlb_or_alb_service(
@@ -85,11 +109,46 @@ where
                     target.environment.namespace(),
                     format!("qovery.com/service-id={}", self.long_id()).as_str(),
                     target.kubernetes.advanced_settings().aws_eks_enable_alb_controller,
-                    event_details,
+                    event_details.clone(),
                 )?;
             }
 
+            // Only services exposing ports are behind endpoints, others have nothing to drain
+            let draining_check_enabled = target.kubernetes.context().is_enabled(FeatureFlag::DrainingCheck);
+            let draining_check = if self.ports.is_empty() || !draining_check_enabled {
+                None
+            } else {
+                Some(DrainingCheck::start(
+                    target.kube.clone(),
+                    target.environment.namespace(),
+                    &self.kube_label_selector(),
+                ))
+            };
             helm.on_create(target)?;
+            if let Some(resized_statefulset_storage) = &resized_statefulset_storage {
+                if restart_statefulset_after_pvcs_update(
+                    resized_statefulset_storage,
+                    target.environment.namespace(),
+                    &event_details,
+                    &target.kube,
+                )? {
+                    logger.info("🔄 Pods restarted to resize the filesystem of their volumes".to_string());
+                }
+            }
+            if let Some(draining_check) = draining_check {
+                draining_check.stop(logger);
+            }
+
+            if self.advanced_settings().deployment_resilience_check {
+                check_resilience(
+                    self.name(),
+                    &self.kube_label_selector(),
+                    self.startup_timeout(),
+                    target,
+                    logger,
+                    &event_details,
+                )?;
+            }
 
             Ok(())
         };
@@ -118,6 +177,8 @@ where
         execute_long_deployment(
             ApplicationDeploymentReporter::new(self, target, Action::Delete),
             |logger: &EnvProgressLogger| {
+                check_no_traffic_before_deletion(self.long_id(), target, &event_details, logger)?;
+
                 let chart = ChartInfo {
                     name: self.helm_release_name(),
                     namespace: HelmChartNamespaces::Custom,
@@ -132,7 +193,8 @@ where
                     PathBuf::from(self.helm_chart_dir().as_str()),
                     None,
                     chart,
-                );
+                )
+                .with_release_owner(*self.long_id(), Some(self.legacy_helm_release_name()));
 
                 helm.on_delete(target)?;
 
@@ -1,3 +1,6 @@
+use crate::environment::action::check_draining::DrainingCheck;
+use crate::environment::action::check_resilience::check_resilience;
+use crate::environment::action::check_traffic::check_no_traffic_before_deletion;
 use crate::environment::action::deploy_helm::HelmDeployment;
 use crate::environment::action::pause_service::PauseServiceAction;
 use crate::environment::action::DeploymentAction;
@@ -5,19 +8,21 @@ use crate::environment::models::container::{get_container_with_invalid_storage_s
 use crate::environment::models::types::{CloudProvider, ToTeraContext};
 use crate::environment::report::application::reporter::ApplicationDeploymentReporter;
 use crate::environment::report::{execute_long_deployment, DeploymentTaskImpl};
-use crate::errors::{CommandError, EngineError};
+use crate::errors::{CommandError, EngineError, Tag};
 use crate::events::{EnvironmentStep, Stage};
 use crate::helm::{ChartInfo, HelmAction, HelmChartNamespaces};
 use crate::infrastructure::models::cloud_provider::service::{Action, Service};
 use crate::infrastructure::models::cloud_provider::{DeploymentTarget, Kind};
+use crate::io_models::feature_flags::FeatureFlag;
 use crate::kubers_utils::{kube_delete_all_from_selector, KubeDeleteMode};
 use crate::runtime::block_on;
 use k8s_openapi::api::core::v1::PersistentVolumeClaim;
 
+use crate::environment::action::promote_image::promote_image;
 use crate::environment::action::restart_service::RestartServiceAction;
 use crate::environment::action::utils::{
-    delete_cached_image, delete_nlb_or_alb_service, get_last_deployed_image, mirror_image_if_necessary, update_pvcs,
-    KubeObjectKind,
+    delete_cached_image, delete_nlb_or_alb_service, ensure_image_mirrored, get_last_deployed_image,
+    restart_statefulset_after_pvcs_update, update_pvcs, warn_if_promotion_pin_is_replaced, KubeObjectKind,
 };
 use crate::environment::report::logger::{EnvProgressLogger, EnvSuccessLogger};
 use std::path::PathBuf;
@@ -29,30 +34,35 @@ where
 {
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
-        let metrics_registry = target.metrics_registry.clone();
         struct TaskContext {
             last_deployed_image: Option<String>,
         }
 
         // We first mirror the image if needed
         let pre_task = |logger: &EnvProgressLogger| -> Result<TaskContext, Box<EngineError>> {
-            mirror_image_if_necessary(
-                self.long_id(),
-                &self.source,
-                target,
-                logger,
-                event_details.clone(),
-                metrics_registry.clone(),
-            )?;
-
-            let last_image = block_on(get_last_deployed_image(
-                target.kube.clone(),
-                &self.kube_label_selector(),
+            let kube_object_kind = || {
                 if self.is_stateful() {
                     KubeObjectKind::Statefulset
                 } else {
                     KubeObjectKind::Deployment
-                },
+                }
+            };
+
+            match &self.source.promotion {
+                Some(promotion) => {
+                    promote_image(self.long_id(), &self.source, promotion, target, logger, event_details.clone())?
+                }
+                None => {
+                    warn_if_promotion_pin_is_replaced(target, &self.kube_label_selector(), kube_object_kind(), logger);
+                    // the image has been mirrored with the other images of the environment, the tag may have moved since
+                    ensure_image_mirrored(self.long_id(), &self.source, target, logger, event_details.clone())?;
+                }
+            }
+
+            let last_image = block_on(get_last_deployed_image(
+                target.kube.clone(),
+                &self.kube_label_selector(),
+                kube_object_kind(),
                 target.environment.namespace(),
             ));
 
@@ -72,24 +82,29 @@ where
             )
             .unpause_if_needed(target);
 
-            match get_container_with_invalid_storage_size(
+            let resized_statefulset_storage = match get_container_with_invalid_storage_size(
                 self,
                 &target.kube,
                 target.environment.namespace(),
                 &event_details,
             ) {
-                Ok(invalid_statefulset_storage) => {
-                    if let Some(invalid_statefulset_storage) = invalid_statefulset_storage {
-                        update_pvcs(
-                            self.as_service(),
-                            &invalid_statefulset_storage,
-                            target.environment.namespace(),
-                            &event_details,
-                            &target.kube,
-                        )?;
-                    }
+                Ok(Some(invalid_statefulset_storage)) => {
+                    update_pvcs(
+                        self.as_service(),
+                        &invalid_statefulset_storage,
+                        target.environment.namespace(),
+                        &event_details,
+                        &target.kube,
+                    )?;
+                    Some(invalid_statefulset_storage)
+                }
+                Ok(None) => None,
+                // volume claim templates can't be shrunk, no need to go further
+                Err(e) if e.tag() == &Tag::K8sStorageCannotBeReduced => return Err(e),
+                Err(e) => {
+                    logger.warning(e.to_string());
+                    None
                 }
-                Err(e) => logger.warning(e.to_string()),
             };
 
             let chart = ChartInfo {
@@ -108,7 +123,8 @@ where
                 PathBuf::from(self.helm_chart_dir()),
                 None,
                 chart,
-            );
+            )
+            .with_release_owner(*self.long_id(), None);
 
             if target.cloud_provider.kind() == Kind::Aws {
                 delete_nlb_or_alb_service(
@@ -120,7 +136,42 @@ where
                 )?;
             }
 
+            // Only services exposing ports are behind endpoints, others have nothing to drain
+            let draining_check_enabled = target.kubernetes.context().is_enabled(FeatureFlag::DrainingCheck);
+            let draining_check = if self.ports.is_empty() || !draining_check_enabled {
+                None
+            } else {
+                Some(DrainingCheck::start(
+                    target.kube.clone(),
+                    target.environment.namespace(),
+                    &self.kube_label_selector(),
+                ))
+            };
             helm.on_create(target)?;
+            if let Some(draining_check) = draining_check {
+                draining_check.stop(logger);
+            }
+            if let Some(resized_statefulset_storage) = &resized_statefulset_storage {
+                if restart_statefulset_after_pvcs_update(
+                    resized_statefulset_storage,
+                    target.environment.namespace(),
+                    &event_details,
+                    &target.kube,
+                )? {
+                    logger.info("🔄 Pods restarted to resize the filesystem of their volumes".to_string());
+                }
+            }
+
+            if self.advanced_settings().deployment_resilience_check {
+                check_resilience(
+                    self.name(),
+                    &self.kube_label_selector(),
+                    self.startup_timeout(),
+                    target,
+                    logger,
+                    &event_details,
+                )?;
+            }
 
             Ok(state)
         };
@@ -194,6 +245,8 @@ where
 
         // Execute the deployment
         let long_task = |logger: &EnvProgressLogger, state: TaskContext| -> Result<TaskContext, Box<EngineError>> {
+            check_no_traffic_before_deletion(self.long_id(), target, &event_details, logger)?;
+
             let chart = ChartInfo {
                 name: self.helm_release_name(),
                 namespace: HelmChartNamespaces::Custom,
@@ -207,7 +260,8 @@ where
                 PathBuf::from(self.helm_chart_dir().as_str()),
                 None,
                 chart,
-            );
+            )
+            .with_release_owner(*self.long_id(), None);
 
             helm.on_delete(target)?;
 
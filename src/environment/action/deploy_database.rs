@@ -1,14 +1,26 @@
 use crate::cmd;
 use crate::cmd::command::{ExecutableCommand, QoveryCommand};
+use crate::cmd::terraform::{self, TerraformError};
 use crate::constants::AWS_DEFAULT_REGION;
 use crate::environment::action::check_dns::CheckDnsForDomains;
+use crate::environment::action::database_credentials::{
+    change_container_database_password, database_credentials_secret, database_password, generate_database_password,
+    rotate_database_credentials, sync_database_credentials_secret,
+};
 use crate::environment::action::deploy_helm::HelmDeployment;
 use crate::environment::action::deploy_terraform::TerraformDeployment;
 use crate::environment::action::pause_service::PauseServiceAction;
+use crate::environment::action::secret_client::SecretClient;
 use crate::environment::action::DeploymentAction;
 use crate::environment::models::database::{
-    get_database_with_invalid_storage_size, Container, Database, DatabaseError, DatabaseService, DatabaseType, Managed,
+    get_database_with_invalid_storage_size, Container, Database, DatabaseError, DatabaseMode, DatabaseService,
+    DatabaseType, Managed,
+};
+use crate::environment::models::database_health::{
+    check_health_snapshot, check_risky_operation_is_allowed, latest_health_snapshot, risky_operation,
 };
+use crate::environment::models::database_parameters::DatabaseParameters;
+use crate::environment::models::database_utils::check_managed_database_version_upgrade;
 use crate::environment::models::types::{CloudProvider, ToTeraContext, VersionsNumber};
 use crate::environment::report::database::reporter::DatabaseDeploymentReporter;
 use crate::environment::report::{execute_long_deployment, DeploymentTaskImpl};
@@ -18,11 +30,14 @@ use crate::helm::{ChartInfo, ChartSetValue, HelmAction, HelmChartNamespaces};
 use crate::infrastructure::models::cloud_provider::service::{get_database_terraform_config, Action, Service};
 use crate::infrastructure::models::cloud_provider::Kind::{self, Aws};
 use crate::infrastructure::models::cloud_provider::{service, DeploymentTarget};
+use crate::io_models::database::DatabaseOptions;
 use crate::kubers_utils::{kube_delete_all_from_selector, KubeDeleteMode};
 use crate::runtime::block_on;
 use crate::services::aws::models::QoveryAwsSdkConfigManagedDatabase;
 use aws_types::SdkConfig;
-use k8s_openapi::api::core::v1::PersistentVolumeClaim;
+use chrono::Utc;
+use k8s_openapi::api::core::v1::{ConfigMap, PersistentVolumeClaim, Secret};
+use kube::Api;
 use semver::Version;
 use serde::Deserialize;
 use std::collections::BTreeMap;
@@ -40,7 +55,7 @@ use std::path::PathBuf;
 use std::thread;
 use std::time::{Duration, Instant};
 
-use super::utils::{are_pvcs_bound, delete_nlb_or_alb_service, update_pvcs};
+use super::utils::{are_pvcs_bound, delete_nlb_or_alb_service, restart_statefulset_after_pvcs_update, update_pvcs};
 
 const DB_READY_STATE: &str = "available";
 const DB_STOPPED_STATE: &str = "stopped";
@@ -264,17 +279,45 @@ fn await_db_state(
     }
 }
 
-fn on_create_managed_impl<C: CloudProvider, T: DatabaseType<C, Managed>>(
+/// Surfaces the custom parameters, and the ones requiring a restart, before applying them
+fn log_database_parameters(logger: &EnvProgressLogger, parameters: &DatabaseParameters, is_container: bool) {
+    if parameters.is_empty() {
+        return;
+    }
+
+    logger.info(format!("⚙️ Applying database parameters: {parameters}"));
+    if is_container {
+        // parameters are part of the container configuration, the pod is rolled out to apply any change
+        logger.warning("⚠️ Changing database parameters restarts the database".to_string());
+        return;
+    }
+
+    let static_parameters = parameters
+        .static_parameters()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>();
+    if !static_parameters.is_empty() {
+        logger.warning(format!(
+            "⚠️ Static parameters ({}) are only applied after the next database restart",
+            static_parameters.join(", ")
+        ));
+    }
+}
+
+fn on_create_managed_impl<C: CloudProvider, T: DatabaseType<C, Managed, DatabaseOptions = DatabaseOptions>>(
     db: &Database<C, Managed, T>,
     logger: &EnvProgressLogger,
     event_details: EventDetails,
     target: &DeploymentTarget,
+    password: &str,
 ) -> Result<(), Box<EngineError>>
 where
     Database<C, Managed, T>: DatabaseService,
 {
     let workspace_dir = db.workspace_directory();
-    let tera_context = db.to_tera_context(target)?;
+    let mut tera_context = db.to_tera_context(target)?;
+    tera_context.insert("database_password", password);
+    log_database_parameters(logger, &db.options.parameters, false);
 
     // Execute terraform to provision database on cloud provider side
     let terraform_deploy = TerraformDeployment::new(
@@ -415,6 +458,172 @@ where
     }
 }
 
+fn credentials_secret<C, M, T>(db: &Database<C, M, T>, target: &DeploymentTarget) -> Secret
+where
+    C: CloudProvider,
+    M: DatabaseMode,
+    T: DatabaseType<C, M, DatabaseOptions = DatabaseOptions>,
+{
+    let mut labels = BTreeMap::from([
+        ("qovery.com/service-id".to_string(), db.long_id.to_string()),
+        ("qovery.com/service-type".to_string(), "database".to_string()),
+        ("qovery.com/environment-id".to_string(), target.environment.long_id.to_string()),
+        (
+            "qovery.com/project-id".to_string(),
+            target.environment.project_long_id.to_string(),
+        ),
+    ]);
+    labels.extend(db.labels_group.common.clone());
+
+    database_credentials_secret(
+        &db.credentials_secret_name(),
+        target.environment.namespace(),
+        labels,
+        T::db_type(),
+        &db.options.login,
+        &db.options.password,
+    )
+}
+
+/// Outcome of the deployment of the credentials secret of a database
+enum DeployedCredentials {
+    /// The database already runs with the new password
+    Rotated,
+    /// Password of the credentials secret, the one the database must be deployed with
+    Current(String),
+}
+
+/// Creates the credentials secret of the database, or rotates its password when requested: `update_database` applies
+/// the new password to the database before the secret is patched.
+fn deploy_database_credentials<C, M, T>(
+    db: &Database<C, M, T>,
+    target: &DeploymentTarget,
+    logger: &EnvProgressLogger,
+    event_details: &EventDetails,
+    update_database: impl FnOnce(&str) -> Result<(), Box<EngineError>>,
+) -> Result<DeployedCredentials, Box<EngineError>>
+where
+    C: CloudProvider,
+    M: DatabaseMode,
+    T: DatabaseType<C, M, DatabaseOptions = DatabaseOptions>,
+{
+    if target.is_dry_run_deploy {
+        return Ok(DeployedCredentials::Current(db.options.password.clone()));
+    }
+
+    let client = SecretClient::new(target.kube.clone(), target.environment.namespace());
+    if !db.options.rotate_credentials {
+        let secret = sync_database_credentials_secret(&client, event_details, &credentials_secret(db, target))?;
+        return Ok(DeployedCredentials::Current(
+            database_password(&secret, T::db_type()).unwrap_or_else(|| db.options.password.clone()),
+        ));
+    }
+
+    logger.info("🔑 Rotating database credentials".to_string());
+    let new_password = generate_database_password();
+    rotate_database_credentials(
+        &client,
+        event_details,
+        &db.credentials_secret_name(),
+        T::db_type(),
+        &new_password,
+        update_database,
+    )?;
+
+    // The core stores the new password, and redeploys the services using the database with it
+    {
+        let mut json: BTreeMap<&str, &str> = BTreeMap::new();
+        json.insert("password", new_password.as_str());
+        logger.core_configuration_for_database(
+            "🔑 Database credentials rotated, environment variables are going to be updated with the new password"
+                .to_string(),
+            serde_json::to_string(&json).unwrap_or_default(),
+        );
+    }
+
+    Ok(DeployedCredentials::Rotated)
+}
+
+/// Password the database runs with: the one of its credentials secret, the payload one until the secret exists
+fn current_database_password<C, M, T>(
+    db: &Database<C, M, T>,
+    target: &DeploymentTarget,
+    event_details: &EventDetails,
+) -> Result<String, Box<EngineError>>
+where
+    C: CloudProvider,
+    M: DatabaseMode,
+    T: DatabaseType<C, M, DatabaseOptions = DatabaseOptions>,
+{
+    let secret = SecretClient::new(target.kube.clone(), target.environment.namespace())
+        .get(&db.credentials_secret_name())
+        .map_err(|err| Box::new(EngineError::new_k8s_get_secret_error(event_details.clone(), err)))?;
+
+    Ok(secret
+        .and_then(|secret| database_password(&secret, T::db_type()))
+        .unwrap_or_else(|| db.options.password.clone()))
+}
+
+fn delete_database_credentials<C, M, T>(
+    db: &Database<C, M, T>,
+    target: &DeploymentTarget,
+    event_details: &EventDetails,
+) -> Result<(), Box<EngineError>>
+where
+    C: CloudProvider,
+    M: DatabaseMode,
+    T: DatabaseType<C, M>,
+{
+    SecretClient::new(target.kube.clone(), target.environment.namespace())
+        .delete(&db.credentials_secret_name())
+        .map_err(|err| Box::new(EngineError::new_k8s_patch_secret_error(event_details.clone(), err)))
+}
+
+/// RDS can only upgrade an instance to the next major version, so skipping majors is refused before terraform
+/// starts to modify the instance.
+fn check_managed_database_upgrade<C: CloudProvider, T: DatabaseType<C, Managed>>(
+    db: &Database<C, Managed, T>,
+    event_details: &EventDetails,
+    target: &DeploymentTarget,
+) -> Result<(), Box<EngineError>> {
+    if target.cloud_provider.kind() != Aws
+        || !matches!(T::db_type(), service::DatabaseType::PostgreSQL | service::DatabaseType::MySQL)
+    {
+        return Ok(());
+    }
+    let Some(sdk_config) = target.cloud_provider.aws_sdk_client() else {
+        return Ok(());
+    };
+
+    // if the instance doesn't exist yet, there is nothing to upgrade
+    let current_version = match block_on(sdk_config.find_managed_rds_database(&db.fqdn_id)) {
+        Ok(result) => result
+            .db_instances()
+            .first()
+            .and_then(|instance| instance.engine_version())
+            .and_then(|version| version.parse::<VersionsNumber>().ok()),
+        Err(_) => None,
+    };
+    let Some(current_version) = current_version else {
+        return Ok(());
+    };
+
+    check_managed_database_version_upgrade(T::db_type(), &current_version, &db.version).map_err(|err| {
+        Box::new(EngineError::new_terraform_error(
+            event_details.clone(),
+            TerraformError::ManagedDatabaseError {
+                database_name: Some(db.fqdn_id.clone()),
+                database_type: T::db_type().to_string(),
+                database_error_sub_type: Box::new(terraform::DatabaseError::VersionUpgradeNotPossible {
+                    from: current_version.to_string(),
+                    to: db.version.to_string(),
+                }),
+                raw_message: err.to_string(),
+            },
+        ))
+    })
+}
+
 #[async_trait]
 impl QoveryAwsSdkConfigManagedDatabase for SdkConfig {
     async fn find_managed_rds_database(
@@ -541,7 +750,8 @@ fn managed_database_exists(
 }
 
 // For Managed database
-impl<C: CloudProvider, T: DatabaseType<C, Managed>> DeploymentAction for Database<C, Managed, T>
+impl<C: CloudProvider, T: DatabaseType<C, Managed, DatabaseOptions = DatabaseOptions>> DeploymentAction
+    for Database<C, Managed, T>
 where
     Database<C, Managed, T>: ToTeraContext,
 {
@@ -549,7 +759,16 @@ where
         let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
         let pre_run = |_: &EnvProgressLogger| -> Result<(), Box<EngineError>> { Ok(()) };
         let run = |logger: &EnvProgressLogger, _: ()| -> Result<(), Box<EngineError>> {
-            on_create_managed_impl(self, logger, event_details.clone(), target)
+            check_managed_database_upgrade(self, &event_details, target)?;
+            // a rotation re-applies terraform with the new password, before it is patched in the credentials secret
+            match deploy_database_credentials(self, target, logger, &event_details, |password| {
+                on_create_managed_impl(self, logger, event_details.clone(), target, password)
+            })? {
+                DeployedCredentials::Rotated => Ok(()),
+                DeployedCredentials::Current(password) => {
+                    on_create_managed_impl(self, logger, event_details.clone(), target, &password)
+                }
+            }
         };
         let post_run = |logger: &EnvSuccessLogger, _: ()| {
             if self.publicly_accessible {
@@ -648,12 +867,13 @@ where
                 )? {
                     // if db has never been deployed. No need to go further
                     info!("Managed database not found on cloud provider. Assuming it does not exist");
-                    return Ok(());
+                    return delete_database_credentials(self, target, &event_details);
                 }
 
                 // Ensure it's in a ready state
                 // because if not, the deletion is going to fail (i.e: cannot snapshot paused db)
-                on_create_managed_impl(self, logger, event_details.clone(), target)?;
+                let password = current_database_password(self, target, &event_details)?;
+                on_create_managed_impl(self, logger, event_details.clone(), target, &password)?;
 
                 let workspace_dir = self.workspace_directory();
 
@@ -685,7 +905,9 @@ where
                     chart,
                 );
 
-                helm.on_delete(target)
+                helm.on_delete(target)?;
+
+                delete_database_credentials(self, target, &event_details)
             },
         )
     }
@@ -701,8 +923,64 @@ where
     }
 }
 
+/// Reads the latest health snapshot of a container database, reports its warnings, and refuses risky operations
+/// when the database is not healthy enough for them.
+fn check_container_database_health<C, T>(
+    db: &Database<C, Container, T>,
+    health_snapshots: Option<&ConfigMap>,
+    logger: &EnvProgressLogger,
+    event_details: &EventDetails,
+) -> Result<(), Box<EngineError>>
+where
+    C: CloudProvider,
+    T: DatabaseType<C, Container, DatabaseOptions = DatabaseOptions>,
+{
+    let now = Utc::now();
+    let snapshot = match health_snapshots
+        .and_then(|config_map| config_map.data.as_ref())
+        .and_then(latest_health_snapshot)
+    {
+        Some(snapshot) if !snapshot.is_stale(now) => snapshot,
+        _ => return Ok(()),
+    };
+
+    for warning in check_health_snapshot(&snapshot) {
+        logger.warning(format!("⚠️ Database health: {warning}"));
+    }
+
+    let operation = risky_operation(&snapshot, &db.version);
+    if let (Some(operation), true) = (&operation, db.options.override_health_checks) {
+        logger.warning(format!("Health checks are overridden, proceeding with {operation}"));
+    }
+
+    check_risky_operation_is_allowed(Some(&snapshot), operation.as_ref(), db.options.override_health_checks, now)
+        .map_err(|reason| {
+            Box::new(EngineError::new_database_health_check_refused(
+                event_details.clone(),
+                db.name().to_string(),
+                reason,
+            ))
+        })
+}
+
+fn health_snapshot_chart<C: CloudProvider, T: DatabaseType<C, Container>>(
+    db: &Database<C, Container, T>,
+    target: &DeploymentTarget,
+    action: HelmAction,
+) -> ChartInfo {
+    ChartInfo {
+        name: db.health_snapshot_helm_release_name(),
+        path: format!("{}/health-snapshot-chart", db.workspace_directory()),
+        action,
+        namespace: HelmChartNamespaces::Custom,
+        custom_namespace: Some(target.environment.namespace().to_string()),
+        ..Default::default()
+    }
+}
+
 // For Container database
-impl<C: CloudProvider, T: DatabaseType<C, Container>> DeploymentAction for Database<C, Container, T>
+impl<C: CloudProvider, T: DatabaseType<C, Container, DatabaseOptions = DatabaseOptions>> DeploymentAction
+    for Database<C, Container, T>
 where
     Database<C, Container, T>: ToTeraContext,
 {
@@ -710,25 +988,62 @@ where
         let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
         let pre_run = |_: &EnvProgressLogger| -> Result<(), Box<EngineError>> { Ok(()) };
         let run = |logger: &EnvProgressLogger, _: ()| -> Result<(), Box<EngineError>> {
-            match get_database_with_invalid_storage_size(
+            // The ConfigMap only exists if health snapshots have been activated once
+            let health_snapshots = match block_on(
+                Api::<ConfigMap>::namespaced(target.kube.clone(), target.environment.namespace())
+                    .get_opt(&self.health_snapshot_config_map_name()),
+            ) {
+                Ok(config_map) => config_map,
+                Err(e) => {
+                    logger.warning(format!("Cannot read database health snapshots: {e}"));
+                    None
+                }
+            };
+            check_container_database_health(self, health_snapshots.as_ref(), logger, &event_details)?;
+            log_database_parameters(logger, &self.options.parameters, true);
+
+            // the chart reads the password from the credentials secret, it must exist before the statefulset
+            deploy_database_credentials(self, target, logger, &event_details, |password| {
+                block_on(change_container_database_password(
+                    &target.kube,
+                    target.environment.namespace(),
+                    &self.kube_label_selector(),
+                    T::db_type(),
+                    password,
+                ))
+                .map_err(|err| {
+                    Box::new(EngineError::new_database_credentials_rotation_failed(
+                        event_details.clone(),
+                        self.name().to_string(),
+                        err,
+                    ))
+                })
+            })?;
+
+            let resized_statefulset_storage = match get_database_with_invalid_storage_size(
                 self,
                 &target.kube,
                 target.environment.namespace(),
                 &event_details,
             ) {
-                Ok(invalid_statefulset_storage) => {
-                    if let Some(invalid_statefulset_storage) = invalid_statefulset_storage {
-                        update_pvcs(
-                            self.as_service(),
-                            &invalid_statefulset_storage,
-                            target.environment.namespace(),
-                            &event_details,
-                            &target.kube,
-                        )?;
-                    }
+                Ok(Some(invalid_statefulset_storage)) => {
+                    update_pvcs(
+                        self.as_service(),
+                        &invalid_statefulset_storage,
+                        target.environment.namespace(),
+                        &event_details,
+                        &target.kube,
+                    )?;
+                    Some(invalid_statefulset_storage)
                 }
-                Err(e) => logger.warning(format!("invalid_statefulset_storage fail with error: {}", e)),
-            }
+                Ok(None) => None,
+                // volume claim templates can't be shrunk, no need to go further
+                Err(e) if e.tag() == &Tag::K8sStorageCannotBeReduced => return Err(e),
+                Err(e) => {
+                    logger.warning(format!("invalid_statefulset_storage fail with error: {}", e));
+                    None
+                }
+            };
 
             let chart = ChartInfo {
                 name: self.helm_release_name(),
@@ -752,7 +1067,8 @@ where
                 PathBuf::from(self.helm_chart_dir()),
                 Some(PathBuf::from(format!("{}/qovery-values.j2.yaml", self.helm_chart_values_dir()))),
                 chart,
-            );
+            )
+            .with_release_owner(*self.long_id(), Some(self.legacy_helm_release_name()));
 
             if target.cloud_provider.kind() == Kind::Aws {
                 delete_nlb_or_alb_service(
@@ -782,6 +1098,37 @@ where
                 };
             };
 
+            if let Some(resized_statefulset_storage) = &resized_statefulset_storage {
+                if restart_statefulset_after_pvcs_update(
+                    resized_statefulset_storage,
+                    target.environment.namespace(),
+                    &event_details,
+                    &target.kube,
+                )? {
+                    logger.info("🔄 Pods restarted to resize the filesystem of their volumes".to_string());
+                }
+            }
+
+            // Health snapshots are collected by an engine managed cronjob, disabled by default
+            if self.options.activate_health_snapshot || health_snapshots.is_some() {
+                let action = match self.options.activate_health_snapshot {
+                    true => HelmAction::Deploy,
+                    false => HelmAction::Destroy,
+                };
+                let health_snapshot_helm = HelmDeployment::new(
+                    event_details.clone(),
+                    self.to_tera_context(target)?,
+                    PathBuf::from(self.health_snapshot_helm_chart_dir()),
+                    None,
+                    health_snapshot_chart(self, target, action),
+                )
+                .with_release_owner(*self.long_id(), Some(self.legacy_health_snapshot_helm_release_name()));
+                match self.options.activate_health_snapshot {
+                    true => health_snapshot_helm.on_create(target)?,
+                    false => health_snapshot_helm.on_delete(target)?,
+                }
+            }
+
             Ok(())
         };
 
@@ -844,10 +1191,21 @@ where
                     PathBuf::from(self.helm_chart_dir()),
                     None,
                     chart,
-                );
+                )
+                .with_release_owner(*self.long_id(), Some(self.legacy_helm_release_name()));
 
                 helm.on_delete(target)?;
 
+                HelmDeployment::new(
+                    event_details.clone(),
+                    tera::Context::default(),
+                    PathBuf::from(self.health_snapshot_helm_chart_dir()),
+                    None,
+                    health_snapshot_chart(self, target, HelmAction::Destroy),
+                )
+                .with_release_owner(*self.long_id(), Some(self.legacy_health_snapshot_helm_release_name()))
+                .on_delete(target)?;
+
                 // FIXME(ENG-1606): Remove this after kubernetes 1.23 is deployed, at it should be done by kubernetes
                 logger.info("🪓 Terminating network volume of the database".to_string());
                 if let Err(err) = block_on(kube_delete_all_from_selector::<PersistentVolumeClaim>(
@@ -863,7 +1221,7 @@ where
                     )));
                 }
 
-                Ok(())
+                delete_database_credentials(self, target, &event_details)
             },
         )
     }
@@ -1,10 +1,17 @@
+use crate::cmd::command::ExecutionContext;
+use crate::engine_task::progress::ProgressReporter;
+use crate::environment::action::check_storage_class::check_cluster_storage_class;
 use crate::environment::action::deploy_namespace::NamespaceDeployment;
+use crate::environment::action::replicate_secrets::reconcile_secret_replicas;
+use crate::environment::action::utils::mirror_image_if_necessary;
 use crate::environment::action::DeploymentAction;
 use crate::environment::models::abort::Abort;
 use crate::environment::models::environment::Environment;
 use crate::environment::models::router::RouterService;
+use crate::environment::models::secret_replication::SecretReplicationAction;
+use crate::environment::report::logger::{EnvLogger, EnvProgressLogger};
 use crate::errors::{EngineError, ErrorMessageVerbosity};
-use crate::events::{EngineEvent, EnvironmentStep, EventDetails, EventMessage};
+use crate::events::{EngineEvent, EnvironmentStep, EventDetails, EventMessage, Stage};
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::cloud_provider::service::Action;
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
@@ -47,37 +54,43 @@ impl<'a> EnvironmentDeployment<'a> {
 
     fn services_without_routers_iter(
         environment: &Environment,
-    ) -> impl DoubleEndedIterator<Item = (Uuid, &dyn DeploymentAction, Action)> {
+    ) -> impl DoubleEndedIterator<Item = (Uuid, &str, &dyn DeploymentAction, Action)> {
         std::iter::empty()
             .chain(
                 environment
                     .databases
                     .iter()
-                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                    .map(|s| (*s.long_id(), s.name(), s.as_deployment_action(), *s.action())),
             )
             .chain(
                 environment
                     .jobs
                     .iter()
-                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                    .map(|s| (*s.long_id(), s.name(), s.as_deployment_action(), *s.action())),
             )
             .chain(
                 environment
                     .containers
                     .iter()
-                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                    .map(|s| (*s.long_id(), s.name(), s.as_deployment_action(), *s.action())),
             )
             .chain(
                 environment
                     .applications
                     .iter()
-                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                    .map(|s| (*s.long_id(), s.name(), s.as_deployment_action(), *s.action())),
             )
             .chain(
                 environment
                     .helm_charts
                     .iter()
-                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                    .map(|s| (*s.long_id(), s.name(), s.as_deployment_action(), *s.action())),
+            )
+            .chain(
+                environment
+                    .k8s_manifests
+                    .iter()
+                    .map(|s| (*s.long_id(), s.name(), s.as_deployment_action(), *s.action())),
             )
     }
 
@@ -111,6 +124,7 @@ impl<'a> EnvironmentDeployment<'a> {
             .deployment_target
             .environment
             .event_details_with_step(EnvironmentStep::Deploy);
+        let _execution_context = ExecutionContext::enter_stage(event_details.stage());
         let resource_expiration = target
             .kubernetes
             .context()
@@ -121,6 +135,9 @@ impl<'a> EnvironmentDeployment<'a> {
         let should_abort = Self::should_abort_wrapper(target, &event_details);
         should_abort()?;
 
+        // fail early rather than waiting for the PVCs of stateful services to be bound
+        check_cluster_storage_class(target, &event_details)?;
+
         // deploy namespace first
         let ns = NamespaceDeployment {
             resource_expiration,
@@ -128,6 +145,9 @@ impl<'a> EnvironmentDeployment<'a> {
         };
         ns.exec_action(target, target.environment.action)?;
 
+        // then images from external registries, the services wait for their own image only once it is mirrored
+        self.mirror_images(&event_details, &should_abort)?;
+
         let services_to_deploy = Self::services_without_routers_iter(target.environment);
         let parallel_deploys = max(target.environment.max_parallel_deploy as usize, 1);
 
@@ -139,17 +159,33 @@ impl<'a> EnvironmentDeployment<'a> {
             )),
         ));
 
+        let progress = ProgressReporter::new(event_details.clone(), self.logger.clone_dyn());
+        let services_count = Self::services_without_routers_iter(target.environment).count() as u32;
+        let started_services = Mutex::new(0);
+
         let deployment_threads_pool = DeploymentThreadsPool::new();
         deployment_threads_pool.run(
             services_to_deploy
                 .into_iter()
-                .map(|(service_id, service, service_action)| {
+                .map(|(service_id, service_name, service, service_action)| {
                     let queueing_record =
                         metrics_registry.start_record(service_id, StepLabel::Service, StepName::DeploymentQueueing);
                     let deployed_services = self.deployed_services.clone();
                     let opt_router = Self::get_associated_router(&target.environment.routers, service_id);
+                    let progress = &progress;
+                    let started_services = &started_services;
                     move || {
                         queueing_record.stop(StepStatus::Success);
+                        {
+                            // reported while locked, so services started concurrently are reported in order
+                            let mut started_services = started_services.lock().unwrap_or_else(|e| e.into_inner());
+                            *started_services += 1;
+                            progress.report_progress(
+                                &format!("Deploying {service_name}"),
+                                *started_services,
+                                services_count,
+                            );
+                        }
 
                         // creating services first
                         deployed_services.lock().unwrap().insert(service_id);
@@ -169,6 +205,9 @@ impl<'a> EnvironmentDeployment<'a> {
                 .unwrap_or(NonZeroUsize::new(1).expect("error trying to instantiate NonZeroUsize")),
         )?;
 
+        // services are deployed, so secrets they created can be replicated as well
+        self.replicate_secrets(&event_details, false);
+
         // clean up nlb
         if let Err(err) = clean_up_deleted_k8s_nlb(event_details.clone(), target) {
             error!(
@@ -180,11 +219,121 @@ impl<'a> EnvironmentDeployment<'a> {
         Ok(())
     }
 
+    /// Mirrors the images of the services to deploy into the cluster registry, several at a time.
+    /// Each service checks again its image is mirrored right before being deployed, see `ensure_image_mirrored`.
+    fn mirror_images(
+        &self,
+        event_details: &EventDetails,
+        should_abort: &(impl Fn() -> Result<(), Box<EngineError>> + Send + Sync),
+    ) -> Result<(), Box<EngineError>> {
+        let target = &self.deployment_target;
+        let images_to_mirror = std::iter::empty()
+            .chain(
+                target
+                    .environment
+                    .containers
+                    .iter()
+                    .filter(|s| *s.action() == Action::Create)
+                    .filter_map(|s| s.image_to_mirror().map(|source| (s.as_service(), source))),
+            )
+            .chain(
+                target
+                    .environment
+                    .jobs
+                    .iter()
+                    .filter(|s| *s.action() == Action::Create)
+                    .filter_map(|s| s.image_to_mirror().map(|source| (s.as_service(), source))),
+            )
+            // services can't be shared between threads, so what the mirroring needs is extracted beforehand
+            .map(|(service, source)| {
+                (
+                    *service.long_id(),
+                    service.get_event_details(Stage::Environment(EnvironmentStep::Deploy)),
+                    EnvLogger::new(service, EnvironmentStep::Deploy, self.logger.clone()),
+                    source,
+                )
+            })
+            .collect_vec();
+        if images_to_mirror.is_empty() {
+            return Ok(());
+        }
+
+        let parallel_mirrorings = max(
+            target.kubernetes.advanced_settings().registry_mirroring_max_parallel as usize,
+            1,
+        );
+        self.logger.log(EngineEvent::Info(
+            event_details.clone(),
+            EventMessage::new_from_safe(format!(
+                "🪞 Mirroring the images of {} service(s), up to {} at a time",
+                images_to_mirror.len(),
+                parallel_mirrorings
+            )),
+        ));
+
+        DeploymentThreadsPool::new().run(
+            images_to_mirror
+                .into_iter()
+                .map(|(service_id, service_event_details, env_logger, source)| {
+                    move || {
+                        mirror_image_if_necessary(
+                            &service_id,
+                            source,
+                            target,
+                            &EnvProgressLogger::new(&env_logger),
+                            service_event_details.clone(),
+                            target.metrics_registry.clone(),
+                        )
+                    }
+                })
+                .collect_vec(),
+            || should_abort().is_err(),
+            NonZeroUsize::new(parallel_mirrorings)
+                .unwrap_or(NonZeroUsize::new(1).expect("error trying to instantiate NonZeroUsize")),
+        )
+    }
+
+    /// Failing to replicate secrets doesn't fail the deployment, it is reported and repaired by the next one
+    fn replicate_secrets(&self, event_details: &EventDetails, is_namespace_deleted: bool) {
+        let target = &self.deployment_target;
+        let actions = match reconcile_secret_replicas(
+            &target.kube,
+            target.environment.namespace(),
+            is_namespace_deleted,
+            event_details,
+            target.is_dry_run_deploy,
+        ) {
+            Ok(actions) => actions,
+            Err(err) => {
+                self.logger.log(EngineEvent::Warning(
+                    event_details.clone(),
+                    EventMessage::new_from_engine_error(*err),
+                ));
+                return;
+            }
+        };
+
+        for action in actions {
+            let message = EventMessage::new_from_safe(format!("🔑 Secret replication: {action}"));
+            match action {
+                SecretReplicationAction::Conflict { .. } | SecretReplicationAction::TargetNamespaceNotFound { .. } => {
+                    self.logger.log(EngineEvent::Warning(event_details.clone(), message))
+                }
+                SecretReplicationAction::Create { .. }
+                | SecretReplicationAction::Update { .. }
+                | SecretReplicationAction::Delete { .. } => {
+                    self.logger.log(EngineEvent::Info(event_details.clone(), message))
+                }
+            }
+        }
+    }
+
     pub fn on_pause(&mut self) -> Result<(), Box<EngineError>> {
         let event_details = self
             .deployment_target
             .environment
             .event_details_with_step(EnvironmentStep::Pause);
+        let _execution_context = ExecutionContext::enter_stage(event_details.stage());
         let target = Arc::new(&self.deployment_target);
 
         let should_abort = Self::should_abort_wrapper(&target, &event_details);
@@ -203,7 +352,7 @@ impl<'a> EnvironmentDeployment<'a> {
         deployment_threads_pool.run(
             services_to_pause
                 .into_iter()
-                .map(|(service_id, service, _service_action)| {
+                .map(|(service_id, _service_name, service, _service_action)| {
                     let deployed_services = self.deployed_services.clone();
                     let local_target = target.clone();
                     let opt_router = Self::get_associated_router(&target.environment.routers, service_id);
@@ -244,6 +393,7 @@ impl<'a> EnvironmentDeployment<'a> {
             .deployment_target
             .environment
             .event_details_with_step(EnvironmentStep::Delete);
+        let _execution_context = ExecutionContext::enter_stage(event_details.stage());
 
         // re-create namespace first, because job can have on-delete action, so ns need to exist for us to run them
         let ns = NamespaceDeployment {
@@ -273,7 +423,7 @@ impl<'a> EnvironmentDeployment<'a> {
         deployment_threads_pool.run(
             services_to_delete
                 .into_iter()
-                .map(|(service_id, service, _service_action)| {
+                .map(|(service_id, _service_name, service, _service_action)| {
                     let deployed_services = self.deployed_services.clone();
                     let opt_router = Self::get_associated_router(&target.environment.routers, service_id);
                     move || {
@@ -304,6 +454,9 @@ impl<'a> EnvironmentDeployment<'a> {
         };
         ns.on_delete(target)?;
 
+        // replicas outlive their source namespace otherwise
+        self.replicate_secrets(&event_details, true);
+
         Ok(())
     }
 
@@ -312,6 +465,7 @@ impl<'a> EnvironmentDeployment<'a> {
             .deployment_target
             .environment
             .event_details_with_step(EnvironmentStep::Restart);
+        let _execution_context = ExecutionContext::enter_stage(event_details.stage());
         let target = Arc::new(&self.deployment_target);
 
         let should_abort = Self::should_abort_wrapper(&target, &event_details);
@@ -330,7 +484,7 @@ impl<'a> EnvironmentDeployment<'a> {
         deployment_threads_pool.run(
             services_to_restart
                 .into_iter()
-                .map(|(service_id, service, _service_action)| {
+                .map(|(service_id, _service_name, service, _service_action)| {
                     let deployed_services = self.deployed_services.clone();
                     let local_target = target.clone();
                     let opt_router = Self::get_associated_router(&target.environment.routers, service_id);
@@ -438,10 +592,12 @@ impl DeploymentThreadsPool {
                     .name(format!("deployer-{}", ix))
                     .spawn_scoped(scope, {
                         let current_span = tracing::Span::current();
+                        let execution_context = ExecutionContext::current();
                         let current_thread = &current_thread;
 
                         move || {
                             let _span = current_span.enter();
+                            let _execution_context = execution_context.map(ExecutionContext::enter);
                             let _guard = scopeguard::guard((), |_| current_thread.unpark());
                             task()
                         }
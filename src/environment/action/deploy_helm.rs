@@ -1,14 +1,23 @@
 use crate::cmd::command::CommandKiller;
 use crate::environment::action::DeploymentAction;
+use crate::environment::models::helm_release::{
+    helm_release_ownership, HelmReleaseOwnership, HELM_RELEASE_OWNER_LABEL,
+};
+use crate::environment::models::pod_security::{validate_rendered_manifests, PodSecurityProfile};
 use crate::errors::{CommandError, EngineError};
 use crate::events::{EnvironmentStep, EventDetails, Stage};
-use crate::helm::{ChartInfo, HelmChart, ServiceChart};
+use crate::helm::{ChartInfo, HelmChart, HelmChartError, ServiceChart};
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
+use crate::runtime::block_on;
 use crate::template::generate_and_copy_all_files_into_dir;
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::{ListParams, Patch, PatchParams};
+use kube::Api;
 use std::env;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tera::Context as TeraContext;
+use uuid::Uuid;
 
 pub fn default_helm_timeout() -> Duration {
     match env::var("HELM_TIMEOUT_IN_SECS") {
@@ -20,6 +29,101 @@ pub fn default_helm_timeout() -> Duration {
     }
 }
 
+fn list_helm_release_secrets(
+    target: &DeploymentTarget,
+    event_details: &EventDetails,
+    namespace: &str,
+    release_name: &str,
+) -> Result<Vec<Secret>, Box<EngineError>> {
+    // helm stores each revision of a release in a secret labelled with the release name
+    let secrets: Api<Secret> = Api::namespaced(target.kube.clone(), namespace);
+    let params = ListParams::default().labels(&format!("owner=helm,name={release_name}"));
+    block_on(secrets.list(&params))
+        .map(|secrets| secrets.items)
+        .map_err(|err| {
+            Box::new(EngineError::new_k8s_get_secret_error(
+                event_details.clone(),
+                CommandError::new_from_safe_message(err.to_string()),
+            ))
+        })
+}
+
+fn get_helm_release_ownership(
+    target: &DeploymentTarget,
+    event_details: &EventDetails,
+    namespace: &str,
+    release_name: &str,
+    service_long_id: &Uuid,
+) -> Result<HelmReleaseOwnership, Box<EngineError>> {
+    let secrets = list_helm_release_secrets(target, event_details, namespace, release_name)?;
+    let no_labels = Default::default();
+    Ok(helm_release_ownership(
+        secrets.iter().map(|s| s.metadata.labels.as_ref().unwrap_or(&no_labels)),
+        service_long_id,
+    ))
+}
+
+/// Fails if a release with the same name already exists in the namespace and belongs to another service,
+/// as deploying would overwrite it.
+pub(crate) fn check_helm_release_owner(
+    target: &DeploymentTarget,
+    event_details: &EventDetails,
+    namespace: &str,
+    release_name: &str,
+    service_long_id: &Uuid,
+) -> Result<(), Box<EngineError>> {
+    match get_helm_release_ownership(target, event_details, namespace, release_name, service_long_id)? {
+        HelmReleaseOwnership::OwnedByAnotherService(owner) => {
+            Err(Box::new(EngineError::new_helm_release_owned_by_another_service(
+                event_details.clone(),
+                namespace,
+                release_name,
+                &owner,
+            )))
+        }
+        HelmReleaseOwnership::NotDeployed | HelmReleaseOwnership::Owned | HelmReleaseOwnership::Unknown => Ok(()),
+    }
+}
+
+/// Labels the revisions of the release with the service owning it. Best effort, as the release is already deployed.
+pub(crate) fn record_helm_release_owner(
+    target: &DeploymentTarget,
+    event_details: &EventDetails,
+    namespace: &str,
+    release_name: &str,
+    service_long_id: &Uuid,
+) {
+    let release_secrets = match list_helm_release_secrets(target, event_details, namespace, release_name) {
+        Ok(release_secrets) => release_secrets,
+        Err(err) => {
+            warn!("Cannot record owner of helm release {}/{}: {}", namespace, release_name, err);
+            return;
+        }
+    };
+
+    let secrets: Api<Secret> = Api::namespaced(target.kube.clone(), namespace);
+    let patch =
+        serde_json::json!({ "metadata": { "labels": { HELM_RELEASE_OWNER_LABEL: service_long_id.to_string() } } });
+    for secret in release_secrets.iter().filter(|s| {
+        s.metadata
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.get(HELM_RELEASE_OWNER_LABEL))
+            .is_none()
+    }) {
+        let name = secret.metadata.name.clone().unwrap_or_default();
+        if let Err(err) = block_on(secrets.patch(&name, &PatchParams::default(), &Patch::Merge(&patch))) {
+            warn!("Cannot record owner of helm release secret {}/{}: {}", namespace, name, err);
+        }
+    }
+}
+
+struct HelmReleaseOwner {
+    service_long_id: Uuid,
+    // name the release had before release names were generated from the service id
+    legacy_release_name: Option<String>,
+}
+
 /// Helm Deployment manages Helm + jinja support
 pub struct HelmDeployment {
     event_details: EventDetails,
@@ -30,6 +134,7 @@ pub struct HelmDeployment {
     pub render_custom_values_file: Option<PathBuf>,
     /// Path should be inside the workspace directory because it will be copied there
     pub helm_chart: ChartInfo,
+    release_owner: Option<HelmReleaseOwner>,
 }
 
 impl HelmDeployment {
@@ -46,34 +151,110 @@ impl HelmDeployment {
             chart_orginal_dir,
             render_custom_values_file,
             helm_chart,
+            release_owner: None,
+        }
+    }
+
+    /// Ensures the release does not belong to another service before deploying it.
+    /// A release still deployed under the legacy name of the service keeps this name, as helm cannot rename a release.
+    pub fn with_release_owner(mut self, service_long_id: Uuid, legacy_release_name: Option<String>) -> Self {
+        self.release_owner = Some(HelmReleaseOwner {
+            service_long_id,
+            legacy_release_name,
+        });
+        self
+    }
+
+    /// Returns the legacy release name of the service if a release deployed under it must be migrated
+    fn legacy_release_to_migrate(&self, target: &DeploymentTarget) -> Result<Option<String>, Box<EngineError>> {
+        let Some(owner) = &self.release_owner else {
+            return Ok(None);
+        };
+        let Some(legacy_release_name) = owner
+            .legacy_release_name
+            .as_ref()
+            .filter(|name| **name != self.helm_chart.name)
+        else {
+            return Ok(None);
+        };
+
+        match get_helm_release_ownership(
+            target,
+            &self.event_details,
+            &self.helm_chart.get_namespace_string(),
+            legacy_release_name,
+            &owner.service_long_id,
+        )? {
+            HelmReleaseOwnership::Owned | HelmReleaseOwnership::Unknown => Ok(Some(legacy_release_name.clone())),
+            // the legacy name is now used by another service, it can't be ours
+            HelmReleaseOwnership::NotDeployed | HelmReleaseOwnership::OwnedByAnotherService(_) => Ok(None),
         }
     }
 
     pub fn prepare_helm_chart(&self) -> Result<(), Box<EngineError>> {
+        self.render_helm_chart(&self.tera_context)
+    }
+
+    fn render_helm_chart(&self, tera_context: &TeraContext) -> Result<(), Box<EngineError>> {
         // Copy the root folder
-        generate_and_copy_all_files_into_dir(&self.chart_orginal_dir, &self.helm_chart.path, &self.tera_context)
-            .map_err(|e| {
+        generate_and_copy_all_files_into_dir(&self.chart_orginal_dir, &self.helm_chart.path, tera_context).map_err(
+            |e| {
                 EngineError::new_cannot_copy_files_from_one_directory_to_another(
                     self.event_details.clone(),
                     self.chart_orginal_dir.to_string_lossy().to_string(),
                     self.helm_chart.path.clone(),
                     e,
                 )
-            })?;
+            },
+        )?;
 
         // If we have some special value override, render and copy it
         if let Some(custom_value) = self.render_custom_values_file.clone() {
             let custom_value_dir_path = custom_value.parent().unwrap_or_else(|| Path::new("./"));
 
-            generate_and_copy_all_files_into_dir(custom_value_dir_path, &self.helm_chart.path, &self.tera_context)
-                .map_err(|e| {
+            generate_and_copy_all_files_into_dir(custom_value_dir_path, &self.helm_chart.path, tera_context).map_err(
+                |e| {
                     EngineError::new_cannot_copy_files_from_one_directory_to_another(
                         self.event_details.clone(),
                         self.chart_orginal_dir.to_string_lossy().to_string(),
                         self.helm_chart.path.clone(),
                         e,
                     )
-                })?;
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Renders the chart and checks every workload complies with the environment pod security profile,
+    /// so the deployment fails with the full list of violations instead of pods being rejected one by one.
+    fn check_pod_security_profile(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let profile = target.environment.pod_security_profile;
+        if profile == PodSecurityProfile::Privileged {
+            return Ok(());
+        }
+
+        let manifests = target
+            .helm
+            .get_template(&self.helm_chart.path, &self.helm_chart)
+            .map_err(|e| (self.event_details.clone(), e))?;
+        let violations = validate_rendered_manifests(profile, &self.helm_chart.name, &manifests).map_err(|e| {
+            (
+                self.event_details.clone(),
+                HelmChartError::RenderingError {
+                    chart_name: self.helm_chart.name.clone(),
+                    msg: format!("Cannot deserialize helm template into kube object: {e}"),
+                },
+            )
+        })?;
+
+        if !violations.is_empty() {
+            return Err(Box::new(EngineError::new_k8s_pod_security_violation(
+                self.event_details.clone(),
+                profile.to_string(),
+                violations.iter().map(|v| v.to_string()).collect(),
+            )));
         }
 
         Ok(())
@@ -82,9 +263,34 @@ impl HelmDeployment {
 
 impl DeploymentAction for HelmDeployment {
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
-        self.prepare_helm_chart()?;
+        // safe security context defaults are rendered by our charts when the profile requires them
+        let mut tera_context = self.tera_context.clone();
+        tera_context.insert(
+            "pod_security_safe_defaults",
+            &target.environment.pod_security_profile.apply_safe_defaults(),
+        );
+        self.render_helm_chart(&tera_context)?;
+        self.check_pod_security_profile(target)?;
+
+        let mut helm_chart = self.helm_chart.clone();
+        if let Some(owner) = &self.release_owner {
+            if let Some(legacy_release_name) = self.legacy_release_to_migrate(target)? {
+                info!(
+                    "Helm release {} has been deployed under its legacy name {}, keeping it",
+                    helm_chart.name, legacy_release_name
+                );
+                helm_chart.name = legacy_release_name;
+            }
+            check_helm_release_owner(
+                target,
+                &self.event_details,
+                &helm_chart.get_namespace_string(),
+                &helm_chart.name,
+                &owner.service_long_id,
+            )?;
+        }
 
-        let service_chart = ServiceChart::new(target.helm.clone(), self.helm_chart.clone());
+        let service_chart = ServiceChart::new(target.helm.clone(), helm_chart.clone());
         let chart: Box<dyn HelmChart> = Box::new(service_chart);
         chart
             .run(
@@ -94,6 +300,17 @@ impl DeploymentAction for HelmDeployment {
                 &CommandKiller::from_cancelable(target.abort),
             )
             .map_err(|e| Box::new(EngineError::new_helm_chart_error(self.event_details.clone(), e)))?;
+
+        if let Some(owner) = &self.release_owner {
+            record_helm_release_owner(
+                target,
+                &self.event_details,
+                &helm_chart.get_namespace_string(),
+                &helm_chart.name,
+                &owner.service_long_id,
+            );
+        }
+
         Ok(())
     }
 
@@ -102,20 +319,29 @@ impl DeploymentAction for HelmDeployment {
     }
 
     fn on_delete(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
-        target
-            .helm
-            .uninstall(
-                &self.helm_chart,
-                &[],
-                &CommandKiller::from_cancelable(target.abort),
-                &mut |line| {
-                    info!("{}", line);
-                },
-                &mut |line| {
-                    info!("{}", line);
-                },
-            )
-            .map_err(|e| EngineError::new_helm_error(self.event_details.clone(), e))?;
+        let mut helm_charts = vec![self.helm_chart.clone()];
+        if let Some(legacy_release_name) = self.legacy_release_to_migrate(target)? {
+            let mut legacy_helm_chart = self.helm_chart.clone();
+            legacy_helm_chart.name = legacy_release_name;
+            helm_charts.push(legacy_helm_chart);
+        }
+
+        for helm_chart in &helm_charts {
+            target
+                .helm
+                .uninstall(
+                    helm_chart,
+                    &[],
+                    &CommandKiller::from_cancelable(target.abort),
+                    &mut |line| {
+                        info!("{}", line);
+                    },
+                    &mut |line| {
+                        info!("{}", line);
+                    },
+                )
+                .map_err(|e| EngineError::new_helm_error(self.event_details.clone(), e))?;
+        }
 
         Ok(())
     }
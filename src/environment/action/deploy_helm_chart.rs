@@ -2,10 +2,12 @@ use crate::infrastructure::models::build_platform::{Credentials, SshKey};
 
 use crate::cmd::command::CommandKiller;
 use crate::cmd::git;
+use crate::environment::action::deploy_helm::{check_helm_release_owner, record_helm_release_owner};
 use crate::environment::action::pause_service::PauseServiceAction;
 use crate::environment::action::restart_service::RestartServiceAction;
 use crate::environment::action::{DeploymentAction, K8sResourceType};
 use crate::environment::models::helm_chart::{HelmChart, HelmChartSource, HelmValueSource};
+use crate::environment::models::pod_security::{validate_rendered_manifests, PodSecurityProfile};
 use crate::environment::models::types::CloudProvider;
 use crate::environment::report::helm_chart::reporter::HelmChartDeploymentReporter;
 use crate::environment::report::logger::{EnvProgressLogger, EnvSuccessLogger};
@@ -16,10 +18,11 @@ use crate::helm::{ChartInfo, HelmChartError};
 use crate::infrastructure::models::cloud_provider::service::{Action, Service};
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
 use crate::io_models::variable_utils::VariableInfo;
+use crate::kubers_utils::server_side_apply_params;
 use anyhow::anyhow;
 use git2::{Cred, CredentialType};
 use itertools::Itertools;
-use kube::api::{DeleteParams, PartialObjectMeta, Patch, PatchParams};
+use kube::api::{DeleteParams, PartialObjectMeta, Patch};
 use serde::Deserialize;
 use std::borrow::Cow;
 use std::collections::HashMap;
@@ -47,8 +50,20 @@ impl<T: CloudProvider> DeploymentAction for HelmChart<T> {
             // Check users does not bypass restrictions (i.e: install cluster wide resources, or not in the correct namespace)
             check_resources_are_allowed_to_install(self, target, event_details.clone(), logger)?;
 
+            // Check workloads comply with the pod security profile of the environment
+            check_pod_security_profile(self, target, event_details.clone(), logger)?;
+
             // Create config map for qovery-webhook-admission-controller to inject labels / annotations
             create_config_map_for_webhook_admission_controller_if_not_exists(self, target, event_details.clone())?;
+
+            // Check we are not going to overwrite the release of another service
+            check_helm_release_owner(
+                target,
+                &event_details,
+                target.environment.namespace(),
+                self.helm_release_name(),
+                self.long_id(),
+            )?;
             Ok(())
         };
 
@@ -90,6 +105,14 @@ impl<T: CloudProvider> DeploymentAction for HelmChart<T> {
                 )
                 .map_err(|err| (event_details.clone(), HelmChartError::HelmError(err)))?;
 
+            record_helm_release_owner(
+                target,
+                &event_details,
+                target.environment.namespace(),
+                self.helm_release_name(),
+                self.long_id(),
+            );
+
             Ok(())
         };
 
@@ -358,7 +381,7 @@ fn replace_qovery_env_variable<'a>(
     Ok(line)
 }
 
-fn git_credentials_callback<'a>(
+pub(super) fn git_credentials_callback<'a>(
     git_credentials: &'a Option<Credentials>,
     ssh_keys: &'a [SshKey],
 ) -> impl Fn(&str) -> Vec<(CredentialType, Cred)> + 'a {
@@ -572,6 +595,55 @@ fn prepare_helm_chart_directory<T: CloudProvider>(
     Ok(())
 }
 
+fn check_pod_security_profile<T: CloudProvider>(
+    this: &HelmChart<T>,
+    target: &DeploymentTarget,
+    event_details: EventDetails,
+    logger: &EnvProgressLogger,
+) -> Result<(), Box<EngineError>> {
+    let profile = target.environment.pod_security_profile;
+    if profile == PodSecurityProfile::Privileged {
+        return Ok(());
+    }
+
+    logger.info(format!(
+        "🔒 Checking workloads comply with the `{profile}` pod security profile"
+    ));
+    let template_args: Vec<_> = this.helm_template_arguments().collect();
+    let template = target
+        .helm
+        .template_raw(
+            this.helm_release_name(),
+            this.chart_workspace_directory(),
+            target.environment.namespace(),
+            &template_args.iter().map(|x| x.as_ref()).collect::<Vec<_>>(),
+            &[],
+            &CommandKiller::from(HELM_CHART_DOWNLOAD_TIMEOUT, target.abort),
+            &mut |line| logger.warning(line),
+        )
+        .map_err(|e| (event_details.clone(), e))?;
+
+    let violations = validate_rendered_manifests(profile, this.name(), &template).map_err(|err| {
+        (
+            event_details.clone(),
+            HelmChartError::RenderingError {
+                chart_name: this.name().to_string(),
+                msg: format!("Cannot deserialize helm template into kube object: {}", err),
+            },
+        )
+    })?;
+
+    if !violations.is_empty() {
+        return Err(Box::new(EngineError::new_k8s_pod_security_violation(
+            event_details,
+            profile.to_string(),
+            violations.iter().map(|v| v.to_string()).collect(),
+        )));
+    }
+
+    Ok(())
+}
+
 fn check_resources_are_allowed_to_install<T: CloudProvider>(
     this: &HelmChart<T>,
     target: &DeploymentTarget,
@@ -769,7 +841,7 @@ fn create_config_map_for_webhook_admission_controller_if_not_exists<T: CloudProv
 
     if let Err(err) = block_on(api_config_map.patch(
         config_map_name.as_str(),
-        &PatchParams::apply("qovery").force(),
+        &server_side_apply_params("qovery", &target.capabilities),
         &Patch::Apply(config_map),
     )) {
         return Err(Box::new(
@@ -801,9 +873,9 @@ mod tests {
     #[test]
     fn test_replace_qovery_env_variables() {
         let envs = hashmap! {
-            "TOTO".to_string() => VariableInfo { value: "toto_var".to_string(), is_secret: false},
-            "LABEL_NAME".to_string() => VariableInfo {value: "toto_label".to_string(), is_secret: false},
-            "NGNIX_TAG".to_string() => VariableInfo {value: "42".to_string(), is_secret: false}
+            "TOTO".to_string() => VariableInfo { value: "toto_var".to_string(), is_secret: false, scope: None },
+            "LABEL_NAME".to_string() => VariableInfo {value: "toto_label".to_string(), is_secret: false, scope: None },
+            "NGNIX_TAG".to_string() => VariableInfo {value: "42".to_string(), is_secret: false, scope: None }
         };
 
         let ret = replace_qovery_env_variable(Cow::Borrowed("    tag: \"qovery.env.NGNIX_TAG\""), &envs);
@@ -912,8 +984,8 @@ controller:
         let env_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
         let project_id = Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap();
         let envs = hashmap! {
-            "NGINX_TAG".to_string() => VariableInfo { value: "42".to_string(), is_secret: false},
-            "LABEL_NAME".to_string() => VariableInfo {value: "toto_label".to_string(), is_secret: false}
+            "NGINX_TAG".to_string() => VariableInfo { value: "42".to_string(), is_secret: false, scope: None },
+            "LABEL_NAME".to_string() => VariableInfo {value: "toto_label".to_string(), is_secret: false, scope: None }
         };
         let mut output: Vec<u8> = vec![];
 
@@ -1,8 +1,8 @@
 use super::utils::delete_cached_image;
-use crate::cmd::kubectl::{kubectl_exec_delete_job, kubectl_get_job_pod_output};
+use crate::cmd::kubectl::{kubectl_exec_delete_job, kubectl_get_job_pod_output, JobPodOutput};
 use crate::cmd::structs::KubernetesPodStatusPhase;
 use crate::environment::action::deploy_helm::HelmDeployment;
-use crate::environment::action::utils::{get_last_deployed_image, mirror_image_if_necessary, KubeObjectKind};
+use crate::environment::action::utils::{ensure_image_mirrored, get_last_deployed_image, KubeObjectKind};
 use crate::environment::action::DeploymentAction;
 use crate::environment::models::job::{ImageSource, Job, JobService};
 use crate::environment::models::types::{CloudProvider, ToTeraContext};
@@ -17,6 +17,7 @@ use crate::infrastructure::models::cloud_provider::service::{Action, Service};
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
 use crate::io_models::job::{JobSchedule, LifecycleType};
 use crate::runtime::block_on;
+use itertools::Itertools;
 use k8s_openapi::api::batch::v1::{CronJob, Job as K8sJob};
 use k8s_openapi::api::core::v1::Pod;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
@@ -156,19 +157,12 @@ fn run_job<'a, T: CloudProvider>(
 where
     Job<T>: JobService,
 {
-    let metrics_registry = target.metrics_registry.clone();
     let pre_run = move |logger: &EnvProgressLogger| -> Result<TaskContext, Box<EngineError>> {
         match &job.image_source {
             // If image come from a registry, we mirror it to the cluster registry in order to avoid losing access to it due to creds expiration
             ImageSource::Registry { source } => {
-                mirror_image_if_necessary(
-                    job.long_id(),
-                    source,
-                    target,
-                    logger,
-                    event_details.clone(),
-                    metrics_registry.clone(),
-                )?;
+                // the image has been mirrored with the other images of the environment, the tag may have moved since
+                ensure_image_mirrored(job.long_id(), source, target, logger, event_details.clone())?;
             }
             ImageSource::Build { .. } => {}
         }
@@ -206,7 +200,8 @@ where
             PathBuf::from(job.helm_chart_dir()),
             None,
             chart,
-        );
+        )
+        .with_release_owner(*job.long_id(), None);
 
         // Wait for the job to terminate in order to have his status
         // For cronjob we dont care as we don't control when it is executed
@@ -281,43 +276,62 @@ where
 
                 info!("Get JSON output from shared volume");
                 // Get JSON output from shared volume
+                let advanced_settings = job.advanced_settings();
+                let max_size_in_bytes = advanced_settings.job_output_max_size_in_kib as u64 * 1024;
                 let result_json_output = kubectl_get_job_pod_output(
                     target.kubernetes.kubeconfig_local_file_path(),
                     target.cloud_provider.credentials_environment_variables(),
                     target.environment.namespace(),
                     &pod_name,
+                    max_size_in_bytes,
                 );
                 match result_json_output {
-                    Ok(json) => {
-                        let result_serde_json: Result<HashMap<String, JobOutputVariable>, serde_json::Error> =
-                            serialize_job_output(&json);
-                        match result_serde_json {
-                            Ok(deserialized_json_hashmap) => {
-                                let deserialized_json_hashmap_with_uppercase_keys: HashMap<String, JobOutputVariable> =
-                                    deserialized_json_hashmap
-                                        .iter()
-                                        .map(|(key, value)| (key.to_uppercase(), value.clone()))
-                                        .collect();
-                                logger.core_configuration_for_job(
-                                    "Job output succeeded. Environment variables will be synchronized.".to_string(),
-                                    serde_json::to_string(&deserialized_json_hashmap_with_uppercase_keys)
-                                        .unwrap_or_else(|_| "{}".to_string()),
-                                )
-                            }
-                            Err(err) => {
+                    Ok(output) => match parse_job_output(
+                        &output,
+                        max_size_in_bytes,
+                        advanced_settings.job_output_value_max_size_in_bytes,
+                    ) {
+                        Ok(job_output) => {
+                            if let Some(message) = job_output.truncation_message() {
                                 logger.log(EngineEvent::Warning(
                                     event_details.clone(),
-                                    EventMessage::new_from_engine_error(
-                                        EngineError::new_invalid_job_output_cannot_be_serialized(
-                                            event_details.clone(),
-                                            err,
-                                            &json,
-                                        ),
-                                    ),
+                                    EventMessage::new_from_safe(message),
                                 ));
                             }
+                            let deserialized_json_hashmap_with_uppercase_keys: HashMap<String, JobOutputVariable> =
+                                job_output
+                                    .variables
+                                    .iter()
+                                    .map(|(key, value)| (key.to_uppercase(), value.clone()))
+                                    .collect();
+                            logger.core_configuration_for_job(
+                                "Job output succeeded. Environment variables will be synchronized.".to_string(),
+                                serde_json::to_string(&deserialized_json_hashmap_with_uppercase_keys)
+                                    .unwrap_or_else(|_| "{}".to_string()),
+                            )
                         }
-                    }
+                        Err(JobOutputError::TooLarge) => {
+                            logger.log(EngineEvent::Warning(
+                                event_details.clone(),
+                                EventMessage::new_from_engine_error(EngineError::new_job_output_too_large(
+                                    event_details.clone(),
+                                    advanced_settings.job_output_max_size_in_kib,
+                                )),
+                            ));
+                        }
+                        Err(JobOutputError::Invalid(err)) => {
+                            logger.log(EngineEvent::Warning(
+                                event_details.clone(),
+                                EventMessage::new_from_engine_error(
+                                    EngineError::new_invalid_job_output_cannot_be_serialized(
+                                        event_details.clone(),
+                                        err,
+                                        &output.content,
+                                    ),
+                                ),
+                            ));
+                        }
+                    },
                     Err(err) => {
                         info!(
                             "Cannot get JSON job output: {}",
@@ -547,7 +561,8 @@ where
             PathBuf::from(job.helm_chart_dir()),
             None,
             chart,
-        );
+        )
+        .with_release_owner(*job.long_id(), None);
 
         helm.on_delete(target)?;
 
@@ -831,9 +846,175 @@ fn serialize_job_output(json: &str) -> Result<HashMap<String, JobOutputVariable>
     Ok(job_output_variables)
 }
 
+const JOB_OUTPUT_TRUNCATION_MARKER: &str = "...[truncated]";
+
+#[derive(Debug)]
+enum JobOutputError {
+    /// The output exceeds the max size and truncation is disabled
+    TooLarge,
+    Invalid(serde_json::Error),
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct JobOutput {
+    variables: HashMap<String, JobOutputVariable>,
+    /// Keys whose value has been truncated
+    truncated_keys: Vec<String>,
+    /// Key of the entry cut by the max size of the output, the entries written after it are never read
+    dropped_keys: Vec<String>,
+    is_cut: bool,
+}
+
+impl JobOutput {
+    fn truncation_message(&self) -> Option<String> {
+        let mut messages = vec![];
+        if self.is_cut {
+            let dropped_keys = self.dropped_keys.iter().map(|key| format!("`{key}`")).join(", ");
+            messages.push(match dropped_keys.is_empty() {
+                true => {
+                    "Job output exceeds its max size, the entries after the last complete one are dropped".to_string()
+                }
+                false => {
+                    format!("Job output exceeds its max size, dropped keys: {dropped_keys} and the following ones")
+                }
+            });
+        }
+        if !self.truncated_keys.is_empty() {
+            let truncated_keys = self.truncated_keys.iter().map(|key| format!("`{key}`")).join(", ");
+            messages.push(format!("Job output values too long are truncated: {truncated_keys}"));
+        }
+
+        match messages.is_empty() {
+            true => None,
+            false => Some(messages.join(". ")),
+        }
+    }
+}
+
+/// Without `value_max_size_in_bytes`, an output exceeding its max size is rejected as a whole. Otherwise, the entries
+/// read before the max size are kept and the values longer than `value_max_size_in_bytes` are truncated.
+fn parse_job_output(
+    output: &JobPodOutput,
+    max_size_in_bytes: u64,
+    value_max_size_in_bytes: Option<u32>,
+) -> Result<JobOutput, JobOutputError> {
+    let is_cut = output.size_in_bytes > max_size_in_bytes;
+    let Some(value_max_size_in_bytes) = value_max_size_in_bytes else {
+        if is_cut {
+            return Err(JobOutputError::TooLarge);
+        }
+        return Ok(JobOutput {
+            variables: serialize_job_output(&output.content).map_err(JobOutputError::Invalid)?,
+            ..Default::default()
+        });
+    };
+
+    let (json, dropped_keys) = match is_cut {
+        true => keep_complete_entries(&output.content),
+        false => (output.content.clone(), vec![]),
+    };
+    let mut variables = serialize_job_output(&json).map_err(JobOutputError::Invalid)?;
+    let mut truncated_keys = vec![];
+    for (key, variable) in variables.iter_mut() {
+        if truncate_value(&mut variable.value, value_max_size_in_bytes as usize) {
+            truncated_keys.push(key.clone());
+        }
+    }
+    truncated_keys.sort();
+
+    Ok(JobOutput {
+        variables,
+        truncated_keys,
+        dropped_keys,
+        is_cut,
+    })
+}
+
+/// Keeps the first `max_size_in_bytes` of the value, followed by a marker
+fn truncate_value(value: &mut String, max_size_in_bytes: usize) -> bool {
+    if value.len() <= max_size_in_bytes {
+        return false;
+    }
+
+    let mut end = max_size_in_bytes;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    value.truncate(end);
+    value.push_str(JOB_OUTPUT_TRUNCATION_MARKER);
+    true
+}
+
+/// Closes the top level object of a json cut in the middle, after its last complete entry.
+/// Returns the json and the key of the entry that was cut, if it has been read entirely.
+fn keep_complete_entries(json: &str) -> (String, Vec<String>) {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut string_start = 0;
+    let mut expecting_key = false;
+    let mut cut_key = None;
+    let mut last_entry_end = None;
+
+    for (index, c) in json.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => {
+                    in_string = false;
+                    if depth == 1 && expecting_key {
+                        cut_key = serde_json::from_str::<String>(&json[string_start..=index]).ok();
+                        expecting_key = false;
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                string_start = index;
+            }
+            '{' | '[' => {
+                depth += 1;
+                if depth == 1 {
+                    expecting_key = true;
+                    last_entry_end = Some(index + 1);
+                }
+            }
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    // the object is complete, nothing has been cut
+                    return (json[..=index].to_string(), vec![]);
+                }
+            }
+            ',' if depth == 1 => {
+                expecting_key = true;
+                cut_key = None;
+                last_entry_end = Some(index);
+            }
+            _ => {}
+        }
+    }
+
+    match last_entry_end {
+        Some(end) => (format!("{}}}", &json[..end]), cut_key.into_iter().collect()),
+        // not even the beginning of an object, left as is to be reported as invalid
+        None => (json.to_string(), vec![]),
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::environment::action::deploy_job::{serialize_job_output, JobOutputVariable};
+    use crate::cmd::kubectl::JobPodOutput;
+    use crate::environment::action::deploy_job::{
+        keep_complete_entries, parse_job_output, serialize_job_output, JobOutputError, JobOutputVariable,
+        JOB_OUTPUT_TRUNCATION_MARKER,
+    };
 
     #[test]
     fn should_serialize_json_to_job_output_variable_with_string_value() {
@@ -917,4 +1098,98 @@ mod test {
         let json_final = serde_json::to_string(&hashmap).unwrap();
         println!("{json_final}");
     }
+
+    fn job_output(content: &str, size_in_bytes: u64) -> JobPodOutput {
+        JobPodOutput {
+            content: content.to_string(),
+            size_in_bytes,
+        }
+    }
+
+    #[test]
+    fn should_reject_oversized_job_output_without_truncation() {
+        let json = r#"{"foo": {"value": "bar"}, "foo_2": {"value": "ba"#;
+
+        let result = parse_job_output(&job_output(json, 1024 * 1024 * 50), 1024, None);
+
+        assert!(matches!(result, Err(JobOutputError::TooLarge)));
+    }
+
+    #[test]
+    fn should_reject_invalid_job_output() {
+        for json in [r#"{"foo": {"value": "bar"}"#, "not a json", ""] {
+            let result = parse_job_output(&job_output(json, json.len() as u64), 1024, None);
+            assert!(matches!(result, Err(JobOutputError::Invalid(_))), "`{json}` should be invalid");
+
+            let result = parse_job_output(&job_output(json, json.len() as u64), 1024, Some(10));
+            assert!(matches!(result, Err(JobOutputError::Invalid(_))), "`{json}` should be invalid");
+        }
+    }
+
+    #[test]
+    fn should_keep_complete_entries_of_oversized_job_output() {
+        // given
+        let json = r#"{"foo": {"value": "bar, \"}"}, "foo_2": {"value": [1, 2]}, "foo_3": {"value": "ba"#;
+
+        // when
+        let job_output = parse_job_output(&job_output(json, 1024 * 1024 * 50), json.len() as u64, Some(1024)).unwrap();
+
+        // then
+        assert_eq!(job_output.variables.len(), 2);
+        assert_eq!(job_output.variables.get("foo").unwrap().value, "bar, \"}");
+        assert_eq!(job_output.variables.get("foo_2").unwrap().value, "[1,2]");
+        assert_eq!(job_output.dropped_keys, vec!["foo_3".to_string()]);
+        assert!(job_output.truncated_keys.is_empty());
+        assert_eq!(
+            job_output.truncation_message(),
+            Some("Job output exceeds its max size, dropped keys: `foo_3` and the following ones".to_string())
+        );
+
+        // cut before the key of the next entry is read
+        assert_eq!(
+            keep_complete_entries(r#"{"foo": {"value": "bar"}, "fo"#),
+            (r#"{"foo": {"value": "bar"}}"#.to_string(), vec![])
+        );
+        assert_eq!(keep_complete_entries(r#"{"fo"#), ("{}".to_string(), vec![]));
+        assert_eq!(
+            keep_complete_entries(r#"{"foo": {"value": "bar"}}"#),
+            (r#"{"foo": {"value": "bar"}}"#.to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn should_truncate_long_job_output_values() {
+        // given
+        let json =
+            r#"{"short": {"value": "bar"}, "long": {"value": "éééé", "sensitive": true}, "number": {"value": 123456}}"#;
+
+        // when
+        let job_output = parse_job_output(&job_output(json, json.len() as u64), 1024, Some(5)).unwrap();
+
+        // then
+        assert_eq!(job_output.variables.get("short").unwrap().value, "bar");
+        // truncated on a char boundary
+        assert_eq!(
+            job_output.variables.get("long").unwrap(),
+            &JobOutputVariable {
+                value: format!("éé{JOB_OUTPUT_TRUNCATION_MARKER}"),
+                sensitive: true,
+                description: "".to_string(),
+            }
+        );
+        assert_eq!(
+            job_output.variables.get("number").unwrap().value,
+            format!("12345{JOB_OUTPUT_TRUNCATION_MARKER}")
+        );
+        assert_eq!(job_output.truncated_keys, vec!["long".to_string(), "number".to_string()]);
+        assert!(job_output.dropped_keys.is_empty());
+        assert_eq!(
+            job_output.truncation_message(),
+            Some("Job output values too long are truncated: `long`, `number`".to_string())
+        );
+
+        // the truncated output is still a valid json for the env vars injection
+        let json_final = serde_json::to_string(&job_output.variables).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&json_final).is_ok());
+    }
 }
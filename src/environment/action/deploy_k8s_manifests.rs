@@ -0,0 +1,695 @@
+use crate::cmd::git;
+use crate::cmd::kubectl::kubectl_exec_kustomize;
+use crate::environment::action::deploy_helm_chart::git_credentials_callback;
+use crate::environment::action::pause_service::PauseServiceAction;
+use crate::environment::action::restart_service::RestartServiceAction;
+use crate::environment::action::{DeploymentAction, K8sResourceType};
+use crate::environment::models::k8s_manifests::{
+    decode_applied_set, encode_applied_set, objects_to_prune, parse_manifests, prepare_objects, K8sManifests,
+    ManifestObjectRef, K8S_MANIFESTS_APPLIED_SET_ANNOTATION, K8S_MANIFESTS_FIELD_MANAGER,
+};
+use crate::environment::models::pod_security::validate_rendered_manifests;
+use crate::environment::report::k8s_manifests::reporter::K8sManifestsDeploymentReporter;
+use crate::environment::report::logger::{EnvProgressLogger, EnvSuccessLogger};
+use crate::environment::report::{execute_long_deployment, DeploymentTaskImpl};
+use crate::errors::{CommandError, EngineError};
+use crate::events::{EnvironmentStep, EventDetails, Stage};
+use crate::infrastructure::models::cloud_provider::service::{Action, Service};
+use crate::infrastructure::models::cloud_provider::DeploymentTarget;
+use crate::infrastructure::models::kubernetes::capabilities::ClusterFeature;
+use crate::kubers_utils::server_side_apply_params;
+use crate::runtime::block_on;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::api::{ApiResource, DeleteParams, DynamicObject, GroupVersionKind, Patch};
+use kube::discovery::{pinned_kind, Scope};
+use kube::runtime::wait::{await_condition, Condition};
+use kube::{Api, ResourceExt};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// Object ready to be applied, with its api resource resolved through discovery
+struct PreparedObject {
+    object: DynamicObject,
+    api_resource: ApiResource,
+}
+
+impl DeploymentAction for K8sManifests {
+    fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
+
+        let pre_run = |logger: &EnvProgressLogger| -> Result<Vec<PreparedObject>, Box<EngineError>> {
+            let manifests = fetch_manifests(self, &event_details, logger)?;
+            prepare_objects_to_apply(self, target, &manifests, &event_details, logger)
+        };
+
+        let run = |logger: &EnvProgressLogger,
+                   objects: Vec<PreparedObject>|
+         -> Result<Vec<PreparedObject>, Box<EngineError>> {
+            apply_objects(self, target, &objects, &event_details, logger)?;
+            wait_for_workloads(self, target, &objects, &event_details, logger)?;
+            Ok(objects)
+        };
+
+        let post_run = |_logger: &EnvSuccessLogger, _objects: Vec<PreparedObject>| {};
+
+        let task = DeploymentTaskImpl {
+            pre_run: &pre_run,
+            run: &run,
+            post_run_success: &post_run,
+        };
+
+        execute_long_deployment(K8sManifestsDeploymentReporter::new(self, target, Action::Create), task)
+    }
+
+    fn on_pause(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Pause));
+
+        let task = |_logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
+            for resource_type in [
+                K8sResourceType::CronJob,
+                K8sResourceType::Deployment,
+                K8sResourceType::StateFulSet,
+            ] {
+                PauseServiceAction::new_with_resource_type(
+                    self.kube_label_selector(),
+                    resource_type,
+                    Duration::from_secs(5 * 60),
+                    event_details.clone(),
+                    false,
+                    false,
+                )
+                .on_pause(target)?;
+            }
+            Ok(())
+        };
+
+        execute_long_deployment(K8sManifestsDeploymentReporter::new(self, target, Action::Pause), task)
+    }
+
+    fn on_delete(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Delete));
+
+        let task = |logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
+            let applied_objects = get_applied_set(self, target, &event_details)?;
+            for object_ref in &applied_objects {
+                logger.info(format!("🗑️ Deleting {}/{}", object_ref.kind, object_ref.name));
+                delete_object(self, target, object_ref, &event_details)?;
+            }
+
+            let config_map_api: Api<ConfigMap> = Api::namespaced(target.kube.clone(), target.environment.namespace());
+            if let Err(err) =
+                block_on(config_map_api.delete(self.applied_set_config_map_name().as_str(), &DeleteParams::default()))
+            {
+                warn!(
+                    "Cannot delete applied set config map {}: {}",
+                    self.applied_set_config_map_name(),
+                    err
+                );
+            }
+
+            Ok(())
+        };
+
+        execute_long_deployment(K8sManifestsDeploymentReporter::new(self, target, Action::Delete), task)
+    }
+
+    fn on_restart(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Restart));
+
+        let task = |_logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
+            for resource_type in [
+                K8sResourceType::DaemonSet,
+                K8sResourceType::Deployment,
+                K8sResourceType::StateFulSet,
+            ] {
+                RestartServiceAction::new_with_resource_type(
+                    self.kube_label_selector(),
+                    resource_type,
+                    event_details.clone(),
+                    false,
+                )
+                .on_restart(target)?;
+            }
+            Ok(())
+        };
+
+        execute_long_deployment(K8sManifestsDeploymentReporter::new(self, target, Action::Restart), task)
+    }
+}
+
+fn to_apply_error(this: &K8sManifests, event_details: &EventDetails, message: String, raw: String) -> Box<EngineError> {
+    Box::new(EngineError::new_k8s_manifests_apply_error(
+        event_details.clone(),
+        this.name().to_string(),
+        CommandError::new(message, Some(raw), None),
+    ))
+}
+
+// Clone the repository at the requested commit and render the manifests, either with kustomize
+// or by concatenating the YAML/JSON files of the directory, like `kubectl apply -f <dir>` would do.
+fn fetch_manifests(
+    this: &K8sManifests,
+    event_details: &EventDetails,
+    logger: &EnvProgressLogger,
+) -> Result<String, Box<EngineError>> {
+    let to_error = |message: &str, raw: String| {
+        Box::new(EngineError::new_k8s_manifests_invalid(
+            event_details.clone(),
+            this.name().to_string(),
+            vec![format!("{message}: {raw}")],
+        ))
+    };
+
+    logger.info(format!(
+        "📥 Cloning manifests from git repository {} at commit {}",
+        this.git_url, this.commit_id
+    ));
+    let repository_dir = this.repository_workspace_directory();
+    let _ = fs::remove_dir_all(&repository_dir);
+    let git_creds = (this.get_credentials)().map_err(|e| to_error("Cannot get git credentials", e.to_string()))?;
+    git::clone_at_commit(
+        &this.git_url,
+        &this.commit_id,
+        &repository_dir,
+        &git_credentials_callback(&git_creds, &this.ssh_keys),
+    )
+    .map_err(|e| to_error("Cannot clone git repository", e.to_string()))?;
+
+    let manifests_dir = this.manifests_workspace_directory();
+    if this.is_kustomize() {
+        logger.info(format!("🏗️ Building kustomization {}", this.root_path.display()));
+        return kubectl_exec_kustomize(&manifests_dir, vec![])
+            .map_err(|e| to_error("Cannot build kustomization", e.message_raw().unwrap_or_default()));
+    }
+
+    let mut files = fs::read_dir(&manifests_dir)
+        .map_err(|e| to_error("Cannot read manifests directory", e.to_string()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("yaml") | Some("yml") | Some("json")
+                )
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut manifests = String::new();
+    for file in files {
+        let content = fs::read_to_string(&file).map_err(|e| to_error("Cannot read manifest file", e.to_string()))?;
+        manifests.push_str("\n---\n");
+        manifests.push_str(&content);
+    }
+
+    Ok(manifests)
+}
+
+fn prepare_objects_to_apply(
+    this: &K8sManifests,
+    target: &DeploymentTarget,
+    manifests: &str,
+    event_details: &EventDetails,
+    logger: &EnvProgressLogger,
+) -> Result<Vec<PreparedObject>, Box<EngineError>> {
+    let invalid = |violations: Vec<String>| {
+        Box::new(EngineError::new_k8s_manifests_invalid(
+            event_details.clone(),
+            this.name().to_string(),
+            violations,
+        ))
+    };
+
+    let objects = parse_manifests(manifests).map_err(|e| invalid(vec![e]))?;
+    if objects.is_empty() {
+        return Err(invalid(vec!["No kubernetes object found in the manifests".to_string()]));
+    }
+
+    // Resolve scope of every kind through discovery, unknown kinds are reported as violations
+    let mut api_resources: HashMap<(String, String), Result<(ApiResource, bool), String>> = HashMap::new();
+    for types in objects.iter().filter_map(|o| o.types.as_ref()) {
+        let key = (types.api_version.clone(), types.kind.clone());
+        if api_resources.contains_key(&key) {
+            continue;
+        }
+        let (group, version) = types
+            .api_version
+            .split_once('/')
+            .unwrap_or(("", types.api_version.as_str()));
+        let gvk = GroupVersionKind::gvk(group, version, &types.kind);
+        let resolved = block_on(pinned_kind(&target.kube, &gvk))
+            .map(|(api_resource, capabilities)| (api_resource, matches!(capabilities.scope, Scope::Namespaced)))
+            .map_err(|e| format!("kind is not served by the cluster: {e}"));
+        api_resources.insert(key, resolved);
+    }
+
+    logger.info("🔬 Checking objects do not cross namespace boundary".to_string());
+    let labels = BTreeMap::from([
+        ("qovery.com/service-id".to_string(), this.long_id.to_string()),
+        ("qovery.com/service-type".to_string(), "k8s-manifests".to_string()),
+        ("qovery.com/environment-id".to_string(), target.environment.long_id.to_string()),
+        (
+            "qovery.com/project-id".to_string(),
+            target.environment.project_long_id.to_string(),
+        ),
+        ("app.kubernetes.io/managed-by".to_string(), "qovery".to_string()),
+    ]);
+    let mut objects = prepare_objects(
+        objects,
+        target.environment.namespace(),
+        this.allowed_kinds_outside_namespace(),
+        &labels,
+        |api_version, kind| match api_resources.get(&(api_version.to_string(), kind.to_string())) {
+            Some(Ok((_, namespaced))) => Ok(*namespaced),
+            Some(Err(err)) => Err(err.clone()),
+            None => Err("kind cannot be resolved".to_string()),
+        },
+    )
+    .map_err(invalid)?;
+
+    if let Err(unsupported) = target.capabilities.require(ClusterFeature::NativeSidecars) {
+        for object in objects.iter_mut() {
+            let kind = object.types.as_ref().map(|t| t.kind.clone()).unwrap_or_default();
+            for sidecar in target.capabilities.downgrade_native_sidecars(&kind, &mut object.data) {
+                logger.warning(format!(
+                    "⚠️ {kind}/{}: sidecar `{sidecar}` is deployed as a regular container, {unsupported}",
+                    object.name_any(),
+                ));
+            }
+        }
+    }
+
+    let violations = validate_rendered_manifests(target.environment.pod_security_profile, this.name(), manifests)
+        .map_err(|e| invalid(vec![e.to_string()]))?;
+    if !violations.is_empty() {
+        return Err(Box::new(EngineError::new_k8s_pod_security_violation(
+            event_details.clone(),
+            target.environment.pod_security_profile.to_string(),
+            violations.iter().map(|v| v.to_string()).collect(),
+        )));
+    }
+
+    Ok(objects
+        .into_iter()
+        .filter_map(|object| {
+            let types = object.types.as_ref()?;
+            let (api_resource, _) = api_resources
+                .get(&(types.api_version.clone(), types.kind.clone()))?
+                .as_ref()
+                .ok()?
+                .clone();
+            Some(PreparedObject { object, api_resource })
+        })
+        .collect())
+}
+
+fn dynamic_api(target: &DeploymentTarget, api_resource: &ApiResource, namespace: Option<&str>) -> Api<DynamicObject> {
+    match namespace {
+        Some(namespace) => Api::namespaced_with(target.kube.clone(), namespace, api_resource),
+        None => Api::all_with(target.kube.clone(), api_resource),
+    }
+}
+
+fn get_applied_set(
+    this: &K8sManifests,
+    target: &DeploymentTarget,
+    event_details: &EventDetails,
+) -> Result<BTreeSet<ManifestObjectRef>, Box<EngineError>> {
+    let config_map_api: Api<ConfigMap> = Api::namespaced(target.kube.clone(), target.environment.namespace());
+    let config_map = block_on(config_map_api.get_opt(&this.applied_set_config_map_name())).map_err(|e| {
+        to_apply_error(
+            this,
+            event_details,
+            "Cannot read previously applied objects".to_string(),
+            e.to_string(),
+        )
+    })?;
+
+    let annotation = config_map
+        .as_ref()
+        .and_then(|cm| cm.annotations().get(K8S_MANIFESTS_APPLIED_SET_ANNOTATION))
+        .cloned()
+        .unwrap_or_default();
+
+    decode_applied_set(&annotation)
+        .map_err(|e| to_apply_error(this, event_details, "Invalid applied set annotation".to_string(), e))
+}
+
+fn save_applied_set(
+    this: &K8sManifests,
+    target: &DeploymentTarget,
+    applied_objects: &BTreeSet<ManifestObjectRef>,
+    event_details: &EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let name = this.applied_set_config_map_name();
+    let config_map = json!({
+        "apiVersion": "v1",
+        "kind": "ConfigMap",
+        "metadata": {
+            "name": name,
+            "labels": {
+                "qovery.com/service-id": this.long_id.to_string(),
+                "qovery.com/service-type": "k8s-manifests",
+            },
+            "annotations": {
+                K8S_MANIFESTS_APPLIED_SET_ANNOTATION: encode_applied_set(applied_objects),
+            },
+        },
+    });
+
+    let config_map_api: Api<ConfigMap> = Api::namespaced(target.kube.clone(), target.environment.namespace());
+    block_on(config_map_api.patch(
+        &name,
+        &server_side_apply_params(K8S_MANIFESTS_FIELD_MANAGER, &target.capabilities),
+        &Patch::Apply(config_map),
+    ))
+    .map_err(|e| to_apply_error(this, event_details, "Cannot save applied objects".to_string(), e.to_string()))?;
+
+    Ok(())
+}
+
+fn delete_object(
+    this: &K8sManifests,
+    target: &DeploymentTarget,
+    object_ref: &ManifestObjectRef,
+    event_details: &EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let (group, version) = object_ref.group_version();
+    let gvk = GroupVersionKind::gvk(group, version, &object_ref.kind);
+    let api_resource = match block_on(pinned_kind(&target.kube, &gvk)) {
+        Ok((api_resource, _)) => api_resource,
+        Err(err) => {
+            // The kind is not served anymore (i.e: CRD removed), so there is no object left to delete
+            warn!("Cannot resolve {} for deletion, skipping: {}", object_ref, err);
+            return Ok(());
+        }
+    };
+
+    let api = dynamic_api(target, &api_resource, object_ref.namespace.as_deref());
+    match block_on(api.delete(&object_ref.name, &DeleteParams::background())) {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(()),
+        Err(err) => Err(to_apply_error(
+            this,
+            event_details,
+            format!("Cannot delete {}/{}", object_ref.kind, object_ref.name),
+            err.to_string(),
+        )),
+    }
+}
+
+// Bookkeeping is done in 3 steps, so an interrupted deployment never loses track of an applied object:
+// 1. save previous + current objects as applied before applying anything
+// 2. apply current objects and prune previous ones not present anymore
+// 3. save only the current objects as applied
+fn apply_objects(
+    this: &K8sManifests,
+    target: &DeploymentTarget,
+    objects: &[PreparedObject],
+    event_details: &EventDetails,
+    logger: &EnvProgressLogger,
+) -> Result<(), Box<EngineError>> {
+    let previously_applied = get_applied_set(this, target, event_details)?;
+    let currently_applied: BTreeSet<ManifestObjectRef> = objects
+        .iter()
+        .filter_map(|o| ManifestObjectRef::from_object(&o.object))
+        .collect();
+
+    let all_objects = previously_applied.union(&currently_applied).cloned().collect();
+    save_applied_set(this, target, &all_objects, event_details)?;
+
+    let patch_params = server_side_apply_params(K8S_MANIFESTS_FIELD_MANAGER, &target.capabilities);
+    for prepared in objects {
+        if target.abort.status().should_cancel() {
+            return Err(Box::new(EngineError::new_task_cancellation_requested(event_details.clone())));
+        }
+
+        let object = &prepared.object;
+        let kind = object.types.as_ref().map(|t| t.kind.as_str()).unwrap_or_default();
+        let name = object.name_any();
+        logger.info(format!("📝 Applying {kind}/{name}"));
+
+        let api = dynamic_api(target, &prepared.api_resource, object.metadata.namespace.as_deref());
+        block_on(api.patch(&name, &patch_params, &Patch::Apply(object)))
+            .map_err(|e| to_apply_error(this, event_details, format!("Cannot apply {kind}/{name}"), e.to_string()))?;
+    }
+
+    for object_ref in objects_to_prune(&previously_applied, &currently_applied) {
+        logger.info(format!(
+            "🧹 Pruning {}/{} not present in the manifests anymore",
+            object_ref.kind, object_ref.name
+        ));
+        delete_object(this, target, &object_ref, event_details)?;
+    }
+
+    save_applied_set(this, target, &currently_applied, event_details)
+}
+
+fn wait_for_workloads(
+    this: &K8sManifests,
+    target: &DeploymentTarget,
+    objects: &[PreparedObject],
+    event_details: &EventDetails,
+    logger: &EnvProgressLogger,
+) -> Result<(), Box<EngineError>> {
+    let deadline = Instant::now() + this.timeout();
+
+    for prepared in objects {
+        let object = &prepared.object;
+        let Some(kind) = object.types.as_ref().map(|t| t.kind.as_str()) else {
+            continue;
+        };
+        let name = object.name_any();
+        let namespace = object
+            .metadata
+            .namespace
+            .clone()
+            .unwrap_or_else(|| target.environment.namespace().to_string());
+        let client = target.kube.clone();
+
+        if !matches!(kind, "Deployment" | "StatefulSet" | "DaemonSet" | "Job") {
+            continue;
+        }
+        if target.abort.status().should_cancel() {
+            return Err(Box::new(EngineError::new_task_cancellation_requested(event_details.clone())));
+        }
+
+        logger.info(format!("⏳ Waiting for {kind}/{name} to be ready"));
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let ret = block_on(async {
+            match kind {
+                "Deployment" => tokio::time::timeout(
+                    remaining,
+                    await_condition(Api::<Deployment>::namespaced(client, &namespace), &name, is_deployment_ready()),
+                )
+                .await
+                .map(|r| r.map(|_| Ok(()))),
+                "StatefulSet" => tokio::time::timeout(
+                    remaining,
+                    await_condition(
+                        Api::<StatefulSet>::namespaced(client, &namespace),
+                        &name,
+                        is_stateful_set_ready(),
+                    ),
+                )
+                .await
+                .map(|r| r.map(|_| Ok(()))),
+                "DaemonSet" => tokio::time::timeout(
+                    remaining,
+                    await_condition(Api::<DaemonSet>::namespaced(client, &namespace), &name, is_daemon_set_ready()),
+                )
+                .await
+                .map(|r| r.map(|_| Ok(()))),
+                _ => tokio::time::timeout(
+                    remaining,
+                    await_condition(Api::<Job>::namespaced(client, &namespace), &name, is_job_finished()),
+                )
+                .await
+                .map(|r| r.map(|job| job_failure(job.as_ref()).map_or(Ok(()), Err))),
+            }
+        });
+
+        match ret {
+            Ok(Ok(Ok(()))) => logger.info(format!("✅ {kind}/{name} is ready")),
+            Ok(Ok(Err(job_failure))) => {
+                return Err(to_apply_error(this, event_details, format!("Job {name} failed"), job_failure))
+            }
+            Ok(Err(err)) => {
+                return Err(to_apply_error(
+                    this,
+                    event_details,
+                    format!("Cannot watch {kind}/{name}"),
+                    err.to_string(),
+                ))
+            }
+            Err(_) => {
+                return Err(to_apply_error(
+                    this,
+                    event_details,
+                    format!("{kind}/{name} is not ready after {} seconds", this.timeout().as_secs()),
+                    "timeout".to_string(),
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn is_deployment_ready() -> impl Condition<Deployment> {
+    |deployment: Option<&Deployment>| {
+        let Some(deployment) = deployment else { return false };
+        let Some(status) = &deployment.status else { return false };
+        let replicas = deployment.spec.as_ref().and_then(|s| s.replicas).unwrap_or(1);
+
+        status.observed_generation >= deployment.metadata.generation
+            && status.updated_replicas.unwrap_or(0) == replicas
+            && status.available_replicas.unwrap_or(0) == replicas
+            && status.replicas.unwrap_or(0) == replicas
+    }
+}
+
+fn is_stateful_set_ready() -> impl Condition<StatefulSet> {
+    |stateful_set: Option<&StatefulSet>| {
+        let Some(stateful_set) = stateful_set else { return false };
+        let Some(status) = &stateful_set.status else {
+            return false;
+        };
+        let replicas = stateful_set.spec.as_ref().and_then(|s| s.replicas).unwrap_or(1);
+
+        status.observed_generation >= stateful_set.metadata.generation
+            && status.updated_replicas.unwrap_or(0) == replicas
+            && status.ready_replicas.unwrap_or(0) == replicas
+    }
+}
+
+fn is_daemon_set_ready() -> impl Condition<DaemonSet> {
+    |daemon_set: Option<&DaemonSet>| {
+        let Some(daemon_set) = daemon_set else { return false };
+        let Some(status) = &daemon_set.status else { return false };
+
+        status.observed_generation >= daemon_set.metadata.generation
+            && status.updated_number_scheduled.unwrap_or(0) == status.desired_number_scheduled
+            && status.number_ready == status.desired_number_scheduled
+    }
+}
+
+fn is_job_finished() -> impl Condition<Job> {
+    |job: Option<&Job>| {
+        job.and_then(|j| j.status.as_ref())
+            .and_then(|s| s.conditions.as_ref())
+            .map(|conditions| {
+                conditions
+                    .iter()
+                    .any(|c| (c.type_ == "Complete" || c.type_ == "Failed") && c.status == "True")
+            })
+            .unwrap_or(false)
+    }
+}
+
+fn job_failure(job: Option<&Job>) -> Option<String> {
+    job.and_then(|j| j.status.as_ref())
+        .and_then(|s| s.conditions.as_ref())
+        .and_then(|conditions| conditions.iter().find(|c| c.type_ == "Failed" && c.status == "True"))
+        .map(|c| {
+            format!(
+                "{}: {}",
+                c.reason.clone().unwrap_or_default(),
+                c.message.clone().unwrap_or_default()
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::apps::v1::{DeploymentSpec, DeploymentStatus, StatefulSetSpec, StatefulSetStatus};
+    use k8s_openapi::api::batch::v1::{JobCondition, JobStatus};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+    fn deployment(generation: i64, observed_generation: i64, replicas: i32, available: i32) -> Deployment {
+        Deployment {
+            metadata: ObjectMeta {
+                generation: Some(generation),
+                ..Default::default()
+            },
+            spec: Some(DeploymentSpec {
+                replicas: Some(replicas),
+                ..Default::default()
+            }),
+            status: Some(DeploymentStatus {
+                observed_generation: Some(observed_generation),
+                replicas: Some(replicas),
+                updated_replicas: Some(available),
+                available_replicas: Some(available),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_deployment_readiness() {
+        assert!(is_deployment_ready().matches_object(Some(&deployment(2, 2, 3, 3))));
+        // rollout of the new generation not observed yet
+        assert!(!is_deployment_ready().matches_object(Some(&deployment(3, 2, 3, 3))));
+        assert!(!is_deployment_ready().matches_object(Some(&deployment(2, 2, 3, 1))));
+        assert!(!is_deployment_ready().matches_object(None));
+    }
+
+    #[test]
+    fn test_stateful_set_readiness() {
+        let mut stateful_set = StatefulSet {
+            metadata: ObjectMeta {
+                generation: Some(1),
+                ..Default::default()
+            },
+            spec: Some(StatefulSetSpec {
+                replicas: Some(2),
+                ..Default::default()
+            }),
+            status: Some(StatefulSetStatus {
+                observed_generation: Some(1),
+                replicas: 2,
+                ready_replicas: Some(1),
+                updated_replicas: Some(2),
+                ..Default::default()
+            }),
+        };
+        assert!(!is_stateful_set_ready().matches_object(Some(&stateful_set)));
+
+        stateful_set.status.as_mut().unwrap().ready_replicas = Some(2);
+        assert!(is_stateful_set_ready().matches_object(Some(&stateful_set)));
+    }
+
+    #[test]
+    fn test_job_completion() {
+        let job = |type_: &str, status: &str| Job {
+            status: Some(JobStatus {
+                conditions: Some(vec![JobCondition {
+                    type_: type_.to_string(),
+                    status: status.to_string(),
+                    reason: Some("BackoffLimitExceeded".to_string()),
+                    message: Some("Job has reached the specified backoff limit".to_string()),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert!(!is_job_finished().matches_object(Some(&Job::default())));
+        assert!(!is_job_finished().matches_object(Some(&job("Complete", "False"))));
+        assert!(is_job_finished().matches_object(Some(&job("Complete", "True"))));
+        assert!(is_job_finished().matches_object(Some(&job("Failed", "True"))));
+
+        assert_eq!(job_failure(Some(&job("Complete", "True"))), None);
+        assert_eq!(
+            job_failure(Some(&job("Failed", "True"))),
+            Some("BackoffLimitExceeded: Job has reached the specified backoff limit".to_string())
+        );
+    }
+}
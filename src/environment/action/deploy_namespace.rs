@@ -1,7 +1,10 @@
 use crate::environment::action::DeploymentAction;
+use crate::environment::models::pod_security::PodSecurityProfile;
 use crate::errors::{CommandError, EngineError};
 use crate::events::EventDetails;
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
+use crate::infrastructure::models::kubernetes::capabilities::ClusterFeature;
+use crate::infrastructure::models::kubernetes::cluster_metadata::{apply_cluster_metadata, ClusterMetadata};
 use crate::infrastructure::models::kubernetes::kube_create_namespace_if_not_exists;
 use crate::runtime::block_on;
 use k8s_openapi::api::core::v1::Namespace;
@@ -25,10 +28,28 @@ impl DeploymentAction for NamespaceDeployment {
             ),
         ]);
 
+        let cluster_metadata = ClusterMetadata::new(target.kubernetes, target.dns_provider.domain().to_string());
+        namespace_labels.extend(cluster_metadata.namespace_labels());
+
         if let Some(resource_expiration) = &self.resource_expiration {
             namespace_labels.insert("ttl".to_string(), format!("{}", resource_expiration.as_secs()));
         };
 
+        // pod security admission labels, always set so a profile change is applied on redeploy
+        let profile = target.environment.pod_security_profile;
+        match target.capabilities.require(ClusterFeature::PodSecurityLabels) {
+            Ok(()) => namespace_labels.extend(profile.namespace_labels()),
+            // nothing to enforce with the privileged profile, labels are only skipped
+            Err(_) if profile == PodSecurityProfile::Privileged => {}
+            Err(err) => {
+                return Err(Box::new(EngineError::new_k8s_unsupported_feature(
+                    self.event_details.clone(),
+                    target.environment.namespace(),
+                    format!("`{profile}` pod security profile cannot be enforced, {err}"),
+                )))
+            }
+        }
+
         // create a namespace with labels if it does not exist
         block_on(kube_create_namespace_if_not_exists(
             &target.kube,
@@ -47,6 +68,19 @@ impl DeploymentAction for NamespaceDeployment {
             )
         })?;
 
+        // only read by the services opting in, they start without it if it is missing
+        if let Err(err) = block_on(apply_cluster_metadata(
+            &target.kube,
+            target.environment.namespace(),
+            &cluster_metadata,
+        )) {
+            warn!(
+                "cannot update the cluster metadata ConfigMap of namespace {}: {}",
+                target.environment.namespace(),
+                err
+            );
+        }
+
         Ok(())
     }
 
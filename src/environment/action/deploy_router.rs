@@ -1,20 +1,62 @@
 use crate::environment::action::check_dns::CheckDnsForDomains;
 use crate::environment::action::deploy_helm::HelmDeployment;
+use crate::environment::action::router_basic_auth::{basic_auth_secret, sync_basic_auth_secret, BasicAuthSecretAction};
+use crate::environment::action::secret_client::SecretClient;
 use crate::environment::action::DeploymentAction;
-use crate::environment::models::router::Router;
+use crate::environment::models::router::{Router, RouterError};
 use crate::environment::models::types::{CloudProvider, ToTeraContext};
 use crate::environment::report::router::reporter::RouterDeploymentReporter;
 use crate::environment::report::{execute_long_deployment, DeploymentTaskImpl};
 use crate::errors::EngineError;
-use crate::events::{EnvironmentStep, Stage};
+use crate::events::{EnvironmentStep, EventDetails, Stage};
 use crate::helm::{ChartInfo, HelmAction, HelmChartNamespaces};
 use crate::infrastructure::models::cloud_provider::service::{Action, Service};
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
 use crate::io_models::models::CustomDomain;
 
 use crate::environment::report::logger::{EnvProgressLogger, EnvSuccessLogger};
+use base64::engine::general_purpose;
+use base64::Engine;
+use k8s_openapi::api::core::v1::Secret;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
+impl<T: CloudProvider> Router<T> {
+    fn basic_auth_secret(
+        &self,
+        target: &DeploymentTarget,
+        event_details: &EventDetails,
+    ) -> Result<Option<Secret>, Box<EngineError>> {
+        let Some(htpasswd) = &self.advanced_settings.basic_auth else {
+            return Ok(None);
+        };
+        let htpasswd = general_purpose::STANDARD.decode(htpasswd).map_err(|_| {
+            Box::new(EngineError::new_router_error(
+                event_details.clone(),
+                RouterError::InvalidConfig("Basic auth htpasswd content is not base64 encoded".to_string()),
+            ))
+        })?;
+
+        let mut labels = BTreeMap::from([
+            ("qovery.com/service-id".to_string(), self.long_id().to_string()),
+            ("qovery.com/service-type".to_string(), "router".to_string()),
+            ("qovery.com/environment-id".to_string(), target.environment.long_id.to_string()),
+            (
+                "qovery.com/project-id".to_string(),
+                target.environment.project_long_id.to_string(),
+            ),
+        ]);
+        labels.extend(self.labels_group.common.clone());
+
+        Ok(Some(basic_auth_secret(
+            &self.basic_auth_secret_name(),
+            target.environment.namespace(),
+            labels,
+            htpasswd,
+        )))
+    }
+}
+
 impl<T: CloudProvider> DeploymentAction for Router<T>
 where
     Router<T>: ToTeraContext,
@@ -23,6 +65,23 @@ where
         let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
         let pre_run = |_: &EnvProgressLogger| -> Result<(), Box<EngineError>> { Ok(()) };
         let run = |logger: &EnvProgressLogger, _: ()| -> Result<(), Box<EngineError>> {
+            if let Some(env_var) = &self.advanced_settings.basic_auth_env_var {
+                logger.warning(format!(
+                    "⚠️ Basic auth defined by the `{env_var}` environment variable (`network.ingress.basic_auth_env_var`) is deprecated, set the basic auth credentials on the router instead"
+                ));
+            }
+
+            // the secret must exist before the ingress referencing it, nginx rejects every request otherwise
+            if !target.is_dry_run_deploy {
+                let client = SecretClient::new(target.kube.clone(), target.environment.namespace());
+                let desired = self.basic_auth_secret(target, &event_details)?;
+                let action =
+                    sync_basic_auth_secret(&client, &event_details, &self.basic_auth_secret_name(), desired.as_ref())?;
+                if action == BasicAuthSecretAction::Updated {
+                    logger.info("🔐 Basic auth credentials rotated".to_string());
+                }
+            }
+
             let chart = ChartInfo {
                 name: self.helm_release_name(),
                 path: self.workspace_directory().to_string(),
@@ -37,7 +96,8 @@ where
                 PathBuf::from(self.helm_chart_dir()),
                 None,
                 chart,
-            );
+            )
+            .with_release_owner(*self.long_id(), Some(self.legacy_helm_release_name()));
 
             helm.on_create(target)?;
 
@@ -95,9 +155,20 @@ where
                     PathBuf::from(self.helm_chart_dir().as_str()),
                     None,
                     chart,
-                );
+                )
+                .with_release_owner(*self.long_id(), Some(self.legacy_helm_release_name()));
+
+                helm.on_delete(target)?;
+
+                let client = SecretClient::new(target.kube.clone(), target.environment.namespace());
+                sync_basic_auth_secret(
+                    &client,
+                    &self.get_event_details(Stage::Environment(EnvironmentStep::Delete)),
+                    &self.basic_auth_secret_name(),
+                    None,
+                )?;
 
-                helm.on_delete(target)
+                Ok(())
                 // FIXME: Delete also certificates
             },
         )
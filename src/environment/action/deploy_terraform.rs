@@ -1,4 +1,5 @@
 use crate::cmd;
+use crate::cmd::command::CommandKiller;
 use crate::cmd::kubectl::kubectl_exec_delete_secret;
 use crate::cmd::terraform_validators::TerraformValidators;
 use crate::environment::action::DeploymentAction;
@@ -94,18 +95,41 @@ impl TerraformDeployment {
 impl DeploymentAction for TerraformDeployment {
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         self.prepare_terraform_files()?;
-        let ret = cmd::terraform::terraform_init_validate_plan_apply(
-            &self.destination_folder.to_string_lossy(),
+        let root_dir = self.destination_folder.to_string_lossy();
+        let envs = target.cloud_provider.credentials_environment_variables();
+        let to_engine_error = |err| Box::new(EngineError::new_terraform_error(self.event_details.clone(), err));
+        let cmd_killer = CommandKiller::from_cancelable(target.abort);
+
+        cmd::terraform::terraform_init_validate(&root_dir, envs.as_slice(), &TerraformValidators::Default, &cmd_killer)
+            .map_err(to_engine_error)?;
+        cmd::terraform::terraform_plan(&root_dir, envs.as_slice(), false, &cmd_killer).map_err(to_engine_error)?;
+
+        // a replaced managed database loses its data
+        let plan_summary =
+            cmd::terraform::terraform_plan_summary(&root_dir, envs.as_slice(), &cmd_killer).map_err(to_engine_error)?;
+        let protected_replacements = plan_summary.protected_replacements();
+        if !protected_replacements.is_empty()
+            && !target
+                .kubernetes
+                .advanced_settings()
+                .terraform_allow_destructive_replacements
+        {
+            return Err(Box::new(EngineError::new_terraform_destructive_replacement_refused(
+                self.event_details.clone(),
+                protected_replacements.into_iter().map(str::to_string).collect(),
+            )));
+        }
+
+        cmd::terraform::terraform_apply(
+            &root_dir,
             self.is_dry_run,
-            target.cloud_provider.credentials_environment_variables().as_slice(),
+            envs.as_slice(),
             &TerraformValidators::Default,
-        );
+            &cmd_killer,
+        )
+        .map_err(to_engine_error)?;
 
-        if let Err(err) = ret {
-            Err(Box::new(EngineError::new_terraform_error(self.event_details.clone(), err)))
-        } else {
-            Ok(())
-        }
+        Ok(())
     }
 
     fn on_pause(&self, _target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
@@ -118,6 +142,7 @@ impl DeploymentAction for TerraformDeployment {
             &self.destination_folder.to_string_lossy(),
             target.cloud_provider.credentials_environment_variables().as_slice(),
             &TerraformValidators::None,
+            &CommandKiller::from_cancelable(target.abort),
         ) {
             Ok(_) => {
                 if let Err(err) = TerraformDeployment::delete_tfstate_secret(
@@ -0,0 +1,313 @@
+use crate::cmd::command::CommandKiller;
+use crate::cmd::docker::{ContainerImage, Docker, DockerError};
+use crate::environment::models::abort::Abort;
+use retry::delay::Fixed;
+use retry::OperationResult;
+use std::time::Duration;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ImageMirroringError {
+    #[error("Image `{image}` does not exist in the registry of the service")]
+    SourceImageNotFound { image: String },
+
+    #[error("Cannot read the digest of image `{image}`: {error}")]
+    CannotGetDigest { image: String, error: String },
+
+    #[error("Cannot copy image `{source_image}` to `{destination}`: {error}")]
+    CopyFailed {
+        source_image: String,
+        destination: String,
+        error: String,
+    },
+
+    #[error("Copy of image `{source_image}` to `{destination}` has been aborted")]
+    Aborted { source_image: String, destination: String },
+
+    #[error(
+        "Image `{destination}` points to digest `{}` instead of `{expected}` after copying `{source_image}`",
+        actual.as_deref().unwrap_or("none")
+    )]
+    DigestMismatch {
+        source_image: String,
+        destination: String,
+        expected: String,
+        actual: Option<String>,
+    },
+}
+
+/// Registry operations needed to mirror an image
+pub trait MirroringRegistry {
+    /// Digest the image points to, `None` when it does not exist
+    fn image_digest(&self, image: &ContainerImage) -> Result<Option<String>, DockerError>;
+    fn copy_image(&self, source: &ContainerImage, dest: &ContainerImage) -> Result<(), DockerError>;
+}
+
+pub struct DockerMirroringRegistry<'a> {
+    pub docker: &'a Docker,
+    pub abort: &'a dyn Abort,
+}
+
+impl MirroringRegistry for DockerMirroringRegistry<'_> {
+    fn image_digest(&self, image: &ContainerImage) -> Result<Option<String>, DockerError> {
+        self.docker.image_digest(image)
+    }
+
+    fn copy_image(&self, source: &ContainerImage, dest: &ContainerImage) -> Result<(), DockerError> {
+        retry::retry(Fixed::from_millis(1000).take(3), || {
+            match self.docker.mirror(
+                source,
+                dest,
+                &mut |line| info!("{}", line),
+                &mut |line| warn!("{}", line),
+                // Set timeout at 15min (arbitrary value)
+                &CommandKiller::from(Duration::from_secs(60 * 15), self.abort),
+            ) {
+                Ok(ret) => OperationResult::Ok(ret),
+                Err(err) if err.is_aborted() => OperationResult::Err(err),
+                Err(err) => {
+                    error!("docker mirror error: {:?}", err);
+                    OperationResult::Retry(err)
+                }
+            }
+        })
+        .map_err(|err| err.error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MirroringPlan {
+    /// The destination already points to the digest of the source tag
+    AlreadyMirrored { digest: String },
+    /// The source tag points to this digest, which must be copied
+    Copy { digest: String },
+}
+
+fn image_digest(
+    registry: &dyn MirroringRegistry,
+    image: &ContainerImage,
+) -> Result<Option<String>, ImageMirroringError> {
+    registry
+        .image_digest(image)
+        .map_err(|err| ImageMirroringError::CannotGetDigest {
+            image: image.image_name(),
+            error: err.to_string(),
+        })
+}
+
+/// Resolves the digest the source tag points to, and whether the destination already points to it.
+/// The copy is then done by digest, so a source tag pushed again in the meantime is not copied half way.
+pub fn plan_image_mirroring(
+    registry: &dyn MirroringRegistry,
+    source: &ContainerImage,
+    dest: &ContainerImage,
+) -> Result<MirroringPlan, ImageMirroringError> {
+    let digest = image_digest(registry, source)?.ok_or_else(|| ImageMirroringError::SourceImageNotFound {
+        image: source.image_name(),
+    })?;
+
+    match image_digest(registry, dest)? {
+        Some(dest_digest) if dest_digest == digest => Ok(MirroringPlan::AlreadyMirrored { digest }),
+        _ => Ok(MirroringPlan::Copy { digest }),
+    }
+}
+
+/// Copies the source pinned to `digest` to the destination tag, then checks the destination points to the same digest
+pub fn copy_image_by_digest(
+    registry: &dyn MirroringRegistry,
+    source: &ContainerImage,
+    digest: &str,
+    dest: &ContainerImage,
+) -> Result<(), ImageMirroringError> {
+    let source_by_digest =
+        ContainerImage::new_for_digest(source.registry.clone(), source.name.clone(), digest.to_string());
+    registry
+        .copy_image(&source_by_digest, dest)
+        .map_err(|err| match err.is_aborted() {
+            true => ImageMirroringError::Aborted {
+                source_image: source_by_digest.image_name(),
+                destination: dest.image_name(),
+            },
+            false => ImageMirroringError::CopyFailed {
+                source_image: source_by_digest.image_name(),
+                destination: dest.image_name(),
+                error: err.to_string(),
+            },
+        })?;
+
+    match image_digest(registry, dest)? {
+        Some(dest_digest) if dest_digest == digest => Ok(()),
+        actual => Err(ImageMirroringError::DigestMismatch {
+            source_image: source_by_digest.image_name(),
+            destination: dest.image_name(),
+            expected: digest.to_string(),
+            actual,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use url::Url;
+
+    const DIGEST: &str = "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+    const OTHER_DIGEST: &str = "sha256:fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210";
+
+    /// In memory registries, digest of each image keyed by its name (`host/name:tag` or `host/name@digest`)
+    #[derive(Default)]
+    struct MockRegistry {
+        digests: RefCell<BTreeMap<String, String>>,
+        copies: RefCell<Vec<(String, String)>>,
+        /// The copy re-creates the manifest with another digest
+        rewrite_digest: bool,
+        fail_copy: bool,
+    }
+
+    impl MockRegistry {
+        fn with_image(self, image: &ContainerImage, digest: &str) -> Self {
+            self.digests.borrow_mut().insert(image.image_name(), digest.to_string());
+            self
+        }
+    }
+
+    impl MirroringRegistry for MockRegistry {
+        fn image_digest(&self, image: &ContainerImage) -> Result<Option<String>, DockerError> {
+            let name = image.image_name();
+            match name.rsplit_once('@') {
+                Some((repository, digest)) => Ok(self
+                    .digests
+                    .borrow()
+                    .iter()
+                    .any(|(image, d)| image.starts_with(repository) && d == digest)
+                    .then(|| digest.to_string())),
+                None => Ok(self.digests.borrow().get(&name).cloned()),
+            }
+        }
+
+        fn copy_image(&self, source: &ContainerImage, dest: &ContainerImage) -> Result<(), DockerError> {
+            if self.fail_copy {
+                return Err(DockerError::Timeout {
+                    raw_error_message: "registry unreachable".to_string(),
+                });
+            }
+
+            self.copies.borrow_mut().push((source.image_name(), dest.image_name()));
+            let source_name = source.image_name();
+            let (_, digest) = source_name.rsplit_once('@').expect("images must be copied by digest");
+            let digest = match self.rewrite_digest {
+                true => OTHER_DIGEST,
+                false => digest,
+            };
+            self.digests.borrow_mut().insert(dest.image_name(), digest.to_string());
+            Ok(())
+        }
+    }
+
+    fn images() -> (ContainerImage, ContainerImage) {
+        (
+            ContainerImage::new(
+                Url::parse("https://source.registry.io").unwrap(),
+                "team/app".to_string(),
+                vec!["v1".to_string()],
+            ),
+            ContainerImage::new(
+                Url::parse("https://cluster.registry.io").unwrap(),
+                "qovery-mirror".to_string(),
+                vec!["team.app.v1".to_string()],
+            ),
+        )
+    }
+
+    #[test]
+    fn test_mirroring_is_skipped_when_destination_has_the_digest() {
+        let (source, dest) = images();
+        let registry = MockRegistry::default()
+            .with_image(&source, DIGEST)
+            .with_image(&dest, DIGEST);
+
+        assert_eq!(
+            plan_image_mirroring(&registry, &source, &dest),
+            Ok(MirroringPlan::AlreadyMirrored {
+                digest: DIGEST.to_string()
+            })
+        );
+        assert!(registry.copies.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_image_is_copied_by_digest() {
+        let (source, dest) = images();
+        // the source tag has been pushed again since the last mirroring
+        let registry = MockRegistry::default()
+            .with_image(&source, DIGEST)
+            .with_image(&dest, OTHER_DIGEST);
+
+        let plan = plan_image_mirroring(&registry, &source, &dest);
+        assert_eq!(
+            plan,
+            Ok(MirroringPlan::Copy {
+                digest: DIGEST.to_string()
+            })
+        );
+
+        // the source tag moves again before the copy, the resolved digest is copied anyway
+        registry
+            .digests
+            .borrow_mut()
+            .insert(source.image_name(), OTHER_DIGEST.to_string());
+        assert_eq!(copy_image_by_digest(&registry, &source, DIGEST, &dest), Ok(()));
+        assert_eq!(
+            registry.copies.borrow().as_slice(),
+            &[(
+                format!("source.registry.io/team/app@{DIGEST}"),
+                "cluster.registry.io/qovery-mirror:team.app.v1".to_string()
+            )]
+        );
+        assert_eq!(registry.image_digest(&dest).unwrap().as_deref(), Some(DIGEST));
+    }
+
+    #[test]
+    fn test_mirroring_errors_name_source_and_destination() {
+        let (source, dest) = images();
+        let registry = MockRegistry::default();
+        let err = plan_image_mirroring(&registry, &source, &dest).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Image `source.registry.io/team/app:v1` does not exist in the registry of the service"
+        );
+
+        let registry = MockRegistry {
+            rewrite_digest: true,
+            ..Default::default()
+        }
+        .with_image(&source, DIGEST);
+        let err = copy_image_by_digest(&registry, &source, DIGEST, &dest).unwrap_err();
+        assert_eq!(
+            err,
+            ImageMirroringError::DigestMismatch {
+                source_image: format!("source.registry.io/team/app@{DIGEST}"),
+                destination: "cluster.registry.io/qovery-mirror:team.app.v1".to_string(),
+                expected: DIGEST.to_string(),
+                actual: Some(OTHER_DIGEST.to_string()),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            format!("Image `cluster.registry.io/qovery-mirror:team.app.v1` points to digest `{OTHER_DIGEST}` instead of `{DIGEST}` after copying `source.registry.io/team/app@{DIGEST}`")
+        );
+
+        let registry = MockRegistry {
+            fail_copy: true,
+            ..Default::default()
+        }
+        .with_image(&source, DIGEST);
+        assert!(matches!(
+            copy_image_by_digest(&registry, &source, DIGEST, &dest),
+            Err(ImageMirroringError::CopyFailed { source_image, destination, .. })
+                if source_image == format!("source.registry.io/team/app@{DIGEST}")
+                    && destination == "cluster.registry.io/qovery-mirror:team.app.v1"
+        ));
+    }
+}
@@ -3,6 +3,11 @@ use crate::infrastructure::models::cloud_provider::service::Action;
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
 
 mod check_dns;
+mod check_draining;
+mod check_resilience;
+mod check_storage_class;
+mod check_traffic;
+pub mod database_credentials;
 mod deploy_application;
 mod deploy_container;
 mod deploy_database;
@@ -10,11 +15,18 @@ pub mod deploy_environment;
 pub mod deploy_helm;
 mod deploy_helm_chart;
 mod deploy_job;
+mod deploy_k8s_manifests;
 pub mod deploy_namespace;
 mod deploy_router;
 mod deploy_terraform;
+mod mirror_image;
 mod pause_service;
+mod promote_image;
+mod replicate_secrets;
+mod resize_statefulset_storage;
 mod restart_service;
+mod router_basic_auth;
+mod secret_client;
 #[cfg(test)]
 pub mod test_utils;
 mod utils;
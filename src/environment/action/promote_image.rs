@@ -0,0 +1,371 @@
+use crate::cmd::command::CommandKiller;
+use crate::cmd::docker::{ContainerImage, Docker, DockerError};
+use crate::environment::action::utils::{create_mirror_repository, get_deployed_image_ids, login_to_registry};
+use crate::environment::models::abort::Abort;
+use crate::environment::models::registry_image_source::{ImagePromotion, RegistryImageSource};
+use crate::environment::report::logger::EnvProgressLogger;
+use crate::errors::{CommandError, EngineError};
+use crate::events::EventDetails;
+use crate::infrastructure::models::cloud_provider::DeploymentTarget;
+use crate::infrastructure::models::container_registry::RegistryTags;
+use crate::runtime::block_on;
+use std::collections::BTreeSet;
+use std::time::Duration;
+use uuid::Uuid;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ImagePromotionError {
+    #[error("No digest recorded for the source service and no running pod to read it from")]
+    MissingDigest,
+
+    #[error("Invalid image digest `{0}`")]
+    InvalidDigest(String),
+
+    #[error("Source service is running several images ({}), wait for its rollout to finish", .0.join(", "))]
+    AmbiguousDigest(Vec<String>),
+
+    #[error("Cannot read the image of the running source pods: {0}")]
+    CannotReadLivePods(String),
+
+    #[error("Image `{0}` does not exist anymore in the source registry")]
+    SourceImageNotFound(String),
+
+    #[error("Image `{0}` has not been copied with the same digest")]
+    DigestNotPreserved(String),
+
+    #[error("Registry error: {0}")]
+    Registry(String),
+}
+
+impl From<DockerError> for ImagePromotionError {
+    fn from(err: DockerError) -> Self {
+        ImagePromotionError::Registry(err.to_string())
+    }
+}
+
+/// Registry operations needed to promote an image
+pub trait PromotionRegistry {
+    fn does_image_exist(&self, image: &ContainerImage) -> Result<bool, DockerError>;
+    fn copy_image(&self, source: &ContainerImage, dest: &ContainerImage) -> Result<(), DockerError>;
+}
+
+pub struct DockerPromotionRegistry<'a> {
+    pub docker: &'a Docker,
+    pub abort: &'a dyn Abort,
+}
+
+impl PromotionRegistry for DockerPromotionRegistry<'_> {
+    fn does_image_exist(&self, image: &ContainerImage) -> Result<bool, DockerError> {
+        self.docker.does_image_exist_remotely(image)
+    }
+
+    fn copy_image(&self, source: &ContainerImage, dest: &ContainerImage) -> Result<(), DockerError> {
+        // a single source manifest is copied as is, so its digest is kept
+        self.docker.mirror(
+            source,
+            dest,
+            &mut |line| info!("{}", line),
+            &mut |line| warn!("{}", line),
+            &CommandKiller::from(Duration::from_secs(60 * 15), self.abort),
+        )
+    }
+}
+
+fn validate_digest(digest: &str) -> Result<String, ImagePromotionError> {
+    match digest.split_once(':') {
+        Some(("sha256", hex)) if hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(digest.to_lowercase())
+        }
+        _ => Err(ImagePromotionError::InvalidDigest(digest.to_string())),
+    }
+}
+
+/// Extracts the digest of an image id reported by the kubelet, i.e: `docker-pullable://repo@sha256:...`
+/// Ids without repository are the id of the local image config, not a manifest digest, and are ignored.
+fn digest_from_image_id(image_id: &str) -> Option<String> {
+    let (_, digest) = image_id.rsplit_once('@')?;
+    validate_digest(digest).ok()
+}
+
+/// Resolves the digest to promote, from the deployment record of the source service or else from its running pods
+pub fn resolve_promoted_digest(
+    promotion: &ImagePromotion,
+    live_image_ids: impl FnOnce(&str) -> Result<Vec<String>, String>,
+) -> Result<String, ImagePromotionError> {
+    if let Some(digest) = promotion.digest() {
+        return validate_digest(digest);
+    }
+
+    let Some(namespace) = &promotion.source_namespace else {
+        return Err(ImagePromotionError::MissingDigest);
+    };
+    let digests = live_image_ids(namespace)
+        .map_err(ImagePromotionError::CannotReadLivePods)?
+        .into_iter()
+        .filter_map(|image_id| digest_from_image_id(&image_id))
+        .collect::<BTreeSet<_>>();
+
+    match digests.len() {
+        0 => Err(ImagePromotionError::MissingDigest),
+        1 => Ok(digests.into_iter().next().unwrap_or_default()),
+        _ => Err(ImagePromotionError::AmbiguousDigest(digests.into_iter().collect())),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromotionCopy {
+    AlreadyPresent,
+    Copied,
+}
+
+/// Makes sure the promoted digest is available in the cluster registry, copying it from the source registry if needed.
+/// `dest` is the tagged image to create, `dest_by_digest` the same image referenced by its digest.
+pub fn ensure_promoted_image_in_registry(
+    registry: &dyn PromotionRegistry,
+    source_by_digest: &ContainerImage,
+    dest: &ContainerImage,
+    dest_by_digest: &ContainerImage,
+) -> Result<PromotionCopy, ImagePromotionError> {
+    if registry.does_image_exist(dest_by_digest)? {
+        return Ok(PromotionCopy::AlreadyPresent);
+    }
+
+    if !registry.does_image_exist(source_by_digest)? {
+        return Err(ImagePromotionError::SourceImageNotFound(source_by_digest.image_name()));
+    }
+
+    registry.copy_image(source_by_digest, dest)?;
+    if !registry.does_image_exist(dest_by_digest)? {
+        return Err(ImagePromotionError::DigestNotPreserved(dest_by_digest.image_name()));
+    }
+
+    Ok(PromotionCopy::Copied)
+}
+
+/// Resolves the digest of the promoted image and makes it available in the cluster registry,
+/// so the service can be deployed pinned to it.
+pub fn promote_image(
+    service_id: &Uuid,
+    source: &RegistryImageSource,
+    promotion: &ImagePromotion,
+    target: &DeploymentTarget,
+    logger: &EnvProgressLogger,
+    event_details: EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let to_engine_error = |err: ImagePromotionError| {
+        Box::new(EngineError::new_cannot_promote_image(
+            event_details.clone(),
+            CommandError::new_from_safe_message(err.to_string()),
+        ))
+    };
+
+    let source_selector = format!("qovery.com/service-id={}", promotion.source_service_id);
+    let digest = resolve_promoted_digest(promotion, |namespace| {
+        block_on(get_deployed_image_ids(target.kube.clone(), &source_selector, namespace)).map_err(|e| e.to_string())
+    })
+    .map_err(to_engine_error)?;
+    let digest = promotion.set_digest(digest);
+    logger.info(format!(
+        "🚀 Promoting image {}@{} deployed by execution {}",
+        source.image, digest, promotion.source_execution_id
+    ));
+
+    let (cluster_registry, image_name, image_tag, must_mirror_image) = source
+        .compute_cluster_container_registry_url_with_image_name_and_image_tag(
+            service_id,
+            target.kubernetes.long_id(),
+            &target.kubernetes.advanced_settings().registry_mirroring_mode,
+            target.container_registry.registry_info(),
+        );
+    let source_by_digest =
+        ContainerImage::new_for_digest(source.registry.url().clone(), source.image.to_string(), digest.to_string());
+    let dest = ContainerImage::new(cluster_registry.clone(), image_name.clone(), vec![image_tag]);
+    let dest_by_digest = ContainerImage::new_for_digest(cluster_registry, image_name, digest.to_string());
+
+    login_to_registry(&source.registry, target, logger, event_details.clone())?;
+    if must_mirror_image {
+        create_mirror_repository(
+            service_id,
+            target,
+            event_details.clone(),
+            RegistryTags {
+                environment_id: target.environment.id.clone(),
+                project_id: target.environment.project_id.clone(),
+                resource_ttl: target.kubernetes.advanced_settings().resource_ttl(),
+            },
+        )?;
+    }
+
+    let registry = DockerPromotionRegistry {
+        docker: target.docker,
+        abort: target.abort,
+    };
+    match ensure_promoted_image_in_registry(&registry, &source_by_digest, &dest, &dest_by_digest)
+        .map_err(to_engine_error)?
+    {
+        PromotionCopy::AlreadyPresent => logger.info("🎯 Promoted image already exists in the registry".to_string()),
+        PromotionCopy::Copied => logger.info("🪞 Promoted image copied to the cluster registry".to_string()),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use url::Url;
+
+    const DIGEST: &str = "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+    const OTHER_DIGEST: &str = "sha256:fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210";
+
+    fn new_promotion(digest: Option<&str>, namespace: Option<&str>) -> ImagePromotion {
+        ImagePromotion::new(
+            Uuid::new_v4(),
+            namespace.map(str::to_string),
+            "execution-id".to_string(),
+            digest.map(str::to_string),
+        )
+    }
+
+    /// In memory registries, keyed by image name (`host/name@digest` or `host/name:tag`)
+    #[derive(Default)]
+    struct MockRegistry {
+        images: RefCell<BTreeSet<String>>,
+        copies: RefCell<Vec<(String, String)>>,
+        keep_digest: bool,
+    }
+
+    impl PromotionRegistry for MockRegistry {
+        fn does_image_exist(&self, image: &ContainerImage) -> Result<bool, DockerError> {
+            Ok(self.images.borrow().contains(&image.image_name()))
+        }
+
+        fn copy_image(&self, source: &ContainerImage, dest: &ContainerImage) -> Result<(), DockerError> {
+            self.copies.borrow_mut().push((source.image_name(), dest.image_name()));
+            let mut images = self.images.borrow_mut();
+            images.insert(dest.image_name());
+            if self.keep_digest {
+                let source_name = source.image_name();
+                let (_, digest) = source_name.rsplit_once('@').unwrap();
+                images.insert(format!("{}@{}", dest.repository_with_host(), digest));
+            }
+            Ok(())
+        }
+    }
+
+    fn images() -> (ContainerImage, ContainerImage, ContainerImage) {
+        let source_registry = Url::parse("https://source.registry.io").unwrap();
+        let cluster_registry = Url::parse("https://cluster.registry.io").unwrap();
+        (
+            ContainerImage::new_for_digest(source_registry, "app".to_string(), DIGEST.to_string()),
+            ContainerImage::new(
+                cluster_registry.clone(),
+                "qovery-mirror".to_string(),
+                vec!["promoted-sha256".to_string()],
+            ),
+            ContainerImage::new_for_digest(cluster_registry, "qovery-mirror".to_string(), DIGEST.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_resolve_digest_from_deployment_record() {
+        let promotion = new_promotion(Some(DIGEST), Some("source-namespace"));
+        let digest = resolve_promoted_digest(&promotion, |_| panic!("live pods must not be read")).unwrap();
+        assert_eq!(digest, DIGEST);
+
+        let promotion = new_promotion(Some("latest"), None);
+        assert_eq!(
+            resolve_promoted_digest(&promotion, |_| Ok(vec![])),
+            Err(ImagePromotionError::InvalidDigest("latest".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_resolve_digest_from_live_pods() {
+        let promotion = new_promotion(None, Some("source-namespace"));
+        let digest = resolve_promoted_digest(&promotion, |namespace| {
+            assert_eq!(namespace, "source-namespace");
+            Ok(vec![
+                format!("docker-pullable://registry.io/app@{DIGEST}"),
+                format!("registry.io/app@{DIGEST}"),
+                // local image config id, not usable
+                OTHER_DIGEST.to_string(),
+            ])
+        })
+        .unwrap();
+        assert_eq!(digest, DIGEST);
+
+        // rollout in progress
+        assert_eq!(
+            resolve_promoted_digest(&promotion, |_| Ok(vec![
+                format!("registry.io/app@{DIGEST}"),
+                format!("registry.io/app@{OTHER_DIGEST}"),
+            ])),
+            Err(ImagePromotionError::AmbiguousDigest(vec![
+                DIGEST.to_string(),
+                OTHER_DIGEST.to_string()
+            ]))
+        );
+        assert_eq!(
+            resolve_promoted_digest(&promotion, |_| Ok(vec![])),
+            Err(ImagePromotionError::MissingDigest)
+        );
+        assert_eq!(
+            resolve_promoted_digest(&promotion, |_| Err("forbidden".to_string())),
+            Err(ImagePromotionError::CannotReadLivePods("forbidden".to_string()))
+        );
+
+        // other cluster, live pods can't be read
+        assert_eq!(
+            resolve_promoted_digest(&new_promotion(None, None), |_| panic!("live pods must not be read")),
+            Err(ImagePromotionError::MissingDigest)
+        );
+    }
+
+    #[test]
+    fn test_promoted_image_already_in_cluster_registry() {
+        let (source, dest, dest_by_digest) = images();
+        let registry = MockRegistry::default();
+        registry.images.borrow_mut().insert(dest_by_digest.image_name());
+
+        assert_eq!(
+            ensure_promoted_image_in_registry(&registry, &source, &dest, &dest_by_digest),
+            Ok(PromotionCopy::AlreadyPresent)
+        );
+        assert!(registry.copies.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_promoted_image_is_copied_across_registries() {
+        let (source, dest, dest_by_digest) = images();
+        let registry = MockRegistry {
+            keep_digest: true,
+            ..Default::default()
+        };
+        registry.images.borrow_mut().insert(source.image_name());
+
+        assert_eq!(
+            ensure_promoted_image_in_registry(&registry, &source, &dest, &dest_by_digest),
+            Ok(PromotionCopy::Copied)
+        );
+        assert_eq!(registry.copies.borrow().as_slice(), &[(source.image_name(), dest.image_name())]);
+    }
+
+    #[test]
+    fn test_promotion_fails_when_source_image_is_gone_or_digest_changes() {
+        let (source, dest, dest_by_digest) = images();
+        let registry = MockRegistry::default();
+        assert_eq!(
+            ensure_promoted_image_in_registry(&registry, &source, &dest, &dest_by_digest),
+            Err(ImagePromotionError::SourceImageNotFound(source.image_name()))
+        );
+
+        // the copy re-created the manifest with another digest
+        registry.images.borrow_mut().insert(source.image_name());
+        assert_eq!(
+            ensure_promoted_image_in_registry(&registry, &source, &dest, &dest_by_digest),
+            Err(ImagePromotionError::DigestNotPreserved(dest_by_digest.image_name()))
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use crate::environment::models::secret_replication::{
+    plan_secret_replication, SecretReplicationAction, REPLICATE_TO_ANNOTATION, REPLICATE_TO_SELECTOR_ANNOTATION,
+    REPLICA_SOURCE_NAMESPACE_LABEL,
+};
+use crate::errors::{CommandError, EngineError};
+use crate::events::EventDetails;
+use crate::runtime::block_on;
+use k8s_openapi::api::core::v1::{Namespace, Secret};
+use kube::api::{DeleteParams, ListParams, Patch, PatchParams};
+use kube::Api;
+
+/// Reconciles the replicas of the secrets of `namespace` annotated for replication.
+/// When the source namespace is being deleted, all its replicas are deleted.
+/// Returns the actions taken, conflicts and missing namespaces included so they can be reported.
+pub(super) fn reconcile_secret_replicas(
+    kube: &kube::Client,
+    namespace: &str,
+    is_namespace_deleted: bool,
+    event_details: &EventDetails,
+    dry_run: bool,
+) -> Result<Vec<SecretReplicationAction>, Box<EngineError>> {
+    let get_secret_error = |err: kube::Error| {
+        Box::new(EngineError::new_k8s_get_secret_error(
+            event_details.clone(),
+            CommandError::new(
+                format!("Cannot list secrets to replicate from namespace {namespace}"),
+                Some(err.to_string()),
+                None,
+            ),
+        ))
+    };
+
+    let sources: Vec<Secret> =
+        block_on(Api::<Secret>::namespaced(kube.clone(), namespace).list(&ListParams::default()))
+            .map_err(get_secret_error)?
+            .items
+            .into_iter()
+            .filter(|_| !is_namespace_deleted)
+            .filter(|secret| {
+                secret.metadata.annotations.as_ref().is_some_and(|annotations| {
+                    annotations.contains_key(REPLICATE_TO_ANNOTATION)
+                        || annotations.contains_key(REPLICATE_TO_SELECTOR_ANNOTATION)
+                })
+            })
+            .collect();
+
+    let all_secrets: Api<Secret> = Api::all(kube.clone());
+    let mut existing = block_on(
+        all_secrets.list(&ListParams::default().labels(&format!("{REPLICA_SOURCE_NAMESPACE_LABEL}={namespace}"))),
+    )
+    .map_err(get_secret_error)?
+    .items;
+    // nothing is replicated and no replica is left, the common case, no need to go further
+    if sources.is_empty() && existing.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // secrets of the same name may be owned by users, they must be known to detect conflicts
+    for name in sources.iter().filter_map(|secret| secret.metadata.name.as_deref()) {
+        existing.extend(
+            block_on(all_secrets.list(&ListParams::default().fields(&format!("metadata.name={name}"))))
+                .map_err(get_secret_error)?
+                .items
+                .into_iter()
+                .filter(|secret| secret.metadata.namespace.as_deref() != Some(namespace)),
+        );
+    }
+
+    let namespaces = block_on(Api::<Namespace>::all(kube.clone()).list(&ListParams::default()))
+        .map_err(get_secret_error)?
+        .items;
+
+    let actions = plan_secret_replication(namespace, &sources, &namespaces, &existing);
+    if dry_run {
+        return Ok(actions);
+    }
+
+    for action in &actions {
+        let result = match action {
+            SecretReplicationAction::Create { replica } | SecretReplicationAction::Update { replica } => {
+                let target_namespace = replica.metadata.namespace.as_deref().unwrap_or_default();
+                let name = replica.metadata.name.as_deref().unwrap_or_default();
+                block_on(Api::<Secret>::namespaced(kube.clone(), target_namespace).patch(
+                    name,
+                    &PatchParams::apply("qovery-engine").force(),
+                    &Patch::Apply(replica),
+                ))
+                .map(|_| ())
+                .map_err(|err| (err, target_namespace))
+            }
+            SecretReplicationAction::Delete {
+                namespace: target_namespace,
+                name,
+            } => match block_on(
+                Api::<Secret>::namespaced(kube.clone(), target_namespace).delete(name, &DeleteParams::default()),
+            ) {
+                Ok(_) => Ok(()),
+                Err(kube::Error::Api(err)) if err.code == 404 => Ok(()),
+                Err(err) => Err((err, target_namespace.as_str())),
+            },
+            SecretReplicationAction::Conflict { .. } | SecretReplicationAction::TargetNamespaceNotFound { .. } => {
+                Ok(())
+            }
+        };
+
+        result.map_err(|(err, target_namespace)| {
+            Box::new(EngineError::new_copy_secrets_to_another_namespace_error(
+                event_details.clone(),
+                err,
+                namespace,
+                target_namespace,
+            ))
+        })?;
+    }
+
+    Ok(actions)
+}
@@ -0,0 +1,335 @@
+use crate::errors::{CommandError, EngineError};
+use crate::events::EventDetails;
+use crate::io_models::models::{InvalidPVCStorage, InvalidStatefulsetStorage};
+use crate::kubers_utils::{
+    kube_delete_all_from_selector, kube_edit_pvc_size, kube_get_resources_by_selector,
+    kube_rollout_restart_statefulset, KubeDeleteMode,
+};
+use crate::runtime::block_on;
+use k8s_openapi::api::apps::v1::StatefulSet;
+use k8s_openapi::api::core::v1::PersistentVolumeClaim;
+use k8s_openapi::api::storage::v1::StorageClass;
+use kube::Api;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// Condition set on a PVC when the volume has been expanded but its filesystem can only be resized by a pod restart
+const FILE_SYSTEM_RESIZE_PENDING_CONDITION: &str = "FileSystemResizePending";
+const ORPHAN_DELETE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Kubernetes calls needed to resize the volumes of a statefulset
+#[cfg_attr(test, faux::create)]
+pub struct StatefulsetStorageClient {
+    kube: kube::Client,
+    namespace: String,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl StatefulsetStorageClient {
+    pub fn new(kube: kube::Client, namespace: &str) -> Self {
+        Self {
+            kube,
+            namespace: namespace.to_string(),
+        }
+    }
+
+    pub fn get_pvc(&self, name: &str) -> Result<Option<PersistentVolumeClaim>, CommandError> {
+        let api: Api<PersistentVolumeClaim> = Api::namespaced(self.kube.clone(), &self.namespace);
+        block_on(api.get_opt(name))
+            .map_err(|err| CommandError::new(format!("Cannot get PVC `{name}`"), Some(err.to_string()), None))
+    }
+
+    pub fn get_storage_class(&self, name: &str) -> Result<Option<StorageClass>, CommandError> {
+        let api: Api<StorageClass> = Api::all(self.kube.clone());
+        block_on(api.get_opt(name))
+            .map_err(|err| CommandError::new(format!("Cannot get storage class `{name}`"), Some(err.to_string()), None))
+    }
+
+    pub fn edit_pvc_size(&self, invalid_pvc: &InvalidPVCStorage) -> Result<(), CommandError> {
+        block_on(kube_edit_pvc_size(&self.kube, &self.namespace, invalid_pvc))
+    }
+
+    /// Deletes the statefulset while keeping its pods and PVCs, and waits for the deletion to be effective
+    pub fn orphan_delete_statefulset(&self, selector: &str) -> Result<(), CommandError> {
+        block_on(kube_delete_all_from_selector::<StatefulSet>(
+            &self.kube,
+            selector,
+            &self.namespace,
+            KubeDeleteMode::Orphan,
+        ))
+        .map_err(|err| CommandError::new_from_safe_message(err.to_string()))?;
+
+        info!("Waiting for orphan StatefulSet deletion to perform.");
+        let now = Instant::now();
+        while now.elapsed() < ORPHAN_DELETE_TIMEOUT {
+            if block_on(kube_get_resources_by_selector::<StatefulSet>(
+                &self.kube,
+                &self.namespace,
+                selector,
+            ))?
+            .items
+            .is_empty()
+            {
+                return Ok(());
+            }
+            sleep(Duration::from_secs(10));
+        }
+
+        Err(CommandError::new_from_safe_message(
+            "Timeout waiting for statefulset deletion".to_string(),
+        ))
+    }
+
+    pub fn rollout_restart_statefulset(&self, name: &str) -> Result<(), CommandError> {
+        block_on(kube_rollout_restart_statefulset(&self.kube, &self.namespace, name))
+    }
+}
+
+fn get_pvc(
+    client: &StatefulsetStorageClient,
+    event_details: &EventDetails,
+    namespace: &str,
+    pvc_name: &str,
+) -> Result<PersistentVolumeClaim, Box<EngineError>> {
+    client
+        .get_pvc(pvc_name)
+        .and_then(|pvc| {
+            pvc.ok_or_else(|| CommandError::new_from_safe_message(format!("PVC `{pvc_name}` doesn't exist")))
+        })
+        .map_err(|err| Box::new(EngineError::new_k8s_cannot_get_pvcs(event_details.clone(), namespace, err)))
+}
+
+/// Expands the PVCs of a statefulset whose storage size has been increased.
+/// The volume claim templates of a statefulset are immutable, so the statefulset is deleted without its pods
+/// and PVCs, the helm deployment of the service recreates it with the new size right after.
+pub fn expand_statefulset_storage(
+    client: &StatefulsetStorageClient,
+    event_details: &EventDetails,
+    namespace: &str,
+    invalid_statefulset: &InvalidStatefulsetStorage,
+) -> Result<(), Box<EngineError>> {
+    // nothing is touched until every PVC is known to be expandable
+    for invalid_pvc in &invalid_statefulset.invalid_pvcs {
+        let pvc = get_pvc(client, event_details, namespace, &invalid_pvc.pvc_name)?;
+        let Some(storage_class_name) = pvc.spec.and_then(|spec| spec.storage_class_name) else {
+            continue;
+        };
+
+        let storage_class = client
+            .get_storage_class(&storage_class_name)
+            .map_err(|err| Box::new(EngineError::new_k8s_cannot_get_pvcs(event_details.clone(), namespace, err)))?;
+        let allow_volume_expansion = storage_class
+            .and_then(|storage_class| storage_class.allow_volume_expansion)
+            .unwrap_or(false);
+        if !allow_volume_expansion {
+            return Err(Box::new(EngineError::new_k8s_storage_class_volume_expansion_not_allowed(
+                event_details.clone(),
+                &storage_class_name,
+                &invalid_pvc.pvc_name,
+            )));
+        }
+    }
+
+    for invalid_pvc in &invalid_statefulset.invalid_pvcs {
+        client.edit_pvc_size(invalid_pvc).map_err(|err| {
+            Box::new(EngineError::new_k8s_cannot_edit_pvc(
+                event_details.clone(),
+                invalid_pvc.pvc_name.to_string(),
+                err,
+            ))
+        })?;
+    }
+
+    client
+        .orphan_delete_statefulset(&invalid_statefulset.statefulset_selector)
+        .map_err(|err| {
+            Box::new(EngineError::new_k8s_cannot_orphan_delete(
+                event_details.clone(),
+                &invalid_statefulset.statefulset_selector,
+                err,
+            ))
+        })
+}
+
+/// Restarts the pods of the statefulset once redeployed, when a volume driver can't resize the filesystem online.
+/// Returns whether the statefulset has been restarted.
+pub fn restart_statefulset_if_filesystem_resize_pending(
+    client: &StatefulsetStorageClient,
+    event_details: &EventDetails,
+    namespace: &str,
+    invalid_statefulset: &InvalidStatefulsetStorage,
+) -> Result<bool, Box<EngineError>> {
+    let mut filesystem_resize_pending = false;
+    for invalid_pvc in &invalid_statefulset.invalid_pvcs {
+        let pvc = get_pvc(client, event_details, namespace, &invalid_pvc.pvc_name)?;
+        filesystem_resize_pending |= pvc
+            .status
+            .and_then(|status| status.conditions)
+            .unwrap_or_default()
+            .iter()
+            .any(|condition| condition.type_ == FILE_SYSTEM_RESIZE_PENDING_CONDITION && condition.status == "True");
+    }
+
+    if !filesystem_resize_pending {
+        return Ok(false);
+    }
+
+    client
+        .rollout_restart_statefulset(&invalid_statefulset.statefulset_name)
+        .map_err(|err| {
+            Box::new(EngineError::new_k8s_cannot_rollout_restart_statefulset(
+                event_details.clone(),
+                &invalid_statefulset.statefulset_name,
+                err,
+            ))
+        })?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::action::test_utils::{command_error, event_details};
+    use crate::errors::Tag;
+    use crate::infrastructure::models::cloud_provider::service::ServiceType;
+    use k8s_openapi::api::core::v1::{
+        PersistentVolumeClaimCondition, PersistentVolumeClaimSpec, PersistentVolumeClaimStatus,
+    };
+    use uuid::Uuid;
+
+    const NAMESPACE: &str = "my-namespace";
+    const PVC_NAME: &str = "data-my-app-0";
+    const STORAGE_CLASS_NAME: &str = "gp2";
+
+    fn invalid_statefulset() -> InvalidStatefulsetStorage {
+        InvalidStatefulsetStorage {
+            service_type: ServiceType::Container,
+            service_id: Uuid::new_v4(),
+            statefulset_selector: "qovery.com/service-id=my-app".to_string(),
+            statefulset_name: "my-app".to_string(),
+            invalid_pvcs: vec![InvalidPVCStorage {
+                pvc_name: PVC_NAME.to_string(),
+                required_disk_size_in_gib: 20,
+            }],
+        }
+    }
+
+    fn pvc(filesystem_resize_pending: bool) -> PersistentVolumeClaim {
+        PersistentVolumeClaim {
+            spec: Some(PersistentVolumeClaimSpec {
+                storage_class_name: Some(STORAGE_CLASS_NAME.to_string()),
+                ..Default::default()
+            }),
+            status: Some(PersistentVolumeClaimStatus {
+                conditions: filesystem_resize_pending.then(|| {
+                    vec![PersistentVolumeClaimCondition {
+                        type_: FILE_SYSTEM_RESIZE_PENDING_CONDITION.to_string(),
+                        status: "True".to_string(),
+                        ..Default::default()
+                    }]
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn storage_class(allow_volume_expansion: Option<bool>) -> StorageClass {
+        StorageClass {
+            allow_volume_expansion,
+            provisioner: "ebs.csi.aws.com".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_expand_statefulset_storage() {
+        let mut client = StatefulsetStorageClient::faux();
+        faux::when!(client.get_pvc(PVC_NAME)).then_return(Ok(Some(pvc(false))));
+        faux::when!(client.get_storage_class(STORAGE_CLASS_NAME)).then_return(Ok(Some(storage_class(Some(true)))));
+        faux::when!(client.edit_pvc_size(_)).then_return(Ok(()));
+        faux::when!(client.orphan_delete_statefulset("qovery.com/service-id=my-app")).then_return(Ok(()));
+
+        assert!(expand_statefulset_storage(&client, &event_details(), NAMESPACE, &invalid_statefulset()).is_ok());
+    }
+
+    #[test]
+    fn test_expand_statefulset_storage_not_allowed_by_storage_class() {
+        // nothing must be edited nor deleted: edit_pvc_size and orphan_delete_statefulset would panic if called
+        for allow_volume_expansion in [Some(false), None] {
+            let mut client = StatefulsetStorageClient::faux();
+            faux::when!(client.get_pvc(PVC_NAME)).then_return(Ok(Some(pvc(false))));
+            faux::when!(client.get_storage_class(STORAGE_CLASS_NAME))
+                .then_return(Ok(Some(storage_class(allow_volume_expansion))));
+
+            let err =
+                expand_statefulset_storage(&client, &event_details(), NAMESPACE, &invalid_statefulset()).unwrap_err();
+            assert_eq!(err.tag(), &Tag::K8sStorageClassVolumeExpansionNotAllowed);
+        }
+    }
+
+    #[test]
+    fn test_expand_statefulset_storage_errors() {
+        let mut client = StatefulsetStorageClient::faux();
+        faux::when!(client.get_pvc(PVC_NAME)).then_return(Ok(None));
+        let err = expand_statefulset_storage(&client, &event_details(), NAMESPACE, &invalid_statefulset()).unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sCannotGetPVCs);
+
+        let mut client = StatefulsetStorageClient::faux();
+        faux::when!(client.get_pvc(PVC_NAME)).then_return(Ok(Some(pvc(false))));
+        faux::when!(client.get_storage_class(STORAGE_CLASS_NAME)).then_return(Ok(Some(storage_class(Some(true)))));
+        faux::when!(client.edit_pvc_size(_)).then_return(Err(command_error()));
+        let err = expand_statefulset_storage(&client, &event_details(), NAMESPACE, &invalid_statefulset()).unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sCannotPVCEdit);
+
+        let mut client = StatefulsetStorageClient::faux();
+        faux::when!(client.get_pvc(PVC_NAME)).then_return(Ok(Some(pvc(false))));
+        faux::when!(client.get_storage_class(STORAGE_CLASS_NAME)).then_return(Ok(Some(storage_class(Some(true)))));
+        faux::when!(client.edit_pvc_size(_)).then_return(Ok(()));
+        faux::when!(client.orphan_delete_statefulset(_)).then_return(Err(command_error()));
+        let err = expand_statefulset_storage(&client, &event_details(), NAMESPACE, &invalid_statefulset()).unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sCannotOrphanDelete);
+    }
+
+    #[test]
+    fn test_restart_statefulset_only_when_filesystem_resize_is_pending() {
+        // filesystem resized online: rollout_restart_statefulset would panic if called
+        let mut client = StatefulsetStorageClient::faux();
+        faux::when!(client.get_pvc(PVC_NAME)).then_return(Ok(Some(pvc(false))));
+        assert_eq!(
+            restart_statefulset_if_filesystem_resize_pending(
+                &client,
+                &event_details(),
+                NAMESPACE,
+                &invalid_statefulset()
+            ),
+            Ok(false)
+        );
+
+        let mut client = StatefulsetStorageClient::faux();
+        faux::when!(client.get_pvc(PVC_NAME)).then_return(Ok(Some(pvc(true))));
+        faux::when!(client.rollout_restart_statefulset("my-app")).then_return(Ok(()));
+        assert_eq!(
+            restart_statefulset_if_filesystem_resize_pending(
+                &client,
+                &event_details(),
+                NAMESPACE,
+                &invalid_statefulset()
+            ),
+            Ok(true)
+        );
+
+        let mut client = StatefulsetStorageClient::faux();
+        faux::when!(client.get_pvc(PVC_NAME)).then_return(Ok(Some(pvc(true))));
+        faux::when!(client.rollout_restart_statefulset("my-app")).then_return(Err(command_error()));
+        let err = restart_statefulset_if_filesystem_resize_pending(
+            &client,
+            &event_details(),
+            NAMESPACE,
+            &invalid_statefulset(),
+        )
+        .unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sCannotRolloutRestartStatefulset);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::environment::action::secret_client::SecretClient;
+use crate::errors::{CommandError, EngineError};
+use crate::events::EventDetails;
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::ByteString;
+use std::collections::BTreeMap;
+
+const HTPASSWD_SECRET_KEY: &str = "auth";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BasicAuthSecretAction {
+    Created,
+    Updated,
+    Unchanged,
+    Deleted,
+}
+
+/// Secret referenced by the `auth-secret` annotation of the ingresses of a router
+pub fn basic_auth_secret(name: &str, namespace: &str, labels: BTreeMap<String, String>, htpasswd: Vec<u8>) -> Secret {
+    Secret {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: Some(namespace.to_string()),
+            labels: Some(labels),
+            ..Default::default()
+        },
+        type_: Some("Opaque".to_string()),
+        data: Some(BTreeMap::from([(HTPASSWD_SECRET_KEY.to_string(), ByteString(htpasswd))])),
+        ..Default::default()
+    }
+}
+
+/// Makes the basic auth secret `name` match the `desired` one, deleting it when basic auth is disabled.
+/// The secret is only patched when the htpasswd content changed, the ingress referencing it is never touched,
+/// so credentials are rotated without redeploying the router.
+pub fn sync_basic_auth_secret(
+    client: &SecretClient,
+    event_details: &EventDetails,
+    name: &str,
+    desired: Option<&Secret>,
+) -> Result<BasicAuthSecretAction, Box<EngineError>> {
+    let existing = client
+        .get(name)
+        .map_err(|err| Box::new(EngineError::new_k8s_get_secret_error(event_details.clone(), err)))?;
+    let patch_error = |err: CommandError| Box::new(EngineError::new_k8s_patch_secret_error(event_details.clone(), err));
+
+    match (desired, existing) {
+        (Some(desired), None) => {
+            client.apply(desired).map_err(patch_error)?;
+            Ok(BasicAuthSecretAction::Created)
+        }
+        (Some(desired), Some(existing)) if existing.data == desired.data => Ok(BasicAuthSecretAction::Unchanged),
+        (Some(desired), Some(_)) => {
+            client.apply(desired).map_err(patch_error)?;
+            Ok(BasicAuthSecretAction::Updated)
+        }
+        (None, Some(_)) => {
+            client.delete(name).map_err(patch_error)?;
+            Ok(BasicAuthSecretAction::Deleted)
+        }
+        (None, None) => Ok(BasicAuthSecretAction::Unchanged),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::action::test_utils::{command_error, event_details};
+    use crate::errors::Tag;
+
+    const SECRET_NAME: &str = "basic-auth-router";
+
+    fn secret(htpasswd: &str) -> Secret {
+        basic_auth_secret(SECRET_NAME, "my-namespace", BTreeMap::new(), htpasswd.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_sync_basic_auth_secret_creates_missing_secret() {
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(None));
+        faux::when!(client.apply(_)).then_return(Ok(()));
+
+        let desired = secret("alice:hash");
+        let action = sync_basic_auth_secret(&client, &event_details(), SECRET_NAME, Some(&desired)).unwrap();
+        assert_eq!(action, BasicAuthSecretAction::Created);
+    }
+
+    #[test]
+    fn test_sync_basic_auth_secret_only_patches_rotated_credentials() {
+        // same credentials, nothing to patch: apply is not mocked and would panic if called
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret("alice:hash"))));
+        let desired = secret("alice:hash");
+        let action = sync_basic_auth_secret(&client, &event_details(), SECRET_NAME, Some(&desired)).unwrap();
+        assert_eq!(action, BasicAuthSecretAction::Unchanged);
+
+        // rotated credentials, only the secret is patched
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret("alice:hash"))));
+        faux::when!(client.apply(_)).then_return(Ok(()));
+        let desired = secret("alice:new-hash\nbob:hash");
+        let action = sync_basic_auth_secret(&client, &event_details(), SECRET_NAME, Some(&desired)).unwrap();
+        assert_eq!(action, BasicAuthSecretAction::Updated);
+    }
+
+    #[test]
+    fn test_sync_basic_auth_secret_deletes_secret_when_basic_auth_is_disabled() {
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret("alice:hash"))));
+        faux::when!(client.delete(SECRET_NAME)).then_return(Ok(()));
+        let action = sync_basic_auth_secret(&client, &event_details(), SECRET_NAME, None).unwrap();
+        assert_eq!(action, BasicAuthSecretAction::Deleted);
+
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(None));
+        let action = sync_basic_auth_secret(&client, &event_details(), SECRET_NAME, None).unwrap();
+        assert_eq!(action, BasicAuthSecretAction::Unchanged);
+    }
+
+    #[test]
+    fn test_sync_basic_auth_secret_errors() {
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Err(command_error()));
+        let desired = secret("alice:hash");
+        let err = sync_basic_auth_secret(&client, &event_details(), SECRET_NAME, Some(&desired)).unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sGetSecretError);
+
+        let mut client = SecretClient::faux();
+        faux::when!(client.get(SECRET_NAME)).then_return(Ok(Some(secret("alice:hash"))));
+        faux::when!(client.apply(_)).then_return(Err(command_error()));
+        let desired = secret("alice:new-hash");
+        let err = sync_basic_auth_secret(&client, &event_details(), SECRET_NAME, Some(&desired)).unwrap_err();
+        assert_eq!(err.tag(), &Tag::K8sPatchSecretError);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::errors::CommandError;
+use crate::runtime::block_on;
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::{DeleteParams, Patch, PatchParams};
+use kube::Api;
+
+/// Kubernetes calls needed to manage the secrets owned by a service, i.e: router basic auth, database credentials
+#[cfg_attr(test, faux::create)]
+pub struct SecretClient {
+    api: Api<Secret>,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl SecretClient {
+    pub fn new(kube: kube::Client, namespace: &str) -> Self {
+        Self {
+            api: Api::namespaced(kube, namespace),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<Secret>, CommandError> {
+        block_on(self.api.get_opt(name)).map_err(|err| to_command_error(format!("Cannot get secret `{name}`"), err))
+    }
+
+    pub fn apply(&self, secret: &Secret) -> Result<(), CommandError> {
+        let name = secret.metadata.name.as_deref().unwrap_or_default();
+        block_on(
+            self.api
+                .patch(name, &PatchParams::apply("qovery-engine").force(), &Patch::Apply(secret)),
+        )
+        .map(|_| ())
+        .map_err(|err| to_command_error(format!("Cannot apply secret `{name}`"), err))
+    }
+
+    /// Deleting a missing secret succeeds
+    pub fn delete(&self, name: &str) -> Result<(), CommandError> {
+        match block_on(self.api.delete(name, &DeleteParams::default())) {
+            Ok(_) => Ok(()),
+            Err(kube::Error::Api(err)) if err.code == 404 => Ok(()),
+            Err(err) => Err(to_command_error(format!("Cannot delete secret `{name}`"), err)),
+        }
+    }
+}
+
+fn to_command_error(safe_message: String, err: kube::Error) -> CommandError {
+    CommandError::new(safe_message, Some(err.to_string()), None)
+}
@@ -1,3 +1,6 @@
+use crate::errors::CommandError;
+use crate::events::{EnvironmentStep, EventDetails, Stage, Transmitter};
+use crate::io_models::QoveryIdentifier;
 use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
 use k8s_openapi::api::autoscaling::v1::HorizontalPodAutoscaler;
 use k8s_openapi::api::batch::v1::CronJob;
@@ -7,6 +10,24 @@ use kube::api::{DeleteParams, PostParams};
 use kube::Api;
 use std::sync::{Arc, Barrier};
 use std::thread;
+use uuid::Uuid;
+
+/// Details of the events of a service deployment, for the actions reporting their errors as engine errors
+pub fn event_details() -> EventDetails {
+    EventDetails::new(
+        None,
+        QoveryIdentifier::new_random(),
+        QoveryIdentifier::new_random(),
+        Uuid::new_v4().to_string(),
+        Stage::Environment(EnvironmentStep::Deploy),
+        Transmitter::Environment(Uuid::new_v4(), "environment".to_string()),
+    )
+}
+
+/// Error returned by the mocked kubernetes clients
+pub fn command_error() -> CommandError {
+    CommandError::new_from_safe_message("something went wrong".to_string())
+}
 
 pub fn get_simple_deployment() -> Deployment {
     serde_json::from_value(serde_json::json!({
@@ -1,5 +1,10 @@
-use crate::cmd::command::CommandKiller;
 use crate::cmd::docker::ContainerImage;
+use crate::environment::action::mirror_image::{
+    copy_image_by_digest, plan_image_mirroring, DockerMirroringRegistry, ImageMirroringError, MirroringPlan,
+};
+use crate::environment::action::resize_statefulset_storage::{
+    expand_statefulset_storage, restart_statefulset_if_filesystem_resize_pending, StatefulsetStorageClient,
+};
 use crate::environment::report::logger::EnvProgressLogger;
 use crate::errors::{CommandError, EngineError};
 use crate::events::EventDetails;
@@ -8,6 +13,7 @@ use crate::infrastructure::models::cloud_provider::io::RegistryMirroringMode;
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
 use crate::infrastructure::models::container_registry::errors::ContainerRegistryError;
 use crate::infrastructure::models::container_registry::RegistryTags;
+use crate::io_models::container::Registry;
 
 use crate::environment::models::container::get_mirror_repository_name;
 use crate::environment::models::kubernetes::K8sObject;
@@ -19,10 +25,10 @@ use crate::services::kube_client::{QubeClient, SelectK8sResourceBy};
 use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
 use k8s_openapi::api::batch::v1::CronJob;
 
-use crate::infrastructure::models::cloud_provider::service::{increase_storage_size, Service};
+use crate::infrastructure::models::cloud_provider::service::Service;
 use crate::io_models::models::InvalidStatefulsetStorage;
 use crate::kubers_utils::kube_get_resources_by_selector;
-use k8s_openapi::api::core::v1::PersistentVolumeClaim;
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Pod};
 use kube::api::ListParams;
 use kube::Api;
 use retry::delay::{Fibonacci, Fixed};
@@ -33,6 +39,8 @@ use std::thread;
 use std::time::Duration;
 use uuid::Uuid;
 
+pub const PROMOTED_FROM_EXECUTION_ID_ANNOTATION: &str = "qovery.com/promoted-from-execution-id";
+
 // specific to AWS
 pub fn delete_nlb_or_alb_service(
     qube_client: QubeClient,
@@ -126,10 +134,11 @@ pub fn delete_cached_image(
     }
 
     // Delete previous image from cache to cleanup resources
-    if let Some(last_image_tag) = last_image
-        .as_ref()
-        .and_then(|img| img.split(':').last().map(str::to_string))
-    {
+    if let Some(last_image_tag) = last_image.as_ref().and_then(|img| match img.rsplit_once('@') {
+        // promoted images are pinned to their digest, and tagged with it in the registry
+        Some((_, digest)) => Some(format!("promoted-{}", digest.replace(':', "-"))),
+        None => img.split(':').last().map(str::to_string),
+    }) {
         if is_service_deletion || last_image_tag != current_image_tag {
             logger(format!("🪓 Deleting previous cached image {last_image_tag}"));
             let mirror_repo_name = get_mirror_repository_name(
@@ -168,7 +177,32 @@ pub fn mirror_image_if_necessary(
     metrics_registry: Arc<dyn MetricsRegistry>,
 ) -> Result<(), Box<EngineError>> {
     let mirror_record = metrics_registry.start_record(*service_id, StepLabel::Service, StepName::MirrorImage);
+    let result = ensure_image_mirrored(service_id, source, target, logger, event_details);
+    mirror_record.stop(match &result {
+        Ok(ImageMirroring::Copied) => StepStatus::Success,
+        Ok(_) => StepStatus::Skip,
+        Err(_) => StepStatus::Error,
+    });
+
+    result.map(|_| ())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageMirroring {
+    SameRegistry,
+    AlreadyMirrored,
+    Copied,
+}
 
+/// Makes sure the cluster registry has the image the tag of the service points to, copying it by digest if needed.
+/// Images already mirrored are only checked, so it can be called again right before deploying the service.
+pub fn ensure_image_mirrored(
+    service_id: &Uuid,
+    source: &RegistryImageSource,
+    target: &DeploymentTarget,
+    logger: &EnvProgressLogger,
+    event_details: EventDetails,
+) -> Result<ImageMirroring, Box<EngineError>> {
     let (cluster_container_registry, image_name, image_tag, must_mirror_image) = source
         .compute_cluster_container_registry_url_with_image_name_and_image_tag(
             service_id,
@@ -176,58 +210,103 @@ pub fn mirror_image_if_necessary(
             &target.kubernetes.advanced_settings().registry_mirroring_mode,
             target.container_registry.registry_info(),
         );
+    if !must_mirror_image {
+        logger.info("🎯 Skipping image mirroring: service and cluster registries are the same".to_string());
+        return Ok(ImageMirroring::SameRegistry);
+    }
+
+    let source_image = ContainerImage::new(
+        source.registry.url().clone(),
+        source.image.to_string(),
+        vec![source.tag.to_string()],
+    );
     let dest_image = ContainerImage::new(cluster_container_registry, image_name, vec![image_tag]);
+    let to_engine_error = |err: ImageMirroringError| {
+        logger.warning(format!("❌ {err}"));
+        Box::new(match err {
+            ImageMirroringError::Aborted { .. } => EngineError::new_task_cancellation_requested(event_details.clone()),
+            err => EngineError::new_cannot_mirror_image(
+                event_details.clone(),
+                CommandError::new_from_safe_message(err.to_string()),
+            ),
+        })
+    };
 
-    if image_already_exist(&dest_image, target) {
-        let skip_image_mirroring_message = if must_mirror_image {
-            format!(
-                "🎯 Skipping image mirroring: image {} already exists in the registry",
-                source.image
-            )
-        } else {
-            "🎯 Skipping image mirroring: service and cluster registries are the same".to_string()
-        };
-        logger.info(skip_image_mirroring_message);
-        mirror_record.stop(StepStatus::Skip);
-        Ok(())
-    } else {
-        let result = mirror_image(
-            service_id,
-            source,
-            &dest_image,
-            target,
-            logger,
-            event_details.clone(),
-            RegistryTags {
-                environment_id: target.environment.id.clone(),
-                project_id: target.environment.project_id.clone(),
-                resource_ttl: target.kubernetes.advanced_settings().resource_ttl(),
-            },
-        );
-        mirror_record.stop(if result.is_ok() {
-            StepStatus::Success
-        } else {
-            StepStatus::Error
+    // We need to login to the registry to get access to the image
+    login_to_registry(&source.registry, target, logger, event_details.clone())?;
+    let registry = DockerMirroringRegistry {
+        docker: target.docker,
+        abort: target.abort,
+    };
+    let digest = match plan_image_mirroring(&registry, &source_image, &dest_image).map_err(to_engine_error)? {
+        MirroringPlan::AlreadyMirrored { digest } => {
+            logger.info(format!(
+                "🎯 Skipping image mirroring: image {}:{} ({}) already exists in the registry",
+                source.image, source.tag, digest
+            ));
+            return Ok(ImageMirroring::AlreadyMirrored);
+        }
+        MirroringPlan::Copy { digest } => digest,
+    };
+
+    // Once we are logged to the registry, we mirror the user image into our cluster private registry
+    // This is required only to avoid to manage rotating credentials
+    logger.info(format!(
+        "🪞 Mirroring image {}:{} ({}) to private cluster registry to ensure reproducibility",
+        source.image, source.tag, digest
+    ));
+    create_mirror_repository(
+        service_id,
+        target,
+        event_details.clone(),
+        RegistryTags {
+            environment_id: target.environment.id.clone(),
+            project_id: target.environment.project_id.clone(),
+            resource_ttl: target.kubernetes.advanced_settings().resource_ttl(),
+        },
+    )?;
+
+    let should_abort_waiting_thread = AtomicBool::new(false);
+    let current_span = tracing::Span::current();
+    let result = thread::scope(|scope| {
+        let waiting_thread = scope.spawn(|| {
+            // making sure to pass the current span to the new thread not to lose any tracing info
+            let _span = current_span.enter();
+            let mut iterations: u16 = 0;
+            loop {
+                thread::sleep(Duration::from_secs(1));
+                if should_abort_waiting_thread.load(Ordering::Relaxed) {
+                    break;
+                }
+                iterations += 1;
+                // Send a message every minute to reassure user
+                if iterations % 60 == 0 {
+                    logger.info("🪞 Mirroring is still in progress...".to_string());
+                    iterations = 0;
+                }
+            }
         });
+
+        let result = copy_image_by_digest(&registry, &source_image, &digest, &dest_image);
+
+        // Release waiting thread
+        should_abort_waiting_thread.store(true, Ordering::Relaxed);
+        waiting_thread.join().unwrap();
+
         result
-    }
-}
+    });
+    result.map_err(to_engine_error)?;
 
-fn image_already_exist(dest_image: &ContainerImage, target: &DeploymentTarget) -> bool {
-    matches!(target.docker.does_image_exist_remotely(dest_image), Ok(true))
+    Ok(ImageMirroring::Copied)
 }
 
-fn mirror_image(
-    service_id: &Uuid,
-    source: &RegistryImageSource,
-    dest_image: &ContainerImage,
+pub fn login_to_registry(
+    registry: &Registry,
     target: &DeploymentTarget,
     logger: &EnvProgressLogger,
     event_details: EventDetails,
-    tags: RegistryTags,
 ) -> Result<(), Box<EngineError>> {
-    // We need to login to the registry to get access to the image
-    let url = source.registry.get_url_with_credentials().map_err(|_| {
+    let url = registry.get_url_with_credentials().map_err(|_| {
         logger.warning("⚠️Cannot get the registry credentials".to_string());
         EngineError::new_error_cannot_get_registry_credentials(event_details.clone())
     })?;
@@ -257,9 +336,15 @@ fn mirror_image(
         }
     }
 
-    // Once we are logged to the registry, we mirror the user image into our cluster private registry
-    // This is required only to avoid to manage rotating credentials
-    logger.info("🪞 Mirroring image to private cluster registry to ensure reproducibility".to_string());
+    Ok(())
+}
+
+pub fn create_mirror_repository(
+    service_id: &Uuid,
+    target: &DeploymentTarget,
+    event_details: EventDetails,
+    tags: RegistryTags,
+) -> Result<(), Box<EngineError>> {
     let mirror_repo_name = get_mirror_repository_name(
         service_id,
         target.kubernetes.long_id(),
@@ -272,77 +357,9 @@ fn mirror_image(
             target.kubernetes.advanced_settings().registry_image_retention_time_sec,
             tags,
         )
-        .map_err(|err| EngineError::new_container_registry_error(event_details.clone(), err))?;
+        .map_err(|err| EngineError::new_container_registry_error(event_details, err))?;
 
-    let source_image = ContainerImage::new(
-        source.registry.url().clone(),
-        source.image.to_string(),
-        vec![source.tag.to_string()],
-    );
-
-    let should_abort_waiting_thread = AtomicBool::new(false);
-    let current_span = tracing::Span::current();
-    let result = thread::scope(|scope| {
-        let waiting_thread = scope.spawn(|| {
-            // making sure to pass the current span to the new thread not to lose any tracing info
-            let _span = current_span.enter();
-            let mut iterations: u16 = 0;
-            loop {
-                thread::sleep(Duration::from_secs(1));
-                if should_abort_waiting_thread.load(Ordering::Relaxed) {
-                    break;
-                }
-                iterations += 1;
-                // Send a message every minute to reassure user
-                if iterations % 60 == 0 {
-                    logger.info("🪞 Mirroring is still in progress...".to_string());
-                    iterations = 0;
-                }
-            }
-        });
-        let docker_mirror_thread = scope.spawn(|| {
-            // making sure to pass the current span to the new thread not to lose any tracing info
-            let _span = current_span.enter();
-            if let Err(err) = retry::retry(Fixed::from_millis(1000).take(3), || {
-                match target.docker.mirror(
-                    &source_image,
-                    dest_image,
-                    &mut |line| info!("{}", line),
-                    &mut |line| warn!("{}", line),
-                    // Set timeout at 15min (arbitrary value)
-                    &CommandKiller::from(Duration::from_secs(60 * 15), target.abort),
-                ) {
-                    Ok(ret) => OperationResult::Ok(ret),
-                    Err(err) if err.is_aborted() => OperationResult::Err(err),
-                    Err(err) => {
-                        error!("docker mirror error: {:?}", err);
-                        logger.info("🪞 Retrying Mirroring image due to error...".to_string());
-                        OperationResult::Retry(err)
-                    }
-                }
-            }) {
-                let msg = format!("❌ Failed to mirror image {}:{} due to {}", source.image, source.tag, err);
-                logger.warning(msg.clone());
-                let user_err = EngineError::new_docker_error(event_details, err.error);
-
-                return Err(Box::new(EngineError::new_engine_error(user_err, msg, None)));
-            }
-
-            Ok(())
-        });
-
-        // Wait docker mirror thread to finish
-        let result = docker_mirror_thread.join().unwrap();
-
-        // Release waiting thread
-        should_abort_waiting_thread.store(true, Ordering::Relaxed);
-        waiting_thread.join().unwrap();
-
-        // Return docker mirroring thread result
-        result
-    });
-
-    result
+    Ok(())
 }
 
 pub enum KubeObjectKind {
@@ -447,6 +464,86 @@ pub async fn get_last_deployed_image(
     }
 }
 
+/// Image ids (`<repository>@<digest>`) of the main container of the running pods
+pub async fn get_deployed_image_ids(
+    client: kube::Client,
+    selector: &str,
+    namespace: &str,
+) -> Result<Vec<String>, kube::Error> {
+    let api: Api<Pod> = Api::namespaced(client, namespace);
+    let pods = api.list(&ListParams::default().labels(selector)).await?;
+
+    Ok(pods
+        .items
+        .into_iter()
+        .filter_map(|pod| {
+            let main_container = pod.spec.as_ref()?.containers.first()?.name.clone();
+            pod.status?
+                .container_statuses?
+                .into_iter()
+                .find(|status| status.name == main_container)
+                .map(|status| status.image_id)
+        })
+        .filter(|image_id| !image_id.is_empty())
+        .collect())
+}
+
+/// Execution id the currently deployed image has been promoted from, if any
+pub async fn get_promoted_from_execution_id(
+    client: kube::Client,
+    selector: &str,
+    service_type: KubeObjectKind,
+    namespace: &str,
+) -> Option<String> {
+    let list_params = ListParams::default().labels(selector);
+    let annotations = match service_type {
+        KubeObjectKind::Deployment => {
+            Api::<Deployment>::namespaced(client, namespace)
+                .list(&list_params)
+                .await
+                .ok()?
+                .items
+                .into_iter()
+                .next()?
+                .metadata
+                .annotations?
+        }
+        KubeObjectKind::Statefulset => {
+            Api::<StatefulSet>::namespaced(client, namespace)
+                .list(&list_params)
+                .await
+                .ok()?
+                .items
+                .into_iter()
+                .next()?
+                .metadata
+                .annotations?
+        }
+        KubeObjectKind::Job | KubeObjectKind::CronJob => return None,
+    };
+
+    annotations.get(PROMOTED_FROM_EXECUTION_ID_ANNOTATION).cloned()
+}
+
+/// Deploying another image than the promoted one breaks the promotion, let the user know about it
+pub fn warn_if_promotion_pin_is_replaced(
+    target: &DeploymentTarget,
+    selector: &str,
+    service_type: KubeObjectKind,
+    logger: &EnvProgressLogger,
+) {
+    if let Some(execution_id) = block_on(get_promoted_from_execution_id(
+        target.kube.clone(),
+        selector,
+        service_type,
+        target.environment.namespace(),
+    )) {
+        logger.warning(format!(
+            "⚠️ The running image has been promoted from execution {execution_id}, this deployment replaces it and breaks the promotion pin"
+        ));
+    }
+}
+
 pub fn are_pvcs_bound(
     service: &dyn Service,
     namespace: &str,
@@ -487,9 +584,22 @@ pub fn update_pvcs(
     event_details: &EventDetails,
     client: &kube::Client,
 ) -> Result<(), Box<EngineError>> {
-    block_on(increase_storage_size(namespace, invalid_statefulset, event_details, client))?;
+    let storage_client = StatefulsetStorageClient::new(client.clone(), namespace);
+    expand_statefulset_storage(&storage_client, event_details, namespace, invalid_statefulset)?;
 
     are_pvcs_bound(service, namespace, event_details, client)?;
 
     Ok(())
 }
+
+/// To call once the statefulset whose PVCs have been updated is redeployed.
+/// Returns whether its pods have been restarted to resize their filesystem.
+pub fn restart_statefulset_after_pvcs_update(
+    invalid_statefulset: &InvalidStatefulsetStorage,
+    namespace: &str,
+    event_details: &EventDetails,
+    client: &kube::Client,
+) -> Result<bool, Box<EngineError>> {
+    let storage_client = StatefulsetStorageClient::new(client.clone(), namespace);
+    restart_statefulset_if_filesystem_resize_pending(&storage_client, event_details, namespace, invalid_statefulset)
+}
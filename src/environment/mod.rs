@@ -2,3 +2,4 @@ pub mod action;
 pub mod models;
 pub mod report;
 pub mod task;
+pub mod variable_preview_task;
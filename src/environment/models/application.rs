@@ -7,17 +7,22 @@ use itertools::Itertools;
 use k8s_openapi::api::core::v1::PersistentVolumeClaim;
 use uuid::Uuid;
 
+use crate::environment::action::database_credentials::service_database_credentials;
 use crate::environment::action::DeploymentAction;
 use crate::environment::models::annotations_group::AnnotationsGroupTeraContext;
+use crate::environment::models::autoscaling::{check_metrics_adapter, hpa_metrics, validate_hpa_metrics};
 use crate::environment::models::container::{
     to_public_l4_ports, ClusterTeraContext, ContainerTeraContext, RegistryTeraContext, ServiceTeraContext,
 };
+use crate::environment::models::graceful_shutdown::{validate_graceful_shutdown, GracefulShutdownTeraContext};
+use crate::environment::models::helm_release::helm_release_name;
 use crate::environment::models::labels_group::LabelsGroupTeraContext;
 use crate::environment::models::probe::Probe;
+use crate::environment::models::service_port::{validate_service_ports, ServicePort};
 use crate::environment::models::types::{CloudProvider, ToTeraContext};
 use crate::environment::models::utils;
 use crate::errors::EngineError;
-use crate::events::{EventDetails, Stage, Transmitter};
+use crate::events::{EnvironmentStep, EventDetails, Stage, Transmitter};
 use crate::infrastructure::models::build_platform::Build;
 use crate::infrastructure::models::cloud_provider::service::{
     get_service_statefulset_name_and_volumes, Action, Service, ServiceType,
@@ -25,8 +30,8 @@ use crate::infrastructure::models::cloud_provider::service::{
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
 use crate::infrastructure::models::cloud_provider::Kind::Scw;
 use crate::io_models::annotations_group::AnnotationsGroup;
+use crate::io_models::application::ApplicationAdvancedSettings;
 use crate::io_models::application::Protocol::{TCP, UDP};
-use crate::io_models::application::{ApplicationAdvancedSettings, Port};
 use crate::io_models::context::Context;
 use crate::io_models::labels_group::LabelsGroup;
 use crate::io_models::models::{
@@ -53,7 +58,7 @@ pub struct Application<T: CloudProvider> {
     pub(crate) name: String,
     pub(crate) kube_name: String,
     pub(crate) public_domain: String,
-    pub(crate) ports: Vec<Port>,
+    pub(crate) ports: Vec<ServicePort>,
     pub(crate) cpu_request_in_milli: KubernetesCpuResourceUnit,
     pub(crate) cpu_limit_in_milli: KubernetesCpuResourceUnit,
     pub(crate) ram_request_in_mib: KubernetesMemoryResourceUnit,
@@ -86,7 +91,7 @@ impl<T: CloudProvider> Application<T> {
         name: &str,
         kube_name: String,
         public_domain: String,
-        ports: Vec<Port>,
+        ports: Vec<ServicePort>,
         min_instances: u32,
         max_instances: u32,
         build: Build,
@@ -109,6 +114,11 @@ impl<T: CloudProvider> Application<T> {
         should_delete_shared_registry: bool,
     ) -> Result<Self, ApplicationError> {
         // TODO: Check that the information provided are coherent
+        validate_service_ports(&ports).map_err(|err| ApplicationError::InvalidConfig(err.to_string()))?;
+        validate_graceful_shutdown(&advanced_settings.to_container_advanced_settings())
+            .map_err(|err| ApplicationError::InvalidConfig(err.to_string()))?;
+        validate_hpa_metrics(&advanced_settings.to_container_advanced_settings())
+            .map_err(|err| ApplicationError::InvalidConfig(err.to_string()))?;
 
         let workspace_directory = crate::fs::workspace_directory(
             context.workspace_root_dir(),
@@ -154,6 +164,11 @@ impl<T: CloudProvider> Application<T> {
     }
 
     pub fn helm_release_name(&self) -> String {
+        helm_release_name("application", &self.long_id)
+    }
+
+    /// Release name used before release names were generated from the service long id
+    pub fn legacy_helm_release_name(&self) -> String {
         crate::string::cut(format!("application-{}-{}", self.id(), self.id()), 50)
     }
 
@@ -161,12 +176,20 @@ impl<T: CloudProvider> Application<T> {
         format!("{}/common/charts/q-container", self.lib_root_directory)
     }
 
-    fn public_ports(&self) -> impl Iterator<Item = &Port> + '_ {
-        self.ports.iter().filter(|port| port.publicly_accessible)
+    fn public_ports(&self) -> impl Iterator<Item = &ServicePort> + '_ {
+        self.ports.iter().filter(|port| port.is_public())
     }
 
-    pub(crate) fn default_tera_context(&self, target: &DeploymentTarget) -> ContainerTeraContext {
+    pub(crate) fn default_tera_context(
+        &self,
+        target: &DeploymentTarget,
+    ) -> Result<ContainerTeraContext, Box<EngineError>> {
         let environment = target.environment;
+        let (environment_variables, database_credentials) = service_database_credentials(
+            target,
+            &self.environment_variables,
+            &self.get_event_details(Stage::Environment(EnvironmentStep::Deploy)),
+        )?;
         let kubernetes = target.kubernetes;
         let mut deployment_affinity_node_required = utils::add_arch_to_deployment_affinity_node(
             &self.advanced_settings.deployment_affinity_node_required,
@@ -197,6 +220,8 @@ impl<T: CloudProvider> Application<T> {
                 user_unsafe_name: self.name.clone(),
                 image_full: self.build.image.full_image_name_with_tag(),
                 image_tag: self.build.image.tag.clone(),
+                promoted_from_execution_id: None,
+                promoted_image_digest: None,
                 version: self.version(),
                 command_args: self.command_args.clone(),
                 entrypoint: self.entrypoint.clone(),
@@ -226,7 +251,10 @@ impl<T: CloudProvider> Application<T> {
                         id: s.id.clone(),
                         long_id: s.long_id,
                         name: s.name.clone(),
-                        storage_type: s.storage_class.0.clone(),
+                        storage_type: kubernetes
+                            .default_storage_class()
+                            .unwrap_or(s.storage_class.0.as_str())
+                            .to_string(),
                         size_in_gib: s.size_in_gib,
                         mount_point: s.mount_point.clone(),
                         snapshot_retention_in_days: s.snapshot_retention_in_days,
@@ -234,6 +262,11 @@ impl<T: CloudProvider> Application<T> {
                     .collect(),
                 readiness_probe: self.readiness_probe.clone(),
                 liveness_probe: self.liveness_probe.clone(),
+                graceful_shutdown: GracefulShutdownTeraContext::new(
+                    &advanced_settings.to_container_advanced_settings(),
+                    !self.ports.is_empty(),
+                ),
+                hpa_metrics: hpa_metrics(&advanced_settings.to_container_advanced_settings()),
                 advanced_settings: advanced_settings.to_container_advanced_settings(),
                 legacy_deployment_matchlabels: true,
                 legacy_volumeclaim_template: true,
@@ -247,7 +280,8 @@ impl<T: CloudProvider> Application<T> {
                     secret_name: format!("{}-registry", self.kube_name()),
                     docker_json_config: Some(docker_json.to_string()),
                 }),
-            environment_variables: self.environment_variables.clone(),
+            environment_variables,
+            database_credentials,
             mounted_files: self.mounted_files.clone().into_iter().collect::<Vec<_>>(),
             resource_expiration_in_seconds: Some(kubernetes.advanced_settings().pleco_resources_ttl),
             loadbalancer_l4_annotations: kubernetes.loadbalancer_l4_annotations(Some(self.kube_name())),
@@ -255,7 +289,7 @@ impl<T: CloudProvider> Application<T> {
             labels_group: self.labels_group.clone(),
         };
 
-        ctx
+        Ok(ctx)
     }
 
     pub fn is_stateful(&self) -> bool {
@@ -400,16 +434,35 @@ impl<T: CloudProvider> Service for Application<T> {
 pub trait ApplicationService: Service + DeploymentAction + ToTeraContext + Send {
     fn get_build(&self) -> &Build;
     fn get_build_mut(&mut self) -> &mut Build;
-    fn public_ports(&self) -> Vec<&Port>;
+    fn public_ports(&self) -> Vec<&ServicePort>;
     fn advanced_settings(&self) -> &ApplicationAdvancedSettings;
     fn startup_timeout(&self) -> Duration;
     fn as_deployment_action(&self) -> &dyn DeploymentAction;
+    /// Registry repositories removed with the application, the shared one only when the core flagged it
+    fn owned_registry_repositories(&self) -> Vec<&str>;
 }
 
 use tera::Context as TeraContext;
 impl<T: CloudProvider> ToTeraContext for Application<T> {
     fn to_tera_context(&self, target: &DeploymentTarget) -> Result<TeraContext, Box<EngineError>> {
-        let context = self.default_tera_context(target);
+        target
+            .capabilities
+            .check_probes(self.readiness_probe.iter().chain(self.liveness_probe.iter()))
+            .map_err(|err| {
+                EngineError::new_k8s_unsupported_feature(
+                    self.get_event_details(Stage::Environment(EnvironmentStep::Deploy)),
+                    self.name(),
+                    err.to_string(),
+                )
+            })?;
+        check_metrics_adapter(
+            &self.advanced_settings.to_container_advanced_settings(),
+            target,
+            self.get_event_details(Stage::Environment(EnvironmentStep::Deploy)),
+            self.name(),
+        )?;
+
+        let context = self.default_tera_context(target)?;
         Ok(TeraContext::from_serialize(context).unwrap_or_default())
     }
 }
@@ -426,10 +479,18 @@ where
         self.build_mut()
     }
 
-    fn public_ports(&self) -> Vec<&Port> {
+    fn public_ports(&self) -> Vec<&ServicePort> {
         self.public_ports().collect_vec()
     }
 
+    fn owned_registry_repositories(&self) -> Vec<&str> {
+        let image = &self.build.image;
+        match self.should_delete_shared_registry {
+            true => vec![image.shared_repository_name(), image.legacy_repository_name()],
+            false => vec![image.legacy_repository_name()],
+        }
+    }
+
     fn advanced_settings(&self) -> &ApplicationAdvancedSettings {
         &self.advanced_settings
     }
@@ -525,14 +586,10 @@ pub fn get_application_with_invalid_storage_size<T: CloudProvider>(
                                 }
 
                                 if storage.size_in_gib < size {
-                                    return Err(Box::new(EngineError::new_invalid_engine_payload(
+                                    return Err(Box::new(EngineError::new_k8s_storage_cannot_be_reduced(
                                         event_details.clone(),
-                                        format!(
-                                            "new storage size ({}) should be equal or greater than actual size ({})",
-                                            storage.size_in_gib, size
-                                        )
-                                        .as_str(),
-                                        None,
+                                        storage.size_in_gib,
+                                        size,
                                     )));
                                 }
                             }
@@ -0,0 +1,294 @@
+use crate::errors::EngineError;
+use crate::events::EventDetails;
+use crate::infrastructure::models::cloud_provider::DeploymentTarget;
+use crate::io_models::container::{ContainerAdvancedSettings, HpaMetric, HpaResourceName};
+use crate::io_models::context::Features;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+
+// Kubernetes quantity, e.g. `100`, `0.5`, `500m`, `10k` or `1Gi`
+static QUANTITY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\d+(\.\d+)?([munkMGTPE]|[KMGTPE]i)?$").expect("invalid quantity regex"));
+
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum AutoscalingError {
+    #[error("hpa.metrics: {0}")]
+    InvalidMetric(String),
+}
+
+/// Validates the metrics the autoscaler of a service scales on
+pub fn validate_hpa_metrics(settings: &ContainerAdvancedSettings) -> Result<(), AutoscalingError> {
+    let mut resources = HashSet::new();
+    for metric in &settings.hpa_metrics {
+        match metric {
+            HpaMetric::Resource {
+                name,
+                target_average_utilization_percent,
+            } => {
+                if !resources.insert(name) {
+                    return Err(AutoscalingError::InvalidMetric(format!(
+                        "resource `{}` is defined more than once",
+                        resource_name(name)
+                    )));
+                }
+                if *target_average_utilization_percent == 0 {
+                    return Err(AutoscalingError::InvalidMetric(format!(
+                        "target utilization of resource `{}` must be greater than 0",
+                        resource_name(name)
+                    )));
+                }
+            }
+            HpaMetric::Pods {
+                metric_name,
+                target_average_value,
+            }
+            | HpaMetric::External {
+                metric_name,
+                target_average_value,
+                ..
+            } => {
+                if metric_name.trim().is_empty() {
+                    return Err(AutoscalingError::InvalidMetric("metric name must not be empty".to_string()));
+                }
+                if !QUANTITY.is_match(target_average_value) {
+                    return Err(AutoscalingError::InvalidMetric(format!(
+                        "target `{target_average_value}` of metric `{metric_name}` is not a valid quantity"
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn resource_name(name: &HpaResourceName) -> &'static str {
+    match name {
+        HpaResourceName::Cpu => "cpu",
+        HpaResourceName::Memory => "memory",
+    }
+}
+
+/// Metrics rendered in the HorizontalPodAutoscaler of the service.
+/// Without explicit metrics, the service keeps scaling on the cpu and memory utilization settings.
+pub(crate) fn hpa_metrics(settings: &ContainerAdvancedSettings) -> Vec<HpaMetric> {
+    if !settings.hpa_metrics.is_empty() {
+        return settings.hpa_metrics.clone();
+    }
+
+    let mut metrics = vec![HpaMetric::Resource {
+        name: HpaResourceName::Cpu,
+        target_average_utilization_percent: settings.hpa_cpu_average_utilization_percent as u32,
+    }];
+    if let Some(memory_percent) = settings.hpa_memory_average_utilization_percent {
+        metrics.push(HpaMetric::Resource {
+            name: HpaResourceName::Memory,
+            target_average_utilization_percent: memory_percent as u32,
+        });
+    }
+
+    metrics
+}
+
+/// Names of the metrics served by the metrics adapter (custom and external metrics APIs)
+pub fn metrics_requiring_adapter(metrics: &[HpaMetric]) -> Vec<String> {
+    metrics
+        .iter()
+        .filter_map(|metric| match metric {
+            HpaMetric::Resource { .. } => None,
+            HpaMetric::Pods { metric_name, .. } | HpaMetric::External { metric_name, .. } => Some(metric_name.clone()),
+        })
+        .collect()
+}
+
+/// The metrics adapter is only deployed on clusters with metrics history, without it the autoscaler can't get
+/// custom and external metrics and never scales the service.
+pub(crate) fn check_metrics_adapter(
+    settings: &ContainerAdvancedSettings,
+    target: &DeploymentTarget,
+    event_details: EventDetails,
+    service_name: &str,
+) -> Result<(), Box<EngineError>> {
+    let metric_names = metrics_requiring_adapter(&settings.hpa_metrics);
+    if metric_names.is_empty()
+        || target
+            .kubernetes
+            .context()
+            .is_feature_enabled(&Features::MetricsHistory)
+    {
+        return Ok(());
+    }
+
+    Err(Box::new(EngineError::new_k8s_metrics_adapter_not_enabled(
+        event_details,
+        service_name,
+        &metric_names,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::models::labels_group::LabelsGroupTeraContext;
+    use maplit::btreemap;
+    use serde_json::json;
+    use std::env;
+    use std::fs;
+    use tera::{Context as TeraContext, Tera};
+
+    fn settings(hpa_metrics: Vec<HpaMetric>) -> ContainerAdvancedSettings {
+        ContainerAdvancedSettings {
+            hpa_metrics,
+            ..Default::default()
+        }
+    }
+
+    fn cpu(target_average_utilization_percent: u32) -> HpaMetric {
+        HpaMetric::Resource {
+            name: HpaResourceName::Cpu,
+            target_average_utilization_percent,
+        }
+    }
+
+    fn sqs_queue_depth(target_average_value: &str) -> HpaMetric {
+        HpaMetric::External {
+            metric_name: "sqs_messages_visible".to_string(),
+            selector: btreemap! { "queue".to_string() => "jobs".to_string() },
+            target_average_value: target_average_value.to_string(),
+        }
+    }
+
+    fn render_hpa(settings: &ContainerAdvancedSettings) -> serde_yaml::Value {
+        let mut context = TeraContext::new();
+        context.insert("namespace", "my-namespace");
+        context.insert("environment_short_id", "env");
+        context.insert("environment_long_id", "a0b1c2d3-0000-0000-0000-000000000002");
+        context.insert("project_long_id", "a0b1c2d3-0000-0000-0000-000000000003");
+        context.insert("labels_group", &LabelsGroupTeraContext::new(vec![]));
+        context.insert("annotations_group", &json!({ "hpa": {} }));
+        context.insert(
+            "service",
+            &json!({
+                "name": "my-app",
+                "long_id": "a0b1c2d3-0000-0000-0000-000000000000",
+                "type": "application",
+                "storages": [],
+                "min_instances": 1,
+                "max_instances": 5,
+                "advanced_settings": settings,
+                "hpa_metrics": hpa_metrics(settings),
+            }),
+        );
+
+        let template = fs::read_to_string(format!(
+            "{}/lib/common/charts/q-container/templates/horizontal_autoscaler.j2.yaml",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .display()
+        ))
+        .expect("cannot read hpa template");
+        let hpa = Tera::one_off(&template, &context, false).expect("cannot render hpa template");
+
+        serde_yaml::from_str(&hpa).expect("invalid hpa yaml")
+    }
+
+    #[test]
+    fn test_validate_hpa_metrics() {
+        assert_eq!(validate_hpa_metrics(&settings(vec![])), Ok(()));
+        assert_eq!(
+            validate_hpa_metrics(&settings(vec![
+                cpu(70),
+                HpaMetric::Pods {
+                    metric_name: "nginx_ingress_requests_per_second".to_string(),
+                    target_average_value: "100".to_string(),
+                },
+                sqs_queue_depth("30"),
+            ])),
+            Ok(())
+        );
+
+        let invalid_metrics = vec![
+            vec![cpu(70), cpu(80)],
+            vec![cpu(0)],
+            vec![sqs_queue_depth("thirty")],
+            vec![sqs_queue_depth("")],
+            vec![HpaMetric::Pods {
+                metric_name: " ".to_string(),
+                target_average_value: "100".to_string(),
+            }],
+        ];
+        for metrics in invalid_metrics {
+            assert!(matches!(
+                validate_hpa_metrics(&settings(metrics)),
+                Err(AutoscalingError::InvalidMetric(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_metrics_requiring_adapter() {
+        assert!(metrics_requiring_adapter(&[cpu(70)]).is_empty());
+        assert_eq!(
+            metrics_requiring_adapter(&[cpu(70), sqs_queue_depth("30")]),
+            vec!["sqs_messages_visible".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_hpa_template_defaults_to_cpu() {
+        let hpa = render_hpa(&ContainerAdvancedSettings::default());
+
+        assert_eq!(hpa["apiVersion"].as_str(), Some("autoscaling/v2"));
+        let metrics = hpa["spec"]["metrics"].as_sequence().unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0]["type"].as_str(), Some("Resource"));
+        assert_eq!(metrics[0]["resource"]["name"].as_str(), Some("cpu"));
+        assert_eq!(metrics[0]["resource"]["target"]["averageUtilization"].as_u64(), Some(60));
+
+        let hpa = render_hpa(&ContainerAdvancedSettings {
+            hpa_memory_average_utilization_percent: Some(80),
+            ..Default::default()
+        });
+        let metrics = hpa["spec"]["metrics"].as_sequence().unwrap();
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics[1]["resource"]["name"].as_str(), Some("memory"));
+        assert_eq!(metrics[1]["resource"]["target"]["averageUtilization"].as_u64(), Some(80));
+    }
+
+    #[test]
+    fn test_hpa_template_with_custom_and_external_metrics() {
+        let hpa = render_hpa(&settings(vec![
+            HpaMetric::Resource {
+                name: HpaResourceName::Memory,
+                target_average_utilization_percent: 75,
+            },
+            HpaMetric::Pods {
+                metric_name: "nginx_ingress_requests_per_second".to_string(),
+                target_average_value: "100".to_string(),
+            },
+            sqs_queue_depth("30"),
+        ]));
+
+        let metrics = hpa["spec"]["metrics"].as_sequence().unwrap();
+        assert_eq!(metrics.len(), 3);
+        assert_eq!(metrics[0]["resource"]["name"].as_str(), Some("memory"));
+        assert_eq!(metrics[0]["resource"]["target"]["averageUtilization"].as_u64(), Some(75));
+
+        assert_eq!(metrics[1]["type"].as_str(), Some("Pods"));
+        assert_eq!(
+            metrics[1]["pods"]["metric"]["name"].as_str(),
+            Some("nginx_ingress_requests_per_second")
+        );
+        assert_eq!(metrics[1]["pods"]["target"]["type"].as_str(), Some("AverageValue"));
+        assert_eq!(metrics[1]["pods"]["target"]["averageValue"].as_str(), Some("100"));
+
+        assert_eq!(metrics[2]["type"].as_str(), Some("External"));
+        assert_eq!(metrics[2]["external"]["metric"]["name"].as_str(), Some("sqs_messages_visible"));
+        assert_eq!(
+            metrics[2]["external"]["metric"]["selector"]["matchLabels"]["queue"].as_str(),
+            Some("jobs")
+        );
+        assert_eq!(metrics[2]["external"]["target"]["averageValue"].as_str(), Some("30"));
+    }
+}
@@ -5,7 +5,7 @@ use crate::environment::models::aws::database_utils::{
     is_allowed_managed_redis_version,
 };
 use crate::environment::models::database::{
-    Container, Database, DatabaseType, Managed, MongoDB, MySQL, PostgresSQL, Redis,
+    managed_final_snapshot_name, Container, Database, DatabaseType, Managed, MongoDB, MySQL, PostgresSQL, Redis,
 };
 use crate::errors::{CommandError, EngineError};
 use crate::events::{EventDetails, Stage};
@@ -279,6 +279,11 @@ where
             .to_string();
         context.insert("version", &version);
 
+        // Specific to postgresql, the parameter group is only created when custom parameters are set
+        if T::db_type() == service::DatabaseType::PostgreSQL {
+            context.insert("parameter_group_family", &format!("postgres{}", self.version.major));
+        }
+
         // Specific to mysql
         if T::db_type() == service::DatabaseType::MySQL {
             context.insert(
@@ -359,15 +364,22 @@ where
         }
         context.insert("database_disk_type", &options.database_disk_type);
         context.insert("encrypt_disk", &options.encrypt_disk);
+        context.insert("database_parameters", options.parameters.parameters());
         context.insert("database_fqdn", &options.host.as_str());
         context.insert("database_id", &self.id());
         context.insert("tfstate_suffix_name", &get_tfstate_suffix(self));
         context.insert("tfstate_name", &get_tfstate_name(self));
         context.insert("skip_final_snapshot", &false);
-        context.insert("final_snapshot_name", &format!("qovery-{}-final-snap", self.id));
+        context.insert("final_snapshot_name", &managed_final_snapshot_name(&self.id));
         context.insert("delete_automated_backups", &target.kubernetes.context().is_test_cluster());
         context.insert("publicly_accessible", &options.publicly_accessible);
         context.insert("labels_group", &self.labels_group);
+        if let Some(maintenance_window) = options.maintenance.maintenance_window() {
+            context.insert("preferred_maintenance_window", &maintenance_window.to_aws_format());
+        }
+        if let Some(backup_retention_days) = options.maintenance.backup_retention_days() {
+            context.insert("backup_retention_period", &backup_retention_days);
+        }
 
         // NLB or ALB controller annotation
         context.insert(
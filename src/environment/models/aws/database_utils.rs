@@ -96,6 +96,9 @@ mod tests {
         assert!(
             is_allowed_managed_mysql_version(&VersionsNumberBuilder::new().major(8).minor(2).patch(3).build()).is_ok()
         );
+
+        // v8.4
+        assert!(is_allowed_managed_mysql_version(&VersionsNumberBuilder::new().major(8).minor(4).build()).is_ok());
     }
 
     #[test]
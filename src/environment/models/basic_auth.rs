@@ -0,0 +1,256 @@
+use crate::environment::models::router::RouterError;
+use crate::io_models::router::BasicAuthCredential;
+use base64::engine::general_purpose;
+use base64::Engine;
+use bcrypt::Version;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+// same cost as `htpasswd -B`, nginx checks the password on every request so a higher cost slows down every call
+const BCRYPT_COST: u32 = 5;
+
+static BCRYPT_HASH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\$2[aby]\$\d{2}\$[./A-Za-z0-9]{53}$").expect("invalid bcrypt hash regex"));
+
+// hash formats understood by nginx `auth_basic_user_file`: bcrypt, crypt (MD5, SHA-256, SHA-512, DES) and `{SHA}`/`{SSHA}`
+static HTPASSWD_HASH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(\$2[aby]\$\d{2}\$[./A-Za-z0-9]{53}|\$(apr1|1)\$[./A-Za-z0-9]{1,8}\$[./A-Za-z0-9]{22}|\$[56]\$(rounds=\d+\$)?[./A-Za-z0-9]{1,16}\$[./A-Za-z0-9]{43,86}|[./A-Za-z0-9]{13}|\{SHA\}[A-Za-z0-9+/]{27}=|\{SSHA\}[A-Za-z0-9+/]+=*)$",
+    )
+    .expect("invalid htpasswd hash regex")
+});
+
+/// Content of the htpasswd secret of a router, base64 encoded: one `username:bcrypt hash` line per credential.
+/// The hash of a password is the same on every deployment, so the secret only changes when credentials are rotated.
+pub fn htpasswd(router_long_id: &Uuid, credentials: &[BasicAuthCredential]) -> Result<String, RouterError> {
+    let mut usernames = HashSet::with_capacity(credentials.len());
+    let mut lines = Vec::with_capacity(credentials.len());
+
+    for credential in credentials {
+        let username = credential.username();
+        if username.is_empty() || username.contains([':', '\n', '\r']) {
+            return Err(invalid_credentials(format!(
+                "username `{username}` must not be empty nor contain `:` or line breaks"
+            )));
+        }
+        if !usernames.insert(username) {
+            return Err(invalid_credentials(format!("username `{username}` is defined more than once")));
+        }
+
+        let hash = match credential {
+            BasicAuthCredential::Password { password, .. } if password.is_empty() => {
+                return Err(invalid_credentials(format!("password of user `{username}` is empty")));
+            }
+            BasicAuthCredential::Password { password, .. } => bcrypt_hash(router_long_id, username, password)?,
+            BasicAuthCredential::BcryptHash { password_hash, .. } if !BCRYPT_HASH.is_match(password_hash) => {
+                return Err(invalid_credentials(format!(
+                    "password hash of user `{username}` is not a bcrypt hash"
+                )));
+            }
+            BasicAuthCredential::BcryptHash { password_hash, .. } => password_hash.clone(),
+        };
+        lines.push(format!("{username}:{hash}"));
+    }
+
+    Ok(general_purpose::STANDARD.encode(lines.join("\n")))
+}
+
+/// Content of the htpasswd secret of a router from the base64 encoded value of the deprecated basic auth env var.
+/// The value holds several `username:hash` entries, separated by line breaks or commas, each of them is validated
+/// so a typo doesn't end up in an nginx configuration rejecting every request.
+pub fn htpasswd_from_env_var(env_var_name: &str, env_var_value: &str) -> Result<String, RouterError> {
+    let content = general_purpose::STANDARD
+        .decode(env_var_value)
+        .ok()
+        .and_then(|content| String::from_utf8(content).ok())
+        .ok_or_else(|| RouterError::BasicAuthEnvVarBase64DecodeError {
+            env_var_name: env_var_name.to_string(),
+        })?;
+
+    let mut usernames = HashSet::new();
+    let mut lines = vec![];
+    for (index, entry) in content
+        .split(['\n', ','])
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, entry)| !entry.is_empty())
+    {
+        // hashes are never part of the error, only the position of the entry
+        let entry_number = index + 1;
+        let Some((username, hash)) = entry.split_once(':') else {
+            return Err(invalid_credentials(format!(
+                "entry {entry_number} of `{env_var_name}` is not formatted as `username:hash`"
+            )));
+        };
+        if username.is_empty() {
+            return Err(invalid_credentials(format!(
+                "entry {entry_number} of `{env_var_name}` has an empty username"
+            )));
+        }
+        if !usernames.insert(username) {
+            return Err(invalid_credentials(format!(
+                "username `{username}` is defined more than once in `{env_var_name}`"
+            )));
+        }
+        if !HTPASSWD_HASH.is_match(hash) {
+            return Err(invalid_credentials(format!(
+                "password hash of user `{username}` in `{env_var_name}` is not a supported htpasswd hash (bcrypt, apr1, crypt, SHA)"
+            )));
+        }
+        lines.push(format!("{username}:{hash}"));
+    }
+
+    if lines.is_empty() {
+        return Err(invalid_credentials(format!("`{env_var_name}` does not define any credentials")));
+    }
+
+    Ok(general_purpose::STANDARD.encode(lines.join("\n")))
+}
+
+fn bcrypt_hash(router_long_id: &Uuid, username: &str, password: &str) -> Result<String, RouterError> {
+    // the salt is derived from the credential instead of being random, to get the same hash on every deployment
+    let digest = Sha256::new()
+        .chain_update(router_long_id.as_bytes())
+        .chain_update(username.as_bytes())
+        .chain_update(password.as_bytes())
+        .finalize();
+    let mut salt = [0u8; 16];
+    salt.copy_from_slice(&digest[..16]);
+
+    bcrypt::hash_with_salt(password, BCRYPT_COST, salt)
+        .map(|hash| hash.format_for_version(Version::TwoY))
+        .map_err(|err| invalid_credentials(format!("cannot hash password of user `{username}`: {err}")))
+}
+
+fn invalid_credentials(reason: String) -> RouterError {
+    RouterError::BasicAuthInvalidCredentials { reason }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(htpasswd: &str) -> Vec<(String, String)> {
+        let content = String::from_utf8(general_purpose::STANDARD.decode(htpasswd).unwrap()).unwrap();
+        content
+            .lines()
+            .map(|line| {
+                let (username, hash) = line.split_once(':').unwrap();
+                (username.to_string(), hash.to_string())
+            })
+            .collect()
+    }
+
+    fn password(username: &str, password: &str) -> BasicAuthCredential {
+        BasicAuthCredential::Password {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_htpasswd_contains_a_bcrypt_line_per_credential() {
+        let router_long_id = Uuid::new_v4();
+        let existing_hash = bcrypt::hash("s3cr3t", 4).unwrap();
+        let credentials = vec![
+            password("alice", "p@ssword:with,commas"),
+            BasicAuthCredential::BcryptHash {
+                username: "bob".to_string(),
+                password_hash: existing_hash.clone(),
+            },
+        ];
+
+        let htpasswd = htpasswd(&router_long_id, &credentials).unwrap();
+        let lines = decode(&htpasswd);
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].0, "alice");
+        assert!(lines[0].1.starts_with("$2y$05$"));
+        assert!(!lines[0].1.contains("p@ssword"));
+        assert!(bcrypt::verify("p@ssword:with,commas", &lines[0].1).unwrap());
+        assert_eq!(lines[1], ("bob".to_string(), existing_hash));
+    }
+
+    #[test]
+    fn test_htpasswd_only_changes_when_credentials_are_rotated() {
+        let router_long_id = Uuid::new_v4();
+        let credentials = vec![password("alice", "password-1")];
+
+        let first = htpasswd(&router_long_id, &credentials).unwrap();
+        assert_eq!(htpasswd(&router_long_id, &credentials).unwrap(), first);
+
+        let rotated = htpasswd(&router_long_id, &[password("alice", "password-2")]).unwrap();
+        assert_ne!(rotated, first);
+        assert!(bcrypt::verify("password-2", &decode(&rotated)[0].1).unwrap());
+
+        // same credentials on another router do not share the hash
+        assert_ne!(htpasswd(&Uuid::new_v4(), &credentials).unwrap(), first);
+    }
+
+    #[test]
+    fn test_htpasswd_rejects_invalid_credentials() {
+        let router_long_id = Uuid::new_v4();
+        let invalid_credentials = vec![
+            vec![password("", "password")],
+            vec![password("ali:ce", "password")],
+            vec![password("alice", "")],
+            vec![password("alice", "password-1"), password("alice", "password-2")],
+            vec![BasicAuthCredential::BcryptHash {
+                username: "bob".to_string(),
+                password_hash: "$apr1$not-bcrypt".to_string(),
+            }],
+        ];
+
+        for credentials in invalid_credentials {
+            let err = htpasswd(&router_long_id, &credentials).unwrap_err();
+            assert!(matches!(err, RouterError::BasicAuthInvalidCredentials { .. }));
+            assert!(!err.to_string().contains("password-"));
+        }
+    }
+
+    #[test]
+    fn test_htpasswd_from_env_var_accepts_several_users() {
+        let bcrypt_hash = bcrypt::hash("s3cr3t", 4).unwrap();
+        let env_var_value = general_purpose::STANDARD.encode(format!(
+            "alice:{bcrypt_hash}\nbob:$apr1$r31.....$HqJZimcKQFAMYayBlzkrA/,carol:{{SHA}}W6ph5Mm5Pz8GgiULbPgzG37mj9g=\n\n"
+        ));
+
+        let htpasswd = htpasswd_from_env_var("BASIC_AUTH", &env_var_value).unwrap();
+
+        assert_eq!(
+            decode(&htpasswd),
+            vec![
+                ("alice".to_string(), bcrypt_hash),
+                ("bob".to_string(), "$apr1$r31.....$HqJZimcKQFAMYayBlzkrA/".to_string()),
+                ("carol".to_string(), "{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_htpasswd_from_env_var_rejects_invalid_entries() {
+        let bcrypt_hash = bcrypt::hash("s3cr3t", 4).unwrap();
+        let invalid_values = vec![
+            "".to_string(),
+            "alice".to_string(),
+            format!(":{bcrypt_hash}"),
+            "alice:hunter2".to_string(),
+            format!("alice:{bcrypt_hash}\nalice:{bcrypt_hash}"),
+        ];
+
+        for value in invalid_values {
+            let err = htpasswd_from_env_var("BASIC_AUTH", &general_purpose::STANDARD.encode(&value)).unwrap_err();
+            assert!(matches!(err, RouterError::BasicAuthInvalidCredentials { .. }), "{value}");
+            assert!(!err.to_string().contains(&bcrypt_hash));
+            assert!(!err.to_string().contains("hunter2"));
+        }
+
+        assert!(matches!(
+            htpasswd_from_env_var("BASIC_AUTH", "not base64"),
+            Err(RouterError::BasicAuthEnvVarBase64DecodeError { .. })
+        ));
+    }
+}
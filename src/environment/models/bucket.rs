@@ -0,0 +1,444 @@
+use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+use crate::infrastructure::models::object_storage::{
+    BucketCredentials, BucketDeleteStrategy, BucketSettings, ObjectStorage,
+};
+use crate::io_models::bucket::Bucket;
+use crate::io_models::variable_utils::{VariableInfo, VariableInjectionScope};
+use crate::runtime::block_on;
+use base64::engine::general_purpose;
+use base64::Engine;
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{DeleteParams, Patch, PatchParams};
+use kube::Api;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use uuid::Uuid;
+
+// lowest limit of the providers, GCS and S3 both allow 63 characters
+const BUCKET_NAME_MAX_LENGTH: usize = 63;
+const BUCKET_NAME_PREFIX: &str = "qovery";
+const BUCKET_NAME_HASH_LENGTH: usize = 12;
+// credentials are kept in the cluster, the namespace of the environment doesn't exist before its first deployment
+const BUCKET_CREDENTIALS_SECRET_NAMESPACE: &str = "qovery";
+const BUCKET_CREDENTIALS_ACCESS_KEY_ID: &str = "access_key_id";
+const BUCKET_CREDENTIALS_SECRET_ACCESS_KEY: &str = "secret_access_key";
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum BucketError {
+    #[error("Bucket name suffix `{name_suffix}` must contain at least one letter or digit")]
+    InvalidNameSuffix { name_suffix: String },
+    #[error("Bucket name suffix `{name_suffix}` is used by several buckets, their variables would collide")]
+    DuplicatedNameSuffix { name_suffix: String },
+}
+
+/// Bucket created and its credentials, once provisioned in the cluster cloud provider account
+pub struct ProvisionedBucket {
+    pub name: String,
+    pub region: String,
+    pub endpoint: String,
+    pub credentials: BucketCredentials,
+}
+
+impl ProvisionedBucket {
+    /// Variables injected into the dependent services, values are base64 encoded like the ones of the services
+    pub fn variables(&self, bucket: &Bucket) -> BTreeMap<String, VariableInfo> {
+        let prefix = format!("BUCKET_{}", variables_infix(&bucket.name_suffix));
+        [
+            ("NAME", &self.name, false),
+            ("REGION", &self.region, false),
+            ("ENDPOINT", &self.endpoint, false),
+            ("ACCESS_KEY_ID", &self.credentials.access_key_id, true),
+            ("SECRET_ACCESS_KEY", &self.credentials.secret_access_key, true),
+        ]
+        .into_iter()
+        .map(|(key, value, is_secret)| {
+            (
+                format!("{prefix}_{key}"),
+                VariableInfo {
+                    value: general_purpose::STANDARD.encode(value),
+                    is_secret,
+                    // credentials may be needed to fetch assets during the build as well
+                    scope: Some(VariableInjectionScope::Both),
+                },
+            )
+        })
+        .collect()
+    }
+}
+
+fn digest(long_id: &Uuid) -> String {
+    Sha256::digest(long_id.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Bucket names are global to all the accounts of a provider: the name ends with a hash of the bucket id,
+/// so two environments can use the same suffix. Only lowercase letters, digits and dashes are kept,
+/// and `google` is removed as GCS rejects it.
+pub fn bucket_name(bucket: &Bucket) -> String {
+    let hash = &digest(&bucket.long_id)[..BUCKET_NAME_HASH_LENGTH];
+    let sanitized = bucket
+        .name_suffix
+        .to_lowercase()
+        .replace("google", "")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+    let mut suffix = sanitized
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    suffix.truncate(BUCKET_NAME_MAX_LENGTH - BUCKET_NAME_PREFIX.len() - BUCKET_NAME_HASH_LENGTH - 2);
+    let suffix = suffix.trim_end_matches('-');
+
+    match suffix.is_empty() {
+        true => format!("{BUCKET_NAME_PREFIX}-{hash}"),
+        false => format!("{BUCKET_NAME_PREFIX}-{suffix}-{hash}"),
+    }
+}
+
+/// Name of the identity owning the credentials of the bucket (IAM user, IAM application or service account).
+/// GCP service accounts are limited to 30 characters.
+pub fn bucket_credentials_id(bucket: &Bucket) -> String {
+    format!("qbkt-{}", &digest(&bucket.long_id)[..16])
+}
+
+fn variables_infix(name_suffix: &str) -> String {
+    name_suffix
+        .to_uppercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+pub fn validate_buckets(buckets: &[Bucket]) -> Result<(), BucketError> {
+    let mut infixes = HashSet::with_capacity(buckets.len());
+    for bucket in buckets {
+        let infix = variables_infix(&bucket.name_suffix);
+        if infix.is_empty() {
+            return Err(BucketError::InvalidNameSuffix {
+                name_suffix: bucket.name_suffix.clone(),
+            });
+        }
+        if !infixes.insert(infix) {
+            return Err(BucketError::DuplicatedNameSuffix {
+                name_suffix: bucket.name_suffix.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates the bucket if needed and applies its settings. Credentials already created for the bucket are reused,
+/// otherwise new ones are created, so the keys used by running services are not revoked on every deployment.
+pub fn provision_bucket(
+    object_storage: &dyn ObjectStorage,
+    bucket: &Bucket,
+    credentials: Option<BucketCredentials>,
+) -> Result<ProvisionedBucket, ObjectStorageError> {
+    let name = bucket_name(bucket);
+    object_storage.create_bucket(&name, None, bucket.versioning_activated)?;
+    object_storage.apply_bucket_settings(
+        &name,
+        &BucketSettings {
+            versioning_activated: bucket.versioning_activated,
+            block_public_access: bucket.block_public_access,
+            objects_expiration_in_days: bucket.objects_expiration_in_days,
+        },
+    )?;
+    let credentials = match credentials {
+        Some(credentials) => credentials,
+        None => object_storage.create_bucket_credentials(&name, &bucket_credentials_id(bucket))?,
+    };
+
+    Ok(ProvisionedBucket {
+        region: object_storage.region_name(),
+        endpoint: object_storage.endpoint(),
+        name,
+        credentials,
+    })
+}
+
+/// Deletes the credentials of the bucket, then the bucket and its objects unless it is retained
+pub fn delete_bucket(object_storage: &dyn ObjectStorage, bucket: &Bucket) -> Result<(), ObjectStorageError> {
+    let name = bucket_name(bucket);
+    object_storage.delete_bucket_credentials(&name, &bucket_credentials_id(bucket))?;
+    if bucket.retain_on_delete || !object_storage.bucket_exists(&name) {
+        return Ok(());
+    }
+
+    object_storage.delete_bucket(&name, BucketDeleteStrategy::HardDelete)
+}
+
+/// Credentials previously created for the bucket, None when they have never been stored
+pub fn load_bucket_credentials(
+    kube_client: &kube::Client,
+    bucket: &Bucket,
+) -> Result<Option<BucketCredentials>, kube::Error> {
+    let secrets: Api<Secret> = Api::namespaced(kube_client.clone(), BUCKET_CREDENTIALS_SECRET_NAMESPACE);
+    let Some(secret) = block_on(secrets.get_opt(&bucket_credentials_id(bucket)))? else {
+        return Ok(None);
+    };
+
+    let data = secret.data.unwrap_or_default();
+    let value = |key: &str| data.get(key).and_then(|value| String::from_utf8(value.0.clone()).ok());
+    Ok(value(BUCKET_CREDENTIALS_ACCESS_KEY_ID)
+        .zip(value(BUCKET_CREDENTIALS_SECRET_ACCESS_KEY))
+        .map(|(access_key_id, secret_access_key)| BucketCredentials {
+            access_key_id,
+            secret_access_key,
+        }))
+}
+
+pub fn store_bucket_credentials(
+    kube_client: &kube::Client,
+    bucket: &Bucket,
+    credentials: &BucketCredentials,
+) -> Result<(), kube::Error> {
+    let secrets: Api<Secret> = Api::namespaced(kube_client.clone(), BUCKET_CREDENTIALS_SECRET_NAMESPACE);
+    let name = bucket_credentials_id(bucket);
+    let secret = Secret {
+        metadata: ObjectMeta {
+            name: Some(name.clone()),
+            namespace: Some(BUCKET_CREDENTIALS_SECRET_NAMESPACE.to_string()),
+            ..Default::default()
+        },
+        string_data: Some(BTreeMap::from([
+            (BUCKET_CREDENTIALS_ACCESS_KEY_ID.to_string(), credentials.access_key_id.clone()),
+            (
+                BUCKET_CREDENTIALS_SECRET_ACCESS_KEY.to_string(),
+                credentials.secret_access_key.clone(),
+            ),
+        ])),
+        ..Default::default()
+    };
+
+    block_on(secrets.patch(&name, &PatchParams::apply("qovery-engine").force(), &Patch::Apply(secret))).map(|_| ())
+}
+
+pub fn delete_stored_bucket_credentials(kube_client: &kube::Client, bucket: &Bucket) -> Result<(), kube::Error> {
+    let secrets: Api<Secret> = Api::namespaced(kube_client.clone(), BUCKET_CREDENTIALS_SECRET_NAMESPACE);
+    match block_on(secrets.delete(&bucket_credentials_id(bucket), &DeleteParams::default())) {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Injects the variables of the bucket into its dependent services.
+/// A variable defined on the service always wins over the bucket one.
+pub fn inject_bucket_variables<'a>(
+    services: impl Iterator<Item = (&'a Uuid, &'a mut BTreeMap<String, VariableInfo>)>,
+    bucket: &Bucket,
+    variables: &BTreeMap<String, VariableInfo>,
+) {
+    for (long_id, service_variables) in services {
+        if !bucket.dependent_service_long_ids.contains(long_id) {
+            continue;
+        }
+        for (key, variable) in variables {
+            service_variables.entry(key.clone()).or_insert_with(|| variable.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::models::object_storage::test_utils::InMemoryObjectStorage;
+    use std::str::FromStr;
+
+    fn bucket(ix: u8, name_suffix: &str) -> Bucket {
+        Bucket {
+            long_id: Uuid::from_str(&format!("00000000-0000-0000-0000-0000000000{ix:02}")).unwrap(),
+            name_suffix: name_suffix.to_string(),
+            versioning_activated: false,
+            block_public_access: true,
+            objects_expiration_in_days: None,
+            retain_on_delete: false,
+            dependent_service_long_ids: vec![],
+        }
+    }
+
+    fn b64(value: &str) -> String {
+        general_purpose::STANDARD.encode(value)
+    }
+
+    #[test]
+    fn test_bucket_names_are_unique_and_valid_for_every_provider() {
+        let name = bucket_name(&bucket(1, "My Uploads_Bucket"));
+        assert_eq!(name, bucket_name(&bucket(1, "My Uploads_Bucket")));
+        assert!(name.starts_with("qovery-my-uploads-bucket-"));
+        assert_ne!(name, bucket_name(&bucket(2, "My Uploads_Bucket")));
+
+        // GCS rejects `google` in names, every provider rejects names longer than 63 characters
+        assert!(!bucket_name(&bucket(1, "google-assets")).contains("google"));
+        for suffix in ["a".repeat(200), "--".to_string(), format!("{}-b", "a".repeat(42))] {
+            let name = bucket_name(&bucket(1, &suffix));
+            assert!(name.len() <= BUCKET_NAME_MAX_LENGTH, "{name}");
+            assert!(!name.contains("--"), "{name}");
+            assert!(name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'));
+        }
+
+        // GCP service accounts ids are 6 to 30 characters long
+        assert!((6..=30).contains(&bucket_credentials_id(&bucket(1, "uploads")).len()));
+    }
+
+    #[test]
+    fn test_buckets_variables_must_not_collide() {
+        assert!(validate_buckets(&[bucket(1, "uploads"), bucket(2, "assets")]).is_ok());
+        assert_eq!(
+            validate_buckets(&[bucket(1, "my-uploads"), bucket(2, "MY_UPLOADS")]),
+            Err(BucketError::DuplicatedNameSuffix {
+                name_suffix: "MY_UPLOADS".to_string()
+            })
+        );
+        assert_eq!(
+            validate_buckets(&[bucket(1, "--")]),
+            Err(BucketError::InvalidNameSuffix {
+                name_suffix: "--".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_variables_are_injected_into_dependent_services_only() {
+        let mut uploads = bucket(1, "my-uploads");
+        let dependent_id = Uuid::new_v4();
+        let other_id = Uuid::new_v4();
+        uploads.dependent_service_long_ids = vec![dependent_id];
+        let provisioned = ProvisionedBucket {
+            name: bucket_name(&uploads),
+            region: "us-east-2".to_string(),
+            endpoint: "https://s3.us-east-2.amazonaws.com".to_string(),
+            credentials: BucketCredentials {
+                access_key_id: "key".to_string(),
+                secret_access_key: "secret".to_string(),
+            },
+        };
+
+        let user_defined = VariableInfo {
+            value: b64("user-defined"),
+            is_secret: false,
+            scope: None,
+        };
+        let mut dependent = BTreeMap::from([("BUCKET_MY_UPLOADS_ENDPOINT".to_string(), user_defined.clone())]);
+        let mut other = BTreeMap::new();
+        inject_bucket_variables(
+            [(&dependent_id, &mut dependent), (&other_id, &mut other)].into_iter(),
+            &uploads,
+            &provisioned.variables(&uploads),
+        );
+
+        assert!(other.is_empty());
+        assert_eq!(
+            dependent,
+            BTreeMap::from([
+                (
+                    "BUCKET_MY_UPLOADS_NAME".to_string(),
+                    VariableInfo {
+                        value: b64(&provisioned.name),
+                        is_secret: false,
+                        scope: Some(VariableInjectionScope::Both),
+                    }
+                ),
+                (
+                    "BUCKET_MY_UPLOADS_REGION".to_string(),
+                    VariableInfo {
+                        value: b64("us-east-2"),
+                        is_secret: false,
+                        scope: Some(VariableInjectionScope::Both),
+                    }
+                ),
+                // defined on the service, it wins over the bucket one
+                ("BUCKET_MY_UPLOADS_ENDPOINT".to_string(), user_defined),
+                (
+                    "BUCKET_MY_UPLOADS_ACCESS_KEY_ID".to_string(),
+                    VariableInfo {
+                        value: b64("key"),
+                        is_secret: true,
+                        scope: Some(VariableInjectionScope::Both),
+                    }
+                ),
+                (
+                    "BUCKET_MY_UPLOADS_SECRET_ACCESS_KEY".to_string(),
+                    VariableInfo {
+                        value: b64("secret"),
+                        is_secret: true,
+                        scope: Some(VariableInjectionScope::Both),
+                    }
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_existing_credentials_are_reused() {
+        let uploads = bucket(1, "uploads");
+        let name = bucket_name(&uploads);
+        let object_storage = InMemoryObjectStorage::default();
+
+        let existing = BucketCredentials {
+            access_key_id: "existing-key".to_string(),
+            secret_access_key: "existing-secret".to_string(),
+        };
+        let provisioned = provision_bucket(&object_storage, &uploads, Some(existing.clone())).unwrap();
+        assert_eq!(provisioned.credentials, existing);
+        assert_eq!(
+            object_storage.calls(),
+            vec![format!("create_bucket {name}"), format!("apply_bucket_settings {name}")]
+        );
+
+        let credentials_id = bucket_credentials_id(&uploads);
+        let provisioned = provision_bucket(&object_storage, &uploads, None).unwrap();
+        assert_eq!(
+            provisioned.credentials.access_key_id,
+            format!("AKIA{}", credentials_id.to_uppercase())
+        );
+        assert_eq!(
+            object_storage.calls().last(),
+            Some(&format!("create_bucket_credentials {name} {credentials_id}"))
+        );
+    }
+
+    #[test]
+    fn test_deletion_deletes_credentials_and_bucket_unless_retained() {
+        let mut uploads = bucket(1, "uploads");
+        let name = bucket_name(&uploads);
+        let credentials_id = bucket_credentials_id(&uploads);
+
+        let object_storage = InMemoryObjectStorage::with_buckets(&[&name]);
+        delete_bucket(&object_storage, &uploads).unwrap();
+        assert_eq!(
+            object_storage.calls(),
+            vec![
+                format!("delete_bucket_credentials {name} {credentials_id}"),
+                format!("delete_bucket {name}"),
+            ]
+        );
+
+        uploads.retain_on_delete = true;
+        let object_storage = InMemoryObjectStorage::with_buckets(&[&name]);
+        delete_bucket(&object_storage, &uploads).unwrap();
+        assert_eq!(
+            object_storage.calls(),
+            vec![format!("delete_bucket_credentials {name} {credentials_id}")]
+        );
+
+        // already deleted, e.g. when the environment deletion is retried
+        uploads.retain_on_delete = false;
+        let object_storage = InMemoryObjectStorage::default();
+        delete_bucket(&object_storage, &uploads).unwrap();
+        assert_eq!(
+            object_storage.calls(),
+            vec![format!("delete_bucket_credentials {name} {credentials_id}")]
+        );
+    }
+}
@@ -9,15 +9,20 @@ use k8s_openapi::api::core::v1::PersistentVolumeClaim;
 use serde::Serialize;
 use uuid::Uuid;
 
+use crate::environment::action::database_credentials::{service_database_credentials, DatabaseCredentialsTeraContext};
 use crate::environment::action::DeploymentAction;
 use crate::environment::models::annotations_group::AnnotationsGroupTeraContext;
+use crate::environment::models::autoscaling::{check_metrics_adapter, hpa_metrics, validate_hpa_metrics};
+use crate::environment::models::graceful_shutdown::{validate_graceful_shutdown, GracefulShutdownTeraContext};
+use crate::environment::models::helm_release::helm_release_name;
 use crate::environment::models::labels_group::LabelsGroupTeraContext;
 use crate::environment::models::probe::Probe;
 use crate::environment::models::registry_image_source::RegistryImageSource;
+use crate::environment::models::service_port::{validate_service_ports, ServicePort};
 use crate::environment::models::types::{CloudProvider, ToTeraContext};
 use crate::environment::models::utils;
 use crate::errors::EngineError;
-use crate::events::{EventDetails, Stage, Transmitter};
+use crate::events::{EnvironmentStep, EventDetails, Stage, Transmitter};
 use crate::infrastructure::models::build_platform::Build;
 use crate::infrastructure::models::cloud_provider::io::RegistryMirroringMode;
 use crate::infrastructure::models::cloud_provider::service::{
@@ -26,9 +31,9 @@ use crate::infrastructure::models::cloud_provider::service::{
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
 use crate::infrastructure::models::kubernetes::Kubernetes;
 use crate::io_models::annotations_group::AnnotationsGroup;
+use crate::io_models::application::Protocol;
 use crate::io_models::application::Protocol::{TCP, UDP};
-use crate::io_models::application::{Port, Protocol};
-use crate::io_models::container::{ContainerAdvancedSettings, Registry};
+use crate::io_models::container::{ContainerAdvancedSettings, HpaMetric, Registry};
 use crate::io_models::context::Context;
 use crate::io_models::labels_group::LabelsGroup;
 use crate::io_models::models::{
@@ -64,7 +69,7 @@ pub struct Container<T: CloudProvider> {
     pub(crate) min_instances: u32,
     pub(crate) max_instances: u32,
     pub(crate) public_domain: String,
-    pub(crate) ports: Vec<Port>,
+    pub(crate) ports: Vec<ServicePort>,
     pub(crate) storages: Vec<Storage>,
     pub(crate) environment_variables: Vec<EnvironmentVariable>,
     pub(crate) mounted_files: BTreeSet<MountedFile>,
@@ -90,12 +95,12 @@ pub fn get_mirror_repository_name(
 }
 
 pub fn to_public_l4_ports<'a>(
-    ports: impl Iterator<Item = &'a Port>,
+    ports: impl Iterator<Item = &'a ServicePort>,
     protocol: Protocol,
     public_domain: &str,
 ) -> Option<PublicL4Ports> {
-    let ports: Vec<Port> = ports
-        .filter(|p| p.publicly_accessible && p.protocol == protocol)
+    let ports: Vec<ServicePort> = ports
+        .filter(|p| p.is_public() && p.protocol == protocol)
         .cloned()
         .collect();
     if ports.is_empty() {
@@ -127,7 +132,7 @@ impl<T: CloudProvider> Container<T> {
         min_instances: u32,
         max_instances: u32,
         public_domain: String,
-        ports: Vec<Port>,
+        ports: Vec<ServicePort>,
         storages: Vec<Storage>,
         environment_variables: Vec<EnvironmentVariable>,
         mounted_files: BTreeSet<MountedFile>,
@@ -151,6 +156,10 @@ impl<T: CloudProvider> Container<T> {
             ));
         }
 
+        validate_service_ports(&ports).map_err(|err| ContainerError::InvalidConfig(err.to_string()))?;
+        validate_graceful_shutdown(&advanced_settings).map_err(|err| ContainerError::InvalidConfig(err.to_string()))?;
+        validate_hpa_metrics(&advanced_settings).map_err(|err| ContainerError::InvalidConfig(err.to_string()))?;
+
         let workspace_directory = crate::fs::workspace_directory(
             context.workspace_root_dir(),
             context.execution_id(),
@@ -198,7 +207,7 @@ impl<T: CloudProvider> Container<T> {
     }
 
     pub fn helm_release_name(&self) -> String {
-        format!("container-{}", self.long_id)
+        helm_release_name("container", &self.long_id)
     }
 
     pub fn helm_chart_dir(&self) -> String {
@@ -209,12 +218,20 @@ impl<T: CloudProvider> Container<T> {
         &self.source.registry
     }
 
-    fn public_ports(&self) -> impl Iterator<Item = &Port> + '_ {
-        self.ports.iter().filter(|port| port.publicly_accessible)
+    fn public_ports(&self) -> impl Iterator<Item = &ServicePort> + '_ {
+        self.ports.iter().filter(|port| port.is_public())
     }
 
-    pub(crate) fn default_tera_context(&self, target: &DeploymentTarget) -> ContainerTeraContext {
+    pub(crate) fn default_tera_context(
+        &self,
+        target: &DeploymentTarget,
+    ) -> Result<ContainerTeraContext, Box<EngineError>> {
         let environment = target.environment;
+        let (environment_variables, database_credentials) = service_database_credentials(
+            target,
+            &self.environment_variables,
+            &self.get_event_details(Stage::Environment(EnvironmentStep::Deploy)),
+        )?;
         let kubernetes = target.kubernetes;
         let mut deployment_affinity_node_required = utils::add_arch_to_deployment_affinity_node(
             &self.advanced_settings.deployment_affinity_node_required,
@@ -245,7 +262,11 @@ impl<T: CloudProvider> Container<T> {
                 &target.kubernetes.advanced_settings().registry_mirroring_mode,
                 target.container_registry.registry_info(),
             );
-        let image_full = format!("{}/{}:{}", repository, image_name, image_tag);
+        // a promoted image is pinned to its digest, so the exact same image as the source environment is deployed
+        let image_full = match self.source.pinned_digest() {
+            Some(digest) => format!("{}/{}@{}", repository, image_name, digest),
+            None => format!("{}/{}:{}", repository, image_name, image_tag),
+        };
 
         let ctx = ContainerTeraContext {
             organization_long_id: environment.organization_long_id,
@@ -263,6 +284,8 @@ impl<T: CloudProvider> Container<T> {
                 // FIXME: We mirror images to cluster private registry
                 image_full,
                 image_tag,
+                promoted_from_execution_id: self.source.promotion.as_ref().map(|p| p.source_execution_id.clone()),
+                promoted_image_digest: self.source.pinned_digest().map(str::to_string),
                 version: self.service_version(),
                 command_args: self.command_args.clone(),
                 entrypoint: self.entrypoint.clone(),
@@ -292,7 +315,10 @@ impl<T: CloudProvider> Container<T> {
                         id: s.id.clone(),
                         long_id: s.long_id,
                         name: s.name.clone(),
-                        storage_type: s.storage_class.0.clone(),
+                        storage_type: kubernetes
+                            .default_storage_class()
+                            .unwrap_or(s.storage_class.0.as_str())
+                            .to_string(),
                         size_in_gib: s.size_in_gib,
                         mount_point: s.mount_point.clone(),
                         snapshot_retention_in_days: s.snapshot_retention_in_days,
@@ -300,6 +326,8 @@ impl<T: CloudProvider> Container<T> {
                     .collect(),
                 readiness_probe: self.readiness_probe.clone(),
                 liveness_probe: self.liveness_probe.clone(),
+                graceful_shutdown: GracefulShutdownTeraContext::new(&advanced_settings, !self.ports.is_empty()),
+                hpa_metrics: hpa_metrics(&advanced_settings),
                 advanced_settings,
                 legacy_deployment_matchlabels: false,
                 legacy_volumeclaim_template: false,
@@ -313,7 +341,8 @@ impl<T: CloudProvider> Container<T> {
                     secret_name: format!("{}-registry", self.kube_name()),
                     docker_json_config: Some(docker_json.to_string()),
                 }),
-            environment_variables: self.environment_variables.clone(),
+            environment_variables,
+            database_credentials,
             mounted_files: self.mounted_files.clone().into_iter().collect::<Vec<_>>(),
             resource_expiration_in_seconds: Some(kubernetes.advanced_settings().pleco_resources_ttl),
             loadbalancer_l4_annotations: kubernetes.loadbalancer_l4_annotations(Some(self.kube_name())),
@@ -321,7 +350,7 @@ impl<T: CloudProvider> Container<T> {
             labels_group: self.labels_group.clone(),
         };
 
-        ctx
+        Ok(ctx)
     }
 
     pub fn is_stateful(&self) -> bool {
@@ -424,9 +453,11 @@ impl<T: CloudProvider> Service for Container<T> {
 }
 
 pub trait ContainerService: Service + DeploymentAction + ToTeraContext + Send {
-    fn public_ports(&self) -> Vec<&Port>;
+    fn public_ports(&self) -> Vec<&ServicePort>;
     fn advanced_settings(&self) -> &ContainerAdvancedSettings;
     fn image_full(&self) -> String;
+    /// Image copied to the cluster registry before deploying the service, none when it is promoted
+    fn image_to_mirror(&self) -> Option<&RegistryImageSource>;
     fn startup_timeout(&self) -> Duration;
     fn as_deployment_action(&self) -> &dyn DeploymentAction;
 }
@@ -435,7 +466,24 @@ use tera::Context as TeraContext;
 
 impl<T: CloudProvider> ToTeraContext for Container<T> {
     fn to_tera_context(&self, target: &DeploymentTarget) -> Result<TeraContext, Box<EngineError>> {
-        let context = self.default_tera_context(target);
+        target
+            .capabilities
+            .check_probes(self.readiness_probe.iter().chain(self.liveness_probe.iter()))
+            .map_err(|err| {
+                EngineError::new_k8s_unsupported_feature(
+                    self.get_event_details(Stage::Environment(EnvironmentStep::Deploy)),
+                    self.name(),
+                    err.to_string(),
+                )
+            })?;
+        check_metrics_adapter(
+            &self.advanced_settings,
+            target,
+            self.get_event_details(Stage::Environment(EnvironmentStep::Deploy)),
+            self.name(),
+        )?;
+
+        let context = self.default_tera_context(target)?;
         Ok(TeraContext::from_serialize(context).unwrap_or_default())
     }
 }
@@ -443,7 +491,7 @@ impl<T: CloudProvider> ContainerService for Container<T>
 where
     Container<T>: Service + ToTeraContext + DeploymentAction,
 {
-    fn public_ports(&self) -> Vec<&Port> {
+    fn public_ports(&self) -> Vec<&ServicePort> {
         self.public_ports().collect_vec()
     }
 
@@ -460,6 +508,13 @@ where
         )
     }
 
+    fn image_to_mirror(&self) -> Option<&RegistryImageSource> {
+        match self.source.promotion {
+            Some(_) => None,
+            None => Some(&self.source),
+        }
+    }
+
     fn startup_timeout(&self) -> Duration {
         let readiness_probe_timeout = if let Some(p) = &self.readiness_probe {
             p.initial_delay_seconds + ((p.timeout_seconds + p.period_seconds) * p.failure_threshold)
@@ -505,7 +560,7 @@ impl From<&dyn Kubernetes> for ClusterTeraContext {
 #[derive(Serialize, Debug, Clone)]
 pub struct PublicL4Ports {
     pub protocol: Protocol,
-    pub ports: Vec<Port>,
+    pub ports: Vec<ServicePort>,
     pub hostnames: Vec<String>,
 }
 
@@ -518,6 +573,8 @@ pub(crate) struct ServiceTeraContext {
     pub(crate) user_unsafe_name: String,
     pub(crate) image_full: String,
     pub(crate) image_tag: String,
+    pub(crate) promoted_from_execution_id: Option<String>,
+    pub(crate) promoted_image_digest: Option<String>,
     pub(crate) version: String,
     pub(crate) command_args: Vec<String>,
     pub(crate) entrypoint: Option<String>,
@@ -528,13 +585,15 @@ pub(crate) struct ServiceTeraContext {
     pub(crate) min_instances: u32,
     pub(crate) max_instances: u32,
     pub(crate) public_domain: String,
-    pub(crate) ports: Vec<Port>,
+    pub(crate) ports: Vec<ServicePort>,
     pub(crate) ports_layer4_public: Vec<PublicL4Ports>,
-    pub(crate) default_port: Option<Port>,
+    pub(crate) default_port: Option<ServicePort>,
     pub(crate) storages: Vec<StorageDataTemplate>,
     pub(crate) readiness_probe: Option<Probe>,
     pub(crate) liveness_probe: Option<Probe>,
     pub(crate) advanced_settings: ContainerAdvancedSettings,
+    pub(crate) graceful_shutdown: GracefulShutdownTeraContext,
+    pub(crate) hpa_metrics: Vec<HpaMetric>,
     pub(crate) legacy_deployment_matchlabels: bool,
     pub(crate) legacy_volumeclaim_template: bool,
     pub(crate) legacy_deployment_from_scaleway: bool,
@@ -558,6 +617,7 @@ pub(crate) struct ContainerTeraContext {
     pub(crate) service: ServiceTeraContext,
     pub(crate) registry: Option<RegistryTeraContext>,
     pub(crate) environment_variables: Vec<EnvironmentVariable>,
+    pub(crate) database_credentials: DatabaseCredentialsTeraContext,
     pub(crate) mounted_files: Vec<MountedFile>,
     pub(crate) resource_expiration_in_seconds: Option<i32>,
     pub(crate) loadbalancer_l4_annotations: Vec<(String, String)>,
@@ -634,14 +694,10 @@ pub fn get_container_with_invalid_storage_size<T: CloudProvider>(
                                 }
 
                                 if storage.size_in_gib < size {
-                                    return Err(Box::new(EngineError::new_invalid_engine_payload(
+                                    return Err(Box::new(EngineError::new_k8s_storage_cannot_be_reduced(
                                         event_details.clone(),
-                                        format!(
-                                            "new storage size ({}) should be equal or greater than actual size ({})",
-                                            storage.size_in_gib, size
-                                        )
-                                        .as_str(),
-                                        None,
+                                        storage.size_in_gib,
+                                        size,
                                     )));
                                 }
                             }
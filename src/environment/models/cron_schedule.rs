@@ -0,0 +1,538 @@
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+// a schedule that does not match within this window never matches (e.g. `0 0 30 2 *`)
+const MAX_YEARS_TO_LOOK_AHEAD: i32 = 8;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_OF_WEEK_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+const DAY_OF_WEEK_LABELS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid cron expression `{expression}` at position {position}: {message}")]
+pub struct CronScheduleError {
+    pub expression: String,
+    /// 1-based character position of the offending part of the expression
+    pub position: usize,
+    pub message: String,
+}
+
+#[derive(Clone, Copy)]
+struct FieldSpec {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+}
+
+const MINUTE: FieldSpec = FieldSpec {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+};
+const HOUR: FieldSpec = FieldSpec {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+};
+const DAY_OF_MONTH: FieldSpec = FieldSpec {
+    name: "day of month",
+    min: 1,
+    max: 31,
+    names: &[],
+};
+const MONTH: FieldSpec = FieldSpec {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: &MONTH_NAMES,
+};
+const DAY_OF_WEEK: FieldSpec = FieldSpec {
+    name: "day of week",
+    min: 0,
+    max: 6,
+    names: &DAY_OF_WEEK_NAMES,
+};
+const SECOND: FieldSpec = FieldSpec {
+    name: "second",
+    min: 0,
+    max: 59,
+    names: &[],
+};
+
+/// A cron expression in the canonical 5-field form accepted by Kubernetes CronJobs.
+///
+/// Parsing accepts:
+/// * the standard 5 fields `minute hour day-of-month month day-of-week`
+/// * 6 fields with leading seconds, as long as seconds are `0`, as Kubernetes has no sub-minute precision
+/// * `?` in place of `*`, it is normalized to `*`
+/// * the `@yearly`, `@annually`, `@monthly`, `@weekly`, `@daily`, `@midnight` and `@hourly` descriptors
+///
+/// The timezone is not part of the expression, it is attached when computing occurrences or descriptions.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn new(expression: &str) -> Result<CronSchedule, CronScheduleError> {
+        let error = |position: usize, message: String| CronScheduleError {
+            expression: expression.to_string(),
+            position,
+            message,
+        };
+
+        let trimmed = expression.trim_start();
+        let offset = expression.chars().count() - trimmed.chars().count();
+        if trimmed.is_empty() {
+            return Err(error(1, "expression is empty".to_string()));
+        }
+        if trimmed.starts_with("CRON_TZ=") || trimmed.starts_with("TZ=") {
+            return Err(error(
+                offset + 1,
+                "timezone must be set with the timezone field, not inside the expression".to_string(),
+            ));
+        }
+
+        let mut fields: Vec<(usize, &str)> = split_fields(expression);
+        if let [(position, descriptor)] = fields.as_slice() {
+            if descriptor.starts_with('@') {
+                let canonical = match *descriptor {
+                    "@yearly" | "@annually" => "0 0 1 1 *",
+                    "@monthly" => "0 0 1 * *",
+                    "@weekly" => "0 0 * * 0",
+                    "@daily" | "@midnight" => "0 0 * * *",
+                    "@hourly" => "0 * * * *",
+                    _ => return Err(error(*position, format!("unknown descriptor `{descriptor}`"))),
+                };
+                return CronSchedule::new(canonical);
+            }
+        }
+
+        match fields.len() {
+            5 => {}
+            6 => {
+                let (position, seconds) = fields.remove(0);
+                let seconds_set = parse_field(seconds, position, SECOND).map_err(|(p, m)| error(p, m))?;
+                if seconds_set != 1 {
+                    return Err(error(
+                        position,
+                        "Kubernetes CronJobs run at minute precision, seconds must be `0`".to_string(),
+                    ));
+                }
+            }
+            count => {
+                return Err(error(
+                    fields.get(5).map(|(position, _)| *position).unwrap_or(offset + 1),
+                    format!("expected 5 fields (or 6 with seconds) but got {count}"),
+                ));
+            }
+        }
+
+        // `?` is an alias of `*`, as in the Kubernetes cron parser
+        for (_, field) in fields.iter_mut() {
+            if *field == "?" {
+                *field = "*";
+            }
+        }
+
+        let mut sets = [0u64; 5];
+        for (set, ((position, field), spec)) in
+            sets.iter_mut()
+                .zip(fields.iter().zip([MINUTE, HOUR, DAY_OF_MONTH, MONTH, DAY_OF_WEEK]))
+        {
+            *set = parse_field(field, *position, spec).map_err(|(p, m)| error(p, m))?;
+        }
+
+        Ok(CronSchedule {
+            expression: fields.iter().map(|(_, field)| *field).join(" "),
+            minutes: sets[0],
+            hours: sets[1],
+            days_of_month: sets[2],
+            months: sets[3],
+            days_of_week: sets[4],
+            day_of_month_restricted: !fields[2].1.starts_with('*'),
+            day_of_week_restricted: !fields[4].1.starts_with('*'),
+        })
+    }
+
+    /// Canonical 5-field expression, as rendered into the CronJob manifest
+    pub fn as_str(&self) -> &str {
+        &self.expression
+    }
+
+    /// Next time the schedule fires strictly after `after`, evaluated in `timezone`.
+    ///
+    /// Local times skipped by a DST transition never fire, and local times repeated by a DST transition fire once,
+    /// on their first occurrence.
+    pub fn next_occurrence(&self, after: DateTime<Utc>, timezone: &Tz) -> Option<DateTime<Utc>> {
+        let local_after = after.with_timezone(timezone).naive_local();
+        let mut current = local_after
+            .date()
+            .and_hms_opt(local_after.hour(), local_after.minute(), 0)?;
+        let last_year = current.year() + MAX_YEARS_TO_LOOK_AHEAD;
+
+        while current.year() <= last_year {
+            if !contains(self.months, current.month()) {
+                let (year, month) = match current.month() {
+                    12 => (current.year() + 1, 1),
+                    month => (current.year(), month + 1),
+                };
+                current = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.matches_day(current.date()) {
+                current = (current.date() + Duration::days(1)).and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !contains(self.hours, current.hour()) {
+                current = current.date().and_hms_opt(current.hour(), 0, 0)? + Duration::hours(1);
+                continue;
+            }
+            if contains(self.minutes, current.minute()) {
+                let first_occurrence = match timezone.from_local_datetime(&current) {
+                    LocalResult::Single(occurrence) => Some(occurrence),
+                    LocalResult::Ambiguous(first, _) => Some(first),
+                    LocalResult::None => None,
+                };
+                if let Some(occurrence) = first_occurrence.filter(|occurrence| *occurrence > after) {
+                    return Some(occurrence.with_timezone(&Utc));
+                }
+            }
+            current += Duration::minutes(1);
+        }
+
+        None
+    }
+
+    /// Human-readable description used in events, e.g. `every day at 02:00 UTC`
+    pub fn describe(&self, timezone: &Tz) -> String {
+        let fields: Vec<&str> = self.expression.split(' ').collect();
+        let single_value = |set: u64| match set.count_ones() {
+            1 => Some(set.trailing_zeros()),
+            _ => None,
+        };
+        let every_day = !self.day_of_month_restricted && !self.day_of_week_restricted && fields[3] == "*";
+
+        match (single_value(self.minutes), single_value(self.hours)) {
+            _ if fields.iter().all(|field| *field == "*") => "every minute".to_string(),
+            _ if fields[1..].iter().all(|field| *field == "*") && fields[0].starts_with("*/") => {
+                format!("every {} minutes", &fields[0][2..])
+            }
+            (Some(minute), _) if fields[1..].iter().all(|field| *field == "*") => {
+                format!("every hour at minute {minute}")
+            }
+            (Some(minute), Some(hour)) if every_day => {
+                format!("every day at {hour:02}:{minute:02} {}", timezone.name())
+            }
+            (Some(minute), Some(hour)) if !self.day_of_month_restricted && fields[3] == "*" => format!(
+                "every {} at {hour:02}:{minute:02} {}",
+                (0..7)
+                    .filter(|day| contains(self.days_of_week, *day))
+                    .map(|day| DAY_OF_WEEK_LABELS[day as usize])
+                    .join(", "),
+                timezone.name()
+            ),
+            (Some(minute), Some(hour)) if !self.day_of_week_restricted && fields[3] == "*" => {
+                match single_value(self.days_of_month) {
+                    Some(day) => format!("on day {day} of every month at {hour:02}:{minute:02} {}", timezone.name()),
+                    None => format!("`{}` {}", self.expression, timezone.name()),
+                }
+            }
+            _ => format!("`{}` {}", self.expression, timezone.name()),
+        }
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = contains(self.days_of_month, date.day());
+        let day_of_week = contains(self.days_of_week, date.weekday().num_days_from_sunday());
+
+        // when both day fields are restricted, cron fires if either of them matches
+        if self.day_of_month_restricted && self.day_of_week_restricted {
+            day_of_month || day_of_week
+        } else {
+            day_of_month && day_of_week
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = CronScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CronSchedule::new(s)
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = CronScheduleError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        CronSchedule::new(&value)
+    }
+}
+
+impl From<CronSchedule> for String {
+    fn from(schedule: CronSchedule) -> Self {
+        schedule.expression
+    }
+}
+
+impl Display for CronSchedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+fn contains(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Whitespace separated fields along with their 1-based character position
+fn split_fields(expression: &str) -> Vec<(usize, &str)> {
+    let mut fields = vec![];
+    let mut start: Option<(usize, usize)> = None;
+    for (position, (index, c)) in expression.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (true, Some((field_position, field_index))) => {
+                fields.push((field_position + 1, &expression[field_index..index]));
+                start = None;
+            }
+            (false, None) => start = Some((position, index)),
+            _ => {}
+        }
+    }
+    if let Some((field_position, field_index)) = start {
+        fields.push((field_position + 1, &expression[field_index..]));
+    }
+
+    fields
+}
+
+/// Parses a comma separated list of `*`, `value`, `start-end`, each optionally followed by `/step`
+fn parse_field(field: &str, position: usize, spec: FieldSpec) -> Result<u64, (usize, String)> {
+    let mut set = 0u64;
+    let mut item_position = position;
+
+    for item in field.split(',') {
+        let error = |message: String| (item_position, format!("{} `{item}`: {message}", spec.name));
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(error(format!("step `{step}` must be a positive number"))),
+            },
+            None => (item, None),
+        };
+
+        let (start, end) = match range {
+            "*" => (spec.min, spec.max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => {
+                    (parse_value(start, spec).map_err(error)?, parse_value(end, spec).map_err(error)?)
+                }
+                // `5/15` is a shorthand for `5-max/15`
+                None if step.is_some() => (parse_value(range, spec).map_err(error)?, spec.max),
+                None => {
+                    let value = parse_value(range, spec).map_err(error)?;
+                    (value, value)
+                }
+            },
+        };
+        if start > end {
+            return Err(error(format!("range start {start} is greater than range end {end}")));
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            set |= 1 << value;
+        }
+        item_position += item.chars().count() + 1;
+    }
+
+    Ok(set)
+}
+
+fn parse_value(value: &str, spec: FieldSpec) -> Result<u32, String> {
+    if let Some(index) = spec.names.iter().position(|name| name.eq_ignore_ascii_case(value)) {
+        return Ok(spec.min + index as u32);
+    }
+
+    match value.parse::<u32>() {
+        Ok(value) if (spec.min..=spec.max).contains(&value) => Ok(value),
+        Ok(value) => Err(format!("{value} is out of range {}-{}", spec.min, spec.max)),
+        Err(_) => Err(format!("`{value}` is not a valid value")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(
+            &NaiveDate::from_ymd_opt(year, month, day)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_parse_and_normalize() {
+        let test_cases = vec![
+            ("*/10 * * * *", "*/10 * * * *"),
+            ("  0   2 * *   mon-fri ", "0 2 * * mon-fri"),
+            ("0 30 2 * * *", "30 2 * * *"),
+            ("00 0 0 1 jan *", "0 0 1 jan *"),
+            ("0 9 ? * sun", "0 9 * * sun"),
+            ("@daily", "0 0 * * *"),
+            ("@midnight", "0 0 * * *"),
+            ("@hourly", "0 * * * *"),
+            ("@weekly", "0 0 * * 0"),
+            ("@monthly", "0 0 1 * *"),
+            ("@yearly", "0 0 1 1 *"),
+            ("@annually", "0 0 1 1 *"),
+        ];
+
+        for (expression, expected) in test_cases {
+            let schedule = CronSchedule::from_str(expression).unwrap_or_else(|e| panic!("{expression}: {e}"));
+            assert_eq!(schedule.as_str(), expected, "{expression}");
+            // normalized form parses to the same schedule
+            assert_eq!(CronSchedule::from_str(schedule.as_str()).unwrap(), schedule);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors_report_position() {
+        let test_cases = vec![
+            ("", 1),
+            ("* * * *", 1),
+            ("* * * * * * *", 11),
+            ("60 * * * *", 1),
+            ("* 24 * * *", 3),
+            ("* * 0 * *", 5),
+            ("* * * 13 *", 7),
+            ("* * * * 7", 9),
+            ("0 0 * * mon,funday", 13),
+            ("*/0 * * * *", 1),
+            ("5-1 * * * *", 1),
+            ("30 0 2 * * *", 1),
+            ("@reboot", 1),
+            ("CRON_TZ=UTC 0 2 * * *", 1),
+            ("my_schedule", 1),
+        ];
+
+        for (expression, position) in test_cases {
+            let error = CronSchedule::from_str(expression).expect_err(expression);
+            assert_eq!(error.position, position, "{expression}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_deserialize_rejects_invalid_expression() {
+        let schedule: CronSchedule = serde_json::from_str(r#""0 0 2 * * *""#).unwrap();
+        assert_eq!(serde_json::to_string(&schedule).unwrap(), r#""0 2 * * *""#);
+
+        let error = serde_json::from_str::<CronSchedule>(r#""0 25 * * *""#).unwrap_err();
+        assert!(error.to_string().contains("at position 3"), "{error}");
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        let test_cases = vec![
+            ("*/15 * * * *", utc(2024, 1, 1, 10, 7), utc(2024, 1, 1, 10, 15)),
+            ("*/15 * * * *", utc(2024, 1, 1, 10, 15), utc(2024, 1, 1, 10, 30)),
+            ("0 2 * * *", utc(2024, 1, 1, 2, 0), utc(2024, 1, 2, 2, 0)),
+            ("0 0 1 * *", utc(2024, 12, 15, 0, 0), utc(2025, 1, 1, 0, 0)),
+            ("0 0 29 2 *", utc(2024, 3, 1, 0, 0), utc(2028, 2, 29, 0, 0)),
+            // day of month and day of week are OR-ed when both are restricted
+            ("0 0 13 * fri", utc(2024, 9, 1, 0, 0), utc(2024, 9, 6, 0, 0)),
+            ("0 0 * * fri", utc(2024, 9, 1, 0, 0), utc(2024, 9, 6, 0, 0)),
+            ("0 0 13 * *", utc(2024, 9, 1, 0, 0), utc(2024, 9, 13, 0, 0)),
+        ];
+
+        for (expression, after, expected) in test_cases {
+            let schedule = CronSchedule::from_str(expression).unwrap();
+            assert_eq!(schedule.next_occurrence(after, &Tz::UTC), Some(expected), "{expression}");
+        }
+
+        assert_eq!(
+            CronSchedule::from_str("0 0 30 2 *")
+                .unwrap()
+                .next_occurrence(utc(2024, 1, 1, 0, 0), &Tz::UTC),
+            None
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_across_dst_transitions() {
+        let paris = Tz::Europe__Paris;
+
+        // winter: 02:00 Paris is 01:00 UTC
+        let schedule = CronSchedule::from_str("0 2 * * *").unwrap();
+        assert_eq!(
+            schedule.next_occurrence(utc(2024, 3, 29, 12, 0), &paris),
+            Some(utc(2024, 3, 30, 1, 0))
+        );
+        // 2024-03-31 02:00 does not exist in Paris, this day is skipped
+        assert_eq!(
+            schedule.next_occurrence(utc(2024, 3, 30, 12, 0), &paris),
+            Some(utc(2024, 4, 1, 0, 0))
+        );
+
+        // 2024-10-27 02:30 happens twice in Paris, it only fires on the first one
+        let schedule = CronSchedule::from_str("30 2 * * *").unwrap();
+        let first = schedule.next_occurrence(utc(2024, 10, 26, 12, 0), &paris);
+        assert_eq!(first, Some(utc(2024, 10, 27, 0, 30)));
+        assert_eq!(schedule.next_occurrence(first.unwrap(), &paris), Some(utc(2024, 10, 28, 1, 30)));
+
+        // every local time fires once, the repeated hour does not run twice
+        let schedule = CronSchedule::from_str("*/30 * * * *").unwrap();
+        assert_eq!(
+            schedule.next_occurrence(utc(2024, 10, 27, 0, 30), &paris),
+            Some(utc(2024, 10, 27, 2, 0))
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        let test_cases = vec![
+            ("* * * * *", Tz::UTC, "every minute"),
+            ("*/10 * * * *", Tz::UTC, "every 10 minutes"),
+            ("15 * * * *", Tz::UTC, "every hour at minute 15"),
+            ("0 2 * * *", Tz::UTC, "every day at 02:00 UTC"),
+            ("0 0 2 * * *", Tz::Europe__Paris, "every day at 02:00 Europe/Paris"),
+            ("30 8 * * mon,wed", Tz::UTC, "every Monday, Wednesday at 08:30 UTC"),
+            ("0 6 1 * *", Tz::UTC, "on day 1 of every month at 06:00 UTC"),
+            ("0 6 1 jan *", Tz::UTC, "`0 6 1 jan *` UTC"),
+        ];
+
+        for (expression, timezone, expected) in test_cases {
+            assert_eq!(CronSchedule::from_str(expression).unwrap().describe(&timezone), expected);
+        }
+    }
+}
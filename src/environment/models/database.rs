@@ -1,9 +1,12 @@
+use crate::environment::action::database_credentials::admin_password_secret_key;
 use crate::environment::action::DeploymentAction;
 use crate::environment::models::annotations_group::AnnotationsGroupTeraContext;
+use crate::environment::models::database_health::{health_snapshot_config_map_name, HEALTH_SNAPSHOT_KUBECTL_IMAGE};
 use crate::environment::models::database_utils::{
     is_allowed_containered_mongodb_version, is_allowed_containered_mysql_version,
     is_allowed_containered_postgres_version, is_allowed_containered_redis_version,
 };
+use crate::environment::models::helm_release::helm_release_name;
 use crate::environment::models::labels_group::LabelsGroupTeraContext;
 use crate::environment::models::types::{CloudProvider, ToTeraContext, VersionsNumber};
 use crate::environment::models::utils;
@@ -127,6 +130,13 @@ pub enum DatabaseError {
         database_version: Arc<str>,
     },
 
+    #[error("Version of database for {database_type:?} cannot be upgraded from `{from}` to `{to}`")]
+    UnsupportedDatabaseVersionUpgrade {
+        database_type: service::DatabaseType,
+        from: Arc<str>,
+        to: Arc<str>,
+    },
+
     #[error("Database instance type `{requested_database_instance_type}` is invalid for cloud provider `{database_cloud_provider}`.")]
     InvalidDatabaseInstance {
         requested_database_instance_type: String,
@@ -282,6 +292,11 @@ impl<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>> Database<C, M, T>
         self.workspace_directory.to_str().unwrap_or("")
     }
 
+    /// Secret holding the login and password of the database, the only place the password is stored in the cluster
+    pub fn credentials_secret_name(&self) -> String {
+        format!("{}-credentials", self.kube_name)
+    }
+
     pub(crate) fn fqdn(&self, target: &DeploymentTarget, fqdn: &str) -> String {
         match &self.publicly_accessible {
             true => fqdn.to_string(),
@@ -374,6 +389,11 @@ impl<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>> Service for Datab
 // Method Only For all container database
 impl<C: CloudProvider, T: DatabaseType<C, Container>> Database<C, Container, T> {
     pub fn helm_release_name(&self) -> String {
+        helm_release_name(T::lib_directory_name(), &self.long_id)
+    }
+
+    /// Release name used before release names were generated from the service long id
+    pub fn legacy_helm_release_name(&self) -> String {
         format!("{}-{}", T::lib_directory_name(), self.id)
     }
 
@@ -390,6 +410,22 @@ impl<C: CloudProvider, T: DatabaseType<C, Container>> Database<C, Container, T>
         )
     }
 
+    pub fn health_snapshot_helm_release_name(&self) -> String {
+        helm_release_name(&format!("{}-health-snapshot", T::lib_directory_name()), &self.long_id)
+    }
+
+    pub fn legacy_health_snapshot_helm_release_name(&self) -> String {
+        format!("{}-health-snapshot", self.legacy_helm_release_name())
+    }
+
+    pub fn health_snapshot_helm_chart_dir(&self) -> String {
+        format!("{}/common/charts/q-database-health-snapshot", self.lib_root_directory)
+    }
+
+    pub fn health_snapshot_config_map_name(&self) -> String {
+        health_snapshot_config_map_name(&self.kube_name)
+    }
+
     pub(crate) fn to_tera_context_for_container(
         &self,
         target: &DeploymentTarget,
@@ -440,13 +476,20 @@ impl<C: CloudProvider, T: DatabaseType<C, Container>> Database<C, Container, T>
         context.insert("service_name", self.fqdn_id.as_str());
         context.insert("database_db_name", &self.name);
         context.insert("database_login", options.login.as_str());
-        context.insert("database_password", options.password.as_str());
+        // the password is only read by the charts from the credentials secret
+        context.insert("credentials_secret_name", &self.credentials_secret_name());
+        context.insert("credentials_admin_password_key", admin_password_secret_key(T::db_type()));
         context.insert("database_port", &self.private_port);
         context.insert("database_disk_size_in_gib", &options.disk_size_in_gib);
         if let Some(i) = &self.database_instance_type {
             context.insert("database_instance_type", i.to_cloud_provider_format().as_str());
         }
-        context.insert("database_disk_type", &options.database_disk_type);
+        context.insert(
+            "database_disk_type",
+            kubernetes
+                .default_storage_class()
+                .unwrap_or(options.database_disk_type.as_str()),
+        );
         context.insert("cpu_request_in_milli", &self.cpu_request_in_milli.to_string());
         context.insert("cpu_limit_in_milli", &self.cpu_limit_in_milli.to_string());
         context.insert("ram_request_in_mib", &self.ram_request_in_mib.to_string());
@@ -454,6 +497,16 @@ impl<C: CloudProvider, T: DatabaseType<C, Container>> Database<C, Container, T>
         context.insert("database_fqdn", &options.host.as_str());
         context.insert("database_id", &self.id());
         context.insert("publicly_accessible", &container_database_publicly_accessible);
+        context.insert("database_parameters", options.parameters.parameters());
+
+        // health snapshot cronjob
+        context.insert("database_type", T::lib_directory_name());
+        context.insert(
+            "database_internal_host",
+            &format!("{}.{}.svc.cluster.local", self.kube_name(), environment.namespace()),
+        );
+        context.insert("health_snapshot_config_map_name", &self.health_snapshot_config_map_name());
+        context.insert("health_snapshot_kubectl_image", HEALTH_SNAPSHOT_KUBECTL_IMAGE);
 
         // NLB or ALB controller annotation
         context.insert(
@@ -549,6 +602,11 @@ pub trait DatabaseService: Service + DeploymentAction + ToTeraContext + Send {
     fn as_deployment_action(&self) -> &dyn DeploymentAction;
 
     fn total_disk_size_in_gb(&self) -> u32;
+
+    /// Snapshot the cloud provider takes before removing a managed database, None for container databases
+    fn final_snapshot_name(&self) -> Option<String>;
+
+    fn credentials_secret_name(&self) -> String;
 }
 
 impl<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>> DatabaseService for Database<C, M, T>
@@ -577,6 +635,21 @@ where
     fn total_disk_size_in_gb(&self) -> u32 {
         self.total_disk_size_in_gb
     }
+
+    fn final_snapshot_name(&self) -> Option<String> {
+        match M::is_managed() {
+            true => Some(managed_final_snapshot_name(&self.id)),
+            false => None,
+        }
+    }
+
+    fn credentials_secret_name(&self) -> String {
+        Database::credentials_secret_name(self)
+    }
+}
+
+pub fn managed_final_snapshot_name(database_short_id: &str) -> String {
+    format!("qovery-{database_short_id}-final-snap")
 }
 
 pub fn get_database_with_invalid_storage_size<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>>(
@@ -646,14 +719,10 @@ pub fn get_database_with_invalid_storage_size<C: CloudProvider, M: DatabaseMode,
                 }
 
                 if database.total_disk_size_in_gb < size {
-                    return Err(Box::new(EngineError::new_invalid_engine_payload(
+                    return Err(Box::new(EngineError::new_k8s_storage_cannot_be_reduced(
                         event_details.clone(),
-                        format!(
-                            "new storage size ({}) should be equal or greater than actual size ({})",
-                            database.total_disk_size_in_gb, size
-                        )
-                        .as_str(),
-                        None,
+                        database.total_disk_size_in_gb,
+                        size,
                     )));
                 }
             }
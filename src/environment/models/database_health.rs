@@ -0,0 +1,339 @@
+use crate::environment::models::types::VersionsNumber;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// Must stay in sync with the collection script of lib/common/charts/q-database-health-snapshot
+pub const HEALTH_SNAPSHOT_SCHEMA_VERSION: u32 = 1;
+pub const HEALTH_SNAPSHOT_KEY_PREFIX: &str = "snapshot-";
+pub const HEALTH_SNAPSHOT_KUBECTL_IMAGE: &str = "public.ecr.aws/bitnami/kubectl:1.29";
+
+pub const DISK_USAGE_REFUSAL_THRESHOLD_PERCENT: u64 = 90;
+pub const CONNECTIONS_WARNING_THRESHOLD_PERCENT: u64 = 90;
+pub const REPLICATION_LAG_WARNING_THRESHOLD_IN_SEC: u64 = 60;
+// Snapshots are collected every hour, an older one says nothing about the current state of the database
+pub const HEALTH_SNAPSHOT_MAX_AGE_IN_HOURS: i64 = 3;
+
+pub fn health_snapshot_config_map_name(database_kube_name: &str) -> String {
+    format!("{database_kube_name}-health-snapshots")
+}
+
+/// Health metrics of a container database, collected periodically by the engine managed cronjob.
+/// Every metric is optional as not all of them make sense for every database type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseHealthSnapshot {
+    pub schema_version: u32,
+    pub collected_at: DateTime<Utc>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub connections: Option<u64>,
+    #[serde(default)]
+    pub max_connections: Option<u64>,
+    #[serde(default)]
+    pub replication_lag_in_sec: Option<u64>,
+    #[serde(default)]
+    pub disk_used_in_bytes: Option<u64>,
+    #[serde(default)]
+    pub disk_total_in_bytes: Option<u64>,
+}
+
+impl DatabaseHealthSnapshot {
+    pub fn disk_usage_percent(&self) -> Option<u64> {
+        match (self.disk_used_in_bytes, self.disk_total_in_bytes) {
+            (Some(used), Some(total)) if total > 0 => Some(used.saturating_mul(100) / total),
+            _ => None,
+        }
+    }
+
+    pub fn connections_usage_percent(&self) -> Option<u64> {
+        match (self.connections, self.max_connections) {
+            (Some(connections), Some(max)) if max > 0 => Some(connections.saturating_mul(100) / max),
+            _ => None,
+        }
+    }
+
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        now - self.collected_at > Duration::hours(HEALTH_SNAPSHOT_MAX_AGE_IN_HOURS)
+    }
+}
+
+/// Returns the most recent snapshot of the rolling window stored in the ConfigMap data.
+/// Entries which cannot be parsed, or written with another schema version, are ignored.
+pub fn latest_health_snapshot(config_map_data: &BTreeMap<String, String>) -> Option<DatabaseHealthSnapshot> {
+    config_map_data
+        .iter()
+        .filter(|(key, _)| key.starts_with(HEALTH_SNAPSHOT_KEY_PREFIX))
+        .filter_map(|(_, value)| serde_json::from_str::<DatabaseHealthSnapshot>(value).ok())
+        .filter(|snapshot| snapshot.schema_version == HEALTH_SNAPSHOT_SCHEMA_VERSION)
+        .max_by_key(|snapshot| snapshot.collected_at)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatabaseHealthWarning {
+    DiskAlmostFull { usage_percent: u64 },
+    ConnectionsAlmostExhausted { connections: u64, max_connections: u64 },
+    ReplicationLagging { lag_in_sec: u64 },
+}
+
+impl Display for DatabaseHealthWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseHealthWarning::DiskAlmostFull { usage_percent } => {
+                write!(f, "disk is {usage_percent}% full")
+            }
+            DatabaseHealthWarning::ConnectionsAlmostExhausted {
+                connections,
+                max_connections,
+            } => write!(f, "{connections} connections are open out of {max_connections} allowed"),
+            DatabaseHealthWarning::ReplicationLagging { lag_in_sec } => {
+                write!(f, "replication is lagging by {lag_in_sec} seconds")
+            }
+        }
+    }
+}
+
+pub fn check_health_snapshot(snapshot: &DatabaseHealthSnapshot) -> Vec<DatabaseHealthWarning> {
+    let mut warnings = vec![];
+
+    if let Some(usage_percent) = snapshot.disk_usage_percent() {
+        if usage_percent > DISK_USAGE_REFUSAL_THRESHOLD_PERCENT {
+            warnings.push(DatabaseHealthWarning::DiskAlmostFull { usage_percent });
+        }
+    }
+
+    if let (Some(usage_percent), Some(connections), Some(max_connections)) = (
+        snapshot.connections_usage_percent(),
+        snapshot.connections,
+        snapshot.max_connections,
+    ) {
+        if usage_percent > CONNECTIONS_WARNING_THRESHOLD_PERCENT {
+            warnings.push(DatabaseHealthWarning::ConnectionsAlmostExhausted {
+                connections,
+                max_connections,
+            });
+        }
+    }
+
+    if let Some(lag_in_sec) = snapshot.replication_lag_in_sec {
+        if lag_in_sec > REPLICATION_LAG_WARNING_THRESHOLD_IN_SEC {
+            warnings.push(DatabaseHealthWarning::ReplicationLagging { lag_in_sec });
+        }
+    }
+
+    warnings
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskyDatabaseOperation {
+    VersionUpgrade { from: String, to: String },
+}
+
+impl Display for RiskyDatabaseOperation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RiskyDatabaseOperation::VersionUpgrade { from, to } => write!(f, "version upgrade from {from} to {to}"),
+        }
+    }
+}
+
+/// Returns the risky operation the deployment of the requested version implies, compared to the running version
+/// reported by the snapshot.
+pub fn risky_operation(
+    snapshot: &DatabaseHealthSnapshot,
+    requested_version: &VersionsNumber,
+) -> Option<RiskyDatabaseOperation> {
+    let running_version = VersionsNumber::from_str(snapshot.version.as_deref()?).ok()?;
+    let as_number = |v: Option<&String>| v.and_then(|v| v.parse::<u32>().ok());
+
+    let is_upgrade = match (
+        running_version.major.parse::<u32>().ok(),
+        requested_version.major.parse::<u32>().ok(),
+    ) {
+        (Some(running_major), Some(requested_major)) if running_major != requested_major => {
+            requested_major > running_major
+        }
+        (Some(_), Some(_)) => match (
+            as_number(running_version.minor.as_ref()),
+            as_number(requested_version.minor.as_ref()),
+        ) {
+            (Some(running_minor), Some(requested_minor)) => requested_minor > running_minor,
+            _ => false,
+        },
+        _ => running_version.major != requested_version.major,
+    };
+
+    if !is_upgrade {
+        return None;
+    }
+
+    Some(RiskyDatabaseOperation::VersionUpgrade {
+        from: snapshot.version.clone().unwrap_or_default(),
+        to: requested_version.to_string(),
+    })
+}
+
+/// Refuses a risky operation when the latest known health of the database makes it likely to fail,
+/// unless the user explicitly asked to override health checks.
+/// A missing or stale snapshot never blocks a deployment, as we know nothing about the database.
+pub fn check_risky_operation_is_allowed(
+    snapshot: Option<&DatabaseHealthSnapshot>,
+    operation: Option<&RiskyDatabaseOperation>,
+    override_health_checks: bool,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let (snapshot, operation) = match (snapshot, operation) {
+        (Some(snapshot), Some(operation)) if !override_health_checks && !snapshot.is_stale(now) => {
+            (snapshot, operation)
+        }
+        _ => return Ok(()),
+    };
+
+    match snapshot.disk_usage_percent() {
+        Some(usage_percent) if usage_percent > DISK_USAGE_REFUSAL_THRESHOLD_PERCENT => Err(format!(
+            "{operation} refused as database disk is {usage_percent}% full (snapshot of {}), above the {DISK_USAGE_REFUSAL_THRESHOLD_PERCENT}% threshold",
+            snapshot.collected_at.to_rfc3339()
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(collected_at: DateTime<Utc>) -> DatabaseHealthSnapshot {
+        DatabaseHealthSnapshot {
+            schema_version: HEALTH_SNAPSHOT_SCHEMA_VERSION,
+            collected_at,
+            version: Some("15.4".to_string()),
+            connections: Some(12),
+            max_connections: Some(100),
+            replication_lag_in_sec: None,
+            disk_used_in_bytes: Some(2 * 1024 * 1024 * 1024),
+            disk_total_in_bytes: Some(10 * 1024 * 1024 * 1024),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_schema() {
+        // as written by the collection script
+        let raw = r#"{"schema_version":1,"collected_at":"2026-10-16T08:00:03Z","version":"15.4","connections":7,"max_connections":100,"replication_lag_in_sec":null,"disk_used_in_bytes":9663676416,"disk_total_in_bytes":10737418240}"#;
+        let snapshot: DatabaseHealthSnapshot = serde_json::from_str(raw).unwrap();
+        assert_eq!(snapshot.schema_version, 1);
+        assert_eq!(snapshot.version.as_deref(), Some("15.4"));
+        assert_eq!(snapshot.connections, Some(7));
+        assert_eq!(snapshot.replication_lag_in_sec, None);
+        assert_eq!(snapshot.disk_usage_percent(), Some(90));
+        assert_eq!(snapshot.connections_usage_percent(), Some(7));
+
+        // metrics not collected for a database type are simply missing
+        let raw = r#"{"schema_version":1,"collected_at":"2026-10-16T08:00:03Z","version":"7.2.4"}"#;
+        let snapshot: DatabaseHealthSnapshot = serde_json::from_str(raw).unwrap();
+        assert_eq!(snapshot.disk_usage_percent(), None);
+        assert_eq!(snapshot.connections_usage_percent(), None);
+    }
+
+    #[test]
+    fn test_latest_health_snapshot() {
+        let now = Utc::now();
+        let older = snapshot(now - Duration::hours(2));
+        let newer = snapshot(now - Duration::hours(1));
+        let mut from_another_schema = snapshot(now);
+        from_another_schema.schema_version = HEALTH_SNAPSHOT_SCHEMA_VERSION + 1;
+
+        let data = BTreeMap::from([
+            ("snapshot-06".to_string(), serde_json::to_string(&older).unwrap()),
+            ("snapshot-07".to_string(), serde_json::to_string(&newer).unwrap()),
+            ("snapshot-08".to_string(), serde_json::to_string(&from_another_schema).unwrap()),
+            ("snapshot-09".to_string(), "{not json".to_string()),
+            ("other".to_string(), serde_json::to_string(&snapshot(now)).unwrap()),
+        ]);
+
+        assert_eq!(latest_health_snapshot(&data), Some(newer));
+        assert_eq!(latest_health_snapshot(&BTreeMap::new()), None);
+    }
+
+    #[test]
+    fn test_check_health_snapshot() {
+        let now = Utc::now();
+        assert!(check_health_snapshot(&snapshot(now)).is_empty());
+
+        let mut unhealthy = snapshot(now);
+        unhealthy.disk_used_in_bytes = Some(95);
+        unhealthy.disk_total_in_bytes = Some(100);
+        unhealthy.connections = Some(98);
+        unhealthy.replication_lag_in_sec = Some(120);
+        assert_eq!(
+            check_health_snapshot(&unhealthy),
+            vec![
+                DatabaseHealthWarning::DiskAlmostFull { usage_percent: 95 },
+                DatabaseHealthWarning::ConnectionsAlmostExhausted {
+                    connections: 98,
+                    max_connections: 100
+                },
+                DatabaseHealthWarning::ReplicationLagging { lag_in_sec: 120 },
+            ]
+        );
+
+        // thresholds are exclusive
+        let mut at_threshold = snapshot(now);
+        at_threshold.disk_used_in_bytes = Some(90);
+        at_threshold.disk_total_in_bytes = Some(100);
+        at_threshold.connections = Some(90);
+        at_threshold.replication_lag_in_sec = Some(REPLICATION_LAG_WARNING_THRESHOLD_IN_SEC);
+        assert!(check_health_snapshot(&at_threshold).is_empty());
+    }
+
+    #[test]
+    fn test_risky_operation() {
+        let now = Utc::now();
+        let running = snapshot(now);
+        let version = |v: &str| VersionsNumber::from_str(v).unwrap();
+
+        assert_eq!(
+            risky_operation(&running, &version("16")),
+            Some(RiskyDatabaseOperation::VersionUpgrade {
+                from: "15.4".to_string(),
+                to: "16".to_string()
+            })
+        );
+        assert!(risky_operation(&running, &version("15.6")).is_some());
+        assert_eq!(risky_operation(&running, &version("15")), None);
+        assert_eq!(risky_operation(&running, &version("15.4")), None);
+        assert_eq!(risky_operation(&running, &version("14")), None);
+
+        let mut unknown_version = snapshot(now);
+        unknown_version.version = None;
+        assert_eq!(risky_operation(&unknown_version, &version("16")), None);
+    }
+
+    #[test]
+    fn test_check_risky_operation_is_allowed() {
+        let now = Utc::now();
+        let upgrade = RiskyDatabaseOperation::VersionUpgrade {
+            from: "15.4".to_string(),
+            to: "16".to_string(),
+        };
+        let mut full_disk = snapshot(now - Duration::minutes(30));
+        full_disk.disk_used_in_bytes = Some(91);
+        full_disk.disk_total_in_bytes = Some(100);
+
+        let err = check_risky_operation_is_allowed(Some(&full_disk), Some(&upgrade), false, now).unwrap_err();
+        assert!(err.starts_with("version upgrade from 15.4 to 16 refused as database disk is 91% full"));
+
+        // user override
+        assert!(check_risky_operation_is_allowed(Some(&full_disk), Some(&upgrade), true, now).is_ok());
+        // nothing risky
+        assert!(check_risky_operation_is_allowed(Some(&full_disk), None, false, now).is_ok());
+        // healthy database
+        assert!(check_risky_operation_is_allowed(Some(&snapshot(now)), Some(&upgrade), false, now).is_ok());
+        // unknown health
+        assert!(check_risky_operation_is_allowed(None, Some(&upgrade), false, now).is_ok());
+        let mut stale = full_disk.clone();
+        stale.collected_at = now - Duration::hours(HEALTH_SNAPSHOT_MAX_AGE_IN_HOURS + 1);
+        assert!(check_risky_operation_is_allowed(Some(&stale), Some(&upgrade), false, now).is_ok());
+    }
+}
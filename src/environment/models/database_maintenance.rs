@@ -0,0 +1,360 @@
+use crate::environment::models::database_parameters::DatabaseParametersTarget;
+use crate::infrastructure::models::cloud_provider::Kind;
+use crate::io_models::database::{MaintenanceDay, MaintenanceWindow};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const MINUTES_PER_WEEK: u32 = 7 * MINUTES_PER_DAY;
+
+// Daily backup window set on AWS managed databases (`preferred_backup_window`), maintenance can't overlap it
+const AWS_BACKUP_WINDOW_START_IN_MINUTES: u32 = 0;
+const AWS_BACKUP_WINDOW_DURATION_IN_MINUTES: u32 = 60;
+
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum DatabaseMaintenanceError {
+    #[error("`{0}` can only be set for managed databases")]
+    ManagedOnly(&'static str),
+
+    #[error("`{field}` is not supported (yet) for managed databases on {cloud_provider}")]
+    UnsupportedCloudProvider { field: &'static str, cloud_provider: Kind },
+
+    #[error("maintenance_window: {0}")]
+    InvalidMaintenanceWindow(String),
+
+    #[error("backup_retention_days: {0}")]
+    InvalidBackupRetention(String),
+}
+
+/// Validated weekly maintenance window, in minutes since Monday 00:00 UTC
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DatabaseMaintenanceWindow {
+    start_in_minutes: u32,
+    duration_in_minutes: u32,
+}
+
+impl DatabaseMaintenanceWindow {
+    fn new(window: &MaintenanceWindow) -> Result<Self, DatabaseMaintenanceError> {
+        let Some((hours, minutes)) = window
+            .start_time
+            .split_once(':')
+            .filter(|(hours, minutes)| hours.len() == 2 && minutes.len() == 2)
+            .and_then(|(hours, minutes)| Some((hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?)))
+            .filter(|(hours, minutes)| *hours < 24 && *minutes < 60)
+        else {
+            return Err(DatabaseMaintenanceError::InvalidMaintenanceWindow(format!(
+                "start time `{}` must be in the HH:MM format (UTC)",
+                window.start_time
+            )));
+        };
+
+        // a longer weekly window would overlap itself, and cloud providers refuse windows shorter than 30 minutes
+        if !(30..=MINUTES_PER_DAY).contains(&window.duration_in_minutes) {
+            return Err(DatabaseMaintenanceError::InvalidMaintenanceWindow(format!(
+                "duration of {} minutes must be between 30 minutes and 24 hours",
+                window.duration_in_minutes
+            )));
+        }
+
+        Ok(DatabaseMaintenanceWindow {
+            start_in_minutes: day_index(&window.day) * MINUTES_PER_DAY + hours * 60 + minutes,
+            duration_in_minutes: window.duration_in_minutes,
+        })
+    }
+
+    /// Whether the window overlaps the given daily time range, the window may wrap around the end of the week,
+    /// hence the day after Sunday
+    fn overlaps_daily(&self, start_in_minutes: u32, duration_in_minutes: u32) -> bool {
+        (0..8).any(|day| {
+            let other_start = day * MINUTES_PER_DAY + start_in_minutes;
+            self.start_in_minutes < other_start + duration_in_minutes
+                && other_start < self.start_in_minutes + self.duration_in_minutes
+        })
+    }
+
+    /// AWS format `ddd:hh24:mi-ddd:hh24:mi`
+    pub fn to_aws_format(&self) -> String {
+        let end_in_minutes = (self.start_in_minutes + self.duration_in_minutes) % MINUTES_PER_WEEK;
+        format!("{}-{}", aws_time(self.start_in_minutes), aws_time(end_in_minutes))
+    }
+}
+
+fn day_index(day: &MaintenanceDay) -> u32 {
+    match day {
+        MaintenanceDay::Monday => 0,
+        MaintenanceDay::Tuesday => 1,
+        MaintenanceDay::Wednesday => 2,
+        MaintenanceDay::Thursday => 3,
+        MaintenanceDay::Friday => 4,
+        MaintenanceDay::Saturday => 5,
+        MaintenanceDay::Sunday => 6,
+    }
+}
+
+fn aws_time(minutes_of_week: u32) -> String {
+    let day = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"][(minutes_of_week / MINUTES_PER_DAY) as usize];
+    let minutes_of_day = minutes_of_week % MINUTES_PER_DAY;
+    format!("{day}:{:02}:{:02}", minutes_of_day / 60, minutes_of_day % 60)
+}
+
+/// Maintenance window and backup retention of a managed database, when not set the cloud provider defaults are kept.
+/// Both are updated in place by the cloud providers, changing them never replaces the database.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct DatabaseMaintenance {
+    maintenance_window: Option<DatabaseMaintenanceWindow>,
+    backup_retention_days: Option<u32>,
+}
+
+impl DatabaseMaintenance {
+    pub fn new(
+        target: &DatabaseParametersTarget,
+        maintenance_window: Option<&MaintenanceWindow>,
+        backup_retention_days: Option<u32>,
+    ) -> Result<Self, DatabaseMaintenanceError> {
+        let cloud_provider = match target {
+            DatabaseParametersTarget::Container => {
+                return match (maintenance_window, backup_retention_days) {
+                    (Some(_), _) => Err(DatabaseMaintenanceError::ManagedOnly("maintenance_window")),
+                    (_, Some(_)) => Err(DatabaseMaintenanceError::ManagedOnly("backup_retention_days")),
+                    (None, None) => Ok(Self::default()),
+                };
+            }
+            DatabaseParametersTarget::Managed(cloud_provider) => cloud_provider,
+        };
+
+        let maintenance_window = match (maintenance_window, cloud_provider) {
+            (None, _) => None,
+            (Some(window), Kind::Aws) => {
+                let window = DatabaseMaintenanceWindow::new(window)?;
+                if window.overlaps_daily(AWS_BACKUP_WINDOW_START_IN_MINUTES, AWS_BACKUP_WINDOW_DURATION_IN_MINUTES) {
+                    return Err(DatabaseMaintenanceError::InvalidMaintenanceWindow(
+                        "must not overlap the daily backup window (00:00-01:00 UTC)".to_string(),
+                    ));
+                }
+                Some(window)
+            }
+            (Some(_), cloud_provider) => {
+                return Err(DatabaseMaintenanceError::UnsupportedCloudProvider {
+                    field: "maintenance_window",
+                    cloud_provider: cloud_provider.clone(),
+                })
+            }
+        };
+
+        let retention_bounds = match cloud_provider {
+            Kind::Aws => Some(1..=35),
+            Kind::Scw => Some(1..=365),
+            Kind::Gcp | Kind::OnPremise => None,
+        };
+        match (backup_retention_days, retention_bounds) {
+            (Some(_), None) => {
+                return Err(DatabaseMaintenanceError::UnsupportedCloudProvider {
+                    field: "backup_retention_days",
+                    cloud_provider: cloud_provider.clone(),
+                })
+            }
+            (Some(days), Some(bounds)) if !bounds.contains(&days) => {
+                return Err(DatabaseMaintenanceError::InvalidBackupRetention(format!(
+                    "{days} days is out of the {}-{} days range supported on {cloud_provider}",
+                    bounds.start(),
+                    bounds.end()
+                )))
+            }
+            _ => {}
+        }
+
+        Ok(DatabaseMaintenance {
+            maintenance_window,
+            backup_retention_days,
+        })
+    }
+
+    pub fn maintenance_window(&self) -> Option<&DatabaseMaintenanceWindow> {
+        self.maintenance_window.as_ref()
+    }
+
+    pub fn backup_retention_days(&self) -> Option<u32> {
+        self.backup_retention_days
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::env;
+    use std::fs;
+    use tera::{Context as TeraContext, Tera};
+
+    fn window(day: MaintenanceDay, start_time: &str, duration_in_minutes: u32) -> MaintenanceWindow {
+        MaintenanceWindow {
+            day,
+            start_time: start_time.to_string(),
+            duration_in_minutes,
+        }
+    }
+
+    fn aws(
+        maintenance_window: Option<&MaintenanceWindow>,
+        backup_retention_days: Option<u32>,
+    ) -> Result<DatabaseMaintenance, DatabaseMaintenanceError> {
+        DatabaseMaintenance::new(
+            &DatabaseParametersTarget::Managed(Kind::Aws),
+            maintenance_window,
+            backup_retention_days,
+        )
+    }
+
+    fn render_variable(template: &str, context: serde_json::Value, variable: &str) -> String {
+        let template = fs::read_to_string(format!(
+            "{}/{template}",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .display()
+        ))
+        .expect("cannot read terraform variables template");
+        let rendered = Tera::one_off(&template, &TeraContext::from_value(context).unwrap(), false)
+            .expect("cannot render terraform variables template");
+        let block = rendered
+            .split(&format!("variable \"{variable}\" {{"))
+            .nth(1)
+            .expect("variable not found");
+
+        block[..block.find('}').unwrap()]
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("default = "))
+            .expect("variable has no default")
+            .to_string()
+    }
+
+    #[test]
+    fn test_maintenance_is_rejected_for_container_databases() {
+        assert_eq!(
+            DatabaseMaintenance::new(&DatabaseParametersTarget::Container, None, None),
+            Ok(DatabaseMaintenance::default())
+        );
+        assert_eq!(
+            DatabaseMaintenance::new(
+                &DatabaseParametersTarget::Container,
+                Some(&window(MaintenanceDay::Sunday, "03:00", 60)),
+                None
+            ),
+            Err(DatabaseMaintenanceError::ManagedOnly("maintenance_window"))
+        );
+        assert_eq!(
+            DatabaseMaintenance::new(&DatabaseParametersTarget::Container, None, Some(7)),
+            Err(DatabaseMaintenanceError::ManagedOnly("backup_retention_days"))
+        );
+    }
+
+    #[test]
+    fn test_backup_retention_bounds() {
+        assert_eq!(aws(None, Some(1)).unwrap().backup_retention_days(), Some(1));
+        assert_eq!(aws(None, Some(35)).unwrap().backup_retention_days(), Some(35));
+        for days in [0, 36] {
+            assert!(matches!(
+                aws(None, Some(days)),
+                Err(DatabaseMaintenanceError::InvalidBackupRetention(_))
+            ));
+        }
+
+        let scw = |days| DatabaseMaintenance::new(&DatabaseParametersTarget::Managed(Kind::Scw), None, Some(days));
+        assert_eq!(scw(365).unwrap().backup_retention_days(), Some(365));
+        assert!(matches!(scw(366), Err(DatabaseMaintenanceError::InvalidBackupRetention(_))));
+
+        assert_eq!(
+            DatabaseMaintenance::new(&DatabaseParametersTarget::Managed(Kind::Gcp), None, Some(7)),
+            Err(DatabaseMaintenanceError::UnsupportedCloudProvider {
+                field: "backup_retention_days",
+                cloud_provider: Kind::Gcp,
+            })
+        );
+    }
+
+    #[test]
+    fn test_maintenance_window_validation() {
+        let payload: MaintenanceWindow =
+            serde_json::from_value(json!({ "day": "WEDNESDAY", "start_time": "03:00", "duration_in_minutes": 120 }))
+                .unwrap();
+        assert_eq!(payload, window(MaintenanceDay::Wednesday, "03:00", 120));
+        assert!(serde_json::from_value::<MaintenanceWindow>(json!({ "day": "WED", "start_time": "03:00" })).is_err());
+
+        let maintenance = aws(Some(&window(MaintenanceDay::Wednesday, "03:00", 120)), None).unwrap();
+        assert_eq!(
+            maintenance.maintenance_window().map(|window| window.to_aws_format()),
+            Some("wed:03:00-wed:05:00".to_string())
+        );
+        let maintenance = aws(Some(&window(MaintenanceDay::Sunday, "22:30", 90)), None).unwrap();
+        assert_eq!(
+            maintenance.maintenance_window().map(|window| window.to_aws_format()),
+            Some("sun:22:30-mon:00:00".to_string())
+        );
+
+        let invalid_windows = vec![
+            window(MaintenanceDay::Monday, "3:00", 60),
+            window(MaintenanceDay::Monday, "24:00", 60),
+            window(MaintenanceDay::Monday, "03:60", 60),
+            window(MaintenanceDay::Monday, "03h00", 60),
+            window(MaintenanceDay::Monday, "03:00", 29),
+            window(MaintenanceDay::Monday, "03:00", 24 * 60 + 1),
+            // overlaps the daily backup window
+            window(MaintenanceDay::Monday, "00:30", 60),
+            window(MaintenanceDay::Sunday, "23:30", 60),
+            window(MaintenanceDay::Tuesday, "02:00", 24 * 60),
+        ];
+        for invalid_window in invalid_windows {
+            assert!(
+                matches!(
+                    aws(Some(&invalid_window), None),
+                    Err(DatabaseMaintenanceError::InvalidMaintenanceWindow(_))
+                ),
+                "{invalid_window:?} should be invalid"
+            );
+        }
+
+        assert_eq!(
+            DatabaseMaintenance::new(
+                &DatabaseParametersTarget::Managed(Kind::Scw),
+                Some(&window(MaintenanceDay::Sunday, "03:00", 60)),
+                None
+            ),
+            Err(DatabaseMaintenanceError::UnsupportedCloudProvider {
+                field: "maintenance_window",
+                cloud_provider: Kind::Scw,
+            })
+        );
+    }
+
+    #[test]
+    fn test_maintenance_in_aws_terraform_variables() {
+        let template = "lib/aws/services/common/common-variables.j2.tf";
+        let mut context = json!({
+            "cluster_name": "cluster",
+            "environment_id": "environment",
+            "final_snapshot_name": "snapshot",
+            "kubernetes_cluster_az_list": ["\"eu-west-3a\""],
+            "kubernetes_cluster_id": "cluster",
+            "owner_id": "owner",
+            "project_id": "project",
+            "publicly_accessible": false,
+            "region": "eu-west-3",
+            "resource_expiration_in_seconds": -1,
+            "skip_final_snapshot": false,
+            "delete_automated_backups": false,
+        });
+
+        // cloud provider defaults are kept
+        assert_eq!(
+            render_variable(template, context.clone(), "preferred_maintenance_window"),
+            "\"Tue:02:00-Tue:04:00\""
+        );
+        assert_eq!(render_variable(template, context.clone(), "backup_retention_period"), "14");
+
+        let maintenance = aws(Some(&window(MaintenanceDay::Wednesday, "03:00", 120)), Some(30)).unwrap();
+        context["preferred_maintenance_window"] = json!(maintenance.maintenance_window().unwrap().to_aws_format());
+        context["backup_retention_period"] = json!(maintenance.backup_retention_days());
+        assert_eq!(
+            render_variable(template, context.clone(), "preferred_maintenance_window"),
+            "\"wed:03:00-wed:05:00\""
+        );
+        assert_eq!(render_variable(template, context, "backup_retention_period"), "30");
+    }
+}
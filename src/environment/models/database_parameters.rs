@@ -0,0 +1,773 @@
+use crate::infrastructure::models::cloud_provider::service::DatabaseType;
+use crate::infrastructure::models::cloud_provider::Kind;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// How a parameter change is taken into account by the database engine.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterApplyMethod {
+    /// Applied on the fly, without restarting the database.
+    Dynamic,
+    /// Only applied once the database has been restarted.
+    Static,
+}
+
+impl ParameterApplyMethod {
+    /// RDS parameter group `apply_method`.
+    pub fn to_rds_apply_method(&self) -> &'static str {
+        match self {
+            ParameterApplyMethod::Dynamic => "immediate",
+            ParameterApplyMethod::Static => "pending-reboot",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ParameterValueType {
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    Float {
+        min: f64,
+        max: f64,
+    },
+    Boolean,
+    Enum(&'static [&'static str]),
+    /// Free text restricted to the given set of characters
+    Text {
+        allowed_chars: &'static str,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ParameterSpec {
+    name: &'static str,
+    value_type: ParameterValueType,
+    apply_method: ParameterApplyMethod,
+    /// Set when managed instances do not allow this parameter to be changed
+    disallowed_on_managed: Option<&'static str>,
+}
+
+const fn spec(name: &'static str, value_type: ParameterValueType, apply_method: ParameterApplyMethod) -> ParameterSpec {
+    ParameterSpec {
+        name,
+        value_type,
+        apply_method,
+        disallowed_on_managed: None,
+    }
+}
+
+const MAX_INT: i64 = i32::MAX as i64;
+
+const POSTGRESQL_PARAMETERS: &[ParameterSpec] = &[
+    spec(
+        "max_connections",
+        ParameterValueType::Integer { min: 10, max: 10_000 },
+        ParameterApplyMethod::Static,
+    ),
+    spec(
+        "shared_buffers",
+        ParameterValueType::Integer {
+            min: 16,
+            max: 1_073_741_823,
+        },
+        ParameterApplyMethod::Static,
+    ),
+    spec(
+        "max_locks_per_transaction",
+        ParameterValueType::Integer { min: 10, max: MAX_INT },
+        ParameterApplyMethod::Static,
+    ),
+    ParameterSpec {
+        name: "wal_level",
+        value_type: ParameterValueType::Enum(&["replica", "logical"]),
+        apply_method: ParameterApplyMethod::Static,
+        disallowed_on_managed: Some("managed instances configure WAL through the cloud provider"),
+    },
+    spec(
+        "work_mem",
+        ParameterValueType::Integer { min: 64, max: MAX_INT },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "maintenance_work_mem",
+        ParameterValueType::Integer {
+            min: 1024,
+            max: MAX_INT,
+        },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "effective_cache_size",
+        ParameterValueType::Integer { min: 1, max: MAX_INT },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "max_wal_size",
+        ParameterValueType::Integer { min: 2, max: MAX_INT },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "statement_timeout",
+        ParameterValueType::Integer { min: 0, max: MAX_INT },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "idle_in_transaction_session_timeout",
+        ParameterValueType::Integer { min: 0, max: MAX_INT },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "log_min_duration_statement",
+        ParameterValueType::Integer { min: -1, max: MAX_INT },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "log_statement",
+        ParameterValueType::Enum(&["none", "ddl", "mod", "all"]),
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "default_statistics_target",
+        ParameterValueType::Integer { min: 1, max: 10_000 },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "random_page_cost",
+        ParameterValueType::Float { min: 0.0, max: 1000.0 },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec("track_io_timing", ParameterValueType::Boolean, ParameterApplyMethod::Dynamic),
+];
+
+const POSTGRESQL_FORBIDDEN_PARAMETERS: &[(&str, &str)] = &[
+    ("listen_addresses", "network access is managed by Qovery"),
+    ("port", "the port is set on the database service"),
+    ("ssl", "TLS is managed by Qovery"),
+    ("fsync", "disabling it can corrupt data on crash"),
+    ("full_page_writes", "disabling it can corrupt data on crash"),
+    ("data_directory", "storage is managed by Qovery"),
+    ("hba_file", "authentication is managed by Qovery"),
+    ("shared_preload_libraries", "loading arbitrary libraries is not supported"),
+    ("archive_command", "it executes arbitrary shell commands"),
+];
+
+const MYSQL_PARAMETERS: &[ParameterSpec] = &[
+    spec(
+        "max_connections",
+        ParameterValueType::Integer { min: 1, max: 100_000 },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "innodb_buffer_pool_size",
+        ParameterValueType::Integer {
+            min: 5_242_880,
+            max: i64::MAX,
+        },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "innodb_log_file_size",
+        ParameterValueType::Integer {
+            min: 4_194_304,
+            max: 549_755_813_888,
+        },
+        ParameterApplyMethod::Static,
+    ),
+    spec(
+        "innodb_flush_log_at_trx_commit",
+        ParameterValueType::Enum(&["0", "1", "2"]),
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "max_allowed_packet",
+        ParameterValueType::Integer {
+            min: 1024,
+            max: 1_073_741_824,
+        },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "wait_timeout",
+        ParameterValueType::Integer {
+            min: 1,
+            max: 31_536_000,
+        },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "interactive_timeout",
+        ParameterValueType::Integer {
+            min: 1,
+            max: 31_536_000,
+        },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "long_query_time",
+        ParameterValueType::Float {
+            min: 0.0,
+            max: 31_536_000.0,
+        },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec("slow_query_log", ParameterValueType::Boolean, ParameterApplyMethod::Dynamic),
+    spec(
+        "table_open_cache",
+        ParameterValueType::Integer { min: 1, max: 524_288 },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "tmp_table_size",
+        ParameterValueType::Integer {
+            min: 1024,
+            max: i64::MAX,
+        },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "character_set_server",
+        ParameterValueType::Enum(&["utf8mb4", "utf8mb3", "latin1"]),
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "sql_mode",
+        ParameterValueType::Text {
+            allowed_chars: "ABCDEFGHIJKLMNOPQRSTUVWXYZ_,",
+        },
+        ParameterApplyMethod::Dynamic,
+    ),
+];
+
+const MYSQL_FORBIDDEN_PARAMETERS: &[(&str, &str)] = &[
+    ("bind_address", "network access is managed by Qovery"),
+    ("port", "the port is set on the database service"),
+    ("datadir", "storage is managed by Qovery"),
+    ("skip_grant_tables", "it disables authentication"),
+    ("local_infile", "it allows reading files from the database host"),
+    ("secure_file_priv", "it allows reading files from the database host"),
+    ("general_log_file", "logs location is managed by Qovery"),
+    ("log_bin_trust_function_creators", "it is managed by Qovery"),
+    (
+        "lower_case_table_names",
+        "it can only be set when the data directory is initialized",
+    ),
+];
+
+const REDIS_PARAMETERS: &[ParameterSpec] = &[
+    spec(
+        "maxmemory-policy",
+        ParameterValueType::Enum(&[
+            "noeviction",
+            "allkeys-lru",
+            "allkeys-lfu",
+            "allkeys-random",
+            "volatile-lru",
+            "volatile-lfu",
+            "volatile-random",
+            "volatile-ttl",
+        ]),
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "maxmemory-samples",
+        ParameterValueType::Integer { min: 1, max: 64 },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "timeout",
+        ParameterValueType::Integer { min: 0, max: MAX_INT },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "tcp-keepalive",
+        ParameterValueType::Integer { min: 0, max: MAX_INT },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "notify-keyspace-events",
+        ParameterValueType::Text {
+            allowed_chars: "KEAg$lshzxetmdn",
+        },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec("appendonly", ParameterValueType::Boolean, ParameterApplyMethod::Dynamic),
+    spec(
+        "lazyfree-lazy-eviction",
+        ParameterValueType::Boolean,
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "databases",
+        ParameterValueType::Integer { min: 1, max: 10_000 },
+        ParameterApplyMethod::Static,
+    ),
+];
+
+const REDIS_FORBIDDEN_PARAMETERS: &[(&str, &str)] = &[
+    ("requirepass", "the password is managed by Qovery"),
+    ("masterauth", "the password is managed by Qovery"),
+    ("rename-command", "Qovery relies on the default commands"),
+    ("bind", "network access is managed by Qovery"),
+    ("port", "the port is set on the database service"),
+    ("protected-mode", "network access is managed by Qovery"),
+    ("dir", "storage is managed by Qovery"),
+    ("dbfilename", "storage is managed by Qovery"),
+];
+
+const MONGODB_PARAMETERS: &[ParameterSpec] = &[
+    spec(
+        "cursorTimeoutMillis",
+        ParameterValueType::Integer { min: 1, max: i64::MAX },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "transactionLifetimeLimitSeconds",
+        ParameterValueType::Integer { min: 1, max: MAX_INT },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec(
+        "maxTransactionLockRequestTimeoutMillis",
+        ParameterValueType::Integer { min: -1, max: MAX_INT },
+        ParameterApplyMethod::Dynamic,
+    ),
+    spec("notablescan", ParameterValueType::Boolean, ParameterApplyMethod::Dynamic),
+    spec("ttlMonitorEnabled", ParameterValueType::Boolean, ParameterApplyMethod::Dynamic),
+    spec(
+        "wiredTigerConcurrentReadTransactions",
+        ParameterValueType::Integer { min: 1, max: 1024 },
+        ParameterApplyMethod::Static,
+    ),
+];
+
+const MONGODB_FORBIDDEN_PARAMETERS: &[(&str, &str)] = &[
+    ("enableLocalhostAuthBypass", "it disables authentication on localhost"),
+    ("authenticationMechanisms", "authentication is managed by Qovery"),
+    ("tlsMode", "TLS is managed by Qovery"),
+];
+
+fn parameter_tables(db_type: DatabaseType) -> (&'static [ParameterSpec], &'static [(&'static str, &'static str)]) {
+    match db_type {
+        DatabaseType::PostgreSQL => (POSTGRESQL_PARAMETERS, POSTGRESQL_FORBIDDEN_PARAMETERS),
+        DatabaseType::MySQL => (MYSQL_PARAMETERS, MYSQL_FORBIDDEN_PARAMETERS),
+        DatabaseType::Redis => (REDIS_PARAMETERS, REDIS_FORBIDDEN_PARAMETERS),
+        DatabaseType::MongoDB => (MONGODB_PARAMETERS, MONGODB_FORBIDDEN_PARAMETERS),
+    }
+}
+
+/// Where the database runs, as parameters allowed depend on it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DatabaseParametersTarget {
+    Container,
+    Managed(Kind),
+}
+
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum DatabaseParameterError {
+    #[error("Parameter `{name}` is not supported for {db_type}")]
+    Unknown { db_type: DatabaseType, name: String },
+
+    #[error("Parameter `{name}` cannot be set for {db_type}: {reason}")]
+    Forbidden {
+        db_type: DatabaseType,
+        name: String,
+        reason: String,
+    },
+
+    #[error("Parameter `{name}` has an invalid value `{value}`: {expected}")]
+    InvalidValue {
+        name: String,
+        value: String,
+        expected: String,
+    },
+
+    #[error("Custom parameters are not supported (yet) for managed {db_type} on {cloud_provider}")]
+    UnsupportedManagedDatabase {
+        db_type: DatabaseType,
+        cloud_provider: Kind,
+    },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct DatabaseParameter {
+    pub name: String,
+    /// Normalized value, in the format expected by the database engine
+    pub value: String,
+    pub apply_method: ParameterApplyMethod,
+    /// Pre-computed for RDS parameter groups
+    pub rds_apply_method: &'static str,
+}
+
+/// Validated custom configuration of a database engine.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct DatabaseParameters {
+    parameters: Vec<DatabaseParameter>,
+}
+
+impl DatabaseParameters {
+    pub fn new(
+        db_type: DatabaseType,
+        target: &DatabaseParametersTarget,
+        raw_parameters: &BTreeMap<String, String>,
+    ) -> Result<Self, DatabaseParameterError> {
+        if raw_parameters.is_empty() {
+            return Ok(Self::default());
+        }
+
+        if let DatabaseParametersTarget::Managed(cloud_provider) = target {
+            let is_supported = matches!(
+                (cloud_provider, db_type),
+                (Kind::Aws | Kind::Scw, DatabaseType::PostgreSQL | DatabaseType::MySQL)
+            );
+            if !is_supported {
+                return Err(DatabaseParameterError::UnsupportedManagedDatabase {
+                    db_type,
+                    cloud_provider: cloud_provider.clone(),
+                });
+            }
+        }
+
+        let (allowed, forbidden) = parameter_tables(db_type);
+        let mut parameters = Vec::with_capacity(raw_parameters.len());
+        for (name, value) in raw_parameters {
+            if let Some((_, reason)) = forbidden.iter().find(|(forbidden_name, _)| forbidden_name == name) {
+                return Err(DatabaseParameterError::Forbidden {
+                    db_type,
+                    name: name.to_string(),
+                    reason: reason.to_string(),
+                });
+            }
+
+            let Some(spec) = allowed.iter().find(|spec| spec.name == name) else {
+                return Err(DatabaseParameterError::Unknown {
+                    db_type,
+                    name: name.to_string(),
+                });
+            };
+
+            if let (DatabaseParametersTarget::Managed(_), Some(reason)) = (target, spec.disallowed_on_managed) {
+                return Err(DatabaseParameterError::Forbidden {
+                    db_type,
+                    name: name.to_string(),
+                    reason: reason.to_string(),
+                });
+            }
+
+            parameters.push(DatabaseParameter {
+                name: name.to_string(),
+                value: validate_value(db_type, name, value.trim(), spec.value_type)?,
+                apply_method: spec.apply_method,
+                rds_apply_method: spec.apply_method.to_rds_apply_method(),
+            });
+        }
+
+        Ok(DatabaseParameters { parameters })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parameters.is_empty()
+    }
+
+    pub fn parameters(&self) -> &[DatabaseParameter] {
+        &self.parameters
+    }
+
+    /// Parameters which are only taken into account after a restart of the database
+    pub fn static_parameters(&self) -> impl Iterator<Item = &DatabaseParameter> {
+        self.parameters
+            .iter()
+            .filter(|p| p.apply_method == ParameterApplyMethod::Static)
+    }
+}
+
+impl Display for DatabaseParameters {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|p| format!("{}={}", p.name, p.value))
+            .collect::<Vec<_>>();
+        write!(f, "{}", parameters.join(", "))
+    }
+}
+
+fn validate_value(
+    db_type: DatabaseType,
+    name: &str,
+    value: &str,
+    value_type: ParameterValueType,
+) -> Result<String, DatabaseParameterError> {
+    let invalid = |expected: String| DatabaseParameterError::InvalidValue {
+        name: name.to_string(),
+        value: value.to_string(),
+        expected,
+    };
+
+    match value_type {
+        ParameterValueType::Integer { min, max } => match value.parse::<i64>() {
+            Ok(v) if (min..=max).contains(&v) => Ok(v.to_string()),
+            _ => Err(invalid(format!("expected an integer between {min} and {max}"))),
+        },
+        ParameterValueType::Float { min, max } => match value.parse::<f64>() {
+            Ok(v) if v.is_finite() && v >= min && v <= max => Ok(value.to_string()),
+            _ => Err(invalid(format!("expected a number between {min} and {max}"))),
+        },
+        ParameterValueType::Boolean => {
+            let enabled = match value.to_lowercase().as_str() {
+                "true" | "on" | "yes" | "1" => true,
+                "false" | "off" | "no" | "0" => false,
+                _ => return Err(invalid("expected a boolean".to_string())),
+            };
+            // each engine has its own way to write booleans
+            let (on, off) = match db_type {
+                DatabaseType::PostgreSQL => ("on", "off"),
+                DatabaseType::MySQL => ("1", "0"),
+                DatabaseType::Redis => ("yes", "no"),
+                DatabaseType::MongoDB => ("true", "false"),
+            };
+            Ok(if enabled { on } else { off }.to_string())
+        }
+        ParameterValueType::Enum(values) => match values.iter().find(|v| v.eq_ignore_ascii_case(value)) {
+            Some(v) => Ok(v.to_string()),
+            None => Err(invalid(format!("expected one of {}", values.join(", ")))),
+        },
+        ParameterValueType::Text { allowed_chars } => {
+            if value.is_empty() || value.chars().any(|c| !allowed_chars.contains(c)) {
+                return Err(invalid(format!("expected only the characters `{allowed_chars}`")));
+            }
+            Ok(value.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(values: &[(&str, &str)]) -> BTreeMap<String, String> {
+        values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_empty_parameters_are_always_valid() {
+        for target in [
+            DatabaseParametersTarget::Container,
+            DatabaseParametersTarget::Managed(Kind::Aws),
+            DatabaseParametersTarget::Managed(Kind::Gcp),
+        ] {
+            for db_type in [
+                DatabaseType::PostgreSQL,
+                DatabaseType::MySQL,
+                DatabaseType::Redis,
+                DatabaseType::MongoDB,
+            ] {
+                assert!(DatabaseParameters::new(db_type, &target, &BTreeMap::new())
+                    .unwrap()
+                    .is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_tables_do_not_allow_and_forbid_the_same_parameter() {
+        for db_type in [
+            DatabaseType::PostgreSQL,
+            DatabaseType::MySQL,
+            DatabaseType::Redis,
+            DatabaseType::MongoDB,
+        ] {
+            let (allowed, forbidden) = parameter_tables(db_type);
+            for spec in allowed {
+                assert!(
+                    !forbidden.iter().any(|(name, _)| *name == spec.name),
+                    "{} is both allowed and forbidden for {db_type}",
+                    spec.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_postgresql_parameters() {
+        let parameters = DatabaseParameters::new(
+            DatabaseType::PostgreSQL,
+            &DatabaseParametersTarget::Managed(Kind::Aws),
+            &params(&[
+                ("max_connections", "200"),
+                ("work_mem", "8192"),
+                ("track_io_timing", "true"),
+                ("log_statement", "DDL"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            parameters.to_string(),
+            "log_statement=ddl, max_connections=200, track_io_timing=on, work_mem=8192"
+        );
+        assert_eq!(
+            parameters
+                .static_parameters()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["max_connections"]
+        );
+        assert_eq!(parameters.parameters()[1].rds_apply_method, "pending-reboot");
+        assert_eq!(parameters.parameters()[0].rds_apply_method, "immediate");
+
+        // out of range
+        assert!(matches!(
+            DatabaseParameters::new(
+                DatabaseType::PostgreSQL,
+                &DatabaseParametersTarget::Container,
+                &params(&[("max_connections", "5")])
+            ),
+            Err(DatabaseParameterError::InvalidValue { .. })
+        ));
+        // dangerous
+        assert!(matches!(
+            DatabaseParameters::new(
+                DatabaseType::PostgreSQL,
+                &DatabaseParametersTarget::Container,
+                &params(&[("fsync", "off")])
+            ),
+            Err(DatabaseParameterError::Forbidden { .. })
+        ));
+        // allowed for containers only
+        assert!(DatabaseParameters::new(
+            DatabaseType::PostgreSQL,
+            &DatabaseParametersTarget::Container,
+            &params(&[("wal_level", "logical")])
+        )
+        .is_ok());
+        assert!(matches!(
+            DatabaseParameters::new(
+                DatabaseType::PostgreSQL,
+                &DatabaseParametersTarget::Managed(Kind::Scw),
+                &params(&[("wal_level", "logical")])
+            ),
+            Err(DatabaseParameterError::Forbidden { .. })
+        ));
+    }
+
+    #[test]
+    fn test_mysql_parameters() {
+        let parameters = DatabaseParameters::new(
+            DatabaseType::MySQL,
+            &DatabaseParametersTarget::Managed(Kind::Scw),
+            &params(&[
+                ("slow_query_log", "on"),
+                ("long_query_time", "0.5"),
+                ("innodb_log_file_size", "134217728"),
+                ("sql_mode", "STRICT_TRANS_TABLES,NO_ZERO_DATE"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            parameters.to_string(),
+            "innodb_log_file_size=134217728, long_query_time=0.5, slow_query_log=1, sql_mode=STRICT_TRANS_TABLES,NO_ZERO_DATE"
+        );
+        assert_eq!(parameters.static_parameters().count(), 1);
+
+        assert!(matches!(
+            DatabaseParameters::new(
+                DatabaseType::MySQL,
+                &DatabaseParametersTarget::Container,
+                &params(&[("sql_mode", "ANSI'; DROP")])
+            ),
+            Err(DatabaseParameterError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            DatabaseParameters::new(
+                DatabaseType::MySQL,
+                &DatabaseParametersTarget::Container,
+                &params(&[("skip_grant_tables", "1")])
+            ),
+            Err(DatabaseParameterError::Forbidden { .. })
+        ));
+        assert!(matches!(
+            DatabaseParameters::new(
+                DatabaseType::MySQL,
+                &DatabaseParametersTarget::Container,
+                &params(&[("long_query_time", "NaN")])
+            ),
+            Err(DatabaseParameterError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_redis_parameters() {
+        let parameters = DatabaseParameters::new(
+            DatabaseType::Redis,
+            &DatabaseParametersTarget::Container,
+            &params(&[
+                ("maxmemory-policy", "allkeys-lru"),
+                ("appendonly", "true"),
+                ("databases", "32"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            parameters.to_string(),
+            "appendonly=yes, databases=32, maxmemory-policy=allkeys-lru"
+        );
+        assert_eq!(
+            parameters
+                .static_parameters()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["databases"]
+        );
+
+        assert!(matches!(
+            DatabaseParameters::new(
+                DatabaseType::Redis,
+                &DatabaseParametersTarget::Container,
+                &params(&[("requirepass", "secret")])
+            ),
+            Err(DatabaseParameterError::Forbidden { .. })
+        ));
+        assert_eq!(
+            DatabaseParameters::new(
+                DatabaseType::Redis,
+                &DatabaseParametersTarget::Managed(Kind::Aws),
+                &params(&[("maxmemory-policy", "allkeys-lru")])
+            ),
+            Err(DatabaseParameterError::UnsupportedManagedDatabase {
+                db_type: DatabaseType::Redis,
+                cloud_provider: Kind::Aws,
+            })
+        );
+    }
+
+    #[test]
+    fn test_mongodb_parameters() {
+        let parameters = DatabaseParameters::new(
+            DatabaseType::MongoDB,
+            &DatabaseParametersTarget::Container,
+            &params(&[("notablescan", "1"), ("cursorTimeoutMillis", "600000")]),
+        )
+        .unwrap();
+        assert_eq!(parameters.to_string(), "cursorTimeoutMillis=600000, notablescan=true");
+        assert_eq!(parameters.static_parameters().count(), 0);
+
+        assert!(matches!(
+            DatabaseParameters::new(
+                DatabaseType::MongoDB,
+                &DatabaseParametersTarget::Container,
+                &params(&[("enableLocalhostAuthBypass", "true")])
+            ),
+            Err(DatabaseParameterError::Forbidden { .. })
+        ));
+        assert!(matches!(
+            DatabaseParameters::new(
+                DatabaseType::MongoDB,
+                &DatabaseParametersTarget::Container,
+                &params(&[("unknownParameter", "true")])
+            ),
+            Err(DatabaseParameterError::Unknown { .. })
+        ));
+    }
+}
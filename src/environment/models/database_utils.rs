@@ -71,12 +71,49 @@ pub fn is_allowed_containered_redis_version(requested_version: &VersionsNumber)
     Ok(())
 }
 
+// MySQL major releases (major, first minor), in upgrade order: 5.7 -> 8.0 -> 8.4
+const MYSQL_RELEASES: [(&str, &str); 3] = [("5", "0"), ("8", "0"), ("8", "4")];
+
+fn mysql_release_index(version: &VersionsNumber) -> Option<usize> {
+    let minor = version.minor.as_deref().unwrap_or("0");
+    MYSQL_RELEASES.iter().rposition(|(major, min_minor)| {
+        *major == version.major && minor.parse::<u32>().ok() >= min_minor.parse::<u32>().ok()
+    })
+}
+
+/// Managed databases can only be upgraded to the next major release in place (e.g. PostgreSQL 15 -> 16 or
+/// MySQL 8.0 -> 8.4), skipping majors or downgrading is refused by the cloud providers.
+pub fn check_managed_database_version_upgrade(
+    database_type: DatabaseType,
+    current_version: &VersionsNumber,
+    requested_version: &VersionsNumber,
+) -> Result<(), DatabaseError> {
+    let (current, requested) = match database_type {
+        DatabaseType::PostgreSQL => (
+            current_version.major.parse::<usize>().ok(),
+            requested_version.major.parse::<usize>().ok(),
+        ),
+        DatabaseType::MySQL => (mysql_release_index(current_version), mysql_release_index(requested_version)),
+        DatabaseType::MongoDB | DatabaseType::Redis => return Ok(()),
+    };
+
+    match (current, requested) {
+        (Some(current), Some(requested)) if requested == current || requested == current + 1 => Ok(()),
+        _ => Err(DatabaseError::UnsupportedDatabaseVersionUpgrade {
+            database_type,
+            from: Arc::from(current_version.to_string()),
+            to: Arc::from(requested_version.to_string()),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::environment::models::database::DatabaseError;
     use crate::environment::models::database_utils::{
-        is_allowed_containered_mongodb_version, is_allowed_containered_mysql_version,
-        is_allowed_containered_postgres_version, is_allowed_containered_redis_version,
+        check_managed_database_version_upgrade, is_allowed_containered_mongodb_version,
+        is_allowed_containered_mysql_version, is_allowed_containered_postgres_version,
+        is_allowed_containered_redis_version,
     };
     use crate::environment::models::types::VersionsNumberBuilder;
     use crate::infrastructure::models::cloud_provider::service::DatabaseType;
@@ -99,6 +136,9 @@ mod tests {
             is_allowed_containered_mysql_version(&VersionsNumberBuilder::new().major(8).minor(2).patch(3).build())
                 .is_ok()
         );
+
+        // v8.4
+        assert!(is_allowed_containered_mysql_version(&VersionsNumberBuilder::new().major(8).minor(4).build()).is_ok());
     }
 
     #[test]
@@ -347,4 +387,46 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_check_managed_database_version_upgrade() {
+        let version = |version: &str| version.parse().unwrap();
+
+        // postgres
+        for (from, to) in [("15", "15"), ("15.4", "15.7"), ("15.7", "16"), ("16.3", "17.1")] {
+            assert!(
+                check_managed_database_version_upgrade(DatabaseType::PostgreSQL, &version(from), &version(to)).is_ok()
+            );
+        }
+        assert_eq!(
+            check_managed_database_version_upgrade(DatabaseType::PostgreSQL, &version("13.12"), &version("16")),
+            Err(DatabaseError::UnsupportedDatabaseVersionUpgrade {
+                database_type: DatabaseType::PostgreSQL,
+                from: Arc::from("13.12"),
+                to: Arc::from("16"),
+            })
+        );
+        assert!(
+            check_managed_database_version_upgrade(DatabaseType::PostgreSQL, &version("16"), &version("15")).is_err()
+        );
+
+        // mysql
+        for (from, to) in [
+            ("5.7.44", "8.0"),
+            ("8.0.35", "8.0.39"),
+            ("8.0.35", "8.4"),
+            ("8", "8.4.2"),
+        ] {
+            assert!(check_managed_database_version_upgrade(DatabaseType::MySQL, &version(from), &version(to)).is_ok());
+        }
+        assert_eq!(
+            check_managed_database_version_upgrade(DatabaseType::MySQL, &version("5.7"), &version("8.4")),
+            Err(DatabaseError::UnsupportedDatabaseVersionUpgrade {
+                database_type: DatabaseType::MySQL,
+                from: Arc::from("5.7"),
+                to: Arc::from("8.4"),
+            })
+        );
+        assert!(check_managed_database_version_upgrade(DatabaseType::MySQL, &version("8.4"), &version("8.0")).is_err());
+    }
 }
@@ -1,6 +1,9 @@
 use itertools::Itertools;
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::net::Ipv4Addr;
+use thiserror::Error;
 
 /// Represent a String path instead of passing a PathBuf struct
 pub type StringPath = String;
@@ -13,8 +16,80 @@ pub trait ToHelmString {
     fn to_helm_format_string(&self) -> String;
 }
 
+// ICANN section of https://publicsuffix.org/list/public_suffix_list.dat. Private suffixes (e.g. `github.io`) are left
+// out on purpose: the root domain is used to find the DNS zone, which is always delegated under an ICANN suffix.
+static PUBLIC_SUFFIX_LIST: Lazy<PublicSuffixList> =
+    Lazy::new(|| PublicSuffixList::parse(include_str!("public_suffix_list.dat")));
+
+const DOMAIN_MAX_LENGTH: usize = 253;
+const LABEL_MAX_LENGTH: usize = 63;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InvalidDomainError {
+    #[error("`{domain}` is not a valid domain name")]
+    InvalidSyntax { domain: String },
+    #[error("`{domain}` is a public suffix, a domain registered under it is expected")]
+    PublicSuffix { domain: String },
+}
+
+struct PublicSuffixList {
+    rules: HashSet<String>,
+    // `*.ck` is stored as `ck`
+    wildcard_rules: HashSet<String>,
+    // `!www.ck` is stored as `www.ck`
+    exception_rules: HashSet<String>,
+}
+
+impl PublicSuffixList {
+    fn parse(list: &str) -> Self {
+        let mut public_suffix_list = PublicSuffixList {
+            rules: HashSet::new(),
+            wildcard_rules: HashSet::new(),
+            exception_rules: HashSet::new(),
+        };
+
+        // only the first word of a line is part of the rule
+        for rule in list
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .filter(|rule| !rule.starts_with("//"))
+        {
+            if let Some(exception) = rule.strip_prefix('!') {
+                public_suffix_list.exception_rules.insert(exception.to_string());
+            } else if let Some(wildcard) = rule.strip_prefix("*.") {
+                public_suffix_list.wildcard_rules.insert(wildcard.to_string());
+            } else {
+                public_suffix_list.rules.insert(rule.to_string());
+            }
+        }
+
+        public_suffix_list
+    }
+
+    /// Number of labels of the public suffix of the (lowercased) labels, following https://publicsuffix.org/list/ algorithm
+    fn suffix_labels_count(&self, labels: &[String]) -> usize {
+        // prevailing `*` rule: the tld is the public suffix of unknown domains
+        let mut count = 1;
+        for index in 0..labels.len() {
+            let candidate = labels[index..].join(".");
+            let candidate_labels_count = labels.len() - index;
+            if self.exception_rules.contains(&candidate) {
+                return candidate_labels_count - 1;
+            }
+            if self.rules.contains(&candidate) {
+                count = count.max(candidate_labels_count);
+            }
+            if index > 0 && self.wildcard_rules.contains(&candidate) {
+                count = count.max(candidate_labels_count + 1);
+            }
+        }
+
+        count
+    }
+}
+
 /// Represents a domain, just plain domain, no protocol.
-/// eq. `test.com`, `sub.test.com`
+/// eq. `test.com`, `sub.test.com`, `sub.test.co.uk`
 #[derive(Clone)]
 pub struct Domain {
     raw: String,
@@ -23,20 +98,18 @@ pub struct Domain {
 
 impl Domain {
     pub fn new(raw: String) -> Self {
-        // TODO(benjaminch): This is very basic solution which doesn't take into account
-        // some edge cases such as: "test.co.uk" domains
-        let sep: &str = ".";
-        let items: Vec<String> = raw.split(sep).map(|e| e.to_string()).collect();
-        let items_count = raw.matches(sep).count() + 1;
-        let top_domain: String = match items_count > 2 {
-            true => items.iter().skip(items_count - 2).join("."),
-            false => items.iter().join("."),
+        // ACME issuers, external-dns and cloud providers only understand the ASCII form of internationalized domains
+        let raw = raw.split('.').map(label_to_ascii).join(".");
+        let labels: Vec<&str> = raw.split('.').collect();
+        let suffix_labels_count = PUBLIC_SUFFIX_LIST.suffix_labels_count(&public_suffix_lookup_labels(&labels));
+
+        // a domain being a public suffix itself (e.g. `co.uk`) is kept as is
+        let root_domain = match labels.len() > suffix_labels_count {
+            true => labels.iter().skip(labels.len() - suffix_labels_count - 1).join("."),
+            false => raw.to_string(),
         };
 
-        Domain {
-            root_domain: top_domain,
-            raw,
-        }
+        Domain { root_domain, raw }
     }
 
     pub fn new_with_subdomain(raw: String, sub_domain: String) -> Self {
@@ -51,6 +124,18 @@ impl Domain {
         Domain::new(self.root_domain.to_string())
     }
 
+    /// Domain with its punycode (`xn--`) labels decoded, to be only used for display purposes (i.e: error messages).
+    /// eq. `xn--mnchen-3ya.example.de` => `münchen.example.de`
+    pub fn raw_unicode(&self) -> String {
+        self.raw.split('.').map(label_to_unicode).join(".")
+    }
+
+    /// Domain with its unicode labels encoded to punycode, this is also the form displayed.
+    /// eq. `münchen.example.de` => `xn--mnchen-3ya.example.de`
+    pub fn to_ascii(&self) -> String {
+        self.raw.to_string()
+    }
+
     pub fn wildcarded(&self) -> Domain {
         if self.is_wildcarded() {
             return self.clone();
@@ -62,11 +147,88 @@ impl Domain {
         }
     }
 
+    /// A valid domain has a correct syntax (a wildcard is only allowed as first label) and is registered under a public
+    /// suffix, `co.uk` or `localhost` are not valid.
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    fn validate(&self) -> Result<(), InvalidDomainError> {
+        let labels: Vec<&str> = self.raw.split('.').collect();
+        let is_valid_label = |index: usize, label: &str| {
+            (index == 0 && label == "*")
+                || (!label.is_empty()
+                    && label.len() <= LABEL_MAX_LENGTH
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        };
+        if self.raw.len() > DOMAIN_MAX_LENGTH
+            || !labels.iter().enumerate().all(|(index, label)| is_valid_label(index, label))
+            // the tld cannot be numeric, `10.0.0.1` is not a domain
+            || labels.last().is_some_and(|tld| tld.chars().all(|c| c.is_ascii_digit()))
+        {
+            return Err(InvalidDomainError::InvalidSyntax {
+                domain: self.raw_unicode(),
+            });
+        }
+
+        // `*.co.uk` has a root domain, but it is not a registered one
+        if labels.len() <= PUBLIC_SUFFIX_LIST.suffix_labels_count(&public_suffix_lookup_labels(&labels))
+            || self.root_domain.starts_with('*')
+        {
+            return Err(InvalidDomainError::PublicSuffix {
+                domain: self.raw_unicode(),
+            });
+        }
+
+        Ok(())
+    }
+
     fn is_wildcarded(&self) -> bool {
         self.raw.starts_with('*')
     }
 }
 
+/// Punycode form of a unicode label, ASCII labels are kept as is
+fn label_to_ascii(label: &str) -> String {
+    match label.is_ascii() {
+        true => label.to_string(),
+        // an invalid label is kept as is, it is rejected by the domain validation
+        false => idna::domain_to_ascii(label).unwrap_or_else(|_| label.to_string()),
+    }
+}
+
+/// Unicode form of a punycode label, other labels are kept as is
+fn label_to_unicode(label: &str) -> String {
+    match label.get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case("xn--")) {
+        true => match idna::domain_to_unicode(label) {
+            (unicode, Ok(())) => unicode,
+            (_, Err(_)) => label.to_string(),
+        },
+        false => label.to_string(),
+    }
+}
+
+// Rules of the public suffix list are written in lowercased unicode
+fn public_suffix_lookup_labels(labels: &[&str]) -> Vec<String> {
+    labels
+        .iter()
+        .map(|label| label_to_unicode(label).to_lowercase())
+        .collect()
+}
+
+impl TryFrom<String> for Domain {
+    type Error = InvalidDomainError;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        let domain = Domain::new(raw);
+        domain.validate()?;
+
+        Ok(domain)
+    }
+}
+
 impl Display for Domain {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.raw.as_str())
@@ -75,13 +237,13 @@ impl Display for Domain {
 
 impl ToTerraformString for Domain {
     fn to_terraform_format_string(&self) -> String {
-        format!("{{{}}}", self.raw)
+        format!("{{{}}}", self.to_ascii())
     }
 }
 
 impl ToHelmString for Domain {
     fn to_helm_format_string(&self) -> String {
-        format!("{{{}}}", self.raw)
+        format!("{{{}}}", self.to_ascii())
     }
 }
 
@@ -93,7 +255,7 @@ impl ToTerraformString for Ipv4Addr {
 
 #[cfg(test)]
 mod tests {
-    use crate::environment::models::domain::Domain;
+    use crate::environment::models::domain::{Domain, InvalidDomainError, ToHelmString, ToTerraformString};
 
     #[test]
     fn test_domain_new() {
@@ -126,9 +288,39 @@ mod tests {
             },
             TestCase {
                 input: "test.co.uk".to_string(),
-                expected_root_domain_output: "co.uk".to_string(), // TODO(benjamin) => Should be test.co.uk in the future
+                expected_root_domain_output: "test.co.uk".to_string(),
                 expected_wildcarded_output: "*.test.co.uk".to_string(),
-                description: "broken edge case domain with special tld input",
+                description: "domain with special tld input",
+            },
+            TestCase {
+                input: "app.sub.test.com.br".to_string(),
+                expected_root_domain_output: "test.com.br".to_string(),
+                expected_wildcarded_output: "*.app.sub.test.com.br".to_string(),
+                description: "sub domain with special tld input",
+            },
+            TestCase {
+                input: "Sub.Test.Org.AU".to_string(),
+                expected_root_domain_output: "Test.Org.AU".to_string(),
+                expected_wildcarded_output: "*.Sub.Test.Org.AU".to_string(),
+                description: "uppercased sub domain with special tld input",
+            },
+            TestCase {
+                input: "sub.test.foo.kawasaki.jp".to_string(),
+                expected_root_domain_output: "test.foo.kawasaki.jp".to_string(),
+                expected_wildcarded_output: "*.sub.test.foo.kawasaki.jp".to_string(),
+                description: "sub domain matching a wildcard rule input",
+            },
+            TestCase {
+                input: "sub.www.ck".to_string(),
+                expected_root_domain_output: "www.ck".to_string(),
+                expected_wildcarded_output: "*.sub.www.ck".to_string(),
+                description: "sub domain matching an exception rule input",
+            },
+            TestCase {
+                input: "co.uk".to_string(),
+                expected_root_domain_output: "co.uk".to_string(),
+                expected_wildcarded_output: "*.co.uk".to_string(),
+                description: "public suffix input",
             },
             TestCase {
                 input: "test".to_string(),
@@ -177,4 +369,125 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_domain_is_valid() {
+        // setup:
+        let test_cases = vec![
+            ("test.com", Ok(())),
+            ("*.sub.test.com", Ok(())),
+            ("my-app.test.co.uk", Ok(())),
+            ("", Err("invalid syntax")),
+            ("test..com", Err("invalid syntax")),
+            ("-test.com", Err("invalid syntax")),
+            ("sub.*.test.com", Err("invalid syntax")),
+            ("test_app.com", Err("invalid syntax")),
+            ("https://test.com", Err("invalid syntax")),
+            ("10.0.0.1", Err("invalid syntax")),
+            ("test", Err("public suffix")),
+            ("co.uk", Err("public suffix")),
+            ("*.co.uk", Err("public suffix")),
+        ];
+
+        for (input, expected) in test_cases {
+            // execute:
+            let result = Domain::try_from(input.to_string());
+
+            // verify:
+            assert_eq!(Domain::new(input.to_string()).is_valid(), expected.is_ok(), "case '{input}'");
+            match (result, expected) {
+                (Ok(domain), Ok(())) => assert_eq!(domain.to_string(), input),
+                (Err(InvalidDomainError::InvalidSyntax { .. }), Err("invalid syntax")) => {}
+                (Err(InvalidDomainError::PublicSuffix { .. }), Err("public suffix")) => {}
+                (result, expected) => {
+                    panic!("case '{input}': expected {expected:?}, got {:?}", result.map(|d| d.to_string()))
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_domain_idn() {
+        struct TestCase<'a> {
+            input: &'a str,
+            expected_ascii_output: &'a str,
+            expected_unicode_output: &'a str,
+            expected_root_domain_output: &'a str,
+            description: &'a str,
+        }
+
+        // setup:
+        let test_cases = vec![
+            TestCase {
+                input: "münchen.example.de",
+                expected_ascii_output: "xn--mnchen-3ya.example.de",
+                expected_unicode_output: "münchen.example.de",
+                expected_root_domain_output: "example.de",
+                description: "unicode sub domain input",
+            },
+            TestCase {
+                input: "app.MÜNCHEN.de",
+                expected_ascii_output: "app.xn--mnchen-3ya.de",
+                expected_unicode_output: "app.münchen.de",
+                expected_root_domain_output: "xn--mnchen-3ya.de",
+                description: "mixed-case unicode domain input",
+            },
+            TestCase {
+                input: "app.xn--mnchen-3ya.de",
+                expected_ascii_output: "app.xn--mnchen-3ya.de",
+                expected_unicode_output: "app.münchen.de",
+                expected_root_domain_output: "xn--mnchen-3ya.de",
+                description: "already punycoded domain input",
+            },
+            TestCase {
+                input: "shop.例子.公司.cn",
+                expected_ascii_output: "shop.xn--fsqu00a.xn--55qx5d.cn",
+                expected_unicode_output: "shop.例子.公司.cn",
+                expected_root_domain_output: "xn--fsqu00a.xn--55qx5d.cn",
+                description: "unicode public suffix input",
+            },
+            TestCase {
+                input: "Sub.Test.com",
+                expected_ascii_output: "Sub.Test.com",
+                expected_unicode_output: "Sub.Test.com",
+                expected_root_domain_output: "Test.com",
+                description: "plain ASCII domain input",
+            },
+        ];
+
+        for tc in test_cases {
+            // execute:
+            let result = Domain::new(tc.input.to_string());
+
+            // verify:
+            assert_eq!(result.to_ascii(), tc.expected_ascii_output, "case {}", tc.description);
+            assert_eq!(result.to_string(), tc.expected_ascii_output, "case {}", tc.description);
+            assert_eq!(
+                result.to_helm_format_string(),
+                format!("{{{}}}", tc.expected_ascii_output),
+                "case {}",
+                tc.description
+            );
+            assert_eq!(
+                result.to_terraform_format_string(),
+                format!("{{{}}}", tc.expected_ascii_output),
+                "case {}",
+                tc.description
+            );
+            assert_eq!(result.raw_unicode(), tc.expected_unicode_output, "case {}", tc.description);
+            assert_eq!(
+                result.root_domain().to_string(),
+                tc.expected_root_domain_output,
+                "case {}",
+                tc.description
+            );
+            assert!(result.is_valid(), "case {}", tc.description);
+        }
+
+        // errors show the domain as it was given
+        assert_eq!(
+            Domain::try_from("公司.cn".to_string()).err().map(|err| err.to_string()),
+            Some("`公司.cn` is a public suffix, a domain registered under it is expected".to_string())
+        );
+    }
 }
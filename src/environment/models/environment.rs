@@ -8,6 +8,8 @@ use crate::environment::models::container::ContainerService;
 use crate::environment::models::database::DatabaseService;
 use crate::environment::models::helm_chart::HelmChartService;
 use crate::environment::models::job::JobService;
+use crate::environment::models::k8s_manifests::K8sManifestsService;
+use crate::environment::models::pod_security::PodSecurityProfile;
 use crate::environment::models::router::RouterService;
 use crate::utilities::to_short_id;
 use uuid::Uuid;
@@ -31,6 +33,8 @@ pub struct Environment {
     pub databases: Vec<Box<dyn DatabaseService>>,
     pub jobs: Vec<Box<dyn JobService>>,
     pub helm_charts: Vec<Box<dyn HelmChartService>>,
+    pub k8s_manifests: Vec<Box<dyn K8sManifestsService>>,
+    pub pod_security_profile: PodSecurityProfile,
 }
 
 impl Environment {
@@ -50,6 +54,8 @@ impl Environment {
         databases: Vec<Box<dyn DatabaseService>>,
         jobs: Vec<Box<dyn JobService>>,
         helm_charts: Vec<Box<dyn HelmChartService>>,
+        k8s_manifests: Vec<Box<dyn K8sManifestsService>>,
+        pod_security_profile: PodSecurityProfile,
     ) -> Self {
         let project_id = to_short_id(&project_long_id);
         let env_id = to_short_id(&long_id);
@@ -77,6 +83,8 @@ impl Environment {
             databases,
             jobs,
             helm_charts,
+            k8s_manifests,
+            pod_security_profile,
         }
     }
 
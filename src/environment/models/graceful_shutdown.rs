@@ -0,0 +1,307 @@
+use crate::io_models::container::{ContainerAdvancedSettings, LifecycleHooks};
+use serde::Serialize;
+
+// Time for the ingress controller to stop routing to a terminating pod when the user did not configure it.
+// Terminating pods are flagged as not ready in EndpointSlices right away, but nginx needs a few seconds to reload.
+pub const DEFAULT_DRAIN_SECONDS: u32 = 10;
+// Upper bound of the grace period set on a service, a stuck pod must not block a rollout or a node drain for hours
+pub const MAX_TERMINATION_GRACE_PERIOD_SECONDS: u32 = 3600;
+
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum GracefulShutdownError {
+    #[error(
+        "graceful_shutdown.termination_grace_seconds ({termination_grace_seconds}s) must be greater or equal to graceful_shutdown.drain_seconds ({drain_seconds}s)"
+    )]
+    GraceShorterThanDrain {
+        drain_seconds: u32,
+        termination_grace_seconds: u32,
+    },
+    #[error(
+        "termination_grace_period_seconds ({termination_grace_period_seconds}s) must be lower or equal to {MAX_TERMINATION_GRACE_PERIOD_SECONDS}s"
+    )]
+    GracePeriodTooLong { termination_grace_period_seconds: u32 },
+    #[error("pre_stop_exec_command must not be empty")]
+    EmptyPreStopCommand,
+}
+
+/// Validates the graceful shutdown settings of a service
+pub fn validate_graceful_shutdown(settings: &ContainerAdvancedSettings) -> Result<(), GracefulShutdownError> {
+    let Some(drain_seconds) = settings.graceful_shutdown_drain_seconds else {
+        // the default drain time adapts to the grace period
+        return Ok(());
+    };
+
+    let termination_grace_seconds = termination_grace_seconds(settings);
+    if termination_grace_seconds < drain_seconds {
+        return Err(GracefulShutdownError::GraceShorterThanDrain {
+            drain_seconds,
+            termination_grace_seconds,
+        });
+    }
+
+    Ok(())
+}
+
+/// Validates the lifecycle hooks set on a service
+pub fn validate_lifecycle_hooks(lifecycle_hooks: &LifecycleHooks) -> Result<(), GracefulShutdownError> {
+    if let Some(pre_stop_exec_command) = &lifecycle_hooks.pre_stop_exec_command {
+        if pre_stop_exec_command
+            .first()
+            .map(|cmd| cmd.trim().is_empty())
+            .unwrap_or(true)
+        {
+            return Err(GracefulShutdownError::EmptyPreStopCommand);
+        }
+    }
+
+    validate_termination_grace_period(lifecycle_hooks.termination_grace_period_seconds)
+}
+
+/// Validates the grace period set on a service, the only lifecycle hook applying to jobs
+pub fn validate_termination_grace_period(
+    termination_grace_period_seconds: Option<u32>,
+) -> Result<(), GracefulShutdownError> {
+    match termination_grace_period_seconds {
+        Some(termination_grace_period_seconds)
+            if termination_grace_period_seconds > MAX_TERMINATION_GRACE_PERIOD_SECONDS =>
+        {
+            Err(GracefulShutdownError::GracePeriodTooLong {
+                termination_grace_period_seconds,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn termination_grace_seconds(settings: &ContainerAdvancedSettings) -> u32 {
+    settings
+        .graceful_shutdown_termination_grace_seconds
+        .unwrap_or(settings.deployment_termination_grace_period_seconds)
+}
+
+/// Values rendered in the pod spec of the service
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub(crate) struct GracefulShutdownTeraContext {
+    pub(crate) termination_grace_period_seconds: u32,
+    /// Sleep injected as preStop hook, so the pod keeps serving until the ingress stopped routing to it
+    pub(crate) pre_stop_sleep_seconds: Option<u32>,
+}
+
+impl GracefulShutdownTeraContext {
+    pub(crate) fn new(settings: &ContainerAdvancedSettings, receives_traffic: bool) -> Self {
+        let termination_grace_period_seconds = termination_grace_seconds(settings);
+        let drain_seconds = settings
+            .graceful_shutdown_drain_seconds
+            .unwrap_or(DEFAULT_DRAIN_SECONDS.min(termination_grace_period_seconds));
+
+        // a preStop hook of the user is never overridden, it is up to them to drain connections
+        let pre_stop_sleep_seconds = if receives_traffic
+            && drain_seconds > 0
+            && settings.deployment_lifecycle_pre_stop_exec_command.is_empty()
+        {
+            Some(drain_seconds)
+        } else {
+            None
+        };
+
+        GracefulShutdownTeraContext {
+            termination_grace_period_seconds,
+            pre_stop_sleep_seconds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::models::labels_group::LabelsGroupTeraContext;
+    use serde_json::json;
+    use std::env;
+    use std::fs;
+    use tera::{Context as TeraContext, Tera};
+
+    fn settings(drain: Option<u32>, grace: Option<u32>) -> ContainerAdvancedSettings {
+        ContainerAdvancedSettings {
+            graceful_shutdown_drain_seconds: drain,
+            graceful_shutdown_termination_grace_seconds: grace,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_graceful_shutdown() {
+        assert_eq!(validate_graceful_shutdown(&settings(None, None)), Ok(()));
+        assert_eq!(validate_graceful_shutdown(&settings(Some(30), Some(30))), Ok(()));
+        assert_eq!(validate_graceful_shutdown(&settings(Some(30), Some(45))), Ok(()));
+        assert_eq!(
+            validate_graceful_shutdown(&settings(Some(30), Some(20))),
+            Err(GracefulShutdownError::GraceShorterThanDrain {
+                drain_seconds: 30,
+                termination_grace_seconds: 20,
+            })
+        );
+        // without explicit grace, the deployment one (60s by default) is used
+        assert_eq!(validate_graceful_shutdown(&settings(Some(60), None)), Ok(()));
+        assert!(validate_graceful_shutdown(&settings(Some(61), None)).is_err());
+        // the default drain time can't conflict with a short grace period
+        assert_eq!(validate_graceful_shutdown(&settings(None, Some(1))), Ok(()));
+    }
+
+    #[test]
+    fn test_graceful_shutdown_tera_context() {
+        assert_eq!(
+            GracefulShutdownTeraContext::new(&settings(None, None), true),
+            GracefulShutdownTeraContext {
+                termination_grace_period_seconds: 60,
+                pre_stop_sleep_seconds: Some(DEFAULT_DRAIN_SECONDS),
+            }
+        );
+        assert_eq!(
+            GracefulShutdownTeraContext::new(&settings(Some(20), Some(40)), true),
+            GracefulShutdownTeraContext {
+                termination_grace_period_seconds: 40,
+                pre_stop_sleep_seconds: Some(20),
+            }
+        );
+        assert_eq!(
+            GracefulShutdownTeraContext::new(&settings(None, Some(3)), true).pre_stop_sleep_seconds,
+            Some(3)
+        );
+        // disabled on purpose
+        assert_eq!(
+            GracefulShutdownTeraContext::new(&settings(Some(0), None), true).pre_stop_sleep_seconds,
+            None
+        );
+        // nothing to drain
+        assert_eq!(
+            GracefulShutdownTeraContext::new(&settings(Some(20), None), false),
+            GracefulShutdownTeraContext {
+                termination_grace_period_seconds: 60,
+                pre_stop_sleep_seconds: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_user_pre_stop_hook_is_kept() {
+        let settings = ContainerAdvancedSettings {
+            deployment_lifecycle_pre_stop_exec_command: vec!["/bin/drain.sh".to_string()],
+            graceful_shutdown_drain_seconds: Some(20),
+            ..Default::default()
+        };
+
+        assert_eq!(GracefulShutdownTeraContext::new(&settings, true).pre_stop_sleep_seconds, None);
+    }
+
+    fn lifecycle_hooks(pre_stop_exec_command: Option<Vec<&str>>, grace: Option<u32>) -> LifecycleHooks {
+        LifecycleHooks {
+            pre_stop_exec_command: pre_stop_exec_command
+                .map(|command| command.into_iter().map(|arg| arg.to_string()).collect()),
+            termination_grace_period_seconds: grace,
+        }
+    }
+
+    fn render_pod_spec(settings: &ContainerAdvancedSettings) -> serde_yaml::Value {
+        let mut context = TeraContext::new();
+        context.insert("namespace", "my-namespace");
+        context.insert("environment_short_id", "env");
+        context.insert("environment_long_id", "a0b1c2d3-0000-0000-0000-000000000002");
+        context.insert("project_long_id", "a0b1c2d3-0000-0000-0000-000000000003");
+        context.insert("labels_group", &LabelsGroupTeraContext::new(vec![]));
+        context.insert("annotations_group", &json!({ "deployment": {}, "pods": {} }));
+        context.insert("registry", &json!({ "secret_name": null }));
+        context.insert("environment_variables", &json!([]));
+        context.insert("mounted_files", &json!([]));
+        context.insert(
+            "service",
+            &json!({
+                "name": "my-app",
+                "long_id": "a0b1c2d3-0000-0000-0000-000000000000",
+                "short_id": "a0b1c2d3",
+                "type": "application",
+                "version": "1",
+                "image_full": "nginx:latest",
+                "command_args": [],
+                "entrypoint": null,
+                "cpu_request_in_milli": "250m",
+                "cpu_limit_in_milli": "250m",
+                "ram_request_in_mib": "256Mi",
+                "ram_limit_in_mib": "256Mi",
+                "min_instances": 1,
+                "max_instances": 1,
+                "ports": [],
+                "storages": [],
+                "readiness_probe": null,
+                "liveness_probe": null,
+                "tolerations": [],
+                "legacy_deployment_from_scaleway": false,
+                "legacy_deployment_matchlabels": false,
+                "promoted_from_execution_id": null,
+                "promoted_image_digest": null,
+                "advanced_settings": settings,
+                "graceful_shutdown": GracefulShutdownTeraContext::new(settings, true),
+            }),
+        );
+
+        let template = fs::read_to_string(format!(
+            "{}/lib/common/charts/q-container/templates/deployment.j2.yaml",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .display()
+        ))
+        .expect("cannot read deployment template");
+        let deployment = Tera::one_off(&template, &context, false).expect("cannot render deployment template");
+        let deployment: serde_yaml::Value = serde_yaml::from_str(&deployment).expect("invalid deployment yaml");
+
+        deployment["spec"]["template"]["spec"].clone()
+    }
+
+    #[test]
+    fn test_validate_lifecycle_hooks() {
+        assert_eq!(validate_lifecycle_hooks(&LifecycleHooks::default()), Ok(()));
+        assert_eq!(
+            validate_lifecycle_hooks(&lifecycle_hooks(Some(vec!["nginx", "-s", "quit"]), Some(0))),
+            Ok(())
+        );
+        assert_eq!(
+            validate_lifecycle_hooks(&lifecycle_hooks(None, Some(MAX_TERMINATION_GRACE_PERIOD_SECONDS))),
+            Ok(())
+        );
+        assert_eq!(
+            validate_lifecycle_hooks(&lifecycle_hooks(None, Some(MAX_TERMINATION_GRACE_PERIOD_SECONDS + 1))),
+            Err(GracefulShutdownError::GracePeriodTooLong {
+                termination_grace_period_seconds: MAX_TERMINATION_GRACE_PERIOD_SECONDS + 1,
+            })
+        );
+        assert_eq!(
+            validate_lifecycle_hooks(&lifecycle_hooks(Some(vec![]), None)),
+            Err(GracefulShutdownError::EmptyPreStopCommand)
+        );
+        assert_eq!(
+            validate_lifecycle_hooks(&lifecycle_hooks(Some(vec![" ", "quit"]), None)),
+            Err(GracefulShutdownError::EmptyPreStopCommand)
+        );
+    }
+
+    #[test]
+    fn test_pod_spec_with_lifecycle_hooks() {
+        let pod_spec = render_pod_spec(&ContainerAdvancedSettings::default());
+        assert_eq!(pod_spec["terminationGracePeriodSeconds"].as_u64(), Some(60));
+        assert_eq!(
+            pod_spec["containers"][0]["lifecycle"]["preStop"]["exec"]["command"],
+            serde_yaml::to_value(["sleep", "10"]).unwrap()
+        );
+
+        let settings = ContainerAdvancedSettings::default().with_lifecycle_hooks(&lifecycle_hooks(
+            Some(vec!["/bin/sh", "-c", "echo \"bye: now\" && nginx -s quit"]),
+            Some(120),
+        ));
+        assert_eq!(validate_graceful_shutdown(&settings), Ok(()));
+        let pod_spec = render_pod_spec(&settings);
+        assert_eq!(pod_spec["terminationGracePeriodSeconds"].as_u64(), Some(120));
+        assert_eq!(
+            pod_spec["containers"][0]["lifecycle"]["preStop"]["exec"]["command"],
+            serde_yaml::to_value(["/bin/sh", "-c", "echo \"bye: now\" && nginx -s quit"]).unwrap()
+        );
+    }
+}
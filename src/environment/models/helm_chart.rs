@@ -1,9 +1,9 @@
 use crate::environment::action::DeploymentAction;
+use crate::environment::models::service_port::{validate_service_ports, ServicePort};
 use crate::environment::models::types::CloudProvider;
 use crate::events::{EventDetails, Stage, Transmitter};
 use crate::infrastructure::models::build_platform::{Build, Credentials, SshKey};
 use crate::infrastructure::models::cloud_provider::service::{Action, Service, ServiceType};
-use crate::io_models::application::Port;
 use crate::io_models::container::Registry;
 use crate::io_models::context::Context;
 use crate::io_models::helm_chart::{HelmChartAdvancedSettings, HelmRawValues};
@@ -46,7 +46,7 @@ pub struct HelmChart<T: CloudProvider> {
     pub(crate) _extra_settings: T::AppExtraSettings,
     pub(crate) workspace_directory: PathBuf,
     pub(crate) chart_workspace_directory: PathBuf,
-    pub(crate) ports: Vec<Port>,
+    pub(crate) ports: Vec<ServicePort>,
 }
 
 // Here we define the common behavior among all providers
@@ -69,8 +69,10 @@ impl<T: CloudProvider> HelmChart<T> {
         advanced_settings: HelmChartAdvancedSettings,
         extra_settings: T::AppExtraSettings,
         mk_event_details: impl Fn(Transmitter) -> EventDetails,
-        ports: Vec<Port>,
+        ports: Vec<ServicePort>,
     ) -> Result<Self, HelmChartError> {
+        validate_service_ports(&ports).map_err(|err| HelmChartError::InvalidConfig(err.to_string()))?;
+
         let workspace_directory = crate::fs::workspace_directory(
             context.workspace_root_dir(),
             context.execution_id(),
@@ -126,8 +128,8 @@ impl<T: CloudProvider> HelmChart<T> {
         })
     }
 
-    fn public_ports(&self) -> impl Iterator<Item = &Port> + '_ {
-        self.ports.iter().filter(|port| port.publicly_accessible)
+    fn public_ports(&self) -> impl Iterator<Item = &ServicePort> + '_ {
+        self.ports.iter().filter(|port| port.is_public())
     }
 
     pub fn helm_selector(&self) -> Option<String> {
@@ -316,7 +318,7 @@ impl<T: CloudProvider> Service for HelmChart<T> {
 }
 
 pub trait HelmChartService: Service + DeploymentAction + Send {
-    fn public_ports(&self) -> Vec<&Port>;
+    fn public_ports(&self) -> Vec<&ServicePort>;
     fn advanced_settings(&self) -> &HelmChartAdvancedSettings;
     fn as_deployment_action(&self) -> &dyn DeploymentAction;
 }
@@ -325,7 +327,7 @@ impl<T: CloudProvider> HelmChartService for HelmChart<T>
 where
     HelmChart<T>: Service + DeploymentAction,
 {
-    fn public_ports(&self) -> Vec<&Port> {
+    fn public_ports(&self) -> Vec<&ServicePort> {
         self.public_ports().collect_vec()
     }
     fn advanced_settings(&self) -> &HelmChartAdvancedSettings {
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+// Helm refuses release names longer than this, as it suffixes them to build its own resources names
+pub const HELM_RELEASE_NAME_MAX_LENGTH: usize = 53;
+pub const HELM_RELEASE_OWNER_LABEL: &str = "qovery.com/service-id";
+// length of the hash replacing the service id in truncated names
+const HELM_RELEASE_NAME_HASH_LENGTH: usize = 16;
+
+// FNV-1a, as the output of the std hasher may change between rust versions while release names must not
+fn fnv1a_64(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn sanitize(prefix: &str) -> String {
+    prefix
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .trim_matches('-')
+        .to_string()
+}
+
+/// Returns the helm release name of a service: `<prefix>-<service long id>`.
+/// When it does not fit in the helm limit, the prefix is truncated and the service id is replaced by a hash of the
+/// full name, so services sharing a long prefix still get distinct release names. The same inputs always give the
+/// same name.
+pub fn helm_release_name(prefix: &str, service_long_id: &Uuid) -> String {
+    let prefix = sanitize(prefix);
+    let name = if prefix.is_empty() {
+        service_long_id.to_string()
+    } else {
+        format!("{prefix}-{service_long_id}")
+    };
+    if name.len() <= HELM_RELEASE_NAME_MAX_LENGTH {
+        return name;
+    }
+
+    let hash = format!("{:0width$x}", fnv1a_64(&name), width = HELM_RELEASE_NAME_HASH_LENGTH);
+    // prefix is ascii only after sanitizing, so we can't cut in the middle of a char
+    let max_prefix_length = HELM_RELEASE_NAME_MAX_LENGTH - HELM_RELEASE_NAME_HASH_LENGTH - 1;
+    let truncated_prefix = prefix[..prefix.len().min(max_prefix_length)].trim_end_matches('-');
+    format!("{truncated_prefix}-{hash}")
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum HelmReleaseOwnership {
+    /// No release with this name exists yet
+    NotDeployed,
+    /// The release belongs to the service
+    Owned,
+    /// The release has been deployed before owners were recorded, the service can adopt it
+    Unknown,
+    /// The release belongs to another service, deploying would overwrite it
+    OwnedByAnotherService(String),
+}
+
+/// Computes who owns a release from the labels of its helm storage secrets (one per revision).
+pub fn helm_release_ownership<'a>(
+    release_secrets_labels: impl IntoIterator<Item = &'a BTreeMap<String, String>>,
+    service_long_id: &Uuid,
+) -> HelmReleaseOwnership {
+    let service_long_id = service_long_id.to_string();
+    let mut ownership = HelmReleaseOwnership::NotDeployed;
+    for labels in release_secrets_labels {
+        match labels.get(HELM_RELEASE_OWNER_LABEL) {
+            Some(owner) if owner != &service_long_id => {
+                return HelmReleaseOwnership::OwnedByAnotherService(owner.to_string())
+            }
+            Some(_) => ownership = HelmReleaseOwnership::Owned,
+            None if ownership == HelmReleaseOwnership::NotDeployed => ownership = HelmReleaseOwnership::Unknown,
+            None => {}
+        }
+    }
+
+    ownership
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::distributions::{Alphanumeric, DistString};
+    use rand::Rng;
+    use std::collections::HashMap;
+
+    fn is_valid_release_name(name: &str) -> bool {
+        name.len() <= HELM_RELEASE_NAME_MAX_LENGTH
+            && !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            && !name.starts_with('-')
+            && !name.ends_with('-')
+    }
+
+    #[test]
+    fn test_helm_release_name_is_kept_when_it_fits() {
+        let id = Uuid::parse_str("6c3e0d5c-7a4f-4e2b-9d1a-0b8e2f1c3a4d").unwrap();
+
+        assert_eq!(
+            helm_release_name("container", &id),
+            "container-6c3e0d5c-7a4f-4e2b-9d1a-0b8e2f1c3a4d"
+        );
+        assert_eq!(helm_release_name("job", &id), "job-6c3e0d5c-7a4f-4e2b-9d1a-0b8e2f1c3a4d");
+        assert_eq!(helm_release_name("My_App", &id), "my-app-6c3e0d5c-7a4f-4e2b-9d1a-0b8e2f1c3a4d");
+        assert_eq!(helm_release_name("", &id), "6c3e0d5c-7a4f-4e2b-9d1a-0b8e2f1c3a4d");
+    }
+
+    #[test]
+    fn test_helm_release_name_truncation() {
+        let id = Uuid::parse_str("6c3e0d5c-7a4f-4e2b-9d1a-0b8e2f1c3a4d").unwrap();
+        let name = helm_release_name("postgresql-health-snapshot", &id);
+
+        assert!(is_valid_release_name(&name), "{name}");
+        assert!(name.starts_with("postgresql-health-snapsh"), "{name}");
+        assert_eq!(name, helm_release_name("postgresql-health-snapshot", &id));
+    }
+
+    #[test]
+    fn test_helm_release_name_properties() {
+        let mut rng = rand::thread_rng();
+        let mut generated: HashMap<String, (String, Uuid)> = HashMap::new();
+        // environments with similar names: long prefixes sharing everything but their last chars
+        let common_prefix = "my-very-long-environment-name-shared-by-every-environment";
+
+        for _ in 0..5_000 {
+            let prefix = match rng.gen_range(0..3) {
+                0 => Alphanumeric.sample_string(&mut rng, rng.gen_range(0..80)),
+                1 => format!("{common_prefix}-{}", rng.gen_range(0..50)),
+                _ => common_prefix[..rng.gen_range(0..common_prefix.len())].to_string(),
+            };
+            let id = if rng.gen_bool(0.5) {
+                Uuid::new_v4()
+            } else {
+                // same service with different prefixes
+                Uuid::nil()
+            };
+
+            let name = helm_release_name(&prefix, &id);
+            assert!(is_valid_release_name(&name), "{prefix} -> {name}");
+            assert_eq!(name, helm_release_name(&prefix, &id), "generation must be deterministic");
+
+            let key = (sanitize(&prefix), id);
+            if let Some(previous) = generated.insert(name.clone(), key.clone()) {
+                assert_eq!(previous, key, "{name} generated for {previous:?} and {key:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_helm_release_ownership() {
+        let id = Uuid::new_v4();
+        let owned = BTreeMap::from([(HELM_RELEASE_OWNER_LABEL.to_string(), id.to_string())]);
+        let other = BTreeMap::from([(HELM_RELEASE_OWNER_LABEL.to_string(), "other".to_string())]);
+        let legacy = BTreeMap::from([("owner".to_string(), "helm".to_string())]);
+
+        assert_eq!(helm_release_ownership([], &id), HelmReleaseOwnership::NotDeployed);
+        assert_eq!(helm_release_ownership([&legacy], &id), HelmReleaseOwnership::Unknown);
+        assert_eq!(helm_release_ownership([&legacy, &owned], &id), HelmReleaseOwnership::Owned);
+        assert_eq!(helm_release_ownership([&owned, &legacy], &id), HelmReleaseOwnership::Owned);
+        assert_eq!(
+            helm_release_ownership([&owned, &other, &legacy], &id),
+            HelmReleaseOwnership::OwnedByAnotherService("other".to_string())
+        );
+    }
+}
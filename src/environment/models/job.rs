@@ -1,12 +1,15 @@
+use crate::environment::action::database_credentials::{service_database_credentials, DatabaseCredentialsTeraContext};
 use crate::environment::action::DeploymentAction;
 use crate::environment::models::annotations_group::AnnotationsGroupTeraContext;
 use crate::environment::models::container::{ClusterTeraContext, RegistryTeraContext};
+use crate::environment::models::helm_release::helm_release_name;
 use crate::environment::models::labels_group::LabelsGroupTeraContext;
 use crate::environment::models::probe::Probe;
 use crate::environment::models::registry_image_source::RegistryImageSource;
 use crate::environment::models::types::{CloudProvider, ToTeraContext};
 use crate::environment::models::utils;
-use crate::events::{EventDetails, Stage, Transmitter};
+use crate::errors::EngineError;
+use crate::events::{EnvironmentStep, EventDetails, Stage, Transmitter};
 use crate::infrastructure::models::build_platform::Build;
 use crate::infrastructure::models::cloud_provider::service::{Action, Service, ServiceType};
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
@@ -145,7 +148,7 @@ impl<T: CloudProvider> Job<T> {
     }
 
     pub fn helm_release_name(&self) -> String {
-        format!("job-{}", self.long_id)
+        helm_release_name("job", &self.long_id)
     }
 
     pub fn helm_chart_dir(&self) -> String {
@@ -164,8 +167,32 @@ impl<T: CloudProvider> Job<T> {
         self.max_nb_restart
     }
 
-    pub(crate) fn default_tera_context(&self, target: &DeploymentTarget) -> JobTeraContext {
+    /// Rejects the probes and the cron job timezone the api server of the cluster doesn't serve
+    pub(crate) fn check_cluster_features(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let capabilities = &target.capabilities;
+        let result = capabilities
+            .check_probes(self.readiness_probe.iter().chain(self.liveness_probe.iter()))
+            .and_then(|_| match &self.schedule {
+                JobSchedule::Cron { timezone, .. } => capabilities.cron_job_timezone(timezone).map(|_| ()),
+                JobSchedule::OnStart { .. } | JobSchedule::OnPause { .. } | JobSchedule::OnDelete { .. } => Ok(()),
+            });
+
+        result.map_err(|err| {
+            Box::new(EngineError::new_k8s_unsupported_feature(
+                self.get_event_details(Stage::Environment(EnvironmentStep::Deploy)),
+                self.name(),
+                err.to_string(),
+            ))
+        })
+    }
+
+    pub(crate) fn default_tera_context(&self, target: &DeploymentTarget) -> Result<JobTeraContext, Box<EngineError>> {
         let environment = target.environment;
+        let (environment_variables, database_credentials) = service_database_credentials(
+            target,
+            &self.environment_variables,
+            &self.get_event_details(Stage::Environment(EnvironmentStep::Deploy)),
+        )?;
         let kubernetes = target.kubernetes;
         let deployment_affinity_node_required = utils::add_arch_to_deployment_affinity_node(
             &self.advanced_settings.deployment_affinity_node_required,
@@ -223,11 +250,16 @@ impl<T: CloudProvider> Job<T> {
                 with_rbac: matches!(self.schedule.lifecycle_type(), Some(LifecycleType::TERRAFORM)),
                 cronjob_schedule: match &self.schedule {
                     JobSchedule::OnStart { .. } | JobSchedule::OnPause { .. } | JobSchedule::OnDelete { .. } => None,
-                    JobSchedule::Cron { schedule, .. } => Some(schedule.clone()),
+                    JobSchedule::Cron { schedule, .. } => Some(schedule.to_string()),
                 },
                 cronjob_timezone: match &self.schedule {
                     JobSchedule::OnStart { .. } | JobSchedule::OnPause { .. } | JobSchedule::OnDelete { .. } => None,
-                    JobSchedule::Cron { timezone, .. } => Some(timezone.clone()),
+                    JobSchedule::Cron { timezone, .. } => target
+                        .capabilities
+                        .cron_job_timezone(timezone)
+                        .ok()
+                        .flatten()
+                        .map(|timezone| timezone.to_string()),
                 },
                 readiness_probe: self.readiness_probe.clone(),
                 liveness_probe: self.liveness_probe.clone(),
@@ -240,14 +272,15 @@ impl<T: CloudProvider> Job<T> {
                     secret_name: format!("{}-registry", self.kube_name()),
                     docker_json_config: Some(docker_json.to_string()),
                 }),
-            environment_variables: self.environment_variables.clone(),
+            environment_variables,
+            database_credentials,
             mounted_files: self.mounted_files.clone().into_iter().collect::<Vec<_>>(),
             resource_expiration_in_seconds: Some(kubernetes.advanced_settings().pleco_resources_ttl),
             annotations_group: self.annotations_group.clone(),
             labels_group: self.labels_group.clone(),
         };
 
-        ctx
+        Ok(ctx)
     }
 
     pub fn service_type(&self) -> ServiceType {
@@ -378,12 +411,16 @@ impl<T: CloudProvider> Service for Job<T> {
 pub trait JobService: Service + DeploymentAction + ToTeraContext + Send {
     fn advanced_settings(&self) -> &JobAdvancedSettings;
     fn image_full(&self) -> String;
+    /// Image copied to the cluster registry before running the job, none when it is built
+    fn image_to_mirror(&self) -> Option<&RegistryImageSource>;
     fn startup_timeout(&self) -> Duration;
     fn as_deployment_action(&self) -> &dyn DeploymentAction;
     fn job_schedule(&self) -> &JobSchedule;
     fn max_duration(&self) -> &Duration;
     fn max_restarts(&self) -> u32;
     fn is_force_trigger(&self) -> bool;
+    /// Registry repositories removed with the job, empty when the image comes from an external registry
+    fn owned_registry_repositories(&self) -> Vec<&str>;
 }
 
 impl<T: CloudProvider> JobService for Job<T>
@@ -408,6 +445,13 @@ where
         }
     }
 
+    fn image_to_mirror(&self) -> Option<&RegistryImageSource> {
+        match &self.image_source {
+            ImageSource::Registry { source } => Some(source),
+            ImageSource::Build { .. } => None,
+        }
+    }
+
     fn startup_timeout(&self) -> Duration {
         let readiness_probe_timeout = if let Some(p) = &self.readiness_probe {
             p.initial_delay_seconds + ((p.timeout_seconds + p.period_seconds) * p.failure_threshold)
@@ -445,6 +489,19 @@ where
     fn is_force_trigger(&self) -> bool {
         self.force_trigger
     }
+
+    fn owned_registry_repositories(&self) -> Vec<&str> {
+        match &self.image_source {
+            ImageSource::Registry { .. } => vec![],
+            ImageSource::Build { source } if self.should_delete_shared_registry => {
+                vec![
+                    source.image.shared_repository_name(),
+                    source.image.legacy_repository_name(),
+                ]
+            }
+            ImageSource::Build { source } => vec![source.image.legacy_repository_name()],
+        }
+    }
 }
 
 pub enum ImageSource {
@@ -489,6 +546,7 @@ pub(crate) struct JobTeraContext {
     pub(crate) service: ServiceTeraContext,
     pub(crate) registry: Option<RegistryTeraContext>,
     pub(crate) environment_variables: Vec<EnvironmentVariable>,
+    pub(crate) database_credentials: DatabaseCredentialsTeraContext,
     pub(crate) mounted_files: Vec<MountedFile>,
     pub(crate) resource_expiration_in_seconds: Option<i32>,
     pub(crate) annotations_group: AnnotationsGroupTeraContext,
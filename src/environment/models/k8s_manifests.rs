@@ -0,0 +1,657 @@
+use crate::environment::action::DeploymentAction;
+use crate::events::{EventDetails, Stage, Transmitter};
+use crate::infrastructure::models::build_platform::{Build, Credentials, SshKey};
+use crate::infrastructure::models::cloud_provider::service::{Action, Service, ServiceType};
+use crate::io_models::context::Context;
+use crate::io_models::models::EnvironmentVariable;
+use crate::utilities::to_short_id;
+use kube::api::DynamicObject;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
+use uuid::Uuid;
+
+/// Field manager used for server side apply, so conflicts with other managers are visible in managedFields.
+pub const K8S_MANIFESTS_FIELD_MANAGER: &str = "qovery";
+/// Annotation of the applied set config map, listing every object applied during the last deployment.
+pub const K8S_MANIFESTS_APPLIED_SET_ANNOTATION: &str = "qovery.com/applied-set";
+
+#[derive(thiserror::Error, Debug)]
+pub enum K8sManifestsError {
+    #[error("K8s manifests invalid configuration: {0}")]
+    InvalidConfig(String),
+}
+
+pub struct K8sManifests {
+    pub(crate) mk_event_details: Box<dyn Fn(Stage) -> EventDetails + Send + Sync>,
+    pub(crate) id: String,
+    pub(crate) long_id: Uuid,
+    pub(crate) name: String,
+    pub(crate) kube_name: String,
+    pub(crate) action: Action,
+    pub(crate) git_url: Url,
+    pub(crate) get_credentials: Box<dyn Fn() -> anyhow::Result<Option<Credentials>> + Send + Sync>,
+    pub(crate) commit_id: String,
+    pub(crate) root_path: PathBuf,
+    pub(crate) ssh_keys: Vec<SshKey>,
+    pub(crate) kustomize: bool,
+    pub(crate) timeout: Duration,
+    pub(crate) allowed_kinds_outside_namespace: Vec<String>,
+    pub(crate) workspace_directory: PathBuf,
+}
+
+impl K8sManifests {
+    pub fn new(
+        context: &Context,
+        long_id: Uuid,
+        name: String,
+        kube_name: String,
+        action: Action,
+        git_url: Url,
+        get_credentials: Box<dyn Fn() -> anyhow::Result<Option<Credentials>> + Send + Sync>,
+        commit_id: String,
+        root_path: PathBuf,
+        ssh_keys: Vec<SshKey>,
+        kustomize: bool,
+        timeout: Duration,
+        allowed_kinds_outside_namespace: Vec<String>,
+        mk_event_details: impl Fn(Transmitter) -> EventDetails,
+    ) -> Result<Self, K8sManifestsError> {
+        let workspace_directory = crate::fs::workspace_directory(
+            context.workspace_root_dir(),
+            context.execution_id(),
+            format!("k8s_manifests/{long_id}"),
+        )
+        .map_err(|_| K8sManifestsError::InvalidConfig("Can't create workspace directory".to_string()))?;
+
+        // Normalize path to be relative in order to concat it easily with the clone directory
+        let root_path = match root_path.strip_prefix("/") {
+            Ok(path) => path.to_path_buf(),
+            Err(_) => root_path,
+        };
+        if root_path.components().any(|c| c == std::path::Component::ParentDir) {
+            return Err(K8sManifestsError::InvalidConfig(format!(
+                "Manifests path {root_path:?} must not go outside of the git repository"
+            )));
+        }
+
+        let event_details = mk_event_details(Transmitter::K8sManifests(long_id, name.to_string()));
+        let mk_event_details = move |stage: Stage| EventDetails::clone_changing_stage(event_details.clone(), stage);
+        Ok(Self {
+            mk_event_details: Box::new(mk_event_details),
+            id: to_short_id(&long_id),
+            long_id,
+            name,
+            kube_name,
+            action,
+            git_url,
+            get_credentials,
+            commit_id,
+            root_path,
+            ssh_keys,
+            kustomize,
+            timeout,
+            allowed_kinds_outside_namespace,
+            workspace_directory,
+        })
+    }
+
+    pub fn service_type(&self) -> ServiceType {
+        ServiceType::K8sManifests
+    }
+
+    pub fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn action(&self) -> &Action {
+        &self.action
+    }
+
+    pub fn kube_label_selector(&self) -> String {
+        format!("qovery.com/service-id={}", self.long_id)
+    }
+
+    pub fn workspace_directory(&self) -> &Path {
+        &self.workspace_directory
+    }
+
+    /// Directory where the git repository is cloned
+    pub fn repository_workspace_directory(&self) -> PathBuf {
+        self.workspace_directory.join("repository")
+    }
+
+    /// Directory, inside the cloned repository, containing the manifests or the kustomization file
+    pub fn manifests_workspace_directory(&self) -> PathBuf {
+        self.repository_workspace_directory().join(&self.root_path)
+    }
+
+    pub fn applied_set_config_map_name(&self) -> String {
+        format!("{}-applied-set", self.kube_name)
+    }
+
+    pub fn is_kustomize(&self) -> bool {
+        self.kustomize
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn allowed_kinds_outside_namespace(&self) -> &[String] {
+        &self.allowed_kinds_outside_namespace
+    }
+}
+
+impl Service for K8sManifests {
+    fn service_type(&self) -> ServiceType {
+        self.service_type()
+    }
+
+    fn id(&self) -> &str {
+        self.id()
+    }
+
+    fn long_id(&self) -> &Uuid {
+        &self.long_id
+    }
+
+    fn name(&self) -> &str {
+        self.name()
+    }
+
+    fn version(&self) -> String {
+        self.commit_id.clone()
+    }
+
+    fn kube_name(&self) -> &str {
+        &self.kube_name
+    }
+
+    fn kube_label_selector(&self) -> String {
+        self.kube_label_selector()
+    }
+
+    fn get_event_details(&self, stage: Stage) -> EventDetails {
+        (self.mk_event_details)(stage)
+    }
+
+    fn action(&self) -> &Action {
+        self.action()
+    }
+
+    fn as_service(&self) -> &dyn Service {
+        self
+    }
+
+    fn as_service_mut(&mut self) -> &mut dyn Service {
+        self
+    }
+
+    fn build(&self) -> Option<&Build> {
+        None
+    }
+
+    fn build_mut(&mut self) -> Option<&mut Build> {
+        None
+    }
+
+    fn get_environment_variables(&self) -> Vec<EnvironmentVariable> {
+        vec![]
+    }
+}
+
+pub trait K8sManifestsService: Service + DeploymentAction + Send {
+    fn as_deployment_action(&self) -> &dyn DeploymentAction;
+}
+
+impl K8sManifestsService for K8sManifests {
+    fn as_deployment_action(&self) -> &dyn DeploymentAction {
+        self
+    }
+}
+
+/// Identifies an applied object, serialized as `apiVersion:kind:namespace:name` in the applied set annotation.
+/// Namespace is empty for cluster scoped objects.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ManifestObjectRef {
+    pub api_version: String,
+    pub kind: String,
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+impl ManifestObjectRef {
+    pub fn from_object(object: &DynamicObject) -> Option<ManifestObjectRef> {
+        let types = object.types.as_ref()?;
+        Some(ManifestObjectRef {
+            api_version: types.api_version.clone(),
+            kind: types.kind.clone(),
+            namespace: object.metadata.namespace.clone(),
+            name: object.metadata.name.clone()?,
+        })
+    }
+
+    /// Returns (group, version) of the object api version
+    pub fn group_version(&self) -> (&str, &str) {
+        match self.api_version.split_once('/') {
+            Some((group, version)) => (group, version),
+            None => ("", self.api_version.as_str()),
+        }
+    }
+}
+
+impl Display for ManifestObjectRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            self.api_version,
+            self.kind,
+            self.namespace.as_deref().unwrap_or_default(),
+            self.name
+        )
+    }
+}
+
+impl FromStr for ManifestObjectRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split(':').collect();
+        match parts.as_slice() {
+            [api_version, kind, namespace, name] if !api_version.is_empty() && !kind.is_empty() && !name.is_empty() => {
+                Ok(ManifestObjectRef {
+                    api_version: api_version.to_string(),
+                    kind: kind.to_string(),
+                    namespace: if namespace.is_empty() {
+                        None
+                    } else {
+                        Some(namespace.to_string())
+                    },
+                    name: name.to_string(),
+                })
+            }
+            _ => Err(format!("Invalid applied object reference `{s}`")),
+        }
+    }
+}
+
+pub fn encode_applied_set(objects: &BTreeSet<ManifestObjectRef>) -> String {
+    objects.iter().map(|o| o.to_string()).collect::<Vec<_>>().join(",")
+}
+
+pub fn decode_applied_set(annotation: &str) -> Result<BTreeSet<ManifestObjectRef>, String> {
+    annotation
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(ManifestObjectRef::from_str)
+        .collect()
+}
+
+/// Objects applied by the previous deployment which are no longer part of the manifests.
+pub fn objects_to_prune(
+    previously_applied: &BTreeSet<ManifestObjectRef>,
+    currently_applied: &BTreeSet<ManifestObjectRef>,
+) -> Vec<ManifestObjectRef> {
+    previously_applied.difference(currently_applied).cloned().collect()
+}
+
+/// Parses plain YAML (multi documents) or `kubectl kustomize` output into kube objects.
+/// Empty documents are skipped and `List` kinds are flattened.
+pub fn parse_manifests(content: &str) -> Result<Vec<DynamicObject>, String> {
+    let mut objects = vec![];
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = serde_yaml::Value::deserialize(document).map_err(|e| format!("Invalid YAML document: {e}"))?;
+        if value.is_null() {
+            continue;
+        }
+
+        let items = match value.get("kind").and_then(|k| k.as_str()) {
+            Some(kind) if kind.ends_with("List") && value.get("items").is_some() => value
+                .get("items")
+                .and_then(|items| items.as_sequence())
+                .cloned()
+                .unwrap_or_default(),
+            _ => vec![value],
+        };
+
+        for item in items {
+            let object: DynamicObject = serde_yaml::from_value(item)
+                .map_err(|e| format!("Cannot deserialize manifest into kube object: {e}"))?;
+            if object.types.is_none() {
+                return Err(format!(
+                    "Object `{}` is missing apiVersion or kind",
+                    object.metadata.name.unwrap_or_default()
+                ));
+            }
+            if object.metadata.name.as_deref().unwrap_or_default().is_empty() {
+                return Err("Object without metadata.name found, generateName is not supported".to_string());
+            }
+            objects.push(object);
+        }
+    }
+
+    Ok(objects)
+}
+
+/// Enforces namespace boundaries on the objects and injects Qovery labels.
+/// * namespaced objects without namespace are moved in the environment namespace
+/// * objects in another namespace or cluster scoped are rejected unless their kind is allowlisted
+///
+/// `is_namespaced` resolves the scope of an (apiVersion, kind), usually through the kube discovery api.
+/// All violations are returned at once.
+pub fn prepare_objects(
+    objects: Vec<DynamicObject>,
+    namespace: &str,
+    allowed_kinds_outside_namespace: &[String],
+    labels: &BTreeMap<String, String>,
+    is_namespaced: impl Fn(&str, &str) -> Result<bool, String>,
+) -> Result<Vec<DynamicObject>, Vec<String>> {
+    let mut violations = vec![];
+    let mut seen = BTreeSet::new();
+    let mut prepared = Vec::with_capacity(objects.len());
+
+    for mut object in objects {
+        let Some(types) = object.types.clone() else {
+            violations.push("Object without apiVersion or kind found".to_string());
+            continue;
+        };
+        let name = object.metadata.name.clone().unwrap_or_default();
+        let is_allowlisted = allowed_kinds_outside_namespace.iter().any(|k| k == &types.kind);
+
+        match is_namespaced(&types.api_version, &types.kind) {
+            Err(err) => {
+                violations.push(format!("{}/{name}: {err}", types.kind));
+                continue;
+            }
+            Ok(true) => match object.metadata.namespace.as_deref() {
+                None | Some("") => object.metadata.namespace = Some(namespace.to_string()),
+                Some(ns) if ns == namespace => {}
+                Some(ns) if is_allowlisted => info!("{}/{name} allowed in namespace {ns}", types.kind),
+                Some(ns) => {
+                    violations.push(format!(
+                        "{}/{name}: namespace `{ns}` is outside of the environment namespace `{namespace}`",
+                        types.kind
+                    ));
+                    continue;
+                }
+            },
+            Ok(false) => {
+                if !is_allowlisted {
+                    violations.push(format!(
+                        "{}/{name}: cluster scoped objects are not allowed, kind must be explicitly allowlisted",
+                        types.kind
+                    ));
+                    continue;
+                }
+                object.metadata.namespace = None;
+            }
+        }
+
+        let object_ref = ManifestObjectRef::from_object(&object).unwrap_or_else(|| ManifestObjectRef {
+            api_version: types.api_version.clone(),
+            kind: types.kind.clone(),
+            namespace: object.metadata.namespace.clone(),
+            name: name.clone(),
+        });
+        if !seen.insert(object_ref) {
+            violations.push(format!("{}/{name}: object is defined more than once", types.kind));
+            continue;
+        }
+
+        object
+            .metadata
+            .labels
+            .get_or_insert_with(Default::default)
+            .extend(labels.iter().map(|(k, v)| (k.clone(), v.clone())));
+        prepared.push(object);
+    }
+
+    if violations.is_empty() {
+        Ok(prepared)
+    } else {
+        Err(violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object_ref(kind: &str, namespace: Option<&str>, name: &str) -> ManifestObjectRef {
+        ManifestObjectRef {
+            api_version: if kind == "Deployment" { "apps/v1" } else { "v1" }.to_string(),
+            kind: kind.to_string(),
+            namespace: namespace.map(|ns| ns.to_string()),
+            name: name.to_string(),
+        }
+    }
+
+    fn is_namespaced(_api_version: &str, kind: &str) -> Result<bool, String> {
+        match kind {
+            "ClusterRole" | "Namespace" | "CustomResourceDefinition" => Ok(false),
+            "Unknown" => Err("kind is not served by the cluster".to_string()),
+            _ => Ok(true),
+        }
+    }
+
+    const MANIFESTS: &str = r#"
+---
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: config
+data:
+  key: value
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app
+  labels:
+    app: app
+spec:
+  selector:
+    matchLabels:
+      app: app
+  template:
+    metadata:
+      labels:
+        app: app
+    spec:
+      containers:
+        - name: app
+          image: nginx
+---
+apiVersion: v1
+kind: List
+items:
+  - apiVersion: v1
+    kind: Service
+    metadata:
+      name: app
+    spec:
+      ports:
+        - port: 80
+"#;
+
+    #[test]
+    fn test_parse_manifests() {
+        let objects = parse_manifests(MANIFESTS).unwrap();
+        let refs: Vec<ManifestObjectRef> = objects.iter().filter_map(ManifestObjectRef::from_object).collect();
+        assert_eq!(
+            refs,
+            vec![
+                object_ref("ConfigMap", None, "config"),
+                object_ref("Deployment", None, "app"),
+                object_ref("Service", None, "app"),
+            ]
+        );
+
+        assert!(parse_manifests("apiVersion: v1\nkind: ConfigMap\nmetadata:\n  generateName: config-\n").is_err());
+        assert!(parse_manifests("metadata:\n  name: config\n").is_err());
+        assert!(parse_manifests("key: [unclosed").is_err());
+    }
+
+    #[test]
+    fn test_prepare_objects_sets_namespace_and_labels() {
+        let labels = BTreeMap::from([("qovery.com/service-id".to_string(), "id".to_string())]);
+        let objects =
+            prepare_objects(parse_manifests(MANIFESTS).unwrap(), "env-ns", &[], &labels, is_namespaced).unwrap();
+
+        assert_eq!(objects.len(), 3);
+        for object in &objects {
+            assert_eq!(object.metadata.namespace.as_deref(), Some("env-ns"));
+            assert_eq!(object.metadata.labels.as_ref().unwrap()["qovery.com/service-id"], "id");
+        }
+        // user labels are kept
+        assert_eq!(objects[1].metadata.labels.as_ref().unwrap()["app"], "app");
+    }
+
+    #[test]
+    fn test_prepare_objects_namespace_enforcement() {
+        let manifests = r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: in-env
+  namespace: env-ns
+---
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: elsewhere
+  namespace: kube-system
+---
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRole
+metadata:
+  name: reader
+---
+apiVersion: example.com/v1
+kind: Unknown
+metadata:
+  name: unknown
+---
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: in-env
+"#;
+
+        let violations = prepare_objects(
+            parse_manifests(manifests).unwrap(),
+            "env-ns",
+            &[],
+            &BTreeMap::new(),
+            is_namespaced,
+        )
+        .unwrap_err();
+        assert_eq!(
+            violations,
+            vec![
+                "ConfigMap/elsewhere: namespace `kube-system` is outside of the environment namespace `env-ns`",
+                "ClusterRole/reader: cluster scoped objects are not allowed, kind must be explicitly allowlisted",
+                "Unknown/unknown: kind is not served by the cluster",
+                "ConfigMap/in-env: object is defined more than once",
+            ]
+        );
+
+        // allowlisted kinds are accepted outside of the environment namespace
+        let manifests = r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: elsewhere
+  namespace: kube-system
+---
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRole
+metadata:
+  name: reader
+  namespace: should-be-dropped
+"#;
+        let objects = prepare_objects(
+            parse_manifests(manifests).unwrap(),
+            "env-ns",
+            &["ConfigMap".to_string(), "ClusterRole".to_string()],
+            &BTreeMap::new(),
+            is_namespaced,
+        )
+        .unwrap();
+        assert_eq!(objects[0].metadata.namespace.as_deref(), Some("kube-system"));
+        assert_eq!(objects[1].metadata.namespace, None);
+    }
+
+    #[test]
+    fn test_applied_set_encoding_roundtrip() {
+        let set = BTreeSet::from([
+            object_ref("Deployment", Some("env-ns"), "app"),
+            object_ref("ConfigMap", Some("env-ns"), "config"),
+            ManifestObjectRef {
+                api_version: "rbac.authorization.k8s.io/v1".to_string(),
+                kind: "ClusterRole".to_string(),
+                namespace: None,
+                name: "reader".to_string(),
+            },
+        ]);
+
+        let encoded = encode_applied_set(&set);
+        assert!(encoded.contains("rbac.authorization.k8s.io/v1:ClusterRole::reader"));
+        assert!(encoded.contains("apps/v1:Deployment:env-ns:app"));
+        assert_eq!(decode_applied_set(&encoded).unwrap(), set);
+
+        assert_eq!(decode_applied_set("").unwrap(), BTreeSet::new());
+        assert!(decode_applied_set("v1:ConfigMap:name").is_err());
+        assert!(decode_applied_set("v1::env-ns:name").is_err());
+    }
+
+    #[test]
+    fn test_group_version() {
+        assert_eq!(object_ref("Deployment", None, "app").group_version(), ("apps", "v1"));
+        assert_eq!(object_ref("ConfigMap", None, "app").group_version(), ("", "v1"));
+    }
+
+    #[test]
+    fn test_objects_to_prune() {
+        let previous = BTreeSet::from([
+            object_ref("ConfigMap", Some("env-ns"), "config"),
+            object_ref("ConfigMap", Some("env-ns"), "old-config"),
+            object_ref("Deployment", Some("env-ns"), "app"),
+            object_ref("Deployment", Some("env-ns"), "old-app"),
+        ]);
+        let current = BTreeSet::from([
+            object_ref("ConfigMap", Some("env-ns"), "config"),
+            object_ref("Deployment", Some("env-ns"), "app"),
+            object_ref("Service", Some("env-ns"), "app"),
+        ]);
+
+        assert_eq!(
+            objects_to_prune(&previous, &current),
+            vec![
+                object_ref("Deployment", Some("env-ns"), "old-app"),
+                object_ref("ConfigMap", Some("env-ns"), "old-config"),
+            ]
+        );
+
+        // first deployment, nothing to prune
+        assert!(objects_to_prune(&BTreeSet::new(), &current).is_empty());
+        // everything removed from the manifests
+        assert_eq!(objects_to_prune(&previous, &BTreeSet::new()).len(), previous.len());
+        // same object moved to another namespace is pruned from the old one
+        let moved = BTreeSet::from([object_ref("ConfigMap", Some("other-ns"), "config")]);
+        assert_eq!(
+            objects_to_prune(&BTreeSet::from([object_ref("ConfigMap", Some("env-ns"), "config")]), &moved),
+            vec![object_ref("ConfigMap", Some("env-ns"), "config")]
+        );
+    }
+}
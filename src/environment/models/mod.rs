@@ -1,22 +1,35 @@
 pub mod abort;
 mod annotations_group;
 pub mod application;
+pub mod autoscaling;
 pub mod aws;
+pub mod basic_auth;
+pub mod bucket;
 pub mod container;
+pub mod cron_schedule;
 pub mod database;
+pub mod database_health;
+pub mod database_maintenance;
+pub mod database_parameters;
 pub(crate) mod database_utils;
 pub mod domain;
 pub mod environment;
 pub mod gcp;
+pub mod graceful_shutdown;
 pub mod helm_chart;
+pub mod helm_release;
 pub mod job;
+pub mod k8s_manifests;
 pub mod kubernetes;
 mod labels_group;
+pub mod pod_security;
 pub mod probe;
 pub mod registry_image_source;
 pub mod router;
 pub mod scaleway;
+pub mod secret_replication;
 pub mod selfmanaged;
+pub mod service_port;
 pub mod third_parties;
 pub mod types;
 pub mod utils;
@@ -0,0 +1,627 @@
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::{Container, Pod, PodSpec};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// Capabilities a container is allowed to add under the baseline profile.
+/// https://kubernetes.io/docs/concepts/security/pod-security-standards/#baseline
+const BASELINE_ALLOWED_CAPABILITIES: [&str; 13] = [
+    "AUDIT_WRITE",
+    "CHOWN",
+    "DAC_OVERRIDE",
+    "FOWNER",
+    "FSETID",
+    "KILL",
+    "MKNOD",
+    "NET_BIND_SERVICE",
+    "SETFCAP",
+    "SETGID",
+    "SETPCAP",
+    "SETUID",
+    "SYS_CHROOT",
+];
+
+/// Pod Security Standard enforced on the environment namespace.
+/// https://kubernetes.io/docs/concepts/security/pod-security-standards/
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PodSecurityProfile {
+    #[default]
+    Privileged,
+    Baseline,
+    Restricted,
+}
+
+impl PodSecurityProfile {
+    pub fn to_k8s_level(&self) -> &str {
+        match self {
+            PodSecurityProfile::Privileged => "privileged",
+            PodSecurityProfile::Baseline => "baseline",
+            PodSecurityProfile::Restricted => "restricted",
+        }
+    }
+
+    /// Labels to set on the namespace so the pod security admission controller enforces the profile.
+    /// All modes are always set, so changing the profile on redeploy overrides previous values.
+    pub fn namespace_labels(&self) -> BTreeMap<String, String> {
+        ["enforce", "warn", "audit"]
+            .iter()
+            .flat_map(|mode| {
+                [
+                    (format!("pod-security.kubernetes.io/{mode}"), self.to_k8s_level().to_string()),
+                    (format!("pod-security.kubernetes.io/{mode}-version"), "latest".to_string()),
+                ]
+            })
+            .collect()
+    }
+
+    /// Whether our own charts must render restricted compliant security contexts (seccomp, capabilities,
+    /// privilege escalation and non root user). Images running as root will refuse to start, as they would
+    /// be rejected by the admission controller anyway.
+    pub fn apply_safe_defaults(&self) -> bool {
+        *self == PodSecurityProfile::Restricted
+    }
+}
+
+impl Display for PodSecurityProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.to_k8s_level())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PodSecurityViolation {
+    pub service_name: String,
+    pub workload: String,
+    pub field: String,
+    pub message: String,
+}
+
+impl Display for PodSecurityViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "service `{}` ({}): `{}` {}",
+            self.service_name, self.workload, self.field, self.message
+        )
+    }
+}
+
+/// Validates every workload of rendered manifests (i.e: helm template output) against the profile.
+/// Documents which are not workloads are ignored.
+pub fn validate_rendered_manifests(
+    profile: PodSecurityProfile,
+    service_name: &str,
+    manifests: &str,
+) -> Result<Vec<PodSecurityViolation>, serde_yaml::Error> {
+    let mut violations = vec![];
+    if profile == PodSecurityProfile::Privileged {
+        return Ok(violations);
+    }
+
+    for document in serde_yaml::Deserializer::from_str(manifests) {
+        let value = serde_yaml::Value::deserialize(document)?;
+        let kind = value
+            .get("kind")
+            .and_then(|k| k.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let name = value
+            .get("metadata")
+            .and_then(|m| m.get("name"))
+            .and_then(|n| n.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        let (pod_spec, prefix) = match kind.as_str() {
+            "Pod" => (serde_yaml::from_value::<Pod>(value)?.spec, "spec"),
+            "Deployment" => (
+                serde_yaml::from_value::<Deployment>(value)?
+                    .spec
+                    .and_then(|s| s.template.spec),
+                "spec.template.spec",
+            ),
+            "StatefulSet" => (
+                serde_yaml::from_value::<StatefulSet>(value)?
+                    .spec
+                    .and_then(|s| s.template.spec),
+                "spec.template.spec",
+            ),
+            "DaemonSet" => (
+                serde_yaml::from_value::<DaemonSet>(value)?
+                    .spec
+                    .and_then(|s| s.template.spec),
+                "spec.template.spec",
+            ),
+            "Job" => (
+                serde_yaml::from_value::<Job>(value)?.spec.and_then(|s| s.template.spec),
+                "spec.template.spec",
+            ),
+            "CronJob" => (
+                serde_yaml::from_value::<CronJob>(value)?
+                    .spec
+                    .and_then(|s| s.job_template.spec)
+                    .and_then(|s| s.template.spec),
+                "spec.jobTemplate.spec.template.spec",
+            ),
+            _ => continue,
+        };
+
+        if let Some(pod_spec) = pod_spec {
+            let workload = format!("{kind}/{name}");
+            violations.extend(
+                validate_pod_spec(profile, service_name, &pod_spec)
+                    .into_iter()
+                    .map(|v| PodSecurityViolation {
+                        workload: workload.clone(),
+                        field: format!("{prefix}.{}", v.field),
+                        ..v
+                    }),
+            );
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Static validation of a pod spec against the profile, returning one violation per offending field.
+pub fn validate_pod_spec(
+    profile: PodSecurityProfile,
+    service_name: &str,
+    pod_spec: &PodSpec,
+) -> Vec<PodSecurityViolation> {
+    let mut violations = vec![];
+    if profile == PodSecurityProfile::Privileged {
+        return violations;
+    }
+
+    let mut violation = |field: String, message: &str| {
+        violations.push(PodSecurityViolation {
+            service_name: service_name.to_string(),
+            workload: "".to_string(),
+            field,
+            message: message.to_string(),
+        })
+    };
+
+    // Baseline
+    for (field, value) in [
+        ("hostNetwork", pod_spec.host_network),
+        ("hostPID", pod_spec.host_pid),
+        ("hostIPC", pod_spec.host_ipc),
+    ] {
+        if value == Some(true) {
+            violation(field.to_string(), "must not be set to true");
+        }
+    }
+
+    for volume in pod_spec.volumes.iter().flatten() {
+        if volume.host_path.is_some() {
+            violation(format!("volumes[{}].hostPath", volume.name), "hostPath volumes are forbidden");
+        }
+    }
+
+    let containers = pod_spec
+        .init_containers
+        .iter()
+        .flatten()
+        .map(|c| ("initContainers", c))
+        .chain(pod_spec.containers.iter().map(|c| ("containers", c)));
+
+    let pod_security_context = pod_spec.security_context.as_ref();
+    let pod_run_as_non_root = pod_security_context.and_then(|sc| sc.run_as_non_root);
+    let pod_seccomp_profile = pod_security_context
+        .and_then(|sc| sc.seccomp_profile.as_ref())
+        .map(|p| p.type_.as_str());
+
+    // Restricted, on the pod: containers inherit these values, even when they override them
+    if profile == PodSecurityProfile::Restricted {
+        if pod_security_context.and_then(|sc| sc.run_as_user) == Some(0) {
+            violation("securityContext.runAsUser".to_string(), "must not be set to 0");
+        }
+        if pod_seccomp_profile == Some("Unconfined") {
+            violation(
+                "securityContext.seccompProfile.type".to_string(),
+                "must not be set to `Unconfined`",
+            );
+        }
+    }
+
+    for (kind, container) in containers {
+        let field = |name: &str| format!("{kind}[{}].securityContext.{name}", container.name);
+        let security_context = container.security_context.as_ref();
+
+        if security_context.and_then(|sc| sc.privileged) == Some(true) {
+            violation(field("privileged"), "privileged containers are forbidden");
+        }
+
+        let added_capabilities = added_capabilities(container);
+        let allowed_capabilities: &[&str] = match profile {
+            PodSecurityProfile::Restricted => &["NET_BIND_SERVICE"],
+            _ => &BASELINE_ALLOWED_CAPABILITIES,
+        };
+        for capability in added_capabilities
+            .iter()
+            .filter(|c| !allowed_capabilities.contains(&c.as_str()))
+        {
+            violation(
+                field("capabilities.add"),
+                &format!("adding capability `{capability}` is forbidden"),
+            );
+        }
+
+        if profile != PodSecurityProfile::Restricted {
+            continue;
+        }
+
+        // Restricted
+        if security_context.and_then(|sc| sc.allow_privilege_escalation) != Some(false) {
+            violation(field("allowPrivilegeEscalation"), "must be set to false");
+        }
+
+        let run_as_non_root = security_context
+            .and_then(|sc| sc.run_as_non_root)
+            .or(pod_run_as_non_root);
+        if run_as_non_root != Some(true) {
+            violation(field("runAsNonRoot"), "must be set to true on the pod or the container");
+        }
+
+        if security_context.and_then(|sc| sc.run_as_user) == Some(0) {
+            violation(field("runAsUser"), "must not be set to 0");
+        }
+
+        let seccomp_profile = security_context
+            .and_then(|sc| sc.seccomp_profile.as_ref())
+            .map(|p| p.type_.as_str())
+            .or(pod_seccomp_profile);
+        if !matches!(seccomp_profile, Some("RuntimeDefault") | Some("Localhost")) {
+            violation(
+                field("seccompProfile.type"),
+                "must be set to `RuntimeDefault` or `Localhost` on the pod or the container",
+            );
+        }
+
+        let drops_all = security_context
+            .and_then(|sc| sc.capabilities.as_ref())
+            .and_then(|c| c.drop.as_ref())
+            .map(|drop| drop.iter().any(|c| c == "ALL"))
+            .unwrap_or(false);
+        if !drops_all {
+            violation(field("capabilities.drop"), "must contain `ALL`");
+        }
+    }
+
+    violations
+}
+
+fn added_capabilities(container: &Container) -> Vec<String> {
+    container
+        .security_context
+        .as_ref()
+        .and_then(|sc| sc.capabilities.as_ref())
+        .and_then(|c| c.add.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::{
+        Capabilities, HostPathVolumeSource, PodSecurityContext, SeccompProfile, SecurityContext, Volume,
+    };
+
+    fn container(name: &str, security_context: Option<SecurityContext>) -> Container {
+        Container {
+            name: name.to_string(),
+            image: Some("nginx:latest".to_string()),
+            security_context,
+            ..Default::default()
+        }
+    }
+
+    fn restricted_security_context() -> SecurityContext {
+        SecurityContext {
+            allow_privilege_escalation: Some(false),
+            run_as_non_root: Some(true),
+            seccomp_profile: Some(SeccompProfile {
+                type_: "RuntimeDefault".to_string(),
+                localhost_profile: None,
+            }),
+            capabilities: Some(Capabilities {
+                add: None,
+                drop: Some(vec!["ALL".to_string()]),
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn fields(violations: &[PodSecurityViolation]) -> Vec<&str> {
+        violations.iter().map(|v| v.field.as_str()).collect()
+    }
+
+    #[test]
+    fn test_namespace_labels() {
+        let labels = PodSecurityProfile::Restricted.namespace_labels();
+        assert_eq!(labels.len(), 6);
+        assert_eq!(labels["pod-security.kubernetes.io/enforce"], "restricted");
+        assert_eq!(labels["pod-security.kubernetes.io/warn"], "restricted");
+        assert_eq!(labels["pod-security.kubernetes.io/audit"], "restricted");
+        assert_eq!(labels["pod-security.kubernetes.io/enforce-version"], "latest");
+
+        // downgrading the profile must override every label previously set
+        let labels = PodSecurityProfile::Privileged.namespace_labels();
+        assert!(labels
+            .iter()
+            .filter(|(k, _)| !k.ends_with("-version"))
+            .all(|(_, v)| v == "privileged"));
+    }
+
+    #[test]
+    fn test_privileged_profile_accepts_anything() {
+        let pod_spec = PodSpec {
+            host_network: Some(true),
+            containers: vec![container(
+                "app",
+                Some(SecurityContext {
+                    privileged: Some(true),
+                    ..Default::default()
+                }),
+            )],
+            ..Default::default()
+        };
+
+        assert!(validate_pod_spec(PodSecurityProfile::Privileged, "my-app", &pod_spec).is_empty());
+    }
+
+    #[test]
+    fn test_default_pod_spec() {
+        let pod_spec = PodSpec {
+            containers: vec![container("app", None)],
+            ..Default::default()
+        };
+
+        assert!(validate_pod_spec(PodSecurityProfile::Baseline, "my-app", &pod_spec).is_empty());
+
+        let violations = validate_pod_spec(PodSecurityProfile::Restricted, "my-app", &pod_spec);
+        assert_eq!(
+            fields(&violations),
+            vec![
+                "containers[app].securityContext.allowPrivilegeEscalation",
+                "containers[app].securityContext.runAsNonRoot",
+                "containers[app].securityContext.seccompProfile.type",
+                "containers[app].securityContext.capabilities.drop",
+            ]
+        );
+        assert!(violations.iter().all(|v| v.service_name == "my-app"));
+    }
+
+    #[test]
+    fn test_restricted_compliant_pod_spec() {
+        let pod_spec = PodSpec {
+            init_containers: Some(vec![container("init", Some(restricted_security_context()))]),
+            containers: vec![container("app", Some(restricted_security_context()))],
+            ..Default::default()
+        };
+
+        assert!(validate_pod_spec(PodSecurityProfile::Restricted, "my-app", &pod_spec).is_empty());
+    }
+
+    #[test]
+    fn test_restricted_pod_level_security_context_is_inherited() {
+        let pod_spec = PodSpec {
+            security_context: Some(PodSecurityContext {
+                run_as_non_root: Some(true),
+                seccomp_profile: Some(SeccompProfile {
+                    type_: "RuntimeDefault".to_string(),
+                    localhost_profile: None,
+                }),
+                ..Default::default()
+            }),
+            containers: vec![container(
+                "app",
+                Some(SecurityContext {
+                    run_as_non_root: None,
+                    seccomp_profile: None,
+                    ..restricted_security_context()
+                }),
+            )],
+            ..Default::default()
+        };
+        assert!(validate_pod_spec(PodSecurityProfile::Restricted, "my-app", &pod_spec).is_empty());
+
+        // container level overrides the pod level
+        let mut pod_spec = pod_spec;
+        pod_spec.containers[0].security_context = Some(SecurityContext {
+            run_as_non_root: Some(false),
+            ..restricted_security_context()
+        });
+        assert_eq!(
+            fields(&validate_pod_spec(PodSecurityProfile::Restricted, "my-app", &pod_spec)),
+            vec!["containers[app].securityContext.runAsNonRoot"]
+        );
+    }
+
+    #[test]
+    fn test_restricted_rejects_unconfined_seccomp_profile_and_root_user() {
+        let unconfined = || {
+            Some(SeccompProfile {
+                type_: "Unconfined".to_string(),
+                localhost_profile: None,
+            })
+        };
+
+        // on the pod, even when the containers override them
+        let pod_spec = PodSpec {
+            security_context: Some(PodSecurityContext {
+                run_as_user: Some(0),
+                seccomp_profile: unconfined(),
+                ..Default::default()
+            }),
+            containers: vec![container(
+                "app",
+                Some(SecurityContext {
+                    run_as_user: Some(1000),
+                    ..restricted_security_context()
+                }),
+            )],
+            ..Default::default()
+        };
+        assert_eq!(
+            fields(&validate_pod_spec(PodSecurityProfile::Restricted, "my-app", &pod_spec)),
+            vec!["securityContext.runAsUser", "securityContext.seccompProfile.type"]
+        );
+        assert!(validate_pod_spec(PodSecurityProfile::Baseline, "my-app", &pod_spec).is_empty());
+
+        // on the container
+        let pod_spec = PodSpec {
+            containers: vec![container(
+                "app",
+                Some(SecurityContext {
+                    run_as_user: Some(0),
+                    seccomp_profile: unconfined(),
+                    ..restricted_security_context()
+                }),
+            )],
+            ..Default::default()
+        };
+        assert_eq!(
+            fields(&validate_pod_spec(PodSecurityProfile::Restricted, "my-app", &pod_spec)),
+            vec![
+                "containers[app].securityContext.runAsUser",
+                "containers[app].securityContext.seccompProfile.type",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_baseline_violations() {
+        let pod_spec = PodSpec {
+            host_pid: Some(true),
+            volumes: Some(vec![Volume {
+                name: "docker-sock".to_string(),
+                host_path: Some(HostPathVolumeSource {
+                    path: "/var/run/docker.sock".to_string(),
+                    type_: None,
+                }),
+                ..Default::default()
+            }]),
+            containers: vec![container(
+                "app",
+                Some(SecurityContext {
+                    privileged: Some(true),
+                    capabilities: Some(Capabilities {
+                        add: Some(vec!["NET_ADMIN".to_string(), "CHOWN".to_string()]),
+                        drop: None,
+                    }),
+                    ..Default::default()
+                }),
+            )],
+            ..Default::default()
+        };
+
+        let violations = validate_pod_spec(PodSecurityProfile::Baseline, "my-app", &pod_spec);
+        assert_eq!(
+            fields(&violations),
+            vec![
+                "hostPID",
+                "volumes[docker-sock].hostPath",
+                "containers[app].securityContext.privileged",
+                "containers[app].securityContext.capabilities.add",
+            ]
+        );
+        assert!(violations[3].message.contains("NET_ADMIN"));
+    }
+
+    #[test]
+    fn test_restricted_capabilities() {
+        let mut security_context = restricted_security_context();
+        security_context.capabilities = Some(Capabilities {
+            add: Some(vec!["NET_BIND_SERVICE".to_string(), "CHOWN".to_string()]),
+            drop: Some(vec!["ALL".to_string()]),
+        });
+        let pod_spec = PodSpec {
+            containers: vec![container("app", Some(security_context))],
+            ..Default::default()
+        };
+
+        let violations = validate_pod_spec(PodSecurityProfile::Restricted, "my-app", &pod_spec);
+        assert_eq!(fields(&violations), vec!["containers[app].securityContext.capabilities.add"]);
+        assert!(violations[0].message.contains("CHOWN"));
+    }
+
+    #[test]
+    fn test_validate_rendered_manifests() {
+        let manifests = r#"
+apiVersion: v1
+kind: Service
+metadata:
+  name: my-app
+spec:
+  ports:
+    - port: 80
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: my-app
+spec:
+  selector:
+    matchLabels:
+      app: my-app
+  template:
+    spec:
+      securityContext:
+        runAsNonRoot: true
+        seccompProfile:
+          type: RuntimeDefault
+      containers:
+        - name: app
+          image: nginx
+          securityContext:
+            allowPrivilegeEscalation: false
+            capabilities:
+              drop: ["ALL"]
+---
+apiVersion: batch/v1
+kind: CronJob
+metadata:
+  name: my-cron
+spec:
+  schedule: "* * * * *"
+  jobTemplate:
+    spec:
+      template:
+        spec:
+          containers:
+            - name: job
+              image: busybox
+          volumes:
+            - name: host
+              hostPath:
+                path: /etc
+"#;
+
+        let violations = validate_rendered_manifests(PodSecurityProfile::Restricted, "my-app", manifests).unwrap();
+        assert!(violations.iter().all(|v| v.workload == "CronJob/my-cron"));
+        assert_eq!(
+            fields(&violations),
+            vec![
+                "spec.jobTemplate.spec.template.spec.volumes[host].hostPath",
+                "spec.jobTemplate.spec.template.spec.containers[job].securityContext.allowPrivilegeEscalation",
+                "spec.jobTemplate.spec.template.spec.containers[job].securityContext.runAsNonRoot",
+                "spec.jobTemplate.spec.template.spec.containers[job].securityContext.seccompProfile.type",
+                "spec.jobTemplate.spec.template.spec.containers[job].securityContext.capabilities.drop",
+            ]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            "service `my-app` (CronJob/my-cron): `spec.jobTemplate.spec.template.spec.volumes[host].hostPath` hostPath volumes are forbidden"
+        );
+
+        assert!(validate_rendered_manifests(PodSecurityProfile::Privileged, "my-app", manifests)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -19,3 +19,151 @@ pub struct Probe {
     pub success_threshold: u32,
     pub failure_threshold: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::models::labels_group::LabelsGroupTeraContext;
+    use serde_json::{json, Value};
+    use std::env;
+    use std::fs;
+    use tera::{Context as TeraContext, Tera};
+
+    fn grpc_probe(service: Option<&str>) -> Probe {
+        Probe {
+            r#type: ProbeType::Grpc {
+                service: service.map(|s| s.to_string()),
+            },
+            port: 9000,
+            initial_delay_seconds: 5,
+            period_seconds: 10,
+            timeout_seconds: 1,
+            success_threshold: 1,
+            failure_threshold: 3,
+        }
+    }
+
+    fn service(service_type: &str) -> Value {
+        json!({
+            "name": "my-service",
+            "long_id": "a0b1c2d3-0000-0000-0000-000000000000",
+            "short_id": "a0b1c2d3",
+            "type": service_type,
+            "version": "1",
+            "image_full": "my-registry/my-image:1",
+            "command_args": [],
+            "entrypoint": null,
+            "cpu_request_in_milli": "100m",
+            "cpu_limit_in_milli": "100m",
+            "ram_request_in_mib": "128Mi",
+            "ram_limit_in_mib": "128Mi",
+            "min_instances": 1,
+            "max_instances": 1,
+            "ports": [],
+            "storages": [],
+            "tolerations": [],
+            "readiness_probe": grpc_probe(Some("grpc.health.v1.Health")),
+            "liveness_probe": grpc_probe(None),
+            "legacy_deployment_from_scaleway": false,
+            "legacy_deployment_matchlabels": false,
+            "legacy_volumeclaim_template": false,
+            "default_port": 9000,
+            "max_nb_restart": 0,
+            "max_duration_in_sec": 300,
+            "with_rbac": false,
+            "cronjob_schedule": null,
+            "cronjob_timezone": "Etc/UTC",
+            "graceful_shutdown": {
+                "termination_grace_period_seconds": 60,
+                "pre_stop_sleep_seconds": null,
+            },
+            "advanced_settings": {
+                "deployment_affinity_node_required": {},
+                "deployment_antiaffinity_pod": "Preferred",
+                "deployment_lifecycle_post_start_exec_command": [],
+                "deployment_lifecycle_pre_stop_exec_command": [],
+                "deployment_update_strategy_type": "RollingUpdate",
+                "deployment_update_strategy_rolling_update_max_surge_percent": 25,
+                "deployment_update_strategy_rolling_update_max_unavailable_percent": 25,
+                "deployment_termination_grace_period_seconds": 60,
+                "security_automount_service_account_token": false,
+                "security_read_only_root_filesystem": false,
+                "security_service_account_name": "",
+                "cronjob_concurrency_policy": "Forbid",
+                "cronjob_failed_jobs_history_limit": 1,
+                "cronjob_success_jobs_history_limit": 1,
+            },
+        })
+    }
+
+    fn render(template: &str, service: &Value) -> serde_yaml::Value {
+        let mut context = TeraContext::new();
+        context.insert("namespace", "my-namespace");
+        context.insert("environment_short_id", "env");
+        context.insert("environment_long_id", "a0b1c2d3-0000-0000-0000-000000000002");
+        context.insert("project_long_id", "a0b1c2d3-0000-0000-0000-000000000003");
+        context.insert("labels_group", &LabelsGroupTeraContext::new(vec![]));
+        context.insert(
+            "annotations_group",
+            &json!({ "deployment": {}, "stateful_set": {}, "job": {}, "cronjob": {}, "pods": {} }),
+        );
+        context.insert("registry", &json!({ "secret_name": null }));
+        context.insert("environment_variables", &json!([]));
+        context.insert("mounted_files", &json!([]));
+        context.insert("service", service);
+
+        let template = fs::read_to_string(format!(
+            "{}/lib/common/charts/{}",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .display(),
+            template
+        ))
+        .unwrap_or_else(|_| panic!("cannot read template {template}"));
+        let rendered = Tera::one_off(&template, &context, false).expect("cannot render template");
+
+        serde_yaml::from_str(&rendered).expect("invalid yaml")
+    }
+
+    fn assert_grpc_probes(container: &serde_yaml::Value) {
+        let readiness_probe = &container["readinessProbe"]["grpc"];
+        assert_eq!(readiness_probe["port"].as_u64(), Some(9000));
+        assert_eq!(readiness_probe["service"].as_str(), Some("grpc.health.v1.Health"));
+
+        // without a service, the server's overall health is checked
+        let liveness_probe = &container["livenessProbe"]["grpc"];
+        assert_eq!(liveness_probe["port"].as_u64(), Some(9000));
+        assert!(liveness_probe.get("service").is_none());
+    }
+
+    #[test]
+    fn test_grpc_probes_in_container_and_application_templates() {
+        for service_type in ["container", "application"] {
+            let mut service = service(service_type);
+            let deployment = render("q-container/templates/deployment.j2.yaml", &service);
+            assert_grpc_probes(&deployment["spec"]["template"]["spec"]["containers"][0]);
+
+            service["storages"] = json!([{
+                "id": "a0b1c2d3",
+                "long_id": "a0b1c2d3-0000-0000-0000-000000000004",
+                "mount_point": "/data",
+                "size_in_gib": 10,
+                "storage_type": "ssd",
+            }]);
+            let statefulset = render("q-container/templates/statefulset.j2.yaml", &service);
+            assert_grpc_probes(&statefulset["spec"]["template"]["spec"]["containers"][0]);
+        }
+    }
+
+    #[test]
+    fn test_grpc_probes_in_job_templates() {
+        let mut service = service("job");
+        // the first container waits for the output of the job one
+        let job = render("q-job/templates/job.j2.yaml", &service);
+        assert_grpc_probes(&job["spec"]["template"]["spec"]["containers"][1]);
+
+        service["cronjob_schedule"] = json!("*/5 * * * *");
+        let cronjob = render("q-job/templates/cronjob.j2.yaml", &service);
+        assert_grpc_probes(&cronjob["spec"]["jobTemplate"]["spec"]["template"]["spec"]["containers"][0]);
+    }
+}
@@ -4,20 +4,71 @@ use crate::environment::models::container::get_mirror_repository_name;
 use crate::infrastructure::models::container_registry::ContainerRegistryInfo;
 use crate::io_models::container::Registry;
 use crate::string::cut;
+use std::sync::OnceLock;
 use url::Url;
 use uuid::Uuid;
 
+/// Image running in another environment, deployed as is (pinned to its digest) instead of being rebuilt
+pub struct ImagePromotion {
+    pub source_service_id: Uuid,
+    /// Namespace of the source environment, set when it runs on the same cluster to read the digest from live pods
+    pub source_namespace: Option<String>,
+    pub source_execution_id: String,
+    digest: OnceLock<String>,
+}
+
+impl ImagePromotion {
+    pub fn new(
+        source_service_id: Uuid,
+        source_namespace: Option<String>,
+        source_execution_id: String,
+        recorded_digest: Option<String>,
+    ) -> Self {
+        let digest = OnceLock::new();
+        if let Some(recorded_digest) = recorded_digest {
+            let _ = digest.set(recorded_digest);
+        }
+
+        ImagePromotion {
+            source_service_id,
+            source_namespace,
+            source_execution_id,
+            digest,
+        }
+    }
+
+    /// Digest of the promoted image, once resolved from the deployment record or the live pods
+    pub fn digest(&self) -> Option<&str> {
+        self.digest.get().map(|d| d.as_str())
+    }
+
+    pub fn set_digest(&self, digest: String) -> &str {
+        self.digest.get_or_init(|| digest)
+    }
+}
+
 pub struct RegistryImageSource {
     pub registry: Registry,
     pub image: String,
     pub tag: String,
     pub registry_mirroring_mode: RegistryMirroringMode,
+    pub promotion: Option<ImagePromotion>,
 }
 
 impl RegistryImageSource {
+    /// Digest the service must be deployed with, when its image is promoted from another environment
+    pub fn pinned_digest(&self) -> Option<&str> {
+        self.promotion.as_ref().and_then(|p| p.digest())
+    }
+
     pub fn tag_for_mirror(&self, service_id: &Uuid) -> String {
         // A tag name must be valid ASCII and may contain lowercase and uppercase letters, digits, underscores, periods and dashes.
         // A tag name may not start with a period or a dash and may contain a maximum of 128 characters.
+        if let Some(digest) = self.pinned_digest() {
+            // the content of the tag is immutable, so it can be shared by all services
+            return cut(format!("promoted-{}", digest.replace(':', "-")), 128);
+        }
+
         match self.registry_mirroring_mode {
             RegistryMirroringMode::Service => {
                 cut(format!("{}.{}.{}", self.image.replace('/', "."), self.tag, service_id), 128)
@@ -1,22 +1,26 @@
 use crate::environment::action::DeploymentAction;
 use crate::environment::models::annotations_group::AnnotationsGroupTeraContext;
+use crate::environment::models::helm_release::helm_release_name;
 use crate::environment::models::labels_group::LabelsGroupTeraContext;
+use crate::environment::models::service_port::ServicePort;
 use crate::environment::models::types::CloudProvider;
 use crate::environment::models::types::ToTeraContext;
 use crate::errors::EngineError;
 use crate::events::{EnvironmentStep, EventDetails, Stage, Transmitter};
+use crate::infrastructure::helm_charts::nginx_ingress_chart::CustomErrorPages;
 use crate::infrastructure::models::build_platform::Build;
 use crate::infrastructure::models::cloud_provider::service::{default_tera_context, Action, Service, ServiceType};
 use crate::infrastructure::models::cloud_provider::DeploymentTarget;
 use crate::io_models::annotations_group::AnnotationsGroup;
-use crate::io_models::application::{Port, Protocol};
+use crate::io_models::application::Protocol;
 use crate::io_models::context::Context;
 use crate::io_models::labels_group::LabelsGroup;
 use crate::io_models::models::{
     CustomDomain, CustomDomainDataTemplate, EnvironmentVariable, HostDataTemplate, KubeService, KubeServicePort, Route,
 };
+use crate::io_models::router::RouteProtocol;
 use crate::utilities::to_short_id;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::iter;
 use std::marker::PhantomData;
 use std::path::PathBuf;
@@ -27,20 +31,31 @@ use uuid::Uuid;
 pub enum RouterError {
     #[error("Router invalid configuration: {0}")]
     InvalidConfig(String),
-    #[error("Error decoding base64 basic Auth environment variable `{env_var_name}`: `{env_var_value}`")]
-    BasicAuthEnvVarBase64DecodeError {
-        env_var_name: String,
-        env_var_value: String,
-    },
+    #[error("Error decoding base64 basic Auth environment variable `{env_var_name}`")]
+    BasicAuthEnvVarBase64DecodeError { env_var_name: String },
     #[error("Basic Auth environment variable `{env_var_name}` not found but defined in the advanced settings")]
     BasicAuthEnvVarNotFound { env_var_name: String },
+    #[error("Invalid basic Auth credentials: {reason}")]
+    BasicAuthInvalidCredentials { reason: String },
+    #[error("Route protocol `{route_protocol}` requires the service to expose a public {port_protocol} port")]
+    InvalidProtocolForPort {
+        route_protocol: RouteProtocol,
+        port_protocol: &'static str,
+    },
 }
 
 #[derive(Default)]
 pub struct RouterAdvancedSettings {
     pub whitelist_source_range: Option<String>,
     pub denylist_source_range: Option<String>,
+    /// Base64 encoded htpasswd content
     pub basic_auth: Option<String>,
+    /// Set when `basic_auth` comes from the deprecated `network.ingress.basic_auth_env_var` advanced setting
+    pub basic_auth_env_var: Option<String>,
+    /// Served by a backend of the router instead of the cluster default one
+    pub custom_error_pages: Option<CustomErrorPages>,
+    /// nginx annotations added to the ingresses of the router, validated with `validate_extra_ingress_annotations`
+    pub extra_ingress_annotations: BTreeMap<String, String>,
 }
 
 impl RouterAdvancedSettings {
@@ -55,6 +70,9 @@ impl RouterAdvancedSettings {
             whitelist_source_range: definitive_whitelist,
             denylist_source_range,
             basic_auth,
+            basic_auth_env_var: None,
+            custom_error_pages: None,
+            extra_ingress_annotations: BTreeMap::new(),
         }
     }
 
@@ -63,6 +81,153 @@ impl RouterAdvancedSettings {
     }
 }
 
+const NGINX_INGRESS_ANNOTATIONS_PREFIX: &str = "nginx.ingress.kubernetes.io/";
+
+/// nginx ingress annotations users can add to the ingresses of a router
+const ALLOWED_EXTRA_INGRESS_ANNOTATIONS: &[&str] = &[
+    "app-root",
+    "auth-cache-duration",
+    "auth-cache-key",
+    "auth-method",
+    "auth-response-headers",
+    "auth-signin",
+    "auth-url",
+    "client-body-buffer-size",
+    "cors-allow-credentials",
+    "cors-expose-headers",
+    "cors-max-age",
+    "enable-access-log",
+    "force-ssl-redirect",
+    "from-to-www-redirect",
+    "limit-connections",
+    "limit-rps",
+    "load-balance",
+    "permanent-redirect",
+    "permanent-redirect-code",
+    "proxy-body-size",
+    "proxy-buffers-number",
+    "proxy-http-version",
+    "proxy-max-temp-file-size",
+    "proxy-next-upstream",
+    "proxy-next-upstream-timeout",
+    "proxy-next-upstream-tries",
+    "proxy-read-timeout",
+    "proxy-redirect-from",
+    "proxy-redirect-to",
+    "proxy-send-timeout",
+    "rewrite-target",
+    "server-alias",
+    "ssl-redirect",
+    "temporal-redirect",
+    "upstream-hash-by",
+    "upstream-vhost",
+    "use-regex",
+    "x-forwarded-prefix",
+];
+
+/// Snippets inject raw nginx configuration, they are only allowed when explicitly flagged as unsafe
+const UNSAFE_EXTRA_INGRESS_ANNOTATIONS: &[&str] = &[
+    "auth-snippet",
+    "configuration-snippet",
+    "modsecurity-snippet",
+    "server-snippet",
+];
+
+/// Annotations set by the router ingress templates, they take precedence over the extra ones
+const ROUTER_INGRESS_ANNOTATIONS: &[&str] = &[
+    "affinity",
+    "affinity-mode",
+    "auth-realm",
+    "auth-secret",
+    "auth-type",
+    "backend-protocol",
+    "configuration-snippet",
+    "cors-allow-headers",
+    "cors-allow-methods",
+    "cors-allow-origin",
+    "custom-http-errors",
+    "default-backend",
+    "denylist-source-range",
+    "enable-cors",
+    "limit-burst-multiplier",
+    "limit-rpm",
+    "proxy-body-size",
+    "proxy-buffer-size",
+    "proxy-buffering",
+    "proxy-connect-timeout",
+    "proxy-read-timeout",
+    "proxy-request-buffering",
+    "proxy-send-timeout",
+    "server-snippet",
+    "session-cookie-expires",
+    "session-cookie-max-age",
+    "session-cookie-name",
+    "session-cookie-samesite",
+    "session-cookie-secure",
+    "whitelist-source-range",
+];
+
+/// Rejects extra ingress annotations which are not nginx ones from the allowed list.
+/// Snippets are refused unless `allow_unsafe` is set.
+pub fn validate_extra_ingress_annotations(
+    annotations: &BTreeMap<String, String>,
+    allow_unsafe: bool,
+) -> Result<(), RouterError> {
+    for key in annotations.keys() {
+        let name = key.strip_prefix(NGINX_INGRESS_ANNOTATIONS_PREFIX).unwrap_or_default();
+        if UNSAFE_EXTRA_INGRESS_ANNOTATIONS.contains(&name) {
+            if !allow_unsafe {
+                return Err(RouterError::InvalidConfig(format!(
+                    "ingress annotation `{key}` injects raw nginx configuration, it requires unsafe ingress annotations to be allowed"
+                )));
+            }
+        } else if !ALLOWED_EXTRA_INGRESS_ANNOTATIONS.contains(&name) {
+            return Err(RouterError::InvalidConfig(format!(
+                "ingress annotation `{key}` is not supported, only some `{NGINX_INGRESS_ANNOTATIONS_PREFIX}*` annotations can be set"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Idle time after which nginx closes WebSocket connections, unless the proxy timeouts are already higher
+const WEBSOCKET_TIMEOUT_SECONDS: u32 = 3600;
+
+/// Checks the service exposes a public port able to serve the protocol of the route.
+/// gRPC needs an HTTP/2 port, declared with the GRPC protocol, WebSocket an HTTP/1.1 one.
+fn validate_route_protocol(route_protocol: RouteProtocol, ports: &[&ServicePort]) -> Result<(), RouterError> {
+    let port_protocol = match route_protocol {
+        // default protocol, each port is served according to its own protocol
+        RouteProtocol::Http1 => return Ok(()),
+        RouteProtocol::Grpc | RouteProtocol::GrpcWeb => Protocol::GRPC,
+        RouteProtocol::WebSocket => Protocol::HTTP,
+    };
+
+    match ports.iter().any(|port| port.protocol == port_protocol) {
+        true => Ok(()),
+        false => Err(RouterError::InvalidProtocolForPort {
+            route_protocol,
+            port_protocol: match port_protocol {
+                Protocol::GRPC => "GRPC",
+                _ => "HTTP",
+            },
+        }),
+    }
+}
+
+/// Extra ingress annotations to render, the ones also set by the router templates are left out
+fn extra_ingress_annotations_to_render(annotations: &BTreeMap<String, String>) -> BTreeMap<&str, &str> {
+    annotations
+        .iter()
+        .filter(|(key, _)| {
+            let name = key.strip_prefix(NGINX_INGRESS_ANNOTATIONS_PREFIX).unwrap_or_default();
+            !ROUTER_INGRESS_ANNOTATIONS.contains(&name)
+        })
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect()
+}
+
 pub struct Router<T: CloudProvider> {
     _marker: PhantomData<T>,
     pub(crate) mk_event_details: Box<dyn Fn(Stage) -> EventDetails + Send + Sync>,
@@ -265,8 +430,23 @@ impl<T: CloudProvider> Router<T> {
                 (helm_chart.kube_name(), helm_chart.public_ports())
             };
 
-        // inject basic auth data
-        context.insert("basic_auth_htaccess", &self.advanced_settings.basic_auth);
+        // the basic auth secret is managed outside of the chart, rotating credentials must not touch the ingress
+        context.insert(
+            "basic_auth_secret_name",
+            &self
+                .advanced_settings
+                .basic_auth
+                .as_ref()
+                .map(|_| self.basic_auth_secret_name()),
+        );
+        context.insert(
+            "custom_error_pages",
+            &self
+                .advanced_settings
+                .custom_error_pages
+                .as_ref()
+                .map(|custom_error_pages| custom_error_pages.to_tera_context()),
+        );
 
         // Get the alternative names we need to generate for the certificate
         // For custom domain, we need to generate a subdomain for each port. p80.mydomain.com, p443.mydomain.com
@@ -276,12 +456,18 @@ impl<T: CloudProvider> Router<T> {
             &generate_certificate_alternative_names(&self.custom_domains, &cluster_domain, &ports),
         );
 
-        let http_ports: Vec<&Port> = ports
+        let route_protocol = self.routes.first().map(|route| route.protocol).unwrap_or_default();
+        validate_route_protocol(route_protocol, &ports)
+            .map_err(|err| EngineError::new_router_error(event_details.clone(), err))?;
+        context.insert("route_protocol", &route_protocol);
+        context.insert("websocket_timeout_seconds", &WEBSOCKET_TIMEOUT_SECONDS);
+
+        let http_ports: Vec<&ServicePort> = ports
             .iter()
             .filter(|port| port.protocol == Protocol::HTTP)
             .cloned()
             .collect();
-        let grpc_ports: Vec<&Port> = ports
+        let grpc_ports: Vec<&ServicePort> = ports
             .iter()
             .filter(|port| port.protocol == Protocol::GRPC)
             .cloned()
@@ -311,6 +497,10 @@ impl<T: CloudProvider> Router<T> {
         context.insert("grpc_hosts_per_namespace", &grpc_hosts_per_namespace);
         context.insert("qovery_additional_services", &qovery_additional_services);
 
+        context.insert(
+            "extra_ingress_annotations",
+            &extra_ingress_annotations_to_render(&self.advanced_settings.extra_ingress_annotations),
+        );
         context.insert("annotations_group", &self.annotations_group);
         context.insert("labels_group", &self.labels_group);
 
@@ -324,9 +514,19 @@ impl<T: CloudProvider> Router<T> {
     }
 
     pub fn helm_release_name(&self) -> String {
+        helm_release_name("router", &self.long_id)
+    }
+
+    /// Release name used before release names were generated from the service long id
+    pub fn legacy_helm_release_name(&self) -> String {
         crate::string::cut(format!("router-{}", self.id), 50)
     }
 
+    /// Name of the secret holding the htpasswd content of the router, referenced by the `auth-secret` annotation
+    pub fn basic_auth_secret_name(&self) -> String {
+        format!("basic-auth-{}", self.kube_name)
+    }
+
     pub fn helm_chart_dir(&self) -> String {
         format!("{}/common/charts/q-ingress-tls", self.lib_root_directory,)
     }
@@ -334,7 +534,7 @@ impl<T: CloudProvider> Router<T> {
 
 fn to_host_data_template(
     service_name: &str,
-    ports: &[&Port],
+    ports: &[&ServicePort],
     default_domain: &str,
     custom_domains: &[CustomDomain],
     cluster_domain: &str,
@@ -433,7 +633,7 @@ fn to_host_data_template(
     hosts_per_namespace
 }
 
-fn to_additional_services(ports: Vec<&Port>) -> Vec<KubeService> {
+fn to_additional_services(ports: Vec<&ServicePort>) -> Vec<KubeService> {
     ports
         .into_iter()
         .filter_map(|port| {
@@ -451,8 +651,8 @@ fn to_additional_services(ports: Vec<&Port>) -> Vec<KubeService> {
         .collect()
 }
 
-fn get_ports_by_namespace(ports: &[&Port]) -> HashMap<Option<String>, Vec<Port>> {
-    let mut ports_by_namespace: HashMap<Option<String>, Vec<Port>> = HashMap::new();
+fn get_ports_by_namespace(ports: &[&ServicePort]) -> HashMap<Option<String>, Vec<ServicePort>> {
+    let mut ports_by_namespace: HashMap<Option<String>, Vec<ServicePort>> = HashMap::new();
     for &port in ports {
         let entry = ports_by_namespace.entry(port.namespace.clone()).or_default();
         entry.push(port.clone());
@@ -460,7 +660,7 @@ fn get_ports_by_namespace(ports: &[&Port]) -> HashMap<Option<String>, Vec<Port>>
     ports_by_namespace
 }
 
-fn get_service_name(port: &Port, default_service_name: &str) -> String {
+fn get_service_name(port: &ServicePort, default_service_name: &str) -> String {
     port.service_name
         .as_ref()
         .cloned()
@@ -476,7 +676,7 @@ fn get_service_name(port: &Port, default_service_name: &str) -> String {
 fn generate_certificate_alternative_names(
     custom_domains: &[CustomDomain],
     cluster_domain: &str,
-    ports: &[&Port],
+    ports: &[&ServicePort],
 ) -> Vec<CustomDomainDataTemplate> {
     if ports.is_empty() || custom_domains.is_empty() {
         return vec![];
@@ -580,6 +780,9 @@ pub trait RouterService: Service + DeploymentAction + ToTeraContext + Send {
     fn as_deployment_action(&self) -> &dyn DeploymentAction;
 
     fn associated_service_id(&self) -> Option<Uuid>;
+
+    /// Default domain then custom domains of the router, wildcard ones included
+    fn domains(&self) -> Vec<String>;
 }
 
 impl<T: CloudProvider> RouterService for Router<T>
@@ -597,17 +800,39 @@ where
     fn associated_service_id(&self) -> Option<Uuid> {
         self.routes.first().map(|route| route.service_long_id)
     }
+
+    fn domains(&self) -> Vec<String> {
+        std::iter::once(self.default_domain.clone())
+            .chain(
+                self.custom_domains
+                    .iter()
+                    .map(|custom_domain| custom_domain.domain.clone()),
+            )
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::to_additional_services;
-    use crate::environment::models::router::{generate_certificate_alternative_names, to_host_data_template};
-    use crate::io_models::application::{Port, Protocol};
+    use crate::environment::models::annotations_group::AnnotationsGroupTeraContext;
+    use crate::environment::models::labels_group::LabelsGroupTeraContext;
+    use crate::environment::models::router::{
+        extra_ingress_annotations_to_render, generate_certificate_alternative_names, to_host_data_template,
+        validate_extra_ingress_annotations, validate_route_protocol, RouterError, WEBSOCKET_TIMEOUT_SECONDS,
+    };
+    use crate::environment::models::service_port::ServicePort;
+    use crate::io_models::application::{PortExposure, Protocol};
+    use crate::io_models::container::ContainerAdvancedSettings;
     use crate::io_models::models::{
         CustomDomain, CustomDomainDataTemplate, HostDataTemplate, KubeService, KubeServicePort,
     };
+    use crate::io_models::router::RouteProtocol;
+    use crate::template::generate_j2_template_files;
     use maplit::btreemap;
+    use std::collections::BTreeMap;
+    use std::env;
+    use tera::Context as TeraContext;
 
     #[test]
     pub fn test_certificate_alternative_names() {
@@ -632,15 +857,15 @@ mod tests {
             },
         ];
 
-        let ports: Vec<&Port> = vec![];
+        let ports: Vec<&ServicePort> = vec![];
 
         let certificate_names = generate_certificate_alternative_names(&custom_domains, "cluster.com", &ports);
         assert_eq!(certificate_names.len(), 0);
 
-        let port = Port {
+        let port = ServicePort {
             long_id: Default::default(),
             name: "http".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 80,
             is_default: false,
             protocol: Protocol::HTTP,
@@ -655,10 +880,10 @@ mod tests {
         assert_eq!(certificate_names.len(), 1);
         assert_eq!(certificate_names[0].domain, "toto.com");
 
-        let port2 = Port {
+        let port2 = ServicePort {
             long_id: Default::default(),
             name: "grpc".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 8080,
             is_default: false,
             protocol: Protocol::GRPC,
@@ -687,10 +912,10 @@ mod tests {
             generate_certificate: true,
             use_cdn: true,
         }];
-        let port2 = Port {
+        let port2 = ServicePort {
             long_id: Default::default(),
             name: "grpc".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 8080,
             is_default: false,
             protocol: Protocol::GRPC,
@@ -712,10 +937,10 @@ mod tests {
 
     #[test]
     pub fn test_ingress_host_template_with_wildcard() {
-        let port_http = Port {
+        let port_http = ServicePort {
             long_id: Default::default(),
             name: "http".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 80,
             is_default: true,
             protocol: Protocol::HTTP,
@@ -723,10 +948,10 @@ mod tests {
             namespace: None,
             additional_service: None,
         };
-        let port_grpc = Port {
+        let port_grpc = ServicePort {
             long_id: Default::default(),
             name: "grpc".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 8080,
             is_default: false,
             protocol: Protocol::GRPC,
@@ -871,10 +1096,10 @@ mod tests {
 
     #[test]
     pub fn test_ingress_host_template_with_custom_domain_managed_by_cluster() {
-        let port_http = Port {
+        let port_http = ServicePort {
             long_id: Default::default(),
             name: "http".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 80,
             is_default: true,
             protocol: Protocol::HTTP,
@@ -914,10 +1139,10 @@ mod tests {
 
     #[test]
     pub fn test_ingress_host_template_with_service_name_defined_in_port() {
-        let port_http = Port {
+        let port_http = ServicePort {
             long_id: Default::default(),
             name: "http-1".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 80,
             is_default: false,
             protocol: Protocol::HTTP,
@@ -925,10 +1150,10 @@ mod tests {
             namespace: None,
             additional_service: None,
         };
-        let port_http_with_service_name = Port {
+        let port_http_with_service_name = ServicePort {
             long_id: Default::default(),
             name: "http-2".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 8080,
             is_default: false,
             protocol: Protocol::HTTP,
@@ -979,10 +1204,10 @@ mod tests {
 
     #[test]
     pub fn test_ingress_host_template_with_service_name_and_namespace_defined_in_port() {
-        let port_http = Port {
+        let port_http = ServicePort {
             long_id: Default::default(),
             name: "http-1".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 80,
             is_default: false,
             protocol: Protocol::HTTP,
@@ -990,10 +1215,10 @@ mod tests {
             namespace: None,
             additional_service: None,
         };
-        let port_http_with_service_name = Port {
+        let port_http_with_service_name = ServicePort {
             long_id: Default::default(),
             name: "http-2".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 8080,
             is_default: false,
             protocol: Protocol::HTTP,
@@ -1046,10 +1271,10 @@ mod tests {
 
     #[test]
     pub fn test_ingress_host_template_with_additional_service_defined_in_port() {
-        let port_http = Port {
+        let port_http = ServicePort {
             long_id: Default::default(),
             name: "http-1".to_string(),
-            publicly_accessible: true,
+            expose: PortExposure::Public,
             port: 80,
             is_default: false,
             protocol: Protocol::HTTP,
@@ -1073,4 +1298,237 @@ mod tests {
             selectors: btreemap![ "a".to_string() => "b".to_string()],
         }));
     }
+
+    #[test]
+    pub fn test_validate_extra_ingress_annotations() {
+        let annotations = btreemap! {
+            "nginx.ingress.kubernetes.io/proxy-body-size".to_string() => "200m".to_string(),
+            "nginx.ingress.kubernetes.io/use-regex".to_string() => "true".to_string(),
+        };
+        assert!(validate_extra_ingress_annotations(&annotations, false).is_ok());
+
+        // unknown annotations are rejected
+        for key in [
+            "nginx.ingress.kubernetes.io/not-an-annotation",
+            "nginx.ingress.kubernetes.io/",
+            "kubernetes.io/ingress.class",
+            "use-regex",
+        ] {
+            let annotations = btreemap! { key.to_string() => "true".to_string() };
+            assert!(matches!(
+                validate_extra_ingress_annotations(&annotations, true),
+                Err(RouterError::InvalidConfig(_))
+            ));
+        }
+
+        // snippets are only allowed when flagged as unsafe
+        let annotations = btreemap! {
+            "nginx.ingress.kubernetes.io/auth-snippet".to_string() => "return 403;".to_string(),
+        };
+        assert!(matches!(
+            validate_extra_ingress_annotations(&annotations, false),
+            Err(RouterError::InvalidConfig(_))
+        ));
+        assert!(validate_extra_ingress_annotations(&annotations, true).is_ok());
+    }
+
+    fn ingress_tera_context(advanced_settings: &ContainerAdvancedSettings) -> TeraContext {
+        let hosts_per_namespace = |port: u16| {
+            btreemap! {
+                "my-namespace".to_string() => vec![HostDataTemplate {
+                    domain_name: format!("p{port}.mydomain.com"),
+                    service_name: "my-service".to_string(),
+                    service_port: port,
+                }],
+            }
+        };
+
+        let mut context = TeraContext::new();
+        context.insert("id", "router-id");
+        context.insert("long_id", "a0b1c2d3-0000-0000-0000-000000000000");
+        context.insert("sanitized_name", "router-name");
+        context.insert("namespace", "my-namespace");
+        context.insert("associated_service_long_id", "a0b1c2d3-0000-0000-0000-000000000001");
+        context.insert("associated_service_type", "container");
+        context.insert("environment_long_id", "a0b1c2d3-0000-0000-0000-000000000002");
+        context.insert("project_long_id", "a0b1c2d3-0000-0000-0000-000000000003");
+        context.insert("advanced_settings", advanced_settings);
+        context.insert("labels_group", &LabelsGroupTeraContext::new(vec![]));
+        context.insert("annotations_group", &AnnotationsGroupTeraContext::new(vec![]));
+        context.insert("has_wildcard_domain", &false);
+        context.insert("basic_auth_secret_name", &None::<String>);
+        context.insert("custom_error_pages", &None::<String>);
+        context.insert("certificate_alternative_names", &Vec::<CustomDomainDataTemplate>::new());
+        context.insert("qovery_additional_services", &Vec::<KubeService>::new());
+        context.insert("http_hosts_per_namespace", &hosts_per_namespace(80));
+        context.insert("grpc_hosts_per_namespace", &hosts_per_namespace(50051));
+        context.insert("extra_ingress_annotations", &BTreeMap::<String, String>::new());
+        context.insert("route_protocol", &RouteProtocol::Http1);
+        context.insert("websocket_timeout_seconds", &WEBSOCKET_TIMEOUT_SECONDS);
+
+        context
+    }
+
+    fn render_ingress_annotations(context: &TeraContext, file_name: &str) -> serde_yaml::Value {
+        let chart_dir = format!(
+            "{}/lib/common/charts/q-ingress-tls",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .display()
+        );
+        let rendered_templates = generate_j2_template_files(&chart_dir, context).expect("cannot render router chart");
+        let ingress = rendered_templates
+            .iter()
+            .find(|template| template.file_name == file_name)
+            .unwrap_or_else(|| panic!("{file_name} not rendered"));
+        let ingress: serde_yaml::Value = serde_yaml::from_str(&ingress.content).expect("invalid ingress yaml");
+
+        ingress["metadata"]["annotations"].clone()
+    }
+
+    #[test]
+    pub fn test_ingress_template_with_extra_annotations() {
+        let extra_ingress_annotations = btreemap! {
+            "nginx.ingress.kubernetes.io/proxy-read-timeout".to_string() => "3600".to_string(),
+            "nginx.ingress.kubernetes.io/use-regex".to_string() => "true".to_string(),
+            "nginx.ingress.kubernetes.io/auth-snippet".to_string() => "if ($http_x_token = \"\") {\n  return 401;\n}".to_string(),
+        };
+        let advanced_settings = ContainerAdvancedSettings::default();
+        let mut context = ingress_tera_context(&advanced_settings);
+        context.insert(
+            "extra_ingress_annotations",
+            &extra_ingress_annotations_to_render(&extra_ingress_annotations),
+        );
+
+        let annotations = render_ingress_annotations(&context, "ingress-http.yaml");
+
+        // extra annotations are merged with the router ones
+        assert_eq!(annotations["nginx.ingress.kubernetes.io/use-regex"].as_str(), Some("true"));
+        assert_eq!(
+            annotations["nginx.ingress.kubernetes.io/auth-snippet"].as_str(),
+            Some("if ($http_x_token = \"\") {\n  return 401;\n}")
+        );
+        assert_eq!(
+            annotations["cert-manager.io/cluster-issuer"].as_str(),
+            Some("letsencrypt-qovery")
+        );
+
+        // on conflict, the router annotation is kept
+        assert_eq!(
+            annotations["nginx.ingress.kubernetes.io/proxy-read-timeout"].as_str(),
+            Some(
+                advanced_settings
+                    .network_ingress_proxy_read_timeout_seconds
+                    .to_string()
+                    .as_str()
+            )
+        );
+    }
+
+    #[test]
+    pub fn test_validate_route_protocol() {
+        let port = |protocol: Protocol| ServicePort {
+            long_id: Default::default(),
+            name: "p".to_string(),
+            expose: PortExposure::Public,
+            port: 80,
+            is_default: true,
+            protocol,
+            service_name: None,
+            namespace: None,
+            additional_service: None,
+        };
+        let http_port = port(Protocol::HTTP);
+        let grpc_port = port(Protocol::GRPC);
+
+        // ports are served according to their own protocol by default
+        assert!(validate_route_protocol(RouteProtocol::Http1, &[&http_port]).is_ok());
+        assert!(validate_route_protocol(RouteProtocol::Http1, &[&grpc_port]).is_ok());
+
+        assert!(validate_route_protocol(RouteProtocol::WebSocket, &[&http_port]).is_ok());
+        assert!(matches!(
+            validate_route_protocol(RouteProtocol::WebSocket, &[&grpc_port]),
+            Err(RouterError::InvalidProtocolForPort { .. })
+        ));
+        for protocol in [RouteProtocol::Grpc, RouteProtocol::GrpcWeb] {
+            assert!(validate_route_protocol(protocol, &[&grpc_port]).is_ok());
+            assert!(validate_route_protocol(protocol, &[&http_port, &grpc_port]).is_ok());
+            assert!(matches!(
+                validate_route_protocol(protocol, &[&http_port]),
+                Err(RouterError::InvalidProtocolForPort { .. })
+            ));
+        }
+    }
+
+    #[test]
+    pub fn test_ingress_template_route_protocols() {
+        let mut advanced_settings = ContainerAdvancedSettings::default();
+        advanced_settings.network_ingress_proxy_send_timeout_seconds = 60;
+        advanced_settings.network_ingress_proxy_read_timeout_seconds = 7200;
+        let mut context = ingress_tera_context(&advanced_settings);
+
+        // HTTP/1.1: nothing changed
+        context.insert("route_protocol", &RouteProtocol::Http1);
+        let http_annotations = render_ingress_annotations(&context, "ingress-http.yaml");
+        let grpc_annotations = render_ingress_annotations(&context, "ingress-grpc.yaml");
+        assert_eq!(http_annotations["nginx.ingress.kubernetes.io/backend-protocol"].as_str(), None);
+        assert_eq!(
+            http_annotations["nginx.ingress.kubernetes.io/proxy-send-timeout"].as_str(),
+            Some("60")
+        );
+        assert_eq!(
+            grpc_annotations["nginx.ingress.kubernetes.io/backend-protocol"].as_str(),
+            Some("GRPC")
+        );
+
+        // gRPC
+        context.insert("route_protocol", &RouteProtocol::Grpc);
+        let grpc_annotations = render_ingress_annotations(&context, "ingress-grpc.yaml");
+        assert_eq!(
+            grpc_annotations["nginx.ingress.kubernetes.io/backend-protocol"].as_str(),
+            Some("GRPC")
+        );
+
+        // gRPC-Web
+        context.insert("route_protocol", &RouteProtocol::GrpcWeb);
+        let grpc_annotations = render_ingress_annotations(&context, "ingress-grpc.yaml");
+        assert_eq!(
+            grpc_annotations["nginx.ingress.kubernetes.io/backend-protocol"].as_str(),
+            Some("HTTP")
+        );
+
+        // WebSocket: timeouts are raised, higher ones are kept
+        context.insert("route_protocol", &RouteProtocol::WebSocket);
+        let http_annotations = render_ingress_annotations(&context, "ingress-http.yaml");
+        assert_eq!(
+            http_annotations["nginx.ingress.kubernetes.io/proxy-send-timeout"].as_str(),
+            Some(WEBSOCKET_TIMEOUT_SECONDS.to_string().as_str())
+        );
+        assert_eq!(
+            http_annotations["nginx.ingress.kubernetes.io/proxy-read-timeout"].as_str(),
+            Some("7200")
+        );
+    }
+
+    #[test]
+    pub fn test_ingress_template_references_basic_auth_secret() {
+        let advanced_settings = ContainerAdvancedSettings::default();
+        let mut context = ingress_tera_context(&advanced_settings);
+
+        for file_name in ["ingress-http.yaml", "ingress-grpc.yaml"] {
+            let annotations = render_ingress_annotations(&context, file_name);
+            assert_eq!(annotations["nginx.ingress.kubernetes.io/auth-type"].as_str(), None);
+            assert_eq!(annotations["nginx.ingress.kubernetes.io/auth-secret"].as_str(), None);
+        }
+
+        context.insert("basic_auth_secret_name", "basic-auth-router-name");
+        for file_name in ["ingress-http.yaml", "ingress-grpc.yaml"] {
+            let annotations = render_ingress_annotations(&context, file_name);
+            assert_eq!(annotations["nginx.ingress.kubernetes.io/auth-type"].as_str(), Some("basic"));
+            assert_eq!(
+                annotations["nginx.ingress.kubernetes.io/auth-secret"].as_str(),
+                Some("basic-auth-router-name")
+            );
+        }
+    }
 }
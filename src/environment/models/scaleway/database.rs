@@ -1,5 +1,6 @@
 use crate::environment::models::database::{
-    Container, Database, DatabaseMode, DatabaseType, Managed, MongoDB, MySQL, PostgresSQL, Redis,
+    managed_final_snapshot_name, Container, Database, DatabaseMode, DatabaseType, Managed, MongoDB, MySQL, PostgresSQL,
+    Redis,
 };
 use crate::environment::models::database_utils::{
     is_allowed_containered_mongodb_version, is_allowed_containered_mysql_version,
@@ -163,11 +164,15 @@ impl<M: DatabaseMode, T: DatabaseType<SCW, M>> Database<SCW, M, T> {
         context.insert("tfstate_suffix_name", &get_tfstate_suffix(self));
         context.insert("tfstate_name", &get_tfstate_name(self));
         context.insert("skip_final_snapshot", &false);
-        context.insert("final_snapshot_name", &format!("qovery-{}-final-snap", self.id));
+        context.insert("final_snapshot_name", &managed_final_snapshot_name(&self.id));
         context.insert("delete_automated_backups", &target.kubernetes.context().is_test_cluster());
         context.insert("publicly_accessible", &options.publicly_accessible);
         context.insert("activate_high_availability", &options.activate_high_availability);
         context.insert("activate_backups", &options.activate_backups);
+        if let Some(backup_retention_days) = options.maintenance.backup_retention_days() {
+            context.insert("backup_retention_period", &backup_retention_days);
+        }
+        context.insert("database_parameters", options.parameters.parameters());
         context.insert(
             "resource_expiration_in_seconds",
             &kubernetes.advanced_settings().pleco_resources_ttl,
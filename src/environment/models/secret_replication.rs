@@ -0,0 +1,509 @@
+// Declarative replication of secrets across namespaces. A source secret lists its target namespaces in an annotation,
+// by name or with a label selector, and every deployment of its environment reconciles the replicas: changed data is
+// copied again, deleted replicas are recreated and replicas no longer listed are removed. Replicas are recognized by
+// their labels, a secret of the same name not created by the replication is never touched.
+
+use k8s_openapi::api::core::v1::{Namespace, Secret};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+/// Comma separated names of the namespaces the secret is replicated to
+pub const REPLICATE_TO_ANNOTATION: &str = "qovery.com/replicate-to";
+/// Label selector of the namespaces the secret is replicated to, eq. `team=data,env!=production`
+pub const REPLICATE_TO_SELECTOR_ANNOTATION: &str = "qovery.com/replicate-to-selector";
+pub const REPLICA_SOURCE_NAMESPACE_LABEL: &str = "qovery.com/replica-source-namespace";
+pub const REPLICA_SOURCE_NAME_LABEL: &str = "qovery.com/replica-source-name";
+pub const REPLICA_DATA_HASH_ANNOTATION: &str = "qovery.com/replica-data-hash";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SecretReplicationAction {
+    Create {
+        replica: Secret,
+    },
+    /// Data of the replica differs from the source one
+    Update {
+        replica: Secret,
+    },
+    /// Replica whose source doesn't list the namespace anymore, or whose source is gone
+    Delete {
+        namespace: String,
+        name: String,
+    },
+    /// A secret not created by the replication already exists with the same name in the target namespace
+    Conflict {
+        namespace: String,
+        name: String,
+    },
+    /// The namespace is explicitly listed by the source, but doesn't exist
+    TargetNamespaceNotFound {
+        namespace: String,
+        name: String,
+    },
+}
+
+impl Display for SecretReplicationAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretReplicationAction::Create { replica } => write!(f, "create {}", secret_path(replica)),
+            SecretReplicationAction::Update { replica } => write!(f, "update {}", secret_path(replica)),
+            SecretReplicationAction::Delete { namespace, name } => write!(f, "delete {namespace}/{name}"),
+            SecretReplicationAction::Conflict { namespace, name } => write!(
+                f,
+                "secret {namespace}/{name} already exists and is not a replica, it is left untouched"
+            ),
+            SecretReplicationAction::TargetNamespaceNotFound { namespace, name } => {
+                write!(
+                    f,
+                    "namespace {namespace} doesn't exist, secret {name} cannot be replicated to it"
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum LabelRequirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    Exists(String),
+}
+
+impl LabelRequirement {
+    fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        match self {
+            LabelRequirement::Equals(key, value) => labels.get(key) == Some(value),
+            LabelRequirement::NotEquals(key, value) => labels.get(key) != Some(value),
+            LabelRequirement::Exists(key) => labels.contains_key(key),
+        }
+    }
+}
+
+/// Parses equality based label selectors, set based ones (`in`, `notin`) are not supported
+fn parse_label_selector(selector: &str) -> Option<Vec<LabelRequirement>> {
+    selector
+        .split(',')
+        .map(str::trim)
+        .filter(|requirement| !requirement.is_empty())
+        .map(|requirement| {
+            let requirement = if let Some((key, value)) = requirement.split_once("!=") {
+                LabelRequirement::NotEquals(key.trim().to_string(), value.trim().to_string())
+            } else if let Some((key, value)) = requirement.split_once("==").or_else(|| requirement.split_once('=')) {
+                LabelRequirement::Equals(key.trim().to_string(), value.trim().to_string())
+            } else {
+                LabelRequirement::Exists(requirement.to_string())
+            };
+
+            match &requirement {
+                LabelRequirement::Equals(key, _)
+                | LabelRequirement::NotEquals(key, _)
+                | LabelRequirement::Exists(key)
+                    if key.is_empty() || key.contains(char::is_whitespace) =>
+                {
+                    None
+                }
+                _ => Some(requirement),
+            }
+        })
+        .collect()
+}
+
+/// Hash of the data of a secret, keys are sorted so it doesn't depend on the order they were written in
+pub fn secret_data_hash(secret: &Secret) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(secret.type_.as_deref().unwrap_or_default());
+    for (key, value) in secret.data.iter().flatten() {
+        hasher.update([0]);
+        hasher.update(key.as_bytes());
+        hasher.update([0]);
+        hasher.update(&value.0);
+    }
+
+    format!("{:x}", hasher.finalize())
+}
+
+fn secret_path(secret: &Secret) -> String {
+    format!(
+        "{}/{}",
+        secret.metadata.namespace.as_deref().unwrap_or_default(),
+        secret.metadata.name.as_deref().unwrap_or_default()
+    )
+}
+
+fn replica_source(secret: &Secret) -> Option<(&str, &str)> {
+    let labels = secret.metadata.labels.as_ref()?;
+    Some((
+        labels.get(REPLICA_SOURCE_NAMESPACE_LABEL)?.as_str(),
+        labels.get(REPLICA_SOURCE_NAME_LABEL)?.as_str(),
+    ))
+}
+
+/// Copy of the source secret in the target namespace, only its type and data are replicated
+pub fn replica_of(source: &Secret, namespace: &str) -> Secret {
+    Secret {
+        metadata: ObjectMeta {
+            name: source.metadata.name.clone(),
+            namespace: Some(namespace.to_string()),
+            labels: Some(BTreeMap::from([
+                (
+                    REPLICA_SOURCE_NAMESPACE_LABEL.to_string(),
+                    source.metadata.namespace.clone().unwrap_or_default(),
+                ),
+                (
+                    REPLICA_SOURCE_NAME_LABEL.to_string(),
+                    source.metadata.name.clone().unwrap_or_default(),
+                ),
+            ])),
+            annotations: Some(BTreeMap::from([(
+                REPLICA_DATA_HASH_ANNOTATION.to_string(),
+                secret_data_hash(source),
+            )])),
+            ..Default::default()
+        },
+        data: source.data.clone(),
+        type_: source.type_.clone(),
+        ..Default::default()
+    }
+}
+
+/// Namespaces a source secret is replicated to, the ones explicitly listed but missing are returned apart
+fn replication_targets(
+    source: &Secret,
+    source_namespace: &str,
+    namespaces: &[Namespace],
+) -> (BTreeSet<String>, BTreeSet<String>) {
+    let annotations = source.metadata.annotations.clone().unwrap_or_default();
+    let existing_namespaces: BTreeMap<&str, BTreeMap<String, String>> = namespaces
+        .iter()
+        .filter_map(|namespace| {
+            Some((
+                namespace.metadata.name.as_deref()?,
+                namespace.metadata.labels.clone().unwrap_or_default(),
+            ))
+        })
+        .collect();
+
+    let mut targets = BTreeSet::new();
+    let mut missing_namespaces = BTreeSet::new();
+    for namespace in annotations
+        .get(REPLICATE_TO_ANNOTATION)
+        .into_iter()
+        .flat_map(|namespaces| namespaces.split(','))
+        .map(str::trim)
+        .filter(|namespace| !namespace.is_empty())
+    {
+        match existing_namespaces.contains_key(namespace) {
+            true => targets.insert(namespace.to_string()),
+            false => missing_namespaces.insert(namespace.to_string()),
+        };
+    }
+
+    // an invalid selector matches nothing rather than everything
+    if let Some(requirements) = annotations
+        .get(REPLICATE_TO_SELECTOR_ANNOTATION)
+        .and_then(|selector| parse_label_selector(selector))
+        .filter(|requirements| !requirements.is_empty())
+    {
+        targets.extend(
+            existing_namespaces
+                .iter()
+                .filter(|(_, labels)| requirements.iter().all(|requirement| requirement.matches(labels)))
+                .map(|(namespace, _)| namespace.to_string()),
+        );
+    }
+
+    targets.remove(source_namespace);
+    (targets, missing_namespaces)
+}
+
+/// Computes the actions bringing the replicas of the secrets of `source_namespace` in line with their sources.
+///
+/// * `sources`: secrets of the source namespace, the ones without replication annotation are ignored
+/// * `namespaces`: all the namespaces of the cluster
+/// * `existing`: replicas of the source namespace secrets, and secrets having the name of a source secret
+pub fn plan_secret_replication(
+    source_namespace: &str,
+    sources: &[Secret],
+    namespaces: &[Namespace],
+    existing: &[Secret],
+) -> Vec<SecretReplicationAction> {
+    let existing_by_path: BTreeMap<(&str, &str), &Secret> = existing
+        .iter()
+        .filter_map(|secret| {
+            Some((
+                (secret.metadata.namespace.as_deref()?, secret.metadata.name.as_deref()?),
+                secret,
+            ))
+        })
+        .collect();
+
+    let mut actions = vec![];
+    let mut desired: BTreeSet<(String, String)> = BTreeSet::new();
+    for source in sources {
+        let Some(name) = source.metadata.name.as_deref() else {
+            continue;
+        };
+        let (targets, missing_namespaces) = replication_targets(source, source_namespace, namespaces);
+        actions.extend(missing_namespaces.into_iter().map(|namespace| {
+            SecretReplicationAction::TargetNamespaceNotFound {
+                namespace,
+                name: name.to_string(),
+            }
+        }));
+
+        let source_hash = secret_data_hash(source);
+        for namespace in targets {
+            desired.insert((namespace.clone(), name.to_string()));
+            match existing_by_path.get(&(namespace.as_str(), name)) {
+                None => actions.push(SecretReplicationAction::Create {
+                    replica: replica_of(source, &namespace),
+                }),
+                Some(secret) if replica_source(secret) != Some((source_namespace, name)) => {
+                    actions.push(SecretReplicationAction::Conflict {
+                        namespace,
+                        name: name.to_string(),
+                    })
+                }
+                // actual data is compared, an edited replica is repaired as well
+                Some(replica) if secret_data_hash(replica) != source_hash => {
+                    actions.push(SecretReplicationAction::Update {
+                        replica: replica_of(source, &namespace),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    for ((namespace, name), secret) in existing_by_path {
+        let is_replica_of_source_namespace = replica_source(secret)
+            .is_some_and(|(replica_source_namespace, _)| replica_source_namespace == source_namespace);
+        if is_replica_of_source_namespace && !desired.contains(&(namespace.to_string(), name.to_string())) {
+            actions.push(SecretReplicationAction::Delete {
+                namespace: namespace.to_string(),
+                name: name.to_string(),
+            });
+        }
+    }
+
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::ByteString;
+
+    fn namespace(name: &str, labels: &[(&str, &str)]) -> Namespace {
+        Namespace {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                labels: Some(labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn secret(namespace: &str, name: &str, data: &str, annotations: &[(&str, &str)]) -> Secret {
+        Secret {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                annotations: Some(
+                    annotations
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+            data: Some(BTreeMap::from([("password".to_string(), ByteString(data.as_bytes().to_vec()))])),
+            type_: Some("Opaque".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn namespaces() -> Vec<Namespace> {
+        vec![
+            namespace("source", &[]),
+            namespace("app-a", &[("team", "data")]),
+            namespace("app-b", &[("team", "data"), ("env", "production")]),
+            namespace("app-c", &[("team", "web")]),
+        ]
+    }
+
+    #[test]
+    fn test_missing_replicas_are_created() {
+        // setup:
+        let source = secret("source", "db", "pwd", &[(REPLICATE_TO_ANNOTATION, "app-a, app-c,unknown")]);
+
+        // execute:
+        let actions = plan_secret_replication("source", std::slice::from_ref(&source), &namespaces(), &[]);
+
+        // verify:
+        assert_eq!(
+            actions,
+            vec![
+                SecretReplicationAction::TargetNamespaceNotFound {
+                    namespace: "unknown".to_string(),
+                    name: "db".to_string()
+                },
+                SecretReplicationAction::Create {
+                    replica: replica_of(&source, "app-a")
+                },
+                SecretReplicationAction::Create {
+                    replica: replica_of(&source, "app-c")
+                },
+            ]
+        );
+        let replica = replica_of(&source, "app-a");
+        assert_eq!(replica.data, source.data);
+        assert_eq!(replica_source(&replica), Some(("source", "db")));
+    }
+
+    #[test]
+    fn test_selector_targets_matching_namespaces_except_the_source_one() {
+        // setup:
+        let source = secret(
+            "source",
+            "db",
+            "pwd",
+            &[(REPLICATE_TO_SELECTOR_ANNOTATION, "team=data,env!=production")],
+        );
+        let source_selecting_itself = secret("source", "other", "pwd", &[(REPLICATE_TO_SELECTOR_ANNOTATION, "team")]);
+        let invalid_selector = secret("source", "invalid", "pwd", &[(REPLICATE_TO_SELECTOR_ANNOTATION, "=data")]);
+        let mut all_namespaces = namespaces();
+        all_namespaces[0] = namespace("source", &[("team", "data")]);
+
+        // execute:
+        let actions = plan_secret_replication(
+            "source",
+            &[source.clone(), source_selecting_itself.clone(), invalid_selector],
+            &all_namespaces,
+            &[],
+        );
+
+        // verify:
+        assert_eq!(
+            actions,
+            vec![
+                SecretReplicationAction::Create {
+                    replica: replica_of(&source, "app-a")
+                },
+                SecretReplicationAction::Create {
+                    replica: replica_of(&source_selecting_itself, "app-a")
+                },
+                SecretReplicationAction::Create {
+                    replica: replica_of(&source_selecting_itself, "app-b")
+                },
+                SecretReplicationAction::Create {
+                    replica: replica_of(&source_selecting_itself, "app-c")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drifted_replicas_are_updated_and_up_to_date_ones_untouched() {
+        // setup:
+        let source = secret("source", "db", "rotated", &[(REPLICATE_TO_ANNOTATION, "app-a,app-b,app-c")]);
+        let previous_source = secret("source", "db", "pwd", &[]);
+        let existing = vec![
+            replica_of(&previous_source, "app-a"),
+            replica_of(&source, "app-b"),
+            // edited by hand, the hash annotation is the right one but not the data
+            Secret {
+                data: previous_source.data.clone(),
+                ..replica_of(&source, "app-c")
+            },
+        ];
+
+        // execute:
+        let actions = plan_secret_replication("source", std::slice::from_ref(&source), &namespaces(), &existing);
+
+        // verify:
+        assert_eq!(
+            actions,
+            vec![
+                SecretReplicationAction::Update {
+                    replica: replica_of(&source, "app-a")
+                },
+                SecretReplicationAction::Update {
+                    replica: replica_of(&source, "app-c")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replicas_no_longer_listed_are_deleted() {
+        // setup:
+        let source = secret("source", "db", "pwd", &[(REPLICATE_TO_ANNOTATION, "app-a")]);
+        let removed_source = secret("source", "removed", "pwd", &[]);
+        let other_source_namespace = secret("other", "db", "pwd", &[]);
+        let existing = vec![
+            replica_of(&source, "app-a"),
+            replica_of(&source, "app-b"),
+            replica_of(&removed_source, "app-c"),
+            replica_of(&other_source_namespace, "app-c"),
+        ];
+
+        // execute:
+        let actions = plan_secret_replication("source", &[source, removed_source], &namespaces(), &existing);
+
+        // verify:
+        assert_eq!(
+            actions,
+            vec![
+                SecretReplicationAction::Delete {
+                    namespace: "app-b".to_string(),
+                    name: "db".to_string()
+                },
+                SecretReplicationAction::Delete {
+                    namespace: "app-c".to_string(),
+                    name: "removed".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_user_owned_secrets_are_reported_as_conflicts() {
+        // setup:
+        let source = secret("source", "db", "pwd", &[(REPLICATE_TO_ANNOTATION, "app-a,app-b")]);
+        let other_source = secret("other", "db", "pwd", &[]);
+        let existing = vec![
+            secret("app-a", "db", "user value", &[]),
+            replica_of(&other_source, "app-b"),
+        ];
+
+        // execute:
+        let actions = plan_secret_replication("source", &[source], &namespaces(), &existing);
+
+        // verify:
+        assert_eq!(
+            actions,
+            vec![
+                SecretReplicationAction::Conflict {
+                    namespace: "app-a".to_string(),
+                    name: "db".to_string()
+                },
+                SecretReplicationAction::Conflict {
+                    namespace: "app-b".to_string(),
+                    name: "db".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_secret_data_hash_is_stable() {
+        let secret_a = secret("a", "db", "pwd", &[]);
+        let secret_b = secret("b", "other", "pwd", &[(REPLICATE_TO_ANNOTATION, "app-a")]);
+
+        assert_eq!(secret_data_hash(&secret_a), secret_data_hash(&secret_b));
+        assert_ne!(secret_data_hash(&secret_a), secret_data_hash(&secret("a", "db", "other", &[])));
+    }
+}
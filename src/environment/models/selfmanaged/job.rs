@@ -6,6 +6,7 @@ use tera::Context as TeraContext;
 
 impl ToTeraContext for Job<OnPremise> {
     fn to_tera_context(&self, target: &DeploymentTarget) -> Result<TeraContext, Box<EngineError>> {
-        Ok(TeraContext::from_serialize(self.default_tera_context(target)).unwrap_or_default())
+        self.check_cluster_features(target)?;
+        Ok(TeraContext::from_serialize(self.default_tera_context(target)?).unwrap_or_default())
     }
 }
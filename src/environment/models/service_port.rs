@@ -0,0 +1,454 @@
+use crate::environment::models::probe::{Probe, ProbeType};
+use crate::io_models::application::{AdditionalService, Port, PortExposure, Protocol};
+use serde_derive::Serialize;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+// 0 is not a valid port, the others are used by kubelet and kube-proxy on every node
+pub const RESERVED_PORTS: [u16; 4] = [0, 10250, 10255, 10256];
+const PORT_NAME_MAX_LENGTH: usize = 63;
+
+#[derive(Serialize, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ServicePort {
+    pub long_id: Uuid,
+    pub name: String,
+    pub port: u16,
+    pub protocol: Protocol,
+    pub expose: PortExposure,
+    pub is_default: bool,
+    // Only set for helm charts, whose ports target services created by the chart
+    pub service_name: Option<String>,
+    pub namespace: Option<String>,
+    pub additional_service: Option<AdditionalService>,
+}
+
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum ServicePortError {
+    #[error("Port name `{0}` must be RFC 1123 compliant: at most 63 lowercase alphanumeric characters or '-', starting and ending with an alphanumeric character")]
+    InvalidName(String),
+    #[error("Port name `{0}` is used by several ports")]
+    DuplicatedName(String),
+    #[error("Port {0} is declared several times")]
+    DuplicatedPort(u16),
+    #[error("Port {0} is reserved and cannot be used")]
+    ReservedPort(u16),
+    #[error("Only one port can be the default one, found {0}")]
+    SeveralDefaultPorts(usize),
+    #[error("Exactly one public port must be the default one to be exposed by a router, found {0}")]
+    InvalidDefaultPublicPort(usize),
+}
+
+impl ServicePort {
+    pub fn is_public(&self) -> bool {
+        self.expose == PortExposure::Public
+    }
+
+    /// Converts the ports received in the payload.
+    /// Ports using the legacy shape (`publicly_accessible` without `expose`) are fixed when possible,
+    /// and a warning is returned for every change made.
+    pub fn from_payload(ports: Vec<Port>) -> (Vec<ServicePort>, Vec<String>) {
+        let mut warnings = vec![];
+        let has_legacy_ports = ports.iter().any(|port| port.expose.is_none());
+
+        let mut service_ports: Vec<ServicePort> = ports
+            .into_iter()
+            .map(|port| {
+                let expose = match port.expose {
+                    Some(expose) => expose,
+                    None if port.publicly_accessible => PortExposure::Public,
+                    None => PortExposure::Internal,
+                };
+
+                let name = match port.expose {
+                    None if !is_rfc1123_label(&port.name) => {
+                        let name = to_rfc1123_label(&port.name, port.port);
+                        warnings.push(format!(
+                            "Port {} name `{}` is not RFC 1123 compliant, `{}` is used instead",
+                            port.port, port.name, name
+                        ));
+                        name
+                    }
+                    _ => port.name,
+                };
+
+                ServicePort {
+                    long_id: port.long_id,
+                    name,
+                    port: port.port,
+                    protocol: port.protocol,
+                    expose,
+                    is_default: port.is_default,
+                    service_name: port.service_name,
+                    namespace: port.namespace,
+                    additional_service: port.additional_service,
+                }
+            })
+            .collect();
+
+        if !has_legacy_ports {
+            return (service_ports, warnings);
+        }
+
+        // Legacy payloads didn't enforce a single default port, the first one was implicitly used
+        let defaults: Vec<u16> = service_ports.iter().filter(|p| p.is_default).map(|p| p.port).collect();
+        if defaults.len() > 1 {
+            warnings.push(format!(
+                "Several ports are marked as default ({}), only port {} is kept as default",
+                defaults.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "),
+                defaults[0]
+            ));
+            for port in service_ports.iter_mut().filter(|p| p.is_default).skip(1) {
+                port.is_default = false;
+            }
+        }
+
+        let has_public_default = service_ports.iter().any(|p| p.is_default && p.is_public());
+        if !has_public_default {
+            if let Some(first_public) = service_ports.iter().position(|p| p.is_public()) {
+                for port in service_ports.iter_mut() {
+                    port.is_default = false;
+                }
+                let port = &mut service_ports[first_public];
+                port.is_default = true;
+                warnings.push(format!(
+                    "No public port is marked as default, public port {} is used as default",
+                    port.port
+                ));
+            }
+        }
+
+        (service_ports, warnings)
+    }
+}
+
+/// Converts the ports of the payload, reporting in the logs the legacy ports which have been fixed
+/// and the network probes targeting a port which is not declared.
+pub fn to_service_ports(service_long_id: &Uuid, ports: Vec<Port>, probes: &[&Probe]) -> Vec<ServicePort> {
+    let (ports, warnings) = ServicePort::from_payload(ports);
+    let probe_warnings = probes
+        .iter()
+        .filter(|_| !ports.is_empty())
+        .filter_map(|probe| undeclared_probe_port(probe, &ports));
+
+    for warning in warnings.into_iter().chain(probe_warnings) {
+        warn!("Ports of service {}: {}", service_long_id, warning);
+    }
+
+    ports
+}
+
+/// Validates the ports of a service: names must be unique and RFC 1123 compliant, numbers must be unique
+/// per targeted kubernetes service and not reserved, and at most one port can be the default one.
+pub fn validate_service_ports(ports: &[ServicePort]) -> Result<(), ServicePortError> {
+    let mut names = HashSet::with_capacity(ports.len());
+    let mut numbers = HashSet::with_capacity(ports.len());
+
+    for port in ports {
+        if !is_rfc1123_label(&port.name) {
+            return Err(ServicePortError::InvalidName(port.name.clone()));
+        }
+        if !names.insert(port.name.as_str()) {
+            return Err(ServicePortError::DuplicatedName(port.name.clone()));
+        }
+        if RESERVED_PORTS.contains(&port.port) {
+            return Err(ServicePortError::ReservedPort(port.port));
+        }
+        if !numbers.insert((port.namespace.as_deref(), port.service_name.as_deref(), port.port)) {
+            return Err(ServicePortError::DuplicatedPort(port.port));
+        }
+    }
+
+    match ports.iter().filter(|p| p.is_default).count() {
+        0 | 1 => Ok(()),
+        count => Err(ServicePortError::SeveralDefaultPorts(count)),
+    }
+}
+
+/// Validates the public ports of a service exposed by a router, which routes the default domain to the default one.
+pub fn validate_routed_ports(public_ports: &[&ServicePort]) -> Result<(), ServicePortError> {
+    match public_ports.iter().filter(|p| p.is_public() && p.is_default).count() {
+        1 => Ok(()),
+        count => Err(ServicePortError::InvalidDefaultPublicPort(count)),
+    }
+}
+
+/// Returns a warning if a network probe targets a port which is not declared on the service
+pub fn undeclared_probe_port(probe: &Probe, ports: &[ServicePort]) -> Option<String> {
+    if matches!(probe.r#type, ProbeType::Exec { .. }) || ports.iter().any(|p| u32::from(p.port) == probe.port) {
+        return None;
+    }
+
+    Some(format!(
+        "Probe targets port {} which is not declared on the service ({})",
+        probe.port,
+        ports.iter().map(|p| p.port.to_string()).collect::<Vec<_>>().join(", ")
+    ))
+}
+
+fn is_rfc1123_label(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= PORT_NAME_MAX_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
+
+fn to_rfc1123_label(name: &str, port: u16) -> String {
+    let name: String = name
+        .to_ascii_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let name: String = name.trim_matches('-').chars().take(PORT_NAME_MAX_LENGTH).collect();
+    let name = name.trim_end_matches('-');
+
+    if name.is_empty() {
+        format!("p{port}")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload_port(port: u16, name: &str, publicly_accessible: bool, is_default: bool) -> Port {
+        Port {
+            long_id: Uuid::new_v4(),
+            port,
+            is_default,
+            name: name.to_string(),
+            publicly_accessible,
+            expose: None,
+            protocol: Protocol::HTTP,
+            service_name: None,
+            namespace: None,
+            additional_service: None,
+        }
+    }
+
+    fn service_port(port: u16, name: &str, expose: PortExposure, is_default: bool) -> ServicePort {
+        ServicePort {
+            long_id: Uuid::new_v4(),
+            name: name.to_string(),
+            port,
+            protocol: Protocol::HTTP,
+            expose,
+            is_default,
+            service_name: None,
+            namespace: None,
+            additional_service: None,
+        }
+    }
+
+    fn probe(r#type: ProbeType, port: u32) -> Probe {
+        Probe {
+            r#type,
+            port,
+            initial_delay_seconds: 30,
+            period_seconds: 10,
+            timeout_seconds: 5,
+            success_threshold: 1,
+            failure_threshold: 3,
+        }
+    }
+
+    #[test]
+    fn test_rfc1123_label() {
+        assert!(is_rfc1123_label("p8080"));
+        assert!(is_rfc1123_label("http-admin"));
+        assert!(is_rfc1123_label("a"));
+        assert!(is_rfc1123_label(&"a".repeat(63)));
+        assert!(!is_rfc1123_label(""));
+        assert!(!is_rfc1123_label(&"a".repeat(64)));
+        assert!(!is_rfc1123_label("Admin"));
+        assert!(!is_rfc1123_label("http_admin"));
+        assert!(!is_rfc1123_label("-http"));
+        assert!(!is_rfc1123_label("http-"));
+        assert!(!is_rfc1123_label("http.admin"));
+
+        assert_eq!(to_rfc1123_label("Http_Admin", 80), "http-admin");
+        assert_eq!(to_rfc1123_label("_admin_", 80), "admin");
+        assert_eq!(to_rfc1123_label("___", 8080), "p8080");
+        assert_eq!(to_rfc1123_label("", 8080), "p8080");
+        assert_eq!(to_rfc1123_label(&format!("{}_b", "a".repeat(62)), 80), "a".repeat(62));
+    }
+
+    #[test]
+    fn test_from_payload_with_typed_ports() {
+        let mut http = payload_port(8080, "http", false, true);
+        http.expose = Some(PortExposure::Public);
+        let mut admin = payload_port(9000, "Admin", true, false);
+        admin.expose = Some(PortExposure::Internal);
+
+        let (ports, warnings) = ServicePort::from_payload(vec![http, admin]);
+
+        // expose wins over publicly_accessible and nothing is fixed
+        assert!(warnings.is_empty());
+        assert_eq!(ports[0].expose, PortExposure::Public);
+        assert!(ports[0].is_default);
+        assert_eq!(ports[1].expose, PortExposure::Internal);
+        assert_eq!(ports[1].name, "Admin");
+        assert_eq!(
+            validate_service_ports(&ports),
+            Err(ServicePortError::InvalidName("Admin".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_from_payload_with_legacy_ports() {
+        let (ports, warnings) = ServicePort::from_payload(vec![
+            payload_port(8080, "p8080", true, false),
+            payload_port(9000, "Admin_Port", false, false),
+        ]);
+
+        assert_eq!(ports[0].expose, PortExposure::Public);
+        assert!(ports[0].is_default);
+        assert_eq!(ports[1].expose, PortExposure::Internal);
+        assert_eq!(ports[1].name, "admin-port");
+        assert!(!ports[1].is_default);
+        assert_eq!(
+            warnings,
+            vec![
+                "Port 9000 name `Admin_Port` is not RFC 1123 compliant, `admin-port` is used instead".to_string(),
+                "No public port is marked as default, public port 8080 is used as default".to_string(),
+            ]
+        );
+        assert_eq!(validate_service_ports(&ports), Ok(()));
+    }
+
+    #[test]
+    fn test_from_payload_with_legacy_default_ports() {
+        // several defaults, only the first one is kept
+        let (ports, warnings) =
+            ServicePort::from_payload(vec![payload_port(80, "p80", true, true), payload_port(81, "p81", true, true)]);
+        assert!(ports[0].is_default);
+        assert!(!ports[1].is_default);
+        assert_eq!(
+            warnings,
+            vec!["Several ports are marked as default (80, 81), only port 80 is kept as default".to_string()]
+        );
+
+        // the default port is internal, the first public one becomes the default
+        let (ports, warnings) = ServicePort::from_payload(vec![
+            payload_port(80, "p80", false, true),
+            payload_port(81, "p81", true, false),
+        ]);
+        assert!(!ports[0].is_default);
+        assert!(ports[1].is_default);
+        assert_eq!(warnings.len(), 1);
+
+        // no public port, nothing to fix
+        let (ports, warnings) = ServicePort::from_payload(vec![payload_port(80, "p80", false, false)]);
+        assert!(!ports[0].is_default);
+        assert!(warnings.is_empty());
+
+        let (ports, warnings) = ServicePort::from_payload(vec![]);
+        assert!(ports.is_empty());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_validate_service_ports() {
+        assert_eq!(validate_service_ports(&[]), Ok(()));
+        assert_eq!(
+            validate_service_ports(&[
+                service_port(8080, "http", PortExposure::Public, true),
+                service_port(9000, "admin", PortExposure::Internal, false),
+            ]),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_service_ports(&[service_port(8080, "http_1", PortExposure::Public, true)]),
+            Err(ServicePortError::InvalidName("http_1".to_string()))
+        );
+        assert_eq!(
+            validate_service_ports(&[
+                service_port(8080, "http", PortExposure::Public, true),
+                service_port(8081, "http", PortExposure::Public, false),
+            ]),
+            Err(ServicePortError::DuplicatedName("http".to_string()))
+        );
+        assert_eq!(
+            validate_service_ports(&[
+                service_port(8080, "http", PortExposure::Public, true),
+                service_port(8080, "admin", PortExposure::Internal, false),
+            ]),
+            Err(ServicePortError::DuplicatedPort(8080))
+        );
+        for reserved in RESERVED_PORTS {
+            assert_eq!(
+                validate_service_ports(&[service_port(reserved, "http", PortExposure::Public, true)]),
+                Err(ServicePortError::ReservedPort(reserved))
+            );
+        }
+        assert_eq!(
+            validate_service_ports(&[
+                service_port(8080, "http", PortExposure::Public, true),
+                service_port(9000, "admin", PortExposure::Internal, true),
+            ]),
+            Err(ServicePortError::SeveralDefaultPorts(2))
+        );
+    }
+
+    #[test]
+    fn test_validate_service_ports_of_several_kubernetes_services() {
+        // helm charts may expose the same port number on different services
+        let mut frontend = service_port(8080, "frontend", PortExposure::Public, true);
+        frontend.service_name = Some("frontend".to_string());
+        let mut backend = service_port(8080, "backend", PortExposure::Public, false);
+        backend.service_name = Some("backend".to_string());
+        assert_eq!(validate_service_ports(&[frontend.clone(), backend.clone()]), Ok(()));
+
+        backend.service_name = Some("frontend".to_string());
+        assert_eq!(
+            validate_service_ports(&[frontend, backend]),
+            Err(ServicePortError::DuplicatedPort(8080))
+        );
+    }
+
+    #[test]
+    fn test_validate_routed_ports() {
+        let default_public = service_port(8080, "http", PortExposure::Public, true);
+        let public = service_port(8081, "grpc", PortExposure::Public, false);
+        let default_internal = service_port(9000, "admin", PortExposure::Internal, true);
+
+        assert_eq!(validate_routed_ports(&[&default_public, &public]), Ok(()));
+        assert_eq!(
+            validate_routed_ports(&[&public]),
+            Err(ServicePortError::InvalidDefaultPublicPort(0))
+        );
+        assert_eq!(
+            validate_routed_ports(&[&public, &default_internal]),
+            Err(ServicePortError::InvalidDefaultPublicPort(0))
+        );
+        assert_eq!(validate_routed_ports(&[]), Err(ServicePortError::InvalidDefaultPublicPort(0)));
+    }
+
+    #[test]
+    fn test_undeclared_probe_port() {
+        let ports = vec![
+            service_port(8080, "http", PortExposure::Public, true),
+            service_port(9000, "admin", PortExposure::Internal, false),
+        ];
+        let http = ProbeType::Http {
+            path: "/".to_string(),
+            scheme: "HTTP".to_string(),
+        };
+
+        assert_eq!(undeclared_probe_port(&probe(http.clone(), 8080), &ports), None);
+        assert_eq!(undeclared_probe_port(&probe(ProbeType::Tcp { host: None }, 9000), &ports), None);
+        assert_eq!(
+            undeclared_probe_port(&probe(ProbeType::Exec { commands: vec![] }, 1234), &ports),
+            None
+        );
+        assert_eq!(
+            undeclared_probe_port(&probe(http, 3000), &ports),
+            Some("Probe targets port 3000 which is not declared on the service (8080, 9000)".to_string())
+        );
+    }
+}
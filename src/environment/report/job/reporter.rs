@@ -6,6 +6,7 @@ use crate::infrastructure::models::cloud_provider::DeploymentTarget;
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::marker::PhantomData;
+use std::str::FromStr;
 
 use std::sync::Arc;
 
@@ -21,6 +22,7 @@ use crate::errors::Tag::JobFailure;
 use crate::io_models::job::JobSchedule;
 use crate::metrics_registry::{MetricsRegistry, StepLabel, StepName, StepStatus};
 use crate::runtime::block_on;
+use chrono_tz::Tz;
 use itertools::Itertools;
 use k8s_openapi::api::batch::v1::Job as K8sJob;
 use std::time::{Duration, Instant};
@@ -68,10 +70,10 @@ impl<T> JobDeploymentReporter<T> {
             JobSchedule::OnStart { .. } => JobType::Job(Action::Create),
             JobSchedule::OnPause { .. } => JobType::Job(Action::Pause),
             JobSchedule::OnDelete { .. } => JobType::Job(Action::Delete),
-            JobSchedule::Cron {
-                schedule,
-                timezone: _timezone,
-            } => JobType::CronJob(schedule.to_string()),
+            JobSchedule::Cron { schedule, timezone } => JobType::CronJob(match Tz::from_str(timezone) {
+                Ok(timezone) => schedule.describe(&timezone),
+                Err(_) => format!("`{schedule}` {timezone}"),
+            }),
         };
 
         JobDeploymentReporter {
@@ -143,7 +145,7 @@ impl<T: Send + Sync> DeploymentReporter for JobDeploymentReporter<T> {
         if self.is_force_trigger {
             match &self.job_type {
                 JobType::CronJob(schedule) => self.logger.send_progress(format!(
-                    "🚀 Force triggering deployment of cronjob with schedule `{}` at tag {} is starting",
+                    "🚀 Force triggering deployment of cronjob running {} at tag {} is starting",
                     schedule, self.tag
                 )),
                 JobType::Job(_) => self.logger.send_progress(format!(
@@ -172,7 +174,7 @@ impl<T: Send + Sync> DeploymentReporter for JobDeploymentReporter<T> {
                 }
             }
             JobType::CronJob(schedule) => self.logger.send_progress(format!(
-                "🚀 Deployment of cronjob with schedule `{}` at tag {} is starting",
+                "🚀 Deployment of cronjob running {} at tag {} is starting",
                 schedule, self.tag
             )),
         }
@@ -9,6 +9,7 @@ pub mod application;
 pub mod database;
 pub mod helm_chart;
 pub mod job;
+pub mod k8s_manifests;
 pub mod logger;
 pub mod obfuscation_service;
 mod recap_reporter;
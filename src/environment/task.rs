@@ -1,28 +1,52 @@
+use crate::cmd::command::CommandKiller;
+use crate::cmd::command::ExecutionContext;
 use crate::cmd::docker::Docker;
 use crate::engine_task;
+use crate::engine_task::deadline::{TaskDeadline, TaskStage, DEFAULT_TASK_MAX_DURATION};
 use crate::engine_task::qovery_api::QoveryApi;
 use crate::engine_task::Task;
 use crate::environment::action::deploy_environment::EnvironmentDeployment;
 use crate::environment::models::abort::{Abort, AbortStatus, AtomicAbortStatus};
+use crate::environment::models::bucket::{
+    delete_bucket, delete_stored_bucket_credentials, inject_bucket_variables, load_bucket_credentials,
+    provision_bucket, store_bucket_credentials, validate_buckets,
+};
 use crate::environment::models::environment::Environment;
 use crate::environment::report::logger::EnvLogger;
-use crate::errors::{EngineError, ErrorMessageVerbosity};
+use crate::environment::variable_preview_task::log_variable_change_preview;
+use crate::errors::{CommandError, EngineError, ErrorMessageVerbosity};
 use crate::events::{EngineEvent, EnvironmentStep, EventDetails, EventMessage, Stage};
+use crate::infrastructure::certificate_watchdog_task::watch_certificates;
+use crate::infrastructure::delete_dry_run::{
+    expected_environment_resources, publish_deletion_report, DeletedNamespaces, KubeDeletionInventory,
+};
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::build_platform;
-use crate::infrastructure::models::build_platform::{BuildError, BuildPlatform};
+use crate::infrastructure::models::build_platform::in_cluster_clone::InClusterCloneAgent;
+use crate::infrastructure::models::build_platform::sbom::SbomPublisher;
+use crate::infrastructure::models::build_platform::{BuildError, BuildPlatform, GitAccess, SourceFetcher};
 use crate::infrastructure::models::cloud_provider::service;
 use crate::infrastructure::models::cloud_provider::service::Service;
 use crate::infrastructure::models::container_registry::errors::ContainerRegistryError;
-use crate::infrastructure::models::container_registry::{to_engine_error, ContainerRegistry, RegistryTags};
+use crate::infrastructure::models::container_registry::{
+    to_engine_error, ContainerRegistry, RegistryCleanupPolicy, RegistryTags,
+};
+use crate::infrastructure::models::deletion_report::{DeletionReport, DeletionScope};
+use crate::infrastructure::models::kubernetes::certificate_watchdog::DEFAULT_RENEWAL_THRESHOLD_IN_DAYS;
+use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+use crate::infrastructure::models::object_storage::ObjectStorage;
 use crate::io_models::context::Context;
-use crate::io_models::engine_request::EnvironmentEngineRequest;
+use crate::io_models::engine_request::{DeleteDryRun, EnvironmentEngineRequest};
+use crate::io_models::environment::EnvironmentRequest;
+use crate::io_models::feature_flags::FeatureFlags;
 use crate::io_models::Action;
 use crate::log_file_writer::LogFileWriter;
 use crate::logger::Logger;
 use crate::metrics_registry::{MetricsRegistry, StepLabel, StepName, StepRecordHandle, StepStatus};
 use base64::Engine;
+use chrono::Utc;
 use itertools::Itertools;
+use std::borrow::Cow;
 use std::cmp::{max, min};
 use std::collections::{HashSet, VecDeque};
 use std::num::NonZeroUsize;
@@ -34,18 +58,27 @@ use std::{env, fs, thread};
 use tokio::sync::broadcast;
 use uuid::Uuid;
 
+const SBOM_GENERATION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
 #[derive(Clone)]
 pub struct DeploymentOption {
     pub force_build: bool,
     pub force_push: bool,
 }
 
+// Minimum durations, a stage is not started with less than that left of the task budget
+const PROVISION_BUCKETS_STAGE: TaskStage = TaskStage::new("provision buckets", Duration::from_secs(60));
+const BUILD_STAGE: TaskStage = TaskStage::new("build", Duration::from_secs(5 * 60));
+const DEPLOY_STAGE: TaskStage = TaskStage::new("deploy", Duration::from_secs(2 * 60));
+const DELETE_BUCKETS_STAGE: TaskStage = TaskStage::new("delete buckets", Duration::from_secs(60));
+
 pub struct EnvironmentTask {
     workspace_root_dir: String,
     lib_root_dir: String,
     docker: Arc<Docker>,
     request: EnvironmentEngineRequest,
     cancel_requested: Arc<AtomicAbortStatus>,
+    deadline: Arc<TaskDeadline>,
     logger: Box<dyn Logger>,
     metrics_registry: Box<dyn MetricsRegistry>,
     qovery_api: Arc<dyn QoveryApi>,
@@ -73,6 +106,10 @@ impl EnvironmentTask {
         );
 
         let secrets = Self::get_secrets(&request);
+        let max_duration = request
+            .max_duration_in_sec
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TASK_MAX_DURATION);
         EnvironmentTask {
             workspace_root_dir,
             lib_root_dir,
@@ -81,6 +118,7 @@ impl EnvironmentTask {
             logger: logger.with_secrets(secrets),
             metrics_registry,
             cancel_requested: Arc::new(AtomicAbortStatus::new(AbortStatus::None)),
+            deadline: Arc::new(TaskDeadline::new(max_duration)),
             qovery_api: Arc::from(qovery_api),
             span,
             is_terminated: {
@@ -100,11 +138,13 @@ impl EnvironmentTask {
             self.lib_root_dir.to_string(),
             self.request.test_cluster,
             self.request.features.clone(),
+            FeatureFlags::resolve(&self.request.feature_flags).0,
             self.request.metadata.clone(),
             self.docker.clone(),
             self.qovery_api.clone(),
             self.request.event_details(),
         )
+        .with_proxy(self.request.proxy.clone())
     }
 
     fn infrastructure_context(&self) -> Result<InfrastructureContext, Box<EngineError>> {
@@ -137,7 +177,7 @@ impl EnvironmentTask {
     ) -> Result<(), Box<EngineError>> {
         // Only keep services that have something to build
         let metrics_registry: Arc<dyn MetricsRegistry> = Arc::from(infra_ctx.metrics_registry().clone_dyn());
-        let services = services
+        let mut services = services
             .into_iter()
             .filter(|srv| srv.build().is_some())
             .collect::<Vec<_>>();
@@ -146,6 +186,30 @@ impl EnvironmentTask {
         if services.first().is_none() {
             return Ok(());
         };
+        let _execution_context = ExecutionContext::enter_stage(&Stage::Environment(EnvironmentStep::Build));
+
+        // Repositories only reachable from the cluster are cloned by an agent running inside it
+        let is_in_cluster_clone = |srv: &dyn Service| {
+            srv.build()
+                .is_some_and(|b| b.git_repository.git_access == GitAccess::InCluster)
+        };
+        if services.iter().any(|srv| is_in_cluster_clone(&**srv)) {
+            let source_fetcher: Arc<dyn SourceFetcher> =
+                Arc::new(InClusterCloneAgent::new(infra_ctx.mk_kube_client()?.client().clone()));
+            for srv in services.iter_mut().filter(|srv| is_in_cluster_clone(&***srv)) {
+                if let Some(build) = srv.build_mut() {
+                    build.git_repository.source_fetcher = Some(source_fetcher.clone());
+                }
+            }
+        }
+
+        // Docker Hub mirrors are a cluster setting, every builder of the cluster uses them
+        let registry_mirrors = &infra_ctx.kubernetes().advanced_settings().registry_mirrors;
+        if !registry_mirrors.is_empty() {
+            for build in services.iter_mut().filter_map(|srv| srv.build_mut()) {
+                build.registry_mirrors.clone_from(registry_mirrors);
+            }
+        }
 
         let max_build_in_parallel = max(min(max_build_in_parallel, services.len()), 1);
 
@@ -168,6 +232,11 @@ impl EnvironmentTask {
         let resource_ttl = infra_ctx.kubernetes().advanced_settings().resource_ttl();
         let cr_registry = infra_ctx.container_registry();
         let build_platform = infra_ctx.build_platform();
+        let sbom_publisher = match infra_ctx.kubernetes().advanced_settings().registry_sbom_enabled {
+            true => SbomPublisher::new(infra_ctx.kubernetes(), &infra_ctx.context().docker),
+            false => None,
+        };
+        let cleanup_policy = infra_ctx.kubernetes().advanced_settings().registry_cleanup_policy();
 
         services.iter().for_each(|service| {
             metrics_registry.start_record(*service.long_id(), StepLabel::Service, StepName::BuildQueueing);
@@ -183,6 +252,8 @@ impl EnvironmentTask {
                         option,
                         cr_registry,
                         build_platform,
+                        sbom_publisher.as_ref(),
+                        &cleanup_policy,
                         img_retention_time_sec,
                         RegistryTags {
                             environment_id: environment_id.to_string(),
@@ -212,6 +283,8 @@ impl EnvironmentTask {
         option: &DeploymentOption,
         cr_registry: &dyn ContainerRegistry,
         build_platform: &dyn BuildPlatform,
+        sbom_publisher: Option<&SbomPublisher>,
+        cleanup_policy: &RegistryCleanupPolicy,
         image_retention_time_sec: u32,
         registry_tags: RegistryTags,
         cr_to_engine_error: impl Fn(ContainerRegistryError) -> EngineError,
@@ -226,8 +299,9 @@ impl EnvironmentTask {
         };
         let image_name = build.image.full_image_name_with_tag();
 
-        // If image already exists in the registry, skip the build
-        if !option.force_build && cr_registry.image_exists(&build.image) {
+        // If image already exists in the registry, skip the build.
+        // The builder checks itself that a multi-architecture image has a manifest list with all the architectures.
+        if !option.force_build && !build.is_multi_architecture() && cr_registry.image_exists(&build.image) {
             let msg = format!("✅ Container image {image_name} already exists and ready to use");
             logger.send_success(msg);
             return Ok(());
@@ -260,6 +334,12 @@ impl EnvironmentTask {
         let build_result = build_platform.build(build, &logger, metrics_registry.clone(), abort);
         match build_result {
             Ok(_) => {
+                if let Some(sbom_publisher) = sbom_publisher {
+                    Self::publish_sbom(service, sbom_publisher, &logger, abort);
+                }
+                if cleanup_policy.is_enabled() {
+                    Self::cleanup_repository(service, cr_registry, cleanup_policy, &logger);
+                }
                 let msg = format!("✅ Container image {} is built and ready to use", &image_name);
                 logger.send_success(msg);
                 Ok(())
@@ -304,10 +384,57 @@ impl EnvironmentTask {
         }
     }
 
+    // A missing SBOM must not fail the deployment, it is only reported as a warning
+    fn publish_sbom(service: &dyn Service, sbom_publisher: &SbomPublisher, logger: &EnvLogger, abort: &dyn Abort) {
+        let Some(build) = service.build() else {
+            return;
+        };
+
+        match sbom_publisher.publish(&build.image, &CommandKiller::from(SBOM_GENERATION_TIMEOUT, abort)) {
+            Ok(object_key) => logger.send_progress(format!("📜 SBOM of the container image published to {object_key}")),
+            Err(err) => {
+                let event_details = service.get_event_details(Stage::Environment(EnvironmentStep::Build));
+                let err = EngineError::new_builder_sbom_generation_error(
+                    event_details.clone(),
+                    build.image.full_image_name_with_tag(),
+                    CommandError::new_from_safe_message(err.to_string()),
+                );
+                logger.log(EngineEvent::Warning(event_details, EventMessage::new_from_engine_error(err)));
+            }
+        }
+    }
+
+    // Purging the images of the repository is best effort, a failure is only reported as a warning
+    fn cleanup_repository(
+        service: &dyn Service,
+        cr_registry: &dyn ContainerRegistry,
+        cleanup_policy: &RegistryCleanupPolicy,
+        logger: &EnvLogger,
+    ) {
+        let Some(build) = service.build() else {
+            return;
+        };
+
+        let repository_name = build.image.repository_name();
+        match cr_registry.cleanup_repository(repository_name, cleanup_policy, Some(&build.image.tag)) {
+            Ok(0) => {}
+            Ok(deleted_images) => logger.send_progress(format!(
+                "🧹 Purged {deleted_images} images from container repository {repository_name}"
+            )),
+            Err(err) => {
+                let event_details = service.get_event_details(Stage::Environment(EnvironmentStep::Build));
+                let err =
+                    EngineError::new_container_registry_cleanup_error(event_details.clone(), repository_name, err);
+                logger.log(EngineEvent::Warning(event_details, EventMessage::new_from_engine_error(err)));
+            }
+        }
+    }
+
     pub fn deploy_environment(
         mut environment: Environment,
         infra_ctx: &InfrastructureContext,
         abort: &dyn Abort,
+        deadline: &TaskDeadline,
     ) -> Result<(), Box<EngineError>> {
         let mut deployed_services: HashSet<Uuid> = HashSet::new();
         let event_details = environment.event_details().clone();
@@ -324,6 +451,9 @@ impl EnvironmentTask {
                 .map(|app| app.as_service_mut())
                 .chain(environment.jobs.iter_mut().map(|job| job.as_service_mut()))
                 .collect();
+            if services_to_build.iter().any(|srv| srv.build().is_some()) {
+                start_stage(deadline, BUILD_STAGE, &event_details)?;
+            }
             Self::build_and_push_services(
                 environment.long_id,
                 environment.project_long_id,
@@ -341,6 +471,7 @@ impl EnvironmentTask {
             if abort.status().should_cancel() {
                 return Err(Box::new(EngineError::new_task_cancellation_requested(event_details)));
             }
+            start_stage(deadline, DEPLOY_STAGE, &event_details)?;
             let mut env_deployment = EnvironmentDeployment::new(infra_ctx, &environment, abort, logger.clone())?;
             let deployment_ret = match environment.action {
                 service::Action::Create => env_deployment.on_create(),
@@ -349,6 +480,7 @@ impl EnvironmentTask {
                 service::Action::Restart => env_deployment.on_restart(),
             };
             deployed_services = env_deployment.deployed_services.lock().map(|v| v.clone()).unwrap();
+            deadline.end_stage();
 
             deployment_ret
         };
@@ -367,7 +499,8 @@ impl EnvironmentTask {
             .chain(environment.routers.iter().map(|x| x.as_service()))
             .chain(environment.databases.iter().map(|x| x.as_service()))
             .chain(environment.jobs.iter().map(|x| x.as_service()))
-            .chain(environment.helm_charts.iter().map(|x| x.as_service()));
+            .chain(environment.helm_charts.iter().map(|x| x.as_service()))
+            .chain(environment.k8s_manifests.iter().map(|x| x.as_service()));
 
         for service in services {
             if deployed_services.contains(service.long_id()) {
@@ -382,6 +515,170 @@ impl EnvironmentTask {
         Err(deployment_err)
     }
 
+    // Lists what the deletion would remove, without running any of the delete actions
+    fn report_delete_dry_run(
+        &self,
+        environment: &Environment,
+        infra_ctx: &InfrastructureContext,
+        dry_run: &DeleteDryRun,
+    ) {
+        let event_details = self.get_event_details(EnvironmentStep::DeleteDryRun);
+        let kube_client = match infra_ctx.mk_kube_client() {
+            Ok(kube_client) => kube_client,
+            Err(err) => {
+                self.logger.log(EngineEvent::Error(*err, None));
+                return;
+            }
+        };
+
+        let inventory = KubeDeletionInventory::new(
+            kube_client.client().clone(),
+            DeletedNamespaces::Environment(environment.namespace().to_string()),
+            infra_ctx.container_registry(),
+            infra_ctx.kubernetes().object_storage(),
+        );
+        let report = DeletionReport::assemble(
+            DeletionScope::Environment,
+            environment.long_id,
+            self.request.target_environment.name.clone(),
+            Utc::now(),
+            &inventory,
+            &expected_environment_resources(environment, infra_ctx.kubernetes(), infra_ctx.container_registry()),
+        );
+
+        publish_deletion_report(&report, dry_run, self.logger.as_ref(), event_details);
+    }
+
+    fn buckets_object_storage<'a>(
+        &self,
+        infra_ctx: &'a InfrastructureContext,
+        event_details: &EventDetails,
+    ) -> Result<&'a dyn ObjectStorage, Box<EngineError>> {
+        infra_ctx.kubernetes().object_storage().ok_or_else(|| {
+            Box::new(EngineError::new_invalid_engine_payload(
+                event_details.clone(),
+                "Buckets are not supported on self-managed clusters",
+                None,
+            ))
+        })
+    }
+
+    /// Provisions the buckets of the environment and returns the request with their variables injected
+    /// into the dependent services. Nothing is provisioned on dry runs, nor for actions not deploying the services.
+    fn provision_buckets(
+        &self,
+        infra_ctx: &InfrastructureContext,
+    ) -> Result<Cow<EnvironmentRequest>, Box<EngineError>> {
+        let buckets = &self.request.target_environment.buckets;
+        if buckets.is_empty() || self.request.action != Action::Create || infra_ctx.context().is_dry_run_deploy() {
+            return Ok(Cow::Borrowed(&self.request.target_environment));
+        }
+
+        let event_details = self.get_event_details(EnvironmentStep::Deploy);
+        validate_buckets(buckets).map_err(|err| {
+            Box::new(EngineError::new_invalid_engine_payload(
+                event_details.clone(),
+                err.to_string().as_str(),
+                None,
+            ))
+        })?;
+        let object_storage = self.buckets_object_storage(infra_ctx, &event_details)?;
+        let kube_client = infra_ctx.mk_kube_client()?;
+        let credentials_error = |bucket_name: String, err: kube::Error| {
+            Box::new(EngineError::new_object_storage_error(
+                event_details.clone(),
+                ObjectStorageError::CannotCreateBucketCredentials {
+                    bucket_name,
+                    raw_error_message: format!("Cannot store the credentials in the cluster: {err}"),
+                },
+            ))
+        };
+
+        let mut target_environment = self.request.target_environment.clone();
+        for bucket in buckets {
+            let stored_credentials = load_bucket_credentials(kube_client.client(), bucket)
+                .map_err(|err| credentials_error(bucket.name_suffix.clone(), err))?;
+            let is_stored = stored_credentials.is_some();
+            let provisioned = provision_bucket(object_storage, bucket, stored_credentials)
+                .map_err(|err| Box::new(EngineError::new_object_storage_error(event_details.clone(), err)))?;
+            if !is_stored {
+                store_bucket_credentials(kube_client.client(), bucket, &provisioned.credentials)
+                    .map_err(|err| credentials_error(provisioned.name.clone(), err))?;
+            }
+
+            inject_bucket_variables(
+                target_environment.services_variables_mut(),
+                bucket,
+                &provisioned.variables(bucket),
+            );
+            self.logger.log(EngineEvent::Info(
+                event_details.clone(),
+                EventMessage::new(format!("🪣 Bucket `{}` is ready", provisioned.name), None),
+            ));
+        }
+
+        Ok(Cow::Owned(target_environment))
+    }
+
+    /// Deletes the credentials of the buckets of the environment, and the buckets not retained
+    fn delete_buckets(&self, infra_ctx: &InfrastructureContext) -> Result<(), Box<EngineError>> {
+        let buckets = &self.request.target_environment.buckets;
+        if buckets.is_empty() {
+            return Ok(());
+        }
+
+        let event_details = self.get_event_details(EnvironmentStep::Delete);
+        let object_storage = self.buckets_object_storage(infra_ctx, &event_details)?;
+        let kube_client = infra_ctx.mk_kube_client()?;
+        for bucket in buckets {
+            delete_bucket(object_storage, bucket)
+                .map_err(|err| Box::new(EngineError::new_object_storage_error(event_details.clone(), err)))?;
+            delete_stored_bucket_credentials(kube_client.client(), bucket).map_err(|err| {
+                Box::new(EngineError::new_object_storage_error(
+                    event_details.clone(),
+                    ObjectStorageError::CannotDeleteBucketCredentials {
+                        bucket_name: bucket.name_suffix.clone(),
+                        raw_error_message: format!("Cannot delete the credentials stored in the cluster: {err}"),
+                    },
+                ))
+            })?;
+
+            let message = match bucket.retain_on_delete {
+                true => format!("🪣 Bucket `{}` is retained, its credentials are deleted", bucket.name_suffix),
+                false => format!("🗑️ Bucket `{}` is deleted", bucket.name_suffix),
+            };
+            self.logger
+                .log(EngineEvent::Info(event_details.clone(), EventMessage::new(message, None)));
+        }
+
+        Ok(())
+    }
+
+    // Best effort, only the certificates of the environment routers are checked
+    fn watch_certificates(&self, namespace: &str, infra_ctx: &InfrastructureContext) {
+        let event_details = self.get_event_details(EnvironmentStep::Deployed);
+        let result = infra_ctx
+            .mk_kube_client()
+            .map_err(|err| err.to_string())
+            .and_then(|kube_client| {
+                watch_certificates(
+                    kube_client.client(),
+                    Some(namespace),
+                    DEFAULT_RENEWAL_THRESHOLD_IN_DAYS,
+                    true,
+                    self.logger.as_ref(),
+                    event_details.clone(),
+                )
+                .map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            self.logger.log(EngineEvent::Warning(
+                event_details,
+                EventMessage::new("Cannot check the certificates of the environment".to_string(), Some(err)),
+            ));
+        }
+    }
+
     fn get_secrets(request: &EnvironmentEngineRequest) -> Vec<String> {
         let mut secrets = vec![];
         let services_secrets = request
@@ -409,6 +706,13 @@ impl EnvironmentTask {
                     .helms
                     .iter()
                     .flat_map(|x| x.environment_vars_with_infos.values()),
+            )
+            .chain(
+                request
+                    .target_environment
+                    .k8s_manifests
+                    .iter()
+                    .flat_map(|x| x.environment_vars_with_infos.values()),
             );
 
         let service_secrets = services_secrets.filter_map(|v| {
@@ -421,6 +725,22 @@ impl EnvironmentTask {
             Some(String::from_utf8(decoded_secret).unwrap_or_default())
         });
         secrets.extend(service_secrets);
+        secrets.extend(
+            request
+                .target_environment
+                .routers
+                .iter()
+                .flat_map(|router| &router.basic_auth_credentials)
+                .map(|credential| credential.secret().to_string()),
+        );
+        secrets.extend(
+            request
+                .kubernetes
+                .advanced_settings
+                .default_secret_environment_variables
+                .values()
+                .cloned(),
+        );
 
         let cloud_provider_secrets = request
             .cloud_provider
@@ -436,6 +756,7 @@ impl EnvironmentTask {
             .cloned();
 
         secrets.extend(cloud_provider_secrets);
+        secrets.extend(request.proxy.iter().flat_map(|proxy| proxy.secrets()));
         secrets
     }
 
@@ -469,11 +790,28 @@ impl Task for EnvironmentTask {
 
         let _span = self.span.enter();
         info!("environment task {} started", self.id());
+        let _execution_context = ExecutionContext::from(&self.request.event_details()).enter();
+        self.deadline.start();
 
         self.logger.log(EngineEvent::Info(
             self.get_event_details(EnvironmentStep::Start),
             EventMessage::new("🚀 Qovery Engine starts to execute the deployment".to_string(), None),
         ));
+        engine_task::log_feature_flags(
+            &self.request.feature_flags,
+            self.logger.as_ref(),
+            self.get_event_details(EnvironmentStep::Start),
+        );
+        engine_task::log_deprecated_fields(
+            &self.request.deprecated_fields,
+            self.logger.as_ref(),
+            self.get_event_details(EnvironmentStep::Start),
+        );
+        engine_task::install_proxy(
+            self.request.proxy.as_ref(),
+            self.logger.as_ref(),
+            self.get_event_details(EnvironmentStep::Start),
+        );
         let guard = scopeguard::guard((), |_| {
             self.logger.log(EngineEvent::Info(
                 self.get_event_details(EnvironmentStep::Terminated),
@@ -492,6 +830,16 @@ impl Task for EnvironmentTask {
                 return;
             }
         };
+        if infra_context.context().is_dry_run_deploy() {
+            if let Some(preview_request) = &self.request.target_environment.variable_change_preview {
+                log_variable_change_preview(
+                    self.logger.as_ref(),
+                    self.get_event_details(EnvironmentStep::VariableChangePreview),
+                    preview_request,
+                );
+            }
+        }
+
         let env_step = self
             .request
             .target_environment
@@ -499,7 +847,16 @@ impl Task for EnvironmentTask {
             .to_service_action()
             .to_environment_step();
         let event_details = self.get_event_details(env_step);
-        let environment = match self.request.target_environment.to_environment_domain(
+        let target_environment = match start_stage(&self.deadline, PROVISION_BUCKETS_STAGE, &event_details)
+            .and_then(|_| self.provision_buckets(&infra_context))
+        {
+            Ok(target_environment) => target_environment,
+            Err(err) => {
+                self.logger.log(EngineEvent::Error(*err, None));
+                return;
+            }
+        };
+        let environment = match target_environment.to_environment_domain(
             infra_context.context(),
             infra_context.cloud_provider(),
             infra_context.container_registry(),
@@ -515,6 +872,11 @@ impl Task for EnvironmentTask {
             }
         };
 
+        if let (Action::Delete, Some(dry_run)) = (&self.request.action, &self.request.delete_dry_run) {
+            self.report_delete_dry_run(&environment, &infra_context, dry_run);
+            return;
+        }
+
         // run the actions
 
         let metrics_registry = Arc::new(infra_context.metrics_registry().clone_dyn());
@@ -524,7 +886,8 @@ impl Task for EnvironmentTask {
             .chain(environment.routers.iter().map(|x| x.as_service().long_id()))
             .chain(environment.databases.iter().map(|x| x.as_service().long_id()))
             .chain(environment.jobs.iter().map(|x| x.as_service().long_id()))
-            .chain(environment.helm_charts.iter().map(|x| x.as_service().long_id()));
+            .chain(environment.helm_charts.iter().map(|x| x.as_service().long_id()))
+            .chain(environment.k8s_manifests.iter().map(|x| x.as_service().long_id()));
 
         let record = metrics_registry.start_record(environment.long_id, StepLabel::Environment, StepName::Total);
         let service_records: Vec<StepRecordHandle> = service_ids
@@ -532,11 +895,38 @@ impl Task for EnvironmentTask {
             .map(|service_id| metrics_registry.start_record(*service_id, StepLabel::Service, StepName::Total))
             .collect();
 
-        let deployment_ret =
-            EnvironmentTask::deploy_environment(environment, &infra_context, self.cancel_checker().as_ref());
+        let namespace = environment.namespace().to_string();
+        let deployment_ret = EnvironmentTask::deploy_environment(
+            environment,
+            &infra_context,
+            self.cancel_checker().as_ref(),
+            &self.deadline,
+        );
+        // buckets are deleted once the services using them are gone
+        let deployment_ret = match deployment_ret {
+            Ok(()) if self.request.action == Action::Delete => {
+                start_stage(&self.deadline, DELETE_BUCKETS_STAGE, &event_details)
+                    .and_then(|_| self.delete_buckets(&infra_context))
+            }
+            deployment_ret => deployment_ret,
+        };
+        // a cancellation requested by the deadline is not one requested by the user
+        let deployment_ret = match (deployment_ret, self.deadline.exceeded()) {
+            (Err(err), Some(deadline_exceeded)) if err.tag().is_cancel() => Err(Box::new(
+                EngineError::new_execution_deadline_exceeded(event_details, deadline_exceeded),
+            )),
+            (deployment_ret, _) => deployment_ret,
+        };
 
         Self::stop_total_steps_records(&deployment_ret, record, service_records);
+        if self.request.action == Action::Create
+            && deployment_ret.is_ok()
+            && !infra_context.context().is_dry_run_deploy()
+        {
+            self.watch_certificates(&namespace, &infra_context);
+        }
 
+        let succeeded = deployment_ret.is_ok();
         match (&self.request.action, deployment_ret) {
             (Action::Create, Ok(())) => self.logger.log(EngineEvent::Info(
                 self.get_event_details(EnvironmentStep::Deployed),
@@ -605,10 +995,11 @@ impl Task for EnvironmentTask {
 
         // only store if not running on a workstation
         if env::var("DEPLOY_FROM_FILE_KIND").is_err() {
-            match crate::fs::create_workspace_archive(
-                infra_context.context().workspace_root_dir(),
-                infra_context.context().execution_id(),
-            ) {
+            match infra_context
+                .context()
+                .workspace()
+                .and_then(|workspace| workspace.archive(succeeded))
+            {
                 Ok(file) => match engine_task::upload_s3_file(self.request.archive.as_ref(), &file) {
                     Ok(_) => {
                         let _ = fs::remove_file(file).map_err(|err| error!("Cannot remove file {}", err));
@@ -640,7 +1031,7 @@ impl Task for EnvironmentTask {
             EventMessage::new(r#"
                     🚫 Cancel received, deployment is going to stop.
                     This may take a while, as a safe point need to be reached.
-                    Some operation cannot be stopped (i.e: terraform actions) and need to be completed before stopping the deployment
+                    Running terraform and helm commands are interrupted, and given some time to stop gracefully before being killed
                     "#.trim().to_string()
                               , None),
         ));
@@ -649,7 +1040,8 @@ impl Task for EnvironmentTask {
 
     fn cancel_checker(&self) -> Box<dyn Abort> {
         let cancel_requested = self.cancel_requested.clone();
-        Box::new(move || cancel_requested.load(Ordering::Relaxed))
+        let deadline = self.deadline.clone();
+        Box::new(move || AbortStatus::merge(cancel_requested.load(Ordering::Relaxed), deadline.abort_status()))
     }
 
     fn is_terminated(&self) -> bool {
@@ -661,6 +1053,18 @@ impl Task for EnvironmentTask {
     }
 }
 
+/// Starts the stage if enough of the task budget remains for it
+fn start_stage(
+    deadline: &TaskDeadline,
+    stage: TaskStage,
+    event_details: &EventDetails,
+) -> Result<(), Box<EngineError>> {
+    deadline
+        .start_stage(stage)
+        .map(|_| ())
+        .map_err(|err| Box::new(EngineError::new_execution_deadline_exceeded(event_details.clone(), err)))
+}
+
 struct BuilderThreadPool {}
 
 impl BuilderThreadPool {
@@ -733,9 +1137,11 @@ impl BuilderThreadPool {
                     .spawn_scoped(scope, {
                         let current_thread = &current_thread;
                         let current_span = tracing::Span::current();
+                        let execution_context = ExecutionContext::current();
 
                         move || {
                             let _span = current_span.enter();
+                            let _execution_context = execution_context.map(ExecutionContext::enter);
                             let _guard = scopeguard::guard((), |_| current_thread.unpark());
                             task()
                         }
@@ -0,0 +1,125 @@
+use crate::cmd::command::ExecutionContext;
+use crate::engine_task::Task;
+use crate::environment::models::abort::{Abort, AbortStatus};
+use crate::errors::{CommandError, EngineError};
+use crate::events::{EngineEvent, EventDetails, EventMessage};
+use crate::io_models::engine_request::VariableChangePreviewEngineRequest;
+use crate::io_models::variable_preview::{preview_variable_change, VariableChangePreviewRequest};
+use crate::logger::Logger;
+use std::sync::RwLock;
+use tokio::sync::broadcast;
+
+/// Computes the services impacted by variable changes, and sends the result to the core.
+/// Used by the standalone task, and by environment deployments running in dry run.
+pub fn log_variable_change_preview(
+    logger: &dyn Logger,
+    event_details: EventDetails,
+    request: &VariableChangePreviewRequest,
+) {
+    let preview = match preview_variable_change(request) {
+        Ok(preview) => preview,
+        Err(err) => {
+            logger.log(EngineEvent::Error(
+                EngineError::new_invalid_variable_change_preview(
+                    event_details,
+                    CommandError::new_from_safe_message(err.to_string()),
+                ),
+                None,
+            ));
+            return;
+        }
+    };
+
+    let message = format!(
+        "🔎 Variable change preview: {} service(s) impacted, {} unaffected, {} change(s) masked",
+        preview.impacted_services.len(),
+        preview.unaffected_services.len(),
+        preview.masked_changes.len()
+    );
+    // the preview only contains ids and keys, never values
+    let Ok(response) = serde_json::to_string(&preview) else {
+        logger.log(EngineEvent::Error(
+            EngineError::new_invalid_variable_change_preview(
+                event_details,
+                CommandError::new_from_safe_message("Cannot serialize the variable change preview".to_string()),
+            ),
+            None,
+        ));
+        return;
+    };
+
+    logger.log(EngineEvent::Info(
+        event_details,
+        EventMessage::new_for_sending_core_data(message, response),
+    ));
+}
+
+pub struct VariableChangePreviewTask {
+    request: VariableChangePreviewEngineRequest,
+    logger: Box<dyn Logger>,
+    span: tracing::Span,
+    is_terminated: (RwLock<Option<broadcast::Sender<()>>>, broadcast::Receiver<()>),
+}
+
+impl VariableChangePreviewTask {
+    pub fn new(request: VariableChangePreviewEngineRequest, logger: Box<dyn Logger>) -> Self {
+        let span = info_span!(
+            "variable_change_preview_task",
+            organization_id = request.organization_long_id.to_string(),
+            execution_id = request.id,
+        );
+
+        VariableChangePreviewTask {
+            request,
+            logger,
+            span,
+            is_terminated: {
+                let (tx, rx) = broadcast::channel(1);
+                (RwLock::new(Some(tx)), rx)
+            },
+        }
+    }
+}
+
+impl Task for VariableChangePreviewTask {
+    fn id(&self) -> &str {
+        self.request.id.as_str()
+    }
+
+    fn run(&self) {
+        let _span = self.span.enter();
+        info!("variable change preview task {} started", self.id());
+        let _execution_context = ExecutionContext::from(&self.request.event_details()).enter();
+
+        let _guard = scopeguard::guard((), |_| {
+            let Some(is_terminated_tx) = self.is_terminated.0.write().unwrap().take() else {
+                return;
+            };
+            let _ = is_terminated_tx.send(());
+        });
+
+        log_variable_change_preview(
+            self.logger.as_ref(),
+            self.request.event_details(),
+            &self.request.target_environment,
+        );
+
+        info!("variable change preview task {} finished", self.id());
+    }
+
+    fn cancel(&self, _force_requested: bool) -> bool {
+        false
+    }
+
+    fn cancel_checker(&self) -> Box<dyn Abort> {
+        Box::new(move || AbortStatus::None)
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.is_terminated.0.read().map(|tx| tx.is_none()).unwrap_or(true)
+    }
+
+    fn await_terminated(&self) -> broadcast::Receiver<()> {
+        self.is_terminated.1.resubscribe()
+    }
+}
@@ -38,6 +38,8 @@ pub enum Tag {
     BuilderDockerCannotReadDockerfile,
     BuilderError,
     BuilderGetBuildError,
+    BuilderSbomGenerationError,
+    BuilderEmulatedBuildTimeout,
     CannotConnectK8sCluster,
     CannotCopyFilesFromDirectoryToDirectory,
     CannotCreateFile,
@@ -100,8 +102,11 @@ pub enum Tag {
     ContainerRegistryRepositoryDoesntExistInRegistry,
     ContainerRegistryRepositoryNameInvalid,
     ContainerRegistryUnknownError,
+    ContainerRegistryCannotCleanupRepository,
     DatabaseError,
     DatabaseFailedToStartAfterSeveralRetries,
+    DatabaseHealthCheckRefused,
+    DatabaseCredentialsRotationFailed,
     DeleteLocalKubeconfigFileError,
     DnsProviderInformationError,
     DnsProviderInvalidApiUrl,
@@ -116,8 +121,10 @@ pub enum Tag {
     HelmChartsUpgradeError,
     HelmDeployTimeout,
     HelmHistoryError,
+    HelmRegistryAuthenticationError,
     HelmReleaseDataNotFound,
     HelmSecretNotFound,
+    HelmReleaseOwnedByAnotherService,
     InvalidEngineApiInputCannotBeDeserialized,
     InvalidEnginePayload,
     InvalidJobOutputCannotBeSerialized,
@@ -134,6 +141,11 @@ pub enum Tag {
     K8sCannotGetCrashLoopingPods,
     K8sCannotGetPVCs,
     K8sCannotGetPods,
+    K8sCannotGenerateKubeconfig,
+    CannotReportClusterHealth,
+    CannotReportServiceInventory,
+    CannotWatchCertificates,
+    CannotReportDeleteDryRun,
     K8sCannotGetServices,
     K8sCannotGetStatefulset,
     K8sCannotOrphanDelete,
@@ -152,13 +164,19 @@ pub enum Tag {
     K8sGetStatefulsetError,
     K8sHistory,
     K8sLoadBalancerConfigurationIssue,
+    K8sManifestsApplyError,
+    K8sManifestsInvalid,
     K8sNodeIsNotReady,
     K8sNodeIsNotReadyWithTheRequestedVersion,
     K8sPatchSecretError,
     K8sPodDisruptionBudgetInInvalidState,
     K8sPodIsNotReady,
+    K8sPodSecurityViolation,
+    K8sUnsupportedFeature,
     K8sPodsDisruptionBudgetCannotBeRetrieved,
+    K8sResilienceCheckFailed,
     K8sScaleReplicas,
+    K8sUpgradePreflightCheckFailed,
     K8sServiceError,
     K8sUpgradeDeployedVsRequestedVersionsInconsistency,
     K8sValidateRequiredCPUandBurstableError,
@@ -185,6 +203,10 @@ pub enum Tag {
     ObjectStorageCannotDeleteFileIntoBucket,
     ObjectStorageCannotEmptyBucket,
     ObjectStorageCannotGetObjectFile,
+    ObjectStorageCannotApplyBucketSettings,
+    ObjectStorageCannotCreateBucketCredentials,
+    ObjectStorageCannotDeleteBucketCredentials,
+    ObjectStorageCannotUseEncryptionKey,
     ObjectStorageCannotPutFileIntoBucket,
     ObjectStorageCannotTagBucket,
     ObjectStorageInvalidBucketName,
@@ -200,6 +222,7 @@ pub enum Tag {
     TerraformCannotImportResource,
     TerraformCannotRemoveEntryOut,
     TerraformCloudProviderActivationRequired,
+    TerraformCloudProviderApiThrottling,
     TerraformCloudProviderQuotasReached,
     TerraformClusterUnsupportedVersionUpdate,
     TerraformConfigFileInvalidContent,
@@ -227,6 +250,7 @@ pub enum Tag {
     TerraformWaitingTimeoutResource,
     TerraformWrongState,
     TerraformValidatorError,
+    TerraformInvalidTargetAddress,
     UncompressError,
     Unknown,
     UnsupportedClusterKind,
@@ -238,10 +262,26 @@ pub enum Tag {
     RouterInvalidConfiguration,
     RouterBasicAuthEnvVarCannotDecodeBase64Error,
     RouterBasicAuthEnvVarNotFound,
+    RouterBasicAuthInvalidCredentials,
     ServiceInstantiationError,
     CannotGetRegistryCredentials,
     K8sCannotDeleteService,
     K8sGetWebHookConfigurationError,
+    InvalidVariableChangePreview,
+    NginxInvalidCustomErrorPages,
+    ClusterAutoscalerInvalidSettings,
+    ClusterAutoscalerSettingsConflictWithKarpenter,
+    CannotPromoteImage,
+    TerraformDestructiveReplacementRefused,
+    ServiceStillReceivingTraffic,
+    CannotMirrorImage,
+    ExecutionDeadlineExceeded,
+    ScalewayPrivateNetworkNotFound,
+    ScalewayPrivateNetworkInWrongRegion,
+    K8sStorageClassNotFound,
+    K8sStorageClassVolumeExpansionNotAllowed,
+    K8sStorageCannotBeReduced,
+    JobOutputTooLarge,
 }
 
 impl From<errors::Tag> for Tag {
@@ -263,6 +303,7 @@ impl From<errors::Tag> for Tag {
             errors::Tag::CannotUninstallHelmChart => Tag::CannotUninstallHelmChart,
             errors::Tag::HelmReleaseDataNotFound => Tag::HelmReleaseDataNotFound,
             errors::Tag::HelmSecretNotFound => Tag::HelmSecretNotFound,
+            errors::Tag::HelmReleaseOwnedByAnotherService => Tag::HelmReleaseOwnedByAnotherService,
             errors::Tag::CannotExecuteK8sVersion => Tag::CannotExecuteK8sVersion,
             errors::Tag::CannotDetermineK8sMasterVersion => Tag::CannotDetermineK8sMasterVersion,
             errors::Tag::CannotDetermineK8sRequestedUpgradeVersion => Tag::CannotDetermineK8sRequestedUpgradeVersion,
@@ -275,6 +316,11 @@ impl From<errors::Tag> for Tag {
             errors::Tag::K8sCannotGetCrashLoopingPods => Tag::K8sCannotGetCrashLoopingPods,
             errors::Tag::K8sCannotDeleteCompletedJobs => Tag::K8sCannotDeleteCompletedJobs,
             errors::Tag::K8sCannotGetPods => Tag::K8sCannotGetPods,
+            errors::Tag::K8sCannotGenerateKubeconfig => Tag::K8sCannotGenerateKubeconfig,
+            errors::Tag::CannotReportClusterHealth => Tag::CannotReportClusterHealth,
+            errors::Tag::CannotReportServiceInventory => Tag::CannotReportServiceInventory,
+            errors::Tag::CannotWatchCertificates => Tag::CannotWatchCertificates,
+            errors::Tag::CannotReportDeleteDryRun => Tag::CannotReportDeleteDryRun,
             errors::Tag::K8sUpgradeDeployedVsRequestedVersionsInconsistency => {
                 Tag::K8sUpgradeDeployedVsRequestedVersionsInconsistency
             }
@@ -288,6 +334,12 @@ impl From<errors::Tag> for Tag {
             errors::Tag::K8sHistory => Tag::K8sHistory,
             errors::Tag::K8sCannotCreateNamespace => Tag::K8sCannotCreateNamespace,
             errors::Tag::K8sPodIsNotReady => Tag::K8sPodIsNotReady,
+            errors::Tag::K8sPodSecurityViolation => Tag::K8sPodSecurityViolation,
+            errors::Tag::K8sUnsupportedFeature => Tag::K8sUnsupportedFeature,
+            errors::Tag::K8sManifestsInvalid => Tag::K8sManifestsInvalid,
+            errors::Tag::K8sManifestsApplyError => Tag::K8sManifestsApplyError,
+            errors::Tag::K8sResilienceCheckFailed => Tag::K8sResilienceCheckFailed,
+            errors::Tag::K8sUpgradePreflightCheckFailed => Tag::K8sUpgradePreflightCheckFailed,
             errors::Tag::K8sGetPodError => Tag::K8sGetPodError,
             errors::Tag::K8sGetDeploymentError => Tag::K8sGetDeploymentError,
             errors::Tag::K8sGetWebHookConfigurationError => Tag::K8sGetWebHookConfigurationError,
@@ -335,6 +387,8 @@ impl From<errors::Tag> for Tag {
             errors::Tag::ClientServiceFailedToStart => Tag::ClientServiceFailedToStart,
             errors::Tag::ClientServiceFailedToDeployBeforeStart => Tag::ClientServiceFailedToDeployBeforeStart,
             errors::Tag::DatabaseFailedToStartAfterSeveralRetries => Tag::DatabaseFailedToStartAfterSeveralRetries,
+            errors::Tag::DatabaseHealthCheckRefused => Tag::DatabaseHealthCheckRefused,
+            errors::Tag::DatabaseCredentialsRotationFailed => Tag::DatabaseCredentialsRotationFailed,
             errors::Tag::RouterFailedToDeploy => Tag::RouterFailedToDeploy,
             errors::Tag::CloudProviderClientInvalidCredentials => Tag::CloudProviderClientInvalidCredentials,
             errors::Tag::VersionNumberParsingError => Tag::VersionNumberParsingError,
@@ -348,6 +402,8 @@ impl From<errors::Tag> for Tag {
             errors::Tag::BuilderDockerCannotBuildContainerImage => Tag::BuilderDockerCannotBuildContainerImage,
             errors::Tag::BuilderGetBuildError => Tag::BuilderGetBuildError,
             errors::Tag::BuilderCloningRepositoryError => Tag::BuilderCloningRepositoryError,
+            errors::Tag::BuilderSbomGenerationError => Tag::BuilderSbomGenerationError,
+            errors::Tag::BuilderEmulatedBuildTimeout => Tag::BuilderEmulatedBuildTimeout,
             errors::Tag::DockerPushImageError => Tag::DockerPushImageError,
             errors::Tag::DockerPullImageError => Tag::DockerPullImageError,
             errors::Tag::ContainerRegistryCannotInstantiateClient => Tag::ContainerRegistryCannotInstantiateClient,
@@ -374,6 +430,7 @@ impl From<errors::Tag> for Tag {
             errors::Tag::ContainerRegistryCannotDeleteRegistry => Tag::ContainerRegistryCannotDeleteRegistry,
             errors::Tag::ContainerRegistryCannotSetRepositoryTags => Tag::ContainerRegistryCannotSetRepositoryTags,
             errors::Tag::ContainerRegistryUnknownError => Tag::ContainerRegistryUnknownError,
+            errors::Tag::ContainerRegistryCannotCleanupRepository => Tag::ContainerRegistryCannotCleanupRepository,
             errors::Tag::ContainerRegistryRepositoryNameInvalid => Tag::ContainerRegistryRepositoryNameInvalid,
             errors::Tag::BuilderDockerCannotListImages => Tag::BuilderDockerCannotListImages,
             errors::Tag::DockerError => Tag::DockerError,
@@ -417,6 +474,7 @@ impl From<errors::Tag> for Tag {
             errors::Tag::TerraformApplyError => Tag::TerraformApplyError,
             errors::Tag::TerraformDestroyError => Tag::TerraformDestroyError,
             errors::Tag::TerraformCloudProviderQuotasReached => Tag::TerraformCloudProviderQuotasReached,
+            errors::Tag::TerraformCloudProviderApiThrottling => Tag::TerraformCloudProviderApiThrottling,
             errors::Tag::TerraformCloudProviderActivationRequired => Tag::TerraformCloudProviderActivationRequired,
             errors::Tag::TerraformInvalidCredentials => Tag::TerraformInvalidCredentials,
             errors::Tag::TerraformServiceNotActivatedOptInRequired => Tag::TerraformServiceNotActivatedOptInRequired,
@@ -433,12 +491,18 @@ impl From<errors::Tag> for Tag {
             errors::Tag::TerraformCannotImportResource => Tag::TerraformCannotImportResource,
             errors::Tag::TerraformManagedDatabaseError => Tag::TerraformManagedDatabaseError,
             errors::Tag::TerraformValidatorError => Tag::TerraformValidatorError,
+            errors::Tag::TerraformInvalidTargetAddress => Tag::TerraformInvalidTargetAddress,
             errors::Tag::HelmDeployTimeout => Tag::HelmDeployTimeout,
+            errors::Tag::HelmRegistryAuthenticationError => Tag::HelmRegistryAuthenticationError,
             errors::Tag::CannotPauseManagedDatabase => Tag::CannotPauseManagedDatabase,
             errors::Tag::ObjectStorageCannotDeleteBucket => Tag::ObjectStorageCannotDeleteBucket,
             errors::Tag::ObjectStorageCannotGetBucket => Tag::ObjectStorageCannotGetBucket,
             errors::Tag::ObjectStorageQuotaExceeded => Tag::ObjectStorageQuotaExceeded,
             errors::Tag::ObjectStorageCannotGetObjectFile => Tag::ObjectStorageCannotGetObjectFile,
+            errors::Tag::ObjectStorageCannotApplyBucketSettings => Tag::ObjectStorageCannotApplyBucketSettings,
+            errors::Tag::ObjectStorageCannotCreateBucketCredentials => Tag::ObjectStorageCannotCreateBucketCredentials,
+            errors::Tag::ObjectStorageCannotDeleteBucketCredentials => Tag::ObjectStorageCannotDeleteBucketCredentials,
+            errors::Tag::ObjectStorageCannotUseEncryptionKey => Tag::ObjectStorageCannotUseEncryptionKey,
             errors::Tag::CloudProviderGetLoadBalancer => Tag::CloudProviderGetLoadBalancer,
             errors::Tag::CloudProviderGetLoadBalancerTags => Tag::CloudProviderGetLoadBalancerTags,
             errors::Tag::K8sCannotDeletePvc => Tag::K8sCannotDeletePvc,
@@ -483,6 +547,7 @@ impl From<errors::Tag> for Tag {
                 Tag::RouterBasicAuthEnvVarCannotDecodeBase64Error
             }
             errors::Tag::RouterBasicAuthEnvVarNotFound => Tag::RouterBasicAuthEnvVarNotFound,
+            errors::Tag::RouterBasicAuthInvalidCredentials => Tag::RouterBasicAuthInvalidCredentials,
             errors::Tag::CannotFetchScalewayPrivateNetworks => Tag::CannotFetchScalewayPrivateNetworks,
             errors::Tag::CannotWriteToFile => Tag::CannotWriteToFile,
             errors::Tag::CannotCreateHelmAdmissionControllerConfigMap => {
@@ -494,6 +559,23 @@ impl From<errors::Tag> for Tag {
             errors::Tag::ServiceInstantiationError => Tag::ServiceInstantiationError,
             errors::Tag::CannotGetRegistryCredentials => Tag::CannotGetRegistryCredentials,
             errors::Tag::CannotCreateAwsServiceLinkedRoleForSpotInstance => Tag::ServiceInstantiationError,
+            errors::Tag::InvalidVariableChangePreview => Tag::InvalidVariableChangePreview,
+            errors::Tag::NginxInvalidCustomErrorPages => Tag::NginxInvalidCustomErrorPages,
+            errors::Tag::ClusterAutoscalerInvalidSettings => Tag::ClusterAutoscalerInvalidSettings,
+            errors::Tag::ClusterAutoscalerSettingsConflictWithKarpenter => {
+                Tag::ClusterAutoscalerSettingsConflictWithKarpenter
+            }
+            errors::Tag::CannotPromoteImage => Tag::CannotPromoteImage,
+            errors::Tag::TerraformDestructiveReplacementRefused => Tag::TerraformDestructiveReplacementRefused,
+            errors::Tag::ServiceStillReceivingTraffic => Tag::ServiceStillReceivingTraffic,
+            errors::Tag::CannotMirrorImage => Tag::CannotMirrorImage,
+            errors::Tag::ExecutionDeadlineExceeded => Tag::ExecutionDeadlineExceeded,
+            errors::Tag::ScalewayPrivateNetworkNotFound => Tag::ScalewayPrivateNetworkNotFound,
+            errors::Tag::ScalewayPrivateNetworkInWrongRegion => Tag::ScalewayPrivateNetworkInWrongRegion,
+            errors::Tag::K8sStorageClassNotFound => Tag::K8sStorageClassNotFound,
+            errors::Tag::K8sStorageClassVolumeExpansionNotAllowed => Tag::K8sStorageClassVolumeExpansionNotAllowed,
+            errors::Tag::K8sStorageCannotBeReduced => Tag::K8sStorageCannotBeReduced,
+            errors::Tag::JobOutputTooLarge => Tag::JobOutputTooLarge,
         }
     }
 }
@@ -502,6 +584,8 @@ impl From<errors::Tag> for Tag {
 #[serde(rename_all = "lowercase")]
 pub struct EngineError {
     tag: Tag,
+    code: String,
+    is_retryable: bool,
     user_log_message: String,
     underlying_error: Option<CommandError>,
     link: Option<String>,
@@ -512,6 +596,8 @@ impl EngineError {
     pub fn from(error: errors::EngineError) -> (Self, EventDetails) {
         (
             EngineError {
+                code: error.code().to_string(),
+                is_retryable: error.is_retryable(),
                 tag: Tag::from(error.tag),
                 user_log_message: error.user_log_message,
                 underlying_error: error.underlying_error.map(CommandError::from),
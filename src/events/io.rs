@@ -35,6 +35,13 @@ pub enum EngineEvent {
         error: EngineError,
         message: Option<EventMessage>,
     },
+    // kept last, untagged variants are tried in order
+    Progress {
+        r#type: String,
+        timestamp: DateTime<Utc>,
+        details: EventDetails,
+        progress: EventProgress,
+    },
 }
 
 impl EngineEvent {
@@ -44,6 +51,7 @@ impl EngineEvent {
             EngineEvent::Info { timestamp, .. } => timestamp,
             EngineEvent::Warning { timestamp, .. } => timestamp,
             EngineEvent::Error { timestamp, .. } => timestamp,
+            EngineEvent::Progress { timestamp, .. } => timestamp,
         }
     }
 }
@@ -80,6 +88,12 @@ impl From<events::EngineEvent> for EngineEvent {
                     message: m.map(EventMessage::from),
                 }
             }
+            events::EngineEvent::Progress(d, p) => EngineEvent::Progress {
+                r#type: "progress".to_string(),
+                timestamp,
+                details: EventDetails::from(d),
+                progress: EventProgress::from(p),
+            },
         }
     }
 }
@@ -100,6 +114,25 @@ impl From<events::EventMessage> for EventMessage {
     }
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct EventProgress {
+    step: String,
+    current: u32,
+    total: u32,
+    eta_in_seconds: Option<u64>,
+}
+
+impl From<events::EventProgress> for EventProgress {
+    fn from(progress: events::EventProgress) -> Self {
+        EventProgress {
+            step: progress.step().to_string(),
+            current: progress.current(),
+            total: progress.total(),
+            eta_in_seconds: progress.eta().map(|eta| eta.as_secs()),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 #[serde(tag = "type", content = "step")]
@@ -144,6 +177,20 @@ pub enum InfrastructureStep {
     CannotProcessRequest,
     GlobalError,
     InfrastructureDiff,
+    GenerateKubeconfig,
+    KubeconfigGenerated,
+    GenerateKubeconfigError,
+    ReportClusterHealth,
+    ClusterHealthReported,
+    ReportClusterHealthError,
+    ReportServiceInventory,
+    ServiceInventoryReported,
+    ReportServiceInventoryError,
+    WatchCertificates,
+    CertificatesWatched,
+    WatchCertificatesError,
+    DeleteDryRun,
+    Cancel,
 }
 
 impl From<events::InfrastructureStep> for InfrastructureStep {
@@ -174,6 +221,20 @@ impl From<events::InfrastructureStep> for InfrastructureStep {
             events::InfrastructureStep::CannotProcessRequest => InfrastructureStep::CannotProcessRequest,
             events::InfrastructureStep::GlobalError => InfrastructureStep::GlobalError,
             events::InfrastructureStep::InfrastructureDiff(_) => InfrastructureStep::InfrastructureDiff,
+            events::InfrastructureStep::GenerateKubeconfig => InfrastructureStep::GenerateKubeconfig,
+            events::InfrastructureStep::KubeconfigGenerated => InfrastructureStep::KubeconfigGenerated,
+            events::InfrastructureStep::GenerateKubeconfigError => InfrastructureStep::GenerateKubeconfigError,
+            events::InfrastructureStep::ReportClusterHealth => InfrastructureStep::ReportClusterHealth,
+            events::InfrastructureStep::ClusterHealthReported => InfrastructureStep::ClusterHealthReported,
+            events::InfrastructureStep::ReportClusterHealthError => InfrastructureStep::ReportClusterHealthError,
+            events::InfrastructureStep::ReportServiceInventory => InfrastructureStep::ReportServiceInventory,
+            events::InfrastructureStep::ServiceInventoryReported => InfrastructureStep::ServiceInventoryReported,
+            events::InfrastructureStep::ReportServiceInventoryError => InfrastructureStep::ReportServiceInventoryError,
+            events::InfrastructureStep::WatchCertificates => InfrastructureStep::WatchCertificates,
+            events::InfrastructureStep::CertificatesWatched => InfrastructureStep::CertificatesWatched,
+            events::InfrastructureStep::WatchCertificatesError => InfrastructureStep::WatchCertificatesError,
+            events::InfrastructureStep::DeleteDryRun => InfrastructureStep::DeleteDryRun,
+            events::InfrastructureStep::Cancel => InfrastructureStep::Cancel,
         }
     }
 }
@@ -205,6 +266,8 @@ pub enum EnvironmentStep {
     GlobalError,
     JobOutput,
     DatabaseOutput,
+    VariableChangePreview,
+    DeleteDryRun,
     Recap,
     Restart,
     Restarted,
@@ -241,6 +304,8 @@ impl From<events::EnvironmentStep> for EnvironmentStep {
             events::EnvironmentStep::RestartedError => EnvironmentStep::RestartedError,
             events::EnvironmentStep::JobOutput => EnvironmentStep::JobOutput,
             events::EnvironmentStep::DatabaseOutput => EnvironmentStep::DatabaseOutput,
+            events::EnvironmentStep::VariableChangePreview => EnvironmentStep::VariableChangePreview,
+            events::EnvironmentStep::DeleteDryRun => EnvironmentStep::DeleteDryRun,
             events::EnvironmentStep::Recap => EnvironmentStep::Recap,
             events::EnvironmentStep::GlobalError => EnvironmentStep::GlobalError,
         }
@@ -267,6 +332,7 @@ pub enum Transmitter {
     Router { id: TransmitterId, name: TransmitterName },
     Job { id: TransmitterId, name: TransmitterName },
     Helm { id: TransmitterId, name: TransmitterName },
+    K8sManifests { id: TransmitterId, name: TransmitterName },
 }
 
 impl From<events::Transmitter> for Transmitter {
@@ -286,6 +352,7 @@ impl From<events::Transmitter> for Transmitter {
             events::Transmitter::Container(id, name) => Transmitter::Container { id, name },
             events::Transmitter::Job(id, name) => Transmitter::Job { id, name },
             events::Transmitter::Helm(id, name) => Transmitter::Helm { id, name },
+            events::Transmitter::K8sManifests(id, name) => Transmitter::K8sManifests { id, name },
         }
     }
 }
@@ -316,9 +383,10 @@ impl From<events::EventDetails> for EventDetails {
 mod test {
     use crate::errors::EngineError;
     use crate::events::io::EngineEvent as EngineEventIo;
-    use crate::events::{EngineEvent, EventDetails, InfrastructureStep, Stage, Transmitter};
+    use crate::events::{EngineEvent, EventDetails, EventProgress, InfrastructureStep, Stage, Transmitter};
     use crate::infrastructure::models::cloud_provider::Kind;
     use crate::io_models::QoveryIdentifier;
+    use std::time::Duration;
     use uuid::Uuid;
 
     #[test]
@@ -352,4 +420,27 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn should_serialize_progress_event() {
+        let event = EngineEvent::Progress(
+            EventDetails::new(
+                Some(Kind::Scw),
+                QoveryIdentifier::new_random(),
+                QoveryIdentifier::new_random(),
+                QoveryIdentifier::new_random().to_string(),
+                Stage::Infrastructure(InfrastructureStep::Create),
+                Transmitter::Kubernetes(Uuid::new_v4(), "".to_string()),
+            ),
+            EventProgress::new("terraform plan".to_string(), 2, 3, Some(Duration::from_secs(90))),
+        );
+
+        let json = serde_json::to_string(&EngineEventIo::from(event)).expect("cannot serialize progress event");
+        assert!(json.starts_with(r#"{"type":"progress","#));
+        assert!(json.contains(r#""progress":{"step":"terraform plan","current":2,"total":3,"eta_in_seconds":90}"#));
+
+        // untagged: the message events must not match a progress event
+        let event_io: EngineEventIo = serde_json::from_str(&json).expect("cannot deserialize progress event");
+        assert!(matches!(event_io, EngineEventIo::Progress { .. }));
+    }
 }
@@ -9,15 +9,20 @@ extern crate url;
 
 use crate::errors::{CommandError, EngineError, ErrorMessageVerbosity};
 use crate::infrastructure::models::cloud_provider::Kind;
+use crate::infrastructure::models::kubernetes::cluster_health::ClusterHealthDocument;
+use crate::infrastructure::models::kubernetes::service_inventory::ServiceInventoryDocument;
 use crate::io_models::QoveryIdentifier;
 use crate::metrics_registry::StepRecord;
 use derivative::Derivative;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
 use uuid::Uuid;
 
 #[derive(Debug, Clone)]
 pub enum EngineMsgPayload {
     Metrics(StepRecord),
+    ClusterHealth(ClusterHealthDocument),
+    ServiceInventory(ServiceInventoryDocument),
 }
 
 #[derive(Debug, Clone)]
@@ -42,6 +47,8 @@ pub enum EngineEvent {
     Warning(EventDetails, EventMessage),
     /// Error: represents an error event.
     Error(EngineError, Option<EventMessage>),
+    /// Progress: represents the progress of a task, i.e: the step being run among the total ones.
+    Progress(EventDetails, EventProgress),
 }
 
 impl EngineEvent {
@@ -52,6 +59,7 @@ impl EngineEvent {
             EngineEvent::Info(details, _message) => details,
             EngineEvent::Warning(details, _message) => details,
             EngineEvent::Error(engine_error, _message) => engine_error.event_details(),
+            EngineEvent::Progress(details, _progress) => details,
         }
     }
 
@@ -62,6 +70,7 @@ impl EngineEvent {
             EngineEvent::Info(_details, message) => message.message(message_verbosity),
             EngineEvent::Warning(_details, message) => message.message(message_verbosity),
             EngineEvent::Error(engine_error, _message) => engine_error.message(message_verbosity.into()),
+            EngineEvent::Progress(_details, progress) => progress.to_string(),
         }
     }
 
@@ -87,6 +96,65 @@ impl EngineEvent {
             EngineEvent::Error(engine_error, None) => {
                 engine_error.obfuscate(transformer);
             }
+            EngineEvent::Progress(_, progress) => {
+                progress.step = transformer(std::mem::take(&mut progress.step));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// EventProgress: represents the progress of a task.
+pub struct EventProgress {
+    step: String,
+    // 1-based index of the step being run.
+    current: u32,
+    total: u32,
+    // Estimated time before the last step is done, unknown until a step is done.
+    eta: Option<Duration>,
+}
+
+impl EventProgress {
+    /// Creates a new EventProgress.
+    ///
+    /// Arguments
+    ///
+    /// * `step`: Name of the step being run.
+    /// * `current`: Index of the step being run, starting at 1.
+    /// * `total`: Number of steps of the task.
+    /// * `eta`: Estimated remaining time of the task, if any.
+    pub fn new(step: String, current: u32, total: u32, eta: Option<Duration>) -> Self {
+        EventProgress {
+            step,
+            current,
+            total,
+            eta,
+        }
+    }
+
+    pub fn step(&self) -> &str {
+        &self.step
+    }
+
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        self.eta
+    }
+}
+
+impl Display for EventProgress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "⏳ [{}/{}] {}", self.current, self.total, self.step)?;
+        match self.eta {
+            Some(eta) => write!(f, " (about {}s remaining)", eta.as_secs()),
+            None => Ok(()),
         }
     }
 }
@@ -352,6 +420,34 @@ pub enum InfrastructureStep {
     RestartedError,
     /// CannotProcessRequest: error returned if the payload sent is wrong
     CannotProcessRequest,
+    /// GenerateKubeconfig: generating a temporary kubeconfig for a user.
+    GenerateKubeconfig,
+    /// KubeconfigGenerated: kubeconfig generation is ok.
+    KubeconfigGenerated,
+    /// GenerateKubeconfigError: error on generating a kubeconfig.
+    GenerateKubeconfigError,
+    /// ReportClusterHealth: checking the health of a self-managed cluster.
+    ReportClusterHealth,
+    /// ClusterHealthReported: cluster health document is published.
+    ClusterHealthReported,
+    /// ReportClusterHealthError: error on publishing the cluster health document.
+    ReportClusterHealthError,
+    /// ReportServiceInventory: listing the Qovery services running on a cluster.
+    ReportServiceInventory,
+    /// ServiceInventoryReported: service inventory document is published.
+    ServiceInventoryReported,
+    /// ReportServiceInventoryError: error on publishing the service inventory document.
+    ReportServiceInventoryError,
+    /// WatchCertificates: checking the Qovery managed certificates of a cluster.
+    WatchCertificates,
+    /// CertificatesWatched: certificates status is reported.
+    CertificatesWatched,
+    /// WatchCertificatesError: error on checking the certificates.
+    WatchCertificatesError,
+    /// DeleteDryRun: report of the resources a cluster deletion would remove.
+    DeleteDryRun,
+    /// Cancel: cancellation of the running infrastructure task has been requested.
+    Cancel,
 }
 
 impl Display for InfrastructureStep {
@@ -383,6 +479,20 @@ impl Display for InfrastructureStep {
                 InfrastructureStep::Restarted => "restarted",
                 InfrastructureStep::RestartedError => "restart-error",
                 InfrastructureStep::CannotProcessRequest => "cannot-process-request",
+                InfrastructureStep::GenerateKubeconfig => "generate-kubeconfig",
+                InfrastructureStep::KubeconfigGenerated => "kubeconfig-generated",
+                InfrastructureStep::GenerateKubeconfigError => "generate-kubeconfig-error",
+                InfrastructureStep::ReportClusterHealth => "report-cluster-health",
+                InfrastructureStep::ClusterHealthReported => "cluster-health-reported",
+                InfrastructureStep::ReportClusterHealthError => "report-cluster-health-error",
+                InfrastructureStep::ReportServiceInventory => "report-service-inventory",
+                InfrastructureStep::ServiceInventoryReported => "service-inventory-reported",
+                InfrastructureStep::ReportServiceInventoryError => "report-service-inventory-error",
+                InfrastructureStep::WatchCertificates => "watch-certificates",
+                InfrastructureStep::CertificatesWatched => "certificates-watched",
+                InfrastructureStep::WatchCertificatesError => "watch-certificates-error",
+                InfrastructureStep::DeleteDryRun => "delete-dry-run",
+                InfrastructureStep::Cancel => "cancel",
                 InfrastructureStep::GlobalError => "global-error",
                 InfrastructureStep::InfrastructureDiff(name) => match name {
                     InfrastructureDiffType::Terraform => "infra-diff-terraform",
@@ -461,6 +571,10 @@ pub enum EnvironmentStep {
 
     /// DatabaseOutput: contains the environment variables to upsert
     DatabaseOutput,
+    /// VariableChangePreview: contains the services impacted by a variable change
+    VariableChangePreview,
+    /// DeleteDryRun: contains the resources an environment deletion would remove
+    DeleteDryRun,
 }
 
 impl EnvironmentStep {
@@ -477,7 +591,13 @@ impl EnvironmentStep {
     }
 
     pub fn is_core_output(&self) -> bool {
-        matches!(self, EnvironmentStep::JobOutput | EnvironmentStep::DatabaseOutput)
+        matches!(
+            self,
+            EnvironmentStep::JobOutput
+                | EnvironmentStep::DatabaseOutput
+                | EnvironmentStep::VariableChangePreview
+                | EnvironmentStep::DeleteDryRun
+        )
     }
 }
 
@@ -514,6 +634,8 @@ impl Display for EnvironmentStep {
                 EnvironmentStep::RestartedError => "restarted-error",
                 EnvironmentStep::JobOutput => "job-output",
                 EnvironmentStep::DatabaseOutput => "database-output",
+                EnvironmentStep::VariableChangePreview => "variable-change-preview",
+                EnvironmentStep::DeleteDryRun => "delete-dry-run",
                 EnvironmentStep::Recap => "recap",
                 EnvironmentStep::GlobalError => "global-error",
             },
@@ -557,6 +679,8 @@ pub enum Transmitter {
     Router(TransmitterId, TransmitterName),
     /// Job: job engine part.
     Job(TransmitterId, TransmitterName),
+    /// K8sManifests: raw kubernetes manifests engine part.
+    K8sManifests(TransmitterId, TransmitterName),
 }
 
 impl Display for Transmitter {
@@ -579,6 +703,7 @@ impl Display for Transmitter {
                 Transmitter::Container(id, name) => format!("container({id}, {name})"),
                 Transmitter::Job(id, name) => format!("job({id}, {name})"),
                 Transmitter::Helm(id, name) => format!("helm_chart({id}, {name})"),
+                Transmitter::K8sManifests(id, name) => format!("k8s_manifests({id}, {name})"),
             }
         )
     }
@@ -648,6 +773,18 @@ impl EventDetails {
                 InfrastructureStep::Restart | InfrastructureStep::Restarted => {
                     Stage::Infrastructure(InfrastructureStep::RestartedError)
                 }
+                InfrastructureStep::GenerateKubeconfig | InfrastructureStep::KubeconfigGenerated => {
+                    Stage::Infrastructure(InfrastructureStep::GenerateKubeconfigError)
+                }
+                InfrastructureStep::ReportClusterHealth | InfrastructureStep::ClusterHealthReported => {
+                    Stage::Infrastructure(InfrastructureStep::ReportClusterHealthError)
+                }
+                InfrastructureStep::ReportServiceInventory | InfrastructureStep::ServiceInventoryReported => {
+                    Stage::Infrastructure(InfrastructureStep::ReportServiceInventoryError)
+                }
+                InfrastructureStep::WatchCertificates | InfrastructureStep::CertificatesWatched => {
+                    Stage::Infrastructure(InfrastructureStep::WatchCertificatesError)
+                }
                 InfrastructureStep::LoadConfiguration
                 | InfrastructureStep::ValidateApiInput
                 | InfrastructureStep::ValidateSystemRequirements
@@ -662,7 +799,13 @@ impl EventDetails {
                 | InfrastructureStep::DeleteError
                 | InfrastructureStep::RestartedError
                 | InfrastructureStep::InfrastructureDiff(_)
-                | InfrastructureStep::CannotProcessRequest => return,
+                | InfrastructureStep::CannotProcessRequest
+                | InfrastructureStep::GenerateKubeconfigError
+                | InfrastructureStep::ReportClusterHealthError
+                | InfrastructureStep::ReportServiceInventoryError
+                | InfrastructureStep::WatchCertificatesError
+                | InfrastructureStep::DeleteDryRun
+                | InfrastructureStep::Cancel => return,
             },
             Stage::Environment(step) => match step {
                 EnvironmentStep::Build | EnvironmentStep::Built => Stage::Environment(EnvironmentStep::BuiltError),
@@ -692,7 +835,9 @@ impl EventDetails {
                 | EnvironmentStep::RestartedError
                 | EnvironmentStep::JobOutput
                 | EnvironmentStep::Recap
-                | EnvironmentStep::DatabaseOutput => return,
+                | EnvironmentStep::DatabaseOutput
+                | EnvironmentStep::VariableChangePreview
+                | EnvironmentStep::DeleteDryRun => return,
             },
         };
     }
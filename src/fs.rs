@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 use std::fs;
-use std::fs::{create_dir_all, File, OpenOptions};
+use std::fs::{create_dir_all, DirBuilder, File, OpenOptions};
 use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
 use std::path::{Path, PathBuf};
 
@@ -13,6 +13,11 @@ use flate2::Compression;
 use itertools::Itertools;
 use serde::__private::from_utf8_lossy;
 use std::ffi::OsStr;
+use std::os::unix::fs::DirBuilderExt;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use tempfile::NamedTempFile;
+use uuid::Uuid;
 use walkdir::WalkDir;
 
 pub fn copy_files(from: &Path, to: &Path, exclude_j2_files: bool) -> Result<(), Error> {
@@ -107,41 +112,205 @@ fn archive_workspace_directory(working_root_dir: &str, execution_id: &str) -> Re
     Ok(tgz_file_path)
 }
 
-pub fn cleanup_workspace_directory(working_root_dir: &str, execution_id: &str) -> Result<(), Error> {
-    return match root_workspace_directory(working_root_dir, execution_id) {
-        Ok(workspace_dir) => {
-            let workspace_dir = workspace_dir.to_string_lossy();
-            match fs::remove_dir_all(match workspace_dir.strip_suffix("/.") {
-                Some(striped_workspace_dir) => striped_workspace_dir, // Removing extra dir name allowing to delete directory properly ("/dir/." => "dir")
-                None => workspace_dir.as_ref(),
-            }) {
-                Ok(_) => Ok(()),
-                Err(err) => {
-                    error!("error trying to remove workspace directory '{}', error: {}", workspace_dir, err);
-                    Err(err)
-                }
-            }
-        }
-        Err(err) => {
-            error!("error trying to get workspace directory from working_root_dir: '{working_root_dir}' execution_id: {execution_id}, error: {err}");
-            Err(err)
+const WORKSPACE_DIRECTORY_NAME: &str = ".qovery-workspace";
+
+/// Directories of the subtree of a service in the workspace
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, EnumIter)]
+pub enum WorkspaceDirectory {
+    /// Repository of the service
+    Source,
+    /// Files generated for the build
+    Build,
+    /// Rendered helm charts
+    Chart,
+    /// Terraform files
+    Terraform,
+}
+
+impl WorkspaceDirectory {
+    fn name(&self) -> &'static str {
+        match self {
+            WorkspaceDirectory::Source => "source",
+            WorkspaceDirectory::Build => "build",
+            WorkspaceDirectory::Chart => "chart",
+            WorkspaceDirectory::Terraform => "terraform",
         }
-    };
+    }
+}
+
+/// What happens to the workspace once the execution is archived
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub enum WorkspaceCleanupPolicy {
+    #[default]
+    Always,
+    /// The workspace of a failed execution is kept on disk to be investigated
+    KeepOnFailure,
+}
+
+// a single path component, so nothing can escape the workspace nor land in the subtree of another service
+fn validate_path_component(name: &str) -> Result<(), Error> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("`{name}` is not a valid workspace directory name"),
+        ));
+    }
+
+    Ok(())
+}
+
+// workspaces contain rendered secrets, they are only readable by the engine
+fn create_private_dir_all(path: &Path) -> Result<(), Error> {
+    DirBuilder::new().recursive(true).mode(0o700).create(path)
 }
 
-pub fn create_workspace_archive(working_root_dir: &str, execution_id: &str) -> Result<PathBuf, Error> {
-    info!("archive workspace directory in progress");
+fn directory_size_in_bytes(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Workspace of an execution, rooted at `<working_root_dir>/.qovery-workspace/<execution_id>`.
+/// Every service, or cluster, owns a subtree named after its long id, so stages processing several services
+/// concurrently never share a path.
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    working_root_dir: PathBuf,
+    execution_id: String,
+    root: PathBuf,
+    cleanup_policy: WorkspaceCleanupPolicy,
+}
+
+impl Workspace {
+    pub fn new<X>(working_root_dir: X, execution_id: &str) -> Result<Workspace, Error>
+    where
+        X: AsRef<Path>,
+    {
+        validate_path_component(execution_id)?;
+        let root = working_root_dir
+            .as_ref()
+            .join(WORKSPACE_DIRECTORY_NAME)
+            .join(execution_id);
+        create_private_dir_all(&root)?;
+
+        Ok(Workspace {
+            working_root_dir: working_root_dir.as_ref().to_path_buf(),
+            execution_id: execution_id.to_string(),
+            root,
+            cleanup_policy: WorkspaceCleanupPolicy::default(),
+        })
+    }
+
+    pub fn with_cleanup_policy(mut self, cleanup_policy: WorkspaceCleanupPolicy) -> Workspace {
+        self.cleanup_policy = cleanup_policy;
+        self
+    }
 
-    match archive_workspace_directory(working_root_dir, execution_id) {
-        Err(err) => {
-            error!("archive workspace directory error: {:?}", err);
-            Err(err)
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory shared by the whole execution, e.g. the logs one
+    pub fn directory(&self, name: &str) -> Result<PathBuf, Error> {
+        validate_path_component(name)?;
+        let dir = self.root.join(name);
+        create_private_dir_all(&dir)?;
+
+        Ok(dir)
+    }
+
+    /// Subtree of a service, its directories are created on the first call
+    pub fn service(&self, long_id: &Uuid) -> Result<ServiceWorkspace, Error> {
+        let root = self.root.join("services").join(long_id.to_string());
+        for directory in WorkspaceDirectory::iter() {
+            create_private_dir_all(&root.join(directory.name()))?;
         }
-        Ok(file) => {
-            info!("workspace directory is archived");
-            cleanup_workspace_directory(working_root_dir, execution_id)?;
-            Ok(file)
+        create_private_dir_all(&root.join("tmp"))?;
+
+        Ok(ServiceWorkspace { root })
+    }
+
+    pub fn size_in_bytes(&self) -> u64 {
+        directory_size_in_bytes(&self.root)
+    }
+
+    /// Removes the workspace, unless the execution failed and the policy keeps it. Returns whether it was removed.
+    pub fn cleanup(&self, succeeded: bool) -> Result<bool, Error> {
+        if !succeeded && self.cleanup_policy == WorkspaceCleanupPolicy::KeepOnFailure {
+            info!(
+                "keeping workspace directory {} of the failed execution",
+                self.root.to_string_lossy()
+            );
+            return Ok(false);
         }
+
+        match fs::remove_dir_all(&self.root) {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(true),
+            Err(err) => {
+                error!(
+                    "error trying to remove workspace directory '{}', error: {}",
+                    self.root.to_string_lossy(),
+                    err
+                );
+                Err(err)
+            }
+        }
+    }
+
+    /// Archives the workspace, then cleans it up according to the cleanup policy
+    pub fn archive(&self, succeeded: bool) -> Result<PathBuf, Error> {
+        info!("archive workspace directory of {} bytes in progress", self.size_in_bytes());
+
+        match archive_workspace_directory(&self.working_root_dir.to_string_lossy(), &self.execution_id) {
+            Err(err) => {
+                error!("archive workspace directory error: {:?}", err);
+                Err(err)
+            }
+            Ok(file) => {
+                info!("workspace directory is archived");
+                self.cleanup(succeeded)?;
+                Ok(file)
+            }
+        }
+    }
+}
+
+/// Subtree of a service in the workspace, with one directory per usage
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ServiceWorkspace {
+    root: PathBuf,
+}
+
+impl ServiceWorkspace {
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn directory(&self, directory: WorkspaceDirectory) -> PathBuf {
+        self.root.join(directory.name())
+    }
+
+    /// Sibling of a directory, when a stage needs several ones of the same kind, e.g. `terraform-bootstrap`.
+    /// Like the directories of the tools, it is created by its user.
+    pub fn directory_variant(&self, directory: WorkspaceDirectory, variant: &'static str) -> PathBuf {
+        debug_assert!(validate_path_component(variant).is_ok());
+        self.root.join(format!("{}-{variant}", directory.name()))
+    }
+
+    /// Temporary file with a unique name, deleted when dropped
+    pub fn temp_file(&self, prefix: &str) -> Result<NamedTempFile, Error> {
+        tempfile::Builder::new()
+            .prefix(prefix)
+            .tempfile_in(self.root.join("tmp"))
+    }
+
+    pub fn size_in_bytes(&self) -> u64 {
+        directory_size_in_bytes(&self.root)
     }
 }
 
@@ -527,4 +696,122 @@ mod tests {
         drop(file);
         tmp_dir.close().expect("error closing temporary directory");
     }
+
+    #[test]
+    fn test_workspace_isolates_concurrent_services() {
+        // setup:
+        let tmp_dir = TempDir::new("workspace").expect("error creating temporary dir");
+        let workspace = Workspace::new(tmp_dir.path(), "123").expect("error creating workspace");
+        let services = [Uuid::new_v4(), Uuid::new_v4()];
+
+        // execute:
+        let service_workspaces: Vec<ServiceWorkspace> = std::thread::scope(|scope| {
+            let handles: Vec<_> = services
+                .iter()
+                .map(|service_id| {
+                    let workspace = &workspace;
+                    scope.spawn(move || {
+                        let service_workspace =
+                            workspace.service(service_id).expect("error creating service workspace");
+                        for directory in WorkspaceDirectory::iter() {
+                            fs::write(service_workspace.directory(directory).join("file.txt"), service_id.to_string())
+                                .expect("error writing file");
+                        }
+                        service_workspace
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        // verify:
+        assert_ne!(service_workspaces[0].root(), service_workspaces[1].root());
+        for (service_id, service_workspace) in services.iter().zip(&service_workspaces) {
+            assert!(service_workspace.root().starts_with(workspace.root()));
+            for directory in WorkspaceDirectory::iter() {
+                let content = fs::read_to_string(service_workspace.directory(directory).join("file.txt"))
+                    .expect("error reading file");
+                assert_eq!(content, service_id.to_string());
+            }
+        }
+        assert_eq!(workspace.service(&services[0]).unwrap(), service_workspaces[0]);
+
+        tmp_dir.close().expect("error closing temporary directory");
+    }
+
+    #[test]
+    fn test_workspace_permissions_and_temp_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // setup:
+        let tmp_dir = TempDir::new("workspace").expect("error creating temporary dir");
+        let workspace = Workspace::new(tmp_dir.path(), "123").expect("error creating workspace");
+        let service_workspace = workspace
+            .service(&Uuid::new_v4())
+            .expect("error creating service workspace");
+
+        // execute:
+        let mut first = service_workspace
+            .temp_file("kubeconfig")
+            .expect("error creating temp file");
+        let second = service_workspace
+            .temp_file("kubeconfig")
+            .expect("error creating temp file");
+        first.write_all(b"content").expect("error writing temp file");
+
+        // verify:
+        for path in [
+            workspace.root().to_path_buf(),
+            service_workspace.directory(WorkspaceDirectory::Source),
+        ] {
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700, "{path:?}");
+        }
+        assert_ne!(first.path(), second.path());
+        assert!(first.path().starts_with(service_workspace.root()));
+        assert_eq!(service_workspace.size_in_bytes(), 7);
+        assert_eq!(workspace.size_in_bytes(), 7);
+
+        drop(first);
+        assert_eq!(workspace.size_in_bytes(), 0);
+        tmp_dir.close().expect("error closing temporary directory");
+    }
+
+    #[test]
+    fn test_workspace_cleanup_policy() {
+        // setup:
+        let tmp_dir = TempDir::new("workspace").expect("error creating temporary dir");
+        let workspace = Workspace::new(tmp_dir.path(), "123")
+            .expect("error creating workspace")
+            .with_cleanup_policy(WorkspaceCleanupPolicy::KeepOnFailure);
+
+        // execute & verify:
+        assert!(!workspace.cleanup(false).unwrap());
+        assert!(workspace.root().exists());
+        assert!(workspace.cleanup(true).unwrap());
+        assert!(!workspace.root().exists());
+
+        let workspace = Workspace::new(tmp_dir.path(), "456").expect("error creating workspace");
+        assert!(workspace.cleanup(false).unwrap());
+        assert!(!workspace.root().exists());
+
+        tmp_dir.close().expect("error closing temporary directory");
+    }
+
+    #[test]
+    fn test_workspace_rejects_paths_escaping_it() {
+        // setup:
+        let tmp_dir = TempDir::new("workspace").expect("error creating temporary dir");
+
+        // execute & verify:
+        assert!(Workspace::new(tmp_dir.path(), "..").is_err());
+        assert!(Workspace::new(tmp_dir.path(), "").is_err());
+        assert!(Workspace::new(tmp_dir.path(), "a/b").is_err());
+
+        let workspace = Workspace::new(tmp_dir.path(), "123").expect("error creating workspace");
+        assert!(workspace.directory("../logs").is_err());
+        assert_eq!(workspace.directory("logs").unwrap(), workspace.root().join("logs"));
+
+        tmp_dir.close().expect("error closing temporary directory");
+    }
 }
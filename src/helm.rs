@@ -11,9 +11,10 @@ use crate::io_models::engine_location::EngineLocation;
 use semver::Version;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
+use url::Url;
 
 use crate::cmd::command::CommandKiller;
 use crate::environment::action::deploy_helm::default_helm_timeout;
@@ -280,10 +281,41 @@ impl Display for VpaControllerResources {
     }
 }
 
+/// Where the chart is installed from
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ChartSource {
+    /// The chart lies in `ChartInfo::path`
+    #[default]
+    Local,
+    /// The chart is pulled from an OCI registry, i.e. `oci://ghcr.io/qovery/charts/my-chart`.
+    /// `ChartInfo::path` then only holds the values files generated for the release.
+    OciRepository {
+        url: Url,
+        version: Option<String>,
+        credentials: Option<ChartRepositoryCredentials>,
+    },
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct ChartRepositoryCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl Debug for ChartRepositoryCredentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChartRepositoryCredentials")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ChartInfo {
     pub name: String,
     pub path: String,
+    pub source: ChartSource,
     pub namespace: HelmChartNamespaces,
     pub custom_namespace: Option<String>,
     pub action: HelmAction,
@@ -371,6 +403,7 @@ impl Default for ChartInfo {
         ChartInfo {
             name: "undefined".to_string(),
             path: "undefined".to_string(),
+            source: ChartSource::Local,
             namespace: HelmChartNamespaces::KubeSystem,
             custom_namespace: None,
             action: Deploy,
@@ -394,9 +427,18 @@ impl Default for ChartInfo {
     }
 }
 
+/// Identifies a chart in a deployment, by its release name
+pub type ChartId = String;
+
 pub trait HelmChart: Send {
     fn clone_dyn(&self) -> Box<dyn HelmChart>;
 
+    /// Charts to deploy before this one, on top of the ones known for cluster charts
+    /// (see `infrastructure::helm_charts::dependencies`). Charts missing from the deployment are ignored.
+    fn depends_on(&self) -> Vec<ChartId> {
+        vec![]
+    }
+
     fn check_prerequisites(&self) -> Result<Option<ChartPayload>, HelmChartError> {
         let chart = self.get_chart_info();
         for file in chart.values_files.iter() {
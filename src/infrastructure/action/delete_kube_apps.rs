@@ -1,5 +1,6 @@
 use crate::cmd::command::CommandKiller;
 use crate::cmd::helm::{to_engine_error, Helm};
+use crate::cmd::helm_uninstall::{uninstall_with_finalizers_cleanup, ForceCleanReport};
 use crate::errors::{CommandError, EngineError, ErrorMessageVerbosity};
 use crate::events::Stage::Infrastructure;
 use crate::events::{EventDetails, EventMessage, InfrastructureStep};
@@ -48,6 +49,13 @@ fn delete_namespace(
     Ok(())
 }
 
+fn log_chart_deleted(chart_name: &str, force_clean_report: Option<ForceCleanReport>, logger: &impl InfraLogger) {
+    if let Some(report) = force_clean_report {
+        logger.warn(report.to_string());
+    }
+    logger.info(format!("Chart `{}` deleted", chart_name));
+}
+
 pub(super) fn delete_kube_apps(
     cluster: &dyn Kubernetes,
     infra_ctx: &InfrastructureContext,
@@ -138,14 +146,14 @@ pub(super) fn delete_kube_apps(
 
         for chart in charts_to_delete {
             let chart_info = ChartInfo::new_from_release_name(&chart.name, &chart.namespace);
-            match helm.uninstall(
+            match uninstall_with_finalizers_cleanup(
+                &helm,
                 &chart_info,
                 &[],
+                kube.client(),
                 &CommandKiller::from_timeout(DELETE_TIMEOUT),
-                &mut |_| {},
-                &mut |_| {},
             ) {
-                Ok(_) => logger.info(format!("Chart `{}` deleted", chart.name)),
+                Ok(report) => log_chart_deleted(&chart.name, report, logger),
                 Err(e) => {
                     let message_safe = format!("Can't delete chart `{}`", chart.name);
                     logger.warn(EventMessage::new(message_safe, Some(e.to_string())));
@@ -167,14 +175,14 @@ pub(super) fn delete_kube_apps(
                 .filter(|helm_chart| !skip_helm_releases.contains(&helm_chart.name))
             {
                 let chart_info = ChartInfo::new_from_release_name(&chart.name, &chart.namespace);
-                match helm.uninstall(
+                match uninstall_with_finalizers_cleanup(
+                    &helm,
                     &chart_info,
                     &[],
+                    kube.client(),
                     &CommandKiller::from_timeout(DELETE_TIMEOUT),
-                    &mut |_| {},
-                    &mut |_| {},
                 ) {
-                    Ok(_) => logger.info(format!("Chart `{}` deleted", chart.name)),
+                    Ok(report) => log_chart_deleted(&chart.name, report, logger),
                     Err(e) => {
                         let message_safe = format!("Error deleting chart `{}`", chart.name);
                         logger.warn(EventMessage::new(message_safe, Some(e.to_string())));
@@ -1,20 +1,32 @@
+use super::image_prepull::ImagePrePull;
 use super::InfraLogger;
-use crate::cmd::command::CommandKiller;
+use crate::cmd::command::{CommandKiller, ExecutionContext};
 use crate::cmd::helm::Helm;
+use crate::cmd::helm_post_renderer::PostRenderer;
+use crate::environment::models::abort::Abort;
 use crate::errors::{CommandError, EngineError};
-use crate::events::{EventDetails, InfrastructureDiffType};
-use crate::helm::{HelmAction, HelmChart, HelmChartError};
+use crate::events::{EventDetails, InfrastructureDiffType, Transmitter};
+use crate::helm::{HelmAction, HelmChart, HelmChartError, HelmChartNamespaces};
+use crate::infrastructure::helm_charts::dependencies::{
+    add_declared_dependencies, charts_dependencies, deployment_batches,
+};
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
+use crate::infrastructure::models::kubernetes::cluster_metadata::{apply_cluster_metadata, ClusterMetadata};
 use crate::io_models::engine_request::{ChartValuesOverrideName, ChartValuesOverrideValues};
 use crate::io_models::models::CustomerHelmChartsOverride;
+use crate::runtime::block_on;
 use itertools::Itertools;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufWriter, Write};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
 use std::thread;
+use std::time::Instant;
 use tera::Context as TeraContext;
+use uuid::Uuid;
 
 pub(super) trait HelmInfraResources {
     type ChartPrerequisite;
@@ -38,69 +50,159 @@ pub(super) trait HelmInfraResources {
         logger.info("⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓⚓");
         self.charts_context().prepare_helm_files_on_disk()?;
         let chart_configs = self.new_chart_prerequisite(infra_ctx);
-        let charts_to_deploy = self.gen_charts_to_deploy(infra_ctx, chart_configs)?;
+        let charts_to_deploy: Vec<(usize, Box<dyn HelmChart>)> = self
+            .gen_charts_to_deploy(infra_ctx, chart_configs)?
+            .into_iter()
+            .enumerate()
+            .flat_map(|(level, charts)| charts.into_iter().map(move |chart| (level, chart)))
+            .collect();
+        let charts_placement = charts_to_deploy
+            .iter()
+            .map(|(level, chart)| (*level, chart.get_chart_info().name.as_str()))
+            .collect_vec();
+        let ev_details = &self.charts_context().event_details;
+        let mut dependencies = charts_dependencies(&charts_placement);
+        let declared_dependencies = charts_to_deploy
+            .iter()
+            .map(|(_, chart)| chart.depends_on())
+            .collect_vec();
+        add_declared_dependencies(&mut dependencies, &charts_placement, &declared_dependencies);
+        let batches = deployment_batches(&dependencies).map_err(|cycle| {
+            Box::new(EngineError::new_helm_chart_error(
+                ev_details.clone(),
+                HelmChartError::CommandError(CommandError::new_from_safe_message(format!(
+                    "Dependency cycle between Helm charts: {}",
+                    cycle.iter().map(|ix| charts_placement[*ix].1).join(" -> ")
+                ))),
+            ))
+        })?;
 
-        logger.info("🛳️ Going to deploy Helm charts in this sequence:");
-        charts_to_deploy.iter().enumerate().for_each(|(ix, charts_lvl)| {
-            logger.info(format!("Level {}: {}", ix, charts_names_user_str(charts_lvl)));
+        // Bounds the load put on the api server, and keeps deployment events readable
+        let max_parallel_charts = infra_ctx
+            .kubernetes()
+            .advanced_settings()
+            .helm_max_parallel_charts
+            .max(1) as usize;
+        logger.info(format!(
+            "🛳️ Going to deploy Helm charts in this sequence, up to {max_parallel_charts} at a time, as soon as the charts they depend on are deployed:"
+        ));
+        batches.iter().enumerate().for_each(|(ix, batch)| {
+            logger.info(format!(
+                "Batch {}: {}",
+                ix,
+                charts_names_user_str(batch.iter().map(|chart_ix| &charts_to_deploy[*chart_ix].1))
+            ));
         });
 
-        let ev_details = &self.charts_context().event_details;
+        let post_renderer_env =
+            PostRenderer::from_cluster_settings(infra_ctx.kubernetes().advanced_settings()).to_env();
         let envs = self
             .charts_context()
             .envs
             .iter()
+            .chain(post_renderer_env.iter())
             .map(|(l, r)| (l.as_str(), r.as_str()))
             .collect_vec();
         let helm = Helm::new(Some(infra_ctx.kubernetes().kubeconfig_local_file_path()), &envs)
             .map_err(|e| Box::new(EngineError::new_helm_chart_error(ev_details.clone(), e.into())))?;
 
-        for (ix, charts_level) in charts_to_deploy.into_iter().enumerate() {
-            logger.info("");
-            logger.info(format!("🏁 Starting level {}", ix));
-            // Show diff for all chart we want to deploy
-            charts_level
-                .iter()
-                .filter(|c| c.get_chart_info().action == HelmAction::Deploy)
-                .for_each(|chart| {
-                    let mut buf_writer = match create_helm_diff_file(
-                        &self.charts_context().destination_folder,
-                        &chart.get_chart_info().name,
-                    ) {
-                        Ok(buf_writer) => buf_writer,
-                        Err(err) => {
-                            logger.warn(format!(
-                                "Unable to create diff file for chart {}: {}",
-                                chart.get_chart_info().name,
-                                err
-                            ));
-                            return;
-                        }
-                    };
-                    logger.info(format!("🔍 Showing diff for chart: {}", chart.get_chart_info().name));
-                    let _ = helm.upgrade_diff(chart.get_chart_info(), &envs, &mut |line| {
-                        let _ = buf_writer.write_all(line.as_bytes());
+        // Show diff for all chart we want to deploy
+        let diff_max_lines = infra_ctx.kubernetes().advanced_settings().helm_diff_max_lines as usize;
+        batches
+            .iter()
+            .flatten()
+            .map(|chart_ix| &charts_to_deploy[*chart_ix].1)
+            .filter(|c| c.get_chart_info().action == HelmAction::Deploy)
+            .for_each(|chart| {
+                let mut buf_writer = match create_helm_diff_file(
+                    &self.charts_context().destination_folder,
+                    &chart.get_chart_info().name,
+                ) {
+                    Ok(buf_writer) => buf_writer,
+                    Err(err) => {
+                        logger.warn(format!(
+                            "Unable to create diff file for chart {}: {}",
+                            chart.get_chart_info().name,
+                            err
+                        ));
+                        return;
+                    }
+                };
+                logger.info(format!("🔍 Showing diff for chart: {}", chart.get_chart_info().name));
+                match helm.manifest_diff(chart.get_chart_info(), &envs, diff_max_lines) {
+                    Ok(diff) => diff.into_iter().for_each(|line| {
+                        let _ = writeln!(buf_writer, "{line}");
                         logger.diff(InfrastructureDiffType::Helm, line);
-                    });
+                    }),
+                    Err(err) => logger.warn(format!(
+                        "Unable to compute diff for chart {}: {}",
+                        chart.get_chart_info().name,
+                        err
+                    )),
+                }
+            });
+
+        // Skip actual deployment if dry run
+        if self.charts_context().is_dry_run {
+            logger.warn("👻 Dry run mode enabled, skipping actual deployment");
+        } else {
+            let image_prepull = ImagePrePull::for_bootstrap(infra_ctx, logger);
+            let prepulled_charts = charts_to_deploy
+                .iter()
+                .map(|(_, chart)| chart.get_chart_info())
+                .filter(|chart| chart.action == HelmAction::Deploy)
+                .cloned()
+                .collect_vec();
+            let kube_client = infra_ctx.mk_kube_client()?;
+            let kubeconfig = infra_ctx.kubernetes().kubeconfig_local_file_path();
+            let metrics_registry = infra_ctx.metrics_registry();
+
+            let deployed = thread::scope(|scope| {
+                let prepull = image_prepull.as_ref().map(|image_prepull| {
+                    logger.info("⏱️ Pre-pulling the images of the charts on every node");
+                    let execution_context = ExecutionContext::current();
+                    scope.spawn(|| {
+                        let _execution_context = execution_context.map(ExecutionContext::enter);
+                        image_prepull.run(&helm, &prepulled_charts, metrics_registry)
+                    })
                 });
 
-            // Skip actual deployment if dry run
-            if self.charts_context().is_dry_run {
-                logger.warn("👻 Dry run mode enabled, skipping actual deployment");
-                continue;
-            }
+                let started_at = Instant::now();
+                let deployed = deploy_charts_concurrently(
+                    kube_client.client(),
+                    &kubeconfig,
+                    &envs,
+                    charts_to_deploy.into_iter().map(|(_, chart)| chart).collect(),
+                    &dependencies,
+                    max_parallel_charts,
+                    self.charts_context().abort.as_ref(),
+                    logger,
+                );
 
-            // We do the actual deployment in parallel
-            let chart_names = charts_names_user_str(&charts_level);
-            logger.info(format!("🛳️ Deploying in parallel charts of level {}: {}", ix, chart_names));
-            deploy_parallel_charts(
-                infra_ctx.mk_kube_client()?.client(),
-                &infra_ctx.kubernetes().kubeconfig_local_file_path(),
-                &envs,
-                charts_level,
-            )
-            .map_err(|e| Box::new(EngineError::new_helm_chart_error(ev_details.clone(), e)))?;
-            logger.info(format!("✅ Charts of level {} deployed", ix));
+                if let (Some(image_prepull), Some(prepull)) = (&image_prepull, prepull) {
+                    let charts_deployed_in = started_at.elapsed();
+                    image_prepull.stop();
+                    let outcome = prepull
+                        .join()
+                        .unwrap_or_else(|_| Err("image pre-pull thread panicked".to_string()));
+                    image_prepull.finish(outcome, charts_deployed_in, logger);
+                }
+
+                deployed
+            });
+            deployed.map_err(|e| Box::new(EngineError::new_helm_chart_error(ev_details.clone(), e)))?;
+
+            // the metadata are not needed by the cluster itself, failing to update them is only reported
+            let cluster_metadata =
+                ClusterMetadata::new(infra_ctx.kubernetes(), infra_ctx.dns_provider().domain().to_string());
+            match block_on(apply_cluster_metadata(
+                kube_client.client(),
+                &HelmChartNamespaces::Qovery.to_string(),
+                &cluster_metadata,
+            )) {
+                Ok(change) => logger.info(format!("📇 Cluster metadata ConfigMap {change}")),
+                Err(err) => logger.warn(format!("Cannot update the cluster metadata ConfigMap: {err}")),
+            }
         }
 
         logger.info("⚓ Helm charts deployed successfully");
@@ -110,9 +212,8 @@ pub(super) trait HelmInfraResources {
     }
 }
 
-fn charts_names_user_str(charts: &[Box<dyn HelmChart>]) -> String {
+fn charts_names_user_str<'a>(charts: impl Iterator<Item = &'a Box<dyn HelmChart>>) -> String {
     charts
-        .iter()
         .map(|c| match c.get_chart_info().action {
             HelmAction::Deploy => format!("📥 {}", c.get_chart_info().name),
             HelmAction::Destroy => format!("📤 {}", c.get_chart_info().name),
@@ -129,6 +230,8 @@ pub struct HelmInfraContext {
     pub event_details: EventDetails,
     pub envs: Vec<(String, String)>,
     pub is_dry_run: bool,
+    /// No new chart is deployed once an abort is requested
+    pub abort: Arc<dyn Abort>,
 }
 
 impl HelmInfraContext {
@@ -140,6 +243,7 @@ impl HelmInfraContext {
         event_details: EventDetails,
         envs: Vec<(String, String)>,
         is_dry_run: bool,
+        abort: Arc<dyn Abort>,
     ) -> Self {
         Self {
             tera_context,
@@ -149,6 +253,7 @@ impl HelmInfraContext {
             event_details,
             envs,
             is_dry_run,
+            abort,
         }
     }
 
@@ -222,60 +327,180 @@ pub(super) fn mk_customer_chart_override_fn(
     }
 }
 
-fn deploy_parallel_charts(
+/// Deploys charts concurrently, each one once all the charts it depends on are deployed.
+/// A failed chart cancels the charts depending on it, the other charts are still deployed.
+fn deploy_charts_concurrently(
     kube_client: &kube::Client,
     kubernetes_config: &Path,
     envs: &[(&str, &str)],
     charts: Vec<Box<dyn HelmChart>>,
+    dependencies: &[Vec<usize>],
+    max_parallel_charts: usize,
+    abort: &dyn Abort,
+    logger: &impl InfraLogger,
 ) -> Result<(), HelmChartError> {
-    thread::scope(|s| {
-        let mut handles = vec![];
-
-        for chart in charts.into_iter() {
-            let path = kubernetes_config.to_path_buf();
-            let current_span = tracing::Span::current();
-            let handle = s.spawn(move || {
-                // making sure to pass the current span to the new thread not to lose any tracing info
-                let _span = current_span.enter();
-                chart.run(kube_client, path.as_path(), envs, &CommandKiller::never())
-            });
+    let chart_names = charts.iter().map(|c| c.get_chart_info().name.to_string()).collect_vec();
+    let transmitter = |chart_ix: usize| {
+        let name = &chart_names[chart_ix];
+        Transmitter::Helm(Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()), name.to_string())
+    };
+    let current_span = tracing::Span::current();
+    let charts_count = chart_names.len() as u32;
+    let mut started_charts = 0;
+
+    let outcomes = deploy_with_dependencies(
+        charts,
+        dependencies,
+        max_parallel_charts,
+        abort,
+        |chart: Box<dyn HelmChart>| {
+            // making sure to pass the current span to the new thread not to lose any tracing info
+            let _span = current_span.enter();
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                chart.run(kube_client, kubernetes_config, envs, &CommandKiller::from_cancelable(abort))
+            }))
+            .unwrap_or_else(|e| {
+                let err = match e.downcast_ref::<&'static str>() {
+                    None => match e.downcast_ref::<String>() {
+                        None => "Unable to get error.",
+                        Some(s) => s.as_str(),
+                    },
+                    Some(s) => *s,
+                };
+                Err(HelmChartError::CommandError(CommandError::new(
+                    "Thread panicked during parallel charts deployments.".to_string(),
+                    Some(err.to_string()),
+                    None,
+                )))
+            })
+        },
+        |chart_ix| {
+            started_charts += 1;
+            logger.report_progress(&format!("Helm chart {}", chart_names[chart_ix]), started_charts, charts_count);
+            logger.info_from(transmitter(chart_ix), format!("🛳️ Deploying chart {}", chart_names[chart_ix]))
+        },
+        |chart_ix, ret| match ret {
+            Ok(()) => logger.info_from(transmitter(chart_ix), format!("✅ Chart {} deployed", chart_names[chart_ix])),
+            Err(_) => logger.info_from(
+                transmitter(chart_ix),
+                format!("❌ Chart {} failed to deploy", chart_names[chart_ix]),
+            ),
+        },
+    );
 
-            handles.push(handle);
+    let mut errors: Vec<(String, HelmChartError)> = vec![];
+    let mut not_deployed: Vec<&str> = vec![];
+    for (name, outcome) in chart_names.iter().zip(outcomes) {
+        match outcome {
+            Some(Ok(())) => {}
+            Some(Err(err)) => errors.push((name.to_string(), err)),
+            None => not_deployed.push(name),
         }
+    }
 
-        let mut errors: Vec<Result<(), HelmChartError>> = vec![];
-        for handle in handles {
-            match handle.join() {
-                Ok(helm_run_ret) => {
-                    if let Err(e) = helm_run_ret {
-                        errors.push(Err(e));
+    if !errors.is_empty() && !not_deployed.is_empty() {
+        logger.warn(format!(
+            "Charts not deployed because of the failed ones: {}",
+            not_deployed.join(", ")
+        ));
+    }
+    if errors.len() > 1 {
+        error!("Deployments of charts failed with: {:?}", errors);
+        return Err(HelmChartError::CommandError(CommandError::new(
+            format!(
+                "Deployments of charts failed: {}",
+                errors.iter().map(|(name, _)| name.as_str()).join(", ")
+            ),
+            Some(
+                errors
+                    .iter()
+                    .map(|(name, err)| format!("{name}: {err}"))
+                    .chain(not_deployed.iter().map(|name| format!("{name}: not deployed")))
+                    .join("\n"),
+            ),
+            None,
+        )));
+    }
+    if let Some((_, err)) = errors.pop() {
+        return Err(err);
+    }
+
+    if !not_deployed.is_empty() {
+        return Err(HelmChartError::CommandError(CommandError::new_from_safe_message(format!(
+            "Charts deployment has been aborted, {} charts have not been deployed",
+            not_deployed.len()
+        ))));
+    }
+
+    Ok(())
+}
+
+/// Deploys items concurrently, up to `max_parallel` at a time, each one once all the items it depends on are deployed.
+/// The items depending on a failed one are not deployed. After an abort request, no new item is deployed,
+/// but the ones in progress are awaited. Returns the outcome of each item, None when it has not been deployed.
+fn deploy_with_dependencies<T: Send, E: Send>(
+    items: Vec<T>,
+    dependencies: &[Vec<usize>],
+    max_parallel: usize,
+    abort: &dyn Abort,
+    deploy: impl Fn(T) -> Result<(), E> + Sync,
+    mut on_start: impl FnMut(usize),
+    mut on_outcome: impl FnMut(usize, &Result<(), E>),
+) -> Vec<Option<Result<(), E>>> {
+    let mut items: Vec<Option<T>> = items.into_iter().map(Some).collect();
+    let mut outcomes: Vec<Option<Result<(), E>>> = items.iter().map(|_| None).collect();
+    let mut remaining_dependencies: Vec<usize> = dependencies.iter().map(|deps| deps.len()).collect();
+    // lowest index first, so with one item at a time they are deployed in their order
+    let mut ready: BTreeSet<usize> = (0..items.len()).filter(|ix| remaining_dependencies[*ix] == 0).collect();
+    let deploy = &deploy;
+
+    thread::scope(|s| {
+        let (tx, rx) = mpsc::channel::<(usize, Result<(), E>)>();
+        let mut running = 0;
+
+        loop {
+            while running < max_parallel.max(1) && !abort.status().should_cancel() {
+                let Some(ix) = ready.pop_first() else {
+                    break;
+                };
+                let Some(item) = items[ix].take() else {
+                    continue;
+                };
+
+                on_start(ix);
+                let tx = tx.clone();
+                let execution_context = ExecutionContext::current();
+                s.spawn(move || {
+                    let _execution_context = execution_context.map(ExecutionContext::enter);
+                    let _ = tx.send((ix, deploy(item)));
+                });
+                running += 1;
+            }
+
+            if running == 0 {
+                break;
+            }
+
+            let Ok((ix, ret)) = rx.recv() else {
+                break;
+            };
+            running -= 1;
+            on_outcome(ix, &ret);
+            if ret.is_ok() {
+                for (dependent_ix, deps) in dependencies.iter().enumerate() {
+                    if deps.contains(&ix) {
+                        remaining_dependencies[dependent_ix] -= 1;
+                        if remaining_dependencies[dependent_ix] == 0 {
+                            ready.insert(dependent_ix);
+                        }
                     }
                 }
-                Err(e) => {
-                    let err = match e.downcast_ref::<&'static str>() {
-                        None => match e.downcast_ref::<String>() {
-                            None => "Unable to get error.",
-                            Some(s) => s.as_str(),
-                        },
-                        Some(s) => *s,
-                    };
-                    let error = Err(HelmChartError::CommandError(CommandError::new(
-                        "Thread panicked during parallel charts deployments.".to_string(),
-                        Some(err.to_string()),
-                        None,
-                    )));
-                    errors.push(error);
-                }
             }
+            outcomes[ix] = Some(ret);
         }
+    });
 
-        if errors.is_empty() {
-            Ok(())
-        } else {
-            error!("Deployments of charts failed with: {:?}", errors);
-            errors.remove(0)
-        }
-    })
+    outcomes
 }
 
 fn create_helm_diff_file(dir_path: &Path, chart_name: &str) -> anyhow::Result<BufWriter<File>> {
@@ -297,3 +522,93 @@ fn create_helm_diff_file(dir_path: &Path, chart_name: &str) -> anyhow::Result<Bu
 
     Ok(BufWriter::new(file))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::deploy_with_dependencies;
+    use crate::environment::models::abort::AbortStatus;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_failure_cancels_dependents_only() {
+        // setup:
+        // 0 <- 1 <- 3, 0 <- 2, and 4 alone
+        let items = vec!["a", "b-fails", "c", "d", "e"];
+        let dependencies = vec![vec![], vec![0], vec![0], vec![1], vec![]];
+        let abort = || AbortStatus::None;
+
+        // execute:
+        let outcomes = deploy_with_dependencies(
+            items,
+            &dependencies,
+            4,
+            &abort,
+            |item: &str| {
+                if item.ends_with("fails") {
+                    Err(item.to_string())
+                } else {
+                    Ok(())
+                }
+            },
+            |_| {},
+            |_, _| {},
+        );
+
+        // verify:
+        assert_eq!(
+            outcomes,
+            vec![
+                Some(Ok(())),
+                Some(Err("b-fails".to_string())),
+                Some(Ok(())),
+                None,
+                Some(Ok(()))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_chart_at_a_time_keeps_order() {
+        // setup:
+        let dependencies = vec![vec![], vec![], vec![0, 1], vec![0], vec![2], vec![]];
+        let running = Mutex::new(0);
+        let mut started = vec![];
+
+        // execute:
+        let outcomes = deploy_with_dependencies(
+            (0..dependencies.len()).collect(),
+            &dependencies,
+            1,
+            &|| AbortStatus::None,
+            |_: usize| {
+                let mut running = running.lock().unwrap();
+                *running += 1;
+                assert_eq!(*running, 1);
+                *running -= 1;
+                Ok::<(), ()>(())
+            },
+            |ix| started.push(ix),
+            |_, _| {},
+        );
+
+        // verify:
+        assert_eq!(started, vec![0, 1, 2, 3, 4, 5]);
+        assert!(outcomes.iter().all(|outcome| outcome == &Some(Ok(()))));
+    }
+
+    #[test]
+    fn test_abort_stops_new_deployments() {
+        let dependencies = vec![vec![], vec![0], vec![1]];
+        let outcomes = deploy_with_dependencies(
+            vec![(); 3],
+            &dependencies,
+            4,
+            &|| AbortStatus::Requested,
+            |_| Ok::<(), ()>(()),
+            |_| {},
+            |_, _| {},
+        );
+
+        assert_eq!(outcomes, vec![None, None, None]);
+    }
+}
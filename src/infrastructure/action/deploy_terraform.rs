@@ -1,28 +1,54 @@
+use crate::cmd::command::CommandKiller;
 use crate::cmd::terraform::{
-    terraform_apply, terraform_apply_with_tf_workers_resources, terraform_destroy, terraform_init_validate,
-    terraform_output, terraform_plan, terraform_remove_resource_from_tf_state, terraform_state_list,
+    terraform_apply, terraform_apply_with_targets, terraform_apply_with_tf_workers_resources, terraform_destroy,
+    terraform_init_validate, terraform_output, terraform_plan, terraform_plan_summary,
+    terraform_remove_resource_from_tf_state, terraform_state_list, terraform_state_pull,
 };
 use crate::cmd::terraform_validators::TerraformValidators;
-use crate::errors::EngineError;
+use crate::environment::models::abort::Abort;
+use crate::errors::{EngineError, Tag};
 use crate::events::{EventDetails, InfrastructureDiffType};
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::InfraLogger;
 use crate::template::generate_and_copy_all_files_into_dir;
 use crate::utilities::envs_to_slice;
 use itertools::Itertools;
 use serde::de::DeserializeOwned;
+use std::collections::BTreeSet;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use tera::Context as TeraContext;
 
-pub struct TerraformInfraResources {
+/// Delays between the attempts of a terraform run: 10s, 20s, 40s, 80s then 160s.
+/// Doubling them lets the cloud provider API rate limit recover when terraform is throttled.
+pub fn terraform_retry_delays() -> impl Iterator<Item = Duration> {
+    (0..5).map(|attempt| Duration::from_secs(10 << attempt))
+}
+
+/// Whether the terraform error is the cloud provider API throttling requests, the run can be retried until the
+/// rate limit recovers
+pub fn is_api_throttling(error: &EngineError) -> bool {
+    error.tag() == &Tag::TerraformCloudProviderApiThrottling
+}
+
+// init, plan and apply (or destroy)
+const TERRAFORM_STEPS: u32 = 3;
+
+pub struct TerraformInfraResources<'a> {
     tera_context: TeraContext,
     terraform_common_folder: PathBuf,
     destination_folder: PathBuf,
     event_details: EventDetails,
     envs: Vec<(String, String)>,
     is_dry_run: bool,
+    allow_destructive_replacements: bool,
+    state_backup: Option<TerraformStateBackup<'a>>,
+    /// Running terraform commands are interrupted once an abort is requested
+    abort: Arc<dyn Abort>,
 }
 
-impl TerraformInfraResources {
+impl<'a> TerraformInfraResources<'a> {
     pub fn new(
         tera_context: TeraContext,
         terraform_common_folder: PathBuf,
@@ -30,7 +56,10 @@ impl TerraformInfraResources {
         event_details: EventDetails,
         envs: Vec<(String, String)>,
         is_dry_run: bool,
-    ) -> TerraformInfraResources {
+        allow_destructive_replacements: bool,
+        state_backup: Option<TerraformStateBackup<'a>>,
+        abort: Arc<dyn Abort>,
+    ) -> TerraformInfraResources<'a> {
         TerraformInfraResources {
             tera_context,
             terraform_common_folder,
@@ -38,9 +67,16 @@ impl TerraformInfraResources {
             event_details,
             envs,
             is_dry_run,
+            allow_destructive_replacements,
+            state_backup,
+            abort,
         }
     }
 
+    fn cmd_killer(&self) -> CommandKiller<'_> {
+        CommandKiller::from_cancelable(self.abort.as_ref())
+    }
+
     fn prepare_terraform_files(&self) -> Result<(), Box<EngineError>> {
         // Copy the root folder
         generate_and_copy_all_files_into_dir(
@@ -65,32 +101,96 @@ impl TerraformInfraResources {
             self.destination_folder.to_string_lossy().as_ref(),
             envs,
             &TerraformValidators::Default,
+            &self.cmd_killer(),
         )
         .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?;
         Ok(())
     }
 
+    /// Copies the current state to the cluster bucket before it gets changed.
+    /// The deployment goes on when the backup fails, it is only logged.
+    fn backup_state(&self, envs: &[(&str, &str)], logger: &impl InfraLogger) {
+        let Some(state_backup) = &self.state_backup else {
+            return;
+        };
+        if self.is_dry_run {
+            return;
+        }
+
+        let state =
+            match terraform_state_pull(self.destination_folder.to_string_lossy().as_ref(), envs, &self.cmd_killer()) {
+                Ok(state) => state,
+                Err(err) => {
+                    logger.warn(format!("Cannot pull terraform state to back it up: {err}"));
+                    return;
+                }
+            };
+        // nothing has been applied yet
+        if state.trim().is_empty() {
+            return;
+        }
+
+        match state_backup.backup(&state, &self.destination_folder) {
+            Ok(pruning_errors) => {
+                logger.info(format!(
+                    "💾 Terraform state backed up to {}/{}",
+                    state_backup.bucket_name(),
+                    state_backup.key()
+                ));
+                for err in pruning_errors {
+                    logger.warn(format!("Cannot delete expired terraform state backup: {err}"));
+                }
+            }
+            Err(err) => logger.warn(format!("Cannot back up terraform state: {err}")),
+        }
+    }
+
     pub fn create<T: DeserializeOwned>(&self, logger: &impl InfraLogger) -> Result<T, Box<EngineError>> {
         let envs = envs_to_slice(self.envs.as_slice());
         self.prepare_terraform_files()?;
+        logger.report_progress("Terraform init", 1, TERRAFORM_STEPS);
         self.terraform_init(&envs)?;
+        self.backup_state(&envs, logger);
 
         logger.info("🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️");
         logger.info("🏗️ Creating terraform resources with the following plan");
-        terraform_plan(self.destination_folder.to_string_lossy().as_ref(), &envs, false)
-            .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?
-            .raw_std_output
-            .into_iter()
-            .for_each(|line| logger.diff(InfrastructureDiffType::Terraform, line));
+        logger.report_progress("Terraform plan", 2, TERRAFORM_STEPS);
+        terraform_plan(
+            self.destination_folder.to_string_lossy().as_ref(),
+            &envs,
+            false,
+            &self.cmd_killer(),
+        )
+        .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?
+        .raw_std_output
+        .into_iter()
+        .for_each(|line| logger.diff(InfrastructureDiffType::Terraform, line));
+
+        let plan_summary =
+            terraform_plan_summary(self.destination_folder.to_string_lossy().as_ref(), &envs, &self.cmd_killer())
+                .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?;
+        match plan_summary.has_changes() {
+            true => logger.info(format!("📋 {plan_summary}")),
+            false => logger.info("📋 Plan: no changes, infrastructure is up to date"),
+        }
+        let protected_replacements = plan_summary.protected_replacements();
+        if !protected_replacements.is_empty() && !self.allow_destructive_replacements {
+            return Err(Box::new(EngineError::new_terraform_destructive_replacement_refused(
+                self.event_details.clone(),
+                protected_replacements.into_iter().map(str::to_string).collect(),
+            )));
+        }
 
         // Apply will be skipped/do nothing if dry run is enabled
         // but to log a message, we do the if/else
         if !self.is_dry_run {
+            logger.report_progress("Terraform apply", 3, TERRAFORM_STEPS);
             terraform_apply(
                 self.destination_folder.to_string_lossy().as_ref(),
                 self.is_dry_run,
                 &envs,
                 &TerraformValidators::Default,
+                &self.cmd_killer(),
             )
             .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?;
         } else {
@@ -98,7 +198,17 @@ impl TerraformInfraResources {
         }
         logger.info("🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️");
 
-        terraform_output::<T>(self.destination_folder.to_string_lossy().as_ref(), &envs)
+        terraform_output::<T>(self.destination_folder.to_string_lossy().as_ref(), &envs, &self.cmd_killer())
+            .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))
+    }
+
+    /// Outputs of the last apply, nothing is planned nor applied
+    pub fn output<T: DeserializeOwned>(&self) -> Result<T, Box<EngineError>> {
+        let envs = envs_to_slice(self.envs.as_slice());
+        self.prepare_terraform_files()?;
+        self.terraform_init(&envs)?;
+
+        terraform_output::<T>(self.destination_folder.to_string_lossy().as_ref(), &envs, &self.cmd_killer())
             .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))
     }
 
@@ -109,26 +219,36 @@ impl TerraformInfraResources {
     ) -> Result<(), Box<EngineError>> {
         let envs = envs_to_slice(self.envs.as_slice());
         self.prepare_terraform_files()?;
+        logger.report_progress("Terraform init", 1, TERRAFORM_STEPS);
         self.terraform_init(&envs)?;
+        self.backup_state(&envs, logger);
 
         logger.info("🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️");
         self.delete_resources_from_state(state_to_rm_before_destroy, logger);
 
         logger.info("🏗️ Deleting terraform resources with the following plan");
-        terraform_plan(self.destination_folder.to_string_lossy().as_ref(), &envs, true)
-            .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?
-            .raw_std_output
-            .into_iter()
-            .for_each(|line| logger.diff(InfrastructureDiffType::Terraform, line));
+        logger.report_progress("Terraform plan", 2, TERRAFORM_STEPS);
+        terraform_plan(
+            self.destination_folder.to_string_lossy().as_ref(),
+            &envs,
+            true,
+            &self.cmd_killer(),
+        )
+        .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?
+        .raw_std_output
+        .into_iter()
+        .for_each(|line| logger.diff(InfrastructureDiffType::Terraform, line));
 
         if self.is_dry_run {
             return Ok(());
         }
 
+        logger.report_progress("Terraform destroy", 3, TERRAFORM_STEPS);
         terraform_destroy(
             self.destination_folder.to_string_lossy().as_ref(),
             &envs,
             &TerraformValidators::None,
+            &self.cmd_killer(),
         )
         .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?;
         logger.info("🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️");
@@ -136,10 +256,11 @@ impl TerraformInfraResources {
         Ok(())
     }
 
-    pub fn pause(&self, resources_filters: &[&str]) -> Result<(), Box<EngineError>> {
+    pub fn pause(&self, resources_filters: &[&str], logger: &impl InfraLogger) -> Result<(), Box<EngineError>> {
         let envs = envs_to_slice(self.envs.as_slice());
         self.prepare_terraform_files()?;
         self.terraform_init(&envs)?;
+        self.backup_state(&envs, logger);
 
         // pause: only select terraform workers elements to pause to avoid applying on the whole config
         // this to avoid failures because of helm deployments on removing workers nodes
@@ -147,6 +268,7 @@ impl TerraformInfraResources {
             self.destination_folder.to_string_lossy().as_ref(),
             &envs,
             &TerraformValidators::Default,
+            &self.cmd_killer(),
         )
         .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?
         .raw_std_output
@@ -161,12 +283,67 @@ impl TerraformInfraResources {
             &envs,
             &TerraformValidators::Default,
             self.is_dry_run,
+            &self.cmd_killer(),
         )
         .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?;
 
         Ok(())
     }
 
+    /// Applies only `targets`, and the resources of the state matching `resources_filters`:
+    /// the ones removed from the configuration are targeted too, so they get destroyed.
+    pub fn repair(
+        &self,
+        targets: &[String],
+        resources_filters: &[&str],
+        logger: &impl InfraLogger,
+    ) -> Result<(), Box<EngineError>> {
+        let envs = envs_to_slice(self.envs.as_slice());
+        self.prepare_terraform_files()?;
+        self.terraform_init(&envs)?;
+        self.backup_state(&envs, logger);
+
+        let mut all_targets: BTreeSet<String> = targets.iter().cloned().collect();
+        all_targets.extend(
+            terraform_state_list(
+                self.destination_folder.to_string_lossy().as_ref(),
+                &envs,
+                &TerraformValidators::Default,
+                &self.cmd_killer(),
+            )
+            .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?
+            .raw_std_output
+            .into_iter()
+            .filter(|resource| resources_filters.iter().any(|filter| resource.starts_with(filter))),
+        );
+        if all_targets.is_empty() {
+            logger.info("🏗️ No terraform resource to repair");
+            return Ok(());
+        }
+
+        logger.info("🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️");
+        logger.info(format!("🏗️ Repairing terraform resources {}", all_targets.iter().join(", ")));
+        terraform_apply_with_targets(
+            self.destination_folder.to_string_lossy().as_ref(),
+            &all_targets.into_iter().collect_vec(),
+            &envs,
+            &TerraformValidators::Default,
+            self.is_dry_run,
+            &self.cmd_killer(),
+        )
+        .map_err(|e| Box::new(EngineError::new_terraform_error(self.event_details.clone(), e)))?
+        .raw_std_output
+        .into_iter()
+        .for_each(|line| logger.diff(InfrastructureDiffType::Terraform, line));
+
+        if self.is_dry_run {
+            logger.warn("👻 Dry run mode enabled, skipping actual terraform apply");
+        }
+        logger.info("🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️ 🏗️");
+
+        Ok(())
+    }
+
     fn delete_resources_from_state(&self, resources: &[&str], logger: &impl InfraLogger) {
         for resource in resources {
             if self.is_dry_run {
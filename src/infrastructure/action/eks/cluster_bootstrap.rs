@@ -1,14 +1,17 @@
 use crate::errors::EngineError;
 use crate::events::{InfrastructureStep, Stage};
-use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
+use crate::fs::WorkspaceDirectory;
+use crate::infrastructure::action::deploy_terraform::{
+    is_api_throttling, terraform_retry_delays, TerraformInfraResources,
+};
 use crate::infrastructure::action::eks::tera_context::eks_tera_context;
 use crate::infrastructure::action::eks::{AwsEksQoveryTerraformOutput, AWS_EKS_DEFAULT_UPGRADE_TIMEOUT_DURATION};
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::InfraLogger;
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::aws::eks::EKS;
 use crate::infrastructure::models::kubernetes::Kubernetes;
 use crate::utilities::envs_to_string;
-use retry::delay::Fixed;
 use retry::{Error, OperationResult};
 
 pub fn bootstrap_eks_cluster(
@@ -24,7 +27,7 @@ pub fn bootstrap_eks_cluster(
     let event_details = kubernetes.get_event_details(Stage::Infrastructure(InfrastructureStep::Create));
 
     logger.info(format!("Preparing {} cluster bootstrap.", kubernetes.kind()));
-    let temp_dir = kubernetes.temp_dir();
+    let workspace = kubernetes.workspace();
 
     let cluster_upgrade_timeout_in_min = AWS_EKS_DEFAULT_UPGRADE_TIMEOUT_DURATION;
 
@@ -46,18 +49,33 @@ pub fn bootstrap_eks_cluster(
     let tf_action = TerraformInfraResources::new(
         tera_context.clone(),
         kubernetes.template_directory.join("terraform"),
-        temp_dir.join("terraform_bootstrap"),
+        workspace.directory_variant(WorkspaceDirectory::Terraform, "bootstrap"),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         infra_ctx.context().is_dry_run_deploy(),
+        kubernetes.advanced_settings.terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(kubernetes),
+        infra_ctx.abort(),
     );
 
-    let tf_apply_result = retry::retry(Fixed::from_millis(3000).take(1), || {
+    // any failure is retried once, API throttling as long as the backoff allows it
+    let mut retries_on_failure = 1;
+    let tf_apply_result = retry::retry(terraform_retry_delays(), || {
         let qovery_terraform_output: Result<AwsEksQoveryTerraformOutput, Box<EngineError>> = tf_action.create(&logger);
 
         match qovery_terraform_output {
             Ok(output) => OperationResult::Ok(output),
-            Err(e) => OperationResult::Retry(e),
+            // canceled, nothing to retry
+            Err(e) if e.tag().is_cancel() => OperationResult::Err(e),
+            Err(e) if is_api_throttling(&e) => {
+                logger.warn(format!("{}, retrying", e.user_log_message()));
+                OperationResult::Retry(e)
+            }
+            Err(e) if retries_on_failure == 0 => OperationResult::Err(e),
+            Err(e) => {
+                retries_on_failure -= 1;
+                OperationResult::Retry(e)
+            }
         }
     });
 
@@ -1,8 +1,11 @@
 use crate::environment::models::kubernetes::K8sObject;
 use crate::errors::{CommandError, EngineError, Tag};
 use crate::events::{EventDetails, InfrastructureStep, Stage};
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::deploy_helms::{HelmInfraContext, HelmInfraResources};
-use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
+use crate::infrastructure::action::deploy_terraform::{
+    is_api_throttling, terraform_retry_delays, TerraformInfraResources,
+};
 use crate::infrastructure::action::eks::custom_vpc::patch_kube_proxy_for_aws_user_network;
 use crate::infrastructure::action::eks::helm_charts::EksHelmsDeployment;
 use crate::infrastructure::action::eks::karpenter::node_groups_when_karpenter_is_enabled;
@@ -10,20 +13,25 @@ use crate::infrastructure::action::eks::karpenter::Karpenter;
 use crate::infrastructure::action::eks::nodegroup::{
     delete_eks_nodegroups, node_group_is_running, should_update_desired_nodes, NodeGroupsDeletionType,
 };
+use crate::infrastructure::action::eks::nodegroup_scaling::{
+    scale_node_groups_without_terraform, EKS_VOLATILE_TERRAFORM_INPUTS,
+};
 use crate::infrastructure::action::eks::sdk::QoveryAwsSdkConfigEks;
 use crate::infrastructure::action::eks::tera_context::eks_tera_context;
 use crate::infrastructure::action::eks::utils::{define_cluster_upgrade_timeout, get_rusoto_eks_client};
 use crate::infrastructure::action::eks::{AwsEksQoveryTerraformOutput, AWS_EKS_DEFAULT_UPGRADE_TIMEOUT_DURATION};
+use crate::infrastructure::action::infra_changes::{record_applied_inputs, AppliedInputs};
 use crate::infrastructure::action::kubeconfig_helper::update_kubeconfig_file;
+use crate::infrastructure::action::pause_helper::{forget_paused_node_groups, load_paused_node_groups};
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::InfraLogger;
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::aws::eks::EKS;
 use crate::infrastructure::models::kubernetes::Kubernetes;
-use crate::io_models::models::KubernetesClusterAction;
+use crate::io_models::models::{node_groups_are_spot_only, KubernetesClusterAction};
 use crate::runtime::block_on;
 use crate::services::kube_client::SelectK8sResourceBy;
 use crate::utilities::envs_to_string;
-use retry::delay::Fixed;
 use retry::{Error, OperationResult};
 use rusoto_eks::EksClient;
 use std::path::PathBuf;
@@ -39,6 +47,9 @@ pub fn create_eks_cluster(
     let dns_provider = infra_ctx.dns_provider();
 
     logger.info(format!("Preparing {} cluster deployment.", kubernetes.kind()));
+    if !kubernetes.is_karpenter_enabled() && node_groups_are_spot_only(&kubernetes.nodes_groups) {
+        logger.warn("⚠️ All node groups run on spot instances, every node of the cluster can be reclaimed by AWS at any time. Add an on-demand node group to keep the cluster available.");
+    }
 
     // old method with rusoto
     let aws_eks_client = get_rusoto_eks_client(event_details.clone(), kubernetes, cloud_provider).ok();
@@ -47,6 +58,14 @@ pub fn create_eks_cluster(
     let aws_conn = cloud_provider
         .aws_sdk_client()
         .ok_or_else(|| Box::new(EngineError::new_aws_sdk_cannot_get_client(event_details.clone())))?;
+    // a resumed cluster gets back the nodes it had when paused
+    let paused_node_groups = match infra_ctx.context().is_first_cluster_deployment() {
+        true => None,
+        false => infra_ctx
+            .mk_kube_client()
+            .ok()
+            .and_then(|kube_client| load_paused_node_groups(kube_client.client())),
+    };
     let terraform_apply = || {
         // don't create node groups if karpenter is enabled
         let nodes_groups = node_groups_when_karpenter_is_enabled(
@@ -57,7 +76,7 @@ pub fn create_eks_cluster(
             KubernetesClusterAction::Update(None),
         )?;
 
-        let node_groups_with_desired_states = should_update_desired_nodes(
+        let mut node_groups_with_desired_states = should_update_desired_nodes(
             event_details.clone(),
             kubernetes,
             if infra_ctx.context().is_first_cluster_deployment() {
@@ -68,6 +87,18 @@ pub fn create_eks_cluster(
             nodes_groups,
             aws_eks_client.clone(),
         )?;
+        if let Some(paused_node_groups) = &paused_node_groups {
+            for node_group in node_groups_with_desired_states.iter_mut() {
+                if let Some(desired_nodes) = paused_node_groups.desired_nodes_on_resume(
+                    &node_group.name,
+                    node_group.min_nodes,
+                    node_group.max_nodes,
+                ) {
+                    node_group.desired_size = desired_nodes;
+                    node_group.enable_desired_size = true;
+                }
+            }
+        }
 
         // in case error, this should no be a blocking error
         let cluster_upgrade_timeout_in_min = if let Ok(kube_client) = infra_ctx.mk_kube_client() {
@@ -103,19 +134,64 @@ pub fn create_eks_cluster(
         let tf_action = TerraformInfraResources::new(
             tera_context.clone(),
             kubernetes.template_directory.join("terraform"),
-            kubernetes.temp_dir.join("terraform"),
+            kubernetes.workspace.directory(WorkspaceDirectory::Terraform),
             event_details.clone(),
             envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
             infra_ctx.context().is_dry_run_deploy(),
+            kubernetes.advanced_settings.terraform_allow_destructive_replacements,
+            TerraformStateBackup::for_cluster(kubernetes),
+            infra_ctx.abort(),
         );
 
-        let tf_apply_result = retry::retry(Fixed::from_millis(3000).take(1), || {
+        let applied_inputs = match AppliedInputs::new(
+            &kubernetes.template_directory.join("terraform"),
+            &tera_context,
+            EKS_VOLATILE_TERRAFORM_INPUTS,
+        ) {
+            Ok(applied_inputs) => Some(applied_inputs),
+            Err(err) => {
+                logger.warn(format!(
+                    "Cannot fingerprint terraform inputs, node groups fast path disabled: {err}"
+                ));
+                None
+            }
+        };
+        let can_skip_terraform =
+            !infra_ctx.context().is_first_cluster_deployment() && !infra_ctx.context().is_dry_run_deploy();
+        if let (true, Some(applied_inputs), Ok(kube_client)) =
+            (can_skip_terraform, &applied_inputs, infra_ctx.mk_kube_client())
+        {
+            if scale_node_groups_without_terraform(
+                kubernetes,
+                kube_client.client(),
+                &aws_conn,
+                &node_groups_with_desired_states,
+                applied_inputs,
+                &logger,
+            ) {
+                logger.info("⚡ Only node groups sizing has changed, skipping terraform apply");
+                let output: AwsEksQoveryTerraformOutput = tf_action.output()?;
+                return Ok((output, tera_context, applied_inputs.clone()));
+            }
+        }
+
+        // any failure is retried once, API throttling as long as the backoff allows it
+        let mut retries_on_failure = 1;
+        let tf_apply_result = retry::retry(terraform_retry_delays(), || {
             let qovery_terraform_output: Result<AwsEksQoveryTerraformOutput, Box<EngineError>> =
                 tf_action.create(&logger);
 
             match qovery_terraform_output {
                 Ok(output) => OperationResult::Ok(output),
+                // canceled, nothing to retry
+                Err(e) if e.tag().is_cancel() => OperationResult::Err(e),
+                Err(e) if is_api_throttling(&e) => {
+                    logger.warn(format!("{}, retrying", e.user_log_message()));
+                    OperationResult::Retry(e)
+                }
+                Err(e) if retries_on_failure == 0 => OperationResult::Err(e),
                 Err(e) => {
+                    retries_on_failure -= 1;
                     // on EKS, clean possible nodegroup deployment failures because of quota issues
                     // do not exit on this error to avoid masking the real Terraform issue
                     logger.info("Ensuring no failed nodegroups are present in the cluster, or delete them if at least one active nodegroup is present");
@@ -133,7 +209,7 @@ pub fn create_eks_cluster(
         });
 
         match tf_apply_result {
-            Ok(output) => Ok((output, tera_context)),
+            Ok(output) => Ok((output, tera_context, applied_inputs)),
             Err(Error { error, .. }) => Err(error),
         }
     };
@@ -156,10 +232,21 @@ pub fn create_eks_cluster(
     }
 
     // apply to generate tf_qovery_config.json
-    let (eks_tf_output, tera_context) = terraform_apply()?;
+    let (eks_tf_output, tera_context, applied_inputs) = terraform_apply()?;
     update_kubeconfig_file(kubernetes, &eks_tf_output.kubeconfig)?;
 
     let kube_client = infra_ctx.mk_kube_client()?;
+    // the next deployment compares its inputs to these ones, to know if terraform can be skipped
+    if let (Some(applied_inputs), false) = (&applied_inputs, infra_ctx.context().is_dry_run_deploy()) {
+        if let Err(err) = record_applied_inputs(kube_client.client(), applied_inputs) {
+            logger.warn(err);
+        }
+    }
+    if paused_node_groups.is_some() && !infra_ctx.context().is_dry_run_deploy() {
+        if let Err(err) = forget_paused_node_groups(kube_client.client()) {
+            logger.warn(err);
+        }
+    }
 
     let credentials_env_vars = envs_to_string(cloud_provider.credentials_environment_variables());
     if let Some(spot_enabled) = &kubernetes.get_karpenter_parameters().map(|x| x.spot_enabled) {
@@ -186,10 +273,11 @@ pub fn create_eks_cluster(
             tera_context,
             PathBuf::from(infra_ctx.context().lib_root_dir()),
             kubernetes.template_directory.clone(),
-            kubernetes.temp_dir().join("helms"),
+            kubernetes.workspace().directory(WorkspaceDirectory::Chart),
             event_details.clone(),
             credentials_env_vars,
             kubernetes.context().is_dry_run_deploy(),
+            infra_ctx.abort(),
         ),
         eks_tf_output,
         kubernetes,
@@ -251,10 +339,15 @@ fn clean_karpenter_installation(
     let tf_action = TerraformInfraResources::new(
         tera_context.clone(),
         kubernetes.template_directory.join("terraform"),
-        kubernetes.temp_dir().join("terrafor_karpenter_cleanup"),
+        kubernetes
+            .workspace()
+            .directory_variant(WorkspaceDirectory::Terraform, "karpenter-cleanup"),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         infra_ctx.context().is_dry_run_deploy(),
+        kubernetes.advanced_settings.terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(kubernetes),
+        infra_ctx.abort(),
     );
 
     let _: AwsEksQoveryTerraformOutput = tf_action.create(logger)?;
@@ -3,6 +3,7 @@ use super::helm_charts::karpenter_configuration::KarpenterConfigurationChart;
 use super::helm_charts::karpenter_crd::KarpenterCrdChart;
 use crate::errors::EngineError;
 use crate::events::{EventMessage, InfrastructureStep, Stage};
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::delete_kube_apps::delete_kube_apps;
 use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
 use crate::infrastructure::action::eks::karpenter::node_groups_when_karpenter_is_enabled;
@@ -14,6 +15,7 @@ use crate::infrastructure::action::eks::tera_context::eks_tera_context;
 use crate::infrastructure::action::eks::utils::{define_cluster_upgrade_timeout, get_rusoto_eks_client};
 use crate::infrastructure::action::eks::{AwsEksQoveryTerraformOutput, AWS_EKS_DEFAULT_UPGRADE_TIMEOUT_DURATION};
 use crate::infrastructure::action::kubeconfig_helper::update_kubeconfig_file;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::InfraLogger;
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::cloud_provider::aws::regions::AwsZone;
@@ -95,10 +97,13 @@ pub fn delete_eks_cluster(
     let tf_resources = TerraformInfraResources::new(
         tera_context.clone(),
         kubernetes.template_directory.join("terraform"),
-        kubernetes.temp_dir.join("terraform"),
+        kubernetes.workspace.directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         infra_ctx.context().is_dry_run_deploy(),
+        kubernetes.advanced_settings.terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(kubernetes),
+        infra_ctx.abort(),
     );
 
     // should apply before destroy to be sure destroy will compute on all resources
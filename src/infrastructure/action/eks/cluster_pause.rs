@@ -1,10 +1,15 @@
-use crate::errors::EngineError;
-use crate::events::{InfrastructureStep, Stage};
+use crate::errors::{CommandError, EngineError};
+use crate::events::{EventDetails, InfrastructureStep, Stage};
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
 use crate::infrastructure::action::eks::karpenter::Karpenter;
-use crate::infrastructure::action::eks::nodegroup::should_update_desired_nodes;
+use crate::infrastructure::action::eks::nodegroup::{node_group_is_running, should_update_desired_nodes};
 use crate::infrastructure::action::eks::tera_context::eks_tera_context;
 use crate::infrastructure::action::eks::utils::{define_cluster_upgrade_timeout, get_rusoto_eks_client};
+use crate::infrastructure::action::pause_helper::{
+    check_no_engine_task_running, cordon_and_drain_nodes, record_paused_node_groups, PausedNodeGroups,
+};
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::InfraLogger;
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::aws::eks::EKS;
@@ -13,7 +18,14 @@ use crate::io_models::models::{KubernetesClusterAction, NodeGroupsFormat};
 use crate::runtime::block_on;
 use crate::services::kube_client::SelectK8sResourceBy;
 use crate::utilities::envs_to_string;
+use aws_sdk_rds::types::DbInstance;
+use aws_types::SdkConfig;
 use std::path::PathBuf;
+use std::time::Duration;
+
+// fargate nodes are managed by AWS, they host karpenter and are not removed by the pause
+const PAUSED_NODES_SELECTOR: &str = "eks.amazonaws.com/compute-type!=fargate";
+const RDS_AVAILABLE_STATE: &str = "available";
 
 pub fn pause_eks_cluster(
     kubernetes: &EKS,
@@ -21,22 +33,51 @@ pub fn pause_eks_cluster(
     logger: impl InfraLogger,
 ) -> Result<(), Box<EngineError>> {
     logger.info("Pausing cluster deployment.");
+    let event_details = kubernetes.get_event_details(Stage::Infrastructure(InfrastructureStep::Pause));
+    check_no_engine_task_running(kubernetes, infra_ctx.cloud_provider(), event_details.clone(), &logger)?;
 
-    // For Karpenter
     let kube_client = infra_ctx.mk_kube_client()?;
+    let aws_eks_client = match get_rusoto_eks_client(event_details.clone(), kubernetes, infra_ctx.cloud_provider()) {
+        Ok(value) => Some(value),
+        Err(_) => None,
+    };
+
+    // the resume restores the desired nodes of the node groups, instead of their min nodes
+    if !kubernetes.is_karpenter_enabled() && !kubernetes.context().is_dry_run_deploy() {
+        let mut paused_node_groups = PausedNodeGroups::default();
+        for node_group in &kubernetes.nodes_groups {
+            if let Some(desired_nodes) =
+                node_group_is_running(kubernetes, &event_details, node_group, aws_eks_client.clone())?
+            {
+                paused_node_groups
+                    .desired_nodes
+                    .insert(node_group.name.clone(), desired_nodes);
+            }
+        }
+        if let Err(err) = record_paused_node_groups(kube_client.client(), &paused_node_groups) {
+            logger.warn(format!("{err}, node groups will be resumed with their min nodes"));
+        }
+    }
+
+    if !kubernetes.context().is_dry_run_deploy() {
+        cordon_and_drain_nodes(
+            &kube_client,
+            event_details.clone(),
+            Some(PAUSED_NODES_SELECTOR),
+            Duration::from_secs(kubernetes.advanced_settings().k8s_pause_drain_timeout_in_sec as u64),
+            &logger,
+        )?;
+    }
+
+    // For Karpenter
     if kubernetes.is_karpenter_enabled() {
         block_on(Karpenter::pause(kubernetes, infra_ctx.cloud_provider(), &kube_client))?;
+        pause_managed_databases(kubernetes, infra_ctx, event_details, &logger)?;
         logger.info(format!("Kubernetes cluster {} successfully paused", kubernetes.name()));
         return Ok(());
     }
 
     // Legacy flow, that manage node groups
-    let event_details = kubernetes.get_event_details(Stage::Infrastructure(InfrastructureStep::Pause));
-    let aws_eks_client = match get_rusoto_eks_client(event_details.clone(), kubernetes, infra_ctx.cloud_provider()) {
-        Ok(value) => Some(value),
-        Err(_) => None,
-    };
-
     let node_groups_with_desired_states = should_update_desired_nodes(
         event_details.clone(),
         kubernetes,
@@ -75,13 +116,123 @@ pub fn pause_eks_cluster(
     let tf_action = TerraformInfraResources::new(
         tera_context.clone(),
         PathBuf::from(&kubernetes.template_directory).join("terraform"),
-        kubernetes.temp_dir().join("terraform"),
+        kubernetes.workspace().directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         kubernetes.context().is_dry_run_deploy(),
+        kubernetes.advanced_settings.terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(kubernetes),
+        infra_ctx.abort(),
     );
-    tf_action.pause(&["aws_eks_node_group."])?;
+    tf_action.pause(&["aws_eks_node_group."], &logger)?;
+    pause_managed_databases(kubernetes, infra_ctx, event_details, &logger)?;
 
     logger.info(format!("Kubernetes cluster {} successfully paused", kubernetes.name()));
     Ok(())
 }
+
+/// Stops the RDS instances created for the cluster, if enabled in the advanced settings.
+/// ElastiCache can't be stopped, and the instances are started again when their environment is redeployed.
+fn pause_managed_databases(
+    kubernetes: &EKS,
+    infra_ctx: &InfrastructureContext,
+    event_details: EventDetails,
+    logger: &impl InfraLogger,
+) -> Result<(), Box<EngineError>> {
+    if !kubernetes.advanced_settings().aws_eks_pause_managed_databases || kubernetes.context().is_dry_run_deploy() {
+        return Ok(());
+    }
+
+    let aws_conn = infra_ctx
+        .cloud_provider()
+        .aws_sdk_client()
+        .ok_or_else(|| Box::new(EngineError::new_aws_sdk_cannot_get_client(event_details.clone())))?;
+    let to_engine_error = |safe_message: String, raw_message: String| {
+        Box::new(EngineError::new_cannot_pause_managed_database(
+            event_details.clone(),
+            CommandError::new(safe_message, Some(raw_message), None),
+        ))
+    };
+
+    let instances = block_on(list_rds_instances(&aws_conn))
+        .map_err(|err| to_engine_error("Cannot list the RDS instances".to_string(), err))?;
+    let client = aws_sdk_rds::Client::new(&aws_conn);
+    for db_instance_identifier in managed_databases_to_stop(&instances, kubernetes.short_id()) {
+        logger.info(format!("Stopping managed database {db_instance_identifier}"));
+        block_on(
+            client
+                .stop_db_instance()
+                .db_instance_identifier(&db_instance_identifier)
+                .send(),
+        )
+        .map_err(|err| {
+            to_engine_error(format!("Cannot stop RDS instance {db_instance_identifier}"), err.to_string())
+        })?;
+    }
+
+    Ok(())
+}
+
+async fn list_rds_instances(aws_conn: &SdkConfig) -> Result<Vec<DbInstance>, String> {
+    let client = aws_sdk_rds::Client::new(aws_conn);
+    let mut instances = vec![];
+    let mut marker = None;
+    loop {
+        let output = client
+            .describe_db_instances()
+            .set_marker(marker)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        instances.extend(output.db_instances().iter().cloned());
+        marker = output.marker().map(|marker| marker.to_string());
+        if marker.is_none() {
+            return Ok(instances);
+        }
+    }
+}
+
+/// Available instances tagged with the cluster id, as the databases templates do
+fn managed_databases_to_stop(instances: &[DbInstance], cluster_id: &str) -> Vec<String> {
+    instances
+        .iter()
+        .filter(|instance| instance.db_instance_status() == Some(RDS_AVAILABLE_STATE))
+        .filter(|instance| {
+            instance
+                .tag_list()
+                .iter()
+                .any(|tag| tag.key() == Some("cluster_id") && tag.value() == Some(cluster_id))
+        })
+        .filter_map(|instance| instance.db_instance_identifier().map(|id| id.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::managed_databases_to_stop;
+    use aws_sdk_rds::types::{DbInstance, Tag};
+
+    fn db_instance(id: &str, status: &str, cluster_id: &str) -> DbInstance {
+        DbInstance::builder()
+            .db_instance_identifier(id)
+            .db_instance_status(status)
+            .tag_list(Tag::builder().key("cluster_id").value(cluster_id).build())
+            .build()
+    }
+
+    #[test]
+    fn test_managed_databases_to_stop() {
+        let instances = vec![
+            db_instance("postgresql-a", "available", "z1234"),
+            db_instance("mysql-b", "stopped", "z1234"),
+            db_instance("postgresql-c", "available", "z5678"),
+            DbInstance::builder()
+                .db_instance_identifier("not-qovery")
+                .db_instance_status("available")
+                .build(),
+        ];
+
+        assert_eq!(managed_databases_to_stop(&instances, "z1234"), vec!["postgresql-a".to_string()]);
+        assert!(managed_databases_to_stop(&instances, "z0000").is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+use crate::errors::EngineError;
+use crate::events::{InfrastructureStep, Stage};
+use crate::fs::WorkspaceDirectory;
+use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
+use crate::infrastructure::action::eks::karpenter::node_groups_when_karpenter_is_enabled;
+use crate::infrastructure::action::eks::nodegroup::should_update_desired_nodes;
+use crate::infrastructure::action::eks::tera_context::eks_tera_context;
+use crate::infrastructure::action::eks::utils::get_rusoto_eks_client;
+use crate::infrastructure::action::eks::AWS_EKS_DEFAULT_UPGRADE_TIMEOUT_DURATION;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
+use crate::infrastructure::action::InfraLogger;
+use crate::infrastructure::infrastructure_context::InfrastructureContext;
+use crate::infrastructure::models::kubernetes::aws::eks::EKS;
+use crate::infrastructure::models::kubernetes::Kubernetes;
+use crate::io_models::models::KubernetesClusterAction;
+use crate::utilities::envs_to_string;
+
+// resources of a node group in eks-workers-nodes.j2.tf, node groups are indexed from 1 in the template
+fn node_group_terraform_resources(index: usize) -> [String; 2] {
+    [
+        format!("aws_launch_template.eks_workers_nodes_{index}"),
+        format!("aws_eks_node_group.eks_cluster_workers_{index}"),
+    ]
+}
+
+pub fn repair_eks_node_groups(
+    kubernetes: &EKS,
+    infra_ctx: &InfrastructureContext,
+    logger: impl InfraLogger,
+) -> Result<(), Box<EngineError>> {
+    let event_details = kubernetes.get_event_details(Stage::Infrastructure(InfrastructureStep::Create));
+    logger.info(format!("Preparing {} cluster node groups repair.", kubernetes.kind()));
+
+    // with Karpenter, nodes are not managed by terraform
+    let nodes_groups = node_groups_when_karpenter_is_enabled(
+        kubernetes,
+        infra_ctx,
+        &kubernetes.nodes_groups,
+        &event_details,
+        KubernetesClusterAction::Update(None),
+    )?;
+    let node_groups_with_desired_states = should_update_desired_nodes(
+        event_details.clone(),
+        kubernetes,
+        KubernetesClusterAction::Update(None),
+        nodes_groups,
+        get_rusoto_eks_client(event_details.clone(), kubernetes, infra_ctx.cloud_provider()).ok(),
+    )?;
+
+    // generate terraform files and copy them into temp dir
+    let tera_context = eks_tera_context(
+        kubernetes,
+        infra_ctx.cloud_provider(),
+        infra_ctx.dns_provider(),
+        &kubernetes.zones,
+        &node_groups_with_desired_states,
+        &kubernetes.options,
+        AWS_EKS_DEFAULT_UPGRADE_TIMEOUT_DURATION,
+        false,
+        kubernetes.advanced_settings(),
+        kubernetes.qovery_allowed_public_access_cidrs.as_ref(),
+    )?;
+
+    let tf_action = TerraformInfraResources::new(
+        tera_context,
+        kubernetes.template_directory.join("terraform"),
+        kubernetes.workspace().directory(WorkspaceDirectory::Terraform),
+        event_details,
+        envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
+        infra_ctx.context().is_dry_run_deploy(),
+        kubernetes.advanced_settings.terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(kubernetes),
+        infra_ctx.abort(),
+    );
+    let targets = (1..=node_groups_with_desired_states.len())
+        .flat_map(node_group_terraform_resources)
+        .collect::<Vec<_>>();
+    tf_action.repair(
+        &targets,
+        &["aws_launch_template.eks_workers_nodes_", "aws_eks_node_group."],
+        &logger,
+    )?;
+
+    logger.info(format!(
+        "Kubernetes cluster {} node groups successfully repaired",
+        kubernetes.name()
+    ));
+    Ok(())
+}
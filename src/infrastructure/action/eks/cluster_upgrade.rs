@@ -2,17 +2,24 @@ use crate::cmd::kubectl::{kubectl_exec_scale_replicas, ScalingKind};
 use crate::errors::EngineError;
 use crate::events::Stage::Infrastructure;
 use crate::events::{EventDetails, InfrastructureStep};
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::delete_kube_apps::prepare_kube_upgrade;
 use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
 use crate::infrastructure::action::eks::nodegroup::should_update_desired_nodes;
-use crate::infrastructure::action::eks::tera_context::eks_tera_context;
+use crate::infrastructure::action::eks::sdk::QoveryAwsSdkConfigEks;
+use crate::infrastructure::action::eks::tera_context::{
+    eks_addons_versions, eks_tera_context, insert_eks_addons_versions,
+};
 use crate::infrastructure::action::eks::utils::{define_cluster_upgrade_timeout, get_rusoto_eks_client};
 use crate::infrastructure::action::eks::AwsEksQoveryTerraformOutput;
 use crate::infrastructure::action::kubectl_utils::check_workers_on_upgrade;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::InfraLogger;
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::aws::eks::EKS;
-use crate::infrastructure::models::kubernetes::{Kubernetes, KubernetesUpgradeStatus};
+use crate::infrastructure::models::kubernetes::{
+    Kubernetes, KubernetesError, KubernetesUpgradeStatus, KubernetesVersion,
+};
 use crate::io_models::models::KubernetesClusterAction;
 use crate::runtime::block_on;
 use crate::services::kube_client::SelectK8sResourceBy;
@@ -28,7 +35,7 @@ pub fn upgrade_eks_cluster(
     let event_details = kubernetes.get_event_details(Infrastructure(InfrastructureStep::Upgrade));
 
     logger.info("Start preparing EKS cluster upgrade process");
-    let temp_dir = kubernetes.temp_dir();
+    let workspace = kubernetes.workspace();
     let aws_eks_client = get_rusoto_eks_client(event_details.clone(), kubernetes, infra_ctx.cloud_provider()).ok();
 
     let nodes_groups = should_update_desired_nodes(
@@ -63,6 +70,10 @@ pub fn upgrade_eks_cluster(
         kubernetes.qovery_allowed_public_access_cidrs.as_ref(),
     )?;
 
+    // fail before touching the cluster if an addon can't follow
+    logger.info("Checking EKS addons versions support the requested Kubernetes version.");
+    check_eks_addons_versions(kubernetes, infra_ctx, &event_details, &logger)?;
+
     //
     // Upgrade master nodes
     //
@@ -79,18 +90,34 @@ pub fn upgrade_eks_cluster(
         "eks_workers_version",
         format!("{}", &kubernetes_upgrade_status.deployed_masters_version).as_str(),
     );
+    // keep the addons of the current masters version, they will be updated once masters are upgraded
+    if let Ok(deployed_masters_version) = kubernetes_upgrade_status
+        .deployed_masters_version
+        .to_major_minor_version_string("0".to_string())
+        .parse::<KubernetesVersion>()
+    {
+        insert_eks_addons_versions(&mut context, &kubernetes.options, &deployed_masters_version);
+    }
 
     logger.info("Upgrading Kubernetes master nodes.");
     let tf_resources = TerraformInfraResources::new(
         context.clone(),
         PathBuf::from(&kubernetes.template_directory).join("terraform"),
-        temp_dir.join("terraform"),
+        workspace.directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         infra_ctx.context().is_dry_run_deploy(),
+        kubernetes.advanced_settings.terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(kubernetes),
+        infra_ctx.abort(),
     );
     let _: AwsEksQoveryTerraformOutput = tf_resources.create(&logger)?;
 
+    //
+    // Upgrade EKS addons, along with worker nodes
+    //
+    insert_eks_addons_versions(&mut context, &kubernetes.options, &kubernetes.version());
+
     //
     // Upgrade worker nodes
     //
@@ -104,10 +131,13 @@ pub fn upgrade_eks_cluster(
     let tf_resources = TerraformInfraResources::new(
         context.clone(),
         PathBuf::from(&kubernetes.template_directory).join("terraform"),
-        temp_dir.join("terraform"),
+        workspace.directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         infra_ctx.context().is_dry_run_deploy(),
+        kubernetes.advanced_settings.terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(kubernetes),
+        infra_ctx.abort(),
     );
 
     logger.info("Start upgrading process for worker nodes.");
@@ -159,6 +189,55 @@ pub fn upgrade_eks_cluster(
     Ok(())
 }
 
+/// Makes sure each EKS addon version is available for the requested kubernetes version before bumping it.
+/// Addons AWS can't be asked about are not blocking the upgrade.
+fn check_eks_addons_versions(
+    kubernetes: &EKS,
+    infra_ctx: &InfrastructureContext,
+    event_details: &EventDetails,
+    logger: &impl InfraLogger,
+) -> Result<(), Box<EngineError>> {
+    let Some(aws_conn) = infra_ctx.cloud_provider().aws_sdk_client() else {
+        logger.warn("Cannot get AWS client, skipping EKS addons versions check.");
+        return Ok(());
+    };
+
+    let requested_version = kubernetes.version();
+    let kubernetes_version = format!("{}.{}", requested_version.major(), requested_version.minor());
+    for (addon, addon_version) in eks_addons_versions(&kubernetes.options, &requested_version) {
+        let supported_addon_versions = match block_on(
+            aws_conn.describe_addon_versions(addon.eks_addon_name(), kubernetes_version.clone()),
+        ) {
+            Ok(output) => output
+                .addons()
+                .iter()
+                .flat_map(|addon_info| addon_info.addon_versions())
+                .filter_map(|addon_version_info| addon_version_info.addon_version())
+                .map(|version| version.to_string())
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                logger.warn(format!(
+                    "Cannot get `{addon}` addon versions for Kubernetes {kubernetes_version}, skipping its check: {e}"
+                ));
+                continue;
+            }
+        };
+
+        if !supported_addon_versions.contains(&addon_version) {
+            return Err(Box::new(EngineError::new_k8s_addon_version_not_supported(
+                event_details.clone(),
+                KubernetesError::AddonUnSupportedKubernetesVersion {
+                    kubernetes_version,
+                    addon,
+                    addon_version,
+                },
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 fn set_cluster_autoscaler_replicas(
     kubernetes: &EKS,
     event_details: EventDetails,
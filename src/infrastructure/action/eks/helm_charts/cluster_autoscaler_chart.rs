@@ -7,6 +7,7 @@ use crate::infrastructure::helm_charts::{
     HelmChartDirectoryLocation, HelmChartPath, HelmChartValuesFilePath, ToCommonHelmChart,
 };
 use crate::infrastructure::models::cloud_provider::aws::regions::AwsRegion;
+use crate::infrastructure::models::kubernetes::cluster_autoscaler::ClusterAutoscalerSettings;
 use kube::Client;
 
 pub struct ClusterAutoscalerChart {
@@ -19,6 +20,7 @@ pub struct ClusterAutoscalerChart {
     prometheus_namespace: HelmChartNamespaces,
     ff_metrics_history_enabled: bool,
     replaced_by_karpenter: bool,
+    autoscaler_settings: ClusterAutoscalerSettings,
 }
 
 impl ClusterAutoscalerChart {
@@ -31,6 +33,7 @@ impl ClusterAutoscalerChart {
         prometheus_namespace: HelmChartNamespaces,
         ff_metrics_history_enabled: bool,
         replaced_by_karpenter: bool,
+        autoscaler_settings: ClusterAutoscalerSettings,
     ) -> Self {
         ClusterAutoscalerChart {
             chart_path: HelmChartPath::new(
@@ -50,6 +53,7 @@ impl ClusterAutoscalerChart {
             prometheus_namespace,
             ff_metrics_history_enabled,
             replaced_by_karpenter,
+            autoscaler_settings,
         }
     }
 
@@ -60,6 +64,45 @@ impl ClusterAutoscalerChart {
 
 impl ToCommonHelmChart for ClusterAutoscalerChart {
     fn to_common_helm_chart(&self) -> Result<CommonChart, HelmChartError> {
+        let mut values = vec![
+            ChartSetValue {
+                key: "cloudProvider".to_string(),
+                value: self.cloud_provider.to_string(),
+            },
+            ChartSetValue {
+                key: "awsRegion".to_string(),
+                value: self.chart_image_region.to_cloud_provider_format().to_string(),
+            },
+            ChartSetValue {
+                key: "autoDiscovery.clusterName".to_string(),
+                value: self.cluster_name.to_string(),
+            },
+            ChartSetValue {
+                // we use string templating (r"...") to escape dot in annotation's key
+                key: r"rbac.serviceAccount.annotations.eks\.amazonaws\.com/role-arn".to_string(),
+                value: self.aws_iam_cluster_autoscaler_role_arn.to_string(),
+            },
+            // observability
+            ChartSetValue {
+                key: "serviceMonitor.enabled".to_string(),
+                value: self.ff_metrics_history_enabled.to_string(),
+            },
+            ChartSetValue {
+                key: "serviceMonitor.namespace".to_string(),
+                value: self.prometheus_namespace.to_string(),
+            },
+        ];
+        // tuning, arguments are part of the pod spec so any change rolls the deployment
+        values.extend(
+            self.autoscaler_settings
+                .to_eks_extra_args()
+                .into_iter()
+                .map(|(arg, value)| ChartSetValue {
+                    key: format!("extraArgs.{arg}"),
+                    value,
+                }),
+        );
+
         Ok(CommonChart {
             chart_info: ChartInfo {
                 name: ClusterAutoscalerChart::chart_name(),
@@ -69,34 +112,7 @@ impl ToCommonHelmChart for ClusterAutoscalerChart {
                 },
                 path: self.chart_path.to_string(),
                 values_files: vec![self.chart_values_path.to_string()],
-                values: vec![
-                    ChartSetValue {
-                        key: "cloudProvider".to_string(),
-                        value: self.cloud_provider.to_string(),
-                    },
-                    ChartSetValue {
-                        key: "awsRegion".to_string(),
-                        value: self.chart_image_region.to_cloud_provider_format().to_string(),
-                    },
-                    ChartSetValue {
-                        key: "autoDiscovery.clusterName".to_string(),
-                        value: self.cluster_name.to_string(),
-                    },
-                    ChartSetValue {
-                        // we use string templating (r"...") to escape dot in annotation's key
-                        key: r"rbac.serviceAccount.annotations.eks\.amazonaws\.com/role-arn".to_string(),
-                        value: self.aws_iam_cluster_autoscaler_role_arn.to_string(),
-                    },
-                    // observability
-                    ChartSetValue {
-                        key: "serviceMonitor.enabled".to_string(),
-                        value: self.ff_metrics_history_enabled.to_string(),
-                    },
-                    ChartSetValue {
-                        key: "serviceMonitor.namespace".to_string(),
-                        value: self.prometheus_namespace.to_string(),
-                    },
-                ],
+                values,
                 ..Default::default()
             },
             chart_installation_checker: Some(Box::new(ClusterAutoscalerChartChecker::new())),
@@ -140,6 +156,7 @@ mod tests {
         HelmChartType, ToCommonHelmChart,
     };
     use crate::infrastructure::models::cloud_provider::aws::regions::AwsRegion;
+    use crate::infrastructure::models::kubernetes::cluster_autoscaler::ClusterAutoscalerSettings;
     use crate::infrastructure::models::kubernetes::Kind as KubernetesKind;
     use std::env;
 
@@ -156,6 +173,7 @@ mod tests {
             HelmChartNamespaces::Prometheus,
             true,
             false,
+            ClusterAutoscalerSettings::default(),
         );
 
         let current_directory = env::current_dir().expect("Impossible to get current directory");
@@ -191,6 +209,7 @@ mod tests {
             HelmChartNamespaces::Prometheus,
             true,
             false,
+            ClusterAutoscalerSettings::default(),
         );
 
         let current_directory = env::current_dir().expect("Impossible to get current directory");
@@ -227,6 +246,7 @@ mod tests {
             HelmChartNamespaces::Prometheus,
             true,
             false,
+            ClusterAutoscalerSettings::default(),
         );
         let common_chart = chart.to_common_helm_chart().unwrap();
 
@@ -14,6 +14,7 @@ use crate::infrastructure::helm_charts::{
     HelmChartVpaType, ToCommonHelmChart,
 };
 use crate::infrastructure::models::cloud_provider::Kind;
+use crate::infrastructure::models::kubernetes::cluster_autoscaler::ClusterAutoscalerSettings;
 use crate::infrastructure::models::kubernetes::Kind as KubernetesKind;
 use crate::io_models::models::{KubernetesCpuResourceUnit, KubernetesMemoryResourceUnit};
 
@@ -51,6 +52,7 @@ use crate::infrastructure::helm_charts::prometheus_adapter_chart::PrometheusAdap
 use crate::infrastructure::helm_charts::qovery_cert_manager_webhook_chart::QoveryCertManagerWebhookChart;
 use crate::infrastructure::helm_charts::qovery_cluster_agent_chart::QoveryClusterAgentChart;
 use crate::infrastructure::helm_charts::qovery_priority_class_chart::QoveryPriorityClassChart;
+use crate::infrastructure::helm_charts::qovery_registry_mirrors_chart::QoveryRegistryMirrorsChart;
 use crate::io_models::QoveryIdentifier;
 use chrono::Duration;
 use std::collections::HashSet;
@@ -177,6 +179,7 @@ pub(super) fn eks_helm_charts(
         chart_config_prerequisites.cluster_name.to_string(),
         chart_config_prerequisites.karpenter_controller_aws_role_arn.clone(),
         chart_config_prerequisites.is_karpenter_enabled,
+        chart_config_prerequisites.is_karpenter_interruption_queue_enabled,
         false,
         chart_config_prerequisites.kubernetes_version_upgrade_requested,
     )
@@ -190,6 +193,7 @@ pub(super) fn eks_helm_charts(
         chart_config_prerequisites.cluster_name.to_string(),
         chart_config_prerequisites.karpenter_controller_aws_role_arn.clone(),
         chart_config_prerequisites.is_karpenter_enabled,
+        chart_config_prerequisites.is_karpenter_interruption_queue_enabled,
         true,
         chart_config_prerequisites.kubernetes_version_upgrade_requested,
     )
@@ -209,6 +213,9 @@ pub(super) fn eks_helm_charts(
         chart_config_prerequisites.karpenter_parameters.clone(),
         chart_config_prerequisites.infra_options.user_provided_network.as_ref(),
         chart_config_prerequisites.cluster_advanced_settings.pleco_resources_ttl,
+        chart_config_prerequisites
+            .cluster_advanced_settings
+            .karpenter_default_node_pool_disruption()?,
     )
     .to_common_helm_chart()?;
 
@@ -222,6 +229,12 @@ pub(super) fn eks_helm_charts(
         prometheus_namespace,
         chart_config_prerequisites.ff_metrics_history_enabled,
         chart_config_prerequisites.is_karpenter_enabled,
+        ClusterAutoscalerSettings::new(
+            &chart_config_prerequisites
+                .cluster_advanced_settings
+                .cluster_autoscaler_overrides(),
+            chart_config_prerequisites.is_karpenter_enabled,
+        )?,
     )
     .to_common_helm_chart()?;
 
@@ -286,6 +299,20 @@ pub(super) fn eks_helm_charts(
     )
     .to_common_helm_chart()?;
 
+    let services_logging = chart_config_prerequisites
+        .cluster_advanced_settings
+        .logging_services
+        .iter()
+        .map(|service_logging| {
+            service_logging.to_model(
+                chart_config_prerequisites
+                    .cluster_advanced_settings
+                    .loki_log_retention_in_week
+                    * 7,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
     // Promtail
     let promtail = match chart_config_prerequisites.ff_log_history_enabled {
         false => None,
@@ -299,6 +326,7 @@ pub(super) fn eks_helm_charts(
                 HelmChartNamespaces::KubeSystem,
                 PriorityClass::Default,
                 chart_config_prerequisites.is_karpenter_enabled,
+                services_logging.clone(),
             )
             .to_common_helm_chart()?,
         ),
@@ -329,6 +357,7 @@ pub(super) fn eks_helm_charts(
                 HelmChartResourcesConstraintType::ChartDefault,
                 HelmChartTimeout::ChartDefault,
                 chart_config_prerequisites.is_karpenter_enabled,
+                services_logging.clone(),
             )
             .to_common_helm_chart()?,
         ),
@@ -546,6 +575,12 @@ pub(super) fn eks_helm_charts(
             .nginx_controller_http_snippet
             .as_ref()
             .map(|nginx_controller_http_snippet_io| nginx_controller_http_snippet_io.to_model()),
+        chart_config_prerequisites
+            .cluster_advanced_settings
+            .nginx_custom_error_pages
+            .as_ref()
+            .map(|nginx_custom_error_pages_io| nginx_custom_error_pages_io.to_model())
+            .transpose()?,
     )
     .to_common_helm_chart()?;
 
@@ -598,6 +633,17 @@ pub(super) fn eks_helm_charts(
     )
     .to_common_helm_chart()?;
 
+    // Docker Hub mirrors of the nodes containerd
+    let q_registry_mirrors_chart = QoveryRegistryMirrorsChart::new(
+        chart_prefix_path,
+        !chart_config_prerequisites
+            .cluster_advanced_settings
+            .registry_mirrors
+            .is_empty(),
+        HelmChartNamespaces::KubeSystem,
+    )
+    .to_common_helm_chart()?;
+
     let qovery_engine = CommonChart {
         chart_info: ChartInfo {
             name: "qovery-engine".to_string(),
@@ -700,6 +746,7 @@ pub(super) fn eks_helm_charts(
     let mut level_0: Vec<Box<dyn HelmChart>> = vec![
         // Box::new(prometheus_service_monitor_crd.clone()), // to be fixed: can cause an error if crd is already installed
         Box::new(q_priority_class_chart),
+        Box::new(q_registry_mirrors_chart),
     ];
 
     let mut level_1: Vec<Box<dyn HelmChart>> = vec![];
@@ -13,6 +13,7 @@ pub struct KarpenterChart {
     cluster_name: String,
     aws_iam_karpenter_controller_role_arn: String,
     replace_cluster_autoscaler: bool,
+    enable_interruption_queue: bool,
     enable_monitoring: bool,
     recreate_pods: bool,
 }
@@ -23,6 +24,7 @@ impl KarpenterChart {
         cluster_name: String,
         aws_iam_karpenter_controller_role_arn: String,
         replace_cluster_autoscaler: bool,
+        enable_interruption_queue: bool,
         enable_monitoring: bool,
         recreate_pods: bool,
     ) -> Self {
@@ -40,6 +42,7 @@ impl KarpenterChart {
             cluster_name,
             aws_iam_karpenter_controller_role_arn,
             replace_cluster_autoscaler,
+            enable_interruption_queue,
             enable_monitoring,
             recreate_pods,
         }
@@ -72,8 +75,12 @@ impl ToCommonHelmChart for KarpenterChart {
                         value: self.aws_iam_karpenter_controller_role_arn.to_string(),
                     },
                     ChartSetValue {
+                        // the queue is named after the cluster, interruption handling is disabled when empty
                         key: "settings.interruptionQueue".to_string(),
-                        value: self.cluster_name.to_string(),
+                        value: match self.enable_interruption_queue {
+                            true => self.cluster_name.to_string(),
+                            false => "".to_string(),
+                        },
                     },
                     ChartSetValue {
                         key: "serviceMonitor.enabled".to_string(),
@@ -146,7 +153,7 @@ mod tests {
     #[test]
     fn karpenter_chart_directory_exists_test() {
         // setup:
-        let chart = KarpenterChart::new(None, "whatever".to_string(), "whatever".to_string(), true, true, false);
+        let chart = KarpenterChart::new(None, "whatever".to_string(), "whatever".to_string(), true, true, true, false);
 
         let current_directory = env::current_dir().expect("Impossible to get current directory");
         let chart_path = format!(
@@ -172,7 +179,7 @@ mod tests {
     #[test]
     fn karpenter_chart_values_file_exists_test() {
         // setup:
-        let chart = KarpenterChart::new(None, "whatever".to_string(), "whatever".to_string(), true, true, false);
+        let chart = KarpenterChart::new(None, "whatever".to_string(), "whatever".to_string(), true, true, true, false);
 
         let current_directory = env::current_dir().expect("Impossible to get current directory");
         let chart_values_path = format!(
@@ -194,12 +201,40 @@ mod tests {
         assert!(values_file.is_ok(), "Chart values file should exist: `{chart_values_path}`");
     }
 
+    #[test]
+    fn karpenter_interruption_queue_values_test() {
+        for (enable_interruption_queue, expected_queue) in [(true, "cluster-name"), (false, "")] {
+            // setup:
+            let chart = KarpenterChart::new(
+                None,
+                "cluster-name".to_string(),
+                "whatever".to_string(),
+                true,
+                enable_interruption_queue,
+                true,
+                false,
+            );
+
+            // execute:
+            let common_chart = chart.to_common_helm_chart().unwrap();
+
+            // verify:
+            let interruption_queue = common_chart
+                .chart_info
+                .values
+                .iter()
+                .find(|value| value.key == "settings.interruptionQueue")
+                .expect("interruption queue should be set");
+            assert_eq!(interruption_queue.value, expected_queue);
+        }
+    }
+
     /// Make sure rust code doesn't set a value not declared inside values file.
     /// All values should be declared / set in values file unless it needs to be injected via rust code.
     #[test]
     fn karpenter_rust_overridden_values_exists_in_values_yaml_test() {
         // setup:
-        let chart = KarpenterChart::new(None, "whatever".to_string(), "whatever".to_string(), true, true, false);
+        let chart = KarpenterChart::new(None, "whatever".to_string(), "whatever".to_string(), true, true, true, false);
         let common_chart = chart.to_common_helm_chart().unwrap();
 
         // execute:
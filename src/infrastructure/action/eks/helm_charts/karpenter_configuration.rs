@@ -7,8 +7,8 @@ use crate::infrastructure::helm_charts::{
     HelmChartDirectoryLocation, HelmChartPath, HelmChartValuesFilePath, ToCommonHelmChart,
 };
 use crate::infrastructure::models::kubernetes::aws::{
-    KarpenterNodePool, KarpenterNodePoolRequirement, KarpenterNodePoolRequirementKey, KarpenterParameters,
-    KarpenterRequirementOperator, UserNetworkConfig,
+    KarpenterDefaultNodePoolDisruption, KarpenterNodePool, KarpenterNodePoolRequirement,
+    KarpenterNodePoolRequirementKey, KarpenterParameters, KarpenterRequirementOperator, UserNetworkConfig,
 };
 use itertools::Itertools;
 use kube::Client;
@@ -27,6 +27,7 @@ pub struct KarpenterConfigurationChart {
     karpenter_parameters: Option<KarpenterParameters>,
     explicit_subnet_ids: Vec<String>,
     pleco_resources_ttl: i32,
+    default_node_pool_disruption: KarpenterDefaultNodePoolDisruption,
 }
 
 impl KarpenterConfigurationChart {
@@ -43,6 +44,7 @@ impl KarpenterConfigurationChart {
         karpenter_parameters: Option<KarpenterParameters>,
         user_network_config: Option<&UserNetworkConfig>,
         pleco_resources_ttl: i32,
+        default_node_pool_disruption: KarpenterDefaultNodePoolDisruption,
     ) -> Self {
         KarpenterConfigurationChart {
             chart_path: HelmChartPath::new(
@@ -80,6 +82,7 @@ impl KarpenterConfigurationChart {
                 vec![]
             },
             pleco_resources_ttl,
+            default_node_pool_disruption,
         }
     }
 
@@ -245,6 +248,35 @@ impl ToCommonHelmChart for KarpenterConfigurationChart {
                 });
             });
 
+        // Default node pool disruption
+        values.push(ChartSetValue {
+            key: "defaultNodePool.disruption.consolidationPolicy".to_string(),
+            value: self.default_node_pool_disruption.consolidation_policy.to_string(),
+        });
+        values.push(ChartSetValue {
+            key: "defaultNodePool.disruption.consolidateAfter".to_string(),
+            value: self.default_node_pool_disruption.consolidate_after.clone(),
+        });
+        for (index, budget) in self.default_node_pool_disruption.budgets.iter().enumerate() {
+            let prefix = format!("defaultNodePool.disruption.budgets[{index}]");
+
+            values.push(ChartSetValue {
+                key: format!("{prefix}.nodes"),
+                value: budget.nodes.clone(),
+            });
+            if let Some((schedule, duration)) = &budget.schedule_and_duration {
+                values.push(ChartSetValue {
+                    key: format!("{prefix}.schedule"),
+                    // helm splits the values on commas
+                    value: schedule.replace(',', "\\,"),
+                });
+                values.push(ChartSetValue {
+                    key: format!("{prefix}.duration"),
+                    value: duration.clone(),
+                });
+            }
+        }
+
         // Inject node pools values
         if let Some(pools) = qovery_node_pools {
             // Stable node pool consolidation
@@ -366,6 +398,7 @@ mod tests {
         HelmChartType, ToCommonHelmChart,
     };
     use crate::infrastructure::models::kubernetes::aws::{
+        KarpenterConsolidationPolicy, KarpenterDefaultNodePoolBudget, KarpenterDefaultNodePoolDisruption,
         KarpenterDefaultNodePoolOverride, KarpenterNodePool, KarpenterNodePoolDisruptionBudget,
         KarpenterNodePoolDisruptionReason, KarpenterNodePoolLimits, KarpenterNodePoolRequirement,
         KarpenterNodePoolRequirementKey, KarpenterParameters, KarpenterRequirementOperator,
@@ -452,6 +485,49 @@ mod tests {
         assert!(missing_fields.is_none(), "Some fields are missing in values file, add those (make sure they still exist in chart values), fields: {}", missing_fields.unwrap_or_default().join(","));
     }
 
+    #[test]
+    fn karpenter_configuration_default_node_pool_disruption_values_test() {
+        // setup:
+        let mut chart = create_chart(false, None);
+        chart.default_node_pool_disruption = KarpenterDefaultNodePoolDisruption {
+            consolidation_policy: KarpenterConsolidationPolicy::WhenEmpty,
+            consolidate_after: "5m".to_string(),
+            budgets: vec![
+                KarpenterDefaultNodePoolBudget {
+                    nodes: "20%".to_string(),
+                    schedule_and_duration: None,
+                },
+                KarpenterDefaultNodePoolBudget {
+                    nodes: "0".to_string(),
+                    schedule_and_duration: Some(("0 9 * * 1,2,3".to_string(), "8h".to_string())),
+                },
+            ],
+        };
+
+        // execute:
+        let common_chart = chart.to_common_helm_chart().expect("Failed to convert to common chart");
+        let disruption_values = common_chart
+            .chart_info
+            .values
+            .iter()
+            .filter(|value| value.key.starts_with("defaultNodePool.disruption."))
+            .map(|value| (value.key.as_str(), value.value.as_str()))
+            .collect_vec();
+
+        // verify:
+        assert_eq!(
+            disruption_values,
+            vec![
+                ("defaultNodePool.disruption.consolidationPolicy", "WhenEmpty"),
+                ("defaultNodePool.disruption.consolidateAfter", "5m"),
+                ("defaultNodePool.disruption.budgets[0].nodes", "20%"),
+                ("defaultNodePool.disruption.budgets[1].nodes", "0"),
+                ("defaultNodePool.disruption.budgets[1].schedule", "0 9 * * 1\\,2\\,3"),
+                ("defaultNodePool.disruption.budgets[1].duration", "8h"),
+            ]
+        );
+    }
+
     #[test]
     fn test_karpenter_configuration() {
         // Define your test cases
@@ -702,6 +778,7 @@ mod tests {
             }),
             None,
             0,
+            KarpenterDefaultNodePoolDisruption::default(),
         )
     }
 
@@ -54,6 +54,7 @@ pub struct EksChartsConfigPrerequisites {
     pub infra_options: Options,
     pub cluster_advanced_settings: ClusterAdvancedSettings,
     pub is_karpenter_enabled: bool,
+    pub is_karpenter_interruption_queue_enabled: bool,
     pub karpenter_parameters: Option<KarpenterParameters>,
     pub aws_account_id: String,
     pub aws_iam_eks_user_mapper_role_arn: String,
@@ -131,6 +132,7 @@ impl<'a> HelmInfraResources for EksHelmsDeployment<'a> {
             dns_provider_config: dns_provider.provider_configuration(),
             cluster_advanced_settings: cluster.advanced_settings().clone(),
             is_karpenter_enabled: cluster.is_karpenter_enabled(),
+            is_karpenter_interruption_queue_enabled: cluster.is_karpenter_interruption_queue_enabled(),
             karpenter_parameters: cluster.get_karpenter_parameters(),
             aws_account_id: self.terraform_output.aws_account_id.clone(),
             aws_iam_eks_user_mapper_role_arn: self.terraform_output.aws_iam_eks_user_mapper_role_arn.clone(),
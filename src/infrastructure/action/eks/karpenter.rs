@@ -1,7 +1,5 @@
 use crate::cmd::command::CommandKiller;
 use crate::cmd::helm::{to_engine_error, Helm};
-use crate::cmd::kubectl::kubectl_exec_get_pods;
-use crate::cmd::structs::KubernetesPodStatusPhase;
 use crate::environment::models::ToCloudProviderFormat;
 use crate::errors::{CommandError, EngineError, ErrorMessageVerbosity};
 use crate::events::{EngineEvent, EventDetails, EventMessage, InfrastructureStep, Stage};
@@ -18,14 +16,14 @@ use crate::infrastructure::models::kubernetes::aws::eks::EKS;
 use crate::infrastructure::models::kubernetes::aws::Options;
 use crate::infrastructure::models::kubernetes::Kubernetes;
 use crate::io_models::models::{KubernetesClusterAction, NodeGroups};
+use crate::kubers_utils::kube_get_pods;
 use crate::runtime::block_on;
 use crate::services::kube_client::{QubeClient, SelectK8sResourceBy};
+use crate::utilities::{create_kube_client, envs_to_string};
 use aws_types::SdkConfig;
 use chrono::Duration as ChronoDuration;
 use jsonptr::Pointer;
 use k8s_openapi::api::core::v1::Node;
-use retry::delay::Fixed;
-use retry::OperationResult;
 use std::str::FromStr;
 use std::string::ToString;
 use std::time::Duration;
@@ -328,10 +326,19 @@ impl Karpenter {
             EngineError::new_unsupported_region(event_details.clone(), kubernetes.region().to_string(), None)
         })?;
         let cluster_name = kubernetes.cluster_name();
+        let default_node_pool_disruption = kubernetes
+            .advanced_settings()
+            .karpenter_default_node_pool_disruption()
+            .map_err(|err| {
+                Box::new(EngineError::new_invalid_engine_payload_invalid_field_value(
+                    event_details.clone(),
+                    err,
+                ))
+            })?;
 
         // Karpenter Configuration
         let mut karpenter_configuration_chart = KarpenterConfigurationChart::new(
-            Some(kubernetes.temp_dir().to_string_lossy().as_ref()),
+            Some(kubernetes.workspace().root().to_string_lossy().as_ref()),
             cluster_name.to_string(),
             true,
             terraform_output.cluster_security_group_id.clone(),
@@ -343,6 +350,7 @@ impl Karpenter {
             Some(karpenter_parameters.clone()),
             options.user_provided_network.as_ref(),
             kubernetes.advanced_settings().pleco_resources_ttl,
+            default_node_pool_disruption,
         )
         .to_common_helm_chart()
         .map_err(|el| {
@@ -374,37 +382,42 @@ impl Karpenter {
         cloud_provider: &dyn CloudProvider,
         event_details: &EventDetails,
     ) -> Result<(), Box<EngineError>> {
-        retry::retry(Fixed::from(Duration::from_secs(10)).take(10), || {
-            match kubectl_exec_get_pods(
-                kubernetes.kubeconfig_local_file_path(),
-                Some(KARPENTER_NAMESPACE),
-                Some(KARPENTER_LABEL_SELECTOR),
-                cloud_provider.credentials_environment_variables(),
-            ) {
-                Ok(res) => {
-                    let running_pods_count = res
-                        .items
+        let kube_client = create_kube_client(
+            kubernetes.kubeconfig_local_file_path(),
+            &envs_to_string(cloud_provider.credentials_environment_variables()),
+        )
+        .await
+        .map_err(|err| EngineError::new_cannot_connect_to_k8s_cluster(event_details.clone(), err))?;
+
+        // retried every 10 seconds, 10 times
+        let mut retries = 0;
+        loop {
+            let error = match kube_get_pods(&kube_client, Some(KARPENTER_NAMESPACE), Some(KARPENTER_LABEL_SELECTOR))
+                .await
+            {
+                Ok(pods) => {
+                    let running_pods_count = pods
                         .iter()
-                        .filter(|pod| pod.status.phase == KubernetesPodStatusPhase::Running)
+                        .filter(|pod| pod.status.as_ref().and_then(|status| status.phase.as_deref()) == Some("Running"))
                         .count();
 
                     if running_pods_count == KARPENTER_EXPECTED_POD_COUNT as usize {
-                        OperationResult::Ok(())
-                    } else {
-                        OperationResult::Retry(CommandError::new_from_safe_message(
-                            "Pods didn't restart yet. Waiting...".to_string(),
-                        ))
+                        return Ok(());
                     }
+                    CommandError::new_from_safe_message("Pods didn't restart yet. Waiting...".to_string())
                 }
-                Err(e) => OperationResult::Retry(e),
+                Err(e) => CommandError::from(e),
+            };
+
+            if retries == 10 {
+                return Err(Box::new(EngineError::new_k8s_cannot_get_pods(
+                    event_details.clone(),
+                    CommandError::new_from_safe_message(format!("Error while trying to scale up Karpenter: {error}")),
+                )));
             }
-        })
-        .map_err(|e| {
-            Box::new(EngineError::new_k8s_cannot_get_pods(
-                event_details.clone(),
-                CommandError::new_from_safe_message(format!("Error while trying to scale up Karpenter: {e}")),
-            ))
-        })
+            retries += 1;
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
     }
 }
 
@@ -2,11 +2,13 @@ mod cluster_bootstrap;
 mod cluster_create;
 mod cluster_delete;
 mod cluster_pause;
+mod cluster_repair;
 mod cluster_upgrade;
 mod custom_vpc;
 mod helm_charts;
 mod karpenter;
 mod nodegroup;
+mod nodegroup_scaling;
 mod sdk;
 mod tera_context;
 mod utils;
@@ -17,6 +19,7 @@ use crate::infrastructure::action::eks::cluster_bootstrap::bootstrap_eks_cluster
 use crate::infrastructure::action::eks::cluster_create::create_eks_cluster;
 use crate::infrastructure::action::eks::cluster_delete::delete_eks_cluster;
 use crate::infrastructure::action::eks::cluster_pause::pause_eks_cluster;
+use crate::infrastructure::action::eks::cluster_repair::repair_eks_node_groups;
 use crate::infrastructure::action::eks::cluster_upgrade::upgrade_eks_cluster;
 use crate::infrastructure::action::InfrastructureAction;
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
@@ -82,6 +85,11 @@ impl InfrastructureAction for EKS {
         })
     }
 
+    fn repair_node_groups(&self, infra_ctx: &InfrastructureContext) -> Result<(), Box<EngineError>> {
+        let logger = mk_logger(infra_ctx.kubernetes(), InfrastructureStep::Create);
+        send_progress_on_long_task(self, Action::Create, || repair_eks_node_groups(self, infra_ctx, logger))
+    }
+
     fn upgrade_node_selector(&self) -> Option<&str> {
         // Exclude fargate nodes from the test in case of karpenter, those will be recreated after helm deploy
         match self.is_karpenter_enabled() {
@@ -396,9 +396,12 @@ fn check_failed_nodegroups_to_remove(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::io_models::models::{CpuArchitecture, KubernetesClusterAction, NodeGroups, NodeGroupsWithDesiredState};
+    use crate::io_models::models::{
+        CpuArchitecture, KubernetesClusterAction, NodeGroupCapacityType, NodeGroups, NodeGroupsWithDesiredState,
+    };
     use aws_sdk_eks::operation::describe_nodegroup::DescribeNodegroupOutput;
     use aws_sdk_eks::types::{Nodegroup, NodegroupStatus};
+    use std::collections::BTreeMap;
 
     use super::check_failed_nodegroups_to_remove;
 
@@ -423,6 +426,9 @@ mod tests {
                 instance_type,
                 disk_size_in_gib,
                 instance_architecture: CpuArchitecture::AMD64,
+                labels: BTreeMap::new(),
+                taints: vec![],
+                capacity_type: NodeGroupCapacityType::OnDemand,
             }
         }
     }
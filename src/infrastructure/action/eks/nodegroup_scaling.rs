@@ -0,0 +1,201 @@
+use crate::infrastructure::action::eks::sdk::QoveryAwsSdkConfigEks;
+use crate::infrastructure::action::infra_changes::{
+    classify_changes, load_applied_inputs, AppliedInputs, InfraChanges,
+};
+use crate::infrastructure::action::InfraLogger;
+use crate::infrastructure::models::kubernetes::Kubernetes;
+use crate::io_models::models::NodeGroupsWithDesiredState;
+use crate::runtime::block_on;
+use aws_sdk_eks::operation::describe_nodegroup::DescribeNodegroupOutput;
+use aws_sdk_eks::types::{NodegroupScalingConfig, NodegroupStatus};
+use aws_types::SdkConfig;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Inputs of the eks tera context changing on every deployment, without changing any terraform resource
+pub const EKS_VOLATILE_TERRAFORM_INPUTS: &[&str] = &[
+    "aws_access_key",
+    "aws_secret_key",
+    "aws_session_token",
+    "aws_access_key_tfstates_account",
+    "aws_secret_key_tfstates_account",
+    "eks_upgrade_timeout_in_min",
+    "execution_id",
+];
+const EKS_NODE_GROUPS_INPUT: &str = "eks_worker_nodes";
+const NODE_GROUP_UPDATE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+const NODE_GROUP_UPDATE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Qovery node group name (from the `QoveryNodeGroupName` tag) to the name of the node group on AWS
+fn aws_node_group_names(descriptions: &[DescribeNodegroupOutput]) -> HashMap<String, String> {
+    descriptions
+        .iter()
+        .filter_map(|description| description.nodegroup())
+        .filter_map(|nodegroup| {
+            let qovery_name = nodegroup.tags()?.get("QoveryNodeGroupName")?;
+            Some((qovery_name.clone(), nodegroup.nodegroup_name()?.to_string()))
+        })
+        .collect()
+}
+
+async fn wait_for_node_group_update(
+    aws_conn: &SdkConfig,
+    cluster_name: &str,
+    nodegroup_name: &str,
+) -> Result<(), String> {
+    let started_at = Instant::now();
+    while started_at.elapsed() < NODE_GROUP_UPDATE_TIMEOUT {
+        let status = aws_conn
+            .describe_nodegroup(cluster_name.to_string(), nodegroup_name.to_string())
+            .await
+            .map_err(|err| format!("cannot get node group {nodegroup_name} status: {err}"))?
+            .nodegroup()
+            .and_then(|nodegroup| nodegroup.status().cloned());
+        match status {
+            Some(NodegroupStatus::Active) => return Ok(()),
+            Some(NodegroupStatus::Updating) | None => tokio::time::sleep(NODE_GROUP_UPDATE_POLL_INTERVAL).await,
+            Some(status) => return Err(format!("node group {nodegroup_name} is {status:?} after its update")),
+        }
+    }
+
+    Err(format!(
+        "node group {nodegroup_name} is still updating after {}s",
+        NODE_GROUP_UPDATE_TIMEOUT.as_secs()
+    ))
+}
+
+/// When the only change since the last terraform apply is the sizing of existing node groups, applies it with the EKS api,
+/// which takes seconds instead of a full terraform run. Returns false when terraform must be applied instead,
+/// including when the fast path fails midway: terraform then reconciles whatever has been changed.
+pub fn scale_node_groups_without_terraform(
+    kubernetes: &dyn Kubernetes,
+    kube_client: &kube::Client,
+    aws_conn: &SdkConfig,
+    node_groups: &[NodeGroupsWithDesiredState],
+    current_inputs: &AppliedInputs,
+    logger: &impl InfraLogger,
+) -> bool {
+    let Some(previous_inputs) = load_applied_inputs(kube_client) else {
+        return false;
+    };
+    let changed_node_groups = match classify_changes(&previous_inputs, current_inputs, EKS_NODE_GROUPS_INPUT) {
+        InfraChanges::NodeGroupsSizing { node_groups } => node_groups,
+        InfraChanges::Nothing | InfraChanges::Other { .. } => return false,
+    };
+
+    let cluster_name = kubernetes.cluster_name();
+    let descriptions = match block_on(async {
+        let nodegroups = aws_conn
+            .list_all_eks_nodegroups(cluster_name.clone())
+            .await
+            .map_err(|err| err.to_string())?;
+        aws_conn
+            .describe_nodegroups(cluster_name.clone(), nodegroups)
+            .await
+            .map_err(|err| err.to_string())
+    }) {
+        Ok(descriptions) => descriptions,
+        Err(err) => {
+            logger.warn(format!("Cannot list node groups, applying terraform instead: {err}"));
+            return false;
+        }
+    };
+    let aws_names = aws_node_group_names(&descriptions);
+
+    let mut updated_node_groups = Vec::with_capacity(changed_node_groups.len());
+    for ix in changed_node_groups {
+        let Some(node_group) = node_groups.get(ix) else {
+            return false;
+        };
+        let Some(aws_name) = aws_names.get(&node_group.name) else {
+            logger.info(format!(
+                "Node group {} is not found on AWS, applying terraform instead",
+                node_group.name
+            ));
+            return false;
+        };
+        updated_node_groups.push((node_group, aws_name));
+    }
+
+    for (node_group, aws_name) in updated_node_groups {
+        logger.info(format!(
+            "⚡ Scaling node group {} with the EKS api: min {}, max {}, desired {}",
+            node_group.name, node_group.min_nodes, node_group.max_nodes, node_group.desired_size
+        ));
+        let scaling_config = NodegroupScalingConfig::builder()
+            .min_size(node_group.min_nodes)
+            .max_size(node_group.max_nodes)
+            .desired_size(node_group.desired_size)
+            .build();
+        let updated = block_on(async {
+            aws_conn
+                .update_nodegroup_scaling(cluster_name.clone(), aws_name.clone(), scaling_config)
+                .await
+                .map_err(|err| format!("cannot update node group {aws_name}: {err}"))?;
+            wait_for_node_group_update(aws_conn, &cluster_name, aws_name).await
+        });
+        if let Err(err) = updated {
+            logger.warn(format!("Node group scaling failed, applying terraform instead: {err}"));
+            return false;
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_eks::types::Nodegroup;
+    use serde_json::json;
+
+    #[test]
+    fn test_node_groups_are_matched_by_qovery_name() {
+        let description = |aws_name: &str, qovery_name: Option<&str>| {
+            let mut nodegroup = Nodegroup::builder().nodegroup_name(aws_name);
+            if let Some(qovery_name) = qovery_name {
+                nodegroup = nodegroup.tags("QoveryNodeGroupName", qovery_name);
+            }
+            DescribeNodegroupOutput::builder().nodegroup(nodegroup.build()).build()
+        };
+
+        let names = aws_node_group_names(&[
+            description("qovery-z1234-default-20240101", Some("default")),
+            description("qovery-z1234-gpu-20240101", Some("gpu")),
+            description("created-by-hand", None),
+        ]);
+
+        assert_eq!(
+            names,
+            HashMap::from([
+                ("default".to_string(), "qovery-z1234-default-20240101".to_string()),
+                ("gpu".to_string(), "qovery-z1234-gpu-20240101".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_new_execution_is_not_a_change() {
+        let inputs = |execution_id: &str, session_token: &str| {
+            AppliedInputs::from_json(
+                "templates-v1".to_string(),
+                &json!({
+                    "kubernetes_cluster_name": "qovery-z1234",
+                    "execution_id": execution_id,
+                    "aws_session_token": session_token,
+                    "eks_worker_nodes": [{ "name": "default", "min_nodes": 3, "max_nodes": 10 }],
+                }),
+                EKS_VOLATILE_TERRAFORM_INPUTS,
+            )
+        };
+
+        assert_eq!(
+            classify_changes(
+                &inputs("execution-1", "token-1"),
+                &inputs("execution-2", "token-2"),
+                EKS_NODE_GROUPS_INPUT
+            ),
+            InfraChanges::Nothing
+        );
+    }
+}
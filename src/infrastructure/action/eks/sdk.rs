@@ -1,9 +1,12 @@
 use async_trait::async_trait;
 use aws_sdk_eks::error::SdkError;
 use aws_sdk_eks::operation::delete_nodegroup::{DeleteNodegroupError, DeleteNodegroupOutput};
+use aws_sdk_eks::operation::describe_addon_versions::{DescribeAddonVersionsError, DescribeAddonVersionsOutput};
 use aws_sdk_eks::operation::describe_nodegroup::{DescribeNodegroupError, DescribeNodegroupOutput};
 use aws_sdk_eks::operation::list_clusters::{ListClustersError, ListClustersOutput};
 use aws_sdk_eks::operation::list_nodegroups::{ListNodegroupsError, ListNodegroupsOutput};
+use aws_sdk_eks::operation::update_nodegroup_config::{UpdateNodegroupConfigError, UpdateNodegroupConfigOutput};
+use aws_sdk_eks::types::NodegroupScalingConfig;
 use aws_sdk_iam::operation::create_service_linked_role::{CreateServiceLinkedRoleError, CreateServiceLinkedRoleOutput};
 use aws_sdk_iam::operation::get_role::{GetRoleError, GetRoleOutput};
 use aws_types::SdkConfig;
@@ -30,6 +33,17 @@ pub trait QoveryAwsSdkConfigEks {
         cluster_id: String,
         nodegroup_id: String,
     ) -> Result<DeleteNodegroupOutput, SdkError<DeleteNodegroupError>>;
+    async fn update_nodegroup_scaling(
+        &self,
+        cluster_name: String,
+        nodegroup_name: String,
+        scaling_config: NodegroupScalingConfig,
+    ) -> Result<UpdateNodegroupConfigOutput, SdkError<UpdateNodegroupConfigError>>;
+    async fn describe_addon_versions(
+        &self,
+        addon_name: &str,
+        kubernetes_version: String,
+    ) -> Result<DescribeAddonVersionsOutput, SdkError<DescribeAddonVersionsError>>;
 
     async fn get_role(&self, name: &str) -> Result<GetRoleOutput, SdkError<GetRoleError>>;
 
@@ -102,6 +116,36 @@ impl QoveryAwsSdkConfigEks for SdkConfig {
             .await
     }
 
+    async fn update_nodegroup_scaling(
+        &self,
+        cluster_name: String,
+        nodegroup_name: String,
+        scaling_config: NodegroupScalingConfig,
+    ) -> Result<UpdateNodegroupConfigOutput, SdkError<UpdateNodegroupConfigError>> {
+        let client = aws_sdk_eks::Client::new(self);
+        client
+            .update_nodegroup_config()
+            .cluster_name(cluster_name)
+            .nodegroup_name(nodegroup_name)
+            .scaling_config(scaling_config)
+            .send()
+            .await
+    }
+
+    async fn describe_addon_versions(
+        &self,
+        addon_name: &str,
+        kubernetes_version: String,
+    ) -> Result<DescribeAddonVersionsOutput, SdkError<DescribeAddonVersionsError>> {
+        let client = aws_sdk_eks::Client::new(self);
+        client
+            .describe_addon_versions()
+            .addon_name(addon_name)
+            .kubernetes_version(kubernetes_version)
+            .send()
+            .await
+    }
+
     async fn get_role(&self, name: &str) -> Result<GetRoleOutput, SdkError<GetRoleError>> {
         let client = aws_sdk_iam::Client::new(self);
         client.get_role().role_name(name).send().await
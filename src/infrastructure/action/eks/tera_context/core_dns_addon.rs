@@ -1,9 +1,8 @@
 use crate::infrastructure::models::kubernetes::KubernetesVersion;
-use serde_derive::Serialize;
 
 /// AWS COREDNS addon
 /// https://docs.aws.amazon.com/eks/latest/userguide/managing-coredns.html
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq)]
 pub struct AwsCoreDnsAddon {
     version: String,
 }
@@ -23,6 +22,10 @@ impl AwsCoreDnsAddon {
                 KubernetesVersion::V1_28 { .. } => "v1.10.1-eksbuild.7",
                 KubernetesVersion::V1_29 { .. } => "v1.10.1-eksbuild.7",
                 KubernetesVersion::V1_30 { .. } => "v1.11.3-eksbuild.1",
+                KubernetesVersion::V1_31 { .. } => "v1.11.3-eksbuild.1",
+                KubernetesVersion::V1_32 { .. } => "v1.11.4-eksbuild.2",
+                KubernetesVersion::V1_33 { .. } => "v1.12.1-eksbuild.2",
+                KubernetesVersion::V1_34 { .. } => "v1.12.3-eksbuild.1",
             }
             .to_string(),
         }
@@ -33,6 +36,10 @@ impl AwsCoreDnsAddon {
             version: addon_version.to_string(),
         }
     }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
 }
 
 #[cfg(test)]
@@ -129,6 +136,46 @@ mod tests {
                     version: "v1.11.3-eksbuild.1".to_string(),
                 },
             },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_31 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsCoreDnsAddon {
+                    version: "v1.11.3-eksbuild.1".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_32 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsCoreDnsAddon {
+                    version: "v1.11.4-eksbuild.2".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_33 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsCoreDnsAddon {
+                    version: "v1.12.1-eksbuild.2".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_34 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsCoreDnsAddon {
+                    version: "v1.12.3-eksbuild.1".to_string(),
+                },
+            },
         ];
 
         for tc in tests_cases {
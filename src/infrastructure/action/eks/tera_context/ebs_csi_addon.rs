@@ -1,9 +1,8 @@
 use crate::infrastructure::models::kubernetes::KubernetesVersion;
-use serde_derive::Serialize;
 
 /// AWS EBS CSI addon
 /// https://docs.aws.amazon.com/eks/latest/userguide/managing-ebs-csi.html
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq)]
 pub struct AwsEbsCsiAddon {
     version: String,
 }
@@ -22,6 +21,10 @@ impl AwsEbsCsiAddon {
                 KubernetesVersion::V1_28 { .. } => "v1.27.0-eksbuild.1",
                 KubernetesVersion::V1_29 { .. } => "v1.33.0-eksbuild.1",
                 KubernetesVersion::V1_30 { .. } => "v1.36.0-eksbuild.1",
+                KubernetesVersion::V1_31 { .. } => "v1.37.0-eksbuild.1",
+                KubernetesVersion::V1_32 { .. } => "v1.38.1-eksbuild.2",
+                KubernetesVersion::V1_33 { .. } => "v1.44.0-eksbuild.1",
+                KubernetesVersion::V1_34 { .. } => "v1.48.0-eksbuild.2",
             }
             .to_string(),
         }
@@ -32,6 +35,10 @@ impl AwsEbsCsiAddon {
             version: addon_version.to_string(),
         }
     }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
 }
 
 #[cfg(test)]
@@ -128,6 +135,46 @@ mod tests {
                     version: "v1.36.0-eksbuild.1".to_string(),
                 },
             },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_31 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsEbsCsiAddon {
+                    version: "v1.37.0-eksbuild.1".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_32 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsEbsCsiAddon {
+                    version: "v1.38.1-eksbuild.2".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_33 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsEbsCsiAddon {
+                    version: "v1.44.0-eksbuild.1".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_34 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsEbsCsiAddon {
+                    version: "v1.48.0-eksbuild.2".to_string(),
+                },
+            },
         ];
 
         for tc in tests_cases {
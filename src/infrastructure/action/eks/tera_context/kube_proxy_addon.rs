@@ -1,8 +1,7 @@
 use crate::infrastructure::models::kubernetes::KubernetesVersion;
-use serde_derive::Serialize;
 
 /// AWS Kube proxy addon
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq)]
 pub struct AwsKubeProxyAddon {
     version: String,
 }
@@ -22,6 +21,10 @@ impl AwsKubeProxyAddon {
                 KubernetesVersion::V1_28 { .. } => "v1.28.2-eksbuild.2",
                 KubernetesVersion::V1_29 { .. } => "v1.29.0-eksbuild.1",
                 KubernetesVersion::V1_30 { .. } => "v1.30.3-eksbuild.5",
+                KubernetesVersion::V1_31 { .. } => "v1.31.2-eksbuild.3",
+                KubernetesVersion::V1_32 { .. } => "v1.32.0-eksbuild.2",
+                KubernetesVersion::V1_33 { .. } => "v1.33.0-eksbuild.2",
+                KubernetesVersion::V1_34 { .. } => "v1.34.0-eksbuild.2",
             }
             .to_string(),
         }
@@ -32,6 +35,10 @@ impl AwsKubeProxyAddon {
             version: addon_version.to_string(),
         }
     }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
 }
 
 #[cfg(test)]
@@ -118,6 +125,46 @@ mod tests {
                     version: "v1.29.0-eksbuild.1".to_string(),
                 },
             },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_31 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsKubeProxyAddon {
+                    version: "v1.31.2-eksbuild.3".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_32 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsKubeProxyAddon {
+                    version: "v1.32.0-eksbuild.2".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_33 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsKubeProxyAddon {
+                    version: "v1.33.0-eksbuild.2".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_34 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsKubeProxyAddon {
+                    version: "v1.34.0-eksbuild.2".to_string(),
+                },
+            },
         ];
 
         for tc in tests_cases {
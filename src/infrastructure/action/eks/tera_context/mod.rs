@@ -7,11 +7,13 @@ use crate::infrastructure::models::cloud_provider::io::ClusterAdvancedSettings;
 use crate::infrastructure::models::cloud_provider::CloudProvider;
 use crate::infrastructure::models::dns_provider::DnsProvider;
 use crate::infrastructure::models::kubernetes::aws::Options;
-use crate::infrastructure::models::kubernetes::Kubernetes;
+use crate::infrastructure::models::kubernetes::{Kubernetes, KubernetesAddon, KubernetesVersion};
 use crate::io_models::context::Features;
-use crate::io_models::models::{NodeGroupsWithDesiredState, VpcQoveryNetworkMode};
+use crate::io_models::models::{NodeGroupCapacityType, NodeGroupsWithDesiredState, VpcQoveryNetworkMode};
 use crate::string::terraform_list_format;
 use chrono::Duration as ChronoDuration;
+use serde_json::json;
+use strum::IntoEnumIterator;
 use tera::Context as TeraContext;
 
 mod core_dns_addon;
@@ -32,6 +34,7 @@ pub fn eks_tera_context(
     qovery_allowed_public_access_cidrs: Option<&Vec<String>>,
 ) -> Result<TeraContext, Box<EngineError>> {
     let event_details = kubernetes.get_event_details(Stage::Infrastructure(InfrastructureStep::LoadConfiguration));
+    check_node_groups_capacity_type(event_details.clone(), node_groups)?;
     let mut context = TeraContext::new();
 
     let (public_access_cidrs, endpoint_private_access) =
@@ -213,6 +216,7 @@ pub fn eks_tera_context(
     // Qovery
     context.insert("organization_id", cloud_provider.organization_id());
     context.insert("organization_long_id", &cloud_provider.organization_long_id().to_string());
+    context.insert("execution_id", kubernetes.context().execution_id());
     context.insert("qovery_api_url", &qovery_api_url);
 
     context.insert("test_cluster", &kubernetes.context().is_test_cluster());
@@ -270,9 +274,16 @@ pub fn eks_tera_context(
     // AWS
     context.insert("aws_access_key", &cloud_provider.access_key_id());
     context.insert("aws_secret_key", &cloud_provider.secret_access_key());
+    if let Some(session_token) = cloud_provider.session_token() {
+        context.insert("aws_session_token", &session_token);
+    }
 
     // Karpenter
     context.insert("enable_karpenter", &kubernetes.is_karpenter_enabled());
+    context.insert(
+        "enable_karpenter_interruption_queue",
+        &kubernetes.is_karpenter_interruption_queue_enabled(),
+    );
     context.insert("bootstrap_on_fargate", &bootstrap_on_fargate);
     context.insert("fargate_profile_zone_a_subnet_blocks", &fargate_profile_zone_a_subnet_blocks);
     context.insert("fargate_profile_zone_b_subnet_blocks", &fargate_profile_zone_b_subnet_blocks);
@@ -463,49 +474,73 @@ pub fn eks_tera_context(
         "aws_iam_user_mapper_sso_role_arn",
         &kubernetes.advanced_settings().aws_iam_user_mapper_sso_role_arn,
     );
-
-    // EKS Addons
-    // CNI
-    context.insert(
-        "eks_addon_vpc_cni",
-        &(match &options.aws_addon_cni_version_override {
-            None => vpc_cni_addon::AwsVpcCniAddon::new_from_k8s_version(kubernetes.version()),
-
-            Some(overridden_version) => vpc_cni_addon::AwsVpcCniAddon::new_with_overridden_version(overridden_version),
-        }),
-    );
-    // Kube-proxy
     context.insert(
-        "eks_addon_kube_proxy",
-        &(match &options.aws_addon_kube_proxy_version_override {
-            None => kube_proxy_addon::AwsKubeProxyAddon::new_from_k8s_version(kubernetes.version()),
-            Some(overridden_version) => {
-                kube_proxy_addon::AwsKubeProxyAddon::new_with_overridden_version(overridden_version)
-            }
-        }),
-    );
-    // EBS CSI
-    context.insert(
-        "eks_addon_ebs_csi",
-        &(match &options.aws_addon_ebs_csi_version_override {
-            None => ebs_csi_addon::AwsEbsCsiAddon::new_from_k8s_version(kubernetes.version()),
-            Some(overridden_version) => ebs_csi_addon::AwsEbsCsiAddon::new_with_overridden_version(overridden_version),
-        }),
-    );
-    // COREDNS
-    context.insert(
-        "eks_addon_coredns",
-        &(match &options.aws_addon_coredns_version_override {
-            None => core_dns_addon::AwsCoreDnsAddon::new_from_k8s_version(kubernetes.version()),
-            Some(overridden_version) => {
-                core_dns_addon::AwsCoreDnsAddon::new_with_overridden_version(overridden_version)
-            }
-        }),
+        "registry_mirrors",
+        &kubernetes.advanced_settings().containerd_registry_mirrors(),
     );
 
+    // EKS Addons
+    insert_eks_addons_versions(&mut context, options, &kubernetes.version());
+
     Ok(context)
 }
 
+/// Version of each EKS managed addon, the one overridden in the cluster options or the default one of the kubernetes version
+pub fn eks_addons_versions(options: &Options, k8s_version: &KubernetesVersion) -> Vec<(KubernetesAddon, String)> {
+    KubernetesAddon::iter()
+        .map(|addon| {
+            let version = match addon {
+                KubernetesAddon::Cni => match &options.aws_addon_cni_version_override {
+                    None => vpc_cni_addon::AwsVpcCniAddon::new_from_k8s_version(k8s_version.clone()),
+                    Some(overridden_version) => {
+                        vpc_cni_addon::AwsVpcCniAddon::new_with_overridden_version(overridden_version)
+                    }
+                }
+                .version()
+                .to_string(),
+                KubernetesAddon::KubeProxy => match &options.aws_addon_kube_proxy_version_override {
+                    None => kube_proxy_addon::AwsKubeProxyAddon::new_from_k8s_version(k8s_version.clone()),
+                    Some(overridden_version) => {
+                        kube_proxy_addon::AwsKubeProxyAddon::new_with_overridden_version(overridden_version)
+                    }
+                }
+                .version()
+                .to_string(),
+                KubernetesAddon::EbsCsi => match &options.aws_addon_ebs_csi_version_override {
+                    None => ebs_csi_addon::AwsEbsCsiAddon::new_from_k8s_version(k8s_version.clone()),
+                    Some(overridden_version) => {
+                        ebs_csi_addon::AwsEbsCsiAddon::new_with_overridden_version(overridden_version)
+                    }
+                }
+                .version()
+                .to_string(),
+                KubernetesAddon::CoreDns => match &options.aws_addon_coredns_version_override {
+                    None => core_dns_addon::AwsCoreDnsAddon::new_from_k8s_version(k8s_version.clone()),
+                    Some(overridden_version) => {
+                        core_dns_addon::AwsCoreDnsAddon::new_with_overridden_version(overridden_version)
+                    }
+                }
+                .version()
+                .to_string(),
+            };
+            (addon, version)
+        })
+        .collect()
+}
+
+/// Sets the EKS addons versions of the terraform context for the given kubernetes version
+pub fn insert_eks_addons_versions(context: &mut TeraContext, options: &Options, k8s_version: &KubernetesVersion) {
+    for (addon, version) in eks_addons_versions(options, k8s_version) {
+        let context_key = match addon {
+            KubernetesAddon::Cni => "eks_addon_vpc_cni",
+            KubernetesAddon::KubeProxy => "eks_addon_kube_proxy",
+            KubernetesAddon::EbsCsi => "eks_addon_ebs_csi",
+            KubernetesAddon::CoreDns => "eks_addon_coredns",
+        };
+        context.insert(context_key, &json!({ "version": version }));
+    }
+}
+
 fn generate_public_access_cidrs(
     advanced_settings: &ClusterAdvancedSettings,
     qovery_allowed_public_access_cidrs: Option<&Vec<String>>,
@@ -534,6 +569,26 @@ fn generate_public_access_cidrs(
     (cidrs, endpoint_private_access)
 }
 
+/// managed node groups have a single capacity type, a mixed one would need self managed autoscaling groups
+fn check_node_groups_capacity_type(
+    event_details: EventDetails,
+    node_groups: &[NodeGroupsWithDesiredState],
+) -> Result<(), Box<EngineError>> {
+    match node_groups
+        .iter()
+        .find(|node_group| matches!(node_group.capacity_type, NodeGroupCapacityType::Mixed { .. }))
+    {
+        Some(node_group) => Err(Box::new(EngineError::new_node_group_capacity_type_not_supported(
+            event_details,
+            node_group.name.clone(),
+            node_group.capacity_type.to_string(),
+            "EKS managed node groups are either on-demand or spot, split it into an on-demand and a spot node group"
+                .to_string(),
+        ))),
+        None => Ok(()),
+    }
+}
+
 /// divide by 2 the total number of subnet to get the exact same number as private and public
 fn check_odd_subnets(
     event_details: EventDetails,
@@ -553,8 +608,221 @@ fn check_odd_subnets(
 
 #[cfg(test)]
 mod tests {
-    use super::generate_public_access_cidrs;
+    use super::core_dns_addon::AwsCoreDnsAddon;
+    use super::ebs_csi_addon::AwsEbsCsiAddon;
+    use super::kube_proxy_addon::AwsKubeProxyAddon;
+    use super::vpc_cni_addon::AwsVpcCniAddon;
+    use super::{check_node_groups_capacity_type, generate_public_access_cidrs};
+    use crate::errors::Tag;
+    use crate::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
     use crate::infrastructure::models::cloud_provider::io::ClusterAdvancedSettings;
+    use crate::infrastructure::models::kubernetes::KubernetesVersion;
+    use crate::io_models::models::{
+        CpuArchitecture, NodeGroupCapacityType, NodeGroupsWithDesiredState, Taint, TaintEffect,
+    };
+    use crate::io_models::QoveryIdentifier;
+    use regex::Regex;
+    use std::collections::BTreeMap;
+    use std::env;
+    use strum::IntoEnumIterator;
+    use tera::{Context, Tera};
+    use uuid::Uuid;
+
+    fn node_group(capacity_type: NodeGroupCapacityType) -> NodeGroupsWithDesiredState {
+        NodeGroupsWithDesiredState {
+            name: "gpu".to_string(),
+            id: None,
+            min_nodes: 1,
+            max_nodes: 3,
+            desired_size: 1,
+            enable_desired_size: false,
+            instance_type: "g4dn.xlarge".to_string(),
+            disk_size_in_gib: 50,
+            instance_architecture: CpuArchitecture::AMD64,
+            labels: BTreeMap::new(),
+            taints: vec![],
+            capacity_type,
+        }
+    }
+
+    fn rendered_eks_workers_nodes(node_group: &NodeGroupsWithDesiredState) -> String {
+        let mut context = Context::new();
+        context.insert("eks_worker_nodes", &vec![node_group]);
+        context.insert("user_provided_network", &false);
+        context.insert("eks_upgrade_timeout_in_min", &60);
+
+        let template = std::fs::read_to_string(format!(
+            "{}/lib/aws/bootstrap/terraform/eks-workers-nodes.j2.tf",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .to_str()
+                .expect("Impossible to convert current directory to string"),
+        ))
+        .unwrap();
+        Tera::one_off(&template, &context, false).unwrap()
+    }
+
+    #[test]
+    fn test_eks_workers_nodes_labels_and_taints_rendering() {
+        let mut node_group = node_group(NodeGroupCapacityType::OnDemand);
+
+        // without labels nor taints, nothing is rendered
+        let rendered = rendered_eks_workers_nodes(&node_group);
+        assert!(!rendered.contains("labels = {"));
+        assert!(!rendered.contains("taint {"));
+
+        node_group.labels = BTreeMap::from([
+            ("qovery.com/workload".to_string(), "gpu".to_string()),
+            ("team".to_string(), "ml".to_string()),
+        ]);
+        node_group.taints = vec![
+            Taint {
+                key: "nvidia.com/gpu".to_string(),
+                value: "true".to_string(),
+                effect: TaintEffect::NoSchedule,
+            },
+            Taint {
+                key: "dedicated".to_string(),
+                value: "".to_string(),
+                effect: TaintEffect::PreferNoSchedule,
+            },
+            Taint {
+                key: "spot".to_string(),
+                value: "true".to_string(),
+                effect: TaintEffect::NoExecute,
+            },
+        ];
+        let rendered = rendered_eks_workers_nodes(&node_group);
+
+        assert!(rendered.contains(
+            r#"
+  labels = {
+    "qovery.com/workload" = "gpu"
+    "team" = "ml"
+  }"#
+        ));
+        assert!(rendered.contains(
+            r#"
+  taint {
+    key    = "nvidia.com/gpu"
+    value  = "true"
+    effect = "NO_SCHEDULE"
+  }"#
+        ));
+        assert!(rendered.contains(
+            r#"
+  taint {
+    key    = "dedicated"
+    effect = "PREFER_NO_SCHEDULE"
+  }"#
+        ));
+        assert!(rendered.contains(
+            r#"
+  taint {
+    key    = "spot"
+    value  = "true"
+    effect = "NO_EXECUTE"
+  }"#
+        ));
+    }
+
+    fn rendered_karpenter_terraform(template_name: &str, enable_karpenter_interruption_queue: bool) -> String {
+        let mut context = Context::new();
+        context.insert("enable_karpenter", &true);
+        context.insert("enable_karpenter_interruption_queue", &enable_karpenter_interruption_queue);
+
+        let template = std::fs::read_to_string(format!(
+            "{}/lib/aws/bootstrap/terraform/{template_name}",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .to_str()
+                .expect("Impossible to convert current directory to string"),
+        ))
+        .unwrap();
+        Tera::one_off(&template, &context, false).unwrap()
+    }
+
+    #[test]
+    fn test_eks_addons_default_versions() {
+        let addon_version_regex = Regex::new(r"^v(\d+)\.(\d+)\.\d+-eksbuild\.\d+$").unwrap();
+
+        // every kubernetes version needs a default version of each addon, bumped on cluster upgrade
+        for k8s_version in KubernetesVersion::iter() {
+            let kube_proxy = AwsKubeProxyAddon::new_from_k8s_version(k8s_version.clone());
+            for addon_version in [
+                AwsVpcCniAddon::new_from_k8s_version(k8s_version.clone()).version(),
+                AwsEbsCsiAddon::new_from_k8s_version(k8s_version.clone()).version(),
+                AwsCoreDnsAddon::new_from_k8s_version(k8s_version.clone()).version(),
+                kube_proxy.version(),
+            ] {
+                assert!(addon_version_regex.is_match(addon_version), "{k8s_version}: {addon_version}");
+            }
+
+            // kube-proxy has to match the minor version of the control plane
+            let captures = addon_version_regex.captures(kube_proxy.version()).unwrap();
+            assert_eq!(&captures[1], k8s_version.major().to_string(), "{k8s_version}");
+            assert_eq!(&captures[2], k8s_version.minor().to_string(), "{k8s_version}");
+        }
+    }
+
+    #[test]
+    fn test_karpenter_interruption_queue_rendering() {
+        // spot node pools: queue, event rules and controller permissions
+        let rendered = rendered_karpenter_terraform("eks-sqs-queue.j2.tf", true);
+        assert!(rendered.contains(r#"resource "aws_sqs_queue" "qovery-eks-queue""#));
+        assert!(rendered.contains(r#"resource "aws_cloudwatch_event_target" "qovery_cloudwatch_event_target""#));
+        for detail_type in [
+            "AWS Health Event",
+            "EC2 Spot Instance Interruption Warning",
+            "EC2 Instance Rebalance Recommendation",
+            "EC2 Instance State-change Notification",
+        ] {
+            assert!(rendered.contains(detail_type), "{detail_type}");
+        }
+        assert!(rendered_karpenter_terraform("eks-karpenter-iam.j2.tf", true).contains("AllowInterruptionQueueActions"));
+
+        // on-demand only node pools: nothing is created
+        assert!(rendered_karpenter_terraform("eks-sqs-queue.j2.tf", false)
+            .trim()
+            .is_empty());
+        assert!(!rendered_karpenter_terraform("eks-karpenter-iam.j2.tf", false).contains("sqs:"));
+    }
+
+    #[test]
+    fn test_eks_workers_nodes_capacity_type_rendering() {
+        assert!(rendered_eks_workers_nodes(&node_group(NodeGroupCapacityType::OnDemand))
+            .contains(r#"capacity_type    = "ON_DEMAND""#));
+        assert!(rendered_eks_workers_nodes(&node_group(NodeGroupCapacityType::Spot))
+            .contains(r#"capacity_type    = "SPOT""#));
+    }
+
+    #[test]
+    fn test_check_node_groups_capacity_type() {
+        let event_details = EventDetails::new(
+            None,
+            QoveryIdentifier::default(),
+            QoveryIdentifier::default(),
+            "".to_string(),
+            Stage::Infrastructure(InfrastructureStep::LoadConfiguration),
+            Transmitter::Kubernetes(Uuid::new_v4(), "".to_string()),
+        );
+
+        assert!(check_node_groups_capacity_type(
+            event_details.clone(),
+            &[
+                node_group(NodeGroupCapacityType::OnDemand),
+                node_group(NodeGroupCapacityType::Spot)
+            ]
+        )
+        .is_ok());
+
+        let mixed = NodeGroupCapacityType::Mixed {
+            on_demand_base: 1,
+            spot_percentage: 50,
+        };
+        let err = check_node_groups_capacity_type(event_details, &[node_group(mixed)]).unwrap_err();
+        assert_eq!(err.tag(), &Tag::NotImplementedError);
+    }
 
     #[test]
     fn test_public_access_cidrs_with_any_parameters_set() {
@@ -1,9 +1,8 @@
 use crate::infrastructure::models::kubernetes::KubernetesVersion;
-use serde_derive::Serialize;
 
 /// AWS VPC CNI addon
 /// https://docs.aws.amazon.com/fr_fr/eks/latest/userguide/managing-vpc-cni.html
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq)]
 pub struct AwsVpcCniAddon {
     version: String,
 }
@@ -23,6 +22,10 @@ impl AwsVpcCniAddon {
                 KubernetesVersion::V1_28 { .. } => "v1.18.0-eksbuild.1",
                 KubernetesVersion::V1_29 { .. } => "v1.18.3-eksbuild.2",
                 KubernetesVersion::V1_30 { .. } => "v1.18.3-eksbuild.2",
+                KubernetesVersion::V1_31 { .. } => "v1.19.0-eksbuild.1",
+                KubernetesVersion::V1_32 { .. } => "v1.19.2-eksbuild.1",
+                KubernetesVersion::V1_33 { .. } => "v1.19.5-eksbuild.1",
+                KubernetesVersion::V1_34 { .. } => "v1.20.1-eksbuild.3",
             }
             .to_string(),
         }
@@ -33,6 +36,10 @@ impl AwsVpcCniAddon {
             version: addon_version.to_string(),
         }
     }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
 }
 
 #[cfg(test)]
@@ -129,6 +136,46 @@ mod tests {
                     version: "v1.18.3-eksbuild.2".to_string(),
                 },
             },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_31 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsVpcCniAddon {
+                    version: "v1.19.0-eksbuild.1".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_32 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsVpcCniAddon {
+                    version: "v1.19.2-eksbuild.1".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_33 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsVpcCniAddon {
+                    version: "v1.19.5-eksbuild.1".to_string(),
+                },
+            },
+            TestCase {
+                k8s_version: KubernetesVersion::V1_34 {
+                    prefix: None,
+                    patch: None,
+                    suffix: None,
+                },
+                expected: AwsVpcCniAddon {
+                    version: "v1.20.1-eksbuild.3".to_string(),
+                },
+            },
         ];
 
         for tc in tests_cases {
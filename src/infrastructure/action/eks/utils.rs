@@ -30,8 +30,12 @@ pub fn get_rusoto_eks_client(
         }
     };
 
-    let credentials =
-        StaticProvider::new(cloud_provider.access_key_id(), cloud_provider.secret_access_key(), None, None);
+    let credentials = StaticProvider::new(
+        cloud_provider.access_key_id(),
+        cloud_provider.secret_access_key(),
+        cloud_provider.session_token(),
+        None,
+    );
 
     let client = Client::new_with(credentials, HttpClient::new().expect("unable to create new Http client"));
     Ok(EksClient::new_with_client(client, region))
@@ -1,12 +1,14 @@
 use crate::errors::EngineError;
 use crate::events::Stage::Infrastructure;
 use crate::events::{EventDetails, InfrastructureStep};
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::deploy_helms::{HelmInfraContext, HelmInfraResources};
 use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
 use crate::infrastructure::action::gke::helm_charts::GkeHelmsDeployment;
 use crate::infrastructure::action::gke::GkeQoveryTerraformOutput;
 use crate::infrastructure::action::kubeconfig_helper::update_kubeconfig_file;
 use crate::infrastructure::action::kubectl_utils::check_workers_on_create;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::{InfraLogger, ToInfraTeraContext};
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::gcp::Gke;
@@ -34,10 +36,13 @@ pub(super) fn create_gke_cluster(
     let tf_resources = TerraformInfraResources::new(
         tera_context.clone(),
         cluster.template_directory.join("terraform"),
-        cluster.temp_dir.join("terraform"),
+        cluster.workspace.directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         cluster.context().is_dry_run_deploy(),
+        cluster.advanced_settings.terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(cluster),
+        infra_ctx.abort(),
     );
     let qovery_terraform_output: GkeQoveryTerraformOutput = tf_resources.create(&logger)?;
     update_kubeconfig_file(cluster, &qovery_terraform_output.kubeconfig)?;
@@ -46,8 +51,12 @@ pub(super) fn create_gke_cluster(
     let _ = cluster.configure_gcloud_for_cluster(infra_ctx); // TODO(ENG-1802): properly handle this error
 
     // Ensure all nodes are ready on Kubernetes
-    check_workers_on_create(cluster, infra_ctx.cloud_provider(), None)
-        .map_err(|e| Box::new(EngineError::new_k8s_node_not_ready(event_details.clone(), e)))?;
+    check_workers_on_create(cluster, infra_ctx.cloud_provider(), None, &logger).map_err(|e| {
+        Box::new(match cluster.options.control_plane_access.enable_private_endpoint {
+            true => EngineError::new_k8s_cannot_reach_private_api(event_details.clone(), e),
+            false => EngineError::new_k8s_node_not_ready(event_details.clone(), e),
+        })
+    })?;
     logger.info("Kubernetes nodes have been successfully created");
 
     let helms_deployments = GkeHelmsDeployment::new(
@@ -55,10 +64,11 @@ pub(super) fn create_gke_cluster(
             tera_context,
             PathBuf::from(infra_ctx.context().lib_root_dir()),
             cluster.template_directory.clone(),
-            cluster.temp_dir().join("helms"),
+            cluster.workspace().directory(WorkspaceDirectory::Chart),
             event_details.clone(),
             vec![],
             cluster.context().is_dry_run_deploy(),
+            infra_ctx.abort(),
         ),
         qovery_terraform_output,
         cluster,
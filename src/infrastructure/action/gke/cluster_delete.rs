@@ -1,10 +1,12 @@
 use crate::errors::EngineError;
 use crate::events::Stage::Infrastructure;
 use crate::events::{EventMessage, InfrastructureStep};
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::delete_kube_apps::delete_kube_apps;
 use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
 use crate::infrastructure::action::gke::GkeQoveryTerraformOutput;
 use crate::infrastructure::action::kubeconfig_helper::update_kubeconfig_file;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::{InfraLogger, ToInfraTeraContext};
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::gcp::Gke;
@@ -21,7 +23,7 @@ pub(super) fn delete_gke_cluster(
     let event_details = cluster.get_event_details(Infrastructure(InfrastructureStep::Delete));
 
     logger.info("Preparing to delete cluster.");
-    let temp_dir = cluster.temp_dir();
+    let workspace = cluster.workspace();
 
     // should apply before destroy to be sure destroy will compute on all resources
     // don't exit on failure, it can happen if we resume a destroy process
@@ -36,10 +38,13 @@ pub(super) fn delete_gke_cluster(
     let tf_resources = TerraformInfraResources::new(
         tera_context.clone(),
         cluster.template_directory.join("terraform"),
-        temp_dir.join("terraform"),
+        workspace.directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         cluster.context().is_dry_run_deploy(),
+        cluster.advanced_settings.terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(cluster),
+        infra_ctx.abort(),
     );
     let qovery_terraform_output: GkeQoveryTerraformOutput = tf_resources.create(&logger)?;
     update_kubeconfig_file(cluster, &qovery_terraform_output.kubeconfig)?;
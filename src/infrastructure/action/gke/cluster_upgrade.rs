@@ -1,6 +1,7 @@
 use crate::errors::EngineError;
 use crate::events::InfrastructureStep;
 use crate::events::Stage::Infrastructure;
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::kubectl_utils::check_control_plane_on_upgrade;
 use crate::infrastructure::models::kubernetes::{Kubernetes, KubernetesUpgradeStatus, KubernetesVersion};
 
@@ -9,6 +10,7 @@ use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::action::delete_kube_apps::prepare_kube_upgrade;
 use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
 use crate::infrastructure::action::gke::GkeQoveryTerraformOutput;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::{InfraLogger, ToInfraTeraContext};
 use crate::infrastructure::models::kubernetes::gcp::Gke;
 use crate::utilities::envs_to_string;
@@ -23,7 +25,7 @@ pub(super) fn upgrade_gke_cluster(
     let event_details = cluster.get_event_details(Infrastructure(InfrastructureStep::Upgrade));
     logger.info("Start preparing GKE cluster upgrade process");
 
-    let temp_dir = cluster.temp_dir();
+    let workspace = cluster.workspace();
     logger.info("Upgrading GKE cluster.");
 
     //
@@ -55,20 +57,26 @@ pub(super) fn upgrade_gke_cluster(
     let tf_resources = TerraformInfraResources::new(
         tera_context,
         cluster.template_directory.join("terraform"),
-        temp_dir.join("terraform"),
+        workspace.directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         cluster.context().is_dry_run_deploy(),
+        cluster.advanced_settings.terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(cluster),
+        infra_ctx.abort(),
     );
 
     let _tf_output: GkeQoveryTerraformOutput = tf_resources.create(&logger)?;
 
     check_control_plane_on_upgrade(cluster, infra_ctx.cloud_provider(), kubernetes_version).map_err(|e| {
-        Box::new(EngineError::new_k8s_node_not_ready_with_requested_version(
-            event_details,
-            kubernetes_upgrade_status.requested_version.to_string(),
-            e,
-        ))
+        Box::new(match cluster.options.control_plane_access.enable_private_endpoint {
+            true => EngineError::new_k8s_cannot_reach_private_api(event_details, e),
+            false => EngineError::new_k8s_node_not_ready_with_requested_version(
+                event_details,
+                kubernetes_upgrade_status.requested_version.to_string(),
+                e,
+            ),
+        })
     })?;
 
     logger.info("Kubernetes control plane has been successfully upgraded.");
@@ -87,6 +87,20 @@ pub(super) fn gke_helm_charts(
     )
     .to_common_helm_chart()?;
 
+    let services_logging = chart_config_prerequisites
+        .cluster_advanced_settings
+        .logging_services
+        .iter()
+        .map(|service_logging| {
+            service_logging.to_model(
+                chart_config_prerequisites
+                    .cluster_advanced_settings
+                    .loki_log_retention_in_week
+                    * 7,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
     // Metrics server is built-in GCP cluster, no need to manage it
     // VPA is built-in GCP cluster, no need to manage it
     let loki: Option<Box<dyn HelmChart>> = match chart_config_prerequisites.ff_log_history_enabled {
@@ -117,6 +131,7 @@ pub(super) fn gke_helm_charts(
                 }),
                 HelmChartTimeout::Custom(Duration::seconds(1200)), // GCP might have a lag in role / authorizations to be working in case you just assigned them, so just allow Loki to wait a bit before failing
                 false,
+                services_logging.clone(),
             )
             .to_common_helm_chart()?,
         )),
@@ -134,6 +149,7 @@ pub(super) fn gke_helm_charts(
                 HelmChartNamespaces::Qovery,
                 PriorityClass::Qovery(QoveryPriorityClass::HighPriority),
                 false,
+                services_logging.clone(),
             )
             .to_common_helm_chart()?,
         )),
@@ -300,6 +316,12 @@ pub(super) fn gke_helm_charts(
             .nginx_controller_http_snippet
             .as_ref()
             .map(|nginx_controller_http_snippet_io| nginx_controller_http_snippet_io.to_model()),
+        chart_config_prerequisites
+            .cluster_advanced_settings
+            .nginx_custom_error_pages
+            .as_ref()
+            .map(|nginx_custom_error_pages_io| nginx_custom_error_pages_io.to_model())
+            .transpose()?,
     )
     .to_common_helm_chart()?;
 
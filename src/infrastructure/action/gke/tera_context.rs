@@ -9,6 +9,7 @@ use crate::infrastructure::models::kubernetes::Kubernetes;
 use crate::io_models::context::Features;
 use crate::io_models::models::VpcQoveryNetworkMode;
 use crate::string::terraform_list_format;
+use serde_json::json;
 use tera::Context as TeraContext;
 use time::format_description;
 
@@ -27,6 +28,7 @@ fn gke_tera_context(cluster: &Gke, infra_ctx: &InfrastructureContext) -> Result<
         "organization_long_id",
         &infra_ctx.cloud_provider().organization_long_id().to_string(),
     );
+    context.insert("execution_id", infra_ctx.context().execution_id());
     context.insert("object_storage_kubeconfig_bucket", &cluster.kubeconfig_bucket_name());
     context.insert("object_storage_logs_bucket", &cluster.logs_bucket_name());
     // Qovery features
@@ -54,61 +56,13 @@ fn gke_tera_context(cluster: &Gke, infra_ctx: &InfrastructureContext) -> Result<
     context.insert("qovery_api_url", cluster.options.qovery_api_url.as_str());
 
     // GCP
-    // credentials
-    context.insert(
-        "gcp_json_credentials_raw",
-        &cluster.options.gcp_json_credentials.r#type.to_string(),
-    );
-    context.insert(
-        "gcp_json_credentials_type",
-        &cluster.options.gcp_json_credentials.r#type.to_string(),
-    );
-    context.insert(
-        "gcp_json_credentials_private_key_id",
-        &cluster.options.gcp_json_credentials.private_key_id.to_string(),
-    );
-    context.insert(
-        "gcp_json_credentials_private_key",
-        &cluster
-            .options
-            .gcp_json_credentials
-            .private_key
-            .as_str()
-            .escape_default() // escape new lines to have \n instead
-            .to_string(),
-    );
+    // credentials of the cloud provider, the impersonated ones when a service account is impersonated,
+    // so the key of the source service account is not written to the workspace
+    context.insert("gcp_json_credentials_raw", &infra_ctx.cloud_provider().secret_access_key());
     context.insert(
         "gcp_json_credentials_client_email",
         &cluster.options.gcp_json_credentials.client_email.to_string(),
     );
-    context.insert(
-        "gcp_json_credentials_client_id",
-        &cluster.options.gcp_json_credentials.client_id.to_string(),
-    );
-    context.insert(
-        "gcp_json_credentials_auth_uri",
-        cluster.options.gcp_json_credentials.auth_uri.as_str(),
-    );
-    context.insert(
-        "gcp_json_credentials_token_uri",
-        cluster.options.gcp_json_credentials.token_uri.as_str(),
-    );
-    context.insert(
-        "gcp_json_credentials_auth_provider_x509_cert_url",
-        cluster
-            .options
-            .gcp_json_credentials
-            .auth_provider_x509_cert_url
-            .as_str(),
-    );
-    context.insert(
-        "gcp_json_credentials_client_x509_cert_url",
-        cluster.options.gcp_json_credentials.client_x509_cert_url.as_str(),
-    );
-    context.insert(
-        "gcp_json_credentials_universe_domain",
-        &cluster.options.gcp_json_credentials.universe_domain.to_string(),
-    );
     context.insert("gcp_project_id", cluster.options.gcp_json_credentials.project_id.as_str());
     context.insert("gcp_region", &cluster.region.to_cloud_provider_format());
     context.insert(
@@ -157,6 +111,25 @@ fn gke_tera_context(cluster: &Gke, infra_ctx: &InfrastructureContext) -> Result<
         }
     }
 
+    // Control plane
+    let control_plane_access = &cluster.options.control_plane_access;
+    context.insert("enable_private_endpoint", &control_plane_access.enable_private_endpoint);
+    context.insert(
+        "master_ipv4_cidr_block",
+        &control_plane_access
+            .master_ipv4_cidr_block
+            .map(|net| net.to_string())
+            .unwrap_or_default(),
+    );
+    context.insert(
+        "master_authorized_networks",
+        &control_plane_access
+            .master_authorized_networks
+            .iter()
+            .map(|network| json!({"cidr_block": network.cidr_block.to_string(), "display_name": network.display_name}))
+            .collect::<Vec<_>>(),
+    );
+
     match &cluster.options.vpc_mode {
         VpcMode::Automatic {
             custom_cluster_ipv4_cidr_block,
@@ -339,3 +312,117 @@ fn gke_tera_context(cluster: &Gke, infra_ctx: &InfrastructureContext) -> Result<
 
     Ok(context)
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::env;
+    use tera::{Context, Tera};
+
+    fn rendered_gke_terraform(template_name: &str, context: &Context) -> String {
+        let template = std::fs::read_to_string(format!(
+            "{}/lib/gcp/bootstrap/terraform/{template_name}",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .to_str()
+                .expect("Impossible to convert current directory to string"),
+        ))
+        .unwrap();
+        Tera::one_off(&template, context, false).unwrap()
+    }
+
+    fn gke_terraform_context(cluster_is_private: bool) -> Context {
+        let mut context = Context::new();
+        for key in [
+            "organization_id",
+            "organization_long_id",
+            "object_storage_kubeconfig_bucket",
+            "object_storage_logs_bucket",
+            "kubernetes_cluster_long_id",
+            "kubernetes_cluster_id",
+            "kubernetes_cluster_name",
+            "kubernetes_cluster_version",
+            "gcp_json_credentials_client_email",
+            "gcp_project_id",
+            "gcp_region",
+            "cluster_maintenance_start_time",
+            "cluster_maintenance_end_time",
+            "vpc_name",
+            "vpc_network_mode",
+            "subnetwork",
+            "network_project_id",
+            "cluster_ipv4_cidr_block",
+            "services_ipv4_cidr_block",
+            "ip_range_pods",
+            "ip_range_services",
+        ] {
+            context.insert(key, "");
+        }
+        context.insert("resource_expiration_in_seconds", &-1);
+        context.insert("gcp_zones", &vec!["europe-west9-a"]);
+        context.insert("additional_ip_range_pods", &Vec::<String>::new());
+        context.insert("vpc_use_existing", &false);
+        context.insert("vpc_enable_flow_logs", &false);
+        context.insert("vpc_flow_logs_sampling", &0.0);
+        context.insert("cluster_is_private", &cluster_is_private);
+        context.insert("enable_private_endpoint", &false);
+        context.insert("master_ipv4_cidr_block", "");
+        context.insert("master_authorized_networks", &Vec::<serde_json::Value>::new());
+        context
+    }
+
+    #[test]
+    fn test_gke_private_endpoint_rendering() {
+        // public cluster: nothing private
+        let rendered = rendered_gke_terraform("cluster.j2.tf", &gke_terraform_context(false));
+        assert!(rendered.contains("enable_private_endpoint = false"));
+        assert!(rendered.contains("enable_private_nodes    = false"));
+        assert!(!rendered.contains("master_ipv4_cidr_block"));
+
+        // private nodes, public endpoint
+        let rendered = rendered_gke_terraform("cluster.j2.tf", &gke_terraform_context(true));
+        assert!(rendered.contains("enable_private_endpoint = false"));
+        assert!(rendered.contains("enable_private_nodes    = true"));
+        assert!(!rendered.contains("master_ipv4_cidr_block"));
+
+        // private nodes, private endpoint with master range
+        let mut context = gke_terraform_context(true);
+        context.insert("enable_private_endpoint", &true);
+        context.insert("master_ipv4_cidr_block", "172.16.0.16/28");
+        let rendered = rendered_gke_terraform("cluster.j2.tf", &context);
+        assert!(rendered.contains("enable_private_endpoint = true"));
+        assert!(rendered.contains("enable_private_nodes    = true"));
+        assert!(rendered.contains(r#"master_ipv4_cidr_block  = "172.16.0.16/28""#));
+    }
+
+    #[test]
+    fn test_gke_master_authorized_networks_rendering() {
+        // no authorized networks
+        let rendered = rendered_gke_terraform("tf-default-vars.j2.tf", &gke_terraform_context(false));
+        assert!(rendered.contains(
+            r#"variable "master_authorized_networks" {
+  type        = list(object({ cidr_block = string, display_name = string }))
+  description = "List of master authorized networks. If none are provided, disallow external access (except the cluster node IPs, which GKE automatically whitelists)."
+  default = [
+  ]
+}"#
+        ));
+
+        // authorized networks
+        let mut context = gke_terraform_context(false);
+        context.insert(
+            "master_authorized_networks",
+            &vec![
+                json!({"cidr_block": "203.0.113.0/24", "display_name": "office"}),
+                json!({"cidr_block": "198.51.100.7/32", "display_name": ""}),
+            ],
+        );
+        let rendered = rendered_gke_terraform("tf-default-vars.j2.tf", &context);
+        assert!(rendered.contains(
+            r#"  default = [
+    { cidr_block = "203.0.113.0/24", display_name = "office" },
+    { cidr_block = "198.51.100.7/32", display_name = "" },
+  ]"#
+        ));
+    }
+}
@@ -0,0 +1,460 @@
+// On a fresh cluster, every node pulls the system images (ingress, loki, agents, ...) only when the charts start their
+// pods, one batch of charts after the other. A daemonset referencing all of them, created as soon as the nodes are
+// ready, makes the nodes pull in parallel with the chart deployments. It is removed once the bootstrap is done.
+
+use crate::cmd::helm::Helm;
+use crate::helm::ChartInfo;
+use crate::infrastructure::action::InfraLogger;
+use crate::infrastructure::infrastructure_context::InfrastructureContext;
+use crate::infrastructure::models::kubernetes::cluster_health::node_readiness;
+use crate::metrics_registry::{MetricsRegistry, StepLabel, StepName, StepStatus};
+use crate::runtime::block_on;
+use k8s_openapi::api::apps::v1::{DaemonSet, DaemonSetSpec, Deployment, StatefulSet};
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::{
+    Container, EmptyDirVolumeSource, Node, Pod, PodSpec, PodTemplateSpec, ResourceRequirements, Toleration, Volume,
+    VolumeMount,
+};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
+use kube::api::{DeleteParams, ListParams, Patch, PatchParams};
+use kube::Api;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const IMAGE_PREPULL_NAME: &str = "qovery-image-prepull";
+// exists from the start on every distribution, unlike the namespaces created by the charts
+const IMAGE_PREPULL_NAMESPACE: &str = "kube-system";
+// statically linked, so its `true` can be run from any image, even distroless ones
+const IMAGE_PREPULL_HELPER_IMAGE: &str = "public.ecr.aws/docker/library/busybox:1.36";
+const IMAGE_PREPULL_PAUSE_IMAGE: &str = "registry.k8s.io/pause:3.9";
+const IMAGE_PREPULL_BIN_DIR: &str = "/prepull";
+const IMAGE_PREPULL_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const IMAGE_PREPULL_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+
+/// Images referenced by the workloads of rendered manifests, init containers included.
+pub fn images_from_rendered_manifests(manifests: &str) -> Result<BTreeSet<String>, serde_yaml::Error> {
+    let mut images = BTreeSet::new();
+    for document in serde_yaml::Deserializer::from_str(manifests) {
+        let value = serde_yaml::Value::deserialize(document)?;
+        let kind = value
+            .get("kind")
+            .and_then(|k| k.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let pod_spec = match kind.as_str() {
+            "Pod" => serde_yaml::from_value::<Pod>(value)?.spec,
+            "Deployment" => serde_yaml::from_value::<Deployment>(value)?
+                .spec
+                .and_then(|s| s.template.spec),
+            "StatefulSet" => serde_yaml::from_value::<StatefulSet>(value)?
+                .spec
+                .and_then(|s| s.template.spec),
+            "DaemonSet" => serde_yaml::from_value::<DaemonSet>(value)?
+                .spec
+                .and_then(|s| s.template.spec),
+            "Job" => serde_yaml::from_value::<Job>(value)?.spec.and_then(|s| s.template.spec),
+            "CronJob" => serde_yaml::from_value::<CronJob>(value)?
+                .spec
+                .and_then(|s| s.job_template.spec)
+                .and_then(|s| s.template.spec),
+            _ => continue,
+        };
+
+        let Some(pod_spec) = pod_spec else {
+            continue;
+        };
+        images.extend(
+            pod_spec
+                .init_containers
+                .unwrap_or_default()
+                .into_iter()
+                .chain(pod_spec.containers)
+                .filter_map(|container| container.image)
+                .filter(|image| !image.is_empty()),
+        );
+    }
+
+    Ok(images)
+}
+
+/// Below `min_nodes`, pulling the images when the pods start costs about the same as pre-pulling them.
+pub fn should_prepull_images(ready_nodes: usize, min_nodes: u32) -> bool {
+    ready_nodes > 0 && ready_nodes >= min_nodes as usize
+}
+
+/// One init container per image, each of them exiting right away once its image is on the node.
+/// The pods become ready when every image has been pulled, which is what the rollout status measures.
+pub fn image_prepull_daemonset(images: &BTreeSet<String>) -> DaemonSet {
+    let labels = BTreeMap::from([("app".to_string(), IMAGE_PREPULL_NAME.to_string())]);
+    let small_resources = ResourceRequirements {
+        requests: Some(BTreeMap::from([
+            ("cpu".to_string(), Quantity("1m".to_string())),
+            ("memory".to_string(), Quantity("8Mi".to_string())),
+        ])),
+        limits: Some(BTreeMap::from([
+            ("cpu".to_string(), Quantity("50m".to_string())),
+            ("memory".to_string(), Quantity("32Mi".to_string())),
+        ])),
+        ..Default::default()
+    };
+    let bin_mount = VolumeMount {
+        name: "bin".to_string(),
+        mount_path: IMAGE_PREPULL_BIN_DIR.to_string(),
+        ..Default::default()
+    };
+
+    let copy_true = Container {
+        name: "copy-true".to_string(),
+        image: Some(IMAGE_PREPULL_HELPER_IMAGE.to_string()),
+        command: Some(vec![
+            "cp".to_string(),
+            "/bin/true".to_string(),
+            format!("{IMAGE_PREPULL_BIN_DIR}/true"),
+        ]),
+        resources: Some(small_resources.clone()),
+        volume_mounts: Some(vec![bin_mount.clone()]),
+        ..Default::default()
+    };
+    let pulls = images.iter().enumerate().map(|(ix, image)| Container {
+        name: format!("pull-{ix}"),
+        image: Some(image.clone()),
+        image_pull_policy: Some("IfNotPresent".to_string()),
+        command: Some(vec![format!("{IMAGE_PREPULL_BIN_DIR}/true")]),
+        resources: Some(small_resources.clone()),
+        volume_mounts: Some(vec![bin_mount.clone()]),
+        ..Default::default()
+    });
+
+    DaemonSet {
+        metadata: ObjectMeta {
+            name: Some(IMAGE_PREPULL_NAME.to_string()),
+            namespace: Some(IMAGE_PREPULL_NAMESPACE.to_string()),
+            labels: Some(labels.clone()),
+            ..Default::default()
+        },
+        spec: Some(DaemonSetSpec {
+            selector: LabelSelector {
+                match_labels: Some(labels.clone()),
+                ..Default::default()
+            },
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels),
+                    ..Default::default()
+                }),
+                spec: Some(PodSpec {
+                    init_containers: Some(std::iter::once(copy_true).chain(pulls).collect()),
+                    containers: vec![Container {
+                        name: "pause".to_string(),
+                        image: Some(IMAGE_PREPULL_PAUSE_IMAGE.to_string()),
+                        resources: Some(small_resources),
+                        ..Default::default()
+                    }],
+                    volumes: Some(vec![Volume {
+                        name: "bin".to_string(),
+                        empty_dir: Some(EmptyDirVolumeSource::default()),
+                        ..Default::default()
+                    }]),
+                    // every node has to pull the images, whatever its taints
+                    tolerations: Some(vec![Toleration {
+                        operator: Some("Exists".to_string()),
+                        ..Default::default()
+                    }]),
+                    termination_grace_period_seconds: Some(0),
+                    ..Default::default()
+                }),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Pull time spent while the charts were deployed: without the pre-pull, it would have been spent by the chart pods.
+pub fn prepull_time_saved(pulled_in: Option<Duration>, charts_deployed_in: Duration) -> Duration {
+    match pulled_in {
+        Some(pulled_in) => pulled_in.min(charts_deployed_in),
+        // still pulling when the charts were done, so it overlapped with the whole deployment
+        None => charts_deployed_in,
+    }
+}
+
+pub struct ImagePrePullOutcome {
+    pub images: usize,
+    /// None when the images were still being pulled at the end of the bootstrap
+    pub pulled_in: Option<Duration>,
+}
+
+pub struct ImagePrePull {
+    client: kube::Client,
+    cluster_id: Uuid,
+    ready_nodes: usize,
+    charts_deployed: AtomicBool,
+}
+
+impl ImagePrePull {
+    /// Only on the first deployment of the cluster, when enabled in the advanced settings and the cluster is big enough.
+    pub fn for_bootstrap(infra_ctx: &InfrastructureContext, logger: &impl InfraLogger) -> Option<Self> {
+        let advanced_settings = infra_ctx.kubernetes().advanced_settings();
+        if !infra_ctx.context().is_first_cluster_deployment() || !advanced_settings.k8s_image_prepull_enabled {
+            return None;
+        }
+
+        let client = match infra_ctx.mk_kube_client() {
+            Ok(kube_client) => kube_client.client().clone(),
+            Err(err) => {
+                logger.warn(format!("Skipping image pre-pull, cannot connect to the cluster: {err}"));
+                return None;
+            }
+        };
+        let nodes: Api<Node> = Api::all(client.clone());
+        let ready_nodes = match block_on(nodes.list(&ListParams::default())) {
+            Ok(nodes) => node_readiness(&nodes.items).ready,
+            Err(err) => {
+                logger.warn(format!("Skipping image pre-pull, cannot list the nodes: {err}"));
+                return None;
+            }
+        };
+        if !should_prepull_images(ready_nodes, advanced_settings.k8s_image_prepull_min_nodes) {
+            logger.info(format!(
+                "Skipping image pre-pull, the cluster has {ready_nodes} ready node(s) and {} are required",
+                advanced_settings.k8s_image_prepull_min_nodes
+            ));
+            return None;
+        }
+
+        Some(ImagePrePull {
+            client,
+            cluster_id: *infra_ctx.kubernetes().long_id(),
+            ready_nodes,
+            charts_deployed: AtomicBool::new(false),
+        })
+    }
+
+    /// Renders the charts, creates the daemonset and waits for every node to have pulled the images.
+    /// Meant to run alongside the chart deployments, it stops waiting as soon as they are over.
+    pub fn run(
+        &self,
+        helm: &Helm,
+        charts: &[ChartInfo],
+        metrics_registry: &dyn MetricsRegistry,
+    ) -> Result<ImagePrePullOutcome, String> {
+        let record = metrics_registry.start_record(self.cluster_id, StepLabel::Infrastructure, StepName::ImagePrePull);
+        let started_at = Instant::now();
+
+        let mut images = BTreeSet::new();
+        for chart in charts {
+            // a chart that cannot be rendered is pulled when its pods start, as without the pre-pull
+            let Ok(manifests) = helm.get_template(&chart.path, chart) else {
+                warn!("image pre-pull: cannot render chart {}", chart.name);
+                continue;
+            };
+            match images_from_rendered_manifests(&manifests) {
+                Ok(chart_images) => images.extend(chart_images),
+                Err(err) => warn!("image pre-pull: cannot read the images of chart {}: {}", chart.name, err),
+            }
+        }
+        if images.is_empty() {
+            record.stop(StepStatus::Skip);
+            return Ok(ImagePrePullOutcome {
+                images: 0,
+                pulled_in: None,
+            });
+        }
+
+        let daemonsets: Api<DaemonSet> = Api::namespaced(self.client.clone(), IMAGE_PREPULL_NAMESPACE);
+        if let Err(err) = block_on(daemonsets.patch(
+            IMAGE_PREPULL_NAME,
+            &PatchParams::apply("qovery-engine").force(),
+            &Patch::Apply(image_prepull_daemonset(&images)),
+        )) {
+            record.stop(StepStatus::Error);
+            return Err(format!("cannot create the image pre-pull daemonset: {err}"));
+        }
+
+        while !self.charts_deployed.load(Ordering::Relaxed) && started_at.elapsed() < IMAGE_PREPULL_TIMEOUT {
+            let status = block_on(daemonsets.get_status(IMAGE_PREPULL_NAME))
+                .ok()
+                .and_then(|daemonset| daemonset.status);
+            if let Some(status) = status {
+                if status.desired_number_scheduled > 0 && status.number_ready >= status.desired_number_scheduled {
+                    record.stop(StepStatus::Success);
+                    return Ok(ImagePrePullOutcome {
+                        images: images.len(),
+                        pulled_in: Some(started_at.elapsed()),
+                    });
+                }
+            }
+            thread::sleep(IMAGE_PREPULL_POLL_INTERVAL);
+        }
+
+        record.stop(StepStatus::Cancel);
+        Ok(ImagePrePullOutcome {
+            images: images.len(),
+            pulled_in: None,
+        })
+    }
+
+    /// The charts are deployed, waiting for the pulls is pointless from now on.
+    pub fn stop(&self) {
+        self.charts_deployed.store(true, Ordering::Relaxed);
+    }
+
+    /// Removes the daemonset, the images are now on the nodes or being pulled, and reports the time saved.
+    pub fn finish(
+        &self,
+        outcome: Result<ImagePrePullOutcome, String>,
+        charts_deployed_in: Duration,
+        logger: &impl InfraLogger,
+    ) {
+        let daemonsets: Api<DaemonSet> = Api::namespaced(self.client.clone(), IMAGE_PREPULL_NAMESPACE);
+        match block_on(daemonsets.delete(IMAGE_PREPULL_NAME, &DeleteParams::background())) {
+            Ok(_) => {}
+            Err(kube::Error::Api(err)) if err.code == 404 => {}
+            Err(err) => logger.warn(format!("Cannot remove the image pre-pull daemonset: {err}")),
+        }
+
+        match outcome {
+            Ok(ImagePrePullOutcome { images: 0, .. }) => {
+                logger.info("Image pre-pull skipped, no image found in the charts");
+            }
+            Ok(outcome) => logger.info(format!(
+                "⏱️ Image pre-pull: {} image(s) on {} node(s), {}. Saved up to {}s of image pulls during the chart deployments",
+                outcome.images,
+                self.ready_nodes,
+                match outcome.pulled_in {
+                    Some(pulled_in) => format!("pulled in {}s", pulled_in.as_secs()),
+                    None => "still pulling at the end of the deployment".to_string(),
+                },
+                prepull_time_saved(outcome.pulled_in, charts_deployed_in).as_secs(),
+            )),
+            Err(err) => logger.warn(format!("Image pre-pull failed, images are pulled by the charts instead: {err}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RENDERED_CHART: &str = r#"
+---
+# Source: ingress-nginx/templates/controller-serviceaccount.yaml
+apiVersion: v1
+kind: ServiceAccount
+metadata:
+  name: ingress-nginx
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: ingress-nginx-controller
+spec:
+  selector:
+    matchLabels:
+      app: ingress-nginx
+  template:
+    spec:
+      initContainers:
+        - name: sysctl
+          image: public.ecr.aws/r3m4q3r9/pub-mirror-busybox:1.36
+      containers:
+        - name: controller
+          image: registry.k8s.io/ingress-nginx/controller:v1.11.2
+---
+apiVersion: batch/v1
+kind: CronJob
+metadata:
+  name: cleanup
+spec:
+  schedule: "0 * * * *"
+  jobTemplate:
+    spec:
+      template:
+        spec:
+          containers:
+            - name: cleanup
+              image: registry.k8s.io/ingress-nginx/controller:v1.11.2
+---
+apiVersion: apps/v1
+kind: DaemonSet
+metadata:
+  name: promtail
+spec:
+  selector:
+    matchLabels:
+      app: promtail
+  template:
+    spec:
+      containers:
+        - name: promtail
+          image: docker.io/grafana/promtail:3.0.0
+---
+"#;
+
+    #[test]
+    fn test_images_are_extracted_from_every_workload_kind() {
+        let images = images_from_rendered_manifests(RENDERED_CHART).unwrap();
+
+        assert_eq!(
+            images.into_iter().collect::<Vec<_>>(),
+            vec![
+                "docker.io/grafana/promtail:3.0.0",
+                "public.ecr.aws/r3m4q3r9/pub-mirror-busybox:1.36",
+                "registry.k8s.io/ingress-nginx/controller:v1.11.2",
+            ]
+        );
+        assert!(images_from_rendered_manifests("").unwrap().is_empty());
+        assert!(images_from_rendered_manifests("kind: Deployment\nspec: 42\n").is_err());
+    }
+
+    #[test]
+    fn test_daemonset_pulls_every_image_on_every_node() {
+        let images = BTreeSet::from([
+            "docker.io/grafana/promtail:3.0.0".to_string(),
+            "registry.k8s.io/ingress-nginx/controller:v1.11.2".to_string(),
+        ]);
+        let daemonset = image_prepull_daemonset(&images);
+        let pod_spec = daemonset.spec.unwrap().template.spec.unwrap();
+        let init_containers = pod_spec.init_containers.unwrap();
+
+        assert_eq!(init_containers.len(), 3);
+        assert_eq!(init_containers[0].image.as_deref(), Some(IMAGE_PREPULL_HELPER_IMAGE));
+        for (container, image) in init_containers[1..].iter().zip(&images) {
+            assert_eq!(container.image.as_ref(), Some(image));
+            assert_eq!(container.command, Some(vec!["/prepull/true".to_string()]));
+            assert_eq!(container.image_pull_policy.as_deref(), Some("IfNotPresent"));
+        }
+        assert_eq!(pod_spec.containers[0].image.as_deref(), Some(IMAGE_PREPULL_PAUSE_IMAGE));
+        assert_eq!(pod_spec.tolerations.unwrap()[0].operator.as_deref(), Some("Exists"));
+        assert_eq!(daemonset.metadata.namespace.as_deref(), Some("kube-system"));
+    }
+
+    #[test]
+    fn test_small_clusters_skip_the_prepull() {
+        assert!(!should_prepull_images(0, 0));
+        assert!(!should_prepull_images(2, 3));
+        assert!(should_prepull_images(3, 3));
+        assert!(should_prepull_images(10, 3));
+    }
+
+    #[test]
+    fn test_time_saved_is_bounded_by_the_chart_deployments() {
+        let charts_deployed_in = Duration::from_secs(300);
+
+        assert_eq!(
+            prepull_time_saved(Some(Duration::from_secs(90)), charts_deployed_in),
+            Duration::from_secs(90)
+        );
+        assert_eq!(
+            prepull_time_saved(Some(Duration::from_secs(400)), charts_deployed_in),
+            charts_deployed_in
+        );
+        assert_eq!(prepull_time_saved(None, charts_deployed_in), charts_deployed_in);
+    }
+}
@@ -0,0 +1,294 @@
+// Tells what a new infrastructure deployment changes compared to the last successful terraform apply,
+// without running terraform: the inputs of the apply (rendered tera context and templates) are fingerprinted
+// and kept in the cluster, then compared with the inputs of the new deployment.
+
+use crate::runtime::block_on;
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Patch, PatchParams};
+use kube::Api;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use tera::Context as TeraContext;
+use walkdir::WalkDir;
+
+const APPLIED_INPUTS_SECRET_NAME: &str = "qovery-terraform-applied-inputs";
+const APPLIED_INPUTS_SECRET_NAMESPACE: &str = "kube-system";
+const APPLIED_INPUTS_SECRET_KEY: &str = "inputs";
+
+// sizing attributes of a node group, the ones a cloud provider api can change in place
+static NODE_GROUP_SIZING_INPUT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<node_groups>[a-z_]+)\[(?P<index>\d+)\]\.(min_nodes|max_nodes|desired_size|enable_desired_size)$")
+        .expect("invalid node group sizing regex")
+});
+
+/// Fingerprints of the inputs of a terraform apply. Values are hashed, so credentials never leave the engine.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedInputs {
+    pub templates_checksum: String,
+    /// Path of every leaf of the tera context (i.e: `eks_worker_nodes[0].max_nodes`) and the hash of its value
+    pub inputs: BTreeMap<String, String>,
+}
+
+impl AppliedInputs {
+    /// `ignored_inputs` are the top level keys changing on every deployment without changing any resource,
+    /// like short-lived credentials or timeouts
+    pub fn new(templates_dir: &Path, tera_context: &TeraContext, ignored_inputs: &[&str]) -> Result<Self, String> {
+        Ok(Self::from_json(
+            templates_checksum(templates_dir)?,
+            &tera_context.clone().into_json(),
+            ignored_inputs,
+        ))
+    }
+
+    pub fn from_json(templates_checksum: String, context: &Value, ignored_inputs: &[&str]) -> Self {
+        let mut inputs = BTreeMap::new();
+        if let Value::Object(keys) = context {
+            for (key, value) in keys.iter().filter(|(key, _)| !ignored_inputs.contains(&key.as_str())) {
+                fingerprint_leaves(key.clone(), value, &mut inputs);
+            }
+        }
+
+        AppliedInputs {
+            templates_checksum,
+            inputs,
+        }
+    }
+}
+
+fn fingerprint_leaves(path: String, value: &Value, inputs: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(keys) => {
+            for (key, value) in keys {
+                fingerprint_leaves(format!("{path}.{key}"), value, inputs);
+            }
+        }
+        Value::Array(items) => {
+            // an emptied list must differ from a missing one
+            inputs.insert(format!("{path}.len"), items.len().to_string());
+            for (ix, item) in items.iter().enumerate() {
+                fingerprint_leaves(format!("{path}[{ix}]"), item, inputs);
+            }
+        }
+        leaf => {
+            inputs.insert(path, format!("{:x}", Sha256::digest(leaf.to_string().as_bytes())));
+        }
+    }
+}
+
+fn templates_checksum(templates_dir: &Path) -> Result<String, String> {
+    let mut files = WalkDir::new(templates_dir)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("cannot list terraform templates: {err}"))?;
+    files.sort_by(|a, b| a.path().cmp(b.path()));
+
+    let mut hasher = Sha256::new();
+    for file in files.iter().filter(|entry| entry.file_type().is_file()) {
+        let content =
+            std::fs::read(file.path()).map_err(|err| format!("cannot read {}: {err}", file.path().display()))?;
+        hasher.update(
+            file.path()
+                .strip_prefix(templates_dir)
+                .unwrap_or(file.path())
+                .to_string_lossy()
+                .as_bytes(),
+        );
+        hasher.update(content);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InfraChanges {
+    Nothing,
+    /// Only min/max/desired sizes of existing node groups, identified by their index in `node_groups_input`
+    NodeGroupsSizing {
+        node_groups: BTreeSet<usize>,
+    },
+    /// Anything else, terraform must be applied. Changed inputs are paths of the tera context
+    Other {
+        changed_inputs: Vec<String>,
+    },
+}
+
+/// Compares the inputs of the last apply with the ones of the new deployment.
+/// `node_groups_input` is the key of the node groups list in the tera context.
+pub fn classify_changes(previous: &AppliedInputs, current: &AppliedInputs, node_groups_input: &str) -> InfraChanges {
+    if previous.templates_checksum != current.templates_checksum {
+        return InfraChanges::Other {
+            changed_inputs: vec!["terraform templates".to_string()],
+        };
+    }
+
+    let changed_inputs = previous
+        .inputs
+        .keys()
+        .chain(current.inputs.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|path| previous.inputs.get(*path) != current.inputs.get(*path))
+        .cloned()
+        .collect::<Vec<_>>();
+    if changed_inputs.is_empty() {
+        return InfraChanges::Nothing;
+    }
+
+    let mut node_groups = BTreeSet::new();
+    for path in &changed_inputs {
+        let sizing = NODE_GROUP_SIZING_INPUT
+            .captures(path)
+            .filter(|captures| &captures["node_groups"] == node_groups_input)
+            .and_then(|captures| captures["index"].parse::<usize>().ok());
+        match sizing {
+            Some(index) => {
+                node_groups.insert(index);
+            }
+            None => return InfraChanges::Other { changed_inputs },
+        }
+    }
+
+    InfraChanges::NodeGroupsSizing { node_groups }
+}
+
+/// Inputs of the last successful apply, None when they have never been recorded or cannot be read
+pub fn load_applied_inputs(kube_client: &kube::Client) -> Option<AppliedInputs> {
+    let secrets: Api<Secret> = Api::namespaced(kube_client.clone(), APPLIED_INPUTS_SECRET_NAMESPACE);
+    let secret = block_on(secrets.get_opt(APPLIED_INPUTS_SECRET_NAME)).ok()??;
+    let inputs = secret.data?.remove(APPLIED_INPUTS_SECRET_KEY)?;
+
+    serde_json::from_slice(&inputs.0).ok()
+}
+
+pub fn record_applied_inputs(kube_client: &kube::Client, applied_inputs: &AppliedInputs) -> Result<(), String> {
+    let secrets: Api<Secret> = Api::namespaced(kube_client.clone(), APPLIED_INPUTS_SECRET_NAMESPACE);
+    let secret = Secret {
+        metadata: ObjectMeta {
+            name: Some(APPLIED_INPUTS_SECRET_NAME.to_string()),
+            namespace: Some(APPLIED_INPUTS_SECRET_NAMESPACE.to_string()),
+            ..Default::default()
+        },
+        string_data: Some(BTreeMap::from([(
+            APPLIED_INPUTS_SECRET_KEY.to_string(),
+            serde_json::to_string(applied_inputs).map_err(|err| err.to_string())?,
+        )])),
+        ..Default::default()
+    };
+
+    block_on(secrets.patch(
+        APPLIED_INPUTS_SECRET_NAME,
+        &PatchParams::apply("qovery-engine").force(),
+        &Patch::Apply(secret),
+    ))
+    .map(|_| ())
+    .map_err(|err| format!("cannot record terraform applied inputs: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn inputs(context: Value) -> AppliedInputs {
+        AppliedInputs::from_json("templates-v1".to_string(), &context, &["aws_session_token"])
+    }
+
+    fn cluster(node_groups: Value, session_token: &str) -> Value {
+        json!({
+            "kubernetes_cluster_name": "qovery-z1234",
+            "aws_session_token": session_token,
+            "eks_worker_nodes": node_groups,
+        })
+    }
+
+    fn node_group(name: &str, min_nodes: i32, max_nodes: i32, instance_type: &str) -> Value {
+        json!({
+            "name": name,
+            "min_nodes": min_nodes,
+            "max_nodes": max_nodes,
+            "desired_size": min_nodes,
+            "enable_desired_size": false,
+            "instance_type": instance_type,
+        })
+    }
+
+    #[test]
+    fn test_ignored_inputs_are_not_changes() {
+        let previous = inputs(cluster(json!([node_group("default", 3, 10, "t3a.large")]), "token-1"));
+        let current = inputs(cluster(json!([node_group("default", 3, 10, "t3a.large")]), "token-2"));
+
+        assert_eq!(classify_changes(&previous, &current, "eks_worker_nodes"), InfraChanges::Nothing);
+        assert!(!current.inputs.contains_key("aws_session_token"));
+    }
+
+    #[test]
+    fn test_sizing_only_changes_are_detected() {
+        let previous = inputs(cluster(
+            json!([
+                node_group("default", 3, 10, "t3a.large"),
+                node_group("gpu", 1, 2, "g4dn.xlarge")
+            ]),
+            "token",
+        ));
+        let current = inputs(cluster(
+            json!([
+                node_group("default", 3, 10, "t3a.large"),
+                node_group("gpu", 2, 5, "g4dn.xlarge")
+            ]),
+            "token",
+        ));
+
+        assert_eq!(
+            classify_changes(&previous, &current, "eks_worker_nodes"),
+            InfraChanges::NodeGroupsSizing {
+                node_groups: BTreeSet::from([1])
+            }
+        );
+    }
+
+    #[test]
+    fn test_any_other_change_requires_terraform() {
+        let previous = inputs(cluster(json!([node_group("default", 3, 10, "t3a.large")]), "token"));
+
+        // sizing and instance type
+        let current = inputs(cluster(json!([node_group("default", 3, 12, "t3a.xlarge")]), "token"));
+        assert_eq!(
+            classify_changes(&previous, &current, "eks_worker_nodes"),
+            InfraChanges::Other {
+                changed_inputs: vec![
+                    "eks_worker_nodes[0].instance_type".to_string(),
+                    "eks_worker_nodes[0].max_nodes".to_string(),
+                ]
+            }
+        );
+
+        // new node group
+        let current = inputs(cluster(
+            json!([
+                node_group("default", 3, 10, "t3a.large"),
+                node_group("gpu", 1, 2, "g4dn.xlarge")
+            ]),
+            "token",
+        ));
+        assert!(matches!(
+            classify_changes(&previous, &current, "eks_worker_nodes"),
+            InfraChanges::Other { .. }
+        ));
+
+        // same inputs, new templates
+        let current = AppliedInputs {
+            templates_checksum: "templates-v2".to_string(),
+            ..previous.clone()
+        };
+        assert!(matches!(
+            classify_changes(&previous, &current, "eks_worker_nodes"),
+            InfraChanges::Other { .. }
+        ));
+    }
+}
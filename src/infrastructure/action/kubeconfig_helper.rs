@@ -1,12 +1,16 @@
 use crate::errors::{CommandError, EngineError};
 use crate::events::Stage::Infrastructure;
 use crate::events::{EventDetails, InfrastructureStep};
+use crate::infrastructure::models::cloud_provider::CloudProvider;
 use crate::infrastructure::models::kubernetes::Kubernetes;
+use crate::runtime::block_on;
+use crate::utilities::create_kube_client;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
+use std::time::Duration;
 
 pub fn update_kubeconfig_file(kube: &dyn Kubernetes, kubeconfig: &str) -> Result<(), Box<EngineError>> {
     // Upload kubeconfig, so we can store it in the core
@@ -76,3 +80,175 @@ pub fn write_kubeconfig_on_disk(
 
     Ok(())
 }
+
+/// Lightweight call to the api server version endpoint, checking the kubeconfig still permits to connect to the cluster
+pub fn check_kubeconfig(kubeconfig_path: &Path, envs: &[(String, String)]) -> Result<(), CommandError> {
+    block_on(async {
+        tokio::time::timeout(Duration::from_secs(30), async {
+            let client = create_kube_client(kubeconfig_path, envs).await?;
+            client.apiserver_version().await
+        })
+        .await
+    })
+    .map_err(|_| CommandError::new_from_safe_message("Timeout while reaching the Kubernetes api server".to_string()))?
+    .map(|_| ())
+    .map_err(|err| {
+        CommandError::new(
+            "Cannot reach the Kubernetes api server with the kubeconfig".to_string(),
+            Some(err.to_string()),
+            None,
+        )
+    })
+}
+
+/// Regenerates the kubeconfig from the cloud provider when the stored one does not permit to connect to the cluster
+/// anymore, then checks the new one once.
+/// Returns true if the kubeconfig has been regenerated.
+pub fn refresh_kubeconfig_if_invalid(
+    kube: &dyn Kubernetes,
+    cloud_provider: &dyn CloudProvider,
+) -> Result<bool, Box<EngineError>> {
+    let envs: Vec<(String, String)> = cloud_provider
+        .credentials_environment_variables()
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    refresh_invalid_kubeconfig(
+        &kube.kubeconfig_local_file_path(),
+        kube.get_event_details(Infrastructure(InfrastructureStep::LoadConfiguration)),
+        |kubeconfig_path| check_kubeconfig(kubeconfig_path, &envs),
+        || kube.regenerate_kubeconfig(cloud_provider),
+        |kubeconfig| update_kubeconfig_file(kube, kubeconfig),
+    )
+}
+
+fn refresh_invalid_kubeconfig(
+    kubeconfig_path: &Path,
+    event_details: EventDetails,
+    check: impl Fn(&Path) -> Result<(), CommandError>,
+    regenerate: impl FnOnce() -> Result<Option<String>, Box<EngineError>>,
+    store: impl FnOnce(&str) -> Result<(), Box<EngineError>>,
+) -> Result<bool, Box<EngineError>> {
+    // kubeconfig may be missing if it is the first time we create the cluster
+    if !kubeconfig_path.exists() {
+        return Ok(false);
+    }
+
+    let err = match check(kubeconfig_path) {
+        Ok(()) => return Ok(false),
+        Err(err) => err,
+    };
+    warn!("kubeconfig does not permit to connect to the cluster, regenerating it: {}", err);
+
+    let Some(kubeconfig) = regenerate()? else {
+        return Err(Box::new(EngineError::new_kubeconfig_file_do_not_match_the_current_cluster(
+            event_details,
+        )));
+    };
+    store(&kubeconfig)?;
+
+    if let Err(err) = check(kubeconfig_path) {
+        warn!("regenerated kubeconfig does not permit to connect to the cluster: {}", err);
+        return Err(Box::new(EngineError::new_kubeconfig_file_do_not_match_the_current_cluster(
+            event_details,
+        )));
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_kubeconfig, refresh_invalid_kubeconfig, write_kubeconfig_on_disk};
+    use crate::errors::{CommandError, Tag};
+    use crate::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
+    use crate::io_models::QoveryIdentifier;
+    use std::cell::Cell;
+    use std::fs;
+    use std::path::Path;
+    use uuid::Uuid;
+
+    const VALID_KUBECONFIG: &str = "apiVersion: v1\nkind: Config\n# regenerated\n";
+
+    fn event_details() -> EventDetails {
+        EventDetails::new(
+            None,
+            QoveryIdentifier::default(),
+            QoveryIdentifier::default(),
+            "".to_string(),
+            Stage::Infrastructure(InfrastructureStep::LoadConfiguration),
+            Transmitter::Kubernetes(Uuid::new_v4(), "".to_string()),
+        )
+    }
+
+    // only the regenerated kubeconfig permits to connect to the cluster
+    fn check(kubeconfig_path: &Path) -> Result<(), CommandError> {
+        match fs::read_to_string(kubeconfig_path).unwrap_or_default() == VALID_KUBECONFIG {
+            true => Ok(()),
+            false => Err(CommandError::new_from_safe_message("unauthorized".to_string())),
+        }
+    }
+
+    #[test]
+    fn test_check_invalid_kubeconfig() {
+        let dir = tempfile::tempdir().unwrap();
+        let kubeconfig_path = dir.path().join("kubeconfig.yaml");
+        fs::write(&kubeconfig_path, "not a kubeconfig: [").unwrap();
+
+        assert!(check_kubeconfig(&kubeconfig_path, &[]).is_err());
+    }
+
+    #[test]
+    fn test_refresh_invalid_kubeconfig() {
+        let dir = tempfile::tempdir().unwrap();
+        let kubeconfig_path = dir.path().join("kubeconfig.yaml");
+        let store = |kubeconfig: &str| write_kubeconfig_on_disk(&kubeconfig_path, kubeconfig, event_details());
+
+        // no kubeconfig yet: nothing to refresh
+        assert!(
+            !refresh_invalid_kubeconfig(&kubeconfig_path, event_details(), check, || unreachable!(), store).unwrap()
+        );
+
+        // valid kubeconfig: kept as is
+        fs::write(&kubeconfig_path, VALID_KUBECONFIG).unwrap();
+        assert!(
+            !refresh_invalid_kubeconfig(&kubeconfig_path, event_details(), check, || unreachable!(), store).unwrap()
+        );
+
+        // invalid kubeconfig: regenerated from the cloud provider, stored, and checked again
+        fs::write(&kubeconfig_path, "expired").unwrap();
+        let regenerated = Cell::new(false);
+        let refreshed = refresh_invalid_kubeconfig(
+            &kubeconfig_path,
+            event_details(),
+            check,
+            || {
+                regenerated.set(true);
+                Ok(Some(VALID_KUBECONFIG.to_string()))
+            },
+            store,
+        )
+        .unwrap();
+        assert!(refreshed);
+        assert!(regenerated.get());
+        assert_eq!(fs::read_to_string(&kubeconfig_path).unwrap(), VALID_KUBECONFIG);
+
+        // invalid kubeconfig the provider cannot regenerate
+        fs::write(&kubeconfig_path, "expired").unwrap();
+        let err = refresh_invalid_kubeconfig(&kubeconfig_path, event_details(), check, || Ok(None), store).unwrap_err();
+        assert_eq!(err.tag(), &Tag::KubeconfigFileDoNotPermitToConnectToK8sCluster);
+
+        // regenerated kubeconfig still invalid: retried only once
+        fs::write(&kubeconfig_path, "expired").unwrap();
+        let err = refresh_invalid_kubeconfig(
+            &kubeconfig_path,
+            event_details(),
+            check,
+            || Ok(Some("still expired".to_string())),
+            store,
+        )
+        .unwrap_err();
+        assert_eq!(err.tag(), &Tag::KubeconfigFileDoNotPermitToConnectToK8sCluster);
+    }
+}
@@ -1,10 +1,11 @@
 use crate::cmd::kubectl::{kubectl_delete_completed_jobs, kubectl_exec_delete_pod, kubectl_get_crash_looping_pods};
 use crate::errors::{CommandError, EngineError};
 use crate::events::Stage;
+use crate::infrastructure::action::InfraLogger;
 use crate::infrastructure::models::cloud_provider::service::Action;
 use crate::infrastructure::models::cloud_provider::CloudProvider;
 use crate::infrastructure::models::kubernetes::{
-    check_master_version_status, check_workers_pause, check_workers_status, check_workers_upgrade_status,
+    check_master_version_status, check_workers_pause, check_workers_readiness, check_workers_upgrade_status,
     send_progress_on_long_task, Kubernetes, KubernetesVersion,
 };
 
@@ -42,12 +43,18 @@ pub fn check_workers_on_create(
     kube: &dyn Kubernetes,
     cloud_provider: &dyn CloudProvider,
     node_selector: Option<&str>,
+    logger: &impl InfraLogger,
 ) -> Result<(), CommandError> {
     send_progress_on_long_task(kube, Action::Create, || {
-        check_workers_status(
+        check_workers_readiness(
             kube.kubeconfig_local_file_path(),
             cloud_provider.credentials_environment_variables(),
             node_selector,
+            |ready_nodes, total_nodes| {
+                if ready_nodes < total_nodes {
+                    logger.report_progress("Worker nodes readiness", ready_nodes + 1, total_nodes);
+                }
+            },
         )
     })
 }
@@ -3,15 +3,23 @@ mod deploy_helms;
 mod deploy_terraform;
 mod eks;
 mod gke;
+mod image_prepull;
+mod infra_changes;
 pub(super) mod kubeconfig_helper;
 mod kubectl_utils;
+mod pause_helper;
 mod scaleway;
 mod self_managed;
+mod terraform_state_backup;
+mod upgrade_preflight;
 mod utils;
 
+use crate::engine_task::progress::ProgressReporter;
 use crate::errors::{EngineError, ErrorMessageVerbosity};
 use crate::events::Stage::Infrastructure;
-use crate::events::{EngineEvent, EventDetails, EventMessage, InfrastructureDiffType, InfrastructureStep};
+use crate::events::{EngineEvent, EventDetails, EventMessage, InfrastructureDiffType, InfrastructureStep, Transmitter};
+use crate::infrastructure::action::kubeconfig_helper::refresh_kubeconfig_if_invalid;
+use crate::infrastructure::action::upgrade_preflight::check_upgrade_preflight;
 use crate::infrastructure::action::utils::mk_logger;
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::cloud_provider::service::Action;
@@ -37,6 +45,11 @@ pub trait InfrastructureAction: Send + Sync {
         infra_ctx: &InfrastructureContext,
         kubernetes_upgrade_status: KubernetesUpgradeStatus,
     ) -> Result<(), Box<EngineError>>;
+    /// Applies only the terraform resources of the node groups of an existing cluster, to repair them
+    /// in minutes instead of a full apply. Providers without a targeted apply fall back to a full creation.
+    fn repair_node_groups(&self, infra_ctx: &InfrastructureContext) -> Result<(), Box<EngineError>> {
+        self.run(infra_ctx, Action::Create)
+    }
 
     fn run(&self, infra_ctx: &InfrastructureContext, action: Action) -> Result<(), Box<EngineError>> {
         let step = match action {
@@ -56,6 +69,18 @@ pub trait InfrastructureAction: Send + Sync {
             infra_ctx.kubernetes().kind(),
             infra_ctx.kubernetes().name()
         ));
+        if !infra_ctx.context().is_first_cluster_deployment() && !infra_ctx.context().is_dry_run_deploy() {
+            match refresh_kubeconfig_if_invalid(infra_ctx.kubernetes(), infra_ctx.cloud_provider()) {
+                Ok(true) => {
+                    logger.info("Kubeconfig did not permit to connect to the cluster anymore, it has been regenerated")
+                }
+                Ok(false) => {}
+                Err(err) => logger.warn(EventMessage::new(
+                    "Kubeconfig does not permit to connect to the cluster and cannot be regenerated".to_string(),
+                    Some(err.message(ErrorMessageVerbosity::FullDetailsWithoutEnvVars)),
+                )),
+            }
+        }
         match action {
             Action::Create => {
                 let mut cluster_has_been_upgraded = false;
@@ -63,6 +88,7 @@ pub trait InfrastructureAction: Send + Sync {
                     self.bootstap_cluster(infra_ctx)?;
                 } else if let Some(upgrade_status) = self.is_upgrade_required(infra_ctx) {
                     cluster_has_been_upgraded = true;
+                    check_upgrade_preflight(infra_ctx, &upgrade_status, self.upgrade_node_selector())?;
                     self.upgrade_cluster(infra_ctx, upgrade_status)?;
                 }
                 self.create_cluster(infra_ctx, cluster_has_been_upgraded)
@@ -123,11 +149,18 @@ pub trait InfraLogger {
     fn error(self, error: EngineError, message: Option<impl Into<EventMessage>>);
 
     fn diff(&self, from: InfrastructureDiffType, message: String);
+
+    /// Logs on behalf of another transmitter, i.e: one chart among the ones deployed concurrently
+    fn info_from(&self, transmitter: Transmitter, message: impl Into<EventMessage>);
+
+    /// Reports that `step`, the `current` one out of `total` (starting at 1), is being run
+    fn report_progress(&self, step: &str, current: u32, total: u32);
 }
 
 struct InfraLoggerImpl {
     event_details: EventDetails,
     logger: Box<dyn Logger>,
+    progress: ProgressReporter,
 }
 
 impl InfraLogger for InfraLoggerImpl {
@@ -152,4 +185,13 @@ impl InfraLogger for InfraLoggerImpl {
         );
         self.logger.log(EngineEvent::Info(ev, EventMessage::from(message)));
     }
+
+    fn info_from(&self, transmitter: Transmitter, message: impl Into<EventMessage>) {
+        let ev = EventDetails::clone_changing_transmitter(self.event_details.clone(), transmitter);
+        self.logger.log(EngineEvent::Info(ev, message.into()));
+    }
+
+    fn report_progress(&self, step: &str, current: u32, total: u32) {
+        self.progress.report_progress(step, current, total);
+    }
 }
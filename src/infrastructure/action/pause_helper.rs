@@ -0,0 +1,396 @@
+// Steps shared by the cluster pauses: no engine task must be running, the nodes are cordoned and drained before
+// being removed, and the desired nodes of the node groups are kept in the cluster so the resume restores them.
+
+use crate::cmd::kubectl::kubectl_exec_api_custom_metrics;
+use crate::cmd::structs::KubernetesApiCustomMetric;
+use crate::errors::{CommandError, EngineError};
+use crate::events::EventDetails;
+use crate::infrastructure::action::InfraLogger;
+use crate::infrastructure::models::cloud_provider::CloudProvider;
+use crate::infrastructure::models::kubernetes::Kubernetes;
+use crate::runtime::block_on;
+use crate::services::kube_client::{QubeClient, SelectK8sResourceBy};
+use json_patch::{AddOperation, PatchOperation};
+use jsonptr::Pointer;
+use k8s_openapi::api::core::v1::{Pod, Secret};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{DeleteParams, EvictParams, ListParams, Patch, PatchParams};
+use kube::{Api, ResourceExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PAUSED_NODE_GROUPS_SECRET_NAME: &str = "qovery-paused-node-groups";
+const PAUSED_NODE_GROUPS_SECRET_NAMESPACE: &str = "kube-system";
+const PAUSED_NODE_GROUPS_SECRET_KEY: &str = "node_groups";
+
+const ENGINE_NAMESPACE: &str = "qovery";
+const ENGINE_RUNNING_TASKS_METRIC: &str = "taskmanager_nb_running_tasks";
+const ENGINE_RUNNING_TASKS_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+// pods of these namespaces keep the cluster working (dns, qovery agents), they are evicted last
+const SYSTEM_NAMESPACES: &[&str] = &["kube-system", "qovery"];
+
+/// Desired nodes of the node groups when the cluster has been paused, restored by the next deployment
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PausedNodeGroups {
+    /// By node group name
+    pub desired_nodes: BTreeMap<String, i32>,
+}
+
+impl PausedNodeGroups {
+    /// Nodes to restore, kept within the current bounds of the node group as they may have changed while paused
+    pub fn desired_nodes_on_resume(&self, node_group_name: &str, min_nodes: i32, max_nodes: i32) -> Option<i32> {
+        self.desired_nodes
+            .get(node_group_name)
+            .map(|desired_nodes| (*desired_nodes).min(max_nodes).max(min_nodes))
+    }
+}
+
+/// Node groups recorded by the last pause, None when the cluster has not been paused or they cannot be read
+pub fn load_paused_node_groups(kube_client: &kube::Client) -> Option<PausedNodeGroups> {
+    let secrets: Api<Secret> = Api::namespaced(kube_client.clone(), PAUSED_NODE_GROUPS_SECRET_NAMESPACE);
+    let secret = block_on(secrets.get_opt(PAUSED_NODE_GROUPS_SECRET_NAME)).ok()??;
+    let node_groups = secret.data?.remove(PAUSED_NODE_GROUPS_SECRET_KEY)?;
+
+    serde_json::from_slice(&node_groups.0).ok()
+}
+
+pub fn record_paused_node_groups(kube_client: &kube::Client, node_groups: &PausedNodeGroups) -> Result<(), String> {
+    // pausing an already paused cluster: there is no node group left, the recorded ones are kept
+    if node_groups.desired_nodes.is_empty() {
+        return Ok(());
+    }
+
+    let secrets: Api<Secret> = Api::namespaced(kube_client.clone(), PAUSED_NODE_GROUPS_SECRET_NAMESPACE);
+    let secret = Secret {
+        metadata: ObjectMeta {
+            name: Some(PAUSED_NODE_GROUPS_SECRET_NAME.to_string()),
+            namespace: Some(PAUSED_NODE_GROUPS_SECRET_NAMESPACE.to_string()),
+            ..Default::default()
+        },
+        string_data: Some(BTreeMap::from([(
+            PAUSED_NODE_GROUPS_SECRET_KEY.to_string(),
+            serde_json::to_string(node_groups).map_err(|err| err.to_string())?,
+        )])),
+        ..Default::default()
+    };
+
+    block_on(secrets.patch(
+        PAUSED_NODE_GROUPS_SECRET_NAME,
+        &PatchParams::apply("qovery-engine").force(),
+        &Patch::Apply(secret),
+    ))
+    .map(|_| ())
+    .map_err(|err| format!("cannot record paused node groups: {err}"))
+}
+
+/// Once resumed, the next pause records the node groups again
+pub fn forget_paused_node_groups(kube_client: &kube::Client) -> Result<(), String> {
+    let secrets: Api<Secret> = Api::namespaced(kube_client.clone(), PAUSED_NODE_GROUPS_SECRET_NAMESPACE);
+    match block_on(secrets.delete(PAUSED_NODE_GROUPS_SECRET_NAME, &DeleteParams::default())) {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(()),
+        Err(err) => Err(format!("cannot forget paused node groups: {err}")),
+    }
+}
+
+fn running_engine_tasks(metric: &KubernetesApiCustomMetric) -> u32 {
+    metric
+        .items
+        .iter()
+        .map(|item| match item.value.strip_suffix('m') {
+            // milli values are averages, a task is still running if any
+            Some(milli_value) => milli_value.parse::<u32>().map(|value| value.div_ceil(1000)),
+            None => item.value.parse::<u32>(),
+        })
+        // an unreadable value is not a proof that no task is running
+        .map(|value| value.unwrap_or(1))
+        .sum()
+}
+
+/// Waits for the engine tasks running in the cluster (deployments, builds) to be over, they would be killed with the nodes
+pub fn check_no_engine_task_running(
+    kubernetes: &dyn Kubernetes,
+    cloud_provider: &dyn CloudProvider,
+    event_details: EventDetails,
+    logger: &impl InfraLogger,
+) -> Result<(), Box<EngineError>> {
+    let started_at = Instant::now();
+    loop {
+        let metric = match kubectl_exec_api_custom_metrics(
+            kubernetes.kubeconfig_local_file_path(),
+            cloud_provider.credentials_environment_variables(),
+            ENGINE_NAMESPACE,
+            ENGINE_RUNNING_TASKS_METRIC,
+        ) {
+            Ok(metric) => metric,
+            Err(err) => {
+                // no engine installed in the cluster, or the metric is not exposed yet
+                logger.warn(format!(
+                    "Cannot get the engine tasks running in the cluster, pausing anyway: {}",
+                    err.message_safe()
+                ));
+                return Ok(());
+            }
+        };
+
+        let running_tasks = running_engine_tasks(&metric);
+        if running_tasks == 0 {
+            return Ok(());
+        }
+        if started_at.elapsed() >= ENGINE_RUNNING_TASKS_TIMEOUT {
+            return Err(Box::new(EngineError::new_cannot_pause_cluster_tasks_are_running(
+                event_details,
+                Some(CommandError::new_from_safe_message(format!(
+                    "{running_tasks} engine tasks are still running after {} seconds",
+                    ENGINE_RUNNING_TASKS_TIMEOUT.as_secs()
+                ))),
+            )));
+        }
+
+        logger.info(format!(
+            "Waiting for {running_tasks} engine tasks to finish before pausing the cluster"
+        ));
+        thread::sleep(Duration::from_secs(10));
+    }
+}
+
+/// Pod to evict from a node
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PodToEvict {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PodToEvict {
+    fn is_system(&self) -> bool {
+        SYSTEM_NAMESPACES.contains(&self.namespace.as_str())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeToDrain {
+    pub name: String,
+    pub pods: Vec<PodToEvict>,
+}
+
+impl NodeToDrain {
+    fn hosts_system_pods(&self) -> bool {
+        self.pods.iter().any(|pod| pod.is_system())
+    }
+}
+
+/// Nodes hosting system pods are drained last, the quickest nodes to drain first.
+/// On each node, applications pods are evicted before the system ones.
+fn drain_order(mut nodes: Vec<NodeToDrain>) -> Vec<NodeToDrain> {
+    for node in nodes.iter_mut() {
+        node.pods.sort_by_key(|pod| pod.is_system());
+    }
+    nodes.sort_by(|a, b| {
+        a.hosts_system_pods()
+            .cmp(&b.hosts_system_pods())
+            .then(a.pods.len().cmp(&b.pods.len()))
+            .then(a.name.cmp(&b.name))
+    });
+
+    nodes
+}
+
+// daemon sets pods are recreated on the node right away, and static pods can't be evicted
+fn is_evictable(pod: &Pod) -> bool {
+    let is_daemon_set = pod.owner_references().iter().any(|owner| owner.kind == "DaemonSet");
+    let is_static = pod.annotations().contains_key("kubernetes.io/config.mirror");
+    let is_terminated = matches!(
+        pod.status.as_ref().and_then(|status| status.phase.as_deref()),
+        Some("Succeeded") | Some("Failed")
+    );
+
+    !is_daemon_set && !is_static && !is_terminated
+}
+
+async fn pods_to_evict(kube_client: &kube::Client, node_name: &str) -> Result<Vec<PodToEvict>, kube::Error> {
+    let pods: Api<Pod> = Api::all(kube_client.clone());
+    let pods = pods
+        .list(&ListParams::default().fields(&format!("spec.nodeName={node_name}")))
+        .await?;
+
+    Ok(pods
+        .items
+        .iter()
+        .filter(|pod| is_evictable(pod))
+        .map(|pod| PodToEvict {
+            namespace: pod.namespace().unwrap_or_default(),
+            name: pod.name_any(),
+        })
+        .collect())
+}
+
+/// Cordons the nodes, then evicts their pods through the eviction api, so pod disruption budgets are respected.
+/// Once the timeout is reached, the remaining pods are left to be stopped with their node.
+pub fn cordon_and_drain_nodes(
+    qube_client: &QubeClient,
+    event_details: EventDetails,
+    node_selector: Option<&str>,
+    timeout: Duration,
+    logger: &impl InfraLogger,
+) -> Result<(), Box<EngineError>> {
+    let nodes = block_on(qube_client.get_nodes(
+        event_details.clone(),
+        match node_selector {
+            Some(selector) => SelectK8sResourceBy::LabelsSelector(selector.to_string()),
+            None => SelectK8sResourceBy::All,
+        },
+    ))?;
+    if nodes.is_empty() {
+        return Ok(());
+    }
+
+    // every node is cordoned first, so evicted pods are not rescheduled on a node drained afterward
+    logger.info(format!("Cordoning {} nodes", nodes.len()));
+    let cordon = [PatchOperation::Add(AddOperation {
+        path: Pointer::new(["spec", "unschedulable"]),
+        value: Value::Bool(true),
+    })];
+    let mut nodes_to_drain = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let name = node.name_any();
+        block_on(qube_client.patch_node(event_details.clone(), node, &cordon))?;
+        let pods = block_on(pods_to_evict(qube_client.client(), &name)).map_err(|err| {
+            EngineError::new_k8s_patch_node_error(
+                event_details.clone(),
+                CommandError::new(format!("Cannot list the pods of node {name}"), Some(err.to_string()), None),
+            )
+        })?;
+        nodes_to_drain.push(NodeToDrain { name, pods });
+    }
+
+    let deadline = Instant::now() + timeout;
+    for node in drain_order(nodes_to_drain) {
+        logger.info(format!("Draining node {}, {} pods to evict", node.name, node.pods.len()));
+        let mut pods = node.pods;
+        while !pods.is_empty() {
+            if Instant::now() >= deadline {
+                logger.warn(format!(
+                    "Drain timeout of {} seconds reached, {} pods of node {} are stopped with it",
+                    timeout.as_secs(),
+                    pods.len(),
+                    node.name
+                ));
+                break;
+            }
+
+            for pod in &pods {
+                let pods_api: Api<Pod> = Api::namespaced(qube_client.client().clone(), &pod.namespace);
+                match block_on(pods_api.evict(&pod.name, &EvictParams::default())) {
+                    Ok(_) => {}
+                    // 429: the eviction would violate a pod disruption budget, it's retried
+                    Err(kube::Error::Api(err)) if err.code == 429 || err.code == 404 => {}
+                    Err(err) => logger.warn(format!(
+                        "Cannot evict pod {}/{} from node {}: {err}",
+                        pod.namespace, pod.name, node.name
+                    )),
+                }
+            }
+
+            thread::sleep(Duration::from_secs(5));
+            pods = match block_on(pods_to_evict(qube_client.client(), &node.name)) {
+                Ok(mut pods) => {
+                    pods.sort_by_key(|pod| pod.is_system());
+                    pods
+                }
+                Err(err) => {
+                    logger.warn(format!("Cannot list the pods of node {}: {err}", node.name));
+                    pods
+                }
+            };
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::structs::KubernetesApiCustomMetricItem;
+
+    fn pod(namespace: &str, name: &str) -> PodToEvict {
+        PodToEvict {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    fn node(name: &str, pods: Vec<PodToEvict>) -> NodeToDrain {
+        NodeToDrain {
+            name: name.to_string(),
+            pods,
+        }
+    }
+
+    #[test]
+    fn test_paused_node_groups_desired_nodes_on_resume() {
+        let paused = PausedNodeGroups {
+            desired_nodes: BTreeMap::from([
+                ("default".to_string(), 4),
+                ("large".to_string(), 8),
+                ("small".to_string(), 0),
+            ]),
+        };
+
+        // restored as recorded
+        assert_eq!(paused.desired_nodes_on_resume("default", 3, 10), Some(4));
+        // bounds changed while paused
+        assert_eq!(paused.desired_nodes_on_resume("large", 1, 5), Some(5));
+        assert_eq!(paused.desired_nodes_on_resume("small", 2, 5), Some(2));
+        // node group added while paused
+        assert_eq!(paused.desired_nodes_on_resume("gpu", 1, 5), None);
+
+        // survives the round trip through the secret
+        let serialized = serde_json::to_string(&paused).unwrap();
+        assert_eq!(serde_json::from_str::<PausedNodeGroups>(&serialized).unwrap(), paused);
+    }
+
+    #[test]
+    fn test_drain_order() {
+        let nodes = vec![
+            node("node-a", vec![pod("qovery", "agent"), pod("app", "front")]),
+            node("node-b", vec![pod("app", "api-1"), pod("app", "api-2"), pod("app", "worker")]),
+            node("node-c", vec![pod("app", "api-3")]),
+            node("node-d", vec![pod("kube-system", "coredns")]),
+        ];
+
+        let ordered = drain_order(nodes);
+
+        // nodes without system pods first, the quickest to drain first
+        assert_eq!(
+            ordered.iter().map(|node| node.name.as_str()).collect::<Vec<_>>(),
+            vec!["node-c", "node-b", "node-d", "node-a"]
+        );
+        // applications pods are evicted before the system ones
+        assert_eq!(ordered[3].pods, vec![pod("app", "front"), pod("qovery", "agent")]);
+    }
+
+    #[test]
+    fn test_running_engine_tasks() {
+        let metric = |values: &[&str]| KubernetesApiCustomMetric {
+            kind: "MetricValueList".to_string(),
+            items: values
+                .iter()
+                .map(|value| KubernetesApiCustomMetricItem {
+                    metric_name: ENGINE_RUNNING_TASKS_METRIC.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+        };
+
+        assert_eq!(running_engine_tasks(&metric(&[])), 0);
+        assert_eq!(running_engine_tasks(&metric(&["0", "0"])), 0);
+        assert_eq!(running_engine_tasks(&metric(&["0", "2"])), 2);
+        assert_eq!(running_engine_tasks(&metric(&["500m"])), 1);
+        assert_eq!(running_engine_tasks(&metric(&["not a quantity"])), 1);
+    }
+}
@@ -1,13 +1,16 @@
 use crate::errors::{CommandError, EngineError, ErrorMessageVerbosity};
 use crate::events::Stage::Infrastructure;
 use crate::events::{EventDetails, InfrastructureStep};
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::deploy_helms::{HelmInfraContext, HelmInfraResources};
 use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
 use crate::infrastructure::action::kubeconfig_helper::update_kubeconfig_file;
 use crate::infrastructure::action::kubectl_utils::check_workers_on_create;
+use crate::infrastructure::action::pause_helper::{forget_paused_node_groups, load_paused_node_groups};
 use crate::infrastructure::action::scaleway::helm_charts::KapsuleHelmsDeployment;
 use crate::infrastructure::action::scaleway::nodegroup::{get_existing_sanitized_node_groups, get_node_group_info};
 use crate::infrastructure::action::scaleway::ScalewayQoveryTerraformOutput;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::{InfraLogger, ToInfraTeraContext};
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::scaleway::kapsule::{Kapsule, ScwNodeGroupErrors};
@@ -17,6 +20,7 @@ use crate::utilities::envs_to_string;
 use retry::delay::Fixed;
 use retry::OperationResult;
 use scaleway_api_rs::models::ScalewayK8sV1Cluster;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 pub fn create_kapsule_cluster(
@@ -27,7 +31,7 @@ pub fn create_kapsule_cluster(
     let event_details = cluster.get_event_details(Infrastructure(InfrastructureStep::Create));
     logger.info("Preparing SCW cluster deployment.");
 
-    let temp_dir = cluster.temp_dir();
+    let workspace = cluster.workspace();
     logger.info("Create Qovery managed object storage buckets");
 
     // Logs bucket
@@ -42,14 +46,39 @@ pub fn create_kapsule_cluster(
     }
 
     // terraform deployment dedicated to cloud resources
-    let tera_context = cluster.to_infra_tera_context(infra_ctx)?;
+    let mut tera_context = cluster.to_infra_tera_context(infra_ctx)?;
+
+    // a resumed cluster gets back the nodes it had when paused
+    let paused_node_groups = match infra_ctx.context().is_first_cluster_deployment() {
+        true => None,
+        false => infra_ctx
+            .mk_kube_client()
+            .ok()
+            .and_then(|kube_client| load_paused_node_groups(kube_client.client())),
+    };
+    if let Some(paused_node_groups) = &paused_node_groups {
+        let desired_size = cluster
+            .nodes_groups
+            .iter()
+            .filter_map(|node_group| {
+                paused_node_groups
+                    .desired_nodes_on_resume(&node_group.name, node_group.min_nodes, node_group.max_nodes)
+                    .map(|desired_nodes| (node_group.name.clone(), desired_nodes))
+            })
+            .collect::<BTreeMap<_, _>>();
+        tera_context.insert("scw_ks_worker_nodes_desired_size", &desired_size);
+    }
+
     let tf_action = TerraformInfraResources::new(
         tera_context.clone(),
         cluster.template_directory.join("terraform"),
-        temp_dir.join("terraform"),
+        workspace.directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         cluster.context().is_dry_run_deploy(),
+        cluster.advanced_settings().terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(cluster),
+        infra_ctx.abort(),
     );
     let qovery_terraform_output: ScalewayQoveryTerraformOutput = tf_action.create(&logger)?;
     update_kubeconfig_file(cluster, &qovery_terraform_output.kubeconfig)?;
@@ -64,7 +93,7 @@ pub fn create_kapsule_cluster(
     sanitize_node_groups(cluster, event_details.clone(), cluster_info, &logger)?;
 
     // ensure all nodes are ready on Kubernetes
-    check_workers_on_create(cluster, infra_ctx.cloud_provider(), None)
+    check_workers_on_create(cluster, infra_ctx.cloud_provider(), None, &logger)
         .map_err(|e| Box::new(EngineError::new_k8s_node_not_ready(event_details.clone(), e)))?;
     logger.info("Kubernetes nodes have been successfully created");
 
@@ -74,16 +103,23 @@ pub fn create_kapsule_cluster(
             tera_context,
             PathBuf::from(infra_ctx.context().lib_root_dir()),
             cluster.template_directory.clone(),
-            cluster.temp_dir().join("helms"),
+            cluster.workspace().directory(WorkspaceDirectory::Chart),
             event_details.clone(),
             envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
             cluster.context().is_dry_run_deploy(),
+            infra_ctx.abort(),
         ),
         qovery_terraform_output,
         cluster,
     );
     helms_deployments.deploy_charts(infra_ctx, &logger)?;
 
+    if paused_node_groups.is_some() && !cluster.context().is_dry_run_deploy() {
+        if let Err(err) = forget_paused_node_groups(infra_ctx.mk_kube_client()?.client()) {
+            logger.warn(err);
+        }
+    }
+
     Ok(())
 }
 
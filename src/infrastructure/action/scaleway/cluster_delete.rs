@@ -1,10 +1,12 @@
 use crate::errors::EngineError;
 use crate::events::InfrastructureStep;
 use crate::events::Stage::Infrastructure;
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::delete_kube_apps::delete_kube_apps;
 use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
 use crate::infrastructure::action::kubeconfig_helper::update_kubeconfig_file;
 use crate::infrastructure::action::scaleway::ScalewayQoveryTerraformOutput;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::{InfraLogger, ToInfraTeraContext};
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::scaleway::kapsule::Kapsule;
@@ -27,10 +29,13 @@ pub fn delete_kapsule_cluster(
     let tf_resources = TerraformInfraResources::new(
         tera_context.clone(),
         cluster.template_directory.join("terraform"),
-        cluster.temp_dir().join("terraform"),
+        cluster.workspace().directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         cluster.context().is_dry_run_deploy(),
+        cluster.advanced_settings().terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(cluster),
+        infra_ctx.abort(),
     );
 
     // should apply before destroy to be sure destroy will compute on all resources
@@ -48,6 +53,12 @@ pub fn delete_kapsule_cluster(
 
     logger.info(format!("Deleting Kubernetes cluster {}/{}", cluster.name(), cluster.short_id()));
     logger.info("Running Terraform destroy");
+    if let Some(private_network_id) = &cluster.options.existing_private_network_id {
+        // only referenced by terraform, and cluster additional resources deletion is disabled
+        logger.info(format!(
+            "Private network {private_network_id} is not managed by Qovery, it will be kept"
+        ));
+    }
     tf_resources.delete(&[], &logger)?;
 
     logger.info("Kubernetes cluster successfully deleted");
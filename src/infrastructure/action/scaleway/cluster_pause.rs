@@ -1,14 +1,21 @@
 use crate::errors::EngineError;
 use crate::events::InfrastructureStep;
 use crate::events::Stage::Infrastructure;
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
 use crate::infrastructure::action::kubectl_utils::check_workers_on_pause;
+use crate::infrastructure::action::pause_helper::{
+    check_no_engine_task_running, cordon_and_drain_nodes, record_paused_node_groups, PausedNodeGroups,
+};
+use crate::infrastructure::action::scaleway::nodegroup::get_node_groups_current_size;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::{InfraLogger, ToInfraTeraContext};
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::scaleway::kapsule::Kapsule;
 use crate::infrastructure::models::kubernetes::Kubernetes;
 use crate::io_models::models::NodeGroupsFormat;
 use crate::utilities::envs_to_string;
+use std::time::Duration;
 
 pub fn pause_kapsule_cluster(
     cluster: &Kapsule,
@@ -17,8 +24,33 @@ pub fn pause_kapsule_cluster(
 ) -> Result<(), Box<EngineError>> {
     let event_details = cluster.get_event_details(Infrastructure(InfrastructureStep::Pause));
     logger.info("Preparing cluster pause.");
+    check_no_engine_task_running(cluster, infra_ctx.cloud_provider(), event_details.clone(), &logger)?;
 
-    let temp_dir = cluster.temp_dir();
+    if !cluster.context().is_dry_run_deploy() {
+        let kube_client = infra_ctx.mk_kube_client()?;
+
+        // the resume restores the current size of the pools, instead of their min nodes
+        if let Some(cluster_id) = cluster.get_scw_cluster_info()?.and_then(|cluster_info| cluster_info.id) {
+            let recorded = get_node_groups_current_size(cluster, &cluster_id)
+                .map_err(|err| err.message_safe())
+                .and_then(|desired_nodes| {
+                    record_paused_node_groups(kube_client.client(), &PausedNodeGroups { desired_nodes })
+                });
+            if let Err(err) = recorded {
+                logger.warn(format!("{err}, node groups will be resumed with their min nodes"));
+            }
+        }
+
+        cordon_and_drain_nodes(
+            &kube_client,
+            event_details.clone(),
+            None,
+            Duration::from_secs(cluster.advanced_settings().k8s_pause_drain_timeout_in_sec as u64),
+            &logger,
+        )?;
+    }
+
+    let workspace = cluster.workspace();
 
     // generate terraform files and copy them into temp dir
     let mut tera_context = cluster.to_infra_tera_context(infra_ctx)?;
@@ -29,14 +61,17 @@ pub fn pause_kapsule_cluster(
     let tf_resources = TerraformInfraResources::new(
         tera_context,
         cluster.template_directory.join("terraform"),
-        temp_dir.join("terraform"),
+        workspace.directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
         cluster.context().is_dry_run_deploy(),
+        cluster.advanced_settings().terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(cluster),
+        infra_ctx.abort(),
     );
 
     logger.info("Pausing cluster deployment.");
-    tf_resources.pause(&["scw_ks_worker_nodes"])?;
+    tf_resources.pause(&["scw_ks_worker_nodes"], &logger)?;
 
     if let Err(e) = check_workers_on_pause(cluster, infra_ctx.cloud_provider(), None) {
         return Err(Box::new(EngineError::new_k8s_node_not_ready(event_details, e)));
@@ -0,0 +1,52 @@
+use crate::errors::EngineError;
+use crate::events::InfrastructureStep;
+use crate::events::Stage::Infrastructure;
+use crate::fs::WorkspaceDirectory;
+use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
+use crate::infrastructure::action::kubectl_utils::check_workers_on_create;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
+use crate::infrastructure::action::{InfraLogger, ToInfraTeraContext};
+use crate::infrastructure::infrastructure_context::InfrastructureContext;
+use crate::infrastructure::models::kubernetes::scaleway::kapsule::Kapsule;
+use crate::infrastructure::models::kubernetes::Kubernetes;
+use crate::utilities::envs_to_string;
+
+pub fn repair_kapsule_node_groups(
+    cluster: &Kapsule,
+    infra_ctx: &InfrastructureContext,
+    logger: impl InfraLogger,
+) -> Result<(), Box<EngineError>> {
+    let event_details = cluster.get_event_details(Infrastructure(InfrastructureStep::Create));
+    logger.info("Preparing SCW cluster node groups repair.");
+
+    // generate terraform files and copy them into temp dir
+    let tera_context = cluster.to_infra_tera_context(infra_ctx)?;
+    let tf_resources = TerraformInfraResources::new(
+        tera_context,
+        cluster.template_directory.join("terraform"),
+        cluster.workspace().directory(WorkspaceDirectory::Terraform),
+        event_details.clone(),
+        envs_to_string(infra_ctx.cloud_provider().credentials_environment_variables()),
+        cluster.context().is_dry_run_deploy(),
+        cluster.advanced_settings().terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(cluster),
+        infra_ctx.abort(),
+    );
+
+    // pools of ks-workers-nodes.j2.tf, indexed from 1 in the template
+    let targets = (1..=cluster.nodes_groups.len())
+        .map(|index| format!("scaleway_k8s_pool.kubernetes_cluster_workers_{index}"))
+        .collect::<Vec<_>>();
+    tf_resources.repair(&targets, &["scaleway_k8s_pool."], &logger)?;
+
+    if !cluster.context().is_dry_run_deploy() {
+        check_workers_on_create(cluster, infra_ctx.cloud_provider(), None, &logger)
+            .map_err(|e| Box::new(EngineError::new_k8s_node_not_ready(event_details, e)))?;
+    }
+
+    logger.info(format!(
+        "Kubernetes cluster {} node groups successfully repaired",
+        cluster.name()
+    ));
+    Ok(())
+}
@@ -1,10 +1,12 @@
 use crate::errors::EngineError;
 use crate::events::InfrastructureStep;
 use crate::events::Stage::Infrastructure;
+use crate::fs::WorkspaceDirectory;
 use crate::infrastructure::action::delete_kube_apps::prepare_kube_upgrade;
 use crate::infrastructure::action::deploy_terraform::TerraformInfraResources;
 use crate::infrastructure::action::kubectl_utils::check_workers_on_upgrade;
 use crate::infrastructure::action::scaleway::ScalewayQoveryTerraformOutput;
+use crate::infrastructure::action::terraform_state_backup::TerraformStateBackup;
 use crate::infrastructure::action::{InfraLogger, ToInfraTeraContext};
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::scaleway::kapsule::Kapsule;
@@ -19,7 +21,7 @@ pub fn upgrade_kapsule_cluster(
     let event_details = cluster.get_event_details(Infrastructure(InfrastructureStep::Upgrade));
     logger.info("Preparing cluster upgrade process.");
 
-    let temp_dir = cluster.temp_dir();
+    let workspace = cluster.workspace();
     // generate terraform files and copy them into temp dir
 
     //
@@ -40,10 +42,13 @@ pub fn upgrade_kapsule_cluster(
     let tf_resources = TerraformInfraResources::new(
         tera_context,
         cluster.template_directory.join("terraform"),
-        temp_dir.join("terraform"),
+        workspace.directory(WorkspaceDirectory::Terraform),
         event_details.clone(),
         vec![],
         cluster.context().is_dry_run_deploy(),
+        cluster.advanced_settings().terraform_allow_destructive_replacements,
+        TerraformStateBackup::for_cluster(cluster),
+        infra_ctx.abort(),
     );
     let _: ScalewayQoveryTerraformOutput = tf_resources.create(&logger)?;
 
@@ -37,6 +37,7 @@ use crate::infrastructure::helm_charts::prometheus_adapter_chart::PrometheusAdap
 use crate::infrastructure::helm_charts::qovery_cert_manager_webhook_chart::QoveryCertManagerWebhookChart;
 use crate::infrastructure::helm_charts::qovery_cluster_agent_chart::QoveryClusterAgentChart;
 use crate::infrastructure::helm_charts::qovery_priority_class_chart::QoveryPriorityClassChart;
+use crate::infrastructure::helm_charts::qovery_registry_mirrors_chart::QoveryRegistryMirrorsChart;
 use crate::io_models::QoveryIdentifier;
 use std::collections::HashSet;
 use std::iter::FromIterator;
@@ -112,6 +113,20 @@ pub fn kapsule_helm_charts(
     )
     .to_common_helm_chart()?;
 
+    let services_logging = chart_config_prerequisites
+        .cluster_advanced_settings
+        .logging_services
+        .iter()
+        .map(|service_logging| {
+            service_logging.to_model(
+                chart_config_prerequisites
+                    .cluster_advanced_settings
+                    .loki_log_retention_in_week
+                    * 7,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
     // Promtail
     let promtail = match chart_config_prerequisites.ff_log_history_enabled {
         false => None,
@@ -125,6 +140,7 @@ pub fn kapsule_helm_charts(
                 HelmChartNamespaces::KubeSystem,
                 PriorityClass::Default,
                 false,
+                services_logging.clone(),
             )
             .to_common_helm_chart()?,
         ),
@@ -155,6 +171,7 @@ pub fn kapsule_helm_charts(
                 HelmChartResourcesConstraintType::ChartDefault,
                 HelmChartTimeout::ChartDefault,
                 false,
+                services_logging.clone(),
             )
             .to_common_helm_chart()?,
         ),
@@ -361,6 +378,12 @@ pub fn kapsule_helm_charts(
             .nginx_controller_http_snippet
             .as_ref()
             .map(|nginx_controller_http_snippet_io| nginx_controller_http_snippet_io.to_model()),
+        chart_config_prerequisites
+            .cluster_advanced_settings
+            .nginx_custom_error_pages
+            .as_ref()
+            .map(|nginx_custom_error_pages_io| nginx_custom_error_pages_io.to_model())
+            .transpose()?,
     )
     .to_common_helm_chart()?;
 
@@ -417,6 +440,17 @@ pub fn kapsule_helm_charts(
     )
     .to_common_helm_chart()?;
 
+    // Docker Hub mirrors of the nodes containerd
+    let q_registry_mirrors_chart = QoveryRegistryMirrorsChart::new(
+        chart_prefix_path,
+        !chart_config_prerequisites
+            .cluster_advanced_settings
+            .registry_mirrors
+            .is_empty(),
+        HelmChartNamespaces::KubeSystem,
+    )
+    .to_common_helm_chart()?;
+
     let qovery_engine = CommonChart {
         chart_info: ChartInfo {
             name: "qovery-engine".to_string(),
@@ -533,6 +567,7 @@ pub fn kapsule_helm_charts(
         Box::new(coredns_config),
         Box::new(vpa),
         Box::new(q_priority_class_chart),
+        Box::new(q_registry_mirrors_chart),
     ];
 
     let mut level_2: Vec<Box<dyn HelmChart>> = vec![];
@@ -3,6 +3,7 @@ use crate::events::InfrastructureStep;
 use crate::infrastructure::action::scaleway::cluster_create::create_kapsule_cluster;
 use crate::infrastructure::action::scaleway::cluster_delete::delete_kapsule_cluster;
 use crate::infrastructure::action::scaleway::cluster_pause::pause_kapsule_cluster;
+use crate::infrastructure::action::scaleway::cluster_repair::repair_kapsule_node_groups;
 use crate::infrastructure::action::scaleway::cluster_upgrade::upgrade_kapsule_cluster;
 use crate::infrastructure::action::InfrastructureAction;
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
@@ -14,6 +15,7 @@ use serde_derive::{Deserialize, Serialize};
 mod cluster_create;
 mod cluster_delete;
 mod cluster_pause;
+mod cluster_repair;
 mod cluster_upgrade;
 mod helm_charts;
 mod nodegroup;
@@ -49,6 +51,11 @@ impl InfrastructureAction for Kapsule {
             upgrade_kapsule_cluster(self, infra_ctx, kubernetes_upgrade_status, logger)
         })
     }
+
+    fn repair_node_groups(&self, infra_ctx: &InfrastructureContext) -> Result<(), Box<EngineError>> {
+        let logger = mk_logger(infra_ctx.kubernetes(), InfrastructureStep::Create);
+        send_progress_on_long_task(self, Action::Create, || repair_kapsule_node_groups(self, infra_ctx, logger))
+    }
 }
 
 use super::utils::{from_terraform_value, mk_logger};
@@ -4,6 +4,7 @@ use crate::infrastructure::models::kubernetes::scaleway::node::ScwNodeGroup;
 use crate::infrastructure::models::kubernetes::Kubernetes;
 use crate::runtime::block_on;
 use scaleway_api_rs::models::ScalewayK8sV1Cluster;
+use std::collections::BTreeMap;
 
 pub(super) fn get_existing_sanitized_node_groups(
     cluster: &Kapsule,
@@ -68,6 +69,45 @@ pub(super) fn get_existing_sanitized_node_groups(
     Ok(nodegroup_pool)
 }
 
+/// Current size of the pools, by name of the node group they have been created for
+pub(super) fn get_node_groups_current_size(
+    cluster: &Kapsule,
+    cluster_id: &str,
+) -> Result<BTreeMap<String, i32>, CommandError> {
+    let pools = block_on(scaleway_api_rs::apis::pools_api::list_pools(
+        &cluster.get_configuration(),
+        cluster.region(),
+        cluster_id,
+        None,
+        None,
+        None,
+        None,
+        None,
+    ))
+    .map_err(|e| {
+        CommandError::new(
+            format!("Error while trying to get SCW pool info from cluster {cluster_id}."),
+            Some(e.to_string()),
+            None,
+        )
+    })?;
+
+    Ok(pools
+        .pools
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|pool| {
+            // see the tags of ks-workers-nodes.j2.tf
+            let node_group_name = pool
+                .tags
+                .unwrap_or_default()
+                .iter()
+                .find_map(|tag| tag.strip_prefix("QoveryNodeGroupName:").map(|name| name.to_string()))?;
+            Some((node_group_name, pool.size as i32))
+        })
+        .collect())
+}
+
 pub(super) fn get_node_group_info(cluster: &Kapsule, pool_id: &str) -> Result<ScwNodeGroup, ScwNodeGroupErrors> {
     let pool = match block_on(scaleway_api_rs::apis::pools_api::get_pool(
         &cluster.get_configuration(),
@@ -1,16 +1,19 @@
 use crate::environment::models::domain::ToTerraformString;
+use crate::environment::models::scaleway::ScwRegion;
 use crate::environment::models::third_parties::LetsEncryptConfig;
 use crate::errors::EngineError;
-use crate::events::InfrastructureStep;
 use crate::events::Stage::Infrastructure;
+use crate::events::{EventDetails, InfrastructureStep};
 use crate::infrastructure::action::ToInfraTeraContext;
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::kubernetes::scaleway::kapsule::Kapsule;
 use crate::infrastructure::models::kubernetes::Kubernetes;
 use crate::io_models::context::Features;
+use crate::io_models::models::{NodeGroupCapacityType, NodeGroups};
 use crate::string::terraform_list_format;
-use reqwest::header;
+use reqwest::{header, StatusCode};
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use tera::Context as TeraContext;
 
 impl ToInfraTeraContext for Kapsule {
@@ -86,6 +89,7 @@ fn kapsule_tera_context(cluster: &Kapsule, infra_ctx: &InfrastructureContext) ->
         "organization_long_id",
         &infra_ctx.cloud_provider().organization_long_id().to_string(),
     );
+    context.insert("execution_id", infra_ctx.context().execution_id());
     context.insert("object_storage_kubeconfig_bucket", &cluster.kubeconfig_bucket_name());
     context.insert("object_storage_logs_bucket", &cluster.logs_bucket_name());
 
@@ -154,11 +158,19 @@ fn kapsule_tera_context(cluster: &Kapsule, infra_ctx: &InfrastructureContext) ->
     context.insert("grafana_admin_password", cluster.options.grafana_admin_password.as_str());
 
     // Kubernetes workers
+    check_node_groups_capacity_type(event_details.clone(), &cluster.nodes_groups)?;
     context.insert("scw_ks_worker_nodes", &cluster.nodes_groups);
+    // set when the cluster is resumed, pools are created with their min nodes otherwise
+    context.insert("scw_ks_worker_nodes_desired_size", &BTreeMap::<String, i32>::new());
     context.insert("scw_ks_pool_autoscale", &true);
+    let autoscaler_settings = cluster
+        .advanced_settings()
+        .cluster_autoscaler_settings(false, event_details.clone())?;
+    context.insert("scw_ks_autoscaler_config", &autoscaler_settings.to_scaleway_autoscaler_config());
 
     // Advanced settings
     context.insert("load_balancer_size", &cluster.advanced_settings().load_balancer_size);
+    context.insert("registry_mirrors", &cluster.advanced_settings().containerd_registry_mirrors());
     context.insert(
         "resource_expiration_in_seconds",
         &cluster.advanced_settings().pleco_resources_ttl,
@@ -168,7 +180,11 @@ fn kapsule_tera_context(cluster: &Kapsule, infra_ctx: &InfrastructureContext) ->
     // Scaleway added a new constraint on scaleway_k8s_cluster to be linked to a private network
     // For existing clusters, exerything is OK
     // For new clusters, we need to inject a resource scaleway_vpc_private_network
-    let mut create_private_network = if cluster.context().is_first_cluster_deployment() {
+    // Unless the cluster is attached to a customer owned private network, which is only referenced
+    let mut create_private_network = if let Some(private_network_id) = &cluster.options.existing_private_network_id {
+        check_existing_private_network(cluster, private_network_id, event_details.clone())?;
+        false
+    } else if cluster.context().is_first_cluster_deployment() {
         true
     } else {
         let mut headers = header::HeaderMap::new();
@@ -205,10 +221,20 @@ fn kapsule_tera_context(cluster: &Kapsule, infra_ctx: &InfrastructureContext) ->
             }
         }
     };
-    if cluster.advanced_settings().scaleway_enable_private_network_migration {
+    if cluster.advanced_settings().scaleway_enable_private_network_migration
+        && cluster.options.existing_private_network_id.is_none()
+    {
         create_private_network = true;
     }
     context.insert("create_private_network", &create_private_network);
+    context.insert(
+        "existing_private_network_id",
+        cluster
+            .options
+            .existing_private_network_id
+            .as_deref()
+            .unwrap_or_default(),
+    );
 
     if let Some(nginx_controller_log_format_upstream) =
         &cluster.advanced_settings().nginx_controller_log_format_upstream
@@ -226,13 +252,305 @@ fn kapsule_tera_context(cluster: &Kapsule, infra_ctx: &InfrastructureContext) ->
     Ok(context)
 }
 
+/// Private networks are regional, the existing one is looked up in the cluster region first, then in the others
+/// to tell the customer where it actually is.
+fn check_existing_private_network(
+    cluster: &Kapsule,
+    private_network_id: &str,
+    event_details: EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let mut headers = header::HeaderMap::new();
+    headers.insert("Content-Type", "application/json".parse().unwrap());
+    headers.insert("X-Auth-Token", cluster.options.scaleway_secret_key.parse().unwrap());
+    let http = reqwest::blocking::Client::new();
+
+    let cluster_region = cluster.zone.region();
+    let mut private_network_region = None;
+    for region in std::iter::once(cluster_region).chain(
+        [ScwRegion::Paris, ScwRegion::Amsterdam, ScwRegion::Warsaw]
+            .into_iter()
+            .filter(|region| *region != cluster_region),
+    ) {
+        let url = format!(
+            "https://api.scaleway.com/vpc/v2/regions/{}/private-networks/{}",
+            region.as_str(),
+            private_network_id
+        );
+        match http.get(url).headers(headers.clone()).send() {
+            Ok(it) if it.status().is_success() => {
+                private_network_region = Some(region);
+                break;
+            }
+            Ok(it) if it.status() == StatusCode::NOT_FOUND => continue,
+            Ok(it) => {
+                return Err(Box::new(EngineError::new_scaleway_cannot_fetch_private_networks(
+                    event_details,
+                    format!(
+                        "unexpected status `{}` for private network `{}`",
+                        it.status(),
+                        private_network_id
+                    ),
+                )));
+            }
+            Err(err) => {
+                return Err(Box::new(EngineError::new_scaleway_cannot_fetch_private_networks(
+                    event_details,
+                    err.to_string(),
+                )));
+            }
+        }
+    }
+
+    check_existing_private_network_region(event_details, private_network_id, cluster_region, private_network_region)
+}
+
+fn check_existing_private_network_region(
+    event_details: EventDetails,
+    private_network_id: &str,
+    cluster_region: ScwRegion,
+    private_network_region: Option<ScwRegion>,
+) -> Result<(), Box<EngineError>> {
+    match private_network_region {
+        None => Err(Box::new(EngineError::new_scaleway_private_network_not_found(
+            event_details,
+            private_network_id,
+            cluster_region.as_str(),
+        ))),
+        Some(region) if region != cluster_region => {
+            Err(Box::new(EngineError::new_scaleway_private_network_in_wrong_region(
+                event_details,
+                private_network_id,
+                cluster_region.as_str(),
+                region.as_str(),
+            )))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 struct PrivateNetworksDto {
     private_networks: Vec<PrivateNetworkDto>,
     total_count: u32,
 }
 
+/// Kapsule pools run on-demand instances only, Scaleway does not sell spot capacity
+fn check_node_groups_capacity_type(
+    event_details: EventDetails,
+    node_groups: &[NodeGroups],
+) -> Result<(), Box<EngineError>> {
+    match node_groups
+        .iter()
+        .find(|node_group| node_group.capacity_type != NodeGroupCapacityType::OnDemand)
+    {
+        Some(node_group) => Err(Box::new(EngineError::new_node_group_capacity_type_not_supported(
+            event_details,
+            node_group.name.clone(),
+            node_group.capacity_type.to_string(),
+            "Scaleway Kapsule pools only run on-demand instances".to_string(),
+        ))),
+        None => Ok(()),
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 struct PrivateNetworkDto {
     project_id: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{check_existing_private_network_region, check_node_groups_capacity_type};
+    use crate::environment::models::scaleway::ScwRegion;
+    use crate::errors::Tag;
+    use crate::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
+    use crate::io_models::models::{CpuArchitecture, NodeGroupCapacityType, NodeGroups, Taint, TaintEffect};
+    use crate::io_models::QoveryIdentifier;
+    use std::env;
+    use tera::{Context, Tera};
+    use uuid::Uuid;
+
+    fn node_group(capacity_type: NodeGroupCapacityType) -> NodeGroups {
+        NodeGroups {
+            name: "gpu".to_string(),
+            id: None,
+            min_nodes: 1,
+            max_nodes: 3,
+            desired_nodes: None,
+            instance_type: "GPU-3070-S".to_string(),
+            disk_size_in_gib: 50,
+            instance_architecture: CpuArchitecture::AMD64,
+            labels: BTreeMap::new(),
+            taints: vec![],
+            capacity_type,
+        }
+    }
+
+    #[test]
+    fn test_check_node_groups_capacity_type() {
+        let event_details = EventDetails::new(
+            None,
+            QoveryIdentifier::default(),
+            QoveryIdentifier::default(),
+            "".to_string(),
+            Stage::Infrastructure(InfrastructureStep::LoadConfiguration),
+            Transmitter::Kubernetes(Uuid::new_v4(), "".to_string()),
+        );
+
+        assert!(
+            check_node_groups_capacity_type(event_details.clone(), &[node_group(NodeGroupCapacityType::OnDemand)])
+                .is_ok()
+        );
+        for capacity_type in [
+            NodeGroupCapacityType::Spot,
+            NodeGroupCapacityType::Mixed {
+                on_demand_base: 1,
+                spot_percentage: 50,
+            },
+        ] {
+            let err = check_node_groups_capacity_type(
+                event_details.clone(),
+                &[node_group(NodeGroupCapacityType::OnDemand), node_group(capacity_type)],
+            )
+            .unwrap_err();
+            assert_eq!(err.tag(), &Tag::NotImplementedError);
+        }
+    }
+
+    #[test]
+    fn test_ks_workers_nodes_labels_and_taints_rendering() {
+        let node_group = NodeGroups {
+            labels: BTreeMap::from([("team".to_string(), "ml".to_string())]),
+            taints: vec![
+                Taint {
+                    key: "nvidia.com/gpu".to_string(),
+                    value: "true".to_string(),
+                    effect: TaintEffect::NoSchedule,
+                },
+                Taint {
+                    key: "spot".to_string(),
+                    value: "".to_string(),
+                    effect: TaintEffect::NoExecute,
+                },
+            ],
+            ..node_group(NodeGroupCapacityType::OnDemand)
+        };
+        let mut context = Context::new();
+        context.insert("scw_ks_worker_nodes", &vec![node_group]);
+        context.insert("scw_ks_worker_nodes_desired_size", &BTreeMap::<String, i32>::new());
+        context.insert("scw_ks_pool_autoscale", &true);
+
+        let template = std::fs::read_to_string(format!(
+            "{}/lib/scaleway/bootstrap/terraform/ks-workers-nodes.j2.tf",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .to_str()
+                .expect("Impossible to convert current directory to string"),
+        ))
+        .unwrap();
+        let rendered = Tera::one_off(&template, &context, false).unwrap();
+
+        assert!(rendered.contains(
+            r#"tags          =  concat(local.tags_ks_list, ["QoveryNodeGroupName:gpu", "QoveryNodeGroupId:${var.kubernetes_cluster_id}_GPU-3070-S_1", "noprefix=team=ml", "taint=noprefix=nvidia.com/gpu=true:NoSchedule", "taint=noprefix=spot=:NoExecute"])"#
+        ));
+    }
+
+    #[test]
+    fn test_ks_workers_nodes_desired_size_rendering() {
+        let mut context = Context::new();
+        context.insert(
+            "scw_ks_worker_nodes",
+            &vec![
+                node_group(NodeGroupCapacityType::OnDemand),
+                NodeGroups {
+                    name: "default".to_string(),
+                    ..node_group(NodeGroupCapacityType::OnDemand)
+                },
+            ],
+        );
+        // paused with 2 nodes in the gpu pool
+        context.insert("scw_ks_worker_nodes_desired_size", &BTreeMap::from([("gpu".to_string(), 2)]));
+        context.insert("scw_ks_pool_autoscale", &true);
+
+        let template = std::fs::read_to_string(format!(
+            "{}/lib/scaleway/bootstrap/terraform/ks-workers-nodes.j2.tf",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .to_str()
+                .expect("Impossible to convert current directory to string"),
+        ))
+        .unwrap();
+        let rendered = Tera::one_off(&template, &context, false).unwrap();
+
+        let sizes = rendered
+            .lines()
+            .filter(|line| line.trim_start().starts_with("size "))
+            .map(|line| line.trim())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![r#"size                = "2""#, r#"size                = "1""#]);
+    }
+
+    fn rendered_ks_master_cluster(create_private_network: bool, existing_private_network_id: &str) -> String {
+        let mut context = Context::new();
+        context.insert("create_private_network", &create_private_network);
+        context.insert("existing_private_network_id", existing_private_network_id);
+        context.insert("scw_ks_autoscaler_config", &BTreeMap::<String, String>::new());
+
+        let template = std::fs::read_to_string(format!(
+            "{}/lib/scaleway/bootstrap/terraform/ks-master-cluster.j2.tf",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .to_str()
+                .expect("Impossible to convert current directory to string"),
+        ))
+        .unwrap();
+        Tera::one_off(&template, &context, false).unwrap()
+    }
+
+    #[test]
+    fn test_ks_master_cluster_private_network_rendering() {
+        // Qovery managed private network
+        let rendered = rendered_ks_master_cluster(true, "");
+        assert!(rendered.contains(r#"resource "scaleway_vpc_private_network" "private_network""#));
+        assert!(rendered.contains("private_network_id = scaleway_vpc_private_network.private_network.id"));
+        assert!(rendered.contains("delete_additional_resources = true"));
+
+        // existing customer private network: referenced, never created nor deleted
+        let rendered = rendered_ks_master_cluster(false, "3f0f2d2c-6a4e-4d6c-8a57-0d1c2a3b4c5d");
+        assert!(!rendered.contains("scaleway_vpc_private_network"));
+        assert!(rendered.contains(r#"private_network_id = "3f0f2d2c-6a4e-4d6c-8a57-0d1c2a3b4c5d""#));
+        assert!(rendered.contains("delete_additional_resources = false"));
+    }
+
+    #[test]
+    fn test_check_existing_private_network_region() {
+        let event_details = EventDetails::new(
+            None,
+            QoveryIdentifier::default(),
+            QoveryIdentifier::default(),
+            "".to_string(),
+            Stage::Infrastructure(InfrastructureStep::LoadConfiguration),
+            Transmitter::Kubernetes(Uuid::new_v4(), "".to_string()),
+        );
+
+        assert!(check_existing_private_network_region(
+            event_details.clone(),
+            "pn",
+            ScwRegion::Paris,
+            Some(ScwRegion::Paris)
+        )
+        .is_ok());
+        assert_eq!(
+            check_existing_private_network_region(event_details.clone(), "pn", ScwRegion::Paris, None)
+                .unwrap_err()
+                .tag(),
+            &Tag::ScalewayPrivateNetworkNotFound
+        );
+        assert_eq!(
+            check_existing_private_network_region(event_details, "pn", ScwRegion::Paris, Some(ScwRegion::Warsaw))
+                .unwrap_err()
+                .tag(),
+            &Tag::ScalewayPrivateNetworkInWrongRegion
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use crate::infrastructure::models::kubernetes::Kubernetes;
+use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+use crate::infrastructure::models::object_storage::ObjectStorage;
+use std::io::Write;
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+const BACKUPS_PREFIX: &str = "terraform-state-backups";
+const INDEX_KEY: &str = "terraform-state-backups/index.json";
+
+/// Copies of the terraform state in the cluster bucket, one per execution, the oldest ones beyond the retention
+/// are deleted. Buckets cannot be listed, so the keys of the copies are kept in an index object, oldest first.
+pub struct TerraformStateBackup<'a> {
+    object_storage: &'a dyn ObjectStorage,
+    bucket_name: String,
+    execution_id: String,
+    retention: usize,
+}
+
+impl<'a> TerraformStateBackup<'a> {
+    pub fn new(
+        object_storage: &'a dyn ObjectStorage,
+        bucket_name: String,
+        execution_id: String,
+        retention: usize,
+    ) -> TerraformStateBackup<'a> {
+        TerraformStateBackup {
+            object_storage,
+            bucket_name,
+            execution_id,
+            retention,
+        }
+    }
+
+    /// Backups in the bucket of the cluster, none when the cluster has no object storage or backups are disabled
+    pub fn for_cluster(kubernetes: &'a dyn Kubernetes) -> Option<TerraformStateBackup<'a>> {
+        let retention = kubernetes.advanced_settings().terraform_state_backups_retention as usize;
+        if retention == 0 {
+            return None;
+        }
+
+        kubernetes.object_storage().map(|object_storage| {
+            TerraformStateBackup::new(
+                object_storage,
+                format!("qovery-kubeconfigs-{}", kubernetes.short_id()),
+                kubernetes.context().execution_id().to_string(),
+                retention,
+            )
+        })
+    }
+
+    pub fn bucket_name(&self) -> &str {
+        &self.bucket_name
+    }
+
+    /// Key of the copy of the current execution
+    pub fn key(&self) -> String {
+        format!("{}/{}.tfstate", BACKUPS_PREFIX, self.execution_id)
+    }
+
+    /// Uploads the state, then deletes the oldest copies beyond the retention.
+    /// Copies failing to be deleted stay in the index to be deleted next time, their errors are returned.
+    pub fn backup(&self, state: &str, temp_dir: &Path) -> Result<Vec<ObjectStorageError>, ObjectStorageError> {
+        let key = self.key();
+        self.upload(&key, state.as_bytes(), temp_dir)?;
+
+        // a re-run of the execution overwrites its copy, it only moves to the end of the index
+        let mut keys = self.index();
+        keys.retain(|existing_key| existing_key != &key);
+        keys.push(key);
+
+        let expired_count = keys.len().saturating_sub(self.retention);
+        let mut kept_keys = Vec::with_capacity(keys.len());
+        let mut pruning_errors = vec![];
+        for (ix, key) in keys.into_iter().enumerate() {
+            if ix >= expired_count {
+                kept_keys.push(key);
+                continue;
+            }
+
+            if let Err(err) = self.object_storage.delete_object(&self.bucket_name, &key) {
+                pruning_errors.push(err);
+                kept_keys.push(key);
+            }
+        }
+
+        let index = serde_json::to_vec(&kept_keys).unwrap_or_default();
+        self.upload(INDEX_KEY, &index, temp_dir)?;
+
+        Ok(pruning_errors)
+    }
+
+    /// Keys of the copies, oldest first. A missing or unreadable index is considered empty: copies it listed are
+    /// then not deleted anymore, but it does not prevent new backups.
+    fn index(&self) -> Vec<String> {
+        self.object_storage
+            .get_object(&self.bucket_name, INDEX_KEY)
+            .ok()
+            .and_then(|index| serde_json::from_slice(&index.value).ok())
+            .unwrap_or_default()
+    }
+
+    fn upload(&self, key: &str, content: &[u8], temp_dir: &Path) -> Result<(), ObjectStorageError> {
+        let to_upload_error = |err: std::io::Error| ObjectStorageError::CannotUploadFile {
+            bucket_name: self.bucket_name.clone(),
+            object_name: key.to_string(),
+            raw_error_message: err.to_string(),
+        };
+        let mut file = NamedTempFile::new_in(temp_dir).map_err(to_upload_error)?;
+        file.write_all(content).map_err(to_upload_error)?;
+
+        self.object_storage
+            .put_object(&self.bucket_name, key, file.path(), None)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::models::object_storage::test_utils::InMemoryObjectStorage;
+
+    const BUCKET_NAME: &str = "qovery-kubeconfigs-z1234";
+
+    fn backup_executions(object_storage: &InMemoryObjectStorage, execution_ids: &[&str], retention: usize) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for execution_id in execution_ids {
+            let backup =
+                TerraformStateBackup::new(object_storage, BUCKET_NAME.to_string(), execution_id.to_string(), retention);
+            let pruning_errors = backup
+                .backup(&format!("{{\"serial\": \"{execution_id}\"}}"), temp_dir.path())
+                .unwrap();
+            assert!(pruning_errors.is_empty());
+        }
+    }
+
+    fn index(object_storage: &InMemoryObjectStorage) -> Vec<String> {
+        serde_json::from_slice(&object_storage.get_object(BUCKET_NAME, INDEX_KEY).unwrap().value).unwrap()
+    }
+
+    #[test]
+    fn test_backup_keeps_the_last_copies() {
+        let object_storage = InMemoryObjectStorage::with_buckets(&[BUCKET_NAME]);
+
+        backup_executions(&object_storage, &["exec-b", "exec-a", "exec-d", "exec-c"], 3);
+
+        assert_eq!(
+            index(&object_storage),
+            vec![
+                "terraform-state-backups/exec-a.tfstate",
+                "terraform-state-backups/exec-d.tfstate",
+                "terraform-state-backups/exec-c.tfstate",
+            ]
+        );
+        assert_eq!(
+            object_storage.keys(BUCKET_NAME),
+            vec![
+                "terraform-state-backups/exec-a.tfstate",
+                "terraform-state-backups/exec-c.tfstate",
+                "terraform-state-backups/exec-d.tfstate",
+                "terraform-state-backups/index.json",
+            ]
+        );
+        assert_eq!(
+            object_storage
+                .get_object(BUCKET_NAME, "terraform-state-backups/exec-c.tfstate")
+                .unwrap()
+                .value,
+            b"{\"serial\": \"exec-c\"}".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_backup_of_a_rerun_execution_replaces_its_copy() {
+        let object_storage = InMemoryObjectStorage::with_buckets(&[BUCKET_NAME]);
+
+        backup_executions(&object_storage, &["exec-a", "exec-b", "exec-a"], 2);
+
+        assert_eq!(
+            index(&object_storage),
+            vec![
+                "terraform-state-backups/exec-b.tfstate",
+                "terraform-state-backups/exec-a.tfstate",
+            ]
+        );
+        assert_eq!(object_storage.keys(BUCKET_NAME).len(), 3);
+    }
+
+    #[test]
+    fn test_backup_retries_to_delete_expired_copies() {
+        let object_storage = InMemoryObjectStorage::with_buckets(&[BUCKET_NAME]);
+        let temp_dir = tempfile::tempdir().unwrap();
+        backup_executions(&object_storage, &["exec-a", "exec-b"], 2);
+
+        object_storage.reject_writes_under(&format!("{BUCKET_NAME}/terraform-state-backups/exec-a"));
+        let backup = TerraformStateBackup::new(&object_storage, BUCKET_NAME.to_string(), "exec-c".to_string(), 2);
+        let pruning_errors = backup.backup("{}", temp_dir.path()).unwrap();
+        assert_eq!(
+            pruning_errors,
+            vec![ObjectStorageError::CannotDeleteFile {
+                bucket_name: BUCKET_NAME.to_string(),
+                object_name: "terraform-state-backups/exec-a.tfstate".to_string(),
+                raw_error_message: "AccessDenied: access denied".to_string(),
+            }]
+        );
+        assert_eq!(
+            index(&object_storage),
+            vec![
+                "terraform-state-backups/exec-a.tfstate",
+                "terraform-state-backups/exec-b.tfstate",
+                "terraform-state-backups/exec-c.tfstate",
+            ]
+        );
+
+        // deletions succeed again: both expired copies are deleted by the next backup
+        object_storage.accept_all_writes();
+        backup_executions(&object_storage, &["exec-d"], 2);
+        assert_eq!(
+            index(&object_storage),
+            vec![
+                "terraform-state-backups/exec-c.tfstate",
+                "terraform-state-backups/exec-d.tfstate",
+            ]
+        );
+        assert_eq!(object_storage.keys(BUCKET_NAME).len(), 3);
+    }
+
+    #[test]
+    fn test_backup_fails_when_the_bucket_does_not_exist() {
+        let object_storage = InMemoryObjectStorage::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let backup = TerraformStateBackup::new(&object_storage, BUCKET_NAME.to_string(), "exec-a".to_string(), 2);
+        assert!(matches!(
+            backup.backup("{}", temp_dir.path()),
+            Err(ObjectStorageError::CannotUploadFile { .. })
+        ));
+    }
+}
@@ -0,0 +1,626 @@
+use crate::cmd::kubectl::kubernetes_get_all_pdbs;
+use crate::cmd::structs::PDBItem;
+use crate::environment::models::types::VersionsNumber;
+use crate::errors::{CommandError, EngineError, ErrorMessageVerbosity};
+use crate::events::Stage::Infrastructure;
+use crate::events::{EventMessage, InfrastructureStep};
+use crate::infrastructure::action::utils::mk_logger;
+use crate::infrastructure::action::InfraLogger;
+use crate::infrastructure::infrastructure_context::InfrastructureContext;
+use crate::infrastructure::models::kubernetes::KubernetesUpgradeStatus;
+use crate::runtime::block_on;
+use k8s_openapi::api::core::v1::Node;
+use kube::api::{ApiResource, DynamicObject, ListParams};
+use kube::Api;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const LAST_APPLIED_CONFIGURATION_ANNOTATION: &str = "kubectl.kubernetes.io/last-applied-configuration";
+
+/// An API version removed from Kubernetes, the objects applied with it can't be applied anymore
+#[derive(Debug, PartialEq, Eq)]
+pub struct RemovedApi {
+    pub group: &'static str,
+    pub version: &'static str,
+    pub kind: &'static str,
+    pub plural: &'static str,
+    pub replacement: &'static str,
+}
+
+impl RemovedApi {
+    pub fn api_version(&self) -> String {
+        format!("{}/{}", self.group, self.version)
+    }
+}
+
+/// APIs removed by each Kubernetes minor version
+/// https://kubernetes.io/docs/reference/using-api/deprecation-guide/
+pub fn removed_apis(minor: u8) -> &'static [RemovedApi] {
+    match minor {
+        25 => &[
+            RemovedApi {
+                group: "batch",
+                version: "v1beta1",
+                kind: "CronJob",
+                plural: "cronjobs",
+                replacement: "batch/v1",
+            },
+            RemovedApi {
+                group: "discovery.k8s.io",
+                version: "v1beta1",
+                kind: "EndpointSlice",
+                plural: "endpointslices",
+                replacement: "discovery.k8s.io/v1",
+            },
+            RemovedApi {
+                group: "autoscaling",
+                version: "v2beta1",
+                kind: "HorizontalPodAutoscaler",
+                plural: "horizontalpodautoscalers",
+                replacement: "autoscaling/v2",
+            },
+            RemovedApi {
+                group: "policy",
+                version: "v1beta1",
+                kind: "PodDisruptionBudget",
+                plural: "poddisruptionbudgets",
+                replacement: "policy/v1",
+            },
+            RemovedApi {
+                group: "policy",
+                version: "v1beta1",
+                kind: "PodSecurityPolicy",
+                plural: "podsecuritypolicies",
+                replacement: "Pod Security Admission",
+            },
+            RemovedApi {
+                group: "node.k8s.io",
+                version: "v1beta1",
+                kind: "RuntimeClass",
+                plural: "runtimeclasses",
+                replacement: "node.k8s.io/v1",
+            },
+        ],
+        26 => &[
+            RemovedApi {
+                group: "flowcontrol.apiserver.k8s.io",
+                version: "v1beta1",
+                kind: "FlowSchema",
+                plural: "flowschemas",
+                replacement: "flowcontrol.apiserver.k8s.io/v1beta3",
+            },
+            RemovedApi {
+                group: "flowcontrol.apiserver.k8s.io",
+                version: "v1beta1",
+                kind: "PriorityLevelConfiguration",
+                plural: "prioritylevelconfigurations",
+                replacement: "flowcontrol.apiserver.k8s.io/v1beta3",
+            },
+            RemovedApi {
+                group: "autoscaling",
+                version: "v2beta2",
+                kind: "HorizontalPodAutoscaler",
+                plural: "horizontalpodautoscalers",
+                replacement: "autoscaling/v2",
+            },
+        ],
+        27 => &[RemovedApi {
+            group: "storage.k8s.io",
+            version: "v1beta1",
+            kind: "CSIStorageCapacity",
+            plural: "csistoragecapacities",
+            replacement: "storage.k8s.io/v1",
+        }],
+        29 => &[
+            RemovedApi {
+                group: "flowcontrol.apiserver.k8s.io",
+                version: "v1beta2",
+                kind: "FlowSchema",
+                plural: "flowschemas",
+                replacement: "flowcontrol.apiserver.k8s.io/v1",
+            },
+            RemovedApi {
+                group: "flowcontrol.apiserver.k8s.io",
+                version: "v1beta2",
+                kind: "PriorityLevelConfiguration",
+                plural: "prioritylevelconfigurations",
+                replacement: "flowcontrol.apiserver.k8s.io/v1",
+            },
+        ],
+        32 => &[
+            RemovedApi {
+                group: "flowcontrol.apiserver.k8s.io",
+                version: "v1beta3",
+                kind: "FlowSchema",
+                plural: "flowschemas",
+                replacement: "flowcontrol.apiserver.k8s.io/v1",
+            },
+            RemovedApi {
+                group: "flowcontrol.apiserver.k8s.io",
+                version: "v1beta3",
+                kind: "PriorityLevelConfiguration",
+                plural: "prioritylevelconfigurations",
+                replacement: "flowcontrol.apiserver.k8s.io/v1",
+            },
+        ],
+        _ => &[],
+    }
+}
+
+/// Calls made to the cluster by the preflight checks
+pub trait UpgradePreflightClient {
+    /// `group/version` of the APIs served by the cluster
+    fn served_api_versions(&self) -> Result<Vec<String>, CommandError>;
+    /// Objects of the kind, read through the removed API
+    fn list_objects(&self, api: &RemovedApi) -> Result<Vec<DynamicObject>, CommandError>;
+    fn list_pod_disruption_budgets(&self) -> Result<Vec<PDBItem>, CommandError>;
+    fn list_nodes(&self) -> Result<Vec<Node>, CommandError>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemovedApiUsage {
+    pub kind: String,
+    /// `namespace/name`, or `name` for cluster scoped objects
+    pub object: String,
+    pub api_version: String,
+    pub removed_in: String,
+    pub replacement: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutdatedNode {
+    pub name: String,
+    pub kubelet_version: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UpgradePreflightReport {
+    pub removed_api_usages: Vec<RemovedApiUsage>,
+    pub outdated_nodes: Vec<OutdatedNode>,
+    /// `namespace/name` of the PDBs not allowing any disruption, they will slow down or block the nodes drain
+    pub blocking_pdbs: Vec<String>,
+}
+
+impl UpgradePreflightReport {
+    /// Findings preventing the upgrade: manifests using removed APIs and nodes too old to be upgraded
+    pub fn blocking_findings(&self) -> Vec<String> {
+        let removed_api_usages = self.removed_api_usages.iter().map(|usage| {
+            format!(
+                "{} `{}` uses `{}` removed in Kubernetes {}, migrate it to {}",
+                usage.kind, usage.object, usage.api_version, usage.removed_in, usage.replacement
+            )
+        });
+        let outdated_nodes = self.outdated_nodes.iter().map(|node| {
+            format!(
+                "node `{}` runs Kubernetes {}, nodes can only be upgraded one minor version at a time",
+                node.name, node.kubelet_version
+            )
+        });
+
+        removed_api_usages.chain(outdated_nodes).collect()
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        self.blocking_pdbs
+            .iter()
+            .map(|pdb| format!("pod disruption budget `{pdb}` allows no disruption and may block the nodes drain"))
+            .collect()
+    }
+}
+
+fn minor_version(version: &VersionsNumber) -> Option<u8> {
+    version.minor.as_deref()?.parse().ok()
+}
+
+/// The api version of the manifest last applied by kubectl, helm releases don't keep it in the objects
+fn last_applied_api_version(object: &DynamicObject) -> Option<String> {
+    let last_applied = object
+        .metadata
+        .annotations
+        .as_ref()?
+        .get(LAST_APPLIED_CONFIGURATION_ANNOTATION)?;
+    let manifest: serde_json::Value = serde_json::from_str(last_applied).ok()?;
+    manifest.get("apiVersion")?.as_str().map(str::to_string)
+}
+
+fn object_name(object: &DynamicObject) -> String {
+    let name = object.metadata.name.clone().unwrap_or_default();
+    match &object.metadata.namespace {
+        Some(namespace) => format!("{namespace}/{name}"),
+        None => name,
+    }
+}
+
+pub fn upgrade_preflight_report(
+    client: &dyn UpgradePreflightClient,
+    deployed_version: &VersionsNumber,
+    requested_version: &VersionsNumber,
+) -> Result<UpgradePreflightReport, CommandError> {
+    let (deployed_minor, requested_minor) = match (minor_version(deployed_version), minor_version(requested_version)) {
+        (Some(deployed_minor), Some(requested_minor)) => (deployed_minor, requested_minor),
+        _ => {
+            return Err(CommandError::new_from_safe_message(format!(
+                "Cannot compare the deployed version {deployed_version} with the requested version {requested_version}"
+            )))
+        }
+    };
+
+    let mut report = UpgradePreflightReport::default();
+
+    // removed APIs are still served by the deployed version, objects read through them are converted so only the
+    // last applied manifest tells the API used by the customer
+    let served_api_versions = client.served_api_versions()?;
+    for minor in deployed_minor + 1..=requested_minor {
+        for api in removed_apis(minor) {
+            let api_version = api.api_version();
+            if !served_api_versions.contains(&api_version) {
+                continue;
+            }
+
+            for object in client.list_objects(api)? {
+                if last_applied_api_version(&object).as_deref() == Some(api_version.as_str()) {
+                    report.removed_api_usages.push(RemovedApiUsage {
+                        kind: api.kind.to_string(),
+                        object: object_name(&object),
+                        api_version: api_version.clone(),
+                        removed_in: format!("1.{minor}"),
+                        replacement: api.replacement.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    report.blocking_pdbs = client
+        .list_pod_disruption_budgets()?
+        .into_iter()
+        .filter(|pdb| pdb.status.expected_pods > 0 && pdb.status.disruptions_allowed == 0)
+        .map(|pdb| format!("{}/{}", pdb.metadata.namespace, pdb.metadata.name))
+        .collect();
+
+    // nodes follow the control plane, they have to be on the previous minor (or already upgraded on a retry)
+    for node in client.list_nodes()? {
+        let Some(kubelet_version) = node.status.as_ref().and_then(|status| status.node_info.as_ref()) else {
+            continue;
+        };
+        let kubelet_version = kubelet_version.kubelet_version.clone();
+        let node_minor = VersionsNumber::from_str(&kubelet_version)
+            .ok()
+            .and_then(|version| minor_version(&version));
+        if node_minor.is_some_and(|node_minor| node_minor + 1 < requested_minor) {
+            report.outdated_nodes.push(OutdatedNode {
+                name: node.metadata.name.clone().unwrap_or_default(),
+                kubelet_version,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+struct ClusterUpgradePreflightClient<'a> {
+    kube_client: kube::Client,
+    kubeconfig_path: PathBuf,
+    envs: Vec<(&'a str, &'a str)>,
+    node_selector: Option<&'a str>,
+}
+
+impl UpgradePreflightClient for ClusterUpgradePreflightClient<'_> {
+    fn served_api_versions(&self) -> Result<Vec<String>, CommandError> {
+        let api_groups = block_on(self.kube_client.list_api_groups()).map_err(|e| {
+            CommandError::new(
+                "Cannot list the APIs served by the cluster".to_string(),
+                Some(e.to_string()),
+                None,
+            )
+        })?;
+
+        Ok(api_groups
+            .groups
+            .into_iter()
+            .flat_map(|group| group.versions.into_iter().map(|version| version.group_version))
+            .collect())
+    }
+
+    fn list_objects(&self, api: &RemovedApi) -> Result<Vec<DynamicObject>, CommandError> {
+        let api_resource = ApiResource {
+            group: api.group.to_string(),
+            version: api.version.to_string(),
+            api_version: api.api_version(),
+            kind: api.kind.to_string(),
+            plural: api.plural.to_string(),
+        };
+        let objects: Api<DynamicObject> = Api::all_with(self.kube_client.clone(), &api_resource);
+
+        block_on(objects.list(&ListParams::default()))
+            .map(|objects| objects.items)
+            .map_err(|e| {
+                CommandError::new(
+                    format!("Cannot list the {} objects through `{}`", api.kind, api.api_version()),
+                    Some(e.to_string()),
+                    None,
+                )
+            })
+    }
+
+    fn list_pod_disruption_budgets(&self) -> Result<Vec<PDBItem>, CommandError> {
+        kubernetes_get_all_pdbs(&self.kubeconfig_path, self.envs.clone(), None)
+            .map(|pdbs| pdbs.items.unwrap_or_default())
+    }
+
+    fn list_nodes(&self) -> Result<Vec<Node>, CommandError> {
+        let nodes: Api<Node> = Api::all(self.kube_client.clone());
+        let list_params = match self.node_selector {
+            Some(node_selector) => ListParams::default().labels(node_selector),
+            None => ListParams::default(),
+        };
+
+        block_on(nodes.list(&list_params))
+            .map(|nodes| nodes.items)
+            .map_err(|e| CommandError::new("Cannot list the nodes".to_string(), Some(e.to_string()), None))
+    }
+}
+
+/// Checks the cluster can be upgraded to the requested version, before touching it.
+/// The checks are best effort, the upgrade goes on when they can't be run.
+pub(super) fn check_upgrade_preflight(
+    infra_ctx: &InfrastructureContext,
+    upgrade_status: &KubernetesUpgradeStatus,
+    node_selector: Option<&str>,
+) -> Result<(), Box<EngineError>> {
+    let event_details = infra_ctx
+        .kubernetes()
+        .get_event_details(Infrastructure(InfrastructureStep::Upgrade));
+    let logger = mk_logger(infra_ctx.kubernetes(), InfrastructureStep::Upgrade);
+    logger.info(format!(
+        "Running preflight checks before upgrading to Kubernetes {}.",
+        upgrade_status.requested_version
+    ));
+
+    let client = ClusterUpgradePreflightClient {
+        kube_client: infra_ctx.mk_kube_client()?.client().clone(),
+        kubeconfig_path: infra_ctx.kubernetes().kubeconfig_local_file_path(),
+        envs: infra_ctx.cloud_provider().credentials_environment_variables(),
+        node_selector,
+    };
+    let report = match upgrade_preflight_report(
+        &client,
+        &upgrade_status.deployed_masters_version,
+        &upgrade_status.requested_version,
+    ) {
+        Ok(report) => report,
+        Err(e) => {
+            logger.warn(EventMessage::new(
+                "Cannot run the upgrade preflight checks, upgrade will proceed without them.".to_string(),
+                Some(e.message(ErrorMessageVerbosity::FullDetailsWithoutEnvVars)),
+            ));
+            return Ok(());
+        }
+    };
+
+    for warning in report.warnings() {
+        logger.warn(format!("Upgrade preflight check: {warning}."));
+    }
+
+    let blocking_findings = report.blocking_findings();
+    if blocking_findings.is_empty() {
+        logger.info("Upgrade preflight checks passed.");
+        return Ok(());
+    }
+
+    for finding in &blocking_findings {
+        logger.warn(format!("Upgrade preflight check failed: {finding}."));
+    }
+
+    Err(Box::new(EngineError::new_k8s_upgrade_preflight_check_failed(
+        event_details,
+        upgrade_status.requested_version.to_string(),
+        blocking_findings,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::structs::{PDBItem, PDBMetadata, PDBStatus};
+    use crate::environment::models::types::VersionsNumber;
+    use crate::errors::CommandError;
+    use crate::infrastructure::action::upgrade_preflight::{
+        removed_apis, upgrade_preflight_report, OutdatedNode, RemovedApi, RemovedApiUsage, UpgradePreflightClient,
+    };
+    use k8s_openapi::api::core::v1::Node;
+    use kube::api::DynamicObject;
+    use serde_json::json;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    #[derive(Default)]
+    struct MockClient {
+        served_api_versions: Vec<&'static str>,
+        /// objects per `group/version/kind`
+        objects: HashMap<String, Vec<DynamicObject>>,
+        pdbs: Vec<PDBItem>,
+        nodes: Vec<Node>,
+        listed_apis: RefCell<Vec<String>>,
+    }
+
+    impl UpgradePreflightClient for MockClient {
+        fn served_api_versions(&self) -> Result<Vec<String>, CommandError> {
+            Ok(self.served_api_versions.iter().map(|v| v.to_string()).collect())
+        }
+
+        fn list_objects(&self, api: &RemovedApi) -> Result<Vec<DynamicObject>, CommandError> {
+            let key = format!("{}/{}", api.api_version(), api.kind);
+            self.listed_apis.borrow_mut().push(key.clone());
+            Ok(self.objects.get(&key).cloned().unwrap_or_default())
+        }
+
+        fn list_pod_disruption_budgets(&self) -> Result<Vec<PDBItem>, CommandError> {
+            Ok(self.pdbs.clone())
+        }
+
+        fn list_nodes(&self) -> Result<Vec<Node>, CommandError> {
+            Ok(self.nodes.clone())
+        }
+    }
+
+    fn object(namespace: &str, name: &str, last_applied_api_version: Option<&str>) -> DynamicObject {
+        let annotations = match last_applied_api_version {
+            Some(api_version) => json!({
+                "kubectl.kubernetes.io/last-applied-configuration": json!({ "apiVersion": api_version }).to_string()
+            }),
+            None => json!({}),
+        };
+        serde_json::from_value(json!({
+            "metadata": { "name": name, "namespace": namespace, "annotations": annotations }
+        }))
+        .unwrap()
+    }
+
+    fn node(name: &str, kubelet_version: &str) -> Node {
+        serde_json::from_value(json!({
+            "metadata": { "name": name },
+            "status": { "nodeInfo": {
+                "kubeletVersion": kubelet_version,
+                "kubeProxyVersion": kubelet_version,
+                "architecture": "amd64",
+                "bootID": "",
+                "containerRuntimeVersion": "containerd://1.7.11",
+                "kernelVersion": "",
+                "machineID": "",
+                "operatingSystem": "linux",
+                "osImage": "",
+                "systemUUID": ""
+            }}
+        }))
+        .unwrap()
+    }
+
+    fn pdb(name: &str, expected_pods: i16, disruptions_allowed: i16) -> PDBItem {
+        PDBItem {
+            api_version: "policy/v1".to_string(),
+            kind: "PodDisruptionBudget".to_string(),
+            status: PDBStatus {
+                expected_pods,
+                disruptions_allowed,
+                ..Default::default()
+            },
+            metadata: PDBMetadata {
+                name: name.to_string(),
+                namespace: "default".to_string(),
+            },
+        }
+    }
+
+    fn version(version: &str) -> VersionsNumber {
+        VersionsNumber::from_str(version).unwrap()
+    }
+
+    #[test]
+    fn test_removed_apis_per_minor() {
+        assert!(removed_apis(25)
+            .iter()
+            .any(|api| api.api_version() == "policy/v1beta1" && api.kind == "PodDisruptionBudget"));
+        assert_eq!(removed_apis(26).len(), 3);
+        assert!(removed_apis(28).is_empty());
+    }
+
+    #[test]
+    fn test_removed_api_usages_block_the_upgrade() {
+        let client = MockClient {
+            served_api_versions: vec!["policy/v1beta1", "policy/v1", "batch/v1"],
+            objects: HashMap::from([(
+                "policy/v1beta1/PodDisruptionBudget".to_string(),
+                vec![
+                    object("default", "old-pdb", Some("policy/v1beta1")),
+                    object("default", "new-pdb", Some("policy/v1")),
+                    object("default", "helm-pdb", None),
+                ],
+            )]),
+            ..Default::default()
+        };
+
+        let report = upgrade_preflight_report(&client, &version("1.24"), &version("1.25")).unwrap();
+
+        assert_eq!(
+            report.removed_api_usages,
+            vec![RemovedApiUsage {
+                kind: "PodDisruptionBudget".to_string(),
+                object: "default/old-pdb".to_string(),
+                api_version: "policy/v1beta1".to_string(),
+                removed_in: "1.25".to_string(),
+                replacement: "policy/v1".to_string(),
+            }]
+        );
+        let findings = report.blocking_findings();
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].contains("`default/old-pdb`"), "{}", findings[0]);
+        // only the served removed APIs are listed
+        assert_eq!(
+            *client.listed_apis.borrow(),
+            vec![
+                "policy/v1beta1/PodDisruptionBudget".to_string(),
+                "policy/v1beta1/PodSecurityPolicy".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apis_removed_by_other_versions_are_ignored() {
+        let client = MockClient {
+            served_api_versions: vec!["policy/v1beta1", "flowcontrol.apiserver.k8s.io/v1beta3"],
+            objects: HashMap::from([(
+                "policy/v1beta1/PodDisruptionBudget".to_string(),
+                vec![object("default", "old-pdb", Some("policy/v1beta1"))],
+            )]),
+            ..Default::default()
+        };
+
+        let report = upgrade_preflight_report(&client, &version("1.29"), &version("1.30")).unwrap();
+
+        assert!(report.blocking_findings().is_empty());
+        assert!(client.listed_apis.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_pdbs_without_allowed_disruption_are_warnings() {
+        let client = MockClient {
+            pdbs: vec![pdb("blocking", 2, 0), pdb("fine", 2, 1), pdb("no-pods", 0, 0)],
+            ..Default::default()
+        };
+
+        let report = upgrade_preflight_report(&client, &version("1.29"), &version("1.30")).unwrap();
+
+        assert_eq!(report.blocking_pdbs, vec!["default/blocking".to_string()]);
+        assert_eq!(report.warnings().len(), 1);
+        assert!(report.blocking_findings().is_empty());
+    }
+
+    #[test]
+    fn test_nodes_must_be_on_the_previous_minor() {
+        let client = MockClient {
+            nodes: vec![
+                node("previous", "v1.29.3-eks-ae9a62a"),
+                node("already-upgraded", "v1.30.0-eks-036c24b"),
+                node("too-old", "v1.28.5-eks-5e0fdde"),
+            ],
+            ..Default::default()
+        };
+
+        let report = upgrade_preflight_report(&client, &version("1.29"), &version("1.30")).unwrap();
+
+        assert_eq!(
+            report.outdated_nodes,
+            vec![OutdatedNode {
+                name: "too-old".to_string(),
+                kubelet_version: "v1.28.5-eks-5e0fdde".to_string(),
+            }]
+        );
+        assert_eq!(report.blocking_findings().len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_versions_cannot_be_checked() {
+        assert!(upgrade_preflight_report(&MockClient::default(), &version("1"), &version("1.30")).is_err());
+    }
+}
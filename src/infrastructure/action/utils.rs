@@ -1,3 +1,4 @@
+use crate::engine_task::progress::ProgressReporter;
 use crate::events::InfrastructureStep;
 use crate::events::Stage::Infrastructure;
 use crate::infrastructure::action::{InfraLogger, InfraLoggerImpl};
@@ -22,6 +23,7 @@ where
 pub fn mk_logger(kube: &dyn Kubernetes, step: InfrastructureStep) -> impl InfraLogger {
     let event_details = kube.get_event_details(Infrastructure(step));
     let logger = InfraLoggerImpl {
+        progress: ProgressReporter::new(event_details.clone(), kube.logger().clone_dyn()),
         event_details,
         logger: kube.logger().clone_dyn(),
     };
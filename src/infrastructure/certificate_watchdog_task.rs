@@ -0,0 +1,263 @@
+use crate::cmd::command::ExecutionContext;
+use crate::cmd::docker::Docker;
+use crate::engine_task::qovery_api::QoveryApi;
+use crate::engine_task::Task;
+use crate::environment::models::abort::{Abort, AbortStatus};
+use crate::errors::{CommandError, EngineError};
+use crate::events::Stage::Infrastructure;
+use crate::events::{EngineEvent, EventDetails, EventMessage, InfrastructureStep};
+use crate::infrastructure::models::kubernetes::certificate_watchdog::{
+    classify_certificate, conditions_triggering_issuance, is_failed_certificate_request, reissuance_decision,
+    CertificateState, ReissuanceDecision, WatchedCertificate, CERTIFICATE_NAME_ANNOTATION,
+};
+use crate::io_models::context::Context;
+use crate::io_models::engine_request::CertificateWatchdogEngineRequest;
+use crate::io_models::feature_flags::FeatureFlags;
+use crate::logger::Logger;
+use crate::metrics_registry::MetricsRegistry;
+use crate::proxy;
+use crate::runtime::block_on;
+use chrono::Utc;
+use kube::api::{ApiResource, DeleteParams, DynamicObject, GroupVersionKind, ListParams, Patch, PatchParams};
+use kube::Api;
+use serde_json::json;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+
+/// Checks the Qovery managed certificates of the cluster, or of a single namespace, and forces the re-issuance of the ones
+/// cert-manager failed to renew. The status of every certificate needing attention is reported in a single event.
+/// Errors are only returned when certificates cannot be listed, a failed re-issuance is part of the report.
+pub fn watch_certificates(
+    client: &kube::Client,
+    namespace: Option<&str>,
+    renewal_threshold_in_days: u32,
+    reissue: bool,
+    logger: &dyn Logger,
+    event_details: EventDetails,
+) -> Result<(), kube::Error> {
+    let api_resource = ApiResource::from_gvk(&GroupVersionKind::gvk("cert-manager.io", "v1", "Certificate"));
+    let certificates: Api<DynamicObject> = match namespace {
+        Some(namespace) => Api::namespaced_with(client.clone(), namespace, &api_resource),
+        None => Api::all_with(client.clone(), &api_resource),
+    };
+
+    let now = Utc::now();
+    let mut healthy = 0;
+    let mut report = vec![];
+    for certificate in block_on(certificates.list(&ListParams::default()))?.items {
+        let Some(watched) =
+            WatchedCertificate::from_dynamic_object(&certificate).filter(|cert| cert.is_qovery_managed())
+        else {
+            continue;
+        };
+        let check = classify_certificate(&watched, now, renewal_threshold_in_days);
+        if check.state == CertificateState::Healthy {
+            healthy += 1;
+            continue;
+        }
+
+        let action = match reissuance_decision(&watched, &check, now) {
+            ReissuanceDecision::NotNeeded => String::new(),
+            ReissuanceDecision::Skip { reason } => format!(" Re-issuance skipped: {reason}."),
+            ReissuanceDecision::Reissue if !reissue => " Re-issuance is disabled.".to_string(),
+            ReissuanceDecision::Reissue => match reissue_certificate(client, &api_resource, &certificate) {
+                Ok(()) => " 🔄 Re-issuance triggered.".to_string(),
+                Err(err) => format!(" Re-issuance failed: {err}."),
+            },
+        };
+        report.push(format!("{check}.{action}"));
+    }
+
+    if report.is_empty() {
+        logger.log(EngineEvent::Info(
+            event_details,
+            EventMessage::new(format!("🔒 {healthy} Qovery managed certificate(s) are healthy"), None),
+        ));
+    } else {
+        logger.log(EngineEvent::Warning(
+            event_details,
+            EventMessage::new(
+                format!(
+                    "🔒 {} Qovery managed certificate(s) need attention, {healthy} are healthy:\n{}",
+                    report.len(),
+                    report.join("\n")
+                ),
+                None,
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+fn reissue_certificate(
+    client: &kube::Client,
+    api_resource: &ApiResource,
+    certificate: &DynamicObject,
+) -> Result<(), kube::Error> {
+    let name = certificate.metadata.name.clone().unwrap_or_default();
+    let namespace = certificate.metadata.namespace.clone().unwrap_or_default();
+
+    // failed requests are removed first, otherwise cert-manager waits for its backoff before creating a new one
+    let request_resource = ApiResource::from_gvk(&GroupVersionKind::gvk("cert-manager.io", "v1", "CertificateRequest"));
+    let requests: Api<DynamicObject> = Api::namespaced_with(client.clone(), &namespace, &request_resource);
+    for request in block_on(requests.list(&ListParams::default()))?.items {
+        let is_owned = request
+            .metadata
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(CERTIFICATE_NAME_ANNOTATION))
+            == Some(&name);
+        if let (true, true, Some(request_name)) = (
+            is_owned,
+            is_failed_certificate_request(&request),
+            request.metadata.name.as_ref(),
+        ) {
+            block_on(requests.delete(request_name, &DeleteParams::default()))?;
+        }
+    }
+
+    let certificates: Api<DynamicObject> = Api::namespaced_with(client.clone(), &namespace, api_resource);
+    block_on(certificates.patch_status(
+        &name,
+        &PatchParams::default(),
+        &Patch::Merge(json!({ "status": { "conditions": conditions_triggering_issuance(certificate, Utc::now()) } })),
+    ))?;
+
+    Ok(())
+}
+
+pub struct CertificateWatchdogTask {
+    workspace_root_dir: String,
+    lib_root_dir: String,
+    docker: Arc<Docker>,
+    request: CertificateWatchdogEngineRequest,
+    logger: Box<dyn Logger>,
+    metrics_registry: Box<dyn MetricsRegistry>,
+    qovery_api: Arc<dyn QoveryApi>,
+    span: tracing::Span,
+    is_terminated: (RwLock<Option<broadcast::Sender<()>>>, broadcast::Receiver<()>),
+}
+
+impl CertificateWatchdogTask {
+    pub fn new(
+        request: CertificateWatchdogEngineRequest,
+        workspace_root_dir: String,
+        lib_root_dir: String,
+        docker: Arc<Docker>,
+        logger: Box<dyn Logger>,
+        metrics_registry: Box<dyn MetricsRegistry>,
+        qovery_api: Box<dyn QoveryApi>,
+    ) -> Self {
+        let span = info_span!(
+            "certificate_watchdog_task",
+            organization_id = request.organization_long_id.to_string(),
+            cluster_id = request.kubernetes.long_id.to_string(),
+        );
+
+        CertificateWatchdogTask {
+            workspace_root_dir,
+            lib_root_dir,
+            docker,
+            request,
+            logger,
+            metrics_registry,
+            qovery_api: Arc::from(qovery_api),
+            span,
+            is_terminated: {
+                let (tx, rx) = broadcast::channel(1);
+                (RwLock::new(Some(tx)), rx)
+            },
+        }
+    }
+
+    fn info_context(&self) -> Context {
+        Context::new(
+            self.request.organization_long_id,
+            self.request.kubernetes.long_id,
+            self.request.id.to_string(),
+            self.workspace_root_dir.to_string(),
+            self.lib_root_dir.to_string(),
+            self.request.test_cluster,
+            self.request.features.clone(),
+            FeatureFlags::resolve(&self.request.feature_flags).0,
+            self.request.metadata.clone(),
+            self.docker.clone(),
+            self.qovery_api.clone(),
+            self.request.event_details(),
+        )
+        .with_proxy(self.request.proxy.clone())
+    }
+
+    fn get_event_details(&self, step: InfrastructureStep) -> EventDetails {
+        EventDetails::clone_changing_stage(self.request.event_details(), Infrastructure(step))
+    }
+
+    fn watch(&self) -> Result<(), Box<EngineError>> {
+        let infra_ctx = self.request.to_infrastructure_context(
+            &self.info_context(),
+            self.request.event_details(),
+            self.logger.clone(),
+            self.metrics_registry.clone(),
+            false,
+        )?;
+        let kube_client = infra_ctx.mk_kube_client()?;
+
+        watch_certificates(
+            kube_client.client(),
+            None,
+            self.request.target_environment.renewal_threshold_in_days,
+            self.request.target_environment.reissue,
+            self.logger.as_ref(),
+            self.get_event_details(InfrastructureStep::CertificatesWatched),
+        )
+        .map_err(|err| {
+            Box::new(EngineError::new_cannot_watch_certificates(
+                self.get_event_details(InfrastructureStep::WatchCertificatesError),
+                CommandError::new("Cannot list the certificates".to_string(), Some(err.to_string()), None),
+            ))
+        })
+    }
+}
+
+impl Task for CertificateWatchdogTask {
+    fn id(&self) -> &str {
+        self.request.id.as_str()
+    }
+
+    fn run(&self) {
+        let _span = self.span.enter();
+        info!("certificate watchdog task {} started", self.id());
+        let _execution_context = ExecutionContext::from(&self.request.event_details()).enter();
+        proxy::install(self.request.proxy.as_ref());
+
+        let _guard = scopeguard::guard((), |_| {
+            let Some(is_terminated_tx) = self.is_terminated.0.write().unwrap().take() else {
+                return;
+            };
+            let _ = is_terminated_tx.send(());
+        });
+
+        if let Err(err) = self.watch() {
+            self.logger.log(EngineEvent::Error(*err, None));
+        }
+
+        info!("certificate watchdog task {} finished", self.id());
+    }
+
+    fn cancel(&self, _force_requested: bool) -> bool {
+        false
+    }
+
+    fn cancel_checker(&self) -> Box<dyn Abort> {
+        Box::new(move || AbortStatus::None)
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.is_terminated.0.read().map(|tx| tx.is_none()).unwrap_or(true)
+    }
+
+    fn await_terminated(&self) -> broadcast::Receiver<()> {
+        self.is_terminated.1.resubscribe()
+    }
+}
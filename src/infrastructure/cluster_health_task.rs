@@ -0,0 +1,274 @@
+use crate::cmd::command::ExecutionContext;
+use crate::cmd::docker::Docker;
+use crate::cmd::helm::Helm;
+use crate::engine_task::qovery_api::QoveryApi;
+use crate::engine_task::upload_json_document;
+use crate::engine_task::Task;
+use crate::environment::models::abort::{Abort, AbortStatus};
+use crate::errors::{CommandError, EngineError};
+use crate::events::Stage::Infrastructure;
+use crate::events::{EngineEvent, EngineMsg, EngineMsgPayload, EventDetails, EventMessage, InfrastructureStep};
+use crate::infrastructure::infrastructure_context::InfrastructureContext;
+use crate::infrastructure::models::kubernetes::cluster_health::{
+    chart_skews, expiring_certificates, node_readiness, observed_certificate, ChartSkew, ClusterHealthDocument,
+    ClusterHealthObservations, ClusterHealthSeverity, ConnectivityCheck, ExpiringCertificate, NodeReadiness,
+};
+use crate::io_models::context::Context;
+use crate::io_models::engine_request::ClusterHealthReportEngineRequest;
+use crate::io_models::feature_flags::FeatureFlags;
+use crate::logger::Logger;
+use crate::metrics_registry::MetricsRegistry;
+use crate::msg_publisher::MsgPublisher;
+use crate::proxy;
+use crate::runtime::block_on;
+use chrono::Utc;
+use k8s_openapi::api::core::v1::Node;
+use kube::api::{ApiResource, DynamicObject, GroupVersionKind, ListParams};
+use kube::Api;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+
+pub struct ClusterHealthReportTask {
+    workspace_root_dir: String,
+    lib_root_dir: String,
+    docker: Arc<Docker>,
+    request: ClusterHealthReportEngineRequest,
+    logger: Box<dyn Logger>,
+    metrics_registry: Box<dyn MetricsRegistry>,
+    msg_publisher: Box<dyn MsgPublisher>,
+    qovery_api: Arc<dyn QoveryApi>,
+    span: tracing::Span,
+    is_terminated: (RwLock<Option<broadcast::Sender<()>>>, broadcast::Receiver<()>),
+}
+
+impl ClusterHealthReportTask {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        request: ClusterHealthReportEngineRequest,
+        workspace_root_dir: String,
+        lib_root_dir: String,
+        docker: Arc<Docker>,
+        logger: Box<dyn Logger>,
+        metrics_registry: Box<dyn MetricsRegistry>,
+        msg_publisher: Box<dyn MsgPublisher>,
+        qovery_api: Box<dyn QoveryApi>,
+    ) -> Self {
+        let span = info_span!(
+            "cluster_health_report_task",
+            organization_id = request.organization_long_id.to_string(),
+            cluster_id = request.kubernetes.long_id.to_string(),
+        );
+
+        ClusterHealthReportTask {
+            workspace_root_dir,
+            lib_root_dir,
+            docker,
+            request,
+            logger,
+            metrics_registry,
+            msg_publisher,
+            qovery_api: Arc::from(qovery_api),
+            span,
+            is_terminated: {
+                let (tx, rx) = broadcast::channel(1);
+                (RwLock::new(Some(tx)), rx)
+            },
+        }
+    }
+
+    fn info_context(&self) -> Context {
+        Context::new(
+            self.request.organization_long_id,
+            self.request.kubernetes.long_id,
+            self.request.id.to_string(),
+            self.workspace_root_dir.to_string(),
+            self.lib_root_dir.to_string(),
+            self.request.test_cluster,
+            self.request.features.clone(),
+            FeatureFlags::resolve(&self.request.feature_flags).0,
+            self.request.metadata.clone(),
+            self.docker.clone(),
+            self.qovery_api.clone(),
+            self.request.event_details(),
+        )
+        .with_proxy(self.request.proxy.clone())
+    }
+
+    fn get_event_details(&self, step: InfrastructureStep) -> EventDetails {
+        EventDetails::clone_changing_stage(self.request.event_details(), Infrastructure(step))
+    }
+
+    // Every check is best effort: a failing check must not prevent the report from being sent
+    fn observe(&self) -> ClusterHealthObservations {
+        let unreachable = |error: String| ClusterHealthObservations {
+            connectivity: ConnectivityCheck::unreachable(error),
+            chart_skews: vec![],
+            nodes: None,
+            expiring_certificates: vec![],
+        };
+
+        let infra_ctx = match self.request.to_infrastructure_context(
+            &self.info_context(),
+            self.request.event_details(),
+            self.logger.clone(),
+            self.metrics_registry.clone(),
+            false,
+        ) {
+            Ok(infra_ctx) => infra_ctx,
+            Err(err) => return unreachable(err.user_log_message().to_string()),
+        };
+        let kube_client = match infra_ctx.mk_kube_client() {
+            Ok(kube_client) => kube_client,
+            Err(err) => return unreachable(err.user_log_message().to_string()),
+        };
+        let client = kube_client.client();
+
+        let connectivity = match block_on(client.apiserver_version()) {
+            Ok(info) => ConnectivityCheck::reachable(info.git_version),
+            Err(err) => return unreachable(format!("Cannot reach the api server: {err}")),
+        };
+
+        ClusterHealthObservations {
+            connectivity,
+            chart_skews: self.observe_chart_skews(&infra_ctx),
+            nodes: observe_nodes(client),
+            expiring_certificates: self.observe_expiring_certificates(client),
+        }
+    }
+
+    fn observe_chart_skews(&self, infra_ctx: &InfrastructureContext) -> Vec<ChartSkew> {
+        // Without a kubeconfig, the engine runs inside the self-managed cluster and uses its in-cluster config
+        let kubeconfig_path = Some(infra_ctx.kubernetes().kubeconfig_local_file_path()).filter(|path| path.exists());
+        let helm = match Helm::new(kubeconfig_path, &infra_ctx.cloud_provider().credentials_environment_variables()) {
+            Ok(helm) => helm,
+            Err(err) => {
+                warn!("cannot check the installed charts: {}", err);
+                return vec![];
+            }
+        };
+
+        match helm.list_release(None, &[]) {
+            Ok(installed_charts) => chart_skews(&self.request.target_environment.expected_charts, &installed_charts),
+            Err(err) => {
+                warn!("cannot list the installed charts: {}", err);
+                vec![]
+            }
+        }
+    }
+
+    fn observe_expiring_certificates(&self, client: &kube::Client) -> Vec<ExpiringCertificate> {
+        let api_resource = ApiResource::from_gvk(&GroupVersionKind::gvk("cert-manager.io", "v1", "Certificate"));
+        let certificates: Api<DynamicObject> = Api::all_with(client.clone(), &api_resource);
+
+        // cert-manager is optional on self-managed clusters
+        match block_on(certificates.list(&ListParams::default())) {
+            Ok(certificates) => {
+                let observed: Vec<_> = certificates.items.iter().filter_map(observed_certificate).collect();
+                expiring_certificates(
+                    &observed,
+                    Utc::now(),
+                    self.request.target_environment.certificate_expiry_warning_in_days,
+                )
+            }
+            Err(err) => {
+                warn!("cannot list cert-manager certificates: {}", err);
+                vec![]
+            }
+        }
+    }
+
+    fn log_document(&self, document: &ClusterHealthDocument, json: String) {
+        let event_details = self.get_event_details(InfrastructureStep::ClusterHealthReported);
+        let message = EventMessage::new_for_sending_core_data(document.to_string(), json);
+        self.logger.log(match document.severity {
+            ClusterHealthSeverity::Ok => EngineEvent::Info(event_details, message),
+            _ => EngineEvent::Warning(event_details, message),
+        });
+    }
+}
+
+fn observe_nodes(client: &kube::Client) -> Option<NodeReadiness> {
+    let nodes: Api<Node> = Api::all(client.clone());
+    match block_on(nodes.list(&ListParams::default())) {
+        Ok(nodes) => Some(node_readiness(&nodes.items)),
+        Err(err) => {
+            warn!("cannot list the nodes of the cluster: {}", err);
+            None
+        }
+    }
+}
+
+impl Task for ClusterHealthReportTask {
+    fn id(&self) -> &str {
+        self.request.id.as_str()
+    }
+
+    fn run(&self) {
+        let _span = self.span.enter();
+        info!("cluster health report task {} started", self.id());
+        let _execution_context = ExecutionContext::from(&self.request.event_details()).enter();
+        proxy::install(self.request.proxy.as_ref());
+
+        let _guard = scopeguard::guard((), |_| {
+            let Some(is_terminated_tx) = self.is_terminated.0.write().unwrap().take() else {
+                return;
+            };
+            let _ = is_terminated_tx.send(());
+        });
+
+        let document = ClusterHealthDocument::new(
+            self.request.kubernetes.long_id,
+            Utc::now(),
+            self.request.target_environment.previous_consecutive_failures,
+            self.observe(),
+        );
+        let json = match serde_json::to_string(&document) {
+            Ok(json) => json,
+            Err(err) => {
+                self.logger.log(EngineEvent::Error(
+                    EngineError::new_cannot_report_cluster_health(
+                        self.get_event_details(InfrastructureStep::ReportClusterHealthError),
+                        CommandError::new(
+                            "Cannot serialize the health document".to_string(),
+                            Some(err.to_string()),
+                            None,
+                        ),
+                    ),
+                    None,
+                ));
+                return;
+            }
+        };
+
+        self.msg_publisher
+            .send(EngineMsg::new(EngineMsgPayload::ClusterHealth(document.clone())));
+        if let Err(err) = upload_json_document(&self.request.target_environment.upload_url, json.clone()) {
+            self.logger.log(EngineEvent::Error(
+                EngineError::new_cannot_report_cluster_health(
+                    self.get_event_details(InfrastructureStep::ReportClusterHealthError),
+                    CommandError::new("Cannot upload the health document".to_string(), Some(err.to_string()), None),
+                ),
+                None,
+            ));
+        }
+        self.log_document(&document, json);
+
+        info!("cluster health report task {} finished", self.id());
+    }
+
+    fn cancel(&self, _force_requested: bool) -> bool {
+        false
+    }
+
+    fn cancel_checker(&self) -> Box<dyn Abort> {
+        Box::new(move || AbortStatus::None)
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.is_terminated.0.read().map(|tx| tx.is_none()).unwrap_or(true)
+    }
+
+    fn await_terminated(&self) -> broadcast::Receiver<()> {
+        self.is_terminated.1.resubscribe()
+    }
+}
@@ -0,0 +1,296 @@
+use crate::engine_task::upload_json_document;
+use crate::environment::models::container::get_mirror_repository_name;
+use crate::environment::models::environment::Environment;
+use crate::errors::{CommandError, EngineError};
+use crate::events::{EngineEvent, EventDetails, EventMessage};
+use crate::infrastructure::models::cloud_provider::io::RegistryMirroringMode;
+use crate::infrastructure::models::cloud_provider::service::Service;
+use crate::infrastructure::models::container_registry::errors::ContainerRegistryError;
+use crate::infrastructure::models::container_registry::ContainerRegistry;
+use crate::infrastructure::models::deletion_report::{
+    DeletionInventory, DeletionReport, ExpectedManagedDatabase, ExpectedResources, ListedLoadBalancer,
+    ListedVolumeClaim, ListedWorkload,
+};
+use crate::infrastructure::models::kubernetes::Kubernetes;
+use crate::infrastructure::models::object_storage::ObjectStorage;
+use crate::io_models::engine_request::DeleteDryRun;
+use crate::logger::Logger;
+use crate::runtime::block_on;
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::{Namespace, PersistentVolumeClaim, Service as KubeService};
+use k8s_openapi::api::networking::v1::Ingress;
+use k8s_openapi::NamespaceResourceScope;
+use kube::api::ListParams;
+use kube::{Api, Resource};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+
+/// Label set on every namespace created for an environment
+const ENVIRONMENT_ID_LABEL: &str = "qovery.com/environment-id";
+
+pub enum DeletedNamespaces {
+    /// The namespace of the deleted environment
+    Environment(String),
+    /// Every environment namespace of the deleted cluster
+    Cluster,
+}
+
+/// Listings of a live cluster and of the cloud provider apis, nothing is ever modified
+pub struct KubeDeletionInventory<'a> {
+    client: kube::Client,
+    namespaces: DeletedNamespaces,
+    container_registry: &'a dyn ContainerRegistry,
+    object_storage: Option<&'a dyn ObjectStorage>,
+}
+
+impl<'a> KubeDeletionInventory<'a> {
+    pub fn new(
+        client: kube::Client,
+        namespaces: DeletedNamespaces,
+        container_registry: &'a dyn ContainerRegistry,
+        object_storage: Option<&'a dyn ObjectStorage>,
+    ) -> Self {
+        KubeDeletionInventory {
+            client,
+            namespaces,
+            container_registry,
+            object_storage,
+        }
+    }
+
+    fn list<K>(&self, namespace: &str) -> Result<Vec<K>, String>
+    where
+        K: Clone + DeserializeOwned + Debug + Resource<Scope = NamespaceResourceScope>,
+        <K as Resource>::DynamicType: Default,
+    {
+        let api: Api<K> = Api::namespaced(self.client.clone(), namespace);
+        block_on(api.list(&ListParams::default()))
+            .map(|list| list.items)
+            .map_err(|err| {
+                format!(
+                    "cannot list {} in namespace {namespace}: {err}",
+                    K::plural(&K::DynamicType::default())
+                )
+            })
+    }
+}
+
+impl DeletionInventory for KubeDeletionInventory<'_> {
+    fn namespaces(&self) -> Result<Vec<String>, String> {
+        let api: Api<Namespace> = Api::all(self.client.clone());
+        match &self.namespaces {
+            // an environment already deleted has no namespace anymore, that is not an error
+            DeletedNamespaces::Environment(namespace) => block_on(api.get_opt(namespace))
+                .map(|found| found.map(|_| namespace.clone()).into_iter().collect())
+                .map_err(|err| format!("cannot get namespace {namespace}: {err}")),
+            DeletedNamespaces::Cluster => block_on(api.list(&ListParams::default().labels(ENVIRONMENT_ID_LABEL)))
+                .map(|list| list.items.into_iter().filter_map(|ns| ns.metadata.name).collect())
+                .map_err(|err| format!("cannot list environment namespaces: {err}")),
+        }
+    }
+
+    fn workloads(&self, namespace: &str) -> Result<Vec<ListedWorkload>, String> {
+        let listed = |kind: &str, name: Option<String>| {
+            name.map(|name| ListedWorkload {
+                kind: kind.to_string(),
+                name,
+            })
+        };
+
+        let mut workloads = vec![];
+        workloads.extend(
+            self.list::<Deployment>(namespace)?
+                .into_iter()
+                .filter_map(|workload| listed("Deployment", workload.metadata.name)),
+        );
+        workloads.extend(
+            self.list::<StatefulSet>(namespace)?
+                .into_iter()
+                .filter_map(|workload| listed("StatefulSet", workload.metadata.name)),
+        );
+        workloads.extend(
+            self.list::<CronJob>(namespace)?
+                .into_iter()
+                .filter_map(|workload| listed("CronJob", workload.metadata.name)),
+        );
+        // jobs spawned by a cron job go away with it
+        workloads.extend(
+            self.list::<Job>(namespace)?
+                .into_iter()
+                .filter(|job| {
+                    !job.metadata
+                        .owner_references
+                        .iter()
+                        .flatten()
+                        .any(|owner| owner.kind == "CronJob")
+                })
+                .filter_map(|workload| listed("Job", workload.metadata.name)),
+        );
+
+        Ok(workloads)
+    }
+
+    fn persistent_volume_claims(&self, namespace: &str) -> Result<Vec<ListedVolumeClaim>, String> {
+        Ok(self
+            .list::<PersistentVolumeClaim>(namespace)?
+            .into_iter()
+            .filter_map(|pvc| {
+                let spec = pvc.spec.unwrap_or_default();
+                Some(ListedVolumeClaim {
+                    name: pvc.metadata.name?,
+                    storage: spec
+                        .resources
+                        .and_then(|resources| resources.requests)
+                        .and_then(|requests| requests.get("storage").map(|quantity| quantity.0.clone())),
+                    storage_class: spec.storage_class_name,
+                })
+            })
+            .collect())
+    }
+
+    fn load_balancers(&self, namespace: &str) -> Result<Vec<ListedLoadBalancer>, String> {
+        Ok(self
+            .list::<KubeService>(namespace)?
+            .into_iter()
+            .filter(|service| service.spec.as_ref().and_then(|spec| spec.type_.as_deref()) == Some("LoadBalancer"))
+            .filter_map(|service| {
+                let address = service
+                    .status
+                    .and_then(|status| status.load_balancer)
+                    .and_then(|load_balancer| load_balancer.ingress)
+                    .and_then(|ingress| ingress.into_iter().next())
+                    .and_then(|ingress| ingress.hostname.or(ingress.ip));
+                Some(ListedLoadBalancer {
+                    name: service.metadata.name?,
+                    address,
+                })
+            })
+            .collect())
+    }
+
+    fn dns_records(&self, namespace: &str) -> Result<Vec<String>, String> {
+        let mut hostnames: Vec<String> = self
+            .list::<Ingress>(namespace)?
+            .into_iter()
+            .flat_map(|ingress| ingress.spec.and_then(|spec| spec.rules).unwrap_or_default())
+            .filter_map(|rule| rule.host)
+            .collect();
+        hostnames.sort();
+        hostnames.dedup();
+
+        Ok(hostnames)
+    }
+
+    fn repository_exists(&self, repository_name: &str) -> Result<bool, String> {
+        match self.container_registry.get_repository(repository_name) {
+            Ok(_) => Ok(true),
+            Err(ContainerRegistryError::RepositoryDoesntExistInRegistry { .. }) => Ok(false),
+            Err(err) => Err(format!("cannot check the registry repository: {err}")),
+        }
+    }
+
+    fn bucket_exists(&self, bucket_name: &str) -> Result<bool, String> {
+        match self.object_storage {
+            Some(object_storage) => Ok(object_storage.bucket_exists(bucket_name)),
+            None => Err("buckets cannot be checked for this cluster kind".to_string()),
+        }
+    }
+}
+
+/// Resources of the environment removed outside of its namespace, deduced from the payload
+pub fn expected_environment_resources(
+    environment: &Environment,
+    kubernetes: &dyn Kubernetes,
+    container_registry: &dyn ContainerRegistry,
+) -> ExpectedResources {
+    let managed_databases = environment
+        .databases
+        .iter()
+        .filter(|database| database.is_managed_service())
+        .map(|database| ExpectedManagedDatabase {
+            name: database.name().to_string(),
+            engine: database.db_type().to_string(),
+            disk_size_in_gib: database.total_disk_size_in_gb(),
+            final_snapshot_name: database.final_snapshot_name(),
+        })
+        .collect();
+
+    let mut registry_repositories: Vec<String> = environment
+        .applications
+        .iter()
+        .flat_map(|application| application.owned_registry_repositories())
+        .chain(
+            environment
+                .jobs
+                .iter()
+                .flat_map(|job| job.owned_registry_repositories()),
+        )
+        .map(str::to_string)
+        .collect();
+
+    // services deployed from an external registry run a mirrored image, its repository is shared in cluster mode
+    let mirroring_mode = &kubernetes.advanced_settings().registry_mirroring_mode;
+    if *mirroring_mode == RegistryMirroringMode::Service {
+        let registry_info = container_registry.registry_info();
+        let mirrored_services = environment
+            .containers
+            .iter()
+            .map(|container| container.as_service().long_id())
+            .chain(
+                environment
+                    .jobs
+                    .iter()
+                    .filter(|job| job.owned_registry_repositories().is_empty())
+                    .map(|job| job.as_service().long_id()),
+            );
+        for service_id in mirrored_services {
+            let mirror_repository_name = get_mirror_repository_name(service_id, kubernetes.long_id(), mirroring_mode);
+            registry_repositories.push(registry_info.get_repository_name(&mirror_repository_name));
+        }
+    }
+
+    ExpectedResources {
+        managed_databases,
+        registry_repositories,
+        buckets: vec![],
+    }
+}
+
+/// Sends the report as a core data event, then uploads it for the console
+pub fn publish_deletion_report(
+    report: &DeletionReport,
+    dry_run: &DeleteDryRun,
+    logger: &dyn Logger,
+    event_details: EventDetails,
+) {
+    let json = match serde_json::to_string(report) {
+        Ok(json) => json,
+        Err(err) => {
+            logger.log(EngineEvent::Error(
+                EngineError::new_cannot_report_delete_dry_run(
+                    event_details,
+                    CommandError::new("Cannot serialize the deletion report".to_string(), Some(err.to_string()), None),
+                ),
+                None,
+            ));
+            return;
+        }
+    };
+
+    if let Err(err) = upload_json_document(&dry_run.upload_url, json.clone()) {
+        logger.log(EngineEvent::Error(
+            EngineError::new_cannot_report_delete_dry_run(
+                event_details.clone(),
+                CommandError::new("Cannot upload the deletion report".to_string(), Some(err.to_string()), None),
+            ),
+            None,
+        ));
+    }
+
+    let message = EventMessage::new_for_sending_core_data(report.to_string(), json);
+    logger.log(match report.unverified_count() {
+        0 => EngineEvent::Info(event_details, message),
+        _ => EngineEvent::Warning(event_details, message),
+    });
+}
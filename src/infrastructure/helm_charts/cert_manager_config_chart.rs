@@ -88,7 +88,7 @@ impl ToCommonHelmChart for CertManagerConfigsChart<'_> {
                             DnsProviderConfiguration::Cloudflare(cloudflare_config) => {
                                 cloudflare_config.cloudflare_api_token.to_string()
                             }
-                            DnsProviderConfiguration::QoveryDns(_) => "not-set".to_string(),
+                            _ => "not-set".to_string(),
                         },
                     },
                     ChartSetValue {
@@ -97,7 +97,7 @@ impl ToCommonHelmChart for CertManagerConfigsChart<'_> {
                             DnsProviderConfiguration::Cloudflare(cloudflare_config) => {
                                 cloudflare_config.cloudflare_email.to_string()
                             }
-                            DnsProviderConfiguration::QoveryDns(_) => "not-set".to_string(),
+                            _ => "not-set".to_string(),
                         },
                     },
                     // Qovery DNS
@@ -126,7 +126,7 @@ impl ToCommonHelmChart for CertManagerConfigsChart<'_> {
                                 // }
                                 format!("\"{}\"", qovery_dns_config.api_url_port)
                             }
-                            DnsProviderConfiguration::Cloudflare(_) => "no-set".to_string(),
+                            _ => "no-set".to_string(),
                         },
                     },
                     ChartSetValue {
@@ -135,7 +135,7 @@ impl ToCommonHelmChart for CertManagerConfigsChart<'_> {
                             DnsProviderConfiguration::QoveryDns(qovery_dns_config) => {
                                 qovery_dns_config.api_url_scheme_and_domain.to_string()
                             }
-                            DnsProviderConfiguration::Cloudflare(_) => "not-set".to_string(),
+                            _ => "not-set".to_string(),
                         },
                     },
                     ChartSetValue {
@@ -144,7 +144,53 @@ impl ToCommonHelmChart for CertManagerConfigsChart<'_> {
                             DnsProviderConfiguration::QoveryDns(qovery_dns_config) => {
                                 qovery_dns_config.api_key.to_string()
                             }
-                            DnsProviderConfiguration::Cloudflare(_) => "not-set".to_string(),
+                            _ => "not-set".to_string(),
+                        },
+                    },
+                    // Azure DNS
+                    ChartSetValue {
+                        key: "provider.azure.tenantId".to_string(),
+                        value: match &self.dns_provider_configuration {
+                            DnsProviderConfiguration::AzureDns(azure_dns_config) => {
+                                azure_dns_config.tenant_id.to_string()
+                            }
+                            _ => "not-set".to_string(),
+                        },
+                    },
+                    ChartSetValue {
+                        key: "provider.azure.subscriptionId".to_string(),
+                        value: match &self.dns_provider_configuration {
+                            DnsProviderConfiguration::AzureDns(azure_dns_config) => {
+                                azure_dns_config.subscription_id.to_string()
+                            }
+                            _ => "not-set".to_string(),
+                        },
+                    },
+                    ChartSetValue {
+                        key: "provider.azure.resourceGroup".to_string(),
+                        value: match &self.dns_provider_configuration {
+                            DnsProviderConfiguration::AzureDns(azure_dns_config) => {
+                                azure_dns_config.resource_group.to_string()
+                            }
+                            _ => "not-set".to_string(),
+                        },
+                    },
+                    ChartSetValue {
+                        key: "provider.azure.clientId".to_string(),
+                        value: match &self.dns_provider_configuration {
+                            DnsProviderConfiguration::AzureDns(azure_dns_config) => {
+                                azure_dns_config.client_id.to_string()
+                            }
+                            _ => "not-set".to_string(),
+                        },
+                    },
+                    ChartSetValue {
+                        key: "provider.azure.clientSecret".to_string(),
+                        value: match &self.dns_provider_configuration {
+                            DnsProviderConfiguration::AzureDns(azure_dns_config) => {
+                                azure_dns_config.client_secret.to_string()
+                            }
+                            _ => "not-set".to_string(),
                         },
                     },
                 ],
@@ -0,0 +1,398 @@
+// Dependencies between cluster charts, used to deploy concurrently the charts which don't need each other.
+//
+// Charts are generated in levels by each cloud provider (see `gen_charts_to_deploy`), a chart only waits for the
+// charts of lower levels it depends on, instead of all of them. Dependencies toward a chart of the same or of a higher
+// level are ignored, so the levels still give the order when a dependency is missing from the deployment.
+// Charts can also declare their own dependencies (see `HelmChart::depends_on`), toward any chart of the deployment.
+
+use crate::helm::ChartId;
+
+/// Charts every other chart is deployed after. They are deployed in their level order.
+/// Pods need priority classes, on EKS they can only run outside of fargate once karpenter is there,
+/// and VPA CRDs are used by most of the charts.
+pub const BOOTSTRAP_CHARTS: &[&str] = &[
+    "qovery-priority-class",
+    "karpenter-crd",
+    "iam-eks-user-mapper",
+    "karpenter",
+    "coredns",
+    "karpenter-configuration",
+    "vertical-pod-autoscaler",
+];
+
+/// Cluster charts, with the charts they must be deployed after, on top of the bootstrap ones.
+/// Charts not listed here are deployed after all the charts of lower levels.
+pub const CLUSTER_CHARTS_DEPENDENCIES: &[(&str, &[&str])] = &[
+    ("q-storageclass", &[]),
+    // provides the ServiceMonitor CRDs
+    ("kube-prometheus-stack", &[]),
+    ("promtail", &[]),
+    ("loki", &["q-storageclass"]),
+    ("prometheus-adapter", &["kube-prometheus-stack"]),
+    ("kube-state-metrics", &["kube-prometheus-stack"]),
+    ("grafana", &["kube-prometheus-stack", "loki"]),
+    // karpenter is deployed again with its monitoring once the ServiceMonitor CRDs exist
+    ("karpenter", &["kube-prometheus-stack"]),
+    ("cert-manager", &["kube-prometheus-stack"]),
+    ("qovery-cert-manager-webhook", &["cert-manager"]),
+    ("cluster-autoscaler", &[]),
+    ("metrics-server", &[]),
+    ("aws-node-term-handler", &[]),
+    ("external-dns", &[]),
+    // its webhook certificate is issued by cert-manager
+    ("aws-load-balancer-controller", &["cert-manager"]),
+    // the load balancer of the ingress is created by the aws load balancer controller when enabled
+    ("nginx-ingress", &["aws-load-balancer-controller", "kube-prometheus-stack"]),
+    // issuers need cert-manager CRDs, and the IngressClass of nginx for HTTP01 challenges
+    (
+        "cert-manager-configs",
+        &["cert-manager", "qovery-cert-manager-webhook", "nginx-ingress"],
+    ),
+    ("cluster-agent", &["kube-prometheus-stack"]),
+    ("shell-agent", &[]),
+    ("qovery-engine", &[]),
+    ("k8s-event-logger", &[]),
+];
+
+/// Returns the charts a chart must be deployed after, or None when the chart is unknown
+pub fn cluster_chart_dependencies(chart_name: &str) -> Option<&'static [&'static str]> {
+    CLUSTER_CHARTS_DEPENDENCIES
+        .iter()
+        .find(|(name, _)| *name == chart_name)
+        .map(|(_, dependencies)| *dependencies)
+}
+
+/// Returns, for charts given as (level, name), the indexes of the charts each one must be deployed after
+pub fn charts_dependencies(charts: &[(usize, &str)]) -> Vec<Vec<usize>> {
+    let is_first_of_bootstrap = |ix: usize| {
+        let (_, name) = charts[ix];
+        BOOTSTRAP_CHARTS.contains(&name) && charts.iter().position(|(_, other)| *other == name) == Some(ix)
+    };
+
+    charts
+        .iter()
+        .enumerate()
+        .map(|(ix, (level, name))| {
+            charts
+                .iter()
+                .enumerate()
+                .filter(|(other_ix, (other_level, other_name))| {
+                    if other_level >= level {
+                        return false;
+                    }
+
+                    // a chart deployed twice is upgraded in order
+                    name == other_name
+                        || is_first_of_bootstrap(*other_ix)
+                        || is_first_of_bootstrap(ix)
+                        || match cluster_chart_dependencies(name) {
+                            Some(dependencies) => dependencies.contains(other_name),
+                            None => !BOOTSTRAP_CHARTS.contains(name),
+                        }
+                })
+                .map(|(other_ix, _)| other_ix)
+                .collect()
+        })
+        .collect()
+}
+
+/// Adds the dependencies declared by the charts, given by name, to the ones computed by `charts_dependencies`
+pub fn add_declared_dependencies(dependencies: &mut [Vec<usize>], charts: &[(usize, &str)], declared: &[Vec<ChartId>]) {
+    for (ix, chart_dependencies) in declared.iter().enumerate() {
+        for dependency in chart_dependencies {
+            dependencies[ix].extend(
+                charts
+                    .iter()
+                    .enumerate()
+                    .filter(|(other_ix, (_, other_name))| *other_ix != ix && other_name == dependency)
+                    .map(|(other_ix, _)| other_ix),
+            );
+        }
+        dependencies[ix].sort_unstable();
+        dependencies[ix].dedup();
+    }
+}
+
+/// Groups charts in batches from their dependencies. Charts of a batch only depend on charts of previous batches.
+/// Returns the charts of a dependency cycle, the first one repeated at the end, when there is one.
+pub fn deployment_batches(dependencies: &[Vec<usize>]) -> Result<Vec<Vec<usize>>, Vec<usize>> {
+    let mut batch_of_chart: Vec<Option<usize>> = vec![None; dependencies.len()];
+    let mut placed = 0;
+    while placed < dependencies.len() {
+        let placed_before = placed;
+        for ix in 0..dependencies.len() {
+            if batch_of_chart[ix].is_some() {
+                continue;
+            }
+            let batch = dependencies[ix]
+                .iter()
+                .map(|dependency| batch_of_chart[*dependency].map(|batch| batch + 1))
+                .try_fold(0, |max, batch| batch.map(|batch| batch.max(max)));
+            if batch.is_some() {
+                batch_of_chart[ix] = batch;
+                placed += 1;
+            }
+        }
+
+        // charts left can't be placed, they are in a cycle or depend on one
+        if placed == placed_before {
+            return Err(find_cycle(dependencies, &batch_of_chart));
+        }
+    }
+
+    let batch_of_chart = batch_of_chart.into_iter().flatten().collect::<Vec<_>>();
+    let batches_count = batch_of_chart.iter().max().map(|max| max + 1).unwrap_or(0);
+    Ok((0..batches_count)
+        .map(|batch| {
+            (0..dependencies.len())
+                .filter(|ix| batch_of_chart[*ix] == batch)
+                .collect()
+        })
+        .collect())
+}
+
+// follows the dependencies not placed yet until a chart is met again
+fn find_cycle(dependencies: &[Vec<usize>], batch_of_chart: &[Option<usize>]) -> Vec<usize> {
+    let mut path = vec![];
+    let mut current = batch_of_chart.iter().position(Option::is_none);
+    while let Some(ix) = current {
+        if let Some(start) = path.iter().position(|other| *other == ix) {
+            path.push(ix);
+            return path.split_off(start);
+        }
+        path.push(ix);
+        current = dependencies[ix]
+            .iter()
+            .copied()
+            .find(|dependency| batch_of_chart[*dependency].is_none());
+    }
+
+    path
+}
+
+/// Groups charts, given as (level, name), in batches. Charts of a batch only depend on charts of previous batches.
+pub fn charts_deployment_batches(charts: &[(usize, &str)]) -> Vec<Vec<usize>> {
+    // dependencies are always toward lower levels, they can't have a cycle
+    deployment_batches(&charts_dependencies(charts)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn find_cycle<'a>(chart: &'a str, path: &mut Vec<&'a str>, checked: &mut HashSet<&'a str>) -> bool {
+        if path.contains(&chart) {
+            path.push(chart);
+            return true;
+        }
+        if !checked.insert(chart) {
+            return false;
+        }
+
+        path.push(chart);
+        for dependency in cluster_chart_dependencies(chart).unwrap_or_default() {
+            if find_cycle(dependency, path, checked) {
+                return true;
+            }
+        }
+        path.pop();
+
+        false
+    }
+
+    #[test]
+    fn test_cluster_charts_dependencies_have_no_cycle() {
+        let mut checked = HashSet::new();
+        for (chart, _) in CLUSTER_CHARTS_DEPENDENCIES {
+            let mut path = vec![];
+            assert!(
+                !find_cycle(chart, &mut path, &mut checked),
+                "dependency cycle between cluster charts: {}",
+                path.join(" -> ")
+            );
+        }
+    }
+
+    #[test]
+    fn test_cluster_charts_dependencies_are_known_charts() {
+        for (chart, dependencies) in CLUSTER_CHARTS_DEPENDENCIES {
+            assert_eq!(
+                CLUSTER_CHARTS_DEPENDENCIES
+                    .iter()
+                    .filter(|(other, _)| other == chart)
+                    .count(),
+                1,
+                "{chart} is declared more than once"
+            );
+            for dependency in dependencies.iter() {
+                assert!(
+                    cluster_chart_dependencies(dependency).is_some(),
+                    "{chart} depends on unknown chart {dependency}"
+                );
+                assert!(
+                    !BOOTSTRAP_CHARTS.contains(dependency),
+                    "{chart} already depends on {dependency}"
+                );
+            }
+        }
+    }
+
+    fn batches_names<'a>(charts: &[(usize, &'a str)]) -> Vec<Vec<&'a str>> {
+        charts_deployment_batches(charts)
+            .into_iter()
+            .map(|batch| batch.into_iter().map(|ix| charts[ix].1).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_eks_charts_deployment_batches() {
+        // EKS with karpenter, observability and the aws load balancer controller
+        let charts = vec![
+            (0, "qovery-priority-class"),
+            (0, "karpenter-crd"),
+            (1, "iam-eks-user-mapper"),
+            (1, "coredns"),
+            (1, "karpenter"),
+            (2, "karpenter-configuration"),
+            (3, "vertical-pod-autoscaler"),
+            (4, "q-storageclass"),
+            (4, "vertical-pod-autoscaler"),
+            (4, "kube-prometheus-stack"),
+            (4, "promtail"),
+            (5, "prometheus-adapter"),
+            (5, "kube-state-metrics"),
+            (5, "loki"),
+            (5, "grafana"),
+            (6, "cert-manager"),
+            (6, "karpenter"),
+            (7, "cluster-autoscaler"),
+            (7, "qovery-cert-manager-webhook"),
+            (8, "metrics-server"),
+            (8, "aws-node-term-handler"),
+            (8, "external-dns"),
+            (8, "aws-load-balancer-controller"),
+            (9, "nginx-ingress"),
+            (10, "cert-manager-configs"),
+            (10, "cluster-agent"),
+            (10, "shell-agent"),
+            (10, "qovery-engine"),
+            (10, "k8s-event-logger"),
+        ];
+
+        assert_eq!(
+            batches_names(&charts),
+            vec![
+                vec!["qovery-priority-class", "karpenter-crd"],
+                vec!["iam-eks-user-mapper", "coredns", "karpenter"],
+                vec!["karpenter-configuration"],
+                vec!["vertical-pod-autoscaler"],
+                vec![
+                    "q-storageclass",
+                    "vertical-pod-autoscaler",
+                    "kube-prometheus-stack",
+                    "promtail",
+                    "cluster-autoscaler",
+                    "metrics-server",
+                    "aws-node-term-handler",
+                    "external-dns",
+                    "shell-agent",
+                    "qovery-engine",
+                    "k8s-event-logger",
+                ],
+                vec![
+                    "prometheus-adapter",
+                    "kube-state-metrics",
+                    "loki",
+                    "grafana",
+                    "cert-manager",
+                    "karpenter",
+                    "cluster-agent",
+                ],
+                vec!["qovery-cert-manager-webhook", "aws-load-balancer-controller"],
+                vec!["nginx-ingress"],
+                vec!["cert-manager-configs"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deployment_batches_detect_cycles() {
+        // 0 <- 1 <- 2 <- 3 <- 1, and 4 depending on the cycle
+        let dependencies = vec![vec![], vec![0, 3], vec![1], vec![2], vec![3]];
+
+        assert_eq!(deployment_batches(&dependencies), Err(vec![1, 3, 2, 1]));
+        assert_eq!(deployment_batches(&[vec![0]]), Err(vec![0, 0]));
+    }
+
+    #[test]
+    fn test_declared_dependencies() {
+        // setup:
+        let charts = vec![
+            (0, "qovery-priority-class"),
+            (1, "kube-prometheus-stack"),
+            (1, "some-operator"),
+            (1, "some-operator-config"),
+        ];
+        let mut dependencies = charts_dependencies(&charts);
+        let declared = vec![
+            vec![],
+            vec![],
+            vec![],
+            vec!["some-operator".to_string(), "unknown".to_string()],
+        ];
+
+        // execute:
+        add_declared_dependencies(&mut dependencies, &charts, &declared);
+
+        // verify:
+        assert_eq!(dependencies[3], vec![0, 2]);
+        assert_eq!(
+            batches_names_from(&charts, &dependencies),
+            vec![
+                vec!["qovery-priority-class"],
+                vec!["kube-prometheus-stack", "some-operator"],
+                vec!["some-operator-config"],
+            ]
+        );
+
+        // a declared dependency toward a chart depending on it is a cycle
+        let mut dependencies = charts_dependencies(&charts);
+        let declared = vec![vec!["some-operator".to_string()], vec![], vec![], vec![]];
+        add_declared_dependencies(&mut dependencies, &charts, &declared);
+        assert_eq!(deployment_batches(&dependencies), Err(vec![0, 2, 0]));
+    }
+
+    fn batches_names_from<'a>(charts: &[(usize, &'a str)], dependencies: &[Vec<usize>]) -> Vec<Vec<&'a str>> {
+        deployment_batches(dependencies)
+            .unwrap()
+            .into_iter()
+            .map(|batch| batch.into_iter().map(|ix| charts[ix].1).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_charts_deployment_batches_without_karpenter() {
+        // coredns is deployed late on EKS without karpenter, everything after waits for it
+        let charts = vec![
+            (0, "qovery-priority-class"),
+            (4, "q-storageclass"),
+            (4, "kube-prometheus-stack"),
+            (5, "coredns"),
+            (6, "cert-manager"),
+            (8, "external-dns"),
+            (10, "some-unknown-chart"),
+        ];
+
+        assert_eq!(
+            batches_names(&charts),
+            vec![
+                vec!["qovery-priority-class"],
+                vec!["q-storageclass", "kube-prometheus-stack"],
+                vec!["coredns"],
+                vec!["cert-manager", "external-dns"],
+                vec!["some-unknown-chart"],
+            ]
+        );
+    }
+}
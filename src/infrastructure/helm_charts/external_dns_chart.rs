@@ -144,6 +144,42 @@ impl ToCommonHelmChart for ExternalDNSChart {
                             _ => "".to_string(),
                         },
                     },
+                    // Azure DNS
+                    ChartSetValue {
+                        key: "azure.tenantId".to_string(),
+                        value: match &self.dns_provider_configuration {
+                            DnsProviderConfiguration::AzureDns(config) => config.tenant_id.to_string(),
+                            _ => "".to_string(),
+                        },
+                    },
+                    ChartSetValue {
+                        key: "azure.subscriptionId".to_string(),
+                        value: match &self.dns_provider_configuration {
+                            DnsProviderConfiguration::AzureDns(config) => config.subscription_id.to_string(),
+                            _ => "".to_string(),
+                        },
+                    },
+                    ChartSetValue {
+                        key: "azure.resourceGroup".to_string(),
+                        value: match &self.dns_provider_configuration {
+                            DnsProviderConfiguration::AzureDns(config) => config.resource_group.to_string(),
+                            _ => "".to_string(),
+                        },
+                    },
+                    ChartSetValue {
+                        key: "azure.aadClientId".to_string(),
+                        value: match &self.dns_provider_configuration {
+                            DnsProviderConfiguration::AzureDns(config) => config.client_id.to_string(),
+                            _ => "".to_string(),
+                        },
+                    },
+                    ChartSetValue {
+                        key: "azure.aadClientSecret".to_string(),
+                        value: match &self.dns_provider_configuration {
+                            DnsProviderConfiguration::AzureDns(config) => config.client_secret.to_string(),
+                            _ => "".to_string(),
+                        },
+                    },
                 ],
                 ..Default::default()
             },
@@ -2,9 +2,11 @@ use std::sync::Arc;
 
 use crate::errors::CommandError;
 use crate::helm::{
-    ChartInfo, ChartInstallationChecker, ChartSetValue, CommonChart, CommonChartVpa, HelmChartError,
-    HelmChartNamespaces, VpaConfig, VpaContainerPolicy, VpaTargetRef, VpaTargetRefApiVersion, VpaTargetRefKind,
+    ChartInfo, ChartInstallationChecker, ChartSetValue, ChartValuesGenerated, CommonChart, CommonChartVpa,
+    HelmChartError, HelmChartNamespaces, VpaConfig, VpaContainerPolicy, VpaTargetRef, VpaTargetRefApiVersion,
+    VpaTargetRefKind,
 };
+use crate::infrastructure::helm_charts::service_logging::{loki_retention_streams, ServiceLogging};
 use crate::infrastructure::helm_charts::{
     HelmChartDirectoryLocation, HelmChartPath, HelmChartResources, HelmChartResourcesConstraintType, HelmChartTimeout,
     HelmChartValuesFilePath, ToCommonHelmChart,
@@ -56,6 +58,7 @@ pub struct LokiChart {
     chart_resources: HelmChartResources,
     additional_char_path: Option<HelmChartValuesFilePath>,
     chart_timeout: HelmChartTimeout,
+    services_logging: Vec<ServiceLogging>,
 }
 
 impl LokiChart {
@@ -71,6 +74,7 @@ impl LokiChart {
         chart_resources: HelmChartResourcesConstraintType,
         chart_timeout: HelmChartTimeout,
         karpenter_enabled: bool,
+        services_logging: Vec<ServiceLogging>,
     ) -> Self {
         LokiChart {
             chart_prefix_path: chart_prefix_path.map(|s| s.to_string()),
@@ -109,6 +113,7 @@ impl LokiChart {
                 false => None,
             },
             chart_timeout,
+            services_logging,
         }
     }
 
@@ -137,6 +142,22 @@ impl ToCommonHelmChart for LokiChart {
             values_files.push(additional_char_path.to_string());
         }
 
+        // per service retention, applied by the compactor
+        let retention_streams = ChartValuesGenerated::new(
+            "qovery_loki_services_logging".to_string(),
+            serde_yaml::to_string(&serde_json::json!({
+                "loki": { "limits_config": { "retention_stream": loki_retention_streams(&self.services_logging) } }
+            }))
+            .map_err(|e| HelmChartError::RenderingError {
+                chart_name: LokiChart::chart_name(),
+                msg: e.to_string(),
+            })?,
+        );
+        let mut yaml_files_content = vec![retention_streams];
+        if let Some(customer_helm_chart_override) = &self.customer_helm_chart_override {
+            yaml_files_content.push(customer_helm_chart_override.to_chart_values_generated());
+        }
+
         Ok(CommonChart {
             chart_info: ChartInfo {
                 name: LokiChart::chart_name(),
@@ -287,10 +308,7 @@ impl ToCommonHelmChart for LokiChart {
                             .to_string(),
                     },
                 ],
-                yaml_files_content: match self.customer_helm_chart_override.clone() {
-                    Some(x) => vec![x.to_chart_values_generated()],
-                    None => vec![],
-                },
+                yaml_files_content,
                 ..Default::default()
             },
             chart_installation_checker: Some(Box::new(LokiChartChecker::new())),
@@ -383,6 +401,7 @@ mod tests {
             HelmChartResourcesConstraintType::ChartDefault,
             HelmChartTimeout::ChartDefault,
             false,
+            vec![],
         );
 
         let current_directory = env::current_dir().expect("Impossible to get current directory");
@@ -457,6 +476,7 @@ mod tests {
             HelmChartResourcesConstraintType::ChartDefault,
             HelmChartTimeout::ChartDefault,
             false,
+            vec![],
         );
         let common_chart = chart.to_common_helm_chart().unwrap();
 
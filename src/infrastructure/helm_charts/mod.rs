@@ -10,6 +10,7 @@ use time::Duration;
 pub mod cert_manager_chart;
 pub mod cert_manager_config_chart;
 pub mod coredns_config_chart;
+pub mod dependencies;
 pub mod external_dns_chart;
 pub mod grafana_chart;
 pub mod k8s_event_logger;
@@ -23,8 +24,10 @@ pub mod promtail_chart;
 pub mod qovery_cert_manager_webhook_chart;
 pub mod qovery_cluster_agent_chart;
 pub mod qovery_priority_class_chart;
+pub mod qovery_registry_mirrors_chart;
 pub mod qovery_shell_agent_chart;
 pub mod qovery_storage_class_chart;
+pub mod service_logging;
 pub mod vertical_pod_autoscaler;
 
 pub enum HelmChartTimeout {
@@ -1,3 +1,6 @@
+use serde_derive::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
 use std::sync::Arc;
 use strum_macros::EnumIter;
@@ -83,6 +86,126 @@ impl NginxServerSnippet {
     }
 }
 
+// Content is stored in a ConfigMap and in the helm release, both are limited to 1MiB
+pub const CUSTOM_ERROR_PAGES_MAX_SIZE_IN_BYTES: usize = 256 * 1024;
+pub const CUSTOM_ERROR_PAGES_IMAGE_REGISTRY: &str = "registry.k8s.io";
+pub const CUSTOM_ERROR_PAGES_IMAGE_NAME: &str = "ingress-nginx/custom-error-pages";
+pub const CUSTOM_ERROR_PAGES_IMAGE_TAG: &str = "v1.0.1";
+const CUSTOM_ERROR_PAGES_CONFIG_MAP_NAME: &str = "qovery-custom-error-pages";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorPageContent {
+    Html(String),
+    /// Image displayed alone in the page, data is base64 encoded
+    Image {
+        content_type: String,
+        data: String,
+    },
+}
+
+/// Page served by the ingress default backend instead of the nginx ones, which expose the controller identity
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CustomErrorPages {
+    http_codes: BTreeSet<u16>,
+    page: String,
+}
+
+impl CustomErrorPages {
+    pub fn new(http_codes: BTreeSet<u16>, content: ErrorPageContent) -> Self {
+        let page = match content {
+            ErrorPageContent::Html(html) => html,
+            ErrorPageContent::Image { content_type, data } => format!(
+                r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>Error</title></head><body style="margin:0;display:flex;align-items:center;justify-content:center;min-height:100vh"><img src="data:{content_type};base64,{data}" alt="Error" style="max-width:100%"></body></html>"#
+            ),
+        };
+
+        CustomErrorPages { http_codes, page }
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        self.files().values().map(|page| page.len()).sum()
+    }
+
+    /// Value of the nginx `custom-http-errors` setting
+    pub fn custom_http_errors(&self) -> String {
+        self.http_codes
+            .iter()
+            .map(|code| code.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Files served by the error pages backend. It looks for `<code>.html` then `<class>xx.html`,
+    /// so one file per class of status code is enough.
+    pub fn files(&self) -> BTreeMap<String, String> {
+        self.http_codes
+            .iter()
+            .map(|code| (format!("{}xx.html", code / 100), self.page.clone()))
+            .collect()
+    }
+
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(format!("{}\n{}", self.custom_http_errors(), self.page)))
+    }
+
+    pub fn to_tera_context(&self) -> CustomErrorPagesTeraContext {
+        CustomErrorPagesTeraContext {
+            custom_http_errors: self.custom_http_errors(),
+            files: self.files(),
+            checksum: self.checksum(),
+            image: format!(
+                "{CUSTOM_ERROR_PAGES_IMAGE_REGISTRY}/{CUSTOM_ERROR_PAGES_IMAGE_NAME}:{CUSTOM_ERROR_PAGES_IMAGE_TAG}"
+            ),
+        }
+    }
+
+    // Values enabling the default backend of the controller, serving the pages from a ConfigMap
+    fn to_chart_values(&self) -> serde_json::Value {
+        serde_json::json!({
+            "controller": {
+                "config": {
+                    "custom-http-errors": self.custom_http_errors(),
+                },
+            },
+            "defaultBackend": {
+                "enabled": true,
+                "replicaCount": 2,
+                "minAvailable": 1,
+                "image": {
+                    "registry": CUSTOM_ERROR_PAGES_IMAGE_REGISTRY,
+                    "image": CUSTOM_ERROR_PAGES_IMAGE_NAME,
+                    "tag": CUSTOM_ERROR_PAGES_IMAGE_TAG,
+                },
+                // pages are reloaded by the kubelet anyway, but this makes the rollout explicit
+                "podAnnotations": {
+                    "checksum/custom-error-pages": self.checksum(),
+                },
+                "extraConfigMaps": [{
+                    "name": CUSTOM_ERROR_PAGES_CONFIG_MAP_NAME,
+                    "data": self.files(),
+                }],
+                "extraVolumes": [{
+                    "name": "custom-error-pages",
+                    "configMap": { "name": CUSTOM_ERROR_PAGES_CONFIG_MAP_NAME },
+                }],
+                "extraVolumeMounts": [{
+                    "name": "custom-error-pages",
+                    "mountPath": "/www",
+                    "readOnly": true,
+                }],
+            },
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CustomErrorPagesTeraContext {
+    pub custom_http_errors: String,
+    pub files: BTreeMap<String, String>,
+    pub checksum: String,
+    pub image: String,
+}
+
 pub struct NginxIngressChart {
     chart_path: HelmChartPath,
     chart_values_path: HelmChartValuesFilePath,
@@ -108,6 +231,7 @@ pub struct NginxIngressChart {
     log_format_escaping: LogFormatEscaping,
     is_alb_enabled: bool,
     http_snippet: Option<NginxHttpSnippet>,
+    custom_error_pages: Option<CustomErrorPages>,
 }
 
 impl NginxIngressChart {
@@ -135,6 +259,7 @@ impl NginxIngressChart {
         log_format_escaping: LogFormatEscaping,
         is_alb_enabled: bool,
         http_snippet: Option<NginxHttpSnippet>,
+        custom_error_pages: Option<CustomErrorPages>,
     ) -> Self {
         NginxIngressChart {
             chart_path: HelmChartPath::new(
@@ -185,6 +310,7 @@ impl NginxIngressChart {
             log_format_escaping,
             is_alb_enabled,
             http_snippet,
+            custom_error_pages,
         }
     }
 
@@ -436,6 +562,21 @@ defaultBackend:
             value: self.domain.wildcarded().to_string(),
         });
 
+        // Toggling the default backend changes the controller arguments, the controller is then rolled out
+        // without any unavailable pod (see updateStrategy in values files)
+        let rendered_custom_error_pages = match &self.custom_error_pages {
+            Some(custom_error_pages) => Some(ChartValuesGenerated::new(
+                "qovery_nginx_ingress_custom_error_pages".to_string(),
+                serde_yaml::to_string(&custom_error_pages.to_chart_values()).map_err(|e| {
+                    HelmChartError::RenderingError {
+                        chart_name: NginxIngressChart::chart_name(),
+                        msg: e.to_string(),
+                    }
+                })?,
+            )),
+            None => None,
+        };
+
         Ok(CommonChart {
             chart_info: ChartInfo {
                 name: NginxIngressChart::chart_old_name(),
@@ -450,6 +591,9 @@ defaultBackend:
                 yaml_files_content: {
                     // order matters: last one overrides previous ones, so customer override should be last
                     let mut x = vec![rendered_nginx_override];
+                    if let Some(custom_error_pages) = rendered_custom_error_pages {
+                        x.push(custom_error_pages);
+                    }
                     if let Some(customer_helm_chart_override) = self.customer_helm_chart_override.clone() {
                         x.push(customer_helm_chart_override.to_chart_values_generated());
                     };
@@ -495,6 +639,7 @@ mod tests {
     use crate::helm::HelmChartNamespaces;
     use crate::infrastructure::helm_charts::nginx_ingress_chart::LogFormatEscaping;
     use crate::infrastructure::helm_charts::nginx_ingress_chart::NginxIngressChart;
+    use crate::infrastructure::helm_charts::nginx_ingress_chart::{CustomErrorPages, ErrorPageContent};
     use crate::infrastructure::helm_charts::HelmChartResourcesConstraintType;
     use crate::infrastructure::helm_charts::HelmChartType;
     use crate::infrastructure::helm_charts::ToCommonHelmChart;
@@ -504,6 +649,7 @@ mod tests {
     use crate::infrastructure::models::cloud_provider::Kind;
     use crate::infrastructure::models::kubernetes::Kind as KubernetesKind;
     use crate::io_models::models::CustomerHelmChartsOverride;
+    use std::collections::BTreeSet;
     use std::env;
     use std::sync::Arc;
     use strum::IntoEnumIterator;
@@ -549,6 +695,7 @@ mod tests {
             LogFormatEscaping::Default,
             false,
             None,
+            None,
         );
 
         let current_directory = env::current_dir().expect("Impossible to get current directory");
@@ -597,6 +744,7 @@ mod tests {
             LogFormatEscaping::Default,
             false,
             None,
+            None,
         );
 
         let current_directory = env::current_dir().expect("Impossible to get current directory");
@@ -647,6 +795,7 @@ mod tests {
                 log_format_escaping.clone(),
                 false,
                 None,
+                None,
             );
 
             // execute:
@@ -671,6 +820,105 @@ mod tests {
         }
     }
 
+    #[test]
+    fn nginx_ingress_chart_custom_error_pages_test() {
+        let new_chart = |custom_error_pages: Option<CustomErrorPages>| {
+            NginxIngressChart::new(
+                None,
+                HelmChartResourcesConstraintType::ChartDefault,
+                HelmChartResourcesConstraintType::ChartDefault,
+                true,
+                get_nginx_ingress_chart_override(),
+                get_domain().wildcarded(),
+                Kind::Aws,
+                "00000000-0000-4000-8000-000000000000".to_string(),
+                "z00000000".to_string(),
+                "10000000-0000-4000-8000-000000000000".to_string(),
+                "z10000000".to_string(),
+                KubernetesKind::Eks,
+                None,
+                None,
+                None,
+                HelmChartNamespaces::NginxIngress,
+                None,
+                true,
+                true,
+                true,
+                LogFormatEscaping::Default,
+                false,
+                None,
+                custom_error_pages,
+            )
+        };
+
+        // disabled: the chart default backend stays disabled
+        let common_chart = new_chart(None)
+            .to_common_helm_chart()
+            .expect("cannot create common chart");
+        assert!(!common_chart
+            .chart_info
+            .yaml_files_content
+            .iter()
+            .any(|values| values.yaml_content.contains("custom-http-errors")));
+
+        // enabled
+        let custom_error_pages = CustomErrorPages::new(
+            BTreeSet::from([404, 502, 503]),
+            ErrorPageContent::Html("<h1>Oops</h1>".to_string()),
+        );
+        let common_chart = new_chart(Some(custom_error_pages.clone()))
+            .to_common_helm_chart()
+            .expect("cannot create common chart");
+        let values_files = &common_chart.chart_info.yaml_files_content;
+        let custom_error_pages_values = values_files
+            .iter()
+            .find(|values| values.filename == "qovery_nginx_ingress_custom_error_pages_override.yaml")
+            .expect("custom error pages values are missing");
+        // customer override must stay last
+        assert_eq!(
+            values_files.last().map(|values| values.filename.as_str()),
+            Some("customer_ingress-nginx_override.yaml")
+        );
+
+        let values: serde_yaml::Value = serde_yaml::from_str(&custom_error_pages_values.yaml_content).unwrap();
+        assert_eq!(
+            values["controller"]["config"]["custom-http-errors"].as_str(),
+            Some("404,502,503")
+        );
+        assert_eq!(values["defaultBackend"]["enabled"].as_bool(), Some(true));
+        assert_eq!(
+            values["defaultBackend"]["image"]["image"].as_str(),
+            Some("ingress-nginx/custom-error-pages")
+        );
+        let files = &values["defaultBackend"]["extraConfigMaps"][0]["data"];
+        assert_eq!(files["4xx.html"].as_str(), Some("<h1>Oops</h1>"));
+        assert_eq!(files["5xx.html"].as_str(), Some("<h1>Oops</h1>"));
+        assert_eq!(
+            values["defaultBackend"]["podAnnotations"]["checksum/custom-error-pages"].as_str(),
+            Some(custom_error_pages.checksum().as_str())
+        );
+        assert_eq!(
+            values["defaultBackend"]["extraVolumes"][0]["configMap"]["name"],
+            values["defaultBackend"]["extraConfigMaps"][0]["name"]
+        );
+    }
+
+    #[test]
+    fn custom_error_pages_image_content_test() {
+        let custom_error_pages = CustomErrorPages::new(
+            BTreeSet::from([503]),
+            ErrorPageContent::Image {
+                content_type: "image/png".to_string(),
+                data: "aW1hZ2U=".to_string(),
+            },
+        );
+
+        let files = custom_error_pages.files();
+        assert_eq!(files.keys().collect::<Vec<_>>(), vec!["5xx.html"]);
+        assert!(files["5xx.html"].contains(r#"src="data:image/png;base64,aW1hZ2U=""#));
+        assert_eq!(custom_error_pages.size_in_bytes(), files["5xx.html"].len());
+    }
+
     /// Make sure rust code doesn't set a value not declared inside values file.
     /// All values should be declared / set in values file unless it needs to be injected via rust code.
     #[test]
@@ -701,6 +949,7 @@ mod tests {
             LogFormatEscaping::Default,
             false,
             None,
+            None,
         );
         let common_chart = chart.to_common_helm_chart().unwrap();
 
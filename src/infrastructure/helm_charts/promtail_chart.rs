@@ -2,10 +2,11 @@ use std::sync::Arc;
 
 use crate::errors::CommandError;
 use crate::helm::{
-    ChartInfo, ChartInstallationChecker, ChartSetValue, CommonChart, CommonChartVpa, HelmChartError,
-    HelmChartNamespaces, PriorityClass, VpaConfig, VpaContainerPolicy, VpaTargetRef, VpaTargetRefApiVersion,
-    VpaTargetRefKind,
+    ChartInfo, ChartInstallationChecker, ChartSetValue, ChartValuesGenerated, CommonChart, CommonChartVpa,
+    HelmChartError, HelmChartNamespaces, PriorityClass, VpaConfig, VpaContainerPolicy, VpaTargetRef,
+    VpaTargetRefApiVersion, VpaTargetRefKind,
 };
+use crate::infrastructure::helm_charts::service_logging::{promtail_pipeline_stages, ServiceLogging};
 use crate::infrastructure::helm_charts::{
     HelmChartDirectoryLocation, HelmChartPath, HelmChartValuesFilePath, ToCommonHelmChart,
 };
@@ -23,6 +24,7 @@ pub struct PromtailChart {
     namespace: HelmChartNamespaces,
     priority_class: PriorityClass,
     additional_char_path: Option<HelmChartValuesFilePath>,
+    services_logging: Vec<ServiceLogging>,
 }
 
 impl PromtailChart {
@@ -35,6 +37,7 @@ impl PromtailChart {
         namespace: HelmChartNamespaces,
         priority_class: PriorityClass,
         karpenter_enabled: bool,
+        services_logging: Vec<ServiceLogging>,
     ) -> Self {
         PromtailChart {
             chart_prefix_path: chart_prefix_path.map(|s| s.to_string()),
@@ -61,6 +64,7 @@ impl PromtailChart {
                 )),
                 false => None,
             },
+            services_logging,
         }
     }
 
@@ -76,6 +80,23 @@ impl ToCommonHelmChart for PromtailChart {
             values_files.push(additional_char_path.to_string());
         }
 
+        // A new pipeline changes the config checksum of the pods, which are rolled one node at a time (see updateStrategy
+        // in values file). The positions file is kept on the node, so the new pod resumes where the previous one stopped.
+        let pipeline_stages = ChartValuesGenerated::new(
+            "qovery_promtail_services_logging".to_string(),
+            serde_yaml::to_string(&serde_json::json!({
+                "config": { "snippets": { "pipelineStages": promtail_pipeline_stages(&self.services_logging) } }
+            }))
+            .map_err(|e| HelmChartError::RenderingError {
+                chart_name: PromtailChart::chart_name(),
+                msg: e.to_string(),
+            })?,
+        );
+        let mut yaml_files_content = vec![pipeline_stages];
+        if let Some(customer_helm_chart_override) = &self.customer_helm_chart_override {
+            yaml_files_content.push(customer_helm_chart_override.to_chart_values_generated());
+        }
+
         let mut chart_info = ChartInfo {
             name: PromtailChart::chart_name(),
             reinstall_chart_if_installed_version_is_below_than: Some(Version::new(5, 1, 0)),
@@ -96,10 +117,7 @@ impl ToCommonHelmChart for PromtailChart {
                     value: format!("http://{}/loki/api/v1/push", self.loki_kube_dns_name),
                 },
             ],
-            yaml_files_content: match self.customer_helm_chart_override.clone() {
-                Some(x) => vec![x.to_chart_values_generated()],
-                None => vec![],
-            },
+            yaml_files_content,
             // As promtail is on every node, it can take some time and failing the chart deployment
             // e.g papershift production cluster has 33 nodes !
             timeout_in_seconds: 1800,
@@ -206,6 +224,7 @@ mod tests {
             HelmChartNamespaces::KubeSystem,
             PriorityClass::Default,
             false,
+            vec![],
         );
 
         let current_directory = env::current_dir().expect("Impossible to get current directory");
@@ -238,6 +257,7 @@ mod tests {
             HelmChartNamespaces::KubeSystem,
             PriorityClass::Default,
             false,
+            vec![],
         );
 
         let current_directory = env::current_dir().expect("Impossible to get current directory");
@@ -274,6 +294,7 @@ mod tests {
             HelmChartNamespaces::KubeSystem,
             PriorityClass::Default,
             false,
+            vec![],
         );
         let common_chart = chart.to_common_helm_chart().unwrap();
 
@@ -0,0 +1,164 @@
+use crate::helm::{ChartInfo, CommonChart, HelmAction, HelmChartError, HelmChartNamespaces};
+use crate::infrastructure::helm_charts::{
+    HelmChartDirectoryLocation, HelmChartPath, HelmChartValuesFilePath, ToCommonHelmChart,
+};
+
+/// Writes the Docker Hub mirrors into the containerd registry configuration of every node.
+/// The hosts.toml is rendered by tera from the cluster advanced settings into the chart values.
+pub struct QoveryRegistryMirrorsChart {
+    chart_path: HelmChartPath,
+    chart_values_path: HelmChartValuesFilePath,
+    namespace: HelmChartNamespaces,
+    enabled: bool,
+}
+
+impl QoveryRegistryMirrorsChart {
+    pub fn new(chart_prefix_path: Option<&str>, enabled: bool, namespace: HelmChartNamespaces) -> Self {
+        QoveryRegistryMirrorsChart {
+            chart_path: HelmChartPath::new(
+                chart_prefix_path,
+                HelmChartDirectoryLocation::CommonFolder,
+                QoveryRegistryMirrorsChart::chart_name(),
+            ),
+            chart_values_path: HelmChartValuesFilePath::new(
+                chart_prefix_path,
+                HelmChartDirectoryLocation::CloudProviderFolder,
+                QoveryRegistryMirrorsChart::chart_name(),
+            ),
+            namespace,
+            enabled,
+        }
+    }
+
+    pub fn chart_name() -> String {
+        "qovery-registry-mirrors".to_string()
+    }
+}
+
+impl ToCommonHelmChart for QoveryRegistryMirrorsChart {
+    fn to_common_helm_chart(&self) -> Result<CommonChart, HelmChartError> {
+        Ok(CommonChart {
+            chart_info: ChartInfo {
+                name: QoveryRegistryMirrorsChart::chart_name(),
+                namespace: self.namespace,
+                // uninstalling the chart removes the mirrors from the nodes
+                action: match self.enabled {
+                    true => HelmAction::Deploy,
+                    false => HelmAction::Destroy,
+                },
+                path: self.chart_path.to_string(),
+                values_files: vec![self.chart_values_path.to_string()],
+                ..Default::default()
+            },
+            chart_installation_checker: None,
+            vertical_pod_autoscaler: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helm::{HelmAction, HelmChartNamespaces};
+    use crate::infrastructure::helm_charts::qovery_registry_mirrors_chart::QoveryRegistryMirrorsChart;
+    use crate::infrastructure::helm_charts::{
+        get_helm_path_kubernetes_provider_sub_folder_name, HelmChartType, ToCommonHelmChart,
+    };
+    use crate::infrastructure::models::cloud_provider::io::ClusterAdvancedSettings;
+    use crate::infrastructure::models::kubernetes::Kind as KubernetesKind;
+    use std::env;
+    use tera::{Context, Tera};
+    use url::Url;
+
+    fn chart_values_path(kubernetes_kind: KubernetesKind) -> String {
+        let chart = QoveryRegistryMirrorsChart::new(None, true, HelmChartNamespaces::KubeSystem);
+        format!(
+            "{}/lib/{}/bootstrap/chart_values/{}.j2.yaml",
+            env::current_dir()
+                .expect("Impossible to get current directory")
+                .to_str()
+                .expect("Impossible to convert current directory to string"),
+            get_helm_path_kubernetes_provider_sub_folder_name(
+                chart.chart_values_path.helm_path(),
+                HelmChartType::CloudProviderSpecific(kubernetes_kind),
+            ),
+            QoveryRegistryMirrorsChart::chart_name(),
+        )
+    }
+
+    fn rendered_hosts_toml(kubernetes_kind: KubernetesKind, registry_mirrors: &[&str]) -> String {
+        let advanced_settings = ClusterAdvancedSettings {
+            registry_mirrors: registry_mirrors
+                .iter()
+                .map(|mirror| Url::parse(mirror).unwrap())
+                .collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new();
+        context.insert("registry_mirrors", &advanced_settings.containerd_registry_mirrors());
+
+        let template = std::fs::read_to_string(chart_values_path(kubernetes_kind)).unwrap();
+        let values: serde_yaml::Value =
+            serde_yaml::from_str(&Tera::one_off(&template, &context, false).unwrap()).unwrap();
+        values["hostsToml"].as_str().unwrap().to_string()
+    }
+
+    /// Makes sure chart directory containing all YAML files exists.
+    #[test]
+    fn qovery_registry_mirrors_chart_directory_exists_test() {
+        // setup:
+        let chart = QoveryRegistryMirrorsChart::new(None, true, HelmChartNamespaces::KubeSystem);
+
+        let current_directory = env::current_dir().expect("Impossible to get current directory");
+        let chart_path = format!(
+            "{}/lib/{}/bootstrap/charts/{}/Chart.yaml",
+            current_directory
+                .to_str()
+                .expect("Impossible to convert current directory to string"),
+            get_helm_path_kubernetes_provider_sub_folder_name(chart.chart_path.helm_path(), HelmChartType::Shared),
+            QoveryRegistryMirrorsChart::chart_name(),
+        );
+
+        // execute
+        let chart_file = std::fs::File::open(&chart_path);
+
+        // verify:
+        assert!(chart_file.is_ok(), "Chart directory should exist: `{chart_path}`");
+    }
+
+    /// Makes sure the mirrors are rendered into the hosts.toml of the nodes, for every cloud provider.
+    #[test]
+    fn qovery_registry_mirrors_chart_values_rendering_test() {
+        for kubernetes_kind in [KubernetesKind::Eks, KubernetesKind::ScwKapsule] {
+            assert_eq!(
+                rendered_hosts_toml(
+                    kubernetes_kind,
+                    &["https://mirror.gcr.io", "http://registry.lan:5000/dockerhub"]
+                ),
+                concat!(
+                    "server = \"https://registry-1.docker.io\"\n",
+                    "\n",
+                    "[host.\"https://mirror.gcr.io\"]\n",
+                    "  capabilities = [\"pull\", \"resolve\"]\n",
+                    "\n",
+                    "[host.\"http://registry.lan:5000/dockerhub\"]\n",
+                    "  capabilities = [\"pull\", \"resolve\"]\n",
+                ),
+                "{kubernetes_kind:?}"
+            );
+            assert_eq!(
+                rendered_hosts_toml(kubernetes_kind, &[]),
+                "server = \"https://registry-1.docker.io\"\n",
+                "{kubernetes_kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn qovery_registry_mirrors_chart_is_uninstalled_without_mirrors_test() {
+        let chart = QoveryRegistryMirrorsChart::new(None, false, HelmChartNamespaces::KubeSystem);
+        assert_eq!(chart.to_common_helm_chart().unwrap().chart_info.action, HelmAction::Destroy);
+
+        let chart = QoveryRegistryMirrorsChart::new(None, true, HelmChartNamespaces::KubeSystem);
+        assert_eq!(chart.to_common_helm_chart().unwrap().chart_info.action, HelmAction::Deploy);
+    }
+}
@@ -0,0 +1,223 @@
+use regex::RegexBuilder;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+pub const SERVICE_LOGGING_MAX_EXCLUDE_PATTERNS: usize = 20;
+const EXCLUDE_PATTERN_MAX_LENGTH: usize = 256;
+// size of the compiled pattern, large repetitions like `(a{100}){100}` exceed it
+const EXCLUDE_PATTERN_MAX_COMPILED_SIZE: usize = 64 * 1024;
+const EXCLUDE_PATTERN_MAX_NESTING: u32 = 10;
+
+/// Logging of a service, applied by the log collector (promtail) and loki.
+/// Services are matched on the `qovery_com_service_id` label the collector maps from the pod labels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServiceLogging {
+    service_id: Uuid,
+    enabled: bool,
+    retention_days: Option<u32>,
+    exclude_patterns: Vec<String>,
+}
+
+impl ServiceLogging {
+    pub fn new(service_id: Uuid, enabled: bool, retention_days: Option<u32>, exclude_patterns: Vec<String>) -> Self {
+        ServiceLogging {
+            service_id,
+            enabled,
+            retention_days,
+            exclude_patterns,
+        }
+    }
+
+    fn selector(&self) -> String {
+        format!("{{qovery_com_service_id=\"{}\"}}", self.service_id)
+    }
+}
+
+/// Promtail evaluates patterns with RE2, which runs in linear time, but a large or deeply nested pattern
+/// still costs memory and cpu on every log line of every node: patterns are bounded in length and complexity.
+/// Constructs RE2 does not support (backreferences, look-arounds) are rejected as well.
+pub fn validate_exclude_pattern(pattern: &str) -> Result<(), String> {
+    if pattern.is_empty() {
+        return Err("pattern is empty".to_string());
+    }
+    if pattern.len() > EXCLUDE_PATTERN_MAX_LENGTH {
+        return Err(format!("pattern is longer than {EXCLUDE_PATTERN_MAX_LENGTH} characters"));
+    }
+
+    RegexBuilder::new(pattern)
+        .size_limit(EXCLUDE_PATTERN_MAX_COMPILED_SIZE)
+        .nest_limit(EXCLUDE_PATTERN_MAX_NESTING)
+        .build()
+        .map(|_| ())
+        .map_err(|err| match err {
+            regex::Error::CompiledTooBig(_) => "pattern is too complex".to_string(),
+            err => format!("pattern is not a valid regex: {err}"),
+        })
+}
+
+/// Stages of the promtail pipeline: lines of disabled services are dropped, then the lines matching the exclude
+/// patterns of their service. The `cri` stage of the chart is kept first, to match on the log line only.
+pub fn promtail_pipeline_stages(services: &[ServiceLogging]) -> Value {
+    let mut stages = vec![json!({ "cri": {} })];
+
+    for service in services {
+        if !service.enabled {
+            stages.push(json!({
+                "match": {
+                    "selector": service.selector(),
+                    "action": "drop",
+                    "drop_counter_reason": "qovery_logging_disabled",
+                }
+            }));
+            continue;
+        }
+
+        if service.exclude_patterns.is_empty() {
+            continue;
+        }
+        let drops = service
+            .exclude_patterns
+            .iter()
+            .map(|pattern| {
+                json!({
+                    "drop": {
+                        "expression": pattern,
+                        "drop_counter_reason": "qovery_excluded_pattern",
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        stages.push(json!({
+            "match": {
+                "selector": service.selector(),
+                "stages": drops,
+            }
+        }));
+    }
+
+    Value::Array(stages)
+}
+
+/// Loki retention overrides, for services keeping their logs less or more than the cluster retention.
+/// Disabled services have no logs to keep.
+pub fn loki_retention_streams(services: &[ServiceLogging]) -> Value {
+    Value::Array(
+        services
+            .iter()
+            .filter(|service| service.enabled)
+            .filter_map(|service| {
+                service.retention_days.map(|retention_days| {
+                    json!({
+                        "selector": service.selector(),
+                        "priority": 1,
+                        "period": format!("{}h", retention_days * 24),
+                    })
+                })
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn service_id(ix: u8) -> Uuid {
+        Uuid::from_str(&format!("00000000-0000-0000-0000-0000000000{ix:02}")).unwrap()
+    }
+
+    #[test]
+    fn test_services_without_settings_keep_the_default_pipeline() {
+        let services = vec![ServiceLogging::new(service_id(1), true, None, vec![])];
+
+        assert_eq!(promtail_pipeline_stages(&services), json!([{ "cri": {} }]));
+        assert_eq!(loki_retention_streams(&services), json!([]));
+    }
+
+    #[test]
+    fn test_disabled_services_are_dropped_whatever_their_other_settings() {
+        let services = vec![ServiceLogging::new(
+            service_id(1),
+            false,
+            Some(3),
+            vec!["GET /health".to_string()],
+        )];
+
+        assert_eq!(
+            promtail_pipeline_stages(&services),
+            json!([
+                { "cri": {} },
+                {
+                    "match": {
+                        "selector": "{qovery_com_service_id=\"00000000-0000-0000-0000-000000000001\"}",
+                        "action": "drop",
+                        "drop_counter_reason": "qovery_logging_disabled",
+                    }
+                },
+            ])
+        );
+        assert_eq!(loki_retention_streams(&services), json!([]));
+    }
+
+    #[test]
+    fn test_exclude_patterns_and_retention_are_scoped_to_their_service() {
+        let services = vec![
+            ServiceLogging::new(
+                service_id(1),
+                true,
+                Some(7),
+                vec!["GET /health".to_string(), r"level=debug\b".to_string()],
+            ),
+            ServiceLogging::new(service_id(2), true, Some(30), vec![]),
+        ];
+
+        assert_eq!(
+            promtail_pipeline_stages(&services),
+            json!([
+                { "cri": {} },
+                {
+                    "match": {
+                        "selector": "{qovery_com_service_id=\"00000000-0000-0000-0000-000000000001\"}",
+                        "stages": [
+                            { "drop": { "expression": "GET /health", "drop_counter_reason": "qovery_excluded_pattern" } },
+                            { "drop": { "expression": r"level=debug\b", "drop_counter_reason": "qovery_excluded_pattern" } },
+                        ],
+                    }
+                },
+            ])
+        );
+        assert_eq!(
+            loki_retention_streams(&services),
+            json!([
+                {
+                    "selector": "{qovery_com_service_id=\"00000000-0000-0000-0000-000000000001\"}",
+                    "priority": 1,
+                    "period": "168h",
+                },
+                {
+                    "selector": "{qovery_com_service_id=\"00000000-0000-0000-0000-000000000002\"}",
+                    "priority": 1,
+                    "period": "720h",
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_exclude_patterns_are_bounded() {
+        assert!(validate_exclude_pattern("GET /(health|ready)z?").is_ok());
+        assert!(validate_exclude_pattern("").is_err());
+        assert!(validate_exclude_pattern(&"a".repeat(EXCLUDE_PATTERN_MAX_LENGTH + 1)).is_err());
+        assert!(validate_exclude_pattern("(unclosed").is_err());
+        // not supported by RE2
+        assert!(validate_exclude_pattern(r"(a)\1").is_err());
+        assert!(validate_exclude_pattern("foo(?=bar)").is_err());
+        // too complex once compiled, or too deeply nested
+        assert_eq!(
+            validate_exclude_pattern(r"(\w{100}){100}"),
+            Err("pattern is too complex".to_string())
+        );
+        assert!(validate_exclude_pattern(&format!("{}a{}", "(".repeat(20), ")".repeat(20))).is_err());
+    }
+}
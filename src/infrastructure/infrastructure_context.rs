@@ -1,12 +1,16 @@
 use std::borrow::Borrow;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
+use crate::environment::models::abort::{Abort, AbortStatus};
 use crate::errors::EngineError;
 use crate::events::InfrastructureStep;
 use crate::events::Stage::Infrastructure;
+use crate::infrastructure::action::kubeconfig_helper::refresh_kubeconfig_if_invalid;
 use crate::infrastructure::models::build_platform::BuildPlatform;
 use crate::infrastructure::models::cloud_provider::CloudProvider;
+#[cfg(feature = "failure-injection")]
+use crate::infrastructure::models::container_registry::failure_injection::FailureInjectionContainerRegistry;
 use crate::infrastructure::models::container_registry::ContainerRegistry;
 use crate::infrastructure::models::dns_provider::DnsProvider;
 use crate::infrastructure::models::kubernetes::Kubernetes;
@@ -47,6 +51,8 @@ pub struct InfrastructureContext {
     metrics_registry: Box<dyn MetricsRegistry>,
     is_infra_deployment: bool,
     kube_client: Mutex<Option<QubeClient>>,
+    /// Abort of the running task, the infrastructure actions interrupt their commands once it is requested
+    abort: Arc<dyn Abort>,
 }
 
 impl InfrastructureContext {
@@ -60,6 +66,11 @@ impl InfrastructureContext {
         metrics_registry: Box<dyn MetricsRegistry>,
         is_infra_deployment: bool,
     ) -> InfrastructureContext {
+        // the calls to the registry can then be replaced by scripted errors
+        #[cfg(feature = "failure-injection")]
+        let container_registry: Box<dyn ContainerRegistry> =
+            Box::new(FailureInjectionContainerRegistry::new(container_registry));
+
         InfrastructureContext {
             context,
             build_platform,
@@ -70,9 +81,19 @@ impl InfrastructureContext {
             metrics_registry,
             is_infra_deployment,
             kube_client: Mutex::new(None),
+            abort: Arc::new(|| AbortStatus::None),
         }
     }
 
+    pub fn with_abort(mut self, abort: Arc<dyn Abort>) -> Self {
+        self.abort = abort;
+        self
+    }
+
+    pub fn abort(&self) -> Arc<dyn Abort> {
+        self.abort.clone()
+    }
+
     pub fn kubernetes(&self) -> &dyn Kubernetes {
         self.kubernetes.as_ref()
     }
@@ -132,6 +153,9 @@ impl InfrastructureContext {
         let kubeconfig_path = {
             let kubeconfig_path = self.kubernetes().kubeconfig_local_file_path();
             if kubeconfig_path.exists() {
+                if let Err(err) = refresh_kubeconfig_if_invalid(self.kubernetes(), self.cloud_provider()) {
+                    warn!("cannot regenerate the invalid kubeconfig: {}", err);
+                }
                 Some(kubeconfig_path)
             } else if self.is_infra_deployment {
                 // Infra deployment must have a kubeconfig file, we cant upgrade infra within the cluster
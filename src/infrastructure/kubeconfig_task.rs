@@ -0,0 +1,302 @@
+use crate::cmd::command::ExecutionContext;
+use crate::cmd::docker::Docker;
+use crate::engine_task::qovery_api::QoveryApi;
+use crate::engine_task::Task;
+use crate::environment::models::abort::{Abort, AbortStatus};
+use crate::errors::{CommandError, EngineError};
+use crate::events::Stage::Infrastructure;
+use crate::events::{EngineEvent, EventDetails, EventMessage, InfrastructureStep};
+use crate::infrastructure::models::kubernetes::kubeconfig_access::{
+    build_kubeconfig, cleanup_expired_kubeconfig_accesses, cluster_endpoint, create_kubeconfig_access,
+    delete_kubeconfig_access, encrypt_kubeconfig, kubeconfig_token_ttl, KUBECONFIG_ACCESS_NAMESPACE,
+};
+use crate::io_models::context::Context;
+use crate::io_models::engine_request::KubeconfigEngineRequest;
+use crate::io_models::feature_flags::FeatureFlags;
+use crate::io_models::kubeconfig::KubeconfigAccess;
+use crate::logger::Logger;
+use crate::metrics_registry::MetricsRegistry;
+use crate::proxy;
+use crate::runtime::block_on;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::ServiceAccount;
+use kube::config::Kubeconfig;
+use kube::Api;
+use reqwest::header::CONTENT_TYPE;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use url::Url;
+use uuid::Uuid;
+
+pub struct KubeconfigTask {
+    workspace_root_dir: String,
+    lib_root_dir: String,
+    docker: Arc<Docker>,
+    request: KubeconfigEngineRequest,
+    logger: Box<dyn Logger>,
+    metrics_registry: Box<dyn MetricsRegistry>,
+    qovery_api: Arc<dyn QoveryApi>,
+    span: tracing::Span,
+    is_terminated: (RwLock<Option<broadcast::Sender<()>>>, broadcast::Receiver<()>),
+}
+
+struct GeneratedKubeconfig {
+    access_name: String,
+    key: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl KubeconfigTask {
+    pub fn new(
+        request: KubeconfigEngineRequest,
+        workspace_root_dir: String,
+        lib_root_dir: String,
+        docker: Arc<Docker>,
+        logger: Box<dyn Logger>,
+        metrics_registry: Box<dyn MetricsRegistry>,
+        qovery_api: Box<dyn QoveryApi>,
+    ) -> Self {
+        let span = info_span!(
+            "kubeconfig_task",
+            organization_id = request.organization_long_id.to_string(),
+            cluster_id = request.kubernetes.long_id.to_string(),
+        );
+
+        KubeconfigTask {
+            workspace_root_dir,
+            lib_root_dir,
+            docker,
+            request,
+            logger,
+            metrics_registry,
+            qovery_api: Arc::from(qovery_api),
+            span,
+            is_terminated: {
+                let (tx, rx) = broadcast::channel(1);
+                (RwLock::new(Some(tx)), rx)
+            },
+        }
+    }
+
+    fn info_context(&self) -> Context {
+        Context::new(
+            self.request.organization_long_id,
+            self.request.kubernetes.long_id,
+            self.request.id.to_string(),
+            self.workspace_root_dir.to_string(),
+            self.lib_root_dir.to_string(),
+            self.request.test_cluster,
+            self.request.features.clone(),
+            FeatureFlags::resolve(&self.request.feature_flags).0,
+            self.request.metadata.clone(),
+            self.docker.clone(),
+            self.qovery_api.clone(),
+            self.request.event_details(),
+        )
+        .with_proxy(self.request.proxy.clone())
+    }
+
+    fn get_event_details(&self, step: InfrastructureStep) -> EventDetails {
+        EventDetails::clone_changing_stage(self.request.event_details(), Infrastructure(step))
+    }
+
+    fn log_audit_event(&self, access_name: &str, expires_at: &DateTime<Utc>) {
+        let request = &self.request.target_environment;
+        let access = match &request.access {
+            KubeconfigAccess::ReadOnly => "read only".to_string(),
+            KubeconfigAccess::Namespace { namespace } => format!("namespace {namespace}"),
+        };
+        let audit = serde_json::json!({
+            "requester": request.requester,
+            "access": request.access,
+            "access_name": access_name,
+            "expires_at": expires_at.to_rfc3339(),
+        });
+
+        self.logger.log(EngineEvent::Info(
+            self.get_event_details(InfrastructureStep::GenerateKubeconfig),
+            EventMessage::new(
+                format!(
+                    "Kubeconfig with {} access given to user {} until {}",
+                    access,
+                    request.requester.user_id,
+                    expires_at.to_rfc3339()
+                ),
+                Some(audit.to_string()),
+            ),
+        ));
+    }
+
+    fn generate_kubeconfig(&self) -> Result<GeneratedKubeconfig, Box<EngineError>> {
+        let event_details = self.get_event_details(InfrastructureStep::GenerateKubeconfig);
+        let to_engine_error =
+            |err: CommandError| Box::new(EngineError::new_k8s_cannot_generate_kubeconfig(event_details.clone(), err));
+
+        let infra_ctx = self.request.to_infrastructure_context(
+            &self.info_context(),
+            self.request.event_details(),
+            self.logger.clone(),
+            self.metrics_registry.clone(),
+            true,
+        )?;
+        let kubernetes = infra_ctx.kubernetes();
+
+        // The server and its CA are taken from our own kubeconfig, but not its credentials, which are the engine ones
+        let engine_kubeconfig = Kubeconfig::read_from(kubernetes.kubeconfig_local_file_path()).map_err(|err| {
+            to_engine_error(CommandError::new(
+                "Cannot read the kubeconfig of the cluster".to_string(),
+                Some(err.to_string()),
+                None,
+            ))
+        })?;
+        let (server, certificate_authority_data) = cluster_endpoint(&engine_kubeconfig).ok_or_else(|| {
+            to_engine_error(CommandError::new_from_safe_message(
+                "Cannot find the api server of the cluster in its kubeconfig".to_string(),
+            ))
+        })?;
+
+        let request = &self.request.target_environment;
+        let ttl = kubeconfig_token_ttl(request.ttl_in_seconds);
+        let access_name = format!("qovery-kubeconfig-{}", &Uuid::new_v4().simple().to_string()[..12]);
+        let kube_client = infra_ctx.mk_kube_client()?;
+        let client = kube_client.client();
+
+        let (token, expires_at) = block_on(async {
+            // Tokens of expired accesses are already refused by the api server, we only remove leftovers
+            match cleanup_expired_kubeconfig_accesses(client, Utc::now()).await {
+                Ok(deleted) if !deleted.is_empty() => info!("deleted expired kubeconfig accesses {:?}", deleted),
+                Ok(_) => {}
+                Err(err) => warn!("cannot delete expired kubeconfig accesses: {}", err),
+            }
+            create_kubeconfig_access(client, &access_name, &request.access, ttl).await
+        })
+        .map_err(|err| to_engine_error(CommandError::from(err)))?;
+
+        // From here, the access exists and must be removed if the user never gets its kubeconfig
+        let revoke_access = || {
+            let service_accounts: Api<ServiceAccount> = Api::namespaced(client.clone(), KUBECONFIG_ACCESS_NAMESPACE);
+            let ret = block_on(async {
+                let service_account = service_accounts.get(&access_name).await?;
+                delete_kubeconfig_access(client, &service_account).await
+            });
+            if let Err(err) = ret {
+                error!("cannot revoke kubeconfig access {}: {}", access_name, err);
+            }
+        };
+
+        let namespace = match &request.access {
+            KubeconfigAccess::ReadOnly => None,
+            KubeconfigAccess::Namespace { namespace } => Some(namespace.as_str()),
+        };
+        let kubeconfig = build_kubeconfig(
+            &kubernetes.cluster_name(),
+            &server,
+            certificate_authority_data.as_deref(),
+            namespace,
+            &token,
+        )
+        .map_err(|err| {
+            revoke_access();
+            to_engine_error(CommandError::new(
+                "Cannot build the kubeconfig".to_string(),
+                Some(err.to_string()),
+                None,
+            ))
+        })?;
+        let encrypted_kubeconfig = encrypt_kubeconfig(&kubeconfig).map_err(|_| {
+            revoke_access();
+            to_engine_error(CommandError::new_from_safe_message("Cannot encrypt the kubeconfig".to_string()))
+        })?;
+
+        if let Err(err) = upload_kubeconfig(&request.upload_url, encrypted_kubeconfig.payload) {
+            revoke_access();
+            return Err(to_engine_error(CommandError::new(
+                "Cannot upload the encrypted kubeconfig".to_string(),
+                Some(err.to_string()),
+                None,
+            )));
+        }
+
+        Ok(GeneratedKubeconfig {
+            access_name,
+            key: encrypted_kubeconfig.key,
+            expires_at,
+        })
+    }
+}
+
+fn upload_kubeconfig(upload_url: &Url, payload: Vec<u8>) -> Result<(), reqwest::Error> {
+    reqwest::blocking::Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .build()?
+        .put(upload_url.clone())
+        .header(CONTENT_TYPE, "application/octet-stream")
+        .body(payload)
+        .timeout(Duration::from_secs(60))
+        .send()?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+impl Task for KubeconfigTask {
+    fn id(&self) -> &str {
+        self.request.id.as_str()
+    }
+
+    fn run(&self) {
+        let _span = self.span.enter();
+        info!("kubeconfig task {} started", self.id());
+        let _execution_context = ExecutionContext::from(&self.request.event_details()).enter();
+        proxy::install(self.request.proxy.as_ref());
+
+        let _guard = scopeguard::guard((), |_| {
+            let Some(is_terminated_tx) = self.is_terminated.0.write().unwrap().take() else {
+                return;
+            };
+            let _ = is_terminated_tx.send(());
+        });
+
+        match self.generate_kubeconfig() {
+            Ok(generated) => {
+                self.log_audit_event(&generated.access_name, &generated.expires_at);
+                let response = serde_json::json!({
+                    "key": generated.key,
+                    "expires_at": generated.expires_at.to_rfc3339(),
+                });
+                self.logger.log(EngineEvent::Info(
+                    self.get_event_details(InfrastructureStep::KubeconfigGenerated),
+                    EventMessage::new_for_sending_core_data(
+                        format!("Kubeconfig generated, valid until {}", generated.expires_at.to_rfc3339()),
+                        response.to_string(),
+                    ),
+                ));
+            }
+            Err(err) => {
+                self.logger.log(EngineEvent::Error(
+                    err.clone_engine_error_with_stage(Infrastructure(InfrastructureStep::GenerateKubeconfigError)),
+                    None,
+                ));
+            }
+        }
+
+        info!("kubeconfig task {} finished", self.id());
+    }
+
+    fn cancel(&self, _force_requested: bool) -> bool {
+        false
+    }
+
+    fn cancel_checker(&self) -> Box<dyn Abort> {
+        Box::new(move || AbortStatus::None)
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.is_terminated.0.read().map(|tx| tx.is_none()).unwrap_or(true)
+    }
+
+    fn await_terminated(&self) -> broadcast::Receiver<()> {
+        self.is_terminated.1.resubscribe()
+    }
+}
@@ -1,5 +1,10 @@
 pub mod action;
+pub mod certificate_watchdog_task;
+pub mod cluster_health_task;
+pub mod delete_dry_run;
 pub mod helm_charts;
 pub mod infrastructure_context;
+pub mod kubeconfig_task;
 pub mod models;
+pub mod service_inventory_task;
 pub mod task;
@@ -0,0 +1,688 @@
+use crate::environment::models::abort::Abort;
+use crate::environment::report::logger::EnvLogger;
+use crate::infrastructure::models::build_platform::{BuildError, Credentials, GitRepository, SourceFetcher, SshKey};
+use crate::runtime::block_on;
+use flate2::read::GzDecoder;
+use k8s_openapi::api::core::v1::{
+    Container, EnvVar, EnvVarSource, KeyToPath, Pod, PodSecurityContext, PodSpec, ResourceRequirements, SeccompProfile,
+    Secret, SecretKeySelector, SecretVolumeSource, SecurityContext, Volume, VolumeMount,
+};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+use kube::api::{AttachParams, AttachedProcess, DeleteParams, PostParams};
+use kube::runtime::wait::{await_condition, conditions};
+use kube::Api;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use uuid::Uuid;
+
+// Agents run in the namespace of Qovery components, which always exists, unlike the environment namespace
+pub const CLONE_AGENT_NAMESPACE: &str = "qovery";
+const CLONE_AGENT_IMAGE: &str = "alpine/git:2.45.2";
+const CLONE_AGENT_CONTAINER_NAME: &str = "clone-agent";
+const CLONE_AGENT_START_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const CLONE_AGENT_HANDOFF_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+// The pod kills itself after this delay, so an agent is never left behind if the engine dies
+const CLONE_AGENT_MAX_LIFETIME_IN_SEC: i64 = 30 * 60;
+pub const MAX_SOURCE_ARCHIVE_SIZE_IN_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+// Handoff protocol, written by the agent on the exec stdout:
+// `QOVERY-SOURCE-V1 <size>\n<size bytes of tar.gz>\nQOVERY-SOURCE-END\n`
+// or `QOVERY-SOURCE-ERROR <message>\n` when the clone failed.
+const HANDOFF_HEADER_PREFIX: &str = "QOVERY-SOURCE-V1 ";
+const HANDOFF_ERROR_PREFIX: &str = "QOVERY-SOURCE-ERROR ";
+const HANDOFF_TRAILER: &[u8] = b"\nQOVERY-SOURCE-END\n";
+const HANDOFF_HEADER_MAX_LENGTH: usize = 4096;
+
+const CLONE_SCRIPT: &str = r#"
+set -u
+mkdir -p /workspace/source ~/.ssh
+SSH_OPTS="-o StrictHostKeyChecking=no -o UserKnownHostsFile=/dev/null"
+for key in /ssh-keys/ssh-key-*; do
+  [ -f "$key" ] || continue
+  cp "$key" ~/.ssh/ && chmod 600 ~/.ssh/"$(basename "$key")"
+  SSH_OPTS="$SSH_OPTS -i $HOME/.ssh/$(basename "$key")"
+done
+export GIT_SSH_COMMAND="ssh $SSH_OPTS"
+git config --global credential.helper '!f() { echo "username=${GIT_USERNAME:-}"; echo "password=${GIT_PASSWORD:-}"; }; f'
+if (
+  set -e
+  cd /workspace/source
+  git init -q .
+  git remote add origin "$GIT_URL"
+  git fetch -q --depth 1 origin "$GIT_COMMIT_ID"
+  git checkout -q FETCH_HEAD
+  git submodule update -q --init --recursive --depth 1
+  git lfs pull || true
+  tar --exclude=.git -czf /workspace/source.tar.gz .
+) > /workspace/clone.log 2>&1; then
+  touch /workspace/ready
+else
+  tail -c 2000 /workspace/clone.log | tr '\n' ' ' > /workspace/failed
+fi
+# Wait for the engine to fetch the archive, the pod deadline stops us otherwise
+while [ ! -f /workspace/done ]; do sleep 1; done
+"#;
+
+const HANDOFF_SCRIPT: &str = r#"
+while [ ! -f /workspace/ready ]; do
+  if [ -f /workspace/failed ]; then
+    printf 'QOVERY-SOURCE-ERROR %s\n' "$(cat /workspace/failed)"
+    touch /workspace/done
+    exit 0
+  fi
+  sleep 1
+done
+printf 'QOVERY-SOURCE-V1 %s\n' "$(wc -c < /workspace/source.tar.gz | tr -d ' ')"
+cat /workspace/source.tar.gz
+printf '\nQOVERY-SOURCE-END\n'
+touch /workspace/done
+"#;
+
+#[derive(thiserror::Error, Debug)]
+pub enum HandoffError {
+    #[error("Invalid source handoff header: {0}")]
+    InvalidHeader(String),
+
+    #[error("Source archive of {size} bytes exceeds the maximum allowed size of {max_size} bytes")]
+    TooLarge { size: u64, max_size: u64 },
+
+    #[error("Source archive stream ended after {received} bytes out of {expected}")]
+    Truncated { received: u64, expected: u64 },
+
+    #[error("Source archive stream is not terminated properly")]
+    MissingTrailer,
+
+    #[error("Clone agent failed to clone the repository: {0}")]
+    AgentFailed(String),
+
+    #[error("IO error during source handoff: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+async fn read_header_line<R: AsyncRead + Unpin>(stream: &mut R) -> Result<String, HandoffError> {
+    let mut line = Vec::new();
+    loop {
+        let byte = match stream.read_u8().await {
+            Ok(byte) => byte,
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                return Err(HandoffError::InvalidHeader(String::from_utf8_lossy(&line).to_string()))
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if byte == b'\n' {
+            return Ok(String::from_utf8_lossy(&line).to_string());
+        }
+
+        line.push(byte);
+        if line.len() > HANDOFF_HEADER_MAX_LENGTH {
+            return Err(HandoffError::InvalidHeader("header is too long".to_string()));
+        }
+    }
+}
+
+/// Reads the source archive sent by the clone agent and writes it to the destination.
+/// The announced size is checked against the limit before anything is read, and the trailer ensures the archive
+/// has not been cut by the end of the exec stream. Returns the size of the archive.
+pub async fn receive_source_archive<R, W>(
+    stream: &mut R,
+    destination: &mut W,
+    max_size: u64,
+) -> Result<u64, HandoffError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let header = read_header_line(stream).await?;
+    if let Some(message) = header.strip_prefix(HANDOFF_ERROR_PREFIX) {
+        return Err(HandoffError::AgentFailed(message.trim().to_string()));
+    }
+
+    let size = header
+        .strip_prefix(HANDOFF_HEADER_PREFIX)
+        .and_then(|size| size.trim().parse::<u64>().ok())
+        .ok_or_else(|| HandoffError::InvalidHeader(header.clone()))?;
+    if size > max_size {
+        return Err(HandoffError::TooLarge { size, max_size });
+    }
+
+    let received = tokio::io::copy(&mut (&mut *stream).take(size), destination).await?;
+    if received != size {
+        return Err(HandoffError::Truncated {
+            received,
+            expected: size,
+        });
+    }
+    destination.flush().await?;
+
+    let mut trailer = vec![0; HANDOFF_TRAILER.len()];
+    match stream.read_exact(&mut trailer).await {
+        Ok(_) if trailer == HANDOFF_TRAILER => Ok(size),
+        Ok(_) => Err(HandoffError::MissingTrailer),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Err(HandoffError::MissingTrailer),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn agent_labels(service_id: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("qovery.com/clone-agent".to_string(), "true".to_string()),
+        ("qovery.com/service-id".to_string(), service_id.to_string()),
+    ])
+}
+
+fn secret_env(secret_name: &str, key: &str) -> EnvVar {
+    EnvVar {
+        name: key.to_string(),
+        value_from: Some(EnvVarSource {
+            secret_key_ref: Some(SecretKeySelector {
+                name: secret_name.to_string(),
+                key: key.to_string(),
+                optional: Some(true),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+// The agent cannot prompt for a passphrase
+fn usable_ssh_keys(git_repository: &GitRepository) -> impl Iterator<Item = &SshKey> {
+    git_repository
+        .deploy_key
+        .iter()
+        .chain(git_repository.ssh_keys.iter())
+        .filter(|key| key.passphrase.is_none())
+}
+
+/// Secret holding the git credentials of the agent, deleted with its pod.
+pub fn clone_agent_secret(
+    name: &str,
+    service_id: &str,
+    git_repository: &GitRepository,
+    credentials: Option<&Credentials>,
+) -> Secret {
+    let mut data = BTreeMap::new();
+    if let Some(credentials) = credentials {
+        data.insert("GIT_USERNAME".to_string(), credentials.login.clone());
+        data.insert("GIT_PASSWORD".to_string(), credentials.password.clone());
+    }
+    for (idx, ssh_key) in usable_ssh_keys(git_repository).enumerate() {
+        data.insert(format!("ssh-key-{idx}"), ssh_key.private_key.clone());
+    }
+
+    Secret {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: Some(CLONE_AGENT_NAMESPACE.to_string()),
+            labels: Some(agent_labels(service_id)),
+            ..Default::default()
+        },
+        string_data: Some(data),
+        ..Default::default()
+    }
+}
+
+/// Pod cloning the repository at the requested commit, and waiting for the engine to fetch the archive.
+/// Credentials are read from the secret of the same name.
+pub fn clone_agent_pod(name: &str, service_id: &str, git_repository: &GitRepository) -> Pod {
+    let resources = BTreeMap::from([
+        ("cpu".to_string(), Quantity("500m".to_string())),
+        ("memory".to_string(), Quantity("512Mi".to_string())),
+    ]);
+
+    Pod {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: Some(CLONE_AGENT_NAMESPACE.to_string()),
+            labels: Some(agent_labels(service_id)),
+            ..Default::default()
+        },
+        spec: Some(PodSpec {
+            restart_policy: Some("Never".to_string()),
+            active_deadline_seconds: Some(CLONE_AGENT_MAX_LIFETIME_IN_SEC),
+            automount_service_account_token: Some(false),
+            security_context: Some(PodSecurityContext {
+                run_as_non_root: Some(true),
+                run_as_user: Some(1000),
+                fs_group: Some(1000),
+                seccomp_profile: Some(SeccompProfile {
+                    type_: "RuntimeDefault".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            containers: vec![Container {
+                name: CLONE_AGENT_CONTAINER_NAME.to_string(),
+                image: Some(CLONE_AGENT_IMAGE.to_string()),
+                command: Some(vec!["/bin/sh".to_string(), "-c".to_string(), CLONE_SCRIPT.to_string()]),
+                env: Some(vec![
+                    EnvVar {
+                        name: "HOME".to_string(),
+                        value: Some("/workspace".to_string()),
+                        ..Default::default()
+                    },
+                    EnvVar {
+                        name: "GIT_URL".to_string(),
+                        value: Some(git_repository.url.to_string()),
+                        ..Default::default()
+                    },
+                    EnvVar {
+                        name: "GIT_COMMIT_ID".to_string(),
+                        value: Some(git_repository.commit_id.clone()),
+                        ..Default::default()
+                    },
+                    secret_env(name, "GIT_USERNAME"),
+                    secret_env(name, "GIT_PASSWORD"),
+                ]),
+                resources: Some(ResourceRequirements {
+                    requests: Some(resources.clone()),
+                    limits: Some(resources),
+                    ..Default::default()
+                }),
+                security_context: Some(SecurityContext {
+                    allow_privilege_escalation: Some(false),
+                    ..Default::default()
+                }),
+                volume_mounts: Some(vec![
+                    VolumeMount {
+                        name: "workspace".to_string(),
+                        mount_path: "/workspace".to_string(),
+                        ..Default::default()
+                    },
+                    VolumeMount {
+                        name: "ssh-keys".to_string(),
+                        mount_path: "/ssh-keys".to_string(),
+                        read_only: Some(true),
+                        ..Default::default()
+                    },
+                ]),
+                ..Default::default()
+            }],
+            volumes: Some(vec![
+                Volume {
+                    name: "workspace".to_string(),
+                    empty_dir: Some(Default::default()),
+                    ..Default::default()
+                },
+                Volume {
+                    name: "ssh-keys".to_string(),
+                    // only the keys, not the other credentials of the secret
+                    secret: Some(SecretVolumeSource {
+                        secret_name: Some(name.to_string()),
+                        default_mode: Some(0o400),
+                        items: Some(
+                            (0..usable_ssh_keys(git_repository).count())
+                                .map(|idx| KeyToPath {
+                                    key: format!("ssh-key-{idx}"),
+                                    path: format!("ssh-key-{idx}"),
+                                    ..Default::default()
+                                })
+                                .collect(),
+                        ),
+                        optional: Some(true),
+                    }),
+                    ..Default::default()
+                },
+            ]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Stdout of an exec into the agent, keeping the process alive while the stream is read.
+struct ExecStdout {
+    _process: AttachedProcess,
+    stdout: Pin<Box<dyn AsyncRead + Send>>,
+}
+
+impl AsyncRead for ExecStdout {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        self.get_mut().stdout.as_mut().poll_read(cx, buf)
+    }
+}
+
+/// Clones git repositories from inside the target cluster, for git providers only reachable from there (i.e: behind a VPN).
+/// A short-lived agent pod does the clone, and the archive of the sources is streamed back through the exec API,
+/// so nothing has to be exposed from the cluster.
+pub struct InClusterCloneAgent {
+    client: kube::Client,
+}
+
+impl InClusterCloneAgent {
+    pub fn new(client: kube::Client) -> Self {
+        InClusterCloneAgent { client }
+    }
+
+    async fn start_agent(
+        &self,
+        name: &str,
+        service_id: &str,
+        git_repository: &GitRepository,
+        credentials: Option<&Credentials>,
+    ) -> Result<(), kube::Error> {
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), CLONE_AGENT_NAMESPACE);
+        let secrets: Api<Secret> = Api::namespaced(self.client.clone(), CLONE_AGENT_NAMESPACE);
+
+        // The pod is created first, so the secret can be owned by it and garbage collected with it
+        let pod = pods
+            .create(&PostParams::default(), &clone_agent_pod(name, service_id, git_repository))
+            .await?;
+        let mut secret = clone_agent_secret(name, service_id, git_repository, credentials);
+        secret.metadata.owner_references = Some(vec![OwnerReference {
+            api_version: "v1".to_string(),
+            kind: "Pod".to_string(),
+            name: name.to_string(),
+            uid: pod.metadata.uid.unwrap_or_default(),
+            ..Default::default()
+        }]);
+        secrets.create(&PostParams::default(), &secret).await?;
+
+        Ok(())
+    }
+
+    async fn wait_agent_running(&self, name: &str) -> Result<(), String> {
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), CLONE_AGENT_NAMESPACE);
+        match tokio::time::timeout(
+            CLONE_AGENT_START_TIMEOUT,
+            await_condition(pods, name, conditions::is_pod_running()),
+        )
+        .await
+        {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(err)) => Err(format!("cannot watch clone agent pod: {err}")),
+            Err(_) => Err(format!(
+                "clone agent pod is not running after {} seconds",
+                CLONE_AGENT_START_TIMEOUT.as_secs()
+            )),
+        }
+    }
+
+    async fn exec_handoff(&self, name: &str) -> Result<ExecStdout, kube::Error> {
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), CLONE_AGENT_NAMESPACE);
+        let mut process = pods
+            .exec(
+                name,
+                vec!["/bin/sh", "-c", HANDOFF_SCRIPT],
+                &AttachParams::default()
+                    .container(CLONE_AGENT_CONTAINER_NAME)
+                    .stdin(false)
+                    .stdout(true)
+                    .stderr(false),
+            )
+            .await?;
+        let stdout = process
+            .stdout()
+            .ok_or_else(|| kube::Error::Service("exec stdout is not available".into()))?;
+
+        Ok(ExecStdout {
+            _process: process,
+            stdout: Box::pin(stdout),
+        })
+    }
+
+    async fn delete_agent(&self, name: &str) {
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), CLONE_AGENT_NAMESPACE);
+        if let Err(err) = pods.delete(name, &DeleteParams::background().grace_period(0)).await {
+            // The pod deadline will stop it anyway
+            warn!("Cannot delete clone agent pod {}: {}", name, err);
+        }
+    }
+}
+
+impl SourceFetcher for InClusterCloneAgent {
+    fn fetch_source(
+        &self,
+        git_repository: &GitRepository,
+        service_id: &str,
+        destination: &Path,
+        logger: &EnvLogger,
+        abort: &dyn Abort,
+    ) -> Result<(), BuildError> {
+        let clone_error = |raw_error_message: String| BuildError::InClusterCloneError {
+            application: service_id.to_string(),
+            raw_error_message,
+        };
+
+        let credentials = match git_repository.credentials() {
+            None => None,
+            Some(Ok(creds)) => Some(creds),
+            Some(Err(err)) => {
+                logger.send_warning(format!("🗝️ Unable to get credentials for git repository: {err}"));
+                None
+            }
+        };
+
+        let name = format!("clone-agent-{}", &Uuid::new_v4().to_string()[..8]);
+        logger.send_progress(format!("🔌 Starting clone agent {name} inside the cluster"));
+        let _agent_cleanup = scopeguard::guard(name.as_str(), |name| {
+            info!("Deleting clone agent pod {}", name);
+            block_on(self.delete_agent(name));
+        });
+        block_on(self.start_agent(&name, service_id, git_repository, credentials.as_ref()))
+            .map_err(|err| clone_error(format!("cannot create clone agent: {err}")))?;
+        block_on(self.wait_agent_running(&name)).map_err(clone_error)?;
+
+        if abort.status().should_cancel() {
+            return Err(BuildError::Aborted {
+                application: service_id.to_string(),
+            });
+        }
+
+        let archive_path = destination.with_extension("tar.gz");
+        let _archive_cleanup = scopeguard::guard(&archive_path, |path| {
+            let _ = std::fs::remove_file(path);
+        });
+        let archive_size = block_on(async {
+            let mut stdout = self
+                .exec_handoff(&name)
+                .await
+                .map_err(|err| HandoffError::Io(Error::new(ErrorKind::Other, err)))?;
+            let mut archive = tokio::fs::File::create(&archive_path).await?;
+            match tokio::time::timeout(
+                CLONE_AGENT_HANDOFF_TIMEOUT,
+                receive_source_archive(&mut stdout, &mut archive, MAX_SOURCE_ARCHIVE_SIZE_IN_BYTES),
+            )
+            .await
+            {
+                Ok(result) => result,
+                Err(_) => Err(HandoffError::Io(Error::new(
+                    ErrorKind::TimedOut,
+                    format!(
+                        "clone agent did not send the sources within {} seconds",
+                        CLONE_AGENT_HANDOFF_TIMEOUT.as_secs()
+                    ),
+                ))),
+            }
+        })
+        .map_err(|err| clone_error(err.to_string()))?;
+        logger.send_progress(format!("📦 Received {archive_size} bytes of sources from the clone agent"));
+
+        let archive = File::open(&archive_path).map_err(|err| BuildError::IoError {
+            application: service_id.to_string(),
+            action_description: "opening source archive".to_string(),
+            raw_error: err,
+        })?;
+        tar::Archive::new(GzDecoder::new(archive))
+            .unpack(destination)
+            .map_err(|err| BuildError::IoError {
+                application: service_id.to_string(),
+                action_description: "unpacking source archive".to_string(),
+                raw_error: err,
+            })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    fn handoff_stream(header: &str, archive: &[u8], trailer: &[u8]) -> Vec<u8> {
+        let mut stream = header.as_bytes().to_vec();
+        stream.extend_from_slice(archive);
+        stream.extend_from_slice(trailer);
+        stream
+    }
+
+    async fn receive(stream: Vec<u8>, max_size: u64) -> (Result<u64, HandoffError>, Vec<u8>) {
+        // the exec stream of the pod is mocked by an in-memory reader
+        let mut stream = std::io::Cursor::new(stream);
+        let mut destination = Vec::new();
+        let result = receive_source_archive(&mut stream, &mut destination, max_size).await;
+        (result, destination)
+    }
+
+    #[tokio::test]
+    async fn test_receive_source_archive() {
+        let archive = b"\x1f\x8b\x08\x00fake-archive\ncontent";
+        let stream = handoff_stream(&format!("QOVERY-SOURCE-V1 {}\n", archive.len()), archive, HANDOFF_TRAILER);
+
+        let (result, destination) = receive(stream, 1024).await;
+
+        assert_eq!(result.unwrap(), archive.len() as u64);
+        assert_eq!(destination, archive);
+    }
+
+    #[tokio::test]
+    async fn test_receive_source_archive_agent_failure() {
+        let stream =
+            b"QOVERY-SOURCE-ERROR fatal: could not read Username for 'https://git.corp': terminal prompts disabled\n";
+
+        let (result, destination) = receive(stream.to_vec(), 1024).await;
+
+        assert!(
+            matches!(result, Err(HandoffError::AgentFailed(msg)) if msg.starts_with("fatal: could not read Username"))
+        );
+        assert!(destination.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_receive_source_archive_too_large() {
+        let archive = [0u8; 100];
+        let stream = handoff_stream("QOVERY-SOURCE-V1 100\n", &archive, HANDOFF_TRAILER);
+
+        let (result, destination) = receive(stream, 99).await;
+
+        assert!(matches!(
+            result,
+            Err(HandoffError::TooLarge {
+                size: 100,
+                max_size: 99
+            })
+        ));
+        assert!(destination.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_receive_source_archive_truncated() {
+        let stream = handoff_stream("QOVERY-SOURCE-V1 100\n", &[0u8; 42], b"");
+
+        let (result, _) = receive(stream, 1024).await;
+
+        assert!(matches!(
+            result,
+            Err(HandoffError::Truncated {
+                received: 42,
+                expected: 100
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_receive_source_archive_missing_trailer() {
+        let archive = [0u8; 10];
+
+        let (result, _) = receive(handoff_stream("QOVERY-SOURCE-V1 10\n", &archive, b""), 1024).await;
+        assert!(matches!(result, Err(HandoffError::MissingTrailer)));
+
+        let (result, _) = receive(
+            handoff_stream("QOVERY-SOURCE-V1 10\n", &archive, b"\nGARBAGE-GARBAGE-GA\n"),
+            1024,
+        )
+        .await;
+        assert!(matches!(result, Err(HandoffError::MissingTrailer)));
+    }
+
+    #[tokio::test]
+    async fn test_receive_source_archive_invalid_header() {
+        for stream in [
+            b"".to_vec(),
+            b"sh: git: not found\n".to_vec(),
+            b"QOVERY-SOURCE-V1 abc\n".to_vec(),
+            b"QOVERY-SOURCE-V1 10".to_vec(),
+            vec![b'a'; HANDOFF_HEADER_MAX_LENGTH + 10],
+        ] {
+            let (result, _) = receive(stream, 1024).await;
+            assert!(matches!(result, Err(HandoffError::InvalidHeader(_))));
+        }
+    }
+
+    #[test]
+    fn test_clone_agent_pod_and_secret() {
+        let git_repository = GitRepository {
+            url: Url::parse("https://git.corp.internal/team/app.git").unwrap(),
+            get_credentials: None,
+            deploy_key: None,
+            ssh_keys: vec![
+                SshKey {
+                    private_key: "key-without-passphrase".to_string(),
+                    passphrase: None,
+                    public_key: None,
+                },
+                SshKey {
+                    private_key: "key-with-passphrase".to_string(),
+                    passphrase: Some("secret".to_string()),
+                    public_key: None,
+                },
+            ],
+            commit_id: "5f70bf18a086".to_string(),
+            dockerfile_path: None,
+            dockerfile_content: None,
+            root_path: Default::default(),
+            git_access: Default::default(),
+            source_fetcher: None,
+            clone_options: Default::default(),
+        };
+
+        let pod = clone_agent_pod("clone-agent-1234", "app-id", &git_repository);
+        let spec = pod.spec.unwrap();
+        assert_eq!(spec.restart_policy.as_deref(), Some("Never"));
+        assert_eq!(spec.active_deadline_seconds, Some(CLONE_AGENT_MAX_LIFETIME_IN_SEC));
+        assert_eq!(spec.automount_service_account_token, Some(false));
+        let env = spec.containers[0].env.clone().unwrap();
+        assert!(env
+            .iter()
+            .any(|e| e.name == "GIT_COMMIT_ID" && e.value.as_deref() == Some("5f70bf18a086")));
+        let ssh_keys_volume = spec
+            .volumes
+            .unwrap()
+            .into_iter()
+            .find(|v| v.name == "ssh-keys")
+            .unwrap();
+        let items = ssh_keys_volume.secret.unwrap().items.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].key, "ssh-key-0");
+
+        let credentials = Credentials {
+            login: "user".to_string(),
+            password: "token".to_string(),
+        };
+        let secret = clone_agent_secret("clone-agent-1234", "app-id", &git_repository, Some(&credentials));
+        let data = secret.string_data.unwrap();
+        assert_eq!(data.get("GIT_USERNAME").map(String::as_str), Some("user"));
+        assert_eq!(data.get("GIT_PASSWORD").map(String::as_str), Some("token"));
+        assert_eq!(data.get("ssh-key-0").map(String::as_str), Some("key-without-passphrase"));
+        assert_eq!(data.len(), 3);
+    }
+}
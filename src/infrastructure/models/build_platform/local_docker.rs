@@ -8,7 +8,8 @@ use std::sync::Arc;
 use std::time::Duration;
 use std::{fs, thread};
 
-use git2::{Cred, CredentialType, ErrorClass};
+use git2::{ErrorClass, ErrorCode};
+use itertools::Itertools;
 use retry::delay::Fibonacci;
 use retry::OperationResult;
 use time::Instant;
@@ -16,15 +17,16 @@ use uuid::Uuid;
 
 use crate::cmd::command::CommandKiller;
 use crate::cmd::docker;
-use crate::cmd::docker::{Architecture, BuilderHandle, ContainerImage};
+use crate::cmd::docker::{Architecture, BuilderHandle, ContainerImage, DockerError};
 use crate::cmd::git_lfs::{GitLfs, GitLfsError};
 use crate::environment::report::logger::EnvLogger;
 use crate::infrastructure::models::build_platform::dockerfile_utils::extract_dockerfile_args;
-use crate::infrastructure::models::build_platform::{to_build_error, Build, BuildError, BuildPlatform, Kind};
+use crate::infrastructure::models::build_platform::{to_build_error, Build, BuildError, BuildPlatform, GitCmd, Kind};
 
 use crate::cmd::git;
+use crate::cmd::git::GitCloneOptions;
 use crate::environment::models::abort::Abort;
-use crate::fs::workspace_directory;
+use crate::fs::WorkspaceDirectory;
 use crate::io_models::container::Registry;
 use crate::io_models::context::Context;
 use crate::metrics_registry::{MetricsRegistry, StepLabel, StepName, StepStatus};
@@ -110,13 +112,30 @@ impl LocalDocker {
             vec![build.image.tag.clone(), "latest".to_string()],
         );
 
-        let image_cache =
-            ContainerImage::new(build.image.registry_url.clone(), build.image.name(), vec!["cache".to_string()]);
+        // Layers cache stored next to the image, so builds on a fresh builder can reuse it
+        let image_cache = match build.disable_cache {
+            true => None,
+            false => Some(ContainerImage::new(
+                build.image.registry_url.clone(),
+                build.image.name(),
+                vec!["cache".to_string()],
+            )),
+        };
+
+        let arch: Vec<Architecture> = build
+            .architectures
+            .iter()
+            .map(|arch| docker::Architecture::from(arch))
+            .collect();
 
         // Check if the image does not exist already remotely, if yes, we skip the build
         let image_name = image_to_build.image_name();
         logger.send_progress(format!("🕵️ Checking if image already exists remotely {image_name}"));
-        if let Ok(true) = self.context.docker.does_image_exist_remotely(&image_to_build) {
+        if let Ok(true) = self
+            .context
+            .docker
+            .does_image_exist_remotely_for_architectures(&image_to_build, &arch)
+        {
             logger.send_progress(format!("🎯 Skipping build. Image already exists in the registry {image_name}"));
             build_record.stop(StepStatus::Skip);
             // skip build
@@ -180,14 +199,20 @@ impl LocalDocker {
             .map(|(k, v)| (k.as_str(), v.as_str()))
             .collect();
 
-        let arch: Vec<Architecture> = build
-            .architectures
+        let secrets: Vec<(&str, &str)> = build
+            .secrets
             .iter()
-            .map(|arch| docker::Architecture::from(arch))
+            .map(|(id, value)| (id.as_str(), value.as_str()))
             .collect();
 
         let builder_handle =
             self.provision_builder(build, |line| logger.send_progress(line), &CommandKiller::from_cancelable(abort))?;
+        if !builder_handle.emulated_architectures.is_empty() {
+            logger.send_warning(format!(
+                "🐢 No native builder for {}, the image is built through emulation which is much slower",
+                builder_handle.emulated_architectures.iter().join(", ")
+            ));
+        }
 
         let exit_status = self.context.docker.build(
             &builder_handle.builder_name.as_deref(),
@@ -195,7 +220,8 @@ impl LocalDocker {
             Path::new(into_dir_docker_style),
             &image_to_build,
             &env_vars,
-            &image_cache,
+            &secrets,
+            image_cache.as_ref(),
             true,
             &arch,
             &mut |line| logger.send_progress(line),
@@ -205,7 +231,38 @@ impl LocalDocker {
 
         if let Err(err) = exit_status {
             build_record.stop(StepStatus::Error);
-            return Err(to_build_error(build.image.service_id.clone(), err));
+            return Err(match err {
+                DockerError::Timeout { raw_error_message } if !builder_handle.emulated_architectures.is_empty() => {
+                    BuildError::EmulatedBuildTimeout {
+                        application: build.image.service_id.clone(),
+                        architectures: builder_handle.emulated_architectures.iter().join(", "),
+                        raw_error_message,
+                    }
+                }
+                err => to_build_error(build.image.service_id.clone(), err),
+            });
+        }
+
+        // Pods are scheduled on every architecture of the cluster, the manifest list must reference all of them
+        if build.is_multi_architecture() {
+            match self.context.docker.image_architectures(&image_to_build) {
+                Ok(Some(image_architectures)) if arch.iter().all(|arch| image_architectures.contains(arch)) => {}
+                Ok(image_architectures) => {
+                    build_record.stop(StepStatus::Error);
+                    return Err(to_build_error(
+                        build.image.service_id.clone(),
+                        DockerError::PushNotVerified {
+                            raw_error_message: format!(
+                                "Manifest list of {image_name} does not reference all the architectures {arch:?}, found {image_architectures:?}"
+                            ),
+                        },
+                    ));
+                }
+                Err(err) => {
+                    build_record.stop(StepStatus::Error);
+                    return Err(to_build_error(build.image.service_id.clone(), err));
+                }
+            }
         }
         build_record.stop(StepStatus::Success);
         Ok(())
@@ -285,6 +342,7 @@ impl LocalDocker {
                     .map(String::as_ref)
                     .collect::<Vec<_>>()
                     .as_slice(),
+                &build.registry_mirrors,
                 true,
             ) {
                 Ok(build_handle) => break build_handle,
@@ -315,17 +373,17 @@ impl LocalDocker {
         Ok(builder_handle)
     }
 
+    // keyed by the long id of the service, short ids can collide when many services are built in parallel
     fn get_repository_build_root_path(&self, build: &Build) -> Result<PathBuf, BuildError> {
-        workspace_directory(
-            self.context.workspace_root_dir(),
-            self.context.execution_id(),
-            format!("build/{}", build.image.service_id.as_str()),
-        )
-        .map_err(|err| BuildError::IoError {
-            application: build.image.service_id.clone(),
-            action_description: "when creating build workspace".to_string(),
-            raw_error: err,
-        })
+        self.context
+            .workspace()
+            .and_then(|workspace| workspace.service(&build.image.service_long_id))
+            .map(|service_workspace| service_workspace.directory(WorkspaceDirectory::Source))
+            .map_err(|err| BuildError::IoError {
+                application: build.image.service_id.clone(),
+                action_description: "when creating build workspace".to_string(),
+                raw_error: err,
+            })
     }
 }
 
@@ -374,26 +432,25 @@ impl BuildPlatform for LocalDocker {
             }
         };
 
+        // The deploy key is written to a file only for the time of the clone
+        let deploy_key = match &build.git_repository.deploy_key {
+            None => None,
+            Some(deploy_key) => Some(git::DeployKeyFile::new(deploy_key).map_err(|err| BuildError::IoError {
+                application: build.image.service_id.clone(),
+                action_description: "writing the deploy key of the repository".to_string(),
+                raw_error: err,
+            })?),
+        };
+
         // Create callback that will be called by git to provide credentials per user
         // If people use submodule, they need to provide us their ssh key
         let get_credentials = |user: &str| {
-            let mut creds: Vec<(CredentialType, Cred)> = Vec::with_capacity(build.git_repository.ssh_keys.len() + 1);
-            for ssh_key in build.git_repository.ssh_keys.iter() {
-                let public_key = ssh_key.public_key.as_deref();
-                let passphrase = ssh_key.passphrase.as_deref();
-                if let Ok(cred) = Cred::ssh_key_from_memory(user, public_key, &ssh_key.private_key, passphrase) {
-                    creds.push((CredentialType::SSH_MEMORY, cred));
-                }
-            }
-
-            if let Some(git_creds) = &git_user_creds {
-                creds.push((
-                    CredentialType::USER_PASS_PLAINTEXT,
-                    Cred::userpass_plaintext(&git_creds.login, &git_creds.password).unwrap(),
-                ));
-            }
-
-            creds
+            git::repository_credentials(
+                user,
+                deploy_key.as_ref(),
+                &build.git_repository.ssh_keys,
+                git_user_creds.as_ref(),
+            )
         };
 
         // Cleanup, mono repo can require to clone multiple time the same repo
@@ -410,54 +467,104 @@ impl BuildPlatform for LocalDocker {
         // Do the real git clone
         let git_clone_record =
             metrics_registry.start_record(build.image.service_long_id, StepLabel::Service, StepName::GitClone);
-        if let Err(error) = retry::retry(retry::delay::Fixed::from_millis(10_000).take(3), || {
-            if let Err(BuildError::GitError {
-                application: _,
-                git_cmd,
-                context,
-                raw_error,
-            }) = git::clone_at_commit(
-                &build.git_repository.url,
-                &build.git_repository.commit_id,
+        if let Some(source_fetcher) = &build.git_repository.source_fetcher {
+            // The repository is not reachable from here, someone else clones it for us
+            if let Err(err) = source_fetcher.fetch_source(
+                &build.git_repository,
+                &build.image.service_id,
                 &repository_root_path,
-                &get_credentials,
+                logger,
+                abort,
             ) {
-                let message = raw_error.message();
-                let git_error_class = raw_error.class();
-                // Some errors can happen "randomly":
-                // - SSL error: syscall failure: Resource temporarily unavailable
-                // - Timeout on git clone
-                debug!("Error on git clone: git_error_class={:?}, message={}", git_error_class, message);
-                return if git_error_class == ErrorClass::Os
-                    || git_error_class == ErrorClass::Ssl
-                    || (git_error_class == ErrorClass::Net && message.contains("timed out"))
-                {
-                    debug!("Retrying git clone...");
-                    logger.send_warning(format!(
-                        "⚠️ Retrying cloning your git repository, due to following error: {}",
-                        message
-                    ));
-                    OperationResult::Retry(BuildError::GitError {
-                        application: build.image.service_id.clone(),
+                git_clone_record.stop(StepStatus::Error);
+                return Err(err);
+            }
+        } else {
+            let clone = |clone_options: &GitCloneOptions| {
+                retry::retry(retry::delay::Fixed::from_millis(10_000).take(3), || {
+                    if let Err(BuildError::GitError {
+                        application: _,
                         git_cmd,
                         context,
                         raw_error,
-                    })
-                } else {
-                    OperationResult::Err(BuildError::GitError {
-                        application: build.image.service_id.clone(),
-                        git_cmd,
-                        context,
+                    }) = git::clone_at_commit(
+                        &build.git_repository.url,
+                        &build.git_repository.commit_id,
+                        &repository_root_path,
+                        &get_credentials,
+                        clone_options,
+                    ) {
+                        let message = raw_error.message();
+                        let git_error_class = raw_error.class();
+                        // Some errors can happen "randomly":
+                        // - SSL error: syscall failure: Resource temporarily unavailable
+                        // - Timeout on git clone
+                        debug!("Error on git clone: git_error_class={:?}, message={}", git_error_class, message);
+                        return if git_error_class == ErrorClass::Os
+                            || git_error_class == ErrorClass::Ssl
+                            || (git_error_class == ErrorClass::Net && message.contains("timed out"))
+                        {
+                            debug!("Retrying git clone...");
+                            logger.send_warning(format!(
+                                "⚠️ Retrying cloning your git repository, due to following error: {}",
+                                message
+                            ));
+                            OperationResult::Retry(BuildError::GitError {
+                                application: build.image.service_id.clone(),
+                                git_cmd,
+                                context,
+                                raw_error,
+                            })
+                        } else {
+                            OperationResult::Err(BuildError::GitError {
+                                application: build.image.service_id.clone(),
+                                git_cmd,
+                                context,
+                                raw_error,
+                            })
+                        };
+                    }
+                    OperationResult::Ok(())
+                })
+            };
+
+            let mut clone_result = clone(&build.git_repository.clone_options);
+            if let Err(retry::Error {
+                error:
+                    BuildError::GitError {
+                        git_cmd: GitCmd::ShallowFetch,
                         raw_error,
-                    })
-                };
+                        ..
+                    },
+                ..
+            }) = &clone_result
+            {
+                logger.send_warning(format!(
+                    "⚠️ Commit {} cannot be fetched alone, cloning the whole repository instead: {}",
+                    build.git_repository.commit_id,
+                    raw_error.message()
+                ));
+                clone_result = clone(&build.git_repository.clone_options.full_clone());
+            }
+
+            if let Err(error) = clone_result {
+                git_clone_record.stop(StepStatus::Error);
+                return Err(match error.error {
+                    BuildError::GitError { raw_error, .. }
+                        if deploy_key.is_some() && raw_error.code() == ErrorCode::Auth =>
+                    {
+                        BuildError::DeployKeyRejected {
+                            application: build.image.service_id.clone(),
+                            repository_url: build.git_repository.url.to_string(),
+                            raw_error,
+                        }
+                    }
+                    error => error,
+                });
             }
-            OperationResult::Ok(())
-        }) {
-            git_clone_record.stop(StepStatus::Error);
-            return Err(error.error);
         }
         git_clone_record.stop(StepStatus::Success);
+        drop(deploy_key);
 
         let _git_cleanup = scopeguard::guard(&repository_root_path, |path| {
             info!("Removing git repository at path: {:?}", path);
@@ -479,9 +586,17 @@ impl BuildPlatform for LocalDocker {
             GitLfs::default()
         };
         let cmd_killer = CommandKiller::from_cancelable(abort);
-        let size_estimate_kb = git_lfs
-            .files_size_estimate_in_kb(&repository_root_path, &build.git_repository.commit_id, &cmd_killer)
-            .unwrap_or(0);
+        // Fetched sources are not a git repository, and already contain the git-lfs files
+        let size_estimate_kb = if build.git_repository.source_fetcher.is_some()
+            || !build.git_repository.clone_options.lfs
+            || !git::declares_lfs_files(&repository_root_path)
+        {
+            0
+        } else {
+            git_lfs
+                .files_size_estimate_in_kb(&repository_root_path, &build.git_repository.commit_id, &cmd_killer)
+                .unwrap_or(0)
+        };
 
         if size_estimate_kb > 0 {
             if size_estimate_kb > MAX_GIT_LFS_SIZE_KB {
@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
 use crate::cmd::docker::DockerError;
+use crate::cmd::git::GitCloneOptions;
 use crate::environment::report::logger::EnvLogger;
 use crate::errors::EngineError;
 use crate::events::EventDetails;
@@ -13,18 +14,22 @@ use crate::metrics_registry::MetricsRegistry;
 use crate::utilities::compute_image_tag;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::hash::Hash;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use url::Url;
 use uuid::Uuid;
 
 pub mod dockerfile_utils;
+pub mod in_cluster_clone;
 pub mod local_docker;
+pub mod sbom;
 
 #[derive(Debug)]
 pub enum GitCmd {
     Fetch,
+    // the commit is not reachable with a shallow fetch, the whole repository has to be cloned
+    ShallowFetch,
     Checkout,
     Submodule,
     SubmoduleUpdate,
@@ -34,6 +39,7 @@ impl Display for GitCmd {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         let msg = match self {
             GitCmd::Fetch => "git fetch",
+            GitCmd::ShallowFetch => "git fetch --depth",
             GitCmd::Checkout => "git checkout",
             GitCmd::Submodule => "git submodule",
             GitCmd::SubmoduleUpdate => "git submodule update",
@@ -78,6 +84,26 @@ pub enum BuildError {
 
     #[error("Cannot get credentials error.")]
     CannotGetCredentials { raw_error_message: String },
+
+    #[error("Cannot clone Application {application:?} from inside the cluster: {raw_error_message:?}")]
+    InClusterCloneError {
+        application: String,
+        raw_error_message: String,
+    },
+
+    #[error("Cannot clone Application {application:?}, the deploy key is refused by {repository_url}: {raw_error:?}")]
+    DeployKeyRejected {
+        application: String,
+        repository_url: String,
+        raw_error: git2::Error,
+    },
+
+    #[error("Build of Application {application:?} emulated for {architectures} has timed out: {raw_error_message:?}")]
+    EmulatedBuildTimeout {
+        application: String,
+        architectures: String,
+        raw_error_message: String,
+    },
 }
 
 pub fn to_build_error(service_id: String, err: DockerError) -> BuildError {
@@ -95,6 +121,14 @@ pub fn to_build_error(service_id: String, err: DockerError) -> BuildError {
 pub fn to_engine_error(event_details: EventDetails, err: BuildError, user_message: String) -> EngineError {
     match err {
         BuildError::Aborted { .. } => EngineError::new_task_cancellation_requested(event_details),
+        BuildError::EmulatedBuildTimeout { .. } => EngineError::new_builder_emulated_build_timeout(event_details, err),
+        BuildError::DeployKeyRejected { ref repository_url, .. } => {
+            EngineError::new_builder_clone_repository_deploy_key_error(
+                event_details,
+                repository_url.clone(),
+                err.into(),
+            )
+        }
         _ => EngineError::new_build_error(event_details, err, user_message),
     }
 }
@@ -117,6 +151,8 @@ pub struct Build {
     pub git_repository: GitRepository,
     pub image: Image,
     pub environment_variables: BTreeMap<String, String>,
+    // secrets given to the build with `--secret`, keyed by id, never part of the image tag
+    pub secrets: BTreeMap<String, String>,
     pub disable_cache: bool,
     pub timeout: Duration,
     pub architectures: Vec<CpuArchitecture>,
@@ -124,9 +160,16 @@ pub struct Build {
     pub max_ram_in_gib: u32,
     // registries used by the build where we need to login to pull image
     pub registries: Vec<Registry>,
+    // mirrors of Docker Hub used by the builder to pull docker.io images, set from the cluster settings
+    pub registry_mirrors: Vec<Url>,
 }
 
 impl Build {
+    /// Whether the image is built for several architectures, as a manifest list
+    pub fn is_multi_architecture(&self) -> bool {
+        self.architectures.iter().any(|arch| *arch != self.architectures[0])
+    }
+
     pub fn compute_image_tag(&mut self) {
         self.image.tag = compute_image_tag(
             &self.git_repository.root_path,
@@ -157,14 +200,44 @@ pub struct SshKey {
     pub public_key: Option<String>,
 }
 
+/// Where the git repository is cloned from
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GitAccess {
+    /// The builder clones the repository itself
+    #[default]
+    Direct,
+    /// The git provider is only reachable from the target cluster (i.e: behind a VPN), an agent clones it from there
+    InCluster,
+}
+
+/// Retrieves the sources of a repository when the builder cannot clone it itself
+pub trait SourceFetcher: Send + Sync {
+    /// Writes the sources of the repository at the requested commit into the destination directory
+    fn fetch_source(
+        &self,
+        git_repository: &GitRepository,
+        service_id: &str,
+        destination: &Path,
+        logger: &EnvLogger,
+        abort: &dyn Abort,
+    ) -> Result<(), BuildError>;
+}
+
 pub struct GitRepository {
     pub url: Url,
     pub get_credentials: Option<Box<dyn Fn() -> anyhow::Result<Credentials> + Send + Sync>>,
+    // read-only key of the application, when the repository is only exposed over ssh
+    pub deploy_key: Option<SshKey>,
     pub ssh_keys: Vec<SshKey>,
     pub commit_id: String,
     pub dockerfile_path: Option<PathBuf>,
     pub dockerfile_content: Option<String>,
     pub root_path: PathBuf,
+    pub git_access: GitAccess,
+    // injected at runtime, when the repository is not cloned by the builder itself
+    pub source_fetcher: Option<Arc<dyn SourceFetcher>>,
+    pub clone_options: GitCloneOptions,
 }
 impl GitRepository {
     fn credentials(&self) -> Option<anyhow::Result<Credentials>> {
@@ -0,0 +1,148 @@
+use crate::cmd::command::CommandKiller;
+use crate::cmd::docker::{ContainerImage, Docker};
+use crate::cmd::syft::{generate_spdx_sbom, is_syft_available, SyftError};
+use crate::infrastructure::models::build_platform::Image;
+use crate::infrastructure::models::kubernetes::Kubernetes;
+use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+use crate::infrastructure::models::object_storage::ObjectStorage;
+use std::path::Path;
+use tempfile::TempDir;
+
+pub const SBOM_OBJECT_PREFIX: &str = "sboms";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SbomError {
+    #[error("Cannot generate the SBOM of image `{image}`: {error}")]
+    CannotGenerate { image: String, error: SyftError },
+
+    #[error("Cannot write the SBOM of image `{image}`: {error}")]
+    IoError { image: String, error: std::io::Error },
+
+    #[error("Cannot upload the SBOM of image `{image}`: {error}")]
+    CannotUpload { image: String, error: ObjectStorageError },
+}
+
+/// Key of the SPDX JSON SBOM of the image in the cluster object storage
+pub fn sbom_object_key(image: &Image) -> String {
+    format!("{}/{}.spdx.json", SBOM_OBJECT_PREFIX, image.name_with_tag())
+}
+
+/// Uploads the SBOM of the image into the bucket and returns its object key
+pub fn upload_sbom(
+    object_storage: &dyn ObjectStorage,
+    bucket_name: &str,
+    image: &Image,
+    sbom_path: &Path,
+) -> Result<String, SbomError> {
+    let object_key = sbom_object_key(image);
+    object_storage
+        .upload_file(bucket_name, &object_key, sbom_path, None)
+        .map_err(|error| SbomError::CannotUpload {
+            image: image.name_with_tag(),
+            error,
+        })?;
+
+    Ok(object_key)
+}
+
+/// Generates the SBOM of the images once pushed, and publishes it into the object storage of the cluster
+pub struct SbomPublisher<'a> {
+    kubernetes: &'a dyn Kubernetes,
+    docker: &'a Docker,
+}
+
+impl<'a> SbomPublisher<'a> {
+    /// Returns None when the cluster has no object storage to publish to, or syft is not installed
+    pub fn new(kubernetes: &'a dyn Kubernetes, docker: &'a Docker) -> Option<Self> {
+        kubernetes.object_storage()?;
+        if !is_syft_available() {
+            info!("syft is not installed, SBOMs of the built images are not generated");
+            return None;
+        }
+
+        Some(SbomPublisher { kubernetes, docker })
+    }
+
+    pub fn publish(&self, image: &Image, cmd_killer: &CommandKiller) -> Result<String, SbomError> {
+        let Some(object_storage) = self.kubernetes.object_storage() else {
+            return Err(SbomError::CannotUpload {
+                image: image.name_with_tag(),
+                error: ObjectStorageError::CannotInstantiateClient {
+                    raw_error_message: "cluster has no object storage".to_string(),
+                },
+            });
+        };
+
+        let workspace = TempDir::with_prefix("sbom-").map_err(|error| SbomError::IoError {
+            image: image.name_with_tag(),
+            error,
+        })?;
+        let sbom_path = workspace.path().join("sbom.spdx.json");
+        let container_image = ContainerImage::new(image.registry_url.clone(), image.name(), vec![image.tag.clone()]);
+        generate_spdx_sbom(&container_image, self.docker.config_path(), &sbom_path, cmd_killer).map_err(|error| {
+            SbomError::CannotGenerate {
+                image: image.name_with_tag(),
+                error,
+            }
+        })?;
+
+        upload_sbom(object_storage, &self.kubernetes.sboms_bucket_name(), image, &sbom_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::models::object_storage::test_utils::InMemoryObjectStorage;
+    use std::fs;
+
+    fn image() -> Image {
+        Image {
+            name: "zabcd1234-my-app".to_string(),
+            tag: "8d9f0a2".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn sbom_file(dir: &TempDir) -> std::path::PathBuf {
+        let path = dir.path().join("sbom.spdx.json");
+        fs::write(&path, r#"{"spdxVersion":"SPDX-2.3"}"#).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_sbom_object_key() {
+        assert_eq!(sbom_object_key(&image()), "sboms/zabcd1234-my-app:8d9f0a2.spdx.json");
+    }
+
+    #[test]
+    fn test_upload_sbom() {
+        let dir = TempDir::new().unwrap();
+        let object_storage = InMemoryObjectStorage::with_buckets(&["qovery-kubeconfigs-z1234"]);
+
+        let key = upload_sbom(&object_storage, "qovery-kubeconfigs-z1234", &image(), &sbom_file(&dir)).unwrap();
+
+        assert_eq!(key, "sboms/zabcd1234-my-app:8d9f0a2.spdx.json");
+        assert_eq!(object_storage.keys("qovery-kubeconfigs-z1234"), vec![key.clone()]);
+        let object = object_storage.get_object("qovery-kubeconfigs-z1234", &key).unwrap();
+        assert_eq!(object.value, br#"{"spdxVersion":"SPDX-2.3"}"#);
+    }
+
+    #[test]
+    fn test_upload_sbom_failure() {
+        let dir = TempDir::new().unwrap();
+        let object_storage = InMemoryObjectStorage::with_buckets(&["qovery-kubeconfigs-z1234"]);
+        object_storage.reject_writes_under("qovery-kubeconfigs-z1234/");
+
+        let ret = upload_sbom(&object_storage, "qovery-kubeconfigs-z1234", &image(), &sbom_file(&dir));
+
+        assert!(matches!(
+            ret,
+            Err(SbomError::CannotUpload {
+                error: ObjectStorageError::CannotUploadFile { .. },
+                ..
+            })
+        ));
+        assert!(object_storage.keys("qovery-kubeconfigs-z1234").is_empty());
+    }
+}
@@ -2,6 +2,8 @@ use aws_sdk_ec2::config::{BehaviorVersion, SharedCredentialsProvider};
 use aws_types::SdkConfig;
 use std::any::Any;
 use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Duration;
 
 use aws_types::region::Region;
 use rusoto_core::{Client, HttpClient};
@@ -9,9 +11,12 @@ use rusoto_credential::StaticProvider;
 use rusoto_sts::{GetCallerIdentityRequest, Sts, StsClient};
 use uuid::Uuid;
 
-use crate::constants::{AWS_ACCESS_KEY_ID, AWS_DEFAULT_REGION, AWS_SECRET_ACCESS_KEY};
-use crate::errors::EngineError;
+use crate::constants::{AWS_ACCESS_KEY_ID, AWS_DEFAULT_REGION, AWS_SECRET_ACCESS_KEY, AWS_SESSION_TOKEN};
+use crate::errors::{CommandError, EngineError};
 use crate::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
+use crate::infrastructure::models::cloud_provider::aws::sts::{
+    session_name, AssumeRoleOptions, AssumedRoleCredentials, AssumedRoleProvider, StsApi,
+};
 use crate::infrastructure::models::cloud_provider::{CloudProvider, Kind, TerraformStateCredentials};
 use crate::infrastructure::models::kubernetes::Kind as KubernetesKind;
 use crate::io_models::context::Context;
@@ -21,6 +26,7 @@ use crate::utilities::to_short_id;
 
 pub mod database_instance_type;
 pub mod regions;
+pub mod sts;
 
 pub struct AWS {
     context: Context,
@@ -29,10 +35,12 @@ pub struct AWS {
     name: String,
     pub access_key_id: String,
     pub secret_access_key: String,
+    pub session_token: Option<String>,
     pub region: String,
     pub zones: Vec<String>,
     kubernetes_kind: KubernetesKind,
     terraform_state_credentials: TerraformStateCredentials,
+    assumed_role: Option<Arc<AssumedRoleCredentials>>,
 }
 
 impl AWS {
@@ -54,19 +62,64 @@ impl AWS {
             name: name.to_string(),
             access_key_id: access_key_id.to_string(),
             secret_access_key: secret_access_key.to_string(),
+            session_token: None,
             region: region.to_string(),
             zones,
             kubernetes_kind,
             terraform_state_credentials,
+            assumed_role: None,
         }
     }
 
+    /// Assumes the given role for the duration of the execution, the long-lived credentials are only used to call STS.
+    /// Temporary credentials are kept in memory only. SDK clients refresh them transparently when they expire,
+    /// while tools receiving them as environment variables (terraform, registry logins) get the ones obtained here,
+    /// so the session duration must cover the execution.
+    pub fn with_assumed_role(
+        mut self,
+        options: AssumeRoleOptions,
+        sts: Box<dyn StsApi>,
+    ) -> Result<Self, Box<EngineError>> {
+        let event_details = self.get_event_details(Stage::Infrastructure(InfrastructureStep::RetrieveClusterConfig));
+        let role_arn = options.role_arn.clone();
+        let assumed_role = block_on(AssumedRoleCredentials::assume(
+            sts,
+            options,
+            session_name(self.context.execution_id()),
+        ))
+        .map_err(|err| {
+            Box::new(EngineError::new_cannot_assume_cloud_provider_role(
+                event_details,
+                role_arn,
+                CommandError::new_from_safe_message(err),
+            ))
+        })?;
+
+        let credentials = assumed_role.current();
+        self.access_key_id = credentials.access_key_id;
+        self.secret_access_key = credentials.secret_access_key;
+        self.session_token = Some(credentials.session_token);
+        self.assumed_role = Some(Arc::new(assumed_role));
+
+        Ok(self)
+    }
+
     pub fn credentials(&self) -> StaticProvider {
-        StaticProvider::new(self.access_key_id.to_string(), self.secret_access_key.to_string(), None, None)
+        StaticProvider::new(
+            self.access_key_id.to_string(),
+            self.secret_access_key.to_string(),
+            self.session_token.clone(),
+            None,
+        )
     }
 
     pub fn client(&self) -> Client {
-        Client::new_with(self.credentials(), HttpClient::new().unwrap())
+        match &self.assumed_role {
+            Some(assumed_role) => {
+                Client::new_with(AssumedRoleProvider(assumed_role.clone()), HttpClient::new().unwrap())
+            }
+            None => Client::new_with(self.credentials(), HttpClient::new().unwrap()),
+        }
     }
 }
 
@@ -107,20 +160,29 @@ impl CloudProvider for AWS {
         self.secret_access_key.to_string()
     }
 
+    fn session_token(&self) -> Option<String> {
+        self.session_token.clone()
+    }
+
     fn region(&self) -> String {
         self.region.to_string()
     }
 
     fn aws_sdk_client(&self) -> Option<SdkConfig> {
+        let credentials_provider = match &self.assumed_role {
+            Some(assumed_role) => SharedCredentialsProvider::new(AssumedRoleProvider(assumed_role.clone())),
+            None => SharedCredentialsProvider::new(aws_credential_types::Credentials::new(
+                self.access_key_id(),
+                self.secret_access_key(),
+                None,
+                None,
+                "qovery-engine",
+            )),
+        };
+
         Some(
             SdkConfig::builder()
-                .credentials_provider(SharedCredentialsProvider::new(aws_credential_types::Credentials::new(
-                    self.access_key_id(),
-                    self.secret_access_key(),
-                    None,
-                    None,
-                    "qovery-engine",
-                )))
+                .credentials_provider(credentials_provider)
                 .behavior_version(BehaviorVersion::latest())
                 .region(Region::new(Cow::from(self.region.clone())))
                 .build(),
@@ -145,18 +207,28 @@ impl CloudProvider for AWS {
     }
 
     fn credentials_environment_variables(&self) -> Vec<(&str, &str)> {
-        vec![
+        let mut envs = vec![
             (AWS_DEFAULT_REGION, self.region.as_str()),
             (AWS_ACCESS_KEY_ID, self.access_key_id.as_str()),
             (AWS_SECRET_ACCESS_KEY, self.secret_access_key.as_str()),
-        ]
+        ];
+        if let Some(session_token) = &self.session_token {
+            envs.push((AWS_SESSION_TOKEN, session_token.as_str()));
+        }
+
+        envs
     }
 
     fn tera_context_environment_variables(&self) -> Vec<(&str, &str)> {
-        vec![
+        let mut envs = vec![
             ("aws_access_key", self.access_key_id.as_str()),
             ("aws_secret_key", self.secret_access_key.as_str()),
-        ]
+        ];
+        if let Some(session_token) = &self.session_token {
+            envs.push(("aws_session_token", session_token.as_str()));
+        }
+
+        envs
     }
 
     fn terraform_state_credentials(&self) -> Option<&TerraformStateCredentials> {
@@ -0,0 +1,344 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusoto_core::{Client, HttpClient};
+use rusoto_credential::{AwsCredentials, CredentialsError, ProvideAwsCredentials, StaticProvider};
+use rusoto_signature::region::Region;
+use rusoto_sts::{AssumeRoleRequest, Sts, StsClient};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+// AWS constraint on RoleSessionName
+const SESSION_NAME_MAX_LENGTH: usize = 64;
+// Credentials are refreshed a bit before their expiration, to not hand out credentials expiring in flight
+const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssumeRoleOptions {
+    pub role_arn: String,
+    pub external_id: Option<String>,
+    pub session_duration: Duration,
+}
+
+#[derive(Clone, Eq, PartialEq)]
+pub struct TemporaryCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: String,
+    pub expiration: DateTime<Utc>,
+}
+
+impl TemporaryCredentials {
+    pub fn needs_refresh(&self, now: DateTime<Utc>) -> bool {
+        now + REFRESH_MARGIN >= self.expiration
+    }
+}
+
+impl Debug for TemporaryCredentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TemporaryCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("expiration", &self.expiration)
+            .finish()
+    }
+}
+
+/// Session name of the assumed role, containing the execution id so CloudTrail events can be linked to an execution.
+pub fn session_name(execution_id: &str) -> String {
+    format!("qovery-engine-{execution_id}")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "+=,.@_-".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .take(SESSION_NAME_MAX_LENGTH)
+        .collect()
+}
+
+#[async_trait]
+pub trait StsApi: Send + Sync {
+    async fn assume_role(
+        &self,
+        options: &AssumeRoleOptions,
+        session_name: &str,
+    ) -> Result<TemporaryCredentials, String>;
+}
+
+/// STS client authenticated with the long-lived credentials, only used to assume the role.
+pub struct RusotoSts {
+    client: StsClient,
+}
+
+impl RusotoSts {
+    pub fn new(access_key_id: &str, secret_access_key: &str) -> Self {
+        let credentials = StaticProvider::new(access_key_id.to_string(), secret_access_key.to_string(), None, None);
+        RusotoSts {
+            client: StsClient::new_with_client(
+                Client::new_with(credentials, HttpClient::new().unwrap()),
+                Region::default(),
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl StsApi for RusotoSts {
+    async fn assume_role(
+        &self,
+        options: &AssumeRoleOptions,
+        session_name: &str,
+    ) -> Result<TemporaryCredentials, String> {
+        let response = self
+            .client
+            .assume_role(AssumeRoleRequest {
+                role_arn: options.role_arn.clone(),
+                role_session_name: session_name.to_string(),
+                external_id: options.external_id.clone(),
+                duration_seconds: Some(options.session_duration.as_secs() as i64),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let credentials = response
+            .credentials
+            .ok_or_else(|| "STS did not return any credentials".to_string())?;
+        let expiration = DateTime::parse_from_rfc3339(&credentials.expiration)
+            .map_err(|e| format!("Invalid credentials expiration `{}`: {e}", credentials.expiration))?;
+
+        Ok(TemporaryCredentials {
+            access_key_id: credentials.access_key_id,
+            secret_access_key: credentials.secret_access_key,
+            session_token: credentials.session_token,
+            expiration: expiration.with_timezone(&Utc),
+        })
+    }
+}
+
+/// Credentials of a role assumed for the duration of an execution.
+/// They are kept in memory only and refreshed when they are about to expire.
+pub struct AssumedRoleCredentials {
+    sts: Box<dyn StsApi>,
+    options: AssumeRoleOptions,
+    session_name: String,
+    current: Mutex<TemporaryCredentials>,
+}
+
+impl AssumedRoleCredentials {
+    pub async fn assume(
+        sts: Box<dyn StsApi>,
+        options: AssumeRoleOptions,
+        session_name: String,
+    ) -> Result<AssumedRoleCredentials, String> {
+        let credentials = sts.assume_role(&options, &session_name).await?;
+
+        Ok(AssumedRoleCredentials {
+            sts,
+            options,
+            session_name,
+            current: Mutex::new(credentials),
+        })
+    }
+
+    pub fn role_arn(&self) -> &str {
+        &self.options.role_arn
+    }
+
+    /// Last credentials obtained, without refreshing them
+    pub fn current(&self) -> TemporaryCredentials {
+        self.current.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Valid credentials, the role is assumed again if the current ones are about to expire
+    pub async fn credentials(&self) -> Result<TemporaryCredentials, String> {
+        let current = self.current();
+        if !current.needs_refresh(Utc::now()) {
+            return Ok(current);
+        }
+
+        let refreshed = self.sts.assume_role(&self.options, &self.session_name).await?;
+        *self.current.lock().unwrap_or_else(|e| e.into_inner()) = refreshed.clone();
+        Ok(refreshed)
+    }
+}
+
+/// Credentials provider for rusoto and aws sdk clients, refreshing the assumed role transparently.
+#[derive(Clone)]
+pub struct AssumedRoleProvider(pub Arc<AssumedRoleCredentials>);
+
+impl Debug for AssumedRoleProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AssumedRoleProvider").field(&self.0.role_arn()).finish()
+    }
+}
+
+#[async_trait]
+impl ProvideAwsCredentials for AssumedRoleProvider {
+    async fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        let credentials = self.0.credentials().await.map_err(CredentialsError::new)?;
+
+        Ok(AwsCredentials::new(
+            credentials.access_key_id,
+            credentials.secret_access_key,
+            Some(credentials.session_token),
+            Some(credentials.expiration),
+        ))
+    }
+}
+
+impl aws_credential_types::provider::ProvideCredentials for AssumedRoleProvider {
+    fn provide_credentials<'a>(&'a self) -> aws_credential_types::provider::future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        aws_credential_types::provider::future::ProvideCredentials::new(async move {
+            let credentials = self
+                .0
+                .credentials()
+                .await
+                .map_err(aws_credential_types::provider::error::CredentialsError::provider_error)?;
+
+            Ok(aws_credential_types::Credentials::new(
+                credentials.access_key_id,
+                credentials.secret_access_key,
+                Some(credentials.session_token),
+                Some(SystemTime::from(credentials.expiration)),
+                "qovery-engine-assumed-role",
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockSts {
+        calls: Arc<AtomicUsize>,
+        validity: chrono::Duration,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl StsApi for MockSts {
+        async fn assume_role(
+            &self,
+            options: &AssumeRoleOptions,
+            session_name: &str,
+        ) -> Result<TemporaryCredentials, String> {
+            if self.fail {
+                return Err(format!("AccessDenied: cannot assume {}", options.role_arn));
+            }
+
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(TemporaryCredentials {
+                access_key_id: format!("ASIA{call}"),
+                secret_access_key: format!("secret-{session_name}"),
+                session_token: format!("token-{}", options.external_id.clone().unwrap_or_default()),
+                expiration: Utc::now() + self.validity,
+            })
+        }
+    }
+
+    fn options() -> AssumeRoleOptions {
+        AssumeRoleOptions {
+            role_arn: "arn:aws:iam::123456789012:role/qovery-engine".to_string(),
+            external_id: Some("external-id".to_string()),
+            session_duration: Duration::from_secs(3600),
+        }
+    }
+
+    #[test]
+    fn test_session_name() {
+        assert_eq!(
+            session_name("8a5f1b3c-0d6e-4f07-9a3b-2c1d0e9f8a7b-1700000000"),
+            "qovery-engine-8a5f1b3c-0d6e-4f07-9a3b-2c1d0e9f8a7b-1700000000"
+        );
+        assert_eq!(session_name("exec id/with:chars"), "qovery-engine-exec-id-with-chars");
+
+        let name = session_name(&"x".repeat(100));
+        assert_eq!(name.len(), SESSION_NAME_MAX_LENGTH);
+        assert!(name.starts_with("qovery-engine-"));
+    }
+
+    #[tokio::test]
+    async fn test_assumed_role_credentials_are_reused_until_expiring() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let sts = MockSts {
+            calls: calls.clone(),
+            validity: chrono::Duration::hours(1),
+            fail: false,
+        };
+
+        let credentials = AssumedRoleCredentials::assume(Box::new(sts), options(), session_name("exec"))
+            .await
+            .unwrap();
+        let current = credentials.current();
+        assert_eq!(current.access_key_id, "ASIA0");
+        assert_eq!(current.secret_access_key, "secret-qovery-engine-exec");
+        assert_eq!(current.session_token, "token-external-id");
+
+        assert_eq!(credentials.credentials().await.unwrap(), current);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_assumed_role_credentials_are_refreshed_when_expiring() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let sts = MockSts {
+            calls: calls.clone(),
+            // expires within the refresh margin
+            validity: chrono::Duration::minutes(2),
+            fail: false,
+        };
+
+        let credentials = AssumedRoleCredentials::assume(Box::new(sts), options(), session_name("exec"))
+            .await
+            .unwrap();
+        assert_eq!(credentials.current().access_key_id, "ASIA0");
+
+        let refreshed = credentials.credentials().await.unwrap();
+        assert_eq!(refreshed.access_key_id, "ASIA1");
+        assert_eq!(credentials.current(), refreshed);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // rusoto clients get refreshed credentials too
+        let aws_credentials = AssumedRoleProvider(Arc::new(credentials)).credentials().await.unwrap();
+        assert_eq!(aws_credentials.aws_access_key_id(), "ASIA2");
+        assert_eq!(aws_credentials.token().as_deref(), Some("token-external-id"));
+    }
+
+    #[tokio::test]
+    async fn test_assume_role_failure() {
+        let sts = MockSts {
+            calls: Arc::new(AtomicUsize::new(0)),
+            validity: chrono::Duration::hours(1),
+            fail: true,
+        };
+
+        let err = AssumedRoleCredentials::assume(Box::new(sts), options(), session_name("exec"))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err, "AccessDenied: cannot assume arn:aws:iam::123456789012:role/qovery-engine");
+    }
+
+    #[test]
+    fn test_needs_refresh() {
+        let now = Utc::now();
+        let credentials = TemporaryCredentials {
+            access_key_id: "ASIA".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: "token".to_string(),
+            expiration: now + chrono::Duration::minutes(30),
+        };
+
+        assert!(!credentials.needs_refresh(now));
+        assert!(credentials.needs_refresh(now + chrono::Duration::minutes(26)));
+        assert!(credentials.needs_refresh(now + chrono::Duration::hours(1)));
+    }
+}
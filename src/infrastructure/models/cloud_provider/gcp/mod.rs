@@ -48,6 +48,30 @@ impl Google {
             terraform_state_credentials,
         }
     }
+
+    /// Impersonates the given service account for the duration of the execution, using the provided credentials as source.
+    /// Tools receiving the credentials (terraform, gcloud, SDK clients) request short-lived access tokens themselves
+    /// and refresh them when they expire, the service account keys are never issued nor persisted.
+    pub fn with_impersonated_service_account(mut self, service_account_email: &str) -> Google {
+        self.json_credentials_raw_json =
+            impersonated_service_account_credentials(&self.json_credentials_raw_json, service_account_email);
+        self
+    }
+}
+
+fn impersonated_service_account_credentials(source_credentials_raw_json: &str, service_account_email: &str) -> String {
+    let source_credentials =
+        serde_json::from_str::<serde_json::Value>(source_credentials_raw_json).unwrap_or(serde_json::Value::Null);
+
+    serde_json::json!({
+        "type": "impersonated_service_account",
+        "service_account_impersonation_url": format!(
+            "https://iamcredentials.googleapis.com/v1/projects/-/serviceAccounts/{service_account_email}:generateAccessToken"
+        ),
+        "source_credentials": source_credentials,
+        "delegates": [],
+    })
+    .to_string()
 }
 
 impl CloudProvider for Google {
@@ -150,3 +174,31 @@ impl CloudProvider for Google {
         Transmitter::CloudProvider(self.long_id, self.name.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_impersonated_service_account_credentials() {
+        let source = r#"{"type":"service_account","client_email":"engine@project.iam.gserviceaccount.com"}"#;
+
+        let credentials: serde_json::Value = serde_json::from_str(&impersonated_service_account_credentials(
+            source,
+            "deployer@project.iam.gserviceaccount.com",
+        ))
+        .unwrap();
+
+        assert_eq!(credentials["type"], "impersonated_service_account");
+        assert_eq!(
+            credentials["service_account_impersonation_url"],
+            "https://iamcredentials.googleapis.com/v1/projects/-/serviceAccounts/deployer@project.iam.gserviceaccount.com:generateAccessToken"
+        );
+        assert_eq!(credentials["source_credentials"]["type"], "service_account");
+        assert_eq!(
+            credentials["source_credentials"]["client_email"],
+            "engine@project.iam.gserviceaccount.com"
+        );
+        assert_eq!(credentials["delegates"], serde_json::json!([]));
+    }
+}
@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::cmd::docker::Docker;
 use crate::cmd::helm::{to_engine_error, Helm};
+use crate::cmd::helm_post_renderer::PostRenderer;
 use crate::environment::models::abort::Abort;
 use crate::environment::models::environment::Environment;
 use crate::environment::report::logger::EnvLogger;
@@ -20,6 +21,7 @@ use crate::infrastructure::models::cloud_provider::service::Service;
 use crate::infrastructure::models::container_registry::ContainerRegistry;
 use crate::infrastructure::models::dns_provider::DnsProvider;
 use crate::infrastructure::models::kubernetes;
+use crate::infrastructure::models::kubernetes::capabilities::ClusterCapabilities;
 use crate::infrastructure::models::kubernetes::Kubernetes;
 use crate::io_models::context::Context;
 use crate::logger::Logger;
@@ -46,6 +48,10 @@ pub trait CloudProvider: Send + Sync {
     }
     fn access_key_id(&self) -> String;
     fn secret_access_key(&self) -> String;
+    /// session token of temporary credentials, if any
+    fn session_token(&self) -> Option<String> {
+        None
+    }
     fn region(&self) -> String;
     // TODO(benjaminch): Remove client from here
     fn aws_sdk_client(&self) -> Option<SdkConfig>;
@@ -133,6 +139,7 @@ pub struct DeploymentTarget<'a> {
     pub docker: &'a Docker,
     pub kube: kube::Client,
     pub helm: Helm,
+    pub capabilities: ClusterCapabilities,
     pub abort: &'a dyn Abort,
     logger: Arc<Box<dyn Logger>>,
     pub metrics_registry: Arc<dyn MetricsRegistry>,
@@ -157,15 +164,19 @@ impl<'a> DeploymentTarget<'a> {
             }
         };
 
+        let post_renderer_env = PostRenderer::from_cluster_settings(kubernetes.advanced_settings()).to_env();
         let helm = if let Some(kubeconfig_path) = &kubeconfig_path {
-            Helm::new(
-                Some(kubeconfig_path),
-                &infra_ctx.cloud_provider().credentials_environment_variables(),
-            )
-            .map_err(|e| to_engine_error(event_details, e))?
+            let mut envs = infra_ctx.cloud_provider().credentials_environment_variables();
+            envs.extend(post_renderer_env.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+            Helm::new(Some(kubeconfig_path), &envs).map_err(|e| to_engine_error(event_details, e))?
         } else {
             Helm::new(Option::<&Path>::None, &[]).map_err(|e| to_engine_error(event_details, e))?
         };
+        let capabilities = ClusterCapabilities::detect(
+            kubeconfig_path.as_deref(),
+            infra_ctx.cloud_provider().credentials_environment_variables(),
+            &kubernetes.version(),
+        );
 
         Ok(DeploymentTarget {
             kubernetes,
@@ -176,6 +187,7 @@ impl<'a> DeploymentTarget<'a> {
             docker: &infra_ctx.context().docker,
             kube: infra_ctx.mk_kube_client()?.client().clone(),
             helm,
+            capabilities,
             abort,
             logger: Arc::new(infra_ctx.kubernetes().logger().clone_dyn()),
             is_dry_run_deploy: kubernetes.context().is_dry_run_deploy(),
@@ -4,24 +4,26 @@ use base64::engine::general_purpose;
 use base64::Engine;
 use std::collections::HashMap;
 use std::str::FromStr;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use rusoto_core::{Client, HttpClient, Region, RusotoError};
 use rusoto_credential::StaticProvider;
 use rusoto_ecr::{
     BatchDeleteImageRequest, CreateRepositoryRequest, DeleteRepositoryError, DeleteRepositoryRequest,
-    DescribeImagesRequest, DescribeRepositoriesError, DescribeRepositoriesRequest, Ecr, EcrClient,
-    GetAuthorizationTokenRequest, ImageDetail, ImageIdentifier, ListTagsForResourceRequest, PutLifecyclePolicyRequest,
-    Tag, TagResourceRequest,
+    DescribeImagesError, DescribeImagesFilter, DescribeImagesRequest, DescribeRepositoriesError,
+    DescribeRepositoriesRequest, Ecr, EcrClient, GetAuthorizationTokenRequest, ImageDetail, ImageIdentifier,
+    ListTagsForResourceRequest, PutLifecyclePolicyRequest, Tag, TagResourceRequest,
 };
 use rusoto_sts::{GetCallerIdentityRequest, Sts, StsClient};
 
 use crate::events::{EngineEvent, EventMessage, InfrastructureStep, Stage};
 use crate::infrastructure::models::build_platform::Image;
+use crate::infrastructure::models::cloud_provider::aws::sts::{AssumedRoleCredentials, AssumedRoleProvider};
 use crate::infrastructure::models::container_registry::errors::ContainerRegistryError;
 use crate::infrastructure::models::container_registry::{
-    take_last_x_chars_and_remove_leading_dash_char, ContainerRegistry, ContainerRegistryInfo, Kind, Repository,
-    RepositoryInfo,
+    take_last_x_chars_and_remove_leading_dash_char, ContainerRegistry, ContainerRegistryInfo, Kind,
+    RegistryCleanupPolicy, Repository, RepositoryInfo,
 };
 use crate::io_models::context::Context;
 use crate::logger::Logger;
@@ -34,12 +36,16 @@ use uuid::Uuid;
 
 use super::RegistryTags;
 
+// max number of image ids accepted by a BatchDeleteImage call
+const BATCH_DELETE_MAX_IMAGE_IDS: usize = 100;
+
 pub struct ECR {
     context: Context,
     long_id: Uuid,
     name: String,
     access_key_id: String,
     secret_access_key: String,
+    assumed_role: Option<Arc<AssumedRoleCredentials>>,
     region: Region,
     registry_info: Option<ContainerRegistryInfo>, // TODO(benjamin): code smell, should not come with an Option
     logger: Box<dyn Logger>,
@@ -47,12 +53,15 @@ pub struct ECR {
 }
 
 impl ECR {
+    /// When a role is assumed, the access key of the registry is only used to call STS and the registry is reached
+    /// with the credentials of the role, refreshed when they expire.
     pub fn new(
         context: Context,
         long_id: Uuid,
         name: &str,
         access_key_id: &str,
         secret_access_key: &str,
+        assumed_role: Option<Arc<AssumedRoleCredentials>>,
         region: &str,
         logger: Box<dyn Logger>,
         tags: HashMap<String, String>,
@@ -63,6 +72,7 @@ impl ECR {
             name: name.to_string(),
             access_key_id: access_key_id.to_string(),
             secret_access_key: secret_access_key.to_string(),
+            assumed_role,
             region: Region::from_str(region).unwrap(),
             registry_info: None,
             logger,
@@ -115,12 +125,16 @@ impl ECR {
         ));
     }
 
-    pub fn credentials(&self) -> StaticProvider {
-        StaticProvider::new(self.access_key_id.to_string(), self.secret_access_key.to_string(), None, None)
-    }
-
     pub fn client(&self) -> Client {
-        Client::new_with(self.credentials(), HttpClient::new().unwrap())
+        match &self.assumed_role {
+            Some(assumed_role) => {
+                Client::new_with(AssumedRoleProvider(assumed_role.clone()), HttpClient::new().unwrap())
+            }
+            None => Client::new_with(
+                StaticProvider::new(self.access_key_id.to_string(), self.secret_access_key.to_string(), None, None),
+                HttpClient::new().unwrap(),
+            ),
+        }
     }
 
     pub fn ecr_client(&self) -> EcrClient {
@@ -339,6 +353,120 @@ impl ECR {
         }
     }
 
+    fn list_repositories(&self) -> Result<Vec<String>, ContainerRegistryError> {
+        let mut repositories = vec![];
+        let mut next_token = None;
+        loop {
+            let request = DescribeRepositoriesRequest {
+                next_token: next_token.take(),
+                ..Default::default()
+            };
+            let response = match block_on_with_timeout(self.ecr_client().describe_repositories(request)) {
+                Ok(Ok(response)) => response,
+                Ok(Err(err)) => {
+                    return Err(ContainerRegistryError::RegistryDoesntExist {
+                        registry_name: self.name.to_string(),
+                        raw_error_message: err.to_string(),
+                    })
+                }
+                Err(err) => {
+                    return Err(ContainerRegistryError::RegistryDoesntExist {
+                        registry_name: self.name.to_string(),
+                        raw_error_message: err.to_string(),
+                    })
+                }
+            };
+
+            repositories.extend(
+                response
+                    .repositories
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|repository| repository.repository_name),
+            );
+            next_token = response.next_token;
+            if next_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(repositories)
+    }
+
+    // tag_status is either TAGGED, UNTAGGED or ANY
+    fn list_images(&self, repository_name: &str, tag_status: &str) -> Result<Vec<ImageDetail>, ContainerRegistryError> {
+        let to_error = |raw_error_message: String| ContainerRegistryError::CannotGetRepository {
+            registry_name: self.name.to_string(),
+            repository_name: repository_name.to_string(),
+            raw_error_message,
+        };
+
+        let mut images = vec![];
+        let mut next_token = None;
+        loop {
+            let request = DescribeImagesRequest {
+                repository_name: repository_name.to_string(),
+                filter: Some(DescribeImagesFilter {
+                    tag_status: Some(tag_status.to_string()),
+                }),
+                next_token: next_token.take(),
+                ..Default::default()
+            };
+            let response = match block_on_with_timeout(self.ecr_client().describe_images(request)) {
+                Ok(Ok(response)) => response,
+                Ok(Err(RusotoError::Service(DescribeImagesError::RepositoryNotFound(_)))) => return Ok(vec![]),
+                Ok(Err(err)) => return Err(to_error(err.to_string())),
+                Err(err) => return Err(to_error(err.to_string())),
+            };
+
+            images.extend(response.image_details.unwrap_or_default());
+            next_token = response.next_token;
+            if next_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(images)
+    }
+
+    // Returns the number of deleted images, the ones already deleted are not considered as failures
+    fn batch_delete_images(
+        &self,
+        repository_name: &str,
+        image_ids: Vec<ImageIdentifier>,
+    ) -> Result<usize, ContainerRegistryError> {
+        let to_error = |raw_error_message: String| ContainerRegistryError::CannotDeleteImage {
+            registry_name: self.name.to_string(),
+            repository_name: repository_name.to_string(),
+            image_name: repository_name.to_string(),
+            raw_error_message,
+        };
+
+        let mut deleted_images = 0;
+        for request in batch_delete_requests(repository_name, image_ids) {
+            let requested_images = request.image_ids.len();
+            let failures = match block_on_with_timeout(self.ecr_client().batch_delete_image(request)) {
+                Ok(Ok(response)) => response.failures.unwrap_or_default(),
+                Ok(Err(err)) => return Err(to_error(err.to_string())),
+                Err(err) => return Err(to_error(err.to_string())),
+            };
+
+            deleted_images += requested_images - failures.len();
+            if let Some(failure) = failures
+                .into_iter()
+                .find(|failure| failure.failure_code.as_deref() != Some("ImageNotFound"))
+            {
+                return Err(to_error(format!(
+                    "{}: {}",
+                    failure.failure_code.unwrap_or_default(),
+                    failure.failure_reason.unwrap_or_default()
+                )));
+            }
+        }
+
+        Ok(deleted_images)
+    }
+
     fn get_or_create_repository(
         &self,
         repository_name: &str,
@@ -528,6 +656,108 @@ impl ContainerRegistry for ECR {
     fn image_exists(&self, image: &Image) -> bool {
         self.get_image(image).is_some()
     }
+
+    fn cleanup_repository(
+        &self,
+        repository_name: &str,
+        cleanup_policy: &RegistryCleanupPolicy,
+        deployed_tag: Option<&str>,
+    ) -> Result<usize, ContainerRegistryError> {
+        let mut image_ids = vec![];
+        if let Some(untagged_image_ttl) = cleanup_policy.untagged_image_ttl {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let images = self.list_images(repository_name, "UNTAGGED")?;
+            image_ids.extend(untagged_images_older_than(&images, untagged_image_ttl, now));
+        }
+        if let Some(max_images) = cleanup_policy.max_images_per_repository {
+            let images = self.list_images(repository_name, "TAGGED")?;
+            image_ids.extend(images_above_max_count(&images, max_images, deployed_tag));
+        }
+
+        self.batch_delete_images(repository_name, image_ids)
+    }
+
+    fn cleanup_untagged_images(
+        &self,
+        repository_prefix: &str,
+        untagged_image_ttl: Duration,
+    ) -> Result<usize, ContainerRegistryError> {
+        let cleanup_policy = RegistryCleanupPolicy {
+            untagged_image_ttl: Some(untagged_image_ttl),
+            max_images_per_repository: None,
+        };
+
+        let mut deleted_images = 0;
+        for repository_name in self.list_repositories()? {
+            if repository_name.starts_with(repository_prefix) {
+                deleted_images += self.cleanup_repository(&repository_name, &cleanup_policy, None)?;
+            }
+        }
+
+        Ok(deleted_images)
+    }
+}
+
+// `now` and the push date of the images are in seconds since epoch
+fn untagged_images_older_than(images: &[ImageDetail], ttl: Duration, now: f64) -> Vec<ImageIdentifier> {
+    images
+        .iter()
+        .filter(|image| image.image_tags.iter().flatten().next().is_none())
+        .filter(|image| {
+            image
+                .image_pushed_at
+                .is_some_and(|pushed_at| pushed_at + ttl.as_secs_f64() < now)
+        })
+        .filter_map(|image| image.image_digest.clone())
+        .map(|digest| ImageIdentifier {
+            image_digest: Some(digest),
+            image_tag: None,
+        })
+        .collect()
+}
+
+// Keeps the `max_images` most recently pushed images, and the deployed one whatever its age.
+// Images are deleted by digest, with all their tags.
+fn images_above_max_count(
+    images: &[ImageDetail],
+    max_images: usize,
+    deployed_tag: Option<&str>,
+) -> Vec<ImageIdentifier> {
+    let is_deployed = |image: &ImageDetail| match (deployed_tag, &image.image_tags) {
+        (Some(deployed_tag), Some(tags)) => tags.iter().any(|tag| tag == deployed_tag),
+        _ => false,
+    };
+
+    let mut images = images.iter().collect::<Vec<_>>();
+    images.sort_by(|a, b| {
+        b.image_pushed_at
+            .unwrap_or_default()
+            .total_cmp(&a.image_pushed_at.unwrap_or_default())
+    });
+    images
+        .into_iter()
+        .skip(max_images)
+        .filter(|image| !is_deployed(image))
+        .filter_map(|image| image.image_digest.clone())
+        .map(|digest| ImageIdentifier {
+            image_digest: Some(digest),
+            image_tag: None,
+        })
+        .collect()
+}
+
+fn batch_delete_requests(repository_name: &str, image_ids: Vec<ImageIdentifier>) -> Vec<BatchDeleteImageRequest> {
+    image_ids
+        .chunks(BATCH_DELETE_MAX_IMAGE_IDS)
+        .map(|image_ids| BatchDeleteImageRequest {
+            registry_id: None,
+            repository_name: repository_name.to_string(),
+            image_ids: image_ids.to_vec(),
+        })
+        .collect()
 }
 
 pub struct ECRCredentials {
@@ -545,3 +775,99 @@ impl ECRCredentials {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(digest: &str, tags: &[&str], pushed_at: f64) -> ImageDetail {
+        ImageDetail {
+            image_digest: Some(digest.to_string()),
+            image_tags: match tags.is_empty() {
+                true => None,
+                false => Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            },
+            image_pushed_at: Some(pushed_at),
+            ..Default::default()
+        }
+    }
+
+    fn digests(image_ids: &[ImageIdentifier]) -> Vec<&str> {
+        image_ids
+            .iter()
+            .filter_map(|image_id| image_id.image_digest.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_delete_requests() {
+        // setup:
+        let image_ids = (0..250)
+            .map(|i| ImageIdentifier {
+                image_digest: Some(format!("sha256:{i}")),
+                image_tag: None,
+            })
+            .collect::<Vec<_>>();
+
+        // execute:
+        let requests = batch_delete_requests("z1234-my-app", image_ids);
+
+        // verify:
+        assert_eq!(
+            requests
+                .iter()
+                .map(|request| request.image_ids.len())
+                .collect::<Vec<_>>(),
+            vec![100, 100, 50]
+        );
+        assert!(requests.iter().all(|request| request.repository_name == "z1234-my-app"));
+        assert_eq!(requests[1].image_ids[0].image_digest.as_deref(), Some("sha256:100"));
+        assert_eq!(requests[2].image_ids[49].image_digest.as_deref(), Some("sha256:249"));
+
+        assert_eq!(
+            batch_delete_requests("z1234-my-app", (0..100).map(|_| ImageIdentifier::default()).collect()).len(),
+            1
+        );
+        assert!(batch_delete_requests("z1234-my-app", vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_untagged_images_older_than() {
+        // setup:
+        let day = 86400.0;
+        let now = 100.0 * day;
+        let images = vec![
+            image("sha256:old-untagged", &[], now - 8.0 * day),
+            image("sha256:recent-untagged", &[], now - 6.0 * day),
+            image("sha256:old-tagged", &["v1"], now - 8.0 * day),
+        ];
+
+        // execute:
+        let image_ids = untagged_images_older_than(&images, Duration::from_secs(7 * 86400), now);
+
+        // verify:
+        assert_eq!(digests(&image_ids), vec!["sha256:old-untagged"]);
+    }
+
+    #[test]
+    fn test_images_above_max_count() {
+        // setup:
+        let images = vec![
+            image("sha256:2", &["v2"], 2.0),
+            image("sha256:4", &["v4", "latest"], 4.0),
+            image("sha256:1", &["v1"], 1.0),
+            image("sha256:3", &["v3"], 3.0),
+        ];
+
+        // execute & verify: the oldest images are deleted first
+        assert_eq!(digests(&images_above_max_count(&images, 2, None)), vec!["sha256:2", "sha256:1"]);
+        assert!(images_above_max_count(&images, 4, None).is_empty());
+
+        // the deployed image is kept whatever its age
+        assert_eq!(digests(&images_above_max_count(&images, 2, Some("v1"))), vec!["sha256:2"]);
+        assert_eq!(
+            digests(&images_above_max_count(&images, 0, Some("latest"))),
+            vec!["sha256:3", "sha256:2", "sha256:1"]
+        );
+    }
+}
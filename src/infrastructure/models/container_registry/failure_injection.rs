@@ -0,0 +1,111 @@
+use crate::cmd::failure_injection::intercept_call;
+use crate::events::{EventDetails, Stage};
+use crate::infrastructure::models::build_platform::Image;
+use crate::infrastructure::models::container_registry::errors::ContainerRegistryError;
+use crate::infrastructure::models::container_registry::{
+    ContainerRegistry, ContainerRegistryInfo, Kind, RegistryCleanupPolicy, RegistryTags, Repository, RepositoryInfo,
+};
+use crate::io_models::context::Context;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Returns the errors scripted for the container registry calls, in place of calling the registry.
+/// Calls are scripted as `container_registry <method> <repository or image name>`.
+pub struct FailureInjectionContainerRegistry {
+    inner: Box<dyn ContainerRegistry>,
+}
+
+impl FailureInjectionContainerRegistry {
+    pub fn new(inner: Box<dyn ContainerRegistry>) -> Self {
+        FailureInjectionContainerRegistry { inner }
+    }
+}
+
+impl ContainerRegistry for FailureInjectionContainerRegistry {
+    fn context(&self) -> &Context {
+        self.inner.context()
+    }
+
+    fn kind(&self) -> Kind {
+        self.inner.kind()
+    }
+
+    fn long_id(&self) -> &Uuid {
+        self.inner.long_id()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn registry_info(&self) -> &ContainerRegistryInfo {
+        self.inner.registry_info()
+    }
+
+    fn create_repository(
+        &self,
+        repository_name: &str,
+        image_retention_time_in_seconds: u32,
+        registry_tags: RegistryTags,
+    ) -> Result<(Repository, RepositoryInfo), ContainerRegistryError> {
+        if let Some(err) = intercept_call(&["container_registry", "create_repository", repository_name]) {
+            return Err(err);
+        }
+        self.inner
+            .create_repository(repository_name, image_retention_time_in_seconds, registry_tags)
+    }
+
+    fn get_repository(&self, repository_name: &str) -> Result<Repository, ContainerRegistryError> {
+        if let Some(err) = intercept_call(&["container_registry", "get_repository", repository_name]) {
+            return Err(err);
+        }
+        self.inner.get_repository(repository_name)
+    }
+
+    fn delete_repository(&self, repository_name: &str) -> Result<(), ContainerRegistryError> {
+        if let Some(err) = intercept_call(&["container_registry", "delete_repository", repository_name]) {
+            return Err(err);
+        }
+        self.inner.delete_repository(repository_name)
+    }
+
+    fn delete_image(&self, image_name: &Image) -> Result<(), ContainerRegistryError> {
+        if let Some(err) = intercept_call(&["container_registry", "delete_image", &image_name.name]) {
+            return Err(err);
+        }
+        self.inner.delete_image(image_name)
+    }
+
+    fn image_exists(&self, image: &Image) -> bool {
+        self.inner.image_exists(image)
+    }
+
+    fn cleanup_repository(
+        &self,
+        repository_name: &str,
+        cleanup_policy: &RegistryCleanupPolicy,
+        deployed_tag: Option<&str>,
+    ) -> Result<usize, ContainerRegistryError> {
+        if let Some(err) = intercept_call(&["container_registry", "cleanup_repository", repository_name]) {
+            return Err(err);
+        }
+        self.inner
+            .cleanup_repository(repository_name, cleanup_policy, deployed_tag)
+    }
+
+    fn cleanup_untagged_images(
+        &self,
+        repository_prefix: &str,
+        untagged_image_ttl: Duration,
+    ) -> Result<usize, ContainerRegistryError> {
+        if let Some(err) = intercept_call(&["container_registry", "cleanup_untagged_images", repository_prefix]) {
+            return Err(err);
+        }
+        self.inner
+            .cleanup_untagged_images(repository_prefix, untagged_image_ttl)
+    }
+
+    fn get_event_details(&self, stage: Stage) -> EventDetails {
+        self.inner.get_event_details(stage)
+    }
+}
@@ -7,15 +7,20 @@ use crate::infrastructure::models::container_registry::{
     ContainerRegistry, ContainerRegistryInfo, Kind, Repository, RepositoryInfo,
 };
 use crate::io_models::context::Context;
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION};
-use reqwest::Error;
+use reqwest::StatusCode;
 use serde_derive::Deserialize;
 use std::time::Duration;
 use url::Url;
 use uuid::Uuid;
 
-#[derive(Clone, Debug)]
+const GITHUB_API_URL: &str = "https://api.github.com";
+// max page size of the packages api
+const VERSIONS_PER_PAGE: usize = 100;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum RegistryType {
     User(String),
     Organization(String),
@@ -32,28 +37,48 @@ impl RegistryType {
 
 pub struct GithubCr {
     generic_cr: GenericCr,
-    http_client: reqwest::blocking::Client,
-    registry_type: RegistryType,
+    packages_api: GithubPackagesApi,
 }
 
 #[derive(Default, Deserialize)]
-struct GithubUserResponse {
-    login: String,
+struct GithubAccountResponse {
+    #[serde(rename = "type")]
+    account_type: String,
 }
 
-impl GithubCr {
-    pub fn new(
-        context: Context,
-        long_id: Uuid,
-        name: &str,
-        url: Url,
-        username: String,
-        token: String,
-    ) -> Result<Self, ContainerRegistryError> {
+#[derive(Default, Deserialize)]
+struct PackageVersion {
+    id: u64,
+    name: String, // the digest, start with sha256:
+    created_at: DateTime<Utc>,
+    metadata: PackageMetadata,
+}
+
+#[derive(Default, Deserialize)]
+struct PackageMetadata {
+    container: PackageContainer,
+}
+
+#[derive(Default, Deserialize)]
+struct PackageContainer {
+    tags: Vec<String>,
+}
+
+/// Container packages of the user or organization owning the registry, through the GitHub Packages API.
+/// GHCR has no api of its own: repositories are created on push, and deleting images goes through their package.
+struct GithubPackagesApi {
+    http_client: reqwest::blocking::Client,
+    api_url: Url,
+    registry_type: RegistryType,
+}
+
+impl GithubPackagesApi {
+    /// Works with a personal access token as well as with a GitHub App installation token, which cannot read /user
+    fn new(token: &str, api_url: Url, username: String) -> Result<Self, ContainerRegistryError> {
         let mut headers = HeaderMap::new();
         headers.insert(ACCEPT, HeaderValue::from_static("application/vnd.github+json"));
         headers.insert("X-GitHub-Api-Version", HeaderValue::from_static("2022-11-28"));
-        let mut auth_header = HeaderValue::from_str(&format!("Bearer {}", &token)).map_err(|e| {
+        let mut auth_header = HeaderValue::from_str(&format!("Bearer {}", token)).map_err(|e| {
             ContainerRegistryError::CannotInstantiateClient {
                 raw_error_message: format!("Cannot create auth header: {}", e),
             }
@@ -67,21 +92,31 @@ impl GithubCr {
             // All api call must have an user agent set
             // https://docs.github.com/en/rest/using-the-rest-api/getting-started-with-the-rest-api?apiVersion=2022-11-28#user-agent
             .user_agent("qovery-engine")
-            //.proxy(reqwest::Proxy::all("http://localhost:8080").unwrap())
             .build()
             .map_err(|e| ContainerRegistryError::CannotInstantiateClient {
                 raw_error_message: format!("Cannot create http client: {}", e),
             })?;
 
-        let response: Result<GithubUserResponse, Error> = http_client
-            .get("https://api.github.com/user")
+        let mut account_url = api_url.clone();
+        account_url
+            .path_segments_mut()
+            .map_err(|_| ContainerRegistryError::InvalidRegistryUrl {
+                registry_url: api_url.to_string(),
+            })?
+            .pop_if_empty()
+            .extend(["users", username.as_str()]);
+        let response: reqwest::Result<GithubAccountResponse> = http_client
+            .get(account_url)
             .send()
             .and_then(|res| res.error_for_status())
-            .and_then(|x| x.json());
+            .and_then(|res| res.json());
 
-        let repository_type = match response {
-            Ok(r) if r.login == username => RegistryType::User(username),
-            Ok(_) => RegistryType::Organization(username),
+        let registry_type = match response {
+            Ok(account) if account.account_type == "Organization" => RegistryType::Organization(username),
+            Ok(_) => RegistryType::User(username),
+            Err(err) if matches!(err.status(), Some(StatusCode::UNAUTHORIZED)) => {
+                return Err(ContainerRegistryError::InvalidCredentials);
+            }
             Err(err) => {
                 warn!(
                     "Cannot determine if the registry is an organization or a user, defaulting to user: {:?}",
@@ -91,21 +126,148 @@ impl GithubCr {
             }
         };
 
+        Ok(GithubPackagesApi {
+            http_client,
+            api_url,
+            registry_type,
+        })
+    }
+
+    // https://api.github.com/user/packages/container/PACKAGE_NAME
+    // https://api.github.com/orgs/ORG/packages/container/PACKAGE_NAME
+    fn package_url(&self, package_name: &str, segments: &[&str]) -> Url {
+        let mut url = self.api_url.clone();
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty();
+            match &self.registry_type {
+                RegistryType::User(_) => path.push("user"),
+                RegistryType::Organization(org) => path.extend(["orgs", org.as_str()]),
+            };
+            // nested package names must be url encoded, i.e: qovery/engine -> qovery%2Fengine
+            path.extend(["packages", "container", package_name]).extend(segments);
+        }
+
+        url
+    }
+
+    fn list_versions(&self, package_name: &str) -> reqwest::Result<Vec<PackageVersion>> {
+        let mut versions = vec![];
+        for page in 1.. {
+            let mut url = self.package_url(package_name, &["versions"]);
+            url.query_pairs_mut()
+                .append_pair("per_page", &VERSIONS_PER_PAGE.to_string())
+                .append_pair("page", &page.to_string());
+
+            let page_versions: Vec<PackageVersion> =
+                match self.http_client.get(url).send().and_then(|res| res.error_for_status()) {
+                    Ok(res) => res.json()?,
+                    Err(err) if matches!(err.status(), Some(StatusCode::NOT_FOUND)) => vec![],
+                    Err(err) => return Err(err),
+                };
+
+            let is_last_page = page_versions.len() < VERSIONS_PER_PAGE;
+            versions.extend(page_versions);
+            if is_last_page {
+                break;
+            }
+        }
+
+        Ok(versions)
+    }
+
+    // https://docs.github.com/en/rest/packages/packages?apiVersion=2022-11-28#delete-package-version-for-an-organization
+    // https://docs.github.com/en/rest/packages/packages?apiVersion=2022-11-28#delete-a-package-version-for-the-authenticated-user
+    fn delete_version(&self, package_name: &str, version_id: u64) -> reqwest::Result<()> {
+        self.delete(self.package_url(package_name, &["versions", &version_id.to_string()]))
+    }
+
+    fn delete_package(&self, package_name: &str) -> reqwest::Result<()> {
+        self.delete(self.package_url(package_name, &[]))
+    }
+
+    fn delete(&self, url: Url) -> reqwest::Result<()> {
+        match self
+            .http_client
+            .delete(url)
+            .send()
+            .and_then(|res| res.error_for_status())
+        {
+            Ok(_) => Ok(()),
+            Err(err) if matches!(err.status(), Some(StatusCode::NOT_FOUND)) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// GHCR has no lifecycle policy, it is emulated by deleting the untagged versions (i.e: images whose tag has been
+    /// moved, or layers of old multi-arch images) pushed before the retention time. Returns the number of deleted versions.
+    fn delete_untagged_versions_older_than(
+        &self,
+        package_name: &str,
+        retention_time: Duration,
+        now: DateTime<Utc>,
+    ) -> reqwest::Result<usize> {
+        let Ok(retention_time) = chrono::Duration::from_std(retention_time) else {
+            return Ok(0);
+        };
+
+        let mut deleted_versions = 0;
+        for version in self.list_versions(package_name)? {
+            if version.metadata.container.tags.is_empty() && version.created_at + retention_time < now {
+                self.delete_version(package_name, version.id)?;
+                deleted_versions += 1;
+            }
+        }
+
+        Ok(deleted_versions)
+    }
+}
+
+// Github api does not want the user prefix. i.e: qovery/engine -> engine
+fn package_name(repository_name: &str) -> &str {
+    repository_name
+        .split_once('/')
+        .map(|(_, package)| package)
+        .unwrap_or(repository_name)
+}
+
+fn to_container_registry_error(
+    err: reqwest::Error,
+    to_error: impl FnOnce(String) -> ContainerRegistryError,
+) -> ContainerRegistryError {
+    match err.status() {
+        Some(StatusCode::UNAUTHORIZED) => ContainerRegistryError::InvalidCredentials,
+        _ => to_error(err.to_string()),
+    }
+}
+
+impl GithubCr {
+    pub fn new(
+        context: Context,
+        long_id: Uuid,
+        name: &str,
+        url: Url,
+        username: String,
+        token: String,
+    ) -> Result<Self, ContainerRegistryError> {
+        let api_url = Url::parse(GITHUB_API_URL).map_err(|_| ContainerRegistryError::InvalidRegistryUrl {
+            registry_url: GITHUB_API_URL.to_string(),
+        })?;
+        let packages_api = GithubPackagesApi::new(&token, api_url, username)?;
+
         let generic_cr = GenericCr::new(
             context,
             long_id,
             name,
             url,
             false,
-            repository_type.repository_prefix().to_string(),
+            packages_api.registry_type.repository_prefix().to_string(),
             Some(("nologin".to_string(), token)),
             true,
         )?;
 
         let cr = Self {
             generic_cr,
-            http_client,
-            registry_type: repository_type,
+            packages_api,
         };
 
         Ok(cr)
@@ -139,8 +301,39 @@ impl ContainerRegistry for GithubCr {
         image_retention_time_in_seconds: u32,
         registry_tags: RegistryTags,
     ) -> Result<(Repository, RepositoryInfo), ContainerRegistryError> {
-        self.generic_cr
-            .create_repository(name, image_retention_time_in_seconds, registry_tags)
+        // GHCR creates the repository on the first push, there is only the retention policy to apply
+        let (mut repository, repository_info) =
+            self.generic_cr
+                .create_repository(name, image_retention_time_in_seconds, registry_tags)?;
+        if image_retention_time_in_seconds == 0 {
+            return Ok((repository, repository_info));
+        }
+
+        let retention_time = Duration::from_secs(image_retention_time_in_seconds as u64);
+        match self
+            .packages_api
+            .delete_untagged_versions_older_than(package_name(name), retention_time, Utc::now())
+        {
+            Ok(0) => {}
+            Ok(deleted_versions) => info!(
+                "Deleted {} untagged versions of repository {} older than the retention time",
+                deleted_versions, name
+            ),
+            // it will be applied again on the next build, it must not prevent it
+            Err(err) => warn!(
+                "{}",
+                to_container_registry_error(err, |raw_error_message| {
+                    ContainerRegistryError::CannotSetRepositoryLifecyclePolicy {
+                        registry_name: self.name().to_string(),
+                        repository_name: name.to_string(),
+                        raw_error_message,
+                    }
+                })
+            ),
+        }
+        repository.ttl = Some(retention_time);
+
+        Ok((repository, repository_info))
     }
 
     fn get_repository(&self, repository_name: &str) -> Result<Repository, ContainerRegistryError> {
@@ -148,35 +341,14 @@ impl ContainerRegistry for GithubCr {
     }
 
     fn delete_repository(&self, repository_name: &str) -> Result<(), ContainerRegistryError> {
-        // Github api does not want the user prefix. i.e: qovery/engine -> engine
-        let repository_name = if let Some((_, repo)) = repository_name.split_once('/') {
-            repo
-        } else {
-            repository_name
-        };
-
-        // https://api.github.com/user/packages/container/
-        // https://api.github.com/orgs/ORG/packages/container/PACKAGE_NAME
-        let api_url = match &self.registry_type {
-            RegistryType::User(_) => format!("https://api.github.com/user/packages/container/{}", repository_name),
-            RegistryType::Organization(org) => {
-                format!("https://api.github.com/orgs/{}/packages/container/{}", org, repository_name)
-            }
-        };
-        match self
-            .http_client
-            .delete(api_url)
-            .send()
-            .map(|res| res.error_for_status())
-        {
-            Ok(_) => Ok(()),
-            Err(err) if matches!(err.status(), Some(reqwest::StatusCode::NOT_FOUND)) => Ok(()),
-            Err(err) => Err(ContainerRegistryError::CannotDeleteRepository {
+        let repository_name = package_name(repository_name);
+        self.packages_api.delete_package(repository_name).map_err(|err| {
+            to_container_registry_error(err, |raw_error_message| ContainerRegistryError::CannotDeleteRepository {
                 registry_name: self.name().to_string(),
                 repository_name: repository_name.to_string(),
-                raw_error_message: err.to_string(),
-            }),
-        }
+                raw_error_message,
+            })
+        })
     }
 
     fn delete_image(&self, image: &Image) -> Result<(), ContainerRegistryError> {
@@ -187,73 +359,12 @@ impl ContainerRegistry for GithubCr {
             raw_error_message,
         };
 
-        #[derive(Default, Deserialize)]
-        struct ImageVersion {
-            id: u64,
-            name: String, // the digest, start with sha256:
-            metadata: ImageMetadata,
-        }
-        #[derive(Default, Deserialize)]
-        struct ImageMetadata {
-            container: ImageContainer,
-        }
-        #[derive(Default, Deserialize)]
-        struct ImageContainer {
-            tags: Vec<String>,
-        }
-
-        fn list_versions(this: &GithubCr, repository_name: &str) -> reqwest::Result<Vec<ImageVersion>> {
-            let api_url = match &this.registry_type {
-                RegistryType::User(_) => {
-                    format!("https://api.github.com/user/packages/container/{}/versions", repository_name)
-                }
-                RegistryType::Organization(org) => format!(
-                    "https://api.github.com/orgs/{}/packages/container/{}/versions",
-                    org, repository_name
-                ),
-            };
-
-            match this
-                .http_client
-                .get(api_url)
-                .send()
-                .and_then(|res| res.error_for_status())
-            {
-                Ok(res) => Ok(res.json().unwrap_or_default()),
-                Err(err) if matches!(err.status(), Some(reqwest::StatusCode::NOT_FOUND)) => Ok(vec![]),
-                Err(err) => Err(err),
-            }
-        }
-
-        fn delete_version(this: &GithubCr, repository_name: &str, version_id: u64) -> reqwest::Result<()> {
-            // https://docs.github.com/en/rest/packages/packages?apiVersion=2022-11-28#delete-package-version-for-an-organization
-            // https://docs.github.com/en/rest/packages/packages?apiVersion=2022-11-28#delete-a-package-version-for-the-authenticated-user
-            let api_url = match &this.registry_type {
-                RegistryType::User(_) => format!(
-                    "https://api.github.com/user/packages/container/{}/versions/{}",
-                    repository_name, version_id
-                ),
-                RegistryType::Organization(org) => format!(
-                    "https://api.github.com/orgs/{}/packages/container/{}/versions/{}",
-                    org, repository_name, version_id
-                ),
-            };
-
-            match this
-                .http_client
-                .delete(api_url)
-                .send()
-                .and_then(|res| res.error_for_status())
-            {
-                Ok(_res) => Ok(()),
-                Err(err) if matches!(err.status(), Some(reqwest::StatusCode::NOT_FOUND)) => Ok(()),
-                Err(err) => Err(err),
-            }
-        }
-
         // list all versions/digest for this image to get the version id
         // Github has its own version/id system for layers, they don't use the sha256 digest for that.
-        let versions = list_versions(self, image.name_without_repository()).map_err(|e| to_error(e.to_string()))?;
+        let versions = self
+            .packages_api
+            .list_versions(image.name_without_repository())
+            .map_err(|e| to_container_registry_error(e, to_error))?;
 
         // Github forbid to delete the last tag of an image, in this case you must delete the repository itself.
         let tags = versions
@@ -279,7 +390,9 @@ impl ContainerRegistry for GithubCr {
             .map_err(|e| to_error(e.to_string()))?;
 
         for digest in versions.iter().filter(|v| image_digests.contains(&v.name)) {
-            let _ = delete_version(self, image.name_without_repository(), digest.id);
+            let _ = self
+                .packages_api
+                .delete_version(image.name_without_repository(), digest.id);
         }
 
         Ok(())
@@ -289,3 +402,166 @@ impl ContainerRegistry for GithubCr {
         self.generic_cr.image_exists(image)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_utils::MockServer;
+
+    fn organization_or(method: &str, target: &str, f: impl Fn(&str, &str) -> (u16, String)) -> (u16, String) {
+        if method == "GET" && target == "/users/qovery" {
+            return (200, r#"{"login":"qovery","type":"Organization"}"#.to_string());
+        }
+        f(method, target)
+    }
+
+    fn version(id: u64, tags: &[&str], created_at: &str) -> String {
+        format!(
+            r#"{{"id":{id},"name":"sha256:{id}","created_at":"{created_at}","metadata":{{"package_type":"container","container":{{"tags":{}}}}}}}"#,
+            serde_json::to_string(tags).unwrap()
+        )
+    }
+
+    #[test]
+    fn test_registry_type() {
+        // setup:
+        let server = MockServer::start(Box::new(|_method: &str, target: &str| match target {
+            "/users/qovery" => (200, r#"{"login":"qovery","type":"Organization"}"#.to_string()),
+            "/users/erebe" => (200, r#"{"login":"erebe","type":"User"}"#.to_string()),
+            "/users/revoked" => (401, r#"{"message":"Bad credentials"}"#.to_string()),
+            _ => (500, "{}".to_string()),
+        }));
+
+        // execute & verify:
+        let registry_type = |username: &str| {
+            GithubPackagesApi::new("token", server.url.clone(), username.to_string()).map(|api| api.registry_type)
+        };
+        assert_eq!(registry_type("qovery"), Ok(RegistryType::Organization("qovery".to_string())));
+        assert_eq!(registry_type("erebe"), Ok(RegistryType::User("erebe".to_string())));
+        assert_eq!(registry_type("revoked"), Err(ContainerRegistryError::InvalidCredentials));
+        // an unknown account type must not prevent using the registry
+        assert_eq!(registry_type("unavailable"), Ok(RegistryType::User("unavailable".to_string())));
+    }
+
+    #[test]
+    fn test_package_url() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, target: &str| {
+            organization_or(method, target, |_, _| (200, r#"{"type":"User"}"#.to_string()))
+        }));
+        let organization = GithubPackagesApi::new("token", server.url.clone(), "qovery".to_string()).unwrap();
+        let user = GithubPackagesApi::new("token", server.url.clone(), "erebe".to_string()).unwrap();
+
+        // verify:
+        assert_eq!(
+            organization.package_url("engine", &["versions", "42"]).path(),
+            "/orgs/qovery/packages/container/engine/versions/42"
+        );
+        assert_eq!(user.package_url("engine", &[]).path(), "/user/packages/container/engine");
+        assert_eq!(
+            user.package_url("qovery/engine", &[]).path(),
+            "/user/packages/container/qovery%2Fengine"
+        );
+    }
+
+    #[test]
+    fn test_list_versions_follows_pages() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, target: &str| {
+            organization_or(method, target, |_, target| match target {
+                "/orgs/qovery/packages/container/engine/versions?per_page=100&page=1" => {
+                    let versions = (0..100).map(|id| version(id, &[], "2024-01-01T00:00:00Z")).join(",");
+                    (200, format!("[{versions}]"))
+                }
+                "/orgs/qovery/packages/container/engine/versions?per_page=100&page=2" => {
+                    (200, format!("[{}]", version(100, &["v1"], "2024-01-01T00:00:00Z")))
+                }
+                _ => (404, r#"{"message":"Package not found."}"#.to_string()),
+            })
+        }));
+        let api = GithubPackagesApi::new("token", server.url.clone(), "qovery".to_string()).unwrap();
+
+        // execute & verify:
+        let versions = api.list_versions("engine").unwrap();
+        assert_eq!(versions.len(), 101);
+        assert_eq!(versions[100].metadata.container.tags, vec!["v1".to_string()]);
+
+        // a package not pushed yet has no version
+        assert!(api.list_versions("not-pushed").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_untagged_versions_older_than() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, target: &str| {
+            organization_or(method, target, |method, target| match (method, target) {
+                ("GET", "/orgs/qovery/packages/container/engine/versions?per_page=100&page=1") => {
+                    let versions = [
+                        version(1, &["v1"], "2024-01-01T00:00:00Z"),
+                        version(2, &[], "2024-01-01T00:00:00Z"),
+                        version(3, &[], "2024-01-09T00:00:00Z"),
+                    ];
+                    (200, format!("[{}]", versions.join(",")))
+                }
+                ("DELETE", _) => (204, "".to_string()),
+                _ => (500, "{}".to_string()),
+            })
+        }));
+        let api = GithubPackagesApi::new("token", server.url.clone(), "qovery".to_string()).unwrap();
+        let now = DateTime::parse_from_rfc3339("2024-01-10T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        // execute:
+        let deleted_versions = api
+            .delete_untagged_versions_older_than("engine", Duration::from_secs(7 * 86400), now)
+            .unwrap();
+
+        // verify: tagged versions are kept whatever their age
+        assert_eq!(deleted_versions, 1);
+        let deletions = server
+            .calls()
+            .into_iter()
+            .filter(|(method, _, _)| method == "DELETE")
+            .map(|(_, target, _)| target)
+            .collect_vec();
+        assert_eq!(deletions, vec!["/orgs/qovery/packages/container/engine/versions/2"]);
+    }
+
+    #[test]
+    fn test_delete_errors() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, target: &str| {
+            organization_or(method, target, |_, target| match target {
+                "/orgs/qovery/packages/container/deleted" => (404, r#"{"message":"Package not found."}"#.to_string()),
+                "/orgs/qovery/packages/container/forbidden" => (403, r#"{"message":"Forbidden"}"#.to_string()),
+                _ => (401, r#"{"message":"Bad credentials"}"#.to_string()),
+            })
+        }));
+        let api = GithubPackagesApi::new("token", server.url.clone(), "qovery".to_string()).unwrap();
+        let to_error = |raw_error_message: String| ContainerRegistryError::CannotDeleteRepository {
+            registry_name: "ghcr".to_string(),
+            repository_name: "engine".to_string(),
+            raw_error_message,
+        };
+
+        // execute & verify:
+        assert!(api.delete_package("deleted").is_ok());
+        assert!(matches!(
+            api.delete_package("forbidden")
+                .map_err(|err| to_container_registry_error(err, to_error)),
+            Err(ContainerRegistryError::CannotDeleteRepository { .. })
+        ));
+        assert_eq!(
+            api.delete_version("expired", 42)
+                .map_err(|err| to_container_registry_error(err, to_error)),
+            Err(ContainerRegistryError::InvalidCredentials)
+        );
+    }
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(package_name("qovery/z1234-engine"), "z1234-engine");
+        assert_eq!(package_name("engine"), "engine");
+    }
+}
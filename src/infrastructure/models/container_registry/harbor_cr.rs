@@ -0,0 +1,871 @@
+use super::RegistryTags;
+use crate::infrastructure::models::build_platform::Image;
+use crate::infrastructure::models::container_registry::errors::ContainerRegistryError;
+use crate::infrastructure::models::container_registry::{
+    take_last_x_chars_and_remove_leading_dash_char, ContainerRegistry, ContainerRegistryInfo, Kind, Repository,
+    RepositoryInfo,
+};
+use crate::io_models::context::Context;
+use base64::engine::general_purpose;
+use base64::Engine;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT};
+use reqwest::StatusCode;
+use serde_derive::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use url::Url;
+use uuid::Uuid;
+
+const HARBOR_API_PATH: [&str; 2] = ["api", "v2.0"];
+// max page size of the harbor api
+const REPOSITORIES_PER_PAGE: usize = 100;
+// Repository of the images inside the project, the project being our repository
+const IMAGE_NAME: &str = "built-by-qovery";
+
+pub struct HarborCr {
+    context: Context,
+    long_id: Uuid,
+    name: String,
+    api: HarborApi,
+    registry_info: ContainerRegistryInfo,
+}
+
+#[derive(Default, Deserialize)]
+struct HarborErrors {
+    errors: Vec<HarborErrorMessage>,
+}
+
+#[derive(Default, Deserialize)]
+struct HarborErrorMessage {
+    code: String,
+    message: String,
+}
+
+#[derive(Default, Deserialize)]
+struct HarborProject {
+    project_id: u64,
+    name: String,
+    #[serde(default)]
+    metadata: HarborProjectMetadata,
+}
+
+#[derive(Default, Deserialize)]
+struct HarborProjectMetadata {
+    retention_id: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct HarborRepository {
+    name: String, // prefixed by the project name, i.e: my-project/built-by-qovery
+}
+
+#[derive(Default, Deserialize)]
+struct HarborRobot {
+    id: u64,
+    name: String, // prefixed by harbor, i.e: robot$qovery-xxx
+    #[serde(default)]
+    secret: Option<String>,
+}
+
+#[derive(Debug)]
+enum HarborApiError {
+    Http(reqwest::Error),
+    Status { status: StatusCode, message: String },
+}
+
+impl HarborApiError {
+    fn status(&self) -> Option<StatusCode> {
+        match self {
+            HarborApiError::Http(err) => err.status(),
+            HarborApiError::Status { status, .. } => Some(*status),
+        }
+    }
+
+    fn is_quota_exceeded(&self) -> bool {
+        match self {
+            HarborApiError::Http(_) => false,
+            HarborApiError::Status { message, .. } => message.to_lowercase().contains("quota"),
+        }
+    }
+}
+
+impl Display for HarborApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HarborApiError::Http(err) => write!(f, "{}", err),
+            HarborApiError::Status { status, message } => write!(f, "harbor api answered {}: {}", status, message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for HarborApiError {
+    fn from(err: reqwest::Error) -> Self {
+        HarborApiError::Http(err)
+    }
+}
+
+/// Client of the Harbor REST API v2.
+/// Harbor projects are our repositories, images are pushed inside them under a single repository.
+struct HarborApi {
+    http_client: reqwest::blocking::Client,
+    api_url: Url,
+    username: String,
+    password: String,
+}
+
+impl HarborApi {
+    /// `ca_bundle` is the PEM bundle of the CA having signed the certificate of a self-hosted Harbor
+    fn new(
+        url: &Url,
+        username: String,
+        password: String,
+        ca_bundle: Option<&str>,
+    ) -> Result<Self, ContainerRegistryError> {
+        let mut api_url = url.clone();
+        api_url
+            .path_segments_mut()
+            .map_err(|_| ContainerRegistryError::InvalidRegistryUrl {
+                registry_url: url.to_string(),
+            })?
+            .pop_if_empty()
+            .extend(HARBOR_API_PATH);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        // project names can be all digits, they must not be taken for project ids
+        headers.insert("X-Is-Resource-Name", HeaderValue::from_static("true"));
+        let mut http_client = reqwest::blocking::Client::builder()
+            .default_headers(headers)
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(30));
+        if let Some(ca_bundle) = ca_bundle {
+            let certificates = reqwest::Certificate::from_pem_bundle(ca_bundle.as_bytes()).map_err(|e| {
+                ContainerRegistryError::CannotInstantiateClient {
+                    raw_error_message: format!("Invalid CA bundle: {}", e),
+                }
+            })?;
+            for certificate in certificates {
+                http_client = http_client.add_root_certificate(certificate);
+            }
+        }
+        let http_client = http_client
+            .build()
+            .map_err(|e| ContainerRegistryError::CannotInstantiateClient {
+                raw_error_message: format!("Cannot create http client: {}", e),
+            })?;
+
+        Ok(HarborApi {
+            http_client,
+            api_url,
+            username,
+            password,
+        })
+    }
+
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.api_url.clone();
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.extend(segments);
+        }
+
+        url
+    }
+
+    // https://.../api/v2.0/projects/PROJECT/repositories/REPOSITORY
+    fn repository_url(&self, project_name: &str, repository_name: &str, segments: &[&str]) -> Url {
+        // nested repository names must be encoded twice, i.e: qovery/engine -> qovery%252Fengine
+        // the path segment encoding does the second one
+        let repository_name = repository_name.replace('/', "%2F");
+        let mut url = self.url(&["projects", project_name, "repositories", &repository_name]);
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.extend(segments);
+        }
+
+        url
+    }
+
+    fn send(&self, request: RequestBuilder) -> Result<Response, HarborApiError> {
+        let response = request.basic_auth(&self.username, Some(&self.password)).send()?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().unwrap_or_default();
+        let message = match serde_json::from_str::<HarborErrors>(&body) {
+            Ok(errors) if !errors.errors.is_empty() => errors
+                .errors
+                .iter()
+                .map(|err| format!("{}: {}", err.code, err.message))
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => body,
+        };
+
+        Err(HarborApiError::Status { status, message })
+    }
+
+    fn get_project(&self, project_name: &str) -> Result<Option<HarborProject>, HarborApiError> {
+        match self.send(self.http_client.get(self.url(&["projects", project_name]))) {
+            Ok(res) => Ok(Some(res.json()?)),
+            Err(err) if err.status() == Some(StatusCode::NOT_FOUND) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns true if the project has been created, false if it already exists
+    fn create_project(&self, project_name: &str) -> Result<bool, HarborApiError> {
+        let body = json!({
+            "project_name": project_name,
+            "metadata": { "public": "false" },
+        });
+        match self.send(self.http_client.post(self.url(&["projects"])).json(&body)) {
+            Ok(_) => Ok(true),
+            Err(err) if err.status() == Some(StatusCode::CONFLICT) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn list_repositories(&self, project_name: &str) -> Result<Vec<HarborRepository>, HarborApiError> {
+        let mut repositories = vec![];
+        for page in 1.. {
+            let mut url = self.url(&["projects", project_name, "repositories"]);
+            url.query_pairs_mut()
+                .append_pair("page_size", &REPOSITORIES_PER_PAGE.to_string())
+                .append_pair("page", &page.to_string());
+
+            let page_repositories: Vec<HarborRepository> = match self.send(self.http_client.get(url)) {
+                Ok(res) => res.json()?,
+                Err(err) if err.status() == Some(StatusCode::NOT_FOUND) => vec![],
+                Err(err) => return Err(err),
+            };
+
+            let is_last_page = page_repositories.len() < REPOSITORIES_PER_PAGE;
+            repositories.extend(page_repositories);
+            if is_last_page {
+                break;
+            }
+        }
+
+        Ok(repositories)
+    }
+
+    /// Harbor refuses to delete a project which still has repositories, they are deleted first
+    fn delete_project(&self, project_name: &str) -> Result<(), HarborApiError> {
+        for repository in self.list_repositories(project_name)? {
+            let repository_name = repository
+                .name
+                .strip_prefix(&format!("{}/", project_name))
+                .unwrap_or(&repository.name);
+            self.delete(self.repository_url(project_name, repository_name, &[]))?;
+        }
+
+        self.delete(self.url(&["projects", project_name]))
+    }
+
+    // The reference is either a tag or a digest, deleting an artifact deletes all its tags
+    fn artifact_exists(
+        &self,
+        project_name: &str,
+        repository_name: &str,
+        reference: &str,
+    ) -> Result<bool, HarborApiError> {
+        let url = self.repository_url(project_name, repository_name, &["artifacts", reference]);
+        match self.send(self.http_client.get(url)) {
+            Ok(_) => Ok(true),
+            Err(err) if err.status() == Some(StatusCode::NOT_FOUND) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn delete_artifact(
+        &self,
+        project_name: &str,
+        repository_name: &str,
+        reference: &str,
+    ) -> Result<(), HarborApiError> {
+        self.delete(self.repository_url(project_name, repository_name, &["artifacts", reference]))
+    }
+
+    fn delete(&self, url: Url) -> Result<(), HarborApiError> {
+        match self.send(self.http_client.delete(url)) {
+            Ok(_) => Ok(()),
+            Err(err) if err.status() == Some(StatusCode::NOT_FOUND) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Retains the artifacts of the project pushed during the retention time, the others are deleted daily by harbor
+    fn set_retention_policy(&self, project: &HarborProject, retention_time: Duration) -> Result<(), HarborApiError> {
+        let body = retention_policy(project.project_id, retention_time);
+        let request = match &project.metadata.retention_id {
+            Some(retention_id) => self.http_client.put(self.url(&["retentions", retention_id])),
+            None => self.http_client.post(self.url(&["retentions"])),
+        };
+
+        self.send(request.json(&body)).map(|_| ())
+    }
+
+    /// Returns the credentials of the system robot account allowed to push and pull in all projects.
+    /// Harbor only returns the secret of a robot at its creation, so its secret is derived from our own credentials
+    /// and set again when the robot already exists: the secret stays the same across deployments, and the pull
+    /// secrets of the services already deployed remain valid.
+    fn robot_account(&self, robot_name: &str) -> Result<(String, String), HarborApiError> {
+        let secret = robot_secret(&self.password, robot_name);
+        let mut url = self.url(&["robots"]);
+        url.query_pairs_mut().append_pair("q", &format!("name=~{}", robot_name));
+        let robots: Vec<HarborRobot> = self.send(self.http_client.get(url))?.json()?;
+
+        // the robot name is prefixed by harbor, the prefix being configurable
+        match robots.into_iter().find(|robot| robot.name.ends_with(robot_name)) {
+            Some(robot) => {
+                self.send(
+                    self.http_client
+                        .patch(self.url(&["robots", &robot.id.to_string()]))
+                        .json(&json!({ "secret": secret })),
+                )?;
+                Ok((robot.name, secret))
+            }
+            None => {
+                let body = json!({
+                    "name": robot_name,
+                    "description": "Push and pull images built by Qovery",
+                    "level": "system",
+                    "duration": -1,
+                    "secret": secret,
+                    "permissions": [{
+                        "kind": "project",
+                        "namespace": "*",
+                        "access": [
+                            { "resource": "repository", "action": "push" },
+                            { "resource": "repository", "action": "pull" },
+                            { "resource": "artifact", "action": "delete" },
+                        ],
+                    }],
+                });
+                let robot: HarborRobot = self
+                    .send(self.http_client.post(self.url(&["robots"])).json(&body))?
+                    .json()?;
+                // older harbor versions generate the secret themselves
+                Ok((robot.name, robot.secret.unwrap_or(secret)))
+            }
+        }
+    }
+}
+
+fn retention_policy(project_id: u64, retention_time: Duration) -> serde_json::Value {
+    let retention_days = retention_time.as_secs().div_ceil(86400).max(1);
+    json!({
+        "algorithm": "or",
+        "rules": [{
+            "action": "retain",
+            "template": "nDaysSinceLastPush",
+            "params": { "nDaysSinceLastPush": retention_days },
+            "tag_selectors": [{ "kind": "doublestar", "decoration": "matches", "pattern": "**" }],
+            "scope_selectors": {
+                "repository": [{ "kind": "doublestar", "decoration": "repoMatches", "pattern": "**" }],
+            },
+        }],
+        "trigger": { "kind": "Schedule", "settings": { "cron": "0 0 0 * * *" } },
+        "scope": { "level": "project", "ref": project_id },
+    })
+}
+
+// Harbor requires at least one upper case letter, one lower case letter and one digit
+fn robot_secret(password: &str, robot_name: &str) -> String {
+    format!("Q{:x}", Sha256::digest(format!("{}:{}", robot_name, password)))
+}
+
+// The images are named <project>/<repository>, i.e: z1234-my-app/built-by-qovery
+fn project_and_repository(image_name: &str) -> (&str, &str) {
+    image_name.split_once('/').unwrap_or((image_name, image_name))
+}
+
+fn to_container_registry_error(
+    err: HarborApiError,
+    to_error: impl FnOnce(String) -> ContainerRegistryError,
+) -> ContainerRegistryError {
+    if err.status() == Some(StatusCode::UNAUTHORIZED) {
+        return ContainerRegistryError::InvalidCredentials;
+    }
+    if err.is_quota_exceeded() {
+        return ContainerRegistryError::Unknown {
+            raw_error_message: format!(
+                "Harbor quota exceeded, increase the quota of the project or of the registry: {}",
+                err
+            ),
+        };
+    }
+
+    to_error(err.to_string())
+}
+
+impl HarborCr {
+    pub fn new(
+        context: Context,
+        long_id: Uuid,
+        name: &str,
+        url: Url,
+        username: String,
+        password: String,
+        ca_bundle: Option<String>,
+    ) -> Result<Self, ContainerRegistryError> {
+        let api = HarborApi::new(&url, username, password, ca_bundle.as_deref())?;
+        let (login, secret) = api
+            .robot_account(&format!("qovery-{}", long_id))
+            .map_err(|err| to_container_registry_error(err, |_| ContainerRegistryError::CannotGetCredentials))?;
+
+        // The docker daemon must trust the CA bundle of a self-signed harbor as well, i.e: in /etc/docker/certs.d
+        let mut registry = url.clone();
+        let _ = registry.set_username(&login);
+        let _ = registry.set_password(Some(&secret));
+        if context.docker.login(&registry).is_err() {
+            return Err(ContainerRegistryError::InvalidCredentials);
+        }
+
+        const MAX_REGISTRY_NAME_LENGTH: usize = 200; // 255 (Harbor project name limit) - 55 (prefix)
+        let registry_info = ContainerRegistryInfo {
+            endpoint: registry,
+            registry_name: name.to_string(),
+            registry_docker_json_config: Some(Self::get_docker_json_config_raw(&url, &login, &secret)),
+            insecure_registry: false,
+            get_shared_image_name: Box::new(|image_build_context| {
+                let git_repo_truncated: String = take_last_x_chars_and_remove_leading_dash_char(
+                    image_build_context.git_repo_url_sanitized.as_str(),
+                    MAX_REGISTRY_NAME_LENGTH,
+                );
+                format!(
+                    "{}-{}/{}",
+                    image_build_context.cluster_id.short(),
+                    git_repo_truncated,
+                    IMAGE_NAME
+                )
+            }),
+            get_image_name: Box::new(move |img_name| format!("{img_name}/{img_name}")),
+            get_shared_repository_name: Box::new(|image_build_context| {
+                let git_repo_truncated: String = take_last_x_chars_and_remove_leading_dash_char(
+                    image_build_context.git_repo_url_sanitized.as_str(),
+                    MAX_REGISTRY_NAME_LENGTH,
+                );
+                format!("{}-{}", image_build_context.cluster_id.short(), git_repo_truncated)
+            }),
+            get_repository_name: Box::new(|repository_name| repository_name.to_string()),
+        };
+
+        Ok(HarborCr {
+            context,
+            long_id,
+            name: name.to_string(),
+            api,
+            registry_info,
+        })
+    }
+
+    fn get_docker_json_config_raw(url: &Url, login: &str, secret: &str) -> String {
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        general_purpose::STANDARD.encode(
+            format!(
+                r#"{{"auths":{{"{}":{{"auth":"{}"}}}}}}"#,
+                host,
+                general_purpose::STANDARD.encode(format!("{login}:{secret}").as_bytes())
+            )
+            .as_bytes(),
+        )
+    }
+
+    fn repository(&self, project_name: &str, ttl: Option<Duration>) -> Repository {
+        Repository {
+            registry_id: project_name.to_string(),
+            name: project_name.to_string(),
+            uri: Some(format!(
+                "{}/{}",
+                self.registry_info.endpoint.host_str().unwrap_or_default(),
+                project_name
+            )),
+            ttl,
+            labels: None,
+        }
+    }
+}
+
+impl ContainerRegistry for HarborCr {
+    fn context(&self) -> &Context {
+        &self.context
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::HarborCr
+    }
+
+    fn long_id(&self) -> &Uuid {
+        &self.long_id
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn registry_info(&self) -> &ContainerRegistryInfo {
+        &self.registry_info
+    }
+
+    fn create_repository(
+        &self,
+        name: &str,
+        image_retention_time_in_seconds: u32,
+        _registry_tags: RegistryTags,
+    ) -> Result<(Repository, RepositoryInfo), ContainerRegistryError> {
+        let to_error = |raw_error_message: String| ContainerRegistryError::CannotCreateRepository {
+            registry_name: self.name.to_string(),
+            repository_name: name.to_string(),
+            raw_error_message,
+        };
+
+        let created = self
+            .api
+            .create_project(name)
+            .map_err(|err| to_container_registry_error(err, to_error))?;
+        if image_retention_time_in_seconds == 0 {
+            return Ok((self.repository(name, None), RepositoryInfo { created }));
+        }
+
+        let retention_time = Duration::from_secs(image_retention_time_in_seconds as u64);
+        let to_error = |raw_error_message: String| ContainerRegistryError::CannotSetRepositoryLifecyclePolicy {
+            registry_name: self.name.to_string(),
+            repository_name: name.to_string(),
+            raw_error_message,
+        };
+        let project = self
+            .api
+            .get_project(name)
+            .map_err(|err| to_container_registry_error(err, to_error))?
+            .ok_or_else(|| to_error(format!("project {} not found after its creation", name)))?;
+        self.api
+            .set_retention_policy(&project, retention_time)
+            .map_err(|err| to_container_registry_error(err, to_error))?;
+
+        Ok((self.repository(&project.name, Some(retention_time)), RepositoryInfo { created }))
+    }
+
+    fn get_repository(&self, repository_name: &str) -> Result<Repository, ContainerRegistryError> {
+        let project = self
+            .api
+            .get_project(repository_name)
+            .map_err(|err| {
+                to_container_registry_error(err, |raw_error_message| ContainerRegistryError::CannotGetRepository {
+                    registry_name: self.name.to_string(),
+                    repository_name: repository_name.to_string(),
+                    raw_error_message,
+                })
+            })?
+            .ok_or_else(|| ContainerRegistryError::RepositoryDoesntExistInRegistry {
+                registry_name: self.name.to_string(),
+                repository_name: repository_name.to_string(),
+            })?;
+
+        Ok(self.repository(&project.name, None))
+    }
+
+    fn delete_repository(&self, repository_name: &str) -> Result<(), ContainerRegistryError> {
+        self.api.delete_project(repository_name).map_err(|err| {
+            to_container_registry_error(err, |raw_error_message| ContainerRegistryError::CannotDeleteRepository {
+                registry_name: self.name.to_string(),
+                repository_name: repository_name.to_string(),
+                raw_error_message,
+            })
+        })
+    }
+
+    fn delete_image(&self, image: &Image) -> Result<(), ContainerRegistryError> {
+        let (project_name, repository_name) = project_and_repository(&image.name);
+        self.api
+            .delete_artifact(project_name, repository_name, &image.tag)
+            .map_err(|err| {
+                to_container_registry_error(err, |raw_error_message| ContainerRegistryError::CannotDeleteImage {
+                    registry_name: self.name.to_string(),
+                    repository_name: image.repository_name().to_string(),
+                    image_name: image.name().to_string(),
+                    raw_error_message,
+                })
+            })
+    }
+
+    fn image_exists(&self, image: &Image) -> bool {
+        let (project_name, repository_name) = project_and_repository(&image.name);
+        matches!(self.api.artifact_exists(project_name, repository_name, &image.tag), Ok(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_utils::MockServer;
+
+    fn harbor_api(server: &MockServer) -> HarborApi {
+        HarborApi::new(&server.url, "admin".to_string(), "Harbor12345".to_string(), None).unwrap()
+    }
+
+    fn harbor_error(code: &str, message: &str) -> String {
+        format!(r#"{{"errors":[{{"code":"{code}","message":"{message}"}}]}}"#)
+    }
+
+    #[test]
+    fn test_api_urls() {
+        // setup:
+        let api = HarborApi::new(
+            &Url::parse("https://harbor.lan/").unwrap(),
+            "admin".to_string(),
+            "Harbor12345".to_string(),
+            None,
+        )
+        .unwrap();
+
+        // verify:
+        assert_eq!(
+            api.url(&["projects", "z1234-my-app"]).as_str(),
+            "https://harbor.lan/api/v2.0/projects/z1234-my-app"
+        );
+        assert_eq!(
+            api.repository_url("z1234-my-app", "built-by-qovery", &["artifacts", "v1"])
+                .as_str(),
+            "https://harbor.lan/api/v2.0/projects/z1234-my-app/repositories/built-by-qovery/artifacts/v1"
+        );
+        assert_eq!(
+            api.repository_url("z1234-my-app", "qovery/engine", &[]).as_str(),
+            "https://harbor.lan/api/v2.0/projects/z1234-my-app/repositories/qovery%252Fengine"
+        );
+        assert_eq!(
+            project_and_repository("z1234-my-app/built-by-qovery"),
+            ("z1234-my-app", "built-by-qovery")
+        );
+        assert_eq!(project_and_repository("z1234"), ("z1234", "z1234"));
+    }
+
+    #[test]
+    fn test_invalid_ca_bundle() {
+        let api = HarborApi::new(
+            &Url::parse("https://harbor.lan").unwrap(),
+            "admin".to_string(),
+            "Harbor12345".to_string(),
+            Some("-----BEGIN CERTIFICATE-----\nnot a certificate\n-----END CERTIFICATE-----\n"),
+        );
+
+        assert!(matches!(api, Err(ContainerRegistryError::CannotInstantiateClient { .. })));
+    }
+
+    #[test]
+    fn test_create_project() {
+        // setup:
+        let server = MockServer::start(Box::new(|_method: &str, _target: &str| (201, "".to_string())));
+        let existing_server = MockServer::start(Box::new(|_method: &str, _target: &str| {
+            (409, harbor_error("CONFLICT", "The project named z1234-my-app already exists"))
+        }));
+
+        // execute & verify:
+        assert!(harbor_api(&server).create_project("z1234-my-app").unwrap());
+        assert!(!harbor_api(&existing_server).create_project("z1234-my-app").unwrap());
+        let calls = server.calls();
+        assert_eq!(calls[0].0, "POST");
+        assert_eq!(calls[0].1, "/api/v2.0/projects");
+        let body: serde_json::Value = serde_json::from_str(&calls[0].2).unwrap();
+        assert_eq!(body["project_name"], "z1234-my-app");
+    }
+
+    #[test]
+    fn test_error_mapping() {
+        // setup:
+        let server = MockServer::start(Box::new(|_method: &str, target: &str| match target {
+            "/api/v2.0/projects/unauthorized" => (401, harbor_error("UNAUTHORIZED", "unauthorized")),
+            "/api/v2.0/projects/quota" => (
+                403,
+                harbor_error(
+                    "FORBIDDEN",
+                    "adding 10.0 MiB of storage will exceed the limit of 10.0 GiB. quota exceeded",
+                ),
+            ),
+            "/api/v2.0/projects/not-json" => (502, "Bad Gateway".to_string()),
+            _ => (500, harbor_error("UNKNOWN", "internal server error")),
+        }));
+        let api = harbor_api(&server);
+        let to_error = |project: &str| {
+            let err = api.get_project(project).err().unwrap();
+            to_container_registry_error(err, |raw_error_message| ContainerRegistryError::CannotGetRepository {
+                registry_name: "harbor".to_string(),
+                repository_name: project.to_string(),
+                raw_error_message,
+            })
+        };
+
+        // execute & verify:
+        assert_eq!(to_error("unauthorized"), ContainerRegistryError::InvalidCredentials);
+        match to_error("quota") {
+            ContainerRegistryError::Unknown { raw_error_message } => {
+                assert!(raw_error_message.starts_with("Harbor quota exceeded"));
+                assert!(raw_error_message.contains("FORBIDDEN: adding 10.0 MiB of storage will exceed"));
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+        assert_eq!(
+            to_error("not-json"),
+            ContainerRegistryError::CannotGetRepository {
+                registry_name: "harbor".to_string(),
+                repository_name: "not-json".to_string(),
+                raw_error_message: "harbor api answered 502 Bad Gateway: Bad Gateway".to_string(),
+            }
+        );
+        assert_eq!(
+            to_error("broken"),
+            ContainerRegistryError::CannotGetRepository {
+                registry_name: "harbor".to_string(),
+                repository_name: "broken".to_string(),
+                raw_error_message: "harbor api answered 500 Internal Server Error: UNKNOWN: internal server error"
+                    .to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_project() {
+        // setup:
+        let server = MockServer::start(Box::new(|_method: &str, target: &str| match target {
+            "/api/v2.0/projects/z1234-my-app" => (
+                200,
+                r#"{"project_id":12,"name":"z1234-my-app","metadata":{"public":"false","retention_id":"7"}}"#
+                    .to_string(),
+            ),
+            _ => (404, harbor_error("NOT_FOUND", "project not found")),
+        }));
+        let api = harbor_api(&server);
+
+        // execute & verify:
+        let project = api.get_project("z1234-my-app").unwrap().unwrap();
+        assert_eq!(project.project_id, 12);
+        assert_eq!(project.metadata.retention_id, Some("7".to_string()));
+        assert!(api.get_project("deleted").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_set_retention_policy() {
+        // setup:
+        let server = MockServer::start(Box::new(|_method: &str, _target: &str| (201, "".to_string())));
+        let api = harbor_api(&server);
+        let project = HarborProject {
+            project_id: 12,
+            name: "z1234-my-app".to_string(),
+            metadata: HarborProjectMetadata::default(),
+        };
+        let project_with_policy = HarborProject {
+            metadata: HarborProjectMetadata {
+                retention_id: Some("7".to_string()),
+            },
+            ..HarborProject::default()
+        };
+
+        // execute:
+        api.set_retention_policy(&project, Duration::from_secs(86400 + 1))
+            .unwrap();
+        api.set_retention_policy(&project_with_policy, Duration::from_secs(3600))
+            .unwrap();
+
+        // verify: an existing policy is updated, the retention is rounded up to days
+        let calls = server.calls();
+        assert_eq!((calls[0].0.as_str(), calls[0].1.as_str()), ("POST", "/api/v2.0/retentions"));
+        assert_eq!((calls[1].0.as_str(), calls[1].1.as_str()), ("PUT", "/api/v2.0/retentions/7"));
+        let body: serde_json::Value = serde_json::from_str(&calls[0].2).unwrap();
+        assert_eq!(body["scope"]["ref"], 12);
+        assert_eq!(body["rules"][0]["params"]["nDaysSinceLastPush"], 2);
+        let body: serde_json::Value = serde_json::from_str(&calls[1].2).unwrap();
+        assert_eq!(body["rules"][0]["params"]["nDaysSinceLastPush"], 1);
+    }
+
+    #[test]
+    fn test_robot_account() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, target: &str| match (method, target) {
+            ("GET", "/api/v2.0/robots?q=name%3D%7Eqovery-existing") => {
+                (200, r#"[{"id":3,"name":"robot$qovery-existing"}]"#.to_string())
+            }
+            ("GET", _) => (200, "[]".to_string()),
+            ("POST", "/api/v2.0/robots") => {
+                (201, r#"{"id":4,"name":"robot$qovery-new","secret":"Generated1"}"#.to_string())
+            }
+            ("PATCH", "/api/v2.0/robots/3") => (200, "".to_string()),
+            _ => (500, harbor_error("UNKNOWN", "unexpected call")),
+        }));
+        let api = harbor_api(&server);
+
+        // execute & verify:
+        let existing_secret = robot_secret("Harbor12345", "qovery-existing");
+        assert_eq!(
+            api.robot_account("qovery-existing").unwrap(),
+            ("robot$qovery-existing".to_string(), existing_secret.clone())
+        );
+        assert_eq!(
+            api.robot_account("qovery-new").unwrap(),
+            ("robot$qovery-new".to_string(), "Generated1".to_string())
+        );
+
+        let calls = server.calls();
+        let patch = calls.iter().find(|(method, _, _)| method == "PATCH").unwrap();
+        assert_eq!(patch.2, format!(r#"{{"secret":"{}"}}"#, existing_secret));
+        let post = calls.iter().find(|(method, _, _)| method == "POST").unwrap();
+        let body: serde_json::Value = serde_json::from_str(&post.2).unwrap();
+        assert_eq!(body["name"], "qovery-new");
+        assert_eq!(body["secret"], robot_secret("Harbor12345", "qovery-new"));
+    }
+
+    #[test]
+    fn test_robot_secret() {
+        let secret = robot_secret("Harbor12345", "qovery-existing");
+
+        // stable across deployments, and following harbor password rules
+        assert_eq!(secret, robot_secret("Harbor12345", "qovery-existing"));
+        assert_ne!(secret, robot_secret("Harbor54321", "qovery-existing"));
+        assert!(secret.chars().any(|c| c.is_ascii_uppercase()));
+        assert!(secret.chars().any(|c| c.is_ascii_lowercase()));
+        assert!(secret.chars().any(|c| c.is_ascii_digit()));
+        assert!(secret.len() <= 128);
+    }
+
+    #[test]
+    fn test_delete_project() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, target: &str| match (method, target) {
+            ("GET", "/api/v2.0/projects/z1234-my-app/repositories?page_size=100&page=1") => (
+                200,
+                r#"[{"id":1,"name":"z1234-my-app/built-by-qovery"},{"id":2,"name":"z1234-my-app/cache/layers"}]"#
+                    .to_string(),
+            ),
+            ("DELETE", "/api/v2.0/projects/z1234-my-app/repositories/cache%252Flayers") => {
+                (404, harbor_error("NOT_FOUND", "repository not found"))
+            }
+            ("DELETE", _) => (200, "".to_string()),
+            _ => (500, harbor_error("UNKNOWN", "unexpected call")),
+        }));
+
+        // execute:
+        harbor_api(&server).delete_project("z1234-my-app").unwrap();
+
+        // verify: repositories are deleted before their project
+        let deletions = server
+            .calls()
+            .into_iter()
+            .filter(|(method, _, _)| method == "DELETE")
+            .map(|(_, target, _)| target)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deletions,
+            vec![
+                "/api/v2.0/projects/z1234-my-app/repositories/built-by-qovery",
+                "/api/v2.0/projects/z1234-my-app/repositories/cache%252Flayers",
+                "/api/v2.0/projects/z1234-my-app",
+            ]
+        );
+    }
+}
@@ -12,9 +12,12 @@ use crate::io_models::QoveryIdentifier;
 
 pub mod ecr;
 pub mod errors;
+#[cfg(feature = "failure-injection")]
+pub mod failure_injection;
 pub mod generic_cr;
 pub mod github_cr;
 pub mod google_artifact_registry;
+pub mod harbor_cr;
 pub mod scaleway_container_registry;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -39,6 +42,21 @@ pub struct RegistryTags {
     pub resource_ttl: Option<Duration>,
 }
 
+/// Images purged from the repositories, on top of the retention policy of the registry
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegistryCleanupPolicy {
+    // untagged images (i.e: left by failed builds, or whose tag has been moved) pushed before this ttl
+    pub untagged_image_ttl: Option<Duration>,
+    // oldest tagged images above this count
+    pub max_images_per_repository: Option<usize>,
+}
+
+impl RegistryCleanupPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.untagged_image_ttl.is_some() || self.max_images_per_repository.is_some()
+    }
+}
+
 pub trait ContainerRegistry: Send + Sync {
     fn context(&self) -> &Context;
     fn kind(&self) -> Kind;
@@ -69,6 +87,28 @@ pub trait ContainerRegistry: Send + Sync {
     // Check on the registry if a specific image already exists
     fn image_exists(&self, image: &Image) -> bool;
 
+    // Purge the images of the repository according to the cleanup policy, once an image has been pushed into it.
+    // The image tagged with `deployed_tag` is never deleted. Returns the number of deleted images.
+    // Only ECR supports it, the other registries rely on their retention policy.
+    fn cleanup_repository(
+        &self,
+        _repository_name: &str,
+        _cleanup_policy: &RegistryCleanupPolicy,
+        _deployed_tag: Option<&str>,
+    ) -> Result<usize, ContainerRegistryError> {
+        Ok(0)
+    }
+
+    // Purge the untagged images of all the repositories whose name starts with the prefix, i.e: the ones of a cluster.
+    // Returns the number of deleted images.
+    fn cleanup_untagged_images(
+        &self,
+        _repository_prefix: &str,
+        _untagged_image_ttl: Duration,
+    ) -> Result<usize, ContainerRegistryError> {
+        Ok(0)
+    }
+
     fn get_event_details(&self, stage: Stage) -> EventDetails {
         let context = self.context();
         let ev = EventDetails::new(
@@ -143,6 +183,7 @@ pub enum Kind {
     GcpArtifactRegistry,
     GenericCr,
     GithubCr,
+    HarborCr,
 }
 
 #[derive(Clone, PartialEq, Debug)]
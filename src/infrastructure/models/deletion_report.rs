@@ -0,0 +1,565 @@
+// Blast radius of an environment or a cluster deletion, computed by a delete dry-run. Nothing is removed: the report
+// lists what the deletion would take with it, from the cluster listings and the cloud provider apis, and flags
+// everything that could not be confirmed so the console never shows a partial list as a complete one.
+
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+/// Bumped on every breaking change of the report, the core refuses versions it does not know.
+pub const DELETION_REPORT_VERSION: u32 = 1;
+
+#[derive(Serialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeletionScope {
+    Environment,
+    Cluster,
+}
+
+impl Display for DeletionScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DeletionScope::Environment => "environment",
+            DeletionScope::Cluster => "cluster",
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeletedResourceKind {
+    Namespace,
+    Workload,
+    PersistentVolumeClaim,
+    ManagedDatabase,
+    RegistryRepository,
+    DnsRecord,
+    LoadBalancer,
+    Bucket,
+}
+
+impl Display for DeletedResourceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DeletedResourceKind::Namespace => "namespace",
+            DeletedResourceKind::Workload => "workload",
+            DeletedResourceKind::PersistentVolumeClaim => "persistent volume claim",
+            DeletedResourceKind::ManagedDatabase => "managed database",
+            DeletedResourceKind::RegistryRepository => "registry repository",
+            DeletedResourceKind::DnsRecord => "dns record",
+            DeletedResourceKind::LoadBalancer => "load balancer",
+            DeletedResourceKind::Bucket => "bucket",
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct DeletedResource {
+    pub kind: DeletedResourceKind,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, String>,
+    pub verified: bool,
+    /// Why the presence of the resource could not be confirmed, it is listed because the deletion targets it anyway
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unverified_reason: Option<String>,
+}
+
+impl DeletedResource {
+    fn verified(kind: DeletedResourceKind, name: String, namespace: Option<&str>) -> Self {
+        DeletedResource {
+            kind,
+            name,
+            namespace: namespace.map(str::to_string),
+            details: BTreeMap::new(),
+            verified: true,
+            unverified_reason: None,
+        }
+    }
+
+    fn unverified(kind: DeletedResourceKind, name: String, reason: String) -> Self {
+        DeletedResource {
+            verified: false,
+            unverified_reason: Some(reason),
+            ..DeletedResource::verified(kind, name, None)
+        }
+    }
+
+    fn with_detail(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.details.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+/// A listing that failed as a whole: the resources of this kind are unknown, not absent
+#[derive(Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct FailedListing {
+    pub kind: DeletedResourceKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListedWorkload {
+    pub kind: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListedVolumeClaim {
+    pub name: String,
+    pub storage: Option<String>,
+    pub storage_class: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListedLoadBalancer {
+    pub name: String,
+    /// Hostname or ip given by the cloud provider, missing while the load balancer is being provisioned
+    pub address: Option<String>,
+}
+
+/// Read-only listings the report is built from, every error is reported instead of aborting the dry-run
+pub trait DeletionInventory {
+    /// Namespaces owned by the deleted environment(s)
+    fn namespaces(&self) -> Result<Vec<String>, String>;
+    fn workloads(&self, namespace: &str) -> Result<Vec<ListedWorkload>, String>;
+    fn persistent_volume_claims(&self, namespace: &str) -> Result<Vec<ListedVolumeClaim>, String>;
+    fn load_balancers(&self, namespace: &str) -> Result<Vec<ListedLoadBalancer>, String>;
+    /// Hostnames served by the namespace, their records are removed by external-dns with the ingresses
+    fn dns_records(&self, namespace: &str) -> Result<Vec<String>, String>;
+    fn repository_exists(&self, repository_name: &str) -> Result<bool, String>;
+    fn bucket_exists(&self, bucket_name: &str) -> Result<bool, String>;
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExpectedManagedDatabase {
+    pub name: String,
+    pub engine: String,
+    pub disk_size_in_gib: u32,
+    /// None when the database is removed without a final snapshot
+    pub final_snapshot_name: Option<String>,
+}
+
+/// Resources the deletion targets from the payload, outside of the cluster listings
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExpectedResources {
+    pub managed_databases: Vec<ExpectedManagedDatabase>,
+    pub registry_repositories: Vec<String>,
+    pub buckets: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DeletionReport {
+    pub version: u32,
+    pub scope: DeletionScope,
+    pub target_id: Uuid,
+    pub target_name: String,
+    pub generated_at: DateTime<Utc>,
+    pub resources: Vec<DeletedResource>,
+    pub failed_listings: Vec<FailedListing>,
+}
+
+impl DeletionReport {
+    pub fn assemble(
+        scope: DeletionScope,
+        target_id: Uuid,
+        target_name: String,
+        now: DateTime<Utc>,
+        inventory: &dyn DeletionInventory,
+        expected: &ExpectedResources,
+    ) -> Self {
+        let mut resources = vec![];
+        let mut failed_listings = vec![];
+        let mut failed = |kind: DeletedResourceKind, namespace: Option<&str>, reason: String| {
+            failed_listings.push(FailedListing {
+                kind,
+                namespace: namespace.map(str::to_string),
+                reason,
+            })
+        };
+
+        match inventory.namespaces() {
+            Ok(namespaces) => {
+                for namespace in namespaces {
+                    resources.push(DeletedResource::verified(
+                        DeletedResourceKind::Namespace,
+                        namespace.clone(),
+                        None,
+                    ));
+                    assemble_namespace(inventory, &namespace, &mut resources, &mut failed);
+                }
+            }
+            Err(err) => failed(DeletedResourceKind::Namespace, None, err),
+        }
+
+        for database in &expected.managed_databases {
+            resources.push(
+                DeletedResource::unverified(
+                    DeletedResourceKind::ManagedDatabase,
+                    database.name.clone(),
+                    "managed databases are not checked against the cloud provider".to_string(),
+                )
+                .with_detail("engine", database.engine.as_str())
+                .with_detail("disk_size", format!("{}Gi", database.disk_size_in_gib))
+                .with_detail(
+                    "final_snapshot",
+                    database.final_snapshot_name.as_deref().unwrap_or("none, data is lost"),
+                ),
+            );
+        }
+
+        for repository in &expected.registry_repositories {
+            match inventory.repository_exists(repository) {
+                Ok(true) => resources.push(DeletedResource::verified(
+                    DeletedResourceKind::RegistryRepository,
+                    repository.clone(),
+                    None,
+                )),
+                Ok(false) => {}
+                Err(err) => resources.push(DeletedResource::unverified(
+                    DeletedResourceKind::RegistryRepository,
+                    repository.clone(),
+                    err,
+                )),
+            }
+        }
+
+        for bucket in &expected.buckets {
+            match inventory.bucket_exists(bucket) {
+                Ok(true) => {
+                    resources.push(DeletedResource::verified(DeletedResourceKind::Bucket, bucket.clone(), None))
+                }
+                Ok(false) => {}
+                Err(err) => {
+                    resources.push(DeletedResource::unverified(DeletedResourceKind::Bucket, bucket.clone(), err))
+                }
+            }
+        }
+
+        DeletionReport {
+            version: DELETION_REPORT_VERSION,
+            scope,
+            target_id,
+            target_name,
+            generated_at: now,
+            resources,
+            failed_listings,
+        }
+    }
+
+    pub fn unverified_count(&self) -> usize {
+        self.resources.iter().filter(|resource| !resource.verified).count() + self.failed_listings.len()
+    }
+}
+
+fn assemble_namespace(
+    inventory: &dyn DeletionInventory,
+    namespace: &str,
+    resources: &mut Vec<DeletedResource>,
+    failed: &mut impl FnMut(DeletedResourceKind, Option<&str>, String),
+) {
+    match inventory.workloads(namespace) {
+        Ok(workloads) => resources.extend(workloads.into_iter().map(|workload| {
+            DeletedResource::verified(DeletedResourceKind::Workload, workload.name, Some(namespace))
+                .with_detail("kind", workload.kind)
+        })),
+        Err(err) => failed(DeletedResourceKind::Workload, Some(namespace), err),
+    }
+
+    match inventory.persistent_volume_claims(namespace) {
+        Ok(claims) => resources.extend(claims.into_iter().map(|claim| {
+            let mut resource =
+                DeletedResource::verified(DeletedResourceKind::PersistentVolumeClaim, claim.name, Some(namespace))
+                    .with_detail("size", claim.storage.unwrap_or_else(|| "unknown".to_string()));
+            if let Some(storage_class) = claim.storage_class {
+                resource = resource.with_detail("storage_class", storage_class);
+            }
+            resource
+        })),
+        Err(err) => failed(DeletedResourceKind::PersistentVolumeClaim, Some(namespace), err),
+    }
+
+    match inventory.load_balancers(namespace) {
+        Ok(load_balancers) => resources.extend(load_balancers.into_iter().map(|load_balancer| {
+            DeletedResource::verified(DeletedResourceKind::LoadBalancer, load_balancer.name, Some(namespace))
+                .with_detail("address", load_balancer.address.unwrap_or_else(|| "pending".to_string()))
+        })),
+        Err(err) => failed(DeletedResourceKind::LoadBalancer, Some(namespace), err),
+    }
+
+    match inventory.dns_records(namespace) {
+        Ok(hostnames) => resources.extend(
+            hostnames
+                .into_iter()
+                .map(|hostname| DeletedResource::verified(DeletedResourceKind::DnsRecord, hostname, Some(namespace))),
+        ),
+        Err(err) => failed(DeletedResourceKind::DnsRecord, Some(namespace), err),
+    }
+}
+
+impl Display for DeletionReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let counts = self
+            .resources
+            .iter()
+            .counts_by(|resource| resource.kind)
+            .into_iter()
+            .sorted()
+            .map(|(kind, count)| format!("{count} {kind}(s)"))
+            .join(", ");
+
+        match counts.is_empty() {
+            true => write!(f, "Deleting {} {} would not remove any resource", self.scope, self.target_name)?,
+            false => write!(f, "Deleting {} {} would remove {}", self.scope, self.target_name, counts)?,
+        }
+
+        match self.unverified_count() {
+            0 => Ok(()),
+            unverified => write!(f, ". {unverified} item(s) could not be verified, the list may be incomplete"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MockInventory {
+        namespaces: Option<Vec<String>>,
+        workloads: HashMap<String, Vec<ListedWorkload>>,
+        claims: HashMap<String, Vec<ListedVolumeClaim>>,
+        load_balancers: HashMap<String, Vec<ListedLoadBalancer>>,
+        hostnames: HashMap<String, Vec<String>>,
+        repositories: Option<Vec<String>>,
+        buckets: Vec<String>,
+    }
+
+    fn listed<T: Clone>(listing: &HashMap<String, Vec<T>>, namespace: &str) -> Result<Vec<T>, String> {
+        listing
+            .get(namespace)
+            .cloned()
+            .ok_or_else(|| format!("forbidden in namespace {namespace}"))
+    }
+
+    impl DeletionInventory for MockInventory {
+        fn namespaces(&self) -> Result<Vec<String>, String> {
+            self.namespaces
+                .clone()
+                .ok_or_else(|| "api server unreachable".to_string())
+        }
+
+        fn workloads(&self, namespace: &str) -> Result<Vec<ListedWorkload>, String> {
+            listed(&self.workloads, namespace)
+        }
+
+        fn persistent_volume_claims(&self, namespace: &str) -> Result<Vec<ListedVolumeClaim>, String> {
+            listed(&self.claims, namespace)
+        }
+
+        fn load_balancers(&self, namespace: &str) -> Result<Vec<ListedLoadBalancer>, String> {
+            listed(&self.load_balancers, namespace)
+        }
+
+        fn dns_records(&self, namespace: &str) -> Result<Vec<String>, String> {
+            listed(&self.hostnames, namespace)
+        }
+
+        fn repository_exists(&self, repository_name: &str) -> Result<bool, String> {
+            match &self.repositories {
+                Some(repositories) => Ok(repositories.iter().any(|name| name == repository_name)),
+                None => Err("registry credentials are invalid".to_string()),
+            }
+        }
+
+        fn bucket_exists(&self, bucket_name: &str) -> Result<bool, String> {
+            Ok(self.buckets.iter().any(|name| name == bucket_name))
+        }
+    }
+
+    fn environment_inventory() -> MockInventory {
+        let namespace = "z1234-z5678".to_string();
+        MockInventory {
+            namespaces: Some(vec![namespace.clone()]),
+            workloads: HashMap::from([(
+                namespace.clone(),
+                vec![
+                    ListedWorkload {
+                        kind: "Deployment".to_string(),
+                        name: "app-z1111".to_string(),
+                    },
+                    ListedWorkload {
+                        kind: "StatefulSet".to_string(),
+                        name: "postgresql-z2222".to_string(),
+                    },
+                ],
+            )]),
+            claims: HashMap::from([(
+                namespace.clone(),
+                vec![ListedVolumeClaim {
+                    name: "data-postgresql-z2222-0".to_string(),
+                    storage: Some("10Gi".to_string()),
+                    storage_class: Some("aws-ebs-gp2-0".to_string()),
+                }],
+            )]),
+            load_balancers: HashMap::from([(
+                namespace.clone(),
+                vec![ListedLoadBalancer {
+                    name: "app-z1111-tcp".to_string(),
+                    address: None,
+                }],
+            )]),
+            hostnames: HashMap::from([(namespace, vec!["app-z1111.example.qovery.io".to_string()])]),
+            repositories: Some(vec!["z1111".to_string()]),
+            buckets: vec![],
+        }
+    }
+
+    fn report(inventory: &MockInventory, expected: &ExpectedResources) -> DeletionReport {
+        DeletionReport::assemble(
+            DeletionScope::Environment,
+            Uuid::nil(),
+            "staging".to_string(),
+            Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap(),
+            inventory,
+            expected,
+        )
+    }
+
+    fn names_of(report: &DeletionReport, kind: DeletedResourceKind) -> Vec<&str> {
+        report
+            .resources
+            .iter()
+            .filter(|resource| resource.kind == kind)
+            .map(|resource| resource.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_environment_report_lists_every_cluster_resource() {
+        let report = report(&environment_inventory(), &ExpectedResources::default());
+
+        assert_eq!(names_of(&report, DeletedResourceKind::Namespace), vec!["z1234-z5678"]);
+        assert_eq!(
+            names_of(&report, DeletedResourceKind::Workload),
+            vec!["app-z1111", "postgresql-z2222"]
+        );
+        assert_eq!(
+            names_of(&report, DeletedResourceKind::DnsRecord),
+            vec!["app-z1111.example.qovery.io"]
+        );
+        assert_eq!(report.unverified_count(), 0);
+
+        let claim = report
+            .resources
+            .iter()
+            .find(|resource| resource.kind == DeletedResourceKind::PersistentVolumeClaim)
+            .unwrap();
+        assert_eq!(claim.namespace.as_deref(), Some("z1234-z5678"));
+        assert_eq!(claim.details.get("size").map(String::as_str), Some("10Gi"));
+
+        let load_balancer = report
+            .resources
+            .iter()
+            .find(|resource| resource.kind == DeletedResourceKind::LoadBalancer)
+            .unwrap();
+        assert_eq!(load_balancer.details.get("address").map(String::as_str), Some("pending"));
+    }
+
+    #[test]
+    fn test_failed_listings_are_reported_instead_of_empty() {
+        let mut inventory = environment_inventory();
+        inventory.claims.clear();
+        let report = report(&inventory, &ExpectedResources::default());
+
+        assert!(names_of(&report, DeletedResourceKind::PersistentVolumeClaim).is_empty());
+        assert_eq!(
+            report.failed_listings,
+            vec![FailedListing {
+                kind: DeletedResourceKind::PersistentVolumeClaim,
+                namespace: Some("z1234-z5678".to_string()),
+                reason: "forbidden in namespace z1234-z5678".to_string(),
+            }]
+        );
+        assert_eq!(report.unverified_count(), 1);
+
+        let unreachable = MockInventory::default();
+        let report = self::report(&unreachable, &ExpectedResources::default());
+        assert!(report.resources.is_empty());
+        assert_eq!(report.failed_listings[0].kind, DeletedResourceKind::Namespace);
+        assert_eq!(
+            report.to_string(),
+            "Deleting environment staging would not remove any resource. 1 item(s) could not be verified, the list may be incomplete"
+        );
+    }
+
+    #[test]
+    fn test_expected_resources_are_checked_against_the_providers() {
+        let mut inventory = environment_inventory();
+        inventory.buckets = vec!["qovery-logs-z5678".to_string()];
+        let expected = ExpectedResources {
+            managed_databases: vec![ExpectedManagedDatabase {
+                name: "orders".to_string(),
+                engine: "POSTGRESQL".to_string(),
+                disk_size_in_gib: 20,
+                final_snapshot_name: Some("qovery-z3333-final-snap".to_string()),
+            }],
+            // z4444 was never pushed, there is nothing to delete
+            registry_repositories: vec!["z1111".to_string(), "z4444".to_string()],
+            buckets: vec!["qovery-logs-z5678".to_string(), "qovery-kubeconfigs-z5678".to_string()],
+        };
+        let report = report(&inventory, &expected);
+
+        assert_eq!(names_of(&report, DeletedResourceKind::RegistryRepository), vec!["z1111"]);
+        assert_eq!(names_of(&report, DeletedResourceKind::Bucket), vec!["qovery-logs-z5678"]);
+
+        // the database is listed from the payload only, so it must not look verified
+        let database = report
+            .resources
+            .iter()
+            .find(|resource| resource.kind == DeletedResourceKind::ManagedDatabase)
+            .unwrap();
+        assert!(!database.verified);
+        assert!(database.unverified_reason.is_some());
+        assert_eq!(
+            database.details.get("final_snapshot").map(String::as_str),
+            Some("qovery-z3333-final-snap")
+        );
+        assert_eq!(report.unverified_count(), 1);
+    }
+
+    #[test]
+    fn test_unreachable_registry_marks_repositories_unverified() {
+        let mut inventory = environment_inventory();
+        inventory.repositories = None;
+        let expected = ExpectedResources {
+            registry_repositories: vec!["z1111".to_string()],
+            ..Default::default()
+        };
+        let report = report(&inventory, &expected);
+
+        let repository = report
+            .resources
+            .iter()
+            .find(|resource| resource.kind == DeletedResourceKind::RegistryRepository)
+            .unwrap();
+        assert!(!repository.verified);
+        assert_eq!(
+            repository.unverified_reason.as_deref(),
+            Some("registry credentials are invalid")
+        );
+        assert_eq!(
+            report.to_string(),
+            "Deleting environment staging would remove 1 namespace(s), 2 workload(s), 1 persistent volume claim(s), 1 registry repository(s), 1 dns record(s), 1 load balancer(s). 1 item(s) could not be verified, the list may be incomplete"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use derivative::Derivative;
+use std::net::Ipv4Addr;
+use tera::Context as TeraContext;
+use uuid::Uuid;
+
+use crate::environment::models::domain::Domain;
+use crate::infrastructure::models::dns_provider::errors::DnsProviderError;
+use crate::infrastructure::models::dns_provider::{DnsProvider, DnsProviderConfiguration, Kind};
+use crate::io_models::context::Context;
+use crate::services::azure::dns_service::{AzureDnsEndpoints, AzureDnsService, AzureDnsServiceError};
+
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct AzureDnsConfig {
+    pub tenant_id: String,
+    pub subscription_id: String,
+    pub resource_group: String,
+    pub client_id: String,
+    #[derivative(Debug = "ignore")]
+    pub client_secret: String,
+}
+
+pub struct AzureDns {
+    context: Context,
+    long_id: Uuid,
+    name: String,
+    domain: Domain,
+    dns_config: AzureDnsConfig,
+    endpoints: AzureDnsEndpoints,
+}
+
+impl AzureDns {
+    pub fn new(context: Context, long_id: Uuid, name: &str, domain: Domain, dns_config: AzureDnsConfig) -> Self {
+        AzureDns {
+            context,
+            long_id,
+            name: name.to_string(),
+            domain,
+            dns_config,
+            endpoints: AzureDnsEndpoints::default(),
+        }
+    }
+
+    /// Returns a client to manage records of the zone, records being owned by the current cluster.
+    pub fn dns_service(&self) -> Result<AzureDnsService, DnsProviderError> {
+        AzureDnsService::new(
+            self.dns_config.clone(),
+            self.endpoints.clone(),
+            self.context.cluster_long_id().to_string(),
+        )
+        .map_err(DnsProviderError::from)
+    }
+}
+
+impl From<AzureDnsServiceError> for DnsProviderError {
+    fn from(error: AzureDnsServiceError) -> Self {
+        match error {
+            AzureDnsServiceError::CannotAuthenticate { .. } => DnsProviderError::InvalidCredentials,
+            AzureDnsServiceError::MissingRole { role, zone, .. } => DnsProviderError::MissingRole { role, zone },
+            AzureDnsServiceError::ZoneNotFound { domain, .. } => DnsProviderError::ZoneNotFound { domain },
+            AzureDnsServiceError::CannotInstantiateClient { raw_error_message }
+            | AzureDnsServiceError::InvalidRecord { raw_error_message, .. }
+            | AzureDnsServiceError::ApiError { raw_error_message, .. } => {
+                DnsProviderError::ApiError { raw_error_message }
+            }
+        }
+    }
+}
+
+impl DnsProvider for AzureDns {
+    fn context(&self) -> &Context {
+        &self.context
+    }
+
+    fn provider_name(&self) -> &str {
+        "azure"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::AzureDns
+    }
+
+    fn long_id(&self) -> &Uuid {
+        &self.long_id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn insert_into_teracontext<'a>(&self, context: &'a mut TeraContext) -> &'a mut TeraContext {
+        context.insert("external_dns_provider", &self.provider_name());
+        context.insert("azure_dns_tenant_id", &self.dns_config.tenant_id);
+        context.insert("azure_dns_subscription_id", &self.dns_config.subscription_id);
+        context.insert("azure_dns_resource_group", &self.dns_config.resource_group);
+        context.insert("azure_dns_client_id", &self.dns_config.client_id);
+        context.insert("azure_dns_client_secret", &self.dns_config.client_secret);
+        context
+    }
+
+    fn provider_configuration(&self) -> DnsProviderConfiguration {
+        DnsProviderConfiguration::AzureDns(self.dns_config.clone())
+    }
+
+    fn domain(&self) -> &Domain {
+        &self.domain
+    }
+
+    fn resolvers(&self) -> Vec<Ipv4Addr> {
+        vec![Ipv4Addr::new(8, 8, 8, 8), Ipv4Addr::new(8, 8, 4, 4)]
+    }
+
+    fn is_valid(&self) -> Result<(), DnsProviderError> {
+        if self.dns_config.tenant_id.is_empty()
+            || self.dns_config.subscription_id.is_empty()
+            || self.dns_config.resource_group.is_empty()
+            || self.dns_config.client_id.is_empty()
+            || self.dns_config.client_secret.is_empty()
+        {
+            return Err(DnsProviderError::InvalidCredentials);
+        }
+
+        // zone discovery and RBAC validation, so a misconfigured principal fails before anything gets deployed
+        let service = self.dns_service()?;
+        let zone = service.find_zone(&self.domain.to_string()).map_err(|err| match err {
+            AzureDnsServiceError::ZoneNotFound { .. } => DnsProviderError::ZoneNotFound {
+                domain: self.domain.raw_unicode(),
+            },
+            err => DnsProviderError::from(err),
+        })?;
+        service.check_permissions(&zone)?;
+
+        Ok(())
+    }
+}
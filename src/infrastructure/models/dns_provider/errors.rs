@@ -1,4 +1,4 @@
-use crate::errors::EngineError;
+use crate::errors::{CommandError, EngineError};
 use crate::events::EventDetails;
 use thiserror::Error;
 
@@ -8,6 +8,12 @@ pub enum DnsProviderError {
     InvalidCredentials,
     #[error("Invalid API url error.")]
     InvalidApiUrl,
+    #[error("Missing role `{role}` on DNS zone `{zone}`.")]
+    MissingRole { role: String, zone: String },
+    #[error("No DNS zone found for domain `{domain}`.")]
+    ZoneNotFound { domain: String },
+    #[error("DNS provider API error: {raw_error_message}.")]
+    ApiError { raw_error_message: String },
 }
 
 impl DnsProviderError {
@@ -17,6 +23,15 @@ impl DnsProviderError {
                 EngineError::new_error_on_dns_provider_invalid_credentials(event_details)
             }
             DnsProviderError::InvalidApiUrl => EngineError::new_error_on_dns_provider_invalid_api_url(event_details),
+            DnsProviderError::MissingRole { role, zone } => {
+                EngineError::new_error_on_dns_provider_missing_role(event_details, role, zone)
+            }
+            DnsProviderError::ZoneNotFound { .. } | DnsProviderError::ApiError { .. } => {
+                EngineError::new_error_on_dns_provider_information(
+                    event_details,
+                    CommandError::new_from_safe_message(self.to_string()),
+                )
+            }
         }
     }
 }
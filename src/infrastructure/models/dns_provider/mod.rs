@@ -1,6 +1,7 @@
 use std::net::Ipv4Addr;
 
 use crate::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
+use crate::infrastructure::models::dns_provider::azure_dns::AzureDnsConfig;
 use crate::infrastructure::models::dns_provider::cloudflare::CloudflareDnsConfig;
 use crate::infrastructure::models::dns_provider::errors::DnsProviderError;
 use crate::infrastructure::models::dns_provider::qoverydns::QoveryDnsConfig;
@@ -11,6 +12,7 @@ use crate::environment::models::domain::Domain;
 use crate::io_models::context::Context;
 use crate::io_models::QoveryIdentifier;
 
+pub mod azure_dns;
 pub mod cloudflare;
 pub mod errors;
 pub mod io;
@@ -20,12 +22,14 @@ pub mod qoverydns;
 pub enum Kind {
     Cloudflare,
     QoveryDns,
+    AzureDns,
 }
 
 #[derive(Clone, Debug)]
 pub enum DnsProviderConfiguration {
     Cloudflare(CloudflareDnsConfig),
     QoveryDns(QoveryDnsConfig),
+    AzureDns(AzureDnsConfig),
 }
 
 impl DnsProviderConfiguration {
@@ -33,6 +37,7 @@ impl DnsProviderConfiguration {
         match self {
             DnsProviderConfiguration::Cloudflare(_) => "cloudflare",
             DnsProviderConfiguration::QoveryDns(_) => "pdns",
+            DnsProviderConfiguration::AzureDns(_) => "azure",
         }
         .to_string()
     }
@@ -1,7 +1,8 @@
 use crate::environment::models::ToCloudProviderFormat;
-use crate::errors::EngineError;
+use crate::errors::{CommandError, EngineError};
 use crate::events::InfrastructureStep;
 use crate::events::Stage::Infrastructure;
+use crate::fs::ServiceWorkspace;
 use crate::infrastructure::action::kubeconfig_helper::write_kubeconfig_on_disk;
 use crate::infrastructure::models::cloud_provider::aws::regions::{AwsRegion, AwsZone};
 use crate::infrastructure::models::cloud_provider::io::ClusterAdvancedSettings;
@@ -9,15 +10,18 @@ use crate::infrastructure::models::cloud_provider::CloudProvider;
 use crate::infrastructure::models::kubernetes::aws;
 use crate::infrastructure::models::kubernetes::aws::{KarpenterParameters, Options};
 use crate::infrastructure::models::kubernetes::{event_details, Kind, Kubernetes, KubernetesVersion};
+use crate::infrastructure::models::object_storage::retry_policy::{RetryPolicy, RetryingObjectStorage};
 use crate::infrastructure::models::object_storage::s3::S3;
+use crate::infrastructure::models::object_storage::ObjectStorage;
 use crate::io_models::context::Context;
 use crate::io_models::engine_request::{ChartValuesOverrideName, ChartValuesOverrideValues};
 use crate::io_models::models::CpuArchitecture;
-use crate::io_models::models::NodeGroups;
+use crate::io_models::models::{node_groups_cpu_architectures, NodeGroups};
 use crate::logger::Logger;
+use crate::runtime::block_on;
 use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 use crate::infrastructure::action::InfrastructureAction;
 use crate::utilities::to_short_id;
@@ -32,7 +36,7 @@ pub struct EKS {
     pub version: KubernetesVersion,
     pub region: AwsRegion,
     pub zones: Vec<AwsZone>,
-    pub s3: S3,
+    pub s3: RetryingObjectStorage<S3>,
     pub nodes_groups: Vec<NodeGroups>,
     pub template_directory: PathBuf,
     pub options: Options,
@@ -40,7 +44,7 @@ pub struct EKS {
     pub advanced_settings: ClusterAdvancedSettings,
     pub customer_helm_charts_override: Option<HashMap<ChartValuesOverrideName, ChartValuesOverrideValues>>,
     pub kubeconfig: Option<String>,
-    pub temp_dir: PathBuf,
+    pub workspace: ServiceWorkspace,
     pub qovery_allowed_public_access_cidrs: Option<Vec<String>>,
 }
 
@@ -59,7 +63,7 @@ impl EKS {
         advanced_settings: ClusterAdvancedSettings,
         customer_helm_charts_override: Option<HashMap<ChartValuesOverrideName, ChartValuesOverrideValues>>,
         kubeconfig: Option<String>,
-        temp_dir: PathBuf,
+        workspace: ServiceWorkspace,
         qovery_allowed_public_access_cidrs: Option<Vec<String>>,
     ) -> Result<Self, Box<EngineError>> {
         let event_details = event_details(cloud_provider, long_id, name.to_string(), &context);
@@ -67,13 +71,18 @@ impl EKS {
 
         let aws_zones = aws::aws_zones(zones, &region, &event_details)?;
         advanced_settings.validate(event_details.clone())?;
+        advanced_settings.cluster_autoscaler_settings(options.karpenter_parameters.is_some(), event_details.clone())?;
 
-        let s3 = S3::new(
-            "s3-temp-id".to_string(),
-            "default-s3".to_string(),
-            cloud_provider.access_key_id(),
-            cloud_provider.secret_access_key(),
-            region.clone(),
+        let s3 = RetryingObjectStorage::new(
+            S3::new(
+                "s3-temp-id".to_string(),
+                "default-s3".to_string(),
+                cloud_provider.access_key_id(),
+                cloud_provider.secret_access_key(),
+                region.clone(),
+            )
+            .with_kms_key_arn(advanced_settings.aws_s3_kms_key_arn.clone()),
+            RetryPolicy::default(),
         );
 
         let cluster = EKS {
@@ -92,7 +101,7 @@ impl EKS {
             advanced_settings,
             customer_helm_charts_override,
             kubeconfig,
-            temp_dir,
+            workspace,
             qovery_allowed_public_access_cidrs,
         };
 
@@ -111,6 +120,14 @@ impl EKS {
     pub fn get_karpenter_parameters(&self) -> Option<KarpenterParameters> {
         self.options.karpenter_parameters.clone()
     }
+
+    /// Spot nodes are reclaimed after a 2 minutes notice, sent by EventBridge to an SQS queue read by Karpenter
+    pub fn is_karpenter_interruption_queue_enabled(&self) -> bool {
+        self.options
+            .karpenter_parameters
+            .as_ref()
+            .is_some_and(|karpenter_parameters| karpenter_parameters.spot_enabled)
+    }
 }
 
 impl Kubernetes for EKS {
@@ -158,12 +175,12 @@ impl Kubernetes for EKS {
         if let Some(karpenter_parameters) = &self.options.karpenter_parameters {
             vec![karpenter_parameters.default_service_architecture]
         } else {
-            self.nodes_groups.iter().map(|x| x.instance_architecture).collect()
+            node_groups_cpu_architectures(&self.nodes_groups)
         }
     }
 
-    fn temp_dir(&self) -> &Path {
-        &self.temp_dir
+    fn workspace(&self) -> &ServiceWorkspace {
+        &self.workspace
     }
 
     fn advanced_settings(&self) -> &ClusterAdvancedSettings {
@@ -216,7 +233,91 @@ impl Kubernetes for EKS {
         }
     }
 
+    fn object_storage(&self) -> Option<&dyn ObjectStorage> {
+        Some(&self.s3)
+    }
+
+    fn buckets_removed_on_deletion(&self) -> Vec<String> {
+        // logs buckets are removed from the terraform state before the destroy, and kept
+        vec![format!("qovery-kubeconfigs-{}", self.short_id())]
+    }
+
+    fn regenerate_kubeconfig(&self, cloud_provider: &dyn CloudProvider) -> Result<Option<String>, Box<EngineError>> {
+        let Some(aws_conn) = cloud_provider.aws_sdk_client() else {
+            return Ok(None);
+        };
+        let event_details = self.get_event_details(Infrastructure(InfrastructureStep::LoadConfiguration));
+
+        let output = block_on(
+            aws_sdk_eks::Client::new(&aws_conn)
+                .describe_cluster()
+                .name(self.cluster_name())
+                .send(),
+        )
+        .map_err(|err| {
+            Box::new(EngineError::new_k8s_cannot_generate_kubeconfig(
+                event_details.clone(),
+                CommandError::new("Cannot describe the EKS cluster".to_string(), Some(err.to_string()), None),
+            ))
+        })?;
+        let cluster = output.cluster();
+        let (Some(endpoint), Some(certificate_authority_data)) = (
+            cluster.and_then(|cluster| cluster.endpoint()),
+            cluster
+                .and_then(|cluster| cluster.certificate_authority())
+                .and_then(|certificate_authority| certificate_authority.data()),
+        ) else {
+            return Err(Box::new(EngineError::new_k8s_cannot_generate_kubeconfig(
+                event_details,
+                CommandError::new_from_safe_message(
+                    "EKS cluster has no endpoint or certificate authority yet".to_string(),
+                ),
+            )));
+        };
+
+        Ok(Some(eks_kubeconfig(
+            self.short_id(),
+            &self.cluster_name(),
+            endpoint,
+            certificate_authority_data,
+        )))
+    }
+
     fn as_infra_actions(&self) -> &dyn InfrastructureAction {
         self
     }
 }
+
+/// Same kubeconfig as the terraform output, the token is issued by the aws cli on each call
+fn eks_kubeconfig(cluster_id: &str, cluster_name: &str, endpoint: &str, certificate_authority_data: &str) -> String {
+    let name = format!("aws_{}", cluster_id.replace('-', "_"));
+    format!(
+        r#"apiVersion: v1
+clusters:
+- cluster:
+    server: {endpoint}
+    certificate-authority-data: {certificate_authority_data}
+  name: {name}
+contexts:
+- context:
+    cluster: {name}
+    user: {name}
+  name: {name}
+current-context: {name}
+kind: Config
+preferences: {{}}
+users:
+- name: {name}
+  user:
+    exec:
+      apiVersion: client.authentication.k8s.io/v1
+      interactiveMode: IfAvailable
+      command: aws
+      args:
+        - "eks"
+        - "get-token"
+        - "--cluster-name"
+        - "{cluster_name}"
+"#
+    )
+}
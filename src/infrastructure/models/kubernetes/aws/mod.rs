@@ -11,8 +11,11 @@ use crate::io_models::models::{
     CpuArchitecture, KubernetesCpuResourceUnit, KubernetesMemoryResourceUnit, VpcCustomRoutingTable,
     VpcQoveryNetworkMode,
 };
+use crate::utilities::validate_cron_schedule;
 use duration_str::deserialize_duration;
 use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_with::DisplayFromStr;
 use std::fmt;
@@ -301,10 +304,88 @@ pub struct KarpenterNodePoolLimits {
     pub max_memory: KubernetesMemoryResourceUnit,
 }
 
+pub const KARPENTER_MAX_DISRUPTION_BUDGETS: usize = 50;
+
+// patterns of the karpenter.sh/v1 NodePool CRD
+static CONSOLIDATE_AFTER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(([0-9]+(s|m|h))+|Never)$").unwrap());
+static BUDGET_NODES_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^((100|[0-9]{1,2})%|[0-9]+)$").unwrap());
+static BUDGET_DURATION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^((([0-9]+(h|m))|([0-9]+h[0-9]+m))(0s)?)$").unwrap());
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KarpenterConsolidationPolicy {
+    WhenEmpty,
+    WhenEmptyOrUnderutilized,
+}
+
+impl fmt::Display for KarpenterConsolidationPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            KarpenterConsolidationPolicy::WhenEmpty => write!(f, "WhenEmpty"),
+            KarpenterConsolidationPolicy::WhenEmptyOrUnderutilized => write!(f, "WhenEmptyOrUnderutilized"),
+        }
+    }
+}
+
+/// Max nodes disrupted at once, during `duration` after each `schedule` hit or all the time without a schedule
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KarpenterDefaultNodePoolBudget {
+    pub nodes: String,
+    pub schedule_and_duration: Option<(String, String)>,
+}
+
+/// Disruption of the default node pool, the stable one follows the budgets of the Karpenter parameters
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KarpenterDefaultNodePoolDisruption {
+    pub consolidation_policy: KarpenterConsolidationPolicy,
+    pub consolidate_after: String,
+    pub budgets: Vec<KarpenterDefaultNodePoolBudget>,
+}
+
+impl Default for KarpenterDefaultNodePoolDisruption {
+    fn default() -> Self {
+        KarpenterDefaultNodePoolDisruption {
+            consolidation_policy: KarpenterConsolidationPolicy::WhenEmptyOrUnderutilized,
+            consolidate_after: "0s".to_string(),
+            budgets: vec![KarpenterDefaultNodePoolBudget {
+                nodes: "10%".to_string(),
+                schedule_and_duration: None,
+            }],
+        }
+    }
+}
+
+pub fn validate_consolidate_after(consolidate_after: &str) -> Result<(), String> {
+    match CONSOLIDATE_AFTER_REGEX.is_match(consolidate_after) {
+        true => Ok(()),
+        false => Err("expected a duration in seconds, minutes or hours (e.g. `30s`, `1h30m`) or `Never`".to_string()),
+    }
+}
+
+pub fn validate_budget_nodes(nodes: &str) -> Result<(), String> {
+    match BUDGET_NODES_REGEX.is_match(nodes) {
+        true => Ok(()),
+        false => Err("expected a number of nodes or a percentage between 0% and 100%".to_string()),
+    }
+}
+
+pub fn validate_budget_duration(duration: &str) -> Result<(), String> {
+    match BUDGET_DURATION_REGEX.is_match(duration) {
+        true => Ok(()),
+        false => Err("expected a duration in minutes or hours (e.g. `30m`, `8h`, `1h30m`)".to_string()),
+    }
+}
+
+/// Cron expression or descriptor, as parsed by Karpenter. Timezones are not supported.
+pub fn validate_budget_schedule(schedule: &str) -> Result<(), String> {
+    validate_cron_schedule(schedule)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::infrastructure::models::kubernetes::aws::{
-        default_karpenter_node_pool_stable_override, KarpenterDefaultNodePoolOverride,
+        default_karpenter_node_pool_stable_override, validate_budget_duration, validate_budget_nodes,
+        validate_budget_schedule, validate_consolidate_after, KarpenterDefaultNodePoolOverride,
         KarpenterNodePoolDisruptionBudget, KarpenterNodePoolDisruptionReason, KarpenterNodePoolLimits,
         KarpenterParameters, KarpenterStableNodePoolOverride,
     };
@@ -670,4 +751,57 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    fn test_validate_budget_schedule() {
+        for schedule in [
+            "0 9 * * MON-FRI",
+            "*/15 8-18 * * 1,3,5",
+            "30 22 1 jan,jul ?",
+            "0 0 1-15/2 * *",
+            "@daily",
+            "@weekly",
+        ] {
+            assert!(validate_budget_schedule(schedule).is_ok(), "`{schedule}` should be valid");
+        }
+
+        for schedule in [
+            "",
+            "* * *",
+            "* * * * * *",
+            "0 25 * * *",
+            "60 * * * *",
+            "0 0 0 * *",
+            "0 0 * 13 *",
+            "0 0 * * 7",
+            "0 9 * * MON-XYZ",
+            "*/0 * * * *",
+            "0 18-8 * * *",
+            "@every 1h",
+            "CRON_TZ=UTC 0 9 * * *",
+        ] {
+            assert!(validate_budget_schedule(schedule).is_err(), "`{schedule}` should be invalid");
+        }
+    }
+
+    #[test]
+    fn test_validate_disruption_values() {
+        assert!(validate_consolidate_after("0s").is_ok());
+        assert!(validate_consolidate_after("1h30m").is_ok());
+        assert!(validate_consolidate_after("Never").is_ok());
+        assert!(validate_consolidate_after("10").is_err());
+        assert!(validate_consolidate_after("1d").is_err());
+
+        assert!(validate_budget_nodes("0").is_ok());
+        assert!(validate_budget_nodes("15").is_ok());
+        assert!(validate_budget_nodes("100%").is_ok());
+        assert!(validate_budget_nodes("101%").is_err());
+        assert!(validate_budget_nodes("-1").is_err());
+
+        assert!(validate_budget_duration("30m").is_ok());
+        assert!(validate_budget_duration("1h30m").is_ok());
+        assert!(validate_budget_duration("8h0m0s").is_ok());
+        assert!(validate_budget_duration("30s").is_err());
+        assert!(validate_budget_duration("").is_err());
+    }
 }
@@ -4293,7 +4293,8 @@ impl FromStr for AwsInstancesType {
 mod tests {
     use crate::infrastructure::models::kubernetes::aws::node::AwsInstancesType;
     use crate::infrastructure::models::kubernetes::InstanceType;
-    use crate::io_models::models::{CpuArchitecture, NodeGroups};
+    use crate::io_models::models::{CpuArchitecture, NodeGroupCapacityType, NodeGroups};
+    use std::collections::BTreeMap;
     use std::str::FromStr;
     use strum::IntoEnumIterator;
 
@@ -4916,6 +4917,9 @@ mod tests {
                 disk_size_in_gib: 20,
                 desired_nodes: None,
                 instance_architecture: CpuArchitecture::AMD64,
+                labels: BTreeMap::new(),
+                taints: vec![],
+                capacity_type: NodeGroupCapacityType::OnDemand,
             }
         );
     }
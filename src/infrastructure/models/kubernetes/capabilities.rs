@@ -0,0 +1,402 @@
+// Features of the api server we rely on, depending on its version. Using a field an older cluster does not serve ends
+// up in an opaque 422 error from the api server, so unsupported features are either downgraded with a warning or
+// rejected before anything is applied, naming the version needed.
+
+use crate::cmd::kubectl::kubectl_exec_version;
+use crate::cmd::structs::ServerVersion;
+use crate::environment::models::probe::{Probe, ProbeType};
+use crate::infrastructure::models::kubernetes::KubernetesVersion;
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClusterFeature {
+    /// `timeZone` of cron jobs, without it they run in the timezone of the controller manager
+    CronJobTimeZone,
+    /// `grpc` liveness and readiness probes
+    GrpcProbes,
+    /// Init containers with `restartPolicy: Always`, kept running alongside the main containers
+    NativeSidecars,
+    /// Pod security admission enforcing `pod-security.kubernetes.io` namespace labels
+    PodSecurityLabels,
+    /// Server side apply taking over fields owned by another field manager
+    ServerSideApplyForceOwnership,
+}
+
+impl ClusterFeature {
+    /// Minimum (major, minor) version of the api server serving the feature
+    pub fn minimum_version(&self) -> (u8, u8) {
+        match self {
+            ClusterFeature::CronJobTimeZone => (1, 27),
+            ClusterFeature::GrpcProbes => (1, 24),
+            ClusterFeature::NativeSidecars => (1, 29),
+            ClusterFeature::PodSecurityLabels => (1, 25),
+            ClusterFeature::ServerSideApplyForceOwnership => (1, 22),
+        }
+    }
+}
+
+impl Display for ClusterFeature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ClusterFeature::CronJobTimeZone => "cron job timezones",
+            ClusterFeature::GrpcProbes => "gRPC probes",
+            ClusterFeature::NativeSidecars => "native sidecar containers",
+            ClusterFeature::PodSecurityLabels => "pod security admission",
+            ClusterFeature::ServerSideApplyForceOwnership => "server side apply with forced ownership",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnsupportedClusterFeature {
+    pub feature: ClusterFeature,
+    pub server_version: (u8, u8),
+}
+
+impl Display for UnsupportedClusterFeature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (major, minor) = self.feature.minimum_version();
+        write!(
+            f,
+            "Kubernetes {}.{} or above is required for {}, but the cluster runs {}.{}",
+            major, minor, self.feature, self.server_version.0, self.server_version.1
+        )
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClusterCapabilities {
+    pub server_version: (u8, u8),
+    pub cronjob_timezone: bool,
+    pub grpc_probes: bool,
+    pub native_sidecars: bool,
+    pub pss_labels: bool,
+    pub server_side_apply_force_ownership: bool,
+}
+
+impl ClusterCapabilities {
+    pub fn new(major: u8, minor: u8) -> Self {
+        let supports = |feature: ClusterFeature| (major, minor) >= feature.minimum_version();
+        ClusterCapabilities {
+            server_version: (major, minor),
+            cronjob_timezone: supports(ClusterFeature::CronJobTimeZone),
+            grpc_probes: supports(ClusterFeature::GrpcProbes),
+            native_sidecars: supports(ClusterFeature::NativeSidecars),
+            pss_labels: supports(ClusterFeature::PodSecurityLabels),
+            server_side_apply_force_ownership: supports(ClusterFeature::ServerSideApplyForceOwnership),
+        }
+    }
+
+    pub fn from_version(version: &KubernetesVersion) -> Self {
+        Self::new(version.major(), version.minor())
+    }
+
+    /// Reads `kubectl version` output. Managed offers report minor versions like `29+`, so only leading digits count.
+    pub fn from_server_version(server_version: &ServerVersion) -> Option<Self> {
+        let parse = |raw: &str| -> Option<u8> {
+            let digits: String = raw.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        };
+
+        Some(Self::new(parse(&server_version.major)?, parse(&server_version.minor)?))
+    }
+
+    /// Capabilities of the api server actually running, which can lag behind the version declared for the cluster
+    /// (i.e: self-managed clusters, or an upgrade in progress). Falls back on the declared version if it cannot be
+    /// queried.
+    pub fn detect(
+        kubeconfig_path: Option<&Path>,
+        envs: Vec<(&str, &str)>,
+        declared_version: &KubernetesVersion,
+    ) -> Self {
+        let Some(kubeconfig_path) = kubeconfig_path else {
+            return Self::from_version(declared_version);
+        };
+
+        match kubectl_exec_version(kubeconfig_path, envs) {
+            Ok(version) => Self::from_server_version(&version.server_version).unwrap_or_else(|| {
+                warn!(
+                    "cannot parse api server version {}, using declared version {}",
+                    version.server_version.git_version, declared_version
+                );
+                Self::from_version(declared_version)
+            }),
+            Err(err) => {
+                warn!(
+                    "cannot get api server version, using declared version {}: {}",
+                    declared_version,
+                    err.message_safe()
+                );
+                Self::from_version(declared_version)
+            }
+        }
+    }
+
+    pub fn supports(&self, feature: ClusterFeature) -> bool {
+        match feature {
+            ClusterFeature::CronJobTimeZone => self.cronjob_timezone,
+            ClusterFeature::GrpcProbes => self.grpc_probes,
+            ClusterFeature::NativeSidecars => self.native_sidecars,
+            ClusterFeature::PodSecurityLabels => self.pss_labels,
+            ClusterFeature::ServerSideApplyForceOwnership => self.server_side_apply_force_ownership,
+        }
+    }
+
+    pub fn require(&self, feature: ClusterFeature) -> Result<(), UnsupportedClusterFeature> {
+        if self.supports(feature) {
+            return Ok(());
+        }
+
+        Err(UnsupportedClusterFeature {
+            feature,
+            server_version: self.server_version,
+        })
+    }
+
+    /// gRPC probes cannot be downgraded without changing what is checked, so they are rejected.
+    pub fn check_probes<'a>(
+        &self,
+        probes: impl IntoIterator<Item = &'a Probe>,
+    ) -> Result<(), UnsupportedClusterFeature> {
+        if probes
+            .into_iter()
+            .any(|probe| matches!(probe.r#type, ProbeType::Grpc { .. }))
+        {
+            return self.require(ClusterFeature::GrpcProbes);
+        }
+
+        Ok(())
+    }
+
+    /// Timezone to render in the cron job, if any. Older clusters run cron jobs in the timezone of the controller
+    /// manager, which is UTC on the clusters we manage: UTC schedules keep working without the field, while other
+    /// timezones are rejected instead of silently running at the wrong time.
+    pub fn cron_job_timezone<'a>(&self, timezone: &'a str) -> Result<Option<&'a str>, UnsupportedClusterFeature> {
+        if self.cronjob_timezone {
+            return Ok(Some(timezone));
+        }
+
+        match timezone {
+            "UTC" | "Etc/UTC" => Ok(None),
+            _ => self.require(ClusterFeature::CronJobTimeZone).map(|_| None),
+        }
+    }
+
+    /// On clusters without native sidecars, sidecar init containers of the object would block the pod from ever
+    /// starting. They are moved to regular containers instead, losing only the start ordering.
+    /// `object` is the body of the object, i.e: the one holding its `spec`.
+    /// Returns the names of the downgraded containers.
+    pub fn downgrade_native_sidecars(&self, kind: &str, object: &mut Value) -> Vec<String> {
+        if self.native_sidecars {
+            return vec![];
+        }
+
+        let pod_spec_path: &[&str] = match kind {
+            "Pod" => &["spec"],
+            "Deployment" | "StatefulSet" | "DaemonSet" | "ReplicaSet" | "Job" => &["spec", "template", "spec"],
+            "CronJob" => &["spec", "jobTemplate", "spec", "template", "spec"],
+            _ => return vec![],
+        };
+        let pod_spec = pod_spec_path.iter().try_fold(object, |value, key| value.get_mut(*key));
+        let Some(Value::Object(pod_spec)) = pod_spec else {
+            return vec![];
+        };
+        let Some(Value::Array(init_containers)) = pod_spec.get_mut("initContainers") else {
+            return vec![];
+        };
+
+        let (mut sidecars, init_containers): (Vec<Value>, Vec<Value>) = init_containers
+            .drain(..)
+            .partition(|container| container.get("restartPolicy").and_then(Value::as_str) == Some("Always"));
+        if sidecars.is_empty() {
+            pod_spec.insert("initContainers".to_string(), Value::Array(init_containers));
+            return vec![];
+        }
+
+        for sidecar in sidecars.iter_mut() {
+            if let Some(sidecar) = sidecar.as_object_mut() {
+                sidecar.remove("restartPolicy");
+            }
+        }
+        let names = sidecars
+            .iter()
+            .map(|c| c.get("name").and_then(Value::as_str).unwrap_or_default().to_string())
+            .collect();
+
+        if init_containers.is_empty() {
+            pod_spec.remove("initContainers");
+        } else {
+            pod_spec.insert("initContainers".to_string(), Value::Array(init_containers));
+        }
+        match pod_spec.get_mut("containers") {
+            Some(Value::Array(containers)) => containers.extend(sidecars),
+            _ => {
+                pod_spec.insert("containers".to_string(), Value::Array(sidecars));
+            }
+        }
+
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use strum::IntoEnumIterator;
+
+    fn probe(r#type: ProbeType) -> Probe {
+        Probe {
+            r#type,
+            port: 8080,
+            initial_delay_seconds: 0,
+            period_seconds: 10,
+            timeout_seconds: 5,
+            success_threshold: 1,
+            failure_threshold: 3,
+        }
+    }
+
+    fn deployment_with_init_containers(init_containers: Value) -> Value {
+        json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": "app" },
+            "spec": {
+                "template": {
+                    "spec": {
+                        "initContainers": init_containers,
+                        "containers": [{ "name": "app", "image": "app:1" }]
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_capabilities_per_kubernetes_version() {
+        for version in KubernetesVersion::iter() {
+            let capabilities = ClusterCapabilities::from_version(&version);
+            let minor = version.minor();
+
+            assert_eq!(capabilities.server_version, (1, minor));
+            assert_eq!(capabilities.cronjob_timezone, minor >= 27, "cron job timezones on 1.{minor}");
+            assert_eq!(capabilities.grpc_probes, minor >= 24, "grpc probes on 1.{minor}");
+            assert_eq!(capabilities.native_sidecars, minor >= 29, "native sidecars on 1.{minor}");
+            assert_eq!(capabilities.pss_labels, minor >= 25, "pss labels on 1.{minor}");
+            assert!(capabilities.server_side_apply_force_ownership, "ssa on 1.{minor}");
+        }
+    }
+
+    #[test]
+    fn test_from_server_version() {
+        let server_version = |major: &str, minor: &str| ServerVersion {
+            major: major.to_string(),
+            minor: minor.to_string(),
+            git_version: format!("v{major}.{minor}"),
+        };
+
+        assert_eq!(
+            ClusterCapabilities::from_server_version(&server_version("1", "29+")),
+            Some(ClusterCapabilities::new(1, 29))
+        );
+        assert_eq!(
+            ClusterCapabilities::from_server_version(&server_version("1", "23")).map(|c| c.grpc_probes),
+            Some(false)
+        );
+        assert_eq!(ClusterCapabilities::from_server_version(&server_version("1", "")), None);
+    }
+
+    #[test]
+    fn test_grpc_probes_are_rejected_on_old_clusters() {
+        let grpc = probe(ProbeType::Grpc { service: None });
+        let http = probe(ProbeType::Http {
+            path: "/".to_string(),
+            scheme: "HTTP".to_string(),
+        });
+
+        let old_cluster = ClusterCapabilities::new(1, 23);
+        assert!(old_cluster.check_probes([&http]).is_ok());
+        let err = old_cluster.check_probes([&http, &grpc]).unwrap_err();
+        assert_eq!(err.feature, ClusterFeature::GrpcProbes);
+        assert_eq!(
+            err.to_string(),
+            "Kubernetes 1.24 or above is required for gRPC probes, but the cluster runs 1.23"
+        );
+
+        assert!(ClusterCapabilities::new(1, 24).check_probes([&http, &grpc]).is_ok());
+    }
+
+    #[test]
+    fn test_cron_job_timezone_on_old_clusters() {
+        let new_cluster = ClusterCapabilities::new(1, 27);
+        assert_eq!(new_cluster.cron_job_timezone("Europe/Paris"), Ok(Some("Europe/Paris")));
+        assert_eq!(new_cluster.cron_job_timezone("Etc/UTC"), Ok(Some("Etc/UTC")));
+
+        // UTC is the default timezone, the field is just not rendered
+        let old_cluster = ClusterCapabilities::new(1, 26);
+        assert_eq!(old_cluster.cron_job_timezone("Etc/UTC"), Ok(None));
+        assert_eq!(old_cluster.cron_job_timezone("UTC"), Ok(None));
+        let err = old_cluster.cron_job_timezone("Europe/Paris").unwrap_err();
+        assert_eq!(err.feature, ClusterFeature::CronJobTimeZone);
+        assert_eq!(
+            err.to_string(),
+            "Kubernetes 1.27 or above is required for cron job timezones, but the cluster runs 1.26"
+        );
+    }
+
+    #[test]
+    fn test_native_sidecars_are_downgraded_on_old_clusters() {
+        let init_containers = json!([
+            { "name": "migrations", "image": "app:1" },
+            { "name": "proxy", "image": "proxy:1", "restartPolicy": "Always" }
+        ]);
+
+        // supported: nothing changes
+        let mut object = deployment_with_init_containers(init_containers.clone());
+        assert!(ClusterCapabilities::new(1, 29)
+            .downgrade_native_sidecars("Deployment", &mut object)
+            .is_empty());
+        assert_eq!(object, deployment_with_init_containers(init_containers.clone()));
+
+        // unsupported: the sidecar becomes a regular container
+        let mut object = deployment_with_init_containers(init_containers);
+        let downgraded = ClusterCapabilities::new(1, 28).downgrade_native_sidecars("Deployment", &mut object);
+        assert_eq!(downgraded, vec!["proxy".to_string()]);
+        assert_eq!(
+            object["spec"]["template"]["spec"]["initContainers"],
+            json!([{ "name": "migrations", "image": "app:1" }])
+        );
+        assert_eq!(
+            object["spec"]["template"]["spec"]["containers"],
+            json!([{ "name": "app", "image": "app:1" }, { "name": "proxy", "image": "proxy:1" }])
+        );
+    }
+
+    #[test]
+    fn test_downgrade_native_sidecars_of_cron_job() {
+        let mut object = json!({
+            "apiVersion": "batch/v1",
+            "kind": "CronJob",
+            "spec": { "jobTemplate": { "spec": { "template": { "spec": {
+                "initContainers": [{ "name": "proxy", "restartPolicy": "Always" }],
+                "containers": [{ "name": "job" }]
+            }}}}}
+        });
+
+        let downgraded = ClusterCapabilities::new(1, 27).downgrade_native_sidecars("CronJob", &mut object);
+
+        let pod_spec = &object["spec"]["jobTemplate"]["spec"]["template"]["spec"];
+        assert_eq!(downgraded, vec!["proxy".to_string()]);
+        assert!(pod_spec.get("initContainers").is_none());
+        assert_eq!(pod_spec["containers"], json!([{ "name": "job" }, { "name": "proxy" }]));
+
+        // objects without pod spec are left untouched
+        let mut config_map = json!({ "data": { "a": "b" } });
+        assert!(ClusterCapabilities::new(1, 27)
+            .downgrade_native_sidecars("ConfigMap", &mut config_map)
+            .is_empty());
+    }
+}
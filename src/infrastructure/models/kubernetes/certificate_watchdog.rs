@@ -0,0 +1,509 @@
+// Detects Qovery managed certificates cert-manager fails to renew silently, and decides when to force their re-issuance.
+// Only decisions live here, kubernetes calls are made by the watchdog in `infrastructure::certificate_watchdog_task`.
+
+use chrono::{DateTime, Duration, Utc};
+use kube::api::DynamicObject;
+use serde_json::{json, Value};
+use std::fmt::{Display, Formatter};
+
+/// Cluster issuer of the certificates Qovery manages, for routers and the cluster wildcard domain
+pub const QOVERY_CLUSTER_ISSUER: &str = "letsencrypt-qovery";
+/// Annotation set by cert-manager on the CertificateRequests of a Certificate
+pub const CERTIFICATE_NAME_ANNOTATION: &str = "cert-manager.io/certificate-name";
+pub const DEFAULT_RENEWAL_THRESHOLD_IN_DAYS: u32 = 14;
+// cert-manager retries failed issuances with an exponential backoff up to 32h, re-issuance shortcuts it but not more
+// than once per hour, to not hammer the ACME server
+const REISSUANCE_BACKOFF_IN_MINUTES: i64 = 60;
+
+/// What the watchdog needs from a cert-manager `Certificate`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchedCertificate {
+    pub name: String,
+    pub namespace: String,
+    pub dns_names: Vec<String>,
+    pub issuer: Option<String>,
+    pub not_after: Option<DateTime<Utc>>,
+    pub last_failure_time: Option<DateTime<Utc>>,
+    pub failed_issuance_attempts: u32,
+    pub ready: Option<CertificateCondition>,
+    pub issuing: Option<CertificateCondition>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertificateCondition {
+    pub status: String,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+}
+
+impl CertificateCondition {
+    fn is_true(&self) -> bool {
+        self.status == "True"
+    }
+}
+
+impl WatchedCertificate {
+    pub fn from_dynamic_object(certificate: &DynamicObject) -> Option<Self> {
+        let spec = certificate.data.get("spec");
+        let status = certificate.data.get("status");
+        let time = |field: &str| {
+            status
+                .and_then(|status| status.get(field))
+                .and_then(Value::as_str)
+                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                .map(|time| time.with_timezone(&Utc))
+        };
+        let condition = |type_: &str| {
+            status
+                .and_then(|status| status.get("conditions"))
+                .and_then(Value::as_array)?
+                .iter()
+                .find(|condition| condition.get("type").and_then(Value::as_str) == Some(type_))
+                .map(|condition| CertificateCondition {
+                    status: condition
+                        .get("status")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    reason: condition.get("reason").and_then(Value::as_str).map(str::to_string),
+                    message: condition.get("message").and_then(Value::as_str).map(str::to_string),
+                })
+        };
+
+        Some(WatchedCertificate {
+            name: certificate.metadata.name.clone()?,
+            namespace: certificate.metadata.namespace.clone().unwrap_or_default(),
+            dns_names: spec
+                .and_then(|spec| spec.get("dnsNames"))
+                .and_then(Value::as_array)
+                .map(|names| names.iter().filter_map(Value::as_str).map(str::to_string).collect())
+                .unwrap_or_default(),
+            issuer: spec
+                .and_then(|spec| spec.get("issuerRef"))
+                .and_then(|issuer| issuer.get("name"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            not_after: time("notAfter"),
+            last_failure_time: time("lastFailureTime"),
+            failed_issuance_attempts: status
+                .and_then(|status| status.get("failedIssuanceAttempts"))
+                .and_then(Value::as_u64)
+                .unwrap_or(0) as u32,
+            ready: condition("Ready"),
+            issuing: condition("Issuing"),
+        })
+    }
+
+    pub fn is_qovery_managed(&self) -> bool {
+        self.issuer.as_deref() == Some(QOVERY_CLUSTER_ISSUER)
+    }
+
+    fn failure_message(&self) -> Option<&str> {
+        // a failed issuance is reported on the Issuing condition, a broken certificate on the Ready one
+        self.issuing
+            .as_ref()
+            .filter(|issuing| !issuing.is_true() && issuing.reason.as_deref() == Some("Failed"))
+            .or(self.ready.as_ref().filter(|ready| !ready.is_true()))
+            .map(|condition| {
+                condition
+                    .message
+                    .as_deref()
+                    .or(condition.reason.as_deref())
+                    .unwrap_or_default()
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CertificateState {
+    Healthy,
+    /// cert-manager is issuing it right now
+    Renewing,
+    /// Valid, but should have been renewed already
+    ExpiringSoon,
+    Failing,
+    Expired,
+}
+
+impl Display for CertificateState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CertificateState::Healthy => "healthy",
+            CertificateState::Renewing => "renewing",
+            CertificateState::ExpiringSoon => "expiring soon",
+            CertificateState::Failing => "failing",
+            CertificateState::Expired => "expired",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureCause {
+    /// Let's Encrypt refuses new orders for the domain, retrying makes it worse
+    RateLimited,
+    /// The ACME challenge cannot be validated because the domain does not resolve to the cluster
+    Dns,
+    Other,
+}
+
+impl FailureCause {
+    fn from_message(message: &str) -> Self {
+        let message = message.to_lowercase();
+        if [
+            "ratelimited",
+            "rate limit",
+            "too many certificates",
+            "too many failed authorizations",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+        {
+            FailureCause::RateLimited
+        } else if [
+            "dns problem",
+            "nxdomain",
+            "no such host",
+            "no valid a records",
+            "servfail",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+        {
+            FailureCause::Dns
+        } else {
+            FailureCause::Other
+        }
+    }
+
+    pub fn remediation_hint(&self) -> &'static str {
+        match self {
+            FailureCause::RateLimited => {
+                "Let's Encrypt rate limit is reached for this domain. No new certificate can be issued until it resets, \
+                 which can take up to 7 days: avoid deleting and re-creating custom domains meanwhile, \
+                 and group subdomains under fewer registered domains when possible."
+            }
+            FailureCause::Dns => {
+                "The domain does not resolve to the cluster. Check the CNAME record of the custom domain points to \
+                 the domain given by Qovery, and that no CAA record forbids letsencrypt.org."
+            }
+            FailureCause::Other => "Check the events of the certificate and its CertificateRequests with kubectl describe.",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertificateCheck {
+    pub name: String,
+    pub namespace: String,
+    pub dns_names: Vec<String>,
+    pub not_after: Option<DateTime<Utc>>,
+    pub state: CertificateState,
+    pub cause: Option<FailureCause>,
+    pub message: Option<String>,
+}
+
+impl Display for CertificateCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.namespace, self.name)?;
+        if !self.dns_names.is_empty() {
+            write!(f, " ({})", self.dns_names.join(", "))?;
+        }
+        write!(f, " is {}", self.state)?;
+        if let Some(not_after) = self.not_after {
+            write!(f, ", valid until {}", not_after.to_rfc3339())?;
+        }
+        if let Some(message) = &self.message {
+            write!(f, ": {message}")?;
+        }
+        if let Some(cause) = &self.cause {
+            write!(f, ". {}", cause.remediation_hint())?;
+        }
+        Ok(())
+    }
+}
+
+pub fn classify_certificate(
+    certificate: &WatchedCertificate,
+    now: DateTime<Utc>,
+    renewal_threshold_in_days: u32,
+) -> CertificateCheck {
+    let failure_message = certificate.failure_message();
+    let issuing = certificate.issuing.as_ref().is_some_and(CertificateCondition::is_true);
+    let expiring = |threshold: DateTime<Utc>| certificate.not_after.is_some_and(|not_after| not_after <= threshold);
+
+    let state = if expiring(now) {
+        CertificateState::Expired
+    } else if issuing {
+        CertificateState::Renewing
+    } else if failure_message.is_some() {
+        CertificateState::Failing
+    } else if expiring(now + Duration::days(renewal_threshold_in_days as i64)) {
+        CertificateState::ExpiringSoon
+    } else {
+        CertificateState::Healthy
+    };
+
+    CertificateCheck {
+        name: certificate.name.clone(),
+        namespace: certificate.namespace.clone(),
+        dns_names: certificate.dns_names.clone(),
+        not_after: certificate.not_after,
+        state,
+        cause: failure_message
+            .filter(|_| state != CertificateState::Renewing)
+            .map(FailureCause::from_message),
+        message: failure_message.map(str::to_string),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReissuanceDecision {
+    NotNeeded,
+    Reissue,
+    Skip { reason: String },
+}
+
+pub fn reissuance_decision(
+    certificate: &WatchedCertificate,
+    check: &CertificateCheck,
+    now: DateTime<Utc>,
+) -> ReissuanceDecision {
+    match check.state {
+        CertificateState::Healthy | CertificateState::Renewing => return ReissuanceDecision::NotNeeded,
+        CertificateState::ExpiringSoon | CertificateState::Failing | CertificateState::Expired => {}
+    }
+
+    if check.cause == Some(FailureCause::RateLimited) {
+        return ReissuanceDecision::Skip {
+            reason: "a new order would be rate limited as well".to_string(),
+        };
+    }
+    if let Some(last_failure_time) = certificate.last_failure_time {
+        if now - last_failure_time < Duration::minutes(REISSUANCE_BACKOFF_IN_MINUTES) {
+            return ReissuanceDecision::Skip {
+                reason: format!("last attempt failed less than {REISSUANCE_BACKOFF_IN_MINUTES} minutes ago"),
+            };
+        }
+    }
+
+    ReissuanceDecision::Reissue
+}
+
+/// Conditions of the certificate once its re-issuance is triggered, the same way `cmctl renew` does it:
+/// cert-manager issues a new certificate as soon as the `Issuing` condition is true.
+pub fn conditions_triggering_issuance(certificate: &DynamicObject, now: DateTime<Utc>) -> Value {
+    let mut conditions: Vec<Value> = certificate
+        .data
+        .get("status")
+        .and_then(|status| status.get("conditions"))
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|condition| condition.get("type").and_then(Value::as_str) != Some("Issuing"))
+        .collect();
+    conditions.push(json!({
+        "type": "Issuing",
+        "status": "True",
+        "reason": "ManuallyTriggered",
+        "message": "Certificate re-issuance triggered by the Qovery certificate watchdog",
+        "lastTransitionTime": now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        "observedGeneration": certificate.metadata.generation,
+    }));
+
+    Value::Array(conditions)
+}
+
+/// A CertificateRequest left in a failed state blocks the next issuance until cert-manager backoff expires
+pub fn is_failed_certificate_request(certificate_request: &DynamicObject) -> bool {
+    certificate_request
+        .data
+        .get("status")
+        .and_then(|status| status.get("conditions"))
+        .and_then(Value::as_array)
+        .is_some_and(|conditions| {
+            conditions.iter().any(|condition| {
+                condition.get("type").and_then(Value::as_str) == Some("Ready")
+                    && condition.get("status").and_then(Value::as_str) == Some("False")
+                    && condition.get("reason").and_then(Value::as_str) == Some("Failed")
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn fixture(status: Value) -> DynamicObject {
+        serde_json::from_value(json!({
+            "apiVersion": "cert-manager.io/v1",
+            "kind": "Certificate",
+            "metadata": { "name": "router-z1234-tls", "namespace": "z1234-production", "generation": 2 },
+            "spec": {
+                "dnsNames": ["app.example.com"],
+                "issuerRef": { "kind": "ClusterIssuer", "name": QOVERY_CLUSTER_ISSUER },
+                "secretName": "router-z1234-tls",
+            },
+            "status": status,
+        }))
+        .unwrap()
+    }
+
+    fn check(status: Value) -> (WatchedCertificate, CertificateCheck) {
+        let certificate = WatchedCertificate::from_dynamic_object(&fixture(status)).unwrap();
+        let check = classify_certificate(&certificate, now(), DEFAULT_RENEWAL_THRESHOLD_IN_DAYS);
+        (certificate, check)
+    }
+
+    const READY: &str = "Certificate is up to date and has not expired";
+    const RATE_LIMITED: &str =
+        "The certificate request has failed to complete and will be retried: Failed to wait for \
+        order resource to become ready: order is in \"errored\" state: Failed to create Order: 429 \
+        urn:ietf:params:acme:error:rateLimited: Error creating new order :: too many certificates already issued";
+    const DNS_FAILURE: &str = "The certificate request has failed to complete and will be retried: \
+        acme: authorization error for app.example.com: 400 urn:ietf:params:acme:error:dns: DNS problem: NXDOMAIN";
+
+    #[test]
+    fn test_healthy_and_renewing_certificates_are_left_alone() {
+        let (certificate, healthy) = check(json!({
+            "notAfter": "2024-08-01T00:00:00Z",
+            "conditions": [{ "type": "Ready", "status": "True", "reason": "Ready", "message": READY }],
+        }));
+        assert_eq!(healthy.state, CertificateState::Healthy);
+        assert_eq!((healthy.cause, healthy.message.as_deref()), (None, None));
+        assert_eq!(certificate.dns_names, vec!["app.example.com".to_string()]);
+        assert!(certificate.is_qovery_managed());
+        assert_eq!(
+            reissuance_decision(&certificate, &healthy, now()),
+            ReissuanceDecision::NotNeeded
+        );
+
+        let (certificate, renewing) = check(json!({
+            "notAfter": "2024-06-10T00:00:00Z",
+            "conditions": [
+                { "type": "Ready", "status": "True", "reason": "Ready", "message": READY },
+                { "type": "Issuing", "status": "True", "reason": "Renewing", "message": "Renewing certificate" },
+            ],
+        }));
+        assert_eq!(renewing.state, CertificateState::Renewing);
+        assert_eq!(
+            reissuance_decision(&certificate, &renewing, now()),
+            ReissuanceDecision::NotNeeded
+        );
+    }
+
+    #[test]
+    fn test_certificates_not_renewed_in_time_are_reissued() {
+        let (certificate, check) = check(json!({
+            "notAfter": "2024-06-10T00:00:00Z",
+            "conditions": [{ "type": "Ready", "status": "True", "reason": "Ready", "message": READY }],
+        }));
+
+        assert_eq!(check.state, CertificateState::ExpiringSoon);
+        assert_eq!(reissuance_decision(&certificate, &check, now()), ReissuanceDecision::Reissue);
+    }
+
+    #[test]
+    fn test_failing_certificates_are_classified_by_cause() {
+        let (certificate, dns) = check(json!({
+            "notAfter": "2024-06-20T00:00:00Z",
+            "lastFailureTime": "2024-06-01T08:00:00Z",
+            "failedIssuanceAttempts": 3,
+            "conditions": [
+                { "type": "Ready", "status": "True", "reason": "Ready", "message": READY },
+                { "type": "Issuing", "status": "False", "reason": "Failed", "message": DNS_FAILURE },
+            ],
+        }));
+        assert_eq!(dns.state, CertificateState::Failing);
+        assert_eq!(dns.cause, Some(FailureCause::Dns));
+        assert_eq!(certificate.failed_issuance_attempts, 3);
+        assert_eq!(reissuance_decision(&certificate, &dns, now()), ReissuanceDecision::Reissue);
+        assert!(dns.to_string().contains("CNAME"));
+
+        // failed a few minutes ago, cert-manager backoff is not shortcut yet
+        let (certificate, recent_failure) = check(json!({
+            "notAfter": "2024-06-20T00:00:00Z",
+            "lastFailureTime": "2024-06-01T11:50:00Z",
+            "conditions": [{ "type": "Issuing", "status": "False", "reason": "Failed", "message": "timeout" }],
+        }));
+        assert_eq!(recent_failure.cause, Some(FailureCause::Other));
+        assert!(matches!(
+            reissuance_decision(&certificate, &recent_failure, now()),
+            ReissuanceDecision::Skip { .. }
+        ));
+    }
+
+    #[test]
+    fn test_rate_limited_certificates_are_never_reissued() {
+        let (certificate, check) = check(json!({
+            "notAfter": "2024-05-31T00:00:00Z",
+            "lastFailureTime": "2024-05-30T00:00:00Z",
+            "conditions": [
+                { "type": "Ready", "status": "False", "reason": "Expired", "message": "Certificate expired" },
+                { "type": "Issuing", "status": "False", "reason": "Failed", "message": RATE_LIMITED },
+            ],
+        }));
+
+        assert_eq!(check.state, CertificateState::Expired);
+        assert_eq!(check.cause, Some(FailureCause::RateLimited));
+        assert_eq!(
+            reissuance_decision(&certificate, &check, now()),
+            ReissuanceDecision::Skip {
+                reason: "a new order would be rate limited as well".to_string()
+            }
+        );
+        assert!(check.to_string().contains("rate limit is reached"));
+    }
+
+    #[test]
+    fn test_never_issued_certificates_are_failing() {
+        let (certificate, check) = check(json!({
+            "conditions": [{ "type": "Ready", "status": "False", "reason": "DoesNotExist", "message": "Issuing certificate as Secret does not exist" }],
+        }));
+
+        assert_eq!(check.state, CertificateState::Failing);
+        assert_eq!(reissuance_decision(&certificate, &check, now()), ReissuanceDecision::Reissue);
+    }
+
+    #[test]
+    fn test_reissuance_sets_the_issuing_condition() {
+        let certificate = fixture(json!({
+            "conditions": [
+                { "type": "Ready", "status": "True", "reason": "Ready" },
+                { "type": "Issuing", "status": "False", "reason": "Failed" },
+            ],
+        }));
+
+        let conditions = conditions_triggering_issuance(&certificate, now());
+
+        assert_eq!(conditions.as_array().unwrap().len(), 2);
+        assert_eq!(conditions[0]["type"], "Ready");
+        assert_eq!(conditions[1]["type"], "Issuing");
+        assert_eq!(conditions[1]["status"], "True");
+        assert_eq!(conditions[1]["reason"], "ManuallyTriggered");
+        assert_eq!(conditions[1]["lastTransitionTime"], "2024-06-01T12:00:00Z");
+        assert_eq!(conditions[1]["observedGeneration"], 2);
+    }
+
+    #[test]
+    fn test_failed_certificate_requests() {
+        let request = |status: &str, reason: &str| -> DynamicObject {
+            serde_json::from_value(json!({
+                "apiVersion": "cert-manager.io/v1",
+                "kind": "CertificateRequest",
+                "metadata": { "name": "router-z1234-tls-1", "namespace": "z1234-production" },
+                "status": { "conditions": [{ "type": "Ready", "status": status, "reason": reason }] },
+            }))
+            .unwrap()
+        };
+
+        assert!(is_failed_certificate_request(&request("False", "Failed")));
+        assert!(!is_failed_certificate_request(&request("False", "Pending")));
+        assert!(!is_failed_certificate_request(&request("True", "Issued")));
+    }
+}
@@ -0,0 +1,362 @@
+// Tuning of the cluster autoscaler exposed through the cluster advanced settings. Only a validated subset of the
+// autoscaler flags is accepted, rendered differently per provider: chart arguments on EKS, terraform autoscaler
+// config on Kapsule. Karpenter replaces the autoscaler, so none of them can be set on a Karpenter enabled cluster.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+pub const SCALE_DOWN_DELAY_AFTER_ADD_SETTING: &str = "cluster_autoscaler.scale_down_delay_after_add";
+pub const SCALE_DOWN_UNNEEDED_TIME_SETTING: &str = "cluster_autoscaler.scale_down_unneeded_time";
+pub const SCALE_DOWN_UTILIZATION_THRESHOLD_SETTING: &str = "cluster_autoscaler.scale_down_utilization_threshold";
+pub const EXPANDER_SETTING: &str = "cluster_autoscaler.expander";
+pub const BALANCE_SIMILAR_NODE_GROUPS_SETTING: &str = "cluster_autoscaler.balance_similar_node_groups";
+
+// Longer durations are most likely a typo and would prevent the cluster from ever scaling down
+const MAX_DURATION_IN_SECONDS: u64 = 24 * 3600;
+static DURATION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:(\d+)h)?(?:(\d+)m)?(?:(\d+)s)?$").expect("invalid duration regex"));
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ClusterAutoscalerSettingsError {
+    #[error("Invalid value `{value}` for `{setting}`: {reason}")]
+    InvalidValue {
+        setting: &'static str,
+        value: String,
+        reason: String,
+    },
+    #[error(
+        "Karpenter replaces the cluster autoscaler on this cluster, the following settings cannot be set: {}",
+        settings.join(", ")
+    )]
+    ConflictsWithKarpenter { settings: Vec<&'static str> },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClusterAutoscalerExpander {
+    Random,
+    MostPods,
+    LeastWaste,
+}
+
+impl ClusterAutoscalerExpander {
+    const ALL: [ClusterAutoscalerExpander; 3] = [
+        ClusterAutoscalerExpander::Random,
+        ClusterAutoscalerExpander::MostPods,
+        ClusterAutoscalerExpander::LeastWaste,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ClusterAutoscalerExpander::Random => "random",
+            ClusterAutoscalerExpander::MostPods => "most-pods",
+            ClusterAutoscalerExpander::LeastWaste => "least-waste",
+        }
+    }
+
+    // Scaleway API uses snake case names for the same expanders
+    fn to_scaleway_format(self) -> String {
+        self.as_str().replace('-', "_")
+    }
+}
+
+impl Display for ClusterAutoscalerExpander {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ClusterAutoscalerExpander {
+    type Err = ClusterAutoscalerSettingsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ClusterAutoscalerExpander::ALL
+            .into_iter()
+            .find(|expander| expander.as_str() == s)
+            .ok_or_else(|| ClusterAutoscalerSettingsError::InvalidValue {
+                setting: EXPANDER_SETTING,
+                value: s.to_string(),
+                reason: format!(
+                    "supported expanders are {}",
+                    ClusterAutoscalerExpander::ALL
+                        .map(|expander| expander.as_str())
+                        .join(", ")
+                ),
+            })
+    }
+}
+
+/// Autoscaler settings as set by the user in the cluster advanced settings, `None` keeps the Qovery default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClusterAutoscalerOverrides {
+    pub scale_down_delay_after_add: Option<String>,
+    pub scale_down_unneeded_time: Option<String>,
+    pub scale_down_utilization_threshold: Option<f64>,
+    pub expander: Option<String>,
+    pub balance_similar_node_groups: Option<bool>,
+}
+
+impl ClusterAutoscalerOverrides {
+    fn settings_set(&self) -> Vec<&'static str> {
+        [
+            (self.scale_down_delay_after_add.is_some(), SCALE_DOWN_DELAY_AFTER_ADD_SETTING),
+            (self.scale_down_unneeded_time.is_some(), SCALE_DOWN_UNNEEDED_TIME_SETTING),
+            (
+                self.scale_down_utilization_threshold.is_some(),
+                SCALE_DOWN_UTILIZATION_THRESHOLD_SETTING,
+            ),
+            (self.expander.is_some(), EXPANDER_SETTING),
+            (self.balance_similar_node_groups.is_some(), BALANCE_SIMILAR_NODE_GROUPS_SETTING),
+        ]
+        .into_iter()
+        .filter_map(|(is_set, setting)| is_set.then_some(setting))
+        .collect()
+    }
+}
+
+/// Validated autoscaler settings, with the Qovery defaults applied to the ones not overridden.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClusterAutoscalerSettings {
+    pub scale_down_delay_after_add: String,
+    pub scale_down_unneeded_time: String,
+    pub scale_down_utilization_threshold: f64,
+    pub expander: ClusterAutoscalerExpander,
+    pub balance_similar_node_groups: bool,
+}
+
+impl Default for ClusterAutoscalerSettings {
+    fn default() -> Self {
+        ClusterAutoscalerSettings {
+            scale_down_delay_after_add: "10m".to_string(),
+            scale_down_unneeded_time: "10m".to_string(),
+            scale_down_utilization_threshold: 0.5,
+            expander: ClusterAutoscalerExpander::Random,
+            balance_similar_node_groups: true,
+        }
+    }
+}
+
+impl ClusterAutoscalerSettings {
+    pub fn new(
+        overrides: &ClusterAutoscalerOverrides,
+        is_karpenter_enabled: bool,
+    ) -> Result<Self, ClusterAutoscalerSettingsError> {
+        let settings_set = overrides.settings_set();
+        if is_karpenter_enabled && !settings_set.is_empty() {
+            return Err(ClusterAutoscalerSettingsError::ConflictsWithKarpenter { settings: settings_set });
+        }
+
+        let default = ClusterAutoscalerSettings::default();
+        let scale_down_utilization_threshold = match overrides.scale_down_utilization_threshold {
+            Some(threshold) if !(threshold > 0.0 && threshold <= 1.0) => {
+                return Err(ClusterAutoscalerSettingsError::InvalidValue {
+                    setting: SCALE_DOWN_UTILIZATION_THRESHOLD_SETTING,
+                    value: threshold.to_string(),
+                    reason: "it must be greater than 0 and lower or equal to 1".to_string(),
+                })
+            }
+            Some(threshold) => threshold,
+            None => default.scale_down_utilization_threshold,
+        };
+
+        Ok(ClusterAutoscalerSettings {
+            scale_down_delay_after_add: match &overrides.scale_down_delay_after_add {
+                Some(duration) => validate_duration(SCALE_DOWN_DELAY_AFTER_ADD_SETTING, duration)?,
+                None => default.scale_down_delay_after_add,
+            },
+            scale_down_unneeded_time: match &overrides.scale_down_unneeded_time {
+                Some(duration) => validate_duration(SCALE_DOWN_UNNEEDED_TIME_SETTING, duration)?,
+                None => default.scale_down_unneeded_time,
+            },
+            scale_down_utilization_threshold,
+            expander: match &overrides.expander {
+                Some(expander) => ClusterAutoscalerExpander::from_str(expander)?,
+                None => default.expander,
+            },
+            balance_similar_node_groups: overrides
+                .balance_similar_node_groups
+                .unwrap_or(default.balance_similar_node_groups),
+        })
+    }
+
+    /// Arguments of the cluster autoscaler container, as expected under `extraArgs` by the EKS chart.
+    pub fn to_eks_extra_args(&self) -> BTreeMap<&'static str, String> {
+        BTreeMap::from([
+            ("scale-down-delay-after-add", self.scale_down_delay_after_add.clone()),
+            ("scale-down-unneeded-time", self.scale_down_unneeded_time.clone()),
+            (
+                "scale-down-utilization-threshold",
+                self.scale_down_utilization_threshold.to_string(),
+            ),
+            ("expander", self.expander.to_string()),
+            ("balance-similar-node-groups", self.balance_similar_node_groups.to_string()),
+        ])
+    }
+
+    /// Attributes of the Kapsule `autoscaler_config` terraform block, values are already formatted as terraform literals.
+    pub fn to_scaleway_autoscaler_config(&self) -> BTreeMap<&'static str, String> {
+        BTreeMap::from([
+            ("scale_down_delay_after_add", format!("\"{}\"", self.scale_down_delay_after_add)),
+            ("scale_down_unneeded_time", format!("\"{}\"", self.scale_down_unneeded_time)),
+            (
+                "scale_down_utilization_threshold",
+                self.scale_down_utilization_threshold.to_string(),
+            ),
+            ("expander", format!("\"{}\"", self.expander.to_scaleway_format())),
+            ("balance_similar_node_groups", self.balance_similar_node_groups.to_string()),
+        ])
+    }
+}
+
+fn validate_duration(setting: &'static str, duration: &str) -> Result<String, ClusterAutoscalerSettingsError> {
+    let invalid = |reason: &str| ClusterAutoscalerSettingsError::InvalidValue {
+        setting,
+        value: duration.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let captures = DURATION_REGEX
+        .captures(duration)
+        .filter(|_| !duration.is_empty())
+        .ok_or_else(|| invalid("expected a duration such as `30s`, `10m` or `1h30m`"))?;
+    let seconds = [(1, 3600), (2, 60), (3, 1)]
+        .into_iter()
+        .filter_map(|(group, unit)| {
+            captures
+                .get(group)
+                .map(|value| value.as_str().parse::<u64>().unwrap_or(u64::MAX).saturating_mul(unit))
+        })
+        .fold(0u64, |total, seconds| total.saturating_add(seconds));
+    if seconds > MAX_DURATION_IN_SECONDS {
+        return Err(invalid("it must not exceed 24h"));
+    }
+
+    Ok(duration.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_are_kept_when_nothing_is_overridden() {
+        let settings = ClusterAutoscalerSettings::new(&ClusterAutoscalerOverrides::default(), false).unwrap();
+        assert_eq!(settings, ClusterAutoscalerSettings::default());
+
+        // Karpenter clusters accept the defaults, the autoscaler is not deployed anyway
+        assert!(ClusterAutoscalerSettings::new(&ClusterAutoscalerOverrides::default(), true).is_ok());
+    }
+
+    #[test]
+    fn test_mapping_table() {
+        let settings = ClusterAutoscalerSettings::new(
+            &ClusterAutoscalerOverrides {
+                scale_down_delay_after_add: Some("1h30m".to_string()),
+                scale_down_unneeded_time: Some("90s".to_string()),
+                scale_down_utilization_threshold: Some(0.65),
+                expander: Some("least-waste".to_string()),
+                balance_similar_node_groups: Some(false),
+            },
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings.to_eks_extra_args(),
+            BTreeMap::from([
+                ("scale-down-delay-after-add", "1h30m".to_string()),
+                ("scale-down-unneeded-time", "90s".to_string()),
+                ("scale-down-utilization-threshold", "0.65".to_string()),
+                ("expander", "least-waste".to_string()),
+                ("balance-similar-node-groups", "false".to_string()),
+            ])
+        );
+        assert_eq!(
+            settings.to_scaleway_autoscaler_config(),
+            BTreeMap::from([
+                ("scale_down_delay_after_add", "\"1h30m\"".to_string()),
+                ("scale_down_unneeded_time", "\"90s\"".to_string()),
+                ("scale_down_utilization_threshold", "0.65".to_string()),
+                ("expander", "\"least_waste\"".to_string()),
+                ("balance_similar_node_groups", "false".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        let test_cases = vec![
+            (
+                ClusterAutoscalerOverrides {
+                    scale_down_delay_after_add: Some("10 minutes".to_string()),
+                    ..Default::default()
+                },
+                SCALE_DOWN_DELAY_AFTER_ADD_SETTING,
+            ),
+            (
+                ClusterAutoscalerOverrides {
+                    scale_down_unneeded_time: Some("".to_string()),
+                    ..Default::default()
+                },
+                SCALE_DOWN_UNNEEDED_TIME_SETTING,
+            ),
+            (
+                ClusterAutoscalerOverrides {
+                    scale_down_unneeded_time: Some("25h".to_string()),
+                    ..Default::default()
+                },
+                SCALE_DOWN_UNNEEDED_TIME_SETTING,
+            ),
+            (
+                ClusterAutoscalerOverrides {
+                    scale_down_utilization_threshold: Some(0.0),
+                    ..Default::default()
+                },
+                SCALE_DOWN_UTILIZATION_THRESHOLD_SETTING,
+            ),
+            (
+                ClusterAutoscalerOverrides {
+                    scale_down_utilization_threshold: Some(1.5),
+                    ..Default::default()
+                },
+                SCALE_DOWN_UTILIZATION_THRESHOLD_SETTING,
+            ),
+            (
+                ClusterAutoscalerOverrides {
+                    expander: Some("priority".to_string()),
+                    ..Default::default()
+                },
+                EXPANDER_SETTING,
+            ),
+        ];
+
+        for (overrides, expected_setting) in test_cases {
+            match ClusterAutoscalerSettings::new(&overrides, false) {
+                Err(ClusterAutoscalerSettingsError::InvalidValue { setting, .. }) => {
+                    assert_eq!(setting, expected_setting, "{overrides:?}")
+                }
+                other => panic!("expected an invalid value error for {overrides:?}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_settings_are_mutually_exclusive_with_karpenter() {
+        let overrides = ClusterAutoscalerOverrides {
+            scale_down_unneeded_time: Some("5m".to_string()),
+            balance_similar_node_groups: Some(true),
+            ..Default::default()
+        };
+
+        assert!(ClusterAutoscalerSettings::new(&overrides, false).is_ok());
+        assert_eq!(
+            ClusterAutoscalerSettings::new(&overrides, true),
+            Err(ClusterAutoscalerSettingsError::ConflictsWithKarpenter {
+                settings: vec![SCALE_DOWN_UNNEEDED_TIME_SETTING, BALANCE_SIMILAR_NODE_GROUPS_SETTING],
+            })
+        );
+    }
+}
@@ -0,0 +1,576 @@
+// Health document of a self-managed cluster. The engine has no other way to notice a dead BYOK cluster than a failed
+// deployment, so the core schedules a report for each of them and relies on this schema, stored as is in object
+// storage. Everything here is pure: the task only gathers the observations.
+
+use crate::cmd::structs::HelmChart;
+use crate::io_models::cluster_health::ExpectedChart;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::Node;
+use kube::api::DynamicObject;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+/// Bumped on every breaking change of the document, the core refuses versions it does not know.
+pub const CLUSTER_HEALTH_DOCUMENT_VERSION: u32 = 1;
+// Unhealthy reports in a row before a degraded cluster is reported as an error
+const DEGRADED_ESCALATION_THRESHOLD: u32 = 3;
+// Unhealthy reports in a row before an unreachable cluster is reported as an error, then as critical
+const UNREACHABLE_ERROR_THRESHOLD: u32 = 2;
+const UNREACHABLE_CRITICAL_THRESHOLD: u32 = 3;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ClusterHealthStatus {
+    Healthy,
+    /// The api server answers, but something needs attention
+    Degraded,
+    /// The api server cannot be reached with the engine credentials
+    Unreachable,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ClusterHealthSeverity {
+    Ok,
+    Warning,
+    Error,
+    Critical,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ConnectivityCheck {
+    pub reachable: bool,
+    pub kubernetes_version: Option<String>,
+    pub error: Option<String>,
+}
+
+impl ConnectivityCheck {
+    pub fn reachable(kubernetes_version: String) -> Self {
+        ConnectivityCheck {
+            reachable: true,
+            kubernetes_version: Some(kubernetes_version),
+            error: None,
+        }
+    }
+
+    pub fn unreachable(error: String) -> Self {
+        ConnectivityCheck {
+            reachable: false,
+            kubernetes_version: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChartSkewKind {
+    /// The release is not installed
+    Missing,
+    /// The installed version is older than the expected one
+    Behind,
+    /// The installed version is newer than the expected one, i.e the cluster was upgraded by hand
+    Ahead,
+    /// One of the versions cannot be parsed, and they differ
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ChartSkew {
+    pub name: String,
+    pub namespace: String,
+    pub expected_version: String,
+    pub installed_version: Option<String>,
+    pub kind: ChartSkewKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Default)]
+pub struct NodeReadiness {
+    pub total: usize,
+    pub ready: usize,
+    pub not_ready: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ObservedCertificate {
+    pub name: String,
+    pub namespace: String,
+    pub not_after: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ExpiringCertificate {
+    pub name: String,
+    pub namespace: String,
+    pub not_after: DateTime<Utc>,
+    pub expired: bool,
+}
+
+/// Raw results of the checks run against the cluster.
+/// Nodes and certificates cannot be observed when the cluster is unreachable.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClusterHealthObservations {
+    pub connectivity: ConnectivityCheck,
+    pub chart_skews: Vec<ChartSkew>,
+    pub nodes: Option<NodeReadiness>,
+    pub expiring_certificates: Vec<ExpiringCertificate>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ClusterHealthDocument {
+    pub version: u32,
+    pub cluster_id: Uuid,
+    pub generated_at: DateTime<Utc>,
+    pub status: ClusterHealthStatus,
+    pub severity: ClusterHealthSeverity,
+    /// Unhealthy reports in a row, this one included. To be sent back in the next request.
+    pub consecutive_failures: u32,
+    pub connectivity: ConnectivityCheck,
+    pub chart_skews: Vec<ChartSkew>,
+    pub nodes: Option<NodeReadiness>,
+    pub expiring_certificates: Vec<ExpiringCertificate>,
+}
+
+impl ClusterHealthDocument {
+    pub fn new(
+        cluster_id: Uuid,
+        generated_at: DateTime<Utc>,
+        previous_consecutive_failures: u32,
+        observations: ClusterHealthObservations,
+    ) -> Self {
+        let status = health_status(&observations);
+        let (consecutive_failures, mut severity) = escalate_severity(status, previous_consecutive_failures);
+        // An expired certificate already breaks traffic, no need to wait for it to happen again
+        if observations.expiring_certificates.iter().any(|cert| cert.expired) {
+            severity = severity.max(ClusterHealthSeverity::Error);
+        }
+
+        ClusterHealthDocument {
+            version: CLUSTER_HEALTH_DOCUMENT_VERSION,
+            cluster_id,
+            generated_at,
+            status,
+            severity,
+            consecutive_failures,
+            connectivity: observations.connectivity,
+            chart_skews: observations.chart_skews,
+            nodes: observations.nodes,
+            expiring_certificates: observations.expiring_certificates,
+        }
+    }
+}
+
+impl Display for ClusterHealthDocument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.status {
+            ClusterHealthStatus::Healthy => return write!(f, "Cluster is healthy"),
+            ClusterHealthStatus::Unreachable => {
+                return write!(
+                    f,
+                    "Cluster is unreachable for {} report(s) in a row: {}",
+                    self.consecutive_failures,
+                    self.connectivity.error.as_deref().unwrap_or("unknown error")
+                )
+            }
+            ClusterHealthStatus::Degraded => {}
+        }
+
+        let mut issues = vec![];
+        if !self.chart_skews.is_empty() {
+            issues.push(format!(
+                "{} Qovery chart(s) not at the expected version",
+                self.chart_skews.len()
+            ));
+        }
+        match &self.nodes {
+            Some(nodes) if nodes.ready < nodes.total || nodes.total == 0 => {
+                issues.push(format!("{}/{} node(s) ready", nodes.ready, nodes.total))
+            }
+            Some(_) => {}
+            None => issues.push("nodes cannot be listed".to_string()),
+        }
+        if !self.expiring_certificates.is_empty() {
+            issues.push(format!(
+                "{} certificate(s) expired or about to expire",
+                self.expiring_certificates.len()
+            ));
+        }
+
+        write!(
+            f,
+            "Cluster is degraded for {} report(s) in a row: {}",
+            self.consecutive_failures,
+            issues.join(", ")
+        )
+    }
+}
+
+pub fn health_status(observations: &ClusterHealthObservations) -> ClusterHealthStatus {
+    if !observations.connectivity.reachable {
+        return ClusterHealthStatus::Unreachable;
+    }
+
+    let nodes_not_ready = match &observations.nodes {
+        Some(nodes) => nodes.total == 0 || nodes.ready < nodes.total,
+        None => true,
+    };
+    if nodes_not_ready || !observations.chart_skews.is_empty() || !observations.expiring_certificates.is_empty() {
+        return ClusterHealthStatus::Degraded;
+    }
+
+    ClusterHealthStatus::Healthy
+}
+
+/// Returns the number of unhealthy reports in a row and the severity to report.
+/// A single unhealthy report is only a warning, as a self-managed cluster can be briefly unreachable during an upgrade.
+pub fn escalate_severity(
+    status: ClusterHealthStatus,
+    previous_consecutive_failures: u32,
+) -> (u32, ClusterHealthSeverity) {
+    let consecutive_failures = previous_consecutive_failures.saturating_add(1);
+    let severity = match status {
+        ClusterHealthStatus::Healthy => return (0, ClusterHealthSeverity::Ok),
+        ClusterHealthStatus::Degraded if consecutive_failures >= DEGRADED_ESCALATION_THRESHOLD => {
+            ClusterHealthSeverity::Error
+        }
+        ClusterHealthStatus::Degraded => ClusterHealthSeverity::Warning,
+        ClusterHealthStatus::Unreachable if consecutive_failures >= UNREACHABLE_CRITICAL_THRESHOLD => {
+            ClusterHealthSeverity::Critical
+        }
+        ClusterHealthStatus::Unreachable if consecutive_failures >= UNREACHABLE_ERROR_THRESHOLD => {
+            ClusterHealthSeverity::Error
+        }
+        ClusterHealthStatus::Unreachable => ClusterHealthSeverity::Warning,
+    };
+
+    (consecutive_failures, severity)
+}
+
+fn parse_version(version: &str) -> Option<Version> {
+    Version::parse(version.trim_start_matches('v')).ok()
+}
+
+/// Compares the installed releases with the expected Qovery charts, releases not managed by Qovery are ignored.
+pub fn chart_skews(expected_charts: &[ExpectedChart], installed_charts: &[HelmChart]) -> Vec<ChartSkew> {
+    expected_charts
+        .iter()
+        .filter_map(|expected| {
+            let installed = installed_charts
+                .iter()
+                .find(|chart| chart.name == expected.name && chart.namespace == expected.namespace);
+            let installed_version = installed.and_then(|chart| chart.chart_version.as_ref());
+
+            let kind = match (installed, installed_version, parse_version(&expected.version)) {
+                (None, _, _) => ChartSkewKind::Missing,
+                (Some(_), Some(installed), Some(wanted)) if *installed == wanted => return None,
+                (Some(_), Some(installed), Some(wanted)) if *installed < wanted => ChartSkewKind::Behind,
+                (Some(_), Some(_), Some(_)) => ChartSkewKind::Ahead,
+                (Some(_), _, _) => ChartSkewKind::Unknown,
+            };
+
+            Some(ChartSkew {
+                name: expected.name.clone(),
+                namespace: expected.namespace.clone(),
+                expected_version: expected.version.clone(),
+                installed_version: installed_version.map(|version| version.to_string()),
+                kind,
+            })
+        })
+        .collect()
+}
+
+fn is_node_ready(node: &Node) -> bool {
+    node.status
+        .as_ref()
+        .and_then(|status| status.conditions.as_ref())
+        .and_then(|conditions| conditions.iter().find(|condition| condition.type_ == "Ready"))
+        .map(|condition| condition.status == "True")
+        .unwrap_or(false)
+}
+
+pub fn node_readiness(nodes: &[Node]) -> NodeReadiness {
+    let mut not_ready: Vec<String> = nodes
+        .iter()
+        .filter(|node| !is_node_ready(node))
+        .map(|node| node.metadata.name.clone().unwrap_or_default())
+        .collect();
+    not_ready.sort();
+
+    NodeReadiness {
+        total: nodes.len(),
+        ready: nodes.len() - not_ready.len(),
+        not_ready,
+    }
+}
+
+/// Reads a cert-manager `Certificate`, skipping the ones never issued as they have no expiration yet.
+pub fn observed_certificate(certificate: &DynamicObject) -> Option<ObservedCertificate> {
+    let not_after = certificate.data.get("status")?.get("notAfter")?.as_str()?;
+    let not_after = DateTime::parse_from_rfc3339(not_after).ok()?.with_timezone(&Utc);
+
+    Some(ObservedCertificate {
+        name: certificate.metadata.name.clone()?,
+        namespace: certificate.metadata.namespace.clone().unwrap_or_default(),
+        not_after,
+    })
+}
+
+pub fn expiring_certificates(
+    certificates: &[ObservedCertificate],
+    now: DateTime<Utc>,
+    warning_in_days: u32,
+) -> Vec<ExpiringCertificate> {
+    let warn_after = now + chrono::Duration::days(warning_in_days as i64);
+    let mut expiring: Vec<ExpiringCertificate> = certificates
+        .iter()
+        .filter(|cert| cert.not_after <= warn_after)
+        .map(|cert| ExpiringCertificate {
+            name: cert.name.clone(),
+            namespace: cert.namespace.clone(),
+            not_after: cert.not_after,
+            expired: cert.not_after <= now,
+        })
+        .collect();
+    expiring.sort_by_key(|cert| cert.not_after);
+
+    expiring
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use k8s_openapi::api::core::v1::{NodeCondition, NodeStatus};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+    fn node(name: &str, ready: Option<&str>) -> Node {
+        Node {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                ..Default::default()
+            },
+            status: Some(NodeStatus {
+                conditions: ready.map(|status| {
+                    vec![NodeCondition {
+                        type_: "Ready".to_string(),
+                        status: status.to_string(),
+                        ..Default::default()
+                    }]
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn expected(name: &str, version: &str) -> ExpectedChart {
+        ExpectedChart {
+            name: name.to_string(),
+            namespace: "qovery".to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    fn installed(name: &str, version: Option<&str>) -> HelmChart {
+        HelmChart::new(
+            name.to_string(),
+            "qovery".to_string(),
+            version.map(|v| Version::parse(v).unwrap()),
+            None,
+        )
+    }
+
+    fn healthy_observations() -> ClusterHealthObservations {
+        ClusterHealthObservations {
+            connectivity: ConnectivityCheck::reachable("v1.30.2".to_string()),
+            chart_skews: vec![],
+            nodes: Some(NodeReadiness {
+                total: 2,
+                ready: 2,
+                not_ready: vec![],
+            }),
+            expiring_certificates: vec![],
+        }
+    }
+
+    #[test]
+    fn test_chart_skews() {
+        let expected_charts = vec![
+            expected("qovery-cluster-agent", "0.4.2"),
+            expected("qovery-shell-agent", "0.3.0"),
+            expected("qovery-engine", "v1.2.0"),
+            expected("qovery-priority-class", "0.1.0"),
+            expected("cert-manager-configs", "0.2.0"),
+        ];
+        let installed_charts = vec![
+            installed("qovery-cluster-agent", Some("0.4.2")),
+            installed("qovery-shell-agent", Some("0.2.9")),
+            installed("qovery-engine", Some("1.3.0")),
+            installed("cert-manager-configs", None),
+            installed("my-own-release", Some("1.0.0")),
+        ];
+
+        let skews = chart_skews(&expected_charts, &installed_charts);
+
+        let kinds: Vec<(&str, ChartSkewKind)> = skews.iter().map(|s| (s.name.as_str(), s.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("qovery-shell-agent", ChartSkewKind::Behind),
+                ("qovery-engine", ChartSkewKind::Ahead),
+                ("qovery-priority-class", ChartSkewKind::Missing),
+                ("cert-manager-configs", ChartSkewKind::Unknown),
+            ]
+        );
+        assert_eq!(skews[0].installed_version.as_deref(), Some("0.2.9"));
+        assert_eq!(skews[2].installed_version, None);
+    }
+
+    #[test]
+    fn test_node_readiness() {
+        let nodes = vec![
+            node("node-c", Some("True")),
+            node("node-b", Some("Unknown")),
+            node("node-a", None),
+        ];
+
+        assert_eq!(
+            node_readiness(&nodes),
+            NodeReadiness {
+                total: 3,
+                ready: 1,
+                not_ready: vec!["node-a".to_string(), "node-b".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_expiring_certificates() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let cert = |name: &str, not_after: DateTime<Utc>| ObservedCertificate {
+            name: name.to_string(),
+            namespace: "qovery".to_string(),
+            not_after,
+        };
+        let certificates = vec![
+            cert("far", now + chrono::Duration::days(60)),
+            cert("soon", now + chrono::Duration::days(3)),
+            cert("expired", now - chrono::Duration::hours(1)),
+        ];
+
+        let expiring = expiring_certificates(&certificates, now, 14);
+
+        let names: Vec<(&str, bool)> = expiring.iter().map(|c| (c.name.as_str(), c.expired)).collect();
+        assert_eq!(names, vec![("expired", true), ("soon", false)]);
+    }
+
+    #[test]
+    fn test_observed_certificate() {
+        let certificate: DynamicObject = serde_json::from_value(serde_json::json!({
+            "apiVersion": "cert-manager.io/v1",
+            "kind": "Certificate",
+            "metadata": { "name": "router-tls", "namespace": "qovery" },
+            "status": { "notAfter": "2024-08-30T10:00:00Z" }
+        }))
+        .unwrap();
+        let not_issued: DynamicObject = serde_json::from_value(serde_json::json!({
+            "apiVersion": "cert-manager.io/v1",
+            "kind": "Certificate",
+            "metadata": { "name": "pending", "namespace": "qovery" },
+            "status": {}
+        }))
+        .unwrap();
+
+        let observed = observed_certificate(&certificate).unwrap();
+        assert_eq!(observed.name, "router-tls");
+        assert_eq!(observed.not_after, Utc.with_ymd_and_hms(2024, 8, 30, 10, 0, 0).unwrap());
+        assert!(observed_certificate(&not_issued).is_none());
+    }
+
+    #[test]
+    fn test_health_status() {
+        assert_eq!(health_status(&healthy_observations()), ClusterHealthStatus::Healthy);
+
+        let mut observations = healthy_observations();
+        observations.nodes = Some(NodeReadiness {
+            total: 2,
+            ready: 1,
+            not_ready: vec!["node-a".to_string()],
+        });
+        assert_eq!(health_status(&observations), ClusterHealthStatus::Degraded);
+
+        let mut observations = healthy_observations();
+        observations.nodes = Some(NodeReadiness::default());
+        assert_eq!(health_status(&observations), ClusterHealthStatus::Degraded);
+
+        let mut observations = healthy_observations();
+        observations.chart_skews = chart_skews(&[expected("qovery-cluster-agent", "0.4.2")], &[]);
+        assert_eq!(health_status(&observations), ClusterHealthStatus::Degraded);
+
+        let observations = ClusterHealthObservations {
+            connectivity: ConnectivityCheck::unreachable("connection refused".to_string()),
+            chart_skews: vec![],
+            nodes: None,
+            expiring_certificates: vec![],
+        };
+        assert_eq!(health_status(&observations), ClusterHealthStatus::Unreachable);
+    }
+
+    #[test]
+    fn test_escalate_severity() {
+        use ClusterHealthSeverity::*;
+        use ClusterHealthStatus::*;
+
+        assert_eq!(escalate_severity(Healthy, 0), (0, Ok));
+        assert_eq!(escalate_severity(Healthy, 7), (0, Ok));
+
+        assert_eq!(escalate_severity(Degraded, 0), (1, Warning));
+        assert_eq!(escalate_severity(Degraded, 1), (2, Warning));
+        assert_eq!(escalate_severity(Degraded, 2), (3, Error));
+        assert_eq!(escalate_severity(Degraded, 10), (11, Error));
+
+        assert_eq!(escalate_severity(Unreachable, 0), (1, Warning));
+        assert_eq!(escalate_severity(Unreachable, 1), (2, Error));
+        assert_eq!(escalate_severity(Unreachable, 2), (3, Critical));
+        assert_eq!(escalate_severity(Unreachable, u32::MAX), (u32::MAX, Critical));
+    }
+
+    #[test]
+    fn test_document() {
+        let cluster_id = Uuid::new_v4();
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+
+        let document = ClusterHealthDocument::new(cluster_id, now, 4, healthy_observations());
+        assert_eq!(document.status, ClusterHealthStatus::Healthy);
+        assert_eq!(document.severity, ClusterHealthSeverity::Ok);
+        assert_eq!(document.consecutive_failures, 0);
+        assert_eq!(document.to_string(), "Cluster is healthy");
+
+        // an expired certificate is an error right away
+        let mut observations = healthy_observations();
+        observations.expiring_certificates = vec![ExpiringCertificate {
+            name: "router-tls".to_string(),
+            namespace: "qovery".to_string(),
+            not_after: now,
+            expired: true,
+        }];
+        let document = ClusterHealthDocument::new(cluster_id, now, 0, observations);
+        assert_eq!(document.status, ClusterHealthStatus::Degraded);
+        assert_eq!(document.severity, ClusterHealthSeverity::Error);
+        assert_eq!(
+            document.to_string(),
+            "Cluster is degraded for 1 report(s) in a row: 1 certificate(s) expired or about to expire"
+        );
+
+        // the core relies on this schema
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(json["version"], CLUSTER_HEALTH_DOCUMENT_VERSION);
+        assert_eq!(json["status"], "DEGRADED");
+        assert_eq!(json["severity"], "ERROR");
+        assert_eq!(json["consecutive_failures"], 1);
+        assert_eq!(json["expiring_certificates"][0]["not_after"], "2024-06-01T00:00:00Z");
+        assert_eq!(serde_json::from_value::<ClusterHealthDocument>(json).unwrap(), document);
+    }
+}
@@ -0,0 +1,293 @@
+// Metadata of the cluster exposed to the workloads, so they know where they run without any per-service variable.
+// The `qovery-cluster-metadata` ConfigMap of the qovery namespace is refreshed on every infrastructure run, and a copy
+// is kept in every environment namespace, for services to mount it or to read some of its keys as variables.
+// Namespaces also carry the metadata short enough to be used as label values.
+
+use crate::infrastructure::models::kubernetes::{Kind, Kubernetes};
+use k8s_openapi::api::core::v1::ConfigMap;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::PostParams;
+use kube::Api;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+pub const CLUSTER_METADATA_CONFIG_MAP_NAME: &str = "qovery-cluster-metadata";
+/// Keys of the ConfigMap, also the names of the variables they are injected as
+pub const CLUSTER_METADATA_KEYS: [&str; 7] = [
+    "QOVERY_CLUSTER_ID",
+    "QOVERY_CLUSTER_SHORT_ID",
+    "QOVERY_CLOUD_PROVIDER",
+    "QOVERY_KUBERNETES_KIND",
+    "QOVERY_CLUSTER_REGION",
+    "QOVERY_CLUSTER_DOMAIN",
+    "QOVERY_ENGINE_VERSION",
+];
+// set when building the engine image
+const ENGINE_VERSION: &str = match option_env!("QOVERY_ENGINE_VERSION") {
+    Some(version) => version,
+    None => env!("CARGO_PKG_VERSION"),
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClusterMetadata {
+    pub cluster_id: Uuid,
+    pub cluster_short_id: String,
+    pub kind: Kind,
+    pub region: String,
+    pub cluster_domain: String,
+    pub engine_version: String,
+}
+
+impl ClusterMetadata {
+    pub fn new(kubernetes: &dyn Kubernetes, cluster_domain: String) -> ClusterMetadata {
+        ClusterMetadata {
+            cluster_id: *kubernetes.long_id(),
+            cluster_short_id: kubernetes.short_id().to_string(),
+            kind: kubernetes.kind(),
+            region: kubernetes.region().to_string(),
+            cluster_domain,
+            engine_version: ENGINE_VERSION.to_string(),
+        }
+    }
+
+    /// Content of the ConfigMap, see [`CLUSTER_METADATA_KEYS`]
+    pub fn data(&self) -> BTreeMap<String, String> {
+        let values = [
+            self.cluster_id.to_string(),
+            self.cluster_short_id.clone(),
+            serialized(&self.kind.get_cloud_provider_kind()),
+            serialized(&self.kind),
+            self.region.clone(),
+            self.cluster_domain.clone(),
+            self.engine_version.clone(),
+        ];
+
+        CLUSTER_METADATA_KEYS
+            .iter()
+            .map(|key| key.to_string())
+            .zip(values)
+            .collect()
+    }
+
+    /// Labels of the namespaces, the domain and the engine version can be longer than a label value allows
+    pub fn namespace_labels(&self) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("qovery.com/cluster-id".to_string(), self.cluster_id.to_string()),
+            (
+                "qovery.com/cloud-provider".to_string(),
+                serialized(&self.kind.get_cloud_provider_kind()),
+            ),
+            ("qovery.com/kubernetes-kind".to_string(), serialized(&self.kind)),
+            ("qovery.com/cluster-region".to_string(), self.region.clone()),
+        ])
+    }
+
+    pub fn config_map(&self, namespace: &str) -> ConfigMap {
+        ConfigMap {
+            metadata: ObjectMeta {
+                name: Some(CLUSTER_METADATA_CONFIG_MAP_NAME.to_string()),
+                namespace: Some(namespace.to_string()),
+                labels: Some(BTreeMap::from([(
+                    "app.kubernetes.io/managed-by".to_string(),
+                    "qovery".to_string(),
+                )])),
+                ..Default::default()
+            },
+            data: Some(self.data()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Same representation as in the engine requests, i.e: `AWS`, `SCW_KAPSULE`
+fn serialized(value: &impl Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigMapChange {
+    Created,
+    /// Keys whose value changed, was added or removed
+    Updated {
+        keys: Vec<String>,
+    },
+    Unchanged,
+}
+
+impl Display for ConfigMapChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigMapChange::Created => f.write_str("created"),
+            ConfigMapChange::Updated { keys } => write!(f, "updated {}", keys.join(", ")),
+            ConfigMapChange::Unchanged => f.write_str("up to date"),
+        }
+    }
+}
+
+/// What writing `data` to the existing ConfigMap, if any, changes
+pub fn config_map_change(existing: Option<&ConfigMap>, data: &BTreeMap<String, String>) -> ConfigMapChange {
+    let Some(existing) = existing else {
+        return ConfigMapChange::Created;
+    };
+
+    let empty = BTreeMap::new();
+    let existing_data = existing.data.as_ref().unwrap_or(&empty);
+    let keys: Vec<String> = existing_data
+        .keys()
+        .chain(data.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|key| existing_data.get(*key) != data.get(*key))
+        .cloned()
+        .collect();
+
+    match keys.is_empty() {
+        true => ConfigMapChange::Unchanged,
+        false => ConfigMapChange::Updated { keys },
+    }
+}
+
+/// Creates or updates the metadata ConfigMap of the namespace, a ConfigMap already up to date is not written
+pub async fn apply_cluster_metadata(
+    kube: &kube::Client,
+    namespace: &str,
+    metadata: &ClusterMetadata,
+) -> Result<ConfigMapChange, kube::Error> {
+    let api: Api<ConfigMap> = Api::namespaced(kube.clone(), namespace);
+    let existing = api.get_opt(CLUSTER_METADATA_CONFIG_MAP_NAME).await?;
+    let mut config_map = metadata.config_map(namespace);
+    let change = config_map_change(existing.as_ref(), config_map.data.as_ref().unwrap_or(&BTreeMap::new()));
+
+    match (&change, existing) {
+        (ConfigMapChange::Created, _) => {
+            api.create(&PostParams::default(), &config_map).await?;
+        }
+        (ConfigMapChange::Updated { .. }, Some(existing)) => {
+            // replaced rather than patched, so keys no longer part of the metadata are removed
+            config_map.metadata.resource_version = existing.metadata.resource_version;
+            api.replace(CLUSTER_METADATA_CONFIG_MAP_NAME, &PostParams::default(), &config_map)
+                .await?;
+        }
+        _ => {}
+    }
+
+    Ok(change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(kind: Kind, region: &str) -> ClusterMetadata {
+        ClusterMetadata {
+            cluster_id: Uuid::parse_str("8f3ae8c5-2b5c-4f1e-9d0a-6c1b2a3d4e5f").unwrap(),
+            cluster_short_id: "z8f3ae8c5".to_string(),
+            kind,
+            region: region.to_string(),
+            cluster_domain: "z8f3ae8c5.rustrocks.com".to_string(),
+            engine_version: "1.2.3".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_cluster_metadata_data() {
+        let data = metadata(Kind::ScwKapsule, "fr-par").data();
+
+        assert_eq!(
+            data,
+            BTreeMap::from([
+                (
+                    "QOVERY_CLUSTER_ID".to_string(),
+                    "8f3ae8c5-2b5c-4f1e-9d0a-6c1b2a3d4e5f".to_string()
+                ),
+                ("QOVERY_CLUSTER_SHORT_ID".to_string(), "z8f3ae8c5".to_string()),
+                ("QOVERY_CLOUD_PROVIDER".to_string(), "SCW".to_string()),
+                ("QOVERY_KUBERNETES_KIND".to_string(), "SCW_KAPSULE".to_string()),
+                ("QOVERY_CLUSTER_REGION".to_string(), "fr-par".to_string()),
+                ("QOVERY_CLUSTER_DOMAIN".to_string(), "z8f3ae8c5.rustrocks.com".to_string()),
+                ("QOVERY_ENGINE_VERSION".to_string(), "1.2.3".to_string()),
+            ])
+        );
+        assert!(data.keys().all(|key| CLUSTER_METADATA_KEYS.contains(&key.as_str())));
+    }
+
+    #[test]
+    fn test_cluster_metadata_namespace_labels() {
+        for kind in [Kind::Eks, Kind::Gke, Kind::ScwKapsule, Kind::OnPremiseSelfManaged] {
+            let labels = metadata(kind, "europe-west9").namespace_labels();
+
+            // label values are limited to 63 alphanumeric characters, `-`, `_` or `.`
+            for value in labels.values() {
+                assert!(value.len() <= 63, "{value} is too long");
+                assert!(
+                    value.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)),
+                    "{value} is not a valid label value"
+                );
+            }
+        }
+
+        assert_eq!(
+            metadata(Kind::EksSelfManaged, "us-east-2").namespace_labels(),
+            BTreeMap::from([
+                (
+                    "qovery.com/cluster-id".to_string(),
+                    "8f3ae8c5-2b5c-4f1e-9d0a-6c1b2a3d4e5f".to_string()
+                ),
+                ("qovery.com/cloud-provider".to_string(), "AWS".to_string()),
+                ("qovery.com/kubernetes-kind".to_string(), "EKS_SELF_MANAGED".to_string()),
+                ("qovery.com/cluster-region".to_string(), "us-east-2".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_config_map_change() {
+        let deployed = metadata(Kind::Eks, "eu-west-3");
+        let config_map = deployed.config_map("qovery");
+        assert_eq!(config_map.metadata.name.as_deref(), Some(CLUSTER_METADATA_CONFIG_MAP_NAME));
+        assert_eq!(config_map.metadata.namespace.as_deref(), Some("qovery"));
+
+        assert_eq!(config_map_change(None, &deployed.data()), ConfigMapChange::Created);
+        assert_eq!(
+            config_map_change(Some(&config_map), &deployed.data()),
+            ConfigMapChange::Unchanged
+        );
+
+        // the engine has been upgraded
+        let upgraded = ClusterMetadata {
+            engine_version: "1.3.0".to_string(),
+            ..deployed.clone()
+        };
+        assert_eq!(
+            config_map_change(Some(&config_map), &upgraded.data()),
+            ConfigMapChange::Updated {
+                keys: vec!["QOVERY_ENGINE_VERSION".to_string()]
+            }
+        );
+
+        // keys edited by hand, or no longer part of the metadata
+        let mut edited = config_map.clone();
+        let edited_data = edited.data.get_or_insert_with(BTreeMap::new);
+        edited_data.remove("QOVERY_CLUSTER_REGION");
+        edited_data.insert("QOVERY_OLD_KEY".to_string(), "value".to_string());
+        let change = config_map_change(Some(&edited), &deployed.data());
+        assert_eq!(
+            change,
+            ConfigMapChange::Updated {
+                keys: vec!["QOVERY_CLUSTER_REGION".to_string(), "QOVERY_OLD_KEY".to_string()]
+            }
+        );
+        assert_eq!(change.to_string(), "updated QOVERY_CLUSTER_REGION, QOVERY_OLD_KEY");
+
+        edited.data = None;
+        assert!(matches!(
+            config_map_change(Some(&edited), &deployed.data()),
+            ConfigMapChange::Updated { keys } if keys.len() == CLUSTER_METADATA_KEYS.len()
+        ));
+    }
+}
@@ -1,10 +1,12 @@
 use crate::cmd::command::{ExecutableCommand, QoveryCommand};
-use crate::errors::EngineError;
+use crate::errors::{CommandError, EngineError};
 use crate::events::Stage::Infrastructure;
 use crate::events::{EventDetails, InfrastructureStep, Transmitter};
+use crate::fs::ServiceWorkspace;
 use crate::infrastructure::action::kubeconfig_helper::write_kubeconfig_on_disk;
 use crate::infrastructure::models::cloud_provider::gcp::locations::GcpRegion;
 use crate::infrastructure::models::cloud_provider::io::ClusterAdvancedSettings;
+use crate::infrastructure::models::cloud_provider::CloudProvider;
 use crate::infrastructure::models::kubernetes::{Kind, Kubernetes, KubernetesVersion, ProviderOptions};
 use crate::io_models::context::Context;
 use crate::io_models::engine_location::EngineLocation;
@@ -19,6 +21,8 @@ use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::cloud_provider;
 use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
 use crate::infrastructure::models::object_storage::google_object_storage::GoogleOS;
+use crate::infrastructure::models::object_storage::retry_policy::{RetryPolicy, RetryingObjectStorage};
+use crate::infrastructure::models::object_storage::ObjectStorage;
 use crate::services::gcp::auth_service::GoogleAuthService;
 use crate::services::gcp::object_storage_regions::GcpStorageRegion;
 use crate::services::gcp::object_storage_service::ObjectStorageService;
@@ -31,9 +35,10 @@ use nonzero_ext::nonzero;
 use once_cell::sync::Lazy;
 use retry::delay::Fixed;
 use retry::OperationResult;
+use serde::Deserialize;
 use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use time::Time;
@@ -97,6 +102,24 @@ impl VpcMode {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MasterAuthorizedNetwork {
+    pub cidr_block: IpNet,
+    pub display_name: String,
+}
+
+/// GKE control plane endpoint access.
+/// Default is a public endpoint reachable from any source IP address (authentication still required).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ControlPlaneAccess {
+    /// Control plane is only reachable through its private endpoint, requires private nodes.
+    pub enable_private_endpoint: bool,
+    /// /28 range used by the control plane private endpoint, GKE picks one if not set.
+    pub master_ipv4_cidr_block: Option<IpNet>,
+    /// If not empty, only those networks can reach the control plane endpoint.
+    pub master_authorized_networks: Vec<MasterAuthorizedNetwork>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GkeOptions {
     // Qovery
@@ -117,6 +140,8 @@ pub struct GkeOptions {
     // VPC
     pub vpc_mode: VpcMode,
     pub vpc_qovery_network_mode: Option<VpcQoveryNetworkMode>,
+    // Control plane
+    pub control_plane_access: ControlPlaneAccess,
 
     // GCP to be checked during integration if needed:
     pub cluster_maintenance_start_time: Time,
@@ -140,6 +165,7 @@ impl GkeOptions {
         gcp_json_credentials: JsonCredentials,
         vpc_mode: VpcMode,
         vpc_qovery_network_mode: Option<VpcQoveryNetworkMode>,
+        control_plane_access: ControlPlaneAccess,
         tls_email_report: String,
         cluster_maintenance_start_time: Time,
         cluster_maintenance_end_time: Option<Time>,
@@ -157,6 +183,7 @@ impl GkeOptions {
             gcp_json_credentials,
             vpc_mode,
             vpc_qovery_network_mode,
+            control_plane_access,
             tls_email_report,
             cluster_maintenance_start_time,
             cluster_maintenance_end_time,
@@ -174,13 +201,13 @@ pub struct Gke {
     pub version: KubernetesVersion,
     pub region: GcpRegion,
     pub template_directory: PathBuf,
-    pub object_storage: GoogleOS,
+    pub object_storage: RetryingObjectStorage<GoogleOS>,
     pub options: GkeOptions,
     pub logger: Box<dyn Logger>,
     pub advanced_settings: ClusterAdvancedSettings,
     pub customer_helm_charts_override: Option<HashMap<ChartValuesOverrideName, ChartValuesOverrideValues>>,
     pub kubeconfig: Option<String>,
-    pub temp_dir: PathBuf,
+    pub workspace: ServiceWorkspace,
 }
 
 impl Gke {
@@ -195,7 +222,7 @@ impl Gke {
         advanced_settings: ClusterAdvancedSettings,
         customer_helm_charts_override: Option<HashMap<ChartValuesOverrideName, ChartValuesOverrideValues>>,
         kubeconfig: Option<String>,
-        temp_dir: PathBuf,
+        workspace: ServiceWorkspace,
     ) -> Result<Self, Box<EngineError>> {
         let event_details = EventDetails::new(
             Some(cloud_provider::Kind::Gcp),
@@ -254,13 +281,13 @@ impl Gke {
             version,
             region,
             template_directory: PathBuf::from(context.lib_root_dir()).join("gcp/bootstrap"),
-            object_storage: google_object_storage,
+            object_storage: RetryingObjectStorage::new(google_object_storage, RetryPolicy::default()),
             options,
             logger,
             advanced_settings,
             customer_helm_charts_override,
             kubeconfig,
-            temp_dir,
+            workspace,
         };
 
         if let Some(kubeconfig) = &cluster.kubeconfig {
@@ -362,8 +389,8 @@ impl Kubernetes for Gke {
         vec![CpuArchitecture::AMD64]
     }
 
-    fn temp_dir(&self) -> &Path {
-        &self.temp_dir
+    fn workspace(&self) -> &ServiceWorkspace {
+        &self.workspace
     }
 
     fn advanced_settings(&self) -> &ClusterAdvancedSettings {
@@ -374,7 +401,105 @@ impl Kubernetes for Gke {
         Vec::with_capacity(0)
     }
 
+    fn object_storage(&self) -> Option<&dyn ObjectStorage> {
+        Some(&self.object_storage)
+    }
+
+    fn buckets_removed_on_deletion(&self) -> Vec<String> {
+        vec![self.logs_bucket_name()]
+    }
+
+    fn regenerate_kubeconfig(&self, cloud_provider: &dyn CloudProvider) -> Result<Option<String>, Box<EngineError>> {
+        let event_details = self.get_event_details(Infrastructure(InfrastructureStep::LoadConfiguration));
+        let to_engine_error =
+            |err: CommandError| Box::new(EngineError::new_k8s_cannot_generate_kubeconfig(event_details.clone(), err));
+
+        GoogleAuthService::activate_service_account(self.options.gcp_json_credentials.clone()).map_err(|err| {
+            to_engine_error(CommandError::new(
+                "Cannot activate service account".to_string(),
+                Some(err.to_string()),
+                None,
+            ))
+        })?;
+
+        let mut stdout = vec![];
+        let mut stderr = vec![];
+        QoveryCommand::new(
+            "gcloud",
+            &[
+                "container",
+                "clusters",
+                "describe",
+                self.cluster_name().as_str(),
+                format!("--region={}", self.region.to_cloud_provider_format()).as_str(),
+                format!("--project={}", self.options.gcp_json_credentials.project_id).as_str(),
+                "--format=json(endpoint,masterAuth.clusterCaCertificate)",
+            ],
+            cloud_provider.credentials_environment_variables().as_slice(),
+        )
+        .exec_with_output(&mut |line| stdout.push(line), &mut |line| stderr.push(line))
+        .map_err(|err| to_engine_error(CommandError::new(err.message_safe(), Some(stderr.join("\n")), None)))?;
+
+        let cluster: GkeClusterEndpoint = serde_json::from_str(&stdout.join("\n")).map_err(|err| {
+            to_engine_error(CommandError::new(
+                "Cannot parse GKE cluster endpoint".to_string(),
+                Some(err.to_string()),
+                None,
+            ))
+        })?;
+
+        Ok(Some(gke_kubeconfig(
+            self.short_id(),
+            &cluster.endpoint,
+            &cluster.master_auth.cluster_ca_certificate,
+        )))
+    }
+
     fn as_infra_actions(&self) -> &dyn InfrastructureAction {
         self
     }
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GkeClusterEndpoint {
+    endpoint: String,
+    master_auth: GkeClusterMasterAuth,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GkeClusterMasterAuth {
+    cluster_ca_certificate: String,
+}
+
+/// Same kubeconfig as the terraform output, the token is issued by the gke auth plugin on each call
+fn gke_kubeconfig(cluster_id: &str, endpoint: &str, cluster_ca_certificate: &str) -> String {
+    let name = format!("gke_{}", cluster_id.replace('-', "_"));
+    format!(
+        r#"apiVersion: v1
+clusters:
+- cluster:
+    certificate-authority-data: {cluster_ca_certificate}
+    server: https://{endpoint}
+  name: {name}
+contexts:
+- context:
+    cluster: {name}
+    user: {name}
+  name: {name}
+current-context: {name}
+kind: Config
+preferences: {{}}
+users:
+- name: {name}
+  user:
+    exec:
+      apiVersion: client.authentication.k8s.io/v1beta1
+      command: gke-gcloud-auth-plugin
+      installHint: Install gke-gcloud-auth-plugin for use with kubectl by following
+        https://cloud.google.com/blog/products/containers-kubernetes/kubectl-auth-changes-in-gke
+      provideClusterInfo: true
+"#
+    )
+}
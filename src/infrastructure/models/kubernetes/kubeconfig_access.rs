@@ -0,0 +1,500 @@
+// Temporary kubectl accesses given to users: a service account bound to a role created on demand, and a token
+// requested from the TokenRequest API. Tokens carry their own expiration and are signed by the cluster, so there is
+// no long-lived certificate to revoke, and deleting the service account invalidates them right away.
+
+use crate::io_models::kubeconfig::KubeconfigAccess;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::authentication::v1::{TokenRequest, TokenRequestSpec};
+use k8s_openapi::api::core::v1::ServiceAccount;
+use k8s_openapi::api::rbac::v1::{ClusterRole, ClusterRoleBinding, PolicyRule, Role, RoleBinding, RoleRef, Subject};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{DeleteParams, ListParams, PostParams};
+use kube::config::Kubeconfig;
+use kube::Api;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::time::Duration;
+
+pub const KUBECONFIG_ACCESS_NAMESPACE: &str = "qovery";
+const KUBECONFIG_ACCESS_LABEL: &str = "qovery.com/kubeconfig-access";
+const KUBECONFIG_ACCESS_EXPIRES_AT_ANNOTATION: &str = "qovery.com/kubeconfig-expires-at";
+const KUBECONFIG_ACCESS_NAMESPACE_ANNOTATION: &str = "qovery.com/kubeconfig-namespace";
+// The TokenRequest API refuses tokens valid less than 10 minutes
+pub const KUBECONFIG_MIN_TTL: Duration = Duration::from_secs(10 * 60);
+pub const KUBECONFIG_MAX_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+const AES_GCM_NONCE_LENGTH: usize = 12;
+
+/// Returns the validity of the kubeconfig, bounded to what we accept to give.
+pub fn kubeconfig_token_ttl(requested_ttl_in_seconds: u32) -> Duration {
+    Duration::from_secs(requested_ttl_in_seconds as u64).clamp(KUBECONFIG_MIN_TTL, KUBECONFIG_MAX_TTL)
+}
+
+pub struct KubeconfigAccessObjects {
+    pub service_account: ServiceAccount,
+    pub cluster_role: Option<ClusterRole>,
+    pub cluster_role_binding: Option<ClusterRoleBinding>,
+    pub role: Option<Role>,
+    pub role_binding: Option<RoleBinding>,
+}
+
+fn access_metadata(name: &str, namespace: Option<&str>, annotations: BTreeMap<String, String>) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(name.to_string()),
+        namespace: namespace.map(str::to_string),
+        labels: Some(BTreeMap::from([(KUBECONFIG_ACCESS_LABEL.to_string(), "true".to_string())])),
+        annotations: Some(annotations),
+        ..Default::default()
+    }
+}
+
+fn read_only_rules() -> Vec<PolicyRule> {
+    let rule = |api_group: &str, resources: &[&str]| PolicyRule {
+        api_groups: Some(vec![api_group.to_string()]),
+        resources: Some(resources.iter().map(|r| r.to_string()).collect()),
+        verbs: vec!["get".to_string(), "list".to_string(), "watch".to_string()],
+        ..Default::default()
+    };
+
+    // secrets are deliberately not readable
+    vec![
+        rule(
+            "",
+            &[
+                "pods",
+                "pods/log",
+                "services",
+                "endpoints",
+                "configmaps",
+                "events",
+                "namespaces",
+                "nodes",
+                "persistentvolumeclaims",
+                "persistentvolumes",
+                "serviceaccounts",
+            ],
+        ),
+        rule("apps", &["*"]),
+        rule("batch", &["*"]),
+        rule("autoscaling", &["*"]),
+        rule("networking.k8s.io", &["*"]),
+        rule("policy", &["poddisruptionbudgets"]),
+        rule("metrics.k8s.io", &["*"]),
+    ]
+}
+
+/// Kubernetes objects giving the access: a service account, and the role bound to it.
+/// A read only access is cluster wide, while a namespace access is only valid inside the namespace.
+pub fn kubeconfig_access_objects(
+    name: &str,
+    access: &KubeconfigAccess,
+    expires_at: DateTime<Utc>,
+) -> KubeconfigAccessObjects {
+    let mut annotations =
+        BTreeMap::from([(KUBECONFIG_ACCESS_EXPIRES_AT_ANNOTATION.to_string(), expires_at.to_rfc3339())]);
+    if let KubeconfigAccess::Namespace { namespace } = access {
+        annotations.insert(KUBECONFIG_ACCESS_NAMESPACE_ANNOTATION.to_string(), namespace.to_string());
+    }
+
+    let service_account = ServiceAccount {
+        metadata: access_metadata(name, Some(KUBECONFIG_ACCESS_NAMESPACE), annotations.clone()),
+        automount_service_account_token: Some(false),
+        ..Default::default()
+    };
+    let subjects = Some(vec![Subject {
+        kind: "ServiceAccount".to_string(),
+        name: name.to_string(),
+        namespace: Some(KUBECONFIG_ACCESS_NAMESPACE.to_string()),
+        ..Default::default()
+    }]);
+
+    match access {
+        KubeconfigAccess::ReadOnly => KubeconfigAccessObjects {
+            service_account,
+            cluster_role: Some(ClusterRole {
+                metadata: access_metadata(name, None, annotations.clone()),
+                rules: Some(read_only_rules()),
+                ..Default::default()
+            }),
+            cluster_role_binding: Some(ClusterRoleBinding {
+                metadata: access_metadata(name, None, annotations),
+                role_ref: RoleRef {
+                    api_group: "rbac.authorization.k8s.io".to_string(),
+                    kind: "ClusterRole".to_string(),
+                    name: name.to_string(),
+                },
+                subjects,
+            }),
+            role: None,
+            role_binding: None,
+        },
+        KubeconfigAccess::Namespace { namespace } => KubeconfigAccessObjects {
+            service_account,
+            cluster_role: None,
+            cluster_role_binding: None,
+            role: Some(Role {
+                metadata: access_metadata(name, Some(namespace), annotations.clone()),
+                rules: Some(vec![PolicyRule {
+                    api_groups: Some(vec!["*".to_string()]),
+                    resources: Some(vec!["*".to_string()]),
+                    verbs: vec!["*".to_string()],
+                    ..Default::default()
+                }]),
+            }),
+            role_binding: Some(RoleBinding {
+                metadata: access_metadata(name, Some(namespace), annotations),
+                role_ref: RoleRef {
+                    api_group: "rbac.authorization.k8s.io".to_string(),
+                    kind: "Role".to_string(),
+                    name: name.to_string(),
+                },
+                subjects,
+            }),
+        },
+    }
+}
+
+fn is_access_expired(metadata: &ObjectMeta, now: DateTime<Utc>) -> bool {
+    // an access we can't read the expiration of is considered expired, to never leave one behind
+    metadata
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(KUBECONFIG_ACCESS_EXPIRES_AT_ANNOTATION))
+        .and_then(|expires_at| DateTime::parse_from_rfc3339(expires_at).ok())
+        .map(|expires_at| expires_at <= now)
+        .unwrap_or(true)
+}
+
+/// Returns the names of the accesses whose token is expired, and whose objects can be deleted
+pub fn expired_kubeconfig_accesses<'a>(
+    service_accounts: impl IntoIterator<Item = &'a ServiceAccount>,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    service_accounts
+        .into_iter()
+        .filter(|sa| is_access_expired(&sa.metadata, now))
+        .filter_map(|sa| sa.metadata.name.clone())
+        .collect()
+}
+
+/// Returns the api server url and its certificate authority data, for the current context of the kubeconfig
+pub fn cluster_endpoint(kubeconfig: &Kubeconfig) -> Option<(String, Option<String>)> {
+    let current_context = kubeconfig.current_context.as_ref()?;
+    let cluster_name = &kubeconfig
+        .contexts
+        .iter()
+        .find(|ctx| &ctx.name == current_context)?
+        .context
+        .as_ref()?
+        .cluster;
+    let cluster = kubeconfig
+        .clusters
+        .iter()
+        .find(|cluster| &cluster.name == cluster_name)?
+        .cluster
+        .as_ref()?;
+
+    Some((cluster.server.clone()?, cluster.certificate_authority_data.clone()))
+}
+
+/// Builds the kubeconfig given to the user, authenticating with the token only
+pub fn build_kubeconfig(
+    cluster_name: &str,
+    server: &str,
+    certificate_authority_data: Option<&str>,
+    namespace: Option<&str>,
+    token: &str,
+) -> Result<String, serde_yaml::Error> {
+    let mut cluster = serde_json::json!({ "server": server });
+    if let Some(certificate_authority_data) = certificate_authority_data {
+        cluster["certificate-authority-data"] = certificate_authority_data.into();
+    }
+    let mut context = serde_json::json!({ "cluster": cluster_name, "user": cluster_name });
+    if let Some(namespace) = namespace {
+        context["namespace"] = namespace.into();
+    }
+
+    serde_yaml::to_string(&serde_json::json!({
+        "apiVersion": "v1",
+        "kind": "Config",
+        "clusters": [{ "name": cluster_name, "cluster": cluster }],
+        "users": [{ "name": cluster_name, "user": { "token": token } }],
+        "contexts": [{ "name": cluster_name, "context": context }],
+        "current-context": cluster_name,
+    }))
+}
+
+pub struct EncryptedKubeconfig {
+    /// base64 encoded one-time key, only given back to the requester
+    pub key: String,
+    /// nonce followed by the AES-256-GCM encrypted kubeconfig
+    pub payload: Vec<u8>,
+}
+
+pub fn encrypt_kubeconfig(kubeconfig: &str) -> Result<EncryptedKubeconfig, aes_gcm::Error> {
+    let key = Aes256Gcm::generate_key(OsRng);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let mut payload = nonce.to_vec();
+    payload.extend(Aes256Gcm::new(&key).encrypt(&nonce, kubeconfig.as_bytes())?);
+
+    Ok(EncryptedKubeconfig {
+        key: general_purpose::STANDARD.encode(key),
+        payload,
+    })
+}
+
+pub fn decrypt_kubeconfig(key: &str, payload: &[u8]) -> Result<String, aes_gcm::Error> {
+    let key = general_purpose::STANDARD.decode(key).map_err(|_| aes_gcm::Error)?;
+    if key.len() != 32 || payload.len() < AES_GCM_NONCE_LENGTH {
+        return Err(aes_gcm::Error);
+    }
+
+    let (nonce, ciphertext) = payload.split_at(AES_GCM_NONCE_LENGTH);
+    let kubeconfig =
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)).decrypt(Nonce::from_slice(nonce), ciphertext)?;
+    String::from_utf8(kubeconfig).map_err(|_| aes_gcm::Error)
+}
+
+async fn create_object<K>(api: Api<K>, object: &K) -> Result<(), kube::Error>
+where
+    K: kube::Resource + Clone + DeserializeOwned + serde::Serialize + Debug,
+{
+    api.create(&PostParams::default(), object).await.map(|_| ())
+}
+
+async fn delete_object<K>(api: Api<K>, name: &str) -> Result<(), kube::Error>
+where
+    K: kube::Resource + Clone + DeserializeOwned + Debug,
+{
+    match api.delete(name, &DeleteParams::background()).await {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Creates the access and returns a token valid for the ttl, along with its actual expiration
+pub async fn create_kubeconfig_access(
+    client: &kube::Client,
+    name: &str,
+    access: &KubeconfigAccess,
+    ttl: Duration,
+) -> Result<(String, DateTime<Utc>), kube::Error> {
+    let expires_at = Utc::now() + ttl;
+    let objects = kubeconfig_access_objects(name, access, expires_at);
+
+    let service_accounts: Api<ServiceAccount> = Api::namespaced(client.clone(), KUBECONFIG_ACCESS_NAMESPACE);
+    create_object(service_accounts.clone(), &objects.service_account).await?;
+    if let Some(cluster_role) = &objects.cluster_role {
+        create_object(Api::all(client.clone()), cluster_role).await?;
+    }
+    if let Some(cluster_role_binding) = &objects.cluster_role_binding {
+        create_object(Api::all(client.clone()), cluster_role_binding).await?;
+    }
+    if let KubeconfigAccess::Namespace { namespace } = access {
+        if let Some(role) = &objects.role {
+            create_object(Api::namespaced(client.clone(), namespace), role).await?;
+        }
+        if let Some(role_binding) = &objects.role_binding {
+            create_object(Api::namespaced(client.clone(), namespace), role_binding).await?;
+        }
+    }
+
+    let token_request = service_accounts
+        .create_token_request(
+            name,
+            &PostParams::default(),
+            &TokenRequest {
+                spec: TokenRequestSpec {
+                    expiration_seconds: Some(ttl.as_secs() as i64),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .await?;
+    let status = token_request
+        .status
+        .ok_or_else(|| kube::Error::Service("token request returned no token".into()))?;
+
+    // the api server can shorten the validity, never lengthen it
+    Ok((status.token, status.expiration_timestamp.0.min(expires_at)))
+}
+
+/// Deletes every object of the access, the tokens of the service account are invalid right after
+pub async fn delete_kubeconfig_access(
+    client: &kube::Client,
+    service_account: &ServiceAccount,
+) -> Result<(), kube::Error> {
+    let name = service_account.metadata.name.clone().unwrap_or_default();
+    let namespace = service_account
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(KUBECONFIG_ACCESS_NAMESPACE_ANNOTATION));
+
+    if let Some(namespace) = namespace {
+        delete_object(Api::<RoleBinding>::namespaced(client.clone(), namespace), &name).await?;
+        delete_object(Api::<Role>::namespaced(client.clone(), namespace), &name).await?;
+    } else {
+        delete_object(Api::<ClusterRoleBinding>::all(client.clone()), &name).await?;
+        delete_object(Api::<ClusterRole>::all(client.clone()), &name).await?;
+    }
+    delete_object(
+        Api::<ServiceAccount>::namespaced(client.clone(), KUBECONFIG_ACCESS_NAMESPACE),
+        &name,
+    )
+    .await
+}
+
+/// Deletes the accesses whose token is expired. Returns the names of the deleted accesses.
+pub async fn cleanup_expired_kubeconfig_accesses(
+    client: &kube::Client,
+    now: DateTime<Utc>,
+) -> Result<Vec<String>, kube::Error> {
+    let service_accounts = Api::<ServiceAccount>::namespaced(client.clone(), KUBECONFIG_ACCESS_NAMESPACE)
+        .list(&ListParams::default().labels(&format!("{KUBECONFIG_ACCESS_LABEL}=true")))
+        .await?
+        .items;
+
+    let expired = expired_kubeconfig_accesses(&service_accounts, now);
+    for service_account in service_accounts
+        .iter()
+        .filter(|sa| sa.metadata.name.as_ref().is_some_and(|name| expired.contains(name)))
+    {
+        delete_kubeconfig_access(client, service_account).await?;
+    }
+
+    Ok(expired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kubeconfig_token_ttl() {
+        assert_eq!(kubeconfig_token_ttl(0), KUBECONFIG_MIN_TTL);
+        assert_eq!(kubeconfig_token_ttl(60), KUBECONFIG_MIN_TTL);
+        assert_eq!(kubeconfig_token_ttl(3600), Duration::from_secs(3600));
+        assert_eq!(kubeconfig_token_ttl(u32::MAX), KUBECONFIG_MAX_TTL);
+    }
+
+    #[test]
+    fn test_read_only_access_objects() {
+        let expires_at = Utc::now();
+        let objects = kubeconfig_access_objects("kubeconfig-abc", &KubeconfigAccess::ReadOnly, expires_at);
+
+        assert!(objects.role.is_none() && objects.role_binding.is_none());
+        let cluster_role = objects.cluster_role.unwrap();
+        for rule in cluster_role.rules.unwrap() {
+            assert_eq!(rule.verbs, vec!["get", "list", "watch"]);
+            assert!(!rule.resources.unwrap().contains(&"secrets".to_string()));
+        }
+        let binding = objects.cluster_role_binding.unwrap();
+        assert_eq!(binding.role_ref.kind, "ClusterRole");
+        assert_eq!(binding.role_ref.name, "kubeconfig-abc");
+        let subject = &binding.subjects.unwrap()[0];
+        assert_eq!(subject.kind, "ServiceAccount");
+        assert_eq!(subject.namespace.as_deref(), Some(KUBECONFIG_ACCESS_NAMESPACE));
+        assert_eq!(
+            objects.service_account.metadata.annotations.unwrap()[KUBECONFIG_ACCESS_EXPIRES_AT_ANNOTATION],
+            expires_at.to_rfc3339()
+        );
+        assert_eq!(objects.service_account.automount_service_account_token, Some(false));
+    }
+
+    #[test]
+    fn test_namespace_access_objects() {
+        let access = KubeconfigAccess::Namespace {
+            namespace: "z1234-env".to_string(),
+        };
+        let objects = kubeconfig_access_objects("kubeconfig-abc", &access, Utc::now());
+
+        assert!(objects.cluster_role.is_none() && objects.cluster_role_binding.is_none());
+        assert_eq!(objects.role.unwrap().metadata.namespace.as_deref(), Some("z1234-env"));
+        let binding = objects.role_binding.unwrap();
+        assert_eq!(binding.metadata.namespace.as_deref(), Some("z1234-env"));
+        assert_eq!(binding.role_ref.kind, "Role");
+        // needed to find the role back during the cleanup
+        assert_eq!(
+            objects.service_account.metadata.annotations.unwrap()[KUBECONFIG_ACCESS_NAMESPACE_ANNOTATION],
+            "z1234-env"
+        );
+    }
+
+    #[test]
+    fn test_expired_kubeconfig_accesses() {
+        let now = Utc::now();
+        let service_account = |name: &str, expires_at: Option<DateTime<Utc>>| {
+            let mut sa = kubeconfig_access_objects(name, &KubeconfigAccess::ReadOnly, now).service_account;
+            sa.metadata.annotations = expires_at.map(|expires_at| {
+                BTreeMap::from([(KUBECONFIG_ACCESS_EXPIRES_AT_ANNOTATION.to_string(), expires_at.to_rfc3339())])
+            });
+            sa
+        };
+
+        let service_accounts = [
+            service_account("expired", Some(now - chrono::Duration::minutes(1))),
+            service_account("valid", Some(now + chrono::Duration::minutes(1))),
+            service_account("no-expiration", None),
+        ];
+
+        assert_eq!(
+            expired_kubeconfig_accesses(&service_accounts, now),
+            vec!["expired".to_string(), "no-expiration".to_string()]
+        );
+        assert_eq!(
+            expired_kubeconfig_accesses(&service_accounts, now + chrono::Duration::minutes(2)),
+            vec!["expired".to_string(), "valid".to_string(), "no-expiration".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_build_kubeconfig() {
+        let kubeconfig = build_kubeconfig(
+            "qovery-z1234",
+            "https://1234.gr7.eu-west-3.eks.amazonaws.com",
+            Some("LS0tLS1CRUdJTg=="),
+            Some("z1234-env"),
+            "my-token",
+        )
+        .unwrap();
+
+        let kubeconfig = Kubeconfig::from_yaml(&kubeconfig).unwrap();
+        assert_eq!(
+            cluster_endpoint(&kubeconfig),
+            Some((
+                "https://1234.gr7.eu-west-3.eks.amazonaws.com".to_string(),
+                Some("LS0tLS1CRUdJTg==".to_string())
+            ))
+        );
+        let context = kubeconfig.contexts[0].context.as_ref().unwrap();
+        assert_eq!(context.namespace.as_deref(), Some("z1234-env"));
+        // no client certificate, the token is the only credential
+        let auth_info = kubeconfig.auth_infos[0].auth_info.as_ref().unwrap();
+        assert!(auth_info.client_certificate_data.is_none() && auth_info.client_key_data.is_none());
+        assert!(auth_info.token.is_some());
+    }
+
+    #[test]
+    fn test_encrypt_kubeconfig() {
+        let encrypted = encrypt_kubeconfig("apiVersion: v1").unwrap();
+        assert!(!String::from_utf8_lossy(&encrypted.payload).contains("apiVersion"));
+        assert_eq!(
+            decrypt_kubeconfig(&encrypted.key, &encrypted.payload).unwrap(),
+            "apiVersion: v1"
+        );
+
+        // every kubeconfig gets its own key
+        let other = encrypt_kubeconfig("apiVersion: v1").unwrap();
+        assert_ne!(encrypted.key, other.key);
+        assert!(decrypt_kubeconfig(&other.key, &encrypted.payload).is_err());
+        assert!(decrypt_kubeconfig("not-a-key", &encrypted.payload).is_err());
+        assert!(decrypt_kubeconfig(&encrypted.key, &encrypted.payload[..5]).is_err());
+    }
+}
@@ -1,30 +1,44 @@
 pub mod aws;
+pub mod capabilities;
+pub mod certificate_watchdog;
+pub mod cluster_autoscaler;
+pub mod cluster_health;
+pub mod cluster_metadata;
 pub mod gcp;
+pub mod kubeconfig_access;
 pub mod scaleway;
 pub mod self_managed;
+pub mod service_inventory;
 
 use crate::cmd::kubectl::kubectl_delete_apiservice;
 use crate::cmd::kubectl::{
     kubectl_delete_objects_in_all_namespaces, kubectl_exec_count_all_objects, kubectl_exec_get_node,
     kubectl_exec_version, kubernetes_get_all_pdbs,
 };
-use crate::cmd::structs::KubernetesNodeCondition;
 use crate::environment::models::types::VersionsNumber;
 use crate::errors::{CommandError, EngineError, ErrorMessageVerbosity};
 use crate::events::Stage::Infrastructure;
 use crate::events::{EngineEvent, EventDetails, EventMessage, InfrastructureStep, Stage, Transmitter};
+use crate::fs::ServiceWorkspace;
 use crate::infrastructure::action::{InfraLogger, InfrastructureAction};
 use crate::infrastructure::models::cloud_provider::io::ClusterAdvancedSettings;
 use crate::infrastructure::models::cloud_provider::service::Action;
 use crate::infrastructure::models::cloud_provider::CloudProvider;
 use crate::infrastructure::models::cloud_provider::Kind as CloudProviderKind;
+use crate::infrastructure::models::object_storage::ObjectStorage;
 use crate::io_models::context::Context;
 use crate::io_models::models::NodeGroupsWithDesiredState;
-use crate::io_models::models::{CpuArchitecture, CpuLimits, InstanceEc2, NodeGroups};
+use crate::io_models::models::{
+    is_valid_kubernetes_label_key, is_valid_kubernetes_label_value, CpuArchitecture, CpuLimits, InstanceEc2,
+    NodeGroupCapacityType, NodeGroups,
+};
 use crate::io_models::QoveryIdentifier;
+use crate::kubers_utils::{kube_get_nodes, kube_list_dynamic, KubeNodeStatus};
 use crate::logger::Logger;
+use crate::runtime::block_on;
+use crate::utilities::{create_kube_client, envs_to_string};
 use k8s_openapi::api::core::v1::{Namespace, Secret, Service};
-use kube::api::{ListParams, ObjectMeta, Patch, PatchParams, PostParams};
+use kube::api::{ApiResource, GroupVersionKind, ListParams, ObjectMeta, Patch, PatchParams, PostParams};
 use kube::core::ObjectList;
 use kube::{Api, Error};
 use retry::delay::{Fibonacci, Fixed};
@@ -32,7 +46,7 @@ use retry::OperationResult;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::any::Any;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -40,6 +54,7 @@ use std::sync::mpsc::TryRecvError;
 use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::Duration;
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use tracing::Span;
 use uuid::Uuid;
@@ -49,10 +64,11 @@ pub trait ProviderOptions {}
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum KubernetesError {
     /// Triggered if an addon version is not supporting the given kubernetes version
-    #[error("Addon `{addon}` doesn't support kubernetes version `{kubernetes_version}`.")]
+    #[error("Addon `{addon}` version `{addon_version}` doesn't support kubernetes version `{kubernetes_version}`.")]
     AddonUnSupportedKubernetesVersion {
         kubernetes_version: String,
         addon: KubernetesAddon,
+        addon_version: String,
     },
 }
 
@@ -63,15 +79,32 @@ impl KubernetesError {
             KubernetesError::AddonUnSupportedKubernetesVersion {
                 kubernetes_version,
                 addon,
-            } => format!("Addon `{addon}` doesn't support kubernetes version `{kubernetes_version}`."),
+                addon_version,
+            } => format!(
+                "Addon `{addon}` version `{addon_version}` doesn't support kubernetes version `{kubernetes_version}`."
+            ),
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
 pub enum KubernetesAddon {
     Cni,
     EbsCsi,
+    CoreDns,
+    KubeProxy,
+}
+
+impl KubernetesAddon {
+    /// Name of the addon in the EKS API
+    pub fn eks_addon_name(&self) -> &'static str {
+        match self {
+            KubernetesAddon::Cni => "vpc-cni",
+            KubernetesAddon::EbsCsi => "aws-ebs-csi-driver",
+            KubernetesAddon::CoreDns => "coredns",
+            KubernetesAddon::KubeProxy => "kube-proxy",
+        }
+    }
 }
 
 impl Display for KubernetesAddon {
@@ -79,10 +112,23 @@ impl Display for KubernetesAddon {
         f.write_str(match self {
             KubernetesAddon::Cni => "cni",
             KubernetesAddon::EbsCsi => "ebs-csi",
+            KubernetesAddon::CoreDns => "coredns",
+            KubernetesAddon::KubeProxy => "kube-proxy",
         })
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum KubernetesVersionError {
+    #[error("Invalid kubernetes version `{version}`, expected `<prefix>MAJOR.MINOR[.PATCH][+suffix]`.")]
+    InvalidFormat { version: String },
+    #[error("Kubernetes version `{version}` is not supported, supported versions are: {supported_versions}.")]
+    UnsupportedVersion {
+        version: String,
+        supported_versions: String,
+    },
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, EnumIter)]
 pub enum KubernetesVersion {
     V1_23 {
@@ -125,6 +171,26 @@ pub enum KubernetesVersion {
         patch: Option<u8>,
         suffix: Option<Arc<str>>,
     },
+    V1_31 {
+        prefix: Option<Arc<str>>,
+        patch: Option<u8>,
+        suffix: Option<Arc<str>>,
+    },
+    V1_32 {
+        prefix: Option<Arc<str>>,
+        patch: Option<u8>,
+        suffix: Option<Arc<str>>,
+    },
+    V1_33 {
+        prefix: Option<Arc<str>>,
+        patch: Option<u8>,
+        suffix: Option<Arc<str>>,
+    },
+    V1_34 {
+        prefix: Option<Arc<str>>,
+        patch: Option<u8>,
+        suffix: Option<Arc<str>>,
+    },
 }
 
 impl KubernetesVersion {
@@ -138,6 +204,10 @@ impl KubernetesVersion {
             KubernetesVersion::V1_28 { prefix, .. } => prefix,
             KubernetesVersion::V1_29 { prefix, .. } => prefix,
             KubernetesVersion::V1_30 { prefix, .. } => prefix,
+            KubernetesVersion::V1_31 { prefix, .. } => prefix,
+            KubernetesVersion::V1_32 { prefix, .. } => prefix,
+            KubernetesVersion::V1_33 { prefix, .. } => prefix,
+            KubernetesVersion::V1_34 { prefix, .. } => prefix,
         }
     }
 
@@ -151,6 +221,10 @@ impl KubernetesVersion {
             KubernetesVersion::V1_28 { .. } => 1,
             KubernetesVersion::V1_29 { .. } => 1,
             KubernetesVersion::V1_30 { .. } => 1,
+            KubernetesVersion::V1_31 { .. } => 1,
+            KubernetesVersion::V1_32 { .. } => 1,
+            KubernetesVersion::V1_33 { .. } => 1,
+            KubernetesVersion::V1_34 { .. } => 1,
         }
     }
 
@@ -164,6 +238,10 @@ impl KubernetesVersion {
             KubernetesVersion::V1_28 { .. } => 28,
             KubernetesVersion::V1_29 { .. } => 29,
             KubernetesVersion::V1_30 { .. } => 30,
+            KubernetesVersion::V1_31 { .. } => 31,
+            KubernetesVersion::V1_32 { .. } => 32,
+            KubernetesVersion::V1_33 { .. } => 33,
+            KubernetesVersion::V1_34 { .. } => 34,
         }
     }
 
@@ -177,6 +255,10 @@ impl KubernetesVersion {
             KubernetesVersion::V1_28 { patch, .. } => patch,
             KubernetesVersion::V1_29 { patch, .. } => patch,
             KubernetesVersion::V1_30 { patch, .. } => patch,
+            KubernetesVersion::V1_31 { patch, .. } => patch,
+            KubernetesVersion::V1_32 { patch, .. } => patch,
+            KubernetesVersion::V1_33 { patch, .. } => patch,
+            KubernetesVersion::V1_34 { patch, .. } => patch,
         }
     }
 
@@ -190,6 +272,10 @@ impl KubernetesVersion {
             KubernetesVersion::V1_28 { suffix, .. } => suffix,
             KubernetesVersion::V1_29 { suffix, .. } => suffix,
             KubernetesVersion::V1_30 { suffix, .. } => suffix,
+            KubernetesVersion::V1_31 { suffix, .. } => suffix,
+            KubernetesVersion::V1_32 { suffix, .. } => suffix,
+            KubernetesVersion::V1_33 { suffix, .. } => suffix,
+            KubernetesVersion::V1_34 { suffix, .. } => suffix,
         }
     }
 
@@ -231,6 +317,26 @@ impl KubernetesVersion {
                 patch: None,
                 suffix: None,
             }),
+            KubernetesVersion::V1_31 { .. } => Some(KubernetesVersion::V1_30 {
+                prefix: None,
+                patch: None,
+                suffix: None,
+            }),
+            KubernetesVersion::V1_32 { .. } => Some(KubernetesVersion::V1_31 {
+                prefix: None,
+                patch: None,
+                suffix: None,
+            }),
+            KubernetesVersion::V1_33 { .. } => Some(KubernetesVersion::V1_32 {
+                prefix: None,
+                patch: None,
+                suffix: None,
+            }),
+            KubernetesVersion::V1_34 { .. } => Some(KubernetesVersion::V1_33 {
+                prefix: None,
+                patch: None,
+                suffix: None,
+            }),
         }
     }
 
@@ -271,7 +377,52 @@ impl KubernetesVersion {
                 patch: None,
                 suffix: None,
             }),
-            KubernetesVersion::V1_30 { .. } => None,
+            KubernetesVersion::V1_30 { .. } => Some(KubernetesVersion::V1_31 {
+                prefix: None,
+                patch: None,
+                suffix: None,
+            }),
+            KubernetesVersion::V1_31 { .. } => Some(KubernetesVersion::V1_32 {
+                prefix: None,
+                patch: None,
+                suffix: None,
+            }),
+            KubernetesVersion::V1_32 { .. } => Some(KubernetesVersion::V1_33 {
+                prefix: None,
+                patch: None,
+                suffix: None,
+            }),
+            KubernetesVersion::V1_33 { .. } => Some(KubernetesVersion::V1_34 {
+                prefix: None,
+                patch: None,
+                suffix: None,
+            }),
+            KubernetesVersion::V1_34 { .. } => None,
+        }
+    }
+
+    /// Returns the supported version matching `major.minor`, if any
+    fn from_minor_version(
+        major: u8,
+        minor: u8,
+        prefix: Option<Arc<str>>,
+        patch: Option<u8>,
+        suffix: Option<Arc<str>>,
+    ) -> Option<Self> {
+        match (major, minor) {
+            (1, 23) => Some(KubernetesVersion::V1_23 { prefix, patch, suffix }),
+            (1, 24) => Some(KubernetesVersion::V1_24 { prefix, patch, suffix }),
+            (1, 25) => Some(KubernetesVersion::V1_25 { prefix, patch, suffix }),
+            (1, 26) => Some(KubernetesVersion::V1_26 { prefix, patch, suffix }),
+            (1, 27) => Some(KubernetesVersion::V1_27 { prefix, patch, suffix }),
+            (1, 28) => Some(KubernetesVersion::V1_28 { prefix, patch, suffix }),
+            (1, 29) => Some(KubernetesVersion::V1_29 { prefix, patch, suffix }),
+            (1, 30) => Some(KubernetesVersion::V1_30 { prefix, patch, suffix }),
+            (1, 31) => Some(KubernetesVersion::V1_31 { prefix, patch, suffix }),
+            (1, 32) => Some(KubernetesVersion::V1_32 { prefix, patch, suffix }),
+            (1, 33) => Some(KubernetesVersion::V1_33 { prefix, patch, suffix }),
+            (1, 34) => Some(KubernetesVersion::V1_34 { prefix, patch, suffix }),
+            _ => None,
         }
     }
 
@@ -321,93 +472,46 @@ impl From<KubernetesVersion> for VersionsNumber {
 }
 
 impl FromStr for KubernetesVersion {
-    type Err = ();
+    type Err = KubernetesVersionError;
 
+    /// Parses `<prefix>MAJOR.MINOR[.PATCH][+suffix]` versions, e.g. `1.30`, `1.33.2` or the k3s `v1.28.5+k3s1`
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "1.23" => Ok(KubernetesVersion::V1_23 {
-                prefix: None,
-                patch: None,
-                suffix: None,
-            }),
-            "1.24" => Ok(KubernetesVersion::V1_24 {
-                prefix: None,
-                patch: None,
-                suffix: None,
-            }),
-            "1.25" => Ok(KubernetesVersion::V1_25 {
-                prefix: None,
-                patch: None,
-                suffix: None,
-            }),
-            "1.26" => Ok(KubernetesVersion::V1_26 {
-                prefix: None,
-                patch: None,
-                suffix: None,
-            }),
-            "1.27" => Ok(KubernetesVersion::V1_27 {
-                prefix: None,
-                patch: None,
-                suffix: None,
-            }),
-            "1.28" => Ok(KubernetesVersion::V1_28 {
-                prefix: None,
-                patch: None,
-                suffix: None,
-            }),
-            "1.29" => Ok(KubernetesVersion::V1_29 {
-                prefix: None,
-                patch: None,
-                suffix: None,
-            }),
-            "1.30" => Ok(KubernetesVersion::V1_30 {
-                prefix: None,
-                patch: None,
-                suffix: None,
-            }),
-            // EC2 specifics
-            "v1.23.16+k3s1" => Ok(KubernetesVersion::V1_23 {
-                prefix: Some(Arc::from("v")),
-                patch: Some(16),
-                suffix: Some(Arc::from("+k3s1")),
-            }),
-            "v1.24.14+k3s1" => Ok(KubernetesVersion::V1_24 {
-                prefix: Some(Arc::from("v")),
-                patch: Some(14),
-                suffix: Some(Arc::from("+k3s1")),
-            }),
-            "v1.25.11+k3s1" => Ok(KubernetesVersion::V1_25 {
-                prefix: Some(Arc::from("v")),
-                patch: Some(11),
-                suffix: Some(Arc::from("+k3s1")),
-            }),
-            "v1.26.6+k3s1" => Ok(KubernetesVersion::V1_26 {
-                prefix: Some(Arc::from("v")),
-                patch: Some(6),
-                suffix: Some(Arc::from("+k3s1")),
-            }),
-            "v1.27.9+k3s1" => Ok(KubernetesVersion::V1_27 {
-                prefix: Some(Arc::from("v")),
-                patch: Some(9),
-                suffix: Some(Arc::from("+k3s1")),
-            }),
-            "v1.28.5+k3s1" => Ok(KubernetesVersion::V1_28 {
-                prefix: Some(Arc::from("v")),
-                patch: Some(5),
-                suffix: Some(Arc::from("+k3s1")),
-            }),
-            "v1.29.7+k3s1" => Ok(KubernetesVersion::V1_29 {
-                prefix: Some(Arc::from("v")),
-                patch: Some(7),
-                suffix: Some(Arc::from("+k3s1")),
-            }),
-            "v1.30.5+k3s1" => Ok(KubernetesVersion::V1_30 {
-                prefix: Some(Arc::from("v")),
-                patch: Some(5),
-                suffix: Some(Arc::from("+k3s1")),
-            }),
-            _ => Err(()),
+        let invalid_format = || KubernetesVersionError::InvalidFormat { version: s.to_string() };
+
+        let version_start = s.find(|c: char| c.is_ascii_digit()).ok_or_else(invalid_format)?;
+        let (prefix, version) = s.split_at(version_start);
+        if !prefix.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(invalid_format());
+        }
+        let (version, suffix) = version.split_at(version.find(['+', '-']).unwrap_or(version.len()));
+        if suffix.len() == 1 {
+            return Err(invalid_format());
         }
+
+        let numbers = version
+            .split('.')
+            .map(|number| number.parse::<u8>())
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid_format())?;
+        let (major, minor, patch) = match numbers[..] {
+            [major, minor] => (major, minor, None),
+            [major, minor, patch] => (major, minor, Some(patch)),
+            _ => return Err(invalid_format()),
+        };
+
+        let non_empty = |part: &str| match part.is_empty() {
+            true => None,
+            false => Some(Arc::from(part)),
+        };
+        KubernetesVersion::from_minor_version(major, minor, non_empty(prefix), patch, non_empty(suffix)).ok_or_else(
+            || KubernetesVersionError::UnsupportedVersion {
+                version: s.to_string(),
+                supported_versions: KubernetesVersion::iter()
+                    .map(|version| version.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            },
+        )
     }
 }
 
@@ -449,17 +553,41 @@ pub trait Kubernetes: Send + Sync {
     }
 
     fn kubeconfig_local_file_path(&self) -> PathBuf {
-        self.temp_dir()
+        self.workspace()
+            .root()
             .join(format!("qovery-kubeconfigs-{}", self.short_id()))
             .join(format!("{}.yaml", self.short_id()))
     }
 
-    fn temp_dir(&self) -> &Path;
+    /// Subtree of the cluster in the workspace of the execution
+    fn workspace(&self) -> &ServiceWorkspace;
 
     fn advanced_settings(&self) -> &ClusterAdvancedSettings;
     fn is_karpenter_enabled(&self) -> bool {
         false
     }
+    /// Object storage of the cluster account, used to check buckets without going through terraform
+    fn object_storage(&self) -> Option<&dyn ObjectStorage> {
+        None
+    }
+    /// Bucket receiving the SBOMs of the images built for the cluster, the one created with it for the kubeconfig
+    fn sboms_bucket_name(&self) -> String {
+        format!("qovery-kubeconfigs-{}", self.short_id())
+    }
+    /// Buckets created with the cluster and removed with it, buckets kept after deletion are not listed
+    fn buckets_removed_on_deletion(&self) -> Vec<String> {
+        vec![]
+    }
+    /// Kubeconfig issued again by the cloud provider, when the stored one does not permit to connect to the cluster
+    /// anymore (expired credentials, rotated certificates). None if the provider cannot issue one.
+    fn regenerate_kubeconfig(&self, _cloud_provider: &dyn CloudProvider) -> Result<Option<String>, Box<EngineError>> {
+        Ok(None)
+    }
+    /// Storage class set on the cluster to back the persistent volumes of the services, instead of the one
+    /// requested by each service. Only self-managed clusters, whose storage classes are unknown to Qovery, set one.
+    fn default_storage_class(&self) -> Option<&str> {
+        None
+    }
     fn loadbalancer_l4_annotations(&self, cloud_provider_lb_name: Option<&str>) -> Vec<(String, String)>;
 
     fn as_infra_actions(&self) -> &dyn InfrastructureAction;
@@ -551,27 +679,42 @@ where
     P: AsRef<Path>,
 {
     // https://cert-manager.io/docs/installation/uninstall/kubernetes/
-
-    let cert_manager_objects = vec![
-        "Issuers",
-        "ClusterIssuers",
-        "Certificates",
-        "CertificateRequests",
-        "Orders",
-        "Challenges",
+    let cert_manager_kinds = [
+        ("cert-manager.io", "Issuer"),
+        ("cert-manager.io", "ClusterIssuer"),
+        ("cert-manager.io", "Certificate"),
+        ("cert-manager.io", "CertificateRequest"),
+        ("acme.cert-manager.io", "Order"),
+        ("acme.cert-manager.io", "Challenge"),
     ];
 
-    for object in cert_manager_objects {
+    let kube_client = kube_client_or_kubectl_fallback(&kubernetes_config, &envs);
+    for (group, kind) in cert_manager_kinds {
+        let gvk = GroupVersionKind::gvk(group, "v1", kind);
+        let resource = ApiResource::from_gvk(&gvk);
+        let object = resource.plural.as_str();
+
         // check resource exist first
-        if let Err(e) = kubectl_exec_count_all_objects(&kubernetes_config, object, envs.clone()) {
-            logger.log(EngineEvent::Warning(
-                event_details.clone(),
-                EventMessage::new(
-                    format!("Encountering issues while trying to get objects kind {object}",),
-                    Some(e.message(ErrorMessageVerbosity::FullDetails)),
-                ),
-            ));
-            continue;
+        let objects_count = match &kube_client {
+            Some(kube_client) => block_on(kube_list_dynamic(kube_client, &gvk, None))
+                .map(|objects| objects.map(|objects| objects.len()))
+                .map_err(CommandError::from),
+            None => kubectl_exec_count_all_objects(&kubernetes_config, object, envs.clone()).map(Some),
+        };
+        match objects_count {
+            Ok(Some(count)) if count > 0 => {}
+            // the CRD is not installed, or there is nothing to delete
+            Ok(_) => continue,
+            Err(e) => {
+                logger.log(EngineEvent::Warning(
+                    event_details.clone(),
+                    EventMessage::new(
+                        format!("Encountering issues while trying to get objects kind {kind}",),
+                        Some(e.message(ErrorMessageVerbosity::FullDetails)),
+                    ),
+                ));
+                continue;
+            }
         }
 
         // delete if resource exists
@@ -582,7 +725,7 @@ where
                 Err(e) => {
                     logger.log(EngineEvent::Warning(
                         event_details.clone(),
-                        EventMessage::new(format!("Failed to delete all {object} objects, retrying...",), None),
+                        EventMessage::new(format!("Failed to delete all {kind} objects, retrying...",), None),
                     ));
                     OperationResult::Retry(e)
                 }
@@ -593,7 +736,7 @@ where
                 let engine_error = EngineError::new_cannot_uninstall_helm_chart(
                     event_details.clone(),
                     "Cert-Manager".to_string(),
-                    object.to_string(),
+                    kind.to_string(),
                     error,
                 );
 
@@ -611,6 +754,39 @@ where
     Ok(())
 }
 
+/// Kube client to list the objects of the cluster, `None` when it cannot be created and kubectl must be used instead
+fn kube_client_or_kubectl_fallback<P>(kubernetes_config: P, envs: &[(&str, &str)]) -> Option<kube::Client>
+where
+    P: AsRef<Path>,
+{
+    match block_on(create_kube_client(kubernetes_config, &envs_to_string(envs.to_vec()))) {
+        Ok(kube_client) => Some(kube_client),
+        Err(err) => {
+            warn!("Cannot create a kube client, falling back to kubectl: {}", err);
+            None
+        }
+    }
+}
+
+/// Nodes matching the selector, listed by kubectl when there is no kube client
+fn get_nodes<P>(
+    kube_client: Option<&kube::Client>,
+    kubernetes_config: P,
+    envs: &[(&str, &str)],
+    node_selector: Option<&str>,
+) -> Result<Vec<KubeNodeStatus>, CommandError>
+where
+    P: AsRef<Path>,
+{
+    match kube_client {
+        Some(kube_client) => block_on(kube_get_nodes(kube_client, node_selector))
+            .map(|nodes| nodes.iter().map(KubeNodeStatus::from).collect())
+            .map_err(CommandError::from),
+        None => kubectl_exec_get_node(kubernetes_config, envs.to_vec(), node_selector)
+            .map(|nodes| nodes.items.iter().map(KubeNodeStatus::from).collect()),
+    }
+}
+
 impl NodeGroupsWithDesiredState {
     pub fn new_from_node_groups(
         nodegroup: &NodeGroups,
@@ -627,6 +803,9 @@ impl NodeGroupsWithDesiredState {
             instance_type: nodegroup.instance_type.clone(),
             disk_size_in_gib: nodegroup.disk_size_in_gib,
             instance_architecture: nodegroup.instance_architecture,
+            labels: nodegroup.labels.clone(),
+            taints: nodegroup.taints.clone(),
+            capacity_type: nodegroup.capacity_type,
         }
     }
 }
@@ -670,30 +849,31 @@ where
 
     // check workers versions
     let mut workers_version: Vec<VersionsNumber> = vec![];
-    let nodes = match kubectl_exec_get_node(kubernetes_config, envs, node_selector) {
+    let kube_client = kube_client_or_kubectl_fallback(&kubernetes_config, &envs);
+    let nodes = match get_nodes(kube_client.as_ref(), &kubernetes_config, &envs, node_selector) {
         Ok(n) => n,
-        Err(e) => return Err(Box::new(EngineError::new_cannot_get_cluster_nodes(event_details, e))),
+        Err(e) => return Err(Box::new(EngineError::new_k8s_cannot_get_nodes(event_details, e))),
     };
 
-    for node in nodes.items {
+    for node in nodes {
         // check kubelet version
-        match VersionsNumber::from_str(node.status.node_info.kubelet_version.as_str()) {
+        match VersionsNumber::from_str(node.kubelet_version.as_str()) {
             Ok(vn) => workers_version.push(vn),
             Err(_) => {
                 return Err(Box::new(EngineError::new_cannot_determine_k8s_kubelet_worker_version(
                     event_details,
-                    node.status.node_info.kubelet_version.to_string(),
+                    node.kubelet_version.to_string(),
                 )));
             }
         }
 
         // check kube-proxy version
-        match VersionsNumber::from_str(node.status.node_info.kube_proxy_version.as_str()) {
+        match VersionsNumber::from_str(node.kube_proxy_version.as_str()) {
             Ok(vn) => workers_version.push(vn),
             Err(_) => {
                 return Err(Box::new(EngineError::new_cannot_determine_k8s_kube_proxy_version(
                     event_details,
-                    node.status.node_info.kube_proxy_version.to_string(),
+                    node.kube_proxy_version.to_string(),
                 )));
             }
         }
@@ -741,12 +921,13 @@ pub fn check_workers_upgrade_status<P>(
 where
     P: AsRef<Path>,
 {
+    let kube_client = kube_client_or_kubectl_fallback(&kubernetes_config, &envs);
     let result = retry::retry(Fixed::from_millis(10000).take(360), || {
-        match kubectl_exec_get_node(kubernetes_config.as_ref(), envs.clone(), node_selector) {
+        match get_nodes(kube_client.as_ref(), &kubernetes_config, &envs, node_selector) {
             Err(e) => OperationResult::Retry(e),
             Ok(nodes) => {
-                for node in nodes.items.iter() {
-                    if !node.status.node_info.kubelet_version.contains(&target_version[..4]) {
+                for node in nodes.iter() {
+                    if !node.kubelet_version.contains(&target_version[..4]) {
                         return OperationResult::Retry(CommandError::new_from_safe_message(
                             "There are still not upgraded nodes.".to_string(),
                         ));
@@ -812,21 +993,37 @@ pub fn check_workers_status<P>(
 where
     P: AsRef<Path>,
 {
+    check_workers_readiness(kubernetes_config, envs, node_selector, |_, _| {})
+}
+
+/// Waits for all the worker nodes to be ready, `on_ready_nodes` is called with the number of ready nodes and the total
+/// number of nodes each time one of them changes
+pub fn check_workers_readiness<P>(
+    kubernetes_config: P,
+    envs: Vec<(&str, &str)>,
+    node_selector: Option<&str>,
+    mut on_ready_nodes: impl FnMut(u32, u32),
+) -> Result<(), CommandError>
+where
+    P: AsRef<Path>,
+{
+    let kube_client = kube_client_or_kubectl_fallback(&kubernetes_config, &envs);
+    let mut last_ready_nodes = None;
     let result = retry::retry(Fixed::from_millis(10000).take(60), || {
-        match kubectl_exec_get_node(kubernetes_config.as_ref(), envs.clone(), node_selector) {
+        match get_nodes(kube_client.as_ref(), &kubernetes_config, &envs, node_selector) {
             Err(e) => OperationResult::Retry(e),
             Ok(nodes) => {
-                let mut conditions: Vec<KubernetesNodeCondition> = Vec::new();
-                for node in nodes.items.into_iter() {
-                    conditions.extend(node.status.conditions.into_iter());
+                let ready_nodes = nodes.iter().filter(|node| node.is_ready).count() as u32;
+                let total_nodes = nodes.len() as u32;
+                if last_ready_nodes != Some((ready_nodes, total_nodes)) {
+                    last_ready_nodes = Some((ready_nodes, total_nodes));
+                    on_ready_nodes(ready_nodes, total_nodes);
                 }
 
-                for condition in conditions.iter() {
-                    if condition.condition_type == "Ready" && condition.status != "True" {
-                        return OperationResult::Retry(CommandError::new_from_safe_message(
-                            "There are still not ready worker nodes.".to_string(),
-                        ));
-                    }
+                if ready_nodes < total_nodes {
+                    return OperationResult::Retry(CommandError::new_from_safe_message(
+                        "There are still not ready worker nodes.".to_string(),
+                    ));
                 }
                 OperationResult::Ok(())
             }
@@ -847,12 +1044,13 @@ pub fn check_workers_pause<P>(
 where
     P: AsRef<Path>,
 {
+    let kube_client = kube_client_or_kubectl_fallback(&kubernetes_config, &envs);
     let result = retry::retry(Fixed::from_millis(10000).take(60), || {
-        match kubectl_exec_get_node(kubernetes_config.as_ref(), envs.clone(), node_selector) {
+        match get_nodes(kube_client.as_ref(), &kubernetes_config, &envs, node_selector) {
             //TODO: handle error properly
             Err(_) => OperationResult::Ok(()),
             Ok(nodes) => {
-                if !nodes.items.is_empty() {
+                if !nodes.is_empty() {
                     return OperationResult::Retry(CommandError::new_from_safe_message(
                         "There are still not paused worker nodes.".to_string(),
                     ));
@@ -1099,9 +1297,54 @@ impl NodeGroups {
             disk_size_in_gib,
             desired_nodes: None,
             instance_architecture,
+            labels: BTreeMap::new(),
+            taints: vec![],
+            capacity_type: NodeGroupCapacityType::OnDemand,
         })
     }
 
+    pub fn validate_capacity_type(&self) -> Result<(), CommandError> {
+        if let NodeGroupCapacityType::Mixed { spot_percentage, .. } = self.capacity_type {
+            if spot_percentage > 100 {
+                return Err(CommandError::new_from_safe_message(format!(
+                    "Spot percentage ({spot_percentage}) of node group {} must be between 0 and 100",
+                    self.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_labels_and_taints(&self) -> Result<(), CommandError> {
+        for (key, value) in &self.labels {
+            if !is_valid_kubernetes_label_key(key) || !is_valid_kubernetes_label_value(value) {
+                return Err(CommandError::new_from_safe_message(format!(
+                    "Label `{key}={value}` of node group {} is not a valid Kubernetes label",
+                    self.name
+                )));
+            }
+        }
+
+        let mut taint_keys_and_effects = HashSet::with_capacity(self.taints.len());
+        for taint in &self.taints {
+            if !is_valid_kubernetes_label_key(&taint.key) || !is_valid_kubernetes_label_value(&taint.value) {
+                return Err(CommandError::new_from_safe_message(format!(
+                    "Taint `{}={}:{}` of node group {} is not a valid Kubernetes taint",
+                    taint.key, taint.value, taint.effect, self.name
+                )));
+            }
+            if !taint_keys_and_effects.insert((taint.key.as_str(), taint.effect)) {
+                return Err(CommandError::new_from_safe_message(format!(
+                    "Taint key {} is set several times with effect {} on node group {}",
+                    taint.key, taint.effect, self.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn to_ec2_instance(&self) -> InstanceEc2 {
         InstanceEc2 {
             instance_type: self.instance_type.clone(),
@@ -1422,7 +1665,7 @@ mod tests {
     };
     use crate::infrastructure::models::kubernetes::{
         kube_copy_secret_to_another_namespace, kube_create_namespace_if_not_exists, kube_does_secret_exists,
-        kube_list_services, KubernetesVersion as K8sVersion,
+        kube_list_services, KubernetesVersion as K8sVersion, KubernetesVersionError,
     };
     use crate::io_models::models::CpuLimits;
     use crate::io_models::QoveryIdentifier;
@@ -1443,6 +1686,10 @@ mod tests {
         fn error(self, _error: EngineError, _message: Option<impl Into<EventMessage>>) {}
 
         fn diff(&self, _from: InfrastructureDiffType, _message: String) {}
+
+        fn info_from(&self, _transmitter: Transmitter, _message: impl Into<EventMessage>) {}
+
+        fn report_progress(&self, _step: &str, _current: u32, _total: u32) {}
     }
 
     pub fn kubeconfig_path() -> String {
@@ -2033,6 +2280,26 @@ mod tests {
                         patch: None,
                         suffix: None,
                     }),
+                    "1.31" => Ok(kubernetes::KubernetesVersion::V1_31 {
+                        prefix: None,
+                        patch: None,
+                        suffix: None,
+                    }),
+                    "1.32" => Ok(kubernetes::KubernetesVersion::V1_32 {
+                        prefix: None,
+                        patch: None,
+                        suffix: None,
+                    }),
+                    "1.33" => Ok(kubernetes::KubernetesVersion::V1_33 {
+                        prefix: None,
+                        patch: None,
+                        suffix: None,
+                    }),
+                    "1.34" => Ok(kubernetes::KubernetesVersion::V1_34 {
+                        prefix: None,
+                        patch: None,
+                        suffix: None,
+                    }),
                     _ => panic!("unsupported k8s version string"),
                 },
                 K8sVersion::from_str(&k8s_version_str)
@@ -2102,6 +2369,89 @@ mod tests {
         assert!(K8sVersion::from_str("toto").is_err());
     }
 
+    #[test]
+    pub fn test_kubernetes_version_parsing() {
+        assert_eq!(
+            K8sVersion::from_str("v1.28.5+k3s1"),
+            Ok(K8sVersion::V1_28 {
+                prefix: Some(Arc::from("v")),
+                patch: Some(5),
+                suffix: Some(Arc::from("+k3s1")),
+            })
+        );
+        assert_eq!(
+            K8sVersion::from_str("1.33.2"),
+            Ok(K8sVersion::V1_33 {
+                prefix: None,
+                patch: Some(2),
+                suffix: None,
+            })
+        );
+        assert_eq!(
+            K8sVersion::from_str("v1.30.4-eks-a737599"),
+            Ok(K8sVersion::V1_30 {
+                prefix: Some(Arc::from("v")),
+                patch: Some(4),
+                suffix: Some(Arc::from("-eks-a737599")),
+            })
+        );
+
+        // display gives back the parsed string
+        for version in ["1.34", "1.33.2", "v1.28.5+k3s1", "v1.30.4-eks-a737599"] {
+            assert_eq!(K8sVersion::from_str(version).unwrap().to_string(), version);
+        }
+
+        for garbage in [
+            "", "toto", "1", "1.", "1.30.", "1.30.4.2", "v", "~1.30", "1.3a", "1.30+", "1.30.999",
+        ] {
+            assert_eq!(
+                K8sVersion::from_str(garbage),
+                Err(KubernetesVersionError::InvalidFormat {
+                    version: garbage.to_string()
+                }),
+                "{garbage}"
+            );
+        }
+
+        for unsupported in ["1.22", "1.35.1", "2.30"] {
+            match K8sVersion::from_str(unsupported) {
+                Err(KubernetesVersionError::UnsupportedVersion {
+                    version,
+                    supported_versions,
+                }) => {
+                    assert_eq!(version, unsupported);
+                    assert!(supported_versions.starts_with("1.23, 1.24"), "{supported_versions}");
+                    assert!(supported_versions.ends_with("1.34"), "{supported_versions}");
+                }
+                result => panic!("{unsupported} should not be supported: {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_kubernetes_version_upgrade_path() {
+        let versions = K8sVersion::iter().collect::<Vec<_>>();
+
+        assert_eq!(versions.first().and_then(|version| version.previous_version()), None);
+        assert_eq!(versions.last().and_then(|version| version.next_version()), None);
+        assert_eq!(versions.last().map(|version| version.minor()), Some(34));
+        for pair in versions.windows(2) {
+            assert_eq!(pair[0].next_version().as_ref(), Some(&pair[1]));
+            assert_eq!(pair[1].previous_version().as_ref(), Some(&pair[0]));
+            assert_eq!(pair[0].minor() + 1, pair[1].minor());
+        }
+
+        // the upgrade path ignores the patch version of the cluster
+        assert_eq!(
+            K8sVersion::from_str("1.33.2").unwrap().next_version(),
+            Some(K8sVersion::V1_34 {
+                prefix: None,
+                patch: None,
+                suffix: None,
+            })
+        );
+    }
+
     #[test]
     pub fn test_kubernetes_version_into_version_number() {
         // EKS / Kapsule / GKE
@@ -1,6 +1,7 @@
 use crate::errors::{CommandError, EngineError};
 use crate::events::Stage::Infrastructure;
 use crate::events::{EngineEvent, EventDetails, InfrastructureStep, Transmitter};
+use crate::fs::ServiceWorkspace;
 use crate::infrastructure::action::kubeconfig_helper::write_kubeconfig_on_disk;
 use crate::infrastructure::models::cloud_provider::io::ClusterAdvancedSettings;
 use crate::infrastructure::models::cloud_provider::CloudProvider;
@@ -11,21 +12,24 @@ use crate::infrastructure::models::kubernetes::{
 use crate::io_models::context::Context;
 use crate::io_models::engine_location::EngineLocation;
 use crate::io_models::engine_request::{ChartValuesOverrideName, ChartValuesOverrideValues};
-use crate::io_models::models::{CpuArchitecture, NodeGroups};
+use crate::io_models::models::{node_groups_cpu_architectures, CpuArchitecture, NodeGroups};
 use crate::io_models::QoveryIdentifier;
 use crate::logger::Logger;
 
 use crate::environment::models::domain::ToTerraformString;
 use crate::environment::models::scaleway::ScwZone;
 use crate::infrastructure::action::InfrastructureAction;
+use crate::infrastructure::models::object_storage::retry_policy::{RetryPolicy, RetryingObjectStorage};
 use crate::infrastructure::models::object_storage::scaleway_object_storage::ScalewayOS;
+use crate::infrastructure::models::object_storage::ObjectStorage;
 use crate::runtime::block_on;
 use crate::utilities::to_short_id;
+use base64::Engine;
 use scaleway_api_rs::models::ScalewayK8sV1Cluster;
 use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::str::FromStr;
 use uuid::Uuid;
 
@@ -81,6 +85,9 @@ pub struct KapsuleOptions {
     pub scaleway_secret_key: String,
     #[serde(default)]
     pub scaleway_kubernetes_type: KapsuleClusterType,
+    /// Attach the cluster to this customer owned private network instead of creating one
+    #[serde(default)]
+    pub existing_private_network_id: Option<String>,
 
     // Other
     pub tls_email_report: String,
@@ -103,6 +110,7 @@ impl KapsuleOptions {
         scaleway_secret_key: String,
         tls_email_report: String,
         scaleway_kubernetes_type: KapsuleClusterType,
+        existing_private_network_id: Option<String>,
     ) -> KapsuleOptions {
         KapsuleOptions {
             qovery_api_url,
@@ -119,6 +127,7 @@ impl KapsuleOptions {
             scaleway_secret_key,
             tls_email_report,
             scaleway_kubernetes_type,
+            existing_private_network_id,
         }
     }
 }
@@ -130,7 +139,7 @@ pub struct Kapsule {
     name: String,
     pub version: KubernetesVersion,
     pub zone: ScwZone,
-    pub object_storage: ScalewayOS,
+    pub object_storage: RetryingObjectStorage<ScalewayOS>,
     pub nodes_groups: Vec<NodeGroups>,
     pub template_directory: PathBuf,
     pub options: KapsuleOptions,
@@ -138,7 +147,7 @@ pub struct Kapsule {
     advanced_settings: ClusterAdvancedSettings,
     pub customer_helm_charts_override: Option<HashMap<ChartValuesOverrideName, ChartValuesOverrideValues>>,
     kubeconfig: Option<String>,
-    temp_dir: PathBuf,
+    workspace: ServiceWorkspace,
 }
 
 impl Kapsule {
@@ -155,7 +164,7 @@ impl Kapsule {
         advanced_settings: ClusterAdvancedSettings,
         customer_helm_charts_override: Option<HashMap<ChartValuesOverrideName, ChartValuesOverrideValues>>,
         kubeconfig: Option<String>,
-        temp_dir: PathBuf,
+        workspace: ServiceWorkspace,
     ) -> Result<Kapsule, Box<EngineError>> {
         let template_directory = PathBuf::from(context.lib_root_dir()).join("scaleway").join("bootstrap");
         let event_details = kubernetes::event_details(cloud_provider, long_id, name.to_string(), &context);
@@ -203,13 +212,17 @@ impl Kapsule {
         }
 
         advanced_settings.validate(event_details.clone())?;
-
-        let object_storage = ScalewayOS::new(
-            "s3-temp-id".to_string(),
-            "default-s3".to_string(),
-            cloud_provider.access_key_id(),
-            cloud_provider.secret_access_key(),
-            zone,
+        advanced_settings.cluster_autoscaler_settings(false, event_details.clone())?;
+
+        let object_storage = RetryingObjectStorage::new(
+            ScalewayOS::new(
+                "s3-temp-id".to_string(),
+                "default-s3".to_string(),
+                cloud_provider.access_key_id(),
+                cloud_provider.secret_access_key(),
+                zone,
+            ),
+            RetryPolicy::default(),
         );
 
         let cluster = Kapsule {
@@ -227,7 +240,7 @@ impl Kapsule {
             advanced_settings,
             customer_helm_charts_override,
             kubeconfig,
-            temp_dir,
+            workspace,
         };
 
         if let Some(kubeconfig) = &cluster.kubeconfig {
@@ -358,14 +371,11 @@ impl Kubernetes for Kapsule {
     }
 
     fn cpu_architectures(&self) -> Vec<CpuArchitecture> {
-        self.nodes_groups
-            .iter()
-            .map(|node| node.instance_architecture)
-            .collect()
+        node_groups_cpu_architectures(&self.nodes_groups)
     }
 
-    fn temp_dir(&self) -> &Path {
-        &self.temp_dir
+    fn workspace(&self) -> &ServiceWorkspace {
+        &self.workspace
     }
 
     fn advanced_settings(&self) -> &ClusterAdvancedSettings {
@@ -404,6 +414,52 @@ impl Kubernetes for Kapsule {
         ]
     }
 
+    fn object_storage(&self) -> Option<&dyn ObjectStorage> {
+        Some(&self.object_storage)
+    }
+
+    fn buckets_removed_on_deletion(&self) -> Vec<String> {
+        vec![self.kubeconfig_bucket_name(), self.logs_bucket_name()]
+    }
+
+    fn regenerate_kubeconfig(&self, _cloud_provider: &dyn CloudProvider) -> Result<Option<String>, Box<EngineError>> {
+        let Some(cluster_id) = self.get_scw_cluster_info()?.and_then(|cluster| cluster.id) else {
+            return Ok(None);
+        };
+        let event_details = self.get_event_details(Infrastructure(InfrastructureStep::LoadConfiguration));
+        let to_engine_error = |safe_message: &str, raw_message: String| {
+            Box::new(EngineError::new_k8s_cannot_generate_kubeconfig(
+                event_details.clone(),
+                CommandError::new(safe_message.to_string(), Some(raw_message), None),
+            ))
+        };
+
+        #[derive(Deserialize)]
+        struct ScwKubeconfig {
+            content: String,
+        }
+
+        let url = format!(
+            "https://api.scaleway.com/k8s/v1/regions/{}/clusters/{}/kubeconfig",
+            self.region(),
+            cluster_id
+        );
+        let kubeconfig = reqwest::blocking::Client::new()
+            .get(url)
+            .header("X-Auth-Token", self.options.scaleway_secret_key.as_str())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json::<ScwKubeconfig>())
+            .map_err(|err| to_engine_error("Cannot retrieve the kubeconfig from Scaleway API", err.to_string()))?;
+        let kubeconfig = base64::engine::general_purpose::STANDARD
+            .decode(kubeconfig.content)
+            .map_err(|err| err.to_string())
+            .and_then(|content| String::from_utf8(content).map_err(|err| err.to_string()))
+            .map_err(|err| to_engine_error("Cannot decode the kubeconfig returned by Scaleway API", err))?;
+
+        Ok(Some(kubeconfig))
+    }
+
     fn as_infra_actions(&self) -> &dyn InfrastructureAction {
         self
     }
@@ -613,7 +613,8 @@ impl ScwNodeGroup {
 mod tests {
     use crate::infrastructure::models::kubernetes::scaleway::node::ScwInstancesType;
     use crate::infrastructure::models::kubernetes::InstanceType;
-    use crate::io_models::models::{CpuArchitecture, NodeGroups};
+    use crate::io_models::models::{CpuArchitecture, NodeGroupCapacityType, NodeGroups};
+    use std::collections::BTreeMap;
     use std::str::FromStr;
     use strum::IntoEnumIterator;
 
@@ -728,6 +729,9 @@ mod tests {
                 disk_size_in_gib: 20,
                 desired_nodes: None,
                 instance_architecture: CpuArchitecture::AMD64,
+                labels: BTreeMap::new(),
+                taints: vec![],
+                capacity_type: NodeGroupCapacityType::OnDemand,
             }
         );
     }
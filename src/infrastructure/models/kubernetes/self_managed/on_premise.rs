@@ -1,11 +1,11 @@
 use std::borrow::Borrow;
 use std::env;
-use std::path::{Path, PathBuf};
 
 use uuid::Uuid;
 
 use crate::cmd::docker;
 use crate::errors::EngineError;
+use crate::fs::ServiceWorkspace;
 use crate::infrastructure::action::InfrastructureAction;
 use crate::infrastructure::models::cloud_provider::io::ClusterAdvancedSettings;
 use crate::infrastructure::models::cloud_provider::CloudProvider;
@@ -27,12 +27,11 @@ pub struct SelfManaged {
     name: String,
     version: KubernetesVersion,
     region: String,
-    #[allow(dead_code)] //TODO(pmavro): not yet implemented
     options: SelfManagedOptions,
     logger: Box<dyn Logger>,
     advanced_settings: ClusterAdvancedSettings,
     _kubeconfig: Option<String>,
-    temp_dir: PathBuf,
+    workspace: ServiceWorkspace,
 }
 
 impl SelfManaged {
@@ -47,7 +46,7 @@ impl SelfManaged {
         logger: Box<dyn Logger>,
         advanced_settings: ClusterAdvancedSettings,
         kubeconfig: Option<String>,
-        temp_dir: PathBuf,
+        workspace: ServiceWorkspace,
     ) -> Result<SelfManaged, Box<EngineError>> {
         let cluster = SelfManaged {
             context,
@@ -61,7 +60,7 @@ impl SelfManaged {
             logger,
             advanced_settings,
             _kubeconfig: kubeconfig,
-            temp_dir,
+            workspace,
         };
 
         Ok(cluster)
@@ -76,6 +75,9 @@ pub struct SelfManagedOptions {
     pub qovery_engine_url: String,
     pub jwt_token: String,
     pub qovery_engine_location: EngineLocation,
+    // Storage
+    #[serde(default)]
+    pub default_storage_class: Option<String>,
 }
 
 impl Kubernetes for SelfManaged {
@@ -140,14 +142,18 @@ impl Kubernetes for SelfManaged {
         }
     }
 
-    fn temp_dir(&self) -> &Path {
-        &self.temp_dir
+    fn workspace(&self) -> &ServiceWorkspace {
+        &self.workspace
     }
 
     fn advanced_settings(&self) -> &ClusterAdvancedSettings {
         &self.advanced_settings
     }
 
+    fn default_storage_class(&self) -> Option<&str> {
+        self.options.default_storage_class.as_deref()
+    }
+
     fn loadbalancer_l4_annotations(&self, _cloud_provider_lb_name: Option<&str>) -> Vec<(String, String)> {
         Vec::with_capacity(0)
     }
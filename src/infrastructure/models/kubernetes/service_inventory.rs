@@ -0,0 +1,455 @@
+// Inventory of the Qovery services running on a cluster, as seen by kubernetes. The core reconciles its database with
+// it, and stores it as is in object storage. Pods are folded into the inventory one page at a time, so memory depends on
+// the number of services rather than on the number of pods.
+
+use chrono::{DateTime, Duration, Utc};
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::{Event, Pod};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+/// Bumped on every breaking change of the document, the core refuses versions it does not know.
+pub const SERVICE_INVENTORY_DOCUMENT_VERSION: u32 = 1;
+pub const SERVICE_ID_LABEL: &str = "qovery.com/service-id";
+const SERVICE_TYPE_LABEL: &str = "qovery.com/service-type";
+const ENVIRONMENT_ID_LABEL: &str = "qovery.com/environment-id";
+// Restarts and events older than this are not reported
+const RECENT_WINDOW_IN_MINUTES: i64 = 60;
+const MAX_EVENTS_PER_SERVICE: usize = 5;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct WorkloadCondition {
+    /// Name of the deployment or statefulset
+    pub workload: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ServiceEvent {
+    /// Kind and name of the object the event is about, e.g `Pod/app-z1234-7d9f8-abcde`
+    pub object: String,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    pub count: i32,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ServiceInventory {
+    pub service_id: Uuid,
+    pub service_type: Option<String>,
+    pub environment_id: Option<String>,
+    pub namespace: String,
+    /// Replicas of the deployments and statefulsets of the service, jobs have none
+    pub desired_replicas: i32,
+    pub ready_replicas: i32,
+    pub pods: usize,
+    /// Digests of the images the containers actually run, e.g `docker.io/library/nginx@sha256:...`
+    pub image_digests: BTreeSet<String>,
+    /// Containers which restarted in the last hour. The api only keeps the last termination of a container,
+    /// so a container restarting several times counts once.
+    pub restarts_last_hour: u32,
+    pub total_restarts: u32,
+    /// Conditions of the workloads which are not `True`
+    pub conditions: Vec<WorkloadCondition>,
+    /// Most recent warning events of the last hour, newest first
+    pub last_events: Vec<ServiceEvent>,
+}
+
+impl ServiceInventory {
+    fn new(service_id: Uuid, metadata: &ObjectMeta) -> Self {
+        let label = |name: &str| metadata.labels.as_ref().and_then(|labels| labels.get(name)).cloned();
+
+        ServiceInventory {
+            service_id,
+            service_type: label(SERVICE_TYPE_LABEL),
+            environment_id: label(ENVIRONMENT_ID_LABEL),
+            namespace: metadata.namespace.clone().unwrap_or_default(),
+            desired_replicas: 0,
+            ready_replicas: 0,
+            pods: 0,
+            image_digests: BTreeSet::new(),
+            restarts_last_hour: 0,
+            total_restarts: 0,
+            conditions: vec![],
+            last_events: vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ServiceInventoryDocument {
+    pub version: u32,
+    pub cluster_id: Uuid,
+    pub generated_at: DateTime<Utc>,
+    /// The listing stopped at the pods limit of the request, some pods are missing
+    pub truncated: bool,
+    pub services: Vec<ServiceInventory>,
+}
+
+impl Display for ServiceInventoryDocument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let pods: usize = self.services.iter().map(|service| service.pods).sum();
+        write!(f, "Inventory of {} service(s) and {} pod(s)", self.services.len(), pods)?;
+        if self.truncated {
+            write!(f, ", truncated as the cluster has too many pods")?;
+        }
+        Ok(())
+    }
+}
+
+/// Folds the kubernetes objects of the Qovery services into their inventory.
+/// Objects without a valid service id label are ignored.
+pub struct ServiceInventoryBuilder {
+    now: DateTime<Utc>,
+    services: BTreeMap<Uuid, ServiceInventory>,
+    // events reference objects by name only
+    service_by_object: HashMap<(String, String, String), Uuid>,
+}
+
+impl ServiceInventoryBuilder {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        ServiceInventoryBuilder {
+            now,
+            services: BTreeMap::new(),
+            service_by_object: HashMap::new(),
+        }
+    }
+
+    fn service(&mut self, kind: &str, metadata: &ObjectMeta) -> Option<&mut ServiceInventory> {
+        let service_id = metadata
+            .labels
+            .as_ref()?
+            .get(SERVICE_ID_LABEL)
+            .and_then(|service_id| Uuid::parse_str(service_id).ok())?;
+        self.service_by_object.insert(
+            (
+                kind.to_string(),
+                metadata.namespace.clone().unwrap_or_default(),
+                metadata.name.clone().unwrap_or_default(),
+            ),
+            service_id,
+        );
+
+        Some(
+            self.services
+                .entry(service_id)
+                .or_insert_with(|| ServiceInventory::new(service_id, metadata)),
+        )
+    }
+
+    pub fn add_deployment(&mut self, deployment: &Deployment) {
+        let name = deployment.metadata.name.clone().unwrap_or_default();
+        let Some(service) = self.service("Deployment", &deployment.metadata) else {
+            return;
+        };
+        service.desired_replicas += deployment.spec.as_ref().and_then(|spec| spec.replicas).unwrap_or(1);
+        let Some(status) = &deployment.status else {
+            return;
+        };
+        service.ready_replicas += status.ready_replicas.unwrap_or(0);
+        for condition in status.conditions.iter().flatten().filter(|c| c.status != "True") {
+            service.conditions.push(WorkloadCondition {
+                workload: name.clone(),
+                type_: condition.type_.clone(),
+                status: condition.status.clone(),
+                reason: condition.reason.clone(),
+                message: condition.message.clone(),
+            });
+        }
+    }
+
+    pub fn add_stateful_set(&mut self, stateful_set: &StatefulSet) {
+        let name = stateful_set.metadata.name.clone().unwrap_or_default();
+        let Some(service) = self.service("StatefulSet", &stateful_set.metadata) else {
+            return;
+        };
+        service.desired_replicas += stateful_set.spec.as_ref().and_then(|spec| spec.replicas).unwrap_or(1);
+        let Some(status) = &stateful_set.status else {
+            return;
+        };
+        service.ready_replicas += status.ready_replicas.unwrap_or(0);
+        for condition in status.conditions.iter().flatten().filter(|c| c.status != "True") {
+            service.conditions.push(WorkloadCondition {
+                workload: name.clone(),
+                type_: condition.type_.clone(),
+                status: condition.status.clone(),
+                reason: condition.reason.clone(),
+                message: condition.message.clone(),
+            });
+        }
+    }
+
+    pub fn add_pod(&mut self, pod: &Pod) {
+        let recent = self.now - Duration::minutes(RECENT_WINDOW_IN_MINUTES);
+        let Some(service) = self.service("Pod", &pod.metadata) else {
+            return;
+        };
+        service.pods += 1;
+
+        let container_statuses = pod.status.iter().flat_map(|status| {
+            status
+                .init_container_statuses
+                .iter()
+                .flatten()
+                .chain(status.container_statuses.iter().flatten())
+        });
+        for container in container_statuses {
+            if !container.image_id.is_empty() {
+                service.image_digests.insert(container.image_id.clone());
+            }
+            service.total_restarts += container.restart_count.max(0) as u32;
+            let last_termination = container
+                .last_state
+                .as_ref()
+                .and_then(|state| state.terminated.as_ref())
+                .and_then(|terminated| terminated.finished_at.as_ref());
+            if last_termination.is_some_and(|finished_at| finished_at.0 >= recent) {
+                service.restarts_last_hour += 1;
+            }
+        }
+    }
+
+    /// Events of the objects added before are kept, the others are ignored
+    pub fn add_event(&mut self, event: &Event) {
+        let involved = &event.involved_object;
+        let key = (
+            involved.kind.clone().unwrap_or_default(),
+            involved.namespace.clone().unwrap_or_default(),
+            involved.name.clone().unwrap_or_default(),
+        );
+        let Some(service_id) = self.service_by_object.get(&key) else {
+            return;
+        };
+        let Some(last_seen) = event
+            .last_timestamp
+            .as_ref()
+            .map(|time| time.0)
+            .or_else(|| event.event_time.as_ref().map(|time| time.0))
+        else {
+            return;
+        };
+        if last_seen < self.now - Duration::minutes(RECENT_WINDOW_IN_MINUTES) {
+            return;
+        }
+        let Some(service) = self.services.get_mut(service_id) else {
+            return;
+        };
+
+        service.last_events.push(ServiceEvent {
+            object: format!("{}/{}", key.0, key.2),
+            reason: event.reason.clone(),
+            message: event.message.clone(),
+            count: event.count.unwrap_or(1),
+            last_seen,
+        });
+        service.last_events.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+        service.last_events.truncate(MAX_EVENTS_PER_SERVICE);
+    }
+
+    pub fn build(self, cluster_id: Uuid, truncated: bool) -> ServiceInventoryDocument {
+        ServiceInventoryDocument {
+            version: SERVICE_INVENTORY_DOCUMENT_VERSION,
+            cluster_id,
+            generated_at: self.now,
+            truncated,
+            services: self.services.into_values().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use k8s_openapi::api::apps::v1::{DeploymentCondition, DeploymentSpec, DeploymentStatus};
+    use k8s_openapi::api::core::v1::{
+        ContainerState, ContainerStateTerminated, ContainerStatus, ObjectReference, PodStatus,
+    };
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+
+    const SERVICE_ID: &str = "8b2b5ef6-1f39-4b0d-9a3f-6d2a3c1c5e10";
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn metadata(name: &str, service_id: Option<&str>) -> ObjectMeta {
+        let mut labels = BTreeMap::from([(SERVICE_TYPE_LABEL.to_string(), "application".to_string())]);
+        if let Some(service_id) = service_id {
+            labels.insert(SERVICE_ID_LABEL.to_string(), service_id.to_string());
+        }
+        ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: Some("z1234-production".to_string()),
+            labels: Some(labels),
+            ..Default::default()
+        }
+    }
+
+    fn deployment(replicas: i32, ready_replicas: i32) -> Deployment {
+        Deployment {
+            metadata: metadata("app-z1234", Some(SERVICE_ID)),
+            spec: Some(DeploymentSpec {
+                replicas: Some(replicas),
+                ..Default::default()
+            }),
+            status: Some(DeploymentStatus {
+                ready_replicas: Some(ready_replicas),
+                conditions: Some(vec![
+                    DeploymentCondition {
+                        type_: "Progressing".to_string(),
+                        status: "True".to_string(),
+                        ..Default::default()
+                    },
+                    DeploymentCondition {
+                        type_: "Available".to_string(),
+                        status: "False".to_string(),
+                        reason: Some("MinimumReplicasUnavailable".to_string()),
+                        ..Default::default()
+                    },
+                ]),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn pod(name: &str, service_id: Option<&str>, restart_count: i32, last_termination: Option<DateTime<Utc>>) -> Pod {
+        Pod {
+            metadata: metadata(name, service_id),
+            spec: None,
+            status: Some(PodStatus {
+                container_statuses: Some(vec![ContainerStatus {
+                    name: "app".to_string(),
+                    image: "nginx:1.27".to_string(),
+                    image_id: "docker.io/library/nginx@sha256:1234".to_string(),
+                    restart_count,
+                    last_state: last_termination.map(|finished_at| ContainerState {
+                        terminated: Some(ContainerStateTerminated {
+                            finished_at: Some(Time(finished_at)),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn event(pod_name: &str, reason: &str, last_seen: DateTime<Utc>) -> Event {
+        Event {
+            involved_object: ObjectReference {
+                kind: Some("Pod".to_string()),
+                namespace: Some("z1234-production".to_string()),
+                name: Some(pod_name.to_string()),
+                ..Default::default()
+            },
+            reason: Some(reason.to_string()),
+            message: Some(format!("{reason} message")),
+            count: Some(2),
+            last_timestamp: Some(Time(last_seen)),
+            metadata: ObjectMeta::default(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_inventory_aggregates_workloads_pods_and_events_per_service() {
+        let mut builder = ServiceInventoryBuilder::new(now());
+        builder.add_deployment(&deployment(2, 1));
+        builder.add_pod(&pod("app-z1234-1", Some(SERVICE_ID), 3, Some(now() - Duration::minutes(5))));
+        builder.add_pod(&pod("app-z1234-2", Some(SERVICE_ID), 1, Some(now() - Duration::hours(3))));
+        // not a Qovery service
+        builder.add_pod(&pod("other", None, 10, Some(now())));
+        builder.add_event(&event("app-z1234-1", "BackOff", now() - Duration::minutes(1)));
+        builder.add_event(&event("app-z1234-1", "Unhealthy", now() - Duration::minutes(2)));
+        builder.add_event(&event("app-z1234-2", "Killing", now() - Duration::hours(2)));
+        builder.add_event(&event("other", "BackOff", now()));
+
+        let document = builder.build(Uuid::nil(), false);
+
+        assert_eq!(document.services.len(), 1);
+        let service = &document.services[0];
+        assert_eq!(service.service_id.to_string(), SERVICE_ID);
+        assert_eq!(service.service_type.as_deref(), Some("application"));
+        assert_eq!(service.namespace, "z1234-production");
+        assert_eq!((service.desired_replicas, service.ready_replicas, service.pods), (2, 1, 2));
+        assert_eq!(
+            service.image_digests,
+            BTreeSet::from(["docker.io/library/nginx@sha256:1234".to_string()])
+        );
+        assert_eq!((service.restarts_last_hour, service.total_restarts), (1, 4));
+        assert_eq!(
+            service.conditions,
+            vec![WorkloadCondition {
+                workload: "app-z1234".to_string(),
+                type_: "Available".to_string(),
+                status: "False".to_string(),
+                reason: Some("MinimumReplicasUnavailable".to_string()),
+                message: None,
+            }]
+        );
+        assert_eq!(
+            service
+                .last_events
+                .iter()
+                .map(|event| event.reason.as_deref().unwrap_or_default())
+                .collect::<Vec<_>>(),
+            vec!["BackOff", "Unhealthy"]
+        );
+        assert_eq!(document.to_string(), "Inventory of 1 service(s) and 2 pod(s)");
+    }
+
+    #[test]
+    fn test_events_are_bounded_per_service() {
+        let mut builder = ServiceInventoryBuilder::new(now());
+        builder.add_pod(&pod("app-z1234-1", Some(SERVICE_ID), 0, None));
+        for minutes in 0..20 {
+            builder.add_event(&event(
+                "app-z1234-1",
+                &format!("Reason{minutes}"),
+                now() - Duration::minutes(minutes),
+            ));
+        }
+
+        let document = builder.build(Uuid::nil(), true);
+
+        let events = &document.services[0].last_events;
+        assert_eq!(events.len(), MAX_EVENTS_PER_SERVICE);
+        assert_eq!(events[0].reason.as_deref(), Some("Reason0"));
+        assert!(document
+            .to_string()
+            .ends_with("truncated as the cluster has too many pods"));
+    }
+
+    #[test]
+    fn test_document_serde_round_trip() {
+        let mut builder = ServiceInventoryBuilder::new(now());
+        builder.add_deployment(&deployment(1, 1));
+        builder.add_pod(&pod("app-z1234-1", Some(SERVICE_ID), 1, Some(now() - Duration::minutes(5))));
+        builder.add_event(&event("app-z1234-1", "BackOff", now()));
+        let document = builder.build(Uuid::nil(), false);
+
+        // the core relies on this schema
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(json["version"], SERVICE_INVENTORY_DOCUMENT_VERSION);
+        assert_eq!(json["generated_at"], "2024-06-01T12:00:00Z");
+        assert_eq!(json["services"][0]["service_id"], SERVICE_ID);
+        assert_eq!(json["services"][0]["conditions"][0]["type"], "Available");
+        assert_eq!(json["services"][0]["last_events"][0]["object"], "Pod/app-z1234-1");
+        assert_eq!(serde_json::from_value::<ServiceInventoryDocument>(json).unwrap(), document);
+
+        let empty = ServiceInventoryBuilder::new(now()).build(Uuid::nil(), false);
+        let json = serde_json::to_string(&empty).unwrap();
+        assert_eq!(serde_json::from_str::<ServiceInventoryDocument>(&json).unwrap(), empty);
+    }
+}
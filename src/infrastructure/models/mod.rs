@@ -1,6 +1,7 @@
 pub mod build_platform;
 pub mod cloud_provider;
 pub mod container_registry;
+pub mod deletion_report;
 pub mod dns_provider;
 pub mod kubernetes;
 pub mod object_storage;
@@ -0,0 +1,478 @@
+use crate::environment::models::ToCloudProviderFormat;
+use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+use crate::infrastructure::models::object_storage::multipart_upload::multipart_upload;
+use crate::infrastructure::models::object_storage::{
+    Bucket, BucketCredentials, BucketDeleteStrategy, BucketObject, BucketRegion, BucketSettings,
+};
+use crate::infrastructure::models::object_storage::{Kind, ObjectStorage};
+use crate::services::azure::blob_storage_service::{
+    AzureBlobStorageService, AzureBlobStorageServiceError, AzureBlockBlobUpload, AzureStorageRegion,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Azure Blob Storage, a bucket being a container of the storage account.
+pub struct AzureBlobOS {
+    id: String,
+    _long_id: Uuid,
+    name: String,
+    region: AzureStorageRegion,
+    service: Arc<AzureBlobStorageService>,
+}
+
+impl AzureBlobOS {
+    pub fn new(
+        id: &str,
+        long_id: Uuid,
+        name: &str,
+        region: AzureStorageRegion,
+        service: Arc<AzureBlobStorageService>,
+    ) -> AzureBlobOS {
+        Self {
+            id: id.to_string(),
+            _long_id: long_id,
+            name: name.to_string(),
+            region,
+            service,
+        }
+    }
+
+    fn bucket_from_metadata(
+        &self,
+        bucket_name: &str,
+        metadata: HashMap<String, String>,
+        versioning_activated: bool,
+    ) -> Bucket {
+        Bucket {
+            name: bucket_name.to_string(),
+            ttl: metadata
+                .get("ttl")
+                .and_then(|ttl| ttl.parse::<u64>().ok())
+                .filter(|ttl| *ttl > 0)
+                .map(Duration::from_secs),
+            versioning_activated,
+            location: BucketRegion::AzureRegion(self.region.clone()),
+            labels: Some(metadata),
+        }
+    }
+}
+
+/// Container naming rules: https://learn.microsoft.com/en-us/rest/api/storageservices/naming-and-referencing-containers--blobs--and-metadata
+/// Unlike S3, dots and uppercase characters are rejected, and hyphens cannot be consecutive.
+pub fn validate_container_name(container_name: &str) -> Result<(), ObjectStorageError> {
+    let invalid = |reason: &str| {
+        Err(ObjectStorageError::InvalidBucketName {
+            bucket_name: container_name.to_string(),
+            raw_error_message: format!("Azure container name {reason}"),
+        })
+    };
+
+    if container_name.len() < 3 || container_name.len() > 63 {
+        return invalid("must be between 3 and 63 characters long");
+    }
+    if !container_name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return invalid("can only contain lowercase letters, numbers and hyphens");
+    }
+    if container_name.starts_with('-') || container_name.ends_with('-') {
+        return invalid("must start and end with a letter or a number");
+    }
+    if container_name.contains("--") {
+        return invalid("cannot contain consecutive hyphens");
+    }
+
+    Ok(())
+}
+
+/// Keeps the failing operation, unless the error is about the client, the name or the account throttling.
+fn to_object_storage_error(
+    bucket_name: &str,
+    error: AzureBlobStorageServiceError,
+    operation_error: impl FnOnce(String) -> ObjectStorageError,
+) -> ObjectStorageError {
+    match error {
+        AzureBlobStorageServiceError::CannotInstantiateClient { .. }
+        | AzureBlobStorageServiceError::CannotAuthenticate { .. } => ObjectStorageError::CannotInstantiateClient {
+            raw_error_message: error.to_string(),
+        },
+        AzureBlobStorageServiceError::Api { ref code, .. } if code == "InvalidResourceName" => {
+            ObjectStorageError::InvalidBucketName {
+                bucket_name: bucket_name.to_string(),
+                raw_error_message: error.to_string(),
+            }
+        }
+        AzureBlobStorageServiceError::Api { ref code, .. } if code == "ServerBusy" => {
+            ObjectStorageError::QuotasExceeded {
+                bucket_name: bucket_name.to_string(),
+                raw_error_message: error.to_string(),
+            }
+        }
+        _ => operation_error(error.to_string()),
+    }
+}
+
+impl ObjectStorage for AzureBlobOS {
+    fn kind(&self) -> Kind {
+        Kind::AzureBlob
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn is_valid(&self) -> Result<(), ObjectStorageError> {
+        // TODO check valid credentials
+        Ok(())
+    }
+
+    fn bucket_exists(&self, bucket_name: &str) -> bool {
+        self.service.get_container(bucket_name).is_ok()
+    }
+
+    fn create_bucket(
+        &self,
+        bucket_name: &str,
+        bucket_ttl: Option<Duration>,
+        bucket_versioning_activated: bool,
+    ) -> Result<Bucket, ObjectStorageError> {
+        validate_container_name(bucket_name)?;
+        if let Ok(existing_bucket) = self.get_bucket(bucket_name) {
+            return Ok(existing_bucket);
+        }
+
+        let creation_date: DateTime<Utc> = Utc::now();
+        // containers have no tags, metadata is used instead
+        let metadata = HashMap::from([
+            ("creation_date".to_string(), creation_date.timestamp().to_string()),
+            (
+                "ttl".to_string(),
+                format!("{}", bucket_ttl.map(|ttl| ttl.as_secs()).unwrap_or(0)),
+            ),
+        ]);
+        let to_error = |e| {
+            to_object_storage_error(bucket_name, e, |raw_error_message| ObjectStorageError::CannotCreateBucket {
+                bucket_name: bucket_name.to_string(),
+                raw_error_message,
+            })
+        };
+        self.service
+            .create_container(bucket_name, &metadata)
+            .map_err(to_error)?;
+
+        let versioning_activated = match bucket_versioning_activated {
+            true => self.service.set_versioning(true).map(|_| true).map_err(|e| {
+                to_object_storage_error(bucket_name, e, |raw_error_message| {
+                    ObjectStorageError::CannotActivateBucketVersioning {
+                        bucket_name: bucket_name.to_string(),
+                        raw_error_message,
+                    }
+                })
+            })?,
+            false => false,
+        };
+
+        Ok(self.bucket_from_metadata(bucket_name, metadata, versioning_activated))
+    }
+
+    fn update_bucket(
+        &self,
+        bucket_name: &str,
+        bucket_versioning_activated: bool,
+    ) -> Result<Bucket, ObjectStorageError> {
+        let bucket = self
+            .get_bucket(bucket_name)
+            .map_err(|e| ObjectStorageError::CannotUpdateBucket {
+                bucket_name: bucket_name.to_string(),
+                raw_error_message: e.to_string(),
+            })?;
+        if bucket.versioning_activated == bucket_versioning_activated {
+            return Ok(bucket);
+        }
+
+        // versioning is shared by every container of the storage account
+        self.service.set_versioning(bucket_versioning_activated).map_err(|e| {
+            to_object_storage_error(bucket_name, e, |raw_error_message| ObjectStorageError::CannotUpdateBucket {
+                bucket_name: bucket_name.to_string(),
+                raw_error_message,
+            })
+        })?;
+
+        Ok(Bucket {
+            versioning_activated: bucket_versioning_activated,
+            ..bucket
+        })
+    }
+
+    fn get_bucket(&self, bucket_name: &str) -> Result<Bucket, ObjectStorageError> {
+        let to_error = |e| {
+            to_object_storage_error(bucket_name, e, |raw_error_message| ObjectStorageError::CannotGetBucket {
+                bucket_name: bucket_name.to_string(),
+                raw_error_message,
+            })
+        };
+        let container = self.service.get_container(bucket_name).map_err(to_error)?;
+        let versioning_activated = self.service.is_versioning_enabled().map_err(to_error)?;
+
+        Ok(self.bucket_from_metadata(bucket_name, container.metadata, versioning_activated))
+    }
+
+    fn delete_bucket(
+        &self,
+        bucket_name: &str,
+        bucket_delete_strategy: BucketDeleteStrategy,
+    ) -> Result<(), ObjectStorageError> {
+        match bucket_delete_strategy {
+            // deleting a container deletes its blobs and their versions
+            BucketDeleteStrategy::HardDelete => self.service.delete_container(bucket_name).map_err(|e| {
+                to_object_storage_error(bucket_name, e, |raw_error_message| ObjectStorageError::CannotDeleteBucket {
+                    bucket_name: bucket_name.to_string(),
+                    raw_error_message,
+                })
+            }),
+            BucketDeleteStrategy::Empty => self.service.empty_container(bucket_name).map_err(|e| {
+                to_object_storage_error(bucket_name, e, |raw_error_message| ObjectStorageError::CannotEmptyBucket {
+                    bucket_name: bucket_name.to_string(),
+                    raw_error_message,
+                })
+            }),
+        }
+    }
+
+    fn delete_bucket_non_blocking(&self, bucket_name: &str) -> Result<(), ObjectStorageError> {
+        // Azure already deletes containers asynchronously
+        self.delete_bucket(bucket_name, BucketDeleteStrategy::HardDelete)
+    }
+
+    fn get_object(&self, bucket_name: &str, object_key: &str) -> Result<BucketObject, ObjectStorageError> {
+        match self.service.get_blob(bucket_name, object_key) {
+            Ok((value, tags)) => Ok(BucketObject {
+                bucket_name: bucket_name.to_string(),
+                key: object_key.to_string(),
+                value,
+                tags,
+            }),
+            Err(e) => Err(to_object_storage_error(bucket_name, e, |raw_error_message| {
+                ObjectStorageError::CannotGetObjectFile {
+                    bucket_name: bucket_name.to_string(),
+                    object_name: object_key.to_string(),
+                    raw_error_message,
+                }
+            })),
+        }
+    }
+
+    fn put_object(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        file_path: &Path,
+        tags: Option<Vec<String>>,
+    ) -> Result<BucketObject, ObjectStorageError> {
+        let to_error = |raw_error_message| ObjectStorageError::CannotUploadFile {
+            bucket_name: bucket_name.to_string(),
+            object_name: object_key.to_string(),
+            raw_error_message,
+        };
+        let file_content = std::fs::read(file_path).map_err(|e| to_error(e.to_string()))?;
+        let tags = tags.unwrap_or_default();
+
+        self.service
+            .put_blob(bucket_name, object_key, file_content.clone(), &tags)
+            .map_err(|e| to_object_storage_error(bucket_name, e, to_error))?;
+
+        Ok(BucketObject {
+            bucket_name: bucket_name.to_string(),
+            key: object_key.to_string(),
+            value: file_content,
+            tags,
+        })
+    }
+
+    fn put_object_streaming(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        reader: &mut dyn Read,
+        size_hint: Option<u64>,
+        tags: Option<Vec<String>>,
+    ) -> Result<(), ObjectStorageError> {
+        let upload = AzureBlockBlobUpload {
+            service: &self.service,
+            container_name: bucket_name,
+            blob_name: object_key,
+            tags: tags.unwrap_or_default(),
+        };
+
+        multipart_upload(&upload, reader, size_hint)
+            .map(|_| ())
+            .map_err(|e| ObjectStorageError::CannotUploadFile {
+                bucket_name: bucket_name.to_string(),
+                object_name: object_key.to_string(),
+                raw_error_message: e.to_string(),
+            })
+    }
+
+    fn delete_object(&self, bucket_name: &str, object_key: &str) -> Result<(), ObjectStorageError> {
+        self.service.delete_blob(bucket_name, object_key, None).map_err(|e| {
+            to_object_storage_error(bucket_name, e, |raw_error_message| ObjectStorageError::CannotDeleteFile {
+                bucket_name: bucket_name.to_string(),
+                object_name: object_key.to_string(),
+                raw_error_message,
+            })
+        })
+    }
+
+    fn endpoint(&self) -> String {
+        // Blob REST API, not S3 compatible
+        self.service.blob_url().as_str().trim_end_matches('/').to_string()
+    }
+
+    fn region_name(&self) -> String {
+        self.region.to_cloud_provider_format().to_string()
+    }
+
+    fn apply_bucket_settings(&self, bucket_name: &str, settings: &BucketSettings) -> Result<(), ObjectStorageError> {
+        let to_error = |raw_error_message| ObjectStorageError::CannotApplyBucketSettings {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message,
+        };
+        if settings.objects_expiration_in_days.is_some() {
+            // lifecycle management policies are set on the whole storage account
+            return Err(to_error(
+                "objects expiration is not supported on Azure Blob Storage containers".to_string(),
+            ));
+        }
+
+        self.service
+            .set_container_public_access(bucket_name, !settings.block_public_access)
+            .map_err(|e| to_object_storage_error(bucket_name, e, to_error))?;
+        if self.service.is_versioning_enabled().ok() != Some(settings.versioning_activated) {
+            self.service
+                .set_versioning(settings.versioning_activated)
+                .map_err(|e| to_object_storage_error(bucket_name, e, to_error))?;
+        }
+
+        Ok(())
+    }
+
+    fn create_bucket_credentials(
+        &self,
+        bucket_name: &str,
+        _credentials_id: &str,
+    ) -> Result<BucketCredentials, ObjectStorageError> {
+        Err(ObjectStorageError::CannotCreateBucketCredentials {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message: "Azure Blob Storage does not provide S3 compatible keys".to_string(),
+        })
+    }
+
+    fn delete_bucket_credentials(&self, _bucket_name: &str, _credentials_id: &str) -> Result<(), ObjectStorageError> {
+        // no credentials can have been created
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::infrastructure::models::object_storage::azure_blob_storage::{
+        to_object_storage_error, validate_container_name,
+    };
+    use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+    use crate::services::azure::blob_storage_service::AzureBlobStorageServiceError;
+
+    #[test]
+    fn validate_container_name_test() {
+        // valid names:
+        for name in ["abc", "qovery-logs-z1234", "123", "a-b-c", &"a".repeat(63)] {
+            assert!(validate_container_name(name).is_ok(), "`{name}` should be valid");
+        }
+
+        // invalid names, some of them being valid S3 bucket names:
+        for name in [
+            "ab",
+            &"a".repeat(64),
+            "Qovery-logs",
+            "qovery.logs",
+            "qovery_logs",
+            "-qovery",
+            "qovery-",
+            "qovery--logs",
+            "",
+        ] {
+            assert!(
+                matches!(
+                    validate_container_name(name),
+                    Err(ObjectStorageError::InvalidBucketName { ref bucket_name, .. }) if bucket_name == name
+                ),
+                "`{name}` should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn to_object_storage_error_test() {
+        let api_error = |status: u16, code: &str| AzureBlobStorageServiceError::Api {
+            status,
+            code: code.to_string(),
+            message: "message".to_string(),
+        };
+        let create_error = |raw_error_message| ObjectStorageError::CannotCreateBucket {
+            bucket_name: "bucket".to_string(),
+            raw_error_message,
+        };
+
+        // client errors
+        for error in [
+            AzureBlobStorageServiceError::CannotInstantiateClient {
+                raw_error_message: "invalid key".to_string(),
+            },
+            AzureBlobStorageServiceError::CannotAuthenticate {
+                raw_error_message: "invalid token".to_string(),
+            },
+        ] {
+            assert!(matches!(
+                to_object_storage_error("bucket", error, create_error),
+                ObjectStorageError::CannotInstantiateClient { .. }
+            ));
+        }
+
+        // errors specific to the bucket
+        assert!(matches!(
+            to_object_storage_error("bucket", api_error(400, "InvalidResourceName"), create_error),
+            ObjectStorageError::InvalidBucketName { ref bucket_name, .. } if bucket_name == "bucket"
+        ));
+        assert!(matches!(
+            to_object_storage_error("bucket", api_error(503, "ServerBusy"), create_error),
+            ObjectStorageError::QuotasExceeded { ref bucket_name, .. } if bucket_name == "bucket"
+        ));
+
+        // other errors are the ones of the failing operation
+        assert_eq!(
+            to_object_storage_error("bucket", api_error(409, "ContainerBeingDeleted"), create_error),
+            create_error(api_error(409, "ContainerBeingDeleted").to_string())
+        );
+        assert!(matches!(
+            to_object_storage_error(
+                "bucket",
+                api_error(404, "BlobNotFound"),
+                |raw_error_message| ObjectStorageError::CannotGetObjectFile {
+                    bucket_name: "bucket".to_string(),
+                    object_name: "kubeconfig".to_string(),
+                    raw_error_message,
+                }
+            ),
+            ObjectStorageError::CannotGetObjectFile { ref object_name, .. } if object_name == "kubeconfig"
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
 use thiserror::Error;
 
 #[derive(Clone, Error, Debug, PartialEq, Eq)]
@@ -49,6 +51,27 @@ pub enum ObjectStorageError {
         bucket_name: String,
         raw_error_message: String,
     },
+    #[error("Cannot apply settings of bucket `{bucket_name:?}`: {raw_error_message:?}.")]
+    CannotApplyBucketSettings {
+        bucket_name: String,
+        raw_error_message: String,
+    },
+    #[error("Cannot create credentials of bucket `{bucket_name:?}`: {raw_error_message:?}.")]
+    CannotCreateBucketCredentials {
+        bucket_name: String,
+        raw_error_message: String,
+    },
+    #[error("Cannot delete credentials of bucket `{bucket_name:?}`: {raw_error_message:?}.")]
+    CannotDeleteBucketCredentials {
+        bucket_name: String,
+        raw_error_message: String,
+    },
+    #[error("Cannot use encryption key `{kms_key_arn:?}` for bucket `{bucket_name:?}`: {raw_error_message:?}.")]
+    CannotUseEncryptionKey {
+        bucket_name: String,
+        kms_key_arn: String,
+        raw_error_message: String,
+    },
     #[error("Cannot get object object `{object_name:?}` error in `{bucket_name:?}`: {raw_error_message:?}.")]
     CannotGetObjectFile {
         bucket_name: String,
@@ -68,3 +91,120 @@ pub enum ObjectStorageError {
         raw_error_message: String,
     },
 }
+
+impl ObjectStorageError {
+    pub fn raw_error_message(&self) -> &str {
+        match self {
+            ObjectStorageError::CannotInstantiateClient { raw_error_message }
+            | ObjectStorageError::QuotasExceeded { raw_error_message, .. }
+            | ObjectStorageError::InvalidBucketName { raw_error_message, .. }
+            | ObjectStorageError::CannotCreateBucket { raw_error_message, .. }
+            | ObjectStorageError::CannotUpdateBucket { raw_error_message, .. }
+            | ObjectStorageError::CannotGetBucket { raw_error_message, .. }
+            | ObjectStorageError::CannotDeleteBucket { raw_error_message, .. }
+            | ObjectStorageError::CannotEmptyBucket { raw_error_message, .. }
+            | ObjectStorageError::CannotTagBucket { raw_error_message, .. }
+            | ObjectStorageError::CannotActivateBucketVersioning { raw_error_message, .. }
+            | ObjectStorageError::CannotApplyBucketSettings { raw_error_message, .. }
+            | ObjectStorageError::CannotCreateBucketCredentials { raw_error_message, .. }
+            | ObjectStorageError::CannotDeleteBucketCredentials { raw_error_message, .. }
+            | ObjectStorageError::CannotUseEncryptionKey { raw_error_message, .. }
+            | ObjectStorageError::CannotGetObjectFile { raw_error_message, .. }
+            | ObjectStorageError::CannotUploadFile { raw_error_message, .. }
+            | ObjectStorageError::CannotDeleteFile { raw_error_message, .. } => raw_error_message,
+        }
+    }
+
+    /// Whether the operation can succeed when retried: throttling, 5xx, timeouts and connection errors.
+    /// Providers errors only come as raw messages, their HTTP status or known transient errors are looked for.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ObjectStorageError::CannotInstantiateClient { .. }
+            | ObjectStorageError::InvalidBucketName { .. }
+            | ObjectStorageError::CannotUseEncryptionKey { .. } => false,
+            ObjectStorageError::QuotasExceeded { .. } => true,
+            _ => is_transient_error_message(self.raw_error_message()),
+        }
+    }
+}
+
+static HTTP_ERROR_STATUS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b([45]\d{2})\b").expect("invalid HTTP status regex"));
+
+const TRANSIENT_ERROR_PATTERNS: &[&str] = &[
+    "timed out",
+    "timeout",
+    "connection reset",
+    "connection refused",
+    "connection closed",
+    "broken pipe",
+    "internalerror",
+    "serviceunavailable",
+    "service unavailable",
+    "slowdown",
+    "serverbusy",
+    "operationtimedout",
+];
+
+fn is_transient_error_message(raw_error_message: &str) -> bool {
+    let http_status = HTTP_ERROR_STATUS
+        .captures(raw_error_message)
+        .and_then(|captures| captures[1].parse::<u16>().ok());
+
+    match http_status {
+        // request timeout and too many requests are the only 4xx worth retrying
+        Some(status) if (400..500).contains(&status) => status == 408 || status == 429,
+        Some(_) => true,
+        None => {
+            let raw_error_message = raw_error_message.to_lowercase();
+            TRANSIENT_ERROR_PATTERNS
+                .iter()
+                .any(|pattern| raw_error_message.contains(pattern))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+
+    #[test]
+    fn test_is_transient() {
+        let create_error = |raw_error_message: &str| ObjectStorageError::CannotCreateBucket {
+            bucket_name: "qovery-logs-z1234".to_string(),
+            raw_error_message: raw_error_message.to_string(),
+        };
+
+        for raw_error_message in [
+            "Unknown(BufferedHttpResponse {status: 503, body: \"<Code>SlowDown</Code>\", headers: {}})",
+            "Azure Blob Storage API error 500 `InternalError`: Server encountered an internal error",
+            "http error status: 502 Bad Gateway",
+            "HttpDispatch(HttpDispatchError { message: \"Error during dispatch: connection reset by peer\" })",
+            "error sending request: operation timed out",
+            "Unknown(BufferedHttpResponse {status: 429, body: \"\", headers: {}})",
+        ] {
+            assert!(create_error(raw_error_message).is_transient(), "{raw_error_message}");
+        }
+
+        for raw_error_message in [
+            "Unknown(BufferedHttpResponse {status: 403, body: \"<Code>AccessDenied</Code>\", headers: {}})",
+            "Azure Blob Storage API error 409 `ContainerBeingDeleted`: the container is being deleted",
+            "BucketAlreadyOwnedByYou",
+            "",
+        ] {
+            assert!(!create_error(raw_error_message).is_transient(), "{raw_error_message}");
+        }
+
+        // permanent whatever the message
+        assert!(!ObjectStorageError::InvalidBucketName {
+            bucket_name: "qovery_logs".to_string(),
+            raw_error_message: "503".to_string(),
+        }
+        .is_transient());
+        assert!(ObjectStorageError::QuotasExceeded {
+            bucket_name: "qovery-logs-z1234".to_string(),
+            raw_error_message: "ServerBusy".to_string(),
+        }
+        .is_transient());
+    }
+}
@@ -1,10 +1,14 @@
+use crate::environment::models::ToCloudProviderFormat;
 use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
-use crate::infrastructure::models::object_storage::{Bucket, BucketDeleteStrategy, BucketObject};
+use crate::infrastructure::models::object_storage::{
+    Bucket, BucketCredentials, BucketDeleteStrategy, BucketObject, BucketSettings,
+};
 use crate::infrastructure::models::object_storage::{Kind, ObjectStorage};
 use crate::services::gcp::object_storage_regions::GcpStorageRegion;
 use crate::services::gcp::object_storage_service::ObjectStorageService;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
+use std::io::Read;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
@@ -197,6 +201,23 @@ impl ObjectStorage for GoogleOS {
         }
     }
 
+    fn put_object_streaming(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        reader: &mut dyn Read,
+        size_hint: Option<u64>,
+        _tags: Option<Vec<String>>,
+    ) -> Result<(), ObjectStorageError> {
+        self.service
+            .put_object_streaming(bucket_name, object_key, reader, size_hint)
+            .map_err(|e| ObjectStorageError::CannotUploadFile {
+                bucket_name: bucket_name.to_string(),
+                object_name: object_key.to_string(),
+                raw_error_message: e.to_string(),
+            })
+    }
+
     fn delete_object(&self, bucket_name: &str, object_key: &str) -> Result<(), ObjectStorageError> {
         // TODO(benjaminch): not optimal since fine grained statuses are not returned, should know if get is error because file doesn't exist or if anything else
         if self.get_object(bucket_name, object_key).is_err() {
@@ -211,6 +232,55 @@ impl ObjectStorage for GoogleOS {
                 raw_error_message: e.to_string(),
             })
     }
+
+    fn endpoint(&self) -> String {
+        // S3 compatible XML API, authenticated with HMAC keys
+        "https://storage.googleapis.com".to_string()
+    }
+
+    fn region_name(&self) -> String {
+        self.region.to_cloud_provider_format().to_string()
+    }
+
+    fn apply_bucket_settings(&self, bucket_name: &str, settings: &BucketSettings) -> Result<(), ObjectStorageError> {
+        self.service
+            .apply_bucket_settings(
+                bucket_name,
+                settings.versioning_activated,
+                settings.block_public_access,
+                settings.objects_expiration_in_days,
+            )
+            .map_err(|e| ObjectStorageError::CannotApplyBucketSettings {
+                bucket_name: bucket_name.to_string(),
+                raw_error_message: e.get_raw_error_message(),
+            })
+    }
+
+    fn create_bucket_credentials(
+        &self,
+        bucket_name: &str,
+        credentials_id: &str,
+    ) -> Result<BucketCredentials, ObjectStorageError> {
+        match self.service.create_bucket_hmac_key(bucket_name, credentials_id) {
+            Ok((access_key_id, secret_access_key)) => Ok(BucketCredentials {
+                access_key_id,
+                secret_access_key,
+            }),
+            Err(e) => Err(ObjectStorageError::CannotCreateBucketCredentials {
+                bucket_name: bucket_name.to_string(),
+                raw_error_message: e.get_raw_error_message(),
+            }),
+        }
+    }
+
+    fn delete_bucket_credentials(&self, bucket_name: &str, credentials_id: &str) -> Result<(), ObjectStorageError> {
+        self.service
+            .delete_bucket_hmac_key(bucket_name, credentials_id)
+            .map_err(|e| ObjectStorageError::CannotDeleteBucketCredentials {
+                bucket_name: bucket_name.to_string(),
+                raw_error_message: e.get_raw_error_message(),
+            })
+    }
 }
 
 #[cfg(test)]
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 use std::time::Duration;
 
@@ -7,13 +9,20 @@ use crate::environment::models::scaleway::ScwZone;
 use crate::environment::models::ToCloudProviderFormat;
 use crate::infrastructure::models::cloud_provider::aws::regions::AwsRegion;
 use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+use crate::infrastructure::models::object_storage::multipart_upload::MULTIPART_UPLOAD_THRESHOLD;
+use crate::services::azure::blob_storage_service::AzureStorageRegion;
 use crate::services::gcp::object_storage_regions::GcpStorageRegion;
 use enum_dispatch::enum_dispatch;
 
+pub mod azure_blob_storage;
 pub mod errors;
 pub mod google_object_storage;
+pub mod multipart_upload;
+pub mod retry_policy;
 pub mod s3;
 pub mod scaleway_object_storage;
+#[cfg(test)]
+pub mod test_utils;
 
 #[derive(Clone)]
 pub enum BucketDeleteStrategy {
@@ -27,6 +36,7 @@ pub enum BucketRegion {
     AwsRegion(AwsRegion),
     ScwRegion(ScwZone),
     GcpRegion(GcpStorageRegion),
+    AzureRegion(AzureStorageRegion),
 }
 
 #[enum_dispatch(StorageRegion)]
@@ -67,7 +77,51 @@ pub trait ObjectStorage {
         file_path: &Path,
         tags: Option<Vec<String>>,
     ) -> Result<BucketObject, ObjectStorageError>;
+    /// Uploads the content of the reader by parts, without loading it in memory. `size_hint` sizes the parts.
+    fn put_object_streaming(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        reader: &mut dyn Read,
+        size_hint: Option<u64>,
+        tags: Option<Vec<String>>,
+    ) -> Result<(), ObjectStorageError>;
+    /// Uploads the file, streamed by parts when it is bigger than MULTIPART_UPLOAD_THRESHOLD
+    fn upload_file(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        file_path: &Path,
+        tags: Option<Vec<String>>,
+    ) -> Result<(), ObjectStorageError> {
+        let to_error = |e: std::io::Error| ObjectStorageError::CannotUploadFile {
+            bucket_name: bucket_name.to_string(),
+            object_name: object_key.to_string(),
+            raw_error_message: e.to_string(),
+        };
+        let file_size = std::fs::metadata(file_path).map_err(to_error)?.len();
+        if file_size <= MULTIPART_UPLOAD_THRESHOLD {
+            return self.put_object(bucket_name, object_key, file_path, tags).map(|_| ());
+        }
+
+        let mut file = File::open(file_path).map_err(to_error)?;
+        self.put_object_streaming(bucket_name, object_key, &mut file, Some(file_size), tags)
+    }
     fn delete_object(&self, bucket_name: &str, object_key: &str) -> Result<(), ObjectStorageError>;
+    /// S3 compatible endpoint of the buckets, for the services accessing them
+    fn endpoint(&self) -> String;
+    fn region_name(&self) -> String;
+    /// Applies versioning, public access and objects expiration of a bucket, replacing the previous ones
+    fn apply_bucket_settings(&self, bucket_name: &str, settings: &BucketSettings) -> Result<(), ObjectStorageError>;
+    /// Creates an identity only allowed to read and write the objects of the bucket, and returns its S3 keys.
+    /// Keys previously created for the same `credentials_id` are revoked.
+    fn create_bucket_credentials(
+        &self,
+        bucket_name: &str,
+        credentials_id: &str,
+    ) -> Result<BucketCredentials, ObjectStorageError>;
+    /// Deletes the identity and its keys, succeeds when they do not exist anymore
+    fn delete_bucket_credentials(&self, bucket_name: &str, credentials_id: &str) -> Result<(), ObjectStorageError>;
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -77,6 +131,7 @@ pub enum Kind {
     Spaces,
     ScalewayOs,
     GcpOs,
+    AzureBlob,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -106,6 +161,40 @@ impl Bucket {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BucketSettings {
+    pub versioning_activated: bool,
+    pub block_public_access: bool,
+    pub objects_expiration_in_days: Option<u32>,
+}
+
+/// S3 keys of an identity scoped to a single bucket
+#[derive(Clone, PartialEq, Eq)]
+pub struct BucketCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+impl std::fmt::Debug for BucketCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BucketCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"***")
+            .finish()
+    }
+}
+
+/// S3 actions granted to the credentials of a bucket: objects only, the bucket itself is managed by Qovery
+pub const BUCKET_CREDENTIALS_ACTIONS: &[&str] = &[
+    "s3:ListBucket",
+    "s3:GetBucketLocation",
+    "s3:GetObject",
+    "s3:PutObject",
+    "s3:DeleteObject",
+    "s3:ListMultipartUploadParts",
+    "s3:AbortMultipartUpload",
+];
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct BucketObject {
     pub bucket_name: String,
@@ -0,0 +1,427 @@
+use crate::infrastructure::models::object_storage::s3::ObjectEncryption;
+use crate::runtime::block_on;
+use retry::delay::Fixed;
+use rusoto_s3::{
+    AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload, CompletedPart,
+    CreateMultipartUploadRequest, S3Client, StreamingBody, UploadPartRequest, S3,
+};
+use std::io::Read;
+use std::time::Duration;
+use thiserror::Error;
+
+const MIB: u64 = 1024 * 1024;
+/// Files bigger than this are streamed by parts instead of being loaded in memory
+pub const MULTIPART_UPLOAD_THRESHOLD: u64 = 64 * MIB;
+pub const MIN_PART_SIZE: u64 = 8 * MIB;
+pub const MAX_PART_SIZE: u64 = 16 * MIB;
+/// Maximum number of parts of an S3 multipart upload
+pub const MAX_PARTS: u64 = 10_000;
+const PART_UPLOAD_ATTEMPTS: usize = 3;
+const PART_UPLOAD_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Error, Debug, PartialEq, Eq)]
+pub enum MultipartUploadError {
+    #[error("Cannot start multipart upload: {raw_error_message}")]
+    CannotCreate { raw_error_message: String },
+    #[error("Cannot read part {part_number}: {raw_error_message}")]
+    CannotReadPart {
+        part_number: u64,
+        raw_error_message: String,
+    },
+    #[error("Cannot upload part {part_number}: {raw_error_message}")]
+    CannotUploadPart {
+        part_number: u64,
+        raw_error_message: String,
+    },
+    #[error("Cannot upload more than {MAX_PARTS} parts")]
+    TooManyParts,
+    #[error("Cannot complete multipart upload: {raw_error_message}")]
+    CannotComplete { raw_error_message: String },
+}
+
+pub struct Part {
+    /// Starts at 1
+    pub number: u64,
+    /// Position of the first byte of the part in the object
+    pub offset: u64,
+    pub data: Vec<u8>,
+    pub is_last: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UploadedPart {
+    pub number: u64,
+    pub e_tag: String,
+}
+
+/// Steps of a multipart upload, implemented for each object storage API
+pub trait MultipartUploadClient {
+    /// Starts the upload and returns its id
+    fn create(&self) -> Result<String, String>;
+    /// Uploads a part and returns its etag, it can be called again for the same part when it fails
+    fn upload_part(&self, upload_id: &str, part: &Part) -> Result<String, String>;
+    fn complete(&self, upload_id: &str, parts: &[UploadedPart]) -> Result<(), String>;
+    /// Drops the parts already uploaded
+    fn abort(&self, upload_id: &str) -> Result<(), String>;
+}
+
+/// Smallest part size, rounded to the MiB, allowing to upload `size_hint` bytes within MAX_PARTS parts.
+/// Without hint, the biggest parts are used to allow the biggest objects.
+pub fn part_size(size_hint: Option<u64>) -> u64 {
+    match size_hint {
+        Some(size) => size
+            .div_ceil(MAX_PARTS)
+            .next_multiple_of(MIB)
+            .clamp(MIN_PART_SIZE, MAX_PART_SIZE),
+        None => MAX_PART_SIZE,
+    }
+}
+
+/// Uploads the content of the reader part by part, at most two parts being in memory.
+/// Each part is retried, and the upload is aborted when it cannot be completed.
+/// Returns the size of the uploaded object.
+pub fn multipart_upload(
+    client: &dyn MultipartUploadClient,
+    reader: &mut dyn Read,
+    size_hint: Option<u64>,
+) -> Result<u64, MultipartUploadError> {
+    multipart_upload_with_retry_delay(client, reader, size_hint, PART_UPLOAD_RETRY_DELAY)
+}
+
+fn multipart_upload_with_retry_delay(
+    client: &dyn MultipartUploadClient,
+    reader: &mut dyn Read,
+    size_hint: Option<u64>,
+    retry_delay: Duration,
+) -> Result<u64, MultipartUploadError> {
+    let upload_id = client
+        .create()
+        .map_err(|raw_error_message| MultipartUploadError::CannotCreate { raw_error_message })?;
+
+    match upload_parts(client, &upload_id, reader, part_size(size_hint), retry_delay) {
+        Ok(size) => Ok(size),
+        Err(err) => {
+            if let Err(abort_err) = client.abort(&upload_id) {
+                warn!("Cannot abort multipart upload `{}`: {}", upload_id, abort_err);
+            }
+            Err(err)
+        }
+    }
+}
+
+fn upload_parts(
+    client: &dyn MultipartUploadClient,
+    upload_id: &str,
+    reader: &mut dyn Read,
+    part_size: u64,
+    retry_delay: Duration,
+) -> Result<u64, MultipartUploadError> {
+    let mut uploaded_parts: Vec<UploadedPart> = vec![];
+    let mut offset = 0;
+    let mut data = read_part(reader, part_size, 1)?;
+    loop {
+        let number = uploaded_parts.len() as u64 + 1;
+        if number > MAX_PARTS {
+            return Err(MultipartUploadError::TooManyParts);
+        }
+
+        // the next part is read ahead to know if the current one is the last
+        let next_data = match data.len() as u64 == part_size {
+            true => read_part(reader, part_size, number + 1)?,
+            false => vec![],
+        };
+        let part = Part {
+            number,
+            offset,
+            data,
+            is_last: next_data.is_empty(),
+        };
+
+        let e_tag = retry::retry(Fixed::from(retry_delay).take(PART_UPLOAD_ATTEMPTS - 1), || {
+            client.upload_part(upload_id, &part)
+        })
+        .map_err(|e| MultipartUploadError::CannotUploadPart {
+            part_number: number,
+            raw_error_message: e.error,
+        })?;
+        offset += part.data.len() as u64;
+        uploaded_parts.push(UploadedPart { number, e_tag });
+
+        if part.is_last {
+            break;
+        }
+        data = next_data;
+    }
+
+    client
+        .complete(upload_id, &uploaded_parts)
+        .map_err(|raw_error_message| MultipartUploadError::CannotComplete { raw_error_message })?;
+
+    Ok(offset)
+}
+
+fn read_part(reader: &mut dyn Read, part_size: u64, part_number: u64) -> Result<Vec<u8>, MultipartUploadError> {
+    let mut data = Vec::with_capacity(part_size as usize);
+    reader
+        .take(part_size)
+        .read_to_end(&mut data)
+        .map_err(|e| MultipartUploadError::CannotReadPart {
+            part_number,
+            raw_error_message: e.to_string(),
+        })?;
+
+    Ok(data)
+}
+
+/// S3 multipart upload, for AWS and S3 compatible object storages
+pub struct S3MultipartUpload<'a> {
+    pub client: &'a S3Client,
+    pub bucket_name: &'a str,
+    pub object_key: &'a str,
+    /// `key=value` tags joined by `&`
+    pub tagging: Option<String>,
+    pub encryption: ObjectEncryption,
+}
+
+impl MultipartUploadClient for S3MultipartUpload<'_> {
+    fn create(&self) -> Result<String, String> {
+        let output = block_on(self.client.create_multipart_upload(CreateMultipartUploadRequest {
+            bucket: self.bucket_name.to_string(),
+            key: self.object_key.to_string(),
+            tagging: self.tagging.clone(),
+            server_side_encryption: self.encryption.server_side_encryption.clone(),
+            ssekms_key_id: self.encryption.ssekms_key_id.clone(),
+            ..Default::default()
+        }))
+        .map_err(|e| e.to_string())?;
+
+        output.upload_id.ok_or_else(|| "no upload id returned".to_string())
+    }
+
+    fn upload_part(&self, upload_id: &str, part: &Part) -> Result<String, String> {
+        let output = block_on(self.client.upload_part(UploadPartRequest {
+            bucket: self.bucket_name.to_string(),
+            key: self.object_key.to_string(),
+            upload_id: upload_id.to_string(),
+            part_number: part.number as i64,
+            content_length: Some(part.data.len() as i64),
+            body: Some(StreamingBody::from(part.data.clone())),
+            ..Default::default()
+        }))
+        .map_err(|e| e.to_string())?;
+
+        Ok(output.e_tag.unwrap_or_default())
+    }
+
+    fn complete(&self, upload_id: &str, parts: &[UploadedPart]) -> Result<(), String> {
+        block_on(
+            self.client.complete_multipart_upload(CompleteMultipartUploadRequest {
+                bucket: self.bucket_name.to_string(),
+                key: self.object_key.to_string(),
+                upload_id: upload_id.to_string(),
+                multipart_upload: Some(CompletedMultipartUpload {
+                    parts: Some(
+                        parts
+                            .iter()
+                            .map(|part| CompletedPart {
+                                e_tag: Some(part.e_tag.to_string()),
+                                part_number: Some(part.number as i64),
+                            })
+                            .collect(),
+                    ),
+                }),
+                ..Default::default()
+            }),
+        )
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    }
+
+    fn abort(&self, upload_id: &str) -> Result<(), String> {
+        block_on(self.client.abort_multipart_upload(AbortMultipartUploadRequest {
+            bucket: self.bucket_name.to_string(),
+            key: self.object_key.to_string(),
+            upload_id: upload_id.to_string(),
+            ..Default::default()
+        }))
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::Mutex;
+
+    /// Records the calls, uploads of part `failing_part` fail `failures` times
+    #[derive(Default)]
+    struct MockMultipartUploadClient {
+        failing_part: Option<u64>,
+        failures: Mutex<usize>,
+        failing_completion: bool,
+        uploaded_parts: Mutex<Vec<(u64, u64, usize, bool)>>,
+        upload_attempts: Mutex<usize>,
+        completed_parts: Mutex<Option<Vec<UploadedPart>>>,
+        aborted_upload_id: Mutex<Option<String>>,
+    }
+
+    impl MultipartUploadClient for MockMultipartUploadClient {
+        fn create(&self) -> Result<String, String> {
+            Ok("upload-1".to_string())
+        }
+
+        fn upload_part(&self, upload_id: &str, part: &Part) -> Result<String, String> {
+            assert_eq!(upload_id, "upload-1");
+            *self.upload_attempts.lock().unwrap() += 1;
+            if self.failing_part == Some(part.number) {
+                let mut failures = self.failures.lock().unwrap();
+                if *failures > 0 {
+                    *failures -= 1;
+                    return Err("connection reset".to_string());
+                }
+            }
+
+            self.uploaded_parts
+                .lock()
+                .unwrap()
+                .push((part.number, part.offset, part.data.len(), part.is_last));
+            Ok(format!("etag-{}", part.number))
+        }
+
+        fn complete(&self, _upload_id: &str, parts: &[UploadedPart]) -> Result<(), String> {
+            if self.failing_completion {
+                return Err("invalid part order".to_string());
+            }
+            *self.completed_parts.lock().unwrap() = Some(parts.to_vec());
+            Ok(())
+        }
+
+        fn abort(&self, upload_id: &str) -> Result<(), String> {
+            *self.aborted_upload_id.lock().unwrap() = Some(upload_id.to_string());
+            Ok(())
+        }
+    }
+
+    fn upload(
+        client: &MockMultipartUploadClient,
+        size: usize,
+        size_hint: Option<u64>,
+    ) -> Result<u64, MultipartUploadError> {
+        let mut reader = Cursor::new(vec![1u8; size]);
+        multipart_upload_with_retry_delay(client, &mut reader, size_hint, Duration::ZERO)
+    }
+
+    #[test]
+    fn test_part_size() {
+        assert_eq!(part_size(None), MAX_PART_SIZE);
+        assert_eq!(part_size(Some(0)), MIN_PART_SIZE);
+        assert_eq!(part_size(Some(100 * MIB)), MIN_PART_SIZE);
+        // 100 GiB need parts of 10.24 MiB
+        assert_eq!(part_size(Some(100 * 1024 * MIB)), 11 * MIB);
+        assert_eq!(part_size(Some(1024 * 1024 * MIB)), MAX_PART_SIZE);
+        for size in [MIB, 80 * 1024 * MIB, 150 * 1024 * MIB] {
+            let part_size = part_size(Some(size));
+            assert!((MIN_PART_SIZE..=MAX_PART_SIZE).contains(&part_size));
+            assert_eq!(part_size % MIB, 0);
+        }
+    }
+
+    #[test]
+    fn test_multipart_upload() {
+        let client = MockMultipartUploadClient::default();
+        let size = 2 * MIN_PART_SIZE as usize + 3;
+
+        assert_eq!(upload(&client, size, Some(size as u64)), Ok(size as u64));
+        let part = MIN_PART_SIZE as usize;
+        assert_eq!(
+            *client.uploaded_parts.lock().unwrap(),
+            vec![
+                (1, 0, part, false),
+                (2, part as u64, part, false),
+                (3, 2 * part as u64, 3, true)
+            ]
+        );
+        assert_eq!(
+            client.completed_parts.lock().unwrap().clone().unwrap(),
+            (1..=3)
+                .map(|number| UploadedPart {
+                    number,
+                    e_tag: format!("etag-{number}"),
+                })
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(*client.aborted_upload_id.lock().unwrap(), None);
+    }
+
+    #[test]
+    fn test_multipart_upload_exact_parts_and_empty_content() {
+        // the last part is known to be the last one without sending an empty part
+        let client = MockMultipartUploadClient::default();
+        assert_eq!(upload(&client, 2 * MAX_PART_SIZE as usize, None), Ok(2 * MAX_PART_SIZE));
+        assert_eq!(
+            *client.uploaded_parts.lock().unwrap(),
+            vec![
+                (1, 0, MAX_PART_SIZE as usize, false),
+                (2, MAX_PART_SIZE, MAX_PART_SIZE as usize, true)
+            ]
+        );
+
+        // an object needs at least one part
+        let client = MockMultipartUploadClient::default();
+        assert_eq!(upload(&client, 0, Some(0)), Ok(0));
+        assert_eq!(*client.uploaded_parts.lock().unwrap(), vec![(1, 0, 0, true)]);
+        assert_eq!(
+            client.completed_parts.lock().unwrap().as_ref().map(|parts| parts.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_multipart_upload_retries_parts() {
+        let client = MockMultipartUploadClient {
+            failing_part: Some(2),
+            failures: Mutex::new(PART_UPLOAD_ATTEMPTS - 1),
+            ..Default::default()
+        };
+
+        assert!(upload(&client, 2 * MIN_PART_SIZE as usize, Some(2 * MIN_PART_SIZE)).is_ok());
+        assert_eq!(*client.upload_attempts.lock().unwrap(), 2 + PART_UPLOAD_ATTEMPTS - 1);
+        assert_eq!(client.uploaded_parts.lock().unwrap().len(), 2);
+        assert_eq!(*client.aborted_upload_id.lock().unwrap(), None);
+    }
+
+    #[test]
+    fn test_multipart_upload_is_aborted_on_failure() {
+        // a part keeps failing
+        let client = MockMultipartUploadClient {
+            failing_part: Some(2),
+            failures: Mutex::new(PART_UPLOAD_ATTEMPTS),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            upload(&client, 3 * MIN_PART_SIZE as usize, Some(3 * MIN_PART_SIZE)),
+            Err(MultipartUploadError::CannotUploadPart {
+                part_number: 2,
+                raw_error_message: "connection reset".to_string(),
+            })
+        );
+        assert_eq!(*client.upload_attempts.lock().unwrap(), 1 + PART_UPLOAD_ATTEMPTS);
+        assert_eq!(client.aborted_upload_id.lock().unwrap().as_deref(), Some("upload-1"));
+        assert!(client.completed_parts.lock().unwrap().is_none());
+
+        // the completion fails
+        let client = MockMultipartUploadClient {
+            failing_completion: true,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            upload(&client, MIB as usize, Some(MIB)),
+            Err(MultipartUploadError::CannotComplete { .. })
+        ));
+        assert_eq!(client.aborted_upload_id.lock().unwrap().as_deref(), Some("upload-1"));
+    }
+}
@@ -0,0 +1,313 @@
+use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+use crate::infrastructure::models::object_storage::{
+    Bucket, BucketCredentials, BucketDeleteStrategy, BucketObject, BucketSettings, Kind, ObjectStorage,
+};
+use rand::Rng;
+use std::fmt::Display;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+/// Exponential backoff with full jitter: the n-th retry waits a random delay up to `initial_delay * 2^(n-1)`,
+/// capped to `max_delay`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts including the first one
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Upper bound of the delay after the failed attempt `attempt`, starting at 1
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+
+    /// Runs the operation until it succeeds, fails with a permanent error or runs out of attempts
+    pub fn run<T, E: Display>(
+        &self,
+        operation_name: &str,
+        is_transient: impl Fn(&E) -> bool,
+        mut operation: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut attempt = 1;
+        loop {
+            match operation() {
+                Err(err) if attempt < self.max_attempts && is_transient(&err) => {
+                    let delay = rand::thread_rng().gen_range(Duration::ZERO..=self.backoff(attempt));
+                    debug!(
+                        "`{}` failed on attempt {}/{}, retrying in {:?}: {}",
+                        operation_name, attempt, self.max_attempts, delay, err
+                    );
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                ret => return ret,
+            }
+        }
+    }
+}
+
+/// Retries the transient errors of the object storage calls
+pub struct RetryingObjectStorage<T: ObjectStorage> {
+    inner: T,
+    retry_policy: RetryPolicy,
+}
+
+impl<T: ObjectStorage> RetryingObjectStorage<T> {
+    pub fn new(inner: T, retry_policy: RetryPolicy) -> Self {
+        RetryingObjectStorage { inner, retry_policy }
+    }
+
+    fn run<R>(
+        &self,
+        operation_name: &str,
+        mut operation: impl FnMut() -> Result<R, ObjectStorageError>,
+    ) -> Result<R, ObjectStorageError> {
+        self.retry_policy.run(
+            &format!("{} {}", self.inner.name_with_id(), operation_name),
+            ObjectStorageError::is_transient,
+            || {
+                // every attempt consults the scripted errors, so injected transient errors are retried as well
+                #[cfg(feature = "failure-injection")]
+                if let Some(err) = crate::cmd::failure_injection::intercept_call(&["object_storage", operation_name]) {
+                    return Err(err);
+                }
+                operation()
+            },
+        )
+    }
+}
+
+impl<T: ObjectStorage> ObjectStorage for RetryingObjectStorage<T> {
+    fn kind(&self) -> Kind {
+        self.inner.kind()
+    }
+
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn is_valid(&self) -> Result<(), ObjectStorageError> {
+        self.run("is_valid", || self.inner.is_valid())
+    }
+
+    fn workspace_dir_relative_path(&self) -> String {
+        self.inner.workspace_dir_relative_path()
+    }
+
+    fn bucket_exists(&self, bucket_name: &str) -> bool {
+        self.inner.bucket_exists(bucket_name)
+    }
+
+    fn create_bucket(
+        &self,
+        bucket_name: &str,
+        bucket_ttl: Option<Duration>,
+        bucket_versioning_activated: bool,
+    ) -> Result<Bucket, ObjectStorageError> {
+        self.run("create_bucket", || {
+            self.inner
+                .create_bucket(bucket_name, bucket_ttl, bucket_versioning_activated)
+        })
+    }
+
+    fn update_bucket(
+        &self,
+        bucket_name: &str,
+        bucket_versioning_activated: bool,
+    ) -> Result<Bucket, ObjectStorageError> {
+        self.run("update_bucket", || {
+            self.inner.update_bucket(bucket_name, bucket_versioning_activated)
+        })
+    }
+
+    fn get_bucket(&self, bucket_name: &str) -> Result<Bucket, ObjectStorageError> {
+        self.run("get_bucket", || self.inner.get_bucket(bucket_name))
+    }
+
+    fn delete_bucket(
+        &self,
+        bucket_name: &str,
+        bucket_delete_strategy: BucketDeleteStrategy,
+    ) -> Result<(), ObjectStorageError> {
+        self.run("delete_bucket", || {
+            self.inner.delete_bucket(bucket_name, bucket_delete_strategy.clone())
+        })
+    }
+
+    fn delete_bucket_non_blocking(&self, bucket_name: &str) -> Result<(), ObjectStorageError> {
+        self.run("delete_bucket_non_blocking", || {
+            self.inner.delete_bucket_non_blocking(bucket_name)
+        })
+    }
+
+    fn get_object(&self, bucket_name: &str, object_key: &str) -> Result<BucketObject, ObjectStorageError> {
+        self.run("get_object", || self.inner.get_object(bucket_name, object_key))
+    }
+
+    fn put_object(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        file_path: &Path,
+        tags: Option<Vec<String>>,
+    ) -> Result<BucketObject, ObjectStorageError> {
+        self.run("put_object", || {
+            self.inner.put_object(bucket_name, object_key, file_path, tags.clone())
+        })
+    }
+
+    fn put_object_streaming(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        reader: &mut dyn Read,
+        size_hint: Option<u64>,
+        tags: Option<Vec<String>>,
+    ) -> Result<(), ObjectStorageError> {
+        #[cfg(feature = "failure-injection")]
+        if let Some(err) = crate::cmd::failure_injection::intercept_call(&["object_storage", "put_object_streaming"]) {
+            return Err(err);
+        }
+
+        // the reader cannot be read again, parts are already retried one by one
+        self.inner
+            .put_object_streaming(bucket_name, object_key, reader, size_hint, tags)
+    }
+
+    fn delete_object(&self, bucket_name: &str, object_key: &str) -> Result<(), ObjectStorageError> {
+        self.run("delete_object", || self.inner.delete_object(bucket_name, object_key))
+    }
+
+    fn endpoint(&self) -> String {
+        self.inner.endpoint()
+    }
+
+    fn region_name(&self) -> String {
+        self.inner.region_name()
+    }
+
+    fn apply_bucket_settings(&self, bucket_name: &str, settings: &BucketSettings) -> Result<(), ObjectStorageError> {
+        self.run("apply_bucket_settings", || {
+            self.inner.apply_bucket_settings(bucket_name, settings)
+        })
+    }
+
+    fn create_bucket_credentials(
+        &self,
+        bucket_name: &str,
+        credentials_id: &str,
+    ) -> Result<BucketCredentials, ObjectStorageError> {
+        self.run("create_bucket_credentials", || {
+            self.inner.create_bucket_credentials(bucket_name, credentials_id)
+        })
+    }
+
+    fn delete_bucket_credentials(&self, bucket_name: &str, credentials_id: &str) -> Result<(), ObjectStorageError> {
+        self.run("delete_bucket_credentials", || {
+            self.inner.delete_bucket_credentials(bucket_name, credentials_id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::models::object_storage::test_utils::InMemoryObjectStorage;
+
+    fn retrying(object_storage: InMemoryObjectStorage) -> RetryingObjectStorage<InMemoryObjectStorage> {
+        RetryingObjectStorage::new(
+            object_storage,
+            RetryPolicy {
+                initial_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(2),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn tagging_error(raw_error_message: &str) -> ObjectStorageError {
+        ObjectStorageError::CannotTagBucket {
+            bucket_name: "qovery-logs-z1234".to_string(),
+            raw_error_message: raw_error_message.to_string(),
+        }
+    }
+
+    const SERVICE_UNAVAILABLE: &str = "Unknown(BufferedHttpResponse {status: 503, body: \"\", headers: {}})";
+
+    #[test]
+    fn test_backoff() {
+        let retry_policy = RetryPolicy::default();
+        assert_eq!(retry_policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(retry_policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(retry_policy.backoff(4), Duration::from_secs(4));
+        assert_eq!(retry_policy.backoff(5), Duration::from_secs(8));
+        assert_eq!(retry_policy.backoff(6), Duration::from_secs(10));
+        assert_eq!(retry_policy.backoff(64), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_retries_transient_errors() {
+        // succeeds on the last attempt
+        let object_storage = retrying(InMemoryObjectStorage::default());
+        object_storage
+            .inner
+            .fail_next_calls(4, tagging_error(SERVICE_UNAVAILABLE));
+        let bucket = object_storage.create_bucket("qovery-logs-z1234", None, false);
+        assert_eq!(bucket.map(|bucket| bucket.name).as_deref(), Ok("qovery-logs-z1234"));
+        assert_eq!(object_storage.inner.call_count(), 5);
+
+        // gives up after 5 attempts
+        let object_storage = retrying(InMemoryObjectStorage::default());
+        object_storage
+            .inner
+            .fail_next_calls(5, tagging_error(SERVICE_UNAVAILABLE));
+        assert_eq!(
+            object_storage.create_bucket("qovery-logs-z1234", None, false),
+            Err(tagging_error(SERVICE_UNAVAILABLE))
+        );
+        assert_eq!(object_storage.inner.call_count(), 5);
+    }
+
+    #[test]
+    fn test_does_not_retry_permanent_errors() {
+        let object_storage = retrying(InMemoryObjectStorage::default());
+        object_storage.inner.fail_next_calls(
+            u32::MAX,
+            tagging_error(
+                "Unknown(BufferedHttpResponse {status: 403, body: \"<Code>AccessDenied</Code>\", headers: {}})",
+            ),
+        );
+        assert!(object_storage.create_bucket("qovery-logs-z1234", None, false).is_err());
+        assert_eq!(object_storage.inner.call_count(), 1);
+
+        let object_storage = retrying(InMemoryObjectStorage::default());
+        object_storage.inner.fail_next_calls(
+            u32::MAX,
+            ObjectStorageError::InvalidBucketName {
+                bucket_name: "qovery_logs".to_string(),
+                raw_error_message: "invalid name".to_string(),
+            },
+        );
+        assert!(object_storage.create_bucket("qovery_logs", None, false).is_err());
+        assert_eq!(object_storage.inner.call_count(), 1);
+    }
+}
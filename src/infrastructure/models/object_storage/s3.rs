@@ -1,6 +1,11 @@
+use aws_sdk_ec2::config::{BehaviorVersion, SharedCredentialsProvider};
+use aws_types::region::Region;
+use aws_types::SdkConfig;
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use retry::delay::Fixed;
+use serde_json::json;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::Read;
 use std::path::Path;
@@ -11,26 +16,73 @@ use crate::infrastructure::models::cloud_provider::aws::regions::AwsRegion;
 use rusoto_core::credential::StaticProvider;
 use rusoto_core::{Client, HttpClient, Region as RusotoRegion};
 use rusoto_s3::{
-    CreateBucketConfiguration, CreateBucketRequest, Delete, DeleteBucketRequest, DeleteObjectRequest,
-    DeleteObjectsRequest, GetBucketLifecycleRequest, GetBucketTaggingRequest, GetBucketVersioningRequest,
-    GetObjectRequest, GetObjectTaggingRequest, HeadBucketRequest, ListObjectsRequest, ObjectIdentifier,
-    PutBucketTaggingRequest, PutBucketVersioningRequest, PutObjectRequest, S3Client, StreamingBody, Tag, Tagging,
-    S3 as RusotoS3,
+    BucketLifecycleConfiguration, CreateBucketConfiguration, CreateBucketRequest, Delete, DeleteBucketLifecycleRequest,
+    DeleteBucketRequest, DeleteObjectRequest, DeleteObjectsRequest, DeletePublicAccessBlockRequest,
+    GetBucketLifecycleRequest, GetBucketTaggingRequest, GetBucketVersioningRequest, GetObjectRequest,
+    GetObjectTaggingRequest, HeadBucketRequest, LifecycleExpiration, LifecycleRule, LifecycleRuleFilter,
+    ListObjectsRequest, ObjectIdentifier, PublicAccessBlockConfiguration, PutBucketEncryptionRequest,
+    PutBucketLifecycleConfigurationRequest, PutBucketTaggingRequest, PutBucketVersioningRequest, PutObjectRequest,
+    PutPublicAccessBlockRequest, S3Client, ServerSideEncryptionByDefault, ServerSideEncryptionConfiguration,
+    ServerSideEncryptionRule, StreamingBody, Tag, Tagging, VersioningConfiguration, S3 as RusotoS3,
 };
 
 use crate::environment::models::ToCloudProviderFormat;
 use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+use crate::infrastructure::models::object_storage::multipart_upload::{multipart_upload, S3MultipartUpload};
 use crate::infrastructure::models::object_storage::{
-    Bucket, BucketDeleteStrategy, BucketObject, BucketRegion, Kind, ObjectStorage,
+    Bucket, BucketCredentials, BucketDeleteStrategy, BucketObject, BucketRegion, BucketSettings, Kind, ObjectStorage,
+    BUCKET_CREDENTIALS_ACTIONS,
 };
 use crate::runtime::block_on;
 
+const S3_CREDENTIALS_USER_PATH: &str = "/qovery/buckets/";
+const S3_CREDENTIALS_POLICY_NAME: &str = "qovery-bucket-access";
+const SSE_KMS_ALGORITHM: &str = "aws:kms";
+
+/// Server side encryption parameters of the uploaded objects, SSE-KMS when a customer managed key is set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ObjectEncryption {
+    pub server_side_encryption: Option<String>,
+    pub ssekms_key_id: Option<String>,
+}
+
+impl ObjectEncryption {
+    pub fn new(kms_key_arn: Option<&str>) -> Self {
+        match kms_key_arn {
+            Some(kms_key_arn) => ObjectEncryption {
+                server_side_encryption: Some(SSE_KMS_ALGORITHM.to_string()),
+                ssekms_key_id: Some(kms_key_arn.to_string()),
+            },
+            None => ObjectEncryption::default(),
+        }
+    }
+}
+
+/// Default encryption of a bucket with a customer managed key, S3 bucket keys reduce the calls made to KMS
+fn bucket_encryption_configuration(kms_key_arn: &str) -> ServerSideEncryptionConfiguration {
+    ServerSideEncryptionConfiguration {
+        rules: vec![ServerSideEncryptionRule {
+            apply_server_side_encryption_by_default: Some(ServerSideEncryptionByDefault {
+                sse_algorithm: SSE_KMS_ALGORITHM.to_string(),
+                kms_master_key_id: Some(kms_key_arn.to_string()),
+            }),
+            bucket_key_enabled: Some(true),
+        }],
+    }
+}
+
+/// KMS errors (missing key, disabled key, key policy denying the caller) are only reported in the error message
+fn is_encryption_key_error(raw_error_message: &str) -> bool {
+    raw_error_message.to_lowercase().contains("kms")
+}
+
 pub struct S3 {
     id: String,
     name: String,
     access_key_id: String,
     secret_access_key: String,
     region: AwsRegion,
+    kms_key_arn: Option<String>,
 }
 
 impl S3 {
@@ -41,6 +93,30 @@ impl S3 {
             access_key_id,
             secret_access_key,
             region,
+            kms_key_arn: None,
+        }
+    }
+
+    /// Encrypts the created buckets and the uploaded objects with a customer managed KMS key
+    pub fn with_kms_key_arn(mut self, kms_key_arn: Option<String>) -> Self {
+        self.kms_key_arn = kms_key_arn;
+        self
+    }
+
+    fn upload_error(&self, bucket_name: &str, object_key: &str, raw_error_message: String) -> ObjectStorageError {
+        match &self.kms_key_arn {
+            Some(kms_key_arn) if is_encryption_key_error(&raw_error_message) => {
+                ObjectStorageError::CannotUseEncryptionKey {
+                    bucket_name: bucket_name.to_string(),
+                    kms_key_arn: kms_key_arn.to_string(),
+                    raw_error_message,
+                }
+            }
+            _ => ObjectStorageError::CannotUploadFile {
+                bucket_name: bucket_name.to_string(),
+                object_name: object_key.to_string(),
+                raw_error_message,
+            },
         }
     }
 
@@ -63,6 +139,44 @@ impl S3 {
         S3Client::new_with_client(client, region)
     }
 
+    fn get_iam_client(&self) -> aws_sdk_iam::Client {
+        let credentials_provider = SharedCredentialsProvider::new(aws_credential_types::Credentials::new(
+            self.access_key_id.clone(),
+            self.secret_access_key.clone(),
+            None,
+            None,
+            "qovery-engine",
+        ));
+
+        aws_sdk_iam::Client::new(
+            &SdkConfig::builder()
+                .credentials_provider(credentials_provider)
+                .behavior_version(BehaviorVersion::latest())
+                .region(Region::new(Cow::from(self.region.to_cloud_provider_format().to_string())))
+                .build(),
+        )
+    }
+
+    fn delete_access_keys(&self, iam_client: &aws_sdk_iam::Client, user_name: &str) -> Result<(), aws_sdk_iam::Error> {
+        let access_keys = match block_on(iam_client.list_access_keys().user_name(user_name).send()) {
+            Ok(output) => output.access_key_metadata,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_entity_exception()) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        for access_key_id in access_keys.iter().filter_map(|key| key.access_key_id()) {
+            block_on(
+                iam_client
+                    .delete_access_key()
+                    .user_name(user_name)
+                    .access_key_id(access_key_id)
+                    .send(),
+            )?;
+        }
+
+        Ok(())
+    }
+
     fn is_bucket_name_valid(bucket_name: &str) -> Result<(), ObjectStorageError> {
         if bucket_name.is_empty() {
             return Err(ObjectStorageError::InvalidBucketName {
@@ -221,6 +335,20 @@ impl ObjectStorage for S3 {
             });
         }
 
+        if let Some(kms_key_arn) = &self.kms_key_arn {
+            if let Err(e) = block_on(s3_client.put_bucket_encryption(PutBucketEncryptionRequest {
+                bucket: bucket_name.to_string(),
+                server_side_encryption_configuration: bucket_encryption_configuration(kms_key_arn),
+                ..Default::default()
+            })) {
+                return Err(ObjectStorageError::CannotUseEncryptionKey {
+                    bucket_name: bucket_name.to_string(),
+                    kms_key_arn: kms_key_arn.to_string(),
+                    raw_error_message: e.to_string(),
+                });
+            }
+        }
+
         if bucket_versioning_activated {
             // Not blocking if fails for the time being
             let _ = block_on(s3_client.put_bucket_versioning(PutBucketVersioningRequest {
@@ -399,6 +527,7 @@ impl ObjectStorage for S3 {
         })?;
 
         let tags = tags.map(|tags| tags.join("&"));
+        let encryption = ObjectEncryption::new(self.kms_key_arn.as_deref());
 
         match block_on(s3_client.put_object(PutObjectRequest {
             bucket: bucket_name.to_string(),
@@ -406,6 +535,8 @@ impl ObjectStorage for S3 {
             body: Some(StreamingBody::from(file_content.clone())),
             expected_bucket_owner: None,
             tagging: tags,
+            server_side_encryption: encryption.server_side_encryption,
+            ssekms_key_id: encryption.ssekms_key_id,
             ..Default::default()
         })) {
             Ok(_o) => Ok(BucketObject {
@@ -414,14 +545,34 @@ impl ObjectStorage for S3 {
                 value: file_content.clone(),
                 tags: vec![],
             }),
-            Err(e) => Err(ObjectStorageError::CannotUploadFile {
-                bucket_name: bucket_name.to_string(),
-                object_name: object_key.to_string(),
-                raw_error_message: e.to_string(),
-            }),
+            Err(e) => Err(self.upload_error(bucket_name, object_key, e.to_string())),
         }
     }
 
+    fn put_object_streaming(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        reader: &mut dyn Read,
+        size_hint: Option<u64>,
+        tags: Option<Vec<String>>,
+    ) -> Result<(), ObjectStorageError> {
+        S3::is_bucket_name_valid(bucket_name)?;
+
+        let s3_client = self.get_s3_client();
+        let upload = S3MultipartUpload {
+            client: &s3_client,
+            bucket_name,
+            object_key,
+            tagging: tags.map(|tags| tags.join("&")),
+            encryption: ObjectEncryption::new(self.kms_key_arn.as_deref()),
+        };
+
+        multipart_upload(&upload, reader, size_hint)
+            .map(|_| ())
+            .map_err(|e| self.upload_error(bucket_name, object_key, e.to_string()))
+    }
+
     fn delete_object(&self, bucket_name: &str, object_key: &str) -> Result<(), ObjectStorageError> {
         if S3::is_bucket_name_valid(bucket_name).is_err() {
             // bucket is missing it's ok as file can't be present
@@ -448,6 +599,176 @@ impl ObjectStorage for S3 {
             }),
         }
     }
+
+    fn endpoint(&self) -> String {
+        format!("https://s3.{}.amazonaws.com", self.region.to_cloud_provider_format())
+    }
+
+    fn region_name(&self) -> String {
+        self.region.to_cloud_provider_format().to_string()
+    }
+
+    fn apply_bucket_settings(&self, bucket_name: &str, settings: &BucketSettings) -> Result<(), ObjectStorageError> {
+        S3::is_bucket_name_valid(bucket_name)?;
+
+        let s3_client = self.get_s3_client();
+        let to_error = |e: String| ObjectStorageError::CannotApplyBucketSettings {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message: e,
+        };
+
+        // versioning cannot be disabled once activated, only suspended
+        block_on(s3_client.put_bucket_versioning(PutBucketVersioningRequest {
+            bucket: bucket_name.to_string(),
+            versioning_configuration: VersioningConfiguration {
+                mfa_delete: None,
+                status: Some(match settings.versioning_activated {
+                    true => "Enabled".to_string(),
+                    false => "Suspended".to_string(),
+                }),
+            },
+            ..Default::default()
+        }))
+        .map_err(|e| to_error(e.to_string()))?;
+
+        match settings.block_public_access {
+            true => block_on(s3_client.put_public_access_block(PutPublicAccessBlockRequest {
+                bucket: bucket_name.to_string(),
+                public_access_block_configuration: PublicAccessBlockConfiguration {
+                    block_public_acls: Some(true),
+                    block_public_policy: Some(true),
+                    ignore_public_acls: Some(true),
+                    restrict_public_buckets: Some(true),
+                },
+                ..Default::default()
+            }))
+            .map_err(|e| to_error(e.to_string()))?,
+            false => block_on(s3_client.delete_public_access_block(DeletePublicAccessBlockRequest {
+                bucket: bucket_name.to_string(),
+                expected_bucket_owner: None,
+            }))
+            .map_err(|e| to_error(e.to_string()))?,
+        }
+
+        match settings.objects_expiration_in_days {
+            Some(days) => block_on(s3_client.put_bucket_lifecycle_configuration(
+                PutBucketLifecycleConfigurationRequest {
+                    bucket: bucket_name.to_string(),
+                    lifecycle_configuration: Some(BucketLifecycleConfiguration {
+                        rules: vec![LifecycleRule {
+                            id: Some("qovery-objects-expiration".to_string()),
+                            status: "Enabled".to_string(),
+                            filter: Some(LifecycleRuleFilter {
+                                prefix: Some("".to_string()),
+                                ..Default::default()
+                            }),
+                            expiration: Some(LifecycleExpiration {
+                                days: Some(i64::from(days)),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }],
+                    }),
+                    ..Default::default()
+                },
+            ))
+            .map_err(|e| to_error(e.to_string())),
+            None => block_on(s3_client.delete_bucket_lifecycle(DeleteBucketLifecycleRequest {
+                bucket: bucket_name.to_string(),
+                expected_bucket_owner: None,
+            }))
+            .map_err(|e| to_error(e.to_string())),
+        }
+    }
+
+    fn create_bucket_credentials(
+        &self,
+        bucket_name: &str,
+        credentials_id: &str,
+    ) -> Result<BucketCredentials, ObjectStorageError> {
+        S3::is_bucket_name_valid(bucket_name)?;
+
+        let iam_client = self.get_iam_client();
+        let to_error = |e: String| ObjectStorageError::CannotCreateBucketCredentials {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message: e,
+        };
+
+        // one IAM user per bucket, with an inline policy so the permissions are deleted along with the user
+        match block_on(
+            iam_client
+                .create_user()
+                .path(S3_CREDENTIALS_USER_PATH)
+                .user_name(credentials_id)
+                .send(),
+        ) {
+            Ok(_) => {}
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_entity_already_exists_exception()) => {}
+            Err(e) => return Err(to_error(e.to_string())),
+        }
+
+        let policy = json!({
+            "Version": "2012-10-17",
+            "Statement": [{
+                "Effect": "Allow",
+                "Action": BUCKET_CREDENTIALS_ACTIONS,
+                "Resource": [format!("arn:aws:s3:::{bucket_name}"), format!("arn:aws:s3:::{bucket_name}/*")],
+            }],
+        });
+        block_on(
+            iam_client
+                .put_user_policy()
+                .user_name(credentials_id)
+                .policy_name(S3_CREDENTIALS_POLICY_NAME)
+                .policy_document(policy.to_string())
+                .send(),
+        )
+        .map_err(|e| to_error(e.to_string()))?;
+
+        // IAM users are limited to 2 access keys, previous ones are revoked before creating a new one
+        self.delete_access_keys(&iam_client, credentials_id)
+            .map_err(|e| to_error(e.to_string()))?;
+        let output = block_on(iam_client.create_access_key().user_name(credentials_id).send())
+            .map_err(|e| to_error(e.to_string()))?;
+        let access_key = output
+            .access_key
+            .ok_or_else(|| to_error("no access key returned".to_string()))?;
+
+        Ok(BucketCredentials {
+            access_key_id: access_key.access_key_id,
+            secret_access_key: access_key.secret_access_key,
+        })
+    }
+
+    fn delete_bucket_credentials(&self, bucket_name: &str, credentials_id: &str) -> Result<(), ObjectStorageError> {
+        let iam_client = self.get_iam_client();
+        let to_error = |e: String| ObjectStorageError::CannotDeleteBucketCredentials {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message: e,
+        };
+
+        // a user cannot be deleted while it still has keys or policies attached
+        self.delete_access_keys(&iam_client, credentials_id)
+            .map_err(|e| to_error(e.to_string()))?;
+        match block_on(
+            iam_client
+                .delete_user_policy()
+                .user_name(credentials_id)
+                .policy_name(S3_CREDENTIALS_POLICY_NAME)
+                .send(),
+        ) {
+            Ok(_) => {}
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_entity_exception()) => {}
+            Err(e) => return Err(to_error(e.to_string())),
+        }
+        match block_on(iam_client.delete_user().user_name(credentials_id).send()) {
+            Ok(_) => Ok(()),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_entity_exception()) => Ok(()),
+            Err(e) => Err(to_error(e.to_string())),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -487,4 +808,66 @@ mod tests {
             assert_eq!(tc.expected_output, result, "{}", tc.description);
         }
     }
+
+    #[test]
+    fn test_encryption_parameters() {
+        let kms_key_arn = "arn:aws:kms:eu-west-3:123456789012:key/1234abcd-12ab-34cd-56ef-1234567890ab";
+
+        // objects inherit the bucket default encryption without a customer key
+        assert_eq!(ObjectEncryption::new(None), ObjectEncryption::default());
+        assert_eq!(
+            ObjectEncryption::new(Some(kms_key_arn)),
+            ObjectEncryption {
+                server_side_encryption: Some("aws:kms".to_string()),
+                ssekms_key_id: Some(kms_key_arn.to_string()),
+            }
+        );
+
+        let configuration = bucket_encryption_configuration(kms_key_arn);
+        assert_eq!(configuration.rules.len(), 1);
+        assert_eq!(configuration.rules[0].bucket_key_enabled, Some(true));
+        let default_encryption = configuration.rules[0]
+            .apply_server_side_encryption_by_default
+            .as_ref()
+            .unwrap();
+        assert_eq!(default_encryption.sse_algorithm, "aws:kms");
+        assert_eq!(default_encryption.kms_master_key_id.as_deref(), Some(kms_key_arn));
+    }
+
+    #[test]
+    fn test_upload_error_with_encryption_key() {
+        let kms_key_arn = "arn:aws:kms:eu-west-3:123456789012:key/1234abcd-12ab-34cd-56ef-1234567890ab";
+        let s3 = S3::new(
+            "id".to_string(),
+            "name".to_string(),
+            "access_key_id".to_string(),
+            "secret_access_key".to_string(),
+            AwsRegion::EuWest3,
+        );
+        let access_denied = "Unknown(BufferedHttpResponse {status: 403, body: \"<Code>AccessDenied</Code><Message>User: arn:aws:iam::123456789012:user/qovery is not authorized to perform: kms:GenerateDataKey</Message>\", headers: {}})";
+
+        // without a customer key, KMS errors can't come from the engine configuration
+        assert!(matches!(
+            s3.upload_error("bucket", "key", access_denied.to_string()),
+            ObjectStorageError::CannotUploadFile { .. }
+        ));
+
+        let s3 = s3.with_kms_key_arn(Some(kms_key_arn.to_string()));
+        assert_eq!(
+            s3.upload_error("bucket", "key", access_denied.to_string()),
+            ObjectStorageError::CannotUseEncryptionKey {
+                bucket_name: "bucket".to_string(),
+                kms_key_arn: kms_key_arn.to_string(),
+                raw_error_message: access_denied.to_string(),
+            }
+        );
+        assert!(matches!(
+            s3.upload_error("bucket", "key", "KMS.NotFoundException: Invalid keyId".to_string()),
+            ObjectStorageError::CannotUseEncryptionKey { .. }
+        ));
+        assert!(matches!(
+            s3.upload_error("bucket", "key", "NoSuchBucket".to_string()),
+            ObjectStorageError::CannotUploadFile { .. }
+        ));
+    }
 }
@@ -5,20 +5,30 @@ use std::path::Path;
 use std::time::Duration;
 
 use crate::infrastructure::models::object_storage::{
-    Bucket, BucketDeleteStrategy, BucketObject, BucketRegion, Kind, ObjectStorage,
+    Bucket, BucketCredentials, BucketDeleteStrategy, BucketObject, BucketRegion, BucketSettings, Kind, ObjectStorage,
+    BUCKET_CREDENTIALS_ACTIONS,
 };
 
 use crate::environment::models::scaleway::ScwZone;
 use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+use crate::infrastructure::models::object_storage::multipart_upload::{multipart_upload, S3MultipartUpload};
+use crate::infrastructure::models::object_storage::s3::ObjectEncryption;
 use crate::runtime::block_on;
 use rusoto_core::{Client, HttpClient, Region as RusotoRegion};
 use rusoto_credential::StaticProvider;
 use rusoto_s3::{
-    CreateBucketConfiguration, CreateBucketRequest, Delete, DeleteBucketRequest, DeleteObjectRequest,
-    DeleteObjectsRequest, GetBucketLifecycleRequest, GetBucketTaggingRequest, GetBucketVersioningRequest,
-    GetObjectRequest, HeadBucketRequest, ListObjectsRequest, ObjectIdentifier, PutBucketTaggingRequest,
-    PutBucketVersioningRequest, PutObjectRequest, S3Client, StreamingBody, Tag, Tagging, S3,
+    BucketLifecycleConfiguration, CreateBucketConfiguration, CreateBucketRequest, Delete, DeleteBucketLifecycleRequest,
+    DeleteBucketRequest, DeleteObjectRequest, DeleteObjectsRequest, GetBucketLifecycleRequest, GetBucketTaggingRequest,
+    GetBucketVersioningRequest, GetObjectRequest, HeadBucketRequest, LifecycleExpiration, LifecycleRule,
+    LifecycleRuleFilter, ListObjectsRequest, ObjectIdentifier, PutBucketAclRequest,
+    PutBucketLifecycleConfigurationRequest, PutBucketPolicyRequest, PutBucketTaggingRequest,
+    PutBucketVersioningRequest, PutObjectRequest, S3Client, StreamingBody, Tag, Tagging, VersioningConfiguration, S3,
 };
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const SCW_API_URL: &str = "https://api.scaleway.com";
 
 // doc: https://www.scaleway.com/en/docs/object-storage-feature/
 pub struct ScalewayOS {
@@ -59,6 +69,63 @@ impl ScalewayOS {
         format!("https://s3.{}.scw.cloud", self.zone.region())
     }
 
+    fn iam_request<T: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<T, String> {
+        let mut request = reqwest::blocking::Client::new()
+            .request(method, format!("{SCW_API_URL}{path}"))
+            .header("X-Auth-Token", &self.secret_token);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        request
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json::<T>())
+            .map_err(|e| e.to_string())
+    }
+
+    fn iam_delete(&self, path: &str) -> Result<(), String> {
+        reqwest::blocking::Client::new()
+            .delete(format!("{SCW_API_URL}{path}"))
+            .header("X-Auth-Token", &self.secret_token)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Key of the cluster credentials, and the organization owning its project
+    fn cluster_key_and_organization(&self) -> Result<(ScwApiKey, String), String> {
+        let cluster_key: ScwApiKey = self.iam_request(
+            reqwest::Method::GET,
+            &format!("/iam/v1alpha1/api-keys/{}", self.access_key),
+            None,
+        )?;
+        let project: ScwProject = self.iam_request(
+            reqwest::Method::GET,
+            &format!("/account/v3/projects/{}", cluster_key.default_project_id),
+            None,
+        )?;
+
+        Ok((cluster_key, project.organization_id))
+    }
+
+    fn find_application(&self, organization_id: &str, name: &str) -> Result<Option<ScwApplication>, String> {
+        let applications: ScwApplications = self.iam_request(
+            reqwest::Method::GET,
+            &format!("/iam/v1alpha1/applications?organization_id={organization_id}&name={name}"),
+            None,
+        )?;
+
+        // the name filter matches on a prefix
+        Ok(applications.applications.into_iter().find(|app| app.name == name))
+    }
+
     fn is_bucket_name_valid(bucket_name: &str) -> Result<(), ObjectStorageError> {
         if bucket_name.is_empty() {
             return Err(ObjectStorageError::InvalidBucketName {
@@ -413,6 +480,35 @@ impl ObjectStorage for ScalewayOS {
         }
     }
 
+    fn put_object_streaming(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        reader: &mut dyn Read,
+        size_hint: Option<u64>,
+        _tags: Option<Vec<String>>,
+    ) -> Result<(), ObjectStorageError> {
+        ScalewayOS::is_bucket_name_valid(bucket_name)?;
+
+        let s3_client = self.get_s3_client();
+        let upload = S3MultipartUpload {
+            client: &s3_client,
+            bucket_name,
+            object_key,
+            // tags are ignored, as by `put_object`
+            tagging: None,
+            encryption: ObjectEncryption::default(),
+        };
+
+        multipart_upload(&upload, reader, size_hint)
+            .map(|_| ())
+            .map_err(|e| ObjectStorageError::CannotUploadFile {
+                bucket_name: bucket_name.to_string(),
+                object_name: object_key.to_string(),
+                raw_error_message: e.to_string(),
+            })
+    }
+
     fn delete_object(&self, bucket_name: &str, object_key: &str) -> Result<(), ObjectStorageError> {
         if ScalewayOS::is_bucket_name_valid(bucket_name).is_err() {
             // bucket is missing it's ok as file can't be present
@@ -439,6 +535,232 @@ impl ObjectStorage for ScalewayOS {
             }),
         }
     }
+
+    fn endpoint(&self) -> String {
+        self.get_endpoint_url_for_region()
+    }
+
+    fn region_name(&self) -> String {
+        self.zone.region().to_string()
+    }
+
+    fn apply_bucket_settings(&self, bucket_name: &str, settings: &BucketSettings) -> Result<(), ObjectStorageError> {
+        ScalewayOS::is_bucket_name_valid(bucket_name)?;
+
+        let s3_client = self.get_s3_client();
+        let to_error = |e: String| ObjectStorageError::CannotApplyBucketSettings {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message: e,
+        };
+
+        block_on(s3_client.put_bucket_versioning(PutBucketVersioningRequest {
+            bucket: bucket_name.to_string(),
+            versioning_configuration: VersioningConfiguration {
+                mfa_delete: None,
+                status: Some(match settings.versioning_activated {
+                    true => "Enabled".to_string(),
+                    false => "Suspended".to_string(),
+                }),
+            },
+            ..Default::default()
+        }))
+        .map_err(|e| to_error(e.to_string()))?;
+
+        // SCW doesn't support public access blocks, buckets are private unless their ACL or objects ACL say otherwise
+        if settings.block_public_access {
+            block_on(s3_client.put_bucket_acl(PutBucketAclRequest {
+                bucket: bucket_name.to_string(),
+                acl: Some("private".to_string()),
+                ..Default::default()
+            }))
+            .map_err(|e| to_error(e.to_string()))?;
+        }
+
+        match settings.objects_expiration_in_days {
+            Some(days) => block_on(s3_client.put_bucket_lifecycle_configuration(
+                PutBucketLifecycleConfigurationRequest {
+                    bucket: bucket_name.to_string(),
+                    lifecycle_configuration: Some(BucketLifecycleConfiguration {
+                        rules: vec![LifecycleRule {
+                            id: Some("qovery-objects-expiration".to_string()),
+                            status: "Enabled".to_string(),
+                            filter: Some(LifecycleRuleFilter {
+                                prefix: Some("".to_string()),
+                                ..Default::default()
+                            }),
+                            expiration: Some(LifecycleExpiration {
+                                days: Some(i64::from(days)),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }],
+                    }),
+                    ..Default::default()
+                },
+            ))
+            .map_err(|e| to_error(e.to_string())),
+            None => block_on(s3_client.delete_bucket_lifecycle(DeleteBucketLifecycleRequest {
+                bucket: bucket_name.to_string(),
+                expected_bucket_owner: None,
+            }))
+            .map_err(|e| to_error(e.to_string())),
+        }
+    }
+
+    fn create_bucket_credentials(
+        &self,
+        bucket_name: &str,
+        credentials_id: &str,
+    ) -> Result<BucketCredentials, ObjectStorageError> {
+        ScalewayOS::is_bucket_name_valid(bucket_name)?;
+
+        let to_error = |e: String| ObjectStorageError::CannotCreateBucketCredentials {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message: e,
+        };
+
+        // credentials are an IAM application without any permission set, access is only granted by the bucket policy
+        let (cluster_key, organization_id) = self.cluster_key_and_organization().map_err(to_error)?;
+
+        let application = match self
+            .find_application(&organization_id, credentials_id)
+            .map_err(to_error)?
+        {
+            Some(application) => application,
+            None => self
+                .iam_request(
+                    reqwest::Method::POST,
+                    "/iam/v1alpha1/applications",
+                    Some(json!({
+                        "name": credentials_id,
+                        "organization_id": organization_id,
+                        "description": format!("Qovery credentials of bucket {bucket_name}"),
+                    })),
+                )
+                .map_err(to_error)?,
+        };
+
+        let previous_keys: ScwApiKeys = self
+            .iam_request(
+                reqwest::Method::GET,
+                &format!("/iam/v1alpha1/api-keys?application_id={}", application.id),
+                None,
+            )
+            .map_err(to_error)?;
+        for key in previous_keys.api_keys {
+            self.iam_delete(&format!("/iam/v1alpha1/api-keys/{}", key.access_key))
+                .map_err(to_error)?;
+        }
+
+        let key: ScwApiKey = self
+            .iam_request(
+                reqwest::Method::POST,
+                "/iam/v1alpha1/api-keys",
+                Some(json!({
+                    "application_id": application.id,
+                    "default_project_id": cluster_key.default_project_id,
+                    "description": format!("Qovery credentials of bucket {bucket_name}"),
+                })),
+            )
+            .map_err(to_error)?;
+
+        // once a bucket has a policy, principals not listed lose their access: the cluster credentials are kept
+        let resources = json!([bucket_name, format!("{bucket_name}/*")]);
+        let mut statements = vec![json!({
+            "Sid": "qovery-bucket-credentials",
+            "Effect": "Allow",
+            "Principal": { "SCW": format!("application_id:{}", application.id) },
+            "Action": BUCKET_CREDENTIALS_ACTIONS,
+            "Resource": resources,
+        })];
+        if let Some(cluster_principal) = cluster_key.principal() {
+            statements.push(json!({
+                "Sid": "qovery-cluster",
+                "Effect": "Allow",
+                "Principal": { "SCW": cluster_principal },
+                "Action": ["s3:*"],
+                "Resource": resources,
+            }));
+        }
+        let policy = json!({ "Version": "2023-04-17", "Statement": statements });
+        block_on(self.get_s3_client().put_bucket_policy(PutBucketPolicyRequest {
+            bucket: bucket_name.to_string(),
+            policy: policy.to_string(),
+            ..Default::default()
+        }))
+        .map_err(|e| to_error(e.to_string()))?;
+
+        Ok(BucketCredentials {
+            access_key_id: key.access_key,
+            secret_access_key: key
+                .secret_key
+                .ok_or_else(|| to_error("no secret key returned".to_string()))?,
+        })
+    }
+
+    fn delete_bucket_credentials(&self, bucket_name: &str, credentials_id: &str) -> Result<(), ObjectStorageError> {
+        let to_error = |e: String| ObjectStorageError::CannotDeleteBucketCredentials {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message: e,
+        };
+
+        let (_, organization_id) = self.cluster_key_and_organization().map_err(to_error)?;
+
+        // deleting the application deletes its api keys
+        match self
+            .find_application(&organization_id, credentials_id)
+            .map_err(to_error)?
+        {
+            Some(application) => self
+                .iam_delete(&format!("/iam/v1alpha1/applications/{}", application.id))
+                .map_err(to_error),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ScwApiKey {
+    access_key: String,
+    #[serde(default)]
+    secret_key: Option<String>,
+    #[serde(default)]
+    application_id: Option<String>,
+    #[serde(default)]
+    user_id: Option<String>,
+    default_project_id: String,
+}
+
+impl ScwApiKey {
+    /// Bucket policy principal of the owner of the key
+    fn principal(&self) -> Option<String> {
+        match (&self.application_id, &self.user_id) {
+            (Some(application_id), _) => Some(format!("application_id:{application_id}")),
+            (None, Some(user_id)) => Some(format!("user_id:{user_id}")),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ScwApiKeys {
+    api_keys: Vec<ScwApiKey>,
+}
+
+#[derive(Deserialize)]
+struct ScwProject {
+    organization_id: String,
+}
+
+#[derive(Deserialize)]
+struct ScwApplication {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ScwApplications {
+    applications: Vec<ScwApplication>,
 }
 
 struct ScalewayObjectStorageErrorManager {}
@@ -0,0 +1,329 @@
+use crate::infrastructure::models::cloud_provider::aws::regions::AwsRegion;
+use crate::infrastructure::models::object_storage::errors::ObjectStorageError;
+use crate::infrastructure::models::object_storage::{
+    Bucket, BucketCredentials, BucketDeleteStrategy, BucketObject, BucketRegion, BucketSettings, Kind, ObjectStorage,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Object storage keeping its buckets and objects in memory, and recording the calls made to it.
+/// Writes of objects under a rejected `<bucket>/<key>` prefix fail, to test how callers handle upload errors,
+/// and the next calls can be made to fail to test how they handle transient ones.
+#[derive(Default)]
+pub struct InMemoryObjectStorage {
+    buckets: Mutex<BTreeMap<String, Bucket>>,
+    objects: Mutex<BTreeMap<(String, String), BucketObject>>,
+    rejected_write_prefixes: Mutex<Vec<String>>,
+    failing_calls: Mutex<Option<(u32, ObjectStorageError)>>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl InMemoryObjectStorage {
+    pub fn with_buckets(bucket_names: &[&str]) -> Self {
+        let object_storage = InMemoryObjectStorage::default();
+        for bucket_name in bucket_names {
+            object_storage.insert_bucket(bucket_name, None, false);
+        }
+
+        object_storage
+    }
+
+    /// The next `count` calls fail with `error`, whatever the operation
+    pub fn fail_next_calls(&self, count: u32, error: ObjectStorageError) {
+        *self.failing_calls.lock().unwrap() = Some((count, error));
+    }
+
+    /// Operations called so far, i.e: `create_bucket <bucket>`, `put_object <bucket>/<key>`
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    pub fn call_count(&self) -> usize {
+        self.calls.lock().unwrap().len()
+    }
+
+    /// Puts and deletes of the objects whose `<bucket>/<key>` starts with `prefix` fail from now on
+    pub fn reject_writes_under(&self, prefix: &str) {
+        self.rejected_write_prefixes.lock().unwrap().push(prefix.to_string());
+    }
+
+    pub fn accept_all_writes(&self) {
+        self.rejected_write_prefixes.lock().unwrap().clear();
+    }
+
+    /// Keys of the objects of the bucket, sorted
+    pub fn keys(&self, bucket_name: &str) -> Vec<String> {
+        self.objects
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(bucket, _)| bucket == bucket_name)
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+
+    fn is_write_rejected(&self, bucket_name: &str, object_key: &str) -> bool {
+        let path = format!("{bucket_name}/{object_key}");
+        self.rejected_write_prefixes
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|prefix| path.starts_with(prefix))
+    }
+
+    /// Records the call, then fails it if it is one of the failing calls
+    fn call(&self, operation: &str, target: &str) -> Result<(), ObjectStorageError> {
+        self.calls.lock().unwrap().push(format!("{operation} {target}"));
+        let mut failing_calls = self.failing_calls.lock().unwrap();
+        match failing_calls.as_mut() {
+            Some((count, error)) if *count > 0 => {
+                *count -= 1;
+                Err(error.clone())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn insert_bucket(&self, bucket_name: &str, bucket_ttl: Option<Duration>, versioning_activated: bool) -> Bucket {
+        let bucket = Bucket::new(
+            bucket_name.to_string(),
+            bucket_ttl,
+            versioning_activated,
+            BucketRegion::AwsRegion(AwsRegion::EuWest3),
+            None,
+        );
+        self.buckets
+            .lock()
+            .unwrap()
+            .insert(bucket_name.to_string(), bucket.clone());
+
+        bucket
+    }
+
+    fn set_versioning(&self, bucket_name: &str, versioning_activated: bool) -> Result<Bucket, ObjectStorageError> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .get_mut(bucket_name)
+            .ok_or_else(|| Self::bucket_not_found(bucket_name))?;
+        bucket.versioning_activated = versioning_activated;
+
+        Ok(bucket.clone())
+    }
+
+    fn store_object(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        content: impl FnOnce() -> std::io::Result<Vec<u8>>,
+        tags: Option<Vec<String>>,
+    ) -> Result<BucketObject, ObjectStorageError> {
+        let upload_error = |raw_error_message: String| ObjectStorageError::CannotUploadFile {
+            bucket_name: bucket_name.to_string(),
+            object_name: object_key.to_string(),
+            raw_error_message,
+        };
+        if !self.bucket_exists(bucket_name) {
+            return Err(upload_error("NoSuchBucket: the specified bucket does not exist".to_string()));
+        }
+        if self.is_write_rejected(bucket_name, object_key) {
+            return Err(upload_error("AccessDenied: access denied".to_string()));
+        }
+
+        let object = BucketObject {
+            bucket_name: bucket_name.to_string(),
+            key: object_key.to_string(),
+            value: content().map_err(|e| upload_error(e.to_string()))?,
+            tags: tags.unwrap_or_default(),
+        };
+        self.objects
+            .lock()
+            .unwrap()
+            .insert((bucket_name.to_string(), object_key.to_string()), object.clone());
+
+        Ok(object)
+    }
+
+    fn bucket_not_found(bucket_name: &str) -> ObjectStorageError {
+        ObjectStorageError::CannotGetBucket {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message: "NoSuchBucket: the specified bucket does not exist".to_string(),
+        }
+    }
+}
+
+impl ObjectStorage for InMemoryObjectStorage {
+    fn kind(&self) -> Kind {
+        Kind::S3
+    }
+
+    fn id(&self) -> &str {
+        "in-memory"
+    }
+
+    fn name(&self) -> &str {
+        "in-memory"
+    }
+
+    fn is_valid(&self) -> Result<(), ObjectStorageError> {
+        Ok(())
+    }
+
+    fn bucket_exists(&self, bucket_name: &str) -> bool {
+        self.buckets.lock().unwrap().contains_key(bucket_name)
+    }
+
+    fn create_bucket(
+        &self,
+        bucket_name: &str,
+        bucket_ttl: Option<Duration>,
+        bucket_versioning_activated: bool,
+    ) -> Result<Bucket, ObjectStorageError> {
+        self.call("create_bucket", bucket_name)?;
+        Ok(self.insert_bucket(bucket_name, bucket_ttl, bucket_versioning_activated))
+    }
+
+    fn update_bucket(
+        &self,
+        bucket_name: &str,
+        bucket_versioning_activated: bool,
+    ) -> Result<Bucket, ObjectStorageError> {
+        self.call("update_bucket", bucket_name)?;
+        self.set_versioning(bucket_name, bucket_versioning_activated)
+    }
+
+    fn get_bucket(&self, bucket_name: &str) -> Result<Bucket, ObjectStorageError> {
+        self.call("get_bucket", bucket_name)?;
+        self.buckets
+            .lock()
+            .unwrap()
+            .get(bucket_name)
+            .cloned()
+            .ok_or_else(|| Self::bucket_not_found(bucket_name))
+    }
+
+    fn delete_bucket(
+        &self,
+        bucket_name: &str,
+        bucket_delete_strategy: BucketDeleteStrategy,
+    ) -> Result<(), ObjectStorageError> {
+        self.call("delete_bucket", bucket_name)?;
+        self.objects
+            .lock()
+            .unwrap()
+            .retain(|(bucket, _), _| bucket != bucket_name);
+        if let BucketDeleteStrategy::HardDelete = bucket_delete_strategy {
+            self.buckets.lock().unwrap().remove(bucket_name);
+        }
+
+        Ok(())
+    }
+
+    fn delete_bucket_non_blocking(&self, bucket_name: &str) -> Result<(), ObjectStorageError> {
+        self.call("delete_bucket_non_blocking", bucket_name)?;
+        self.objects
+            .lock()
+            .unwrap()
+            .retain(|(bucket, _), _| bucket != bucket_name);
+        self.buckets.lock().unwrap().remove(bucket_name);
+
+        Ok(())
+    }
+
+    fn get_object(&self, bucket_name: &str, object_key: &str) -> Result<BucketObject, ObjectStorageError> {
+        self.call("get_object", &format!("{bucket_name}/{object_key}"))?;
+        self.objects
+            .lock()
+            .unwrap()
+            .get(&(bucket_name.to_string(), object_key.to_string()))
+            .cloned()
+            .ok_or_else(|| ObjectStorageError::CannotGetObjectFile {
+                bucket_name: bucket_name.to_string(),
+                object_name: object_key.to_string(),
+                raw_error_message: "NoSuchKey: the specified key does not exist".to_string(),
+            })
+    }
+
+    fn put_object(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        file_path: &Path,
+        tags: Option<Vec<String>>,
+    ) -> Result<BucketObject, ObjectStorageError> {
+        self.call("put_object", &format!("{bucket_name}/{object_key}"))?;
+        self.store_object(bucket_name, object_key, || fs::read(file_path), tags)
+    }
+
+    fn put_object_streaming(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        reader: &mut dyn Read,
+        _size_hint: Option<u64>,
+        tags: Option<Vec<String>>,
+    ) -> Result<(), ObjectStorageError> {
+        self.call("put_object_streaming", &format!("{bucket_name}/{object_key}"))?;
+        let content = || {
+            let mut content = vec![];
+            reader.read_to_end(&mut content).map(|_| content)
+        };
+        self.store_object(bucket_name, object_key, content, tags).map(|_| ())
+    }
+
+    fn delete_object(&self, bucket_name: &str, object_key: &str) -> Result<(), ObjectStorageError> {
+        self.call("delete_object", &format!("{bucket_name}/{object_key}"))?;
+        if self.is_write_rejected(bucket_name, object_key) {
+            return Err(ObjectStorageError::CannotDeleteFile {
+                bucket_name: bucket_name.to_string(),
+                object_name: object_key.to_string(),
+                raw_error_message: "AccessDenied: access denied".to_string(),
+            });
+        }
+
+        // like S3, deleting a missing object succeeds
+        self.objects
+            .lock()
+            .unwrap()
+            .remove(&(bucket_name.to_string(), object_key.to_string()));
+
+        Ok(())
+    }
+
+    fn endpoint(&self) -> String {
+        "https://s3.eu-west-3.amazonaws.com".to_string()
+    }
+
+    fn region_name(&self) -> String {
+        "eu-west-3".to_string()
+    }
+
+    fn apply_bucket_settings(&self, bucket_name: &str, settings: &BucketSettings) -> Result<(), ObjectStorageError> {
+        self.call("apply_bucket_settings", bucket_name)?;
+        self.set_versioning(bucket_name, settings.versioning_activated)?;
+        Ok(())
+    }
+
+    fn create_bucket_credentials(
+        &self,
+        bucket_name: &str,
+        credentials_id: &str,
+    ) -> Result<BucketCredentials, ObjectStorageError> {
+        self.call("create_bucket_credentials", &format!("{bucket_name} {credentials_id}"))?;
+        if !self.bucket_exists(bucket_name) {
+            return Err(Self::bucket_not_found(bucket_name));
+        }
+
+        Ok(BucketCredentials {
+            access_key_id: format!("AKIA{}", credentials_id.to_uppercase()),
+            secret_access_key: "secret".to_string(),
+        })
+    }
+
+    fn delete_bucket_credentials(&self, bucket_name: &str, credentials_id: &str) -> Result<(), ObjectStorageError> {
+        self.call("delete_bucket_credentials", &format!("{bucket_name} {credentials_id}"))
+    }
+}
@@ -0,0 +1,258 @@
+use crate::cmd::command::ExecutionContext;
+use crate::cmd::docker::Docker;
+use crate::engine_task::qovery_api::QoveryApi;
+use crate::engine_task::upload_json_document;
+use crate::engine_task::Task;
+use crate::environment::models::abort::{Abort, AbortStatus};
+use crate::errors::{CommandError, EngineError};
+use crate::events::Stage::Infrastructure;
+use crate::events::{EngineEvent, EngineMsg, EngineMsgPayload, EventDetails, EventMessage, InfrastructureStep};
+use crate::infrastructure::models::kubernetes::service_inventory::{
+    ServiceInventoryBuilder, ServiceInventoryDocument, SERVICE_ID_LABEL,
+};
+use crate::io_models::context::Context;
+use crate::io_models::engine_request::ServiceInventoryEngineRequest;
+use crate::io_models::feature_flags::FeatureFlags;
+use crate::logger::Logger;
+use crate::metrics_registry::MetricsRegistry;
+use crate::msg_publisher::MsgPublisher;
+use crate::proxy;
+use crate::runtime::block_on;
+use chrono::Utc;
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::{Event, Pod};
+use kube::api::ListParams;
+use kube::Api;
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+
+// Objects are listed page by page and folded into the inventory, so only one page is in memory at a time
+const LIST_PAGE_SIZE: u32 = 500;
+
+pub struct ServiceInventoryTask {
+    workspace_root_dir: String,
+    lib_root_dir: String,
+    docker: Arc<Docker>,
+    request: ServiceInventoryEngineRequest,
+    logger: Box<dyn Logger>,
+    metrics_registry: Box<dyn MetricsRegistry>,
+    msg_publisher: Box<dyn MsgPublisher>,
+    qovery_api: Arc<dyn QoveryApi>,
+    span: tracing::Span,
+    is_terminated: (RwLock<Option<broadcast::Sender<()>>>, broadcast::Receiver<()>),
+}
+
+impl ServiceInventoryTask {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        request: ServiceInventoryEngineRequest,
+        workspace_root_dir: String,
+        lib_root_dir: String,
+        docker: Arc<Docker>,
+        logger: Box<dyn Logger>,
+        metrics_registry: Box<dyn MetricsRegistry>,
+        msg_publisher: Box<dyn MsgPublisher>,
+        qovery_api: Box<dyn QoveryApi>,
+    ) -> Self {
+        let span = info_span!(
+            "service_inventory_task",
+            organization_id = request.organization_long_id.to_string(),
+            cluster_id = request.kubernetes.long_id.to_string(),
+        );
+
+        ServiceInventoryTask {
+            workspace_root_dir,
+            lib_root_dir,
+            docker,
+            request,
+            logger,
+            metrics_registry,
+            msg_publisher,
+            qovery_api: Arc::from(qovery_api),
+            span,
+            is_terminated: {
+                let (tx, rx) = broadcast::channel(1);
+                (RwLock::new(Some(tx)), rx)
+            },
+        }
+    }
+
+    fn info_context(&self) -> Context {
+        Context::new(
+            self.request.organization_long_id,
+            self.request.kubernetes.long_id,
+            self.request.id.to_string(),
+            self.workspace_root_dir.to_string(),
+            self.lib_root_dir.to_string(),
+            self.request.test_cluster,
+            self.request.features.clone(),
+            FeatureFlags::resolve(&self.request.feature_flags).0,
+            self.request.metadata.clone(),
+            self.docker.clone(),
+            self.qovery_api.clone(),
+            self.request.event_details(),
+        )
+        .with_proxy(self.request.proxy.clone())
+    }
+
+    fn get_event_details(&self, step: InfrastructureStep) -> EventDetails {
+        EventDetails::clone_changing_stage(self.request.event_details(), Infrastructure(step))
+    }
+
+    fn build_inventory(&self) -> Result<ServiceInventoryDocument, Box<EngineError>> {
+        let error_details = self.get_event_details(InfrastructureStep::ReportServiceInventoryError);
+        let infra_ctx = self.request.to_infrastructure_context(
+            &self.info_context(),
+            self.request.event_details(),
+            self.logger.clone(),
+            self.metrics_registry.clone(),
+            false,
+        )?;
+        let client = infra_ctx.mk_kube_client()?.client().clone();
+        let list_error = |kind: &str, err: kube::Error| {
+            Box::new(EngineError::new_cannot_report_service_inventory(
+                error_details.clone(),
+                CommandError::new(format!("Cannot list the {kind} of the cluster"), Some(err.to_string()), None),
+            ))
+        };
+
+        let mut inventory = ServiceInventoryBuilder::new(Utc::now());
+        let services = ListParams::default().labels(SERVICE_ID_LABEL);
+
+        // workloads first, so pods and events can be attached to their service
+        list_paginated(&Api::<Deployment>::all(client.clone()), &services, None, |deployment| {
+            inventory.add_deployment(deployment)
+        })
+        .map_err(|err| list_error("deployments", err))?;
+        list_paginated(&Api::<StatefulSet>::all(client.clone()), &services, None, |stateful_set| {
+            inventory.add_stateful_set(stateful_set)
+        })
+        .map_err(|err| list_error("statefulsets", err))?;
+        let truncated = list_paginated(
+            &Api::<Pod>::all(client.clone()),
+            &services,
+            Some(self.request.target_environment.max_pods),
+            |pod| inventory.add_pod(pod),
+        )
+        .map_err(|err| list_error("pods", err))?;
+
+        // events are not labeled, the builder keeps the ones of the objects listed above
+        let warnings = ListParams::default().fields("type=Warning");
+        if let Err(err) =
+            list_paginated(&Api::<Event>::all(client), &warnings, None, |event| inventory.add_event(event))
+        {
+            warn!("cannot list the events of the cluster: {}", err);
+        }
+
+        Ok(inventory.build(self.request.kubernetes.long_id, truncated))
+    }
+
+    fn publish(&self, document: &ServiceInventoryDocument) -> Result<String, Box<EngineError>> {
+        let error_details = self.get_event_details(InfrastructureStep::ReportServiceInventoryError);
+        let json = serde_json::to_string(document).map_err(|err| {
+            Box::new(EngineError::new_cannot_report_service_inventory(
+                error_details.clone(),
+                CommandError::new(
+                    "Cannot serialize the inventory document".to_string(),
+                    Some(err.to_string()),
+                    None,
+                ),
+            ))
+        })?;
+
+        self.msg_publisher
+            .send(EngineMsg::new(EngineMsgPayload::ServiceInventory(document.clone())));
+        upload_json_document(&self.request.target_environment.upload_url, json.clone()).map_err(|err| {
+            Box::new(EngineError::new_cannot_report_service_inventory(
+                error_details,
+                CommandError::new("Cannot upload the inventory document".to_string(), Some(err.to_string()), None),
+            ))
+        })?;
+
+        Ok(json)
+    }
+}
+
+/// Calls `f` on every object matching `params`, one page at a time.
+/// Returns true when the listing stopped after `max_items` objects, before the last page.
+fn list_paginated<K>(
+    api: &Api<K>,
+    params: &ListParams,
+    max_items: Option<usize>,
+    mut f: impl FnMut(&K),
+) -> Result<bool, kube::Error>
+where
+    K: Clone + DeserializeOwned + Debug,
+{
+    let mut params = params.clone().limit(LIST_PAGE_SIZE);
+    let mut listed = 0;
+    loop {
+        let page = block_on(api.list(&params))?;
+        for item in &page.items {
+            if max_items.is_some_and(|max_items| listed >= max_items) {
+                return Ok(true);
+            }
+            f(item);
+            listed += 1;
+        }
+
+        let Some(token) = page.metadata.continue_.filter(|token| !token.is_empty()) else {
+            return Ok(false);
+        };
+        if max_items.is_some_and(|max_items| listed >= max_items) {
+            return Ok(true);
+        }
+        params = params.continue_token(&token);
+    }
+}
+
+impl Task for ServiceInventoryTask {
+    fn id(&self) -> &str {
+        self.request.id.as_str()
+    }
+
+    fn run(&self) {
+        let _span = self.span.enter();
+        info!("service inventory task {} started", self.id());
+        let _execution_context = ExecutionContext::from(&self.request.event_details()).enter();
+        proxy::install(self.request.proxy.as_ref());
+
+        let _guard = scopeguard::guard((), |_| {
+            let Some(is_terminated_tx) = self.is_terminated.0.write().unwrap().take() else {
+                return;
+            };
+            let _ = is_terminated_tx.send(());
+        });
+
+        match self
+            .build_inventory()
+            .and_then(|document| self.publish(&document).map(|json| (document, json)))
+        {
+            Ok((document, json)) => self.logger.log(EngineEvent::Info(
+                self.get_event_details(InfrastructureStep::ServiceInventoryReported),
+                EventMessage::new_for_sending_core_data(document.to_string(), json),
+            )),
+            Err(err) => self.logger.log(EngineEvent::Error(*err, None)),
+        }
+
+        info!("service inventory task {} finished", self.id());
+    }
+
+    fn cancel(&self, _force_requested: bool) -> bool {
+        false
+    }
+
+    fn cancel_checker(&self) -> Box<dyn Abort> {
+        Box::new(move || AbortStatus::None)
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.is_terminated.0.read().map(|tx| tx.is_none()).unwrap_or(true)
+    }
+
+    fn await_terminated(&self) -> broadcast::Receiver<()> {
+        self.is_terminated.1.resubscribe()
+    }
+}
@@ -1,26 +1,43 @@
+use crate::cmd::command::ExecutionContext;
 use crate::cmd::docker::Docker;
 use crate::engine_task;
+use crate::engine_task::deadline::{TaskDeadline, TaskStage, DEFAULT_TASK_MAX_DURATION};
 use crate::engine_task::qovery_api::QoveryApi;
 use crate::engine_task::Task;
-use crate::environment::models::abort::{Abort, AbortStatus};
+use crate::environment::models::abort::{Abort, AbortStatus, AtomicAbortStatus};
 use crate::errors::EngineError;
 use crate::events::Stage::Infrastructure;
 use crate::events::{EngineEvent, EventDetails, EventMessage, InfrastructureStep, Transmitter};
+use crate::infrastructure::certificate_watchdog_task::watch_certificates;
+use crate::infrastructure::delete_dry_run::{publish_deletion_report, DeletedNamespaces, KubeDeletionInventory};
+use crate::infrastructure::infrastructure_context::InfrastructureContext;
+use crate::infrastructure::models::deletion_report::{DeletionReport, DeletionScope, ExpectedResources};
+use crate::infrastructure::models::kubernetes::certificate_watchdog::DEFAULT_RENEWAL_THRESHOLD_IN_DAYS;
 use crate::io_models::context::Context;
-use crate::io_models::engine_request::InfrastructureEngineRequest;
+use crate::io_models::engine_request::{DeleteDryRun, InfrastructureEngineRequest, InfrastructureRepair};
+use crate::io_models::feature_flags::FeatureFlags;
 use crate::io_models::{Action, QoveryIdentifier};
 use crate::log_file_writer::LogFileWriter;
 use crate::logger::Logger;
 use crate::metrics_registry::MetricsRegistry;
+use chrono::Utc;
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use std::{env, fs};
 use tokio::sync::broadcast;
 
+// Minimum durations, a stage is not started with less than that left of the task budget
+const INFRASTRUCTURE_STAGE: TaskStage = TaskStage::new("infrastructure", Duration::from_secs(10 * 60));
+const CLUSTER_CHECKS_STAGE: TaskStage = TaskStage::new("cluster checks", Duration::from_secs(60));
+
 pub struct InfrastructureTask {
     workspace_root_dir: String,
     lib_root_dir: String,
     docker: Arc<Docker>,
     request: InfrastructureEngineRequest,
+    cancel_requested: Arc<AtomicAbortStatus>,
+    deadline: Arc<TaskDeadline>,
     logger: Box<dyn Logger>,
     metrics_registry: Box<dyn MetricsRegistry>,
     qovery_api: Arc<dyn QoveryApi>,
@@ -58,12 +75,26 @@ impl InfrastructureTask {
             },
         );
 
+        let proxy_secrets = request
+            .proxy
+            .iter()
+            .flat_map(|proxy| proxy.secrets())
+            .collect::<Vec<_>>();
+        let max_duration = request
+            .max_duration_in_sec
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TASK_MAX_DURATION);
         InfrastructureTask {
             workspace_root_dir,
             lib_root_dir,
             docker,
             request,
-            logger,
+            cancel_requested: Arc::new(AtomicAbortStatus::new(AbortStatus::None)),
+            deadline: Arc::new(TaskDeadline::new(max_duration)),
+            logger: match proxy_secrets.is_empty() {
+                true => logger,
+                false => logger.with_secrets(proxy_secrets),
+            },
             metrics_registry,
             qovery_api: Arc::from(qovery_api),
             span,
@@ -84,17 +115,77 @@ impl InfrastructureTask {
             self.lib_root_dir.to_string(),
             self.request.test_cluster,
             self.request.features.clone(),
+            FeatureFlags::resolve(&self.request.feature_flags).0,
             self.request.metadata.clone(),
             self.docker.clone(),
             self.qovery_api.clone(),
             self.request.event_details(),
         )
+        .with_proxy(self.request.proxy.clone())
     }
 
     fn get_event_details(&self, step: InfrastructureStep) -> EventDetails {
         EventDetails::clone_changing_stage(self.request.event_details(), Infrastructure(step))
     }
 
+    // Lists what the cluster deletion would remove, without touching the infrastructure
+    fn report_delete_dry_run(&self, infra_ctx: &InfrastructureContext, dry_run: &DeleteDryRun) {
+        let event_details = self.get_event_details(InfrastructureStep::DeleteDryRun);
+        let kube_client = match infra_ctx.mk_kube_client() {
+            Ok(kube_client) => kube_client,
+            Err(err) => {
+                self.logger.log(EngineEvent::Error(*err, None));
+                return;
+            }
+        };
+
+        let kubernetes = infra_ctx.kubernetes();
+        let inventory = KubeDeletionInventory::new(
+            kube_client.client().clone(),
+            DeletedNamespaces::Cluster,
+            infra_ctx.container_registry(),
+            kubernetes.object_storage(),
+        );
+        let report = DeletionReport::assemble(
+            DeletionScope::Cluster,
+            *kubernetes.long_id(),
+            kubernetes.name().to_string(),
+            Utc::now(),
+            &inventory,
+            &ExpectedResources {
+                buckets: kubernetes.buckets_removed_on_deletion(),
+                ..Default::default()
+            },
+        );
+
+        publish_deletion_report(&report, dry_run, self.logger.as_ref(), event_details);
+    }
+
+    // Best effort, a certificate cert-manager failed to renew must not fail the cluster deployment
+    fn watch_certificates(&self, infra_ctx: &InfrastructureContext) {
+        let event_details = self.get_event_details(InfrastructureStep::WatchCertificates);
+        let result = infra_ctx
+            .mk_kube_client()
+            .map_err(|err| err.to_string())
+            .and_then(|kube_client| {
+                watch_certificates(
+                    kube_client.client(),
+                    None,
+                    DEFAULT_RENEWAL_THRESHOLD_IN_DAYS,
+                    true,
+                    self.logger.as_ref(),
+                    event_details.clone(),
+                )
+                .map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            self.logger.log(EngineEvent::Warning(
+                event_details,
+                EventMessage::new("Cannot check the certificates of the cluster".to_string(), Some(err)),
+            ));
+        }
+    }
+
     fn handle_transaction_result(&self, logger: Box<dyn Logger>, transaction_result: Result<(), Box<EngineError>>) {
         match transaction_result {
             Ok(()) => self.send_infrastructure_progress(logger.clone(), None),
@@ -144,6 +235,43 @@ impl InfrastructureTask {
             logger.log(engine_event);
         }
     }
+
+    // Best effort, images failed to be purged are purged on the next deployment
+    fn cleanup_untagged_images(&self, infra_ctx: &InfrastructureContext) {
+        let Some(untagged_image_ttl) = infra_ctx
+            .kubernetes()
+            .advanced_settings()
+            .registry_cleanup_policy()
+            .untagged_image_ttl
+        else {
+            return;
+        };
+
+        // repositories of the cluster are prefixed by its short id
+        let repository_prefix = format!("{}-", QoveryIdentifier::new(*infra_ctx.kubernetes().long_id()).short());
+        let event_details = self.get_event_details(InfrastructureStep::Create);
+        match infra_ctx
+            .container_registry()
+            .cleanup_untagged_images(&repository_prefix, untagged_image_ttl)
+        {
+            Ok(0) => {}
+            Ok(deleted_images) => self.logger.log(EngineEvent::Info(
+                event_details,
+                EventMessage::new_from_safe(format!(
+                    "🧹 Purged {deleted_images} untagged images from the container registry"
+                )),
+            )),
+            Err(err) => {
+                let err = EngineError::new_container_registry_cleanup_error(
+                    event_details.clone(),
+                    &format!("{repository_prefix}*"),
+                    err,
+                );
+                self.logger
+                    .log(EngineEvent::Warning(event_details, EventMessage::new_from_engine_error(err)));
+            }
+        }
+    }
 }
 
 impl Task for InfrastructureTask {
@@ -162,11 +290,28 @@ impl Task for InfrastructureTask {
             self.id(),
             self.request.cloud_provider.id.as_str(),
         );
+        let _execution_context = ExecutionContext::from(&self.request.event_details()).enter();
+        self.deadline.start();
 
         self.logger.log(EngineEvent::Info(
             self.get_event_details(InfrastructureStep::Start),
             EventMessage::new("Qovery Engine has started the infrastructure deployment".to_string(), None),
         ));
+        engine_task::log_feature_flags(
+            &self.request.feature_flags,
+            self.logger.as_ref(),
+            self.get_event_details(InfrastructureStep::Start),
+        );
+        engine_task::log_deprecated_fields(
+            &self.request.deprecated_fields,
+            self.logger.as_ref(),
+            self.get_event_details(InfrastructureStep::Start),
+        );
+        engine_task::install_proxy(
+            self.request.proxy.as_ref(),
+            self.logger.as_ref(),
+            self.get_event_details(InfrastructureStep::Start),
+        );
         let guard = scopeguard::guard((), |_| {
             self.logger.log(EngineEvent::Info(
                 self.get_event_details(InfrastructureStep::Terminated),
@@ -185,17 +330,56 @@ impl Task for InfrastructureTask {
             self.metrics_registry.clone(),
             true,
         ) {
-            Ok(engine) => engine,
+            Ok(engine) => engine.with_abort(Arc::from(self.cancel_checker())),
             Err(err) => {
                 self.send_infrastructure_progress(self.logger.clone(), Some(err));
                 return;
             }
         };
 
-        let ret = infra_ctx
-            .kubernetes()
-            .as_infra_actions()
-            .run(&infra_ctx, self.request.action.to_service_action());
+        if let (Action::Delete, Some(dry_run)) = (&self.request.action, &self.request.delete_dry_run) {
+            self.report_delete_dry_run(&infra_ctx, dry_run);
+            return;
+        }
+
+        let event_details = self.get_event_details(match self.request.action {
+            Action::Create => InfrastructureStep::Create,
+            Action::Pause => InfrastructureStep::Pause,
+            Action::Delete => InfrastructureStep::Delete,
+            Action::Restart => InfrastructureStep::Restart,
+        });
+        let _execution_context = ExecutionContext::enter_stage(event_details.stage());
+        let ret = self
+            .deadline
+            .start_stage(INFRASTRUCTURE_STAGE)
+            .map_err(|err| Box::new(EngineError::new_execution_deadline_exceeded(event_details.clone(), err)))
+            .and_then(|_| match (&self.request.action, &self.request.infrastructure_repair) {
+                (Action::Create, Some(InfrastructureRepair::NodeGroups)) => {
+                    infra_ctx.kubernetes().as_infra_actions().repair_node_groups(&infra_ctx)
+                }
+                _ => infra_ctx
+                    .kubernetes()
+                    .as_infra_actions()
+                    .run(&infra_ctx, self.request.action.to_service_action()),
+            });
+        // a cancellation requested by the deadline is not one requested by the user
+        let ret = match (ret, self.deadline.exceeded()) {
+            (Err(err), Some(deadline_exceeded)) if err.tag().is_cancel() => Err(Box::new(
+                EngineError::new_execution_deadline_exceeded(event_details, deadline_exceeded),
+            )),
+            (ret, _) => ret,
+        };
+        // best effort checks, skipped when the budget is exhausted
+        if self.request.action == Action::Create
+            && ret.is_ok()
+            && !infra_ctx.context().is_dry_run_deploy()
+            && self.deadline.start_stage(CLUSTER_CHECKS_STAGE).is_ok()
+        {
+            self.watch_certificates(&infra_ctx);
+            self.cleanup_untagged_images(&infra_ctx);
+        }
+        self.deadline.end_stage();
+        let succeeded = ret.is_ok();
         self.handle_transaction_result(self.logger.clone(), ret);
 
         // Uploading to S3 can take a lot of time, and might hit the core timeout
@@ -205,10 +389,11 @@ impl Task for InfrastructureTask {
 
         // only store if not running on a workstation
         if env::var("DEPLOY_FROM_FILE_KIND").is_err() {
-            match crate::fs::create_workspace_archive(
-                infra_ctx.context().workspace_root_dir(),
-                infra_ctx.context().execution_id(),
-            ) {
+            match infra_ctx
+                .context()
+                .workspace()
+                .and_then(|workspace| workspace.archive(succeeded))
+            {
                 Ok(file) => match engine_task::upload_s3_file(self.request.archive.as_ref(), &file) {
                     Ok(_) => {
                         let _ = fs::remove_file(file).map_err(|err| error!("Cannot delete file {}", err));
@@ -222,12 +407,38 @@ impl Task for InfrastructureTask {
         info!("infrastructure task {} finished", self.id());
     }
 
-    fn cancel(&self, _force_requested: bool) -> bool {
-        false
+    fn cancel(&self, force_requested: bool) -> bool {
+        if self.is_terminated() {
+            info!("Skipping cancel action as the task is already terminated.");
+            return false;
+        }
+
+        self.cancel_requested.store(
+            match force_requested {
+                true => AbortStatus::UserForceRequested,
+                false => AbortStatus::Requested,
+            },
+            Ordering::Relaxed,
+        );
+        self.logger.log(EngineEvent::Info(
+            self.get_event_details(InfrastructureStep::Cancel),
+            EventMessage::new(
+                r#"
+                    🚫 Cancel received, infrastructure deployment is going to stop.
+                    Running terraform and helm commands are interrupted, and given some time to stop gracefully before being killed
+                    "#
+                .trim()
+                .to_string(),
+                None,
+            ),
+        ));
+        true
     }
 
     fn cancel_checker(&self) -> Box<dyn Abort> {
-        Box::new(move || AbortStatus::None)
+        let cancel_requested = self.cancel_requested.clone();
+        let deadline = self.deadline.clone();
+        Box::new(move || AbortStatus::merge(cancel_requested.load(Ordering::Relaxed), deadline.abort_status()))
     }
 
     fn is_terminated(&self) -> bool {
@@ -3,39 +3,42 @@ use std::str;
 use std::sync::Arc;
 use std::time::Duration;
 
-use base64::engine::general_purpose;
-use base64::Engine;
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use url::Url;
 use uuid::Uuid;
 
+use crate::cmd::git::{GitCloneOptions, SshHostVerification};
 use crate::engine_task::qovery_api::QoveryApi;
 use crate::environment::models;
 use crate::environment::models::application::{ApplicationError, ApplicationService};
 use crate::environment::models::aws::AwsAppExtraSettings;
 use crate::environment::models::gcp::GcpAppExtraSettings;
+use crate::environment::models::graceful_shutdown::validate_lifecycle_hooks;
 use crate::environment::models::scaleway::ScwAppExtraSettings;
 use crate::environment::models::selfmanaged::OnPremiseAppExtraSettings;
+use crate::environment::models::service_port::to_service_ports;
 use crate::environment::models::types::{OnPremise, AWS, GCP, SCW};
-use crate::infrastructure::models::build_platform::{Build, GitRepository, Image, SshKey};
+use crate::infrastructure::models::build_platform::{Build, GitAccess, GitRepository, Image, SshKey};
 use crate::infrastructure::models::cloud_provider::io::{NginxConfigurationSnippet, NginxServerSnippet};
 use crate::infrastructure::models::cloud_provider::service::ServiceType;
 use crate::infrastructure::models::cloud_provider::{CloudProvider, Kind as CPKind};
 use crate::infrastructure::models::container_registry::ContainerRegistryInfo;
 use crate::io_models::annotations_group::AnnotationsGroup;
-use crate::io_models::container::{ContainerAdvancedSettings, Registry};
+use crate::io_models::container::{ContainerAdvancedSettings, HpaMetric, LifecycleHooks, Registry};
 use crate::io_models::context::Context;
 use crate::io_models::labels_group::LabelsGroup;
 use crate::io_models::models::{
     CpuArchitecture, EnvironmentVariable, KubernetesCpuResourceUnit, KubernetesMemoryResourceUnit, StorageClass,
 };
 use crate::io_models::probe::Probe;
-use crate::io_models::variable_utils::{default_environment_vars_with_info, VariableInfo};
+use crate::io_models::variable_utils::{
+    build_secrets, build_variables, default_environment_vars_with_info, runtime_variables, VariableInfo,
+};
 use crate::io_models::{
-    fetch_git_token, normalize_root_and_dockerfile_path, sanitized_git_url, ssh_keys_from_env_vars, Action,
-    MountedFile, QoveryIdentifier,
+    fetch_git_token, normalize_root_and_dockerfile_path, parse_git_url, sanitized_git_url, ssh_keys_from_env_vars,
+    Action, MountedFile, QoveryIdentifier,
 };
 use crate::utilities::to_short_id;
 
@@ -55,13 +58,24 @@ impl Protocol {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PortExposure {
+    Internal,
+    Public,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Port {
     pub long_id: Uuid,
     pub port: u16,
     pub is_default: bool,
     pub name: String,
+    // legacy shape, superseded by `expose` when set
+    #[serde(default)]
     pub publicly_accessible: bool,
+    #[serde(default)]
+    pub expose: Option<PortExposure>,
     pub protocol: Protocol,
     pub service_name: Option<String>,
     pub namespace: Option<String>,
@@ -73,8 +87,9 @@ pub struct AdditionalService {
     pub selectors: BTreeMap<String, String>,
 }
 
+/// Variables of the running service, the build time only ones are left out
 pub fn to_environment_variable(env_vars: BTreeMap<String, VariableInfo>) -> Vec<EnvironmentVariable> {
-    env_vars
+    runtime_variables(env_vars)
         .into_iter()
         .map(|(k, variable_infos)| EnvironmentVariable {
             key: k,
@@ -85,12 +100,49 @@ pub fn to_environment_variable(env_vars: BTreeMap<String, VariableInfo>) -> Vec<
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
-pub struct GitCredentials {
+#[serde(untagged)]
+pub enum GitCredentials {
+    // the token itself is fetched from the Qovery API when the repository is cloned, it may have expired since
+    AccessToken(GitAccessToken),
+    SshKey(SshKeyCredentials),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct GitAccessToken {
     pub login: String,
     pub access_token: String,
     pub expired_at: DateTime<Utc>,
 }
 
+/// Read-only deploy key, for repositories only exposed over ssh
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SshKeyCredentials {
+    pub private_key: String,
+    pub passphrase: Option<String>,
+    /// known_hosts entries of the git server, the host key is checked against the engine ones when not set
+    pub known_hosts: Option<String>,
+    /// SHA256 fingerprint the host key must have, as printed by `ssh-keygen -l`, it takes precedence over known_hosts
+    pub host_key_fingerprint: Option<String>,
+}
+
+impl SshKeyCredentials {
+    pub fn to_deploy_key(&self) -> SshKey {
+        SshKey {
+            private_key: self.private_key.clone(),
+            passphrase: self.passphrase.clone(),
+            public_key: None,
+        }
+    }
+
+    pub fn host_verification(&self) -> SshHostVerification {
+        match (&self.host_key_fingerprint, &self.known_hosts) {
+            (Some(fingerprint), _) => SshHostVerification::PinnedFingerprint(fingerprint.clone()),
+            (None, Some(known_hosts)) => SshHostVerification::KnownHosts(known_hosts.clone()),
+            (None, None) => SshHostVerification::EngineKnownHosts,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 #[serde(default)]
 pub struct ApplicationAdvancedSettings {
@@ -119,6 +171,18 @@ pub struct ApplicationAdvancedSettings {
     pub deployment_lifecycle_post_start_exec_command: Vec<String>,
     #[serde(alias = "deployment.lifecycle.pre_stop_exec_command")]
     pub deployment_lifecycle_pre_stop_exec_command: Vec<String>,
+    #[serde(alias = "deployment.resilience_check")]
+    pub deployment_resilience_check: bool,
+    #[serde(alias = "deployment.cluster_metadata.mount_path")]
+    pub deployment_cluster_metadata_mount_path: String,
+    #[serde(alias = "deployment.cluster_metadata.env_keys")]
+    pub deployment_cluster_metadata_env_keys: Vec<String>,
+
+    // Graceful shutdown
+    #[serde(alias = "graceful_shutdown.drain_seconds")]
+    pub graceful_shutdown_drain_seconds: Option<u32>,
+    #[serde(alias = "graceful_shutdown.termination_grace_seconds")]
+    pub graceful_shutdown_termination_grace_seconds: Option<u32>,
 
     // Build
     #[serde(alias = "build.timeout_max_sec")]
@@ -127,6 +191,13 @@ pub struct ApplicationAdvancedSettings {
     pub build_cpu_max_in_milli: u32,
     #[serde(alias = "build.ram_max_in_gib")]
     pub build_ram_max_in_gib: u32,
+    #[serde(alias = "build.registry_cache.enabled")]
+    pub build_registry_cache_enabled: bool,
+    // 0 fetches the whole history of the repository
+    #[serde(alias = "build.git.clone_depth")]
+    pub build_git_clone_depth: u32,
+    #[serde(alias = "build.git.lfs.enabled")]
+    pub build_git_lfs_enabled: bool,
 
     // Ingress
     #[serde(alias = "network.ingress.proxy_body_size_mb")]
@@ -188,6 +259,9 @@ pub struct ApplicationAdvancedSettings {
     pub hpa_cpu_average_utilization_percent: u8,
     #[serde(alias = "hpa.memory.average_utilization_percent")]
     pub hpa_memory_average_utilization_percent: Option<u8>,
+    /// Metrics the autoscaler scales on, the cpu and memory settings above are used when empty
+    #[serde(alias = "hpa.metrics")]
+    pub hpa_metrics: Vec<HpaMetric>,
 }
 
 impl Default for ApplicationAdvancedSettings {
@@ -204,9 +278,17 @@ impl Default for ApplicationAdvancedSettings {
             deployment_antiaffinity_pod: PodAntiAffinity::Preferred,
             deployment_lifecycle_post_start_exec_command: vec![],
             deployment_lifecycle_pre_stop_exec_command: vec![],
+            deployment_resilience_check: false,
+            deployment_cluster_metadata_mount_path: "".to_string(),
+            deployment_cluster_metadata_env_keys: vec![],
+            graceful_shutdown_drain_seconds: None,
+            graceful_shutdown_termination_grace_seconds: None,
             build_timeout_max_sec: 30 * 60,
             build_cpu_max_in_milli: 4000,
             build_ram_max_in_gib: 8,
+            build_registry_cache_enabled: true,
+            build_git_clone_depth: 1,
+            build_git_lfs_enabled: true,
             network_ingress_proxy_body_size_mb: 100,
             network_ingress_cors_enable: false,
             network_ingress_sticky_session_enable: false,
@@ -235,11 +317,24 @@ impl Default for ApplicationAdvancedSettings {
             network_ingress_nginx_limit_burst_multiplier: None,
             hpa_cpu_average_utilization_percent: 60,
             hpa_memory_average_utilization_percent: None,
+            hpa_metrics: vec![],
         }
     }
 }
 
 impl ApplicationAdvancedSettings {
+    /// Lifecycle hooks set on the service take precedence over the equivalent advanced settings
+    pub fn with_lifecycle_hooks(mut self, lifecycle_hooks: &LifecycleHooks) -> Self {
+        if let Some(pre_stop_exec_command) = &lifecycle_hooks.pre_stop_exec_command {
+            self.deployment_lifecycle_pre_stop_exec_command = pre_stop_exec_command.clone();
+        }
+        if let Some(termination_grace_period_seconds) = lifecycle_hooks.termination_grace_period_seconds {
+            self.graceful_shutdown_termination_grace_seconds = Some(termination_grace_period_seconds);
+        }
+
+        self
+    }
+
     pub fn to_container_advanced_settings(&self) -> ContainerAdvancedSettings {
         ContainerAdvancedSettings {
             security_service_account_name: self.security_service_account_name.clone(),
@@ -255,6 +350,11 @@ impl ApplicationAdvancedSettings {
             deployment_antiaffinity_pod: self.deployment_antiaffinity_pod.clone(),
             deployment_lifecycle_post_start_exec_command: self.deployment_lifecycle_post_start_exec_command.clone(),
             deployment_lifecycle_pre_stop_exec_command: self.deployment_lifecycle_pre_stop_exec_command.clone(),
+            deployment_resilience_check: self.deployment_resilience_check,
+            deployment_cluster_metadata_mount_path: self.deployment_cluster_metadata_mount_path.clone(),
+            deployment_cluster_metadata_env_keys: self.deployment_cluster_metadata_env_keys.clone(),
+            graceful_shutdown_drain_seconds: self.graceful_shutdown_drain_seconds,
+            graceful_shutdown_termination_grace_seconds: self.graceful_shutdown_termination_grace_seconds,
             network_ingress_proxy_body_size_mb: self.network_ingress_proxy_body_size_mb,
             network_ingress_cors_enable: self.network_ingress_cors_enable,
             network_ingress_sticky_session_enable: self.network_ingress_sticky_session_enable,
@@ -287,6 +387,7 @@ impl ApplicationAdvancedSettings {
                 .clone(),
             hpa_cpu_average_utilization_percent: self.hpa_cpu_average_utilization_percent,
             hpa_memory_average_utilization_percent: self.hpa_memory_average_utilization_percent,
+            hpa_metrics: self.hpa_metrics.clone(),
         }
     }
 }
@@ -334,6 +435,20 @@ pub struct Application {
     pub should_delete_shared_registry: bool,
     #[serde(default)] // Default is false
     pub shared_image_feature_enabled: bool,
+    #[serde(default)]
+    pub git_access: GitAccess,
+    #[serde(default)]
+    pub build_secrets: Vec<BuildSecret>,
+    #[serde(flatten)]
+    pub lifecycle_hooks: LifecycleHooks,
+}
+
+/// Variable given to the docker build as a secret (`--secret id=<id>`) instead of a build arg,
+/// so its value never ends up in the image layers or history
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct BuildSecret {
+    pub id: String,
+    pub env_var_name: String,
 }
 
 fn default_root_path_value() -> String {
@@ -364,6 +479,18 @@ impl Application {
             .cloned()
             .collect_vec();
 
+        validate_lifecycle_hooks(&self.lifecycle_hooks)
+            .map_err(|err| ApplicationError::InvalidConfig(err.to_string()))?;
+        let advanced_settings = self.advanced_settings.with_lifecycle_hooks(&self.lifecycle_hooks);
+
+        let readiness_probe = self.readiness_probe.map(|p| p.to_domain());
+        let liveness_probe = self.liveness_probe.map(|p| p.to_domain());
+        let ports = to_service_ports(
+            &self.long_id,
+            ports,
+            &readiness_probe.iter().chain(liveness_probe.iter()).collect_vec(),
+        );
+
         match cloud_provider.kind() {
             CPKind::Aws => {
                 // Note: we check if kubernetes is EC2 to map to the proper implementation
@@ -376,7 +503,7 @@ impl Application {
                     self.name.as_str(),
                     self.kube_name,
                     self.public_domain,
-                    self.ports,
+                    ports,
                     self.min_instances,
                     self.max_instances,
                     build,
@@ -388,9 +515,9 @@ impl Application {
                         .iter()
                         .map(|e| e.to_domain())
                         .collect::<BTreeSet<_>>(),
-                    self.readiness_probe.map(|p| p.to_domain()),
-                    self.liveness_probe.map(|p| p.to_domain()),
-                    self.advanced_settings,
+                    readiness_probe,
+                    liveness_probe,
+                    advanced_settings,
                     AwsAppExtraSettings {},
                     |transmitter| context.get_event_details(transmitter),
                     annotations_groups,
@@ -409,7 +536,7 @@ impl Application {
                 self.name.as_str(),
                 self.kube_name,
                 self.public_domain,
-                self.ports,
+                ports,
                 self.min_instances,
                 self.max_instances,
                 build,
@@ -421,9 +548,9 @@ impl Application {
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.readiness_probe.map(|p| p.to_domain()),
-                self.liveness_probe.map(|p| p.to_domain()),
-                self.advanced_settings,
+                readiness_probe,
+                liveness_probe,
+                advanced_settings,
                 ScwAppExtraSettings {},
                 |transmitter| context.get_event_details(transmitter),
                 annotations_groups,
@@ -441,7 +568,7 @@ impl Application {
                 self.name.as_str(),
                 self.kube_name,
                 self.public_domain,
-                self.ports,
+                ports,
                 self.min_instances,
                 self.max_instances,
                 build,
@@ -453,9 +580,9 @@ impl Application {
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.readiness_probe.map(|p| p.to_domain()),
-                self.liveness_probe.map(|p| p.to_domain()),
-                self.advanced_settings,
+                readiness_probe,
+                liveness_probe,
+                advanced_settings,
                 GcpAppExtraSettings {},
                 |transmitter| context.get_event_details(transmitter),
                 annotations_groups,
@@ -473,7 +600,7 @@ impl Application {
                 self.name.as_str(),
                 self.kube_name,
                 self.public_domain,
-                self.ports,
+                ports,
                 self.min_instances,
                 self.max_instances,
                 build,
@@ -485,9 +612,9 @@ impl Application {
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.readiness_probe.map(|p| p.to_domain()),
-                self.liveness_probe.map(|p| p.to_domain()),
-                self.advanced_settings,
+                readiness_probe,
+                liveness_probe,
+                advanced_settings,
                 OnPremiseAppExtraSettings {},
                 |transmitter| context.get_event_details(transmitter),
                 annotations_groups,
@@ -537,50 +664,52 @@ impl Application {
         let (root_path, dockerfile_path) = normalize_root_and_dockerfile_path(&self.root_path, &self.dockerfile_path);
 
         //FIXME: Return a result the function
-        let url = Url::parse(&self.git_url).unwrap_or_else(|_| Url::parse("https://invalid-git-url.com").unwrap());
+        let url = parse_git_url(&self.git_url).unwrap_or_else(|| Url::parse("https://invalid-git-url.com").unwrap());
+        let ssh_key_credentials = match &self.git_credentials {
+            Some(GitCredentials::SshKey(ssh_key_credentials)) => Some(ssh_key_credentials),
+            _ => None,
+        };
 
-        let mut disable_build_cache = false;
+        let (mut environment_variables, disable_build_cache) = build_variables(&self.environment_vars_with_infos);
+        // variables used as secrets must not leak as build args
+        for secret in &self.build_secrets {
+            environment_variables.remove(&secret.env_var_name);
+        }
         let mut build = Build {
             git_repository: GitRepository {
                 url,
-                get_credentials: if self.git_credentials.is_none() {
-                    None
-                } else {
+                get_credentials: if let Some(GitCredentials::AccessToken(_)) = self.git_credentials {
                     let id = self.long_id;
                     Some(Box::new(move || fetch_git_token(&*qovery_api, ServiceType::Application, &id)))
+                } else {
+                    None
                 },
+                deploy_key: ssh_key_credentials.map(|credentials| credentials.to_deploy_key()),
                 ssh_keys,
                 commit_id: self.commit_id.clone(),
                 dockerfile_path,
                 dockerfile_content: None,
                 root_path,
+                git_access: self.git_access,
+                source_fetcher: None,
+                clone_options: GitCloneOptions {
+                    depth: Some(self.advanced_settings.build_git_clone_depth),
+                    lfs: self.advanced_settings.build_git_lfs_enabled,
+                    ssh_host_verification: ssh_key_credentials
+                        .map(|credentials| credentials.host_verification())
+                        .unwrap_or_default(),
+                },
             },
             image: self.to_image(registry_url, cluster_id),
-            environment_variables: self
-                .environment_vars_with_infos
-                .iter()
-                .filter_map(|(k, variable_infos)| {
-                    // Remove special vars
-                    let v = String::from_utf8(
-                        general_purpose::STANDARD
-                            .decode(variable_infos.value.as_bytes())
-                            .unwrap_or_default(),
-                    )
-                    .unwrap_or_default();
-                    if k == "QOVERY_DISABLE_BUILD_CACHE" && v.to_lowercase() == "true" {
-                        disable_build_cache = true;
-                        return None;
-                    }
-
-                    Some((k.clone(), v))
-                })
-                .collect::<BTreeMap<_, _>>(),
-            disable_cache: disable_build_cache,
+            environment_variables,
+            secrets: build_secrets(&self.build_secrets, &self.environment_vars_with_infos),
+            disable_cache: disable_build_cache || !self.advanced_settings.build_registry_cache_enabled,
             timeout: Duration::from_secs(self.advanced_settings.build_timeout_max_sec as u64),
             architectures,
             max_cpu_in_milli: self.advanced_settings.build_cpu_max_in_milli,
             max_ram_in_gib: self.advanced_settings.build_ram_max_in_gib,
             registries: self.container_registries.clone(),
+            registry_mirrors: vec![],
         };
 
         build.compute_image_tag();
@@ -0,0 +1,27 @@
+use serde_derive::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Object storage bucket of an environment, created in the account of the cluster cloud provider.
+/// Credentials scoped to the bucket are injected into the dependent services as environment variables.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Bucket {
+    pub long_id: Uuid,
+    /// Part of the bucket name chosen by the user, also used to name the injected variables
+    pub name_suffix: String,
+    #[serde(default)]
+    pub versioning_activated: bool,
+    #[serde(default = "default_block_public_access")]
+    pub block_public_access: bool,
+    /// Objects are deleted this number of days after their creation
+    #[serde(default)]
+    pub objects_expiration_in_days: Option<u32>,
+    /// The bucket and its objects are kept when the environment is deleted, only its credentials are deleted
+    #[serde(default)]
+    pub retain_on_delete: bool,
+    #[serde(default)]
+    pub dependent_service_long_ids: Vec<Uuid>,
+}
+
+fn default_block_public_access() -> bool {
+    true
+}
@@ -0,0 +1,21 @@
+use crate::infrastructure::models::kubernetes::certificate_watchdog::DEFAULT_RENEWAL_THRESHOLD_IN_DAYS;
+use serde::{Deserialize, Serialize};
+
+/// Payload of a `certificate_watchdog` task, checking the Qovery managed certificates of a cluster between deployments
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CertificateWatchdogRequest {
+    /// Certificates expiring in less than this and not renewed yet are re-issued
+    #[serde(default = "default_renewal_threshold_in_days")]
+    pub renewal_threshold_in_days: u32,
+    /// When false, certificates are only reported
+    #[serde(default = "default_reissue")]
+    pub reissue: bool,
+}
+
+fn default_renewal_threshold_in_days() -> u32 {
+    DEFAULT_RENEWAL_THRESHOLD_IN_DAYS
+}
+
+fn default_reissue() -> bool {
+    true
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Payload of a `cluster_health_report` task, scheduled by the core for every self-managed cluster
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClusterHealthReportRequest {
+    /// Qovery charts that must be installed on the cluster, with the version of the current release
+    pub expected_charts: Vec<ExpectedChart>,
+    /// Number of unhealthy reports in a row before this one, as stored by the core
+    #[serde(default)]
+    pub previous_consecutive_failures: u32,
+    /// Certificates expiring in less than this are reported
+    #[serde(default = "default_certificate_expiry_warning_in_days")]
+    pub certificate_expiry_warning_in_days: u32,
+    /// Pre-signed url where the health document is uploaded, valid for a single object
+    pub upload_url: Url,
+}
+
+fn default_certificate_expiry_warning_in_days() -> u32 {
+    14
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ExpectedChart {
+    pub name: String,
+    pub namespace: String,
+    pub version: String,
+}
@@ -3,9 +3,11 @@ use crate::environment::models;
 use crate::environment::models::aws::AwsAppExtraSettings;
 use crate::environment::models::container::{ContainerError, ContainerService};
 use crate::environment::models::gcp::GcpAppExtraSettings;
+use crate::environment::models::graceful_shutdown::validate_lifecycle_hooks;
 use crate::environment::models::registry_image_source::RegistryImageSource;
 use crate::environment::models::scaleway::ScwAppExtraSettings;
 use crate::environment::models::selfmanaged::OnPremiseAppExtraSettings;
+use crate::environment::models::service_port::to_service_ports;
 use crate::environment::models::types::{OnPremise, AWS, GCP, SCW};
 use crate::infrastructure::models::cloud_provider::io::{NginxConfigurationSnippet, NginxServerSnippet};
 use crate::infrastructure::models::cloud_provider::{CloudProvider, Kind as CPKind};
@@ -19,7 +21,7 @@ use crate::io_models::context::Context;
 use crate::io_models::labels_group::LabelsGroup;
 use crate::io_models::models::{KubernetesCpuResourceUnit, KubernetesMemoryResourceUnit};
 use crate::io_models::probe::Probe;
-use crate::io_models::variable_utils::{default_environment_vars_with_info, VariableInfo};
+use crate::io_models::variable_utils::{default_environment_vars_with_info, ClusterDefaultVariables, VariableInfo};
 use crate::io_models::{Action, MountedFile};
 use itertools::Itertools;
 use rusoto_core::{Client, HttpClient, Region};
@@ -233,6 +235,18 @@ pub struct ContainerAdvancedSettings {
     pub deployment_lifecycle_post_start_exec_command: Vec<String>,
     #[serde(alias = "deployment.lifecycle.pre_stop_exec_command")]
     pub deployment_lifecycle_pre_stop_exec_command: Vec<String>,
+    #[serde(alias = "deployment.resilience_check")]
+    pub deployment_resilience_check: bool,
+    #[serde(alias = "deployment.cluster_metadata.mount_path")]
+    pub deployment_cluster_metadata_mount_path: String,
+    #[serde(alias = "deployment.cluster_metadata.env_keys")]
+    pub deployment_cluster_metadata_env_keys: Vec<String>,
+
+    // Graceful shutdown
+    #[serde(alias = "graceful_shutdown.drain_seconds")]
+    pub graceful_shutdown_drain_seconds: Option<u32>,
+    #[serde(alias = "graceful_shutdown.termination_grace_seconds")]
+    pub graceful_shutdown_termination_grace_seconds: Option<u32>,
 
     // Ingress
     #[serde(alias = "network.ingress.proxy_body_size_mb")]
@@ -294,6 +308,39 @@ pub struct ContainerAdvancedSettings {
     pub hpa_cpu_average_utilization_percent: u8,
     #[serde(alias = "hpa.memory.average_utilization_percent")]
     pub hpa_memory_average_utilization_percent: Option<u8>,
+    /// Metrics the autoscaler scales on, the cpu and memory settings above are used when empty
+    #[serde(alias = "hpa.metrics")]
+    pub hpa_metrics: Vec<HpaMetric>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HpaResourceName {
+    Cpu,
+    Memory,
+}
+
+/// Metric of an `autoscaling/v2` HorizontalPodAutoscaler, targets are Kubernetes quantities
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HpaMetric {
+    /// Average utilization of the requests of the pods
+    Resource {
+        name: HpaResourceName,
+        target_average_utilization_percent: u32,
+    },
+    /// Metric exposed per pod through the custom metrics API, e.g. requests per second from the ingress
+    Pods {
+        metric_name: String,
+        target_average_value: String,
+    },
+    /// Metric not related to any Kubernetes object through the external metrics API, e.g. a queue depth
+    External {
+        metric_name: String,
+        #[serde(default)]
+        selector: BTreeMap<String, String>,
+        target_average_value: String,
+    },
 }
 
 impl Default for ContainerAdvancedSettings {
@@ -310,6 +357,11 @@ impl Default for ContainerAdvancedSettings {
             deployment_antiaffinity_pod: PodAntiAffinity::Preferred,
             deployment_lifecycle_post_start_exec_command: vec![],
             deployment_lifecycle_pre_stop_exec_command: vec![],
+            deployment_resilience_check: false,
+            deployment_cluster_metadata_mount_path: "".to_string(),
+            deployment_cluster_metadata_env_keys: vec![],
+            graceful_shutdown_drain_seconds: None,
+            graceful_shutdown_termination_grace_seconds: None,
             network_ingress_proxy_body_size_mb: 100,
             network_ingress_cors_enable: false,
             network_ingress_sticky_session_enable: false,
@@ -338,10 +390,50 @@ impl Default for ContainerAdvancedSettings {
             network_ingress_nginx_controller_configuration_snippet: None,
             hpa_cpu_average_utilization_percent: 60,
             hpa_memory_average_utilization_percent: None,
+            hpa_metrics: vec![],
+        }
+    }
+}
+
+impl ContainerAdvancedSettings {
+    /// Lifecycle hooks set on the service take precedence over the equivalent advanced settings
+    pub fn with_lifecycle_hooks(mut self, lifecycle_hooks: &LifecycleHooks) -> Self {
+        if let Some(pre_stop_exec_command) = &lifecycle_hooks.pre_stop_exec_command {
+            self.deployment_lifecycle_pre_stop_exec_command = pre_stop_exec_command.clone();
         }
+        if let Some(termination_grace_period_seconds) = lifecycle_hooks.termination_grace_period_seconds {
+            self.graceful_shutdown_termination_grace_seconds = Some(termination_grace_period_seconds);
+        }
+
+        self
     }
 }
 
+/// Pod lifecycle of a service, the fields are set at the root of the service payload
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[serde(default)]
+pub struct LifecycleHooks {
+    /// Command run in the container before it receives SIGTERM
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_stop_exec_command: Option<Vec<String>>,
+    /// Time given to the pod to stop, preStop hook included, before it is killed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub termination_grace_period_seconds: Option<u32>,
+}
+
+/// Deploy the exact image running in another environment, instead of the one referenced by the tag
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ImagePromotion {
+    pub source_service_id: Uuid,
+    pub source_execution_id: String,
+    /// Set when the source environment runs on the same cluster, to fallback on the live pods to get the digest
+    #[serde(default)]
+    pub source_namespace: Option<String>,
+    /// Digest recorded in the deployment record of the source service
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 pub struct Container {
     pub long_id: Uuid,
@@ -376,6 +468,10 @@ pub struct Container {
     pub annotations_group_ids: BTreeSet<Uuid>,
     #[serde(default)]
     pub labels_group_ids: BTreeSet<Uuid>,
+    #[serde(default)]
+    pub promote_image: Option<ImagePromotion>,
+    #[serde(flatten)]
+    pub lifecycle_hooks: LifecycleHooks,
 }
 
 impl Container {
@@ -388,6 +484,8 @@ impl Container {
         annotations_group: &BTreeMap<Uuid, AnnotationsGroup>,
         labels_group: &BTreeMap<Uuid, LabelsGroup>,
     ) -> Result<Box<dyn ContainerService>, ContainerError> {
+        ClusterDefaultVariables::from(cluster.advanced_settings())
+            .inject(&self.long_id, &mut self.environment_vars_with_infos);
         let environment_variables = to_environment_variable(self.environment_vars_with_infos);
 
         // Default registry is a bit special as the core does not knows its url/credentials as it is retrieved
@@ -402,6 +500,14 @@ impl Container {
             image: self.image,
             tag: self.tag,
             registry_mirroring_mode: cluster.advanced_settings().registry_mirroring_mode.clone(),
+            promotion: self.promote_image.map(|p| {
+                models::registry_image_source::ImagePromotion::new(
+                    p.source_service_id,
+                    p.source_namespace,
+                    p.source_execution_id,
+                    p.digest,
+                )
+            }),
         };
         let annotations_groups = self
             .annotations_group_ids
@@ -416,6 +522,18 @@ impl Container {
             .cloned()
             .collect_vec();
 
+        validate_lifecycle_hooks(&self.lifecycle_hooks)
+            .map_err(|err| ContainerError::InvalidConfig(err.to_string()))?;
+        let advanced_settings = self.advanced_settings.with_lifecycle_hooks(&self.lifecycle_hooks);
+
+        let readiness_probe = self.readiness_probe.map(|p| p.to_domain());
+        let liveness_probe = self.liveness_probe.map(|p| p.to_domain());
+        let ports = to_service_ports(
+            &self.long_id,
+            ports,
+            &readiness_probe.iter().chain(liveness_probe.iter()).collect_vec(),
+        );
+
         let service: Box<dyn ContainerService> = match cloud_provider.kind() {
             CPKind::Aws => Box::new(models::container::Container::<AWS>::new(
                 context,
@@ -433,16 +551,16 @@ impl Container {
                 self.min_instances,
                 self.max_instances,
                 self.public_domain,
-                self.ports,
+                ports,
                 self.storages.iter().map(|s| s.to_storage()).collect::<Vec<_>>(),
                 environment_variables,
                 self.mounted_files
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.readiness_probe.map(|p| p.to_domain()),
-                self.liveness_probe.map(|p| p.to_domain()),
-                self.advanced_settings,
+                readiness_probe,
+                liveness_probe,
+                advanced_settings,
                 AwsAppExtraSettings {},
                 |transmitter| context.get_event_details(transmitter),
                 annotations_groups,
@@ -464,16 +582,16 @@ impl Container {
                 self.min_instances,
                 self.max_instances,
                 self.public_domain,
-                self.ports,
+                ports,
                 self.storages.iter().map(|s| s.to_storage()).collect::<Vec<_>>(),
                 environment_variables,
                 self.mounted_files
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.readiness_probe.map(|p| p.to_domain()),
-                self.liveness_probe.map(|p| p.to_domain()),
-                self.advanced_settings,
+                readiness_probe,
+                liveness_probe,
+                advanced_settings,
                 ScwAppExtraSettings {},
                 |transmitter| context.get_event_details(transmitter),
                 annotations_groups,
@@ -495,16 +613,16 @@ impl Container {
                 self.min_instances,
                 self.max_instances,
                 self.public_domain,
-                self.ports,
+                ports,
                 self.storages.iter().map(|s| s.to_storage()).collect::<Vec<_>>(),
                 environment_variables,
                 self.mounted_files
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.readiness_probe.map(|p| p.to_domain()),
-                self.liveness_probe.map(|p| p.to_domain()),
-                self.advanced_settings,
+                readiness_probe,
+                liveness_probe,
+                advanced_settings,
                 GcpAppExtraSettings {},
                 |transmitter| context.get_event_details(transmitter),
                 annotations_groups,
@@ -526,16 +644,16 @@ impl Container {
                 self.min_instances,
                 self.max_instances,
                 self.public_domain,
-                self.ports,
+                ports,
                 self.storages.iter().map(|s| s.to_storage()).collect::<Vec<_>>(),
                 environment_variables,
                 self.mounted_files
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.readiness_probe.map(|p| p.to_domain()),
-                self.liveness_probe.map(|p| p.to_domain()),
-                self.advanced_settings,
+                readiness_probe,
+                liveness_probe,
+                advanced_settings,
                 OnPremiseAppExtraSettings {},
                 |transmitter| context.get_event_details(transmitter),
                 annotations_groups,
@@ -546,3 +664,91 @@ impl Container {
         Ok(service)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn container(lifecycle_hooks: LifecycleHooks) -> Container {
+        Container {
+            long_id: Uuid::new_v4(),
+            name: "my-container".to_string(),
+            kube_name: "my-container".to_string(),
+            action: Action::Create,
+            registry: Registry::DockerHub {
+                long_id: Uuid::new_v4(),
+                url: Url::parse("https://docker.io").unwrap(),
+                credentials: None,
+            },
+            image: "nginx".to_string(),
+            tag: "latest".to_string(),
+            command_args: vec![],
+            entrypoint: None,
+            cpu_request_in_milli: 250,
+            cpu_limit_in_milli: 250,
+            ram_request_in_mib: 256,
+            ram_limit_in_mib: 256,
+            min_instances: 1,
+            max_instances: 1,
+            public_domain: "".to_string(),
+            ports: vec![],
+            storages: vec![],
+            environment_vars_with_infos: BTreeMap::new(),
+            mounted_files: vec![],
+            readiness_probe: None,
+            liveness_probe: None,
+            advanced_settings: ContainerAdvancedSettings::default(),
+            annotations_group_ids: BTreeSet::new(),
+            labels_group_ids: BTreeSet::new(),
+            promote_image: None,
+            lifecycle_hooks,
+        }
+    }
+
+    #[test]
+    fn test_lifecycle_hooks_serde_round_trip() {
+        let container = container(LifecycleHooks {
+            pre_stop_exec_command: Some(vec!["/bin/sh".to_string(), "-c".to_string(), "nginx -s quit".to_string()]),
+            termination_grace_period_seconds: Some(120),
+        });
+
+        let payload = serde_json::to_value(&container).unwrap();
+        assert_eq!(payload["pre_stop_exec_command"], json!(["/bin/sh", "-c", "nginx -s quit"]));
+        assert_eq!(payload["termination_grace_period_seconds"], json!(120));
+        assert!(serde_json::from_value::<Container>(payload).unwrap() == container);
+
+        // payloads sent before the hooks existed
+        let container = container(LifecycleHooks::default());
+        let payload = serde_json::to_value(&container).unwrap();
+        assert!(payload.get("pre_stop_exec_command").is_none());
+        assert!(payload.get("termination_grace_period_seconds").is_none());
+        assert!(serde_json::from_value::<Container>(payload).unwrap() == container);
+    }
+
+    #[test]
+    fn test_lifecycle_hooks_override_advanced_settings() {
+        let advanced_settings = ContainerAdvancedSettings {
+            deployment_lifecycle_pre_stop_exec_command: vec!["/bin/drain.sh".to_string()],
+            graceful_shutdown_termination_grace_seconds: Some(30),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            advanced_settings
+                .clone()
+                .with_lifecycle_hooks(&LifecycleHooks::default()),
+            advanced_settings
+        );
+
+        let advanced_settings = advanced_settings.with_lifecycle_hooks(&LifecycleHooks {
+            pre_stop_exec_command: Some(vec!["nginx".to_string(), "-s".to_string(), "quit".to_string()]),
+            termination_grace_period_seconds: Some(90),
+        });
+        assert_eq!(
+            advanced_settings.deployment_lifecycle_pre_stop_exec_command,
+            vec!["nginx".to_string(), "-s".to_string(), "quit".to_string()]
+        );
+        assert_eq!(advanced_settings.graceful_shutdown_termination_grace_seconds, Some(90));
+    }
+}
@@ -1,6 +1,9 @@
 use crate::cmd::docker::Docker;
 use crate::engine_task::qovery_api::QoveryApi;
 use crate::events::{EventDetails, Transmitter};
+use crate::fs::{Workspace, WorkspaceCleanupPolicy};
+use crate::io_models::feature_flags::{FeatureFlag, FeatureFlags};
+use crate::proxy::ProxyConfig;
 use crate::utilities::to_short_id;
 use rand::distributions::Alphanumeric;
 use rand::Rng;
@@ -19,7 +22,9 @@ pub struct Context {
     lib_root_dir: String,
     test_cluster: bool,
     features: Vec<Features>,
+    feature_flags: FeatureFlags,
     metadata: Option<Metadata>,
+    proxy: Option<ProxyConfig>,
     pub docker: Arc<Docker>,
     pub qovery_api: Arc<dyn QoveryApi>,
     event_details: EventDetails,
@@ -34,6 +39,7 @@ impl Context {
         lib_root_dir: String,
         test_cluster: bool,
         features: Vec<Features>,
+        feature_flags: FeatureFlags,
         metadata: Option<Metadata>,
         docker: Arc<Docker>,
         qovery_api: Arc<dyn QoveryApi>,
@@ -49,7 +55,9 @@ impl Context {
             lib_root_dir,
             test_cluster,
             features,
+            feature_flags,
             metadata,
+            proxy: None,
             docker,
             qovery_api,
             event_details,
@@ -99,6 +107,15 @@ impl Context {
         self.metadata.as_ref()
     }
 
+    pub fn with_proxy(mut self, proxy: Option<ProxyConfig>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    pub fn proxy(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
     pub fn is_dry_run_deploy(&self) -> bool {
         match &self.metadata {
             Some(meta) => matches!(meta.dry_run_deploy, Some(true)),
@@ -113,6 +130,14 @@ impl Context {
         }
     }
 
+    /// Whether the safety checks refusing a deletion must be bypassed
+    pub fn is_forced(&self) -> bool {
+        match &self.metadata {
+            Some(meta) => matches!(meta.force, Some(true)),
+            _ => false,
+        }
+    }
+
     pub fn is_test_cluster(&self) -> bool {
         self.test_cluster
     }
@@ -147,6 +172,26 @@ impl Context {
         false
     }
 
+    pub fn is_enabled(&self, flag: FeatureFlag) -> bool {
+        self.feature_flags.is_enabled(flag)
+    }
+
+    pub fn feature_flags(&self) -> &FeatureFlags {
+        &self.feature_flags
+    }
+
+    /// Workspace of the execution, stages must get their paths from it
+    pub fn workspace(&self) -> Result<Workspace, std::io::Error> {
+        Ok(
+            Workspace::new(&self.workspace_root_dir, &self.execution_id)?.with_cleanup_policy(
+                match self.is_enabled(FeatureFlag::KeepWorkspaceOnFailure) {
+                    true => WorkspaceCleanupPolicy::KeepOnFailure,
+                    false => WorkspaceCleanupPolicy::Always,
+                },
+            ),
+        )
+    }
+
     pub fn get_event_details(&self, transmitter: Transmitter) -> EventDetails {
         EventDetails::clone_changing_transmitter(self.event_details.clone(), transmitter)
     }
@@ -160,6 +205,8 @@ pub struct Metadata {
     pub forced_upgrade: Option<bool>,
     pub resource_expiration_in_seconds: Option<u32>,
     pub is_first_cluster_deployment: Option<bool>,
+    /// Bypasses the safety checks refusing a deletion, i.e: a service still receiving traffic
+    pub force: Option<bool>,
 }
 
 impl Metadata {
@@ -168,12 +215,14 @@ impl Metadata {
         resource_expiration_in_seconds: Option<u32>,
         forced_upgrade: Option<bool>,
         is_first_cluster_deployment: Option<bool>,
+        force: Option<bool>,
     ) -> Self {
         Metadata {
             dry_run_deploy,
             resource_expiration_in_seconds,
             forced_upgrade,
             is_first_cluster_deployment,
+            force,
         }
     }
     pub fn update_is_first_cluster_deployment(&mut self, is_first_cluster_deployment: bool) {
@@ -224,6 +273,7 @@ mod tests {
         assert_eq!(None, result.resource_expiration_in_seconds);
         assert_eq!(None, result.forced_upgrade);
         assert_eq!(None, result.dry_run_deploy);
+        assert_eq!(None, result.force);
     }
 
     #[test]
@@ -2,6 +2,8 @@ use crate::environment::models;
 use crate::environment::models::database::{
     Container, DatabaseError, DatabaseInstanceType, DatabaseService, Managed, MongoDB, MySQL, PostgresSQL, Redis,
 };
+use crate::environment::models::database_maintenance::DatabaseMaintenance;
+use crate::environment::models::database_parameters::{DatabaseParameters, DatabaseParametersTarget};
 use crate::environment::models::types::{CloudProvider as CloudProviderTrait, GCP};
 use crate::environment::models::types::{OnPremise, VersionsNumber, AWS, SCW};
 use crate::infrastructure::models::cloud_provider::aws::database_instance_type::AwsDatabaseInstanceType;
@@ -28,6 +30,27 @@ pub enum DatabaseMode {
     CONTAINER,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MaintenanceDay {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+/// Weekly window during which the cloud provider is allowed to restart a managed database for maintenance
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct MaintenanceWindow {
+    pub day: MaintenanceDay,
+    /// HH:MM, in UTC
+    pub start_time: String,
+    pub duration_in_minutes: u32,
+}
+
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 pub struct Database {
     pub kind: DatabaseKind,
@@ -54,12 +77,28 @@ pub struct Database {
     pub activate_high_availability: bool,
     #[serde(default)] // => false if not present in input
     pub activate_backups: bool,
+    #[serde(default)] // => false if not present in input
+    pub activate_health_snapshot: bool,
+    #[serde(default)] // => false if not present in input
+    pub override_health_checks: bool,
+    /// Generates a new password on this deployment, the services using the database get it from the core afterward
+    #[serde(default)] // => false if not present in input
+    pub rotate_credentials: bool,
     pub publicly_accessible: bool,
     pub mode: DatabaseMode,
     #[serde(default)]
     pub annotations_group_ids: BTreeSet<Uuid>,
     #[serde(default)]
     pub labels_group_ids: BTreeSet<Uuid>,
+    /// Custom engine configuration, validated against a per engine allowlist
+    #[serde(default)]
+    pub parameters: BTreeMap<String, String>,
+    /// Only for managed databases, the cloud provider default is kept if not set
+    #[serde(default)]
+    pub maintenance_window: Option<MaintenanceWindow>,
+    /// Only for managed databases, the cloud provider default is kept if not set
+    #[serde(default)]
+    pub backup_retention_days: Option<u32>,
 }
 
 impl Database {
@@ -70,6 +109,16 @@ impl Database {
         annotations_group: &BTreeMap<Uuid, AnnotationsGroup>,
         labels_group: &BTreeMap<Uuid, LabelsGroup>,
     ) -> Result<Box<dyn DatabaseService>, DatabaseError> {
+        let parameters_target = match self.mode {
+            DatabaseMode::MANAGED => DatabaseParametersTarget::Managed(cloud_provider.kind()),
+            DatabaseMode::CONTAINER => DatabaseParametersTarget::Container,
+        };
+        let parameters = DatabaseParameters::new(self.kind.to_database_type(), &parameters_target, &self.parameters)
+            .map_err(|e| DatabaseError::InvalidConfig(e.to_string()))?;
+        let maintenance =
+            DatabaseMaintenance::new(&parameters_target, self.maintenance_window.as_ref(), self.backup_retention_days)
+                .map_err(|e| DatabaseError::InvalidConfig(e.to_string()))?;
+
         let database_options = DatabaseOptions {
             mode: self.mode.clone(),
             login: self.username.clone(),
@@ -81,7 +130,12 @@ impl Database {
             encrypt_disk: self.encrypt_disk,
             activate_high_availability: self.activate_high_availability,
             activate_backups: self.activate_backups,
+            activate_health_snapshot: self.activate_health_snapshot,
+            override_health_checks: self.override_health_checks,
+            rotate_credentials: self.rotate_credentials,
             publicly_accessible: self.publicly_accessible,
+            parameters,
+            maintenance,
         };
 
         let annotations_groups = self
@@ -799,6 +853,15 @@ impl DatabaseKind {
             DatabaseKind::Redis => "redis",
         }
     }
+
+    pub fn to_database_type(&self) -> service::DatabaseType {
+        match self {
+            DatabaseKind::Mongodb => service::DatabaseType::MongoDB,
+            DatabaseKind::Mysql => service::DatabaseType::MySQL,
+            DatabaseKind::Postgresql => service::DatabaseType::PostgreSQL,
+            DatabaseKind::Redis => service::DatabaseType::Redis,
+        }
+    }
 }
 
 #[derive(Eq, PartialEq)]
@@ -813,5 +876,10 @@ pub struct DatabaseOptions {
     pub encrypt_disk: bool,
     pub activate_high_availability: bool,
     pub activate_backups: bool,
+    pub activate_health_snapshot: bool,
+    pub override_health_checks: bool,
+    pub rotate_credentials: bool,
     pub publicly_accessible: bool,
+    pub parameters: DatabaseParameters,
+    pub maintenance: DatabaseMaintenance,
 }
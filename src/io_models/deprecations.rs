@@ -0,0 +1,268 @@
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+/// Schema version of the payloads not setting one
+pub const DEFAULT_SCHEMA_VERSION: u32 = 1;
+
+/// A payload field still accepted while the clients migrate to its replacement
+#[derive(Debug, PartialEq, Eq)]
+pub struct DeprecatedField {
+    /// Path of the objects holding the field, `[]` standing for every item of an array
+    pub parent: &'static [&'static str],
+    /// Name of the field and its aliases
+    pub names: &'static [&'static str],
+    pub replaced_by: &'static str,
+    /// Payloads from this schema version on are rejected when they use the field
+    pub removed_in_schema_version: u32,
+    pub migration_hint: &'static str,
+}
+
+/// Every deprecated field: payloads using them get warnings, then are rejected once their schema version reaches the
+/// removal version
+pub const DEPRECATED_FIELDS: &[DeprecatedField] = &[
+    DeprecatedField {
+        parent: &["target_environment", "applications", "[]", "ports", "[]"],
+        names: &["publicly_accessible"],
+        replaced_by: "expose",
+        removed_in_schema_version: 2,
+        migration_hint: "set `expose` to `PUBLIC` or `INTERNAL` on the port",
+    },
+    DeprecatedField {
+        parent: &["target_environment", "containers", "[]", "ports", "[]"],
+        names: &["publicly_accessible"],
+        replaced_by: "expose",
+        removed_in_schema_version: 2,
+        migration_hint: "set `expose` to `PUBLIC` or `INTERNAL` on the port",
+    },
+    DeprecatedField {
+        parent: &["target_environment", "applications", "[]", "advanced_settings"],
+        names: &[
+            "network.ingress.basic_auth_env_var",
+            "network_ingress_basic_auth_env_var",
+        ],
+        replaced_by: "target_environment.routers[].basic_auth_credentials",
+        removed_in_schema_version: 2,
+        migration_hint: "set the basic auth credentials on the routers of the application",
+    },
+    DeprecatedField {
+        parent: &["target_environment", "containers", "[]", "advanced_settings"],
+        names: &[
+            "network.ingress.basic_auth_env_var",
+            "network_ingress_basic_auth_env_var",
+        ],
+        replaced_by: "target_environment.routers[].basic_auth_credentials",
+        removed_in_schema_version: 2,
+        migration_hint: "set the basic auth credentials on the routers of the container",
+    },
+];
+
+/// A deprecated field set in a payload
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeprecatedFieldUsage {
+    /// Location of the field in the payload, e.g. `target_environment.applications[0].ports[1].publicly_accessible`
+    pub location: String,
+    pub field: &'static DeprecatedField,
+}
+
+impl Display for DeprecatedFieldUsage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` is deprecated and rejected from schema version {}, use `{}` instead: {}",
+            self.location, self.field.removed_in_schema_version, self.field.replaced_by, self.field.migration_hint
+        )
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub struct RemovedFieldsError {
+    pub schema_version: u32,
+    pub usages: Vec<DeprecatedFieldUsage>,
+}
+
+impl Display for RemovedFieldsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Payload with schema version {} uses {} removed field(s):",
+            self.schema_version,
+            self.usages.len()
+        )?;
+        for usage in &self.usages {
+            write!(
+                f,
+                "\n- `{}`: use `{}` instead, {}",
+                usage.location, usage.field.replaced_by, usage.field.migration_hint
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Deprecated fields set in the payload, to be reported as warnings.
+/// Fails listing all of them when at least one has been removed in the schema version of the payload.
+/// A field set to null, false or an empty value is considered unset.
+pub fn check_deprecated_fields(payload: &Value) -> Result<Vec<DeprecatedFieldUsage>, RemovedFieldsError> {
+    let schema_version = payload
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map(|version| version as u32)
+        .unwrap_or(DEFAULT_SCHEMA_VERSION);
+
+    let usages: Vec<DeprecatedFieldUsage> = DEPRECATED_FIELDS
+        .iter()
+        .flat_map(|field| find_usages(payload, field))
+        .collect();
+    let removed: Vec<DeprecatedFieldUsage> = usages
+        .iter()
+        .filter(|usage| schema_version >= usage.field.removed_in_schema_version)
+        .cloned()
+        .collect();
+
+    if removed.is_empty() {
+        Ok(usages)
+    } else {
+        Err(RemovedFieldsError {
+            schema_version,
+            usages: removed,
+        })
+    }
+}
+
+fn find_usages(payload: &Value, field: &'static DeprecatedField) -> Vec<DeprecatedFieldUsage> {
+    let mut parents = vec![(String::new(), payload)];
+    for segment in field.parent {
+        parents = parents
+            .into_iter()
+            .flat_map(|(location, value)| match (*segment, value) {
+                ("[]", Value::Array(items)) => items
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, item)| (format!("{location}[{ix}]"), item))
+                    .collect(),
+                ("[]", _) => vec![],
+                (key, value) => match value.get(key) {
+                    Some(child) if location.is_empty() => vec![(key.to_string(), child)],
+                    Some(child) => vec![(format!("{location}.{key}"), child)],
+                    None => vec![],
+                },
+            })
+            .collect();
+    }
+
+    parents
+        .into_iter()
+        .flat_map(|(location, parent)| {
+            field
+                .names
+                .iter()
+                .filter(move |name| parent.get(**name).is_some_and(is_set))
+                .map(move |name| DeprecatedFieldUsage {
+                    location: format!("{location}.{name}"),
+                    field,
+                })
+        })
+        .collect()
+}
+
+fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::String(value) => !value.is_empty(),
+        Value::Array(values) => !values.is_empty(),
+        Value::Object(values) => !values.is_empty(),
+        Value::Number(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn payload(schema_version: Option<u32>) -> Value {
+        let mut payload = json!({
+            "target_environment": {
+                "applications": [
+                    {
+                        "ports": [
+                            { "port": 80, "publicly_accessible": false, "expose": "INTERNAL" },
+                            { "port": 443, "publicly_accessible": true }
+                        ],
+                        "advanced_settings": { "network.ingress.basic_auth_env_var": "" }
+                    }
+                ],
+                "containers": [
+                    { "ports": [], "advanced_settings": {} },
+                    { "ports": [], "advanced_settings": { "network.ingress.basic_auth_env_var": "BASIC_AUTH" } }
+                ]
+            }
+        });
+        if let Some(schema_version) = schema_version {
+            payload["schema_version"] = json!(schema_version);
+        }
+
+        payload
+    }
+
+    #[test]
+    fn test_deprecated_fields_are_warnings_before_their_removal() {
+        for schema_version in [None, Some(1)] {
+            let usages = check_deprecated_fields(&payload(schema_version)).unwrap();
+            let locations: Vec<&str> = usages.iter().map(|usage| usage.location.as_str()).collect();
+            assert_eq!(
+                locations,
+                vec![
+                    "target_environment.applications[0].ports[1].publicly_accessible",
+                    "target_environment.containers[1].advanced_settings.network.ingress.basic_auth_env_var",
+                ]
+            );
+            assert_eq!(
+                usages[0].to_string(),
+                "`target_environment.applications[0].ports[1].publicly_accessible` is deprecated and rejected from schema version 2, use `expose` instead: set `expose` to `PUBLIC` or `INTERNAL` on the port"
+            );
+        }
+    }
+
+    #[test]
+    fn test_removed_fields_fail_with_every_usage() {
+        for schema_version in [2, 3] {
+            let err = check_deprecated_fields(&payload(Some(schema_version))).unwrap_err();
+            assert_eq!(err.schema_version, schema_version);
+            assert_eq!(err.usages.len(), 2);
+            assert_eq!(
+                err.to_string(),
+                format!("Payload with schema version {schema_version} uses 2 removed field(s):\n- `target_environment.applications[0].ports[1].publicly_accessible`: use `expose` instead, set `expose` to `PUBLIC` or `INTERNAL` on the port\n- `target_environment.containers[1].advanced_settings.network.ingress.basic_auth_env_var`: use `target_environment.routers[].basic_auth_credentials` instead, set the basic auth credentials on the routers of the container")
+            );
+        }
+    }
+
+    #[test]
+    fn test_payload_without_deprecated_fields_is_accepted_by_every_schema_version() {
+        let payload = json!({
+            "schema_version": 2,
+            "target_environment": {
+                "applications": [{ "ports": [{ "port": 80, "expose": "PUBLIC" }], "advanced_settings": {} }],
+                "containers": []
+            }
+        });
+        assert_eq!(check_deprecated_fields(&payload), Ok(vec![]));
+        // infrastructure payloads have no environment
+        assert_eq!(
+            check_deprecated_fields(&json!({ "schema_version": 2, "target_environment": null })),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn test_deprecated_fields_registry_is_consistent() {
+        for field in DEPRECATED_FIELDS {
+            assert!(!field.names.is_empty());
+            assert!(field.removed_in_schema_version > DEFAULT_SCHEMA_VERSION);
+            assert!(!field.migration_hint.is_empty());
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
@@ -7,11 +8,13 @@ use crate::environment::models::gcp::io::JsonCredentials as JsonCredentialsIo;
 use crate::environment::models::gcp::JsonCredentials;
 use crate::environment::models::scaleway::{ScwRegion, ScwZone};
 use crate::errors::{CommandError, EngineError as IoEngineError, EngineError};
-use crate::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
-use crate::fs::workspace_directory;
+use crate::events::{EnvironmentStep, EventDetails, InfrastructureStep, Stage, Transmitter};
 use crate::infrastructure::infrastructure_context::InfrastructureContext;
 use crate::infrastructure::models::build_platform::local_docker::LocalDocker;
 use crate::infrastructure::models::cloud_provider::aws::regions::AwsRegion;
+use crate::infrastructure::models::cloud_provider::aws::sts::{
+    session_name, AssumeRoleOptions, AssumedRoleCredentials, RusotoSts,
+};
 use crate::infrastructure::models::cloud_provider::aws::AWS;
 use crate::infrastructure::models::cloud_provider::gcp::locations::GcpRegion;
 use crate::infrastructure::models::cloud_provider::gcp::Google;
@@ -22,7 +25,9 @@ use crate::infrastructure::models::container_registry::ecr::ECR;
 use crate::infrastructure::models::container_registry::generic_cr::GenericCr;
 use crate::infrastructure::models::container_registry::github_cr::{GithubCr, RegistryType};
 use crate::infrastructure::models::container_registry::google_artifact_registry::GoogleArtifactRegistry;
+use crate::infrastructure::models::container_registry::harbor_cr::HarborCr;
 use crate::infrastructure::models::container_registry::scaleway_container_registry::ScalewayCR;
+use crate::infrastructure::models::dns_provider::azure_dns::{AzureDns, AzureDnsConfig};
 use crate::infrastructure::models::dns_provider::cloudflare::Cloudflare;
 use crate::infrastructure::models::dns_provider::io::Kind;
 use crate::infrastructure::models::dns_provider::qoverydns::QoveryDns;
@@ -32,26 +37,42 @@ use crate::infrastructure::models::kubernetes::scaleway::kapsule::Kapsule;
 use crate::infrastructure::models::kubernetes::{event_details, Kubernetes, KubernetesVersion};
 use crate::infrastructure::models::{build_platform, cloud_provider, container_registry, dns_provider, kubernetes};
 use crate::io_models;
+use crate::io_models::certificate_watchdog::CertificateWatchdogRequest;
+use crate::io_models::cluster_health::ClusterHealthReportRequest;
 use crate::io_models::context::{Context, Features, Metadata};
+use crate::io_models::deprecations::{
+    check_deprecated_fields, DeprecatedFieldUsage, RemovedFieldsError, DEFAULT_SCHEMA_VERSION,
+};
 use crate::io_models::environment::EnvironmentRequest;
+use crate::io_models::kubeconfig::KubeconfigRequest;
 use crate::io_models::models::NodeGroups;
+use crate::io_models::service_inventory::ServiceInventoryRequest;
+use crate::io_models::variable_preview::VariableChangePreviewRequest;
 use crate::io_models::{Action, QoveryIdentifier};
 use crate::logger::Logger;
 use crate::metrics_registry::MetricsRegistry;
+use crate::proxy::ProxyConfig;
+use crate::runtime::block_on;
 use crate::services::gcp::artifact_registry_service::ArtifactRegistryService;
-use crate::utilities::to_short_id;
 use anyhow::{anyhow, Context as OtherContext};
 use derivative::Derivative;
 use governor::{Quota, RateLimiter};
 use nonzero_ext::nonzero;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
 use url::Url;
 use uuid::Uuid;
 
 pub type EnvironmentEngineRequest = EngineRequest<EnvironmentRequest>;
 pub type InfrastructureEngineRequest = EngineRequest<Option<()>>;
+pub type ClusterHealthReportEngineRequest = EngineRequest<ClusterHealthReportRequest>;
+pub type CertificateWatchdogEngineRequest = EngineRequest<CertificateWatchdogRequest>;
+pub type ServiceInventoryEngineRequest = EngineRequest<ServiceInventoryRequest>;
+pub type KubeconfigEngineRequest = EngineRequest<KubeconfigRequest>;
+pub type VariableChangePreviewEngineRequest = EngineRequest<VariableChangePreviewRequest>;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct EngineRequest<T> {
@@ -62,6 +83,9 @@ pub struct EngineRequest<T> {
     pub created_at: DateTime<Utc>,
     pub action: Action,
     pub features: Vec<Features>,
+    /// Engine behaviors switched for this cluster, see `FeatureFlag` for the registered ones
+    #[serde(default)]
+    pub feature_flags: BTreeMap<String, bool>,
     pub test_cluster: bool,
     pub build_platform: BuildPlatform,
     pub cloud_provider: CloudProvider,
@@ -71,6 +95,48 @@ pub struct EngineRequest<T> {
     pub target_environment: T,
     pub metadata: Option<Metadata>,
     pub archive: Option<Archive>,
+    /// With a delete action, nothing is deleted and the resources that would be removed are reported instead
+    #[serde(default)]
+    pub delete_dry_run: Option<DeleteDryRun>,
+    /// With a create action on a cluster, only this part of the infrastructure is applied, to repair it quickly
+    #[serde(default)]
+    pub infrastructure_repair: Option<InfrastructureRepair>,
+    /// Maximum duration of the whole task, `DEFAULT_TASK_MAX_DURATION` when not set
+    #[serde(default)]
+    pub max_duration_in_sec: Option<u64>,
+    /// HTTP proxy all the outbound traffic of the engine must go through
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Decides whether deprecated fields are still accepted, see `DEPRECATED_FIELDS`
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+    /// Deprecated fields set in the payload, filled by `from_json`
+    #[serde(skip)]
+    pub deprecated_fields: Vec<DeprecatedFieldUsage>,
+}
+
+fn default_schema_version() -> u32 {
+    DEFAULT_SCHEMA_VERSION
+}
+
+#[derive(Error, Debug)]
+pub enum EngineRequestError {
+    #[error("Invalid engine request: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error(transparent)]
+    RemovedFields(#[from] RemovedFieldsError),
+}
+
+impl<T: DeserializeOwned> EngineRequest<T> {
+    /// Rejects the payloads using fields removed in their schema version, and keeps track of the deprecated ones
+    pub fn from_json(payload: &str) -> Result<EngineRequest<T>, EngineRequestError> {
+        let payload: Value = serde_json::from_str(payload)?;
+        let deprecated_fields = check_deprecated_fields(&payload)?;
+        let mut request: EngineRequest<T> = serde_json::from_value(payload)?;
+        request.deprecated_fields = deprecated_fields;
+
+        Ok(request)
+    }
 }
 
 impl<T> EngineRequest<T> {
@@ -85,19 +151,12 @@ impl<T> EngineRequest<T> {
         let build_platform = self
             .build_platform
             .to_engine_build_platform(context, metrics_registry.clone_dyn());
-        let cloud_provider = self
-            .cloud_provider
-            .to_engine_cloud_provider(context.clone(), &self.kubernetes.region, self.kubernetes.kind)
-            .ok_or_else(|| {
-                Box::new(IoEngineError::new_error_on_cloud_provider_information(
-                    event_details.clone(),
-                    CommandError::new(
-                        "Invalid cloud provider information".to_string(),
-                        Some(format!("Invalid cloud provider information: {:?}", self.cloud_provider)),
-                        None,
-                    ),
-                ))
-            })?;
+        let cloud_provider = self.cloud_provider.to_engine_cloud_provider(
+            context.clone(),
+            &self.kubernetes.region,
+            self.kubernetes.kind,
+            event_details.clone(),
+        )?;
 
         let qovery_tags = HashMap::from([
             ("ClusterId".to_string(), context.cluster_short_id().to_string()),
@@ -209,6 +268,75 @@ impl InfrastructureEngineRequest {
     }
 }
 
+impl KubeconfigEngineRequest {
+    pub fn event_details(&self) -> EventDetails {
+        let kubernetes = &self.kubernetes;
+        EventDetails::new(
+            Some(self.cloud_provider.kind.clone()),
+            QoveryIdentifier::new(self.organization_long_id),
+            QoveryIdentifier::new(kubernetes.long_id),
+            self.id.to_string(),
+            Stage::Infrastructure(InfrastructureStep::GenerateKubeconfig),
+            Transmitter::Kubernetes(kubernetes.long_id, kubernetes.name.to_string()),
+        )
+    }
+}
+
+impl ClusterHealthReportEngineRequest {
+    pub fn event_details(&self) -> EventDetails {
+        let kubernetes = &self.kubernetes;
+        EventDetails::new(
+            Some(self.cloud_provider.kind.clone()),
+            QoveryIdentifier::new(self.organization_long_id),
+            QoveryIdentifier::new(kubernetes.long_id),
+            self.id.to_string(),
+            Stage::Infrastructure(InfrastructureStep::ReportClusterHealth),
+            Transmitter::Kubernetes(kubernetes.long_id, kubernetes.name.to_string()),
+        )
+    }
+}
+
+impl CertificateWatchdogEngineRequest {
+    pub fn event_details(&self) -> EventDetails {
+        let kubernetes = &self.kubernetes;
+        EventDetails::new(
+            Some(self.cloud_provider.kind.clone()),
+            QoveryIdentifier::new(self.organization_long_id),
+            QoveryIdentifier::new(kubernetes.long_id),
+            self.id.to_string(),
+            Stage::Infrastructure(InfrastructureStep::WatchCertificates),
+            Transmitter::Kubernetes(kubernetes.long_id, kubernetes.name.to_string()),
+        )
+    }
+}
+
+impl ServiceInventoryEngineRequest {
+    pub fn event_details(&self) -> EventDetails {
+        let kubernetes = &self.kubernetes;
+        EventDetails::new(
+            Some(self.cloud_provider.kind.clone()),
+            QoveryIdentifier::new(self.organization_long_id),
+            QoveryIdentifier::new(kubernetes.long_id),
+            self.id.to_string(),
+            Stage::Infrastructure(InfrastructureStep::ReportServiceInventory),
+            Transmitter::Kubernetes(kubernetes.long_id, kubernetes.name.to_string()),
+        )
+    }
+}
+
+impl VariableChangePreviewEngineRequest {
+    pub fn event_details(&self) -> EventDetails {
+        EventDetails::new(
+            Some(self.cloud_provider.kind.clone()),
+            QoveryIdentifier::new(self.organization_long_id),
+            QoveryIdentifier::new(self.kubernetes.long_id),
+            self.id.to_string(),
+            Stage::Environment(EnvironmentStep::VariableChangePreview),
+            Transmitter::TaskManager(self.organization_long_id, "variable-change-preview".to_string()),
+        )
+    }
+}
+
 impl EnvironmentEngineRequest {
     pub fn event_details(&self) -> EventDetails {
         let kubernetes = &self.kubernetes;
@@ -265,7 +393,18 @@ impl CloudProvider {
         context: Context,
         region: &str,
         cluster_kind: kubernetes::Kind,
-    ) -> Option<Box<dyn cloud_provider::CloudProvider>> {
+        event_details: EventDetails,
+    ) -> Result<Box<dyn cloud_provider::CloudProvider>, Box<EngineError>> {
+        let invalid_information = || {
+            Box::new(IoEngineError::new_error_on_cloud_provider_information(
+                event_details.clone(),
+                CommandError::new(
+                    "Invalid cloud provider information".to_string(),
+                    Some(format!("Invalid cloud provider information: {:?}", self)),
+                    None,
+                ),
+            ))
+        };
         let terraform_state_credentials = cloud_provider::TerraformStateCredentials {
             access_key_id: self.terraform_state_credentials.access_key_id.clone(),
             secret_access_key: self.terraform_state_credentials.secret_access_key.clone(),
@@ -275,49 +414,86 @@ impl CloudProvider {
         };
 
         match self.kind {
-            cloud_provider::Kind::Aws => Some(Box::new(AWS::new(
-                context,
-                self.long_id,
-                self.name.as_str(),
-                self.options.access_key_id.as_ref()?.as_str(),
-                self.options.secret_access_key.as_ref()?.as_str(),
-                region,
-                self.zones.clone(),
-                cluster_kind,
-                terraform_state_credentials,
-            ))),
-            cloud_provider::Kind::Scw => Some(Box::new(Scaleway::new(
+            cloud_provider::Kind::Aws => {
+                let access_key_id = self.options.access_key_id.as_ref().ok_or_else(invalid_information)?;
+                let secret_access_key = self
+                    .options
+                    .secret_access_key
+                    .as_ref()
+                    .ok_or_else(invalid_information)?;
+                let aws = AWS::new(
+                    context,
+                    self.long_id,
+                    self.name.as_str(),
+                    access_key_id,
+                    secret_access_key,
+                    region,
+                    self.zones.clone(),
+                    cluster_kind,
+                    terraform_state_credentials,
+                );
+
+                match &self.options.aws_assume_role_arn {
+                    Some(role_arn) => Ok(Box::new(aws.with_assumed_role(
+                        AssumeRoleOptions {
+                            role_arn: role_arn.to_string(),
+                            external_id: self.options.aws_assume_role_external_id.clone(),
+                            session_duration: Duration::from_secs(
+                                self.options.aws_assume_role_duration_sec.unwrap_or(3600),
+                            ),
+                        },
+                        Box::new(RusotoSts::new(access_key_id, secret_access_key)),
+                    )?)),
+                    None => Ok(Box::new(aws)),
+                }
+            }
+            // Scaleway IAM has no token exchange: API keys can only be created by an IAM application with its own
+            // long-lived key, there is no short-lived token to derive from the provided one for the execution.
+            // The provided key, expected to be scoped to the project of the cluster, is used as is.
+            cloud_provider::Kind::Scw => Ok(Box::new(Scaleway::new(
                 context,
                 self.long_id,
                 self.name.as_str(),
-                self.options.scaleway_access_key.as_ref()?.as_str(),
-                self.options.scaleway_secret_key.as_ref()?.as_str(),
-                self.options.scaleway_project_id.as_ref()?.as_str(),
+                self.options
+                    .scaleway_access_key
+                    .as_ref()
+                    .ok_or_else(invalid_information)?,
+                self.options
+                    .scaleway_secret_key
+                    .as_ref()
+                    .ok_or_else(invalid_information)?,
+                self.options
+                    .scaleway_project_id
+                    .as_ref()
+                    .ok_or_else(invalid_information)?,
                 region,
                 terraform_state_credentials,
             ))),
             cloud_provider::Kind::Gcp => {
-                let credentials = match &self.options.gcp_credentials {
-                    Some(creds) => match JsonCredentials::try_from(creds.clone()) {
-                        Ok(c) => c,
-                        Err(_e) => return None,
-                    },
-                    None => return None,
-                };
-                let region = match GcpRegion::from_str(region) {
-                    Ok(r) => r,
-                    Err(_e) => return None,
-                };
-                Some(Box::new(Google::new(
+                let credentials = self
+                    .options
+                    .gcp_credentials
+                    .as_ref()
+                    .and_then(|creds| JsonCredentials::try_from(creds.clone()).ok())
+                    .ok_or_else(invalid_information)?;
+                let region = GcpRegion::from_str(region).map_err(|_| invalid_information())?;
+                let google = Google::new(
                     context,
                     self.long_id,
                     self.name.as_str(),
                     credentials,
                     region,
                     terraform_state_credentials,
-                )))
+                );
+
+                match &self.options.gcp_impersonated_service_account {
+                    Some(service_account_email) => {
+                        Ok(Box::new(google.with_impersonated_service_account(service_account_email)))
+                    }
+                    None => Ok(Box::new(google)),
+                }
             }
-            cloud_provider::Kind::OnPremise => Some(Box::new(SelfManaged::new(
+            cloud_provider::Kind::OnPremise => Ok(Box::new(SelfManaged::new(
                 context,
                 self.clone().long_id,
                 self.name.clone(),
@@ -369,17 +545,15 @@ impl KubernetesDto {
     ) -> Result<Box<dyn Kubernetes + 'a>, Box<EngineError>> {
         let event_details = event_details(cloud_provider, *context.cluster_long_id(), self.name.to_string(), context);
 
-        let temp_dir = workspace_directory(
-            context.workspace_root_dir(),
-            context.execution_id(),
-            format!("bootstrap/{}", to_short_id(&self.long_id)),
-        )
-        .map_err(|err| {
-            Box::new(EngineError::new_cannot_get_workspace_directory(
-                event_details.clone(),
-                CommandError::new("Error creating workspace directory.".to_string(), Some(err.to_string()), None),
-            ))
-        })?;
+        let workspace = context
+            .workspace()
+            .and_then(|workspace| workspace.service(&self.long_id))
+            .map_err(|err| {
+                Box::new(EngineError::new_cannot_get_workspace_directory(
+                    event_details.clone(),
+                    CommandError::new("Error creating workspace directory.".to_string(), Some(err.to_string()), None),
+                ))
+            })?;
 
         let decoded_helm_charts_override: Option<HashMap<ChartValuesOverrideName, ChartValuesOverrideValues>> =
             match &self.customer_helm_charts_override {
@@ -405,13 +579,25 @@ impl KubernetesDto {
                 None => None,
             };
 
+        for node_group in &self.nodes_groups {
+            node_group
+                .validate_labels_and_taints()
+                .and_then(|_| node_group.validate_capacity_type())
+                .map_err(|e| {
+                    Box::new(EngineError::new_invalid_engine_payload(
+                        event_details.clone(),
+                        e.message_safe().as_str(),
+                        Some(e),
+                    ))
+                })?;
+        }
+
         match self.kind {
             kubernetes::Kind::Eks => match EKS::new(
                 context.clone(),
                 self.long_id,
                 self.name.as_str(),
-                KubernetesVersion::from_str(&self.version)
-                    .unwrap_or_else(|_| panic!("Kubernetes version `{}` is not supported", &self.version)),
+                KubernetesVersion::from_str(&self.version).unwrap_or_else(|e| panic!("{e}")),
                 AwsRegion::from_str(self.region.as_str()).expect("This AWS region is not supported"),
                 cloud_provider.zones().clone(),
                 cloud_provider,
@@ -422,7 +608,7 @@ impl KubernetesDto {
                 self.advanced_settings.clone(),
                 decoded_helm_charts_override,
                 self.kubeconfig.clone(),
-                temp_dir,
+                workspace,
                 self.qovery_allowed_public_access_cidrs.clone(),
             ) {
                 Ok(res) => Ok(Box::new(res)),
@@ -432,8 +618,7 @@ impl KubernetesDto {
                 context.clone(),
                 self.long_id,
                 self.name.clone(),
-                KubernetesVersion::from_str(&self.version)
-                    .unwrap_or_else(|_| panic!("Kubernetes version `{}` is not supported", &self.version)),
+                KubernetesVersion::from_str(&self.version).unwrap_or_else(|e| panic!("{e}")),
                 ScwZone::from_str(self.region.as_str()).unwrap_or_else(|_| {
                     panic!(
                         "cannot parse `{}`, it doesn't seem to be a valid SCW zone",
@@ -448,7 +633,7 @@ impl KubernetesDto {
                 self.advanced_settings.clone(),
                 decoded_helm_charts_override,
                 self.kubeconfig.clone(),
-                temp_dir,
+                workspace,
             ) {
                 Ok(res) => Ok(Box::new(res)),
                 Err(e) => Err(e),
@@ -470,8 +655,7 @@ impl KubernetesDto {
                     context.clone(),
                     self.long_id,
                     &self.name,
-                    KubernetesVersion::from_str(&self.version)
-                        .unwrap_or_else(|_| panic!("Kubernetes version `{}` is not supported", &self.version)),
+                    KubernetesVersion::from_str(&self.version).unwrap_or_else(|e| panic!("{e}")),
                     GcpRegion::from_str(self.region.as_str()).unwrap_or_else(|_| {
                         panic!(
                             "cannot parse `{}`, it doesn't seem to be a valid GCP region",
@@ -483,7 +667,7 @@ impl KubernetesDto {
                     self.advanced_settings.clone(),
                     decoded_helm_charts_override,
                     self.kubeconfig.clone(),
-                    temp_dir,
+                    workspace,
                 ) {
                     Ok(res) => Ok(Box::new(res)),
                     Err(e) => Err(e),
@@ -498,8 +682,7 @@ impl KubernetesDto {
                     self.long_id,
                     self.name.to_string(),
                     self.kind,
-                    KubernetesVersion::from_str(&self.version)
-                        .unwrap_or_else(|_| panic!("Kubernetes version `{}` is not supported", &self.version)),
+                    KubernetesVersion::from_str(&self.version).unwrap_or_else(|e| panic!("{e}")),
                     cloud_provider,
                     serde_json::from_value::<kubernetes::self_managed::on_premise::SelfManagedOptions>(
                         self.options.clone(),
@@ -508,7 +691,7 @@ impl KubernetesDto {
                     logger,
                     ClusterAdvancedSettings::default(),
                     self.kubeconfig.clone(),
-                    temp_dir,
+                    workspace,
                 ) {
                     Ok(res) => Ok(Box::new(res)),
                     Err(e) => Err(e),
@@ -546,6 +729,11 @@ pub enum ContainerRegistry {
         name: String,
         options: GithubCrOptions,
     },
+    HarborCr {
+        long_id: Uuid,
+        name: String,
+        options: HarborCrOptions,
+    },
 }
 impl ContainerRegistry {}
 
@@ -557,16 +745,38 @@ impl ContainerRegistry {
         tags: HashMap<String, String>,
     ) -> Result<Box<dyn container_registry::ContainerRegistry>, anyhow::Error> {
         match self.clone() {
-            ContainerRegistry::Ecr { long_id, name, options } => Ok(Box::new(ECR::new(
-                context,
-                long_id,
-                name.as_str(),
-                &options.access_key_id,
-                &options.secret_access_key,
-                &options.region,
-                logger,
-                tags,
-            )?)),
+            ContainerRegistry::Ecr { long_id, name, options } => {
+                // the registry is reached with its own credentials, through the role they assume if one is set
+                let assumed_role = match &options.aws_assume_role_arn {
+                    Some(role_arn) => Some(Arc::new(
+                        block_on(AssumedRoleCredentials::assume(
+                            Box::new(RusotoSts::new(&options.access_key_id, &options.secret_access_key)),
+                            AssumeRoleOptions {
+                                role_arn: role_arn.to_string(),
+                                external_id: options.aws_assume_role_external_id.clone(),
+                                session_duration: Duration::from_secs(
+                                    options.aws_assume_role_duration_sec.unwrap_or(3600),
+                                ),
+                            },
+                            session_name(context.execution_id()),
+                        ))
+                        .map_err(|err| anyhow!("cannot assume role `{}` for the ECR registry: {}", role_arn, err))?,
+                    )),
+                    None => None,
+                };
+
+                Ok(Box::new(ECR::new(
+                    context,
+                    long_id,
+                    name.as_str(),
+                    &options.access_key_id,
+                    &options.secret_access_key,
+                    assumed_role,
+                    &options.region,
+                    logger,
+                    tags,
+                )?))
+            }
             ContainerRegistry::ScalewayCr { long_id, name, options } => Ok(Box::new(ScalewayCR::new(
                 context,
                 long_id,
@@ -622,6 +832,15 @@ impl ContainerRegistry {
                 options.username,
                 options.token,
             )?)),
+            ContainerRegistry::HarborCr { long_id, name, options } => Ok(Box::new(HarborCr::new(
+                context,
+                long_id,
+                &name,
+                options.url,
+                options.username,
+                options.password,
+                options.ca_bundle,
+            )?)),
         }
     }
 }
@@ -641,6 +860,8 @@ impl DnsProvider {
         context: Context,
         cluster_jwt_token: String,
     ) -> Option<Box<dyn dns_provider::DnsProvider>> {
+        let domain = Domain::try_from(self.domain.clone()).ok()?;
+
         match self.kind {
             Kind::Cloudflare => {
                 let token = self.options.get("cloudflare_api_token")?;
@@ -655,7 +876,7 @@ impl DnsProvider {
                     context,
                     self.long_id,
                     self.name.as_str(),
-                    Domain::new(self.domain.clone()),
+                    domain,
                     token.as_str(),
                     email.as_str(),
                     proxied,
@@ -671,12 +892,25 @@ impl DnsProvider {
                         api_url,
                         &cluster_jwt_token,
                         self.name.as_str(),
-                        Domain::new(self.domain.clone()),
+                        domain,
                     )));
                 }
 
                 None
             }
+            Kind::AzureDns => Some(Box::new(AzureDns::new(
+                context,
+                self.long_id,
+                self.name.as_str(),
+                domain,
+                AzureDnsConfig {
+                    tenant_id: self.options.get("azure_tenant_id")?.to_string(),
+                    subscription_id: self.options.get("azure_subscription_id")?.to_string(),
+                    resource_group: self.options.get("azure_resource_group")?.to_string(),
+                    client_id: self.options.get("azure_client_id")?.to_string(),
+                    client_secret: self.options.get("azure_client_secret")?.to_string(),
+                },
+            ))),
         }
     }
 }
@@ -706,6 +940,11 @@ pub struct Options {
     #[derivative(Debug = "ignore")]
     pub token: Option<String>,
     region: Option<String>,
+    // Role assumed for every execution, the access key above is then only used to call STS
+    aws_assume_role_arn: Option<String>,
+    aws_assume_role_external_id: Option<String>,
+    aws_assume_role_duration_sec: Option<u64>,
+    gcp_impersonated_service_account: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Derivative)]
@@ -714,6 +953,10 @@ pub struct EcrOptions {
     #[derivative(Debug = "ignore")]
     secret_access_key: String,
     region: String,
+    // Role assumed with the access key above to reach the registry
+    aws_assume_role_arn: Option<String>,
+    aws_assume_role_external_id: Option<String>,
+    aws_assume_role_duration_sec: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Derivative)]
@@ -743,6 +986,17 @@ pub struct GithubCrOptions {
     pub token: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Derivative)]
+pub struct HarborCrOptions {
+    pub url: Url,
+    pub username: String,
+    #[derivative(Debug = "ignore")]
+    pub password: String,
+    // PEM bundle of the CA of a harbor using a self-signed certificate
+    #[serde(default)]
+    pub ca_bundle: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Derivative)]
 pub enum GithubCrRepoType {
     User(String),
@@ -782,6 +1036,19 @@ pub struct Archive {
     pub upload_url: Url,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeleteDryRun {
+    /// Pre-signed url where the deletion report is uploaded, valid for a single object
+    pub upload_url: Url,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum InfrastructureRepair {
+    /// Terraform resources of the node groups, nothing else is applied
+    NodeGroups,
+}
+
 impl From<GithubCrRepoType> for RegistryType {
     fn from(value: GithubCrRepoType) -> Self {
         match value {
@@ -1,24 +1,34 @@
 use crate::environment::models::application::{ApplicationError, ApplicationService};
+use crate::environment::models::basic_auth::{htpasswd, htpasswd_from_env_var};
+use crate::environment::models::bucket::{validate_buckets, BucketError};
 use crate::environment::models::container::{ContainerError, ContainerService};
 use crate::environment::models::database::{DatabaseError, DatabaseService};
 use crate::environment::models::environment::Environment;
 use crate::environment::models::helm_chart::{HelmChartError, HelmChartService};
 use crate::environment::models::job::{JobError, JobService};
-use crate::environment::models::router::{RouterAdvancedSettings, RouterError};
+use crate::environment::models::k8s_manifests::{K8sManifestsError, K8sManifestsService};
+use crate::environment::models::pod_security::PodSecurityProfile;
+use crate::environment::models::router::{validate_extra_ingress_annotations, RouterAdvancedSettings, RouterError};
+use crate::environment::models::service_port::validate_routed_ports;
+use crate::infrastructure::models::cloud_provider::io::CustomErrorPages;
+use crate::infrastructure::models::cloud_provider::service::Service;
 use crate::infrastructure::models::cloud_provider::CloudProvider;
 use crate::infrastructure::models::container_registry::ContainerRegistry;
 use crate::infrastructure::models::kubernetes::Kubernetes;
 use crate::io_models::annotations_group::AnnotationsGroup;
 use crate::io_models::application::Application;
+use crate::io_models::bucket::Bucket;
 use crate::io_models::container::Container;
 use crate::io_models::context::Context;
 use crate::io_models::database::Database;
 use crate::io_models::helm_chart::HelmChart;
 use crate::io_models::job::Job;
+use crate::io_models::k8s_manifests::K8sManifests;
 use crate::io_models::labels_group::LabelsGroup;
 use crate::io_models::router::Router;
+use crate::io_models::variable_preview::VariableChangePreviewRequest;
+use crate::io_models::variable_utils::{ClusterDefaultVariables, VariableInfo};
 use crate::io_models::{Action, QoveryIdentifier};
-use crate::utilities::base64_replace_comma_to_new_line;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
@@ -44,10 +54,23 @@ pub struct EnvironmentRequest {
     pub databases: Vec<Database>,
     #[serde(default)]
     pub helms: Vec<HelmChart>,
+    #[serde(default)]
+    pub k8s_manifests: Vec<K8sManifests>,
     #[serde(default = "default_annotations_groups")]
     pub annotations_groups: BTreeMap<Uuid, AnnotationsGroup>,
     #[serde(default = "default_labels_groups")]
     pub labels_groups: BTreeMap<Uuid, LabelsGroup>,
+    #[serde(default)]
+    pub pod_security_profile: PodSecurityProfile,
+    /// Error pages of the environment routers, replacing the ones of the cluster (e.g. for white-label customers)
+    #[serde(default)]
+    pub custom_error_pages: Option<CustomErrorPages>,
+    /// Variable changes whose impact is previewed when the deployment is a dry run
+    #[serde(default)]
+    pub variable_change_preview: Option<VariableChangePreviewRequest>,
+    /// Object storage buckets of the environment, provisioned before its services are deployed
+    #[serde(default)]
+    pub buckets: Vec<Bucket>,
 }
 
 fn default_max_parallel_build() -> u32 {
@@ -80,9 +103,43 @@ pub enum DomainError {
     JobError(#[from] JobError),
     #[error("Invalid helm chart: {0}")]
     HelmChartError(#[from] HelmChartError),
+    #[error("Invalid k8s manifests: {0}")]
+    K8sManifestsError(#[from] K8sManifestsError),
+    #[error("Invalid bucket: {0}")]
+    BucketError(#[from] BucketError),
 }
 
 impl EnvironmentRequest {
+    /// Variables of the services able to receive injected ones, by service long id
+    pub fn services_variables_mut(&mut self) -> impl Iterator<Item = (&Uuid, &mut BTreeMap<String, VariableInfo>)> {
+        std::iter::empty()
+            .chain(
+                self.applications
+                    .iter_mut()
+                    .map(|srv| (&srv.long_id, &mut srv.environment_vars_with_infos)),
+            )
+            .chain(
+                self.containers
+                    .iter_mut()
+                    .map(|srv| (&srv.long_id, &mut srv.environment_vars_with_infos)),
+            )
+            .chain(
+                self.jobs
+                    .iter_mut()
+                    .map(|srv| (&srv.long_id, &mut srv.environment_vars_with_infos)),
+            )
+            .chain(
+                self.helms
+                    .iter_mut()
+                    .map(|srv| (&srv.long_id, &mut srv.environment_vars_with_infos)),
+            )
+            .chain(
+                self.k8s_manifests
+                    .iter_mut()
+                    .map(|srv| (&srv.long_id, &mut srv.environment_vars_with_infos)),
+            )
+    }
+
     pub fn to_environment_domain(
         &self,
         context: &Context,
@@ -90,17 +147,23 @@ impl EnvironmentRequest {
         container_registry: &dyn ContainerRegistry,
         cluster: &dyn Kubernetes,
     ) -> Result<Environment, DomainError> {
+        validate_buckets(&self.buckets)?;
+        let cluster_default_variables = ClusterDefaultVariables::from(cluster.advanced_settings());
         let applications: Result<Vec<Box<dyn ApplicationService>>, ApplicationError> = self
             .applications
             .iter()
             .cloned()
-            .map(|srv| {
-                let build = srv.to_build(
+            .map(|mut srv| {
+                // build is computed before injecting cluster default variables, only the flagged ones are exposed to it
+                let mut build = srv.to_build(
                     container_registry.registry_info(),
                     context.qovery_api.clone(),
                     cluster.cpu_architectures(),
                     &QoveryIdentifier::new(*cluster.long_id()),
                 );
+                cluster_default_variables
+                    .merge_into_build(&srv.environment_vars_with_infos, &mut build.environment_variables);
+                cluster_default_variables.inject(&srv.long_id, &mut srv.environment_vars_with_infos);
                 srv.to_application_domain(context, build, cloud_provider, &self.annotations_groups, &self.labels_groups)
             })
             .collect();
@@ -123,9 +186,24 @@ impl EnvironmentRequest {
             .collect();
         let containers = containers?;
 
+        let custom_error_pages = self
+            .custom_error_pages
+            .as_ref()
+            .map(|custom_error_pages| custom_error_pages.to_model())
+            .transpose()
+            .map_err(|err| DomainError::RouterError(RouterError::InvalidConfig(err.to_string())))?;
         let mut routers = Vec::with_capacity(self.routers.len());
         for router in &self.routers {
-            let mut router_advanced_settings = RouterAdvancedSettings::default();
+            validate_extra_ingress_annotations(
+                &router.extra_ingress_annotations,
+                router.allow_unsafe_ingress_annotations,
+            )
+            .map_err(DomainError::RouterError)?;
+            let mut router_advanced_settings = RouterAdvancedSettings {
+                custom_error_pages: custom_error_pages.clone(),
+                extra_ingress_annotations: router.extra_ingress_annotations.clone(),
+                ..Default::default()
+            };
             let mut annotations_groups_ids = BTreeSet::new();
             let mut labels_groups_ids = BTreeSet::new();
 
@@ -154,17 +232,14 @@ impl EnvironmentRequest {
                                 .get(&app.advanced_settings.network_ingress_basic_auth_env_var)
                             {
                                 Some(variable_infos) => {
-                                    let secret = base64_replace_comma_to_new_line(variable_infos.value.clone())
-                                        .map_err(|_| {
-                                            DomainError::RouterError(RouterError::BasicAuthEnvVarBase64DecodeError {
-                                                env_var_name: app
-                                                    .advanced_settings
-                                                    .network_ingress_basic_auth_env_var
-                                                    .to_string(),
-                                                env_var_value: variable_infos.value.clone(),
-                                            })
-                                        })?;
+                                    let secret = htpasswd_from_env_var(
+                                        &app.advanced_settings.network_ingress_basic_auth_env_var,
+                                        &variable_infos.value,
+                                    )
+                                    .map_err(DomainError::RouterError)?;
                                     router_advanced_settings.basic_auth = Some(secret);
+                                    router_advanced_settings.basic_auth_env_var =
+                                        Some(app.advanced_settings.network_ingress_basic_auth_env_var.clone());
                                 }
                                 None => {
                                     return Err(DomainError::RouterError(RouterError::BasicAuthEnvVarNotFound {
@@ -213,17 +288,14 @@ impl EnvironmentRequest {
                                 .get(&container.advanced_settings.network_ingress_basic_auth_env_var)
                             {
                                 Some(variable_infos) => {
-                                    let secret = base64_replace_comma_to_new_line(variable_infos.value.clone())
-                                        .map_err(|_| {
-                                            DomainError::RouterError(RouterError::BasicAuthEnvVarBase64DecodeError {
-                                                env_var_name: container
-                                                    .advanced_settings
-                                                    .network_ingress_basic_auth_env_var
-                                                    .to_string(),
-                                                env_var_value: variable_infos.value.clone(),
-                                            })
-                                        })?;
+                                    let secret = htpasswd_from_env_var(
+                                        &container.advanced_settings.network_ingress_basic_auth_env_var,
+                                        &variable_infos.value,
+                                    )
+                                    .map_err(DomainError::RouterError)?;
                                     router_advanced_settings.basic_auth = Some(secret);
+                                    router_advanced_settings.basic_auth_env_var =
+                                        Some(container.advanced_settings.network_ingress_basic_auth_env_var.clone());
                                 }
                                 None => {
                                     return Err(DomainError::RouterError(RouterError::BasicAuthEnvVarNotFound {
@@ -261,17 +333,14 @@ impl EnvironmentRequest {
                                 .get(&helm.advanced_settings.network_ingress_basic_auth_env_var)
                             {
                                 Some(variable_infos) => {
-                                    let secret = base64_replace_comma_to_new_line(variable_infos.value.clone())
-                                        .map_err(|_| {
-                                            DomainError::RouterError(RouterError::BasicAuthEnvVarBase64DecodeError {
-                                                env_var_name: helm
-                                                    .advanced_settings
-                                                    .network_ingress_basic_auth_env_var
-                                                    .to_string(),
-                                                env_var_value: variable_infos.value.clone(),
-                                            })
-                                        })?;
+                                    let secret = htpasswd_from_env_var(
+                                        &helm.advanced_settings.network_ingress_basic_auth_env_var,
+                                        &variable_infos.value,
+                                    )
+                                    .map_err(DomainError::RouterError)?;
                                     router_advanced_settings.basic_auth = Some(secret);
+                                    router_advanced_settings.basic_auth_env_var =
+                                        Some(helm.advanced_settings.network_ingress_basic_auth_env_var.clone());
                                 }
                                 None => {
                                     return Err(DomainError::RouterError(RouterError::BasicAuthEnvVarNotFound {
@@ -287,6 +356,13 @@ impl EnvironmentRequest {
                 }
             }
 
+            // credentials of the router take precedence over the deprecated basic auth env var
+            if !router.basic_auth_credentials.is_empty() {
+                router_advanced_settings.basic_auth =
+                    Some(htpasswd(&router.long_id, &router.basic_auth_credentials).map_err(DomainError::RouterError)?);
+                router_advanced_settings.basic_auth_env_var = None;
+            }
+
             let annotations_groups = annotations_groups_ids
                 .iter()
                 .flat_map(|annotations_group_id| self.annotations_groups.get(annotations_group_id))
@@ -341,10 +417,53 @@ impl EnvironmentRequest {
             .helms
             .iter()
             .cloned()
-            .map(|helm_chart| helm_chart.to_helm_chart_domain(context, cloud_provider))
+            .map(|mut helm_chart| {
+                cluster_default_variables.inject(&helm_chart.long_id, &mut helm_chart.environment_vars_with_infos);
+                helm_chart.to_helm_chart_domain(context, cloud_provider)
+            })
             .collect();
         let helm_charts = helm_charts?;
 
+        // A router exposes the default domain on the default public port of the service it routes to
+        for route in self.routers.iter().flat_map(|router| &router.routes) {
+            let public_ports = applications
+                .iter()
+                .find(|app| app.long_id() == &route.service_long_id)
+                .map(|app| app.public_ports())
+                .or_else(|| {
+                    containers
+                        .iter()
+                        .find(|container| container.long_id() == &route.service_long_id)
+                        .map(|container| container.public_ports())
+                })
+                .or_else(|| {
+                    helm_charts
+                        .iter()
+                        .find(|helm_chart| helm_chart.long_id() == &route.service_long_id)
+                        .map(|helm_chart| helm_chart.public_ports())
+                });
+
+            if let Some(public_ports) = public_ports {
+                validate_routed_ports(&public_ports).map_err(|err| {
+                    DomainError::RouterError(RouterError::InvalidConfig(format!(
+                        "service {}: {}",
+                        route.service_long_id, err
+                    )))
+                })?;
+            }
+        }
+
+        let k8s_manifests: Result<Vec<Box<dyn K8sManifestsService>>, K8sManifestsError> = self
+            .k8s_manifests
+            .iter()
+            .cloned()
+            .map(|mut manifests| {
+                cluster_default_variables.inject(&manifests.long_id, &mut manifests.environment_vars_with_infos);
+                manifests.to_k8s_manifests_domain(context)
+            })
+            .collect();
+        let k8s_manifests = k8s_manifests?;
+
         Ok(Environment::new(
             self.long_id,
             self.name.clone(),
@@ -361,6 +480,8 @@ impl EnvironmentRequest {
             databases,
             jobs,
             helm_charts,
+            k8s_manifests,
+            self.pod_security_profile,
         ))
     }
 }
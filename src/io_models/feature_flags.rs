@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// Engine behaviors that can be switched per cluster from the request, to roll them out gradually.
+/// Flags can only be queried through this enum, so a flag missing from the registry does not compile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter)]
+pub enum FeatureFlag {
+    /// Reports the pods removed while still receiving traffic during a rollout
+    DrainingCheck,
+    /// Keeps the workspace of a failed execution on disk once archived
+    KeepWorkspaceOnFailure,
+}
+
+impl FeatureFlag {
+    /// Name of the flag in the request
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeatureFlag::DrainingCheck => "draining_check",
+            FeatureFlag::KeepWorkspaceOnFailure => "keep_workspace_on_failure",
+        }
+    }
+
+    /// Value when the request does not set the flag
+    pub fn default_value(&self) -> bool {
+        match self {
+            FeatureFlag::DrainingCheck => true,
+            FeatureFlag::KeepWorkspaceOnFailure => false,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<FeatureFlag> {
+        FeatureFlag::iter().find(|flag| flag.name() == name)
+    }
+}
+
+impl Display for FeatureFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Former names still sent by the core, and the flag they now stand for
+const DEPRECATED_FEATURE_FLAGS: &[(&str, FeatureFlag)] = &[("new_drain_controller", FeatureFlag::DrainingCheck)];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FeatureFlagWarning {
+    Unknown { name: String },
+    Deprecated { name: String, replaced_by: FeatureFlag },
+}
+
+impl Display for FeatureFlagWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeatureFlagWarning::Unknown { name } => write!(f, "Unknown feature flag `{name}` is ignored"),
+            FeatureFlagWarning::Deprecated { name, replaced_by } => {
+                write!(f, "Feature flag `{name}` is deprecated, use `{replaced_by}` instead")
+            }
+        }
+    }
+}
+
+/// Flags set by the request, the registry defaults apply to the others
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeatureFlags {
+    overrides: BTreeMap<FeatureFlag, bool>,
+}
+
+impl FeatureFlags {
+    /// Unknown flags are ignored and deprecated ones mapped to their replacement.
+    /// When both a deprecated name and its replacement are set, the replacement wins.
+    pub fn resolve(requested: &BTreeMap<String, bool>) -> (FeatureFlags, Vec<FeatureFlagWarning>) {
+        let mut overrides = BTreeMap::new();
+        let mut warnings = vec![];
+
+        for (name, enabled) in requested {
+            if let Some((_, flag)) = DEPRECATED_FEATURE_FLAGS
+                .iter()
+                .find(|(deprecated, _)| deprecated == name)
+            {
+                overrides.entry(*flag).or_insert(*enabled);
+                warnings.push(FeatureFlagWarning::Deprecated {
+                    name: name.clone(),
+                    replaced_by: *flag,
+                });
+            } else if FeatureFlag::from_name(name).is_none() {
+                warnings.push(FeatureFlagWarning::Unknown { name: name.clone() });
+            }
+        }
+        for (name, enabled) in requested {
+            if let Some(flag) = FeatureFlag::from_name(name) {
+                overrides.insert(flag, *enabled);
+            }
+        }
+
+        (FeatureFlags { overrides }, warnings)
+    }
+
+    pub fn is_enabled(&self, flag: FeatureFlag) -> bool {
+        self.overrides
+            .get(&flag)
+            .copied()
+            .unwrap_or_else(|| flag.default_value())
+    }
+
+    /// Value of every registered flag, defaults included
+    pub fn effective(&self) -> EffectiveFeatureFlags {
+        EffectiveFeatureFlags(
+            FeatureFlag::iter()
+                .map(|flag| (flag.name(), self.is_enabled(flag)))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EffectiveFeatureFlags(pub BTreeMap<&'static str, bool>);
+
+impl Display for EffectiveFeatureFlags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let flags = self
+            .0
+            .iter()
+            .map(|(name, enabled)| format!("{name}={}", if *enabled { "on" } else { "off" }))
+            .collect::<Vec<_>>();
+        write!(f, "{}", flags.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requested(flags: &[(&str, bool)]) -> BTreeMap<String, bool> {
+        flags.iter().map(|(name, value)| (name.to_string(), *value)).collect()
+    }
+
+    #[test]
+    fn test_flags_not_requested_use_registry_defaults() {
+        let (flags, warnings) = FeatureFlags::resolve(&BTreeMap::new());
+
+        assert!(warnings.is_empty());
+        for flag in FeatureFlag::iter() {
+            assert_eq!(flags.is_enabled(flag), flag.default_value());
+        }
+        assert_eq!(
+            flags.effective().to_string(),
+            "draining_check=on, keep_workspace_on_failure=off"
+        );
+    }
+
+    #[test]
+    fn test_requested_flags_override_defaults() {
+        let (flags, warnings) = FeatureFlags::resolve(&requested(&[("draining_check", false)]));
+
+        assert!(warnings.is_empty());
+        assert!(!flags.is_enabled(FeatureFlag::DrainingCheck));
+        assert_eq!(
+            flags.effective().to_string(),
+            "draining_check=off, keep_workspace_on_failure=off"
+        );
+    }
+
+    #[test]
+    fn test_unknown_flags_are_ignored_with_a_warning() {
+        let (flags, warnings) = FeatureFlags::resolve(&requested(&[("make_it_faster", true)]));
+
+        assert_eq!(flags, FeatureFlags::default());
+        assert_eq!(
+            warnings,
+            vec![FeatureFlagWarning::Unknown {
+                name: "make_it_faster".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_deprecated_flags_are_mapped_to_their_replacement() {
+        let (flags, warnings) = FeatureFlags::resolve(&requested(&[("new_drain_controller", false)]));
+
+        assert!(!flags.is_enabled(FeatureFlag::DrainingCheck));
+        assert_eq!(
+            warnings,
+            vec![FeatureFlagWarning::Deprecated {
+                name: "new_drain_controller".to_string(),
+                replaced_by: FeatureFlag::DrainingCheck,
+            }]
+        );
+
+        // the current name wins over the deprecated one
+        let (flags, _) =
+            FeatureFlags::resolve(&requested(&[("new_drain_controller", true), ("draining_check", false)]));
+        assert!(!flags.is_enabled(FeatureFlag::DrainingCheck));
+    }
+}
@@ -1,6 +1,9 @@
 use crate::environment::models::gcp::io::JsonCredentials;
 use crate::environment::models::gcp::JsonCredentials as GkeJsonCredentials;
-use crate::infrastructure::models::kubernetes::gcp::{GkeOptions as GkeOptionsModel, VpcMode as GkeVpcMode};
+use crate::infrastructure::models::kubernetes::gcp::{
+    ControlPlaneAccess as GkeControlPlaneAccess, GkeOptions as GkeOptionsModel,
+    MasterAuthorizedNetwork as GkeMasterAuthorizedNetwork, VpcMode as GkeVpcMode,
+};
 use crate::io_models::engine_location::EngineLocation;
 use crate::io_models::models::VpcQoveryNetworkMode;
 use ipnet::IpNet;
@@ -24,6 +27,13 @@ pub struct UserProvidedVPCNetwork {
     ip_range_services_name: Option<String>,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MasterAuthorizedNetwork {
+    cidr_block: String,
+    #[serde(default)]
+    display_name: String,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct GkeOptions {
     // Qovery
@@ -55,6 +65,13 @@ pub struct GkeOptions {
     pub user_provided_network: Option<UserProvidedVPCNetwork>,
     #[serde(default)]
     pub vpc_qovery_network_mode: Option<VpcQoveryNetworkMode>,
+    // Control plane
+    #[serde(default)]
+    pub enable_private_endpoint: bool,
+    #[serde(default)]
+    pub master_ipv4_cidr_block: Option<String>,
+    #[serde(default)]
+    pub master_authorized_networks: Vec<MasterAuthorizedNetwork>,
 
     // GCP to be checked during integration if needed:
     pub cluster_maintenance_start_time: String,
@@ -96,6 +113,84 @@ impl GkeOptions {
 
         Ok(vpc_mode)
     }
+
+    fn to_gke_control_plane_access(&self, vpc_mode: &GkeVpcMode) -> Result<GkeControlPlaneAccess, String> {
+        let master_ipv4_cidr_block = match &self.master_ipv4_cidr_block {
+            Some(cidr) => {
+                let master_cidr = IpNet::from_str(cidr.as_str())
+                    .map_err(|e| format!("cannot parse master_ipv4_cidr_block to IP Net: `{e}`"))?;
+                if !matches!(master_cidr, IpNet::V4(_)) || master_cidr.prefix_len() != 28 {
+                    return Err(format!("master_ipv4_cidr_block `{cidr}` must be an IPv4 /28 range"));
+                }
+                Some(master_cidr)
+            }
+            None => None,
+        };
+
+        // control plane private endpoint and its range only exist for private nodes
+        let has_private_nodes = self.vpc_qovery_network_mode == Some(VpcQoveryNetworkMode::WithNatGateways);
+        if self.enable_private_endpoint && !has_private_nodes {
+            return Err(format!(
+                "enable_private_endpoint requires private nodes, vpc_qovery_network_mode must be `{}`",
+                VpcQoveryNetworkMode::WithNatGateways
+            ));
+        }
+        if master_ipv4_cidr_block.is_some() && !has_private_nodes {
+            return Err(format!(
+                "master_ipv4_cidr_block requires private nodes, vpc_qovery_network_mode must be `{}`",
+                VpcQoveryNetworkMode::WithNatGateways
+            ));
+        }
+
+        if let (
+            Some(master_cidr),
+            GkeVpcMode::Automatic {
+                custom_cluster_ipv4_cidr_block,
+                custom_services_ipv4_cidr_block,
+            },
+        ) = (&master_ipv4_cidr_block, vpc_mode)
+        {
+            for (range_name, range) in [
+                ("cluster_ipv4_cidr_block", custom_cluster_ipv4_cidr_block),
+                ("services_ipv4_cidr_block", custom_services_ipv4_cidr_block),
+            ] {
+                if let Some(range) = range {
+                    // CIDR ranges are either disjoint or nested
+                    if master_cidr.contains(range) || range.contains(master_cidr) {
+                        return Err(format!(
+                            "master_ipv4_cidr_block `{master_cidr}` overlaps {range_name} `{range}`"
+                        ));
+                    }
+                }
+            }
+        }
+
+        let mut master_authorized_networks = Vec::with_capacity(self.master_authorized_networks.len());
+        for network in &self.master_authorized_networks {
+            let cidr_block = IpNet::from_str(network.cidr_block.as_str()).map_err(|e| {
+                format!(
+                    "cannot parse master_authorized_networks cidr_block `{}` to IP Net: `{e}`",
+                    network.cidr_block
+                )
+            })?;
+            if network.display_name.contains(['"', '\\']) {
+                return Err(format!(
+                    "master_authorized_networks display_name `{}` cannot contain quotes or backslashes",
+                    network.display_name
+                ));
+            }
+            master_authorized_networks.push(GkeMasterAuthorizedNetwork {
+                cidr_block,
+                display_name: network.display_name.to_string(),
+            });
+        }
+
+        Ok(GkeControlPlaneAccess {
+            enable_private_endpoint: self.enable_private_endpoint,
+            master_ipv4_cidr_block,
+            master_authorized_networks,
+        })
+    }
 }
 
 /// Allow to properly deserialize JSON credentials from string, making sure to escape \n from keys strings
@@ -117,6 +212,9 @@ impl TryFrom<GkeOptions> for GkeOptionsModel {
         let vpc_mode = value
             .to_gke_vpc_mode()
             .map_err(|e| format!("cannot parse VPCMode: `{e}`"))?;
+        let control_plane_access = value
+            .to_gke_control_plane_access(&vpc_mode)
+            .map_err(|e| format!("cannot parse control plane access: `{e}`"))?;
 
         Ok(GkeOptionsModel::new(
             value.qovery_api_url,
@@ -132,6 +230,7 @@ impl TryFrom<GkeOptions> for GkeOptionsModel {
                 .map_err(|e| format!("Cannot parse JSON credentials: {e}"))?,
             vpc_mode,
             value.vpc_qovery_network_mode,
+            control_plane_access,
             value.tls_email_report,
             Time::parse(
                 value.cluster_maintenance_start_time.as_str(),
@@ -170,10 +269,8 @@ mod tests {
   "universe_domain": "gcp_json_credentials_universe_domain"
 }"#;
 
-    #[test]
-    fn test_gke_options_to_gke_vpc_mode() {
-        // setup:
-        let basic_gke_options = GkeOptions {
+    fn basic_gke_options() -> GkeOptions {
+        GkeOptions {
             qovery_api_url: "https://api.qovery.com".to_string(),
             qovery_grpc_url: "https://grpc.qovery.com".to_string(),
             qovery_engine_url: "https://engine.qovery.com".to_string(),
@@ -193,7 +290,16 @@ mod tests {
             services_ipv4_cidr_block: None,
             user_provided_network: None,
             vpc_qovery_network_mode: None,
-        };
+            enable_private_endpoint: false,
+            master_ipv4_cidr_block: None,
+            master_authorized_networks: vec![],
+        }
+    }
+
+    #[test]
+    fn test_gke_options_to_gke_vpc_mode() {
+        // setup:
+        let basic_gke_options = basic_gke_options();
 
         // execute & validate:
 
@@ -253,4 +359,112 @@ mod tests {
                 .expect("Cannot convert GkeOptions to GkeVpcMode")
         );
     }
+
+    #[test]
+    fn test_gke_options_to_gke_control_plane_access() {
+        // setup:
+        let mut private_gke_options = basic_gke_options();
+        private_gke_options.vpc_qovery_network_mode = Some(VpcQoveryNetworkMode::WithNatGateways);
+        private_gke_options.cluster_ipv4_cidr_block = Some("10.0.0.0/14".to_string());
+        private_gke_options.services_ipv4_cidr_block = Some("10.4.0.0/20".to_string());
+        let to_control_plane_access = |gke_options: &GkeOptions| {
+            gke_options.to_gke_control_plane_access(&gke_options.to_gke_vpc_mode().expect("Cannot get GkeVpcMode"))
+        };
+
+        // execute & validate:
+
+        // case 1: public endpoint with default values (nothing specified)
+        assert_eq!(
+            GkeControlPlaneAccess::default(),
+            to_control_plane_access(&basic_gke_options()).expect("Cannot get control plane access")
+        );
+
+        // case 2: private endpoint with master range and authorized networks
+        let mut gke_options_to_test = private_gke_options.clone();
+        gke_options_to_test.enable_private_endpoint = true;
+        gke_options_to_test.master_ipv4_cidr_block = Some("172.16.0.16/28".to_string());
+        gke_options_to_test.master_authorized_networks = vec![
+            MasterAuthorizedNetwork {
+                cidr_block: "203.0.113.0/24".to_string(),
+                display_name: "office".to_string(),
+            },
+            MasterAuthorizedNetwork {
+                cidr_block: "198.51.100.7/32".to_string(),
+                display_name: "".to_string(),
+            },
+        ];
+        assert_eq!(
+            GkeControlPlaneAccess {
+                enable_private_endpoint: true,
+                master_ipv4_cidr_block: Some(IpNet::from_str("172.16.0.16/28").expect("Cannot parse IP Net")),
+                master_authorized_networks: vec![
+                    GkeMasterAuthorizedNetwork {
+                        cidr_block: IpNet::from_str("203.0.113.0/24").expect("Cannot parse IP Net"),
+                        display_name: "office".to_string(),
+                    },
+                    GkeMasterAuthorizedNetwork {
+                        cidr_block: IpNet::from_str("198.51.100.7/32").expect("Cannot parse IP Net"),
+                        display_name: "".to_string(),
+                    },
+                ],
+            },
+            to_control_plane_access(&gke_options_to_test).expect("Cannot get control plane access")
+        );
+
+        // case 3: authorized networks on a public endpoint
+        let mut gke_options_to_test = basic_gke_options();
+        gke_options_to_test.master_authorized_networks = vec![MasterAuthorizedNetwork {
+            cidr_block: "203.0.113.0/24".to_string(),
+            display_name: "office".to_string(),
+        }];
+        assert!(to_control_plane_access(&gke_options_to_test).is_ok());
+
+        // case 4: invalid master range
+        for invalid_master_cidr in ["172.16.0.16", "172.16.0.0/24", "fd00::/28", "not-a-cidr"] {
+            let mut gke_options_to_test = private_gke_options.clone();
+            gke_options_to_test.master_ipv4_cidr_block = Some(invalid_master_cidr.to_string());
+            assert!(
+                to_control_plane_access(&gke_options_to_test).is_err(),
+                "{invalid_master_cidr} should be rejected"
+            );
+        }
+
+        // case 5: master range overlapping pods or services ranges
+        for overlapping_master_cidr in ["10.1.2.0/28", "10.4.15.240/28"] {
+            let mut gke_options_to_test = private_gke_options.clone();
+            gke_options_to_test.master_ipv4_cidr_block = Some(overlapping_master_cidr.to_string());
+            assert!(
+                to_control_plane_access(&gke_options_to_test)
+                    .expect_err("overlapping master range should be rejected")
+                    .contains("overlaps"),
+                "{overlapping_master_cidr} should be rejected"
+            );
+        }
+
+        // case 6: private endpoint or master range without private nodes
+        let mut gke_options_to_test = basic_gke_options();
+        gke_options_to_test.enable_private_endpoint = true;
+        assert!(to_control_plane_access(&gke_options_to_test).is_err());
+        let mut gke_options_to_test = basic_gke_options();
+        gke_options_to_test.vpc_qovery_network_mode = Some(VpcQoveryNetworkMode::WithoutNatGateways);
+        gke_options_to_test.master_ipv4_cidr_block = Some("172.16.0.16/28".to_string());
+        assert!(to_control_plane_access(&gke_options_to_test).is_err());
+
+        // case 7: invalid authorized networks
+        for (cidr_block, display_name) in [
+            ("203.0.113.0/33", "office"),
+            ("office", "office"),
+            ("203.0.113.0/24", "\"office\""),
+        ] {
+            let mut gke_options_to_test = basic_gke_options();
+            gke_options_to_test.master_authorized_networks = vec![MasterAuthorizedNetwork {
+                cidr_block: cidr_block.to_string(),
+                display_name: display_name.to_string(),
+            }];
+            assert!(
+                to_control_plane_access(&gke_options_to_test).is_err(),
+                "{cidr_block} / {display_name} should be rejected"
+            );
+        }
+    }
 }
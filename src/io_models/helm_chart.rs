@@ -5,6 +5,7 @@ use crate::environment::models::gcp::GcpAppExtraSettings;
 use crate::environment::models::helm_chart::{HelmChartError, HelmChartService};
 use crate::environment::models::scaleway::ScwAppExtraSettings;
 use crate::environment::models::selfmanaged::OnPremiseAppExtraSettings;
+use crate::environment::models::service_port::to_service_ports;
 use crate::environment::models::types::{OnPremise, AWS, GCP, SCW};
 use crate::infrastructure::models::build_platform::SshKey;
 use crate::infrastructure::models::cloud_provider::io::{NginxConfigurationSnippet, NginxServerSnippet};
@@ -208,10 +209,10 @@ impl HelmChart {
                 root_path,
             } => models::helm_chart::HelmChartSource::Git {
                 git_url,
-                get_credentials: if git_credentials.is_none() {
-                    Box::new(|| Ok(None))
-                } else {
+                get_credentials: if let Some(GitCredentials::AccessToken(_)) = git_credentials {
                     Box::new(move || fetch_git_token(&*qovery_api, ServiceType::HelmChart, &service_id).map(Some))
+                } else {
+                    Box::new(|| Ok(None))
                 },
                 commit_id,
                 root_path,
@@ -235,10 +236,10 @@ impl HelmChart {
                 values_path,
             } => models::helm_chart::HelmValueSource::Git {
                 git_url,
-                get_credentials: if git_credentials.is_none() {
-                    Box::new(|| Ok(None))
-                } else {
+                get_credentials: if let Some(GitCredentials::AccessToken(_)) = git_credentials {
                     Box::new(move || fetch_git_token(&*qovery_api, ServiceType::HelmChart, &service_id).map(Some))
+                } else {
+                    Box::new(|| Ok(None))
                 },
                 commit_id,
                 values_path,
@@ -268,6 +269,7 @@ impl HelmChart {
                 (k, v)
             })
             .collect();
+        let ports = to_service_ports(&self.long_id, ports, &[]);
         let service: Box<dyn HelmChartService> = match cloud_provider.kubernetes_kind() {
             kubernetes::Kind::Eks | kubernetes::Kind::EksSelfManaged => {
                 Box::new(models::helm_chart::HelmChart::<AWS>::new(
@@ -293,7 +295,7 @@ impl HelmChart {
                     self.advanced_settings,
                     AwsAppExtraSettings {},
                     |transmitter| context.get_event_details(transmitter),
-                    self.ports,
+                    ports,
                 )?)
             }
             kubernetes::Kind::ScwKapsule | kubernetes::Kind::ScwSelfManaged => {
@@ -320,7 +322,7 @@ impl HelmChart {
                     self.advanced_settings,
                     ScwAppExtraSettings {},
                     |transmitter| context.get_event_details(transmitter),
-                    self.ports,
+                    ports,
                 )?)
             }
             kubernetes::Kind::Gke | kubernetes::Kind::GkeSelfManaged => {
@@ -347,7 +349,7 @@ impl HelmChart {
                     self.advanced_settings,
                     GcpAppExtraSettings {},
                     |transmitter| context.get_event_details(transmitter),
-                    self.ports,
+                    ports,
                 )?)
             }
             kubernetes::Kind::OnPremiseSelfManaged => Box::new(models::helm_chart::HelmChart::<OnPremise>::new(
@@ -373,7 +375,7 @@ impl HelmChart {
                 self.advanced_settings,
                 OnPremiseAppExtraSettings {},
                 |transmitter| context.get_event_details(transmitter),
-                self.ports,
+                ports,
             )?),
         };
 
@@ -477,6 +479,7 @@ fn test_helm_deserialization_with_env_variables_with_infos() {
         &VariableInfo {
             value: "value".to_string(),
             is_secret: false,
+            scope: None,
         }
     );
     assert_eq!(
@@ -484,6 +487,7 @@ fn test_helm_deserialization_with_env_variables_with_infos() {
         &VariableInfo {
             value: "my password".to_string(),
             is_secret: true,
+            scope: None,
         }
     );
 }
@@ -1,34 +1,37 @@
+use crate::cmd::git::GitCloneOptions;
 use crate::engine_task::qovery_api::QoveryApi;
 use crate::environment::models;
 use crate::environment::models::aws::AwsAppExtraSettings;
+use crate::environment::models::cron_schedule::CronSchedule;
 use crate::environment::models::gcp::GcpAppExtraSettings;
+use crate::environment::models::graceful_shutdown::validate_termination_grace_period;
 use crate::environment::models::job::{ImageSource, JobError, JobService};
 use crate::environment::models::registry_image_source::RegistryImageSource;
 use crate::environment::models::scaleway::ScwAppExtraSettings;
 use crate::environment::models::selfmanaged::OnPremiseAppExtraSettings;
 use crate::environment::models::types::{OnPremise, AWS, GCP, SCW};
-use crate::infrastructure::models::build_platform::{Build, GitRepository, Image, SshKey};
+use crate::infrastructure::models::build_platform::{Build, GitAccess, GitRepository, Image, SshKey};
 use crate::infrastructure::models::cloud_provider::service::ServiceType;
 use crate::infrastructure::models::cloud_provider::{CloudProvider, Kind};
 use crate::infrastructure::models::container_registry::{ContainerRegistry, ContainerRegistryInfo};
 use crate::infrastructure::models::kubernetes::Kubernetes;
 use crate::io_models::annotations_group::AnnotationsGroup;
 use crate::io_models::application::{to_environment_variable, GitCredentials};
-use crate::io_models::container::Registry;
+use crate::io_models::container::{LifecycleHooks, Registry};
 use crate::io_models::context::Context;
 use crate::io_models::labels_group::LabelsGroup;
 use crate::io_models::models::{CpuArchitecture, KubernetesCpuResourceUnit, KubernetesMemoryResourceUnit};
 use crate::io_models::probe::Probe;
-use crate::io_models::variable_utils::{default_environment_vars_with_info, VariableInfo};
+use crate::io_models::variable_utils::{
+    build_variables, default_environment_vars_with_info, ClusterDefaultVariables, VariableInfo,
+};
 use crate::io_models::{
-    fetch_git_token, normalize_root_and_dockerfile_path, sanitized_git_url, ssh_keys_from_env_vars, Action,
-    MountedFile, QoveryIdentifier,
+    fetch_git_token, normalize_root_and_dockerfile_path, parse_git_url, sanitized_git_url, ssh_keys_from_env_vars,
+    Action, MountedFile, QoveryIdentifier,
 };
-use crate::utilities::to_short_id;
-use base64::engine::general_purpose;
-use base64::Engine;
+use crate::utilities::{to_short_id, validate_timezone};
 use itertools::Itertools;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 use std::time::Duration;
@@ -49,6 +52,14 @@ pub struct JobAdvancedSettings {
     #[serde(alias = "cronjob.success_jobs_history_limit")]
     pub cronjob_success_jobs_history_limit: u32,
 
+    // Max size of the output the job writes in /qovery-output/qovery-output.json, bigger outputs are not read entirely
+    #[serde(alias = "job.output.max_size_in_kib")]
+    pub job_output_max_size_in_kib: u32,
+    // When set, longer output values are truncated and the entries of an oversized output read before the max size
+    // are kept, instead of ignoring the whole output
+    #[serde(alias = "job.output.value_max_size_in_bytes")]
+    pub job_output_value_max_size_in_bytes: Option<u32>,
+
     // Deployment
     #[serde(alias = "deployment.termination_grace_period_seconds")]
     pub deployment_termination_grace_period_seconds: u32,
@@ -62,6 +73,13 @@ pub struct JobAdvancedSettings {
     pub build_cpu_max_in_milli: u32,
     #[serde(alias = "build.ram_max_in_gib")]
     pub build_ram_max_in_gib: u32,
+    #[serde(alias = "build.registry_cache.enabled")]
+    pub build_registry_cache_enabled: bool,
+    // 0 fetches the whole history of the repository
+    #[serde(alias = "build.git.clone_depth")]
+    pub build_git_clone_depth: u32,
+    #[serde(alias = "build.git.lfs.enabled")]
+    pub build_git_lfs_enabled: bool,
 
     #[serde(alias = "security.service_account_name")]
     pub security_service_account_name: String,
@@ -80,9 +98,14 @@ impl Default for JobAdvancedSettings {
             cronjob_concurrency_policy: "Forbid".to_string(),
             cronjob_failed_jobs_history_limit: 1,
             cronjob_success_jobs_history_limit: 1,
+            job_output_max_size_in_kib: 1024,
+            job_output_value_max_size_in_bytes: None,
             build_timeout_max_sec: 30 * 60, // 30 minutes
             build_cpu_max_in_milli: 4000,
             build_ram_max_in_gib: 8,
+            build_registry_cache_enabled: true,
+            build_git_clone_depth: 1,
+            build_git_lfs_enabled: true,
             security_service_account_name: "".to_string(),
             security_read_only_root_filesystem: false,
             security_automount_service_account_token: false,
@@ -90,6 +113,18 @@ impl Default for JobAdvancedSettings {
     }
 }
 
+impl JobAdvancedSettings {
+    /// Only the termination grace period applies to jobs, their pods run to completion and are never drained,
+    /// so the preStop hook is ignored
+    pub fn with_lifecycle_hooks(mut self, lifecycle_hooks: &LifecycleHooks) -> Self {
+        if let Some(termination_grace_period_seconds) = lifecycle_hooks.termination_grace_period_seconds {
+            self.deployment_termination_grace_period_seconds = termination_grace_period_seconds;
+        }
+
+        self
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum LifecycleType {
     TERRAFORM,
@@ -101,10 +136,35 @@ pub enum LifecycleType {
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum JobSchedule {
-    OnStart { lifecycle_type: LifecycleType },
-    OnPause { lifecycle_type: LifecycleType },
-    OnDelete { lifecycle_type: LifecycleType },
-    Cron { schedule: String, timezone: String },
+    OnStart {
+        lifecycle_type: LifecycleType,
+    },
+    OnPause {
+        lifecycle_type: LifecycleType,
+    },
+    OnDelete {
+        lifecycle_type: LifecycleType,
+    },
+    Cron {
+        #[serde(deserialize_with = "deserialize_cron_schedule")]
+        schedule: CronSchedule,
+        #[serde(deserialize_with = "deserialize_cron_timezone")]
+        timezone: String,
+    },
+}
+
+/// Invalid schedules are rejected with the payload, instead of failing when the cronjob is applied
+fn deserialize_cron_schedule<'de, D: Deserializer<'de>>(deserializer: D) -> Result<CronSchedule, D::Error> {
+    let schedule = String::deserialize(deserializer)?;
+    CronSchedule::new(&schedule).map_err(|err| de::Error::custom(format!("schedule.cron.schedule: {err}")))
+}
+
+fn deserialize_cron_timezone<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let timezone = String::deserialize(deserializer)?;
+    validate_timezone(&timezone)
+        .map_err(|message| de::Error::custom(format!("schedule.cron.timezone `{timezone}`: {message}")))?;
+
+    Ok(timezone)
 }
 
 impl JobSchedule {
@@ -186,6 +246,8 @@ pub struct Job {
     pub should_delete_shared_registry: bool,
     #[serde(default)] // Default is false
     pub shared_image_feature_enabled: bool,
+    #[serde(flatten)]
+    pub lifecycle_hooks: LifecycleHooks,
 }
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
@@ -238,52 +300,50 @@ impl Job {
         // Convert our root path to an relative path to be able to append them correctly
         let (root_path, dockerfile_path) = normalize_root_and_dockerfile_path(root_path, dockerfile_path);
 
-        let url = Url::parse(git_url).unwrap_or_else(|_| {
+        let url = parse_git_url(git_url).unwrap_or_else(|| {
             Url::parse("https://invalid-git-url.com").expect("Error while trying to parse invalid git url")
         });
+        let ssh_key_credentials = match git_credentials {
+            Some(GitCredentials::SshKey(ssh_key_credentials)) => Some(ssh_key_credentials),
+            _ => None,
+        };
 
-        let mut disable_build_cache = false;
+        let (environment_variables, disable_build_cache) = build_variables(&self.environment_vars_with_infos);
         let mut build = Build {
             git_repository: GitRepository {
                 url,
-                get_credentials: if git_credentials.is_none() {
-                    None
-                } else {
+                get_credentials: if let Some(GitCredentials::AccessToken(_)) = git_credentials {
                     let id = self.long_id;
                     Some(Box::new(move || fetch_git_token(&*qovery_api, ServiceType::Job, &id)))
+                } else {
+                    None
                 },
+                deploy_key: ssh_key_credentials.map(|credentials| credentials.to_deploy_key()),
                 ssh_keys,
                 commit_id: commit_id.clone(),
                 dockerfile_path,
                 dockerfile_content: dockerfile_content.clone(),
                 root_path,
+                git_access: GitAccess::Direct,
+                source_fetcher: None,
+                clone_options: GitCloneOptions {
+                    depth: Some(self.advanced_settings.build_git_clone_depth),
+                    lfs: self.advanced_settings.build_git_lfs_enabled,
+                    ssh_host_verification: ssh_key_credentials
+                        .map(|credentials| credentials.host_verification())
+                        .unwrap_or_default(),
+                },
             },
             image: self.to_image(commit_id.to_string(), registry_url, cluster_id, git_url),
-            environment_variables: self
-                .environment_vars_with_infos
-                .iter()
-                .filter_map(|(k, variable_infos)| {
-                    // Remove special vars
-                    let v = String::from_utf8(
-                        general_purpose::STANDARD
-                            .decode(variable_infos.value.as_bytes())
-                            .unwrap_or_default(),
-                    )
-                    .unwrap_or_default();
-                    if k == "QOVERY_DISABLE_BUILD_CACHE" && v.to_lowercase() == "true" {
-                        disable_build_cache = true;
-                        return None;
-                    }
-
-                    Some((k.clone(), v))
-                })
-                .collect::<BTreeMap<_, _>>(),
-            disable_cache: disable_build_cache,
+            environment_variables,
+            secrets: BTreeMap::new(),
+            disable_cache: disable_build_cache || !self.advanced_settings.build_registry_cache_enabled,
             timeout: Duration::from_secs(self.advanced_settings.build_timeout_max_sec as u64),
             architectures,
             max_cpu_in_milli: self.advanced_settings.build_cpu_max_in_milli,
             max_ram_in_gib: self.advanced_settings.build_ram_max_in_gib,
             registries: self.container_registries.registries.clone(),
+            registry_mirrors: vec![],
         };
 
         build.compute_image_tag();
@@ -326,9 +386,10 @@ impl Job {
         annotations_group: &BTreeMap<Uuid, AnnotationsGroup>,
         labels_group: &BTreeMap<Uuid, LabelsGroup>,
     ) -> Result<Box<dyn JobService>, JobError> {
+        let cluster_default_variables = ClusterDefaultVariables::from(cluster.advanced_settings());
         let image_source = match self.source {
             JobSource::Docker { .. } => {
-                let build = match self.to_build(
+                let mut build = match self.to_build(
                     default_container_registry.registry_info(),
                     context.qovery_api.clone(),
                     cluster.cpu_architectures(),
@@ -339,6 +400,8 @@ impl Job {
                         "Cannot convert docker JobSource to Build source".to_string(),
                     )),
                 }?;
+                cluster_default_variables
+                    .merge_into_build(&self.environment_vars_with_infos, &mut build.environment_variables);
 
                 ImageSource::Build {
                     source: Box::new(build),
@@ -359,12 +422,19 @@ impl Job {
                         image,
                         tag,
                         registry_mirroring_mode: cluster.advanced_settings().registry_mirroring_mode.clone(),
+                        promotion: None,
                     }),
                 }
             }
         };
 
-        let environment_variables = to_environment_variable(self.environment_vars_with_infos);
+        validate_termination_grace_period(self.lifecycle_hooks.termination_grace_period_seconds)
+            .map_err(|err| JobError::InvalidConfig(err.to_string()))?;
+        let advanced_settings = self.advanced_settings.with_lifecycle_hooks(&self.lifecycle_hooks);
+
+        let mut environment_vars_with_infos = self.environment_vars_with_infos;
+        cluster_default_variables.inject(&self.long_id, &mut environment_vars_with_infos);
+        let environment_variables = to_environment_variable(environment_vars_with_infos);
         let annotations_groups = self
             .annotations_group_ids
             .iter()
@@ -402,7 +472,7 @@ impl Job {
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.advanced_settings,
+                advanced_settings,
                 self.readiness_probe.map(|p| p.to_domain()),
                 self.liveness_probe.map(|p| p.to_domain()),
                 AwsAppExtraSettings {},
@@ -434,7 +504,7 @@ impl Job {
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.advanced_settings,
+                advanced_settings,
                 self.readiness_probe.map(|p| p.to_domain()),
                 self.liveness_probe.map(|p| p.to_domain()),
                 ScwAppExtraSettings {},
@@ -466,7 +536,7 @@ impl Job {
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.advanced_settings,
+                advanced_settings,
                 self.readiness_probe.map(|p| p.to_domain()),
                 self.liveness_probe.map(|p| p.to_domain()),
                 GcpAppExtraSettings {},
@@ -498,7 +568,7 @@ impl Job {
                     .iter()
                     .map(|e| e.to_domain())
                     .collect::<BTreeSet<_>>(),
-                self.advanced_settings,
+                advanced_settings,
                 self.readiness_probe.map(|p| p.to_domain()),
                 self.liveness_probe.map(|p| p.to_domain()),
                 OnPremiseAppExtraSettings {},
@@ -512,3 +582,58 @@ impl Job {
         Ok(service)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_ignores_pre_stop_hook() {
+        let advanced_settings = JobAdvancedSettings::default().with_lifecycle_hooks(&LifecycleHooks {
+            pre_stop_exec_command: Some(vec!["/bin/drain.sh".to_string()]),
+            termination_grace_period_seconds: None,
+        });
+        assert_eq!(advanced_settings, JobAdvancedSettings::default());
+
+        let advanced_settings = JobAdvancedSettings::default().with_lifecycle_hooks(&LifecycleHooks {
+            pre_stop_exec_command: Some(vec!["/bin/drain.sh".to_string()]),
+            termination_grace_period_seconds: Some(300),
+        });
+        assert_eq!(advanced_settings.deployment_termination_grace_period_seconds, 300);
+    }
+
+    fn cron_schedule(schedule: &str, timezone: &str) -> Result<JobSchedule, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({ "cron": { "schedule": schedule, "timezone": timezone } }))
+    }
+
+    #[test]
+    fn test_cron_schedule_deserialization() {
+        for schedule in ["@hourly", "@midnight", "*/15 * * * *", "0 9 * * MON-FRI"] {
+            assert!(cron_schedule(schedule, "Europe/Paris").is_ok(), "`{schedule}` should be valid");
+        }
+
+        let Err(err) = cron_schedule("0 25 * * *", "Etc/UTC") else {
+            panic!("`0 25 * * *` should be invalid");
+        };
+        assert_eq!(
+            err.to_string(),
+            "schedule.cron.schedule: invalid cron expression `0 25 * * *` at position 3: hour `25`: 25 is out of range 0-23"
+        );
+        assert!(cron_schedule("@every 1h", "Etc/UTC").is_err());
+        assert!(cron_schedule("CRON_TZ=Europe/Paris 0 9 * * *", "Etc/UTC").is_err());
+    }
+
+    #[test]
+    fn test_cron_timezone_deserialization() {
+        assert!(cron_schedule("0 9 * * *", "Etc/UTC").is_ok());
+        assert!(cron_schedule("0 9 * * *", "America/New_York").is_ok());
+
+        let Err(err) = cron_schedule("0 9 * * *", "Europe/Lille") else {
+            panic!("`Europe/Lille` should be invalid");
+        };
+        assert!(err
+            .to_string()
+            .starts_with("schedule.cron.timezone `Europe/Lille`: expected a timezone name from the IANA database"));
+        assert!(cron_schedule("0 9 * * *", "").is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::environment::models;
+use crate::environment::models::k8s_manifests::{K8sManifestsError, K8sManifestsService};
+use crate::infrastructure::models::build_platform::SshKey;
+use crate::infrastructure::models::cloud_provider::service::ServiceType;
+use crate::io_models::application::GitCredentials;
+use crate::io_models::context::Context;
+use crate::io_models::variable_utils::{default_environment_vars_with_info, VariableInfo};
+use crate::io_models::{fetch_git_token, ssh_keys_from_env_vars, Action};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use url::Url;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+pub struct K8sManifests {
+    pub long_id: Uuid,
+    pub name: String,
+    pub kube_name: String,
+    pub action: Action,
+    pub git_url: Url,
+    pub git_credentials: Option<GitCredentials>,
+    pub commit_id: String,
+    /// Path of the directory, inside the repository, containing the manifests or the kustomization file
+    pub root_path: PathBuf,
+    /// Run `kubectl kustomize` on the directory instead of reading the YAML files it contains
+    #[serde(default)]
+    pub kustomize: bool,
+    pub timeout_sec: u64,
+    /// Kinds allowed to be cluster scoped or to be applied outside of the environment namespace
+    #[serde(default)]
+    pub allowed_kinds_outside_namespace: Vec<String>,
+    /// Only used to retrieve the git ssh keys
+    #[serde(default = "default_environment_vars_with_info")]
+    pub environment_vars_with_infos: BTreeMap<String, VariableInfo>,
+}
+
+impl K8sManifests {
+    pub fn to_k8s_manifests_domain(self, context: &Context) -> Result<Box<dyn K8sManifestsService>, K8sManifestsError> {
+        let ssh_keys: Vec<SshKey> = ssh_keys_from_env_vars(&self.environment_vars_with_infos);
+        let qovery_api = context.qovery_api.clone();
+        let service_id = self.long_id;
+
+        let service = models::k8s_manifests::K8sManifests::new(
+            context,
+            self.long_id,
+            self.name,
+            self.kube_name,
+            self.action.to_service_action(),
+            self.git_url,
+            if let Some(GitCredentials::AccessToken(_)) = self.git_credentials {
+                Box::new(move || fetch_git_token(&*qovery_api, ServiceType::K8sManifests, &service_id).map(Some))
+            } else {
+                Box::new(|| Ok(None))
+            },
+            self.commit_id,
+            self.root_path,
+            ssh_keys,
+            self.kustomize,
+            Duration::from_secs(self.timeout_sec),
+            self.allowed_kinds_outside_namespace,
+            |transmitter| context.get_event_details(transmitter),
+        )?;
+
+        Ok(Box::new(service))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io_models::k8s_manifests::K8sManifests;
+
+    #[test]
+    fn test_k8s_manifests_deserialization_with_defaults() {
+        let data = r#"
+        {
+          "long_id": "f84d837d-717e-4c39-bba4-573b22c5f848",
+          "name": "name",
+          "kube_name": "kube-name",
+          "action": "CREATE",
+          "git_url": "https://github.com/Qovery/engine-testing.git",
+          "git_credentials": null,
+          "commit_id": "fc575a2f3be0b9100492c8a463bf18134a8698a5",
+          "root_path": "/manifests/overlays/prod",
+          "timeout_sec": 600
+        }"#;
+
+        let manifests: K8sManifests = serde_json::from_str(data).unwrap();
+        assert!(!manifests.kustomize);
+        assert!(manifests.allowed_kinds_outside_namespace.is_empty());
+        assert!(manifests.environment_vars_with_infos.is_empty());
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Payload of a `generate_kubeconfig` task, giving a user a temporary kubectl access to its cluster
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KubeconfigRequest {
+    pub access: KubeconfigAccess,
+    #[serde(default = "default_kubeconfig_ttl_in_seconds")]
+    pub ttl_in_seconds: u32,
+    pub requester: KubeconfigRequester,
+    /// Pre-signed url where the encrypted kubeconfig is uploaded, valid for a single object
+    pub upload_url: Url,
+}
+
+fn default_kubeconfig_ttl_in_seconds() -> u32 {
+    3600
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum KubeconfigAccess {
+    /// Read access on every namespace, secrets excluded
+    ReadOnly,
+    /// Full access restricted to a single namespace
+    Namespace { namespace: String },
+}
+
+/// Who asked for the kubeconfig, recorded in the audit event
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct KubeconfigRequester {
+    pub user_id: String,
+    pub email: Option<String>,
+    pub ip_address: Option<String>,
+    pub reason: Option<String>,
+}
@@ -11,24 +11,34 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
+use url::Url;
 use uuid::Uuid;
 
 pub mod annotations_group;
 pub mod application;
+pub mod bucket;
+pub mod certificate_watchdog;
 pub mod container;
+pub mod cluster_health;
 pub mod context;
 pub mod database;
+pub mod deprecations;
 pub mod engine_location;
 pub mod engine_request;
 pub mod environment;
+pub mod feature_flags;
 mod gke;
 pub mod helm_chart;
 pub mod job;
+pub mod k8s_manifests;
+pub mod kubeconfig;
 pub mod labels_group;
 pub mod models;
 pub mod probe;
 pub mod router;
+pub mod service_inventory;
 mod types;
+pub mod variable_preview;
 pub mod variable_utils;
 
 #[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize, Hash, Debug, Default)]
@@ -243,6 +253,20 @@ pub fn fetch_git_token(
     })
 }
 
+/// Parses the url of a git repository, scp-like ssh urls (`git@gitlab.com:group/project.git`) are converted to ssh:// ones
+pub fn parse_git_url(git_url: &str) -> Option<Url> {
+    if let Ok(url) = Url::parse(git_url) {
+        return Some(url);
+    }
+
+    let (user_and_host, path) = git_url.split_once(':')?;
+    if !user_and_host.contains('@') || user_and_host.contains('/') {
+        return None;
+    }
+
+    Url::parse(&format!("ssh://{}/{}", user_and_host, path.trim_start_matches('/'))).ok()
+}
+
 pub fn sanitized_git_url(git_url: &str) -> String {
     let sanitized_git_url = git_url
         .to_ascii_lowercase()
@@ -252,3 +276,26 @@ pub fn sanitized_git_url(git_url: &str) -> String {
         .replace_all(&sanitized_git_url, "-")
         .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_url() {
+        assert_eq!(
+            parse_git_url("https://github.com/Qovery/engine.git").unwrap().as_str(),
+            "https://github.com/Qovery/engine.git"
+        );
+        assert_eq!(
+            parse_git_url("ssh://git@gitlab.qovery.com:2222/qovery/engine.git").unwrap().as_str(),
+            "ssh://git@gitlab.qovery.com:2222/qovery/engine.git"
+        );
+        assert_eq!(
+            parse_git_url("git@gitlab.qovery.com:qovery/engine.git").unwrap().as_str(),
+            "ssh://git@gitlab.qovery.com/qovery/engine.git"
+        );
+        assert!(parse_git_url("gitlab.qovery.com/qovery/engine.git").is_none());
+        assert!(parse_git_url("qovery/engine@v1:main").is_none());
+    }
+}
@@ -8,6 +8,7 @@ use std::str::FromStr;
 use uuid::Uuid;
 
 use crate::helm::ChartValuesGenerated;
+use crate::io_models::router::RouteProtocol;
 
 #[derive(Serialize, Debug, Clone, Eq, PartialEq, Hash)]
 pub struct EnvironmentVariable {
@@ -110,6 +111,7 @@ pub struct HostDataTemplate {
 pub struct Route {
     pub path: String,
     pub service_long_id: Uuid,
+    pub protocol: RouteProtocol,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -146,6 +148,57 @@ pub struct NodeGroups {
     pub instance_type: String,
     pub disk_size_in_gib: i32,
     pub instance_architecture: CpuArchitecture,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    #[serde(default)]
+    pub capacity_type: NodeGroupCapacityType,
+}
+
+/// Distinct architectures of the node groups, the images deployed on the cluster must be built for each of them
+pub fn node_groups_cpu_architectures(node_groups: &[NodeGroups]) -> Vec<CpuArchitecture> {
+    [CpuArchitecture::AMD64, CpuArchitecture::ARM64]
+        .into_iter()
+        .filter(|arch| {
+            node_groups
+                .iter()
+                .any(|node_group| node_group.instance_architecture == *arch)
+        })
+        .collect()
+}
+
+/// True when every node of the cluster can be reclaimed by the cloud provider at any time
+pub fn node_groups_are_spot_only(node_groups: &[NodeGroups]) -> bool {
+    !node_groups.is_empty()
+        && node_groups
+            .iter()
+            .all(|node_group| node_group.capacity_type == NodeGroupCapacityType::Spot)
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Hash, Default)]
+pub enum NodeGroupCapacityType {
+    #[default]
+    OnDemand,
+    Spot,
+    /// The first `on_demand_base` nodes are on-demand, `spot_percentage` percent of the nodes above it are spot
+    Mixed {
+        on_demand_base: u32,
+        spot_percentage: u32,
+    },
+}
+
+impl Display for NodeGroupCapacityType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeGroupCapacityType::OnDemand => write!(f, "OnDemand"),
+            NodeGroupCapacityType::Spot => write!(f, "Spot"),
+            NodeGroupCapacityType::Mixed {
+                on_demand_base,
+                spot_percentage,
+            } => write!(f, "Mixed (on-demand base: {on_demand_base}, spot: {spot_percentage}%)"),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Hash)]
@@ -163,6 +216,60 @@ impl Display for CpuArchitecture {
     }
 }
 
+/// Kubernetes taint set on every node of a node group, only pods tolerating it get scheduled there
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct Taint {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+    pub effect: TaintEffect,
+}
+
+/// Effects as spelled by Kubernetes, any other value is rejected at payload deserialization
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum TaintEffect {
+    NoSchedule,
+    PreferNoSchedule,
+    NoExecute,
+}
+
+impl Display for TaintEffect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TaintEffect::NoSchedule => write!(f, "NoSchedule"),
+            TaintEffect::PreferNoSchedule => write!(f, "PreferNoSchedule"),
+            TaintEffect::NoExecute => write!(f, "NoExecute"),
+        }
+    }
+}
+
+// name part of a label key, a label value or a taint value
+// https://kubernetes.io/docs/concepts/overview/working-with-objects/labels/#syntax-and-character-set
+static KUBERNETES_LABEL_NAME_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[a-zA-Z0-9]([-_.a-zA-Z0-9]{0,61}[a-zA-Z0-9])?$").unwrap());
+static KUBERNETES_LABEL_PREFIX_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[a-z0-9]([-a-z0-9]*[a-z0-9])?(\.[a-z0-9]([-a-z0-9]*[a-z0-9])?)*$").unwrap());
+
+/// Label or taint key: an optional DNS subdomain prefix followed by a `/` and a name of 63 characters at most
+pub fn is_valid_kubernetes_label_key(key: &str) -> bool {
+    let (prefix, name) = match key.split_once('/') {
+        Some((prefix, name)) => (Some(prefix), name),
+        None => (None, key),
+    };
+
+    let is_valid_prefix = match prefix {
+        Some(prefix) => prefix.len() <= 253 && KUBERNETES_LABEL_PREFIX_REGEX.is_match(prefix),
+        None => true,
+    };
+
+    is_valid_prefix && KUBERNETES_LABEL_NAME_REGEX.is_match(name)
+}
+
+/// Label or taint value: empty or a name of 63 characters at most
+pub fn is_valid_kubernetes_label_value(value: &str) -> bool {
+    value.is_empty() || KUBERNETES_LABEL_NAME_REGEX.is_match(value)
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 pub struct NodeGroupsWithDesiredState {
     pub name: String,
@@ -174,6 +281,12 @@ pub struct NodeGroupsWithDesiredState {
     pub instance_type: String,
     pub disk_size_in_gib: i32,
     pub instance_architecture: CpuArchitecture,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    #[serde(default)]
+    pub capacity_type: NodeGroupCapacityType,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -346,10 +459,15 @@ impl CustomerHelmChartsOverride {
 
 #[cfg(test)]
 mod tests {
-    use crate::io_models::models::{KubernetesCpuResourceUnit, KubernetesMemoryResourceUnit};
+    use crate::cmd::docker::{platforms_flag, Architecture};
+    use crate::io_models::models::{
+        node_groups_are_spot_only, node_groups_cpu_architectures, CpuArchitecture, KubernetesCpuResourceUnit,
+        KubernetesMemoryResourceUnit, NodeGroupCapacityType, NodeGroups, Taint, TaintEffect,
+    };
     use serde::Deserialize;
     use serde_derive::Serialize;
     use serde_with::DisplayFromStr;
+    use std::collections::BTreeMap;
     use std::str::FromStr;
 
     #[test]
@@ -555,4 +673,224 @@ mod tests {
             }
         );
     }
+
+    fn node_group(name: &str, instance_architecture: CpuArchitecture) -> NodeGroups {
+        NodeGroups {
+            name: name.to_string(),
+            id: None,
+            min_nodes: 1,
+            max_nodes: 3,
+            desired_nodes: None,
+            instance_type: "t3a.large".to_string(),
+            disk_size_in_gib: 50,
+            instance_architecture,
+            labels: BTreeMap::new(),
+            taints: vec![],
+            capacity_type: NodeGroupCapacityType::OnDemand,
+        }
+    }
+
+    fn platforms_flag_of(node_groups: &[NodeGroups]) -> Option<String> {
+        let architectures = node_groups_cpu_architectures(node_groups)
+            .iter()
+            .map(Architecture::from)
+            .collect::<Vec<_>>();
+        platforms_flag(&architectures)
+    }
+
+    #[test]
+    fn test_platforms_flag_from_node_groups() {
+        assert_eq!(platforms_flag_of(&[]), None);
+        assert_eq!(
+            platforms_flag_of(&[
+                node_group("a", CpuArchitecture::AMD64),
+                node_group("b", CpuArchitecture::AMD64)
+            ]),
+            Some("--platform=linux/amd64".to_string())
+        );
+        assert_eq!(
+            platforms_flag_of(&[node_group("a", CpuArchitecture::ARM64)]),
+            Some("--platform=linux/arm64".to_string())
+        );
+        // mixed clusters get a manifest list, whatever the order of the node groups
+        assert_eq!(
+            platforms_flag_of(&[
+                node_group("a", CpuArchitecture::ARM64),
+                node_group("b", CpuArchitecture::AMD64),
+                node_group("c", CpuArchitecture::ARM64),
+            ]),
+            Some("--platform=linux/amd64,linux/arm64".to_string())
+        );
+    }
+
+    #[test]
+    fn test_node_groups_payload_without_labels_and_taints() {
+        let json = r#"
+        {
+            "name": "default",
+            "id": null,
+            "min_nodes": 1,
+            "max_nodes": 3,
+            "desired_nodes": null,
+            "instance_type": "t3a.large",
+            "disk_size_in_gib": 50,
+            "instance_architecture": "AMD64"
+        }
+        "#;
+
+        let deserialized = serde_json::from_str::<NodeGroups>(json).expect("Should be Ok");
+
+        assert_eq!(deserialized, node_group("default", CpuArchitecture::AMD64));
+    }
+
+    #[test]
+    fn test_node_groups_payload_with_labels_and_taints() {
+        let json = r#"
+        {
+            "name": "gpu",
+            "id": null,
+            "min_nodes": 1,
+            "max_nodes": 3,
+            "desired_nodes": null,
+            "instance_type": "t3a.large",
+            "disk_size_in_gib": 50,
+            "instance_architecture": "AMD64",
+            "labels": { "qovery.com/workload": "gpu" },
+            "taints": [
+                { "key": "nvidia.com/gpu", "value": "true", "effect": "NoSchedule" },
+                { "key": "dedicated", "effect": "PreferNoSchedule" }
+            ]
+        }
+        "#;
+
+        let deserialized = serde_json::from_str::<NodeGroups>(json).expect("Should be Ok");
+
+        let expected = NodeGroups {
+            labels: BTreeMap::from([("qovery.com/workload".to_string(), "gpu".to_string())]),
+            taints: vec![
+                Taint {
+                    key: "nvidia.com/gpu".to_string(),
+                    value: "true".to_string(),
+                    effect: TaintEffect::NoSchedule,
+                },
+                Taint {
+                    key: "dedicated".to_string(),
+                    value: "".to_string(),
+                    effect: TaintEffect::PreferNoSchedule,
+                },
+            ],
+            ..node_group("gpu", CpuArchitecture::AMD64)
+        };
+        assert_eq!(deserialized, expected);
+
+        // round trip
+        let serialized = serde_json::to_string(&deserialized).expect("Should be Ok");
+        assert_eq!(serde_json::from_str::<NodeGroups>(&serialized).expect("Should be Ok"), expected);
+
+        // effects are spelled the Kubernetes way
+        let invalid_effect = json.replace("PreferNoSchedule", "NO_SCHEDULE");
+        assert!(serde_json::from_str::<NodeGroups>(&invalid_effect).is_err());
+    }
+
+    #[test]
+    fn test_node_groups_labels_and_taints_validation() {
+        let valid = NodeGroups {
+            labels: BTreeMap::from([
+                ("qovery.com/workload".to_string(), "gpu".to_string()),
+                ("team".to_string(), "".to_string()),
+            ]),
+            taints: vec![
+                Taint {
+                    key: "nvidia.com/gpu".to_string(),
+                    value: "true".to_string(),
+                    effect: TaintEffect::NoSchedule,
+                },
+                // same key with another effect is allowed
+                Taint {
+                    key: "nvidia.com/gpu".to_string(),
+                    value: "true".to_string(),
+                    effect: TaintEffect::NoExecute,
+                },
+            ],
+            ..node_group("gpu", CpuArchitecture::AMD64)
+        };
+        assert!(valid.validate_labels_and_taints().is_ok());
+
+        for invalid_key in ["", "-team", "team/", "Qovery.com/workload", "a/b/c", &"a".repeat(64)] {
+            let invalid = NodeGroups {
+                labels: BTreeMap::from([(invalid_key.to_string(), "gpu".to_string())]),
+                ..valid.clone()
+            };
+            assert!(invalid.validate_labels_and_taints().is_err(), "{invalid_key}");
+        }
+
+        let invalid_value = NodeGroups {
+            labels: BTreeMap::from([("team".to_string(), "machine learning".to_string())]),
+            ..valid.clone()
+        };
+        assert!(invalid_value.validate_labels_and_taints().is_err());
+
+        let invalid_taint = NodeGroups {
+            taints: vec![Taint {
+                key: "nvidia.com/gpu".to_string(),
+                value: "true:false".to_string(),
+                effect: TaintEffect::NoSchedule,
+            }],
+            ..valid.clone()
+        };
+        assert!(invalid_taint.validate_labels_and_taints().is_err());
+
+        let duplicated_taint = NodeGroups {
+            taints: vec![valid.taints[0].clone(), valid.taints[0].clone()],
+            ..valid.clone()
+        };
+        assert!(duplicated_taint.validate_labels_and_taints().is_err());
+    }
+
+    #[test]
+    fn test_node_groups_capacity_type() {
+        let json = r#"
+        {
+            "name": "mixed",
+            "id": null,
+            "min_nodes": 1,
+            "max_nodes": 10,
+            "desired_nodes": null,
+            "instance_type": "t3a.large",
+            "disk_size_in_gib": 50,
+            "instance_architecture": "AMD64",
+            "capacity_type": { "Mixed": { "on_demand_base": 2, "spot_percentage": 75 } }
+        }
+        "#;
+
+        let deserialized = serde_json::from_str::<NodeGroups>(json).expect("Should be Ok");
+        assert_eq!(
+            deserialized.capacity_type,
+            NodeGroupCapacityType::Mixed {
+                on_demand_base: 2,
+                spot_percentage: 75
+            }
+        );
+        assert!(deserialized.validate_capacity_type().is_ok());
+
+        let invalid = NodeGroups {
+            capacity_type: NodeGroupCapacityType::Mixed {
+                on_demand_base: 2,
+                spot_percentage: 101,
+            },
+            ..deserialized
+        };
+        assert!(invalid.validate_capacity_type().is_err());
+
+        let spot = |name| NodeGroups {
+            capacity_type: NodeGroupCapacityType::Spot,
+            ..node_group(name, CpuArchitecture::AMD64)
+        };
+        assert!(!node_groups_are_spot_only(&[]));
+        assert!(node_groups_are_spot_only(&[spot("a"), spot("b")]));
+        assert!(!node_groups_are_spot_only(&[
+            spot("a"),
+            node_group("b", CpuArchitecture::AMD64)
+        ]));
+    }
 }
@@ -52,3 +52,82 @@ impl Probe {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn probe(r#type: ProbeType) -> Probe {
+        Probe {
+            r#type,
+            port: 9000,
+            initial_delay_seconds: 5,
+            period_seconds: 10,
+            timeout_seconds: 1,
+            success_threshold: 1,
+            failure_threshold: 3,
+        }
+    }
+
+    #[test]
+    fn test_probe_serde_round_trip() {
+        let probes = vec![
+            probe(ProbeType::Exec {
+                commands: vec!["cat".to_string(), "/tmp/healthy".to_string()],
+            }),
+            probe(ProbeType::Http {
+                path: "/health".to_string(),
+                scheme: "HTTP".to_string(),
+            }),
+            probe(ProbeType::Tcp { host: None }),
+            probe(ProbeType::Grpc { service: None }),
+            probe(ProbeType::Grpc {
+                service: Some("grpc.health.v1.Health".to_string()),
+            }),
+        ];
+
+        for probe in probes {
+            let json = serde_json::to_string(&probe).unwrap();
+            let deserialized: Probe = serde_json::from_str(&json).unwrap();
+            assert!(deserialized == probe, "probe does not round trip: {json}");
+        }
+    }
+
+    #[test]
+    fn test_grpc_probe_deserialization() {
+        let grpc_probe: Probe = serde_json::from_value(json!({
+            "type": { "grpc": { "service": "grpc.health.v1.Health" } },
+            "port": 9000,
+            "initial_delay_seconds": 5,
+            "period_seconds": 10,
+            "timeout_seconds": 1,
+            "success_threshold": 1,
+            "failure_threshold": 3,
+        }))
+        .unwrap();
+        assert!(
+            grpc_probe
+                == probe(ProbeType::Grpc {
+                    service: Some("grpc.health.v1.Health".to_string())
+                })
+        );
+
+        // the service is optional, the server's overall health is checked without it
+        let grpc_probe: Probe = serde_json::from_value(json!({
+            "type": { "grpc": {} },
+            "port": 9000,
+            "initial_delay_seconds": 5,
+            "period_seconds": 10,
+            "timeout_seconds": 1,
+            "success_threshold": 1,
+            "failure_threshold": 3,
+        }))
+        .unwrap();
+        assert!(grpc_probe == probe(ProbeType::Grpc { service: None }));
+        assert!(matches!(
+            grpc_probe.to_domain().r#type,
+            models::ProbeType::Grpc { service: None }
+        ));
+    }
+}
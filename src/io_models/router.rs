@@ -11,6 +11,8 @@ use crate::io_models::context::Context;
 use crate::io_models::labels_group::LabelsGroup;
 use crate::io_models::Action;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
 use uuid::Uuid;
 
 fn default_generate_certificate() -> bool {
@@ -31,6 +33,40 @@ pub struct Router {
     pub public_port: u16,
     pub custom_domains: Vec<CustomDomain>,
     pub routes: Vec<Route>,
+    /// Users allowed through the basic auth of the router, basic auth is disabled when empty
+    #[serde(default)]
+    pub basic_auth_credentials: Vec<BasicAuthCredential>,
+    /// nginx annotations added to the ingresses of the router, the ones set by Qovery take precedence
+    #[serde(default)]
+    pub extra_ingress_annotations: BTreeMap<String, String>,
+    /// Allows snippets in the extra ingress annotations, they inject raw nginx configuration
+    #[serde(default)]
+    pub allow_unsafe_ingress_annotations: bool,
+}
+
+/// The password is either given in clear or already hashed with bcrypt
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+#[serde(untagged)]
+pub enum BasicAuthCredential {
+    Password { username: String, password: String },
+    BcryptHash { username: String, password_hash: String },
+}
+
+impl BasicAuthCredential {
+    pub fn username(&self) -> &str {
+        match self {
+            BasicAuthCredential::Password { username, .. } => username,
+            BasicAuthCredential::BcryptHash { username, .. } => username,
+        }
+    }
+
+    /// Value to obfuscate in logs
+    pub fn secret(&self) -> &str {
+        match self {
+            BasicAuthCredential::Password { password, .. } => password,
+            BasicAuthCredential::BcryptHash { password_hash, .. } => password_hash,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
@@ -47,6 +83,33 @@ pub struct CustomDomain {
 pub struct Route {
     pub path: String,
     pub service_long_id: Uuid,
+    #[serde(default)]
+    pub protocol: RouteProtocol,
+}
+
+/// Protocol spoken by the clients of a route, it selects the nginx configuration of the ingresses
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RouteProtocol {
+    /// Each port of the service is served according to its own protocol
+    #[default]
+    Http1,
+    Grpc,
+    /// gRPC-Web requests are forwarded as is to a gRPC port, the service has to handle them
+    GrpcWeb,
+    /// Idle connections are kept open longer
+    WebSocket,
+}
+
+impl Display for RouteProtocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RouteProtocol::Http1 => "HTTP1",
+            RouteProtocol::Grpc => "GRPC",
+            RouteProtocol::GrpcWeb => "GRPC_WEB",
+            RouteProtocol::WebSocket => "WEB_SOCKET",
+        })
+    }
 }
 
 impl Router {
@@ -75,6 +138,7 @@ impl Router {
             .map(|x| crate::io_models::models::Route {
                 path: x.path.clone(),
                 service_long_id: x.service_long_id,
+                protocol: x.protocol,
             })
             .collect::<Vec<_>>();
 
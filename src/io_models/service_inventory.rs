@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Payload of a `service_inventory` task, scheduled by the core to reconcile the services it knows with the cluster
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServiceInventoryRequest {
+    /// Pods listed before the inventory stops, the document is then flagged as truncated
+    #[serde(default = "default_max_pods")]
+    pub max_pods: usize,
+    /// Pre-signed url where the inventory document is uploaded, valid for a single object
+    pub upload_url: Url,
+}
+
+fn default_max_pods() -> usize {
+    20_000
+}
@@ -0,0 +1,847 @@
+use crate::io_models::variable_utils::VariableInjectionScope;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+/// Scope where a variable is defined, ordered by precedence: a service variable wins over an environment one,
+/// which wins over a project one.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VariableScope {
+    Project,
+    Environment,
+    Service,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VariableDefinitionKind {
+    /// Value is base64 encoded
+    Value { value: String },
+    /// Exposes the value of another variable under its own key
+    Alias { aliased_variable_id: Uuid },
+    /// Replaces the value of a variable of a broader scope, keeping its key
+    Override {
+        overridden_variable_id: Uuid,
+        value: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct VariableDefinition {
+    pub id: Uuid,
+    pub key: String,
+    pub scope: VariableScope,
+    #[serde(default)]
+    pub is_secret: bool,
+    /// Aliases and overrides are exposed at the same stages as the variable they point to
+    #[serde(default)]
+    pub injection_scope: VariableInjectionScope,
+    #[serde(flatten)]
+    pub kind: VariableDefinitionKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServiceVariables {
+    pub service_id: Uuid,
+    pub variables: Vec<VariableDefinition>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EnvironmentVariables {
+    pub environment_id: Uuid,
+    pub variables: Vec<VariableDefinition>,
+    pub services: Vec<ServiceVariables>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "action", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VariableChange {
+    /// Creates or updates a variable. Environment and service ids are only needed to create
+    /// an environment or a service variable.
+    Upsert {
+        variable: VariableDefinition,
+        environment_id: Option<Uuid>,
+        service_id: Option<Uuid>,
+    },
+    Delete {
+        variable_id: Uuid,
+    },
+}
+
+/// Variables of a project, as defined by the user, and the changes to preview
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VariableChangePreviewRequest {
+    pub project_variables: Vec<VariableDefinition>,
+    pub environments: Vec<EnvironmentVariables>,
+    pub changes: Vec<VariableChange>,
+}
+
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum VariablePreviewError {
+    #[error("variable `{0}` does not exist")]
+    UnknownVariable(Uuid),
+    #[error("environment `{0}` does not exist")]
+    UnknownEnvironment(Uuid),
+    #[error("service `{0}` does not exist")]
+    UnknownService(Uuid),
+    #[error("cannot create variable `{0}`: the environment or service it belongs to is missing")]
+    MissingParent(Uuid),
+}
+
+#[derive(Serialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MaskReason {
+    /// An override of the changed variable, at a narrower scope, gives the key its value
+    Overridden,
+    /// Another variable with the same key is defined at a narrower scope
+    Shadowed,
+    /// An alias uses the same key at a narrower scope
+    Aliased,
+}
+
+/// The variable giving its value to the key of a changed variable
+#[derive(Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct VariableMask {
+    pub variable_id: Uuid,
+    pub scope: VariableScope,
+    pub reason: MaskReason,
+}
+
+/// A changed variable which has no effect on a service
+#[derive(Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct MaskedVariableChange {
+    pub environment_id: Uuid,
+    pub service_id: Uuid,
+    pub variable_id: Uuid,
+    pub key: String,
+    pub masked_by: VariableMask,
+}
+
+/// Keys whose resolved value changes for a service. Values are never reported.
+#[derive(Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct ServiceVariablesImpact {
+    pub environment_id: Uuid,
+    pub service_id: Uuid,
+    pub added_keys: Vec<String>,
+    pub removed_keys: Vec<String>,
+    pub modified_keys: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct VariableChangePreview {
+    /// Services whose resolved variables change, they would be restarted
+    pub impacted_services: Vec<ServiceVariablesImpact>,
+    /// Services of the affected environments whose resolved variables don't change
+    pub unaffected_services: Vec<Uuid>,
+    pub masked_changes: Vec<MaskedVariableChange>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct ResolvedVariable {
+    // secret values are only known by their hash
+    comparable_value: String,
+    is_secret: bool,
+    injection_scope: VariableInjectionScope,
+    variable_id: Uuid,
+    scope: VariableScope,
+    reason: MaskReason,
+}
+
+fn comparable_value(value: &str, is_secret: bool) -> String {
+    if is_secret {
+        format!("{:x}", Sha256::digest(value.as_bytes()))
+    } else {
+        value.to_string()
+    }
+}
+
+// a variable only replaces one of the same or of a broader scope
+fn set_resolved(resolved: &mut BTreeMap<String, ResolvedVariable>, key: &str, variable: ResolvedVariable) {
+    if resolved.get(key).map(|v| v.scope <= variable.scope).unwrap_or(true) {
+        resolved.insert(key.to_string(), variable);
+    }
+}
+
+/// Resolves the variables visible by a service into the set of variables it receives
+fn resolve_variables<'a>(
+    variables: impl Iterator<Item = &'a VariableDefinition> + Clone,
+) -> BTreeMap<String, ResolvedVariable> {
+    let by_id: BTreeMap<Uuid, &VariableDefinition> = variables.clone().map(|v| (v.id, v)).collect();
+    let mut sorted: Vec<&VariableDefinition> = variables.collect();
+    // narrower scopes are applied last, so they win
+    sorted.sort_by_key(|v| v.scope);
+
+    let mut resolved: BTreeMap<String, ResolvedVariable> = BTreeMap::new();
+
+    for variable in &sorted {
+        if let VariableDefinitionKind::Value { value } = &variable.kind {
+            set_resolved(
+                &mut resolved,
+                &variable.key,
+                ResolvedVariable {
+                    comparable_value: comparable_value(value, variable.is_secret),
+                    is_secret: variable.is_secret,
+                    injection_scope: variable.injection_scope,
+                    variable_id: variable.id,
+                    scope: variable.scope,
+                    reason: MaskReason::Shadowed,
+                },
+            );
+        }
+    }
+
+    for variable in &sorted {
+        let VariableDefinitionKind::Override {
+            overridden_variable_id,
+            value,
+        } = &variable.kind
+        else {
+            continue;
+        };
+        let Some(overridden) = by_id.get(overridden_variable_id) else {
+            continue;
+        };
+        if overridden.scope >= variable.scope {
+            continue;
+        }
+
+        set_resolved(
+            &mut resolved,
+            &overridden.key,
+            ResolvedVariable {
+                comparable_value: comparable_value(value, overridden.is_secret),
+                is_secret: overridden.is_secret,
+                injection_scope: overridden.injection_scope,
+                variable_id: variable.id,
+                scope: variable.scope,
+                reason: MaskReason::Overridden,
+            },
+        );
+    }
+
+    // aliases follow the resolved value of the key they point to, overrides included
+    let values = resolved.clone();
+    for variable in &sorted {
+        let VariableDefinitionKind::Alias { aliased_variable_id } = &variable.kind else {
+            continue;
+        };
+        let Some(aliased) = by_id
+            .get(aliased_variable_id)
+            .and_then(|aliased| values.get(&aliased.key))
+        else {
+            continue;
+        };
+
+        set_resolved(
+            &mut resolved,
+            &variable.key,
+            ResolvedVariable {
+                comparable_value: aliased.comparable_value.clone(),
+                is_secret: aliased.is_secret,
+                injection_scope: aliased.injection_scope,
+                variable_id: variable.id,
+                scope: variable.scope,
+                reason: MaskReason::Aliased,
+            },
+        );
+    }
+
+    resolved
+}
+
+/// Returns what prevents a variable to give its value to a service, if anything
+fn variable_mask(
+    variable: &VariableDefinition,
+    visible: &[&VariableDefinition],
+    resolved: &BTreeMap<String, ResolvedVariable>,
+) -> Option<VariableMask> {
+    let key = match &variable.kind {
+        VariableDefinitionKind::Override {
+            overridden_variable_id, ..
+        } => &visible.iter().find(|v| v.id == *overridden_variable_id)?.key,
+        VariableDefinitionKind::Value { .. } | VariableDefinitionKind::Alias { .. } => &variable.key,
+    };
+    let winner = resolved.get(key)?;
+    if winner.variable_id == variable.id {
+        return None;
+    }
+
+    Some(VariableMask {
+        variable_id: winner.variable_id,
+        scope: winner.scope,
+        reason: winner.reason,
+    })
+}
+
+fn find_variable(request: &VariableChangePreviewRequest, variable_id: &Uuid) -> Option<(Option<Uuid>, Option<Uuid>)> {
+    if request.project_variables.iter().any(|v| v.id == *variable_id) {
+        return Some((None, None));
+    }
+
+    for environment in &request.environments {
+        if environment.variables.iter().any(|v| v.id == *variable_id) {
+            return Some((Some(environment.environment_id), None));
+        }
+        for service in &environment.services {
+            if service.variables.iter().any(|v| v.id == *variable_id) {
+                return Some((Some(environment.environment_id), Some(service.service_id)));
+            }
+        }
+    }
+
+    None
+}
+
+fn remove_variable(request: &mut VariableChangePreviewRequest, variable_id: &Uuid) {
+    request.project_variables.retain(|v| v.id != *variable_id);
+    for environment in &mut request.environments {
+        environment.variables.retain(|v| v.id != *variable_id);
+        for service in &mut environment.services {
+            service.variables.retain(|v| v.id != *variable_id);
+        }
+    }
+}
+
+fn find_environment<'a>(
+    request: &'a mut VariableChangePreviewRequest,
+    environment_id: Option<Uuid>,
+    variable_id: &Uuid,
+) -> Result<&'a mut EnvironmentVariables, VariablePreviewError> {
+    let environment_id = environment_id.ok_or(VariablePreviewError::MissingParent(*variable_id))?;
+    request
+        .environments
+        .iter_mut()
+        .find(|e| e.environment_id == environment_id)
+        .ok_or(VariablePreviewError::UnknownEnvironment(environment_id))
+}
+
+/// Applies the changes of the request, and returns the environments they affect (None meaning all of them)
+fn apply_changes(
+    request: &VariableChangePreviewRequest,
+) -> Result<(VariableChangePreviewRequest, Option<BTreeSet<Uuid>>), VariablePreviewError> {
+    let mut updated = request.clone();
+    let mut affected_environments: Option<BTreeSet<Uuid>> = Some(BTreeSet::new());
+    let mut affect = |environment_id: Option<Uuid>| match environment_id {
+        None => affected_environments = None,
+        Some(environment_id) => {
+            if let Some(environments) = affected_environments.as_mut() {
+                environments.insert(environment_id);
+            }
+        }
+    };
+
+    for change in &request.changes {
+        match change {
+            VariableChange::Delete { variable_id } => {
+                let (environment_id, _) =
+                    find_variable(&updated, variable_id).ok_or(VariablePreviewError::UnknownVariable(*variable_id))?;
+                remove_variable(&mut updated, variable_id);
+                affect(environment_id);
+            }
+            VariableChange::Upsert {
+                variable,
+                environment_id,
+                service_id,
+            } => {
+                let (previous_environment_id, previous_service_id) =
+                    find_variable(&updated, &variable.id).unwrap_or((*environment_id, *service_id));
+                remove_variable(&mut updated, &variable.id);
+                // a variable moving from an environment to the project affects all of them
+                affect(previous_environment_id);
+
+                let environment_id = environment_id.or(previous_environment_id);
+                let service_id = service_id.or(previous_service_id);
+
+                match variable.scope {
+                    VariableScope::Project => {
+                        updated.project_variables.push(variable.clone());
+                        affect(None);
+                    }
+                    VariableScope::Environment => {
+                        find_environment(&mut updated, environment_id, &variable.id)?
+                            .variables
+                            .push(variable.clone());
+                        affect(environment_id);
+                    }
+                    VariableScope::Service => {
+                        let service_id = service_id.ok_or(VariablePreviewError::MissingParent(variable.id))?;
+                        find_environment(&mut updated, environment_id, &variable.id)?
+                            .services
+                            .iter_mut()
+                            .find(|s| s.service_id == service_id)
+                            .ok_or(VariablePreviewError::UnknownService(service_id))?
+                            .variables
+                            .push(variable.clone());
+                        affect(environment_id);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok((updated, affected_environments))
+}
+
+fn visible_variables<'a>(
+    request: &'a VariableChangePreviewRequest,
+    environment: &'a EnvironmentVariables,
+    service: &'a ServiceVariables,
+) -> Vec<&'a VariableDefinition> {
+    request
+        .project_variables
+        .iter()
+        .chain(environment.variables.iter())
+        .chain(service.variables.iter())
+        .collect()
+}
+
+/// Computes which services would be restarted if the variable changes were applied, without applying them
+pub fn preview_variable_change(
+    request: &VariableChangePreviewRequest,
+) -> Result<VariableChangePreview, VariablePreviewError> {
+    let (updated, affected_environments) = apply_changes(request)?;
+    let mut preview = VariableChangePreview::default();
+
+    for environment in &updated.environments {
+        if let Some(affected_environments) = &affected_environments {
+            if !affected_environments.contains(&environment.environment_id) {
+                continue;
+            }
+        }
+        let previous_environment = request
+            .environments
+            .iter()
+            .find(|e| e.environment_id == environment.environment_id);
+
+        for service in &environment.services {
+            let visible = visible_variables(&updated, environment, service);
+            let resolved = resolve_variables(visible.iter().copied());
+            let (previous_visible, previous_resolved) = match previous_environment.and_then(|e| {
+                e.services
+                    .iter()
+                    .find(|s| s.service_id == service.service_id)
+                    .map(|s| (e, s))
+            }) {
+                Some((previous_environment, previous_service)) => {
+                    let previous_visible = visible_variables(request, previous_environment, previous_service);
+                    let previous_resolved = resolve_variables(previous_visible.iter().copied());
+                    (previous_visible, previous_resolved)
+                }
+                None => (vec![], BTreeMap::new()),
+            };
+
+            // masked changes, an upserted variable is checked against the new variables, a deleted one against the previous ones
+            for change in &request.changes {
+                let (variable, visible, resolved) = match change {
+                    VariableChange::Upsert { variable, .. } => (Some(variable), &visible, &resolved),
+                    VariableChange::Delete { variable_id } => (
+                        previous_visible.iter().find(|v| v.id == *variable_id).copied(),
+                        &previous_visible,
+                        &previous_resolved,
+                    ),
+                };
+                let Some(variable) = variable.filter(|v| visible.iter().any(|visible| visible.id == v.id)) else {
+                    continue;
+                };
+                if let Some(masked_by) = variable_mask(variable, visible, resolved) {
+                    preview.masked_changes.push(MaskedVariableChange {
+                        environment_id: environment.environment_id,
+                        service_id: service.service_id,
+                        variable_id: variable.id,
+                        key: variable.key.clone(),
+                        masked_by,
+                    });
+                }
+            }
+
+            let added_keys: Vec<String> = resolved
+                .keys()
+                .filter(|key| !previous_resolved.contains_key(*key))
+                .cloned()
+                .collect();
+            let removed_keys: Vec<String> = previous_resolved
+                .keys()
+                .filter(|key| !resolved.contains_key(*key))
+                .cloned()
+                .collect();
+            let modified_keys: Vec<String> = resolved
+                .iter()
+                .filter(|(key, variable)| {
+                    previous_resolved.get(*key).is_some_and(|previous| {
+                        previous.comparable_value != variable.comparable_value
+                            || previous.is_secret != variable.is_secret
+                            || previous.injection_scope != variable.injection_scope
+                    })
+                })
+                .map(|(key, _)| key.clone())
+                .collect();
+
+            if added_keys.is_empty() && removed_keys.is_empty() && modified_keys.is_empty() {
+                preview.unaffected_services.push(service.service_id);
+            } else {
+                preview.impacted_services.push(ServiceVariablesImpact {
+                    environment_id: environment.environment_id,
+                    service_id: service.service_id,
+                    added_keys,
+                    removed_keys,
+                    modified_keys,
+                });
+            }
+        }
+    }
+
+    Ok(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENV_ID: Uuid = Uuid::from_u128(1);
+    const OTHER_ENV_ID: Uuid = Uuid::from_u128(2);
+    const API_ID: Uuid = Uuid::from_u128(11);
+    const WORKER_ID: Uuid = Uuid::from_u128(12);
+    const FRONT_ID: Uuid = Uuid::from_u128(21);
+
+    fn value(id: u128, key: &str, scope: VariableScope, value: &str) -> VariableDefinition {
+        VariableDefinition {
+            id: Uuid::from_u128(id),
+            key: key.to_string(),
+            scope,
+            is_secret: false,
+            injection_scope: VariableInjectionScope::Both,
+            kind: VariableDefinitionKind::Value {
+                value: value.to_string(),
+            },
+        }
+    }
+
+    fn alias(id: u128, key: &str, scope: VariableScope, aliased: u128) -> VariableDefinition {
+        VariableDefinition {
+            id: Uuid::from_u128(id),
+            key: key.to_string(),
+            scope,
+            is_secret: false,
+            injection_scope: VariableInjectionScope::Both,
+            kind: VariableDefinitionKind::Alias {
+                aliased_variable_id: Uuid::from_u128(aliased),
+            },
+        }
+    }
+
+    fn override_of(id: u128, key: &str, scope: VariableScope, overridden: u128, value: &str) -> VariableDefinition {
+        VariableDefinition {
+            id: Uuid::from_u128(id),
+            key: key.to_string(),
+            scope,
+            is_secret: false,
+            injection_scope: VariableInjectionScope::Both,
+            kind: VariableDefinitionKind::Override {
+                overridden_variable_id: Uuid::from_u128(overridden),
+                value: value.to_string(),
+            },
+        }
+    }
+
+    fn update(variable: VariableDefinition) -> VariableChange {
+        VariableChange::Upsert {
+            variable,
+            environment_id: None,
+            service_id: None,
+        }
+    }
+
+    // project: DATABASE_URL (100)
+    // env: API_DATABASE_URL alias of DATABASE_URL (200)
+    // api: LOG_LEVEL (300)
+    // worker: DATABASE_URL override (301)
+    // other env front: no variable
+    fn request(changes: Vec<VariableChange>) -> VariableChangePreviewRequest {
+        VariableChangePreviewRequest {
+            project_variables: vec![value(100, "DATABASE_URL", VariableScope::Project, "cG9zdGdyZXM=")],
+            environments: vec![
+                EnvironmentVariables {
+                    environment_id: ENV_ID,
+                    variables: vec![alias(200, "API_DATABASE_URL", VariableScope::Environment, 100)],
+                    services: vec![
+                        ServiceVariables {
+                            service_id: API_ID,
+                            variables: vec![value(300, "LOG_LEVEL", VariableScope::Service, "aW5mbw==")],
+                        },
+                        ServiceVariables {
+                            service_id: WORKER_ID,
+                            variables: vec![override_of(
+                                301,
+                                "DATABASE_URL",
+                                VariableScope::Service,
+                                100,
+                                "cmVwbGljYQ==",
+                            )],
+                        },
+                    ],
+                },
+                EnvironmentVariables {
+                    environment_id: OTHER_ENV_ID,
+                    variables: vec![],
+                    services: vec![ServiceVariables {
+                        service_id: FRONT_ID,
+                        variables: vec![],
+                    }],
+                },
+            ],
+            changes,
+        }
+    }
+
+    #[test]
+    fn test_resolve_variables_precedence() {
+        let variables = [
+            value(1, "A", VariableScope::Project, "project"),
+            value(2, "A", VariableScope::Service, "service"),
+            value(3, "A", VariableScope::Environment, "environment"),
+            value(4, "B", VariableScope::Project, "b"),
+            override_of(5, "B", VariableScope::Environment, 4, "b-overridden"),
+            alias(6, "C", VariableScope::Service, 4),
+            // an alias can't take the key of a narrower variable
+            alias(7, "A", VariableScope::Environment, 4),
+        ];
+        let resolved = resolve_variables(variables.iter());
+
+        assert_eq!(resolved["A"].comparable_value, "service");
+        assert_eq!(resolved["B"].comparable_value, "b-overridden");
+        assert_eq!(resolved["B"].reason, MaskReason::Overridden);
+        // aliases follow overrides
+        assert_eq!(resolved["C"].comparable_value, "b-overridden");
+        assert_eq!(resolved["C"].variable_id, Uuid::from_u128(6));
+    }
+
+    #[test]
+    fn test_override_of_a_narrower_variable_is_ignored() {
+        let variables = [
+            value(1, "A", VariableScope::Service, "service"),
+            override_of(2, "A", VariableScope::Environment, 1, "environment"),
+        ];
+
+        assert_eq!(resolve_variables(variables.iter())["A"].comparable_value, "service");
+    }
+
+    #[test]
+    fn test_project_variable_change_follows_aliases_and_is_masked_by_overrides() {
+        let preview = preview_variable_change(&request(vec![update(value(
+            100,
+            "DATABASE_URL",
+            VariableScope::Project,
+            "bXlzcWw=",
+        ))]))
+        .unwrap();
+
+        assert_eq!(
+            preview.impacted_services,
+            vec![
+                ServiceVariablesImpact {
+                    environment_id: ENV_ID,
+                    service_id: API_ID,
+                    added_keys: vec![],
+                    removed_keys: vec![],
+                    modified_keys: vec!["API_DATABASE_URL".to_string(), "DATABASE_URL".to_string()],
+                },
+                // project variables are visible by all the environments
+                ServiceVariablesImpact {
+                    environment_id: OTHER_ENV_ID,
+                    service_id: FRONT_ID,
+                    added_keys: vec![],
+                    removed_keys: vec![],
+                    modified_keys: vec!["DATABASE_URL".to_string()],
+                },
+            ]
+        );
+        // the worker keeps its override, which its alias follows too
+        assert_eq!(preview.unaffected_services, vec![WORKER_ID]);
+        assert_eq!(
+            preview.masked_changes,
+            vec![MaskedVariableChange {
+                environment_id: ENV_ID,
+                service_id: WORKER_ID,
+                variable_id: Uuid::from_u128(100),
+                key: "DATABASE_URL".to_string(),
+                masked_by: VariableMask {
+                    variable_id: Uuid::from_u128(301),
+                    scope: VariableScope::Service,
+                    reason: MaskReason::Overridden,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_alias_follows_the_override_of_its_service() {
+        let request = request(vec![update(override_of(
+            301,
+            "DATABASE_URL",
+            VariableScope::Service,
+            100,
+            "cmVwbGljYS0y",
+        ))]);
+        let preview = preview_variable_change(&request).unwrap();
+
+        assert_eq!(preview.impacted_services.len(), 1);
+        assert_eq!(preview.impacted_services[0].service_id, WORKER_ID);
+        assert_eq!(
+            preview.impacted_services[0].modified_keys,
+            vec!["API_DATABASE_URL".to_string(), "DATABASE_URL".to_string()]
+        );
+        // other environments are not resolved at all
+        assert_eq!(preview.unaffected_services, vec![API_ID]);
+        assert!(preview.masked_changes.is_empty());
+    }
+
+    #[test]
+    fn test_environment_variable_shadowed_by_service_variable() {
+        let preview = preview_variable_change(&request(vec![VariableChange::Upsert {
+            variable: value(201, "LOG_LEVEL", VariableScope::Environment, "ZGVidWc="),
+            environment_id: Some(ENV_ID),
+            service_id: None,
+        }]))
+        .unwrap();
+
+        assert_eq!(preview.impacted_services.len(), 1);
+        assert_eq!(preview.impacted_services[0].service_id, WORKER_ID);
+        assert_eq!(preview.impacted_services[0].added_keys, vec!["LOG_LEVEL".to_string()]);
+        assert_eq!(preview.unaffected_services, vec![API_ID]);
+        assert_eq!(
+            preview.masked_changes,
+            vec![MaskedVariableChange {
+                environment_id: ENV_ID,
+                service_id: API_ID,
+                variable_id: Uuid::from_u128(201),
+                key: "LOG_LEVEL".to_string(),
+                masked_by: VariableMask {
+                    variable_id: Uuid::from_u128(300),
+                    scope: VariableScope::Service,
+                    reason: MaskReason::Shadowed,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_deleted_aliased_variable() {
+        let preview = preview_variable_change(&request(vec![VariableChange::Delete {
+            variable_id: Uuid::from_u128(100),
+        }]))
+        .unwrap();
+
+        assert_eq!(
+            preview.impacted_services,
+            vec![
+                ServiceVariablesImpact {
+                    environment_id: ENV_ID,
+                    service_id: API_ID,
+                    added_keys: vec![],
+                    removed_keys: vec!["API_DATABASE_URL".to_string(), "DATABASE_URL".to_string()],
+                    modified_keys: vec![],
+                },
+                ServiceVariablesImpact {
+                    environment_id: ENV_ID,
+                    service_id: WORKER_ID,
+                    added_keys: vec![],
+                    removed_keys: vec!["API_DATABASE_URL".to_string(), "DATABASE_URL".to_string()],
+                    modified_keys: vec![],
+                },
+                ServiceVariablesImpact {
+                    environment_id: OTHER_ENV_ID,
+                    service_id: FRONT_ID,
+                    added_keys: vec![],
+                    removed_keys: vec!["DATABASE_URL".to_string()],
+                    modified_keys: vec![],
+                },
+            ]
+        );
+        assert!(preview.unaffected_services.is_empty());
+        // the worker override was already giving its value to DATABASE_URL
+        assert_eq!(preview.masked_changes.len(), 1);
+        assert_eq!(preview.masked_changes[0].service_id, WORKER_ID);
+        assert_eq!(preview.masked_changes[0].masked_by.reason, MaskReason::Overridden);
+    }
+
+    #[test]
+    fn test_injection_scope_follows_aliases_and_overrides() {
+        let build_time = |variable: VariableDefinition| VariableDefinition {
+            injection_scope: VariableInjectionScope::BuildTime,
+            ..variable
+        };
+        let variables = [
+            build_time(value(1, "NPM_TOKEN", VariableScope::Project, "token")),
+            // the override and the alias don't choose their own scope
+            override_of(2, "NPM_TOKEN", VariableScope::Service, 1, "service-token"),
+            alias(3, "YARN_TOKEN", VariableScope::Environment, 1),
+        ];
+        let resolved = resolve_variables(variables.iter());
+
+        assert_eq!(resolved["NPM_TOKEN"].comparable_value, "service-token");
+        assert_eq!(resolved["NPM_TOKEN"].injection_scope, VariableInjectionScope::BuildTime);
+        assert_eq!(resolved["YARN_TOKEN"].injection_scope, VariableInjectionScope::BuildTime);
+
+        // only exposing the project variable at runtime changes what every service receives, the alias included
+        let preview = preview_variable_change(&request(vec![update(VariableDefinition {
+            injection_scope: VariableInjectionScope::Runtime,
+            ..value(100, "DATABASE_URL", VariableScope::Project, "cG9zdGdyZXM=")
+        })]))
+        .unwrap();
+        assert_eq!(
+            preview
+                .impacted_services
+                .iter()
+                .map(|impact| (impact.service_id, impact.modified_keys.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (API_ID, vec!["API_DATABASE_URL".to_string(), "DATABASE_URL".to_string()]),
+                (WORKER_ID, vec!["API_DATABASE_URL".to_string(), "DATABASE_URL".to_string()]),
+                (FRONT_ID, vec!["DATABASE_URL".to_string()]),
+            ]
+        );
+        assert!(preview.unaffected_services.is_empty());
+    }
+
+    #[test]
+    fn test_secrets_are_compared_by_hash() {
+        let secret = |value_b64: &str| VariableDefinition {
+            is_secret: true,
+            ..value(302, "API_KEY", VariableScope::Service, value_b64)
+        };
+        let mut base = request(vec![]);
+        base.environments[0].services[0].variables.push(secret("c2VjcmV0"));
+
+        // same value, the secret is re-saved without any change
+        let mut same = base.clone();
+        same.changes = vec![update(secret("c2VjcmV0"))];
+        let preview = preview_variable_change(&same).unwrap();
+        assert!(preview.impacted_services.is_empty());
+        assert_eq!(preview.unaffected_services, vec![API_ID, WORKER_ID]);
+
+        let mut rotated = base;
+        rotated.changes = vec![update(secret("bmV3LXNlY3JldA=="))];
+        let preview = preview_variable_change(&rotated).unwrap();
+        assert_eq!(preview.impacted_services[0].modified_keys, vec!["API_KEY".to_string()]);
+
+        let resolved = resolve_variables([secret("c2VjcmV0")].iter());
+        assert_ne!(resolved["API_KEY"].comparable_value, "c2VjcmV0");
+        assert!(!serde_json::to_string(&preview).unwrap().contains("bmV3LXNlY3JldA=="));
+    }
+
+    #[test]
+    fn test_unknown_variables() {
+        assert_eq!(
+            preview_variable_change(&request(vec![VariableChange::Delete {
+                variable_id: Uuid::from_u128(999),
+            }])),
+            Err(VariablePreviewError::UnknownVariable(Uuid::from_u128(999)))
+        );
+        assert_eq!(
+            preview_variable_change(&request(vec![update(value(999, "NEW", VariableScope::Service, "bmV3"))])),
+            Err(VariablePreviewError::MissingParent(Uuid::from_u128(999)))
+        );
+    }
+}
@@ -1,9 +1,13 @@
+use crate::cmd::structs::KubernetesNode;
 use crate::errors::CommandError;
+use crate::infrastructure::models::kubernetes::capabilities::ClusterCapabilities;
 use crate::io_models::models::InvalidPVCStorage;
 use k8s_openapi::api::apps::v1::StatefulSet;
-use k8s_openapi::api::core::v1::PersistentVolumeClaim;
+use k8s_openapi::api::core::v1::{Node, PersistentVolumeClaim, Pod};
 use k8s_openapi::NamespaceResourceScope;
-use kube::api::{DeleteParams, ListParams, ObjectList, Patch, PatchParams, PostParams};
+use kube::api::{
+    ApiResource, DeleteParams, DynamicObject, GroupVersionKind, ListParams, ObjectList, Patch, PatchParams, PostParams,
+};
 use kube::{Api, Resource};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -41,6 +45,16 @@ where
     Ok(())
 }
 
+/// Server side apply parameters, taking over fields owned by other managers when the cluster allows it.
+pub fn server_side_apply_params(field_manager: &str, capabilities: &ClusterCapabilities) -> PatchParams {
+    let params = PatchParams::apply(field_manager);
+    if capabilities.server_side_apply_force_ownership {
+        params.force()
+    } else {
+        params
+    }
+}
+
 pub async fn kube_edit_pvc_size(
     client: &kube::Client,
     namespace: &str,
@@ -143,3 +157,331 @@ pub async fn kube_rollout_restart_statefulset(
 
     Ok(())
 }
+
+fn list_params(selector: Option<&str>) -> ListParams {
+    match selector {
+        Some(selector) => ListParams::default().labels(selector),
+        None => ListParams::default(),
+    }
+}
+
+/// Nodes of the cluster, only the ones matching the label selector if any
+pub async fn kube_get_nodes(client: &kube::Client, selector: Option<&str>) -> Result<Vec<Node>, kube::Error> {
+    let api: Api<Node> = Api::all(client.clone());
+    Ok(api.list(&list_params(selector)).await?.items)
+}
+
+/// Pods of the namespace, or of all the namespaces, only the ones matching the label selector if any
+pub async fn kube_get_pods(
+    client: &kube::Client,
+    namespace: Option<&str>,
+    selector: Option<&str>,
+) -> Result<Vec<Pod>, kube::Error> {
+    let api: Api<Pod> = match namespace {
+        Some(namespace) => Api::namespaced(client.clone(), namespace),
+        None => Api::all(client.clone()),
+    };
+    Ok(api.list(&list_params(selector)).await?.items)
+}
+
+/// Objects of any kind in all the namespaces, `None` when the cluster doesn't serve this kind (i.e: its CRD is not
+/// installed). The plural of the resource is guessed from its kind.
+pub async fn kube_list_dynamic(
+    client: &kube::Client,
+    gvk: &GroupVersionKind,
+    selector: Option<&str>,
+) -> Result<Option<Vec<DynamicObject>>, kube::Error> {
+    let api: Api<DynamicObject> = Api::all_with(client.clone(), &ApiResource::from_gvk(gvk));
+    match api.list(&list_params(selector)).await {
+        Ok(objects) => Ok(Some(objects.items)),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// What the upgrade and readiness checks need to know about a node
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KubeNodeStatus {
+    pub name: String,
+    pub kubelet_version: String,
+    pub kube_proxy_version: String,
+    /// A node not reporting its `Ready` condition is considered ready
+    pub is_ready: bool,
+}
+
+impl From<&Node> for KubeNodeStatus {
+    fn from(node: &Node) -> Self {
+        let status = node.status.as_ref();
+        let node_info = status.and_then(|status| status.node_info.as_ref());
+        KubeNodeStatus {
+            name: node.metadata.name.clone().unwrap_or_default(),
+            kubelet_version: node_info.map(|info| info.kubelet_version.clone()).unwrap_or_default(),
+            kube_proxy_version: node_info
+                .map(|info| info.kube_proxy_version.clone())
+                .unwrap_or_default(),
+            is_ready: status
+                .and_then(|status| status.conditions.as_ref())
+                .map(|conditions| {
+                    conditions
+                        .iter()
+                        .filter(|condition| condition.type_ == "Ready")
+                        .all(|condition| condition.status == "True")
+                })
+                .unwrap_or(true),
+        }
+    }
+}
+
+/// Nodes listed by kubectl, its output is not parsed for their names
+impl From<&KubernetesNode> for KubeNodeStatus {
+    fn from(node: &KubernetesNode) -> Self {
+        KubeNodeStatus {
+            name: String::new(),
+            kubelet_version: node.status.node_info.kubelet_version.clone(),
+            kube_proxy_version: node.status.node_info.kube_proxy_version.clone(),
+            is_ready: node
+                .status
+                .conditions
+                .iter()
+                .filter(|condition| condition.condition_type == "Ready")
+                .all(|condition| condition.status == "True"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::structs::KubernetesList;
+    use http::{Request, Response, StatusCode};
+    use k8s_openapi::api::core::v1::{NodeCondition, NodeStatus, NodeSystemInfo, PodStatus};
+    use kube::api::ObjectMeta;
+    use kube::client::Body;
+    use serde_json::{json, Value};
+    use tokio::task::JoinHandle;
+
+    /// Kube client answering its requests with the responses, in order. The server returns the requested URIs.
+    fn mock_client(responses: Vec<(StatusCode, Value)>) -> (kube::Client, JoinHandle<Vec<String>>) {
+        let (service, mut handle) = tower_test::mock::pair::<Request<Body>, Response<Body>>();
+        let server = tokio::spawn(async move {
+            let mut uris = vec![];
+            for (status, body) in responses {
+                let (request, send) = handle.next_request().await.expect("the client sent no request");
+                uris.push(request.uri().to_string());
+                send.send_response(
+                    Response::builder()
+                        .status(status)
+                        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                        .unwrap(),
+                );
+            }
+            uris
+        });
+
+        (kube::Client::new(service, "default"), server)
+    }
+
+    fn list(kind: &str, items: Vec<Value>) -> Value {
+        json!({ "apiVersion": "v1", "kind": kind, "metadata": {}, "items": items })
+    }
+
+    fn node(name: &str, kubelet_version: &str, ready: Option<&str>) -> Value {
+        let node = Node {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                ..Default::default()
+            },
+            status: Some(NodeStatus {
+                node_info: Some(NodeSystemInfo {
+                    kubelet_version: kubelet_version.to_string(),
+                    kube_proxy_version: kubelet_version.to_string(),
+                    ..Default::default()
+                }),
+                conditions: ready.map(|ready| {
+                    vec![
+                        NodeCondition {
+                            type_: "MemoryPressure".to_string(),
+                            status: "False".to_string(),
+                            ..Default::default()
+                        },
+                        NodeCondition {
+                            type_: "Ready".to_string(),
+                            status: ready.to_string(),
+                            ..Default::default()
+                        },
+                    ]
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        serde_json::to_value(node).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_kube_get_nodes() {
+        let (client, server) = mock_client(vec![(
+            StatusCode::OK,
+            list(
+                "NodeList",
+                vec![
+                    node("node-1", "v1.30.4-eks-a737599", Some("True")),
+                    node("node-2", "v1.29.8-eks-a737599", Some("Unknown")),
+                    node("node-3", "v1.30.4-eks-a737599", None),
+                ],
+            ),
+        )]);
+
+        let nodes = kube_get_nodes(&client, Some("karpenter.sh/nodepool"))
+            .await
+            .expect("nodes should be listed");
+        let uris = server.await.unwrap();
+
+        assert_eq!(uris.len(), 1);
+        assert!(uris[0].starts_with("/api/v1/nodes?"));
+        assert!(uris[0].contains("labelSelector=karpenter.sh%2Fnodepool"));
+        assert_eq!(
+            nodes.iter().map(KubeNodeStatus::from).collect::<Vec<_>>(),
+            vec![
+                KubeNodeStatus {
+                    name: "node-1".to_string(),
+                    kubelet_version: "v1.30.4-eks-a737599".to_string(),
+                    kube_proxy_version: "v1.30.4-eks-a737599".to_string(),
+                    is_ready: true,
+                },
+                KubeNodeStatus {
+                    name: "node-2".to_string(),
+                    kubelet_version: "v1.29.8-eks-a737599".to_string(),
+                    kube_proxy_version: "v1.29.8-eks-a737599".to_string(),
+                    is_ready: false,
+                },
+                KubeNodeStatus {
+                    name: "node-3".to_string(),
+                    kubelet_version: "v1.30.4-eks-a737599".to_string(),
+                    kube_proxy_version: "v1.30.4-eks-a737599".to_string(),
+                    is_ready: true,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kube_get_pods() {
+        let pod = |name: &str, phase: &str| {
+            serde_json::to_value(Pod {
+                metadata: ObjectMeta {
+                    name: Some(name.to_string()),
+                    namespace: Some("kube-system".to_string()),
+                    ..Default::default()
+                },
+                status: Some(PodStatus {
+                    phase: Some(phase.to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        let (client, server) = mock_client(vec![(
+            StatusCode::OK,
+            list("PodList", vec![pod("karpenter-0", "Running"), pod("karpenter-1", "Pending")]),
+        )]);
+
+        let pods = kube_get_pods(&client, Some("kube-system"), None)
+            .await
+            .expect("pods should be listed");
+
+        assert_eq!(server.await.unwrap(), vec!["/api/v1/namespaces/kube-system/pods?".to_string()]);
+        assert_eq!(
+            pods.iter()
+                .map(|pod| pod.status.as_ref().and_then(|status| status.phase.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![Some("Running"), Some("Pending")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kube_list_dynamic() {
+        let not_found = json!({
+            "kind": "Status",
+            "apiVersion": "v1",
+            "metadata": {},
+            "status": "Failure",
+            "message": "the server could not find the requested resource",
+            "reason": "NotFound",
+            "code": 404
+        });
+        let forbidden = json!({
+            "kind": "Status",
+            "apiVersion": "v1",
+            "metadata": {},
+            "status": "Failure",
+            "message": "challenges.acme.cert-manager.io is forbidden",
+            "reason": "Forbidden",
+            "code": 403
+        });
+        let challenges = json!({
+            "apiVersion": "acme.cert-manager.io/v1",
+            "kind": "ChallengeList",
+            "metadata": {},
+            "items": [{
+                "apiVersion": "acme.cert-manager.io/v1",
+                "kind": "Challenge",
+                "metadata": { "name": "qovery-wildcard-1", "namespace": "qovery" },
+                "spec": {}
+            }]
+        });
+        let (client, server) = mock_client(vec![
+            (StatusCode::NOT_FOUND, not_found),
+            (StatusCode::OK, challenges),
+            (StatusCode::FORBIDDEN, forbidden),
+        ]);
+        let challenge = GroupVersionKind::gvk("acme.cert-manager.io", "v1", "Challenge");
+
+        // the CRD is not installed yet
+        assert_eq!(
+            kube_list_dynamic(&client, &challenge, None)
+                .await
+                .expect("a missing kind is not an error")
+                .map(|objects| objects.len()),
+            None
+        );
+
+        let objects = kube_list_dynamic(&client, &challenge, None)
+            .await
+            .expect("challenges should be listed")
+            .expect("challenges should be served");
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].metadata.name.as_deref(), Some("qovery-wildcard-1"));
+
+        assert!(matches!(
+            kube_list_dynamic(&client, &challenge, None).await,
+            Err(kube::Error::Api(err)) if err.code == 403
+        ));
+        assert!(server
+            .await
+            .unwrap()
+            .iter()
+            .all(|uri| uri.starts_with("/apis/acme.cert-manager.io/v1/challenges?")));
+    }
+
+    #[test]
+    fn test_kube_node_status_from_kubectl_output() {
+        let mut kubectl_node = node("node-1", "v1.30.4-eks-a737599", Some("False"));
+        let resources = json!({ "cpu": "2", "memory": "8Gi", "pods": "110" });
+        kubectl_node["status"]["allocatable"] = resources.clone();
+        kubectl_node["status"]["capacity"] = resources;
+        let nodes: KubernetesList<KubernetesNode> =
+            serde_json::from_value(list("List", vec![kubectl_node])).expect("kubectl output should be parsed");
+
+        assert_eq!(
+            KubeNodeStatus::from(&nodes.items[0]),
+            KubeNodeStatus {
+                name: String::new(),
+                kubelet_version: "v1.30.4-eks-a737599".to_string(),
+                kube_proxy_version: "v1.30.4-eks-a737599".to_string(),
+                is_ready: false,
+            }
+        );
+    }
+}
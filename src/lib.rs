@@ -25,6 +25,7 @@ pub mod log_file_writer;
 pub mod logger;
 pub mod metrics_registry;
 pub mod msg_publisher;
+pub mod proxy;
 pub mod runtime;
 pub mod services;
 mod string;
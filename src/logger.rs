@@ -57,6 +57,7 @@ impl Logger for StdIoLogger {
                 EngineEvent::Info(_, _) => info!("{}", event.message(EventMessageVerbosity::FullDetails)),
                 EngineEvent::Warning(_, _) => warn!("{}", event.message(EventMessageVerbosity::FullDetails)),
                 EngineEvent::Error(_, _) => error!("{}", event.message(EventMessageVerbosity::FullDetails)),
+                EngineEvent::Progress(_, _) => info!("{}", event.message(EventMessageVerbosity::FullDetails)),
             };
         });
     }
@@ -231,6 +232,7 @@ mod tests {
                     EngineEvent::Info(_, _) => "INFO",
                     EngineEvent::Warning(_, _) => "WARN",
                     EngineEvent::Error(_, _) => "ERROR",
+                    EngineEvent::Progress(_, _) => "INFO",
                 }),
                 "{}",
                 tc.description
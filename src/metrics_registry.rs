@@ -17,6 +17,7 @@ pub enum StepName {
     MirrorImage,
     DeploymentQueueing,
     Deployment,
+    ImagePrePull,
 }
 
 impl Display for StepName {
@@ -31,6 +32,7 @@ impl Display for StepName {
             StepName::MirrorImage => "MirrorImage".to_string(),
             StepName::DeploymentQueueing => "DeploymentQueueing".to_string(),
             StepName::Deployment => "Deployment".to_string(),
+            StepName::ImagePrePull => "ImagePrePull".to_string(),
         };
         write!(f, "{}", str)
     }
@@ -40,6 +42,7 @@ impl Display for StepName {
 pub enum StepLabel {
     Service,
     Environment,
+    Infrastructure,
 }
 
 #[derive(Clone, Debug, PartialEq)]
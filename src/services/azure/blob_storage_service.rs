@@ -0,0 +1,908 @@
+use crate::environment::models::ToCloudProviderFormat;
+use crate::infrastructure::models::object_storage::multipart_upload::{MultipartUploadClient, Part, UploadedPart};
+use crate::infrastructure::models::object_storage::StorageRegion;
+use base64::engine::general_purpose;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use derivative::Derivative;
+use itertools::Itertools;
+use reqwest::blocking::{Client, Response};
+use reqwest::Method;
+use serde_derive::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+use url::Url;
+
+const AZURE_LOGIN_URL: &str = "https://login.microsoftonline.com";
+const AZURE_MANAGEMENT_URL: &str = "https://management.azure.com";
+const AZURE_MANAGEMENT_SCOPE: &str = "https://management.azure.com/.default";
+const AZURE_STORAGE_SCOPE: &str = "https://storage.azure.com/.default";
+const BLOB_API_VERSION: &str = "2021-12-02";
+const STORAGE_MANAGEMENT_API_VERSION: &str = "2023-01-01";
+
+#[derive(Clone, Error, Debug, PartialEq, Eq)]
+pub enum AzureBlobStorageServiceError {
+    #[error("Cannot instantiate Azure Blob Storage client, error: `{raw_error_message}`")]
+    CannotInstantiateClient { raw_error_message: String },
+    #[error("Cannot authenticate against Azure Active Directory, error: `{raw_error_message}`")]
+    CannotAuthenticate { raw_error_message: String },
+    #[error("Unsupported Azure Blob Storage operation: `{raw_error_message}`")]
+    Unsupported { raw_error_message: String },
+    #[error("Cannot reach Azure Blob Storage, error: `{raw_error_message}`")]
+    Http { raw_error_message: String },
+    #[error("Azure Blob Storage API error {status} `{code}`: {message}")]
+    Api { status: u16, code: String, message: String },
+}
+
+impl AzureBlobStorageServiceError {
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, AzureBlobStorageServiceError::Api { status: 404, .. })
+    }
+}
+
+/// Location of the storage account, i.e: `westeurope`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AzureStorageRegion(String);
+
+impl AzureStorageRegion {
+    pub fn new(location: &str) -> Self {
+        AzureStorageRegion(location.to_string())
+    }
+}
+
+impl ToCloudProviderFormat for AzureStorageRegion {
+    fn to_cloud_provider_format(&self) -> &str {
+        &self.0
+    }
+}
+
+impl StorageRegion for AzureStorageRegion {}
+
+/// Storage account holding the containers, the resource group and subscription are needed by the management API.
+#[derive(Clone, Debug)]
+pub struct AzureStorageAccount {
+    pub name: String,
+    pub subscription_id: String,
+    pub resource_group: String,
+}
+
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub enum AzureStorageCredentials {
+    /// Shared Key of the storage account, it only gives access to the data plane.
+    AccountKey(#[derivative(Debug = "ignore")] String),
+    /// Federated token of the pod service account, exchanged against Azure AD tokens.
+    WorkloadIdentity {
+        tenant_id: String,
+        client_id: String,
+        federated_token_file: PathBuf,
+    },
+}
+
+/// Azure endpoints, overridable to target sovereign clouds or a mock server.
+#[derive(Clone, Debug)]
+pub struct AzureBlobStorageEndpoints {
+    pub login_url: Url,
+    pub management_url: Url,
+    /// None targets `https://{account}.blob.core.windows.net`
+    pub blob_url: Option<Url>,
+}
+
+impl Default for AzureBlobStorageEndpoints {
+    fn default() -> Self {
+        AzureBlobStorageEndpoints {
+            login_url: Url::parse(AZURE_LOGIN_URL).expect("invalid Azure login url"),
+            management_url: Url::parse(AZURE_MANAGEMENT_URL).expect("invalid Azure management url"),
+            blob_url: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AzureContainer {
+    pub name: String,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AzureBlob {
+    pub name: String,
+    pub version_id: Option<String>,
+    pub is_current_version: bool,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+pub struct AzureBlobStorageService {
+    account: AzureStorageAccount,
+    credentials: AzureStorageCredentials,
+    endpoints: AzureBlobStorageEndpoints,
+    blob_url: Url,
+    http_client: Client,
+    // Azure AD tokens by scope, with their expiration
+    tokens: Mutex<HashMap<&'static str, (String, DateTime<Utc>)>>,
+}
+
+impl AzureBlobStorageService {
+    pub fn new(
+        account: AzureStorageAccount,
+        credentials: AzureStorageCredentials,
+        endpoints: AzureBlobStorageEndpoints,
+    ) -> Result<Self, AzureBlobStorageServiceError> {
+        let to_error =
+            |raw_error_message: String| AzureBlobStorageServiceError::CannotInstantiateClient { raw_error_message };
+        if let AzureStorageCredentials::AccountKey(key) = &credentials {
+            general_purpose::STANDARD
+                .decode(key)
+                .map_err(|e| to_error(format!("storage account key is not valid base64: {e}")))?;
+        }
+
+        let blob_url = match &endpoints.blob_url {
+            Some(url) => url.clone(),
+            None => Url::parse(&format!("https://{}.blob.core.windows.net", account.name))
+                .map_err(|e| to_error(e.to_string()))?,
+        };
+        let http_client = Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(300))
+            .user_agent("qovery-engine")
+            .build()
+            .map_err(|e| to_error(e.to_string()))?;
+
+        Ok(AzureBlobStorageService {
+            account,
+            credentials,
+            endpoints,
+            blob_url,
+            http_client,
+            tokens: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn blob_url(&self) -> &Url {
+        &self.blob_url
+    }
+
+    fn access_token(&self, scope: &'static str) -> Result<String, AzureBlobStorageServiceError> {
+        let AzureStorageCredentials::WorkloadIdentity {
+            tenant_id,
+            client_id,
+            federated_token_file,
+        } = &self.credentials
+        else {
+            return Err(AzureBlobStorageServiceError::CannotAuthenticate {
+                raw_error_message: "a storage account key cannot be exchanged against an Azure AD token".to_string(),
+            });
+        };
+
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((token, expiration)) = tokens.get(scope) {
+            if *expiration > Utc::now() {
+                return Ok(token.to_string());
+            }
+        }
+
+        let to_error =
+            |raw_error_message: String| AzureBlobStorageServiceError::CannotAuthenticate { raw_error_message };
+        // the federated token is rotated by the kubelet, it is read again at each exchange
+        let assertion = std::fs::read_to_string(federated_token_file).map_err(|e| {
+            to_error(format!(
+                "cannot read federated token `{}`: {e}",
+                federated_token_file.to_string_lossy()
+            ))
+        })?;
+        let response: TokenResponse = self
+            .http_client
+            .post(format!(
+                "{}/{}/oauth2/v2.0/token",
+                self.endpoints.login_url.as_str().trim_end_matches('/'),
+                tenant_id
+            ))
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id.as_str()),
+                ("scope", scope),
+                (
+                    "client_assertion_type",
+                    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
+                ),
+                ("client_assertion", assertion.trim()),
+            ])
+            .send()
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.json())
+            .map_err(|e| to_error(e.to_string()))?;
+
+        // refreshed a minute before it expires
+        let expiration = Utc::now() + chrono::Duration::seconds(response.expires_in.saturating_sub(60) as i64);
+        tokens.insert(scope, (response.access_token.clone(), expiration));
+        Ok(response.access_token)
+    }
+
+    /// Sends a request to the blob endpoint, `path` being already percent encoded.
+    fn send(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<Response, AzureBlobStorageServiceError> {
+        let mut url = self.blob_url.clone();
+        url.set_path(path);
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+
+        let mut headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), value.to_string()))
+            .collect_vec();
+        headers.push((
+            "x-ms-date".to_string(),
+            Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        ));
+        headers.push(("x-ms-version".to_string(), BLOB_API_VERSION.to_string()));
+
+        let authorization = match &self.credentials {
+            AzureStorageCredentials::AccountKey(key) => {
+                let string_to_sign =
+                    shared_key_string_to_sign(method.as_str(), &self.account.name, &url, &headers, body.len());
+                format!(
+                    "SharedKey {}:{}",
+                    self.account.name,
+                    shared_key_signature(key, &string_to_sign)?
+                )
+            }
+            AzureStorageCredentials::WorkloadIdentity { .. } => {
+                format!("Bearer {}", self.access_token(AZURE_STORAGE_SCOPE)?)
+            }
+        };
+
+        let mut request = self
+            .http_client
+            .request(method, url)
+            .header("Authorization", authorization);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .body(body)
+            .send()
+            .map_err(|e| AzureBlobStorageServiceError::Http {
+                raw_error_message: e.to_string(),
+            })?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+
+        let status = response.status().as_u16();
+        let code = response
+            .headers()
+            .get("x-ms-error-code")
+            .and_then(|code| code.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = response.text().unwrap_or_default();
+        Err(AzureBlobStorageServiceError::Api {
+            status,
+            code,
+            message: xml_elements(&body, "Message")
+                .first()
+                .map(|message| xml_unescape(message))
+                .unwrap_or(body),
+        })
+    }
+
+    fn management_url(&self) -> String {
+        format!(
+            "{}/subscriptions/{}/resourceGroups/{}/providers/Microsoft.Storage/storageAccounts/{}/blobServices/default?api-version={}",
+            self.endpoints.management_url.as_str().trim_end_matches('/'),
+            self.account.subscription_id,
+            self.account.resource_group,
+            self.account.name,
+            STORAGE_MANAGEMENT_API_VERSION
+        )
+    }
+
+    pub fn create_container(
+        &self,
+        container_name: &str,
+        metadata: &HashMap<String, String>,
+    ) -> Result<(), AzureBlobStorageServiceError> {
+        let headers = metadata
+            .iter()
+            .map(|(key, value)| (format!("x-ms-meta-{key}"), value.to_string()))
+            .collect_vec();
+        self.send(
+            Method::PUT,
+            &format!("/{container_name}"),
+            &[("restype", "container")],
+            &headers.iter().map(|(k, v)| (k.as_str(), v.to_string())).collect_vec(),
+            vec![],
+        )
+        .map(|_| ())
+    }
+
+    pub fn get_container(&self, container_name: &str) -> Result<AzureContainer, AzureBlobStorageServiceError> {
+        let response = self.send(
+            Method::HEAD,
+            &format!("/{container_name}"),
+            &[("restype", "container")],
+            &[],
+            vec![],
+        )?;
+
+        Ok(AzureContainer {
+            name: container_name.to_string(),
+            metadata: response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((
+                        name.as_str().strip_prefix("x-ms-meta-")?.to_string(),
+                        value.to_str().ok()?.to_string(),
+                    ))
+                })
+                .collect(),
+        })
+    }
+
+    /// Containers are deleted asynchronously by Azure, their blobs included.
+    pub fn delete_container(&self, container_name: &str) -> Result<(), AzureBlobStorageServiceError> {
+        match self.send(
+            Method::DELETE,
+            &format!("/{container_name}"),
+            &[("restype", "container")],
+            &[],
+            vec![],
+        ) {
+            Ok(_) => Ok(()),
+            Err(e) if e.is_not_found() => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Makes the container private, or readable by anyone for its blobs.
+    pub fn set_container_public_access(
+        &self,
+        container_name: &str,
+        public_blobs: bool,
+    ) -> Result<(), AzureBlobStorageServiceError> {
+        let headers = match public_blobs {
+            true => vec![("x-ms-blob-public-access", "blob".to_string())],
+            false => vec![],
+        };
+        self.send(
+            Method::PUT,
+            &format!("/{container_name}"),
+            &[("restype", "container"), ("comp", "acl")],
+            &headers,
+            vec![],
+        )
+        .map(|_| ())
+    }
+
+    pub fn list_blobs(&self, container_name: &str) -> Result<Vec<AzureBlob>, AzureBlobStorageServiceError> {
+        let mut blobs = vec![];
+        let mut marker = String::new();
+        loop {
+            let mut query = vec![("restype", "container"), ("comp", "list"), ("include", "versions")];
+            if !marker.is_empty() {
+                query.push(("marker", marker.as_str()));
+            }
+            let body = self
+                .send(Method::GET, &format!("/{container_name}"), &query, &[], vec![])?
+                .text()
+                .map_err(|e| AzureBlobStorageServiceError::Http {
+                    raw_error_message: e.to_string(),
+                })?;
+
+            blobs.extend(parse_blobs(&body));
+            match xml_elements(&body, "NextMarker").first() {
+                Some(next_marker) if !next_marker.is_empty() => marker = xml_unescape(next_marker),
+                _ => return Ok(blobs),
+            }
+        }
+    }
+
+    /// Deletes every blob of the container, with their previous versions.
+    pub fn empty_container(&self, container_name: &str) -> Result<(), AzureBlobStorageServiceError> {
+        for blob in self.list_blobs(container_name)? {
+            // the current version can only be deleted through the base blob, it then becomes a previous version
+            if blob.is_current_version || blob.version_id.is_none() {
+                self.delete_blob(container_name, &blob.name, None)?;
+            }
+            if let Some(version_id) = &blob.version_id {
+                self.delete_blob(container_name, &blob.name, Some(version_id))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn put_blob(
+        &self,
+        container_name: &str,
+        blob_name: &str,
+        content: Vec<u8>,
+        tags: &[String],
+    ) -> Result<(), AzureBlobStorageServiceError> {
+        let mut headers = vec![
+            ("x-ms-blob-type", "BlockBlob".to_string()),
+            ("content-type", "application/octet-stream".to_string()),
+        ];
+        if !tags.is_empty() {
+            headers.push(("x-ms-tags", tags.join("&")));
+        }
+
+        self.send(Method::PUT, &blob_path(container_name, blob_name), &[], &headers, content)
+            .map(|_| ())
+    }
+
+    /// Stages a block of a block blob, it is only visible once committed by `put_block_list`
+    pub fn put_block(
+        &self,
+        container_name: &str,
+        blob_name: &str,
+        block_id: &str,
+        content: Vec<u8>,
+    ) -> Result<(), AzureBlobStorageServiceError> {
+        self.send(
+            Method::PUT,
+            &blob_path(container_name, blob_name),
+            &[("comp", "block"), ("blockid", block_id)],
+            &[],
+            content,
+        )
+        .map(|_| ())
+    }
+
+    /// Commits the staged blocks, in order, as the content of the blob
+    pub fn put_block_list(
+        &self,
+        container_name: &str,
+        blob_name: &str,
+        block_ids: &[String],
+        tags: &[String],
+    ) -> Result<(), AzureBlobStorageServiceError> {
+        let mut headers = vec![("x-ms-blob-content-type", "application/octet-stream".to_string())];
+        if !tags.is_empty() {
+            headers.push(("x-ms-tags", tags.join("&")));
+        }
+        let block_list = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><BlockList>{}</BlockList>"#,
+            block_ids
+                .iter()
+                .map(|block_id| format!("<Latest>{block_id}</Latest>"))
+                .join("")
+        );
+
+        self.send(
+            Method::PUT,
+            &blob_path(container_name, blob_name),
+            &[("comp", "blocklist")],
+            &headers,
+            block_list.into_bytes(),
+        )
+        .map(|_| ())
+    }
+
+    /// Returns the content of the blob and its tags, as `key=value`
+    pub fn get_blob(
+        &self,
+        container_name: &str,
+        blob_name: &str,
+    ) -> Result<(Vec<u8>, Vec<String>), AzureBlobStorageServiceError> {
+        let response = self.send(Method::GET, &blob_path(container_name, blob_name), &[], &[], vec![])?;
+        let has_tags = response
+            .headers()
+            .get("x-ms-tag-count")
+            .and_then(|count| count.to_str().ok())
+            .and_then(|count| count.parse::<u32>().ok())
+            .unwrap_or(0)
+            > 0;
+        let content = response.bytes().map_err(|e| AzureBlobStorageServiceError::Http {
+            raw_error_message: e.to_string(),
+        })?;
+
+        let tags = match has_tags {
+            true => self
+                .send(
+                    Method::GET,
+                    &blob_path(container_name, blob_name),
+                    &[("comp", "tags")],
+                    &[],
+                    vec![],
+                )?
+                .text()
+                .map(|body| parse_tags(&body))
+                .unwrap_or_default(),
+            false => vec![],
+        };
+
+        Ok((content.to_vec(), tags))
+    }
+
+    /// Deletes the blob, or one of its versions, succeeds when it does not exist
+    pub fn delete_blob(
+        &self,
+        container_name: &str,
+        blob_name: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), AzureBlobStorageServiceError> {
+        let query = version_id
+            .map(|version_id| vec![("versionid", version_id)])
+            .unwrap_or_default();
+        match self.send(Method::DELETE, &blob_path(container_name, blob_name), &query, &[], vec![]) {
+            Ok(_) => Ok(()),
+            Err(e) if e.is_not_found() => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Blob versioning is a setting of the whole storage account, only reachable through the management API
+    pub fn is_versioning_enabled(&self) -> Result<bool, AzureBlobStorageServiceError> {
+        if let AzureStorageCredentials::AccountKey(_) = self.credentials {
+            return Ok(false);
+        }
+
+        let token = self.access_token(AZURE_MANAGEMENT_SCOPE)?;
+        let blob_service: serde_json::Value = self
+            .http_client
+            .get(self.management_url())
+            .bearer_auth(token)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.json())
+            .map_err(|e| to_management_error(&e))?;
+
+        Ok(blob_service["properties"]["isVersioningEnabled"]
+            .as_bool()
+            .unwrap_or(false))
+    }
+
+    pub fn set_versioning(&self, enabled: bool) -> Result<(), AzureBlobStorageServiceError> {
+        if let AzureStorageCredentials::AccountKey(_) = self.credentials {
+            return Err(AzureBlobStorageServiceError::Unsupported {
+                raw_error_message: format!(
+                    "blob versioning of storage account `{}` cannot be changed with a storage account key",
+                    self.account.name
+                ),
+            });
+        }
+
+        let token = self.access_token(AZURE_MANAGEMENT_SCOPE)?;
+        self.http_client
+            .put(self.management_url())
+            .bearer_auth(token)
+            .json(&json!({ "properties": { "isVersioningEnabled": enabled } }))
+            .send()
+            .and_then(|res| res.error_for_status())
+            .map(|_| ())
+            .map_err(|e| to_management_error(&e))
+    }
+}
+
+/// Block blob upload, its blocks being the parts of the multipart upload
+pub struct AzureBlockBlobUpload<'a> {
+    pub service: &'a AzureBlobStorageService,
+    pub container_name: &'a str,
+    pub blob_name: &'a str,
+    pub tags: Vec<String>,
+}
+
+/// Block ids of a blob must all have the same length
+fn block_id(part_number: u64) -> String {
+    general_purpose::STANDARD.encode(format!("{part_number:08}"))
+}
+
+impl MultipartUploadClient for AzureBlockBlobUpload<'_> {
+    fn create(&self) -> Result<String, String> {
+        // blocks are staged on the blob itself, there is no upload to start
+        Ok(self.blob_name.to_string())
+    }
+
+    fn upload_part(&self, _upload_id: &str, part: &Part) -> Result<String, String> {
+        // blocks cannot be empty, an empty block list commits an empty blob
+        if part.data.is_empty() {
+            return Ok(String::new());
+        }
+        self.service
+            .put_block(self.container_name, self.blob_name, &block_id(part.number), part.data.clone())
+            .map(|_| block_id(part.number))
+            .map_err(|e| e.to_string())
+    }
+
+    fn complete(&self, _upload_id: &str, parts: &[UploadedPart]) -> Result<(), String> {
+        let block_ids = parts
+            .iter()
+            .filter(|part| !part.e_tag.is_empty())
+            .map(|part| part.e_tag.to_string())
+            .collect_vec();
+        self.service
+            .put_block_list(self.container_name, self.blob_name, &block_ids, &self.tags)
+            .map_err(|e| e.to_string())
+    }
+
+    fn abort(&self, _upload_id: &str) -> Result<(), String> {
+        // uncommitted blocks are garbage collected by Azure after a week
+        Ok(())
+    }
+}
+
+fn to_management_error(error: &reqwest::Error) -> AzureBlobStorageServiceError {
+    match error.status() {
+        Some(status) => AzureBlobStorageServiceError::Api {
+            status: status.as_u16(),
+            code: status.canonical_reason().unwrap_or_default().to_string(),
+            message: error.to_string(),
+        },
+        None => AzureBlobStorageServiceError::Http {
+            raw_error_message: error.to_string(),
+        },
+    }
+}
+
+fn blob_path(container_name: &str, blob_name: &str) -> String {
+    format!(
+        "/{}/{}",
+        container_name,
+        blob_name
+            .split('/')
+            .map(|segment| urlencoding::encode(segment))
+            .join("/")
+    )
+}
+
+/// https://learn.microsoft.com/en-us/rest/api/storageservices/authorize-with-shared-key
+fn shared_key_string_to_sign(
+    method: &str,
+    account_name: &str,
+    url: &Url,
+    headers: &[(String, String)],
+    content_length: usize,
+) -> String {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_default()
+    };
+    let canonicalized_headers = headers
+        .iter()
+        .filter(|(name, _)| name.starts_with("x-ms-"))
+        .sorted()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .join("");
+    let canonicalized_query = url
+        .query_pairs()
+        .map(|(name, value)| (name.to_lowercase(), value.to_string()))
+        .into_group_map()
+        .into_iter()
+        .sorted()
+        .map(|(name, values)| format!("\n{}:{}", name, values.into_iter().sorted().join(",")))
+        .join("");
+
+    [
+        method,
+        header("content-encoding"),
+        header("content-language"),
+        // an empty body has no content length since API version 2015-02-21
+        &match content_length {
+            0 => String::new(),
+            length => length.to_string(),
+        },
+        header("content-md5"),
+        header("content-type"),
+        "", // date, x-ms-date is used instead
+        header("if-modified-since"),
+        header("if-match"),
+        header("if-none-match"),
+        header("if-unmodified-since"),
+        header("range"),
+        &format!(
+            "{}/{}{}{}",
+            canonicalized_headers,
+            account_name,
+            url.path(),
+            canonicalized_query
+        ),
+    ]
+    .join("\n")
+}
+
+fn shared_key_signature(account_key: &str, string_to_sign: &str) -> Result<String, AzureBlobStorageServiceError> {
+    let key = general_purpose::STANDARD.decode(account_key).map_err(|e| {
+        AzureBlobStorageServiceError::CannotAuthenticate {
+            raw_error_message: format!("storage account key is not valid base64: {e}"),
+        }
+    })?;
+
+    Ok(general_purpose::STANDARD.encode(hmac_sha256(&key, string_to_sign.as_bytes())))
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// Content of the `<tag>` elements, the blob storage API answers with flat XML documents
+fn xml_elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{tag}>"), format!("</{tag}>"));
+    let mut elements = vec![];
+    let mut remaining = xml;
+    while let Some(start) = remaining.find(&open) {
+        remaining = &remaining[start + open.len()..];
+        let Some(end) = remaining.find(&close) else {
+            break;
+        };
+        elements.push(&remaining[..end]);
+        remaining = &remaining[end + close.len()..];
+    }
+
+    elements
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn parse_blobs(xml: &str) -> Vec<AzureBlob> {
+    xml_elements(xml, "Blob")
+        .into_iter()
+        .filter_map(|blob| {
+            Some(AzureBlob {
+                name: xml_unescape(xml_elements(blob, "Name").first()?),
+                version_id: xml_elements(blob, "VersionId").first().map(|id| xml_unescape(id)),
+                is_current_version: xml_elements(blob, "IsCurrentVersion").first() == Some(&"true"),
+            })
+        })
+        .collect()
+}
+
+fn parse_tags(xml: &str) -> Vec<String> {
+    xml_elements(xml, "Tag")
+        .into_iter()
+        .filter_map(|tag| {
+            Some(format!(
+                "{}={}",
+                xml_unescape(xml_elements(tag, "Key").first()?),
+                xml_unescape(xml_elements(tag, "Value").first().unwrap_or(&""))
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            mac.iter().map(|b| format!("{b:02x}")).join(""),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_shared_key_string_to_sign() {
+        let url = Url::parse(
+            "https://myaccount.blob.core.windows.net/qovery-logs/cluster/app%20logs.tar.gz?restype=container&comp=list&include=versions",
+        )
+        .unwrap();
+        let headers = vec![
+            ("x-ms-version".to_string(), BLOB_API_VERSION.to_string()),
+            ("content-type".to_string(), "application/octet-stream".to_string()),
+            ("x-ms-date".to_string(), "Fri, 16 Oct 2026 10:00:00 GMT".to_string()),
+            ("x-ms-blob-type".to_string(), "BlockBlob".to_string()),
+        ];
+
+        assert_eq!(
+            shared_key_string_to_sign("PUT", "myaccount", &url, &headers, 42),
+            concat!(
+                "PUT\n\n\n42\n\napplication/octet-stream\n\n\n\n\n\n\n",
+                "x-ms-blob-type:BlockBlob\n",
+                "x-ms-date:Fri, 16 Oct 2026 10:00:00 GMT\n",
+                "x-ms-version:2021-12-02\n",
+                "/myaccount/qovery-logs/cluster/app%20logs.tar.gz\n",
+                "comp:list\n",
+                "include:versions\n",
+                "restype:container",
+            )
+        );
+
+        // no content length for an empty body
+        let url = Url::parse("https://myaccount.blob.core.windows.net/qovery-logs?restype=container").unwrap();
+        assert!(shared_key_string_to_sign("DELETE", "myaccount", &url, &[], 0).starts_with("DELETE\n\n\n\n"));
+    }
+
+    #[test]
+    fn test_block_id() {
+        assert_eq!(block_id(1), "MDAwMDAwMDE=");
+        assert_eq!(block_id(1).len(), block_id(10_000).len());
+    }
+
+    #[test]
+    fn test_blob_path() {
+        assert_eq!(
+            blob_path("qovery-logs", "cluster/app logs.tar.gz"),
+            "/qovery-logs/cluster/app%20logs.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_parse_list_blobs() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+            <EnumerationResults ServiceEndpoint="https://myaccount.blob.core.windows.net/" ContainerName="qovery-logs">
+              <Blobs>
+                <Blob><Name>kubeconfig.yaml</Name><VersionId>2026-10-16T10:00:00.0000000Z</VersionId><IsCurrentVersion>true</IsCurrentVersion><Properties /></Blob>
+                <Blob><Name>kubeconfig.yaml</Name><VersionId>2026-10-15T10:00:00.0000000Z</VersionId><Properties /></Blob>
+                <Blob><Name>logs/a&amp;b.tar.gz</Name><Properties /></Blob>
+              </Blobs>
+              <NextMarker>2!96!MDAwMDE</NextMarker>
+            </EnumerationResults>"#;
+
+        assert_eq!(
+            parse_blobs(xml),
+            vec![
+                AzureBlob {
+                    name: "kubeconfig.yaml".to_string(),
+                    version_id: Some("2026-10-16T10:00:00.0000000Z".to_string()),
+                    is_current_version: true,
+                },
+                AzureBlob {
+                    name: "kubeconfig.yaml".to_string(),
+                    version_id: Some("2026-10-15T10:00:00.0000000Z".to_string()),
+                    is_current_version: false,
+                },
+                AzureBlob {
+                    name: "logs/a&b.tar.gz".to_string(),
+                    version_id: None,
+                    is_current_version: false,
+                },
+            ]
+        );
+        assert_eq!(xml_elements(xml, "NextMarker"), vec!["2!96!MDAwMDE"]);
+        assert!(xml_elements("<EnumerationResults><NextMarker /></EnumerationResults>", "NextMarker").is_empty());
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+            <Tags><TagSet><Tag><Key>cluster</Key><Value>z1234</Value></Tag><Tag><Key>ttl</Key><Value>0</Value></Tag></TagSet></Tags>"#;
+        assert_eq!(parse_tags(xml), vec!["cluster=z1234", "ttl=0"]);
+    }
+}
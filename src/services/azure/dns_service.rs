@@ -0,0 +1,874 @@
+use crate::infrastructure::models::dns_provider::azure_dns::AzureDnsConfig;
+use itertools::Itertools;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use thiserror::Error;
+use url::Url;
+
+const AZURE_LOGIN_URL: &str = "https://login.microsoftonline.com";
+const AZURE_MANAGEMENT_URL: &str = "https://management.azure.com";
+const AZURE_MANAGEMENT_SCOPE: &str = "https://management.azure.com/.default";
+const DNS_API_VERSION: &str = "2018-05-01";
+const AUTHORIZATION_API_VERSION: &str = "2022-04-01";
+const OWNERSHIP_RECORD_PREFIX: &str = "qvy-owner";
+
+/// Built-in Azure role granting every action required to manage records of a zone.
+pub const AZURE_DNS_REQUIRED_ROLE: &str = "DNS Zone Contributor";
+
+/// Actions the service principal must be granted on the zone, all covered by `AZURE_DNS_REQUIRED_ROLE`.
+const AZURE_DNS_REQUIRED_ACTIONS: [&str; 7] = [
+    "Microsoft.Network/dnsZones/read",
+    "Microsoft.Network/dnsZones/A/write",
+    "Microsoft.Network/dnsZones/A/delete",
+    "Microsoft.Network/dnsZones/CNAME/write",
+    "Microsoft.Network/dnsZones/CNAME/delete",
+    "Microsoft.Network/dnsZones/TXT/write",
+    "Microsoft.Network/dnsZones/TXT/delete",
+];
+
+#[derive(Clone, Error, Debug, PartialEq, Eq)]
+pub enum AzureDnsServiceError {
+    #[error("Cannot instantiate Azure DNS client, error: `{raw_error_message}`")]
+    CannotInstantiateClient { raw_error_message: String },
+    #[error("Cannot authenticate against Azure Active Directory, error: `{raw_error_message}`")]
+    CannotAuthenticate { raw_error_message: String },
+    #[error("No Azure DNS zone found for domain `{domain}` in resource group `{resource_group}`")]
+    ZoneNotFound { domain: String, resource_group: String },
+    #[error("Azure service principal is missing role `{role}` on zone `{zone}`, missing actions: {missing_actions:?}")]
+    MissingRole {
+        role: String,
+        zone: String,
+        missing_actions: Vec<String>,
+    },
+    #[error("Invalid DNS record `{record_name}`: {raw_error_message}")]
+    InvalidRecord {
+        record_name: String,
+        raw_error_message: String,
+    },
+    #[error("Azure DNS API error on `{record_name}`, error: `{raw_error_message}`")]
+    ApiError {
+        record_name: String,
+        raw_error_message: String,
+    },
+}
+
+/// Azure endpoints, overridable to target sovereign clouds or a mock server.
+#[derive(Clone, Debug)]
+pub struct AzureDnsEndpoints {
+    pub login_url: Url,
+    pub management_url: Url,
+}
+
+impl Default for AzureDnsEndpoints {
+    fn default() -> Self {
+        AzureDnsEndpoints {
+            login_url: Url::parse(AZURE_LOGIN_URL).expect("invalid Azure login url"),
+            management_url: Url::parse(AZURE_MANAGEMENT_URL).expect("invalid Azure management url"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AzureDnsZone {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DnsRecordTarget {
+    Ipv4(Ipv4Addr),
+    Cname(String),
+    /// Alias record pointing to an Azure resource, typically the public IP of a load balancer.
+    AzureResource {
+        resource_id: String,
+    },
+}
+
+impl DnsRecordTarget {
+    fn record_type(&self) -> &'static str {
+        match self {
+            DnsRecordTarget::Ipv4(_) | DnsRecordTarget::AzureResource { .. } => "A",
+            DnsRecordTarget::Cname(_) => "CNAME",
+        }
+    }
+}
+
+/// A DNS record, `name` being relative to the zone (`@` for apex, `*` or `*.sub` for wildcards).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DnsRecord {
+    pub name: String,
+    pub target: DnsRecordTarget,
+    pub ttl: u32,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct ListResponse<T> {
+    value: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct ZoneResponse {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PermissionResponse {
+    #[serde(default)]
+    actions: Vec<String>,
+    #[serde(default)]
+    not_actions: Vec<String>,
+}
+
+pub struct AzureDnsService {
+    config: AzureDnsConfig,
+    endpoints: AzureDnsEndpoints,
+    http_client: Client,
+    owner_id: String,
+}
+
+impl AzureDnsService {
+    pub fn new(
+        config: AzureDnsConfig,
+        endpoints: AzureDnsEndpoints,
+        owner_id: String,
+    ) -> Result<Self, AzureDnsServiceError> {
+        let http_client = Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(30))
+            .user_agent("qovery-engine")
+            .build()
+            .map_err(|e| AzureDnsServiceError::CannotInstantiateClient {
+                raw_error_message: e.to_string(),
+            })?;
+
+        Ok(AzureDnsService {
+            config,
+            endpoints,
+            http_client,
+            owner_id,
+        })
+    }
+
+    fn access_token(&self) -> Result<String, AzureDnsServiceError> {
+        let url = format!(
+            "{}/{}/oauth2/v2.0/token",
+            self.endpoints.login_url.as_str().trim_end_matches('/'),
+            self.config.tenant_id
+        );
+        let response: TokenResponse = self
+            .http_client
+            .post(url)
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", self.config.client_id.as_str()),
+                ("client_secret", self.config.client_secret.as_str()),
+                ("scope", AZURE_MANAGEMENT_SCOPE),
+            ])
+            .send()
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.json())
+            .map_err(|e| AzureDnsServiceError::CannotAuthenticate {
+                raw_error_message: e.to_string(),
+            })?;
+
+        Ok(response.access_token)
+    }
+
+    fn management_url(&self, path: &str, api_version: &str) -> String {
+        format!(
+            "{}{}?api-version={}",
+            self.endpoints.management_url.as_str().trim_end_matches('/'),
+            path,
+            api_version
+        )
+    }
+
+    fn zones_path(&self) -> String {
+        format!(
+            "/subscriptions/{}/resourceGroups/{}/providers/Microsoft.Network/dnsZones",
+            self.config.subscription_id, self.config.resource_group
+        )
+    }
+
+    /// Finds the most specific zone of the resource group hosting the given domain.
+    pub fn find_zone(&self, domain: &str) -> Result<AzureDnsZone, AzureDnsServiceError> {
+        let token = self.access_token()?;
+        let zones: ListResponse<ZoneResponse> = self
+            .http_client
+            .get(self.management_url(&self.zones_path(), DNS_API_VERSION))
+            .bearer_auth(&token)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.json())
+            .map_err(|e| AzureDnsServiceError::ApiError {
+                record_name: domain.to_string(),
+                raw_error_message: e.to_string(),
+            })?;
+
+        let domain = domain.trim_start_matches("*.").trim_end_matches('.');
+        zones
+            .value
+            .into_iter()
+            .filter(|zone| domain == zone.name || domain.ends_with(&format!(".{}", zone.name)))
+            .max_by_key(|zone| zone.name.len())
+            .map(|zone| AzureDnsZone {
+                id: zone.id,
+                name: zone.name,
+            })
+            .ok_or_else(|| AzureDnsServiceError::ZoneNotFound {
+                domain: domain.to_string(),
+                resource_group: self.config.resource_group.to_string(),
+            })
+    }
+
+    /// Makes sure the service principal is allowed to manage records of the zone.
+    pub fn check_permissions(&self, zone: &AzureDnsZone) -> Result<(), AzureDnsServiceError> {
+        let token = self.access_token()?;
+        let permissions: ListResponse<PermissionResponse> = self
+            .http_client
+            .get(self.management_url(
+                &format!("{}/providers/Microsoft.Authorization/permissions", zone.id),
+                AUTHORIZATION_API_VERSION,
+            ))
+            .bearer_auth(&token)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.json())
+            .map_err(|e| AzureDnsServiceError::ApiError {
+                record_name: zone.name.to_string(),
+                raw_error_message: e.to_string(),
+            })?;
+
+        let missing_actions = missing_actions(&permissions.value);
+        if !missing_actions.is_empty() {
+            return Err(AzureDnsServiceError::MissingRole {
+                role: AZURE_DNS_REQUIRED_ROLE.to_string(),
+                zone: zone.name.to_string(),
+                missing_actions,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Creates or updates records, one API call per record set, alongside their ownership TXT record.
+    pub fn upsert_records(&self, zone: &AzureDnsZone, records: &[DnsRecord]) -> Result<(), AzureDnsServiceError> {
+        let record_sets = group_into_record_sets(records)?;
+        if record_sets.is_empty() {
+            return Ok(());
+        }
+
+        let token = self.access_token()?;
+        for ((name, record_type), record_set) in record_sets {
+            if let Some(owner) = self.get_owner(&token, zone, &name)? {
+                if owner != self.owner_id {
+                    return Err(AzureDnsServiceError::ApiError {
+                        record_name: name,
+                        raw_error_message: format!("record is owned by `{owner}`, refusing to overwrite it"),
+                    });
+                }
+            }
+
+            self.put_record_set(&token, zone, &name, record_type, &record_set)?;
+            self.put_record_set(
+                &token,
+                zone,
+                &ownership_record_name(&name),
+                "TXT",
+                &json!({
+                    "properties": {
+                        "TTL": record_set["properties"]["TTL"],
+                        "TXTRecords": [{ "value": [ownership_record_value(&self.owner_id)] }],
+                    }
+                }),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes records owned by this owner, records without a matching ownership TXT record are left untouched.
+    /// Returns the names of the records actually deleted.
+    pub fn delete_records(
+        &self,
+        zone: &AzureDnsZone,
+        records: &[DnsRecord],
+    ) -> Result<Vec<String>, AzureDnsServiceError> {
+        let record_sets = group_into_record_sets(records)?;
+        if record_sets.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let token = self.access_token()?;
+        let mut deleted = vec![];
+        for (name, record_type) in record_sets.into_keys() {
+            match self.get_owner(&token, zone, &name)? {
+                Some(owner) if owner == self.owner_id => {
+                    self.delete_record_set(&token, zone, &name, record_type)?;
+                    self.delete_record_set(&token, zone, &ownership_record_name(&name), "TXT")?;
+                    deleted.push(name);
+                }
+                _ => warn!(
+                    "Skipping deletion of DNS record `{}` in zone `{}`, it is not owned by `{}`",
+                    name, zone.name, self.owner_id
+                ),
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    fn record_set_url(&self, zone: &AzureDnsZone, name: &str, record_type: &str) -> String {
+        self.management_url(&format!("{}/{}/{}", zone.id, record_type, name), DNS_API_VERSION)
+    }
+
+    fn get_owner(&self, token: &str, zone: &AzureDnsZone, name: &str) -> Result<Option<String>, AzureDnsServiceError> {
+        let to_error = |raw_error_message: String| AzureDnsServiceError::ApiError {
+            record_name: name.to_string(),
+            raw_error_message,
+        };
+
+        let response = self
+            .http_client
+            .get(self.record_set_url(zone, &ownership_record_name(name), "TXT"))
+            .bearer_auth(token)
+            .send()
+            .map_err(|e| to_error(e.to_string()))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let record_set: Value = response
+            .error_for_status()
+            .and_then(|res| res.json())
+            .map_err(|e| to_error(e.to_string()))?;
+
+        Ok(record_set["properties"]["TXTRecords"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|txt| txt["value"].as_array().cloned().unwrap_or_default())
+            .filter_map(|value| value.as_str().and_then(parse_ownership_record_value))
+            .next())
+    }
+
+    fn put_record_set(
+        &self,
+        token: &str,
+        zone: &AzureDnsZone,
+        name: &str,
+        record_type: &str,
+        body: &Value,
+    ) -> Result<(), AzureDnsServiceError> {
+        self.http_client
+            .put(self.record_set_url(zone, name, record_type))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .map(|_| ())
+            .map_err(|e| AzureDnsServiceError::ApiError {
+                record_name: name.to_string(),
+                raw_error_message: e.to_string(),
+            })
+    }
+
+    fn delete_record_set(
+        &self,
+        token: &str,
+        zone: &AzureDnsZone,
+        name: &str,
+        record_type: &str,
+    ) -> Result<(), AzureDnsServiceError> {
+        match self
+            .http_client
+            .delete(self.record_set_url(zone, name, record_type))
+            .bearer_auth(token)
+            .send()
+            .and_then(|res| res.error_for_status())
+        {
+            Ok(_) => Ok(()),
+            Err(err) if matches!(err.status(), Some(StatusCode::NOT_FOUND)) => Ok(()),
+            Err(err) => Err(AzureDnsServiceError::ApiError {
+                record_name: name.to_string(),
+                raw_error_message: err.to_string(),
+            }),
+        }
+    }
+}
+
+fn ownership_record_name(name: &str) -> String {
+    // `*` is not allowed in the middle of a record name, so wildcard records are tracked under `any`
+    match name {
+        "@" => OWNERSHIP_RECORD_PREFIX.to_string(),
+        _ => format!("{}-{}", OWNERSHIP_RECORD_PREFIX, name.replace('*', "any")),
+    }
+}
+
+fn ownership_record_value(owner_id: &str) -> String {
+    format!("heritage=qovery,qovery/owner={owner_id}")
+}
+
+fn parse_ownership_record_value(value: &str) -> Option<String> {
+    let mut fields = value.trim_matches('"').split(',');
+    if fields.next()? != "heritage=qovery" {
+        return None;
+    }
+
+    fields
+        .find_map(|field| field.strip_prefix("qovery/owner="))
+        .map(|owner| owner.to_string())
+}
+
+fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let value = value.to_lowercase();
+    let parts = pattern.split('*').collect_vec();
+    if parts.len() == 1 {
+        return pattern == value;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !value.starts_with(first) || !value[first.len()..].ends_with(last) {
+        return false;
+    }
+
+    let mut remaining = &value[first.len()..value.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match remaining.find(part) {
+            Some(idx) => remaining = &remaining[idx + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+fn missing_actions(permissions: &[PermissionResponse]) -> Vec<String> {
+    AZURE_DNS_REQUIRED_ACTIONS
+        .iter()
+        .filter(|action| {
+            !permissions.iter().any(|permission| {
+                permission.actions.iter().any(|granted| glob_match(granted, action))
+                    && !permission.not_actions.iter().any(|denied| glob_match(denied, action))
+            })
+        })
+        .map(|action| action.to_string())
+        .collect()
+}
+
+/// Azure DNS works with record sets: all records sharing a name and a type are sent in a single call.
+fn group_into_record_sets(
+    records: &[DnsRecord],
+) -> Result<BTreeMap<(String, &'static str), Value>, AzureDnsServiceError> {
+    let mut record_sets = BTreeMap::new();
+    for ((name, record_type), group) in &records
+        .iter()
+        .sorted_by(|a, b| (&a.name, a.target.record_type()).cmp(&(&b.name, b.target.record_type())))
+        .chunk_by(|record| (record.name.to_string(), record.target.record_type()))
+    {
+        let group = group.collect_vec();
+        let invalid_record = |raw_error_message: &str| AzureDnsServiceError::InvalidRecord {
+            record_name: name.to_string(),
+            raw_error_message: raw_error_message.to_string(),
+        };
+        let ttl = group.iter().map(|record| record.ttl).min().unwrap_or(300);
+
+        let properties = match group.as_slice() {
+            [DnsRecord {
+                target: DnsRecordTarget::AzureResource { resource_id },
+                ..
+            }] => json!({ "TTL": ttl, "targetResource": { "id": resource_id } }),
+            [DnsRecord {
+                target: DnsRecordTarget::Cname(cname),
+                ..
+            }] => json!({ "TTL": ttl, "CNAMERecord": { "cname": cname } }),
+            records if records.iter().all(|r| matches!(r.target, DnsRecordTarget::Ipv4(_))) => {
+                let ips = records
+                    .iter()
+                    .filter_map(|r| match &r.target {
+                        DnsRecordTarget::Ipv4(ip) => Some(*ip),
+                        _ => None,
+                    })
+                    .unique()
+                    .map(|ip| json!({ "ipv4Address": ip.to_string() }))
+                    .collect_vec();
+                json!({ "TTL": ttl, "ARecords": ips })
+            }
+            records
+                if records
+                    .iter()
+                    .any(|r| matches!(r.target, DnsRecordTarget::AzureResource { .. })) =>
+            {
+                return Err(invalid_record("an alias record cannot be mixed with other targets"));
+            }
+            _ => return Err(invalid_record("a CNAME record must have a single target")),
+        };
+
+        record_sets.insert((name, record_type), json!({ "properties": properties }));
+    }
+
+    Ok(record_sets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_utils::MockServer;
+
+    const ZONE_ID: &str = "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.Network/dnsZones/example.com";
+
+    fn service(server: &MockServer) -> AzureDnsService {
+        AzureDnsService::new(
+            AzureDnsConfig {
+                tenant_id: "tenant".to_string(),
+                subscription_id: "sub".to_string(),
+                resource_group: "rg".to_string(),
+                client_id: "client".to_string(),
+                client_secret: "secret".to_string(),
+            },
+            AzureDnsEndpoints {
+                login_url: server.url.clone(),
+                management_url: server.url.clone(),
+            },
+            "cluster-id".to_string(),
+        )
+        .expect("cannot create service")
+    }
+
+    fn zone() -> AzureDnsZone {
+        AzureDnsZone {
+            id: ZONE_ID.to_string(),
+            name: "example.com".to_string(),
+        }
+    }
+
+    fn record_set_target(record_type: &str, name: &str) -> String {
+        format!("{ZONE_ID}/{record_type}/{name}?api-version={DNS_API_VERSION}")
+    }
+
+    fn token_or(method: &str, path: &str, f: impl Fn(&str, &str) -> (u16, String)) -> (u16, String) {
+        if method == "POST" && path == "/tenant/oauth2/v2.0/token" {
+            return (200, r#"{"access_token":"token","token_type":"Bearer"}"#.to_string());
+        }
+        f(method, path)
+    }
+
+    #[test]
+    fn test_find_zone_picks_most_specific_zone() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, path: &str| {
+            token_or(method, path, |_, _| {
+                (
+                    200,
+                    json!({ "value": [
+                        { "id": "/zones/example.com", "name": "example.com" },
+                        { "id": "/zones/prod.example.com", "name": "prod.example.com" },
+                        { "id": "/zones/other.com", "name": "other.com" },
+                    ]})
+                    .to_string(),
+                )
+            })
+        }));
+
+        // execute:
+        let zone = service(&server).find_zone("*.cluster.prod.example.com");
+
+        // verify:
+        assert_eq!(
+            Ok(AzureDnsZone {
+                id: "/zones/prod.example.com".to_string(),
+                name: "prod.example.com".to_string()
+            }),
+            zone
+        );
+        assert_eq!(
+            format!(
+                "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.Network/dnsZones?api-version={DNS_API_VERSION}"
+            ),
+            server.calls()[1].1
+        );
+    }
+
+    #[test]
+    fn test_find_zone_not_found() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, path: &str| {
+            token_or(method, path, |_, _| {
+                (
+                    200,
+                    json!({ "value": [{ "id": "/zones/other.com", "name": "other.com" }]}).to_string(),
+                )
+            })
+        }));
+
+        // execute & verify:
+        assert_eq!(
+            Err(AzureDnsServiceError::ZoneNotFound {
+                domain: "cluster.example.com".to_string(),
+                resource_group: "rg".to_string()
+            }),
+            service(&server).find_zone("cluster.example.com")
+        );
+    }
+
+    #[test]
+    fn test_check_permissions() {
+        struct TestCase<'a> {
+            permissions: Value,
+            expected_missing_actions: Vec<&'a str>,
+            description: &'a str,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                permissions: json!({ "value": [{ "actions": ["Microsoft.Network/dnsZones/*"], "notActions": [] }]}),
+                expected_missing_actions: vec![],
+                description: "DNS Zone Contributor role",
+            },
+            TestCase {
+                permissions: json!({ "value": [{ "actions": ["*"], "notActions": [] }]}),
+                expected_missing_actions: vec![],
+                description: "Owner role",
+            },
+            TestCase {
+                permissions: json!({ "value": [{ "actions": ["*/read"], "notActions": [] }]}),
+                expected_missing_actions: AZURE_DNS_REQUIRED_ACTIONS[1..].to_vec(),
+                description: "Reader role",
+            },
+            TestCase {
+                permissions: json!({ "value": [{ "actions": ["*"], "notActions": ["Microsoft.Network/dnsZones/*/delete"] }]}),
+                expected_missing_actions: vec![
+                    "Microsoft.Network/dnsZones/A/delete",
+                    "Microsoft.Network/dnsZones/CNAME/delete",
+                    "Microsoft.Network/dnsZones/TXT/delete",
+                ],
+                description: "delete actions explicitly denied",
+            },
+        ];
+
+        for tc in test_cases {
+            // setup:
+            let body = tc.permissions.to_string();
+            let server = MockServer::start(Box::new(move |method: &str, path: &str| {
+                token_or(method, path, |_, _| (200, body.clone()))
+            }));
+
+            // execute:
+            let result = service(&server).check_permissions(&zone());
+
+            // verify:
+            match tc.expected_missing_actions.is_empty() {
+                true => assert_eq!(Ok(()), result, "{}", tc.description),
+                false => assert_eq!(
+                    Err(AzureDnsServiceError::MissingRole {
+                        role: AZURE_DNS_REQUIRED_ROLE.to_string(),
+                        zone: "example.com".to_string(),
+                        missing_actions: tc.expected_missing_actions.iter().map(|a| a.to_string()).collect(),
+                    }),
+                    result,
+                    "{}",
+                    tc.description
+                ),
+            }
+            assert_eq!(
+                format!(
+                    "{ZONE_ID}/providers/Microsoft.Authorization/permissions?api-version={AUTHORIZATION_API_VERSION}"
+                ),
+                server.calls()[1].1,
+                "{}",
+                tc.description
+            );
+        }
+    }
+
+    #[test]
+    fn test_upsert_records_batches_record_sets_and_writes_ownership() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, path: &str| {
+            token_or(method, path, |method, _| match method {
+                "GET" => (404, "{}".to_string()),
+                _ => (200, "{}".to_string()),
+            })
+        }));
+        let records = vec![
+            DnsRecord {
+                name: "*.cluster".to_string(),
+                target: DnsRecordTarget::Ipv4(Ipv4Addr::new(10, 0, 0, 1)),
+                ttl: 300,
+            },
+            DnsRecord {
+                name: "*.cluster".to_string(),
+                target: DnsRecordTarget::Ipv4(Ipv4Addr::new(10, 0, 0, 2)),
+                ttl: 60,
+            },
+            DnsRecord {
+                name: "lb".to_string(),
+                target: DnsRecordTarget::AzureResource {
+                    resource_id:
+                        "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.Network/publicIPAddresses/lb-ip"
+                            .to_string(),
+                },
+                ttl: 300,
+            },
+        ];
+
+        // execute:
+        let result = service(&server).upsert_records(&zone(), &records);
+
+        // verify:
+        assert_eq!(Ok(()), result);
+        let calls = server.calls();
+        let puts = calls.iter().filter(|(method, _, _)| method == "PUT").collect_vec();
+        assert_eq!(
+            vec![
+                record_set_target("A", "*.cluster"),
+                record_set_target("TXT", "qvy-owner-any.cluster"),
+                record_set_target("A", "lb"),
+                record_set_target("TXT", "qvy-owner-lb"),
+            ],
+            puts.iter().map(|(_, path, _)| path.to_string()).collect_vec()
+        );
+
+        let wildcard_body: Value = serde_json::from_str(&puts[0].2).unwrap();
+        assert_eq!(
+            json!({ "properties": { "TTL": 60, "ARecords": [{ "ipv4Address": "10.0.0.1" }, { "ipv4Address": "10.0.0.2" }] } }),
+            wildcard_body
+        );
+        let ownership_body: Value = serde_json::from_str(&puts[1].2).unwrap();
+        assert_eq!(
+            json!(["heritage=qovery,qovery/owner=cluster-id"]),
+            ownership_body["properties"]["TXTRecords"][0]["value"]
+        );
+        let alias_body: Value = serde_json::from_str(&puts[2].2).unwrap();
+        assert_eq!(
+            json!("/subscriptions/sub/resourceGroups/rg/providers/Microsoft.Network/publicIPAddresses/lb-ip"),
+            alias_body["properties"]["targetResource"]["id"]
+        );
+    }
+
+    #[test]
+    fn test_upsert_records_refuses_to_overwrite_foreign_records() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, path: &str| {
+            token_or(method, path, |_, _| {
+                (
+                    200,
+                    json!({ "properties": { "TXTRecords": [{ "value": ["heritage=qovery,qovery/owner=another-cluster"] }] } })
+                        .to_string(),
+                )
+            })
+        }));
+        let records = vec![DnsRecord {
+            name: "app".to_string(),
+            target: DnsRecordTarget::Cname("lb.example.com".to_string()),
+            ttl: 300,
+        }];
+
+        // execute:
+        let result = service(&server).upsert_records(&zone(), &records);
+
+        // verify:
+        assert!(matches!(result, Err(AzureDnsServiceError::ApiError { .. })));
+        assert!(server.calls().iter().all(|(method, _, _)| method != "PUT"));
+    }
+
+    #[test]
+    fn test_delete_records_only_deletes_owned_records() {
+        // setup:
+        let server = MockServer::start(Box::new(|method: &str, path: &str| {
+            token_or(method, path, |method, path| {
+                match (method, path) {
+                ("GET", p) if p == record_set_target("TXT", "qvy-owner-owned") => (
+                    200,
+                    json!({ "properties": { "TXTRecords": [{ "value": ["heritage=qovery,qovery/owner=cluster-id"] }] } })
+                        .to_string(),
+                ),
+                ("GET", _) => (404, "{}".to_string()),
+                _ => (200, "{}".to_string()),
+            }
+            })
+        }));
+        let records = ["owned", "not-owned"]
+            .iter()
+            .map(|name| DnsRecord {
+                name: name.to_string(),
+                target: DnsRecordTarget::Ipv4(Ipv4Addr::new(10, 0, 0, 1)),
+                ttl: 300,
+            })
+            .collect_vec();
+
+        // execute:
+        let result = service(&server).delete_records(&zone(), &records);
+
+        // verify:
+        assert_eq!(Ok(vec!["owned".to_string()]), result);
+        assert_eq!(
+            vec![
+                record_set_target("A", "owned"),
+                record_set_target("TXT", "qvy-owner-owned")
+            ],
+            server
+                .calls()
+                .iter()
+                .filter(|(method, _, _)| method == "DELETE")
+                .map(|(_, path, _)| path.to_string())
+                .collect_vec()
+        );
+    }
+
+    #[test]
+    fn test_group_into_record_sets_rejects_mixed_alias() {
+        // setup:
+        let records = vec![
+            DnsRecord {
+                name: "app".to_string(),
+                target: DnsRecordTarget::Ipv4(Ipv4Addr::new(10, 0, 0, 1)),
+                ttl: 300,
+            },
+            DnsRecord {
+                name: "app".to_string(),
+                target: DnsRecordTarget::AzureResource {
+                    resource_id: "/ip".to_string(),
+                },
+                ttl: 300,
+            },
+        ];
+
+        // execute & verify:
+        assert!(matches!(
+            group_into_record_sets(&records),
+            Err(AzureDnsServiceError::InvalidRecord { .. })
+        ));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", "Microsoft.Network/dnsZones/A/write"));
+        assert!(glob_match("Microsoft.Network/dnsZones/*", "Microsoft.Network/dnsZones/A/write"));
+        assert!(glob_match(
+            "microsoft.network/dnszones/*/write",
+            "Microsoft.Network/dnsZones/TXT/write"
+        ));
+        assert!(!glob_match(
+            "Microsoft.Network/dnsZones/*/read",
+            "Microsoft.Network/dnsZones/A/write"
+        ));
+        assert!(!glob_match(
+            "Microsoft.Network/dnsZones/read",
+            "Microsoft.Network/dnsZones/A/write"
+        ));
+    }
+}
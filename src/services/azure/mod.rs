@@ -0,0 +1,2 @@
+pub mod blob_storage_service;
+pub mod dns_service;
@@ -1,6 +1,10 @@
+use crate::cmd::command::{ExecutableCommand, QoveryCommand};
 use crate::environment::models::gcp::JsonCredentials;
 use crate::environment::models::ToCloudProviderFormat;
 use crate::infrastructure::models::cloud_provider::gcp::locations::GcpRegion as GcpCloudJobRegion;
+use crate::infrastructure::models::object_storage::multipart_upload::{
+    multipart_upload, MultipartUploadClient, Part, UploadedPart,
+};
 use crate::infrastructure::models::object_storage::{Bucket, BucketObject};
 use crate::runtime::block_on;
 use crate::services::gcp::cloud_job_service::CloudJobService;
@@ -9,29 +13,33 @@ use crate::services::gcp::object_storage_regions::GcpStorageRegion;
 use google_cloud_storage::client::{Client, ClientConfig};
 use google_cloud_storage::http::buckets::delete::DeleteBucketRequest;
 use google_cloud_storage::http::buckets::get::GetBucketRequest;
+use google_cloud_storage::http::buckets::iam_configuration::PublicAccessPrevention;
 use google_cloud_storage::http::buckets::insert::{BucketCreationConfig, InsertBucketParam, InsertBucketRequest};
 use google_cloud_storage::http::buckets::lifecycle::rule::{Action, ActionType, Condition};
 use google_cloud_storage::http::buckets::lifecycle::Rule;
 use google_cloud_storage::http::buckets::list::ListBucketsRequest;
 use google_cloud_storage::http::buckets::patch::{BucketPatchConfig, PatchBucketRequest};
 use google_cloud_storage::http::buckets::Lifecycle;
-use google_cloud_storage::http::buckets::{Bucket as GcpBucket, Versioning};
+use google_cloud_storage::http::buckets::{Bucket as GcpBucket, IamConfiguration, Versioning};
 use google_cloud_storage::http::objects::delete::DeleteObjectRequest;
 use google_cloud_storage::http::objects::download::Range;
 use google_cloud_storage::http::objects::get::GetObjectRequest;
 use google_cloud_storage::http::objects::list::ListObjectsRequest;
 use google_cloud_storage::http::objects::upload::{UploadObjectRequest, UploadType};
 use google_cloud_storage::http::objects::Object as GcpObject;
+use google_cloud_storage::http::resumable_upload_client::{ChunkSize, ResumableUploadClient};
 use governor::middleware::NoOpMiddleware;
 use governor::state::{InMemoryState, NotKeyed};
 use governor::{clock, RateLimiter};
 
 use chrono::{DateTime, Utc};
 use reqwest::Body;
+use serde::Deserialize;
 use std::cmp::max;
 use std::collections::HashMap;
+use std::io::Read;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
@@ -84,6 +92,16 @@ pub enum ObjectStorageServiceError {
         bucket_name: String,
         raw_error_message: String,
     },
+    #[error("Cannot create credentials of bucket `{bucket_name}`: {raw_error_message:?}")]
+    CannotCreateBucketCredentials {
+        bucket_name: String,
+        raw_error_message: String,
+    },
+    #[error("Cannot delete credentials of bucket `{bucket_name}`: {raw_error_message:?}")]
+    CannotDeleteBucketCredentials {
+        bucket_name: String,
+        raw_error_message: String,
+    },
     #[error("Cannot proceed, admission control blocked after several tries")]
     AdmissionControlCannotProceedAfterSeveralTries,
 }
@@ -101,6 +119,8 @@ impl ObjectStorageServiceError {
             ObjectStorageServiceError::CannotListObjects { raw_error_message, .. } => raw_error_message,
             ObjectStorageServiceError::CannotPutObjectToBucket { raw_error_message, .. } => raw_error_message,
             ObjectStorageServiceError::CannotGetObject { raw_error_message, .. } => raw_error_message,
+            ObjectStorageServiceError::CannotCreateBucketCredentials { raw_error_message, .. } => raw_error_message,
+            ObjectStorageServiceError::CannotDeleteBucketCredentials { raw_error_message, .. } => raw_error_message,
             ObjectStorageServiceError::AdmissionControlCannotProceedAfterSeveralTries => "".to_string(),
         }
     }
@@ -111,6 +131,95 @@ enum StorageResourceKind {
     Object,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HmacKeyMetadata {
+    access_id: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct HmacKey {
+    metadata: HmacKeyMetadata,
+    secret: String,
+}
+
+/// Resumable upload, its chunks being the parts of the multipart upload
+struct GcsResumableUpload<'a> {
+    client: &'a Client,
+    bucket_name: &'a str,
+    object_key: &'a str,
+    session: Mutex<Option<ResumableUploadClient>>,
+}
+
+impl MultipartUploadClient for GcsResumableUpload<'_> {
+    fn create(&self) -> Result<String, String> {
+        let session = block_on(self.client.prepare_resumable_upload(
+            &UploadObjectRequest {
+                bucket: self.bucket_name.to_string(),
+                ..Default::default()
+            },
+            &UploadType::Multipart(Box::new(GcpObject {
+                name: self.object_key.to_string(),
+                ..Default::default()
+            })),
+        ))
+        .map_err(|e| e.to_string())?;
+
+        let session_url = session.url().to_string();
+        *self.session.lock().unwrap_or_else(|e| e.into_inner()) = Some(session);
+        Ok(session_url)
+    }
+
+    fn upload_part(&self, _upload_id: &str, part: &Part) -> Result<String, String> {
+        let session = self
+            .session
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .ok_or_else(|| "resumable upload is not started".to_string())?;
+
+        // chunks have no etag, the object is created by the last one
+        if part.data.is_empty() {
+            return block_on(session.upload_single_chunk(Vec::<u8>::new(), 0))
+                .map(|_| String::new())
+                .map_err(|e| e.to_string());
+        }
+        let last_byte = part.offset + part.data.len() as u64 - 1;
+        let total_object_size = part.is_last.then_some(last_byte + 1);
+        block_on(
+            session
+                .upload_multiple_chunk(part.data.clone(), &ChunkSize::new(part.offset, last_byte, total_object_size)),
+        )
+        .map(|_| String::new())
+        .map_err(|e| e.to_string())
+    }
+
+    fn complete(&self, _upload_id: &str, _parts: &[UploadedPart]) -> Result<(), String> {
+        // already completed by the last chunk
+        Ok(())
+    }
+
+    fn abort(&self, _upload_id: &str) -> Result<(), String> {
+        match self.session.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            Some(session) => block_on(session.cancel()).map_err(|e| e.to_string()),
+            None => Ok(()),
+        }
+    }
+}
+
+// TODO(ENG-1809): to be done using rust SDK for GCP once IAM and HMAC keys are supported
+/// Runs a gcloud command and returns its output, which is not logged as it can contain secrets
+fn gcloud(args: &[&str]) -> Result<String, String> {
+    let mut stdout = vec![];
+    let mut stderr = vec![];
+    QoveryCommand::new("gcloud", args, &[])
+        .exec_with_output(&mut |line| stdout.push(line), &mut |line| stderr.push(line))
+        .map_err(|e| format!("{}: {}", e.message_safe(), stderr.join("\n")))?;
+
+    Ok(stdout.join("\n"))
+}
+
 #[cfg_attr(test, faux::create)]
 pub struct ObjectStorageService {
     client: Client,
@@ -312,6 +421,165 @@ impl ObjectStorageService {
         }
     }
 
+    pub fn apply_bucket_settings(
+        &self,
+        bucket_name: &str,
+        versioning_activated: bool,
+        public_access_prevention: bool,
+        objects_expiration_in_days: Option<u32>,
+    ) -> Result<(), ObjectStorageServiceError> {
+        let patch_bucket_request = PatchBucketRequest {
+            bucket: bucket_name.to_string(),
+            metadata: Some(BucketPatchConfig {
+                versioning: Some(Versioning {
+                    enabled: versioning_activated,
+                }),
+                iam_configuration: Some(IamConfiguration {
+                    uniform_bucket_level_access: None,
+                    public_access_prevention: Some(match public_access_prevention {
+                        true => PublicAccessPrevention::Enforced,
+                        false => PublicAccessPrevention::Inherited,
+                    }),
+                }),
+                // an empty lifecycle removes the previous rules
+                lifecycle: Some(Lifecycle {
+                    rule: objects_expiration_in_days
+                        .map(|days| Rule {
+                            action: Some(Action {
+                                r#type: ActionType::Delete,
+                                storage_class: None,
+                            }),
+                            condition: Some(Condition {
+                                age: i32::try_from(days).unwrap_or(i32::MAX),
+                                ..Default::default()
+                            }),
+                        })
+                        .into_iter()
+                        .collect(),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        self.wait_for_a_slot_in_admission_control(Duration::from_secs(10 * 60), StorageResourceKind::Bucket)?;
+        block_on(self.client.patch_bucket(&patch_bucket_request))
+            .map(|_| ())
+            .map_err(|e| ObjectStorageServiceError::CannotUpdateBucket {
+                bucket_name: bucket_name.to_string(),
+                raw_error_message: e.to_string(),
+            })
+    }
+
+    /// Creates a service account only allowed to manage the objects of the bucket, and returns a new HMAC key
+    /// (access id and secret) of it. Previous HMAC keys of the service account are deleted.
+    pub fn create_bucket_hmac_key(
+        &self,
+        bucket_name: &str,
+        service_account_name: &str,
+    ) -> Result<(String, String), ObjectStorageServiceError> {
+        let to_error = |e: String| ObjectStorageServiceError::CannotCreateBucketCredentials {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message: e,
+        };
+        let project = format!("--project={}", self.project_id);
+        let service_account_email = self.bucket_service_account_email(service_account_name);
+
+        if gcloud(&["iam", "service-accounts", "describe", &service_account_email, &project]).is_err() {
+            gcloud(&[
+                "iam",
+                "service-accounts",
+                "create",
+                service_account_name,
+                &format!("--display-name=Qovery bucket {bucket_name}"),
+                &project,
+            ])
+            .map_err(to_error)?;
+        }
+        gcloud(&[
+            "storage",
+            "buckets",
+            "add-iam-policy-binding",
+            &format!("gs://{bucket_name}"),
+            &format!("--member=serviceAccount:{service_account_email}"),
+            "--role=roles/storage.objectAdmin",
+            &project,
+        ])
+        .map_err(to_error)?;
+
+        self.delete_hmac_keys(&service_account_email).map_err(to_error)?;
+        let key: HmacKey = serde_json::from_str(
+            &gcloud(&[
+                "storage",
+                "hmac",
+                "create",
+                &service_account_email,
+                &project,
+                "--format=json",
+            ])
+            .map_err(to_error)?,
+        )
+        .map_err(|e| to_error(e.to_string()))?;
+
+        Ok((key.metadata.access_id, key.secret))
+    }
+
+    /// Deletes the service account created for the bucket and its HMAC keys, if they exist
+    pub fn delete_bucket_hmac_key(
+        &self,
+        bucket_name: &str,
+        service_account_name: &str,
+    ) -> Result<(), ObjectStorageServiceError> {
+        let to_error = |e: String| ObjectStorageServiceError::CannotDeleteBucketCredentials {
+            bucket_name: bucket_name.to_string(),
+            raw_error_message: e,
+        };
+        let project = format!("--project={}", self.project_id);
+        let service_account_email = self.bucket_service_account_email(service_account_name);
+
+        if gcloud(&["iam", "service-accounts", "describe", &service_account_email, &project]).is_err() {
+            return Ok(());
+        }
+        self.delete_hmac_keys(&service_account_email).map_err(to_error)?;
+        gcloud(&[
+            "iam",
+            "service-accounts",
+            "delete",
+            &service_account_email,
+            &project,
+            "--quiet",
+        ])
+        .map(|_| ())
+        .map_err(to_error)
+    }
+
+    fn bucket_service_account_email(&self, service_account_name: &str) -> String {
+        format!("{service_account_name}@{}.iam.gserviceaccount.com", self.project_id)
+    }
+
+    fn delete_hmac_keys(&self, service_account_email: &str) -> Result<(), String> {
+        let project = format!("--project={}", self.project_id);
+        let keys: Vec<HmacKeyMetadata> = serde_json::from_str(&gcloud(&[
+            "storage",
+            "hmac",
+            "list",
+            &format!("--service-account={service_account_email}"),
+            &project,
+            "--format=json",
+        ])?)
+        .map_err(|e| e.to_string())?;
+
+        // only inactive keys can be deleted
+        for key in keys.iter().filter(|key| key.state != "DELETED") {
+            if key.state == "ACTIVE" {
+                gcloud(&["storage", "hmac", "update", &key.access_id, "--deactivate", &project])?;
+            }
+            gcloud(&["storage", "hmac", "delete", &key.access_id, &project])?;
+        }
+
+        Ok(())
+    }
+
     pub fn delete_bucket(
         &self,
         bucket_name: &str,
@@ -478,6 +746,31 @@ impl ObjectStorageService {
         }
     }
 
+    /// Streams the content of the reader into a resumable upload, without loading it in memory
+    pub fn put_object_streaming(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        reader: &mut dyn Read,
+        size_hint: Option<u64>,
+    ) -> Result<(), ObjectStorageServiceError> {
+        self.wait_for_a_slot_in_admission_control(Duration::from_secs(10 * 60), StorageResourceKind::Object)?;
+        let upload = GcsResumableUpload {
+            client: &self.client,
+            bucket_name,
+            object_key,
+            session: Mutex::new(None),
+        };
+
+        multipart_upload(&upload, reader, size_hint).map(|_| ()).map_err(|e| {
+            ObjectStorageServiceError::CannotPutObjectToBucket {
+                bucket_name: bucket_name.to_string(),
+                object_key: object_key.to_string(),
+                raw_error_message: e.to_string(),
+            }
+        })
+    }
+
     pub fn get_object(&self, bucket_name: &str, object_key: &str) -> Result<BucketObject, ObjectStorageServiceError> {
         let object_request = GetObjectRequest {
             bucket: bucket_name.to_string(),
@@ -1,3 +1,6 @@
 pub mod aws;
+pub mod azure;
 pub mod gcp;
 pub mod kube_client;
+#[cfg(test)]
+pub mod test_utils;
@@ -0,0 +1,75 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use url::Url;
+
+pub type Handler = Box<dyn Fn(&str, &str) -> (u16, String) + Send>;
+
+/// Minimal HTTP server answering each request through the given handler, called with the method and the
+/// `path?query` target of the request, and recording the `(method, target, body)` calls.
+pub struct MockServer {
+    pub url: Url,
+    calls: Arc<Mutex<Vec<(String, String, String)>>>,
+}
+
+impl MockServer {
+    pub fn start(handler: Handler) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("cannot bind mock server");
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let calls = Arc::new(Mutex::new(vec![]));
+        let recorded_calls = calls.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(s) => s,
+                    Err(_) => break,
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                if reader.read_line(&mut request_line).is_err() {
+                    continue;
+                }
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    if reader.read_line(&mut header).is_err() || header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap_or(0);
+                        }
+                    }
+                }
+                let mut request_body = vec![0; content_length];
+                let _ = reader.read_exact(&mut request_body);
+
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let target = parts.next().unwrap_or_default().to_string();
+                let (status, response_body) = handler(&method, &target);
+                recorded_calls.lock().unwrap().push((
+                    method,
+                    target,
+                    String::from_utf8_lossy(&request_body).to_string(),
+                ));
+
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} MOCK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    response_body.len(),
+                    response_body
+                );
+            }
+        });
+
+        MockServer { url, calls }
+    }
+
+    pub fn calls(&self) -> Vec<(String, String, String)> {
+        self.calls.lock().unwrap().clone()
+    }
+}
@@ -7,7 +7,11 @@ use std::convert::TryFrom;
 use std::hash::{Hash, Hasher};
 use std::path::Path;
 
+use crate::environment::models::cron_schedule::CronSchedule;
+use crate::proxy;
+use crate::proxy::mask_credentials;
 use reqwest::header::{HeaderMap, HeaderValue};
+use url::Url;
 use uuid::Uuid;
 
 // generate the right header for digital ocean with token
@@ -58,6 +62,25 @@ pub fn to_short_id(id: &Uuid) -> String {
     format!("z{}", id.to_string().split_at(8).0)
 }
 
+// The api server is reached through the proxy of the engine, unless its no_proxy rules exclude it
+fn with_installed_proxy(mut kube_config: kube::Config) -> kube::Config {
+    let Some(proxy) = proxy::installed() else {
+        return kube_config;
+    };
+
+    let proxy_url = Url::parse(&kube_config.cluster_url.to_string())
+        .ok()
+        .and_then(|cluster_url| proxy.proxy_for(&cluster_url).cloned());
+    if let Some(proxy_url) = proxy_url {
+        match proxy_url.as_str().parse() {
+            Ok(proxy_url) => kube_config.proxy_url = Some(proxy_url),
+            Err(err) => warn!("Cannot use proxy {} for the kube client: {}", mask_credentials(&proxy_url), err),
+        }
+    }
+
+    kube_config
+}
+
 pub async fn create_kube_client<P: AsRef<Path>>(
     kubeconfig_path: P,
     envs: &[(String, String)],
@@ -86,6 +109,7 @@ pub async fn create_kube_client<P: AsRef<Path>>(
     let kube_config = kube::Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default())
         .await
         .map_err(to_err)?;
+    let kube_config = with_installed_proxy(kube_config);
     let kube_client = kube::Client::try_from(kube_config)?;
 
     // Try to contact the api to verify we are correctly connected
@@ -99,7 +123,7 @@ pub async fn create_kube_client_in_cluster() -> Result<kube::Client, kube::Error
     };
 
     // build kube client: the kube config must have already the good context selected
-    let kube_config = kube::Config::incluster().map_err(to_err)?;
+    let kube_config = with_installed_proxy(kube::Config::incluster().map_err(to_err)?);
     let kube_client = kube::Client::try_from(kube_config)?;
 
     // Try to contact the api to verify we are correctly connected
@@ -125,9 +149,30 @@ pub fn envs_to_string(env_var: Vec<(&str, &str)>) -> Vec<(String, String)> {
         .collect()
 }
 
+/// Standard 5 fields cron expression or descriptor (e.g. `@hourly`), as parsed by Kubernetes CronJobs and Karpenter.
+/// Timezones are set apart, never with a `CRON_TZ=` or `TZ=` prefix.
+pub fn validate_cron_schedule(schedule: &str) -> Result<(), String> {
+    // the seconds field accepted by `CronSchedule` is not rendered as is, Karpenter would reject it
+    if schedule.split_whitespace().count() == 6 {
+        return Err("expected 5 fields: minute, hour, day of month, month and day of week".to_string());
+    }
+
+    CronSchedule::new(schedule).map(|_| ()).map_err(|err| err.to_string())
+}
+
+/// Timezone name from the IANA database, e.g. `Europe/Paris`
+pub fn validate_timezone(timezone: &str) -> Result<(), String> {
+    match timezone.parse::<chrono_tz::Tz>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err("expected a timezone name from the IANA database (e.g. `Europe/Paris`)".to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests_utilities {
-    use crate::utilities::{base64_replace_comma_to_new_line, compute_image_tag};
+    use crate::utilities::{
+        base64_replace_comma_to_new_line, compute_image_tag, validate_cron_schedule, validate_timezone,
+    };
     use base64::engine::general_purpose;
     use base64::Engine;
     use std::collections::BTreeMap;
@@ -212,4 +257,45 @@ mod tests_utilities {
         let decoded_res_string = decoded_res.iter().map(|c| *c as char).collect::<String>();
         assert_eq!(decoded_res_string, "dennis:ritchie\nlinus:torvalds".to_string());
     }
+
+    #[test]
+    fn test_validate_cron_schedule() {
+        for schedule in [
+            "@hourly",
+            "@yearly",
+            "0 * * * *",
+            "*/5 9-17 * * MON-FRI",
+            "0 0 31 2 *",
+            "0 0 ? * SUN",
+        ] {
+            assert!(validate_cron_schedule(schedule).is_ok(), "`{schedule}` should be valid");
+        }
+
+        for schedule in [
+            "@every 1h",
+            "@Hourly",
+            "TZ=Europe/Paris 0 9 * * *",
+            "0 9 * *",
+            "0 24 * * *",
+            "0 0 1 0 *",
+            "*/0 * * * *",
+        ] {
+            assert!(validate_cron_schedule(schedule).is_err(), "`{schedule}` should be invalid");
+        }
+
+        assert_eq!(
+            validate_cron_schedule("61 * * * *"),
+            Err("invalid cron expression `61 * * * *` at position 1: minute `61`: 61 is out of range 0-59".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_timezone() {
+        for timezone in ["UTC", "Etc/UTC", "Europe/Paris", "America/Argentina/Buenos_Aires"] {
+            assert!(validate_timezone(timezone).is_ok(), "`{timezone}` should be valid");
+        }
+        for timezone in ["", "Europe/Lille", "CEST", "+02:00", "europe/paris "] {
+            assert!(validate_timezone(timezone).is_err(), "`{timezone}` should be invalid");
+        }
+    }
 }
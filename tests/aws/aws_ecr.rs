@@ -31,6 +31,7 @@ fn create_ecr_repository_with_tags() {
             registry_name.as_str(),
             &secrets.AWS_ACCESS_KEY_ID.expect("Unable to get access key"),
             &secrets.AWS_SECRET_ACCESS_KEY.expect("Unable to get secret key"),
+            None,
             &secrets.AWS_DEFAULT_REGION.expect("Unable to get default region"),
             logger(),
             hashmap! {"ttl".to_string() => AWS_QUICK_RESOURCE_TTL_IN_SECONDS.to_string()},
@@ -28,7 +28,7 @@ use qovery_engine::io_models::helm_chart::{HelmChart, HelmChartSource, HelmRawVa
 use qovery_engine::io_models::job::{ContainerRegistries, Job, JobSchedule, JobSource, LifecycleType};
 use qovery_engine::io_models::labels_group::{Label, LabelsGroup};
 use qovery_engine::io_models::probe::{Probe, ProbeType};
-use qovery_engine::io_models::router::{CustomDomain, Route, Router};
+use qovery_engine::io_models::router::{CustomDomain, Route, RouteProtocol, Router};
 use qovery_engine::io_models::variable_utils::VariableInfo;
 use qovery_engine::io_models::{Action, MountedFile, QoveryIdentifier};
 use qovery_engine::metrics_registry::{StepLabel, StepName, StepStatus};
@@ -665,6 +665,7 @@ fn deploy_a_working_environment_with_custom_domain_and_disable_check_on_custom_d
                 is_default: false,
                 name: "grpc".to_string(),
                 publicly_accessible: true,
+                expose: None,
                 protocol: Protocol::GRPC,
                 service_name: None,
                 namespace: None,
@@ -1676,6 +1677,7 @@ fn deploy_container_with_no_router_and_affinitiy_on_aws_eks() {
                     is_default: true,
                     name: format!("p8080-{}", host_suffix),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
@@ -1687,6 +1689,7 @@ fn deploy_container_with_no_router_and_affinitiy_on_aws_eks() {
                     is_default: false,
                     name: format!("grpc-{}", host_suffix),
                     publicly_accessible: false,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
@@ -1696,6 +1699,7 @@ fn deploy_container_with_no_router_and_affinitiy_on_aws_eks() {
             storages: vec![],
             environment_vars_with_infos: btreemap! { "MY_VAR".to_string() => VariableInfo { value: general_purpose::STANDARD.encode("my_value"), is_secret: false}},
             mounted_files: vec![],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -1717,6 +1721,7 @@ fn deploy_container_with_no_router_and_affinitiy_on_aws_eks() {
             advanced_settings: Default::default(),
             annotations_group_ids: btreeset! {},
             labels_group_ids: btreeset! {},
+            promote_image: None,
         }];
 
         let ret = environment.deploy_environment(&environment, &infra_ctx);
@@ -1904,6 +1909,7 @@ fn deploy_container_with_no_router_on_aws_eks() {
                     is_default: true,
                     name: "p8080".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
@@ -1915,6 +1921,7 @@ fn deploy_container_with_no_router_on_aws_eks() {
                     is_default: false,
                     name: "grpc".to_string(),
                     publicly_accessible: false,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
@@ -1924,6 +1931,7 @@ fn deploy_container_with_no_router_on_aws_eks() {
             storages: vec![],
             environment_vars_with_infos: btreemap! { "MY_VAR".to_string() => VariableInfo { value: general_purpose::STANDARD.encode("my_value"), is_secret: false} },
             mounted_files: vec![],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -1945,6 +1953,7 @@ fn deploy_container_with_no_router_on_aws_eks() {
             advanced_settings: Default::default(),
             annotations_group_ids: btreeset! {},
             labels_group_ids: btreeset! {},
+            promote_image: None,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -2043,11 +2052,13 @@ fn deploy_container_with_storages_on_aws_eks() {
                 is_default: true,
                 name: "http".to_string(),
                 publicly_accessible: false,
+                expose: None,
                 protocol: HTTP,
                 service_name: None,
                 namespace: None,
                 additional_service: None,
             }],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -2091,6 +2102,7 @@ fn deploy_container_with_storages_on_aws_eks() {
             advanced_settings: Default::default(),
             annotations_group_ids: btreeset! {},
             labels_group_ids: btreeset! {},
+            promote_image: None,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -2193,6 +2205,7 @@ fn deploy_container_on_aws_eks_with_mounted_files_as_volume() {
                     is_default: true,
                     name: "http".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
@@ -2204,12 +2217,14 @@ fn deploy_container_on_aws_eks_with_mounted_files_as_volume() {
                     is_default: false,
                     name: "grpc".to_string(),
                     publicly_accessible: false,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
                     additional_service: None,
                 },
             ],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -2234,6 +2249,7 @@ fn deploy_container_on_aws_eks_with_mounted_files_as_volume() {
             advanced_settings: Default::default(),
             annotations_group_ids: btreeset! {},
             labels_group_ids: btreeset! {},
+            promote_image: None,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -2359,6 +2375,7 @@ fn deploy_container_with_router_on_aws_eks() {
                     is_default: true,
                     name: format!("http-{}", suffix),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
@@ -2370,12 +2387,14 @@ fn deploy_container_with_router_on_aws_eks() {
                     is_default: false,
                     name: format!("grpc-{}", suffix),
                     publicly_accessible: false,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
                     additional_service: None,
                 },
             ],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Http {
                     path: "/".to_string(),
@@ -2403,6 +2422,7 @@ fn deploy_container_with_router_on_aws_eks() {
             advanced_settings: Default::default(),
             annotations_group_ids: btreeset! { annotations_group_id },
             labels_group_ids: btreeset! { labels_group_id },
+            promote_image: None,
         }];
         environment.annotations_groups = btreemap! { annotations_group_id => AnnotationsGroup {
             annotations: vec![Annotation {
@@ -2441,7 +2461,11 @@ fn deploy_container_with_router_on_aws_eks() {
             routes: vec![Route {
                 path: "/".to_string(),
                 service_long_id: environment.containers[0].long_id,
+                protocol: RouteProtocol::Http1,
             }],
+            basic_auth_credentials: vec![],
+            extra_ingress_annotations: BTreeMap::new(),
+            allow_unsafe_ingress_annotations: false,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -2533,6 +2557,7 @@ fn deploy_job_on_aws_eks() {
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -2655,6 +2680,7 @@ CMD ["/bin/sh", "-c", "echo hello"]
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -2739,7 +2765,7 @@ fn deploy_cronjob_on_aws_eks() {
             kube_name: "job-test".to_string(),
             action: Action::Create,
             schedule: JobSchedule::Cron {
-                schedule: "* * * * *".to_string(),
+                schedule: "* * * * *".parse().unwrap(),
                 timezone: "Etc/UTC".to_string(),
             },
             source: JobSource::Image {
@@ -2767,6 +2793,7 @@ fn deploy_cronjob_on_aws_eks() {
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -2869,7 +2896,7 @@ fn deploy_cronjob_force_trigger_on_aws_eks() {
             kube_name: "job-test".to_string(),
             action: Action::Create,
             schedule: JobSchedule::Cron {
-                schedule: "*/10 * * * *".to_string(),
+                schedule: "*/10 * * * *".parse().unwrap(),
                 timezone: "Etc/UTC".to_string(),
             },
             source: JobSource::Image {
@@ -2897,6 +2924,7 @@ fn deploy_cronjob_force_trigger_on_aws_eks() {
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -3038,6 +3066,7 @@ fn build_and_deploy_job_on_aws_eks() {
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -3175,6 +3204,7 @@ fn test_restart_deployment() {
                     is_default: true,
                     name: "http".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
@@ -3186,12 +3216,14 @@ fn test_restart_deployment() {
                     is_default: false,
                     name: "grpc".to_string(),
                     publicly_accessible: false,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
                     additional_service: None,
                 },
             ],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -3216,6 +3248,7 @@ fn test_restart_deployment() {
             advanced_settings: Default::default(),
             annotations_group_ids: btreeset! {},
             labels_group_ids: btreeset! {},
+            promote_image: None,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -3323,6 +3356,7 @@ fn test_restart_statefulset() {
                     is_default: true,
                     name: "http".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
@@ -3334,12 +3368,14 @@ fn test_restart_statefulset() {
                     is_default: false,
                     name: "grpc".to_string(),
                     publicly_accessible: false,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
                     additional_service: None,
                 },
             ],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -3362,6 +3398,7 @@ fn test_restart_statefulset() {
             advanced_settings: Default::default(),
             annotations_group_ids: btreeset! {},
             labels_group_ids: btreeset! {},
+            promote_image: None,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -3467,6 +3504,7 @@ fn build_and_deploy_job_on_aws_eks_with_mounted_files_as_volume() {
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![mounted_file.clone()],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -3779,6 +3817,7 @@ fn deploy_container_with_udp_tcp_public_ports() {
                     is_default: true,
                     name: format!("p{}", tcp_port),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::TCP,
                     service_name: None,
                     namespace: None,
@@ -3790,6 +3829,7 @@ fn deploy_container_with_udp_tcp_public_ports() {
                     is_default: false,
                     name: "p5432".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::TCP,
                     service_name: None,
                     namespace: None,
@@ -3801,6 +3841,7 @@ fn deploy_container_with_udp_tcp_public_ports() {
                     is_default: false,
                     name: format!("p{}", udp_port),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::UDP,
                     service_name: None,
                     namespace: None,
@@ -3810,6 +3851,7 @@ fn deploy_container_with_udp_tcp_public_ports() {
             storages: vec![],
             environment_vars_with_infos: btreemap! { "MY_VAR".to_string() => VariableInfo{value: general_purpose::STANDARD.encode("my_value"), is_secret: false} },
             mounted_files: vec![],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: tcp_port as u32,
@@ -3831,6 +3873,7 @@ fn deploy_container_with_udp_tcp_public_ports() {
             advanced_settings: Default::default(),
             annotations_group_ids: btreeset! {},
             labels_group_ids: btreeset! {},
+            promote_image: None,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -4436,6 +4479,7 @@ fn deploy_helm_chart_with_router() {
                     is_default: false,
                     name: format!("service1-p8080-{}", host_suffix),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     namespace: None,
                     service_name: Some("inner-namespace-service1".to_string()),
@@ -4447,6 +4491,7 @@ fn deploy_helm_chart_with_router() {
                     is_default: false,
                     name: format!("service2-p8080-{}", host_suffix),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     namespace: Some(extra_namespace.clone()),
                     service_name: Some("outside-namespace-service2".to_string()),
@@ -4465,7 +4510,11 @@ fn deploy_helm_chart_with_router() {
             routes: vec![Route {
                 path: "/".to_string(),
                 service_long_id: environment.helms[0].long_id,
+                protocol: RouteProtocol::Http1,
             }],
+            basic_auth_credentials: vec![],
+            extra_ingress_annotations: BTreeMap::new(),
+            allow_unsafe_ingress_annotations: false,
         }];
 
         let mut environment_for_delete = environment.clone();
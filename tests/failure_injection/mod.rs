@@ -0,0 +1,20 @@
+// Error paths scenarios, provoked by replacing the commands run by the engine with scripted failures.
+// Run with `cargo test --features failure-injection failure_injection`, no cloud account nor cluster needed.
+mod scenarios;
+
+use qovery_engine::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
+use qovery_engine::infrastructure::models::cloud_provider::Kind;
+use qovery_engine::io_models::QoveryIdentifier;
+use uuid::Uuid;
+
+pub fn event_details(provider_kind: Option<Kind>) -> EventDetails {
+    let cluster_id = Uuid::new_v4();
+    EventDetails::new(
+        provider_kind,
+        QoveryIdentifier::new(Uuid::new_v4()),
+        QoveryIdentifier::new(cluster_id),
+        Uuid::new_v4().to_string(),
+        Stage::Infrastructure(InfrastructureStep::Create),
+        Transmitter::Kubernetes(cluster_id, "failure-injection".to_string()),
+    )
+}
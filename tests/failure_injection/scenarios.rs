@@ -0,0 +1,248 @@
+use crate::failure_injection::event_details;
+use qovery_engine::cmd::command::CommandKiller;
+use qovery_engine::cmd::docker::{ContainerImage, Docker};
+use qovery_engine::cmd::failure_injection::{FailureInjection, ScriptedCommand, ScriptedError};
+use qovery_engine::cmd::helm::Helm;
+use qovery_engine::cmd::kubectl::kubectl_exec_get_number_of_restart;
+use qovery_engine::cmd::terraform::terraform_apply;
+use qovery_engine::cmd::terraform_validators::TerraformValidators;
+use qovery_engine::engine_task::qovery_api::FakeQoveryApi;
+use qovery_engine::errors::{EngineError, Tag};
+use qovery_engine::events::{EnvironmentStep, Stage};
+use qovery_engine::helm::ChartInfo;
+use qovery_engine::infrastructure::models::cloud_provider::Kind;
+use qovery_engine::infrastructure::models::container_registry::errors::ContainerRegistryError;
+use qovery_engine::infrastructure::models::container_registry::failure_injection::FailureInjectionContainerRegistry;
+use qovery_engine::infrastructure::models::container_registry::generic_cr::GenericCr;
+use qovery_engine::infrastructure::models::container_registry::{ContainerRegistry, RegistryTags};
+use qovery_engine::io_models::context::Context;
+use qovery_engine::io_models::feature_flags::FeatureFlags;
+use std::sync::Arc;
+use url::Url;
+use uuid::Uuid;
+
+#[test]
+fn terraform_quota_exceeded_during_node_group_update() {
+    let scenario = FailureInjection::start();
+    scenario.inject("terraform plan", ScriptedCommand::success()).inject(
+        "terraform apply",
+        ScriptedCommand::exit_code(1)
+            .with_stdout("aws_eks_node_group.eks_cluster_workers[0]: Modifying...")
+            .with_stderr(
+                "Error: updating EKS Node Group (qovery-z1234:qovery-z1234-default): \
+                     You've reached your quota for maximum Fleet Requests for this account",
+            ),
+    );
+    let root_dir = tempfile::tempdir().unwrap();
+
+    let err = terraform_apply(
+        root_dir.path().to_str().unwrap(),
+        false,
+        &[],
+        &TerraformValidators::Default,
+        &CommandKiller::never(),
+    )
+    .expect_err("terraform apply must fail");
+    let engine_error = EngineError::new_terraform_error(event_details(Some(Kind::Aws)), err);
+
+    assert_eq!(engine_error.tag(), &Tag::TerraformCloudProviderQuotasReached);
+    assert_eq!(
+        engine_error.hint_message().as_deref(),
+        Some("Request AWS to increase your `Fleet Requests` limit (current count = NA, max count = NA) via this page https://aws.amazon.com/contact-us/ec2-request.")
+    );
+    // the apply is retried once, after a new plan
+    assert_eq!(
+        scenario
+            .intercepted_commands()
+            .iter()
+            .map(|cmd| cmd.split_whitespace().take(2).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>(),
+        vec!["terraform apply", "terraform plan", "terraform apply", "terraform plan"]
+    );
+}
+
+#[test]
+fn registry_forbidden_during_mirroring() {
+    let scenario = FailureInjection::start();
+    scenario
+        .inject("docker buildx version", ScriptedCommand::success())
+        .inject(
+            "docker buildx imagetools create",
+            ScriptedCommand::exit_code(1).with_stderr(
+                "ERROR: failed to push registry.example.com/z1234/app:1.0: unexpected status from HEAD request: 403 Forbidden",
+            ),
+        );
+    let docker = Docker::new(None).unwrap();
+    let registry = Url::parse("https://registry.example.com").unwrap();
+    let source = ContainerImage::new(registry.clone(), "library/app".to_string(), vec!["1.0".to_string()]);
+    let destination = ContainerImage::new(registry, "z1234/app".to_string(), vec!["1.0".to_string()]);
+
+    let mut stderr = vec![];
+    let err = docker
+        .mirror(
+            &source,
+            &destination,
+            &mut |_| {},
+            &mut |line| stderr.push(line),
+            &CommandKiller::never(),
+        )
+        .expect_err("mirroring must fail");
+    let engine_error = EngineError::new_docker_error(event_details(None), err);
+
+    assert_eq!(engine_error.tag(), &Tag::DockerError);
+    assert!(engine_error.hint_message().is_none());
+    assert!(stderr.iter().any(|line| line.contains("403 Forbidden")));
+}
+
+#[test]
+fn registry_forbidden_while_creating_mirror_repository() {
+    let scenario = FailureInjection::start();
+    scenario.inject_error(
+        "container_registry create_repository",
+        ScriptedError::new(ContainerRegistryError::CannotCreateRepository {
+            registry_name: "registry.example.com".to_string(),
+            repository_name: "z1234-mirror".to_string(),
+            raw_error_message: "unexpected status from PUT request: 403 Forbidden".to_string(),
+        })
+        .times(1),
+    );
+    let context = Context::new(
+        Uuid::new_v4(),
+        Uuid::new_v4(),
+        Uuid::new_v4().to_string(),
+        "/tmp".to_string(),
+        "/tmp".to_string(),
+        true,
+        vec![],
+        FeatureFlags::default(),
+        None,
+        Arc::new(Docker::new(None).unwrap()),
+        Arc::new(FakeQoveryApi {}),
+        event_details(None),
+    );
+    let registry = GenericCr::new(
+        context,
+        Uuid::new_v4(),
+        "registry",
+        Url::parse("https://registry.example.com").unwrap(),
+        false,
+        "z1234".to_string(),
+        None,
+        false,
+    )
+    .unwrap();
+    let registry = FailureInjectionContainerRegistry::new(Box::new(registry));
+    let registry_tags = || RegistryTags {
+        environment_id: Uuid::new_v4().to_string(),
+        project_id: Uuid::new_v4().to_string(),
+        resource_ttl: None,
+    };
+
+    // the mirror repository is created before copying the image into it
+    let err = registry
+        .create_repository("z1234-mirror", 3600, registry_tags())
+        .expect_err("repository creation must fail");
+    let engine_error = EngineError::new_container_registry_error(
+        registry.get_event_details(Stage::Environment(EnvironmentStep::Deploy)),
+        err,
+    );
+
+    assert_eq!(engine_error.tag(), &Tag::ContainerRegistryCannotCreateRepository);
+    assert!(engine_error.user_log_message().contains("z1234-mirror"));
+    assert!(engine_error.user_log_message().contains("403 Forbidden"));
+    // only the first call has been scripted, the next one reaches the registry
+    assert!(registry
+        .create_repository("z1234-mirror", 3600, registry_tags())
+        .is_ok());
+    assert_eq!(
+        scenario.intercepted_commands(),
+        vec!["container_registry create_repository z1234-mirror"]
+    );
+}
+
+#[test]
+fn mirroring_aborted_by_user() {
+    let scenario = FailureInjection::start();
+    scenario
+        .inject("docker buildx version", ScriptedCommand::success())
+        .inject("docker buildx imagetools create", ScriptedCommand::killed());
+    let docker = Docker::new(None).unwrap();
+    let registry = Url::parse("https://registry.example.com").unwrap();
+    let image = ContainerImage::new(registry, "z1234/app".to_string(), vec!["1.0".to_string()]);
+
+    let err = docker
+        .mirror(&image, &image, &mut |_| {}, &mut |_| {}, &CommandKiller::never())
+        .expect_err("mirroring must fail");
+    let engine_error = EngineError::new_docker_error(event_details(None), err);
+
+    // a cancellation must never be reported as a docker failure
+    assert_eq!(engine_error.tag(), &Tag::TaskCancellationRequested);
+}
+
+#[test]
+fn helm_upgrade_on_locked_release() {
+    let scenario = FailureInjection::start();
+    scenario
+        .inject(
+            "helm status",
+            ScriptedCommand::exit_code(1).with_stderr("Error: release: not found"),
+        )
+        .inject(
+            "helm upgrade",
+            ScriptedCommand::exit_code(1)
+                .with_stderr("Error: UPGRADE FAILED: another operation (install/upgrade/rollback) is in progress"),
+        );
+    let helm = Helm::new(None::<&str>, &[]).unwrap();
+    let chart = ChartInfo::new_from_release_name("app-z1234", "z5678-env");
+
+    let err = helm
+        .upgrade(&chart, &[], &CommandKiller::never())
+        .expect_err("upgrade must fail");
+    let engine_error = EngineError::new_helm_error(event_details(None), err);
+
+    assert_eq!(engine_error.tag(), &Tag::HelmChartsDeployError);
+    assert!(engine_error.user_log_message().contains("app-z1234"));
+}
+
+#[test]
+fn helm_upgrade_timeout() {
+    let scenario = FailureInjection::start();
+    scenario
+        .inject(
+            "helm status",
+            ScriptedCommand::exit_code(1).with_stderr("Error: release: not found"),
+        )
+        .inject("helm upgrade", ScriptedCommand::timeout());
+    let helm = Helm::new(None::<&str>, &[]).unwrap();
+    let chart = ChartInfo::new_from_release_name("app-z1234", "z5678-env");
+
+    let err = helm
+        .upgrade(&chart, &[], &CommandKiller::never())
+        .expect_err("upgrade must fail");
+
+    assert_eq!(
+        EngineError::new_helm_error(event_details(None), err).tag(),
+        &Tag::HelmDeployTimeout
+    );
+}
+
+#[test]
+fn kubectl_failure_does_not_leak_environment() {
+    let scenario = FailureInjection::start();
+    scenario.inject(
+        "kubectl get po",
+        ScriptedCommand::exit_code(1).with_stderr("error: You must be logged in to the server (Unauthorized)"),
+    );
+
+    let err = kubectl_exec_get_number_of_restart(
+        "/does/not/exist",
+        "z5678-env",
+        &Uuid::new_v4(),
+        vec![("AWS_SECRET_ACCESS_KEY", "my-secret")],
+    )
+    .expect_err("kubectl must fail");
+
+    assert_eq!(err.message_safe(), "Error while executing a kubectl command.");
+    assert!(!err.message_safe().contains("my-secret"));
+    assert_eq!(scenario.intercepted_commands().len(), 1);
+}
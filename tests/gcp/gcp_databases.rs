@@ -340,9 +340,15 @@ fn postgresql_deploy_a_working_environment_and_redeploy() {
             encrypt_disk: false,
             activate_high_availability: false,
             activate_backups: false,
+            activate_health_snapshot: false,
+            override_health_checks: false,
+            rotate_credentials: false,
             publicly_accessible: false,
             annotations_group_ids: btreeset! {},
             labels_group_ids: btreeset! {},
+            parameters: Default::default(),
+            maintenance_window: None,
+            backup_retention_days: None,
         }];
         environment.applications = environment
             .applications
@@ -356,6 +362,7 @@ fn postgresql_deploy_a_working_environment_and_redeploy() {
                     name: "p1234".to_string(),
                     is_default: true,
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
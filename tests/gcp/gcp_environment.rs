@@ -17,9 +17,10 @@ use qovery_engine::io_models::container::{Container, Registry};
 use qovery_engine::io_models::context::CloneForTest;
 use qovery_engine::io_models::labels_group::{Label, LabelsGroup};
 use qovery_engine::io_models::probe::{Probe, ProbeType};
-use qovery_engine::io_models::router::{CustomDomain, Route, Router};
+use qovery_engine::io_models::router::{CustomDomain, Route, RouteProtocol, Router};
 use qovery_engine::io_models::variable_utils::VariableInfo;
 use qovery_engine::io_models::{Action, QoveryIdentifier};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use tracing::log::warn;
 use tracing::span;
@@ -524,6 +525,7 @@ fn gcp_gke_deploy_a_working_environment_with_domain() {
                 is_default: false,
                 name: "grpc".to_string(),
                 publicly_accessible: true,
+                expose: None,
                 protocol: Protocol::GRPC,
                 service_name: None,
                 namespace: None,
@@ -624,6 +626,7 @@ fn gcp_gke_deploy_container_with_router() {
                     is_default: true,
                     name: format!("http-{}", suffix),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
@@ -635,12 +638,14 @@ fn gcp_gke_deploy_container_with_router() {
                     is_default: false,
                     name: format!("grpc-{}", suffix),
                     publicly_accessible: false,
+                    expose: None,
                     protocol: HTTP,
                     service_name: None,
                     namespace: None,
                     additional_service: None,
                 },
             ],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Http {
                     path: "/".to_string(),
@@ -668,6 +673,7 @@ fn gcp_gke_deploy_container_with_router() {
             advanced_settings: Default::default(),
             annotations_group_ids: btreeset! { annotations_group_id },
             labels_group_ids: btreeset! { labels_group_id },
+            promote_image: None,
         }];
         environment.annotations_groups = btreemap! { annotations_group_id => AnnotationsGroup {
             annotations: vec![Annotation {
@@ -706,7 +712,11 @@ fn gcp_gke_deploy_container_with_router() {
             routes: vec![Route {
                 path: "/".to_string(),
                 service_long_id: environment.containers[0].long_id,
+                protocol: RouteProtocol::Http1,
             }],
+            basic_auth_credentials: vec![],
+            extra_ingress_annotations: BTreeMap::new(),
+            allow_unsafe_ingress_annotations: false,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -6,7 +6,7 @@ use kube::core::DynamicObject;
 use qovery_engine::cmd::helm::Helm;
 use qovery_engine::environment::action::deploy_helm::HelmDeployment;
 use qovery_engine::helm::CommonChart;
-use qovery_engine::helm::{ChartInfo, HelmAction, HelmChartNamespaces};
+use qovery_engine::helm::{ChartInfo, ChartSource, HelmAction, HelmChartNamespaces};
 use std::collections::HashMap;
 use std::fs;
 use std::fs::{read_dir, File};
@@ -92,6 +92,7 @@ fn q_ingress_test() {
         chart_info: ChartInfo {
             name: chart_name.to_string(),
             path: chart_path(&test_info.temp_dir, &test_info.service_folder_type, &uuid, chart_name),
+            source: ChartSource::Local,
             namespace: HelmChartNamespaces::KubeSystem,
             custom_namespace: None,
             action: HelmAction::Deploy,
@@ -137,6 +138,7 @@ fn q_container_test() {
         chart_info: ChartInfo {
             name: chart_name.to_string(),
             path: chart_path(&test_info.temp_dir, &test_info.service_folder_type, &uuid, chart_name),
+            source: ChartSource::Local,
             namespace: HelmChartNamespaces::KubeSystem,
             custom_namespace: None,
             action: HelmAction::Deploy,
@@ -188,6 +190,7 @@ fn q_application_test() {
         chart_info: ChartInfo {
             name: chart_name.to_string(),
             path: chart_path(&test_info.temp_dir, &test_info.service_folder_type, &uuid, chart_name),
+            source: ChartSource::Local,
             namespace: HelmChartNamespaces::KubeSystem,
             custom_namespace: None,
             action: HelmAction::Deploy,
@@ -238,6 +241,7 @@ fn q_container_psql_test() {
         chart_info: ChartInfo {
             name: chart_name.to_string(),
             path: chart_path(&test_info.temp_dir, &test_info.service_folder_type, &uuid, chart_name),
+            source: ChartSource::Local,
             namespace: HelmChartNamespaces::KubeSystem,
             custom_namespace: None,
             action: HelmAction::Deploy,
@@ -282,6 +286,7 @@ fn q_managed_psql_test() {
         chart_info: ChartInfo {
             name: chart_name.to_string(),
             path: chart_path(&test_info.temp_dir, &test_info.service_folder_type, &uuid, chart_name),
+            source: ChartSource::Local,
             namespace: HelmChartNamespaces::KubeSystem,
             custom_namespace: None,
             action: HelmAction::Deploy,
@@ -326,6 +331,7 @@ fn q_job_test() {
         chart_info: ChartInfo {
             name: chart_name.to_string(),
             path: chart_path(&test_info.temp_dir, &test_info.service_folder_type, &uuid, chart_name),
+            source: ChartSource::Local,
             namespace: HelmChartNamespaces::KubeSystem,
             custom_namespace: None,
             action: HelmAction::Deploy,
@@ -10,14 +10,16 @@ use qovery_engine::environment::models::container::Container;
 use qovery_engine::environment::models::database::{Container as ContainerDB, Database, Managed, PostgresSQL};
 use qovery_engine::environment::models::environment::Environment;
 use qovery_engine::environment::models::job::{ImageSource, Job};
+use qovery_engine::environment::models::pod_security::PodSecurityProfile;
 use qovery_engine::environment::models::probe::{Probe, ProbeType};
 use qovery_engine::environment::models::registry_image_source::RegistryImageSource;
 use qovery_engine::environment::models::router::{Router, RouterAdvancedSettings};
+use qovery_engine::environment::models::service_port::ServicePort;
 use qovery_engine::environment::models::types::{VersionsNumber, AWS as AWSType};
 use qovery_engine::events::{EnvironmentStep, EventDetails, Stage};
 use qovery_engine::fs::workspace_directory;
 use qovery_engine::infrastructure::infrastructure_context::InfrastructureContext;
-use qovery_engine::infrastructure::models::build_platform::{Build, GitRepository, Image, SshKey};
+use qovery_engine::infrastructure::models::build_platform::{Build, GitAccess, GitRepository, Image, SshKey};
 use qovery_engine::infrastructure::models::cloud_provider::aws::database_instance_type::AwsDatabaseInstanceType;
 use qovery_engine::infrastructure::models::cloud_provider::aws::{
     regions::{AwsRegion, AwsZone},
@@ -29,7 +31,7 @@ use qovery_engine::infrastructure::models::cloud_provider::{CloudProvider, Deplo
 use qovery_engine::infrastructure::models::kubernetes::aws::eks::EKS;
 use qovery_engine::infrastructure::models::kubernetes::{Kind::Eks, Kubernetes, KubernetesVersion};
 use qovery_engine::io_models::annotations_group::{Annotation, AnnotationsGroup, AnnotationsGroupScope};
-use qovery_engine::io_models::application::{ApplicationAdvancedSettings, Port, Protocol};
+use qovery_engine::io_models::application::{ApplicationAdvancedSettings, PortExposure, Protocol};
 use qovery_engine::io_models::container::{ContainerAdvancedSettings, Registry};
 use qovery_engine::io_models::database::{DatabaseMode, DatabaseOptions};
 use qovery_engine::io_models::engine_location::EngineLocation;
@@ -39,6 +41,7 @@ use qovery_engine::io_models::models::{
     CpuArchitecture, CustomDomain, EnvironmentVariable, KubernetesCpuResourceUnit, KubernetesMemoryResourceUnit,
     MountedFile, Route, Storage, StorageClass,
 };
+use qovery_engine::io_models::router::RouteProtocol;
 use qovery_engine::io_models::{PodAntiAffinity, QoveryIdentifier, UpdateStrategy};
 use qovery_engine::utilities::to_short_id;
 use std::collections::{BTreeMap, HashMap};
@@ -154,16 +157,18 @@ fn test_environment(kube: &dyn Kubernetes, domain: &str) -> Environment {
         ],
         vec![Box::new(test_job(kube))],
         vec![], // TODO (helm): add helm charts test
+        vec![],
+        PodSecurityProfile::default(),
     )
 }
 
-fn test_port() -> Port {
-    Port {
+fn test_port() -> ServicePort {
+    ServicePort {
         long_id: Uuid::new_v4(),
         port: 1234,
         is_default: true,
-        name: "my_port_name".to_string(),
-        publicly_accessible: true,
+        name: "my-port-name".to_string(),
+        expose: PortExposure::Public,
         protocol: Protocol::HTTP,
         service_name: None,
         namespace: None,
@@ -218,6 +223,7 @@ fn test_route(uuid: Uuid) -> Route {
     Route {
         path: "my_route_path".to_string(),
         service_long_id: uuid,
+        protocol: RouteProtocol::Http1,
     }
 }
 
@@ -238,6 +244,7 @@ pub fn test_application(test_kube: &dyn Kubernetes, domain: &str) -> Application
             git_repository: GitRepository {
                 url: Url::parse("https://my_git_url.com").unwrap(),
                 get_credentials: None,
+                deploy_key: None,
                 ssh_keys: vec![SshKey {
                     private_key: "my_private_ssh_key".to_string(),
                     passphrase: Some("my_ssh_passphrase".to_string()),
@@ -247,6 +254,9 @@ pub fn test_application(test_kube: &dyn Kubernetes, domain: &str) -> Application
                 dockerfile_path: Some(PathBuf::from("my_dockerfile_path")),
                 dockerfile_content: None,
                 root_path: PathBuf::from("my_root_path"),
+                git_access: GitAccess::Direct,
+                source_fetcher: None,
+                clone_options: Default::default(),
             },
             image: Image {
                 service_id: "my_application_id".to_string(),
@@ -264,12 +274,14 @@ pub fn test_application(test_kube: &dyn Kubernetes, domain: &str) -> Application
                 shared_image_feature_enabled: false,
             },
             environment_variables: BTreeMap::new(),
+            secrets: BTreeMap::new(),
             disable_cache: false,
             timeout: Duration::from_secs(42),
             architectures: test_kube.cpu_architectures(),
             max_cpu_in_milli: 2000,
             max_ram_in_gib: 4,
             registries: vec![],
+            registry_mirrors: vec![],
         },
         vec![],
         None,
@@ -307,9 +319,12 @@ pub fn test_application(test_kube: &dyn Kubernetes, domain: &str) -> Application
             deployment_update_strategy_rolling_update_max_surge_percent: 25,
             deployment_lifecycle_post_start_exec_command: vec![],
             deployment_lifecycle_pre_stop_exec_command: vec![],
+            graceful_shutdown_drain_seconds: None,
+            graceful_shutdown_termination_grace_seconds: None,
             build_timeout_max_sec: 2,
             build_cpu_max_in_milli: 2000,
             build_ram_max_in_gib: 4,
+            build_registry_cache_enabled: true,
             network_ingress_proxy_body_size_mb: 3,
             network_ingress_cors_enable: true,
             network_ingress_sticky_session_enable: false,
@@ -338,6 +353,7 @@ pub fn test_application(test_kube: &dyn Kubernetes, domain: &str) -> Application
             network_ingress_nginx_limit_burst_multiplier: None,
             hpa_cpu_average_utilization_percent: 31,
             hpa_memory_average_utilization_percent: None,
+            hpa_metrics: vec![],
             deployment_affinity_node_required: BTreeMap::new(),
             deployment_antiaffinity_pod: PodAntiAffinity::Preferred,
         },
@@ -371,6 +387,7 @@ pub fn test_container(test_kube: &dyn Kubernetes) -> Container<AWSType> {
             image: "my_image".to_string(),
             tag: "my_tag".to_string(),
             registry_mirroring_mode: RegistryMirroringMode::Service,
+            promotion: None,
         },
         vec![test_cmd_arg()],
         Some("my_entrypoint".to_string()),
@@ -415,6 +432,8 @@ pub fn test_container(test_kube: &dyn Kubernetes) -> Container<AWSType> {
             deployment_antiaffinity_pod: PodAntiAffinity::Preferred,
             deployment_lifecycle_post_start_exec_command: vec![],
             deployment_lifecycle_pre_stop_exec_command: vec![],
+            graceful_shutdown_drain_seconds: None,
+            graceful_shutdown_termination_grace_seconds: None,
             network_ingress_proxy_body_size_mb: 11,
             network_ingress_cors_enable: true,
             network_ingress_sticky_session_enable: false,
@@ -443,6 +462,7 @@ pub fn test_container(test_kube: &dyn Kubernetes) -> Container<AWSType> {
             network_ingress_nginx_controller_configuration_snippet: None,
             hpa_cpu_average_utilization_percent: 41,
             hpa_memory_average_utilization_percent: None,
+            hpa_metrics: vec![],
             security_service_account_name: "".to_string(),
             security_read_only_root_filesystem: false,
             security_automount_service_account_token: false,
@@ -527,7 +547,12 @@ pub fn test_managed_database(test_kube: &dyn Kubernetes) -> Database<AWSType, Ma
             encrypt_disk: true,
             activate_high_availability: true,
             activate_backups: true,
+            activate_health_snapshot: false,
+            override_health_checks: false,
+            rotate_credentials: false,
             publicly_accessible: true,
+            parameters: Default::default(),
+            maintenance: Default::default(),
         },
         |transmitter| test_kube.context().get_event_details(transmitter),
         vec![],
@@ -567,7 +592,12 @@ pub fn test_container_database(test_kube: &dyn Kubernetes) -> Database<AWSType,
             encrypt_disk: true,
             activate_high_availability: true,
             activate_backups: true,
+            activate_health_snapshot: false,
+            override_health_checks: false,
+            rotate_credentials: false,
             publicly_accessible: true,
+            parameters: Default::default(),
+            maintenance: Default::default(),
         },
         |transmitter| test_kube.context().get_event_details(transmitter),
         vec![],
@@ -593,6 +623,8 @@ pub fn test_router(test_kube: &dyn Kubernetes, app_id: Uuid) -> Router<AWSType>
             whitelist_source_range: None,
             denylist_source_range: None,
             basic_auth: None,
+            basic_auth_env_var: None,
+            custom_error_pages: None,
         },
         |transmitter| test_kube.context().get_event_details(transmitter),
         vec![],
@@ -618,10 +650,11 @@ fn test_job(test_kube: &dyn Kubernetes) -> Job<AWSType> {
                 image: "my_image".to_string(),
                 tag: "my_tag".to_string(),
                 registry_mirroring_mode: RegistryMirroringMode::Service,
+                promotion: None,
             }),
         },
         JobSchedule::Cron {
-            schedule: "my_schedule".to_string(),
+            schedule: "0 2 * * *".parse().unwrap(),
             timezone: "Etc/UTC".to_string(),
         },
         1,
@@ -643,9 +676,12 @@ fn test_job(test_kube: &dyn Kubernetes) -> Job<AWSType> {
             cronjob_concurrency_policy: "my_cronjob_concurrency_policy".to_string(),
             cronjob_failed_jobs_history_limit: 9,
             cronjob_success_jobs_history_limit: 10,
+            job_output_max_size_in_kib: 1024,
+            job_output_value_max_size_in_bytes: None,
             build_timeout_max_sec: 30 * 60,
             build_cpu_max_in_milli: 2000,
             build_ram_max_in_gib: 4,
+            build_registry_cache_enabled: true,
             security_service_account_name: "".to_string(),
             security_read_only_root_filesystem: false,
             security_automount_service_account_token: false,
@@ -53,6 +53,7 @@ pub fn container_registry_ecr(context: &Context, logger: Box<dyn Logger>) -> ECR
         "ea59qe62xaw3wjai",
         secrets.AWS_ACCESS_KEY_ID.unwrap().as_str(),
         secrets.AWS_SECRET_ACCESS_KEY.unwrap().as_str(),
+        None,
         secrets.AWS_DEFAULT_REGION.unwrap().as_str(),
         logger,
         hashmap! {},
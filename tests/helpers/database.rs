@@ -13,6 +13,7 @@ use core::option::Option::{None, Some};
 use core::result::Result::{Err, Ok};
 use qovery_engine::cmd::structs::SVCItem;
 use qovery_engine::environment::models::environment::Environment;
+use qovery_engine::environment::models::pod_security::PodSecurityProfile;
 use qovery_engine::infrastructure::models::cloud_provider::aws::AWS;
 use qovery_engine::infrastructure::models::cloud_provider::scaleway::Scaleway;
 use qovery_engine::infrastructure::models::cloud_provider::Kind;
@@ -20,6 +21,7 @@ use qovery_engine::infrastructure::models::kubernetes::Kind as KubernetesKind;
 use qovery_engine::io_models::engine_location::EngineLocation;
 
 use qovery_engine::infrastructure::infrastructure_context::InfrastructureContext;
+use qovery_engine::infrastructure::models::build_platform::GitAccess;
 use qovery_engine::io_models::application::{Application, Port, Protocol};
 use qovery_engine::io_models::context::{CloneForTest, Context};
 use qovery_engine::io_models::database::DatabaseMode::{CONTAINER, MANAGED};
@@ -42,6 +44,7 @@ use crate::helpers::gcp::GCP_KUBERNETES_VERSION;
 use crate::helpers::on_premise::ON_PREMISE_KUBERNETES_VERSION;
 use base64::engine::general_purpose;
 use base64::Engine;
+use qovery_engine::engine_task::deadline::{TaskDeadline, DEFAULT_TASK_MAX_DURATION};
 use qovery_engine::environment::models::abort::AbortStatus;
 use qovery_engine::environment::models::types::VersionsNumber;
 use qovery_engine::errors::EngineError;
@@ -109,7 +112,12 @@ impl Infrastructure for EnvironmentRequest {
             .unwrap();
 
         env.action = qovery_engine::infrastructure::models::cloud_provider::service::Action::Create;
-        EnvironmentTask::deploy_environment(env, infra_ctx, &|| AbortStatus::None)
+        EnvironmentTask::deploy_environment(
+            env,
+            infra_ctx,
+            &|| AbortStatus::None,
+            &TaskDeadline::new(DEFAULT_TASK_MAX_DURATION),
+        )
     }
 
     fn pause_environment(
@@ -127,7 +135,12 @@ impl Infrastructure for EnvironmentRequest {
             .unwrap();
 
         env.action = qovery_engine::infrastructure::models::cloud_provider::service::Action::Pause;
-        EnvironmentTask::deploy_environment(env, infra_ctx, &|| AbortStatus::None)
+        EnvironmentTask::deploy_environment(
+            env,
+            infra_ctx,
+            &|| AbortStatus::None,
+            &TaskDeadline::new(DEFAULT_TASK_MAX_DURATION),
+        )
     }
 
     fn delete_environment(
@@ -145,7 +158,12 @@ impl Infrastructure for EnvironmentRequest {
             .unwrap();
 
         env.action = qovery_engine::infrastructure::models::cloud_provider::service::Action::Delete;
-        EnvironmentTask::deploy_environment(env, infra_ctx, &|| AbortStatus::None)
+        EnvironmentTask::deploy_environment(
+            env,
+            infra_ctx,
+            &|| AbortStatus::None,
+            &TaskDeadline::new(DEFAULT_TASK_MAX_DURATION),
+        )
     }
 
     fn restart_environment(
@@ -163,7 +181,12 @@ impl Infrastructure for EnvironmentRequest {
             .unwrap();
 
         env.action = qovery_engine::infrastructure::models::cloud_provider::service::Action::Restart;
-        EnvironmentTask::deploy_environment(env, infra_ctx, &|| AbortStatus::None)
+        EnvironmentTask::deploy_environment(
+            env,
+            infra_ctx,
+            &|| AbortStatus::None,
+            &TaskDeadline::new(DEFAULT_TASK_MAX_DURATION),
+        )
     }
 }
 
@@ -258,6 +281,7 @@ pub fn environment_3_apps_3_databases(
                     is_default: true,
                     name: "p1234".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
@@ -270,6 +294,7 @@ pub fn environment_3_apps_3_databases(
                 min_instances: 1,
                 max_instances: 1,
                 advanced_settings: Default::default(),
+                lifecycle_hooks: Default::default(),
                 readiness_probe: Some(Probe {
                     r#type: ProbeType::Http {
                         path: "/".to_string(),
@@ -296,6 +321,8 @@ pub fn environment_3_apps_3_databases(
                 labels_group_ids: BTreeSet::new(),
                 should_delete_shared_registry: false,
                 shared_image_feature_enabled: false,
+                git_access: GitAccess::Direct,
+                build_secrets: vec![],
             },
             Application {
                 long_id: Uuid::new_v4(),
@@ -325,6 +352,7 @@ pub fn environment_3_apps_3_databases(
                     is_default: true,
                     name: "p1234".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
@@ -337,6 +365,7 @@ pub fn environment_3_apps_3_databases(
                 min_instances: 1,
                 max_instances: 1,
                 advanced_settings: Default::default(),
+                lifecycle_hooks: Default::default(),
                 readiness_probe: Some(Probe {
                     r#type: ProbeType::Http {
                         path: "/".to_string(),
@@ -364,6 +393,8 @@ pub fn environment_3_apps_3_databases(
                 labels_group_ids: BTreeSet::new(),
                 should_delete_shared_registry: false,
                 shared_image_feature_enabled: false,
+                git_access: GitAccess::Direct,
+                build_secrets: vec![],
             },
             Application {
                 long_id: Uuid::new_v4(),
@@ -396,6 +427,7 @@ pub fn environment_3_apps_3_databases(
                     is_default: true,
                     name: "p1234".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
@@ -408,6 +440,7 @@ pub fn environment_3_apps_3_databases(
                 min_instances: 1,
                 max_instances: 1,
                 advanced_settings: Default::default(),
+                lifecycle_hooks: Default::default(),
                 readiness_probe: Some(Probe {
                     r#type: ProbeType::Http {
                         path: "/".to_string(),
@@ -434,6 +467,8 @@ pub fn environment_3_apps_3_databases(
                 labels_group_ids: BTreeSet::new(),
                 should_delete_shared_registry: false,
                 shared_image_feature_enabled: false,
+                git_access: GitAccess::Direct,
+                build_secrets: vec![],
             },
         ],
         containers: vec![],
@@ -463,10 +498,16 @@ pub fn environment_3_apps_3_databases(
                 encrypt_disk: true,
                 activate_high_availability: false,
                 activate_backups: false,
+                activate_health_snapshot: false,
+                override_health_checks: false,
+                rotate_credentials: false,
                 publicly_accessible: false,
                 mode: CONTAINER,
                 annotations_group_ids: btreeset! {},
                 labels_group_ids: btreeset! {},
+                parameters: Default::default(),
+                maintenance_window: None,
+                backup_retention_days: None,
             },
             Database {
                 kind: DatabaseKind::Postgresql,
@@ -491,10 +532,16 @@ pub fn environment_3_apps_3_databases(
                 encrypt_disk: true,
                 activate_high_availability: false,
                 activate_backups: false,
+                activate_health_snapshot: false,
+                override_health_checks: false,
+                rotate_credentials: false,
                 publicly_accessible: false,
                 mode: CONTAINER,
                 annotations_group_ids: btreeset! {},
                 labels_group_ids: btreeset! {},
+                parameters: Default::default(),
+                maintenance_window: None,
+                backup_retention_days: None,
             },
             Database {
                 kind: DatabaseKind::Mongodb,
@@ -519,15 +566,26 @@ pub fn environment_3_apps_3_databases(
                 encrypt_disk: true,
                 activate_high_availability: false,
                 activate_backups: false,
+                activate_health_snapshot: false,
+                override_health_checks: false,
+                rotate_credentials: false,
                 publicly_accessible: false,
                 mode: CONTAINER,
                 annotations_group_ids: btreeset! {},
                 labels_group_ids: btreeset! {},
+                parameters: Default::default(),
+                maintenance_window: None,
+                backup_retention_days: None,
             },
         ],
         helms: vec![],
+        k8s_manifests: vec![],
         annotations_groups: btreemap! {},
         labels_groups: btreemap! {},
+        pod_security_profile: PodSecurityProfile::default(),
+        custom_error_pages: None,
+        variable_change_preview: None,
+        buckets: vec![],
     }
 }
 
@@ -570,6 +628,7 @@ pub fn database_test_environment(context: &Context) -> EnvironmentRequest {
             min_instances: 1,
             max_instances: 1,
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: None,
             liveness_probe: None,
             public_domain: format!("{}.example.com", Uuid::new_v4()),
@@ -578,14 +637,21 @@ pub fn database_test_environment(context: &Context) -> EnvironmentRequest {
             labels_group_ids: BTreeSet::new(),
             should_delete_shared_registry: false,
             shared_image_feature_enabled: false,
+            git_access: GitAccess::Direct,
+            build_secrets: vec![],
         }],
         containers: vec![],
         jobs: vec![],
         routers: vec![],
         databases: vec![],
         helms: vec![],
+        k8s_manifests: vec![],
         annotations_groups: btreemap! {},
         labels_groups: btreemap! {},
+        pod_security_profile: PodSecurityProfile::default(),
+        custom_error_pages: None,
+        variable_change_preview: None,
+        buckets: vec![],
     }
 }
 
@@ -629,6 +695,7 @@ pub fn database_test_environment_on_upgrade(context: &Context) -> EnvironmentReq
             min_instances: 1,
             max_instances: 1,
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: None,
             liveness_probe: None,
             container_registries: Vec::new(),
@@ -636,14 +703,21 @@ pub fn database_test_environment_on_upgrade(context: &Context) -> EnvironmentReq
             labels_group_ids: BTreeSet::new(),
             should_delete_shared_registry: false,
             shared_image_feature_enabled: false,
+            git_access: GitAccess::Direct,
+            build_secrets: vec![],
         }],
         containers: vec![],
         jobs: vec![],
         routers: vec![],
         databases: vec![],
         helms: vec![],
+        k8s_manifests: vec![],
         annotations_groups: btreemap! {},
         labels_groups: btreemap! {},
+        pod_security_profile: PodSecurityProfile::default(),
+        custom_error_pages: None,
+        variable_change_preview: None,
+        buckets: vec![],
     }
 }
 
@@ -745,10 +819,16 @@ pub fn test_db(
         encrypt_disk: true,
         activate_high_availability: false,
         activate_backups: false,
+        activate_health_snapshot: false,
+        override_health_checks: false,
+        rotate_credentials: false,
         publicly_accessible: is_public,
         mode: database_mode.clone(),
         annotations_group_ids: btreeset! {},
         labels_group_ids: btreeset! {},
+        parameters: Default::default(),
+        maintenance_window: None,
+        backup_retention_days: None,
     };
 
     environment.databases = vec![db.clone()];
@@ -777,6 +857,7 @@ pub fn test_db(
                 is_default: true,
                 name: "p1234".to_string(),
                 publicly_accessible: true,
+                expose: None,
                 protocol: Protocol::HTTP,
                 service_name: None,
                 namespace: None,
@@ -1071,10 +1152,16 @@ pub fn test_pause_managed_db(
         encrypt_disk: true,
         activate_high_availability: false,
         activate_backups: false,
+        activate_health_snapshot: false,
+        override_health_checks: false,
+        rotate_credentials: false,
         publicly_accessible: is_public,
         mode: database_mode.clone(),
         annotations_group_ids: btreeset! {},
         labels_group_ids: btreeset! {},
+        parameters: Default::default(),
+        maintenance_window: None,
+        backup_retention_days: None,
     };
 
     environment.databases = vec![db];
@@ -1336,10 +1423,16 @@ pub fn test_db_on_upgrade(
         encrypt_disk: true,
         activate_high_availability: false,
         activate_backups: false,
+        activate_health_snapshot: false,
+        override_health_checks: false,
+        rotate_credentials: false,
         publicly_accessible: is_public,
         mode: database_mode.clone(),
         annotations_group_ids: btreeset! {},
         labels_group_ids: btreeset! {},
+        parameters: Default::default(),
+        maintenance_window: None,
+        backup_retention_days: None,
     };
 
     environment.databases = vec![db];
@@ -1359,6 +1452,7 @@ pub fn test_db_on_upgrade(
                 is_default: true,
                 name: "p1234".to_string(),
                 publicly_accessible: true,
+                expose: None,
                 protocol: Protocol::HTTP,
                 service_name: None,
                 namespace: None,
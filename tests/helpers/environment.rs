@@ -3,6 +3,8 @@ use base64::engine::general_purpose;
 use base64::Engine;
 use chrono::Utc;
 use qovery_engine::environment::models::database::DatabaseInstanceType;
+use qovery_engine::environment::models::pod_security::PodSecurityProfile;
+use qovery_engine::infrastructure::models::build_platform::GitAccess;
 use qovery_engine::infrastructure::models::cloud_provider::Kind;
 use qovery_engine::io_models::application::{Application, ApplicationAdvancedSettings, Port, Protocol};
 use qovery_engine::io_models::context::Context;
@@ -10,7 +12,7 @@ use qovery_engine::io_models::database::DatabaseMode::CONTAINER;
 use qovery_engine::io_models::database::{Database, DatabaseKind};
 use qovery_engine::io_models::environment::EnvironmentRequest;
 use qovery_engine::io_models::probe::{Probe, ProbeType};
-use qovery_engine::io_models::router::{Route, Router};
+use qovery_engine::io_models::router::{Route, RouteProtocol, Router};
 use qovery_engine::io_models::variable_utils::VariableInfo;
 use qovery_engine::io_models::{Action, MountedFile, QoveryIdentifier};
 use qovery_engine::utilities::to_short_id;
@@ -71,6 +73,7 @@ pub fn working_environment(
                 is_default: true,
                 name: "p80".to_string(),
                 publicly_accessible: true,
+                expose: None,
                 protocol: Protocol::HTTP,
                 service_name: None,
                 namespace: None,
@@ -82,6 +85,7 @@ pub fn working_environment(
             ram_limit_in_mib: 512,
             min_instances: 1,
             max_instances: 1,
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Http {
                     path: "/".to_string(),
@@ -110,14 +114,21 @@ pub fn working_environment(
             labels_group_ids: btreeset! {},
             should_delete_shared_registry: false,
             shared_image_feature_enabled: git_url_override.is_some(),
+            git_access: GitAccess::Direct,
+            build_secrets: vec![],
         }],
         containers: vec![],
         jobs: vec![],
         routers: vec![],
         databases: vec![],
         helms: vec![],
+        k8s_manifests: vec![],
         annotations_groups: btreemap! {},
         labels_groups: btreemap! {},
+        pod_security_profile: PodSecurityProfile::default(),
+        custom_error_pages: None,
+        variable_change_preview: None,
+        buckets: vec![],
     };
 
     if with_router {
@@ -132,7 +143,11 @@ pub fn working_environment(
             routes: vec![Route {
                 path: "/".to_string(),
                 service_long_id: application_id.to_uuid(),
+                protocol: RouteProtocol::Http1,
             }],
+            basic_auth_credentials: vec![],
+            extra_ingress_annotations: BTreeMap::new(),
+            allow_unsafe_ingress_annotations: false,
         }]
     }
 
@@ -276,10 +291,16 @@ pub fn environment_2_app_2_routers_1_psql(
             encrypt_disk: true,
             activate_high_availability: false,
             activate_backups: false,
+            activate_health_snapshot: false,
+            override_health_checks: false,
+            rotate_credentials: false,
             publicly_accessible: false,
             mode: CONTAINER,
             annotations_group_ids: btreeset! {},
             labels_group_ids: btreeset! {},
+            parameters: Default::default(),
+            maintenance_window: None,
+            backup_retention_days: None,
         }],
         applications: vec![
             Application {
@@ -310,6 +331,7 @@ pub fn environment_2_app_2_routers_1_psql(
                     is_default: true,
                     name: "p1234".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
@@ -322,6 +344,7 @@ pub fn environment_2_app_2_routers_1_psql(
                 min_instances: 1,
                 max_instances: 1,
                 advanced_settings: Default::default(),
+                lifecycle_hooks: Default::default(),
                 readiness_probe: Some(Probe {
                     r#type: ProbeType::Http {
                         path: "/".to_string(),
@@ -349,6 +372,8 @@ pub fn environment_2_app_2_routers_1_psql(
                 labels_group_ids: btreeset! {},
                 should_delete_shared_registry: false,
                 shared_image_feature_enabled: false,
+                git_access: GitAccess::Direct,
+                build_secrets: vec![],
             },
             Application {
                 long_id: application_id2,
@@ -379,6 +404,7 @@ pub fn environment_2_app_2_routers_1_psql(
                     is_default: true,
                     name: "p1234".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
@@ -391,6 +417,7 @@ pub fn environment_2_app_2_routers_1_psql(
                 min_instances: 1,
                 max_instances: 1,
                 advanced_settings: Default::default(),
+                lifecycle_hooks: Default::default(),
                 readiness_probe: Some(Probe {
                     r#type: ProbeType::Http {
                         path: "/".to_string(),
@@ -417,6 +444,8 @@ pub fn environment_2_app_2_routers_1_psql(
                 labels_group_ids: btreeset! {},
                 should_delete_shared_registry: false,
                 shared_image_feature_enabled: false,
+                git_access: GitAccess::Direct,
+                build_secrets: vec![],
             },
         ],
         containers: vec![],
@@ -433,7 +462,11 @@ pub fn environment_2_app_2_routers_1_psql(
                 routes: vec![Route {
                     path: "/".to_string(),
                     service_long_id: application_id1,
+                    protocol: RouteProtocol::Http1,
                 }],
+                basic_auth_credentials: vec![],
+                extra_ingress_annotations: BTreeMap::new(),
+                allow_unsafe_ingress_annotations: false,
             },
             Router {
                 long_id: router_2,
@@ -446,14 +479,23 @@ pub fn environment_2_app_2_routers_1_psql(
                 routes: vec![Route {
                     path: "/coco".to_string(),
                     service_long_id: application_id2,
+                    protocol: RouteProtocol::Http1,
                 }],
+                basic_auth_credentials: vec![],
+                extra_ingress_annotations: BTreeMap::new(),
+                allow_unsafe_ingress_annotations: false,
             },
         ],
         max_parallel_build: 1,
         max_parallel_deploy: 1,
         helms: vec![],
+        k8s_manifests: vec![],
         annotations_groups: btreemap! {},
         labels_groups: btreemap! {},
+        pod_security_profile: PodSecurityProfile::default(),
+        custom_error_pages: None,
+        variable_change_preview: None,
+        buckets: vec![],
     }
 }
 
@@ -515,6 +557,7 @@ pub fn echo_app_environment(context: &Context, test_domain: &str) -> Environment
                 is_default: true,
                 name: "p5678".to_string(),
                 publicly_accessible: true,
+                expose: None,
                 protocol: Protocol::HTTP,
                 service_name: None,
                 namespace: None,
@@ -527,6 +570,7 @@ pub fn echo_app_environment(context: &Context, test_domain: &str) -> Environment
             min_instances: 1,
             max_instances: 1,
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Http {
                     path: "/".to_string(),
@@ -553,6 +597,8 @@ pub fn echo_app_environment(context: &Context, test_domain: &str) -> Environment
             labels_group_ids: btreeset! {},
             should_delete_shared_registry: false,
             shared_image_feature_enabled: false,
+            git_access: GitAccess::Direct,
+            build_secrets: vec![],
         }],
         containers: vec![],
         jobs: vec![],
@@ -567,12 +613,21 @@ pub fn echo_app_environment(context: &Context, test_domain: &str) -> Environment
             routes: vec![Route {
                 path: "/".to_string(),
                 service_long_id: application_id,
+                protocol: RouteProtocol::Http1,
             }],
+            basic_auth_credentials: vec![],
+            extra_ingress_annotations: BTreeMap::new(),
+            allow_unsafe_ingress_annotations: false,
         }],
         databases: vec![],
         helms: vec![],
+        k8s_manifests: vec![],
         annotations_groups: btreemap! {},
         labels_groups: btreemap! {},
+        pod_security_profile: PodSecurityProfile::default(),
+        custom_error_pages: None,
+        variable_change_preview: None,
+        buckets: vec![],
     }
 }
 
@@ -629,6 +684,7 @@ pub fn environment_only_http_server(
                 is_default: true,
                 name: "p80".to_string(),
                 publicly_accessible: true,
+                expose: None,
                 protocol: Protocol::HTTP,
                 service_name: None,
                 namespace: None,
@@ -641,6 +697,7 @@ pub fn environment_only_http_server(
             min_instances: 1,
             max_instances: 1,
             advanced_settings: settings,
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Http {
                     path: "/".to_string(),
@@ -667,14 +724,21 @@ pub fn environment_only_http_server(
             labels_group_ids: btreeset! {},
             should_delete_shared_registry: false,
             shared_image_feature_enabled: false,
+            git_access: GitAccess::Direct,
+            build_secrets: vec![],
         }],
         containers: vec![],
         jobs: vec![],
         routers: vec![],
         databases: vec![],
         helms: vec![],
+        k8s_manifests: vec![],
         annotations_groups: btreemap! {},
         labels_groups: btreemap! {},
+        pod_security_profile: PodSecurityProfile::default(),
+        custom_error_pages: None,
+        variable_change_preview: None,
+        buckets: vec![],
     };
 
     if with_router {
@@ -689,7 +753,11 @@ pub fn environment_only_http_server(
             routes: vec![Route {
                 path: "/".to_string(),
                 service_long_id: application_id,
+                protocol: RouteProtocol::Http1,
             }],
+            basic_auth_credentials: vec![],
+            extra_ingress_annotations: BTreeMap::new(),
+            allow_unsafe_ingress_annotations: false,
         }]
     }
 
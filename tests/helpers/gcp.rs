@@ -19,7 +19,7 @@ use qovery_engine::infrastructure::models::cloud_provider::{CloudProvider, Terra
 use qovery_engine::infrastructure::models::container_registry::errors::ContainerRegistryError;
 use qovery_engine::infrastructure::models::container_registry::google_artifact_registry::GoogleArtifactRegistry;
 use qovery_engine::infrastructure::models::dns_provider::DnsProvider;
-use qovery_engine::infrastructure::models::kubernetes::gcp::{Gke, GkeOptions, VpcMode};
+use qovery_engine::infrastructure::models::kubernetes::gcp::{ControlPlaneAccess, Gke, GkeOptions, VpcMode};
 use qovery_engine::infrastructure::models::kubernetes::{Kind as KubernetesKind, KubernetesVersion};
 use qovery_engine::io_models::context::Context;
 use qovery_engine::io_models::engine_location::EngineLocation;
@@ -286,6 +286,7 @@ impl Cluster<Google, GkeOptions> for Gke {
                 custom_services_ipv4_cidr_block: None,
             },
             vpc_network_mode,
+            ControlPlaneAccess::default(),
             secrets
                 .LETS_ENCRYPT_EMAIL_REPORT
                 .expect("LETS_ENCRYPT_EMAIL_REPORT is not set in secrets"),
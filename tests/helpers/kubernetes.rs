@@ -9,7 +9,6 @@ use core::option::Option::{None, Some};
 use core::result::Result::Err;
 use qovery_engine::environment::models::scaleway::ScwZone;
 use qovery_engine::environment::task::EnvironmentTask;
-use qovery_engine::fs::workspace_directory;
 use qovery_engine::infrastructure::models::cloud_provider::aws::regions::AwsRegion;
 use qovery_engine::infrastructure::models::cloud_provider::aws::AWS;
 use qovery_engine::infrastructure::models::cloud_provider::gcp::locations::GcpRegion;
@@ -28,6 +27,7 @@ use qovery_engine::logger::Logger;
 use qovery_engine::metrics_registry::MetricsRegistry;
 
 use crate::helpers::on_premise::ON_PREMISE_KUBERNETES_VERSION;
+use qovery_engine::engine_task::deadline::{TaskDeadline, DEFAULT_TASK_MAX_DURATION};
 use qovery_engine::environment::models::abort::AbortStatus;
 use qovery_engine::infrastructure::models::cloud_provider;
 use qovery_engine::infrastructure::models::cloud_provider::service::Action;
@@ -160,7 +160,12 @@ pub fn cluster_test(
             .unwrap();
 
         env.action = qovery_engine::infrastructure::models::cloud_provider::service::Action::Create;
-        if let Err(ret) = EnvironmentTask::deploy_environment(env, &engine, &|| AbortStatus::None) {
+        if let Err(ret) = EnvironmentTask::deploy_environment(
+            env,
+            &engine,
+            &|| AbortStatus::None,
+            &TaskDeadline::new(DEFAULT_TASK_MAX_DURATION),
+        ) {
             panic!("{ret:?}")
         }
     }
@@ -316,7 +321,12 @@ pub fn cluster_test(
             .unwrap();
 
         env.action = qovery_engine::infrastructure::models::cloud_provider::service::Action::Delete;
-        if let Err(ret) = EnvironmentTask::deploy_environment(env, &engine, &|| AbortStatus::None) {
+        if let Err(ret) = EnvironmentTask::deploy_environment(
+            env,
+            &engine,
+            &|| AbortStatus::None,
+            &TaskDeadline::new(DEFAULT_TASK_MAX_DURATION),
+        ) {
             panic!("{ret:?}")
         }
     }
@@ -345,12 +355,10 @@ pub fn get_environment_test_kubernetes(
 ) -> Box<dyn Kubernetes> {
     let secrets = FuncTestsSecrets::new();
 
-    let temp_dir = workspace_directory(
-        context.workspace_root_dir(),
-        context.execution_id(),
-        format!("bootstrap/{}", context.cluster_short_id()),
-    )
-    .unwrap();
+    let workspace = context
+        .workspace()
+        .and_then(|workspace| workspace.service(context.cluster_long_id()))
+        .unwrap();
 
     let kubernetes: Box<dyn Kubernetes> = match cloud_provider.kubernetes_kind() {
         KubernetesKind::Eks => {
@@ -384,7 +392,7 @@ pub fn get_environment_test_kubernetes(
                     },
                     None,
                     kubeconfig,
-                    temp_dir,
+                    workspace,
                     None,
                 )
                 .unwrap(),
@@ -412,7 +420,7 @@ pub fn get_environment_test_kubernetes(
                     },
                     None,
                     secrets.SCALEWAY_TEST_KUBECONFIG_b64,
-                    temp_dir,
+                    workspace,
                 )
                 .expect("Cannot instantiate SCW Kapsule"),
             )
@@ -442,7 +450,7 @@ pub fn get_environment_test_kubernetes(
                     },
                     None,
                     secrets.GCP_TEST_KUBECONFIG_b64,
-                    temp_dir,
+                    workspace,
                 )
                 .expect("Cannot instantiate GKE"),
             )
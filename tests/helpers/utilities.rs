@@ -49,6 +49,7 @@ use qovery_engine::infrastructure::models::kubernetes::Kind as KKind;
 use qovery_engine::io_models::context::{Context, Features, Metadata};
 use qovery_engine::io_models::database::{DatabaseKind, DatabaseMode};
 use qovery_engine::io_models::environment::EnvironmentRequest;
+use qovery_engine::io_models::feature_flags::FeatureFlags;
 use qovery_engine::io_models::variable_utils::VariableInfo;
 use qovery_engine::io_models::QoveryIdentifier;
 use qovery_engine::logger::{Logger, StdIoLogger};
@@ -110,6 +111,7 @@ fn context(organization_id: Uuid, cluster_id: Uuid, ttl: u32, kind: Option<KKind
         },
         forced_upgrade: Option::from(env::var_os("forced_upgrade").is_some()),
         is_first_cluster_deployment: Some(false),
+        force: None,
     };
     let mut enabled_features = vec![Features::LogsHistory, Features::MetricsHistory];
     if let Some(kkind) = kind {
@@ -128,6 +130,7 @@ fn context(organization_id: Uuid, cluster_id: Uuid, ttl: u32, kind: Option<KKind
         lib_root_dir,
         true,
         enabled_features,
+        FeatureFlags::default(),
         Option::from(metadata),
         Arc::new(docker),
         Arc::new(StaticQoveryApi { versions }),
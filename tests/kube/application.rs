@@ -10,6 +10,7 @@ use qovery_engine::environment::action::update_pvcs;
 use qovery_engine::environment::models::abort::AbortStatus;
 use qovery_engine::environment::models::application::{get_application_with_invalid_storage_size, Application};
 use qovery_engine::environment::models::aws::{AwsAppExtraSettings, AwsStorageType};
+use qovery_engine::environment::models::service_port::ServicePort;
 use qovery_engine::environment::models::types::AWS;
 use qovery_engine::infrastructure::models::cloud_provider::service::ServiceType;
 use qovery_engine::infrastructure::models::cloud_provider::DeploymentTarget;
@@ -79,7 +80,7 @@ fn should_increase_app_storage_size() {
             resized_app.name.as_str(),
             resized_app.name.clone(),
             resized_app.public_domain.clone(),
-            resized_app.ports.clone(),
+            ServicePort::from_payload(resized_app.ports.clone()).0,
             resized_app.min_instances,
             resized_app.max_instances,
             resized_app.to_build(
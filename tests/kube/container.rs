@@ -11,6 +11,7 @@ use qovery_engine::environment::models::abort::AbortStatus;
 use qovery_engine::environment::models::aws::{AwsAppExtraSettings, AwsStorageType};
 use qovery_engine::environment::models::container::{get_container_with_invalid_storage_size, Container};
 use qovery_engine::environment::models::registry_image_source::RegistryImageSource;
+use qovery_engine::environment::models::service_port::ServicePort;
 use qovery_engine::environment::models::types::AWS;
 use qovery_engine::infrastructure::models::cloud_provider::io::RegistryMirroringMode;
 use qovery_engine::infrastructure::models::cloud_provider::service::ServiceType;
@@ -85,6 +86,7 @@ fn should_increase_container_storage_size() {
                 image: resized_container.image.clone(),
                 tag: resized_container.tag.clone(),
                 registry_mirroring_mode: RegistryMirroringMode::Service,
+                promotion: None,
             },
             resized_container.command_args.clone(),
             resized_container.entrypoint.clone(),
@@ -95,7 +97,7 @@ fn should_increase_container_storage_size() {
             resized_container.min_instances,
             resized_container.max_instances,
             resized_container.public_domain.clone(),
-            resized_container.ports.clone(),
+            ServicePort::from_payload(resized_container.ports.clone()).0,
             storages,
             envs,
             BTreeSet::default(),
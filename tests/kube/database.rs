@@ -82,7 +82,12 @@ fn should_increase_db_storage_size() {
                 encrypt_disk: resized_db.encrypt_disk,
                 activate_high_availability: resized_db.activate_high_availability,
                 activate_backups: resized_db.activate_backups,
+                activate_health_snapshot: resized_db.activate_health_snapshot,
+                override_health_checks: resized_db.override_health_checks,
+                rotate_credentials: resized_db.rotate_credentials,
                 publicly_accessible: resized_db.publicly_accessible,
+                parameters: Default::default(),
+                maintenance: Default::default(),
             },
             |transmitter| infra_ctx.context().get_event_details(transmitter),
             vec![],
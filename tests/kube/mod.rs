@@ -6,7 +6,9 @@ use crate::helpers::utilities::{
 };
 use chrono::Utc;
 use qovery_engine::environment::models::aws::AwsStorageType;
+use qovery_engine::environment::models::pod_security::PodSecurityProfile;
 use qovery_engine::infrastructure::infrastructure_context::InfrastructureContext;
+use qovery_engine::infrastructure::models::build_platform::GitAccess;
 use qovery_engine::infrastructure::models::cloud_provider::Kind::Aws;
 use qovery_engine::io_models::application::{Application, Port, Protocol, Storage};
 use qovery_engine::io_models::container::{Container, Registry};
@@ -74,8 +76,13 @@ pub fn kube_test_env(options: TestEnvOption) -> (InfrastructureContext, Environm
         routers: vec![],
         databases: vec![],
         helms: vec![],
+        k8s_manifests: vec![],
         annotations_groups: btreemap! {},
         labels_groups: btreemap! {},
+        pod_security_profile: PodSecurityProfile::default(),
+        custom_error_pages: None,
+        variable_change_preview: None,
+        buckets: vec![],
     };
 
     match options {
@@ -104,11 +111,17 @@ pub fn kube_test_env(options: TestEnvOption) -> (InfrastructureContext, Environm
                 encrypt_disk: true,
                 activate_high_availability: false,
                 activate_backups: false,
+                activate_health_snapshot: false,
+                override_health_checks: false,
+                rotate_credentials: false,
                 publicly_accessible: false,
                 mode: CONTAINER,
                 database_instance_type: None,
                 annotations_group_ids: btreeset! {},
                 labels_group_ids: btreeset! {},
+                parameters: Default::default(),
+                maintenance_window: None,
+                backup_retention_days: None,
             };
             environment.databases = vec![db];
         }
@@ -155,11 +168,13 @@ pub fn kube_test_env(options: TestEnvOption) -> (InfrastructureContext, Environm
                     is_default: true,
                     name: "http".to_string(),
                     publicly_accessible: false,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
                     additional_service: None,
                 }],
+                lifecycle_hooks: Default::default(),
                 readiness_probe: Some(Probe {
                     r#type: ProbeType::Tcp { host: None },
                     port: 8080,
@@ -203,6 +218,7 @@ pub fn kube_test_env(options: TestEnvOption) -> (InfrastructureContext, Environm
                 mounted_files: vec![],
                 annotations_group_ids: BTreeSet::new(),
                 labels_group_ids: btreeset! {},
+                promote_image: None,
             };
             environment.containers = vec![container];
         }
@@ -252,11 +268,13 @@ pub fn kube_test_env(options: TestEnvOption) -> (InfrastructureContext, Environm
                     is_default: true,
                     name: "p80".to_string(),
                     publicly_accessible: false,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
                     additional_service: None,
                 }],
+                lifecycle_hooks: Default::default(),
                 readiness_probe: Some(Probe {
                     r#type: ProbeType::Http {
                         path: "/".to_string(),
@@ -291,6 +309,8 @@ pub fn kube_test_env(options: TestEnvOption) -> (InfrastructureContext, Environm
                 labels_group_ids: btreeset! {},
                 should_delete_shared_registry: false,
                 shared_image_feature_enabled: false,
+                git_access: GitAccess::Direct,
+                build_secrets: vec![],
             };
             environment.applications = vec![app];
         }
@@ -315,7 +335,7 @@ pub fn kube_test_env(options: TestEnvOption) -> (InfrastructureContext, Environm
                 ram_limit_in_mib: 250,
                 action: Action::Create,
                 schedule: JobSchedule::Cron {
-                    schedule: "*/30 * * * *".to_string(), // <- every 30 minutes
+                    schedule: "*/30 * * * *".parse().unwrap(), // <- every 30 minutes
                     timezone: "Etc/UTC".to_string(),
                 },
                 source: JobSource::Image {
@@ -332,6 +352,7 @@ pub fn kube_test_env(options: TestEnvOption) -> (InfrastructureContext, Environm
                 advanced_settings: Default::default(),
                 mounted_files: vec![],
                 default_port: None,
+                lifecycle_hooks: Default::default(),
                 readiness_probe: None,
                 liveness_probe: None,
                 container_registries: ContainerRegistries { registries: vec![] },
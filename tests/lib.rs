@@ -4,6 +4,8 @@ extern crate core;
 
 mod aws;
 mod container_registries;
+#[cfg(feature = "failure-injection")]
+mod failure_injection;
 mod gcp;
 mod helm;
 pub mod helpers;
@@ -23,7 +23,7 @@ use qovery_engine::io_models::context::CloneForTest;
 use qovery_engine::io_models::job::{ContainerRegistries, Job, JobSchedule, JobSource, LifecycleType};
 use qovery_engine::io_models::labels_group::{Label, LabelsGroup};
 use qovery_engine::io_models::probe::{Probe, ProbeType};
-use qovery_engine::io_models::router::{CustomDomain, Route, Router};
+use qovery_engine::io_models::router::{CustomDomain, Route, RouteProtocol, Router};
 use qovery_engine::io_models::variable_utils::VariableInfo;
 use qovery_engine::io_models::{Action, MountedFile, QoveryIdentifier};
 use qovery_engine::utilities::to_short_id;
@@ -500,6 +500,7 @@ fn scaleway_kapsule_deploy_a_working_environment_with_domain() {
                 is_default: false,
                 name: "grpc".to_string(),
                 publicly_accessible: true,
+                expose: None,
                 protocol: Protocol::GRPC,
                 service_name: None,
                 namespace: None,
@@ -1697,6 +1698,7 @@ fn deploy_container_with_no_router_on_scw() {
                     name: "http".to_string(),
                     is_default: true,
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
@@ -1708,12 +1710,14 @@ fn deploy_container_with_no_router_on_scw() {
                     name: "grpc".to_string(),
                     is_default: false,
                     publicly_accessible: false,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
                     additional_service: None,
                 },
             ],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -1738,6 +1742,7 @@ fn deploy_container_with_no_router_on_scw() {
             advanced_settings: Default::default(),
             annotations_group_ids: BTreeSet::new(),
             labels_group_ids: btreeset! {},
+            promote_image: None,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -1842,6 +1847,7 @@ fn deploy_container_on_scw_with_mounted_files_as_volume() {
                     name: "http".to_string(),
                     is_default: true,
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
@@ -1853,6 +1859,7 @@ fn deploy_container_on_scw_with_mounted_files_as_volume() {
                     name: "grpc".to_string(),
                     is_default: false,
                     publicly_accessible: false,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
@@ -1863,6 +1870,7 @@ fn deploy_container_on_scw_with_mounted_files_as_volume() {
             environment_vars_with_infos: btreemap! { "MY_VAR".to_string() =>  VariableInfo{ value: general_purpose::STANDARD.encode("my_value"), is_secret: false} },
             mounted_files: vec![mounted_file.clone()],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -1883,6 +1891,7 @@ fn deploy_container_on_scw_with_mounted_files_as_volume() {
             }),
             annotations_group_ids: BTreeSet::new(),
             labels_group_ids: btreeset! {},
+            promote_image: None,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -2009,6 +2018,7 @@ fn deploy_container_with_router_on_scw() {
                     name: "http".to_string(),
                     is_default: true,
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
@@ -2020,6 +2030,7 @@ fn deploy_container_with_router_on_scw() {
                     name: "grpc".to_string(),
                     is_default: false,
                     publicly_accessible: false,
+                    expose: None,
                     protocol: Protocol::HTTP,
                     service_name: None,
                     namespace: None,
@@ -2030,6 +2041,7 @@ fn deploy_container_with_router_on_scw() {
             environment_vars_with_infos: btreemap! { "MY_VAR".to_string() => VariableInfo{ value:general_purpose::STANDARD.encode("my_value"), is_secret: false} },
             mounted_files: vec![],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 80,
@@ -2050,6 +2062,7 @@ fn deploy_container_with_router_on_scw() {
             }),
             annotations_group_ids: btreeset! { annotations_group_id },
             labels_group_ids: btreeset! { labels_group_id },
+            promote_image: None,
         }];
         environment.annotations_groups = btreemap! { annotations_group_id => AnnotationsGroup {
             annotations: vec![Annotation {
@@ -2084,7 +2097,11 @@ fn deploy_container_with_router_on_scw() {
             routes: vec![Route {
                 path: "/".to_string(),
                 service_long_id: environment.containers[0].long_id,
+                protocol: RouteProtocol::Http1,
             }],
+            basic_auth_credentials: vec![],
+            extra_ingress_annotations: BTreeMap::new(),
+            allow_unsafe_ingress_annotations: false,
         }];
 
         let mut environment_for_delete = environment.clone();
@@ -2176,6 +2193,7 @@ fn deploy_job_on_scw_kapsule() {
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -2278,7 +2296,7 @@ fn deploy_cronjob_on_scw_kapsule() {
             kube_name: "job-test".to_string(),
             action: Action::Create,
             schedule: JobSchedule::Cron {
-                schedule: "* * * * *".to_string(),
+                schedule: "* * * * *".parse().unwrap(),
                 timezone: "Etc/UTC".to_string(),
             },
             source: JobSource::Image {
@@ -2306,6 +2324,7 @@ fn deploy_cronjob_on_scw_kapsule() {
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -2387,7 +2406,7 @@ fn deploy_cronjob_force_trigger_on_scw_kapsule() {
             kube_name: "job-test".to_string(),
             action: Action::Create,
             schedule: JobSchedule::Cron {
-                schedule: "*/10 * * * *".to_string(),
+                schedule: "*/10 * * * *".parse().unwrap(),
                 timezone: "Etc/UTC".to_string(),
             },
             source: JobSource::Image {
@@ -2415,6 +2434,7 @@ fn deploy_cronjob_force_trigger_on_scw_kapsule() {
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -2526,6 +2546,7 @@ fn build_and_deploy_job_on_scw_kapsule() {
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -2648,6 +2669,7 @@ fn build_and_deploy_job_on_scw_kapsule_with_mounted_files() {
             environment_vars_with_infos: Default::default(),
             mounted_files: vec![mounted_file.clone()],
             advanced_settings: Default::default(),
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 8080,
@@ -2800,6 +2822,7 @@ fn deploy_container_with_tcp_public_port() {
                     is_default: true,
                     name: "p443".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::TCP,
                     service_name: None,
                     namespace: None,
@@ -2811,6 +2834,7 @@ fn deploy_container_with_tcp_public_port() {
                     is_default: false,
                     name: "p5432".to_string(),
                     publicly_accessible: true,
+                    expose: None,
                     protocol: Protocol::TCP,
                     service_name: None,
                     namespace: None,
@@ -2828,6 +2852,7 @@ fn deploy_container_with_tcp_public_port() {
                     additional_service: None,
                 },
             ],
+            lifecycle_hooks: Default::default(),
             readiness_probe: Some(Probe {
                 r#type: ProbeType::Tcp { host: None },
                 port: 443,
@@ -2852,6 +2877,7 @@ fn deploy_container_with_tcp_public_port() {
             advanced_settings: Default::default(),
             annotations_group_ids: BTreeSet::new(),
             labels_group_ids: btreeset! {},
+            promote_image: None,
         }];
 
         let mut environment_for_delete = environment.clone();